@@ -1,8 +1,16 @@
 //! Dockerfile static analysis (Layer 1).
+//!
+//! [`extract_build`] reads the build a Dockerfile describes — the
+//! language stage, its `RUN` steps, and the entry point the image runs —
+//! so `warp pack --from-dockerfile` can replay it as a component build.
+//! [`analyze_dockerfile`] judges what the image does at runtime — base
+//! images, system packages, ports, entry point, volumes — for
+//! `warp convert analyze`.
 
 use anyhow::{Result, bail};
 use regex::Regex;
 use std::path::Path;
+use warp_core::{Blocker, ShimItem};
 
 /// Attempt to detect the project language from a Dockerfile.
 pub fn detect_language_from_dockerfile(dockerfile: &Path) -> Result<String> {
@@ -27,42 +35,709 @@ pub fn detect_language_from_dockerfile(dockerfile: &Path) -> Result<String> {
     bail!("Could not detect language from Dockerfile base images")
 }
 
-/// Extract metadata from a Dockerfile.
-pub struct DockerfileInfo {
-    pub base_images: Vec<String>,
-    pub exposed_ports: Vec<u16>,
-    pub apt_packages: Vec<String>,
-    pub entrypoint: Option<String>,
+/// What a Dockerfile says about running the service as a component.
+#[derive(Debug, Default)]
+pub struct DockerfileFindings {
+    /// Blockers, each located at `Dockerfile:line`.
+    pub blockers: Vec<Blocker>,
+    /// System packages a shim stands in for.
+    pub shim_items: Vec<ShimItem>,
+    /// TCP ports the final stage exposes.
+    pub ports: Vec<u16>,
+    /// Path the final stage's `HEALTHCHECK` probes over HTTP.
+    pub health_endpoint: Option<String>,
 }
 
-pub fn parse_dockerfile(path: &Path) -> Result<DockerfileInfo> {
-    let content = std::fs::read_to_string(path)?;
-    let from_re = Regex::new(r"(?i)^FROM\s+(\S+)")?;
-    let expose_re = Regex::new(r"(?i)^EXPOSE\s+(\d+)")?;
-    let apt_re = Regex::new(r"apt-get\s+install\s+.*?(\S+(?:\s+\S+)*)")?;
-    let entry_re = Regex::new(r#"(?i)^(?:ENTRYPOINT|CMD)\s+(.+)"#)?;
-
-    let mut info = DockerfileInfo {
-        base_images: vec![],
-        exposed_ports: vec![],
-        apt_packages: vec![],
-        entrypoint: None,
+/// Base images with no component equivalent: (image substring, reason, fix).
+const UNSUPPORTED_IMAGES: &[(&str, &str, &str)] = &[
+    ("windows", "Windows base image", "Rebuild the service on a Linux toolchain image"),
+    ("nanoserver", "Windows base image", "Rebuild the service on a Linux toolchain image"),
+    ("cuda", "GPU access is not available to components", "Move GPU work to a separate service"),
+    ("openjdk", "No Wasm component toolchain for the JVM", "Port the service to a supported language"),
+    ("eclipse-temurin", "No Wasm component toolchain for the JVM", "Port the service to a supported language"),
+    ("amazoncorretto", "No Wasm component toolchain for the JVM", "Port the service to a supported language"),
+    ("ruby", "No Wasm component toolchain for Ruby", "Port the service to a supported language"),
+    ("php", "No Wasm component toolchain for PHP", "Port the service to a supported language"),
+];
+
+/// What an installed system package means for a component.
+enum PackageNeed {
+    /// A shim provides what the package was installed for.
+    Shim(&'static str, &'static str),
+    /// Native code the component cannot run or link.
+    Blocker(&'static str, &'static str),
+}
+
+/// apt/apk/yum packages with a known meaning; others are ignored.
+fn package_need(package: &str) -> Option<PackageNeed> {
+    use PackageNeed::*;
+    Some(match package {
+        "tzdata" => Shim("timezone", "Timezone database; served by the timezone shim"),
+        "netbase" | "dnsutils" | "bind-tools" => {
+            Shim("dns", "Name resolution files; served by the dns shim")
+        }
+        "libpq5" | "libpq-dev" | "postgresql-client" | "postgresql-dev" | "libpq" => {
+            Shim("database_proxy", "Postgres client library; connections go through the database proxy")
+        }
+        "default-libmysqlclient-dev" | "libmariadb3" | "libmariadb-dev" | "mariadb-connector-c"
+        | "mariadb-dev" | "mysql-client" => {
+            Shim("database_proxy", "MySQL client library; connections go through the database proxy")
+        }
+        "libssl-dev" | "libssl3" | "openssl-dev" | "openssl-libs" => Blocker(
+            "Native OpenSSL library",
+            "Use a pure TLS implementation (rustls) or outbound wasi:http",
+        ),
+        "imagemagick" | "libvips" | "libvips-dev" | "vips-dev" | "ffmpeg" | "ghostscript"
+        | "poppler-utils" | "libreoffice" => Blocker(
+            "Native media tooling the component cannot run or link",
+            "Use a Wasm build of the library or call a separate service",
+        ),
+        "chromium" | "chromium-browser" | "google-chrome-stable" => Blocker(
+            "Headless browser binary",
+            "Call a rendering service over HTTP",
+        ),
+        "supervisor" => Blocker(
+            "Process supervisor for several processes; a component is one handler",
+            "Split the processes into separate deployments",
+        ),
+        _ => return None,
+    })
+}
+
+/// Packages a `RUN` command installs with apt, apk, yum, dnf, or microdnf.
+fn installed_packages(command: &str) -> Vec<String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let Some(start) = words.windows(2).position(|pair| {
+        matches!(
+            pair,
+            ["apt-get" | "apt" | "yum" | "dnf" | "microdnf", "install"] | ["apk", "add"]
+        )
+    }) else {
+        return Vec::new();
     };
+    words[start + 2..]
+        .iter()
+        .filter(|word| !word.starts_with('-'))
+        // `pkg=1.2-3` (apt) and `pkg~=1.2` / `pkg=1.2` (apk) pin versions.
+        .map(|word| word.split(['=', '~']).next().unwrap_or(word).to_string())
+        .collect()
+}
 
-    for line in content.lines() {
+/// Logical lines (continuations joined, comments dropped) with the line
+/// number each starts on.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    for (index, line) in content.lines().enumerate() {
         let trimmed = line.trim();
-        if let Some(caps) = from_re.captures(trimmed) {
-            info.base_images.push(caps[1].to_string());
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        if current.is_empty() {
+            start = index + 1;
         }
-        if let Some(caps) = expose_re.captures(trimmed) {
-            if let Ok(port) = caps[1].parse() {
-                info.exposed_ports.push(port);
+        match trimmed.strip_suffix('\\') {
+            Some(head) => {
+                current.push_str(head);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(trimmed);
+                if !current.trim().is_empty() {
+                    lines.push((start, std::mem::take(&mut current)));
+                }
+                current.clear();
+            }
+        }
+    }
+    lines
+}
+
+/// Evaluate a Dockerfile: base images, installed packages, and the final
+/// stage's `EXPOSE`, `ENTRYPOINT`/`CMD`, `VOLUME`, and `HEALTHCHECK`.
+pub fn analyze_dockerfile(path: &Path) -> Result<DockerfileFindings> {
+    let content = std::fs::read_to_string(path)?;
+    let file = path.file_name().and_then(|n| n.to_str()).unwrap_or("Dockerfile");
+    let lines = logical_lines(&content);
+    let final_stage = lines
+        .iter()
+        .rposition(|(_, line)| keyword(line).0 == "FROM")
+        .unwrap_or(0);
+
+    let mut findings = DockerfileFindings::default();
+    let blocker = |line: usize, construct: &str, reason: &str, fix: &str, hours: f64| Blocker {
+        dependency: construct.to_string(),
+        reason: reason.to_string(),
+        fix: fix.to_string(),
+        effort_hours: Some(hours),
+        location: Some(format!("{file}:{line}")),
+    };
+    let health_url = Regex::new(r#"https?://[^/\s'"]+(/[^\s'"]*)"#)?;
+    let mut stage_names = Vec::new();
+    let mut seen_packages = Vec::new();
+    let mut entry = None;
+
+    for (index, (number, line)) in lines.iter().enumerate() {
+        let (keyword, args) = keyword(line);
+        let in_final = index >= final_stage;
+        match keyword.as_str() {
+            "FROM" => {
+                let mut image_words = args.split_whitespace().filter(|word| !word.starts_with("--"));
+                let image = image_words.next().unwrap_or_default().to_lowercase();
+                if let (Some(as_kw), Some(name)) = (image_words.next(), image_words.next())
+                    && as_kw.eq_ignore_ascii_case("as")
+                {
+                    stage_names.push(name.to_lowercase());
+                }
+                if stage_names.contains(&image) {
+                    continue;
+                }
+                if let Some((_, reason, fix)) =
+                    UNSUPPORTED_IMAGES.iter().find(|(pattern, _, _)| image.contains(pattern))
+                {
+                    findings.blockers.push(blocker(*number, &image, reason, fix, 40.0));
+                }
+            }
+            "RUN" => {
+                for command in run_commands(args) {
+                    for package in installed_packages(&command) {
+                        if seen_packages.contains(&package) {
+                            continue;
+                        }
+                        match package_need(&package) {
+                            Some(PackageNeed::Shim(shim, description)) => {
+                                findings.shim_items.push(ShimItem {
+                                    name: package.clone(),
+                                    shim: shim.to_string(),
+                                    description: description.to_string(),
+                                });
+                            }
+                            Some(PackageNeed::Blocker(reason, fix)) => {
+                                findings.blockers.push(blocker(*number, &package, reason, fix, 4.0));
+                            }
+                            None => {}
+                        }
+                        seen_packages.push(package);
+                    }
+                }
+            }
+            "EXPOSE" if in_final => {
+                for port in args.split_whitespace() {
+                    let (port, protocol) = port.split_once('/').unwrap_or((port, "tcp"));
+                    let Ok(port) = port.parse::<u16>() else {
+                        continue;
+                    };
+                    if protocol.eq_ignore_ascii_case("udp") {
+                        findings.blockers.push(blocker(
+                            *number,
+                            &format!("EXPOSE {port}/udp"),
+                            "UDP listener; components only receive HTTP requests",
+                            "Move the UDP service out of the component",
+                            8.0,
+                        ));
+                    } else if !findings.ports.contains(&port) {
+                        findings.ports.push(port);
+                        if findings.ports.len() == 2 {
+                            findings.blockers.push(blocker(
+                                *number,
+                                &format!("EXPOSE {port}"),
+                                "Listens on more than one port; a component serves one HTTP trigger",
+                                "Serve every route from one port, or split into separate deployments",
+                                4.0,
+                            ));
+                        }
+                    }
+                }
+            }
+            "ENTRYPOINT" | "CMD" if in_final => {
+                // The last ENTRYPOINT (and CMD) wins; judge the pair once.
+                let slot = entry.get_or_insert((*number, Vec::new(), Vec::new()));
+                slot.0 = slot.0.max(*number);
+                if keyword == "ENTRYPOINT" {
+                    slot.1 = words(args);
+                } else {
+                    slot.2 = words(args);
+                }
+            }
+            "VOLUME" if in_final => findings.blockers.push(blocker(
+                *number,
+                &format!("VOLUME {args}"),
+                "Persistent volume; components have no writable filesystem that outlives an instance",
+                "Keep state in a database through the database_proxy shim, or in object storage",
+                8.0,
+            )),
+            "HEALTHCHECK" if in_final => {
+                findings.health_endpoint = health_url
+                    .captures(args)
+                    .map(|caps| caps[1].to_string());
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((number, entrypoint, cmd)) = entry {
+        let mut command: Vec<String> = entrypoint.into_iter().chain(cmd).collect();
+        // Init wrappers only forward signals; judge what they run.
+        const INITS: &[&str] = &["tini", "dumb-init", "--"];
+        while command.first().is_some_and(|w| INITS.contains(&program(w))) {
+            command.remove(0);
+        }
+        if let Some(first) = command.first() {
+            let program = program(first);
+            let shell = ["sh", "bash", "ash", "dash", "zsh"].contains(&program);
+            if shell || program.ends_with(".sh") {
+                findings.blockers.push(blocker(
+                    number,
+                    first,
+                    "Entry point is a shell script; components have no shell",
+                    "Do setup at build time or in the guest's on-start hook, and export the handler directly",
+                    2.0,
+                ));
+            } else if ["supervisord", "s6-svscan", "runsvdir"].contains(&program) {
+                findings.blockers.push(blocker(
+                    number,
+                    first,
+                    "Runs a process supervisor; a component is one handler",
+                    "Split the processes into separate deployments",
+                    8.0,
+                ));
+            } else if ["nginx", "caddy", "httpd", "apache2", "haproxy", "envoy"].contains(&program) {
+                findings.blockers.push(blocker(
+                    number,
+                    first,
+                    "Runs a native proxy or web server; warpd's ingress routes the traffic",
+                    "Use ingress hosts and path prefixes, and serve static files from the component",
+                    4.0,
+                ));
+            }
+        }
+    }
+
+    tracing::info!(
+        blockers = findings.blockers.len(),
+        shims = findings.shim_items.len(),
+        "Analyzed Dockerfile"
+    );
+    Ok(findings)
+}
+
+/// Upper-cased keyword and arguments of a logical line.
+fn keyword(line: &str) -> (String, &str) {
+    let (keyword, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    (keyword.to_uppercase(), args.trim())
+}
+
+/// File name of a program path (`/usr/bin/tini` → `tini`).
+fn program(word: &str) -> &str {
+    word.rsplit('/').next().unwrap_or(word)
+}
+
+/// The build a Dockerfile describes, as `warp pack --from-dockerfile` uses it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerBuild {
+    /// Language of the build stage: rust, go, js, typescript, bun, python, or dotnet.
+    pub lang: String,
+    /// Base image of the build stage.
+    pub base_image: String,
+    /// Commands of the build stage's `RUN` instructions, split on `&&`.
+    pub steps: Vec<BuildStep>,
+    /// Entry point, relative to the project, when the Dockerfile names one.
+    pub entry: Option<String>,
+}
+
+/// One command of a `RUN` instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildStep {
+    pub command: String,
+    pub kind: StepKind,
+}
+
+/// What a build step means for a component build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// Fetches dependencies or generates sources (`npm ci`, `go mod download`,
+    /// `pip install`); still needed before componentizing.
+    Prepare,
+    /// Compiles a native binary (`cargo build`, `go build`); replaced by the
+    /// componentization path.
+    Compile,
+    /// Sets up the image (`apt-get`, `useradd`, ...); no equivalent.
+    System,
+}
+
+/// Language of the toolchain an image ships, if it ships one.
+pub fn image_language(image: &str) -> Option<&'static str> {
+    let image = image.to_lowercase();
+    if image.contains("rust") {
+        Some("rust")
+    } else if image.contains("golang") || image.contains("go:") || image.contains("tinygo") {
+        Some("go")
+    } else if image.contains("bun") {
+        Some("bun")
+    } else if image.contains("node") || image.contains("deno") {
+        Some("typescript")
+    } else if image.contains("python") {
+        Some("python")
+    } else if image.contains("dotnet") {
+        Some("dotnet")
+    } else {
+        None
+    }
+}
+
+/// One `FROM` stage: its image, working directory, and instructions.
+struct Stage {
+    image: String,
+    workdir: Option<String>,
+    instructions: Vec<(String, String)>,
+}
+
+/// Split a Dockerfile into stages, joining `\` continuations.
+fn stages(content: &str) -> Vec<Stage> {
+    let mut stages: Vec<Stage> = Vec::new();
+    for (_, line) in logical_lines(content) {
+        let (keyword, args) = keyword(&line);
+        let args = args.to_string();
+        if keyword == "FROM" {
+            let image = args
+                .split_whitespace()
+                .find(|word| !word.starts_with("--"))
+                .unwrap_or_default()
+                .to_string();
+            stages.push(Stage { image, workdir: None, instructions: Vec::new() });
+        } else if let Some(stage) = stages.last_mut() {
+            if keyword == "WORKDIR" {
+                stage.workdir = Some(args.trim_end_matches('/').to_string());
             }
+            stage.instructions.push((keyword, args));
         }
-        if let Some(caps) = entry_re.captures(trimmed) {
-            info.entrypoint = Some(caps[1].to_string());
+    }
+    stages
+}
+
+/// Words of a shell-form or JSON exec-form instruction.
+fn words(args: &str) -> Vec<String> {
+    if args.starts_with('[')
+        && let Ok(words) = serde_json::from_str::<Vec<String>>(args)
+    {
+        // `["sh", "-c", "..."]` runs the last word as a script.
+        if words.len() == 3 && words[1] == "-c" {
+            return words[2].split_whitespace().map(String::from).collect();
         }
+        return words;
+    }
+    args.split_whitespace().map(String::from).collect()
+}
+
+/// Commands of a `RUN` instruction, split on `&&` and `;`.
+fn run_commands(args: &str) -> Vec<String> {
+    let script = if args.starts_with('[') {
+        words(args).join(" ")
+    } else {
+        args.to_string()
+    };
+    script
+        .split("&&")
+        .flat_map(|part| part.split(';'))
+        .map(|command| command.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|command| !command.is_empty())
+        .collect()
+}
+
+/// Classify a build command by the program it runs.
+pub fn classify_step(command: &str) -> StepKind {
+    let words: Vec<&str> = command
+        .split_whitespace()
+        .skip_while(|word| word.contains('=') && !word.starts_with('-'))
+        .collect();
+    match words.as_slice() {
+        ["cargo", "build" | "install" | "component", ..]
+        | ["go", "build" | "install", ..]
+        | ["tinygo", ..]
+        | ["dotnet", "publish" | "build", ..]
+        | ["bun", "build", ..] => StepKind::Compile,
+        ["npm" | "npx" | "yarn" | "pnpm" | "bun" | "tsc", ..]
+        | ["go", "mod" | "generate", ..]
+        | ["pip" | "pip3" | "poetry" | "uv", ..]
+        | ["python" | "python3", "-m", "pip", ..]
+        | ["dotnet", "restore", ..]
+        | ["cargo", "fetch", ..] => StepKind::Prepare,
+        _ => StepKind::System,
     }
+}
+
+/// Read the build a Dockerfile describes.
+///
+/// The build stage is the first stage on a language toolchain image; the
+/// entry point comes from the final stage's `ENTRYPOINT`/`CMD` (a source
+/// file it runs) or, for Go and .NET, from the build stage's compile step.
+pub fn extract_build(dockerfile: &Path) -> Result<DockerBuild> {
+    let content = std::fs::read_to_string(dockerfile)?;
+    let stages = stages(&content);
+    let Some((stage, lang)) = stages
+        .iter()
+        .find_map(|stage| Some((stage, image_language(&stage.image)?)))
+    else {
+        bail!(
+            "No build stage in {}: no FROM image ships a supported toolchain (rust, golang, node, bun, python, dotnet)",
+            dockerfile.display()
+        );
+    };
+
+    let steps: Vec<BuildStep> = stage
+        .instructions
+        .iter()
+        .filter(|(keyword, _)| keyword == "RUN")
+        .flat_map(|(_, args)| run_commands(args))
+        .map(|command| BuildStep { kind: classify_step(&command), command })
+        .collect();
 
-    Ok(info)
+    let entry = match lang {
+        "go" | "dotnet" => steps
+            .iter()
+            .filter(|step| step.kind == StepKind::Compile)
+            .find_map(|step| compile_entry(lang, &step.command)),
+        _ => stages.last().and_then(run_entry),
+    };
+    // Node images run JavaScript unless the entry is TypeScript.
+    let lang = match (lang, entry.as_deref()) {
+        ("typescript", Some(entry))
+            if [".js", ".mjs", ".cjs"].iter().any(|ext| entry.ends_with(ext)) =>
+        {
+            "js"
+        }
+        _ => lang,
+    };
+
+    Ok(DockerBuild {
+        lang: lang.to_string(),
+        base_image: stage.image.clone(),
+        steps,
+        entry,
+    })
+}
+
+/// Source file the stage's `ENTRYPOINT` + `CMD` runs, relative to its
+/// `WORKDIR`.
+fn run_entry(stage: &Stage) -> Option<String> {
+    let last = |keyword: &str| {
+        stage
+            .instructions
+            .iter()
+            .rev()
+            .find(|(k, _)| k == keyword)
+            .map(|(_, args)| words(args))
+            .unwrap_or_default()
+    };
+    let mut command = last("ENTRYPOINT");
+    command.extend(last("CMD"));
+
+    let source = command.iter().enumerate().find_map(|(i, word)| {
+        const SOURCES: &[&str] = &[".js", ".mjs", ".cjs", ".ts", ".py"];
+        if SOURCES.iter().any(|ext| word.ends_with(ext)) {
+            Some(word.clone())
+        } else if word == "-m" {
+            // `python -m app` runs app.py.
+            command.get(i + 1).map(|module| format!("{}.py", module.replace('.', "/")))
+        } else {
+            None
+        }
+    })?;
+    Some(relative_to(&source, stage.workdir.as_deref()))
+}
+
+/// Source file or package a Go / .NET compile step builds.
+fn compile_entry(lang: &str, command: &str) -> Option<String> {
+    const VALUE_FLAGS: &[&str] = &[
+        "-o", "-ldflags", "-tags", "-gcflags", "-asmflags", "-mod", "-p",
+        "-c", "--configuration", "--output", "-r", "--runtime",
+    ];
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let mut args = Vec::new();
+    let mut i = 2;
+    while i < words.len() {
+        if VALUE_FLAGS.contains(&words[i]) {
+            i += 2;
+            continue;
+        }
+        if !words[i].starts_with('-') {
+            args.push(words[i]);
+        }
+        i += 1;
+    }
+    match lang {
+        "go" => {
+            let target = args.last().copied().unwrap_or(".");
+            if target.ends_with(".go") {
+                Some(target.trim_start_matches("./").to_string())
+            } else {
+                let dir = target
+                    .trim_start_matches("./")
+                    .trim_end_matches("/...")
+                    .trim_end_matches('/');
+                Some(if dir.is_empty() || dir == "." {
+                    "main.go".to_string()
+                } else {
+                    format!("{dir}/main.go")
+                })
+            }
+        }
+        _ => args
+            .iter()
+            .find(|arg| arg.ends_with(".csproj"))
+            .map(|project| project.trim_start_matches("./").to_string()),
+    }
+}
+
+/// `path` relative to the image's `workdir` (where the project is copied).
+fn relative_to(path: &str, workdir: Option<&str>) -> String {
+    let relative = workdir
+        .and_then(|dir| path.strip_prefix(dir))
+        .map(|rest| rest.trim_start_matches('/'))
+        .unwrap_or(path);
+    relative.trim_start_matches("./").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(content: &str) -> Result<DockerBuild> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Dockerfile");
+        std::fs::write(&path, content).unwrap();
+        extract_build(&path)
+    }
+
+    fn analyze(content: &str) -> DockerfileFindings {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Dockerfile");
+        std::fs::write(&path, content).unwrap();
+        analyze_dockerfile(&path).unwrap()
+    }
+
+    #[test]
+    fn test_packages_map_to_shims_and_blockers() {
+        let findings = analyze(
+            "FROM python:3.12-slim\n\
+             # system packages\n\
+             RUN apt-get update && apt-get install -y --no-install-recommends \\\n    \
+             ca-certificates curl tzdata=2024a-1 libpq5 imagemagick\n\
+             RUN apk add --no-cache bind-tools\n\
+             CMD [\"python\", \"app.py\"]\n",
+        );
+        let shims: Vec<_> =
+            findings.shim_items.iter().map(|s| (s.name.as_str(), s.shim.as_str())).collect();
+        assert_eq!(
+            shims,
+            vec![("tzdata", "timezone"), ("libpq5", "database_proxy"), ("bind-tools", "dns")]
+        );
+        assert_eq!(findings.blockers.len(), 1);
+        assert_eq!(findings.blockers[0].dependency, "imagemagick");
+        // The install starts on line 3, after the comment.
+        assert_eq!(findings.blockers[0].location.as_deref(), Some("Dockerfile:3"));
+    }
+
+    #[test]
+    fn test_final_stage_ports_entrypoint_and_healthcheck() {
+        let findings = analyze(
+            "FROM eclipse-temurin:21 AS tools\n\
+             EXPOSE 9999\n\
+             FROM node:20-alpine\n\
+             EXPOSE 3000 8125/udp\n\
+             VOLUME /data\n\
+             HEALTHCHECK CMD curl -f http://localhost:3000/health/live || exit 1\n\
+             ENTRYPOINT [\"/sbin/tini\", \"--\"]\n\
+             CMD [\"./docker-entrypoint.sh\"]\n",
+        );
+        assert_eq!(findings.ports, vec![3000]);
+        assert_eq!(findings.health_endpoint.as_deref(), Some("/health/live"));
+        let blockers: Vec<_> = findings
+            .blockers
+            .iter()
+            .map(|b| (b.dependency.as_str(), b.location.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            blockers,
+            vec![
+                ("eclipse-temurin:21", "Dockerfile:1"),
+                ("EXPOSE 8125/udp", "Dockerfile:4"),
+                ("VOLUME /data", "Dockerfile:5"),
+                ("./docker-entrypoint.sh", "Dockerfile:8"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clean_dockerfile_has_no_findings() {
+        let findings = analyze(
+            "FROM golang:1.22 AS build\n\
+             RUN apt-get install -y ca-certificates\n\
+             FROM build\n\
+             EXPOSE 8080\n\
+             ENTRYPOINT [\"/app/server\"]\n",
+        );
+        assert!(findings.blockers.is_empty());
+        assert!(findings.shim_items.is_empty());
+        assert_eq!(findings.ports, vec![8080]);
+    }
+
+    #[test]
+    fn test_multi_stage_node_build() {
+        let build = extract(
+            "FROM --platform=linux/amd64 node:20-alpine AS build\n\
+             WORKDIR /app\n\
+             COPY . .\n\
+             RUN apk add --no-cache git && \\\n    npm ci && npm run build\n\
+             FROM gcr.io/distroless/nodejs20\n\
+             WORKDIR /srv\n\
+             COPY --from=build /app/dist ./dist\n\
+             CMD [\"/srv/dist/server.js\"]\n",
+        )
+        .unwrap();
+        assert_eq!(build.lang, "js");
+        assert_eq!(build.base_image, "node:20-alpine");
+        assert_eq!(build.entry.as_deref(), Some("dist/server.js"));
+        let steps: Vec<_> = build.steps.iter().map(|s| (s.command.as_str(), s.kind)).collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("apk add --no-cache git", StepKind::System),
+                ("npm ci", StepKind::Prepare),
+                ("npm run build", StepKind::Prepare),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_entry_comes_from_the_compile_step() {
+        let build = extract(
+            "FROM golang:1.22 AS builder\n\
+             RUN go mod download\n\
+             RUN CGO_ENABLED=0 go build -o /out/server -ldflags \"-s\" ./cmd/server\n\
+             FROM scratch\nENTRYPOINT [\"/server\"]\n",
+        )
+        .unwrap();
+        assert_eq!(build.lang, "go");
+        assert_eq!(build.entry.as_deref(), Some("cmd/server/main.go"));
+        assert_eq!(build.steps[1].kind, StepKind::Compile);
+    }
+
+    #[test]
+    fn test_python_module_entry() {
+        let build = extract(
+            "FROM python:3.12-slim\nWORKDIR /app\nRUN pip install -r requirements.txt\nCMD python -m api.main\n",
+        )
+        .unwrap();
+        assert_eq!(build.lang, "python");
+        assert_eq!(build.entry.as_deref(), Some("api/main.py"));
+        assert_eq!(build.steps[0].kind, StepKind::Prepare);
+    }
+
+    #[test]
+    fn test_no_toolchain_stage_is_an_error() {
+        let err = extract("FROM alpine:3.19\nRUN echo hi\n").unwrap_err();
+        assert!(err.to_string().contains("No build stage"), "{err}");
+    }
 }
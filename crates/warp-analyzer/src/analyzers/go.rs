@@ -1,4 +1,6 @@
 //! Go project analyzer — parses go.mod.
+//!
+//! Requirements marked `// indirect` are reported as transitive.
 
 use anyhow::Result;
 use regex::Regex;
@@ -12,7 +14,7 @@ pub fn analyze_go_mod(project_path: &Path) -> Result<Vec<DependencyVerdict>> {
     }
 
     let content = std::fs::read_to_string(&go_mod_path)?;
-    let dep_re = Regex::new(r"^\s+(\S+)\s+(v\S+)")?;
+    let dep_re = Regex::new(r"^(\S+)\s+(v\S+)")?;
     let mut deps = Vec::new();
     let mut in_require = false;
 
@@ -26,14 +28,23 @@ pub fn analyze_go_mod(project_path: &Path) -> Result<Vec<DependencyVerdict>> {
             in_require = false;
             continue;
         }
-        if in_require {
-            if let Some(caps) = dep_re.captures(line) {
-                deps.push(DependencyVerdict {
-                    name: caps[1].to_string(),
-                    version: Some(caps[2].to_string()),
-                    verdict: warp_core::Verdict::Unknown,
-                });
-            }
+        // `require module v1.2.3` outside a block names a single module.
+        let spec = match trimmed.strip_prefix("require ") {
+            Some(single) => Some(single),
+            None if in_require => Some(trimmed),
+            None => None,
+        };
+        if let Some(spec) = spec
+            && let Some(caps) = dep_re.captures(spec)
+        {
+            deps.push(DependencyVerdict {
+                name: caps[1].to_string(),
+                version: Some(caps[2].to_string()),
+                verdict: warp_core::Verdict::Unknown,
+                // Recorded by `go mod tidy` for modules only needed by others.
+                transitive: line.contains("// indirect"),
+                via: Vec::new(),
+            });
         }
     }
 
@@ -0,0 +1,630 @@
+//! Transitive dependency discovery from lockfiles.
+//!
+//! Manifests only name direct dependencies; a blocker such as `openssl-sys`
+//! usually arrives through one of them. Each supported lockfile is read into
+//! a package graph, which is walked breadth-first from the direct
+//! dependencies so every indirect package is reported once, with the
+//! shortest chain that pulls it in. Direct dependencies are pinned to
+//! their locked versions on the way, since manifests usually hold ranges
+//! and version-ranged compat-db rules need the resolved version.
+//!
+//! | Language            | Lockfiles (first found wins)                              |
+//! |---------------------|-----------------------------------------------------------|
+//! | rust                | `Cargo.lock`                                              |
+//! | go                  | `go.sum` (no graph: indirect modules carry no chain)      |
+//! | typescript, bun     | `package-lock.json` (v2/v3), `yarn.lock` (v1), `bun.lock`, `bun.lockb` |
+//!
+//! `bun.lockb` is binary; it is decoded by running `bun bun.lockb`, which
+//! prints the equivalent `yarn.lock`. Without `bun` on `PATH` it is skipped.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use warp_core::{DependencyVerdict, Verdict};
+
+/// Indirect dependencies of the project at `project_path`, excluding
+/// anything already in `direct`. Entries of `direct` that the lockfile
+/// resolves get its version.
+pub fn transitive_dependencies(
+    project_path: &Path,
+    language: &str,
+    direct: &mut [DependencyVerdict],
+) -> Result<Vec<DependencyVerdict>> {
+    let names: HashSet<&str> = direct.iter().map(|d| d.name.as_str()).collect();
+    let (deps, locked) = match language {
+        "rust" => match read_optional(&project_path.join("Cargo.lock"))? {
+            Some(text) => {
+                let graph = cargo_lock(&text)?;
+                (walk(&graph, &names), graph.root_versions())
+            }
+            None => return Ok(missing("Cargo.lock")),
+        },
+        // go.mod already requires exact versions.
+        "go" => match read_optional(&project_path.join("go.sum"))? {
+            Some(text) => (go_sum(&text, &names), HashMap::new()),
+            None => return Ok(missing("go.sum")),
+        },
+        "typescript" | "bun" => match js_graph(project_path, &names)? {
+            Some(graph) => (walk(&graph, &names), graph.root_versions()),
+            None => return Ok(missing("package-lock.json, yarn.lock, or bun.lock")),
+        },
+        _ => (Vec::new(), HashMap::new()),
+    };
+    for dep in direct.iter_mut() {
+        if let Some(version) = locked.get(&dep.name) {
+            dep.version = Some(version.clone());
+        }
+    }
+    tracing::info!(count = deps.len(), "Resolved transitive dependencies");
+    Ok(deps)
+}
+
+fn missing(lockfile: &str) -> Vec<DependencyVerdict> {
+    tracing::warn!("No {lockfile} found; only direct dependencies are evaluated");
+    Vec::new()
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    std::fs::read_to_string(path)
+        .map(Some)
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// A resolved package graph. Ids are lockfile-specific and unique; names
+/// are what the compat-db is keyed on.
+#[derive(Default)]
+struct Graph {
+    packages: HashMap<String, Package>,
+    /// Ids of the packages the project depends on directly.
+    roots: Vec<String>,
+}
+
+struct Package {
+    name: String,
+    version: Option<String>,
+    /// Ids of the packages this one depends on.
+    deps: Vec<String>,
+}
+
+impl Graph {
+    /// Locked version of each direct dependency, by name.
+    fn root_versions(&self) -> HashMap<String, String> {
+        let mut versions = HashMap::new();
+        for package in self.roots.iter().filter_map(|id| self.packages.get(id)) {
+            if let Some(version) = &package.version {
+                versions.entry(package.name.clone()).or_insert_with(|| version.clone());
+            }
+        }
+        versions
+    }
+}
+
+/// Breadth-first walk from the roots; the first (shortest) chain to each
+/// package name wins.
+fn walk(graph: &Graph, direct: &HashSet<&str>) -> Vec<DependencyVerdict> {
+    let mut reported: HashSet<&str> = direct.clone();
+    let mut visited = HashSet::new();
+    let mut queue: VecDeque<(&str, Vec<String>)> =
+        graph.roots.iter().map(|id| (id.as_str(), Vec::new())).collect();
+    let mut out = Vec::new();
+
+    while let Some((id, via)) = queue.pop_front() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let Some(package) = graph.packages.get(id) else {
+            continue;
+        };
+        if !via.is_empty() && reported.insert(&package.name) {
+            out.push(DependencyVerdict {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                verdict: Verdict::Unknown,
+                transitive: true,
+                via: via.clone(),
+            });
+        }
+        let mut next = via;
+        next.push(package.name.clone());
+        for dep in &package.deps {
+            queue.push_back((dep, next.clone()));
+        }
+    }
+    out
+}
+
+// ── Cargo.lock ──────────────────────────────────────────────────
+
+#[derive(serde::Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<CargoPackage>,
+}
+
+#[derive(serde::Deserialize)]
+struct CargoPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// Packages without a `source` are workspace members or path dependencies;
+/// what they depend on is what the project depends on directly.
+fn cargo_lock(text: &str) -> Result<Graph> {
+    let lock: CargoLock = toml::from_str(text).context("Invalid Cargo.lock")?;
+    let id = |name: &str, version: &str| format!("{name} {version}");
+    let mut versions: HashMap<&str, Vec<&str>> = HashMap::new();
+    for p in &lock.package {
+        versions.entry(&p.name).or_default().push(&p.version);
+    }
+
+    // Entries are `name`, `name version`, or `name version (source)`; the
+    // version is only written when several are locked.
+    let resolve = |dep: &str| -> Option<String> {
+        let mut parts = dep.split_whitespace();
+        let name = parts.next()?;
+        match parts.next() {
+            Some(version) => Some(id(name, version)),
+            None => versions.get(name).map(|v| id(name, v[0])),
+        }
+    };
+
+    let mut graph = Graph::default();
+    for p in &lock.package {
+        let deps: Vec<String> = p.dependencies.iter().filter_map(|d| resolve(d)).collect();
+        if p.source.is_none() {
+            graph.roots.extend(deps.iter().cloned());
+        }
+        graph.packages.insert(
+            id(&p.name, &p.version),
+            Package {
+                name: p.name.clone(),
+                version: Some(p.version.clone()),
+                deps,
+            },
+        );
+    }
+    Ok(graph)
+}
+
+// ── go.sum ──────────────────────────────────────────────────────
+
+/// Modules whose source is checksummed (not just their `go.mod`) are in the
+/// build; those not required directly are indirect. `go.sum` records no
+/// edges, so they carry no chain.
+fn go_sum(text: &str, direct: &HashSet<&str>) -> Vec<DependencyVerdict> {
+    let mut modules: Vec<(&str, &str)> = Vec::new();
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(module), Some(version)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if version.ends_with("/go.mod") || direct.contains(module) {
+            continue;
+        }
+        // go.sum is sorted, so a later line for the same module is newer.
+        match modules.iter_mut().find(|(m, _)| *m == module) {
+            Some(entry) => entry.1 = version,
+            None => modules.push((module, version)),
+        }
+    }
+    modules
+        .into_iter()
+        .map(|(module, version)| DependencyVerdict {
+            name: module.to_string(),
+            version: Some(version.to_string()),
+            verdict: Verdict::Unknown,
+            transitive: true,
+            via: Vec::new(),
+        })
+        .collect()
+}
+
+// ── JavaScript lockfiles ────────────────────────────────────────
+
+fn js_graph(project_path: &Path, direct: &HashSet<&str>) -> Result<Option<Graph>> {
+    if let Some(text) = read_optional(&project_path.join("package-lock.json"))? {
+        return package_lock(&text).map(Some);
+    }
+    if let Some(text) = read_optional(&project_path.join("yarn.lock"))? {
+        return Ok(Some(yarn_lock(&text, direct)));
+    }
+    if let Some(text) = read_optional(&project_path.join("bun.lock"))? {
+        return bun_lock(&text).map(Some);
+    }
+    let lockb = project_path.join("bun.lockb");
+    if lockb.exists() {
+        let output = std::process::Command::new("bun")
+            .arg("bun.lockb")
+            .current_dir(project_path)
+            .output();
+        return Ok(match output {
+            Ok(out) if out.status.success() => {
+                Some(yarn_lock(&String::from_utf8_lossy(&out.stdout), direct))
+            }
+            _ => {
+                tracing::warn!("Could not decode bun.lockb (is bun installed?); only direct dependencies are evaluated");
+                Some(Graph::default())
+            }
+        });
+    }
+    Ok(None)
+}
+
+const JS_DEP_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+fn dep_names(entry: &serde_json::Value) -> impl Iterator<Item = &str> {
+    JS_DEP_FIELDS
+        .iter()
+        .filter_map(|field| entry.get(field).and_then(|d| d.as_object()))
+        .flat_map(|deps| deps.keys().map(String::as_str))
+}
+
+/// npm lockfile v2/v3: `packages` is keyed by install path
+/// (`node_modules/a/node_modules/b`), and a dependency resolves to the
+/// nearest enclosing `node_modules` that has it, as Node does.
+fn package_lock(text: &str) -> Result<Graph> {
+    let lock: serde_json::Value = serde_json::from_str(text).context("Invalid package-lock.json")?;
+    let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) else {
+        tracing::warn!("package-lock.json v1 is not supported; run `npm install` to upgrade it");
+        return Ok(Graph::default());
+    };
+
+    let resolve = |from: &str, dep: &str| -> Option<String> {
+        let mut base = from;
+        loop {
+            let candidate = if base.is_empty() {
+                format!("node_modules/{dep}")
+            } else {
+                format!("{base}/node_modules/{dep}")
+            };
+            if packages.contains_key(&candidate) {
+                return Some(candidate);
+            }
+            if base.is_empty() {
+                return None;
+            }
+            base = base.rfind("/node_modules/").map_or("", |i| &base[..i]);
+        }
+    };
+
+    let mut graph = Graph::default();
+    for (path, entry) in packages {
+        let deps = dep_names(entry).filter_map(|d| resolve(path, d)).collect();
+        if path.is_empty() {
+            graph.roots = deps;
+            continue;
+        }
+        let name = match path.rfind("node_modules/") {
+            Some(i) => &path[i + "node_modules/".len()..],
+            None => path.as_str(),
+        };
+        graph.packages.insert(
+            path.clone(),
+            Package {
+                name: name.to_string(),
+                version: entry.get("version").and_then(|v| v.as_str()).map(String::from),
+                deps,
+            },
+        );
+    }
+    Ok(graph)
+}
+
+/// Split `name@range` (names may be scoped: `@scope/name@range`).
+fn split_descriptor(descriptor: &str) -> (&str, &str) {
+    match descriptor.get(1..).and_then(|rest| rest.find('@')) {
+        Some(i) => (&descriptor[..i + 1], &descriptor[i + 2..]),
+        None => (descriptor, ""),
+    }
+}
+
+/// Yarn v1 lockfile (also what `bun bun.lockb` prints). Blocks are keyed by
+/// every `name@range` descriptor they satisfy; dependencies are looked up
+/// by the same descriptor.
+fn yarn_lock(text: &str, direct: &HashSet<&str>) -> Graph {
+    struct Block {
+        descriptors: Vec<String>,
+        version: Option<String>,
+        deps: Vec<String>,
+    }
+
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut in_deps = false;
+    for line in text.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        if indent == 0 {
+            let descriptors = line
+                .trim_end_matches(':')
+                .split(", ")
+                .map(|d| d.trim_matches('"').to_string())
+                .collect();
+            blocks.push(Block { descriptors, version: None, deps: Vec::new() });
+            in_deps = false;
+            continue;
+        }
+        let Some(block) = blocks.last_mut() else { continue };
+        if indent == 2 {
+            in_deps = line == "dependencies:" || line == "optionalDependencies:";
+            if let Some(version) = line.strip_prefix("version ") {
+                block.version = Some(version.trim_matches('"').to_string());
+            }
+        } else if in_deps && let Some((name, range)) = line.split_once(' ') {
+            block
+                .deps
+                .push(format!("{}@{}", name.trim_matches('"'), range.trim_matches('"')));
+        }
+    }
+
+    let mut ids = HashMap::new();
+    for block in &blocks {
+        for d in &block.descriptors {
+            ids.insert(d.as_str(), block.descriptors[0].as_str());
+        }
+    }
+    let mut graph = Graph::default();
+    for block in &blocks {
+        let id = &block.descriptors[0];
+        let name = split_descriptor(id).0;
+        if direct.contains(name) {
+            graph.roots.push(id.clone());
+        }
+        graph.packages.insert(
+            id.clone(),
+            Package {
+                name: name.to_string(),
+                version: block.version.clone(),
+                deps: block
+                    .deps
+                    .iter()
+                    .filter_map(|d| ids.get(d.as_str()).map(|id| id.to_string()))
+                    .collect(),
+            },
+        );
+    }
+    graph
+}
+
+/// Bun's text lockfile: JSON with trailing commas. `packages` maps a key
+/// (`name`, or `parent/name` when nested) to
+/// `["name@version", registry, { dependencies, .. }, integrity]`.
+fn bun_lock(text: &str) -> Result<Graph> {
+    let trailing_commas = Regex::new(r",(\s*[}\]])")?;
+    let lock: serde_json::Value = serde_json::from_str(&trailing_commas.replace_all(text, "$1"))
+        .context("Invalid bun.lock")?;
+    let packages = lock.get("packages").and_then(|p| p.as_object());
+    let Some(packages) = packages else {
+        return Ok(Graph::default());
+    };
+    let resolve = |from: &str, dep: &str| -> Option<String> {
+        [format!("{from}/{dep}"), dep.to_string()]
+            .into_iter()
+            .find(|key| packages.contains_key(key))
+    };
+
+    let mut graph = Graph::default();
+    if let Some(root) = lock.pointer("/workspaces/") {
+        graph.roots = dep_names(root).filter_map(|d| resolve("", d)).collect();
+    }
+    for (key, entry) in packages {
+        let (name, version) = entry
+            .get(0)
+            .and_then(|v| v.as_str())
+            .map(split_descriptor)
+            .unwrap_or((key, ""));
+        let deps = entry
+            .get(2)
+            .map(|meta| dep_names(meta).filter_map(|d| resolve(key, d)).collect())
+            .unwrap_or_default();
+        graph.packages.insert(
+            key.clone(),
+            Package {
+                name: name.to_string(),
+                version: (!version.is_empty()).then(|| version.to_string()),
+                deps,
+            },
+        );
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn direct(names: &[&str]) -> Vec<DependencyVerdict> {
+        names
+            .iter()
+            .map(|name| DependencyVerdict {
+                name: name.to_string(),
+                version: None,
+                verdict: Verdict::Unknown,
+                transitive: false,
+                via: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn find<'a>(deps: &'a [DependencyVerdict], name: &str) -> &'a DependencyVerdict {
+        deps.iter().find(|d| d.name == name).unwrap_or_else(|| panic!("{name} not found"))
+    }
+
+    #[test]
+    fn test_cargo_lock_reports_chain_to_openssl_sys() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("Cargo.lock"),
+            r#"
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["reqwest", "serde"]
+
+[[package]]
+name = "reqwest"
+version = "0.12.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["native-tls", "serde 1.0.200"]
+
+[[package]]
+name = "native-tls"
+version = "0.2.12"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["openssl-sys 0.9.103 (registry+https://github.com/rust-lang/crates.io-index)"]
+
+[[package]]
+name = "openssl-sys"
+version = "0.9.103"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+
+        let mut direct = direct(&["reqwest", "serde"]);
+        let deps = transitive_dependencies(tmp.path(), "rust", &mut direct).unwrap();
+        let names: Vec<_> = deps.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["native-tls", "openssl-sys"]);
+        let openssl = find(&deps, "openssl-sys");
+        assert!(openssl.transitive);
+        assert_eq!(openssl.version.as_deref(), Some("0.9.103"));
+        assert_eq!(openssl.via, ["reqwest", "native-tls"]);
+        // Direct dependencies are pinned to their locked versions.
+        assert_eq!(find(&direct, "reqwest").version.as_deref(), Some("0.12.0"));
+        assert_eq!(find(&direct, "serde").version.as_deref(), Some("1.0.200"));
+    }
+
+    #[test]
+    fn test_go_sum_reports_modules_not_in_go_mod() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("go.sum"),
+            "github.com/jackc/pgx/v5 v5.7.4 h1:a=\n\
+             github.com/jackc/pgx/v5 v5.7.4/go.mod h1:b=\n\
+             github.com/jackc/puddle/v2 v2.2.1 h1:c=\n\
+             github.com/jackc/puddle/v2 v2.2.2 h1:d=\n\
+             github.com/stretchr/objx v0.1.0/go.mod h1:e=\n",
+        )
+        .unwrap();
+
+        let deps = transitive_dependencies(tmp.path(), "go", &mut direct(&["github.com/jackc/pgx/v5"])).unwrap();
+        assert_eq!(deps.len(), 1, "go.mod-only modules are not built");
+        assert_eq!(deps[0].name, "github.com/jackc/puddle/v2");
+        assert_eq!(deps[0].version.as_deref(), Some("v2.2.2"));
+        assert!(deps[0].transitive && deps[0].via.is_empty());
+    }
+
+    #[test]
+    fn test_package_lock_resolves_nested_node_modules() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("package-lock.json"),
+            r#"{
+              "lockfileVersion": 3,
+              "packages": {
+                "": { "dependencies": { "pg": "^8.0.0" } },
+                "node_modules/pg": { "version": "8.11.0", "dependencies": { "pg-native": "^3", "buffer-writer": "2" } },
+                "node_modules/pg/node_modules/pg-native": { "version": "3.0.1", "dependencies": { "libpq": "^1" } },
+                "node_modules/pg-native": { "version": "2.0.0" },
+                "node_modules/libpq": { "version": "1.8.12" },
+                "node_modules/buffer-writer": { "version": "2.0.0" }
+              }
+            }"#,
+        )
+        .unwrap();
+
+        let deps = transitive_dependencies(tmp.path(), "typescript", &mut direct(&["pg"])).unwrap();
+        // The nested copy shadows the hoisted one.
+        assert_eq!(find(&deps, "pg-native").version.as_deref(), Some("3.0.1"));
+        assert_eq!(find(&deps, "libpq").via, ["pg", "pg-native"]);
+        assert_eq!(find(&deps, "buffer-writer").via, ["pg"]);
+    }
+
+    #[test]
+    fn test_yarn_lock_follows_descriptors() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("yarn.lock"),
+            r#"# yarn lockfile v1
+
+"@scope/db@^1.0.0":
+  version "1.2.0"
+  dependencies:
+    bindings "~1.5.0"
+
+bindings@~1.5.0, bindings@^1.5.0:
+  version "1.5.0"
+  resolved "https://registry.yarnpkg.com/bindings/-/bindings-1.5.0.tgz"
+"#,
+        )
+        .unwrap();
+
+        let deps = transitive_dependencies(tmp.path(), "typescript", &mut direct(&["@scope/db"])).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].name, "bindings");
+        assert_eq!(deps[0].version.as_deref(), Some("1.5.0"));
+        assert_eq!(deps[0].via, ["@scope/db"]);
+    }
+
+    #[test]
+    fn test_bun_lock_text_format() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("bun.lock"),
+            r#"{
+  "lockfileVersion": 1,
+  "workspaces": {
+    "": {
+      "name": "app",
+      "dependencies": {
+        "hono": "^4.0.0",
+      },
+    },
+  },
+  "packages": {
+    "hono": ["hono@4.6.0", "", { "dependencies": { "sharp": "^0.33" } }, "sha512-x"],
+    "sharp": ["sharp@0.33.5", "", {}, "sha512-y"],
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let deps = transitive_dependencies(tmp.path(), "bun", &mut direct(&["hono"])).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].name, "sharp");
+        assert_eq!(deps[0].version.as_deref(), Some("0.33.5"));
+        assert_eq!(deps[0].via, ["hono"]);
+    }
+
+    #[test]
+    fn test_missing_lockfile_yields_nothing() {
+        let tmp = TempDir::new().unwrap();
+        for lang in ["rust", "go", "typescript", "bun", "python"] {
+            assert!(transitive_dependencies(tmp.path(), lang, &mut []).unwrap().is_empty());
+        }
+    }
+}
@@ -1,8 +1,12 @@
 pub mod bun;
 pub mod rust;
 pub mod go;
+pub mod python;
 pub mod typescript;
 pub mod dockerfile;
+pub mod lockfile;
+pub mod source;
+pub mod size;
 
 use anyhow::{Result, bail};
 use std::path::Path;
@@ -11,11 +15,14 @@ use std::path::Path;
 ///
 /// `bunfig.toml` takes priority over `package.json` — a project with both
 /// is detected as `"bun"`, while `package.json` alone maps to `"typescript"`.
+/// `pyproject.toml` or `requirements.txt` maps to `"python"`.
 pub fn detect_language(path: &Path) -> Result<String> {
     if path.join("Cargo.toml").exists() {
         Ok("rust".to_string())
     } else if path.join("go.mod").exists() {
         Ok("go".to_string())
+    } else if path.join("pyproject.toml").exists() || path.join("requirements.txt").exists() {
+        Ok("python".to_string())
     } else if path.join("bunfig.toml").exists() {
         Ok("bun".to_string())
     } else if path.join("package.json").exists() {
@@ -23,7 +30,19 @@ pub fn detect_language(path: &Path) -> Result<String> {
     } else if path.join("Dockerfile").exists() {
         dockerfile::detect_language_from_dockerfile(&path.join("Dockerfile"))
     } else {
-        bail!("Could not detect project language. No Cargo.toml, go.mod, bunfig.toml, package.json, or Dockerfile found.")
+        bail!("Could not detect project language. No Cargo.toml, go.mod, pyproject.toml, requirements.txt, bunfig.toml, package.json, or Dockerfile found.")
+    }
+}
+
+/// Entry point a scaffolded `warp.toml` uses for `lang`.
+pub fn default_entry(lang: &str) -> &'static str {
+    match lang {
+        "rust" => "src/main.rs",
+        "go" => "main.go",
+        "typescript" | "bun" => "src/index.ts",
+        "js" => "src/index.js",
+        "python" => "app.py",
+        _ => "src/main",
     }
 }
 
@@ -81,6 +100,17 @@ mod tests {
         assert_eq!(lang, "go");
     }
 
+    #[test]
+    fn test_detect_python() {
+        for marker in ["pyproject.toml", "requirements.txt"] {
+            let tmp = TempDir::new().unwrap();
+            fs::write(tmp.path().join(marker), "").unwrap();
+
+            let lang = detect_language(tmp.path()).unwrap();
+            assert_eq!(lang, "python");
+        }
+    }
+
     #[test]
     fn test_no_marker_files_errors() {
         let tmp = TempDir::new().unwrap();
@@ -0,0 +1,204 @@
+//! Python project analyzer — parses pyproject.toml and requirements.txt.
+//!
+//! Package names are normalized per PEP 503 (lowercase, runs of `-`, `_`
+//! and `.` collapsed to `-`) so they match the compat DB regardless of how
+//! the project spells them.
+
+use anyhow::Result;
+use regex::Regex;
+use std::path::Path;
+use warp_core::DependencyVerdict;
+
+pub fn analyze_python_deps(project_path: &Path) -> Result<Vec<DependencyVerdict>> {
+    let mut deps = Vec::new();
+
+    let pyproject_path = project_path.join("pyproject.toml");
+    if pyproject_path.exists() {
+        let content = std::fs::read_to_string(&pyproject_path)?;
+        let manifest: toml::Value = toml::from_str(&content)?;
+        deps.extend(parse_pyproject(&manifest));
+    }
+
+    let requirements_path = project_path.join("requirements.txt");
+    if requirements_path.exists() {
+        let content = std::fs::read_to_string(&requirements_path)?;
+        for line in content.lines() {
+            if let Some(dep) = parse_requirement(line)
+                && !deps.iter().any(|d| d.name == dep.name)
+            {
+                deps.push(dep);
+            }
+        }
+    }
+
+    tracing::info!(count = deps.len(), "Parsed Python dependencies");
+    Ok(deps)
+}
+
+/// Collect dependencies from PEP 621 `[project]` and Poetry sections.
+fn parse_pyproject(manifest: &toml::Value) -> Vec<DependencyVerdict> {
+    let mut deps = Vec::new();
+
+    if let Some(project) = manifest.get("project") {
+        let required = project.get("dependencies").and_then(|d| d.as_array());
+        let optional = project
+            .get("optional-dependencies")
+            .and_then(|d| d.as_table())
+            .into_iter()
+            .flat_map(|t| t.values())
+            .filter_map(|v| v.as_array());
+        for list in required.into_iter().chain(optional) {
+            deps.extend(
+                list.iter()
+                    .filter_map(|v| v.as_str())
+                    .filter_map(parse_requirement),
+            );
+        }
+    }
+
+    if let Some(dep_table) = manifest
+        .get("tool")
+        .and_then(|t| t.get("poetry"))
+        .and_then(|p| p.get("dependencies"))
+        .and_then(|d| d.as_table())
+    {
+        for (name, value) in dep_table {
+            // Poetry lists the interpreter itself as a dependency.
+            if name == "python" {
+                continue;
+            }
+            let version = match value {
+                toml::Value::String(v) => Some(v.clone()),
+                toml::Value::Table(t) => t.get("version").and_then(|v| v.as_str()).map(String::from),
+                _ => None,
+            };
+            deps.push(DependencyVerdict {
+                name: normalize_name(name),
+                version,
+                verdict: warp_core::Verdict::Unknown,
+                transitive: false,
+                via: Vec::new(),
+            });
+        }
+    }
+
+    deps
+}
+
+/// Parse a single PEP 508 requirement (`name[extra]>=1.0; marker`).
+///
+/// Returns `None` for blank lines, comments, and pip options (`-r`, `-e`, …).
+fn parse_requirement(line: &str) -> Option<DependencyVerdict> {
+    let line = line.split('#').next()?.trim();
+    if line.is_empty() || line.starts_with('-') {
+        return None;
+    }
+
+    let req_re = Regex::new(r"^([A-Za-z0-9][A-Za-z0-9._-]*)\s*(?:\[[^\]]*\])?\s*([^;]*)").ok()?;
+    let caps = req_re.captures(line)?;
+    let version = caps[2].trim();
+
+    Some(DependencyVerdict {
+        name: normalize_name(&caps[1]),
+        version: (!version.is_empty()).then(|| version.to_string()),
+        verdict: warp_core::Verdict::Unknown,
+        transitive: false,
+        via: Vec::new(),
+    })
+}
+
+/// Normalize a package name per PEP 503.
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut prev_sep = false;
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !prev_sep {
+                normalized.push('-');
+            }
+            prev_sep = true;
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+            prev_sep = false;
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_requirements_txt() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("requirements.txt"),
+            "# web\nFlask==3.0.0\npsycopg2-binary>=2.9 ; python_version >= '3.8'\n\
+             -r dev.txt\nrequests[socks]\n\n",
+        )
+        .unwrap();
+
+        let deps = analyze_python_deps(tmp.path()).unwrap();
+        let names: Vec<&str> = deps.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["flask", "psycopg2-binary", "requests"]);
+        assert_eq!(deps[0].version.as_deref(), Some("==3.0.0"));
+        assert_eq!(deps[1].version.as_deref(), Some(">=2.9"));
+        assert!(deps[2].version.is_none());
+    }
+
+    #[test]
+    fn test_parse_pyproject_pep621_and_poetry() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("pyproject.toml"),
+            r#"
+[project]
+name = "app"
+dependencies = ["fastapi>=0.110", "NumPy"]
+
+[project.optional-dependencies]
+db = ["asyncpg"]
+
+[tool.poetry.dependencies]
+python = "^3.12"
+Pillow = { version = "^10" }
+"#,
+        )
+        .unwrap();
+
+        let deps = analyze_python_deps(tmp.path()).unwrap();
+        let names: Vec<&str> = deps.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["fastapi", "numpy", "asyncpg", "pillow"]);
+        assert_eq!(deps[3].version.as_deref(), Some("^10"));
+    }
+
+    #[test]
+    fn test_requirements_do_not_duplicate_pyproject() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("pyproject.toml"),
+            "[project]\nname = \"app\"\ndependencies = [\"flask\"]\n",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("requirements.txt"), "Flask==3.0\n").unwrap();
+
+        let deps = analyze_python_deps(tmp.path()).unwrap();
+        assert_eq!(deps.len(), 1);
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Typing_Extensions"), "typing-extensions");
+        assert_eq!(normalize_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_name("a__-b"), "a-b");
+    }
+
+    #[test]
+    fn test_no_manifests() {
+        let tmp = TempDir::new().unwrap();
+        assert!(analyze_python_deps(tmp.path()).unwrap().is_empty());
+    }
+}
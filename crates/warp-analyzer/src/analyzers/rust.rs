@@ -26,6 +26,8 @@ pub fn analyze_cargo_toml(project_path: &Path) -> Result<Vec<DependencyVerdict>>
                 name: name.clone(),
                 version,
                 verdict: warp_core::Verdict::Unknown, // Will be resolved by compat DB
+                transitive: false,
+                via: Vec::new(),
             });
         }
     }
@@ -43,6 +45,8 @@ pub fn analyze_cargo_toml(project_path: &Path) -> Result<Vec<DependencyVerdict>>
                     name: name.clone(),
                     version,
                     verdict: warp_core::Verdict::Unknown,
+                    transitive: false,
+                    via: Vec::new(),
                 });
             }
         }
@@ -0,0 +1,174 @@
+//! Compiled component size estimate.
+//!
+//! A ported project's component is roughly its language runtime, plus what
+//! each dependency compiles to, plus any large files the project bundles.
+//! The estimate adds up those three parts:
+//!
+//! - a per-language base (an empty handler built with the usual toolchain:
+//!   cargo-component, TinyGo, componentize-js, componentize-py),
+//! - each dependency's `size_kb` from the compat DB, or the language's
+//!   default when the DB has none (transitive dependencies count a quarter
+//!   of the default, since most of them are small helpers),
+//! - every file of at least [`LARGE_ASSET_BYTES`] in the project, reported
+//!   separately as a large asset.
+//!
+//! The result is meant for comparing projects and spotting outliers before
+//! porting, not as a build-size guarantee.
+
+use std::path::Path;
+
+use anyhow::Result;
+use walkdir::WalkDir;
+use warp_core::{DependencyVerdict, LargeAsset};
+
+use crate::analyzers::source;
+use crate::db::CompatDb;
+
+/// Files at least this large are counted and flagged as large assets.
+pub const LARGE_ASSET_BYTES: u64 = 1024 * 1024;
+
+/// Size of an empty handler for `language`, in KiB.
+fn base_kb(language: &str) -> u32 {
+    match language {
+        "rust" => 250,
+        // TinyGo runtime and GC.
+        "go" => 600,
+        // StarlingMonkey (SpiderMonkey) engine.
+        "typescript" | "bun" => 9_000,
+        // CPython interpreter and stdlib.
+        "python" => 22_000,
+        _ => 1_000,
+    }
+}
+
+/// Default contribution of a direct dependency not sized in the compat DB, in KiB.
+fn default_dependency_kb(language: &str) -> u32 {
+    match language {
+        "rust" => 150,
+        "go" => 150,
+        // Bundled and minified source.
+        "typescript" | "bun" => 60,
+        "python" => 200,
+        _ => 100,
+    }
+}
+
+/// The estimate and its parts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeEstimate {
+    pub base_mb: f64,
+    pub dependencies_mb: f64,
+    pub large_assets: Vec<LargeAsset>,
+}
+
+impl SizeEstimate {
+    /// Total estimated size in MiB, rounded to one decimal.
+    pub fn total_mb(&self) -> f64 {
+        let assets: u64 = self.large_assets.iter().map(|a| a.size_bytes).sum();
+        let total = self.base_mb + self.dependencies_mb + assets as f64 / (1024.0 * 1024.0);
+        (total * 10.0).round() / 10.0
+    }
+}
+
+/// Estimate the compiled size of the `language` project at `project_path`.
+pub fn estimate(
+    project_path: &Path,
+    language: &str,
+    deps: &[DependencyVerdict],
+    db: &CompatDb,
+) -> Result<SizeEstimate> {
+    let default_kb = default_dependency_kb(language);
+    let dependencies_kb: u64 = deps
+        .iter()
+        .map(|dep| match db.size_kb(dep, language) {
+            Some(kb) => u64::from(kb),
+            None if dep.transitive => u64::from(default_kb / 4),
+            None => u64::from(default_kb),
+        })
+        .sum();
+
+    Ok(SizeEstimate {
+        base_mb: f64::from(base_kb(language)) / 1024.0,
+        dependencies_mb: dependencies_kb as f64 / 1024.0,
+        large_assets: large_assets(project_path)?,
+    })
+}
+
+/// Files of at least [`LARGE_ASSET_BYTES`] under `project_path`, largest
+/// first. Dependency, build, and test directories are skipped.
+fn large_assets(project_path: &Path) -> Result<Vec<LargeAsset>> {
+    if !project_path.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut assets = Vec::new();
+    let walker = WalkDir::new(project_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !source::skipped(e));
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let size_bytes = entry.metadata()?.len();
+        if size_bytes >= LARGE_ASSET_BYTES {
+            let path = entry.path().strip_prefix(project_path).unwrap_or(entry.path());
+            assets.push(LargeAsset {
+                path: path.display().to_string(),
+                size_bytes,
+            });
+        }
+    }
+    assets.sort_by_key(|asset| std::cmp::Reverse(asset.size_bytes));
+    Ok(assets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp_core::Verdict;
+
+    fn dep(name: &str, transitive: bool) -> DependencyVerdict {
+        DependencyVerdict {
+            name: name.to_string(),
+            version: Some("1.0.0".to_string()),
+            verdict: Verdict::Unknown,
+            transitive,
+            via: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_dependencies_use_compat_db_sizes_and_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = CompatDb::builtin();
+        let deps = [dep("sqlx", false), dep("my-helper", false), dep("itoa", true)];
+        let estimate = estimate(tmp.path(), "rust", &deps, &db).unwrap();
+
+        // 1500 (sqlx, from the DB) + 150 (direct default) + 37 (transitive).
+        assert_eq!(estimate.dependencies_mb, 1687.0 / 1024.0);
+        assert!(estimate.large_assets.is_empty());
+        assert_eq!(estimate.total_mb(), 1.9);
+    }
+
+    #[test]
+    fn test_large_assets_are_counted_and_skipped_dirs_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("static")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("node_modules/big")).unwrap();
+        std::fs::write(tmp.path().join("static/model.bin"), vec![0u8; 3 * 1024 * 1024]).unwrap();
+        std::fs::write(tmp.path().join("static/logo.png"), vec![0u8; 10 * 1024]).unwrap();
+        std::fs::write(tmp.path().join("node_modules/big/blob"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+
+        let estimate = estimate(tmp.path(), "typescript", &[], &CompatDb::builtin()).unwrap();
+        assert_eq!(
+            estimate.large_assets,
+            [LargeAsset {
+                path: "static/model.bin".to_string(),
+                size_bytes: 3 * 1024 * 1024,
+            }]
+        );
+        // 9000 KiB engine + 3 MiB asset.
+        assert_eq!(estimate.total_mb(), 11.8);
+    }
+}
@@ -0,0 +1,334 @@
+//! Source scanner — finds native code and OS access in the project itself.
+//!
+//! Dependency verdicts miss FFI, process spawning, and raw sockets written
+//! in the repo. Each source file of the project's language is matched line
+//! by line against [`RULES`]; every hit becomes a blocker located at
+//! `path:line`, relative to the project root.
+//!
+//! Dependency and build directories (`target`, `node_modules`, `vendor`,
+//! ...), test directories, Go `_test.go` files, and comment lines are
+//! skipped: none of them end up in the component.
+
+use anyhow::Result;
+use regex::Regex;
+use std::path::Path;
+use walkdir::{DirEntry, WalkDir};
+use warp_core::Blocker;
+
+/// A pattern that marks code a Wasm component cannot run as written.
+struct Rule {
+    languages: &'static [&'static str],
+    /// Short name of the construct; reported as the blocker's dependency.
+    construct: &'static str,
+    pattern: &'static str,
+    reason: &'static str,
+    fix: &'static str,
+    effort_hours: f64,
+}
+
+const JS: &[&str] = &["typescript", "bun"];
+
+const RULES: &[Rule] = &[
+    // Rust
+    Rule {
+        languages: &["rust"],
+        construct: "extern \"C\"",
+        pattern: r#"\bextern\s+"C"|#\[link\("#,
+        reason: "FFI to native code",
+        fix: "Replace the native library with a pure-Rust implementation",
+        effort_hours: 4.0,
+    },
+    Rule {
+        languages: &["rust"],
+        construct: "std::process::Command",
+        pattern: r"\bprocess::Command\b|\bCommand::new\(",
+        reason: "Spawns OS processes; components have no process model",
+        fix: "Do the work in-process or call a service over HTTP",
+        effort_hours: 2.0,
+    },
+    Rule {
+        languages: &["rust"],
+        construct: "libc",
+        pattern: r"\blibc::\w",
+        reason: "Raw libc calls",
+        fix: "Use the std or WASI equivalent",
+        effort_hours: 2.0,
+    },
+    Rule {
+        languages: &["rust"],
+        construct: "TcpListener",
+        pattern: r"\bTcpListener::bind\b|\bUdpSocket::bind\b",
+        reason: "Binds its own socket; the HTTP trigger owns the listener",
+        fix: "Export wasi:http/incoming-handler instead of serving",
+        effort_hours: 1.0,
+    },
+    // Go
+    Rule {
+        languages: &["go"],
+        construct: "cgo",
+        pattern: r#"^\s*import\s+"C"\s*$"#,
+        reason: "cgo links native code",
+        fix: "Replace the C code with pure Go",
+        effort_hours: 4.0,
+    },
+    Rule {
+        languages: &["go"],
+        construct: "syscall",
+        pattern: r#"\bsyscall\.\w|"golang\.org/x/sys/unix""#,
+        reason: "Raw syscalls",
+        fix: "Use the os or net package equivalent",
+        effort_hours: 2.0,
+    },
+    Rule {
+        languages: &["go"],
+        construct: "os/exec",
+        pattern: r"\bexec\.Command(Context)?\(",
+        reason: "Spawns OS processes; components have no process model",
+        fix: "Do the work in-process or call a service over HTTP",
+        effort_hours: 2.0,
+    },
+    Rule {
+        languages: &["go"],
+        construct: "net.Listen",
+        pattern: r"\bnet\.Listen(TCP|UDP|Packet)?\(|\bhttp\.ListenAndServe(TLS)?\(",
+        reason: "Binds its own socket; the HTTP trigger owns the listener",
+        fix: "Register the handler with the WarpGrid Go SDK instead of listening",
+        effort_hours: 1.0,
+    },
+    // TypeScript / JavaScript
+    Rule {
+        languages: JS,
+        construct: "native addon",
+        pattern: r#"require\(\s*['"][^'"]+\.node['"]\s*\)|\bprocess\.dlopen\(|\brequire\(\s*['"]bindings['"]\s*\)"#,
+        reason: "Loads a native Node addon",
+        fix: "Replace the addon with a pure-JS or Wasm package",
+        effort_hours: 4.0,
+    },
+    Rule {
+        languages: JS,
+        construct: "bun:ffi",
+        pattern: r#"['"]bun:ffi['"]"#,
+        reason: "FFI to native code",
+        fix: "Replace the native library with a pure-JS or Wasm package",
+        effort_hours: 4.0,
+    },
+    Rule {
+        languages: JS,
+        construct: "child_process",
+        pattern: r#"['"](node:)?child_process['"]|\bBun\.spawn(Sync)?\("#,
+        reason: "Spawns OS processes; components have no process model",
+        fix: "Do the work in-process or call a service over HTTP",
+        effort_hours: 2.0,
+    },
+    Rule {
+        languages: JS,
+        construct: "worker_threads",
+        pattern: r#"['"](node:)?worker_threads['"]"#,
+        reason: "Threads are not available to components",
+        fix: "Run the work inline",
+        effort_hours: 2.0,
+    },
+    // Python
+    Rule {
+        languages: &["python"],
+        construct: "ctypes",
+        pattern: r"^\s*(import|from)\s+(ctypes|cffi)\b",
+        reason: "FFI to native code",
+        fix: "Replace the native library with a pure-Python package",
+        effort_hours: 4.0,
+    },
+    Rule {
+        languages: &["python"],
+        construct: "subprocess",
+        pattern: r"^\s*(import|from)\s+subprocess\b|\bos\.(system|popen|fork|exec\w*)\(",
+        reason: "Spawns OS processes; components have no process model",
+        fix: "Do the work in-process or call a service over HTTP",
+        effort_hours: 2.0,
+    },
+    Rule {
+        languages: &["python"],
+        construct: "multiprocessing",
+        pattern: r"^\s*(import|from)\s+(multiprocessing|threading)\b",
+        reason: "Threads and processes are not available to components",
+        fix: "Run the work inline",
+        effort_hours: 2.0,
+    },
+];
+
+/// Directories that hold dependencies, build output, tests, or warp's own
+/// caches.
+const SKIP_DIRS: &[&str] = &[
+    ".git",
+    ".warp",
+    "target",
+    "node_modules",
+    "vendor",
+    "dist",
+    "build",
+    ".venv",
+    "venv",
+    "__pycache__",
+    "tests",
+    "test",
+    "__tests__",
+];
+
+fn extensions(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" => &["rs"],
+        "go" => &["go"],
+        "typescript" | "bun" => &["ts", "tsx", "mts", "cts", "js", "mjs", "cjs"],
+        "python" => &["py"],
+        _ => &[],
+    }
+}
+
+fn is_comment(line: &str, language: &str) -> bool {
+    let line = line.trim_start();
+    match language {
+        "python" => line.starts_with('#'),
+        _ => line.starts_with("//") || line.starts_with("/*") || line.starts_with('*'),
+    }
+}
+
+pub(crate) fn skipped(entry: &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.file_name().to_str().is_some_and(|name| SKIP_DIRS.contains(&name))
+}
+
+/// Scan the project's own sources for constructs in [`RULES`].
+pub fn scan_sources(project_path: &Path, language: &str) -> Result<Vec<Blocker>> {
+    let exts = extensions(language);
+    let rules: Vec<(&Rule, Regex)> = RULES
+        .iter()
+        .filter(|rule| rule.languages.contains(&language))
+        .map(|rule| Ok((rule, Regex::new(rule.pattern)?)))
+        .collect::<Result<_>>()?;
+    if rules.is_empty() || !project_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut blockers = Vec::new();
+    let walker = WalkDir::new(project_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !skipped(e));
+    for entry in walker {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy();
+        if !entry.file_type().is_file()
+            || !path.extension().and_then(|e| e.to_str()).is_some_and(|e| exts.contains(&e))
+            || name.ends_with("_test.go")
+        {
+            continue;
+        }
+        // Non-UTF-8 files are not source we can judge.
+        let Ok(text) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let relative = path.strip_prefix(project_path).unwrap_or(path).display().to_string();
+        for (number, line) in text.lines().enumerate() {
+            if is_comment(line, language) {
+                continue;
+            }
+            for (rule, re) in &rules {
+                if re.is_match(line) {
+                    blockers.push(Blocker {
+                        dependency: rule.construct.to_string(),
+                        reason: rule.reason.to_string(),
+                        fix: rule.fix.to_string(),
+                        effort_hours: Some(rule.effort_hours),
+                        location: Some(format!("{relative}:{}", number + 1)),
+                    });
+                }
+            }
+        }
+    }
+
+    tracing::info!(count = blockers.len(), "Scanned project sources");
+    Ok(blockers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, path: &str, content: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn locations(blockers: &[Blocker]) -> Vec<(&str, &str)> {
+        blockers
+            .iter()
+            .map(|b| (b.dependency.as_str(), b.location.as_deref().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_ffi_and_process_are_located() {
+        let tmp = TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "src/main.rs",
+            "use std::process::Command;\n\
+             // Command::new(\"ls\") in a comment is fine\n\
+             unsafe extern \"C\" {\n    fn getpid() -> i32;\n}\n",
+        );
+        write(tmp.path(), "target/debug/build/out.rs", "extern \"C\" {}\n");
+        write(tmp.path(), "tests/it.rs", "fn f() { std::process::Command::new(\"x\"); }\n");
+
+        let blockers = scan_sources(tmp.path(), "rust").unwrap();
+        assert_eq!(
+            locations(&blockers),
+            [("std::process::Command", "src/main.rs:1"), ("extern \"C\"", "src/main.rs:3")]
+        );
+        assert_eq!(blockers[1].reason, "FFI to native code");
+    }
+
+    #[test]
+    fn test_go_cgo_and_listen() {
+        let tmp = TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "main.go",
+            "package main\n\nimport \"C\"\n\nfunc main() {\n\tnet.Listen(\"tcp\", \":80\")\n}\n",
+        );
+        write(tmp.path(), "main_test.go", "exec.Command(\"ls\")\n");
+
+        let blockers = scan_sources(tmp.path(), "go").unwrap();
+        assert_eq!(locations(&blockers), [("cgo", "main.go:3"), ("net.Listen", "main.go:6")]);
+    }
+
+    #[test]
+    fn test_js_native_addons_and_child_process() {
+        let tmp = TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "src/index.ts",
+            "import { spawn } from 'node:child_process';\n\
+             const addon = require('./build/Release/addon.node');\n",
+        );
+        write(tmp.path(), "node_modules/x/index.js", "require('child_process')\n");
+
+        let blockers = scan_sources(tmp.path(), "typescript").unwrap();
+        assert_eq!(
+            locations(&blockers),
+            [("child_process", "src/index.ts:1"), ("native addon", "src/index.ts:2")]
+        );
+    }
+
+    #[test]
+    fn test_python_imports() {
+        let tmp = TempDir::new().unwrap();
+        write(tmp.path(), "app.py", "# import subprocess\nimport ctypes\nos.system('ls')\n");
+
+        let blockers = scan_sources(tmp.path(), "python").unwrap();
+        assert_eq!(locations(&blockers), [("ctypes", "app.py:2"), ("subprocess", "app.py:3")]);
+    }
+}
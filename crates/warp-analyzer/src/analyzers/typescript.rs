@@ -21,6 +21,8 @@ pub fn analyze_package_json(project_path: &Path) -> Result<Vec<DependencyVerdict
                     name: name.clone(),
                     version: version.as_str().map(String::from),
                     verdict: warp_core::Verdict::Unknown,
+                    transitive: false,
+                    via: Vec::new(),
                 });
             }
         }
@@ -0,0 +1,193 @@
+//! Analysis cache and diff baseline.
+//!
+//! The dependency half of an analysis — reading the manifest and lockfile
+//! and evaluating every dependency against the compat-db — is keyed by:
+//!
+//! - the language
+//! - the digest of each manifest and lockfile the language reads
+//! - the compat-db [`fingerprint`](crate::db::CompatDb::fingerprint)
+//!
+//! so it is reused until one of those changes. Source and Dockerfile
+//! scanning always run. Each run also records its report as the baseline
+//! `warp convert analyze --diff` compares against.
+//!
+//! Entries live in `<project>/.warp/analyze/`. Only the most recent
+//! [`MAX_ENTRIES`] are kept. Cache I/O failures never fail an analysis.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use warp_core::{AnalysisReport, Blocker, DependencyVerdict, ShimItem};
+
+/// Bumped whenever the key derivation or entry format changes.
+const CACHE_VERSION: &str = "v1";
+
+/// Entries kept per project.
+const MAX_ENTRIES: usize = 8;
+
+/// The report of the last run, for `--diff`.
+const LAST_REPORT: &str = "last-report.json";
+
+/// Files the dependency analyzers read, for every language.
+const DEPENDENCY_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "go.mod",
+    "go.sum",
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "bun.lock",
+    "bun.lockb",
+    "pyproject.toml",
+    "requirements.txt",
+];
+
+/// Cache directory for a project.
+fn cache_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".warp").join("analyze")
+}
+
+/// The cached dependency half of an analysis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyAnalysis {
+    pub dependencies: Vec<DependencyVerdict>,
+    pub blockers: Vec<Blocker>,
+    pub shim_items: Vec<ShimItem>,
+}
+
+/// Compute the cache key for the dependencies of `project_path`.
+pub fn key(project_path: &Path, language: &str, db_fingerprint: &str) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("warp-analyze-cache {CACHE_VERSION}\nlang={language}\ndb={db_fingerprint}\n"));
+    for name in DEPENDENCY_FILES {
+        let path = project_path.join(name);
+        if path.is_file() {
+            let bytes = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            hasher.update(format!("{name}={}\n", hex::encode(Sha256::digest(&bytes))));
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// The cached analysis for `key`, if there is one.
+pub fn restore(project_path: &Path, key: &str) -> Result<Option<DependencyAnalysis>> {
+    let path = cache_dir(project_path).join(format!("{key}.json"));
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&path)?;
+    match serde_json::from_slice(&bytes) {
+        Ok(analysis) => {
+            // Touch the entry so pruning keeps recently used ones.
+            fs::write(&path, &bytes)?;
+            tracing::debug!("Analysis cache hit ({})", &key[..12]);
+            Ok(Some(analysis))
+        }
+        Err(e) => {
+            tracing::warn!("Analysis cache entry {key} is corrupt ({e}); re-analyzing");
+            let _ = fs::remove_file(&path);
+            Ok(None)
+        }
+    }
+}
+
+/// Store a fresh analysis under `key` and prune old entries.
+pub fn store(project_path: &Path, key: &str, analysis: &DependencyAnalysis) -> Result<()> {
+    let dir = cache_dir(project_path);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(format!("{key}.json")), serde_json::to_vec(analysis)?)?;
+    prune(&dir)
+}
+
+/// The report recorded by the last run, if any.
+pub fn last_report(project_path: &Path) -> Result<Option<AnalysisReport>> {
+    let path = cache_dir(project_path).join(LAST_REPORT);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path)?;
+    let report = serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(Some(report))
+}
+
+/// Record `report` as the baseline for the next `--diff`.
+pub fn store_last_report(project_path: &Path, report: &AnalysisReport) -> Result<()> {
+    let dir = cache_dir(project_path);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(LAST_REPORT), serde_json::to_vec_pretty(report)?)?;
+    Ok(())
+}
+
+/// Drop all but the [`MAX_ENTRIES`] most recently used entries.
+fn prune(dir: &Path) -> Result<()> {
+    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter(|p| p.file_name().is_some_and(|name| name != LAST_REPORT))
+        .filter_map(|p| Some((fs::metadata(&p).ok()?.modified().ok()?, p)))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+    for (_, path) in entries.into_iter().skip(MAX_ENTRIES) {
+        let _ = fs::remove_file(&path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_tracks_lockfiles_and_rules_only() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[dependencies]\nserde = \"1\"\n").unwrap();
+        let base = key(dir.path(), "rust", "db1").unwrap();
+
+        fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        assert_eq!(key(dir.path(), "rust", "db1").unwrap(), base, "sources are not part of the key");
+
+        assert_ne!(key(dir.path(), "rust", "db2").unwrap(), base);
+        assert_ne!(key(dir.path(), "go", "db1").unwrap(), base);
+        fs::write(dir.path().join("Cargo.lock"), "version = 3\n").unwrap();
+        assert_ne!(key(dir.path(), "rust", "db1").unwrap(), base);
+    }
+
+    #[test]
+    fn test_store_restore_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let analysis = DependencyAnalysis {
+            dependencies: Vec::new(),
+            blockers: Vec::new(),
+            shim_items: Vec::new(),
+        };
+        assert!(restore(dir.path(), "k0-0000000000").unwrap().is_none());
+        for i in 0..=MAX_ENTRIES {
+            store(dir.path(), &format!("k{i}-0000000000"), &analysis).unwrap();
+        }
+        store_last_report(dir.path(), &AnalysisReport {
+            project_name: "t".into(),
+            language: "rust".into(),
+            overall_verdict: warp_core::OverallVerdict::Convertible,
+            dependencies: Vec::new(),
+            blockers: Vec::new(),
+            shim_items: Vec::new(),
+            estimated_wasm_size_mb: None,
+            large_assets: Vec::new(),
+            fixes: Vec::new(),
+            suggested_config: None,
+        })
+        .unwrap();
+
+        let kept = fs::read_dir(cache_dir(dir.path())).unwrap().count();
+        assert_eq!(kept, MAX_ENTRIES + 1, "entries plus the last report");
+        assert!(last_report(dir.path()).unwrap().is_some());
+
+        fs::write(cache_dir(dir.path()).join("bad-0000000000.json"), "{").unwrap();
+        assert!(restore(dir.path(), "bad-0000000000").unwrap().is_none());
+        assert!(!cache_dir(dir.path()).join("bad-0000000000.json").exists());
+    }
+}
@@ -1,25 +1,434 @@
 //! Compatibility database — resolves dependency verdicts.
+//!
+//! A dependency may have several entries, each limited to a semver range
+//! with `versions` (for example `ring` below 0.17 is incompatible, from
+//! 0.17 on compatible). The entry whose range contains the dependency's
+//! resolved version wins; an entry without `versions` covers the rest.
+//!
+//! An incompatible entry's `alternative` that is a single package name is
+//! one `warp convert fix` can swap in, at `alternative_version`.
+//!
+//! An entry may also record `size_kb`, roughly what the dependency adds to
+//! a compiled component. The analyzer's size estimate uses it in place of
+//! the per-language default.
 
+use anyhow::{Context, Result, bail};
+use semver::{Prerelease, Version, VersionReq};
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use warp_core::{Blocker, DependencyVerdict, ShimItem};
 
+pub mod sync;
+
 /// Bun compat-db results.json embedded at compile time.
 const BUN_RESULTS_JSON: &str = include_str!("../../../../compat-db/bun/results.json");
 
-#[derive(Debug, Deserialize)]
+/// Ecosystems a compat-db entry can belong to (the analyzer's language names).
+const ECOSYSTEMS: &[&str] = &["rust", "go", "typescript", "python", "bun"];
+
+/// Verdicts a compat-db entry can carry.
+const VERDICTS: &[&str] = &["compatible", "incompatible", "shim_compatible"];
+
+/// One `[[dependency]]` entry of a compat-db TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct CompatEntry {
+    ecosystem: String,
     name: String,
     verdict: String,
     reason: Option<String>,
     alternative: Option<String>,
+    /// Version requirement to write when `warp convert fix` swaps in
+    /// `alternative` (`*` when unset).
+    alternative_version: Option<String>,
     shim: Option<String>,
     migration_guide: Option<String>,
+    /// Semver requirement (e.g. `< 0.17`) the verdict is limited to.
+    versions: Option<String>,
+    /// What the dependency adds to a compiled component, in KiB; used for
+    /// the report's size estimate.
+    size_kb: Option<u32>,
+    /// Version range the verdict was checked against (informational).
+    #[allow(dead_code)]
+    version: Option<String>,
+    #[allow(dead_code)]
+    notes: Option<String>,
 }
 
-/// Built-in compatibility rules (shipped with the binary).
-/// In production, this loads from compat-db/ TOML files.
-fn builtin_rules() -> HashMap<String, CompatEntry> {
+/// A compat-db TOML file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompatFile {
+    #[serde(default)]
+    dependency: Vec<CompatEntry>,
+}
+
+impl CompatEntry {
+    /// Check the entry is one the evaluator can act on.
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("`name` must not be empty".to_string());
+        }
+        if !ECOSYSTEMS.contains(&self.ecosystem.as_str()) {
+            return Err(format!(
+                "unknown ecosystem '{}' (expected one of: {})",
+                self.ecosystem,
+                ECOSYSTEMS.join(", ")
+            ));
+        }
+        if !VERDICTS.contains(&self.verdict.as_str()) {
+            return Err(format!(
+                "unknown verdict '{}' (expected one of: {})",
+                self.verdict,
+                VERDICTS.join(", ")
+            ));
+        }
+        if self.verdict == "shim_compatible" && self.shim.is_none() {
+            return Err("`shim_compatible` entries must name a `shim`".to_string());
+        }
+        if self.alternative_version.is_some() && self.alternative.is_none() {
+            return Err("`alternative_version` needs an `alternative`".to_string());
+        }
+        if let Some(range) = &self.versions {
+            VersionReq::parse(range).map_err(|e| format!("invalid `versions` '{range}': {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Whether this entry's range contains `version`; entries without a
+    /// range contain every version.
+    fn covers(&self, version: &Version) -> bool {
+        self.versions
+            .as_deref()
+            .and_then(|range| VersionReq::parse(range).ok())
+            .is_none_or(|req| req.matches(version))
+    }
+
+    /// Blockers outrank shims, which outrank compatible entries.
+    fn severity(&self) -> u8 {
+        match self.verdict.as_str() {
+            "incompatible" => 2,
+            "shim_compatible" => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Approximate size contributions (KiB) of built-in dependencies that are
+/// well above their language's per-dependency default.
+const BUILTIN_SIZES_KB: &[(&str, &str, u32)] = &[
+    ("rust", "tokio", 400),
+    ("rust", "axum", 350),
+    ("rust", "hyper", 300),
+    ("rust", "reqwest", 700),
+    ("rust", "sqlx", 1500),
+    ("rust", "rustls", 600),
+    ("rust", "ring", 250),
+    ("go", "github.com/jackc/pgx", 800),
+    ("go", "github.com/go-sql-driver/mysql", 400),
+    ("go", "github.com/redis/go-redis/v9", 900),
+    ("typescript", "express", 400),
+    ("python", "flask", 900),
+    ("python", "fastapi", 2500),
+];
+
+/// Versions `warp convert fix` writes for built-in alternatives.
+const BUILTIN_ALTERNATIVE_VERSIONS: &[(&str, &str, &str)] = &[
+    ("rust", "openssl", "0.23"),
+    ("rust", "openssl-sys", "0.23"),
+    ("typescript", "bcrypt", "^3.0.0"),
+    ("typescript", "better-sqlite3", "^1.12.0"),
+];
+
+/// A drop-in replacement for a dependency, as `warp convert fix` applies it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    pub name: String,
+    /// Version requirement for the manifest.
+    pub version: String,
+}
+
+/// Rules keyed by `(ecosystem, name)`, in file order.
+type Rules = HashMap<(String, String), Vec<CompatEntry>>;
+
+/// Parse a lockfile or manifest version as semver, leniently: a leading
+/// `v` or requirement operator is dropped and missing components are
+/// zero (`0.16` is `0.16.0`). Pre-releases, Go pseudo-versions included,
+/// are matched as their release.
+fn parse_version(raw: &str) -> Option<Version> {
+    let raw = raw.trim().trim_start_matches(['v', '=', '^', '~']).trim();
+    let mut version = Version::parse(raw).ok().or_else(|| {
+        let parts: Vec<u64> = raw.split('.').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        match parts[..] {
+            [major] => Some(Version::new(major, 0, 0)),
+            [major, minor] => Some(Version::new(major, minor, 0)),
+            _ => None,
+        }
+    })?;
+    version.pre = Prerelease::EMPTY;
+    Some(version)
+}
+
+/// The entry that applies to `dep`, and whether its version was unknown.
+///
+/// With a version, the first ranged entry covering it wins, then the
+/// unranged one. Without one, the unranged entry applies if there is
+/// one, and otherwise the most severe ranged entry.
+fn select<'a>(entries: &'a [CompatEntry], dep: &DependencyVerdict) -> Option<(&'a CompatEntry, bool)> {
+    let (ranged, unranged): (Vec<_>, Vec<_>) = entries.iter().partition(|e| e.versions.is_some());
+    match dep.version.as_deref().and_then(parse_version) {
+        Some(version) => ranged
+            .into_iter()
+            .find(|e| e.covers(&version))
+            .or(unranged.first().copied())
+            .map(|e| (e, false)),
+        None => match unranged.first() {
+            Some(e) => Some((e, false)),
+            None => ranged.into_iter().max_by_key(|e| e.severity()).map(|e| (e, true)),
+        },
+    }
+}
+
+/// Say which versions a ranged verdict is about, and whether the
+/// dependency's own version could not be checked against it.
+fn with_range(reason: String, entry: &CompatEntry, unknown_version: bool) -> String {
+    let Some(range) = &entry.versions else {
+        return reason;
+    };
+    let note = if unknown_version {
+        format!("versions {range}; locked version unknown")
+    } else {
+        format!("versions {range}")
+    };
+    if reason.is_empty() { note } else { format!("{reason} ({note})") }
+}
+
+/// The compatibility database: built-in rules with compat-db TOML files
+/// layered on top.
+///
+/// Precedence, lowest first:
+/// 1. Built-in rules (and, for Bun, the embedded `results.json`)
+/// 2. `~/.warp/compat-sync/` — the last signed remote update; see [`sync`]
+/// 3. `$WARP_COMPAT_DB` — a compat-db directory such as this repo's `compat-db/`
+/// 4. `~/.warp/compat.d/` — user-level overrides
+///
+/// A later layer replaces an earlier layer's entries for the same ecosystem
+/// and name outright. Within one layer, defining the same dependency twice
+/// for the same `versions` range is an error, as is any entry that fails
+/// validation.
+#[derive(Debug)]
+pub struct CompatDb {
+    rules: Rules,
+    /// Digest of each layer's files, in the order they were layered.
+    layers: Vec<String>,
+}
+
+impl CompatDb {
+    /// Only the rules compiled into the binary.
+    pub fn builtin() -> Self {
+        Self {
+            rules: builtin_rules(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Built-in rules plus every layer from [`default_dirs`].
+    pub fn load() -> Result<Self> {
+        let mut db = Self::builtin();
+        if let Some(dir) = sync::sync_dir().filter(|dir| dir.is_dir()) {
+            db.layer_dir(&dir)?;
+        }
+        if let Ok(dir) = std::env::var("WARP_COMPAT_DB") {
+            let dir = PathBuf::from(dir);
+            if !dir.is_dir() {
+                bail!("WARP_COMPAT_DB is set to '{}' but it is not a directory", dir.display());
+            }
+            db.layer_dir(&dir)?;
+        }
+        if let Some(dir) = user_dir().filter(|dir| dir.is_dir()) {
+            db.layer_dir(&dir)?;
+        }
+        Ok(db)
+    }
+
+    /// Layer every `*.toml` file under `dir` (recursively) over the current
+    /// rules. Returns the number of entries loaded.
+    pub fn layer_dir(&mut self, dir: &Path) -> Result<usize> {
+        let mut files = Vec::new();
+        let paths = walkdir::WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "toml"));
+        for file in paths {
+            let path = file.path();
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            files.push((path.to_path_buf(), text));
+        }
+        self.layer_files(files)
+    }
+
+    /// Layer one compat-db document over the current rules. `source` names
+    /// it in errors. Returns the number of entries loaded.
+    pub fn layer_toml(&mut self, source: &Path, text: &str) -> Result<usize> {
+        self.layer_files(vec![(source.to_path_buf(), text.to_string())])
+    }
+
+    /// Layer `(path, contents)` compat-db files over the current rules as
+    /// one layer.
+    fn layer_files(&mut self, files: Vec<(PathBuf, String)>) -> Result<usize> {
+        let mut layer: HashMap<(String, String), Vec<(CompatEntry, PathBuf)>> = HashMap::new();
+        let mut digest = Sha256::new();
+        for (path, text) in &files {
+            digest.update(Sha256::digest(text.as_bytes()));
+            let parsed: CompatFile =
+                toml::from_str(text).with_context(|| format!("Invalid compat-db file {}", path.display()))?;
+            for entry in parsed.dependency {
+                if let Err(e) = entry.validate() {
+                    bail!("{}: dependency '{}': {e}", path.display(), entry.name);
+                }
+                let key = (entry.ecosystem.clone(), entry.name.clone());
+                let defined = layer.entry(key).or_default();
+                if let Some((_, first)) = defined.iter().find(|(e, _)| e.versions == entry.versions) {
+                    let range = entry.versions.as_deref().map(|r| format!(" {r}")).unwrap_or_default();
+                    bail!(
+                        "{}: dependency '{}'{range} ({}) is already defined in {}",
+                        path.display(),
+                        entry.name,
+                        entry.ecosystem,
+                        first.display()
+                    );
+                }
+                defined.push((entry, path.clone()));
+            }
+        }
+
+        self.layers.push(hex::encode(digest.finalize()));
+        let mut count = 0;
+        for (key, entries) in layer {
+            for (_, path) in &entries {
+                tracing::debug!(ecosystem = %key.0, name = %key.1, source = %path.display(), "compat-db entry");
+            }
+            count += entries.len();
+            self.rules.insert(key, entries.into_iter().map(|(entry, _)| entry).collect());
+        }
+        Ok(count)
+    }
+
+    /// Identifies the rules in effect: equal fingerprints mean every
+    /// dependency gets the same verdict.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(concat!("warp-analyzer ", env!("CARGO_PKG_VERSION"), "\n"));
+        for layer in &self.layers {
+            hasher.update(layer.as_bytes());
+            hasher.update(b"\n");
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// What `dep` of a `language` project adds to a compiled component, in
+    /// KiB, when its matching entry records a `size_kb`.
+    pub fn size_kb(&self, dep: &DependencyVerdict, language: &str) -> Option<u32> {
+        let entries = self.rules.get(&(language.to_string(), dep.name.clone()))?;
+        select(entries, dep)?.0.size_kb
+    }
+
+    /// The package that replaces `dep` of a `language` project, when its
+    /// matching entry is incompatible and names a single-package alternative.
+    pub fn replacement(&self, dep: &DependencyVerdict, language: &str) -> Option<Replacement> {
+        let entries = self.rules.get(&(language.to_string(), dep.name.clone()))?;
+        let (entry, _) = select(entries, dep)?;
+        let name = entry.alternative.as_deref()?;
+        let single_package = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '@'));
+        (entry.verdict == "incompatible" && single_package).then(|| Replacement {
+            name: name.to_string(),
+            version: entry.alternative_version.clone().unwrap_or_else(|| "*".to_string()),
+        })
+    }
+
+    /// Evaluate a list of dependencies of a `language` project.
+    ///
+    /// Ranged entries are matched against each dependency's resolved
+    /// version. Bun dependencies without a TOML entry fall back to `results.json`.
+    pub fn evaluate(
+        &self,
+        deps: &[DependencyVerdict],
+        language: &str,
+    ) -> (Vec<Blocker>, Vec<ShimItem>) {
+        let bun_rules = (language == "bun").then(bun_compat_rules);
+        let mut blockers = Vec::new();
+        let mut shim_items = Vec::new();
+
+        for dep in deps {
+            let key = (language.to_string(), dep.name.clone());
+            let entries = self.rules.get(&key);
+            if let Some((entry, unknown_version)) = entries.and_then(|entries| select(entries, dep)) {
+                let reason = with_range(entry.reason.clone().unwrap_or_default(), entry, unknown_version);
+                match entry.verdict.as_str() {
+                    "incompatible" => {
+                        blockers.push(Blocker {
+                            dependency: dep.name.clone(),
+                            reason: with_provenance(reason, dep),
+                            fix: match (&entry.alternative, &entry.migration_guide) {
+                                (Some(a), Some(guide)) => format!("Replace with: {a} (see {guide})"),
+                                (Some(a), None) => format!("Replace with: {a}"),
+                                (None, _) => "No known alternative".to_string(),
+                            },
+                            effort_hours: Some(2.0),
+                            location: None,
+                        });
+                    }
+                    "shim_compatible" => {
+                        shim_items.push(ShimItem {
+                            name: dep.name.clone(),
+                            shim: entry.shim.clone().unwrap_or_default(),
+                            description: with_provenance(reason, dep),
+                        });
+                    }
+                    _ => {} // compatible
+                }
+            } else if entries.is_none()
+                && let Some(mut blocker) = bun_rules.as_ref().and_then(|rules| bun_blocker(rules, dep))
+            {
+                blocker.reason = with_provenance(blocker.reason, dep);
+                blockers.push(blocker);
+            }
+        }
+
+        (blockers, shim_items)
+    }
+}
+
+/// Note how a transitive dependency got into the project, since that is
+/// where the fix usually has to happen.
+fn with_provenance(reason: String, dep: &DependencyVerdict) -> String {
+    if !dep.transitive {
+        return reason;
+    }
+    let origin = if dep.via.is_empty() {
+        "indirect dependency".to_string()
+    } else {
+        format!("pulled in via {}", dep.via.join(" → "))
+    };
+    if reason.is_empty() { origin } else { format!("{reason} ({origin})") }
+}
+
+/// `~/.warp/compat.d`, the user-level override directory.
+fn user_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".warp").join("compat.d"))
+}
+
+/// Built-in compatibility rules (shipped with the binary). compat-db TOML
+/// files are layered over these at runtime; see [`CompatDb`].
+fn builtin_rules() -> Rules {
     let mut rules = HashMap::new();
 
     // Rust ecosystem
@@ -35,21 +444,46 @@ fn builtin_rules() -> HashMap<String, CompatEntry> {
         ("openssl-sys", "incompatible", Some("FFI to native OpenSSL"), Some("rustls"), None),
         ("openssl", "incompatible", Some("FFI to native OpenSSL"), Some("rustls"), None),
         ("libz-sys", "incompatible", Some("FFI to native zlib"), Some("flate2 with rust backend"), None),
-        ("ring", "incompatible", Some("Contains platform-specific assembly"), Some("aws-lc-rs or rustls"), None),
         ("nix", "incompatible", Some("Direct Unix syscall wrappers"), None, None),
         ("libc", "shim_compatible", None, None, Some("filesystem")),
     ];
     for (name, verdict, reason, alt, shim) in rust_rules {
-        rules.insert(name.to_string(), CompatEntry {
+        rules.insert(("rust".to_string(), name.to_string()), vec![CompatEntry {
+            ecosystem: "rust".to_string(),
             name: name.to_string(),
             verdict: verdict.to_string(),
             reason: reason.map(String::from),
             alternative: alt.map(String::from),
+            alternative_version: None,
             shim: shim.map(String::from),
             migration_guide: None,
-        });
+            versions: None,
+            size_kb: None,
+            version: None,
+            notes: None,
+        }]);
     }
 
+    // ring only builds for Wasm without its assembly from 0.17 on.
+    let ring = |versions: &str, verdict: &str, reason: Option<&str>, alternative: Option<&str>| CompatEntry {
+        ecosystem: "rust".to_string(),
+        name: "ring".to_string(),
+        verdict: verdict.to_string(),
+        reason: reason.map(String::from),
+        alternative: alternative.map(String::from),
+        alternative_version: None,
+        shim: None,
+        migration_guide: None,
+        versions: Some(versions.to_string()),
+        size_kb: None,
+        version: None,
+        notes: None,
+    };
+    rules.insert(("rust".to_string(), "ring".to_string()), vec![
+        ring("<0.17", "incompatible", Some("Contains platform-specific assembly"), Some("ring 0.17, aws-lc-rs, or rustls")),
+        ring(">=0.17", "compatible", None, None),
+    ]);
+
     // Go ecosystem
     let go_rules = vec![
         ("github.com/gin-gonic/gin", "incompatible", Some("Uses net/http extensively"), Some("TinyGo-compatible HTTP framework"), None),
@@ -59,14 +493,20 @@ fn builtin_rules() -> HashMap<String, CompatEntry> {
         ("github.com/redis/go-redis/v9", "shim_compatible", Some("TCP sockets via net.Dial"), None, Some("database_proxy")),
     ];
     for (name, verdict, reason, alt, shim) in go_rules {
-        rules.insert(name.to_string(), CompatEntry {
+        rules.insert(("go".to_string(), name.to_string()), vec![CompatEntry {
+            ecosystem: "go".to_string(),
             name: name.to_string(),
             verdict: verdict.to_string(),
             reason: reason.map(String::from),
             alternative: alt.map(String::from),
+            alternative_version: None,
             shim: shim.map(String::from),
             migration_guide: None,
-        });
+            versions: None,
+            size_kb: None,
+            version: None,
+            notes: None,
+        }]);
     }
 
     // TypeScript ecosystem
@@ -79,14 +519,70 @@ fn builtin_rules() -> HashMap<String, CompatEntry> {
         ("pg", "shim_compatible", Some("TCP sockets"), None, Some("database_proxy")),
     ];
     for (name, verdict, reason, alt, shim) in ts_rules {
-        rules.insert(name.to_string(), CompatEntry {
+        rules.insert(("typescript".to_string(), name.to_string()), vec![CompatEntry {
+            ecosystem: "typescript".to_string(),
+            name: name.to_string(),
+            verdict: verdict.to_string(),
+            reason: reason.map(String::from),
+            alternative: alt.map(String::from),
+            alternative_version: None,
+            shim: shim.map(String::from),
+            migration_guide: None,
+            versions: None,
+            size_kb: None,
+            version: None,
+            notes: None,
+        }]);
+    }
+
+    // Python ecosystem (PEP 503-normalized names). componentize-py can only
+    // bundle pure-Python packages, so C extensions are blockers.
+    let python_rules = vec![
+        ("flask", "compatible", None, None, None),
+        ("fastapi", "compatible", None, None, None),
+        ("pydantic", "incompatible", Some("pydantic-core is a compiled Rust extension"), Some("pydantic<2 (pure-Python)"), None),
+        ("psycopg2", "shim_compatible", Some("libpq TCP connection"), None, Some("database_proxy")),
+        ("psycopg2-binary", "shim_compatible", Some("libpq TCP connection"), None, Some("database_proxy")),
+        ("psycopg", "shim_compatible", Some("libpq TCP connection"), None, Some("database_proxy")),
+        ("asyncpg", "incompatible", Some("Cython C extension"), Some("psycopg2 via database_proxy shim"), None),
+        ("pymysql", "shim_compatible", Some("TCP sockets"), None, Some("database_proxy")),
+        ("redis", "shim_compatible", Some("TCP sockets"), None, Some("database_proxy")),
+        ("numpy", "incompatible", Some("C extension"), None, None),
+        ("pandas", "incompatible", Some("C extension (depends on numpy)"), None, None),
+        ("scipy", "incompatible", Some("C/Fortran extension"), None, None),
+        ("lxml", "incompatible", Some("C extension (libxml2)"), Some("xml.etree.ElementTree"), None),
+        ("pillow", "incompatible", Some("C extension (libjpeg, zlib)"), None, None),
+        ("cryptography", "incompatible", Some("Compiled Rust/OpenSSL extension"), Some("pure-Python hashlib/hmac"), None),
+        ("grpcio", "incompatible", Some("C++ extension"), None, None),
+        ("uvloop", "incompatible", Some("Cython extension over libuv"), None, None),
+        ("orjson", "incompatible", Some("Compiled Rust extension"), Some("json (stdlib)"), None),
+    ];
+    for (name, verdict, reason, alt, shim) in python_rules {
+        rules.insert(("python".to_string(), name.to_string()), vec![CompatEntry {
+            ecosystem: "python".to_string(),
             name: name.to_string(),
             verdict: verdict.to_string(),
             reason: reason.map(String::from),
             alternative: alt.map(String::from),
+            alternative_version: None,
             shim: shim.map(String::from),
             migration_guide: None,
-        });
+            versions: None,
+            size_kb: None,
+            version: None,
+            notes: None,
+        }]);
+    }
+
+    for (ecosystem, name, version) in BUILTIN_ALTERNATIVE_VERSIONS {
+        for entry in rules.get_mut(&(ecosystem.to_string(), name.to_string())).into_iter().flatten() {
+            entry.alternative_version = Some(version.to_string());
+        }
+    }
+    for (ecosystem, name, kb) in BUILTIN_SIZES_KB {
+        for entry in rules.get_mut(&(ecosystem.to_string(), name.to_string())).into_iter().flatten() {
+            entry.size_kb = Some(*kb);
+        }
     }
 
     rules
@@ -126,99 +622,58 @@ fn bun_compat_rules() -> HashMap<String, BunCompatResult> {
         .collect()
 }
 
-/// Evaluate a list of dependencies against the compat DB.
+/// Evaluate a list of dependencies against the built-in rules.
 ///
 /// When `language` is `"bun"`, uses the Bun-specific compat DB (`results.json`).
-/// For all other languages, uses the built-in rule set.
+/// [`CompatDb::load`] adds the runtime compat-db layers.
 pub fn evaluate_dependencies(
     deps: &[DependencyVerdict],
     language: &str,
 ) -> (Vec<Blocker>, Vec<ShimItem>) {
-    if language == "bun" {
-        return evaluate_bun_dependencies(deps);
-    }
-
-    let rules = builtin_rules();
-    let mut blockers = Vec::new();
-    let mut shim_items = Vec::new();
-
-    for dep in deps {
-        if let Some(entry) = rules.get(&dep.name) {
-            match entry.verdict.as_str() {
-                "incompatible" => {
-                    blockers.push(Blocker {
-                        dependency: dep.name.clone(),
-                        reason: entry.reason.clone().unwrap_or_default(),
-                        fix: entry.alternative.as_ref()
-                            .map(|a| format!("Replace with: {a}"))
-                            .unwrap_or_else(|| "No known alternative".to_string()),
-                        effort_hours: Some(2.0),
-                        location: None,
-                    });
-                }
-                "shim_compatible" => {
-                    shim_items.push(ShimItem {
-                        name: dep.name.clone(),
-                        shim: entry.shim.clone().unwrap_or_default(),
-                        description: entry.reason.clone().unwrap_or_default(),
-                    });
-                }
-                _ => {} // compatible or unknown
-            }
-        }
-    }
-
-    (blockers, shim_items)
+    CompatDb::builtin().evaluate(deps, language)
 }
 
-/// Evaluate Bun dependencies against `compat-db/bun/results.json`.
+/// Evaluate a Bun dependency against `compat-db/bun/results.json`.
 ///
 /// Maps `status: "pass"` → compatible (no action), any other status → blocker.
-fn evaluate_bun_dependencies(deps: &[DependencyVerdict]) -> (Vec<Blocker>, Vec<ShimItem>) {
-    let bun_rules = bun_compat_rules();
-    let mut blockers = Vec::new();
-
-    for dep in deps {
-        if let Some(entry) = bun_rules.get(&dep.name) {
-            if entry.status != "pass" {
-                // Build a descriptive reason from the result fields
-                let mut notes = Vec::new();
-                if !entry.bundle_ok {
-                    notes.push("bundle failed".to_string());
-                }
-                if !entry.componentize_ok {
-                    notes.push("componentize failed".to_string());
-                }
-                if let Some(stage) = &entry.error_stage {
-                    notes.push(format!("failed at {stage} stage"));
-                }
+/// Dependencies not in the DB are treated as unknown (not blocked), and the
+/// Bun results have no shim entries — all are pass/fail.
+fn bun_blocker(bun_rules: &HashMap<String, BunCompatResult>, dep: &DependencyVerdict) -> Option<Blocker> {
+    let entry = bun_rules.get(&dep.name)?;
+    if entry.status == "pass" {
+        return None;
+    }
 
-                let reason = if let Some(err) = &entry.error {
-                    // Truncate long error messages
-                    let truncated = if err.len() > 120 {
-                        format!("{}…", &err[..120])
-                    } else {
-                        err.clone()
-                    };
-                    truncated
-                } else {
-                    notes.join("; ")
-                };
-
-                blockers.push(Blocker {
-                    dependency: dep.name.clone(),
-                    reason,
-                    fix: "Check compat-db/bun/results.json for details".to_string(),
-                    effort_hours: None,
-                    location: None,
-                });
-            }
-        }
-        // Dependencies not in the DB are treated as unknown (not blocked)
+    // Build a descriptive reason from the result fields
+    let mut notes = Vec::new();
+    if !entry.bundle_ok {
+        notes.push("bundle failed".to_string());
+    }
+    if !entry.componentize_ok {
+        notes.push("componentize failed".to_string());
+    }
+    if let Some(stage) = &entry.error_stage {
+        notes.push(format!("failed at {stage} stage"));
     }
 
-    // Bun compat DB doesn't have shim entries — all are pass/fail
-    (blockers, vec![])
+    let reason = if let Some(err) = &entry.error {
+        // Truncate long error messages
+        if err.len() > 120 {
+            format!("{}…", &err[..120])
+        } else {
+            err.clone()
+        }
+    } else {
+        notes.join("; ")
+    };
+
+    Some(Blocker {
+        dependency: dep.name.clone(),
+        reason,
+        fix: "Check compat-db/bun/results.json for details".to_string(),
+        effort_hours: None,
+        location: None,
+    })
 }
 
 #[cfg(test)]
@@ -231,9 +686,25 @@ mod tests {
             name: name.to_string(),
             version: Some("1.0.0".to_string()),
             verdict: Verdict::Unknown,
+            transitive: false,
+            via: Vec::new(),
         }
     }
 
+    #[test]
+    fn test_transitive_blocker_names_its_chain() {
+        let mut dep = make_dep("openssl-sys");
+        dep.transitive = true;
+        dep.via = vec!["reqwest".to_string(), "native-tls".to_string()];
+        let (blockers, _) = evaluate_dependencies(&[dep], "rust");
+        assert_eq!(blockers.len(), 1);
+        assert!(
+            blockers[0].reason.ends_with("(pulled in via reqwest → native-tls)"),
+            "{}",
+            blockers[0].reason
+        );
+    }
+
     #[test]
     fn test_bun_compat_rules_loads_results_json() {
         let rules = bun_compat_rules();
@@ -288,6 +759,16 @@ mod tests {
         assert_eq!(blockers[0].dependency, "openssl-sys");
     }
 
+    #[test]
+    fn test_python_db_driver_is_shim_and_c_extension_is_blocker() {
+        let deps = vec![make_dep("psycopg2"), make_dep("numpy"), make_dep("flask")];
+        let (blockers, shims) = evaluate_dependencies(&deps, "python");
+        assert_eq!(blockers.len(), 1);
+        assert_eq!(blockers[0].dependency, "numpy");
+        assert_eq!(shims.len(), 1);
+        assert_eq!(shims[0].shim, "database_proxy");
+    }
+
     #[test]
     fn test_bun_results_json_status_mapping() {
         let rules = bun_compat_rules();
@@ -304,4 +785,190 @@ mod tests {
         assert!(!marked.componentize_ok);
         assert!(marked.error.is_some());
     }
+
+    fn write(dir: &Path, file: &str, text: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn test_repo_compat_db_files_are_valid() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../compat-db");
+        let mut db = CompatDb::builtin();
+        assert!(db.layer_dir(&dir).unwrap() > 0);
+        // Shipped TOML takes precedence over the built-in reason.
+        let (blockers, _) = db.evaluate(&[make_dep("openssl-sys")], "rust");
+        assert!(blockers[0].reason.contains("Wasm cannot link"), "{}", blockers[0].reason);
+        assert!(blockers[0].fix.ends_with("(see https://warpgrid.dev/guides/rust-openssl-to-rustls)"));
+    }
+
+    #[test]
+    fn test_later_layers_override_earlier_ones() {
+        let system = tempfile::tempdir().unwrap();
+        let user = tempfile::tempdir().unwrap();
+        write(system.path(), "rust/diesel.toml", r#"
+[[dependency]]
+ecosystem = "rust"
+name = "diesel"
+verdict = "incompatible"
+reason = "Links libpq"
+"#);
+        write(user.path(), "overrides.toml", r#"
+[[dependency]]
+ecosystem = "rust"
+name = "diesel"
+verdict = "shim_compatible"
+reason = "Pure-Rust postgres backend"
+shim = "database_proxy"
+
+[[dependency]]
+ecosystem = "rust"
+name = "tokio"
+verdict = "incompatible"
+reason = "Pinned to a fork"
+
+[[dependency]]
+ecosystem = "bun"
+name = "marked"
+verdict = "compatible"
+"#);
+
+        let mut db = CompatDb::builtin();
+        let builtin = db.fingerprint();
+        assert_eq!(db.layer_dir(system.path()).unwrap(), 1);
+        let (blockers, _) = db.evaluate(&[make_dep("diesel")], "rust");
+        assert_eq!(blockers.len(), 1);
+        let layered = db.fingerprint();
+        assert_ne!(layered, builtin, "layers change the fingerprint");
+        let mut again = CompatDb::builtin();
+        again.layer_dir(system.path()).unwrap();
+        assert_eq!(again.fingerprint(), layered);
+
+        assert_eq!(db.layer_dir(user.path()).unwrap(), 3);
+        let (blockers, shims) = db.evaluate(&[make_dep("diesel"), make_dep("tokio")], "rust");
+        assert_eq!(blockers.len(), 1);
+        assert_eq!(blockers[0].dependency, "tokio");
+        assert_eq!(shims[0].name, "diesel");
+        // Rules are per ecosystem.
+        assert!(db.evaluate(&[make_dep("diesel")], "go").1.is_empty());
+        // A TOML entry wins over results.json for Bun.
+        assert!(db.evaluate(&[make_dep("marked")], "bun").0.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_entries_are_rejected() {
+        let cases = [
+            ("ecosystem = \"cobol\"\nname = \"x\"\nverdict = \"compatible\"", "unknown ecosystem"),
+            ("ecosystem = \"go\"\nname = \"x\"\nverdict = \"maybe\"", "unknown verdict"),
+            ("ecosystem = \"go\"\nname = \"x\"\nverdict = \"shim_compatible\"", "must name a `shim`"),
+            ("ecosystem = \"go\"\nname = \"x\"\nverdict = \"compatible\"\nalternatives = \"y\"", "Invalid compat-db file"),
+            ("ecosystem = \"go\"\nname = \"x\"\nverdict = \"compatible\"\nversions = \"newer\"", "invalid `versions` 'newer'"),
+            ("ecosystem = \"go\"\nname = \"x\"\nverdict = \"incompatible\"\nalternative_version = \"1\"", "needs an `alternative`"),
+        ];
+        for (entry, expected) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            write(dir.path(), "bad.toml", &format!("[[dependency]]\n{entry}\n"));
+            let err = CompatDb::builtin().layer_dir(dir.path()).unwrap_err();
+            assert!(format!("{err:#}").contains(expected), "{err:#}");
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let entry = "[[dependency]]\necosystem = \"go\"\nname = \"x\"\nverdict = \"compatible\"\n";
+        write(dir.path(), "a.toml", entry);
+        write(dir.path(), "b.toml", entry);
+        let err = CompatDb::builtin().layer_dir(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("already defined in"), "{err}");
+    }
+
+    fn versioned(name: &str, version: Option<&str>) -> DependencyVerdict {
+        DependencyVerdict {
+            version: version.map(String::from),
+            ..make_dep(name)
+        }
+    }
+
+    #[test]
+    fn test_parse_version_is_lenient() {
+        assert_eq!(parse_version("v1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse_version("0.16"), Some(Version::new(0, 16, 0)));
+        assert_eq!(parse_version("^1"), Some(Version::new(1, 0, 0)));
+        assert_eq!(parse_version("v0.0.0-20240101000000-abcdef123456"), Some(Version::new(0, 0, 0)));
+        assert_eq!(parse_version("2.0rc1"), None);
+        assert_eq!(parse_version(">=1, <2"), None);
+    }
+
+    #[test]
+    fn test_ring_verdict_follows_the_locked_version() {
+        let (blockers, _) = evaluate_dependencies(&[versioned("ring", Some("0.16.20"))], "rust");
+        assert_eq!(blockers.len(), 1);
+        assert!(blockers[0].reason.ends_with("(versions <0.17)"), "{}", blockers[0].reason);
+
+        let (blockers, shims) = evaluate_dependencies(&[versioned("ring", Some("0.17.8"))], "rust");
+        assert!(blockers.is_empty() && shims.is_empty());
+
+        // Unknown version: the most severe range applies, and says so.
+        let (blockers, _) = evaluate_dependencies(&[versioned("ring", None)], "rust");
+        assert!(blockers[0].reason.ends_with("(versions <0.17; locked version unknown)"), "{}", blockers[0].reason);
+    }
+
+    #[test]
+    fn test_replacements_are_single_package_alternatives() {
+        let db = CompatDb::builtin();
+        assert_eq!(
+            db.replacement(&make_dep("openssl"), "rust"),
+            Some(Replacement { name: "rustls".to_string(), version: "0.23".to_string() })
+        );
+        assert_eq!(
+            db.replacement(&make_dep("sharp"), "typescript"),
+            Some(Replacement { name: "wasm-vips".to_string(), version: "*".to_string() })
+        );
+        // A prose alternative, a compatible dependency, and one without an alternative.
+        assert_eq!(db.replacement(&make_dep("libz-sys"), "rust"), None);
+        assert_eq!(db.replacement(&make_dep("tokio"), "rust"), None);
+        assert_eq!(db.replacement(&make_dep("nix"), "rust"), None);
+    }
+
+    #[test]
+    fn test_ranged_entries_fall_back_to_unranged_one() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "rust/rusqlite.toml", r#"
+[[dependency]]
+ecosystem = "rust"
+name = "rusqlite"
+versions = "<0.30"
+verdict = "incompatible"
+reason = "Bundled SQLite needs a C toolchain"
+
+[[dependency]]
+ecosystem = "rust"
+name = "rusqlite"
+versions = ">=0.30, <0.32"
+verdict = "shim_compatible"
+shim = "filesystem"
+
+[[dependency]]
+ecosystem = "rust"
+name = "rusqlite"
+verdict = "compatible"
+"#);
+        let mut db = CompatDb::builtin();
+        assert_eq!(db.layer_dir(dir.path()).unwrap(), 3);
+        let deps = [
+            versioned("rusqlite", Some("0.29.0")),
+            versioned("rusqlite", Some("0.31.0")),
+            versioned("rusqlite", Some("0.32.1")),
+            versioned("rusqlite", None),
+        ];
+        let (blockers, shims) = db.evaluate(&deps, "rust");
+        assert_eq!(blockers.len(), 1);
+        assert!(blockers[0].reason.starts_with("Bundled SQLite"));
+        assert_eq!(shims.len(), 1);
+        assert_eq!(shims[0].description, "versions >=0.30, <0.32");
+
+        let entry = "[[dependency]]\necosystem = \"go\"\nname = \"x\"\nversions = \"<2\"\nverdict = \"compatible\"\n";
+        write(dir.path(), "go/dup.toml", &format!("{entry}\n{entry}"));
+        let err = CompatDb::builtin().layer_dir(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("dependency 'x' <2 (go) is already defined"), "{err}");
+    }
 }
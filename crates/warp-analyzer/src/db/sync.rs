@@ -0,0 +1,203 @@
+//! Remote compat-db updates.
+//!
+//! `warp convert db update` fetches a compat-db TOML document and its
+//! detached Ed25519 signature (`<url>.sig`, hex), checks the signature
+//! against a trusted public key, validates every entry, and caches the
+//! document in `~/.warp/compat-sync/`. [`CompatDb::load`] layers the cache
+//! over the built-in rules, so verdicts improve between CLI releases.
+//!
+//! Nothing is written unless the signature and every entry check out; a
+//! failed update leaves the previous cache in place.
+//!
+//! [`CompatDb::load`]: super::CompatDb::load
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::CompatDb;
+
+/// Cached rules, layered by [`CompatDb::load`].
+const RULES_FILE: &str = "rules.toml";
+/// The verified signature of [`RULES_FILE`].
+const SIGNATURE_FILE: &str = "rules.toml.sig";
+/// [`SyncState`] of the cache.
+const STATE_FILE: &str = "sync.json";
+
+/// Where a sync came from and what it installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub url: String,
+    /// `sha256:<hex>` of the cached document.
+    pub digest: String,
+    /// Entries in the document.
+    pub entries: usize,
+    /// When the document was installed (Unix seconds).
+    pub fetched_at: u64,
+}
+
+/// Result of [`install`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// The document differed from the cache and replaced it.
+    Updated(SyncState),
+    /// The cache already held this document.
+    Unchanged(SyncState),
+}
+
+/// `~/.warp/compat-sync`, the cache directory.
+pub fn sync_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".warp").join("compat-sync"))
+}
+
+/// Parse a hex-encoded Ed25519 public key.
+pub fn parse_public_key(hex_key: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(hex_key.trim()).context("compat-db public key is not valid hex")?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow::anyhow!("compat-db public key must be 32 bytes, got {}", bytes.len()))
+}
+
+/// Check `signature` (hex, as served in `<url>.sig`) over `document`.
+pub fn verify(document: &[u8], signature: &[u8], public_key: &[u8; 32]) -> Result<()> {
+    let signature = std::str::from_utf8(signature)
+        .ok()
+        .and_then(|text| hex::decode(text.trim()).ok())
+        .context("compat-db signature is not valid hex")?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(document, &signature)
+        .map_err(|_| anyhow::anyhow!("compat-db signature does not match the trusted public key"))
+}
+
+/// Verify and validate a fetched document, then cache it in `dir`.
+pub fn install(
+    dir: &Path,
+    url: &str,
+    document: &[u8],
+    signature: &[u8],
+    public_key: &[u8; 32],
+    now: u64,
+) -> Result<SyncOutcome> {
+    verify(document, signature, public_key)?;
+    let text = std::str::from_utf8(document).context("compat-db document is not UTF-8")?;
+    let entries = CompatDb::builtin()
+        .layer_toml(Path::new(url), text)
+        .context("fetched compat-db document is invalid")?;
+
+    let state = SyncState {
+        url: url.to_string(),
+        digest: format!("sha256:{}", hex::encode(Sha256::digest(document))),
+        entries,
+        fetched_at: now,
+    };
+    if let Some(current) = status(dir)?
+        && current.digest == state.digest
+    {
+        return Ok(SyncOutcome::Unchanged(current));
+    }
+
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    write_atomic(&dir.join(SIGNATURE_FILE), signature)?;
+    write_atomic(&dir.join(RULES_FILE), document)?;
+    write_atomic(&dir.join(STATE_FILE), serde_json::to_string_pretty(&state)?.as_bytes())?;
+    Ok(SyncOutcome::Updated(state))
+}
+
+/// The state of the cache in `dir`, if it holds a synced document.
+pub fn status(dir: &Path) -> Result<Option<SyncState>> {
+    let path = dir.join(STATE_FILE);
+    if !path.is_file() || !dir.join(RULES_FILE).is_file() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let state = serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(Some(state))
+}
+
+/// Write through a temporary file so readers never see a partial file.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const RULES: &str = r#"
+[[dependency]]
+ecosystem = "rust"
+name = "diesel"
+verdict = "incompatible"
+reason = "Links libpq"
+"#;
+
+    fn keypair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn sign(key: &Ed25519KeyPair, document: &str) -> Vec<u8> {
+        hex::encode(key.sign(document.as_bytes())).into_bytes()
+    }
+
+    fn public(key: &Ed25519KeyPair) -> [u8; 32] {
+        parse_public_key(&hex::encode(key.public_key())).unwrap()
+    }
+
+    #[test]
+    fn test_install_caches_a_verified_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = keypair();
+        let url = "http://rules.example/compat.toml";
+
+        let outcome = install(dir.path(), url, RULES.as_bytes(), &sign(&key, RULES), &public(&key), 100).unwrap();
+        let SyncOutcome::Updated(state) = outcome else {
+            panic!("expected an update, got {outcome:?}");
+        };
+        assert_eq!(state.entries, 1);
+        assert_eq!(status(dir.path()).unwrap(), Some(state.clone()));
+
+        let again = install(dir.path(), url, RULES.as_bytes(), &sign(&key, RULES), &public(&key), 200).unwrap();
+        assert_eq!(again, SyncOutcome::Unchanged(state));
+
+        // The cache is an ordinary layer.
+        let mut db = CompatDb::builtin();
+        assert_eq!(db.layer_dir(dir.path()).unwrap(), 1);
+        let dep = warp_core::DependencyVerdict {
+            name: "diesel".to_string(),
+            version: None,
+            verdict: warp_core::Verdict::Unknown,
+            transitive: false,
+            via: Vec::new(),
+        };
+        assert_eq!(db.evaluate(&[dep], "rust").0.len(), 1);
+    }
+
+    #[test]
+    fn test_install_rejects_bad_signatures_and_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = keypair();
+        let url = "http://rules.example/compat.toml";
+
+        let tampered = RULES.replace("incompatible", "compatible");
+        let err = install(dir.path(), url, tampered.as_bytes(), &sign(&key, RULES), &public(&key), 0).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+        let err = install(dir.path(), url, RULES.as_bytes(), &sign(&keypair(), RULES), &public(&key), 0).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+
+        let invalid = RULES.replace("\"rust\"", "\"cobol\"");
+        let err = install(dir.path(), url, invalid.as_bytes(), &sign(&key, &invalid), &public(&key), 0).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown ecosystem"), "{err:#}");
+
+        assert_eq!(status(dir.path()).unwrap(), None, "nothing was cached");
+        assert!(parse_public_key("abcd").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! Verdict changes between two analyses — `warp convert analyze --diff`.
+//!
+//! Every dependency and every source or Dockerfile finding gets a verdict
+//! (`compatible`, `shim:<name>`, or `blocker`). The diff lists the
+//! subjects whose verdict changed, appeared, or went away; reasons and
+//! wording changes alone are not reported.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use warp_core::{AnalysisReport, OverallVerdict};
+
+/// One subject whose verdict changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerdictChange {
+    /// Dependency name, or `name (location)` for a source finding.
+    pub subject: String,
+    /// `None` when the subject is new.
+    pub before: Option<String>,
+    /// `None` when the subject is gone.
+    pub after: Option<String>,
+    /// The current blocker or shim reason, for context.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl VerdictChange {
+    /// Whether the change introduces a blocker.
+    pub fn is_regression(&self) -> bool {
+        self.after.as_deref() == Some("blocker") && self.before.as_deref() != Some("blocker")
+    }
+}
+
+/// The changes from a baseline analysis to the current one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnalysisDiff {
+    /// `None` when there was no baseline; every subject is then new.
+    pub overall_before: Option<OverallVerdict>,
+    pub overall_after: OverallVerdict,
+    pub changes: Vec<VerdictChange>,
+}
+
+impl AnalysisDiff {
+    /// Whether any change introduces a blocker.
+    pub fn has_regressions(&self) -> bool {
+        self.changes.iter().any(VerdictChange::is_regression)
+    }
+}
+
+/// Subject → (verdict, reason) for every dependency and finding.
+fn verdicts(report: &AnalysisReport) -> BTreeMap<String, (String, Option<String>)> {
+    let mut verdicts: BTreeMap<_, _> = report
+        .dependencies
+        .iter()
+        .map(|dep| (dep.name.clone(), ("compatible".to_string(), None)))
+        .collect();
+    for item in &report.shim_items {
+        verdicts.insert(item.name.clone(), (format!("shim:{}", item.shim), Some(item.description.clone())));
+    }
+    for blocker in &report.blockers {
+        let subject = match &blocker.location {
+            Some(location) => format!("{} ({location})", blocker.dependency),
+            None => blocker.dependency.clone(),
+        };
+        verdicts.insert(subject, ("blocker".to_string(), Some(blocker.reason.clone())));
+    }
+    verdicts
+}
+
+/// Compare `after` with `before` (`None`: no previous run).
+pub fn diff(before: Option<&AnalysisReport>, after: &AnalysisReport) -> AnalysisDiff {
+    let old = before.map(verdicts).unwrap_or_default();
+    let new = verdicts(after);
+    let mut changes = Vec::new();
+    for (subject, (verdict, reason)) in &new {
+        let previous = old.get(subject).map(|(verdict, _)| verdict);
+        if previous != Some(verdict) {
+            changes.push(VerdictChange {
+                subject: subject.clone(),
+                before: previous.cloned(),
+                after: Some(verdict.clone()),
+                reason: reason.clone(),
+            });
+        }
+    }
+    for (subject, (verdict, _)) in &old {
+        if !new.contains_key(subject) {
+            changes.push(VerdictChange {
+                subject: subject.clone(),
+                before: Some(verdict.clone()),
+                after: None,
+                reason: None,
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.subject.cmp(&b.subject));
+    AnalysisDiff {
+        overall_before: before.map(|report| report.overall_verdict.clone()),
+        overall_after: after.overall_verdict.clone(),
+        changes,
+    }
+}
+
+/// Human-readable diff.
+pub fn format_diff(diff: &AnalysisDiff) -> String {
+    let mut out = String::new();
+    match &diff.overall_before {
+        None => out.push_str(&format!(
+            "No previous analysis; showing every verdict. Overall: {}\n",
+            diff.overall_after.label()
+        )),
+        Some(before) if *before != diff.overall_after => out.push_str(&format!(
+            "Overall: {} → {}\n",
+            before.label(),
+            diff.overall_after.label()
+        )),
+        Some(_) => out.push_str(&format!("Overall: {} (unchanged)\n", diff.overall_after.label())),
+    }
+    if diff.changes.is_empty() {
+        out.push_str("No verdict changes since the last analysis.\n");
+        return out;
+    }
+    for change in &diff.changes {
+        let marker = if change.is_regression() {
+            "✗"
+        } else if change.after.is_none() {
+            "-"
+        } else {
+            "~"
+        };
+        let before = change.before.as_deref().unwrap_or("new");
+        let after = change.after.as_deref().unwrap_or("removed");
+        out.push_str(&format!("  {marker} {}: {before} → {after}", change.subject));
+        if let Some(reason) = change.reason.as_deref().filter(|r| !r.is_empty()) {
+            out.push_str(&format!(" — {reason}"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp_core::{Blocker, DependencyVerdict, ShimItem, Verdict};
+
+    fn report(deps: &[&str], blockers: &[&str], shims: &[(&str, &str)]) -> AnalysisReport {
+        AnalysisReport {
+            project_name: "api".into(),
+            language: "rust".into(),
+            overall_verdict: if blockers.is_empty() {
+                OverallVerdict::Convertible
+            } else {
+                OverallVerdict::PartiallyConvertible
+            },
+            dependencies: deps
+                .iter()
+                .map(|name| DependencyVerdict {
+                    name: name.to_string(),
+                    version: None,
+                    verdict: Verdict::Unknown,
+                    transitive: false,
+                    via: Vec::new(),
+                })
+                .collect(),
+            blockers: blockers
+                .iter()
+                .map(|name| Blocker {
+                    dependency: name.to_string(),
+                    reason: "FFI".into(),
+                    fix: String::new(),
+                    effort_hours: None,
+                    location: None,
+                })
+                .collect(),
+            shim_items: shims
+                .iter()
+                .map(|(name, shim)| ShimItem {
+                    name: name.to_string(),
+                    shim: shim.to_string(),
+                    description: String::new(),
+                })
+                .collect(),
+            estimated_wasm_size_mb: None,
+            large_assets: Vec::new(),
+            fixes: Vec::new(),
+            suggested_config: None,
+        }
+    }
+
+    #[test]
+    fn test_only_changed_verdicts_are_reported() {
+        let before = report(&["serde", "openssl", "libc"], &["openssl"], &[("libc", "filesystem")]);
+        let mut after = report(&["serde", "rustls", "libc", "nix"], &["nix"], &[("libc", "filesystem")]);
+        after.blockers[0].reason = "reworded".into();
+
+        let diff = diff(Some(&before), &after);
+        let summary: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|c| (c.subject.as_str(), c.before.as_deref(), c.after.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("nix", None, Some("blocker")),
+                ("openssl", Some("blocker"), None),
+                ("rustls", None, Some("compatible")),
+            ]
+        );
+        assert!(diff.has_regressions());
+        assert_eq!(diff.overall_before, Some(OverallVerdict::PartiallyConvertible));
+
+        let same = super::diff(Some(&after), &after);
+        assert!(same.changes.is_empty());
+        assert!(format_diff(&same).contains("No verdict changes"));
+    }
+
+    #[test]
+    fn test_without_baseline_everything_is_new() {
+        let after = report(&["serde"], &[], &[]);
+        let diff = diff(None, &after);
+        assert_eq!(diff.changes.len(), 1);
+        assert!(!diff.has_regressions());
+        assert!(format_diff(&diff).starts_with("No previous analysis"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Auto-fix — swap incompatible dependencies for their alternatives.
+//!
+//! `warp convert fix` rewrites the project manifest (`Cargo.toml` or
+//! `package.json`) so each direct dependency with a single-package
+//! alternative in the compat DB (see [`CompatDb::replacement`]) is replaced
+//! by it: `openssl` becomes `rustls`, `bcrypt` becomes `bcryptjs`. Only
+//! the manifest changes; code using the old API still has to be migrated,
+//! which the blockers that remain in the report point out.
+//!
+//! Edits keep the rest of the file as written. In `Cargo.toml` the new
+//! dependency is added at the end of its table; in `package.json` it takes
+//! the old entry's place. When the alternative is already declared, the
+//! old entry is only removed.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use warp_core::{DependencyFix, DependencyVerdict, Verdict};
+
+use crate::db::CompatDb;
+
+/// Dependency tables of `Cargo.toml` that are fixed.
+const CARGO_SECTIONS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Dependency objects of `package.json` that are fixed.
+const NPM_SECTIONS: &[&str] = &["dependencies", "devDependencies", "optionalDependencies"];
+
+/// Find the fixes for the `language` project at `project_path` and, with
+/// `apply`, write them to its manifest.
+pub fn fix_manifests(project_path: &Path, language: &str, db: &CompatDb, apply: bool) -> Result<Vec<DependencyFix>> {
+    match language {
+        "rust" => fix_cargo_toml(project_path, db, apply),
+        "typescript" | "bun" => fix_package_json(project_path, language, db, apply),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn direct(name: &str, version: Option<&str>) -> DependencyVerdict {
+    DependencyVerdict {
+        name: name.to_string(),
+        version: version.map(String::from),
+        verdict: Verdict::Unknown,
+        transitive: false,
+        via: Vec::new(),
+    }
+}
+
+fn fix_cargo_toml(project_path: &Path, db: &CompatDb, apply: bool) -> Result<Vec<DependencyFix>> {
+    let path = project_path.join("Cargo.toml");
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut doc: toml_edit::DocumentMut =
+        text.parse().with_context(|| format!("Invalid {}", path.display()))?;
+
+    let mut fixes = Vec::new();
+    for section in CARGO_SECTIONS {
+        let Some(table) = doc.get_mut(section).and_then(|item| item.as_table_like_mut()) else {
+            continue;
+        };
+        let planned: Vec<_> = table
+            .iter()
+            .filter_map(|(name, item)| {
+                let version = item
+                    .as_str()
+                    .or_else(|| item.as_table_like()?.get("version")?.as_str());
+                db.replacement(&direct(name, version), "rust").map(|r| (name.to_string(), r))
+            })
+            .collect();
+        for (name, replacement) in planned {
+            table.remove(&name);
+            if !table.contains_key(&replacement.name) {
+                table.insert(&replacement.name, toml_edit::value(replacement.version.clone()));
+            }
+            fixes.push(DependencyFix {
+                manifest: "Cargo.toml".to_string(),
+                section: section.to_string(),
+                removed: name,
+                added: replacement.name,
+                version: replacement.version,
+                applied: apply,
+            });
+        }
+    }
+
+    if apply && !fixes.is_empty() {
+        std::fs::write(&path, doc.to_string()).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(fixes)
+}
+
+fn fix_package_json(project_path: &Path, language: &str, db: &CompatDb, apply: bool) -> Result<Vec<DependencyFix>> {
+    let path = project_path.join("package.json");
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let mut text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let pkg: serde_json::Value = serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+
+    let mut fixes = Vec::new();
+    for section in NPM_SECTIONS {
+        let Some(deps) = pkg.get(section).and_then(|d| d.as_object()) else {
+            continue;
+        };
+        for (name, version) in deps {
+            let Some(replacement) = db.replacement(&direct(name, version.as_str()), language) else {
+                continue;
+            };
+            let already_declared = deps.contains_key(&replacement.name)
+                || fixes.iter().any(|f: &DependencyFix| f.section == *section && f.added == replacement.name);
+            text = rewrite_npm_entry(&text, section, name, (!already_declared).then_some(&replacement))
+                .with_context(|| format!("Failed to edit '{name}' in {}", path.display()))?;
+            fixes.push(DependencyFix {
+                manifest: "package.json".to_string(),
+                section: section.to_string(),
+                removed: name.clone(),
+                added: replacement.name,
+                version: replacement.version,
+                applied: apply,
+            });
+        }
+    }
+
+    if apply && !fixes.is_empty() {
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(fixes)
+}
+
+/// Replace (or, without a replacement, remove) the `"name": "..."` entry
+/// of the `section` object in `text`, leaving everything else untouched.
+fn rewrite_npm_entry(
+    text: &str,
+    section: &str,
+    name: &str,
+    replacement: Option<&crate::db::Replacement>,
+) -> Result<String> {
+    let start = Regex::new(&format!(r#""{}"\s*:\s*\{{"#, regex::escape(section)))?
+        .find(text)
+        .context("dependency section not found")?
+        .end();
+    // Dependency objects only hold strings, so the first `}` closes it.
+    let end = start + text[start..].find('}').context("unterminated dependency section")?;
+    let body = &text[start..end];
+
+    let entry = Regex::new(&format!(r#""{}"\s*:\s*"[^"]*""#, regex::escape(name)))?
+        .find(body)
+        .context("dependency entry not found")?;
+    let (from, to) = match replacement {
+        Some(_) => (entry.start(), entry.end()),
+        // Take the separating comma with it: the one after the entry, or
+        // the one before when it is the last entry.
+        None => match Regex::new(r"^\s*,\s*")?.find(&body[entry.end()..]) {
+            Some(comma) => (entry.start(), entry.end() + comma.end()),
+            None => (body[..entry.start()].trim_end().trim_end_matches(',').len(), entry.end()),
+        },
+    };
+    let new_entry = replacement
+        .map(|r| format!(r#""{}": "{}""#, r.name, r.version))
+        .unwrap_or_default();
+    Ok(format!("{}{}{}", &text[..start + from], new_entry, &text[start + to..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_toml_swaps_openssl_for_rustls() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\n# TLS\nopenssl = { version = \"0.10\", features = [\"vendored\"] }\nserde = \"1\" # keep\n\n[dev-dependencies]\nnix = \"0.29\"\n";
+        std::fs::write(tmp.path().join("Cargo.toml"), manifest).unwrap();
+
+        let fixes = fix_manifests(tmp.path(), "rust", &CompatDb::builtin(), false).unwrap();
+        assert_eq!(fixes.len(), 1);
+        assert_eq!((fixes[0].removed.as_str(), fixes[0].added.as_str()), ("openssl", "rustls"));
+        assert!(!fixes[0].applied);
+        // A dry run leaves the manifest alone.
+        assert_eq!(std::fs::read_to_string(tmp.path().join("Cargo.toml")).unwrap(), manifest);
+
+        let fixes = fix_manifests(tmp.path(), "rust", &CompatDb::builtin(), true).unwrap();
+        assert!(fixes[0].applied);
+        let fixed = std::fs::read_to_string(tmp.path().join("Cargo.toml")).unwrap();
+        assert!(fixed.contains("serde = \"1\" # keep\nrustls = \"0.23\"\n"), "{fixed}");
+        assert!(!fixed.contains("openssl"), "{fixed}");
+        assert!(fixed.contains("[dev-dependencies]\nnix = \"0.29\""), "{fixed}");
+    }
+
+    #[test]
+    fn test_package_json_keeps_formatting() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"bcrypt\": \"^5.1.0\",\n    \"hono\": \"^4.0.0\"\n  },\n  \"devDependencies\": {\n    \"bcryptjs\": \"^2.4.3\",\n    \"better-sqlite3\": \"^9.0.0\"\n  }\n}\n";
+        std::fs::write(tmp.path().join("package.json"), manifest).unwrap();
+
+        let fixes = fix_manifests(tmp.path(), "typescript", &CompatDb::builtin(), true).unwrap();
+        let changed: Vec<_> = fixes.iter().map(|f| (f.section.as_str(), f.removed.as_str(), f.added.as_str())).collect();
+        assert_eq!(
+            changed,
+            [("dependencies", "bcrypt", "bcryptjs"), ("devDependencies", "better-sqlite3", "sql.js")]
+        );
+        let fixed = std::fs::read_to_string(tmp.path().join("package.json")).unwrap();
+        assert_eq!(
+            fixed,
+            "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"bcryptjs\": \"^3.0.0\",\n    \"hono\": \"^4.0.0\"\n  },\n  \"devDependencies\": {\n    \"bcryptjs\": \"^2.4.3\",\n    \"sql.js\": \"^1.12.0\"\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_package_json_drops_entry_when_alternative_is_declared() {
+        let text = "{\"dependencies\": {\"bcryptjs\": \"^2\", \"bcrypt\": \"^5\"}}";
+        let fixed = rewrite_npm_entry(text, "dependencies", "bcrypt", None).unwrap();
+        assert_eq!(fixed, "{\"dependencies\": {\"bcryptjs\": \"^2\"}}");
+
+        let text = "{\"dependencies\": {\"bcrypt\": \"^5\", \"bcryptjs\": \"^2\"}}";
+        let fixed = rewrite_npm_entry(text, "dependencies", "bcrypt", None).unwrap();
+        assert_eq!(fixed, "{\"dependencies\": {\"bcryptjs\": \"^2\"}}");
+    }
+}
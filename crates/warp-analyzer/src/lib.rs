@@ -1,6 +1,10 @@
 pub mod analyzers;
+pub mod cache;
 pub mod db;
+pub mod diff;
+pub mod fix;
 pub mod report;
+pub mod sarif;
 
 use anyhow::Result;
 use std::path::Path;
@@ -10,6 +14,26 @@ use warp_core::{AnalysisReport, OverallVerdict};
 ///
 /// If `lang_override` is provided, it is used instead of auto-detection.
 pub fn analyze(path: &Path, lang_override: Option<&str>) -> Result<AnalysisReport> {
+    run_analysis(path, lang_override, false)
+}
+
+/// [`analyze`], reusing the cached dependency analysis while the project's
+/// manifests, lockfiles, and the compat-db are unchanged, and recording
+/// the report as the baseline for [`diff`]. See [`cache`].
+pub fn analyze_cached(path: &Path, lang_override: Option<&str>) -> Result<AnalysisReport> {
+    run_analysis(path, lang_override, true)
+}
+
+/// The project directory of `path`, which may name a Dockerfile.
+pub fn project_dir(path: &Path) -> &Path {
+    if path.is_file() {
+        path.parent().unwrap_or(Path::new("."))
+    } else {
+        path
+    }
+}
+
+fn run_analysis(path: &Path, lang_override: Option<&str>, use_cache: bool) -> Result<AnalysisReport> {
     let language = match lang_override {
         Some(lang) => {
             tracing::info!(language = %lang, "Using language override");
@@ -20,48 +44,77 @@ pub fn analyze(path: &Path, lang_override: Option<&str>) -> Result<AnalysisRepor
 
     tracing::info!(language = %language, "Detected project language");
 
-    let deps = match language.as_str() {
-        "rust" => analyzers::rust::analyze_cargo_toml(path)?,
-        "go" => analyzers::go::analyze_go_mod(path)?,
-        "typescript" => analyzers::typescript::analyze_package_json(path)?,
-        "bun" => analyzers::bun::analyze_package_json(path)?,
-        _ => {
-            tracing::warn!("Unsupported language: {language}");
-            vec![]
+    let db = db::CompatDb::load()?;
+    let project = project_dir(path);
+    let cache_key = if use_cache {
+        cache::key(project, &language, &db.fingerprint())
+            .inspect_err(|e| tracing::warn!("Analysis cache unavailable: {e:#}"))
+            .ok()
+    } else {
+        None
+    };
+    let cached = cache_key.as_deref().and_then(|key| {
+        cache::restore(project, key)
+            .inspect_err(|e| tracing::warn!("Failed to read the analysis cache: {e:#}"))
+            .ok()
+            .flatten()
+    });
+    let cache::DependencyAnalysis { dependencies: deps, mut blockers, mut shim_items } = match cached {
+        Some(analysis) => analysis,
+        None => {
+            let analysis = analyze_dependencies(path, &language, &db)?;
+            if let Some(key) = &cache_key
+                && let Err(e) = cache::store(project, key, &analysis)
+            {
+                tracing::warn!("Failed to update the analysis cache: {e:#}");
+            }
+            analysis
         }
     };
+    let size = analyzers::size::estimate(path, &language, &deps, &db)?;
+    let total = deps.len();
+    let compatible = total - blockers.len() - shim_items.len();
+    // Native code in the project itself; these carry a `location`.
+    let mut findings = analyzers::source::scan_sources(path, &language)?;
+    let dockerfile = if path.is_file() { path.to_path_buf() } else { path.join("Dockerfile") };
+    let mut health_endpoint = None;
+    if dockerfile.is_file() {
+        let docker = analyzers::dockerfile::analyze_dockerfile(&dockerfile)?;
+        findings.extend(docker.blockers);
+        shim_items.extend(docker.shim_items);
+        health_endpoint = docker.health_endpoint;
+    }
 
-    let (blockers, shim_items) = db::evaluate_dependencies(&deps, &language);
-
-    let blocking_count = blockers.len();
     let shim_count = shim_items.len();
-    let total = deps.len();
-    let compatible = total - blocking_count - shim_count;
+    // Each source or Dockerfile finding weighs like one blocked dependency.
+    let weight = total + findings.len();
+    blockers.extend(findings);
+    let blocking_count = blockers.len();
 
     let overall_verdict = if blocking_count == 0 && shim_count == 0 {
         OverallVerdict::Convertible
     } else if blocking_count == 0 {
         OverallVerdict::ConvertibleWithShims
-    } else if compatible as f64 / total as f64 > 0.5 {
+    } else if compatible as f64 / weight as f64 > 0.5 {
         OverallVerdict::PartiallyConvertible
     } else {
         OverallVerdict::NotConvertible
     };
 
-    let config = warp_core::WarpConfig::scaffold(
+    let mut config = warp_core::WarpConfig::scaffold_with_shims(
         path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("my-app"),
         &language,
-        match language.as_str() {
-            "rust" => "src/main.rs",
-            "go" => "main.go",
-            "typescript" | "bun" => "src/index.ts",
-            _ => "src/main",
-        },
+        analyzers::default_entry(&language),
+        &shim_items,
     );
+    if let (Some(endpoint), Some(health)) = (health_endpoint, config.health.as_mut()) {
+        health.endpoint = Some(endpoint);
+    }
 
-    Ok(AnalysisReport {
+    let suggested_config = config.to_scaffold_string(&shim_items).ok();
+    let report = AnalysisReport {
         project_name: path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown")
@@ -71,7 +124,48 @@ pub fn analyze(path: &Path, lang_override: Option<&str>) -> Result<AnalysisRepor
         dependencies: deps,
         blockers,
         shim_items,
-        estimated_wasm_size_mb: None,
-        suggested_config: config.to_toml_string().ok(),
-    })
+        estimated_wasm_size_mb: Some(size.total_mb()),
+        large_assets: size.large_assets,
+        fixes: Vec::new(),
+        suggested_config,
+    };
+    if use_cache && let Err(e) = cache::store_last_report(project, &report) {
+        tracing::warn!("Failed to record the analysis baseline: {e:#}");
+    }
+    Ok(report)
+}
+
+/// Read the manifest and lockfile and evaluate every dependency.
+fn analyze_dependencies(path: &Path, language: &str, db: &db::CompatDb) -> Result<cache::DependencyAnalysis> {
+    let mut deps = match language {
+        "rust" => analyzers::rust::analyze_cargo_toml(path)?,
+        "go" => analyzers::go::analyze_go_mod(path)?,
+        "typescript" => analyzers::typescript::analyze_package_json(path)?,
+        "bun" => analyzers::bun::analyze_package_json(path)?,
+        "python" => analyzers::python::analyze_python_deps(path)?,
+        _ => {
+            tracing::warn!("Unsupported language: {language}");
+            vec![]
+        }
+    };
+    let transitive = analyzers::lockfile::transitive_dependencies(path, language, &mut deps)?;
+    deps.extend(transitive);
+    let (blockers, shim_items) = db.evaluate(&deps, language);
+    Ok(cache::DependencyAnalysis { dependencies: deps, blockers, shim_items })
+}
+
+/// Swap incompatible dependencies in the project manifest for their
+/// compat-db alternatives, then analyze the result.
+///
+/// With `apply` unset nothing is written: the report is of the project as
+/// it is, and its `fixes` are the edits a run with `apply` would make.
+pub fn fix(path: &Path, lang_override: Option<&str>, apply: bool) -> Result<AnalysisReport> {
+    let language = match lang_override {
+        Some(lang) => lang.to_string(),
+        None => analyzers::detect_language(path)?,
+    };
+    let fixes = fix::fix_manifests(path, &language, &db::CompatDb::load()?, apply)?;
+    let mut report = analyze(path, Some(&language))?;
+    report.fixes = fixes;
+    Ok(report)
 }
@@ -14,14 +14,51 @@ pub fn format_report(report: &AnalysisReport) -> String {
     out.push_str(&format!("╚══════════════════════════════════════════╝\n\n"));
 
     let total = report.dependencies.len();
-    let blocking = report.blockers.len();
-    let shim = report.shim_items.len();
+    // Source and Dockerfile findings are located; dependency blockers are not.
+    let located = |dockerfile: bool| {
+        report.blockers.iter().filter(move |b| {
+            b.location.as_deref().is_some_and(|l| l.starts_with("Dockerfile") == dockerfile)
+        })
+    };
+    let findings = located(false).count();
+    let docker_findings = located(true).count();
+    let blocking = report.blockers.len() - findings - docker_findings;
+    // Shim items that name no dependency are system packages from the Dockerfile.
+    let docker_shims = report
+        .shim_items
+        .iter()
+        .filter(|s| !report.dependencies.iter().any(|d| d.name == s.name))
+        .count();
+    let shim = report.shim_items.len() - docker_shims;
     let compat = total.saturating_sub(blocking + shim);
 
-    out.push_str(&format!("Dependencies ({total} total):\n"));
+    let transitive = report.dependencies.iter().filter(|d| d.transitive).count();
+    if transitive > 0 {
+        out.push_str(&format!("Dependencies ({total} total, {transitive} transitive):\n"));
+    } else {
+        out.push_str(&format!("Dependencies ({total} total):\n"));
+    }
     out.push_str(&format!("  ✅ {compat} fully compatible\n"));
     out.push_str(&format!("  ⚠️  {shim} compatible via shim layer\n"));
-    out.push_str(&format!("  ❌ {blocking} require changes\n\n"));
+    out.push_str(&format!("  ❌ {blocking} require changes\n"));
+    if findings > 0 {
+        out.push_str(&format!("  ❌ {findings} native code findings in the project sources\n"));
+    }
+    if docker_findings + docker_shims > 0 {
+        out.push_str(&format!(
+            "  🐳 Dockerfile: {docker_findings} findings, {docker_shims} system packages via shims\n"
+        ));
+    }
+    out.push('\n');
+
+    if let Some(size) = report.estimated_wasm_size_mb {
+        out.push_str(&format!("Estimated component size: ~{size:.1} MB\n"));
+        for asset in &report.large_assets {
+            let mb = asset.size_bytes as f64 / (1024.0 * 1024.0);
+            out.push_str(&format!("  📦 {} ({mb:.1} MB, large asset)\n", asset.path));
+        }
+        out.push('\n');
+    }
 
     // Bun-specific: show a compatibility table for each dependency
     if report.language == "bun" && !report.dependencies.is_empty() {
@@ -32,6 +69,9 @@ pub fn format_report(report: &AnalysisReport) -> String {
         out.push_str("❌ BLOCKERS:\n\n");
         for (i, b) in report.blockers.iter().enumerate() {
             out.push_str(&format!("  {}. {}\n", i + 1, b.dependency));
+            if let Some(location) = &b.location {
+                out.push_str(&format!("     At:     {location}\n"));
+            }
             out.push_str(&format!("     Reason: {}\n", b.reason));
             out.push_str(&format!("     Fix:    {}\n", b.fix));
             if let Some(h) = b.effort_hours {
@@ -49,6 +89,18 @@ pub fn format_report(report: &AnalysisReport) -> String {
         out.push('\n');
     }
 
+    if !report.fixes.is_empty() {
+        let applied = report.fixes.iter().all(|f| f.applied);
+        out.push_str(if applied { "🔧 FIXES APPLIED:\n\n" } else { "🔧 FIXES (dry run, nothing written):\n\n" });
+        for f in &report.fixes {
+            out.push_str(&format!(
+                "  • {} [{}]: {} → {} {}\n",
+                f.manifest, f.section, f.removed, f.added, f.version
+            ));
+        }
+        out.push('\n');
+    }
+
     if let Some(config) = &report.suggested_config {
         out.push_str("SUGGESTED warp.toml:\n\n");
         for line in config.lines() {
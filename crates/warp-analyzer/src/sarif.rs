@@ -0,0 +1,258 @@
+//! SARIF 2.1.0 output, for GitHub code scanning and other CI dashboards.
+//!
+//! Every compat-db entry that produced a finding becomes a rule,
+//! `warp/<language>/<dependency>`; source findings use
+//! `warp/source/<construct>` and Dockerfile findings
+//! `warp/dockerfile/<construct>`. Blockers are `error` results, shim items
+//! `note` results. Dependency results point at the line of the manifest
+//! (or, for transitive dependencies, the lockfile) that names them.
+
+use serde_json::{Value, json};
+use std::path::Path;
+use warp_core::{AnalysisReport, Blocker, DependencyVerdict};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Manifests and lockfiles per language, in the order they are tried.
+fn manifests(language: &str, transitive: bool) -> &'static [&'static str] {
+    match (language, transitive) {
+        ("rust", false) => &["Cargo.toml"],
+        ("rust", true) => &["Cargo.lock"],
+        ("go", false) => &["go.mod"],
+        ("go", true) => &["go.mod", "go.sum"],
+        ("typescript" | "bun", false) => &["package.json"],
+        ("typescript" | "bun", true) => &["package-lock.json", "yarn.lock", "bun.lock"],
+        ("python", _) => &["pyproject.toml", "requirements.txt"],
+        // System packages a Dockerfile installs.
+        ("dockerfile", _) => &["Dockerfile"],
+        _ => &[],
+    }
+}
+
+/// `(file, line)` of the first manifest line naming `dep`; line 1 of the
+/// first existing manifest when none does.
+fn dependency_location(
+    project_path: &Path,
+    language: &str,
+    dep: Option<&DependencyVerdict>,
+    name: &str,
+) -> Option<(String, usize)> {
+    let transitive = dep.is_some_and(|d| d.transitive);
+    let mut fallback = None;
+    for file in manifests(language, transitive) {
+        let Ok(text) = std::fs::read_to_string(project_path.join(file)) else {
+            continue;
+        };
+        let hit = text.lines().position(|line| {
+            line.match_indices(name).any(|(i, _)| {
+                let before = line[..i].chars().next_back();
+                let after = line[i + name.len()..].chars().next();
+                let boundary =
+                    |c: Option<char>| c.is_none_or(|c| !(c.is_alphanumeric() || c == '-' || c == '_'));
+                boundary(before) && boundary(after)
+            })
+        });
+        match hit {
+            Some(index) => return Some((file.to_string(), index + 1)),
+            None => {
+                fallback.get_or_insert((file.to_string(), 1));
+            }
+        }
+    }
+    fallback
+}
+
+/// `path:line` of a source finding.
+fn source_location(location: &str) -> Option<(String, usize)> {
+    let (file, line) = location.rsplit_once(':')?;
+    Some((file.to_string(), line.parse().ok()?))
+}
+
+fn slug(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_end_matches('-').to_string()
+}
+
+fn physical_location((uri, line): (String, usize)) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" },
+            "region": { "startLine": line },
+        }
+    })
+}
+
+#[derive(Default)]
+struct Rules {
+    ids: Vec<String>,
+    rules: Vec<Value>,
+}
+
+impl Rules {
+    /// Index of rule `id`, adding it on first use.
+    fn index(&mut self, id: String, description: &str, help: &str, level: &str) -> usize {
+        if let Some(index) = self.ids.iter().position(|known| *known == id) {
+            return index;
+        }
+        self.rules.push(json!({
+            "id": id,
+            "shortDescription": { "text": description },
+            "help": { "text": help },
+            "defaultConfiguration": { "level": level },
+        }));
+        self.ids.push(id);
+        self.ids.len() - 1
+    }
+}
+
+fn blocker_result(rules: &mut Rules, report: &AnalysisReport, project_path: &Path, b: &Blocker) -> Value {
+    let (rule_id, location) = match &b.location {
+        Some(location) => {
+            let kind = if location.starts_with("Dockerfile") { "dockerfile" } else { "source" };
+            (format!("warp/{kind}/{}", slug(&b.dependency)), source_location(location))
+        }
+        None => {
+            let dep = report.dependencies.iter().find(|d| d.name == b.dependency);
+            (
+                format!("warp/{}/{}", report.language, b.dependency),
+                dependency_location(project_path, &report.language, dep, &b.dependency),
+            )
+        }
+    };
+    let index = rules.index(rule_id.clone(), &b.reason, &b.fix, "error");
+    json!({
+        "ruleId": rule_id,
+        "ruleIndex": index,
+        "level": "error",
+        "message": { "text": format!("{}: {}. Fix: {}", b.dependency, b.reason, b.fix) },
+        "locations": location.map(physical_location).into_iter().collect::<Vec<_>>(),
+    })
+}
+
+/// Render `report` (of the project at `project_path`) as a SARIF log.
+pub fn to_sarif(report: &AnalysisReport, project_path: &Path) -> Value {
+    let mut rules = Rules::default();
+    let mut results = Vec::new();
+
+    for b in &report.blockers {
+        results.push(blocker_result(&mut rules, report, project_path, b));
+    }
+    for s in &report.shim_items {
+        let dep = report.dependencies.iter().find(|d| d.name == s.name);
+        // Shim items that name no dependency come from the Dockerfile.
+        let kind = if dep.is_some() { report.language.as_str() } else { "dockerfile" };
+        let rule_id = format!("warp/{kind}/{}", s.name);
+        let help = format!("Enable the {} shim", s.shim);
+        let index = rules.index(rule_id.clone(), &s.description, &help, "note");
+        let location = dependency_location(project_path, kind, dep, &s.name);
+        results.push(json!({
+            "ruleId": rule_id,
+            "ruleIndex": index,
+            "level": "note",
+            "message": { "text": format!("{} runs on the {} shim: {}", s.name, s.shim, s.description) },
+            "locations": location.map(physical_location).into_iter().collect::<Vec<_>>(),
+        }));
+    }
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "warp convert analyze",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.rules,
+                }
+            },
+            "results": results,
+            "properties": {
+                "project": report.project_name,
+                "language": report.language,
+                "verdict": report.overall_verdict.label(),
+            },
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use warp_core::{OverallVerdict, ShimItem, Verdict};
+
+    #[test]
+    fn test_sarif_rules_and_locations() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[dependencies]\nopenssl-sys = \"0.9\"\nopenssl = \"0.10\"\nsqlx = \"0.8\"\n",
+        )
+        .unwrap();
+        let blocker = |dependency: &str, location: Option<&str>| Blocker {
+            dependency: dependency.to_string(),
+            reason: "FFI to native code".to_string(),
+            fix: "Replace with: rustls".to_string(),
+            effort_hours: Some(2.0),
+            location: location.map(String::from),
+        };
+        let report = AnalysisReport {
+            project_name: "app".to_string(),
+            language: "rust".to_string(),
+            overall_verdict: OverallVerdict::PartiallyConvertible,
+            dependencies: ["openssl", "openssl-sys", "sqlx"]
+                .iter()
+                .map(|name| DependencyVerdict {
+                    name: name.to_string(),
+                    version: None,
+                    verdict: Verdict::Unknown,
+                    transitive: false,
+                    via: Vec::new(),
+                })
+                .collect(),
+            blockers: vec![
+                blocker("openssl", None),
+                blocker("extern \"C\"", Some("src/ffi.rs:12")),
+                blocker("extern \"C\"", Some("src/main.rs:3")),
+            ],
+            shim_items: vec![ShimItem {
+                name: "sqlx".to_string(),
+                shim: "database_proxy".to_string(),
+                description: "Postgres over the proxy".to_string(),
+            }],
+            estimated_wasm_size_mb: None,
+            large_assets: Vec::new(),
+            fixes: Vec::new(),
+            suggested_config: None,
+        };
+
+        let sarif = to_sarif(&report, tmp.path());
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        let rule_ids: Vec<_> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rule_ids, ["warp/rust/openssl", "warp/source/extern-c", "warp/rust/sqlx"]);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        // `openssl` is on line 6, not inside `openssl-sys` on line 5.
+        let region = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(region["artifactLocation"]["uri"], "Cargo.toml");
+        assert_eq!(region["region"]["startLine"], 6);
+        assert_eq!(results[2]["ruleIndex"], 1);
+        assert_eq!(results[2]["locations"][0]["physicalLocation"]["region"]["startLine"], 3);
+        assert_eq!(results[3]["level"], "note");
+        assert_eq!(results[3]["locations"][0]["physicalLocation"]["region"]["startLine"], 7);
+    }
+}
@@ -125,6 +125,8 @@ fn test_bun_json_output() {
     let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
     assert_eq!(parsed["language"], "bun");
     assert!(parsed["dependencies"].as_array().unwrap().len() > 0);
+    // The JS engine alone is several megabytes.
+    assert!(parsed["estimated_wasm_size_mb"].as_f64().unwrap() > 8.0);
 }
 
 /// Test --lang bun override on a project without bunfig.toml.
@@ -0,0 +1,687 @@
+//! Minimal client for the warpd management API (`/api/v1`).
+//!
+//! Plain HTTP/1.1 over a `TcpStream`, like `warp pack --notify`: the CLI
+//! makes a handful of small requests, so it does without an async stack.
+//! The endpoint and token come from flags, then `WARP_API_URL` /
+//! `WARP_API_TOKEN`, then `~/.warp/config.toml` (`api_url`, `token`, in
+//! the current context if one is set; see `warp context`). Other CLI
+//! settings resolve the same way through [`setting`].
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use serde::de::DeserializeOwned;
+
+/// Endpoint used when nothing else is configured (warpd's default API port).
+pub const DEFAULT_API_URL: &str = "http://127.0.0.1:8443";
+
+/// `Upgrade` protocol of a port-forward tunnel (see warpgrid-api
+/// `portforward`).
+const TUNNEL_PROTOCOL: &str = "warp-tunnel";
+
+/// Timeout for connecting and for each read of a non-streaming request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A warpd API endpoint.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    /// `host:port`.
+    authority: String,
+    /// Path prefix from the URL, without a trailing slash.
+    base_path: String,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    /// Client for `url` (`http://host[:port][/prefix]`).
+    pub fn new(url: &str, token: Option<String>) -> anyhow::Result<Self> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            if url.starts_with("https://") {
+                bail!("API URL '{url}': https is not supported yet; use an http:// endpoint");
+            }
+            bail!("API URL must start with http:// (got '{url}')");
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            bail!("API URL '{url}' has no host");
+        }
+        let authority = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{authority}:80")
+        };
+        Ok(Self {
+            authority,
+            base_path: path.trim_end_matches('/').to_string(),
+            token,
+        })
+    }
+
+    /// Client from `--api-url`/`--token` flags, falling back to the
+    /// environment, then `~/.warp/config.toml`, then [`DEFAULT_API_URL`].
+    pub fn from_env(url: Option<&str>, token: Option<&str>) -> anyhow::Result<Self> {
+        let url = setting(url, "WARP_API_URL")?.unwrap_or_else(|| DEFAULT_API_URL.to_string());
+        Self::new(&url, setting(token, "WARP_API_TOKEN")?)
+    }
+
+    /// The endpoint, for display.
+    pub fn url(&self) -> String {
+        format!("http://{}{}", self.authority, self.base_path)
+    }
+
+    /// `GET /api/v1<path>`, returning the response's `data`.
+    pub fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let (status, body) = self.request("GET", path, None)?;
+        unwrap_data(status, &body)
+    }
+
+    /// Like [`get`](Self::get), with `None` for a 404.
+    pub fn get_optional<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<Option<T>> {
+        let (status, body) = self.request("GET", path, None)?;
+        if status == 404 {
+            return Ok(None);
+        }
+        unwrap_data(status, &body).map(Some)
+    }
+
+    /// `POST /api/v1<path>` with a JSON body, returning the response's `data`.
+    pub fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> anyhow::Result<T> {
+        let (status, body) = self.request("POST", path, Some(&body.to_string()))?;
+        unwrap_data(status, &body)
+    }
+
+    /// `PUT /api/v1<path>` with a JSON body, returning the response's `data`.
+    pub fn put<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> anyhow::Result<T> {
+        let (status, body) = self.request("PUT", path, Some(&body.to_string()))?;
+        unwrap_data(status, &body)
+    }
+
+    /// `PATCH /api/v1<path>` with a JSON body, returning the response's `data`.
+    pub fn patch<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> anyhow::Result<T> {
+        let (status, body) = self.request("PATCH", path, Some(&body.to_string()))?;
+        unwrap_data(status, &body)
+    }
+
+    /// `DELETE /api/v1<path>`, returning the response's `data`.
+    pub fn delete<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let (status, body) = self.request("DELETE", path, None)?;
+        unwrap_data(status, &body)
+    }
+
+    /// `GET` the URL the client was created with, outside `/api/v1`.
+    /// Returns the raw body of a 200 response.
+    fn download(&self) -> anyhow::Result<Vec<u8>> {
+        let mut stream = self.connect()?;
+        let target = if self.base_path.is_empty() { "/" } else { &self.base_path };
+        self.write_request(&mut stream, "GET", target, None, "*/*")?;
+        let mut reader = BufReader::new(stream);
+        let head = read_head(&mut reader)?;
+        if head.status != 200 {
+            bail!("{} answered {}", self.url(), head.status);
+        }
+        read_body(reader, &head)
+    }
+
+    /// Open the server-sent event stream at `GET /api/v1<path>`.
+    pub fn events(&self, path: &str) -> anyhow::Result<EventStream> {
+        let mut stream = self.connect()?;
+        // The stream stays open indefinitely; only the connect is bounded.
+        stream.set_read_timeout(None)?;
+        self.write_request(&mut stream, "GET", path, None, "text/event-stream")?;
+        let mut reader = BufReader::new(stream);
+        let head = read_head(&mut reader)?;
+        if head.status != 200 {
+            let body = read_body(reader, &head)?;
+            unwrap_data::<serde_json::Value>(head.status, &body)?;
+            bail!("{} answered {} to the event stream", self.url(), head.status);
+        }
+        let body: Box<dyn BufRead + Send> = if head.chunked {
+            Box::new(BufReader::new(Chunked::new(reader)))
+        } else {
+            Box::new(reader)
+        };
+        Ok(EventStream { body })
+    }
+
+    /// Open a tunnel with `GET /api/v1<path>` upgraded to `warp-tunnel`.
+    /// Bytes written to the returned stream reach the other end as they
+    /// are, with no read timeout.
+    pub fn tunnel(&self, path: &str) -> anyhow::Result<TcpStream> {
+        let mut stream = self.connect()?;
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: {TUNNEL_PROTOCOL}\r\n",
+            self.api_path(path),
+            self.authority
+        );
+        if let Some(token) = &self.token {
+            request.push_str(&format!("Authorization: Bearer {token}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes())?;
+        let mut reader = BufReader::new(stream);
+        let head = read_head(&mut reader)?;
+        if head.status != 101 {
+            let body = read_body(reader, &head)?;
+            unwrap_data::<serde_json::Value>(head.status, &body)?;
+            bail!("{} answered {} instead of opening a tunnel", self.url(), head.status);
+        }
+        // The server speaks only once the client has, so nothing past the
+        // head can be buffered yet.
+        if !reader.buffer().is_empty() {
+            bail!("{} sent data before the tunnel was used", self.url());
+        }
+        let stream = reader.into_inner();
+        stream.set_read_timeout(None)?;
+        Ok(stream)
+    }
+
+    fn connect(&self) -> anyhow::Result<TcpStream> {
+        let addr = std::net::ToSocketAddrs::to_socket_addrs(&self.authority)
+            .with_context(|| format!("Cannot resolve {}", self.authority))?
+            .next()
+            .with_context(|| format!("Cannot resolve {}", self.authority))?;
+        let stream = TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT)
+            .with_context(|| format!("Cannot reach the WarpGrid API at {}", self.url()))?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        Ok(stream)
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<&str>) -> anyhow::Result<(u16, Vec<u8>)> {
+        let mut stream = self.connect()?;
+        self.write_request(&mut stream, method, &self.api_path(path), body, "application/json")?;
+        let mut reader = BufReader::new(stream);
+        let head = read_head(&mut reader)?;
+        let body = read_body(reader, &head)?;
+        Ok((head.status, body))
+    }
+
+    /// The request target for `/api/v1<path>`.
+    fn api_path(&self, path: &str) -> String {
+        format!("{}/api/v1{path}", self.base_path)
+    }
+
+    fn write_request(
+        &self,
+        stream: &mut TcpStream,
+        method: &str,
+        target: &str,
+        body: Option<&str>,
+        accept: &str,
+    ) -> anyhow::Result<()> {
+        let mut request = format!(
+            "{method} {target} HTTP/1.1\r\nHost: {}\r\nAccept: {accept}\r\nConnection: close\r\n",
+            self.authority
+        );
+        if let Some(token) = &self.token {
+            request.push_str(&format!("Authorization: Bearer {token}\r\n"));
+        }
+        if let Some(body) = body {
+            request.push_str(&format!(
+                "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ));
+        } else {
+            request.push_str("\r\n");
+        }
+        stream.write_all(request.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Fetch `url` (`http://...`) with a plain `GET`.
+pub fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    ApiClient::new(url, None)?.download()
+}
+
+/// A setting from its flag, then the environment variable `var`, then the
+/// matching key in `~/.warp/config.toml` (see [`credentials`]).
+pub fn setting(flag: Option<&str>, var: &str) -> anyhow::Result<Option<String>> {
+    if let Some(value) = flag {
+        return Ok(Some(value.to_string()));
+    }
+    if let Some(value) = std::env::var(var).ok().filter(|v| !v.is_empty()) {
+        return Ok(Some(value));
+    }
+    let config = match config_path().filter(|path| path.is_file()) {
+        Some(path) => credentials(&path)?,
+        None => Vec::new(),
+    };
+    Ok(config.into_iter().find(|(v, _)| *v == var).map(|(_, value)| value))
+}
+
+/// The deployment id for a `name` or `namespace/name` reference.
+pub fn deployment_id(reference: &str, namespace: &str) -> String {
+    if reference.contains('/') {
+        reference.to_string()
+    } else {
+        format!("{namespace}/{reference}")
+    }
+}
+
+/// Percent-encode a deployment id (`prod/api`) for use as one path segment.
+pub fn path_segment(id: &str) -> String {
+    let mut out = String::with_capacity(id.len());
+    for byte in id.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// `~/.warp/config.toml`.
+pub fn config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".warp").join("config.toml"))
+}
+
+/// Config keys of `~/.warp/config.toml` and the variables they stand in for.
+const CONFIG_KEYS: &[(&str, &str)] = &[
+    ("api_url", "WARP_API_URL"),
+    ("token", "WARP_API_TOKEN"),
+    ("namespace", "WARP_NAMESPACE"),
+    ("compat_db_url", "WARP_COMPAT_DB_URL"),
+    ("compat_db_key", "WARP_COMPAT_DB_KEY"),
+];
+
+/// `WARP_API_URL`, `WARP_API_TOKEN`, and the other settings in
+/// [`CONFIG_KEYS`] from a `~/.warp/config.toml`.
+///
+/// Keys of the current context — the `[contexts.<name>]` table named by
+/// `WARP_CONTEXT` or `current_context` — take precedence over top-level
+/// ones.
+pub fn credentials(path: &Path) -> anyhow::Result<Vec<(&'static str, String)>> {
+    let table = read_config(path)?;
+    let context = std::env::var("WARP_CONTEXT").ok().filter(|v| !v.is_empty());
+    config_settings(&table, context.as_deref(), path)
+}
+
+fn config_settings(
+    table: &toml::Table,
+    context: Option<&str>,
+    path: &Path,
+) -> anyhow::Result<Vec<(&'static str, String)>> {
+    let context = match context.or_else(|| table.get("current_context").and_then(toml::Value::as_str)) {
+        Some(name) => Some(
+            table
+                .get("contexts")
+                .and_then(|contexts| contexts.get(name))
+                .and_then(toml::Value::as_table)
+                .with_context(|| format!("{}: no context named '{name}'", path.display()))?,
+        ),
+        None => None,
+    };
+    let mut vars = Vec::new();
+    for &(key, var) in CONFIG_KEYS {
+        match context.and_then(|context| context.get(key)).or_else(|| table.get(key)) {
+            Some(toml::Value::String(value)) => vars.push((var, value.clone())),
+            Some(_) => bail!("{}: `{key}` must be a string", path.display()),
+            None => {}
+        }
+    }
+    Ok(vars)
+}
+
+/// The parsed config file; empty when it does not exist.
+pub fn read_config(path: &Path) -> anyhow::Result<toml::Table> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))
+}
+
+/// The namespace for names given without one: `--namespace`, then
+/// `WARP_NAMESPACE`, then the config file, then `default`.
+pub fn namespace(flag: Option<&str>) -> anyhow::Result<String> {
+    Ok(setting(flag, "WARP_NAMESPACE")?.unwrap_or_else(|| "default".to_string()))
+}
+
+/// The `{ success, data, error }` envelope every `/api/v1` handler returns.
+#[derive(serde::Deserialize)]
+struct Envelope<T> {
+    data: Option<T>,
+    error: Option<String>,
+}
+
+fn unwrap_data<T: DeserializeOwned>(status: u16, body: &[u8]) -> anyhow::Result<T> {
+    if status == 401 {
+        bail!("the API rejected the token (401); set --token or WARP_API_TOKEN");
+    }
+    let envelope: Envelope<T> = serde_json::from_slice(body).with_context(|| {
+        format!("Unexpected API response ({status}): {}", String::from_utf8_lossy(body).trim())
+    })?;
+    match (envelope.data, envelope.error) {
+        (_, Some(error)) => bail!("API error ({status}): {error}"),
+        (Some(data), None) if (200..300).contains(&status) => Ok(data),
+        _ => bail!("API request failed ({status})"),
+    }
+}
+
+/// Status line and the headers this client cares about.
+pub(crate) struct Head {
+    pub(crate) status: u16,
+    chunked: bool,
+    content_length: Option<usize>,
+}
+
+pub(crate) fn read_head(reader: &mut impl BufRead) -> anyhow::Result<Head> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .with_context(|| format!("Malformed HTTP status line '{}'", line.trim()))?;
+    let mut head = Head {
+        status,
+        chunked: false,
+        content_length: None,
+    };
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(head);
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            head.chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            head.content_length = value.parse().ok();
+        }
+    }
+}
+
+pub(crate) fn read_body(reader: impl BufRead, head: &Head) -> anyhow::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    if head.chunked {
+        Chunked::new(reader).read_to_end(&mut body)?;
+    } else if let Some(len) = head.content_length {
+        reader.take(len as u64).read_to_end(&mut body)?;
+    } else {
+        let mut reader = reader;
+        reader.read_to_end(&mut body)?;
+    }
+    Ok(body)
+}
+
+/// Decoder for a `Transfer-Encoding: chunked` body.
+struct Chunked<R> {
+    inner: R,
+    /// Bytes left in the current chunk.
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Chunked<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let mut line = String::new();
+            if self.inner.read_line(&mut line)? == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+            let size = line.trim().split(';').next().unwrap_or_default();
+            self.remaining = usize::from_str_radix(size, 16).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, format!("bad chunk size '{size}'"))
+            })?;
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let want = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..want])?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= n;
+        if self.remaining == 0 {
+            // Consume the CRLF that ends the chunk.
+            let mut crlf = String::new();
+            self.inner.read_line(&mut crlf)?;
+        }
+        Ok(n)
+    }
+}
+
+/// One server-sent event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// The `event:` field (`message` when absent).
+    pub name: String,
+    pub data: String,
+}
+
+/// Events read from an open `text/event-stream` response.
+pub struct EventStream {
+    body: Box<dyn BufRead + Send>,
+}
+
+impl Iterator for EventStream {
+    type Item = anyhow::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_event(&mut self.body).transpose()
+    }
+}
+
+/// Read the next event, skipping comments and keep-alives. `None` at the
+/// end of the stream.
+fn next_event(reader: &mut impl BufRead) -> anyhow::Result<Option<Event>> {
+    let mut name = None;
+    let mut data: Option<String> = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            if let Some(data) = data.take() {
+                let name = name.take().unwrap_or_else(|| "message".to_string());
+                return Ok(Some(Event { name, data }));
+            }
+            name = None;
+            continue;
+        }
+        if line.starts_with(':') {
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => name = Some(value.to_string()),
+            "data" => match &mut data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => data = Some(value.to_string()),
+            },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_new_parses_urls() {
+        let client = ApiClient::new("http://warp.internal:9000/proxy/", Some("t".into())).unwrap();
+        assert_eq!(client.authority, "warp.internal:9000");
+        assert_eq!(client.base_path, "/proxy");
+        assert_eq!(ApiClient::new("http://localhost", None).unwrap().authority, "localhost:80");
+        assert!(ApiClient::new("https://warp.internal", None).is_err());
+        assert!(ApiClient::new("warp.internal:8443", None).is_err());
+        assert_eq!(path_segment("prod/api v2"), "prod%2Fapi%20v2");
+    }
+
+    #[test]
+    fn test_chunked_event_stream() {
+        // Chunk boundaries deliberately split lines and events.
+        let chunks = [":keep-alive\n\nevent: overview\n", "data: {\"revision\":1}\n\n", "data: a\ndata: b\n\nevent: "];
+        let mut raw = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ntransfer-encoding: chunked\r\n\r\n".to_string();
+        for chunk in chunks {
+            raw.push_str(&format!("{:x}\r\n{chunk}\r\n", chunk.len()));
+        }
+        raw.push_str("0\r\n\r\n");
+        let mut reader = BufReader::new(Cursor::new(raw));
+        let head = read_head(&mut reader).unwrap();
+        assert_eq!(head.status, 200);
+        assert!(head.chunked);
+
+        let mut body = BufReader::new(Chunked::new(reader));
+        let first = next_event(&mut body).unwrap().unwrap();
+        assert_eq!(first, Event { name: "overview".into(), data: "{\"revision\":1}".into() });
+        let second = next_event(&mut body).unwrap().unwrap();
+        assert_eq!(second, Event { name: "message".into(), data: "a\nb".into() });
+        assert_eq!(next_event(&mut body).unwrap(), None);
+    }
+
+    #[test]
+    fn test_post_sends_token_and_unwraps_data() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            while reader.read_line(&mut request).unwrap() > 2 && !request.ends_with("\r\n\r\n") {}
+            let body = r#"{"success":true,"data":{"target":3,"status":"scaling"}}"#;
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+            request
+        });
+
+        let client = ApiClient::new(&url, Some("s3cret".into())).unwrap();
+        let data: serde_json::Value =
+            client.post("/deployments/prod%2Fapi/scale", &serde_json::json!({"target": 3})).unwrap();
+        assert_eq!(data["status"], "scaling");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /api/v1/deployments/prod%2Fapi/scale HTTP/1.1\r\n"), "{request}");
+        assert!(request.contains("Authorization: Bearer s3cret\r\n"), "{request}");
+
+        let error = br#"{"success":false,"error":"deployment not found"}"#;
+        let err = unwrap_data::<serde_json::Value>(404, error).unwrap_err();
+        assert!(err.to_string().contains("deployment not found"), "{err}");
+    }
+
+    #[test]
+    fn test_tunnel_hands_over_the_upgraded_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            while reader.read_line(&mut request).unwrap() > 2 && !request.ends_with("\r\n\r\n") {}
+            write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: warp-tunnel\r\n\r\n").unwrap();
+            let mut ping = [0u8; 4];
+            reader.read_exact(&mut ping).unwrap();
+            stream.write_all(b"pong").unwrap();
+            (request, ping)
+        });
+
+        let client = ApiClient::new(&url, None).unwrap();
+        let mut tunnel = client.tunnel("/deployments/default%2Fapi/port-forward?port=8080").unwrap();
+        tunnel.write_all(b"ping").unwrap();
+        let mut pong = [0u8; 4];
+        tunnel.read_exact(&mut pong).unwrap();
+        assert_eq!(&pong, b"pong");
+        let (request, ping) = server.join().unwrap();
+        assert_eq!(&ping, b"ping");
+        assert!(request.starts_with("GET /api/v1/deployments/default%2Fapi/port-forward?port=8080 HTTP/1.1\r\n"));
+        assert!(request.contains("Upgrade: warp-tunnel\r\n"), "{request}");
+    }
+
+    #[test]
+    fn test_download_fetches_outside_the_api_prefix() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/compat/rules.toml", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            for status in ["200 OK", "404 Not Found"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let body = "[[dependency]]\n";
+                write!(stream, "HTTP/1.1 {status}\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+                assert_eq!(line, "GET /compat/rules.toml HTTP/1.1\r\n");
+            }
+        });
+
+        assert_eq!(download(&url).unwrap(), b"[[dependency]]\n");
+        let err = download(&url).unwrap_err();
+        assert!(err.to_string().contains("404"), "{err}");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_context_settings_override_top_level_ones() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            api_url = "http://127.0.0.1:8443"
+            token = "local"
+            current_context = "staging"
+
+            [contexts.staging]
+            api_url = "http://staging.internal:8443"
+            namespace = "team-a"
+
+            [contexts.prod]
+            api_url = "http://prod.internal:8443"
+            token = "prod-token"
+            "#,
+        )
+        .unwrap();
+        let path = Path::new("config.toml");
+        assert_eq!(
+            config_settings(&table, None, path).unwrap(),
+            [
+                ("WARP_API_URL", "http://staging.internal:8443".to_string()),
+                ("WARP_API_TOKEN", "local".to_string()),
+                ("WARP_NAMESPACE", "team-a".to_string()),
+            ]
+        );
+        assert_eq!(
+            config_settings(&table, Some("prod"), path).unwrap()[..2],
+            [
+                ("WARP_API_URL", "http://prod.internal:8443".to_string()),
+                ("WARP_API_TOKEN", "prod-token".to_string()),
+            ]
+        );
+        let err = config_settings(&table, Some("dev"), path).unwrap_err();
+        assert_eq!(err.to_string(), "config.toml: no context named 'dev'");
+    }
+}
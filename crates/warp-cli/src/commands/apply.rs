@@ -0,0 +1,410 @@
+//! `warp apply` — converge a cluster on a declarative manifest.
+//!
+//! The manifest (`.toml`, or `.yaml`/`.yml`) lists deployments with their
+//! source, instance count or autoscaling bounds, resources, shims, HTTP
+//! route, health check, env, and labels:
+//!
+//! ```toml
+//! namespace = "prod"
+//!
+//! [[deployments]]
+//! name = "api"
+//! source = "oci://ghcr.io/acme/api:v1"
+//! replicas = 2
+//! route = { hosts = ["api.example.com"] }
+//! shims = { dns = true }
+//! ```
+//!
+//! Each entry is turned into a spec on top of the deployment's current one,
+//! as `warp deploy` does. The manifest owns every field it can set, so a
+//! field left out is reset to its default. Priority, disruption budget, and
+//! paused are kept. The specs go to `POST /api/v1/apply`, which diffs them
+//! against the cluster, converges, and returns the plan; with `--prune`,
+//! deployments in the manifest's namespaces that it no longer lists are
+//! deleted. `--dry-run` asks for the plan without changing anything.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use warp_core::config::{HealthConfig, ShimsConfig};
+
+use super::deploy::{DEFAULT_CPU_WEIGHT, DEFAULT_MEMORY_BYTES, epoch_secs, parse_memory, preflight};
+use crate::api::ApiClient;
+use crate::output;
+
+pub struct ApplyOptions<'a> {
+    /// Manifest file.
+    pub file: &'a str,
+    /// Namespace for entries and manifests that do not name one.
+    pub namespace: &'a str,
+    /// Delete deployments the manifest does not list.
+    pub prune: bool,
+    /// Print the plan only.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    namespace: Option<String>,
+    #[serde(default)]
+    deployments: Vec<ManifestDeployment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestDeployment {
+    name: String,
+    namespace: Option<String>,
+    source: String,
+    /// Fixed instance count; exclusive with `scaling.min`/`scaling.max`.
+    replicas: Option<u32>,
+    scaling: Option<Scaling>,
+    memory_limit: Option<String>,
+    cpu_weight: Option<u32>,
+    execution_budget_ms: Option<u64>,
+    route: Option<Route>,
+    shims: Option<ShimsConfig>,
+    health: Option<HealthConfig>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+/// Instance bounds, and autoscaling when `metric` or `target_value` is set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scaling {
+    min: Option<u32>,
+    max: Option<u32>,
+    metric: Option<String>,
+    target_value: Option<f64>,
+    scale_up_window: Option<String>,
+    scale_down_window: Option<String>,
+}
+
+/// Where the HTTP trigger is served.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Route {
+    port: Option<u16>,
+    #[serde(default)]
+    hosts: Vec<String>,
+    path_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Create,
+    Update,
+    Delete,
+    Unchanged,
+}
+
+/// One line of the plan `POST /apply` returns.
+#[derive(Debug, Serialize, Deserialize)]
+struct Change {
+    id: String,
+    action: Action,
+    /// Dotted paths of the fields an update changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<String>,
+    /// Why applying the change failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Plan {
+    changes: Vec<Change>,
+}
+
+pub fn apply(client: &ApiClient, options: &ApplyOptions, format: &str) -> anyhow::Result<()> {
+    let manifest = read_manifest(Path::new(options.file))?;
+    let current: Vec<Value> = client.get("/deployments")?;
+    let specs = desired_specs(&manifest, options.namespace, &current, epoch_secs())?;
+
+    if let Some(capabilities) = client.get_optional::<Value>("/capabilities")? {
+        let mut problems = Vec::new();
+        for spec in &specs {
+            let id = spec["id"].as_str().unwrap_or_default();
+            problems.extend(preflight(spec, &capabilities).into_iter().map(|p| format!("{id}: {p}")));
+        }
+        if !problems.is_empty() {
+            bail!("{} cannot run on {}:\n  {}", options.file, client.url(), problems.join("\n  "));
+        }
+    }
+    let plan: Plan = client
+        .post(&format!("/apply?dry_run={}", options.dry_run), &json!({"deployments": specs, "prune": options.prune}))
+        .with_context(|| format!("Applying {}", options.file))?;
+    output::print(format, &plan.changes, || format_plan(&plan.changes))?;
+
+    let failed: Vec<String> = plan
+        .changes
+        .iter()
+        .filter_map(|change| change.error.as_ref().map(|e| format!("{}: {e}", change.id)))
+        .collect();
+    if !failed.is_empty() {
+        bail!("{} was partly applied to {}:\n  {}", options.file, client.url(), failed.join("\n  "));
+    }
+    if !options.dry_run && format != "json" && format != "yaml" {
+        println!("Applied {} to {}", options.file, client.url());
+    }
+    Ok(())
+}
+
+/// Parse a manifest as YAML when its extension says so, else as TOML.
+fn read_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let manifest = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(anyhow::Error::from),
+        _ => toml::from_str(&text).map_err(anyhow::Error::from),
+    };
+    manifest.with_context(|| format!("Invalid manifest {}", path.display()))
+}
+
+/// The spec for each of `manifest`'s deployments, on top of its current
+/// spec in `current` when there is one.
+fn desired_specs(manifest: &Manifest, namespace: &str, current: &[Value], now: u64) -> anyhow::Result<Vec<Value>> {
+    let default_namespace = manifest.namespace.as_deref().unwrap_or(namespace);
+    manifest
+        .deployments
+        .iter()
+        .map(|deployment| {
+            let namespace = deployment.namespace.as_deref().unwrap_or(default_namespace);
+            let id = format!("{namespace}/{}", deployment.name);
+            let existing = current.iter().find(|spec| spec["id"] == id.as_str()).cloned();
+            desired_spec(deployment, namespace, existing, now).with_context(|| id.clone())
+        })
+        .collect()
+}
+
+/// The spec for `deployment`, on top of its current spec when there is one.
+fn desired_spec(
+    deployment: &ManifestDeployment,
+    namespace: &str,
+    existing: Option<Value>,
+    now: u64,
+) -> anyhow::Result<Value> {
+    let scaling = deployment.scaling.as_ref();
+    let (min, max) = match (deployment.replicas, scaling.and_then(|s| s.min.or(s.max))) {
+        (Some(_), Some(_)) => bail!("set either replicas or scaling.min/max, not both"),
+        (Some(replicas), None) => (replicas, replicas),
+        (None, _) => {
+            let min = scaling.and_then(|s| s.min).unwrap_or(1);
+            (min, scaling.and_then(|s| s.max).unwrap_or(min))
+        }
+    };
+    if max < min {
+        bail!("scaling.max ({max}) is below scaling.min ({min})");
+    }
+    let autoscaling = scaling.filter(|s| s.metric.is_some() || s.target_value.is_some()).map(|s| {
+        json!({
+            "metric": s.metric.as_deref().unwrap_or("rps"),
+            "target_value": s.target_value.unwrap_or(100.0),
+            "scale_up_window": s.scale_up_window.as_deref().unwrap_or("30s"),
+            "scale_down_window": s.scale_down_window.as_deref().unwrap_or("5m"),
+        })
+    });
+    let memory_bytes = match deployment.memory_limit.as_deref() {
+        Some(limit) => parse_memory(limit)?,
+        None => DEFAULT_MEMORY_BYTES,
+    };
+    let route = deployment.route.as_ref();
+    let mut trigger = json!({"type": "http", "port": route.and_then(|r| r.port)});
+    if let Some(route) = route.filter(|r| !r.hosts.is_empty()) {
+        trigger["hosts"] = json!(route.hosts);
+    }
+    if let Some(prefix) = route.and_then(|r| r.path_prefix.as_deref()) {
+        trigger["path_prefix"] = json!(prefix);
+    }
+    let health = deployment.health.as_ref().and_then(|h| {
+        h.endpoint.as_ref().map(|endpoint| {
+            json!({
+                "endpoint": endpoint,
+                "interval": h.interval.as_deref().unwrap_or("5s"),
+                "timeout": h.timeout.as_deref().unwrap_or("2s"),
+                "unhealthy_threshold": h.unhealthy_threshold.unwrap_or(3),
+            })
+        })
+    });
+    let shims = deployment.shims.clone().unwrap_or_default();
+    let on = |flag: Option<bool>| flag.unwrap_or(false);
+
+    let mut spec = existing.unwrap_or_else(|| json!({"created_at": now}));
+    let fields = json!({
+        "id": format!("{namespace}/{}", deployment.name),
+        "namespace": namespace,
+        "name": deployment.name,
+        "source": deployment.source,
+        "trigger": trigger,
+        "instances": {"min": min, "max": max},
+        "resources": {
+            "memory_bytes": memory_bytes,
+            "cpu_weight": deployment.cpu_weight.unwrap_or(DEFAULT_CPU_WEIGHT),
+            "execution_budget_ms": deployment.execution_budget_ms,
+        },
+        "scaling": autoscaling,
+        "health": health,
+        "shims": {
+            "timezone": on(shims.timezone),
+            "dev_urandom": on(shims.dev_urandom),
+            "dns": on(shims.dns),
+            "signals": on(shims.signals),
+            "database_proxy": on(shims.database_proxy),
+        },
+        "env": deployment.env,
+        "labels": deployment.labels,
+        "updated_at": now,
+    });
+    let object = spec.as_object_mut().context("the current deployment spec is not an object")?;
+    for (key, value) in fields.as_object().expect("built above") {
+        object.insert(key.clone(), value.clone());
+    }
+    // The API omits empty labels; match it so they do not read as a change.
+    if deployment.labels.is_empty() {
+        object.remove("labels");
+    }
+    Ok(spec)
+}
+
+fn format_plan(changes: &[Change]) -> String {
+    let width = changes.iter().map(|c| c.id.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    let mut counts = [0; 4];
+    for change in changes {
+        let (mark, label) = match change.action {
+            Action::Create => ("+", "create".to_string()),
+            Action::Update => ("~", format!("update: {}", change.fields.join(", "))),
+            Action::Delete => ("-", "delete".to_string()),
+            Action::Unchanged => ("=", "unchanged".to_string()),
+        };
+        counts[change.action as usize] += 1;
+        match &change.error {
+            Some(error) => out.push_str(&format!("{mark} {:<width$}  {label} (failed: {error})\n", change.id)),
+            None => out.push_str(&format!("{mark} {:<width$}  {label}\n", change.id)),
+        }
+    }
+    let [create, update, delete, unchanged] = counts;
+    out.push_str(&format!(
+        "Plan: {create} to create, {update} to update, {delete} to delete, {unchanged} unchanged.\n"
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(toml: &str) -> Manifest {
+        toml::from_str(toml).unwrap()
+    }
+
+    const MANIFEST: &str = r#"
+namespace = "prod"
+
+[[deployments]]
+name = "api"
+source = "oci://ghcr.io/acme/api:v2"
+replicas = 3
+memory_limit = "64MiB"
+route = { hosts = ["api.example.com"] }
+shims = { dns = true }
+env = { RUST_LOG = "info" }
+
+[[deployments]]
+name = "worker"
+namespace = "jobs"
+source = "oci://ghcr.io/acme/worker:v1"
+scaling = { min = 1, max = 4, metric = "rps", target_value = 50 }
+"#;
+
+    #[test]
+    fn test_desired_spec() {
+        let manifest = manifest(MANIFEST);
+        let api = desired_spec(&manifest.deployments[0], "prod", None, 42).unwrap();
+        assert_eq!(api["id"], "prod/api");
+        assert_eq!(api["instances"], json!({"min": 3, "max": 3}));
+        assert_eq!(api["resources"]["memory_bytes"], 64 << 20);
+        assert_eq!(api["trigger"], json!({"type": "http", "port": null, "hosts": ["api.example.com"]}));
+        assert_eq!(api["shims"]["dns"], true);
+        assert_eq!(api["scaling"], Value::Null);
+        assert_eq!(api["created_at"], 42);
+        assert!(api.get("labels").is_none());
+
+        let worker = desired_spec(&manifest.deployments[1], "jobs", None, 42).unwrap();
+        assert_eq!(worker["instances"], json!({"min": 1, "max": 4}));
+        assert_eq!(worker["scaling"]["target_value"], 50.0);
+        assert_eq!(worker["scaling"]["scale_down_window"], "5m");
+    }
+
+    #[test]
+    fn test_desired_spec_rejects_conflicting_scaling() {
+        let both = manifest("[[deployments]]\nname = \"a\"\nsource = \"x\"\nreplicas = 2\nscaling = { max = 3 }\n");
+        assert!(desired_spec(&both.deployments[0], "default", None, 0).is_err());
+        let inverted = manifest("[[deployments]]\nname = \"a\"\nsource = \"x\"\nscaling = { min = 3, max = 1 }\n");
+        assert!(desired_spec(&inverted.deployments[0], "default", None, 0).is_err());
+        assert!(toml::from_str::<Manifest>("[[deployments]]\nname = \"a\"\nsource = \"x\"\nreplica = 2\n").is_err());
+    }
+
+    #[test]
+    fn test_desired_specs() {
+        let manifest = manifest(MANIFEST);
+        // What the API returns for prod/api once applied, plus fields the
+        // manifest does not manage.
+        let mut applied = desired_spec(&manifest.deployments[0], "prod", None, 1).unwrap();
+        applied["priority"] = json!(2);
+        applied["instances"]["min"] = json!(1);
+        let current = vec![applied, json!({"id": "staging/api", "namespace": "staging"})];
+
+        let specs = desired_specs(&manifest, "default", &current, 2).unwrap();
+        let ids: Vec<_> = specs.iter().map(|spec| spec["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["prod/api", "jobs/worker"]);
+        assert_eq!(specs[0]["instances"]["min"], 3);
+        assert_eq!((specs[0]["priority"].as_u64(), specs[0]["created_at"].as_u64()), (Some(2), Some(1)));
+        assert_eq!((specs[1]["created_at"].as_u64(), specs[1]["namespace"].as_str()), (Some(2), Some("jobs")));
+    }
+
+    #[test]
+    fn test_read_yaml_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cluster.yaml");
+        std::fs::write(
+            &path,
+            "deployments:\n  - name: api\n    source: oci://ghcr.io/acme/api:v2\n    route:\n      port: 8080\n      path_prefix: /api\n",
+        )
+        .unwrap();
+        let manifest = read_manifest(&path).unwrap();
+        let spec = desired_spec(&manifest.deployments[0], "default", None, 0).unwrap();
+        assert_eq!(spec["trigger"], json!({"type": "http", "port": 8080, "path_prefix": "/api"}));
+    }
+
+    #[test]
+    fn test_format_plan() {
+        let change = |id: &str, action, fields: &[&str]| Change {
+            id: id.into(),
+            action,
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            error: None,
+        };
+        let mut legacy = change("prod/legacy", Action::Delete, &[]);
+        legacy.error = Some("storage error".into());
+        let out = format_plan(&[
+            change("prod/api", Action::Update, &["source", "instances.min"]),
+            change("jobs/worker", Action::Create, &[]),
+            legacy,
+        ]);
+        assert_eq!(
+            out,
+            "~ prod/api     update: source, instances.min\n\
+             + jobs/worker  create\n\
+             - prod/legacy  delete (failed: storage error)\n\
+             Plan: 1 to create, 1 to update, 1 to delete, 0 unchanged.\n"
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! `warp bench` — drive load at a deployment and report how it held up.
+//!
+//! Requests go out on a fixed schedule, `--rps` per second for
+//! `--duration`, from a pool of `--concurrency` keep-alive connections.
+//! Latency is measured from when a request was due, not when a connection
+//! came free, so a deployment that falls behind shows it in the tail
+//! instead of silently getting less load.
+//!
+//! By default each connection is a port-forward tunnel to the deployment's
+//! ingress port (see `warp port-forward`), so no route or DNS is needed.
+//! `--url` sends to an ingress URL instead, which also exercises host and
+//! path routing.
+//!
+//! Connection failures and 5xx responses count as errors. Afterwards the
+//! report is set beside the server's own view from
+//! `GET /api/v1/deployments/:id/metrics?since=`: request rate, p99, error
+//! rate, and how many instances ran, which shows whether autoscaling kept
+//! up with the load.
+
+use std::collections::BTreeMap;
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiClient, path_segment, read_body, read_head};
+use crate::output;
+
+/// Timeout for connecting and for each response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait after the run for a metrics snapshot that covers its end.
+const SETTLE: Duration = Duration::from_secs(15);
+
+pub struct BenchOptions<'a> {
+    /// Deployment id (`namespace/name`).
+    pub deployment: &'a str,
+    /// Requests per second to send.
+    pub rps: u32,
+    pub duration: Duration,
+    /// Connections to send from.
+    pub concurrency: usize,
+    /// Request path when tunneling.
+    pub path: &'a str,
+    /// Ingress port to tunnel to.
+    pub port: u16,
+    /// Ingress URL to send to instead of tunneling.
+    pub url: Option<&'a str>,
+}
+
+/// Where requests go.
+enum Target {
+    Tunnel { client: ApiClient, path: String, request: String },
+    Direct { authority: String, request: String },
+}
+
+impl Target {
+    fn connect(&self) -> anyhow::Result<TcpStream> {
+        let stream = match self {
+            Self::Tunnel { client, path, .. } => client.tunnel(path)?,
+            Self::Direct { authority, .. } => {
+                let addr = std::net::ToSocketAddrs::to_socket_addrs(authority)
+                    .with_context(|| format!("Cannot resolve {authority}"))?
+                    .next()
+                    .with_context(|| format!("Cannot resolve {authority}"))?;
+                TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT)?
+            }
+        };
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        Ok(stream)
+    }
+
+    fn request(&self) -> &[u8] {
+        match self {
+            Self::Tunnel { request, .. } | Self::Direct { request, .. } => request.as_bytes(),
+        }
+    }
+}
+
+/// When each request is due: evenly spaced from `start`.
+struct Schedule {
+    start: Instant,
+    interval: Duration,
+    total: u64,
+    next: AtomicU64,
+}
+
+impl Schedule {
+    fn new(rps: u32, duration: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            interval: Duration::from_secs(1) / rps,
+            total: (duration.as_secs_f64() * f64::from(rps)).round() as u64,
+            next: AtomicU64::new(0),
+        }
+    }
+
+    /// The due time of the next request, or `None` once all are taken.
+    fn next(&self) -> Option<Instant> {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        (i < self.total).then(|| self.start + self.interval * i as u32)
+    }
+}
+
+/// One request: how long it took and its status (`None` if it failed).
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    latency: Duration,
+    status: Option<u16>,
+}
+
+/// The part of a metrics snapshot the report uses.
+#[derive(Debug, Deserialize)]
+struct Snapshot {
+    epoch: u64,
+    rps: f64,
+    latency_p99_ms: f64,
+    error_rate: f64,
+    active_instances: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct BenchReport {
+    deployment: String,
+    target_rps: u32,
+    duration_secs: f64,
+    requests: usize,
+    achieved_rps: f64,
+    latency_ms: Latency,
+    errors: usize,
+    error_rate: f64,
+    statuses: BTreeMap<String, usize>,
+    /// The server's metrics over the run, when it took any snapshots.
+    server: Option<ServerView>,
+}
+
+#[derive(Debug, Serialize)]
+struct Latency {
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    max: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct ServerView {
+    snapshots: usize,
+    /// Mean request rate across the snapshots.
+    rps: f64,
+    /// Highest p99 in any snapshot.
+    latency_p99_ms: f64,
+    /// Error rate weighted by each snapshot's request rate.
+    error_rate: f64,
+    instances_min: u32,
+    instances_max: u32,
+}
+
+pub fn bench(client: &ApiClient, options: &BenchOptions, format: &str) -> anyhow::Result<()> {
+    if options.rps == 0 || options.duration.is_zero() {
+        bail!("--rps and --duration must be more than zero");
+    }
+    let target = match options.url {
+        Some(url) => {
+            let (authority, host, path) = parse_url(url)?;
+            Target::Direct { authority, request: request_head(&host, &path) }
+        }
+        None => {
+            let path = format!("/deployments/{}/port-forward?port={}", path_segment(options.deployment), options.port);
+            drop(client.tunnel(&path)?);
+            Target::Tunnel { client: client.clone(), path, request: request_head("localhost", options.path) }
+        }
+    };
+    let text = format != "json" && format != "yaml";
+    if text {
+        let via = options.url.map_or_else(|| format!("port {} via the API", options.port), str::to_string);
+        println!(
+            "Sending {} rps to {} ({via}) for {}s on {} connections",
+            options.rps,
+            options.deployment,
+            options.duration.as_secs(),
+            options.concurrency
+        );
+    }
+
+    let started_epoch = epoch_secs();
+    let schedule = Schedule::new(options.rps, options.duration);
+    let samples = Mutex::new(Vec::with_capacity(schedule.total as usize));
+    std::thread::scope(|scope| {
+        for _ in 0..options.concurrency.max(1) {
+            scope.spawn(|| worker(&target, &schedule, &samples));
+        }
+    });
+    let elapsed = schedule.start.elapsed();
+    let samples = samples.into_inner().unwrap_or_else(|e| e.into_inner());
+
+    let snapshots = server_snapshots(client, options.deployment, started_epoch, text)?;
+    let report = report(options.deployment, options.rps, elapsed, &samples, &snapshots);
+    output::print(format, &report, || format_report(&report))
+}
+
+/// Send requests as they come due until the schedule runs out.
+fn worker(target: &Target, schedule: &Schedule, samples: &Mutex<Vec<Sample>>) {
+    let mut conn = None;
+    let mut local = Vec::new();
+    while let Some(due) = schedule.next() {
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+        let status = send(target, &mut conn).ok();
+        if status.is_none() {
+            conn = None;
+        }
+        local.push(Sample { latency: due.elapsed(), status });
+    }
+    samples.lock().unwrap_or_else(|e| e.into_inner()).extend(local);
+}
+
+/// One request on the open connection, or a new one. A kept-alive
+/// connection the server has since closed is replaced once.
+fn send(target: &Target, conn: &mut Option<BufReader<TcpStream>>) -> anyhow::Result<u16> {
+    if let Some(reader) = conn.as_mut()
+        && let Ok(status) = exchange(reader, target.request())
+    {
+        return Ok(status);
+    }
+    let reader = conn.insert(BufReader::new(target.connect()?));
+    exchange(reader, target.request())
+}
+
+fn exchange(reader: &mut BufReader<TcpStream>, request: &[u8]) -> anyhow::Result<u16> {
+    reader.get_mut().write_all(request)?;
+    let head = read_head(reader)?;
+    if !matches!(head.status, 204 | 304) {
+        read_body(&mut *reader, &head)?;
+    }
+    Ok(head.status)
+}
+
+/// A keep-alive `GET`.
+fn request_head(host: &str, path: &str) -> String {
+    format!("GET {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: warp-bench\r\n\r\n")
+}
+
+/// `http://host[:port][/path]` → (`host:port`, `Host` header, path).
+fn parse_url(url: &str) -> anyhow::Result<(String, String, String)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        bail!("--url must start with http:// (got '{url}')");
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        bail!("--url '{url}' has no host");
+    }
+    let authority = if host.contains(':') { host.to_string() } else { format!("{host}:80") };
+    Ok((authority, host.to_string(), path.to_string()))
+}
+
+/// Snapshots the server took since `since`, waiting up to [`SETTLE`] for
+/// one that covers the end of the run.
+fn server_snapshots(client: &ApiClient, id: &str, since: u64, text: bool) -> anyhow::Result<Vec<Snapshot>> {
+    let path = format!("/deployments/{}/metrics?since={since}", path_segment(id));
+    let ended = epoch_secs();
+    let deadline = Instant::now() + SETTLE;
+    if text {
+        println!("Waiting for the server's metrics...");
+    }
+    loop {
+        let snapshots: Vec<Snapshot> = client.get(&path)?;
+        if snapshots.iter().any(|s| s.epoch >= ended) || Instant::now() >= deadline {
+            return Ok(snapshots);
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn report(deployment: &str, rps: u32, elapsed: Duration, samples: &[Sample], snapshots: &[Snapshot]) -> BenchReport {
+    let mut latencies: Vec<Duration> = samples.iter().map(|s| s.latency).collect();
+    latencies.sort_unstable();
+    let ms = |q: f64| percentile(&latencies, q).as_secs_f64() * 1000.0;
+    let mut statuses = BTreeMap::new();
+    for sample in samples {
+        let key = sample.status.map_or_else(|| "failed".to_string(), |status| status.to_string());
+        *statuses.entry(key).or_insert(0) += 1;
+    }
+    let errors = samples.iter().filter(|s| s.status.is_none_or(|status| status >= 500)).count();
+    BenchReport {
+        deployment: deployment.to_string(),
+        target_rps: rps,
+        duration_secs: elapsed.as_secs_f64(),
+        requests: samples.len(),
+        achieved_rps: samples.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        latency_ms: Latency { p50: ms(0.5), p90: ms(0.9), p99: ms(0.99), max: ms(1.0) },
+        errors,
+        error_rate: if samples.is_empty() { 0.0 } else { errors as f64 / samples.len() as f64 },
+        statuses,
+        server: server_view(snapshots),
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[Duration], q: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn server_view(snapshots: &[Snapshot]) -> Option<ServerView> {
+    if snapshots.is_empty() {
+        return None;
+    }
+    let total_rps: f64 = snapshots.iter().map(|s| s.rps).sum();
+    let errors: f64 = snapshots.iter().map(|s| s.rps * s.error_rate).sum();
+    Some(ServerView {
+        snapshots: snapshots.len(),
+        rps: total_rps / snapshots.len() as f64,
+        latency_p99_ms: snapshots.iter().map(|s| s.latency_p99_ms).fold(0.0, f64::max),
+        error_rate: if total_rps > 0.0 { errors / total_rps } else { 0.0 },
+        instances_min: snapshots.iter().map(|s| s.active_instances).min().unwrap_or_default(),
+        instances_max: snapshots.iter().map(|s| s.active_instances).max().unwrap_or_default(),
+    })
+}
+
+fn format_report(report: &BenchReport) -> String {
+    let latency = &report.latency_ms;
+    let statuses: Vec<String> = report.statuses.iter().map(|(status, n)| format!("{status}: {n}")).collect();
+    let mut out = format!(
+        "Requests:  {} in {:.1}s ({:.1} rps, target {})\n\
+         Latency:   p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms\n\
+         Errors:    {} ({:.2}%)\n\
+         Statuses:  {}\n",
+        report.requests,
+        report.duration_secs,
+        report.achieved_rps,
+        report.target_rps,
+        latency.p50,
+        latency.p90,
+        latency.p99,
+        latency.max,
+        report.errors,
+        report.error_rate * 100.0,
+        statuses.join(", "),
+    );
+    match &report.server {
+        Some(server) => out.push_str(&format!(
+            "Server:    {:.1} rps, p99 up to {:.1}ms, {:.2}% errors, {} instances ({} snapshots)\n",
+            server.rps,
+            server.latency_p99_ms,
+            server.error_rate * 100.0,
+            instance_range(server.instances_min, server.instances_max),
+            server.snapshots,
+        )),
+        None => out.push_str("Server:    no metrics snapshots yet\n"),
+    }
+    out
+}
+
+fn instance_range(min: u32, max: u32) -> String {
+    if min == max { min.to_string() } else { format!("{min}-{max}") }
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Read};
+    use std::net::TcpListener;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule = Schedule::new(4, Duration::from_secs(2));
+        let due: Vec<_> = std::iter::from_fn(|| schedule.next()).map(|at| at - schedule.start).collect();
+        assert_eq!(due, [ms(0), ms(250), ms(500), ms(750), ms(1000), ms(1250), ms(1500), ms(1750)]);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let sorted: Vec<_> = (1..=100).map(ms).collect();
+        assert_eq!(percentile(&sorted, 0.5), ms(50));
+        assert_eq!(percentile(&sorted, 0.99), ms(99));
+        assert_eq!(percentile(&sorted, 1.0), ms(100));
+        assert_eq!(percentile(&sorted[..1], 0.5), ms(1));
+        assert_eq!(percentile(&[], 0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("http://api.example.com/v1/items").unwrap(),
+            ("api.example.com:80".into(), "api.example.com".into(), "/v1/items".into())
+        );
+        assert_eq!(
+            parse_url("http://127.0.0.1:8080").unwrap(),
+            ("127.0.0.1:8080".into(), "127.0.0.1:8080".into(), "/".into())
+        );
+        assert!(parse_url("https://api.example.com").is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let sample = |latency, status| Sample { latency: ms(latency), status };
+        let samples = [sample(10, Some(200)), sample(20, Some(200)), sample(30, Some(503)), sample(40, None)];
+        let snapshots = [
+            Snapshot { epoch: 1, rps: 3.0, latency_p99_ms: 25.0, error_rate: 0.0, active_instances: 1 },
+            Snapshot { epoch: 2, rps: 1.0, latency_p99_ms: 31.0, error_rate: 1.0, active_instances: 3 },
+        ];
+        let report = report("default/api", 2, Duration::from_secs(2), &samples, &snapshots);
+        assert_eq!((report.requests, report.errors, report.achieved_rps), (4, 2, 2.0));
+        assert_eq!((report.latency_ms.p50, report.latency_ms.max), (20.0, 40.0));
+        assert_eq!(report.statuses, BTreeMap::from([("200".into(), 2), ("503".into(), 1), ("failed".into(), 1)]));
+        let server = report.server.as_ref().unwrap();
+        assert_eq!((server.rps, server.latency_p99_ms, server.error_rate), (2.0, 31.0, 0.25));
+        assert_eq!((server.instances_min, server.instances_max), (1, 3));
+        assert!(format_report(&report).contains("Server:    2.0 rps, p99 up to 31.0ms, 25.00% errors, 1-3 instances"));
+    }
+
+    #[test]
+    fn test_send_reuses_and_replaces_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Answers two requests on the first connection, then one on the next.
+        let server = std::thread::spawn(move || {
+            for requests in [2, 1] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut writer = stream;
+                for _ in 0..requests {
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap() > 2 {
+                        line.clear();
+                    }
+                    writer.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok").unwrap();
+                }
+                let _ = reader.read(&mut [0; 1]);
+            }
+        });
+        let target = Target::Direct { authority: addr.to_string(), request: request_head("x", "/") };
+        let mut conn = None;
+        assert_eq!(send(&target, &mut conn).unwrap(), 200);
+        assert_eq!(send(&target, &mut conn).unwrap(), 200);
+        assert_eq!(send(&target, &mut conn).unwrap(), 200);
+        drop(conn);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! `warp context` — switch between clusters.
+//!
+//! A context is a `[contexts.<name>]` table in `~/.warp/config.toml` with
+//! an `api_url`, `token`, and default `namespace`. `use` records the one
+//! commands talk to as `current_context`; its keys then take the place of
+//! the top-level ones, below flags and environment variables. Setting
+//! `WARP_CONTEXT` picks a context for a single command. `set` creates or
+//! updates a context and `list` shows them all.
+//!
+//! ```toml
+//! current_context = "staging"
+//!
+//! [contexts.staging]
+//! api_url = "http://staging.internal:8443"
+//! token = "..."
+//! namespace = "team-a"
+//! ```
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use serde_json::{Value, json};
+
+use super::status::table;
+use crate::api::{self, ApiClient};
+use crate::output;
+
+/// Fields `set` can change; unset ones are left as they are.
+pub struct ContextFields<'a> {
+    pub api_url: Option<&'a str>,
+    pub token: Option<&'a str>,
+    pub namespace: Option<&'a str>,
+}
+
+pub fn list(format: &str) -> anyhow::Result<()> {
+    let config = api::read_config(&config_path()?)?;
+    output::print(format, &contexts_json(&config), || format_contexts(&config))
+}
+
+pub fn use_context(name: &str) -> anyhow::Result<()> {
+    let path = config_path()?;
+    let mut config = api::read_config(&path)?;
+    if contexts(&config).and_then(|contexts| contexts.get(name)).is_none() {
+        bail!("No context named '{name}'; create it with `warp context set {name} --api-url <URL>`");
+    }
+    config.insert("current_context".to_string(), name.into());
+    write_config(&path, &config)?;
+    println!("Switched to context {name}");
+    Ok(())
+}
+
+pub fn set(name: &str, fields: &ContextFields) -> anyhow::Result<()> {
+    let path = config_path()?;
+    let mut config = api::read_config(&path)?;
+    let created = set_fields(&mut config, name, fields)?;
+    write_config(&path, &config)?;
+    println!("{} context {name}", if created { "Created" } else { "Updated" });
+    Ok(())
+}
+
+/// Apply `fields` to context `name`, creating it if needed. Returns
+/// whether it was created.
+fn set_fields(config: &mut toml::Table, name: &str, fields: &ContextFields) -> anyhow::Result<bool> {
+    if name.is_empty() {
+        bail!("Context names cannot be empty");
+    }
+    if let Some(url) = fields.api_url {
+        ApiClient::new(url, None)?;
+    }
+    let contexts = config
+        .entry("contexts")
+        .or_insert_with(|| toml::Table::new().into())
+        .as_table_mut()
+        .context("`contexts` must be a table")?;
+    let created = !contexts.contains_key(name);
+    let context = contexts
+        .entry(name)
+        .or_insert_with(|| toml::Table::new().into())
+        .as_table_mut()
+        .with_context(|| format!("context '{name}' must be a table"))?;
+    for (key, value) in [("api_url", fields.api_url), ("token", fields.token), ("namespace", fields.namespace)] {
+        if let Some(value) = value {
+            context.insert(key.to_string(), value.into());
+        }
+    }
+    Ok(created)
+}
+
+fn contexts(config: &toml::Table) -> Option<&toml::Table> {
+    config.get("contexts").and_then(toml::Value::as_table)
+}
+
+fn current(config: &toml::Table) -> Option<&str> {
+    config.get("current_context").and_then(toml::Value::as_str)
+}
+
+fn format_contexts(config: &toml::Table) -> String {
+    let Some(contexts) = contexts(config).filter(|contexts| !contexts.is_empty()) else {
+        return "No contexts; create one with `warp context set <name> --api-url <URL>`\n".to_string();
+    };
+    let field = |context: &toml::Value, key: &str| context.get(key).and_then(toml::Value::as_str).unwrap_or("-").to_string();
+    let rows: Vec<[String; 4]> = contexts
+        .iter()
+        .map(|(name, context)| {
+            [
+                if current(config) == Some(name) { "*" } else { "" }.to_string(),
+                name.clone(),
+                field(context, "api_url"),
+                field(context, "namespace"),
+            ]
+        })
+        .collect();
+    table(["CURRENT", "NAME", "API URL", "NAMESPACE"], &rows, "")
+}
+
+/// Contexts as JSON, with tokens left out.
+fn contexts_json(config: &toml::Table) -> Value {
+    let contexts: Vec<Value> = contexts(config)
+        .into_iter()
+        .flatten()
+        .map(|(name, context)| {
+            json!({
+                "name": name,
+                "current": current(config) == Some(name),
+                "api_url": context.get("api_url").and_then(toml::Value::as_str),
+                "namespace": context.get("namespace").and_then(toml::Value::as_str),
+                "has_token": context.get("token").is_some(),
+            })
+        })
+        .collect();
+    Value::Array(contexts)
+}
+
+fn config_path() -> anyhow::Result<std::path::PathBuf> {
+    api::config_path().context("HOME is not set; cannot find ~/.warp/config.toml")
+}
+
+/// Write the config readable only by the owner, since it holds tokens.
+fn write_config(path: &Path, config: &toml::Table) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let contents = toml::to_string(config)?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Tokens live here, so the file is never readable by others, not even
+    // between creating and writing it.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).with_context(|| format!("Failed to write {}", path.display()))?;
+    // `mode` only applies to new files; tighten one created before.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes()).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_fields_creates_then_updates() {
+        let mut config: toml::Table = toml::from_str("api_url = \"http://127.0.0.1:8443\"\n").unwrap();
+        let fields = ContextFields { api_url: Some("http://staging:8443"), token: Some("t0k"), namespace: None };
+        assert!(set_fields(&mut config, "staging", &fields).unwrap());
+        let fields = ContextFields { api_url: None, token: None, namespace: Some("team-a") };
+        assert!(!set_fields(&mut config, "staging", &fields).unwrap());
+
+        let staging = &config["contexts"]["staging"];
+        assert_eq!(staging["api_url"].as_str(), Some("http://staging:8443"));
+        assert_eq!(staging["token"].as_str(), Some("t0k"));
+        assert_eq!(staging["namespace"].as_str(), Some("team-a"));
+        // Top-level settings are untouched.
+        assert_eq!(config["api_url"].as_str(), Some("http://127.0.0.1:8443"));
+
+        let bad = ContextFields { api_url: Some("ftp://staging"), token: None, namespace: None };
+        assert!(set_fields(&mut config, "staging", &bad).is_err());
+    }
+
+    #[test]
+    fn test_format_contexts() {
+        let config: toml::Table = toml::from_str(
+            r#"
+            current_context = "prod"
+            [contexts.local]
+            api_url = "http://127.0.0.1:8443"
+            [contexts.prod]
+            api_url = "http://prod.internal:8443"
+            namespace = "payments"
+            token = "secret"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            format_contexts(&config),
+            "CURRENT  NAME   API URL                    NAMESPACE\n         \
+             local  http://127.0.0.1:8443      -\n\
+             *        prod   http://prod.internal:8443  payments\n"
+        );
+        let json = contexts_json(&config);
+        assert_eq!(
+            json[1],
+            json!({"name": "prod", "current": true, "api_url": "http://prod.internal:8443", "namespace": "payments", "has_token": true})
+        );
+        assert!(format_contexts(&toml::Table::new()).starts_with("No contexts"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_config_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warp").join("config.toml");
+        let config: toml::Table = toml::from_str("token = \"t0k\"\n").unwrap();
+        write_config(&path, &config).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_config(&path, &config).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "token = \"t0k\"\n");
+    }
+}
@@ -1,12 +1,22 @@
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn analyze(path: &str, format: &str, lang: Option<&str>) -> anyhow::Result<bool> {
+use anyhow::{Context, bail};
+use warp_analyzer::db::sync::{self, SyncOutcome};
+
+use crate::{api, output};
+
+pub fn analyze(path: &str, format: &str, lang: Option<&str>, no_cache: bool) -> anyhow::Result<bool> {
     let project_path = Path::new(path);
-    let report = warp_analyzer::analyze(project_path, lang)?;
+    let report = run_analysis(project_path, lang, no_cache)?;
 
     match format {
-        "json" => {
-            println!("{}", serde_json::to_string_pretty(&report)?);
+        "json" | "yaml" => output::print(format, &report, String::new)?,
+        "sarif" => {
+            // Locations are relative to the project, not a Dockerfile in it.
+            let root = warp_analyzer::project_dir(project_path);
+            let sarif = warp_analyzer::sarif::to_sarif(&report, root);
+            println!("{}", serde_json::to_string_pretty(&sarif)?);
         }
         _ => {
             println!("{}", warp_analyzer::report::format_report(&report));
@@ -17,6 +27,55 @@ pub fn analyze(path: &str, format: &str, lang: Option<&str>) -> anyhow::Result<b
     Ok(has_blockers)
 }
 
+/// Analyze and print only the verdict changes against `baseline` (a JSON
+/// report) or, when `None`, the previous run. Returns whether a change
+/// adds a blocker.
+pub fn analyze_diff(
+    path: &str,
+    format: &str,
+    lang: Option<&str>,
+    baseline: Option<&str>,
+    no_cache: bool,
+) -> anyhow::Result<bool> {
+    let project_path = Path::new(path);
+    // Read the baseline first: this run replaces the recorded one.
+    let before = match baseline {
+        Some(file) => {
+            let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read {file}"))?;
+            Some(serde_json::from_str(&text).with_context(|| format!("{file} is not a `--format json` report"))?)
+        }
+        None => warp_analyzer::cache::last_report(warp_analyzer::project_dir(project_path))?,
+    };
+    let report = run_analysis(project_path, lang, no_cache)?;
+    let diff = warp_analyzer::diff::diff(before.as_ref(), &report);
+
+    match format {
+        "json" | "yaml" | "text" => output::print(format, &diff, || warp_analyzer::diff::format_diff(&diff))?,
+        other => bail!("--diff supports text, json, or yaml output, not {other}"),
+    }
+    Ok(diff.has_regressions())
+}
+
+fn run_analysis(path: &Path, lang: Option<&str>, no_cache: bool) -> anyhow::Result<warp_core::AnalysisReport> {
+    if no_cache {
+        warp_analyzer::analyze(path, lang)
+    } else {
+        warp_analyzer::analyze_cached(path, lang)
+    }
+}
+
+pub fn fix(path: &str, format: &str, lang: Option<&str>, apply: bool) -> anyhow::Result<()> {
+    let report = warp_analyzer::fix(Path::new(path), lang, apply)?;
+
+    output::print(format, &report, || {
+        let mut text = String::new();
+        if report.fixes.is_empty() {
+            text.push_str("No dependencies with a drop-in alternative were found.\n");
+        }
+        text + &warp_analyzer::report::format_report(&report) + "\n"
+    })
+}
+
 pub fn init(path: &str) -> anyhow::Result<()> {
     let project_path = Path::new(path);
     let report = warp_analyzer::analyze(project_path, None)?;
@@ -29,3 +88,44 @@ pub fn init(path: &str) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Fetch the compat-db document at `url` and its `<url>.sig`, verify it
+/// against `public_key`, and cache it for every later analysis.
+pub fn db_update(url: Option<&str>, public_key: Option<&str>) -> anyhow::Result<()> {
+    let Some(url) = api::setting(url, "WARP_COMPAT_DB_URL")? else {
+        bail!("no compat-db URL; pass --url, set WARP_COMPAT_DB_URL, or set compat_db_url in ~/.warp/config.toml");
+    };
+    let Some(public_key) = api::setting(public_key, "WARP_COMPAT_DB_KEY")? else {
+        bail!("no compat-db public key; pass --public-key, set WARP_COMPAT_DB_KEY, or set compat_db_key in ~/.warp/config.toml");
+    };
+    let public_key = sync::parse_public_key(&public_key)?;
+    let dir = sync::sync_dir().context("HOME is not set; cannot locate ~/.warp/compat-sync")?;
+
+    let document = api::download(&url).with_context(|| format!("Failed to fetch {url}"))?;
+    let signature = api::download(&format!("{url}.sig")).with_context(|| format!("Failed to fetch {url}.sig"))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    match sync::install(&dir, &url, &document, &signature, &public_key, now)? {
+        SyncOutcome::Updated(state) => {
+            println!("✓ Updated compat-db: {} entries ({})", state.entries, state.digest);
+        }
+        SyncOutcome::Unchanged(state) => {
+            println!("Compat-db is up to date: {} entries ({})", state.entries, state.digest);
+        }
+    }
+    Ok(())
+}
+
+/// Show what the last `warp convert db update` installed.
+pub fn db_status(format: &str) -> anyhow::Result<()> {
+    let state = match sync::sync_dir() {
+        Some(dir) => sync::status(&dir)?,
+        None => None,
+    };
+    output::print(format, &state, || match &state {
+        Some(state) => format!(
+            "Source:     {}\nDigest:     {}\nEntries:    {}\nFetched at: {} (Unix time)\n",
+            state.url, state.digest, state.entries, state.fetched_at
+        ),
+        None => "No compat-db update installed; using the built-in rules.\n".to_string(),
+    })
+}
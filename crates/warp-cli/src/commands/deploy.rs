@@ -0,0 +1,385 @@
+//! `warp deploy` — pack a project and run it on a cluster.
+//!
+//! 1. Pack the project, unless `--artifact` names an already packed one.
+//! 2. Build the deployment spec from `warp.toml`: `[runtime]` trigger,
+//!    instance bounds, resources and scaling, `[health]`, `[shims]`, and
+//!    `[env]`. Redeploying keeps the fields only the API sets (labels,
+//!    priority, disruption budget, paused) and the creation time.
+//! 3. Check the spec against `GET /api/v1/capabilities` (skipped when the
+//!    daemon predates it): every shim it enables must be enabled on the
+//!    cluster, and its memory limit must fit on the largest node.
+//! 4. `POST /api/v1/deployments` creates or replaces the spec.
+//! 5. Poll `GET /api/v1/deployments/:id/health` until the minimum number
+//!    of instances is available, printing progress as it changes.
+//!
+//! The spec's source is the artifact's absolute `file://` path, so the
+//! daemon has to be able to read it (same host or a shared volume).
+//! `--source` records another URI instead, such as an `oci://` reference
+//! the artifact was pushed to.
+
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use warp_core::WarpConfig;
+
+use crate::api::{ApiClient, path_segment};
+
+/// Memory limit when `[runtime.resources].memory_limit` is unset.
+pub(crate) const DEFAULT_MEMORY_BYTES: u64 = 128 * 1024 * 1024;
+
+/// CPU weight when `[runtime.resources].cpu_weight` is unset.
+pub(crate) const DEFAULT_CPU_WEIGHT: u32 = 100;
+
+/// How often to check on instances while waiting.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct DeployOptions<'a> {
+    /// Project directory holding `warp.toml`.
+    pub path: &'a str,
+    /// Deploy this artifact instead of packing.
+    pub artifact: Option<&'a str>,
+    /// Source URI to record instead of the artifact's `file://` path.
+    pub source: Option<&'a str>,
+    pub namespace: &'a str,
+    /// Give up waiting for instances after this long (`None`: don't wait).
+    pub wait: Option<Duration>,
+}
+
+/// The part of `/deployments/:id/health` progress is reported from.
+#[derive(Debug, Deserialize)]
+struct Health {
+    status: String,
+    available: u32,
+    desired: u32,
+    #[serde(default)]
+    reasons: Vec<Reason>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reason {
+    message: String,
+}
+
+pub fn deploy(client: &ApiClient, options: &DeployOptions) -> anyhow::Result<()> {
+    let project = Path::new(options.path);
+    let config = WarpConfig::from_file(&project.join("warp.toml"))
+        .with_context(|| format!("Cannot read {}", project.join("warp.toml").display()))?;
+
+    let artifact = match options.artifact {
+        Some(artifact) => artifact.to_string(),
+        None => {
+            let result = warp_pack::pack_with_options(project, &warp_pack::PackOptions::default())
+                .context("Pack failed")?;
+            super::pack::print_result(&result);
+            result.output_path
+        }
+    };
+    let source = match options.source {
+        Some(source) => source.to_string(),
+        None => {
+            let path = Path::new(&artifact)
+                .canonicalize()
+                .with_context(|| format!("Artifact {artifact} not found"))?;
+            format!("file://{}", path.display())
+        }
+    };
+
+    let id = format!("{}/{}", options.namespace, config.package.name);
+    let existing: Option<Value> = client.get_optional(&format!("/deployments/{}", path_segment(&id)))?;
+    let verb = if existing.is_some() { "Updating" } else { "Creating" };
+    let spec = deployment_spec(&config, options.namespace, &source, existing, epoch_secs())?;
+    if let Some(capabilities) = client.get_optional::<Value>("/capabilities")? {
+        let problems = preflight(&spec, &capabilities);
+        if !problems.is_empty() {
+            bail!("{id} cannot run on {}:\n  {}", client.url(), problems.join("\n  "));
+        }
+    }
+    println!("{verb} deployment {id} on {}", client.url());
+    println!("  Source: {source}");
+    let _: Value = client.post("/deployments", &spec)?;
+
+    match options.wait {
+        Some(timeout) => wait_until_ready(client, &id, timeout),
+        None => {
+            println!("Deployed {id} (not waiting for instances)");
+            Ok(())
+        }
+    }
+}
+
+/// The `DeploymentSpec` JSON for `config`, on top of the deployment's
+/// current spec when there is one.
+pub(crate) fn deployment_spec(
+    config: &WarpConfig,
+    namespace: &str,
+    source: &str,
+    existing: Option<Value>,
+    now: u64,
+) -> anyhow::Result<Value> {
+    let name = &config.package.name;
+    let runtime = config.runtime.as_ref();
+    let trigger = match runtime.and_then(|r| r.trigger.as_deref()).unwrap_or("http") {
+        "http" => {
+            // Keep the routing set through the API (hosts, path prefix, port).
+            match existing.as_ref().map(|spec| &spec["trigger"]) {
+                Some(trigger) if trigger["type"] == "http" => trigger.clone(),
+                _ => json!({"type": "http", "port": null}),
+            }
+        }
+        other => bail!("warp deploy supports the http trigger only; warp.toml sets trigger = \"{other}\""),
+    };
+    let min = runtime.and_then(|r| r.min_instances).unwrap_or(1);
+    let max = runtime.and_then(|r| r.max_instances).unwrap_or(min).max(min);
+    let resources = runtime.and_then(|r| r.resources.as_ref());
+    let memory_bytes = match resources.and_then(|r| r.memory_limit.as_deref()) {
+        Some(limit) => parse_memory(limit)?,
+        None => DEFAULT_MEMORY_BYTES,
+    };
+    let cpu_weight = resources.and_then(|r| r.cpu_weight).unwrap_or(DEFAULT_CPU_WEIGHT);
+    let scaling = runtime.and_then(|r| r.scaling.as_ref()).map(|s| {
+        json!({
+            "metric": s.metric.as_deref().unwrap_or("rps"),
+            "target_value": s.target_value.unwrap_or(100) as f64,
+            "scale_up_window": s.scale_up_window.as_deref().unwrap_or("30s"),
+            "scale_down_window": s.scale_down_window.as_deref().unwrap_or("5m"),
+        })
+    });
+    let health = config.health.as_ref().and_then(|h| h.endpoint.as_ref().map(|endpoint| {
+        json!({
+            "endpoint": endpoint,
+            "interval": h.interval.as_deref().unwrap_or("5s"),
+            "timeout": h.timeout.as_deref().unwrap_or("2s"),
+            "unhealthy_threshold": h.unhealthy_threshold.unwrap_or(3),
+        })
+    }));
+    let shims = config.shims.clone().unwrap_or_default();
+    let on = |flag: Option<bool>| flag.unwrap_or(false);
+
+    let mut spec = existing.unwrap_or_else(|| json!({"created_at": now}));
+    let fields = json!({
+        "id": format!("{namespace}/{name}"),
+        "namespace": namespace,
+        "name": name,
+        "source": source,
+        "trigger": trigger,
+        "instances": {"min": min, "max": max},
+        "resources": {
+            "memory_bytes": memory_bytes,
+            "cpu_weight": cpu_weight,
+            "execution_budget_ms": spec["resources"]["execution_budget_ms"].clone(),
+        },
+        "scaling": scaling,
+        "health": health,
+        "shims": {
+            "timezone": on(shims.timezone),
+            "dev_urandom": on(shims.dev_urandom),
+            "dns": on(shims.dns),
+            "signals": on(shims.signals),
+            "database_proxy": on(shims.database_proxy),
+        },
+        "env": config.env.clone().unwrap_or_default(),
+        "updated_at": now,
+    });
+    let object = spec.as_object_mut().context("the current deployment spec is not an object")?;
+    for (key, value) in fields.as_object().expect("built above") {
+        object.insert(key.clone(), value.clone());
+    }
+    Ok(spec)
+}
+
+/// Reasons `spec` cannot run on a cluster with `capabilities`.
+pub(crate) fn preflight(spec: &Value, capabilities: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    // `[shims]` keys and the interfaces that serve them.
+    let interfaces = [
+        ("timezone", "filesystem"),
+        ("dev_urandom", "filesystem"),
+        ("dns", "dns"),
+        ("signals", "signals"),
+        ("database_proxy", "database-proxy"),
+    ];
+    let shims = capabilities["shims"].as_array().map(Vec::as_slice).unwrap_or_default();
+    for (key, interface) in interfaces {
+        if spec["shims"][key] != true {
+            continue;
+        }
+        let name = format!("warpgrid:shim/{interface}");
+        match shims.iter().find(|shim| shim["name"] == name.as_str()) {
+            Some(shim) if shim["enabled"] == true => {}
+            Some(_) => problems.push(format!("[shims] {key} needs {name}, which the cluster has disabled")),
+            None => problems.push(format!("[shims] {key} needs {name}, which the cluster does not offer")),
+        }
+    }
+    let memory = spec["resources"]["memory_bytes"].as_u64().unwrap_or_default();
+    if let Some(max) = capabilities["limits"]["max_instance_memory_bytes"].as_u64()
+        && memory > max
+    {
+        problems.push(format!(
+            "memory_limit is {} MiB but the largest node has {} MiB",
+            memory >> 20,
+            max >> 20
+        ));
+    }
+    problems
+}
+
+/// `128MB`, `64MiB`, `1G`, `512k`, or a plain byte count. Units are
+/// binary: `MB` and `MiB` both mean 2^20 bytes.
+pub(crate) fn parse_memory(limit: &str) -> anyhow::Result<u64> {
+    let limit = limit.trim();
+    let digits = limit.find(|c: char| !c.is_ascii_digit()).unwrap_or(limit.len());
+    let (number, unit) = limit.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid memory_limit '{limit}': expected e.g. \"128MB\""))?;
+    let shift = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 10,
+        "m" | "mb" | "mib" => 20,
+        "g" | "gb" | "gib" => 30,
+        other => bail!("Invalid memory_limit '{limit}': unknown unit '{other}'"),
+    };
+    number
+        .checked_mul(1 << shift)
+        .with_context(|| format!("memory_limit '{limit}' is too large"))
+}
+
+/// Poll the deployment's health until enough instances are available.
+fn wait_until_ready(client: &ApiClient, id: &str, timeout: Duration) -> anyhow::Result<()> {
+    let path = format!("/deployments/{}/health", path_segment(id));
+    let started = Instant::now();
+    let mut last = None;
+    loop {
+        let health: Health = client.get(&path)?;
+        let progress = (health.available, health.desired, health.status.clone());
+        if last.as_ref() != Some(&progress) {
+            println!("  {}/{} instances ready ({})", health.available, health.desired, health.status);
+            last = Some(progress);
+        }
+        if health.available >= health.desired && health.status != "unhealthy" {
+            println!("Deployed {id} in {:.1}s", started.elapsed().as_secs_f64());
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            let reasons: Vec<&str> = health.reasons.iter().map(|r| r.message.as_str()).collect();
+            bail!(
+                "{id} is not ready after {}s: {}",
+                timeout.as_secs(),
+                if reasons.is_empty() { health.status.clone() } else { reasons.join("; ") }
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub(crate) fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> WarpConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_spec_from_warp_toml() {
+        let config = config(
+            r#"
+[package]
+name = "api"
+version = "1.0.0"
+
+[runtime]
+trigger = "http"
+min_instances = 2
+max_instances = 8
+
+[runtime.resources]
+memory_limit = "64MiB"
+
+[health]
+endpoint = "/healthz"
+
+[shims]
+dns = true
+
+[env]
+RUST_LOG = "info"
+"#,
+        );
+        let spec = deployment_spec(&config, "prod", "file:///srv/api.wasm", None, 42).unwrap();
+        assert_eq!(spec["id"], "prod/api");
+        assert_eq!(spec["trigger"]["type"], "http");
+        assert_eq!(spec["instances"], json!({"min": 2, "max": 8}));
+        assert_eq!(spec["resources"]["memory_bytes"], 64 * 1024 * 1024);
+        assert_eq!(spec["resources"]["cpu_weight"], DEFAULT_CPU_WEIGHT);
+        assert_eq!(spec["health"]["interval"], "5s");
+        assert_eq!(spec["shims"]["dns"], true);
+        assert_eq!(spec["shims"]["database_proxy"], false);
+        assert_eq!(spec["env"]["RUST_LOG"], "info");
+        assert_eq!((spec["created_at"].as_u64(), spec["updated_at"].as_u64()), (Some(42), Some(42)));
+    }
+
+    #[test]
+    fn test_redeploy_keeps_api_fields() {
+        let config = config("[package]\nname = \"api\"\nversion = \"1.0.0\"\n");
+        let existing = json!({
+            "id": "default/api",
+            "created_at": 7,
+            "labels": {"team": "payments"},
+            "trigger": {"type": "http", "port": null, "hosts": ["api.example.com"]},
+            "resources": {"memory_bytes": 1, "cpu_weight": 1, "execution_budget_ms": 250},
+        });
+        let spec = deployment_spec(&config, "default", "oci://reg/api:1", Some(existing), 99).unwrap();
+        assert_eq!(spec["created_at"], 7);
+        assert_eq!(spec["updated_at"], 99);
+        assert_eq!(spec["labels"]["team"], "payments");
+        assert_eq!(spec["trigger"]["hosts"][0], "api.example.com");
+        assert_eq!(spec["resources"]["execution_budget_ms"], 250);
+        assert_eq!(spec["resources"]["memory_bytes"], DEFAULT_MEMORY_BYTES);
+        assert_eq!(spec["instances"], json!({"min": 1, "max": 1}));
+
+        let cron = self::config("[package]\nname = \"job\"\nversion = \"1\"\n[runtime]\ntrigger = \"cron\"\n");
+        assert!(deployment_spec(&cron, "default", "file:///x", None, 0).is_err());
+    }
+
+    #[test]
+    fn test_preflight_against_capabilities() {
+        let config = config("[package]\nname = \"api\"\nversion = \"1\"\n[shims]\ndns = true\ndatabase_proxy = true\n");
+        let spec = deployment_spec(&config, "default", "file:///x", None, 0).unwrap();
+        let capabilities = json!({
+            "shims": [
+                {"name": "warpgrid:shim/dns", "version": "0.1.0", "enabled": true},
+                {"name": "warpgrid:shim/database-proxy", "version": "0.1.0", "enabled": false},
+            ],
+            "limits": {"max_instance_memory_bytes": 64 * 1024 * 1024},
+        });
+        let problems = preflight(&spec, &capabilities);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("database-proxy, which the cluster has disabled"));
+        assert!(problems[1].contains("128 MiB but the largest node has 64 MiB"));
+
+        assert!(preflight(&spec, &json!({"shims": [
+            {"name": "warpgrid:shim/dns", "enabled": true},
+            {"name": "warpgrid:shim/database-proxy", "enabled": true},
+        ]})).is_empty());
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("128MB").unwrap(), 128 << 20);
+        assert_eq!(parse_memory("1 GiB").unwrap(), 1 << 30);
+        assert_eq!(parse_memory("512k").unwrap(), 512 << 10);
+        assert_eq!(parse_memory("4096").unwrap(), 4096);
+        assert!(parse_memory("lots").is_err());
+        assert!(parse_memory("3TB").is_err());
+    }
+}
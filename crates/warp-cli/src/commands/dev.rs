@@ -1,37 +1,165 @@
-//! `warp dev` — Local development server with file watching and hot-reload.
+//! `warp dev` — local development server with hot reload.
 //!
-//! Dispatches to language-specific dev servers:
-//! - `--lang bun`: Spawns Bun dev server from `@warpgrid/bun-sdk`
+//! Packs the project, compiles the component into an embedded runtime,
+//! and serves its HTTP trigger on `127.0.0.1:<port>` — no warpd needed.
+//! The source tree is then watched like `warp pack --watch`; every rebuild
+//! that changes the artifact is compiled and swapped in behind the same
+//! listener, so in-flight requests finish on the old component and the
+//! next ones reach the new one. A rebuild that fails to pack or compile is
+//! reported and the previous component keeps serving.
 //!
-//! The Bun dev server supports two modes:
-//! - Default (Wasm): Compiles handler, serves via jco serve, watches and recompiles
-//! - `--native`: Runs handler directly in Bun with hot-reload
+//! The deployment spec comes from `warp.toml` exactly as `warp deploy`
+//! builds it (`default/<package name>`, env, limits), except that the
+//! route answers on any host so `localhost` reaches it.
+//!
+//! `--native` (Bun projects only) instead runs the handler directly in Bun
+//! through the `@warpgrid/bun-sdk` dev server, with Bun's own hot reload.
 
+use std::net::{Ipv4Addr, SocketAddr};
+use std::ops::ControlFlow;
 use std::path::Path;
 use std::process::Command;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{bail, Context, Result};
 use tracing::info;
 use warp_core::WarpConfig;
+use warp_runtime::{Runtime, ShimConfig};
+use warpgrid_state::DeploymentSpec;
+use warpgrid_trigger::{IngressRoute, IngressRouter, IngressServer, ResponseLimits};
+
+/// Namespace of the deployment `warp dev` serves.
+const DEV_NAMESPACE: &str = "default";
+
+pub struct DevOptions<'a> {
+    /// Project directory holding `warp.toml`.
+    pub path: &'a str,
+    pub port: u16,
+    /// Override the build language.
+    pub lang: Option<&'a str>,
+    /// Run a Bun handler natively instead of as a component.
+    pub native: bool,
+    pub no_cache: bool,
+}
 
 /// Run the `warp dev` command.
-///
-/// Reads `warp.toml` to detect language, then spawns the appropriate dev server.
-pub fn dev(path: &str, port: u16, native: bool) -> Result<()> {
-    let project_path = Path::new(path).canonicalize().unwrap_or_else(|_| Path::new(path).to_path_buf());
+pub fn dev(options: &DevOptions) -> Result<()> {
+    let project_path = Path::new(options.path)
+        .canonicalize()
+        .with_context(|| format!("Project directory {} not found", options.path))?;
+
+    if options.native {
+        let lang = match options.lang {
+            Some(lang) => lang.to_string(),
+            None => detect_language(&project_path)?,
+        };
+        if lang != "bun" {
+            bail!("--native runs Bun handlers only; this is a {lang} project");
+        }
+        info!("Starting native Bun dev server for {}", project_path.display());
+        return dev_bun(&project_path, options.port);
+    }
+    serve(&project_path, options)
+}
 
-    // Try to read warp.toml for language detection
-    let lang = detect_language(&project_path)?;
+/// Serve the project's component from an embedded runtime, repacking and
+/// swapping it on every source change.
+fn serve(project_path: &Path, options: &DevOptions) -> Result<()> {
+    let config = WarpConfig::from_file(&project_path.join("warp.toml"))
+        .with_context(|| format!("Cannot read {}", project_path.join("warp.toml").display()))?;
+    let spec = dev_spec(&config)?;
+
+    let tokio = tokio::runtime::Runtime::new()?;
+    let router = IngressRouter::new();
+    router.set_routes(vec![dev_route(&spec)?]);
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, options.port));
+    // Bind up front so a taken port fails before the first build.
+    std::net::TcpListener::bind(addr).with_context(|| format!("Cannot listen on {addr}"))?;
+    let (_shutdown, shutdown_rx) = tokio::sync::watch::channel(false);
+    let server = tokio.spawn(IngressServer::new(vec![addr], router.clone()).serve(shutdown_rx));
+    // Metered, so the access log shows each request's guest CPU time.
+    let runtime = Runtime::new_metered(ShimConfig::default(), warp_runtime::usage::DEFAULT_EPOCH_TICK)?;
+
+    let pack_options = warp_pack::PackOptions {
+        lang: options.lang.map(String::from),
+        no_cache: options.no_cache,
+    };
+    let mut serving: Option<String> = None;
+    warp_pack::watch::watch(
+        project_path,
+        &pack_options,
+        warp_pack::watch::DEFAULT_DEBOUNCE,
+        |result| {
+            if server.is_finished() {
+                return ControlFlow::Break(());
+            }
+            match result {
+                Ok(result) if serving.as_deref() == Some(result.sha256.as_str()) => {
+                    println!("No changes to the component");
+                }
+                Ok(result) => {
+                    let started = Instant::now();
+                    match tokio.block_on(swap(&runtime, &router, &spec, &result.output_path)) {
+                        Ok(()) => {
+                            let verb = if serving.is_some() { "Reloaded" } else { "Serving" };
+                            println!(
+                                "{verb} {} on http://{addr} ({:.1} MB, compiled in {} ms)",
+                                spec.id,
+                                result.size_bytes as f64 / 1_048_576.0,
+                                started.elapsed().as_millis()
+                            );
+                            serving = Some(result.sha256);
+                        }
+                        Err(e) => eprintln!("Cannot load {}: {e:#}", result.output_path),
+                    }
+                }
+                Err(e) => eprintln!("Pack failed: {e:#}"),
+            }
+            if serving.is_some() {
+                println!("Watching for changes...");
+            }
+            ControlFlow::Continue(())
+        },
+    )?;
+    // The watch only stops early when the listener went away.
+    match tokio.block_on(server) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(e.context(format!("Dev server on {addr} stopped"))),
+        Err(e) => bail!("Dev server on {addr} stopped: {e}"),
+    }
+}
 
-    info!("Starting dev server for {} project at {}", lang, project_path.display());
+/// Compile `artifact` and make it the handler for `spec`'s route. The
+/// previous handler keeps serving if this fails.
+async fn swap(runtime: &Runtime, router: &IngressRouter, spec: &DeploymentSpec, artifact: &str) -> Result<()> {
+    // Release the previous compile; its handler holds its own reference.
+    runtime.unload_module(&spec.name).await;
+    let module = runtime.load_module_from_file(&spec.name, artifact).await?;
+    let handler = warpgrid_trigger::component::component_handler(
+        runtime.engine(),
+        module.component(),
+        spec,
+        Default::default(),
+        ResponseLimits::default(),
+        None,
+    )?;
+    router.register(&spec.id, handler);
+    Ok(())
+}
 
-    match lang.as_str() {
-        "bun" => dev_bun(&project_path, port, native),
-        "rust" => bail!("Rust dev server not yet implemented."),
-        "go" => bail!("Go dev server not yet implemented."),
-        "typescript" => bail!("TypeScript dev server not yet implemented. Use --lang bun for Bun."),
-        _ => bail!("Unsupported language for dev server: {lang}"),
-    }
+/// The spec `warp deploy` would create for this project.
+fn dev_spec(config: &WarpConfig) -> Result<DeploymentSpec> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let spec = super::deploy::deployment_spec(config, DEV_NAMESPACE, "file://dist", None, now)?;
+    serde_json::from_value(spec).context("Invalid deployment spec")
+}
+
+/// `spec`'s route, opened up to any host and listener port.
+fn dev_route(spec: &DeploymentSpec) -> Result<IngressRoute> {
+    let mut route = IngressRoute::from_spec(spec).context("warp dev serves HTTP-triggered components only")?;
+    route.port = None;
+    route.hosts.clear();
+    Ok(route)
 }
 
 /// Detect language from warp.toml [build].lang field.
@@ -64,11 +192,11 @@ fn detect_language(project_path: &Path) -> Result<String> {
     )
 }
 
-/// Launch the Bun dev server.
+/// Launch the native Bun dev server.
 ///
 /// Resolves the `dev-cli.ts` entry point from the `@warpgrid/bun-sdk` package
 /// and spawns it as a child process.
-fn dev_bun(project_path: &Path, port: u16, native: bool) -> Result<()> {
+fn dev_bun(project_path: &Path, port: u16) -> Result<()> {
     let dev_cli = resolve_dev_cli(project_path)?;
 
     info!("Using dev CLI: {}", dev_cli.display());
@@ -78,11 +206,8 @@ fn dev_bun(project_path: &Path, port: u16, native: bool) -> Result<()> {
         .arg(&dev_cli)
         .arg("--port")
         .arg(port.to_string())
-        .arg(project_path);
-
-    if native {
-        cmd.arg("--native");
-    }
+        .arg(project_path)
+        .arg("--native");
 
     // Inherit stdio so the user sees the dev server output
     let status = cmd
@@ -193,6 +318,40 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_dev_spec_routes_any_host() {
+        let config: WarpConfig = toml::from_str(
+            "[package]\nname = \"api\"\nversion = \"0.1.0\"\n\n[env]\nMODE = \"dev\"\n",
+        )
+        .unwrap();
+        let mut spec = dev_spec(&config).unwrap();
+        assert_eq!(spec.id, "default/api");
+        assert_eq!(spec.env["MODE"], "dev");
+
+        spec.trigger = warpgrid_state::TriggerConfig::Http {
+            port: Some(8080),
+            hosts: vec!["api.example.com".to_string()],
+            path_prefix: Some("/api".to_string()),
+        };
+        let route = dev_route(&spec).unwrap();
+        assert_eq!((route.port, route.hosts.len(), route.path_prefix.as_str()), (None, 0, "/api"));
+    }
+
+    #[test]
+    fn test_native_requires_bun() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("go.mod"), "module test\n").unwrap();
+        let options = DevOptions {
+            path: dir.path().to_str().unwrap(),
+            port: 0,
+            lang: None,
+            native: true,
+            no_cache: false,
+        };
+        let err = dev(&options).unwrap_err();
+        assert!(err.to_string().contains("Bun handlers only"), "{err}");
+    }
+
     #[test]
     fn test_resolve_dev_cli_not_found() {
         let dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,60 @@
+//! `warp exec` — call a component export directly.
+//!
+//! Posts the export name and its arguments to
+//! `POST /api/v1/deployments/:id/exec`, which runs the call on one of the
+//! deployment's pooled instances. Exports are named `function` for a
+//! top-level function or `interface#function` for one in an exported
+//! interface; `--json` takes the arguments as a JSON array. The result is
+//! printed as JSON (`null` when the function returns nothing).
+
+use anyhow::{Context, bail};
+use serde_json::{Value, json};
+
+use crate::api::{ApiClient, path_segment};
+use crate::output;
+
+pub struct ExecOptions<'a> {
+    /// Deployment id (`namespace/name`).
+    pub deployment: &'a str,
+    pub export: &'a str,
+    /// Arguments as a JSON array.
+    pub args: Option<&'a str>,
+    /// `text` prints the result; `json` and `yaml` the full response with
+    /// timing.
+    pub format: &'a str,
+}
+
+pub fn exec(client: &ApiClient, options: &ExecOptions) -> anyhow::Result<()> {
+    let body = json!({"export": options.export, "args": parse_args(options.args)?});
+    let response: Value = client.post(&format!("/deployments/{}/exec", path_segment(options.deployment)), &body)?;
+    let result = serde_json::to_string_pretty(&response["result"])?;
+    output::print(options.format, &response, || result + "\n")
+}
+
+/// `--json` as the argument list.
+fn parse_args(args: Option<&str>) -> anyhow::Result<Vec<Value>> {
+    let Some(args) = args else {
+        return Ok(Vec::new());
+    };
+    match serde_json::from_str(args).context("--json is not valid JSON")? {
+        Value::Array(args) => Ok(args),
+        other => bail!("--json must be an array of arguments, e.g. '[{other}]'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(None).unwrap(), Vec::<Value>::new());
+        assert_eq!(
+            parse_args(Some(r#"[1, "two", {"three": [3]}]"#)).unwrap(),
+            vec![json!(1), json!("two"), json!({"three": [3]})]
+        );
+        let err = parse_args(Some("42")).unwrap_err();
+        assert_eq!(err.to_string(), "--json must be an array of arguments, e.g. '[42]'");
+        assert!(parse_args(Some("[1,")).is_err());
+    }
+}
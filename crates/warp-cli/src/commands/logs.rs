@@ -0,0 +1,179 @@
+//! `warp logs` — print a deployment's captured guest output.
+//!
+//! Reads `GET /api/v1/deployments/:id/logs`, or with `--follow` its
+//! `follow=true` event stream, reconnecting from the last line seen if
+//! the connection drops. Lines the guest wrote to stderr go to stderr.
+//! `--grep` filters on the client with a regular expression.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, bail};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::api::{ApiClient, path_segment};
+
+/// Wait before reopening a dropped `--follow` stream.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub struct LogsOptions<'a> {
+    /// Deployment id (`namespace/name`).
+    pub deployment: &'a str,
+    pub follow: bool,
+    /// Only lines from this far back.
+    pub since: Option<Duration>,
+    pub instance: Option<&'a str>,
+    /// Only lines matching this pattern.
+    pub grep: Option<&'a str>,
+    /// The last this many lines (before `--grep`).
+    pub tail: Option<usize>,
+}
+
+/// One captured line (see warpgrid-metrics `guest_logs`).
+#[derive(Debug, Deserialize)]
+struct LogLine {
+    seq: u64,
+    timestamp_ms: u64,
+    instance: String,
+    stream: String,
+    line: String,
+}
+
+pub fn logs(client: &ApiClient, options: &LogsOptions) -> anyhow::Result<()> {
+    let grep = options
+        .grep
+        .map(Regex::new)
+        .transpose()
+        .context("Invalid --grep pattern")?;
+    let base = format!("/deployments/{}/logs", path_segment(options.deployment));
+    let since_ms = options.since.map(|since| epoch_ms().saturating_sub(since.as_millis() as u64));
+    let print = |line: &LogLine| {
+        if grep.as_ref().is_none_or(|grep| grep.is_match(&line.line)) {
+            print_line(line);
+        }
+    };
+
+    if !options.follow {
+        let query = query_string(false, since_ms, None, options.instance, options.tail);
+        let lines: Vec<LogLine> = client.get(&format!("{base}{query}"))?;
+        lines.iter().for_each(print);
+        return Ok(());
+    }
+
+    let mut after = None;
+    loop {
+        // The backlog limit only applies to the first connection.
+        let tail = if after.is_none() { options.tail } else { None };
+        let query = query_string(true, since_ms, after, options.instance, tail);
+        for event in client.events(&format!("{base}{query}"))? {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("warp logs: {e:#}; reconnecting");
+                    break;
+                }
+            };
+            if event.name != "log" {
+                continue;
+            }
+            let line: LogLine = serde_json::from_str(&event.data).context("Unreadable log event")?;
+            after = Some(line.seq);
+            print(&line);
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+fn query_string(
+    follow: bool,
+    since_ms: Option<u64>,
+    after: Option<u64>,
+    instance: Option<&str>,
+    tail: Option<usize>,
+) -> String {
+    let mut params = Vec::new();
+    if follow {
+        params.push("follow=true".to_string());
+    }
+    if let Some(since_ms) = since_ms {
+        params.push(format!("since_ms={since_ms}"));
+    }
+    if let Some(after) = after {
+        params.push(format!("after={after}"));
+    }
+    if let Some(instance) = instance {
+        params.push(format!("instance={}", path_segment(instance)));
+    }
+    if let Some(tail) = tail {
+        params.push(format!("limit={tail}"));
+    }
+    if params.is_empty() { String::new() } else { format!("?{}", params.join("&")) }
+}
+
+/// `HH:MM:SS.mmm instance line`, to stdout or stderr like the guest wrote it.
+fn print_line(line: &LogLine) {
+    let out = format!("{} {} {}", clock(line.timestamp_ms), line.instance, line.line);
+    if line.stream == "stderr" {
+        eprintln!("{out}");
+    } else {
+        println!("{out}");
+    }
+}
+
+/// UTC time of day of a Unix timestamp in milliseconds.
+pub(crate) fn clock(timestamp_ms: u64) -> String {
+    let ms = timestamp_ms % 1000;
+    let secs = timestamp_ms / 1000 % 86_400;
+    format!("{:02}:{:02}:{:02}.{ms:03}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// `--since` values: a number with an `s`, `m`, `h`, or `d` suffix.
+pub fn parse_since(value: &str) -> anyhow::Result<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration '{value}': expected e.g. 30s, 10m, 2h"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => bail!("Invalid duration '{value}': unit must be s, m, h, or d"),
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+fn epoch_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_since("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_since("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_since("1d").unwrap(), Duration::from_secs(86_400));
+        assert!(parse_since("10").is_err());
+        assert!(parse_since("m").is_err());
+        assert!(parse_since("5w").is_err());
+    }
+
+    #[test]
+    fn test_query_string_and_clock() {
+        assert_eq!(query_string(false, None, None, None, None), "");
+        assert_eq!(
+            query_string(false, Some(5), Some(9), Some("req-1"), Some(100)),
+            "?since_ms=5&after=9&instance=req-1&limit=100"
+        );
+        assert_eq!(query_string(true, None, Some(9), None, None), "?follow=true&after=9");
+        assert_eq!(clock(86_400_000 + 3_723_045), "01:02:03.045");
+    }
+}
@@ -1,4 +1,19 @@
+pub mod apply;
+pub mod bench;
+pub mod context;
 pub mod convert;
+pub mod deploy;
 pub mod dev;
+pub mod exec;
 pub mod init;
+pub mod logs;
+pub mod nodes;
 pub mod pack;
+pub mod plugin;
+pub mod port_forward;
+pub mod registry;
+pub mod rollout;
+pub mod scale;
+pub mod secrets;
+pub mod status;
+pub mod top;
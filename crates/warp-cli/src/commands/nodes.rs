@@ -0,0 +1,223 @@
+//! `warp nodes` — inspect nodes and take them out of rotation.
+//!
+//! `list` and `describe` read `GET /api/v1/nodes` and `/nodes/:id`.
+//! `cordon` stops new placements on a node and `uncordon` allows them
+//! again. `drain` cordons the node and evicts its instances, except ones a
+//! deployment's disruption budget (`min_available`) needs; `--force`
+//! evicts those too. `label` sets labels given as `key=value` and removes
+//! those given as `key-`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::bail;
+use serde_json::{Value, json};
+
+use super::status::{format_instances, table, text};
+use super::top::format_bytes;
+use crate::api::{ApiClient, path_segment};
+use crate::output;
+
+/// Heartbeats older than this mark a node as not ready in `list`.
+const STALE_HEARTBEAT_SECS: u64 = 30;
+
+pub fn list(client: &ApiClient, format: &str) -> anyhow::Result<()> {
+    let mut nodes: Vec<Value> = client.get("/nodes")?;
+    nodes.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    output::print(format, &nodes, || format_nodes(&nodes, epoch_secs()))
+}
+
+pub fn describe(client: &ApiClient, node: &str, format: &str) -> anyhow::Result<()> {
+    let detail: Value = client.get(&format!("/nodes/{}", path_segment(node)))?;
+    output::print(format, &detail, || format_detail(&detail, epoch_secs()))
+}
+
+pub fn cordon(client: &ApiClient, node: &str) -> anyhow::Result<()> {
+    let _: Value = client.post(&format!("/nodes/{}/cordon", path_segment(node)), &json!({}))?;
+    println!("Cordoned {node}: no new instances will be placed on it");
+    Ok(())
+}
+
+pub fn uncordon(client: &ApiClient, node: &str) -> anyhow::Result<()> {
+    let _: Value = client.post(&format!("/nodes/{}/uncordon", path_segment(node)), &json!({}))?;
+    println!("Uncordoned {node}");
+    Ok(())
+}
+
+pub fn drain(client: &ApiClient, node: &str, force: bool) -> anyhow::Result<()> {
+    let query = if force { "?force=true" } else { "" };
+    let report: Value = client.post(&format!("/nodes/{}/drain{query}", path_segment(node)), &json!({}))?;
+    let evicted = report["evicted"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let blocked = report["blocked"].as_array().map(Vec::as_slice).unwrap_or_default();
+    println!("Cordoned {node} and evicted {} instances", evicted.len());
+    if !evicted.is_empty() {
+        print!("{}", format_instances(evicted));
+    }
+    if !blocked.is_empty() {
+        print!("{}", format_instances(blocked));
+        bail!(
+            "{} instances on {node} were kept to honour disruption budgets; \
+             scale those deployments up and drain again, or use --force",
+            blocked.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn label(client: &ApiClient, node: &str, changes: &[String]) -> anyhow::Result<()> {
+    let patch = label_patch(changes)?;
+    let updated: Value = client.patch(&format!("/nodes/{}/labels", path_segment(node)), &patch)?;
+    let labels = updated["labels"].as_object().map(|labels| labels.len()).unwrap_or_default();
+    println!("Labelled {node}: {labels} labels");
+    Ok(())
+}
+
+/// The `PATCH /nodes/:id/labels` body for `key=value` and `key-` arguments.
+fn label_patch(changes: &[String]) -> anyhow::Result<Value> {
+    let mut patch = serde_json::Map::new();
+    for change in changes {
+        let (key, value) = match (change.split_once('='), change.strip_suffix('-')) {
+            (Some((key, value)), _) => (key, json!(value)),
+            (None, Some(key)) => (key, Value::Null),
+            (None, None) => bail!("'{change}' is neither key=value nor key-"),
+        };
+        if key.is_empty() {
+            bail!("'{change}' has no label key");
+        }
+        patch.insert(key.to_string(), value);
+    }
+    Ok(Value::Object(patch))
+}
+
+fn format_nodes(nodes: &[Value], now: u64) -> String {
+    if nodes.is_empty() {
+        return "No nodes\n".to_string();
+    }
+    let rows: Vec<[String; 5]> = nodes
+        .iter()
+        .map(|node| {
+            [
+                text(&node["id"]),
+                format!("{}:{}", text(&node["address"]), text(&node["port"])),
+                node_status(node, now),
+                usage(node, "memory_bytes", true),
+                usage(node, "cpu_weight", false),
+            ]
+        })
+        .collect();
+    table(["NODE", "ADDRESS", "STATUS", "MEMORY", "CPU"], &rows, "")
+}
+
+fn format_detail(detail: &Value, now: u64) -> String {
+    let node = &detail["node"];
+    let mut out = format!(
+        "{}  {}\n  Address:   {}:{}\n  Memory:    {}\n  CPU:       {}\n",
+        text(&node["id"]),
+        node_status(node, now),
+        text(&node["address"]),
+        text(&node["port"]),
+        usage(node, "memory_bytes", true),
+        usage(node, "cpu_weight", false),
+    );
+    out.push_str(&format!(
+        "  Heartbeat: {}s ago\n",
+        now.saturating_sub(node["last_heartbeat"].as_u64().unwrap_or_default())
+    ));
+    if let Some(labels) = node["labels"].as_object().filter(|labels| !labels.is_empty()) {
+        let mut labels: Vec<String> = labels.iter().map(|(k, v)| format!("{k}={}", text(v))).collect();
+        labels.sort();
+        out.push_str(&format!("  Labels:    {}\n", labels.join(", ")));
+    }
+    let instances = detail["instances"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if instances.is_empty() {
+        out.push_str("\n  No instances\n");
+    } else {
+        out.push('\n');
+        out.push_str(&format_instances(instances));
+    }
+    out
+}
+
+/// `ready`, `not-ready` (stale heartbeat), `pressure`, or `cordoned`,
+/// comma-joined when more than one applies.
+fn node_status(node: &Value, now: u64) -> String {
+    let mut status = Vec::new();
+    let heartbeat = node["last_heartbeat"].as_u64().unwrap_or_default();
+    status.push(if now.saturating_sub(heartbeat) > STALE_HEARTBEAT_SECS { "not-ready" } else { "ready" });
+    if node["pressure_until"].as_u64().is_some_and(|until| until > now) {
+        status.push("pressure");
+    }
+    if node["cordoned"] == true {
+        status.push("cordoned");
+    }
+    status.join(",")
+}
+
+/// `used/capacity`, against the calibrated CPU weight when there is one.
+fn usage(node: &Value, resource: &str, bytes: bool) -> String {
+    let used = node[format!("used_{resource}")].as_u64().unwrap_or_default();
+    let capacity = match resource {
+        "cpu_weight" => node["calibrated_cpu_weight"].as_u64(),
+        _ => None,
+    }
+    .or_else(|| node[format!("capacity_{resource}")].as_u64())
+    .unwrap_or_default();
+    if bytes {
+        format!("{}/{}", format_bytes(used), format_bytes(capacity))
+    } else {
+        format!("{used}/{capacity}")
+    }
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, heartbeat: u64, cordoned: bool) -> Value {
+        json!({
+            "id": id, "address": "10.0.0.1", "port": 9000,
+            "capacity_memory_bytes": 8u64 << 30, "used_memory_bytes": 512u64 << 20,
+            "capacity_cpu_weight": 1000, "used_cpu_weight": 250, "calibrated_cpu_weight": 1450,
+            "labels": {"zone": "a"}, "last_heartbeat": heartbeat, "cordoned": cordoned,
+        })
+    }
+
+    #[test]
+    fn test_format_nodes() {
+        let nodes = [node("node-a", 1000, false), node("node-b", 900, true)];
+        assert_eq!(
+            format_nodes(&nodes, 1010),
+            "NODE    ADDRESS        STATUS              MEMORY     CPU\n\
+             node-a  10.0.0.1:9000  ready               512M/8.0G  250/1450\n\
+             node-b  10.0.0.1:9000  not-ready,cordoned  512M/8.0G  250/1450\n"
+        );
+        assert_eq!(format_nodes(&[], 0), "No nodes\n");
+    }
+
+    #[test]
+    fn test_format_detail() {
+        let detail = json!({
+            "node": node("node-a", 1000, true),
+            "instances": [{"id": "inst-0", "node_id": "node-a", "status": "running", "health": "healthy", "restart_count": 0}],
+        });
+        let text = format_detail(&detail, 1005);
+        assert!(text.starts_with("node-a  ready,cordoned\n"), "{text}");
+        assert!(text.contains("  Heartbeat: 5s ago\n"), "{text}");
+        assert!(text.contains("  Labels:    zone=a\n"), "{text}");
+        assert!(text.contains("  inst-0    node-a  running"), "{text}");
+    }
+
+    #[test]
+    fn test_label_patch() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let patch = label_patch(&args(&["zone=eu-1", "gpu-", "note=a=b"])).unwrap();
+        assert_eq!(patch, json!({"zone": "eu-1", "gpu": null, "note": "a=b"}));
+        assert!(label_patch(&args(&["zone"])).is_err());
+        assert!(label_patch(&args(&["=x"])).is_err());
+    }
+}
@@ -1,12 +1,23 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::ops::ControlFlow;
 use std::path::Path;
+use std::time::Duration;
 
-pub fn pack(path: &str, lang: Option<&str>) -> anyhow::Result<()> {
+use anyhow::{Context, bail};
+
+pub fn pack(path: &str, lang: Option<&str>, no_cache: bool) -> anyhow::Result<()> {
     let project_path = Path::new(path);
-    match warp_pack::pack_with_lang(project_path, lang) {
+    let options = warp_pack::PackOptions {
+        lang: lang.map(String::from),
+        no_cache,
+    };
+    if is_workspace(project_path) {
+        return pack_workspace(project_path, &options);
+    }
+    match warp_pack::pack_with_options(project_path, &options) {
         Ok(result) => {
-            println!("Compiled to Wasm ({:.1} MB)", result.size_bytes as f64 / 1_048_576.0);
-            println!("  Output: {}", result.output_path);
-            println!("  SHA256: {}", result.sha256);
+            print_result(&result);
             Ok(())
         }
         Err(e) => {
@@ -15,3 +26,194 @@ pub fn pack(path: &str, lang: Option<&str>) -> anyhow::Result<()> {
         }
     }
 }
+
+/// `warp pack --from-dockerfile`: pack from the build a Dockerfile describes.
+pub fn pack_from_dockerfile(
+    path: &str,
+    dockerfile: &str,
+    lang: Option<&str>,
+    no_cache: bool,
+) -> anyhow::Result<()> {
+    let options = warp_pack::PackOptions {
+        lang: lang.map(String::from),
+        no_cache,
+    };
+    match warp_pack::dockerfile::pack_from_dockerfile(Path::new(path), Path::new(dockerfile), &options) {
+        Ok(result) => {
+            print_result(&result);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Pack failed: {e:#}");
+            Err(e)
+        }
+    }
+}
+
+/// Pack every component of a multi-component workspace.
+fn pack_workspace(root: &Path, options: &warp_pack::PackOptions) -> anyhow::Result<()> {
+    match warp_pack::workspace::pack_workspace(root, options) {
+        Ok(result) => {
+            for (name, component) in &result.components {
+                println!("[{name}]");
+                print_result(component);
+            }
+            println!(
+                "Packed {} components\n  Manifest: {}",
+                result.components.len(),
+                result.manifest_path
+            );
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Pack failed: {e:#}");
+            Err(e)
+        }
+    }
+}
+
+/// Whether `path` holds a workspace `warp.toml` (one with `[components]`).
+fn is_workspace(path: &Path) -> bool {
+    warp_core::WarpConfig::from_file(&path.join("warp.toml"))
+        .is_ok_and(|config| warp_pack::workspace::is_workspace(&config))
+}
+
+/// `warp pack --watch`: repack on every source change until interrupted.
+///
+/// With `notify`, each new artifact is POSTed to a running `warp dev`
+/// server (e.g. `http://localhost:3000/__warp/reload`) so it can swap the
+/// component in place.
+pub fn watch(
+    path: &str,
+    lang: Option<&str>,
+    no_cache: bool,
+    notify: Option<&str>,
+) -> anyhow::Result<()> {
+    if is_workspace(Path::new(path)) {
+        bail!("--watch packs a single component; run it in a component directory of the workspace");
+    }
+    let options = warp_pack::PackOptions {
+        lang: lang.map(String::from),
+        no_cache,
+    };
+    let mut last_sha = None;
+    warp_pack::watch::watch(
+        Path::new(path),
+        &options,
+        warp_pack::watch::DEFAULT_DEBOUNCE,
+        |result| {
+            match result {
+                Ok(result) => {
+                    print_result(&result);
+                    if let Some(url) = notify
+                        && last_sha.as_deref() != Some(result.sha256.as_str())
+                    {
+                        match notify_reload(url, &result) {
+                            Ok(()) => println!("  Reloaded: {url}"),
+                            Err(e) => eprintln!("  Reload notification failed: {e:#}"),
+                        }
+                    }
+                    last_sha = Some(result.sha256);
+                }
+                Err(e) => eprintln!("Pack failed: {e}"),
+            }
+            println!("Watching for changes...");
+            ControlFlow::Continue(())
+        },
+    )
+}
+
+pub(crate) fn print_result(result: &warp_pack::PackResult) {
+    if result.cached {
+        println!("Up to date (build cache hit, {:.1} MB)", result.size_bytes as f64 / 1_048_576.0);
+    } else {
+        println!("Compiled to Wasm ({:.1} MB)", result.size_bytes as f64 / 1_048_576.0);
+    }
+    println!("  Output: {}", result.output_path);
+    println!("  SHA256: {}", result.sha256);
+    if let Some(opt) = &result.optimization {
+        println!(
+            "  Optimized (-O{}): {:.1} MB -> {:.1} MB",
+            opt.level,
+            opt.size_before as f64 / 1_048_576.0,
+            opt.size_after as f64 / 1_048_576.0
+        );
+    }
+    if let Some(sbom) = &result.sbom {
+        println!("  SBOM: {sbom}");
+    }
+    if let Some(bundle) = &result.signature_bundle {
+        println!("  Signature: {bundle}");
+    }
+    if let Some(manifest) = &result.manifest {
+        println!("  Manifest: {manifest}");
+    }
+}
+
+/// Tell a `warp dev` server at `url` to load the freshly packed artifact.
+///
+/// The dev server is local, so a plain HTTP/1.1 request is all this needs.
+fn notify_reload(url: &str, result: &warp_pack::PackResult) -> anyhow::Result<()> {
+    let (authority, path) = split_http_url(url)?;
+    let artifact = Path::new(&result.output_path)
+        .canonicalize()
+        .unwrap_or_else(|_| result.output_path.clone().into());
+    let body = serde_json::json!({
+        "artifact": artifact,
+        "sha256": result.sha256,
+    })
+    .to_string();
+
+    let mut stream = TcpStream::connect(authority)
+        .with_context(|| format!("Cannot reach dev server at {authority}"))?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {authority}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        let status_line = response.lines().next().unwrap_or_default();
+        bail!("dev server answered '{status_line}'");
+    }
+    Ok(())
+}
+
+/// Split `http://host:port/path` into `("host:port", "/path")`.
+fn split_http_url(url: &str) -> anyhow::Result<(&str, &str)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        bail!("reload URL must start with http:// (got '{url}')");
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    if !authority.contains(':') {
+        bail!("reload URL must include a port (got '{url}')");
+    }
+    Ok((authority, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_http_url() {
+        assert_eq!(
+            split_http_url("http://localhost:3000/__warp/reload").unwrap(),
+            ("localhost:3000", "/__warp/reload")
+        );
+        assert_eq!(
+            split_http_url("http://127.0.0.1:3000").unwrap(),
+            ("127.0.0.1:3000", "/")
+        );
+        assert!(split_http_url("https://localhost:3000/").is_err());
+        assert!(split_http_url("http://localhost/reload").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! kubectl-style plugins: `warp foo ...` runs `warp-foo ...` from `$PATH`.
+//!
+//! Built-in commands always win; only unknown subcommands are dispatched.
+//! The plugin inherits stdio and its exit code becomes warp's. It gets
+//! the CLI's context in its environment:
+//!
+//! - `WARP_BIN`, `WARP_VERSION`, `WARP_PLUGIN_NAME`
+//! - `WARP_PROJECT_DIR`, `WARP_CONFIG` — the nearest directory (from the
+//!   working directory upward) with a `warp.toml`, if any
+//! - `WARP_API_URL`, `WARP_API_TOKEN` — from the environment, else from
+//!   `~/.warp/config.toml` (`api_url`, `token`)
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, bail};
+
+use crate::api;
+
+/// Prefix of plugin executables.
+const PREFIX: &str = "warp-";
+
+/// Run the plugin for `args` (`[name, plugin args...]`) and exit with its
+/// status.
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let Some((name, rest)) = args.split_first() else {
+        bail!("no subcommand given; see `warp --help`");
+    };
+    let name = name.to_string_lossy().to_string();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let Some(binary) = find(&name, std::env::split_paths(&path)) else {
+        bail!(
+            "unrecognized subcommand '{name}'\n\n\
+             No built-in command or plugin '{PREFIX}{name}' on PATH. See `warp --help`, or\n\
+             `warp plugin list` for installed plugins."
+        );
+    };
+
+    let cwd = std::env::current_dir()?;
+    let status = Command::new(&binary)
+        .args(rest)
+        .envs(context(&name, &cwd)?)
+        .status()
+        .with_context(|| format!("Failed to run plugin {}", binary.display()))?;
+    // A plugin killed by a signal has no code; report a generic failure.
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Print every plugin on `$PATH`, noting shadowed ones and ones a
+/// built-in command hides.
+pub fn list(builtins: &[String]) -> anyhow::Result<()> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let plugins = discover(std::env::split_paths(&path));
+    if plugins.is_empty() {
+        println!("No plugins found on PATH (executables named {PREFIX}<name>).");
+        return Ok(());
+    }
+    for (name, paths) in &plugins {
+        let note = if builtins.contains(name) {
+            "  (hidden by the built-in command)"
+        } else {
+            ""
+        };
+        println!("{name}\t{}{note}", paths[0].display());
+        for shadowed in &paths[1..] {
+            println!("  shadowed: {}", shadowed.display());
+        }
+    }
+    Ok(())
+}
+
+/// The first `warp-<name>` executable in `dirs`.
+fn find(name: &str, dirs: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    if name.is_empty() || name.contains(std::path::is_separator) {
+        return None;
+    }
+    dirs.into_iter()
+        .map(|dir| dir.join(format!("{PREFIX}{name}")))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Plugin name → executables providing it, in `$PATH` order.
+fn discover(dirs: impl IntoIterator<Item = PathBuf>) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut plugins: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<(String, PathBuf)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let name = file_name.strip_prefix(PREFIX)?.to_string();
+                let path = entry.path();
+                (!name.is_empty() && is_executable(&path)).then_some((name, path))
+            })
+            .collect();
+        found.sort();
+        for (name, path) in found {
+            let paths = plugins.entry(name).or_default();
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    plugins
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Environment passed to plugin `name` run from `cwd`.
+fn context(name: &str, cwd: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let mut env = vec![
+        ("WARP_PLUGIN_NAME".to_string(), name.to_string()),
+        ("WARP_VERSION".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    if let Ok(bin) = std::env::current_exe() {
+        env.push(("WARP_BIN".to_string(), bin.to_string_lossy().to_string()));
+    }
+    if let Some(project) = cwd.ancestors().find(|dir| dir.join("warp.toml").is_file()) {
+        env.push(("WARP_PROJECT_DIR".to_string(), project.to_string_lossy().to_string()));
+        env.push((
+            "WARP_CONFIG".to_string(),
+            project.join("warp.toml").to_string_lossy().to_string(),
+        ));
+    }
+
+    if let Some(path) = api::config_path().filter(|path| path.is_file()) {
+        for (var, value) in api::credentials(&path)? {
+            // Explicit environment variables win; the child inherits them.
+            if std::env::var_os(var).is_none() {
+                env.push((var.to_string(), value));
+            }
+        }
+    }
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn plugin(dir: &Path, file: &str, mode: u32) -> PathBuf {
+        let path = dir.join(file);
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_find_and_discover_follow_path_order() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let deploy = plugin(first.path(), "warp-deploy", 0o755);
+        let shadowed = plugin(second.path(), "warp-deploy", 0o755);
+        let lint = plugin(second.path(), "warp-lint", 0o755);
+        plugin(first.path(), "warp-notes", 0o644);
+        let dirs = || vec![first.path().to_path_buf(), second.path().to_path_buf()];
+
+        assert_eq!(find("deploy", dirs()), Some(deploy.clone()));
+        assert_eq!(find("lint", dirs()), Some(lint.clone()));
+        // Not executable, or not a plain name.
+        assert_eq!(find("notes", dirs()), None);
+        assert_eq!(find("../deploy", dirs()), None);
+
+        let plugins = discover(dirs());
+        assert_eq!(plugins.keys().collect::<Vec<_>>(), ["deploy", "lint"]);
+        assert_eq!(plugins["deploy"], [deploy, shadowed]);
+    }
+
+    #[test]
+    fn test_context_finds_project_and_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("warp.toml"), "[package]\nname = \"api\"\n").unwrap();
+        let nested = dir.path().join("src/handlers");
+        fs::create_dir_all(&nested).unwrap();
+
+        let env = context("deploy", &nested).unwrap();
+        let get = |key: &str| env.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        assert_eq!(get("WARP_PLUGIN_NAME"), Some("deploy"));
+        assert_eq!(get("WARP_PROJECT_DIR"), dir.path().to_str());
+        assert!(get("WARP_CONFIG").unwrap().ends_with("warp.toml"));
+
+        let config = dir.path().join("config.toml");
+        fs::write(&config, "api_url = \"https://warp.internal:8443\"\ntoken = \"s3cret\"\n").unwrap();
+        assert_eq!(
+            api::credentials(&config).unwrap(),
+            [
+                ("WARP_API_URL", "https://warp.internal:8443".to_string()),
+                ("WARP_API_TOKEN", "s3cret".to_string()),
+            ]
+        );
+        fs::write(&config, "token = 42\n").unwrap();
+        assert!(api::credentials(&config).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! `warp port-forward` — reach a deployment's HTTP trigger from localhost.
+//!
+//! Listens on `127.0.0.1:<local>` and opens one tunnel per accepted
+//! connection through `GET /api/v1/deployments/:id/port-forward?port=`,
+//! which warpd serves with the deployment's handler for ingress port
+//! `<remote>`. Host and path routing is skipped, so deployments that only
+//! answer for a public host name work too. A tunnel is opened and closed
+//! once up front so a wrong port or deployment fails before listening.
+//! Runs until interrupted.
+
+use std::io;
+use std::net::{Shutdown, TcpListener, TcpStream};
+
+use anyhow::{Context, bail};
+
+use crate::api::{ApiClient, path_segment};
+
+pub struct PortForwardOptions<'a> {
+    /// Deployment id (`namespace/name`).
+    pub deployment: &'a str,
+    /// `<local>:<remote>`, or one port for both.
+    pub ports: &'a str,
+}
+
+pub fn port_forward(client: &ApiClient, options: &PortForwardOptions) -> anyhow::Result<()> {
+    let (local, remote) = parse_ports(options.ports)?;
+    let path = format!("/deployments/{}/port-forward?port={remote}", path_segment(options.deployment));
+    drop(client.tunnel(&path)?);
+
+    let listener =
+        TcpListener::bind(("127.0.0.1", local)).with_context(|| format!("Cannot listen on 127.0.0.1:{local}"))?;
+    println!("Forwarding from {} -> {}:{remote}", listener.local_addr()?, options.deployment);
+    for conn in listener.incoming() {
+        let conn = match conn {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("warp port-forward: accept failed: {e}");
+                continue;
+            }
+        };
+        let client = client.clone();
+        let path = path.clone();
+        std::thread::spawn(move || {
+            let peer = conn.peer_addr().map_or_else(|_| "?".to_string(), |addr| addr.to_string());
+            println!("Handling connection from {peer}");
+            match client.tunnel(&path) {
+                Ok(tunnel) => pipe(conn, tunnel),
+                Err(e) => eprintln!("warp port-forward: {e:#}"),
+            }
+        });
+    }
+    Ok(())
+}
+
+/// `8080:80` → (8080, 80); `8080` → (8080, 8080).
+fn parse_ports(spec: &str) -> anyhow::Result<(u16, u16)> {
+    let port = |s: &str| s.parse::<u16>().with_context(|| format!("'{s}' in '{spec}' is not a port"));
+    match spec.split_once(':') {
+        Some((local, remote)) => Ok((port(local)?, port(remote)?)),
+        None if !spec.is_empty() => port(spec).map(|p| (p, p)),
+        None => bail!("give the ports as <local>:<remote>"),
+    }
+}
+
+/// Copy both ways until each side has finished sending.
+fn pipe(local: TcpStream, remote: TcpStream) {
+    let halves = local.try_clone().and_then(|local_read| Ok((local_read, remote.try_clone()?)));
+    let Ok((mut local_read, mut remote_write)) = halves else {
+        return;
+    };
+    let upstream = std::thread::spawn(move || {
+        let _ = io::copy(&mut local_read, &mut remote_write);
+        let _ = remote_write.shutdown(Shutdown::Write);
+    });
+    let (mut remote_read, mut local_write) = (remote, local);
+    let _ = io::copy(&mut remote_read, &mut local_write);
+    let _ = local_write.shutdown(Shutdown::Write);
+    let _ = upstream.join();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_parse_ports() {
+        assert_eq!(parse_ports("8080:80").unwrap(), (8080, 80));
+        assert_eq!(parse_ports("9000").unwrap(), (9000, 9000));
+        assert!(parse_ports("8080:http").is_err());
+        assert!(parse_ports("70000:80").is_err());
+        assert!(parse_ports("").is_err());
+    }
+
+    #[test]
+    fn test_pipe_copies_both_ways() {
+        let pair = || {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            (client, listener.accept().unwrap().0)
+        };
+        let (mut app, local) = pair();
+        let (remote, mut server) = pair();
+        let piping = std::thread::spawn(move || pipe(local, remote));
+
+        app.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        app.shutdown(Shutdown::Write).unwrap();
+        let mut request = String::new();
+        server.read_to_string(&mut request).unwrap();
+        assert_eq!(request, "GET / HTTP/1.1\r\n\r\n");
+        server.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+        drop(server);
+        let mut response = String::new();
+        app.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "HTTP/1.1 204 No Content\r\n\r\n");
+        piping.join().unwrap();
+    }
+}
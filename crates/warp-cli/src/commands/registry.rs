@@ -0,0 +1,272 @@
+//! `warp registry` — store packed components in an OCI registry.
+//!
+//! Wraps the [ORAS](https://oras.land) CLI, which speaks the OCI
+//! distribution protocol and keeps credentials in the Docker config:
+//!
+//! - `login <registry>` runs `oras login`, with `--password-stdin` for CI.
+//! - `push <ref>` pushes a packed artifact (packing the project unless
+//!   `--artifact` is given) as a single `application/wasm` layer, plus its
+//!   sigstore bundle when one sits next to it. `-t` adds tags. The build
+//!   metadata becomes OCI annotations (title, version, revision).
+//! - `pull <ref>` downloads into a directory and prints the component's
+//!   build metadata, ready for `warp deploy --artifact`.
+//!
+//! References are `registry/repository[:tag|@digest]`, with or without
+//! `oci://`. The tag defaults to `latest`.
+//!
+//! Requires oras on `PATH`, or `WARPGRID_ORAS_PATH`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, bail};
+use warp_core::BuildMetadata;
+
+/// OCI artifact type of a pushed component.
+pub const ARTIFACT_TYPE: &str = "application/vnd.warpgrid.component.v1+wasm";
+
+/// Media type of a sigstore bundle layer.
+const BUNDLE_MEDIA_TYPE: &str = "application/vnd.dev.sigstore.bundle.v0.3+json";
+
+pub struct PushOptions<'a> {
+    pub reference: &'a str,
+    /// Extra tags for the same manifest.
+    pub tags: &'a [String],
+    /// Packed artifact; packs the project at `path` when `None`.
+    pub artifact: Option<&'a str>,
+    pub path: &'a str,
+}
+
+pub fn login(registry: &str, username: Option<&str>, password_stdin: bool) -> anyhow::Result<()> {
+    run(login_command(&find_oras()?, registry, username, password_stdin))
+}
+
+pub fn push(options: &PushOptions) -> anyhow::Result<()> {
+    let target = push_target(options.reference, options.tags)?;
+    let artifact = match options.artifact {
+        Some(artifact) => PathBuf::from(artifact),
+        None => {
+            let result = warp_pack::pack_with_options(Path::new(options.path), &warp_pack::PackOptions::default())
+                .context("Pack failed")?;
+            super::pack::print_result(&result);
+            PathBuf::from(result.output_path)
+        }
+    };
+    let bytes = std::fs::read(&artifact).with_context(|| format!("Failed to read {}", artifact.display()))?;
+    let meta = BuildMetadata::from_wasm(&bytes)?;
+    run(push_command(&find_oras()?, &target, &artifact, meta.as_ref()))?;
+    println!("Pushed {} to oci://{target}", artifact.display());
+    Ok(())
+}
+
+pub fn pull(reference: &str, output: &str, format: &str) -> anyhow::Result<()> {
+    let reference = strip_scheme(reference);
+    run(pull_command(&find_oras()?, reference, Path::new(output)))?;
+    let artifact = find_component(Path::new(output))?;
+    super::status::artifact_status(&artifact.to_string_lossy(), format)?;
+    if format != "json" && format != "yaml" {
+        println!();
+        println!("Deploy it with: warp deploy --artifact {} --source oci://{reference}", artifact.display());
+    }
+    Ok(())
+}
+
+/// `ref` for `oras push`, with every extra tag: `registry/repo:v1,latest`.
+fn push_target(reference: &str, tags: &[String]) -> anyhow::Result<String> {
+    let reference = strip_scheme(reference);
+    if reference.contains('@') {
+        bail!("push needs a tag, not a digest: {reference}");
+    }
+    let (repository, tag) = split_tag(reference);
+    if !repository.contains('/') {
+        bail!("'{reference}' has no registry; use registry/repository[:tag]");
+    }
+    let mut target = format!("{repository}:{}", tag.unwrap_or("latest"));
+    for tag in tags {
+        target.push(',');
+        target.push_str(tag);
+    }
+    Ok(target)
+}
+
+fn strip_scheme(reference: &str) -> &str {
+    reference.strip_prefix("oci://").unwrap_or(reference)
+}
+
+/// Split off the tag: the last `:` after the last `/`, so a registry port
+/// is not mistaken for one.
+fn split_tag(reference: &str) -> (&str, Option<&str>) {
+    let name_start = reference.rfind('/').map_or(0, |i| i + 1);
+    match reference[name_start..].rfind(':') {
+        Some(i) => (&reference[..name_start + i], Some(&reference[name_start + i + 1..])),
+        None => (reference, None),
+    }
+}
+
+/// Locate the oras binary: `$WARPGRID_ORAS_PATH`, then `oras` on `PATH`.
+fn find_oras() -> anyhow::Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_ORAS_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            return Ok(p);
+        }
+        bail!("WARPGRID_ORAS_PATH is set to '{path}' but the file does not exist.");
+    }
+    let output = Command::new("which").arg("oras").output().ok();
+    if let Some(output) = output {
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !path.is_empty() {
+            return Ok(PathBuf::from(path));
+        }
+    }
+    bail!("oras not found. Install it (https://oras.land/docs/installation) or set WARPGRID_ORAS_PATH.")
+}
+
+fn login_command(oras: &Path, registry: &str, username: Option<&str>, password_stdin: bool) -> Command {
+    let mut cmd = Command::new(oras);
+    cmd.arg("login").arg(strip_scheme(registry));
+    if let Some(username) = username {
+        cmd.arg("--username").arg(username);
+    }
+    if password_stdin {
+        cmd.arg("--password-stdin");
+    }
+    cmd
+}
+
+/// `oras push`, run from the artifact's directory: oras stores file names
+/// as layer titles and refuses absolute paths.
+fn push_command(oras: &Path, target: &str, artifact: &Path, meta: Option<&BuildMetadata>) -> Command {
+    let mut cmd = Command::new(oras);
+    cmd.arg("push").arg(target).arg("--artifact-type").arg(ARTIFACT_TYPE);
+    if let Some(meta) = meta {
+        cmd.arg("--annotation").arg(format!("org.opencontainers.image.title={}", meta.package));
+        cmd.arg("--annotation").arg(format!("org.opencontainers.image.version={}", meta.version));
+        if let Some(sha) = &meta.git_sha {
+            cmd.arg("--annotation").arg(format!("org.opencontainers.image.revision={sha}"));
+        }
+    }
+    let name = artifact.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    cmd.arg(format!("{name}:application/wasm"));
+    if let Some(dir) = artifact.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        cmd.current_dir(dir);
+    }
+    let bundle = format!("{name}.sigstore.json");
+    if artifact.with_file_name(&bundle).is_file() {
+        cmd.arg(format!("{bundle}:{BUNDLE_MEDIA_TYPE}"));
+    }
+    cmd
+}
+
+fn pull_command(oras: &Path, reference: &str, output: &Path) -> Command {
+    let mut cmd = Command::new(oras);
+    cmd.arg("pull").arg(reference).arg("--output").arg(output);
+    cmd
+}
+
+/// The single `.wasm` file a pull wrote.
+fn find_component(dir: &Path) -> anyhow::Result<PathBuf> {
+    let mut found = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "wasm") {
+            found.push(path);
+        }
+    }
+    match found.len() {
+        1 => Ok(found.remove(0)),
+        0 => bail!("the pulled artifact has no .wasm file in {}", dir.display()),
+        _ => bail!("the pulled artifact has several .wasm files in {}", dir.display()),
+    }
+}
+
+/// Run with the terminal attached, so oras can prompt and show progress.
+fn run(mut cmd: Command) -> anyhow::Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = cmd.status().with_context(|| format!("Failed to run {program}"))?;
+    if !status.success() {
+        bail!("{program} exited with {}", status.code().unwrap_or(-1));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_push_target() {
+        assert_eq!(push_target("ghcr.io/acme/api:v1", &[]).unwrap(), "ghcr.io/acme/api:v1");
+        assert_eq!(push_target("oci://ghcr.io/acme/api", &[]).unwrap(), "ghcr.io/acme/api:latest");
+        assert_eq!(
+            push_target("localhost:5000/api:v2", &["latest".into(), "stable".into()]).unwrap(),
+            "localhost:5000/api:v2,latest,stable"
+        );
+        assert!(push_target("api:v1", &[]).is_err());
+        assert!(push_target("ghcr.io/acme/api@sha256:abc", &[]).is_err());
+    }
+
+    #[test]
+    fn test_split_tag_ignores_registry_port() {
+        assert_eq!(split_tag("localhost:5000/api"), ("localhost:5000/api", None));
+        assert_eq!(split_tag("localhost:5000/api:v1"), ("localhost:5000/api", Some("v1")));
+    }
+
+    #[test]
+    fn test_push_command_runs_from_the_artifact_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        std::fs::write(&artifact, b"\0asm").unwrap();
+        let meta = BuildMetadata {
+            package: "api".into(),
+            version: "1.2.0".into(),
+            git_sha: Some("abc123".into()),
+            build_time: 0,
+            language: "rust".into(),
+            shims: vec![],
+        };
+        let cmd = push_command(Path::new("oras"), "ghcr.io/acme/api:v1", &artifact, Some(&meta));
+        assert_eq!(
+            args(&cmd),
+            [
+                "push",
+                "ghcr.io/acme/api:v1",
+                "--artifact-type",
+                ARTIFACT_TYPE,
+                "--annotation",
+                "org.opencontainers.image.title=api",
+                "--annotation",
+                "org.opencontainers.image.version=1.2.0",
+                "--annotation",
+                "org.opencontainers.image.revision=abc123",
+                "handler.wasm:application/wasm",
+            ]
+        );
+        assert_eq!(cmd.get_current_dir(), Some(dir.path()));
+
+        std::fs::write(dir.path().join("handler.wasm.sigstore.json"), b"{}").unwrap();
+        let cmd = push_command(Path::new("oras"), "ghcr.io/acme/api:v1", &artifact, None);
+        assert_eq!(args(&cmd).last().unwrap(), &format!("handler.wasm.sigstore.json:{BUNDLE_MEDIA_TYPE}"));
+    }
+
+    #[test]
+    fn test_login_and_pull_commands() {
+        let cmd = login_command(Path::new("oras"), "oci://ghcr.io", Some("ci"), true);
+        assert_eq!(args(&cmd), ["login", "ghcr.io", "--username", "ci", "--password-stdin"]);
+        let cmd = pull_command(Path::new("oras"), "ghcr.io/acme/api:v1", Path::new("pulled"));
+        assert_eq!(args(&cmd), ["pull", "ghcr.io/acme/api:v1", "--output", "pulled"]);
+    }
+
+    #[test]
+    fn test_find_component() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(find_component(dir.path()).is_err());
+        std::fs::write(dir.path().join("handler.wasm"), b"").unwrap();
+        std::fs::write(dir.path().join("handler.wasm.sigstore.json"), b"").unwrap();
+        assert_eq!(find_component(dir.path()).unwrap(), dir.path().join("handler.wasm"));
+    }
+}
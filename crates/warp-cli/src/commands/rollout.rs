@@ -0,0 +1,257 @@
+//! `warp rollout` — roll a new version out to a deployment.
+//!
+//! `start` posts `/api/v1/deployments/:id/rollout` with a rolling, canary,
+//! or blue-green strategy; flags left unset take the daemon's defaults.
+//! `status` reads `/rollouts/:id`, and `pause`, `resume`, and `rollback`
+//! post to the matching `/rollouts/:id/<action>` endpoint.
+//!
+//! With `--watch`, `start` and `status` keep polling the rollout and print
+//! a progress line each time its phase changes — the batch bar of a rolling
+//! update, or the observe → promote → complete stages of a canary — until
+//! it completes or is rolled back.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, bail};
+use serde_json::{Map, Value, json};
+
+use super::status::{phase_label, text};
+use super::top::{bar, percent};
+use crate::api::{ApiClient, path_segment};
+use crate::output;
+
+/// How often to check on the rollout while watching.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The phases of a canary before it completes, and their stage names.
+const CANARY_STAGES: [(&str, &str); 2] = [("CanaryObserving", "observe"), ("CanaryPromoting", "promote")];
+
+pub struct StartOptions<'a> {
+    /// Deployment id (`namespace/name`).
+    pub deployment: &'a str,
+    /// Source URI of the version to roll out.
+    pub version: &'a str,
+    /// `rolling`, `canary`, or `blue-green`.
+    pub strategy: &'a str,
+    pub batch_size: Option<u32>,
+    pub batch_interval_secs: Option<u64>,
+    pub canary_percent: Option<u32>,
+    pub canary_instances: Option<u32>,
+    pub observe_secs: Option<u64>,
+    /// Follow the rollout until it finishes.
+    pub watch: bool,
+}
+
+pub fn start(client: &ApiClient, options: &StartOptions) -> anyhow::Result<()> {
+    let id = options.deployment;
+    let body = start_request(options)?;
+    let rollout: Value = client.post(&format!("/deployments/{}/rollout", path_segment(id)), &body)?;
+    println!(
+        "Started {} rollout of {id}: {} → {}",
+        options.strategy,
+        text(&rollout["old_version"]),
+        text(&rollout["new_version"]),
+    );
+    if options.watch {
+        watch(client, id, rollout)
+    } else {
+        println!("  {}", progress(&rollout["phase"]));
+        Ok(())
+    }
+}
+
+pub fn status(client: &ApiClient, id: &str, format: &str, follow: bool) -> anyhow::Result<()> {
+    let rollout = get(client, id)?;
+    if follow {
+        return watch(client, id, rollout);
+    }
+    output::print(format, &rollout, || format_rollout(&rollout))
+}
+
+pub fn pause(client: &ApiClient, id: &str) -> anyhow::Result<()> {
+    let rollout = act(client, id, "pause")?;
+    println!("Paused the rollout of {id} ({})", phase_label(&rollout["phase"]));
+    Ok(())
+}
+
+pub fn resume(client: &ApiClient, id: &str) -> anyhow::Result<()> {
+    let rollout = act(client, id, "resume")?;
+    println!("Resumed the rollout of {id} ({})", phase_label(&rollout["phase"]));
+    Ok(())
+}
+
+pub fn rollback(client: &ApiClient, id: &str) -> anyhow::Result<()> {
+    let rollout = act(client, id, "rollback")?;
+    println!("Rolled back {id} to {}", text(&rollout["old_version"]));
+    Ok(())
+}
+
+fn get(client: &ApiClient, id: &str) -> anyhow::Result<Value> {
+    client
+        .get_optional(&format!("/rollouts/{}", path_segment(id)))?
+        .with_context(|| format!("{id} has no rollout"))
+}
+
+fn act(client: &ApiClient, id: &str, action: &str) -> anyhow::Result<Value> {
+    client.post(&format!("/rollouts/{}/{action}", path_segment(id)), &json!({}))
+}
+
+/// Poll until the rollout finishes, printing a line whenever the phase
+/// changes. Fails if it was rolled back.
+fn watch(client: &ApiClient, id: &str, mut rollout: Value) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let mut last = None;
+    loop {
+        let phase = &rollout["phase"];
+        if last.as_ref() != Some(phase) {
+            println!("  [{:>4}s] {}", started.elapsed().as_secs(), progress(phase));
+            last = Some(phase.clone());
+        }
+        match phase_label(phase).as_str() {
+            "Completed" => {
+                println!("Rolled out {} to {id}", text(&rollout["new_version"]));
+                return Ok(());
+            }
+            "RolledBack" => bail!("The rollout of {id} was rolled back: {}", text(&phase["RolledBack"]["reason"])),
+            _ => {}
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        rollout = get(client, id)?;
+    }
+}
+
+/// The `StartRolloutRequest` JSON for the flags given.
+fn start_request(options: &StartOptions) -> anyhow::Result<Value> {
+    let rolling = [
+        ("batch_size", options.batch_size.map(Value::from)),
+        ("batch_interval_secs", options.batch_interval_secs.map(Value::from)),
+    ];
+    let canary = [
+        ("traffic_percent", options.canary_percent.map(Value::from)),
+        ("canary_instances", options.canary_instances.map(Value::from)),
+        ("observation_secs", options.observe_secs.map(Value::from)),
+    ];
+    let set = |fields: &[(&str, Option<Value>)]| -> Map<String, Value> {
+        fields
+            .iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.clone()?)))
+            .collect()
+    };
+    let (rolling, canary) = (set(&rolling), set(&canary));
+    let strategy = match options.strategy {
+        "rolling" if canary.is_empty() => json!({"Rolling": rolling}),
+        "canary" if rolling.is_empty() => {
+            if options.canary_percent.is_some_and(|percent| percent > 100) {
+                bail!("--canary-percent must be between 0 and 100");
+            }
+            json!({"Canary": canary})
+        }
+        "blue-green" if rolling.is_empty() && canary.is_empty() => json!("BlueGreen"),
+        "rolling" | "canary" | "blue-green" => {
+            bail!("--batch-* flags apply to rolling rollouts and --canary-*/--observe to canary ones")
+        }
+        other => bail!("Unknown strategy '{other}' (expected rolling, canary, or blue-green)"),
+    };
+    Ok(json!({"strategy": strategy, "new_version": options.version}))
+}
+
+fn format_rollout(rollout: &Value) -> String {
+    let mut out = format!(
+        "{}  {}\n  Version:   {} → {}\n  Instances: {}\n  Progress:  {}\n",
+        text(&rollout["deployment_id"]),
+        phase_label(&rollout["phase"]),
+        text(&rollout["old_version"]),
+        text(&rollout["new_version"]),
+        text(&rollout["target_instances"]),
+        progress(&rollout["phase"]),
+    );
+    if let Some(reason) = rollout["phase"]["RolledBack"]["reason"].as_str() {
+        out.push_str(&format!("  Reason:    {reason}\n"));
+    }
+    out
+}
+
+/// One line of progress for a phase: a batch bar for rolling updates, the
+/// current stage for canaries, else the phase label.
+fn progress(phase: &Value) -> String {
+    if let Some(batch) = phase.get("RollingBatch") {
+        let current = batch["current"].as_u64().unwrap_or_default();
+        let total = batch["total"].as_u64().unwrap_or_default();
+        let done = current.saturating_sub(1);
+        return format!("{} batch {current}/{total} ({done} done)", bar(percent(done, total)));
+    }
+    let label = phase_label(phase);
+    let Some(current) = CANARY_STAGES.iter().position(|(name, _)| *name == label) else {
+        return label;
+    };
+    let mut stages: Vec<String> = CANARY_STAGES
+        .iter()
+        .enumerate()
+        .map(|(i, (_, stage))| if i == current { format!("[{stage}]") } else { stage.to_string() })
+        .collect();
+    stages.push("complete".to_string());
+    stages.join(" → ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(strategy: &'static str) -> StartOptions<'static> {
+        StartOptions {
+            deployment: "default/api",
+            version: "oci://registry/api:v2",
+            strategy,
+            batch_size: None,
+            batch_interval_secs: None,
+            canary_percent: None,
+            canary_instances: None,
+            observe_secs: None,
+            watch: false,
+        }
+    }
+
+    #[test]
+    fn test_start_request() {
+        let rolling = StartOptions { batch_size: Some(2), ..options("rolling") };
+        assert_eq!(
+            start_request(&rolling).unwrap(),
+            json!({"strategy": {"Rolling": {"batch_size": 2}}, "new_version": "oci://registry/api:v2"})
+        );
+        let canary = StartOptions { canary_percent: Some(20), observe_secs: Some(60), ..options("canary") };
+        assert_eq!(
+            start_request(&canary).unwrap()["strategy"],
+            json!({"Canary": {"traffic_percent": 20, "observation_secs": 60}})
+        );
+        assert_eq!(start_request(&options("blue-green")).unwrap()["strategy"], "BlueGreen");
+
+        assert!(start_request(&StartOptions { batch_size: Some(2), ..options("canary") }).is_err());
+        assert!(start_request(&StartOptions { canary_percent: Some(101), ..options("canary") }).is_err());
+        assert!(start_request(&options("big-bang")).is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(
+            progress(&json!({"RollingBatch": {"current": 3, "total": 4}})),
+            "█████░░░░░ batch 3/4 (2 done)"
+        );
+        assert_eq!(progress(&json!("CanaryObserving")), "[observe] → promote → complete");
+        assert_eq!(progress(&json!("CanaryPromoting")), "observe → [promote] → complete");
+        assert_eq!(progress(&json!("Completed")), "Completed");
+        assert_eq!(progress(&json!({"RolledBack": {"reason": "bad"}})), "RolledBack");
+    }
+
+    #[test]
+    fn test_format_rollout() {
+        let rollout = json!({
+            "deployment_id": "prod/api", "old_version": "v1", "new_version": "v2", "target_instances": 3,
+            "phase": {"RolledBack": {"reason": "rolled back by operator"}},
+        });
+        assert_eq!(
+            format_rollout(&rollout),
+            "prod/api  RolledBack\n  Version:   v1 → v2\n  Instances: 3\n  Progress:  RolledBack\n  \
+             Reason:    rolled back by operator\n"
+        );
+    }
+}
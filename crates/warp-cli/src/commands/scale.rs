@@ -0,0 +1,117 @@
+//! `warp scale` — change how many instances a deployment runs.
+//!
+//! `--replicas` sets the number of instances the scheduler keeps placed
+//! (the deployment's minimum); `--min` and `--max` set the bounds the
+//! autoscaler moves within. All go through
+//! `POST /api/v1/deployments/:id/scale`, which stores them in the spec.
+//! `warp scale` then polls `/deployments/:id/instances` until the running
+//! count is within the new bounds and prints the instances.
+
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::api::{ApiClient, path_segment};
+
+/// How often to check on instances while waiting.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ScaleOptions<'a> {
+    /// Deployment id (`namespace/name`).
+    pub deployment: &'a str,
+    pub replicas: Option<u32>,
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+    /// Give up waiting for instances after this long (`None`: don't wait).
+    pub wait: Option<Duration>,
+}
+
+/// The bounds the API settled on.
+#[derive(Debug, Deserialize)]
+struct Scaled {
+    min: u32,
+    max: u32,
+}
+
+pub fn scale(client: &ApiClient, options: &ScaleOptions) -> anyhow::Result<()> {
+    let id = options.deployment;
+    let body = scale_request(options)?;
+    let segment = path_segment(id);
+    let scaled: Scaled = client.post(&format!("/deployments/{segment}/scale"), &body)?;
+    println!("Scaling {id} to {}", bounds(scaled.min, scaled.max));
+
+    let Some(timeout) = options.wait else {
+        return Ok(());
+    };
+    let path = format!("/deployments/{segment}/instances");
+    let started = Instant::now();
+    let mut last = None;
+    loop {
+        let instances: Vec<Value> = client.get(&path)?;
+        let running = running(&instances);
+        if last != Some(running) {
+            println!("  {running} instances running");
+            last = Some(running);
+        }
+        if (scaled.min..=scaled.max).contains(&running) {
+            println!("Scaled {id} in {:.1}s", started.elapsed().as_secs_f64());
+            print!("{}", super::status::format_instances(&instances));
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            print!("{}", super::status::format_instances(&instances));
+            bail!(
+                "{id} has {running} running instances after {}s, wanted {}",
+                timeout.as_secs(),
+                bounds(scaled.min, scaled.max)
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The `ScaleRequest` JSON for the flags given.
+fn scale_request(options: &ScaleOptions) -> anyhow::Result<Value> {
+    if options.replicas.is_some() && options.min.is_some() {
+        bail!("--replicas sets the minimum; pass --replicas or --min, not both");
+    }
+    let mut body = Map::new();
+    for (key, value) in [("target", options.replicas), ("min", options.min), ("max", options.max)] {
+        if let Some(value) = value {
+            body.insert(key.to_string(), json!(value));
+        }
+    }
+    if body.is_empty() {
+        bail!("Nothing to change: pass --replicas, --min, or --max");
+    }
+    Ok(Value::Object(body))
+}
+
+fn running(instances: &[Value]) -> u32 {
+    instances.iter().filter(|i| i["status"] == "running").count() as u32
+}
+
+fn bounds(min: u32, max: u32) -> String {
+    if min == max { format!("{min} instances") } else { format!("{min}-{max} instances") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(replicas: Option<u32>, min: Option<u32>, max: Option<u32>) -> ScaleOptions<'static> {
+        ScaleOptions { deployment: "default/api", replicas, min, max, wait: None }
+    }
+
+    #[test]
+    fn test_scale_request() {
+        assert_eq!(scale_request(&options(Some(3), None, None)).unwrap(), json!({"target": 3}));
+        assert_eq!(scale_request(&options(None, Some(1), Some(8))).unwrap(), json!({"min": 1, "max": 8}));
+        assert!(scale_request(&options(None, None, None)).is_err());
+        assert!(scale_request(&options(Some(3), Some(1), None)).is_err());
+        assert_eq!(bounds(3, 3), "3 instances");
+        assert_eq!(bounds(1, 8), "1-8 instances");
+    }
+}
@@ -0,0 +1,163 @@
+//! `warp secrets` — manage cluster secrets.
+//!
+//! `set` writes a value through `PUT /api/v1/secrets/:id`, taken from the
+//! command line, a file (`--from-file`), or standard input (`--stdin`, one
+//! trailing newline dropped). `list` reads `GET /secrets?namespace=`, which
+//! never includes values. `get` reads `/secrets/:id`, which only returns the
+//! value for `?reveal=true`, so the value is masked unless `--reveal` is
+//! given, in text and JSON alike. `delete` removes the secret.
+
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, bail};
+use serde_json::{Value, json};
+
+use super::status::{table, text};
+use crate::api::{ApiClient, path_segment};
+use crate::output;
+
+/// What a masked value is shown as; fixed so it does not hint at length.
+const MASK: &str = "********";
+
+/// Where `set` reads the value from.
+pub enum Source<'a> {
+    Literal(&'a str),
+    File(&'a str),
+    Stdin,
+}
+
+impl<'a> Source<'a> {
+    /// The one source the flags name.
+    pub fn from_flags(value: Option<&'a str>, file: Option<&'a str>, stdin: bool) -> anyhow::Result<Self> {
+        match (value, file, stdin) {
+            (Some(value), None, false) => Ok(Self::Literal(value)),
+            (None, Some(path), false) => Ok(Self::File(path)),
+            (None, None, true) => Ok(Self::Stdin),
+            (None, None, false) => bail!("give the value, --from-file <PATH>, or --stdin"),
+            _ => bail!("give only one of the value, --from-file, or --stdin"),
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<String> {
+        match self {
+            Self::Literal(value) => Ok(value.to_string()),
+            Self::File(path) => std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}")),
+            Self::Stdin => {
+                let mut value = String::new();
+                std::io::stdin().read_to_string(&mut value).context("Failed to read standard input")?;
+                Ok(trim_newline(value))
+            }
+        }
+    }
+}
+
+pub fn set(client: &ApiClient, id: &str, source: &Source) -> anyhow::Result<()> {
+    let value = source.read()?;
+    let info: Value = client.put(&format!("/secrets/{}", path_segment(id)), &json!({"value": value}))?;
+    println!("Set secret {id} ({} bytes)", text(&info["size"]));
+    Ok(())
+}
+
+pub fn get(client: &ApiClient, id: &str, reveal: bool, format: &str) -> anyhow::Result<()> {
+    let query = if reveal { "?reveal=true" } else { "" };
+    let mut secret: Value = client
+        .get_optional(&format!("/secrets/{}{query}", path_segment(id)))?
+        .with_context(|| format!("Secret {id} not found"))?;
+    if !reveal {
+        secret["value"] = json!(MASK);
+    }
+    output::print(format, &secret, || format!("{}\n", text(&secret["value"])))
+}
+
+pub fn list(client: &ApiClient, namespace: &str, format: &str) -> anyhow::Result<()> {
+    let secrets: Vec<Value> = client.get(&format!("/secrets?namespace={}", path_segment(namespace)))?;
+    output::print(format, &secrets, || format_secrets(&secrets, epoch_secs()))
+}
+
+pub fn delete(client: &ApiClient, id: &str) -> anyhow::Result<()> {
+    let _: Value = client.delete(&format!("/secrets/{}", path_segment(id)))?;
+    println!("Deleted secret {id}");
+    Ok(())
+}
+
+fn format_secrets(secrets: &[Value], now: u64) -> String {
+    if secrets.is_empty() {
+        return "No secrets\n".to_string();
+    }
+    let rows: Vec<[String; 4]> = secrets
+        .iter()
+        .map(|secret| {
+            [
+                text(&secret["name"]),
+                MASK.to_string(),
+                format!("{}B", text(&secret["size"])),
+                age(now.saturating_sub(secret["updated_at"].as_u64().unwrap_or_default())),
+            ]
+        })
+        .collect();
+    table(["NAME", "VALUE", "SIZE", "UPDATED"], &rows, "")
+}
+
+/// `42s`, `5m`, `3h`, or `2d` ago.
+fn age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+fn trim_newline(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_from_flags() {
+        assert!(matches!(Source::from_flags(Some("v"), None, false), Ok(Source::Literal("v"))));
+        assert!(matches!(Source::from_flags(None, Some("key.pem"), false), Ok(Source::File("key.pem"))));
+        assert!(matches!(Source::from_flags(None, None, true), Ok(Source::Stdin)));
+        assert!(Source::from_flags(None, None, false).is_err());
+        assert!(Source::from_flags(Some("v"), None, true).is_err());
+    }
+
+    #[test]
+    fn test_trim_newline() {
+        assert_eq!(trim_newline("hunter2\n".into()), "hunter2");
+        assert_eq!(trim_newline("hunter2\r\n".into()), "hunter2");
+        assert_eq!(trim_newline("a\n\n".into()), "a\n");
+        assert_eq!(trim_newline("hunter2".into()), "hunter2");
+    }
+
+    #[test]
+    fn test_format_secrets() {
+        let secrets = [
+            json!({"name": "api-key", "size": 32, "updated_at": 9910}),
+            json!({"name": "db-password", "size": 7, "updated_at": 2800}),
+        ];
+        assert_eq!(
+            format_secrets(&secrets, 10_000),
+            "NAME         VALUE     SIZE  UPDATED\n\
+             api-key      ********  32B   1m ago\n\
+             db-password  ********  7B    2h ago\n"
+        );
+        assert_eq!(format_secrets(&[], 0), "No secrets\n");
+    }
+}
@@ -0,0 +1,285 @@
+//! `warp status` — what is running, or what an artifact is.
+//!
+//! With a path to a packed `.wasm`, prints the build metadata `warp pack`
+//! embedded in it. With a deployment, prints its health, instances,
+//! rollout, health problems, and the last lines its guests wrote to
+//! stderr. With neither, prints one line per deployment in the cluster.
+
+use std::path::Path;
+
+use anyhow::Context;
+use serde_json::{Value, json};
+use warp_core::BuildMetadata;
+
+use crate::api::{ApiClient, path_segment};
+use crate::commands::logs::clock;
+use crate::output;
+
+/// Stderr lines shown as a deployment's recent errors.
+const RECENT_ERRORS: usize = 5;
+
+/// Print the build metadata `warp pack` embedded in an artifact.
+pub fn artifact_status(path: &str, format: &str) -> anyhow::Result<()> {
+    let bytes = std::fs::read(Path::new(path)).with_context(|| format!("Failed to read {path}"))?;
+    let Some(meta) = BuildMetadata::from_wasm(&bytes)? else {
+        anyhow::bail!("{path} has no build metadata (packed by an older warp?)");
+    };
+
+    output::print(format, &meta, || format_metadata(&meta))
+}
+
+fn format_metadata(meta: &BuildMetadata) -> String {
+    let shims = if meta.shims.is_empty() {
+        "none".to_string()
+    } else {
+        meta.shims.join(", ")
+    };
+    format!(
+        "{}@{}\n  Language: {}\n  Commit:   {}\n  Built:    {} (unix)\n  Shims:    {shims}\n",
+        meta.package,
+        meta.version,
+        meta.language,
+        meta.git_sha.as_deref().unwrap_or("unknown"),
+        meta.build_time,
+    )
+}
+
+/// Print one deployment's status.
+pub fn deployment_status(client: &ApiClient, id: &str, format: &str) -> anyhow::Result<()> {
+    let segment = path_segment(id);
+    let spec: Value = client
+        .get_optional(&format!("/deployments/{segment}"))?
+        .with_context(|| format!("Deployment {id} not found"))?;
+    let health: Option<Value> = client.get_optional(&format!("/deployments/{segment}/health"))?;
+    let instances: Value = client.get(&format!("/deployments/{segment}/instances"))?;
+    let rollout: Option<Value> = client.get_optional(&format!("/rollouts/{segment}"))?;
+    let errors: Option<Value> =
+        client.get_optional(&format!("/deployments/{segment}/logs?stream=stderr&limit={RECENT_ERRORS}"))?;
+    let status = json!({
+        "deployment": spec,
+        "health": health,
+        "instances": instances,
+        "rollout": rollout,
+        "recent_errors": errors.unwrap_or_else(|| json!([])),
+    });
+    output::print(format, &status, || format_deployment(&status))
+}
+
+/// Print every deployment's health on one line each.
+pub fn cluster_status(client: &ApiClient, format: &str) -> anyhow::Result<()> {
+    let deployments: Vec<Value> = client.get("/deployments")?;
+    let mut rows = Vec::new();
+    for spec in deployments {
+        let id = spec["id"].as_str().unwrap_or_default().to_string();
+        let health: Option<Value> = client.get_optional(&format!("/deployments/{}/health", path_segment(&id)))?;
+        let rollout: Option<Value> = client.get_optional(&format!("/rollouts/{}", path_segment(&id)))?;
+        rows.push(json!({"id": id, "paused": spec["paused"], "health": health, "rollout": rollout}));
+    }
+    rows.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    output::print(format, &rows, || format_cluster(&rows))
+}
+
+fn format_deployment(status: &Value) -> String {
+    let spec = &status["deployment"];
+    let health = &status["health"];
+    let mut out = format!(
+        "{}  {}  {}/{} available\n  Source:    {}\n",
+        text(&spec["id"]),
+        health_label(health, spec),
+        health["available"].as_u64().unwrap_or_default(),
+        health["desired"].as_u64().unwrap_or_default(),
+        text(&spec["source"]),
+    );
+    out.push_str(&format!(
+        "  Instances: {}-{}\n",
+        spec["instances"]["min"].as_u64().unwrap_or_default(),
+        spec["instances"]["max"].as_u64().unwrap_or_default(),
+    ));
+    if let Some(rollout) = status["rollout"].as_object() {
+        out.push_str(&format!(
+            "  Rollout:   {} ({} → {})\n",
+            phase_label(&rollout["phase"]),
+            text(&rollout["old_version"]),
+            text(&rollout["new_version"]),
+        ));
+    }
+
+    let instances = status["instances"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if !instances.is_empty() {
+        out.push('\n');
+        out.push_str(&format_instances(instances));
+    }
+
+    let reasons = health["reasons"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if !reasons.is_empty() {
+        out.push_str("\n  Problems:\n");
+        for reason in reasons {
+            out.push_str(&format!("    - {}\n", text(&reason["message"])));
+        }
+    }
+    let errors = status["recent_errors"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if !errors.is_empty() {
+        out.push_str("\n  Recent errors:\n");
+        for line in errors {
+            out.push_str(&format!(
+                "    {} {} {}\n",
+                clock(line["timestamp_ms"].as_u64().unwrap_or_default()),
+                text(&line["instance"]),
+                text(&line["line"]),
+            ));
+        }
+    }
+    out
+}
+
+/// The `/deployments/:id/instances` list as an indented table.
+pub(crate) fn format_instances(instances: &[Value]) -> String {
+    let rows: Vec<[String; 5]> = instances
+        .iter()
+        .map(|i| {
+            [
+                text(&i["id"]),
+                text(&i["node_id"]),
+                text(&i["status"]),
+                text(&i["health"]),
+                i["restart_count"].as_u64().unwrap_or_default().to_string(),
+            ]
+        })
+        .collect();
+    table(["INSTANCE", "NODE", "STATUS", "HEALTH", "RESTARTS"], &rows, "  ")
+}
+
+fn format_cluster(rows: &[Value]) -> String {
+    if rows.is_empty() {
+        return "No deployments\n".to_string();
+    }
+    let rows: Vec<[String; 4]> = rows
+        .iter()
+        .map(|row| {
+            let health = &row["health"];
+            [
+                text(&row["id"]),
+                health_label(health, row),
+                format!(
+                    "{}/{}",
+                    health["available"].as_u64().unwrap_or_default(),
+                    health["desired"].as_u64().unwrap_or_default()
+                ),
+                row["rollout"]
+                    .as_object()
+                    .map_or("-".to_string(), |rollout| phase_label(&rollout["phase"])),
+            ]
+        })
+        .collect();
+    table(["DEPLOYMENT", "STATUS", "READY", "ROLLOUT"], &rows, "")
+}
+
+/// `healthy`, `degraded`, …, or `paused`.
+fn health_label(health: &Value, spec: &Value) -> String {
+    if spec["paused"] == true {
+        return "paused".to_string();
+    }
+    health["status"].as_str().unwrap_or("unknown").to_string()
+}
+
+/// A rollout phase as the API serializes it (`"Paused"`,
+/// `{"RollingBatch": {"current": 1, "total": 3}}`), as one label.
+pub(crate) fn phase_label(phase: &Value) -> String {
+    match phase {
+        Value::String(name) => name.clone(),
+        Value::Object(map) => match map.iter().next() {
+            Some((name, fields)) => match (fields.get("current"), fields.get("total")) {
+                (Some(current), Some(total)) => format!("{name} {current}/{total}"),
+                _ => name.clone(),
+            },
+            None => "Unknown".to_string(),
+        },
+        _ => "Unknown".to_string(),
+    }
+}
+
+/// Left-aligned columns, each `indent`ed.
+pub(crate) fn table<const N: usize>(header: [&str; N], rows: &[[String; N]], indent: &str) -> String {
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        format!("{indent}{}\n", padded.join("  ").trim_end())
+    };
+    let mut out = line(header.to_vec());
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+pub(crate) fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "-".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_metadata() {
+        let meta = BuildMetadata {
+            package: "api".into(),
+            version: "1.2.0".into(),
+            git_sha: None,
+            build_time: 1_700_000_000,
+            language: "go".into(),
+            shims: vec!["dns".into(), "timezone".into()],
+        };
+        let text = format_metadata(&meta);
+        assert!(text.starts_with("api@1.2.0\n"), "{text}");
+        assert!(text.contains("Commit:   unknown"), "{text}");
+        assert!(text.contains("Shims:    dns, timezone"), "{text}");
+    }
+
+    #[test]
+    fn test_format_deployment() {
+        let status = json!({
+            "deployment": {"id": "default/api", "source": "file:///srv/api.wasm", "instances": {"min": 2, "max": 4}, "paused": false},
+            "health": {"status": "degraded", "available": 1, "desired": 2, "reasons": [{"message": "1 of 2 instances unhealthy"}]},
+            "instances": [
+                {"id": "inst-0", "node_id": "node-1", "status": "running", "health": "healthy", "restart_count": 0},
+                {"id": "inst-1", "node_id": "node-1", "status": "unhealthy", "health": "unhealthy", "restart_count": 3},
+            ],
+            "rollout": {"phase": {"RollingBatch": {"current": 1, "total": 2}}, "old_version": "v1", "new_version": "v2"},
+            "recent_errors": [{"timestamp_ms": 3_723_045, "instance": "req-7", "line": "db timeout"}],
+        });
+        let text = format_deployment(&status);
+        assert!(text.starts_with("default/api  degraded  1/2 available\n"), "{text}");
+        assert!(text.contains("Rollout:   RollingBatch 1/2 (v1 → v2)"), "{text}");
+        assert!(text.contains("  INSTANCE  NODE    STATUS     HEALTH     RESTARTS\n"), "{text}");
+        assert!(text.contains("  inst-1    node-1  unhealthy  unhealthy  3\n"), "{text}");
+        assert!(text.contains("- 1 of 2 instances unhealthy"), "{text}");
+        assert!(text.contains("01:02:03.045 req-7 db timeout"), "{text}");
+    }
+
+    #[test]
+    fn test_format_cluster() {
+        let rows = [
+            json!({"id": "default/api", "paused": false, "health": {"status": "healthy", "available": 2, "desired": 2}, "rollout": null}),
+            json!({"id": "default/web", "paused": true, "health": null, "rollout": {"phase": "Paused"}}),
+        ];
+        assert_eq!(
+            format_cluster(&rows),
+            "DEPLOYMENT   STATUS   READY  ROLLOUT\ndefault/api  healthy  2/2    -\ndefault/web  paused   0/0    Paused\n"
+        );
+        assert_eq!(format_cluster(&[]), "No deployments\n");
+    }
+}
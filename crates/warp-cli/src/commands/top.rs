@@ -0,0 +1,649 @@
+//! `warp top`: a live terminal view of a cluster.
+//!
+//! Subscribes to the API's `/watch` event stream and redraws on every
+//! update: deployments with instance counts, request rate, p99 latency,
+//! error rate, total and per-instance memory, and rollout phase; node utilization; and a log of
+//! recent changes. The stream reconnects on its own if warpd restarts.
+//!
+//! Keys: `↑`/`↓` (or `k`/`j`) select a deployment, `s` scales it, `p`
+//! pauses or resumes its rollout, `q` quits.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table, TableState};
+use serde::Deserialize;
+
+use crate::api::{self, ApiClient};
+
+/// Recent events kept for the events pane.
+const EVENT_HISTORY: usize = 200;
+
+/// Wait before reconnecting a dropped event stream.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// How long to wait for a key before checking for updates again.
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+// ── Watch stream documents (see warpgrid-api `watch`) ─────────────
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Overview {
+    revision: u64,
+    #[serde(default)]
+    deployments: Vec<Deployment>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Deployment {
+    id: String,
+    min_instances: u32,
+    max_instances: u32,
+    instances: u32,
+    running: u32,
+    metrics: Option<Metrics>,
+    rollout: Option<Rollout>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Metrics {
+    rps: f64,
+    latency_p99_ms: f64,
+    error_rate: f64,
+    total_memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Rollout {
+    phase: serde_json::Value,
+    new_version: String,
+}
+
+impl Rollout {
+    /// The phase name, with batch progress for rolling updates.
+    fn phase(&self) -> String {
+        super::status::phase_label(&self.phase)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.phase == "Paused"
+    }
+
+    fn is_active(&self) -> bool {
+        let phase = self.phase();
+        phase != "Completed" && phase != "RolledBack"
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Node {
+    id: String,
+    capacity_memory_bytes: u64,
+    capacity_cpu_weight: u32,
+    used_memory_bytes: u64,
+    used_cpu_weight: u32,
+    pressure_until: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Change {
+    table: String,
+    key: String,
+    op: String,
+}
+
+// ── App state ─────────────────────────────────────────────────────
+
+/// Something that changes what the screen shows.
+enum Update {
+    Connected,
+    Disconnected(String),
+    Overview(Overview),
+    Change(Change),
+    /// Outcome of a scale or rollout action.
+    Action(Result<String, String>),
+}
+
+/// A request to the API triggered by a key.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Scale { id: String, target: u32 },
+    PauseRollout(String),
+    ResumeRollout(String),
+}
+
+impl Action {
+    /// Status line while the request is in flight.
+    fn pending(&self) -> String {
+        match self {
+            Action::Scale { id, target } => format!("scaling {id} to {target}…"),
+            Action::PauseRollout(id) => format!("pausing the rollout of {id}…"),
+            Action::ResumeRollout(id) => format!("resuming the rollout of {id}…"),
+        }
+    }
+}
+
+enum Mode {
+    Browse,
+    /// Typing the target instance count for the selected deployment.
+    Scale(String),
+}
+
+struct App {
+    url: String,
+    connection: Result<(), String>,
+    overview: Option<Overview>,
+    selected: usize,
+    events: VecDeque<String>,
+    status: Option<String>,
+    mode: Mode,
+    quit: bool,
+}
+
+impl App {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            connection: Err("connecting".to_string()),
+            overview: None,
+            selected: 0,
+            events: VecDeque::new(),
+            status: None,
+            mode: Mode::Browse,
+            quit: false,
+        }
+    }
+
+    fn log(&mut self, message: String) {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let (h, m, s) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        self.events.push_front(format!("{h:02}:{m:02}:{s:02} {message}"));
+        self.events.truncate(EVENT_HISTORY);
+    }
+
+    fn deployments(&self) -> &[Deployment] {
+        self.overview.as_ref().map_or(&[], |o| &o.deployments)
+    }
+
+    fn selected(&self) -> Option<&Deployment> {
+        self.deployments().get(self.selected)
+    }
+
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Connected => {
+                if self.connection.is_err() {
+                    self.log(format!("connected to {}", self.url));
+                }
+                self.connection = Ok(());
+            }
+            Update::Disconnected(reason) => {
+                if self.connection.is_ok() {
+                    self.log(format!("disconnected: {reason}"));
+                }
+                self.connection = Err(reason);
+            }
+            Update::Overview(overview) => {
+                for message in overview_changes(self.overview.as_ref(), &overview) {
+                    self.log(message);
+                }
+                // Keep the selection on the same deployment as rows come and go.
+                let selected_id = self.selected().map(|d| d.id.clone());
+                self.selected = selected_id
+                    .and_then(|id| overview.deployments.iter().position(|d| d.id == id))
+                    .unwrap_or(self.selected)
+                    .min(overview.deployments.len().saturating_sub(1));
+                self.overview = Some(overview);
+            }
+            Update::Change(change) => {
+                let verb = if change.op == "delete" { "removed" } else { "updated" };
+                let table = change.table.trim_end_matches('s');
+                self.log(format!("{table} {} {verb}", change.key));
+            }
+            Update::Action(Ok(message)) => {
+                self.log(message.clone());
+                self.status = Some(message);
+            }
+            Update::Action(Err(error)) => self.status = Some(format!("error: {error}")),
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return None;
+        }
+        match &mut self.mode {
+            Mode::Scale(input) => match key.code {
+                KeyCode::Char(c) if c.is_ascii_digit() && input.len() < 6 => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Enter => {
+                    let target = input.parse().ok();
+                    self.mode = Mode::Browse;
+                    let id = self.selected()?.id.clone();
+                    match target {
+                        Some(target) => return Some(Action::Scale { id, target }),
+                        None => self.status = Some("scale: enter a number of instances".to_string()),
+                    }
+                }
+                _ => {}
+            },
+            Mode::Browse => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+                KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let last = self.deployments().len().saturating_sub(1);
+                    self.selected = (self.selected + 1).min(last);
+                }
+                KeyCode::Char('s') if self.selected().is_some() => {
+                    self.mode = Mode::Scale(String::new());
+                    self.status = None;
+                }
+                KeyCode::Char('p') => {
+                    let deployment = self.selected()?;
+                    let id = deployment.id.clone();
+                    match &deployment.rollout {
+                        Some(rollout) if rollout.is_paused() => return Some(Action::ResumeRollout(id)),
+                        Some(rollout) if rollout.is_active() => return Some(Action::PauseRollout(id)),
+                        _ => self.status = Some(format!("{id} has no active rollout")),
+                    }
+                }
+                _ => {}
+            },
+        }
+        None
+    }
+}
+
+/// Log lines for what changed between two overviews that the change
+/// journal does not cover: rollout phases and node membership.
+fn overview_changes(old: Option<&Overview>, new: &Overview) -> Vec<String> {
+    let Some(old) = old else {
+        return Vec::new();
+    };
+    let mut messages = Vec::new();
+    let phases = |o: &Overview| -> BTreeMap<String, String> {
+        o.deployments
+            .iter()
+            .filter_map(|d| Some((d.id.clone(), d.rollout.as_ref()?.phase())))
+            .collect()
+    };
+    let (before, after) = (phases(old), phases(new));
+    for (id, phase) in &after {
+        if before.get(id) != Some(phase) {
+            messages.push(format!("rollout {id}: {phase}"));
+        }
+    }
+    for node in &new.nodes {
+        if !old.nodes.iter().any(|n| n.id == node.id) {
+            messages.push(format!("node {} joined", node.id));
+        }
+    }
+    for node in &old.nodes {
+        if !new.nodes.iter().any(|n| n.id == node.id) {
+            messages.push(format!("node {} left", node.id));
+        }
+    }
+    messages
+}
+
+// ── Entry point ───────────────────────────────────────────────────
+
+/// Run the monitor until the user quits.
+pub fn top(client: ApiClient, interval_ms: u64) -> anyhow::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    {
+        let client = client.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || watch(client, interval_ms, tx));
+    }
+
+    let mut app = App::new(client.url());
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app, &client, &tx, &rx);
+    ratatui::restore();
+    result
+}
+
+fn run(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+    client: &ApiClient,
+    tx: &Sender<Update>,
+    rx: &Receiver<Update>,
+) -> anyhow::Result<()> {
+    while !app.quit {
+        while let Ok(update) = rx.try_recv() {
+            app.apply(update);
+        }
+        terminal.draw(|frame| draw(frame, app))?;
+        if event::poll(INPUT_POLL)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && let Some(action) = app.key(key)
+        {
+            app.status = Some(action.pending());
+            perform(client.clone(), action, tx.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Follow the watch stream, reconnecting until the receiver goes away.
+fn watch(client: ApiClient, interval_ms: u64, tx: Sender<Update>) {
+    let path = format!("/watch?interval_ms={interval_ms}");
+    loop {
+        let reason = match client.events(&path) {
+            Ok(events) => {
+                if tx.send(Update::Connected).is_err() {
+                    return;
+                }
+                let mut reason = "stream closed".to_string();
+                for event in events {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(e) => {
+                            reason = format!("{e:#}");
+                            break;
+                        }
+                    };
+                    let update = match event.name.as_str() {
+                        "overview" => serde_json::from_str(&event.data).map(Update::Overview),
+                        "change" => serde_json::from_str(&event.data).map(Update::Change),
+                        _ => continue,
+                    };
+                    // A document this version cannot read is skipped, not fatal.
+                    if let Ok(update) = update
+                        && tx.send(update).is_err()
+                    {
+                        return;
+                    }
+                }
+                reason
+            }
+            Err(e) => format!("{e:#}"),
+        };
+        if tx.send(Update::Disconnected(reason)).is_err() {
+            return;
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Send `action` to the API in the background and report the outcome.
+fn perform(client: ApiClient, action: Action, tx: Sender<Update>) {
+    std::thread::spawn(move || {
+        let result = match &action {
+            Action::Scale { id, target } => client
+                .post::<serde_json::Value>(
+                    &format!("/deployments/{}/scale", api::path_segment(id)),
+                    &serde_json::json!({ "target": target }),
+                )
+                .map(|_| format!("{id}: scaling to {target} instances")),
+            Action::PauseRollout(id) => client
+                .post::<serde_json::Value>(&format!("/rollouts/{}/pause", api::path_segment(id)), &serde_json::json!({}))
+                .map(|_| format!("{id}: rollout paused")),
+            Action::ResumeRollout(id) => client
+                .post::<serde_json::Value>(&format!("/rollouts/{}/resume", api::path_segment(id)), &serde_json::json!({}))
+                .map(|_| format!("{id}: rollout resumed")),
+        };
+        let _ = tx.send(Update::Action(result.map_err(|e| format!("{e:#}"))));
+    });
+}
+
+// ── Rendering ─────────────────────────────────────────────────────
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [header, deployments, bottom, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(6),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [nodes, events] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(bottom);
+
+    frame.render_widget(Paragraph::new(header_line(app)), header);
+
+    let mut state = TableState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(deployments_table(app.deployments()), deployments, &mut state);
+
+    let node_list = app.overview.as_ref().map_or(&[][..], |o| &o.nodes);
+    frame.render_widget(nodes_table(node_list), nodes);
+
+    let items: Vec<ListItem> = app.events.iter().map(|e| ListItem::new(e.as_str())).collect();
+    frame.render_widget(List::new(items).block(Block::bordered().title(" Events ")), events);
+
+    frame.render_widget(Paragraph::new(footer_line(app)), footer);
+}
+
+fn header_line(app: &App) -> Line<'static> {
+    let connection = match &app.connection {
+        Ok(()) => "live".green(),
+        Err(reason) => format!("offline ({reason})").red(),
+    };
+    let summary = match &app.overview {
+        Some(o) => {
+            let instances: u32 = o.deployments.iter().map(|d| d.instances).sum();
+            format!(
+                "  {} deployments  {instances} instances  {} nodes  rev {}",
+                o.deployments.len(),
+                o.nodes.len(),
+                o.revision
+            )
+        }
+        None => String::new(),
+    };
+    Line::from(vec![
+        " warp top ".bold().reversed(),
+        format!(" {} ", app.url).into(),
+        connection,
+        summary.into(),
+    ])
+}
+
+fn footer_line(app: &App) -> Line<'static> {
+    match (&app.mode, &app.status) {
+        (Mode::Scale(input), _) => {
+            let id = app.selected().map_or("", |d| d.id.as_str());
+            Line::from(format!(" Scale {id} to: {input}▏  (enter to apply, esc to cancel)")).yellow()
+        }
+        (Mode::Browse, Some(status)) => Line::from(format!(" {status}")),
+        (Mode::Browse, None) => {
+            Line::from(" ↑/↓ select   s scale   p pause/resume rollout   q quit").dim()
+        }
+    }
+}
+
+fn deployments_table(deployments: &[Deployment]) -> Table<'static> {
+    let rows = deployments.iter().map(|d| {
+        let (rps, p99, errors, memory, per_instance) = match &d.metrics {
+            Some(m) => (
+                format!("{:.1}", m.rps),
+                format!("{:.0}ms", m.latency_p99_ms),
+                format!("{:.1}%", m.error_rate * 100.0),
+                format_bytes(m.total_memory_bytes),
+                instance_memory(m.total_memory_bytes, d.running),
+            ),
+            None => ("-".into(), "-".into(), "-".into(), "-".into(), "-".into()),
+        };
+        let rollout = d
+            .rollout
+            .as_ref()
+            .map_or_else(String::new, |r| format!("{} → {}", r.phase(), r.new_version));
+        let row = Row::new(vec![
+            d.id.clone(),
+            format!("{}/{}", d.running, d.instances),
+            format!("{}-{}", d.min_instances, d.max_instances),
+            rps,
+            p99,
+            errors,
+            memory,
+            per_instance,
+            rollout,
+        ]);
+        match &d.metrics {
+            Some(m) if m.error_rate >= 0.05 => row.red(),
+            _ if d.running < d.min_instances => row.yellow(),
+            _ => row,
+        }
+    });
+    Table::new(
+        rows,
+        [
+            Constraint::Min(20),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Min(16),
+        ],
+    )
+    .header(
+        Row::new(["DEPLOYMENT", "RUNNING", "MIN-MAX", "RPS", "P99", "ERRORS", "MEMORY", "MEM/INST", "ROLLOUT"])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::new().bg(Color::DarkGray))
+    .block(Block::bordered().title(" Deployments "))
+}
+
+fn nodes_table(nodes: &[Node]) -> Table<'static> {
+    let rows = nodes.iter().map(|n| {
+        let cpu = percent(n.used_cpu_weight as u64, n.capacity_cpu_weight as u64);
+        let memory = percent(n.used_memory_bytes, n.capacity_memory_bytes);
+        let row = Row::new(vec![
+            n.id.clone(),
+            format!("{} {cpu:>3}%", bar(cpu)),
+            format!("{} {memory:>3}%", bar(memory)),
+        ]);
+        if n.pressure_until.is_some() { row.red() } else { row }
+    });
+    Table::new(rows, [Constraint::Min(12), Constraint::Length(16), Constraint::Length(16)])
+        .header(Row::new(["NODE", "CPU", "MEMORY"]).style(Style::new().add_modifier(Modifier::BOLD)))
+        .block(Block::bordered().title(" Nodes "))
+}
+
+/// Average memory of a running instance.
+fn instance_memory(total: u64, running: u32) -> String {
+    if running == 0 { "-".to_string() } else { format_bytes(total / running as u64) }
+}
+
+pub(crate) fn percent(used: u64, capacity: u64) -> u64 {
+    (used * 100).checked_div(capacity).map_or(0, |percent| percent.min(100))
+}
+
+/// A ten-cell utilization bar.
+pub(crate) fn bar(percent: u64) -> String {
+    let filled = (percent as usize).div_ceil(10).min(10);
+    format!("{}{}", "█".repeat(filled), "░".repeat(10 - filled))
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    let mib = bytes as f64 / MIB;
+    if mib >= 1024.0 { format!("{:.1}G", mib / 1024.0) } else { format!("{mib:.0}M") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overview(json: serde_json::Value) -> Overview {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn sample() -> Overview {
+        overview(serde_json::json!({
+            "revision": 4,
+            "deployments": [
+                {"id": "prod/api", "min_instances": 1, "max_instances": 8, "instances": 2, "running": 2,
+                 "metrics": null,
+                 "rollout": {"phase": {"RollingBatch": {"current": 1, "total": 3}}, "new_version": "v2",
+                             "deployment_id": "prod/api", "old_version": "v1", "target_instances": 2}},
+                {"id": "prod/web", "min_instances": 1, "max_instances": 2, "instances": 1, "running": 1,
+                 "metrics": {"rps": 3.0, "latency_p99_ms": 12.0, "error_rate": 0.0, "total_memory_bytes": 0},
+                 "rollout": null}
+            ],
+            "nodes": [{"id": "node-1", "capacity_memory_bytes": 100, "capacity_cpu_weight": 10,
+                       "used_memory_bytes": 50, "used_cpu_weight": 1, "pressure_until": null}]
+        }))
+    }
+
+    #[test]
+    fn test_keys_map_to_actions() {
+        let mut app = App::new("http://127.0.0.1:8443".into());
+        app.apply(Update::Overview(sample()));
+
+        // Rolling batch: `p` pauses.
+        assert_eq!(app.key(key(KeyCode::Char('p'))), Some(Action::PauseRollout("prod/api".into())));
+
+        app.key(key(KeyCode::Down));
+        app.key(key(KeyCode::Char('s')));
+        app.key(key(KeyCode::Char('4')));
+        app.key(key(KeyCode::Char('x')));
+        assert_eq!(
+            app.key(key(KeyCode::Enter)),
+            Some(Action::Scale { id: "prod/web".into(), target: 4 })
+        );
+        // No rollout on prod/web.
+        assert_eq!(app.key(key(KeyCode::Char('p'))), None);
+        assert!(app.status.as_deref().unwrap().contains("no active rollout"));
+
+        app.key(key(KeyCode::Char('q')));
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_overview_updates_are_logged_and_keep_selection() {
+        let mut app = App::new("http://127.0.0.1:8443".into());
+        app.apply(Update::Overview(sample()));
+        app.key(key(KeyCode::Down));
+        assert!(app.events.is_empty());
+
+        let mut next = sample();
+        next.deployments.remove(0);
+        next.nodes.clear();
+        app.apply(Update::Overview(next));
+        app.apply(Update::Change(Change {
+            table: "deployments".into(),
+            key: "prod/api".into(),
+            op: "delete".into(),
+        }));
+
+        assert_eq!(app.selected().unwrap().id, "prod/web");
+        let log: Vec<_> = app.events.iter().map(|e| &e[9..]).collect();
+        assert_eq!(log, ["deployment prod/api removed", "node node-1 left"]);
+    }
+
+    #[test]
+    fn test_rollout_phase_and_formatting() {
+        let rollout = |phase| Rollout { phase, new_version: "v2".into() };
+        assert_eq!(rollout(serde_json::json!("Paused")).phase(), "Paused");
+        assert!(rollout(serde_json::json!("Paused")).is_paused());
+        assert!(!rollout(serde_json::json!({"RolledBack": {"reason": "x"}})).is_active());
+        assert_eq!(bar(35), "████░░░░░░");
+        assert_eq!(format_bytes(64 * 1024 * 1024), "64M");
+        assert_eq!(percent(5, 0), 0);
+        assert_eq!(instance_memory(192 * 1024 * 1024, 3), "64M");
+        assert_eq!(instance_memory(0, 0), "-");
+    }
+}
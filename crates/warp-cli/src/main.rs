@@ -1,6 +1,10 @@
-use clap::{Parser, Subcommand};
+use std::ffi::OsString;
 
+use clap::{CommandFactory, Parser, Subcommand};
+
+mod api;
 mod commands;
+mod output;
 mod templates;
 
 #[derive(Parser)]
@@ -13,6 +17,10 @@ mod templates;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Output format for every command that prints data, overriding its
+    /// --format
+    #[arg(short, long, global = true, value_enum)]
+    output: Option<output::Output>,
 }
 
 #[derive(Subcommand)]
@@ -24,19 +32,67 @@ enum Commands {
     },
     /// Package a project as a Wasm component.
     ///
-    /// Supported languages: rust, go, js, typescript, bun.
+    /// Supported languages: rust, go, js, typescript, bun, python, dotnet.
     ///
     /// Language is read from [build].lang in warp.toml, or auto-detected
     /// from project marker files (bunfig.toml → bun, Cargo.toml → rust,
-    /// go.mod → go, package.json → typescript/js). Use --lang to override.
+    /// go.mod → go, pyproject.toml/requirements.txt → python,
+    /// *.csproj → dotnet, package.json → typescript/js). Use --lang to override.
+    ///
+    /// A warp.toml with a [components] table packs every listed component
+    /// and writes a combined manifest to dist/components.json.
     Pack {
         /// Project directory (default: current directory)
         #[arg(short, long, default_value = ".")]
         path: String,
-        /// Override the build language (rust, go, js, typescript, bun).
+        /// Override the build language (rust, go, js, typescript, bun, python, dotnet).
         /// If not specified, reads from warp.toml or auto-detects.
         #[arg(short, long)]
         lang: Option<String>,
+        /// Always recompile, ignoring and not updating the build cache.
+        #[arg(long)]
+        no_cache: bool,
+        /// Keep running and repack whenever a source file changes.
+        #[arg(short, long)]
+        watch: bool,
+        /// With --watch, POST each new artifact to a running `warp dev`
+        /// server so it hot-swaps the component
+        /// (e.g. http://localhost:3000/__warp/reload).
+        #[arg(long, requires = "watch", value_name = "URL")]
+        notify: Option<String>,
+        /// Derive the language, entry point, and dependency steps from a
+        /// Dockerfile (default: <path>/Dockerfile) instead of requiring a
+        /// warp.toml [build] section. Native compile steps are replaced by
+        /// componentization.
+        #[arg(
+            long,
+            value_name = "DOCKERFILE",
+            num_args = 0..=1,
+            default_missing_value = "Dockerfile",
+            conflicts_with = "watch"
+        )]
+        from_dockerfile: Option<String>,
+    },
+    /// Serve the project locally and hot-reload it on every change.
+    ///
+    /// Packs the project, serves its HTTP trigger from an embedded runtime,
+    /// and swaps in the new component after each rebuild. No warpd needed.
+    Dev {
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value = ".")]
+        path: String,
+        /// Port to serve on (127.0.0.1)
+        #[arg(long, default_value = "3000")]
+        port: u16,
+        /// Override the build language (rust, go, js, typescript, bun, python, dotnet).
+        #[arg(short, long)]
+        lang: Option<String>,
+        /// Run a Bun handler directly in Bun instead of as a component
+        #[arg(long)]
+        native: bool,
+        /// Always recompile, ignoring and not updating the build cache.
+        #[arg(long)]
+        no_cache: bool,
     },
     /// Scaffold a new WarpGrid project from a template.
     ///
@@ -49,12 +105,555 @@ enum Commands {
         #[arg(short, long)]
         path: Option<String>,
     },
-    // Phase 3+:
-    // Deploy { ... },
-    // Status { ... },
-    // Logs { ... },
-    // Scale { ... },
-    // Nodes { ... },
+    /// Show a deployment's status, or which build a packed artifact is.
+    ///
+    /// With a deployment: health, instances, rollout, and recent errors.
+    /// With no argument: one line per deployment. With a .wasm path: the
+    /// package, version, commit, build time, language, and enabled shims.
+    Status {
+        /// Packed .wasm artifact, or deployment name or namespace/name
+        target: Option<String>,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// Namespace of a deployment given by name [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Pack the project and deploy it to a cluster.
+    ///
+    /// Creates or updates the deployment `<namespace>/<package name>` from
+    /// warp.toml, then waits until its minimum number of instances is ready.
+    Deploy {
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value = ".")]
+        path: String,
+        /// Deploy this packed .wasm artifact instead of packing the project
+        #[arg(long, value_name = "WASM")]
+        artifact: Option<String>,
+        /// Source URI to record in the spec [default: file:// path of the
+        /// artifact, which the daemon must be able to read]
+        #[arg(long, value_name = "URI")]
+        source: Option<String>,
+        /// Namespace to deploy into [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// Return once the spec is accepted instead of waiting for instances
+        #[arg(long)]
+        no_wait: bool,
+        /// How long to wait for instances, in seconds
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Converge the cluster on a manifest of deployments.
+    ///
+    /// Compares each deployment's source, scaling, resources, shims, and
+    /// route with the cluster, prints the plan, and creates or updates what
+    /// differs.
+    Apply {
+        /// Manifest file (.toml, .yaml, or .yml)
+        #[arg(short, long, value_name = "FILE")]
+        file: String,
+        /// Delete deployments in the manifest's namespaces that it does not list
+        #[arg(long)]
+        prune: bool,
+        /// Print the plan without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Namespace for deployments the manifest does not place [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// Output format: text, json, or yaml
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Push packed components to an OCI registry and pull them back.
+    ///
+    /// Wraps the oras CLI; credentials live in the Docker config.
+    Registry {
+        #[command(subcommand)]
+        action: RegistryAction,
+    },
+    /// Change how many instances a deployment runs.
+    ///
+    /// Waits until the running count is within the new bounds, then lists
+    /// the instances.
+    Scale {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Namespace of the deployment [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// Number of instances to keep running (sets the minimum)
+        #[arg(long, value_name = "N")]
+        replicas: Option<u32>,
+        /// Fewest instances the autoscaler may scale down to
+        #[arg(long, value_name = "N")]
+        min: Option<u32>,
+        /// Most instances the autoscaler may scale up to
+        #[arg(long, value_name = "N")]
+        max: Option<u32>,
+        /// Return once the change is accepted instead of waiting for instances
+        #[arg(long)]
+        no_wait: bool,
+        /// How long to wait for instances, in seconds
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Call a component export on one of a deployment's instances.
+    Exec {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Export to call: `function`, or `interface#function` for one in
+        /// an exported interface
+        export: String,
+        /// Arguments as a JSON array, e.g. '[1, "two"]'
+        #[arg(long, value_name = "ARGS")]
+        json: Option<String>,
+        /// Namespace of the deployment [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// Output format: text (the result), or json or yaml (with timing)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Forward a local port to a deployment's HTTP trigger through warpd.
+    ///
+    /// `<local>:<remote>` listens on 127.0.0.1:<local> and sends each
+    /// connection to the deployment as if it had arrived on ingress port
+    /// <remote>, whatever its host name. One port means the same on both.
+    PortForward {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Ports as <local>:<remote> (e.g. 9000:8080)
+        #[arg(value_name = "LOCAL:REMOTE")]
+        ports: String,
+        /// Namespace of the deployment [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Send load to a deployment and report latency and errors.
+    ///
+    /// Requests go through a port-forward tunnel unless --url names an
+    /// ingress URL. The report is compared with the server's metrics over
+    /// the same period.
+    Bench {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Requests per second
+        #[arg(long, default_value = "10")]
+        rps: u32,
+        /// How long to send for (e.g. 30s, 2m)
+        #[arg(long, default_value = "60s", value_parser = commands::logs::parse_since)]
+        duration: std::time::Duration,
+        /// Connections to send from
+        #[arg(short, long, default_value = "16")]
+        concurrency: usize,
+        /// Request path
+        #[arg(long, default_value = "/", conflicts_with = "url")]
+        path: String,
+        /// Ingress port to tunnel to
+        #[arg(long, default_value = "8080", conflicts_with = "url")]
+        port: u16,
+        /// Send to this ingress URL instead of tunneling through the API
+        #[arg(long, value_name = "URL")]
+        url: Option<String>,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// Namespace of the deployment [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Print a deployment's guest stdout and stderr.
+    Logs {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Namespace of the deployment [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// Keep printing new lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+        /// Only lines from this far back (e.g. 30s, 10m, 2h)
+        #[arg(long, value_parser = commands::logs::parse_since)]
+        since: Option<std::time::Duration>,
+        /// Only lines from this instance
+        #[arg(long)]
+        instance: Option<String>,
+        /// Only lines matching this regular expression
+        #[arg(long, value_name = "REGEX")]
+        grep: Option<String>,
+        /// Start with the last N lines
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Roll a new version out to a deployment, and follow, pause, resume,
+    /// or roll back the rollout.
+    Rollout {
+        #[command(subcommand)]
+        action: RolloutAction,
+        /// Namespace of a deployment given by name [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long, global = true)]
+        namespace: Option<String>,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL", global = true)]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long, global = true)]
+        token: Option<String>,
+    },
+    /// Set, read, list, and delete cluster secrets.
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+        /// Namespace of a secret given by name [default:
+        /// $WARP_NAMESPACE, namespace in ~/.warp/config.toml, or default]
+        #[arg(short, long, global = true)]
+        namespace: Option<String>,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL", global = true)]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long, global = true)]
+        token: Option<String>,
+    },
+    /// List and inspect nodes, and take them out of rotation.
+    Nodes {
+        #[command(subcommand)]
+        action: NodesAction,
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL", global = true)]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long, global = true)]
+        token: Option<String>,
+    },
+    /// Live view of a cluster: deployments, instances, request rate,
+    /// latency, node utilization, and recent events.
+    ///
+    /// Select a deployment with the arrow keys, then press `s` to scale it
+    /// or `p` to pause/resume its rollout. `q` quits.
+    Top {
+        /// warpd API endpoint [default: $WARP_API_URL, api_url in
+        /// ~/.warp/config.toml, or http://127.0.0.1:8443]
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API [default: $WARP_API_TOKEN or token in
+        /// ~/.warp/config.toml]
+        #[arg(long)]
+        token: Option<String>,
+        /// How often the server checks for changes, in milliseconds
+        #[arg(long, default_value = "1000")]
+        interval_ms: u64,
+    },
+    /// Switch between clusters: named API endpoints, tokens, and default
+    /// namespaces kept in ~/.warp/config.toml.
+    Context {
+        #[command(subcommand)]
+        action: ContextAction,
+    },
+    /// Manage CLI plugins (`warp-<name>` executables on PATH).
+    ///
+    /// `warp <name> ...` runs `warp-<name> ...` for any name that is not a
+    /// built-in command.
+    Plugin {
+        #[command(subcommand)]
+        action: PluginAction,
+    },
+    /// Print a shell completion script.
+    ///
+    /// For example `warp completions bash > /etc/bash_completion.d/warp`,
+    /// `warp completions zsh > "${fpath[1]}/_warp"`, or
+    /// `warp completions fish > ~/.config/fish/completions/warp.fish`.
+    Completions {
+        shell: clap_complete::Shell,
+    },
+    /// Any other subcommand runs the matching `warp-<name>` plugin.
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+#[derive(Subcommand)]
+enum RolloutAction {
+    /// Start rolling out a new version
+    #[command(disable_version_flag = true)]
+    Start {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Source URI of the new version (e.g. oci://registry/app:v2)
+        #[arg(long, value_name = "URI")]
+        version: String,
+        /// rolling, canary, or blue-green
+        #[arg(long, default_value = "rolling")]
+        strategy: String,
+        /// Instances replaced per batch (rolling)
+        #[arg(long, value_name = "N")]
+        batch_size: Option<u32>,
+        /// Seconds between batches (rolling)
+        #[arg(long, value_name = "SECS")]
+        batch_interval: Option<u64>,
+        /// Percentage of traffic sent to the canary (canary)
+        #[arg(long, value_name = "PERCENT")]
+        canary_percent: Option<u32>,
+        /// Canary instances to start (canary)
+        #[arg(long, value_name = "N")]
+        canary_instances: Option<u32>,
+        /// Seconds to observe the canary before promoting it (canary)
+        #[arg(long, value_name = "SECS")]
+        observe: Option<u64>,
+        /// Follow progress until the rollout completes or is rolled back
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Show a deployment's rollout
+    Status {
+        /// Deployment name, or namespace/name
+        deployment: String,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// Follow progress until the rollout completes or is rolled back
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Pause a rollout before its next step
+    Pause {
+        /// Deployment name, or namespace/name
+        deployment: String,
+    },
+    /// Resume a paused rollout
+    Resume {
+        /// Deployment name, or namespace/name
+        deployment: String,
+    },
+    /// Abandon an unfinished rollout and go back to the old version
+    Rollback {
+        /// Deployment name, or namespace/name
+        deployment: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NodesAction {
+    /// List nodes with their status and resource usage
+    List {
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// Show a node and the instances placed on it
+    Describe {
+        node: String,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// Stop placing new instances on a node
+    Cordon { node: String },
+    /// Allow placements on a cordoned node again
+    Uncordon { node: String },
+    /// Cordon a node and evict its instances
+    Drain {
+        node: String,
+        /// Also evict instances a disruption budget would keep
+        #[arg(long)]
+        force: bool,
+    },
+    /// Set node labels (key=value) or remove them (key-)
+    Label {
+        node: String,
+        #[arg(required = true)]
+        labels: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsAction {
+    /// Create or replace a secret
+    Set {
+        /// Secret name, or namespace/name
+        secret: String,
+        /// The value (visible in shell history; prefer --stdin or --from-file)
+        value: Option<String>,
+        /// Read the value from a file
+        #[arg(long, value_name = "PATH")]
+        from_file: Option<String>,
+        /// Read the value from standard input
+        #[arg(long)]
+        stdin: bool,
+    },
+    /// Print a secret's value, masked unless --reveal is given
+    Get {
+        /// Secret name, or namespace/name
+        secret: String,
+        /// Print the value instead of a mask
+        #[arg(long)]
+        reveal: bool,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// List the secrets in a namespace (values are never listed)
+    List {
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// Delete a secret
+    Delete {
+        /// Secret name, or namespace/name
+        secret: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RegistryAction {
+    /// Log in to a registry
+    Login {
+        /// Registry host (e.g. ghcr.io)
+        registry: String,
+        #[arg(short, long)]
+        username: Option<String>,
+        /// Read the password or token from standard input
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Push a packed component
+    Push {
+        /// Reference as registry/repository[:tag] (tag defaults to latest)
+        reference: String,
+        /// Additional tags for the same artifact
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Push this packed .wasm artifact instead of packing the project
+        #[arg(long, value_name = "WASM")]
+        artifact: Option<String>,
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value = ".")]
+        path: String,
+    },
+    /// Pull a component and show its build metadata
+    Pull {
+        /// Reference as registry/repository[:tag|@digest]
+        reference: String,
+        /// Directory to write the artifact to
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        dir: String,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContextAction {
+    /// List contexts, marking the current one
+    List {
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// Make a context the current one
+    Use { name: String },
+    /// Create a context, or update the given fields of an existing one
+    Set {
+        name: String,
+        /// warpd API endpoint
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+        /// Bearer token for the API
+        #[arg(long)]
+        token: Option<String>,
+        /// Default namespace
+        #[arg(short, long)]
+        namespace: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PluginAction {
+    /// List plugins found on PATH
+    List,
 }
 
 #[derive(Subcommand)]
@@ -64,19 +663,75 @@ enum ConvertAction {
         /// Path to project directory or Dockerfile
         #[arg(short, long, default_value = ".")]
         path: String,
-        /// Output format: text or json
+        /// Output format: text, json, yaml, or sarif (SARIF 2.1.0, for
+        /// code scanning dashboards)
         #[arg(short, long, default_value = "text")]
         format: String,
-        /// Override the project language (rust, go, typescript, bun).
+        /// Override the project language (rust, go, typescript, bun, python).
         /// If not specified, auto-detects from project files.
         #[arg(short, long)]
         lang: Option<String>,
+        /// Report only verdict changes since the previous run, or since the
+        /// given JSON report. Exits 1 only when a change adds a blocker.
+        #[arg(long, value_name = "REPORT", num_args = 0..=1)]
+        diff: Option<Option<String>>,
+        /// Re-analyze dependencies, ignoring and not updating the cache in
+        /// .warp/analyze/.
+        #[arg(long)]
+        no_cache: bool,
     },
     /// Generate a warp.toml scaffold from analysis
     Init {
         #[arg(short, long, default_value = ".")]
         path: String,
     },
+    /// Replace incompatible dependencies with their known alternatives
+    /// in Cargo.toml or package.json
+    #[command(group = clap::ArgGroup::new("mode").required(true).args(["dry_run", "apply"]))]
+    Fix {
+        /// Path to project directory
+        #[arg(short, long, default_value = ".")]
+        path: String,
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// Override the project language (rust, typescript, bun)
+        #[arg(short, long)]
+        lang: Option<String>,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Rewrite the manifest
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Update the compatibility database from a signed remote source
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Fetch the latest rules and their `.sig`, verify the signature, and
+    /// cache them in ~/.warp/compat-sync
+    Update {
+        /// Rules document URL [default: $WARP_COMPAT_DB_URL or
+        /// compat_db_url in ~/.warp/config.toml]
+        #[arg(long)]
+        url: Option<String>,
+        /// Hex Ed25519 key the rules must be signed with [default:
+        /// $WARP_COMPAT_DB_KEY or compat_db_key in ~/.warp/config.toml]
+        #[arg(long)]
+        public_key: Option<String>,
+    },
+    /// Show the source and digest of the cached rules
+    Status {
+        /// Output format: text, json, or yaml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
@@ -85,14 +740,27 @@ fn main() -> anyhow::Result<()> {
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive("warp=info".parse()?)
         )
+        // Keep stdout for command output (`--format json|sarif` is piped).
+        .with_writer(std::io::stderr)
         .init();
 
     let cli = Cli::parse();
+    let output = cli.output;
 
     match cli.command {
         Commands::Convert { action } => match action {
-            ConvertAction::Analyze { path, format, lang } => {
-                let has_blockers = commands::convert::analyze(&path, &format, lang.as_deref())?;
+            ConvertAction::Analyze { path, format, lang, diff: Some(baseline), no_cache } => {
+                let format = output::format(output, &format);
+                let regressed =
+                    commands::convert::analyze_diff(&path, format, lang.as_deref(), baseline.as_deref(), no_cache)?;
+                if regressed {
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+            ConvertAction::Analyze { path, format, lang, diff: None, no_cache } => {
+                let format = output::format(output, &format);
+                let has_blockers = commands::convert::analyze(&path, format, lang.as_deref(), no_cache)?;
                 if has_blockers {
                     std::process::exit(1);
                 }
@@ -101,12 +769,264 @@ fn main() -> anyhow::Result<()> {
             ConvertAction::Init { path } => {
                 commands::convert::init(&path)
             }
+            ConvertAction::Fix { path, format, lang, apply, .. } => {
+                commands::convert::fix(&path, output::format(output, &format), lang.as_deref(), apply)
+            }
+            ConvertAction::Db { action: DbAction::Update { url, public_key } } => {
+                commands::convert::db_update(url.as_deref(), public_key.as_deref())
+            }
+            ConvertAction::Db { action: DbAction::Status { format } } => {
+                commands::convert::db_status(output::format(output, &format))
+            }
         },
-        Commands::Pack { path, lang } => {
-            commands::pack::pack(&path, lang.as_deref())
+        Commands::Pack { path, lang, no_cache, from_dockerfile: Some(dockerfile), .. } => {
+            commands::pack::pack_from_dockerfile(&path, &dockerfile, lang.as_deref(), no_cache)
+        }
+        Commands::Pack { path, lang, no_cache, watch: false, .. } => {
+            commands::pack::pack(&path, lang.as_deref(), no_cache)
+        }
+        Commands::Pack { path, lang, no_cache, watch: true, notify, .. } => {
+            commands::pack::watch(&path, lang.as_deref(), no_cache, notify.as_deref())
+        }
+        Commands::Dev { path, port, lang, native, no_cache } => {
+            commands::dev::dev(&commands::dev::DevOptions {
+                path: &path,
+                port,
+                lang: lang.as_deref(),
+                native,
+                no_cache,
+            })
         }
         Commands::Init { template, path } => {
             commands::init::init(&template, path.as_deref())
         }
+        Commands::Status { target: Some(path), format, .. } if std::path::Path::new(&path).is_file() => {
+            commands::status::artifact_status(&path, output::format(output, &format))
+        }
+        Commands::Status { target, format, namespace, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            match target {
+                Some(deployment) => {
+                    let id = api::deployment_id(&deployment, &namespace);
+                    commands::status::deployment_status(&client, &id, output::format(output, &format))
+                }
+                None => commands::status::cluster_status(&client, output::format(output, &format)),
+            }
+        }
+        Commands::Deploy { path, artifact, source, namespace, no_wait, timeout, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            commands::deploy::deploy(&client, &commands::deploy::DeployOptions {
+                path: &path,
+                artifact: artifact.as_deref(),
+                source: source.as_deref(),
+                namespace: &namespace,
+                wait: (!no_wait).then(|| std::time::Duration::from_secs(timeout)),
+            })
+        }
+        Commands::Apply { file, prune, dry_run, namespace, format, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            let options = commands::apply::ApplyOptions { file: &file, namespace: &namespace, prune, dry_run };
+            commands::apply::apply(&client, &options, output::format(output, &format))
+        }
+        Commands::Registry { action } => match action {
+            RegistryAction::Login { registry, username, password_stdin } => {
+                commands::registry::login(&registry, username.as_deref(), password_stdin)
+            }
+            RegistryAction::Push { reference, tags, artifact, path } => {
+                commands::registry::push(&commands::registry::PushOptions {
+                    reference: &reference,
+                    tags: &tags,
+                    artifact: artifact.as_deref(),
+                    path: &path,
+                })
+            }
+            RegistryAction::Pull { reference, dir, format } => {
+                commands::registry::pull(&reference, &dir, output::format(output, &format))
+            }
+        },
+        Commands::Scale { deployment, namespace, replicas, min, max, no_wait, timeout, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            commands::scale::scale(&client, &commands::scale::ScaleOptions {
+                deployment: &api::deployment_id(&deployment, &namespace),
+                replicas,
+                min,
+                max,
+                wait: (!no_wait).then(|| std::time::Duration::from_secs(timeout)),
+            })
+        }
+        Commands::Exec { deployment, export, json, namespace, format, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            commands::exec::exec(&client, &commands::exec::ExecOptions {
+                deployment: &api::deployment_id(&deployment, &namespace),
+                export: &export,
+                args: json.as_deref(),
+                format: output::format(output, &format),
+            })
+        }
+        Commands::PortForward { deployment, ports, namespace, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            commands::port_forward::port_forward(&client, &commands::port_forward::PortForwardOptions {
+                deployment: &api::deployment_id(&deployment, &namespace),
+                ports: &ports,
+            })
+        }
+        Commands::Bench {
+            deployment,
+            rps,
+            duration,
+            concurrency,
+            path,
+            port,
+            url,
+            format,
+            namespace,
+            api_url,
+            token,
+        } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            let options = commands::bench::BenchOptions {
+                deployment: &api::deployment_id(&deployment, &namespace),
+                rps,
+                duration,
+                concurrency,
+                path: &path,
+                port,
+                url: url.as_deref(),
+            };
+            commands::bench::bench(&client, &options, output::format(output, &format))
+        }
+        Commands::Logs { deployment, namespace, follow, since, instance, grep, tail, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            commands::logs::logs(&client, &commands::logs::LogsOptions {
+                deployment: &api::deployment_id(&deployment, &namespace),
+                follow,
+                since,
+                instance: instance.as_deref(),
+                grep: grep.as_deref(),
+                tail,
+            })
+        }
+        Commands::Rollout { action, namespace, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            match action {
+                RolloutAction::Start {
+                    deployment,
+                    version,
+                    strategy,
+                    batch_size,
+                    batch_interval,
+                    canary_percent,
+                    canary_instances,
+                    observe,
+                    watch,
+                } => commands::rollout::start(&client, &commands::rollout::StartOptions {
+                    deployment: &api::deployment_id(&deployment, &namespace),
+                    version: &version,
+                    strategy: &strategy,
+                    batch_size,
+                    batch_interval_secs: batch_interval,
+                    canary_percent,
+                    canary_instances,
+                    observe_secs: observe,
+                    watch,
+                }),
+                RolloutAction::Status { deployment, format, watch } => {
+                    let id = api::deployment_id(&deployment, &namespace);
+                    commands::rollout::status(&client, &id, output::format(output, &format), watch)
+                }
+                RolloutAction::Pause { deployment } => {
+                    commands::rollout::pause(&client, &api::deployment_id(&deployment, &namespace))
+                }
+                RolloutAction::Resume { deployment } => {
+                    commands::rollout::resume(&client, &api::deployment_id(&deployment, &namespace))
+                }
+                RolloutAction::Rollback { deployment } => {
+                    commands::rollout::rollback(&client, &api::deployment_id(&deployment, &namespace))
+                }
+            }
+        }
+        Commands::Secrets { action, namespace, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            let namespace = api::namespace(namespace.as_deref())?;
+            match action {
+                SecretsAction::Set { secret, value, from_file, stdin } => {
+                    let source =
+                        commands::secrets::Source::from_flags(value.as_deref(), from_file.as_deref(), stdin)?;
+                    commands::secrets::set(&client, &api::deployment_id(&secret, &namespace), &source)
+                }
+                SecretsAction::Get { secret, reveal, format } => {
+                    let id = api::deployment_id(&secret, &namespace);
+                    commands::secrets::get(&client, &id, reveal, output::format(output, &format))
+                }
+                SecretsAction::List { format } => {
+                    commands::secrets::list(&client, &namespace, output::format(output, &format))
+                }
+                SecretsAction::Delete { secret } => {
+                    commands::secrets::delete(&client, &api::deployment_id(&secret, &namespace))
+                }
+            }
+        }
+        Commands::Nodes { action, api_url, token } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            match action {
+                NodesAction::List { format } => commands::nodes::list(&client, output::format(output, &format)),
+                NodesAction::Describe { node, format } => {
+                    commands::nodes::describe(&client, &node, output::format(output, &format))
+                }
+                NodesAction::Cordon { node } => commands::nodes::cordon(&client, &node),
+                NodesAction::Uncordon { node } => commands::nodes::uncordon(&client, &node),
+                NodesAction::Drain { node, force } => commands::nodes::drain(&client, &node, force),
+                NodesAction::Label { node, labels } => commands::nodes::label(&client, &node, &labels),
+            }
+        }
+        Commands::Top { api_url, token, interval_ms } => {
+            let client = api::ApiClient::from_env(api_url.as_deref(), token.as_deref())?;
+            commands::top::top(client, interval_ms)
+        }
+        Commands::Context { action } => match action {
+            ContextAction::List { format } => commands::context::list(output::format(output, &format)),
+            ContextAction::Use { name } => commands::context::use_context(&name),
+            ContextAction::Set { name, api_url, token, namespace } => {
+                let fields = commands::context::ContextFields {
+                    api_url: api_url.as_deref(),
+                    token: token.as_deref(),
+                    namespace: namespace.as_deref(),
+                };
+                commands::context::set(&name, &fields)
+            }
+        },
+        Commands::Plugin { action: PluginAction::List } => {
+            let builtins: Vec<String> = Cli::command()
+                .get_subcommands()
+                .map(|command| command.get_name().to_string())
+                .collect();
+            commands::plugin::list(&builtins)
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "warp", &mut std::io::stdout());
+            Ok(())
+        }
+        Commands::External(args) => commands::plugin::run(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        // Completions build every subcommand, so clap's checks must pass
+        // for all of them, not just the ones a test happens to parse.
+        Cli::command().debug_assert();
     }
 }
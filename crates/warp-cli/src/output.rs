@@ -0,0 +1,69 @@
+//! Output formats shared by every command that prints data.
+//!
+//! Commands take a `-f/--format` of their own; the global `-o/--output`
+//! overrides it so a pipeline can ask for `json` or `yaml` everywhere
+//! without knowing each command's flags. `table` is the human-readable
+//! layout the `text` format already prints. Commands that only report
+//! progress (`deploy`, `scale`, `logs`) ignore it.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Output {
+    #[value(alias = "text")]
+    Table,
+    Json,
+    Yaml,
+}
+
+impl Output {
+    /// The `--format` value this stands for.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Table => "text",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+        }
+    }
+}
+
+/// The format a command should use: `--output` if given, else its own flag.
+pub fn format(output: Option<Output>, flag: &str) -> &str {
+    output.map_or(flag, |output| output.as_str())
+}
+
+/// Print `value` as JSON or YAML, or print what `table` renders for any
+/// other format.
+pub fn print<T: Serialize>(format: &str, value: &T, table: impl FnOnce() -> String) -> anyhow::Result<()> {
+    print!("{}", render(format, value, table)?);
+    Ok(())
+}
+
+fn render<T: Serialize>(format: &str, value: &T, table: impl FnOnce() -> String) -> anyhow::Result<String> {
+    Ok(match format {
+        "json" => serde_json::to_string_pretty(value)? + "\n",
+        "yaml" => serde_yaml::to_string(value)?,
+        _ => table(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_output_overrides_format_flag() {
+        assert_eq!(format(None, "json"), "json");
+        assert_eq!(format(Some(Output::Yaml), "json"), "yaml");
+        assert_eq!(format(Some(Output::Table), "json"), "text");
+    }
+
+    #[test]
+    fn test_render() {
+        let value = json!({"id": "default/api", "replicas": 3});
+        assert_eq!(render("json", &value, String::new).unwrap(), "{\n  \"id\": \"default/api\",\n  \"replicas\": 3\n}\n");
+        assert_eq!(render("yaml", &value, String::new).unwrap(), "id: default/api\nreplicas: 3\n");
+        assert_eq!(render("text", &value, || "api  3\n".into()).unwrap(), "api  3\n");
+    }
+}
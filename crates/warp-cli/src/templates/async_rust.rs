@@ -26,6 +26,10 @@ pub fn files() -> Vec<TemplateFile> {
             path: "wit/async-handler.wit",
             content: include_str!("../../../../crates/warpgrid-host/wit/async-handler.wit"),
         },
+        TemplateFile {
+            path: "wit/lifecycle.wit",
+            content: include_str!("../../../../crates/warpgrid-host/wit/lifecycle.wit"),
+        },
         TemplateFile {
             path: "wit/http-types.wit",
             content: include_str!("../../../../crates/warpgrid-host/wit/http-types.wit"),
@@ -50,6 +54,14 @@ pub fn files() -> Vec<TemplateFile> {
             path: "wit/threading.wit",
             content: include_str!("../../../../crates/warpgrid-host/wit/threading.wit"),
         },
+        TemplateFile {
+            path: "wit/render.wit",
+            content: include_str!("../../../../crates/warpgrid-host/wit/render.wit"),
+        },
+        TemplateFile {
+            path: "wit/feature-flags.wit",
+            content: include_str!("../../../../crates/warpgrid-host/wit/feature-flags.wit"),
+        },
     ]
 }
 
@@ -0,0 +1,83 @@
+//! Content-addressed configuration bundles.
+//!
+//! A [`ConfigBundle`] is the set of virtual config files a deployment's
+//! guests read through the filesystem shim. Bundles are immutable and
+//! identified by their [`digest`](ConfigBundle::digest), so the control
+//! plane can ship a new bundle to every node running a deployment before
+//! any instance switches to it, and nodes can tell two bundles apart
+//! without comparing their contents.
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Config files by absolute virtual path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigBundle {
+    pub files: BTreeMap<String, String>,
+}
+
+impl ConfigBundle {
+    /// `sha256:<hex>` over every path and its content, in path order.
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (path, content) in &self.files {
+            // Length prefixes keep `("a", "bc")` and `("ab", "c")` apart.
+            hasher.update((path.len() as u64).to_le_bytes());
+            hasher.update(path.as_bytes());
+            hasher.update((content.len() as u64).to_le_bytes());
+            hasher.update(content.as_bytes());
+        }
+        format!("sha256:{}", hex::encode(hasher.finalize()))
+    }
+
+    /// Reject bundles the filesystem shim cannot serve.
+    pub fn validate(&self) -> Result<(), String> {
+        for path in self.files.keys() {
+            if !path.starts_with('/') || path.ends_with('/') {
+                return Err(format!("config file path must be an absolute file path, got '{path}'"));
+            }
+            if path.split('/').any(|part| part == "." || part == "..") {
+                return Err(format!("config file path must not contain '.' or '..', got '{path}'"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The content of the file at `path`, if the bundle has one.
+    pub fn file(&self, path: &str) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(files: &[(&str, &str)]) -> ConfigBundle {
+        ConfigBundle {
+            files: files.iter().map(|(p, c)| (p.to_string(), c.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn digest_depends_on_content_only() {
+        let a = bundle(&[("/etc/app.toml", "port = 80"), ("/etc/motd", "hi")]);
+        let b = bundle(&[("/etc/motd", "hi"), ("/etc/app.toml", "port = 80")]);
+        assert_eq!(a.digest(), b.digest());
+        assert!(a.digest().starts_with("sha256:"));
+
+        assert_ne!(a.digest(), bundle(&[("/etc/app.toml", "port = 81"), ("/etc/motd", "hi")]).digest());
+        assert_ne!(bundle(&[("/a", "bc")]).digest(), bundle(&[("/ab", "c")]).digest());
+    }
+
+    #[test]
+    fn validate_rejects_relative_and_traversing_paths() {
+        assert!(bundle(&[("/etc/app.toml", "")]).validate().is_ok());
+        assert!(bundle(&[("etc/app.toml", "")]).validate().is_err());
+        assert!(bundle(&[("/etc/", "")]).validate().is_err());
+        assert!(bundle(&[("/etc/../passwd", "")]).validate().is_err());
+    }
+}
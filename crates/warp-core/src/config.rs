@@ -1,9 +1,11 @@
 //! warp.toml configuration parser.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
+use crate::types::ShimItem;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WarpConfig {
     pub package: PackageConfig,
@@ -13,6 +15,9 @@ pub struct WarpConfig {
     pub health: Option<HealthConfig>,
     pub shims: Option<ShimsConfig>,
     pub env: Option<HashMap<String, String>>,
+    /// Components of a multi-component workspace, by name.
+    pub components: Option<BTreeMap<String, ComponentConfig>>,
+    pub toolchain: Option<ToolchainConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +33,76 @@ pub struct BuildConfig {
     pub entry: String,
     pub target: Option<String>,
     pub flags: Option<Vec<String>>,
+    pub optimize: Option<OptimizeConfig>,
+    pub sign: Option<SignConfig>,
+    pub sbom: Option<SbomConfig>,
+    /// WIT directory, relative to the project (default: `wit/`).
+    pub wit: Option<String>,
+    /// WIT world to componentize against (default: the first world found).
+    pub world: Option<String>,
+}
+
+/// `[components.<name>]` — one component of a multi-component workspace.
+///
+/// Each component is packed from its own directory. A component without
+/// its own `warp.toml` is described entirely by this table; one with a
+/// `warp.toml` can still have its language, entry, and world overridden.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentConfig {
+    /// Component project directory, relative to the workspace `warp.toml`.
+    pub path: String,
+    /// Build language (default: the component's `warp.toml`, else auto-detected).
+    pub lang: Option<String>,
+    /// Entry point, as in `[build].entry`.
+    pub entry: Option<String>,
+    /// WIT world to componentize against.
+    pub world: Option<String>,
+}
+
+/// `[build.optimize]` — post-componentization wasm-opt stage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizeConfig {
+    /// wasm-opt level: "0"–"4", "s", or "z" (default "s").
+    pub level: Option<String>,
+    /// Strip the name section and DWARF debug info (default true).
+    pub strip_debug: Option<bool>,
+    /// Run the asyncify transform (default false).
+    pub asyncify: Option<bool>,
+}
+
+/// `[build.sign]` — sign the packed artifact with cosign.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignConfig {
+    /// cosign key reference (file path or KMS URI). When unset, signing is
+    /// keyless via Fulcio/Rekor.
+    pub key: Option<String>,
+}
+
+/// `[build.sbom]` — emit a software bill of materials with the artifact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SbomConfig {
+    /// "cyclonedx" (default) or "spdx".
+    pub format: Option<String>,
+    /// Also embed the SBOM in the artifact as a custom section (default false).
+    pub embed: Option<bool>,
+}
+
+/// `[toolchain]` — pinned tool versions and reproducible builds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolchainConfig {
+    pub cargo_component: Option<String>,
+    pub tinygo: Option<String>,
+    pub jco: Option<String>,
+    pub bun: Option<String>,
+    pub esbuild: Option<String>,
+    pub componentize_py: Option<String>,
+    pub wasm_tools: Option<String>,
+    /// Fix timestamps and strip build-machine paths (default true).
+    pub reproducible: Option<bool>,
+    /// Build time recorded in the artifact (default: the last git commit).
+    pub source_date_epoch: Option<u64>,
+    /// Expected artifact SHA-256; packing fails when the output differs.
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,7 +136,7 @@ pub struct HealthConfig {
     pub unhealthy_threshold: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ShimsConfig {
     pub timezone: Option<bool>,
     pub dev_urandom: Option<bool>,
@@ -71,6 +146,43 @@ pub struct ShimsConfig {
     pub database_proxy: Option<bool>,
 }
 
+impl ShimsConfig {
+    /// The `[shims]` table enabling every shim the analyzer findings rely
+    /// on, or `None` when they need none.
+    pub fn required_by(items: &[ShimItem]) -> Option<Self> {
+        let mut shims = ShimsConfig::default();
+        for key in items.iter().flat_map(|item| shim_keys(&item.shim)) {
+            match *key {
+                "timezone" => shims.timezone = Some(true),
+                "dev_urandom" => shims.dev_urandom = Some(true),
+                "dns" => shims.dns = Some(true),
+                "signals" => shims.signals = Some(true),
+                "database_proxy" => shims.database_proxy = Some(true),
+                _ => {}
+            }
+        }
+        let any = [shims.timezone, shims.dev_urandom, shims.dns, shims.signals, shims.database_proxy]
+            .iter()
+            .any(Option::is_some);
+        any.then_some(shims)
+    }
+}
+
+/// The `[shims]` keys that provide a compat-db shim. There is no
+/// `filesystem` key: the host filesystem shim is mounted whenever
+/// `timezone` or `dev_urandom` is on, so it enables both.
+fn shim_keys(shim: &str) -> &'static [&'static str] {
+    match shim {
+        "timezone" => &["timezone"],
+        "dev_urandom" => &["dev_urandom"],
+        "filesystem" => &["timezone", "dev_urandom"],
+        "dns" => &["dns"],
+        "signals" => &["signals"],
+        "database_proxy" => &["database_proxy"],
+        _ => &[],
+    }
+}
+
 impl WarpConfig {
     pub fn from_file(path: &Path) -> anyhow::Result<Self> {
         let content = std::fs::read_to_string(path)?;
@@ -95,6 +207,11 @@ impl WarpConfig {
                 entry: entry.to_string(),
                 target: Some("wasip2".to_string()),
                 flags: None,
+                optimize: None,
+                sign: None,
+                sbom: None,
+                wit: None,
+                world: None,
             }),
             runtime: Some(RuntimeConfig {
                 trigger: Some("http".to_string()),
@@ -112,7 +229,48 @@ impl WarpConfig {
             }),
             shims: None,
             env: None,
+            components: None,
+            toolchain: None,
+        }
+    }
+
+    /// [`scaffold`](Self::scaffold) with a `[shims]` table enabling
+    /// exactly the shims `shim_items` rely on.
+    pub fn scaffold_with_shims(name: &str, lang: &str, entry: &str, shim_items: &[ShimItem]) -> Self {
+        let mut config = Self::scaffold(name, lang, entry);
+        config.shims = ShimsConfig::required_by(shim_items);
+        config
+    }
+
+    /// Like [`to_toml_string`](Self::to_toml_string), with a comment above
+    /// each `[shims]` key naming the dependencies that need it and why.
+    pub fn to_scaffold_string(&self, shim_items: &[ShimItem]) -> anyhow::Result<String> {
+        let toml = self.to_toml_string()?;
+        let mut out = String::with_capacity(toml.len());
+        let mut in_shims = false;
+        for line in toml.lines() {
+            if line.starts_with('[') {
+                in_shims = line == "[shims]";
+                out.push_str(line);
+                out.push('\n');
+                if in_shims {
+                    out.push_str("# Enabled for the dependencies below; see `warp convert analyze`.\n");
+                }
+                continue;
+            }
+            if in_shims && let Some((key, _)) = line.split_once(" = ") {
+                for item in shim_items.iter().filter(|item| shim_keys(&item.shim).contains(&key)) {
+                    out.push_str(&format!("# {}: {}", item.name, item.shim));
+                    if !item.description.is_empty() {
+                        out.push_str(&format!(" — {}", item.description));
+                    }
+                    out.push('\n');
+                }
+            }
+            out.push_str(line);
+            out.push('\n');
         }
+        Ok(out)
     }
 }
 
@@ -128,6 +286,39 @@ mod tests {
         assert!(toml_str.contains("rust"));
     }
 
+    #[test]
+    fn test_scaffold_with_shims() {
+        let item = |name: &str, shim: &str, description: &str| ShimItem {
+            name: name.to_string(),
+            shim: shim.to_string(),
+            description: description.to_string(),
+        };
+        let items = [
+            item("sqlx", "database_proxy", "Connects through the host database proxy"),
+            item("chrono-tz", "filesystem", "Reads /usr/share/zoneinfo"),
+            item("reqwest", "dns", ""),
+        ];
+        let config = WarpConfig::scaffold_with_shims("my-api", "rust", "src/main.rs", &items);
+        let shims = config.shims.as_ref().unwrap();
+        assert_eq!(shims.database_proxy, Some(true));
+        assert_eq!(shims.dns, Some(true));
+        assert_eq!((shims.timezone, shims.dev_urandom), (Some(true), Some(true)));
+        assert_eq!(shims.signals, None);
+
+        let toml_str = config.to_scaffold_string(&items).unwrap();
+        assert!(toml_str.contains(
+            "# sqlx: database_proxy — Connects through the host database proxy\ndatabase_proxy = true"
+        ));
+        assert!(toml_str.contains("# chrono-tz: filesystem — Reads /usr/share/zoneinfo\ntimezone = true"));
+        assert!(toml_str.contains("# reqwest: dns\ndns = true"));
+        let parsed: WarpConfig = toml::from_str(&toml_str).unwrap();
+        assert_eq!(parsed.shims.unwrap().dns, Some(true));
+
+        let plain = WarpConfig::scaffold_with_shims("my-api", "rust", "src/main.rs", &[]);
+        assert!(plain.shims.is_none());
+        assert!(!plain.to_scaffold_string(&[]).unwrap().contains("[shims]"));
+    }
+
     #[test]
     fn test_parse_minimal() {
         let toml_str = r#"
@@ -138,4 +329,27 @@ version = "0.1.0"
         let config: WarpConfig = toml::from_str(toml_str).unwrap();
         assert_eq!(config.package.name, "test");
     }
+
+    #[test]
+    fn test_parse_components() {
+        let toml_str = r#"
+[package]
+name = "shop"
+version = "0.1.0"
+
+[components.gateway]
+path = "gateway-svc"
+world = "gateway-service"
+
+[components.users]
+path = "user-svc"
+lang = "go"
+"#;
+        let config: WarpConfig = toml::from_str(toml_str).unwrap();
+        let components = config.components.unwrap();
+        let names: Vec<_> = components.keys().map(String::as_str).collect();
+        assert_eq!(names, ["gateway", "users"]);
+        assert_eq!(components["gateway"].world.as_deref(), Some("gateway-service"));
+        assert_eq!(components["users"].lang.as_deref(), Some("go"));
+    }
 }
@@ -0,0 +1,154 @@
+//! Deployment-scoped feature flags.
+//!
+//! A deployment's [`FlagSet`] is managed through the API, stored by the
+//! control plane, and read by guests through the `warpgrid:shim/feature-flags`
+//! interface. Guests evaluate a flag against a request key (a user or
+//! tenant id); percentage flags bucket the key deterministically, so the
+//! same key gets the same answer on every instance and every node.
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One flag's definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeatureFlag {
+    /// On or off for every key.
+    Boolean { enabled: bool },
+    /// The same string for every key.
+    String { value: String },
+    /// On for `percent` of keys, chosen by hashing the flag name and key.
+    Percentage { percent: u8 },
+}
+
+impl FeatureFlag {
+    /// Reject definitions that cannot be evaluated.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Percentage { percent } if *percent > 100 => {
+                Err(format!("percentage must be between 0 and 100, got {percent}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// All flags of one deployment, by name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FlagSet {
+    pub flags: BTreeMap<String, FeatureFlag>,
+}
+
+impl FlagSet {
+    /// Boolean value of `name` for `key`. Percentage flags answer here;
+    /// string flags and undefined flags do not.
+    pub fn boolean(&self, name: &str, key: &str) -> Option<bool> {
+        match self.flags.get(name)? {
+            FeatureFlag::Boolean { enabled } => Some(*enabled),
+            FeatureFlag::Percentage { percent } => Some(bucket(name, key) < u32::from(*percent)),
+            FeatureFlag::String { .. } => None,
+        }
+    }
+
+    /// String value of `name`, when it is a string flag.
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.flags.get(name)? {
+            FeatureFlag::String { value } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Check every flag and its name.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, flag) in &self.flags {
+            validate_name(name)?;
+            flag.validate().map_err(|e| format!("flag '{name}': {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Flag names are non-empty and limited to `[A-Za-z0-9._-]`.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 128
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid flag name '{name}': use 1-128 letters, digits, '.', '_' or '-'"
+        ))
+    }
+}
+
+/// Bucket in `0..100` for `key` under flag `name` (FNV-1a, so it is
+/// stable across builds and platforms).
+fn bucket(name: &str, key: &str) -> u32 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes().chain([0]).chain(key.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % 100) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(flags: &[(&str, FeatureFlag)]) -> FlagSet {
+        FlagSet {
+            flags: flags
+                .iter()
+                .map(|(name, flag)| (name.to_string(), flag.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn percentage_rollout_is_deterministic_and_proportional() {
+        let flags = set(&[("new-checkout", FeatureFlag::Percentage { percent: 25 })]);
+        let on = (0..10_000)
+            .filter(|i| flags.boolean("new-checkout", &format!("user-{i}")) == Some(true))
+            .count();
+        assert!((2_000..3_000).contains(&on), "{on} of 10000 keys enabled");
+        assert_eq!(
+            flags.boolean("new-checkout", "user-42"),
+            flags.boolean("new-checkout", "user-42")
+        );
+
+        let none = set(&[("f", FeatureFlag::Percentage { percent: 0 })]);
+        let all = set(&[("f", FeatureFlag::Percentage { percent: 100 })]);
+        assert_eq!(none.boolean("f", "k"), Some(false));
+        assert_eq!(all.boolean("f", "k"), Some(true));
+    }
+
+    #[test]
+    fn values_by_flag_type() {
+        let flags = set(&[
+            ("dark-mode", FeatureFlag::Boolean { enabled: true }),
+            ("banner", FeatureFlag::String { value: "sale".into() }),
+        ]);
+        assert_eq!(flags.boolean("dark-mode", "any"), Some(true));
+        assert_eq!(flags.boolean("banner", "any"), None);
+        assert_eq!(flags.string("banner"), Some("sale"));
+        assert_eq!(flags.string("dark-mode"), None);
+        assert_eq!(flags.boolean("missing", "any"), None);
+    }
+
+    #[test]
+    fn validation_and_wire_format() {
+        let bad = set(&[("f", FeatureFlag::Percentage { percent: 101 })]);
+        assert!(bad.validate().unwrap_err().contains("flag 'f'"));
+        assert!(validate_name("has space").is_err());
+        assert!(validate_name("checkout.v2_beta-1").is_ok());
+
+        let flag: FeatureFlag = serde_json::from_str(r#"{"type":"percentage","percent":10}"#).unwrap();
+        assert_eq!(flag, FeatureFlag::Percentage { percent: 10 });
+    }
+}
@@ -1,7 +1,14 @@
+pub mod bundle;
 pub mod config;
+pub mod flags;
+pub mod meta;
 pub mod source;
 pub mod types;
+pub mod wasm;
 
+pub use bundle::ConfigBundle;
 pub use config::WarpConfig;
+pub use flags::{FeatureFlag, FlagSet};
+pub use meta::BuildMetadata;
 pub use source::SourceUri;
 pub use types::*;
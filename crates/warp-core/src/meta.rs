@@ -0,0 +1,122 @@
+//! Build metadata embedded in packed artifacts.
+//!
+//! `warp pack` appends a [`SECTION_NAME`] custom section holding a JSON
+//! [`BuildMetadata`] to every artifact, so the runtime and `warp status`
+//! can report exactly which build of which package is running.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ShimsConfig;
+use crate::wasm;
+
+/// Name of the custom section holding the build metadata.
+pub const SECTION_NAME: &str = "warpgrid.meta";
+
+/// What an artifact was built from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildMetadata {
+    /// `[package].name` from `warp.toml`.
+    pub package: String,
+    /// `[package].version` from `warp.toml`.
+    pub version: String,
+    /// Commit the project was built from, when it is in a git repository.
+    /// Suffixed with `-dirty` when the working tree had local changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_sha: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub build_time: u64,
+    /// Language the artifact was compiled from.
+    pub language: String,
+    /// Shims enabled in `[shims]`, by `warp.toml` key.
+    #[serde(default)]
+    pub shims: Vec<String>,
+}
+
+impl BuildMetadata {
+    /// The metadata embedded in `wasm`, if any.
+    ///
+    /// Returns an error when the section exists but cannot be decoded.
+    pub fn from_wasm(wasm: &[u8]) -> anyhow::Result<Option<Self>> {
+        let Some(payload) = wasm::custom_section(wasm, SECTION_NAME) else {
+            return Ok(None);
+        };
+        let meta = serde_json::from_slice(payload)
+            .map_err(|e| anyhow::anyhow!("invalid {SECTION_NAME} section: {e}"))?;
+        Ok(Some(meta))
+    }
+
+    /// Append this metadata to `wasm` as a [`SECTION_NAME`] section.
+    pub fn embed(&self, wasm: &mut Vec<u8>) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(self)?;
+        wasm::append_custom_section(wasm, SECTION_NAME, &payload);
+        Ok(())
+    }
+}
+
+/// `warp.toml` keys of the shims `[shims]` turns on.
+pub fn enabled_shims(shims: &ShimsConfig) -> Vec<String> {
+    let flags = [
+        ("timezone", shims.timezone),
+        ("dev_urandom", shims.dev_urandom),
+        ("dns", shims.dns),
+        ("signals", shims.signals),
+        ("database_proxy", shims.database_proxy),
+    ];
+    let mut enabled: Vec<String> = flags
+        .into_iter()
+        .filter(|(_, on)| *on == Some(true))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if let Some(model) = &shims.threading {
+        enabled.push(format!("threading={model}"));
+    }
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> BuildMetadata {
+        BuildMetadata {
+            package: "api".into(),
+            version: "1.2.0".into(),
+            git_sha: Some("0123abcd".into()),
+            build_time: 1_700_000_000,
+            language: "rust".into(),
+            shims: vec!["dns".into()],
+        }
+    }
+
+    #[test]
+    fn test_round_trip_through_custom_section() {
+        let mut binary = b"\0asm\x0d\0\x01\0".to_vec();
+        assert_eq!(BuildMetadata::from_wasm(&binary).unwrap(), None);
+
+        metadata().embed(&mut binary).unwrap();
+        assert_eq!(BuildMetadata::from_wasm(&binary).unwrap(), Some(metadata()));
+    }
+
+    #[test]
+    fn test_corrupt_section_is_an_error() {
+        let mut binary = b"\0asm\x0d\0\x01\0".to_vec();
+        wasm::append_custom_section(&mut binary, SECTION_NAME, b"{not json");
+        assert!(BuildMetadata::from_wasm(&binary).is_err());
+    }
+
+    #[test]
+    fn test_enabled_shims() {
+        let shims = ShimsConfig {
+            timezone: Some(true),
+            dev_urandom: Some(false),
+            dns: Some(true),
+            threading: Some("cooperative".into()),
+            signals: None,
+            database_proxy: None,
+        };
+        assert_eq!(
+            enabled_shims(&shims),
+            vec!["timezone", "dns", "threading=cooperative"]
+        );
+    }
+}
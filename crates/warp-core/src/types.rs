@@ -42,7 +42,15 @@ pub struct AnalysisReport {
     pub dependencies: Vec<DependencyVerdict>,
     pub blockers: Vec<Blocker>,
     pub shim_items: Vec<ShimItem>,
+    /// Rough size of the compiled component: language base, dependencies,
+    /// and large assets.
     pub estimated_wasm_size_mb: Option<f64>,
+    /// Large files in the project that would be bundled with it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub large_assets: Vec<LargeAsset>,
+    /// Manifest edits made (or, on a dry run, proposed) by `warp convert fix`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<DependencyFix>,
     pub suggested_config: Option<String>,
 }
 
@@ -70,6 +78,13 @@ pub struct DependencyVerdict {
     pub name: String,
     pub version: Option<String>,
     pub verdict: Verdict,
+    /// Pulled in by another dependency rather than declared by the project.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub transitive: bool,
+    /// Dependency path from a direct dependency down to this one's parent
+    /// (`["reqwest", "native-tls"]`), when the lockfile records it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub via: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +96,29 @@ pub struct Blocker {
     pub location: Option<String>,
 }
 
+/// A large file found in a project, counted in its size estimate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LargeAsset {
+    /// Path relative to the project root.
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+/// One dependency swapped for its compatible alternative in a manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DependencyFix {
+    /// Manifest file, relative to the project root.
+    pub manifest: String,
+    /// Dependency table, such as `dependencies` or `devDependencies`.
+    pub section: String,
+    pub removed: String,
+    pub added: String,
+    /// Version requirement written for `added`.
+    pub version: String,
+    /// Whether the manifest was rewritten (`false` on a dry run).
+    pub applied: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShimItem {
     pub name: String,
@@ -0,0 +1,279 @@
+//! Just enough of the wasm binary format to add, find, and drop custom
+//! sections in packed artifacts, without a round trip through
+//! wasm-tools. Shared by warp-pack, which writes the sections, and the
+//! runtime, which reads them back.
+//!
+//! Works on core modules and components; components are walked into so
+//! that their nested core modules and components are covered as well.
+
+/// `\0asm` followed by the core module version.
+const MODULE_HEADER: [u8; 8] = [0, b'a', b's', b'm', 1, 0, 0, 0];
+/// `\0asm` followed by the component version and layer.
+const COMPONENT_HEADER: [u8; 8] = [0, b'a', b's', b'm', 0x0d, 0, 1, 0];
+
+const CUSTOM_SECTION: u8 = 0;
+/// Component section ids holding a nested core module / component.
+const CORE_MODULE_SECTION: u8 = 1;
+const COMPONENT_SECTION: u8 = 4;
+/// Component section id of the import section.
+const COMPONENT_IMPORT_SECTION: u8 = 10;
+
+pub fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_leb128(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// One top-level section: its id and contents.
+struct Section<'a> {
+    id: u8,
+    contents: &'a [u8],
+}
+
+impl<'a> Section<'a> {
+    /// Name and payload of a custom section.
+    fn custom(&self) -> Option<(&'a [u8], &'a [u8])> {
+        if self.id != CUSTOM_SECTION {
+            return None;
+        }
+        let mut pos = 0;
+        let len = read_leb128(self.contents, &mut pos)?;
+        let name = self.contents.get(pos..pos.checked_add(len)?)?;
+        Some((name, &self.contents[pos + len..]))
+    }
+}
+
+/// The top-level sections of a module or component, or `None` when the
+/// binary is not well formed.
+fn sections(wasm: &[u8]) -> Option<Vec<Section<'_>>> {
+    let header = wasm.get(..8)?;
+    if header != MODULE_HEADER && header != COMPONENT_HEADER {
+        return None;
+    }
+    let mut sections = Vec::new();
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = read_leb128(wasm, &mut pos)?;
+        let end = pos.checked_add(size).filter(|&end| end <= wasm.len())?;
+        sections.push(Section {
+            id,
+            contents: &wasm[pos..end],
+        });
+        pos = end;
+    }
+    Some(sections)
+}
+
+/// Append a custom section to a core module or component.
+pub fn append_custom_section(wasm: &mut Vec<u8>, name: &str, payload: &[u8]) {
+    let mut contents = Vec::with_capacity(name.len() + payload.len() + 5);
+    write_leb128(&mut contents, name.len());
+    contents.extend_from_slice(name.as_bytes());
+    contents.extend_from_slice(payload);
+    wasm.push(CUSTOM_SECTION);
+    write_leb128(wasm, contents.len());
+    wasm.extend_from_slice(&contents);
+}
+
+/// Payload of the first top-level custom section called `name`.
+pub fn custom_section<'a>(wasm: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    sections(wasm)?
+        .iter()
+        .filter_map(Section::custom)
+        .find(|(section, _)| *section == name.as_bytes())
+        .map(|(_, payload)| payload)
+}
+
+/// Names a component imports at its top level (e.g.
+/// `wasi:http/types@0.2.3`), in order. Returns `None` when `wasm` is not a
+/// well-formed component.
+pub fn component_imports(wasm: &[u8]) -> Option<Vec<String>> {
+    if wasm.get(..8)? != COMPONENT_HEADER {
+        return None;
+    }
+    let mut names = Vec::new();
+    for section in sections(wasm)? {
+        if section.id != COMPONENT_IMPORT_SECTION {
+            continue;
+        }
+        let bytes = section.contents;
+        let mut pos = 0;
+        let count = read_leb128(bytes, &mut pos)?;
+        for _ in 0..count {
+            // importname': 0x00 name | 0x01 name version-suffix
+            let tag = *bytes.get(pos)?;
+            pos += 1;
+            names.push(read_string(bytes, &mut pos)?.to_string());
+            match tag {
+                0x00 => {}
+                0x01 => {
+                    read_string(bytes, &mut pos)?;
+                }
+                _ => return None,
+            }
+            skip_externdesc(bytes, &mut pos)?;
+        }
+    }
+    Some(names)
+}
+
+fn read_string<'a>(bytes: &'a [u8], pos: &mut usize) -> Option<&'a str> {
+    let len = read_leb128(bytes, pos)?;
+    let end = pos.checked_add(len)?;
+    let s = std::str::from_utf8(bytes.get(*pos..end)?).ok()?;
+    *pos = end;
+    Some(s)
+}
+
+/// Step over an `externdesc` (what kind of item an import is).
+fn skip_externdesc(bytes: &[u8], pos: &mut usize) -> Option<()> {
+    let kind = *bytes.get(*pos)?;
+    *pos += 1;
+    match kind {
+        // Core module: 0x11 then a core type index.
+        0x00 => {
+            if *bytes.get(*pos)? != 0x11 {
+                return None;
+            }
+            *pos += 1;
+            read_leb128(bytes, pos)?;
+        }
+        // Func, component, instance: a type index.
+        0x01 | 0x04 | 0x05 => {
+            read_leb128(bytes, pos)?;
+        }
+        // Value or type: a bound, with an index or valtype after 0x00 / 0x01
+        // (a type's 0x01 is `sub resource` and has nothing after it).
+        0x02 | 0x03 => {
+            let bound = *bytes.get(*pos)?;
+            *pos += 1;
+            if bound == 0x00 || kind == 0x02 {
+                read_leb128(bytes, pos)?;
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Copy of `wasm` without the custom sections `drop` selects, at every
+/// nesting level. Returns `None` when the binary is not well formed.
+pub fn strip_custom_sections(wasm: &[u8], drop: &dyn Fn(&str) -> bool) -> Option<Vec<u8>> {
+    let is_component = wasm.get(..8)? == COMPONENT_HEADER;
+    let mut out = wasm[..8].to_vec();
+    for section in sections(wasm)? {
+        let contents = match section.id {
+            CUSTOM_SECTION => {
+                let (name, _) = section.custom()?;
+                if drop(&String::from_utf8_lossy(name)) {
+                    continue;
+                }
+                section.contents.to_vec()
+            }
+            CORE_MODULE_SECTION | COMPONENT_SECTION if is_component => {
+                strip_custom_sections(section.contents, drop)?
+            }
+            _ => section.contents.to_vec(),
+        };
+        out.push(section.id);
+        write_leb128(&mut out, contents.len());
+        out.extend_from_slice(&contents);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leb128_round_trip() {
+        for value in [0, 1, 127, 128, 300, 16_384, 1 << 28] {
+            let mut buf = Vec::new();
+            write_leb128(&mut buf, value);
+            let mut pos = 0;
+            assert_eq!(read_leb128(&buf, &mut pos), Some(value));
+            assert_eq!(pos, buf.len());
+        }
+    }
+
+    #[test]
+    fn test_custom_sections_in_nested_modules_are_stripped() {
+        let mut module = MODULE_HEADER.to_vec();
+        append_custom_section(&mut module, ".debug_info", b"/home/ci/src/lib.rs");
+        append_custom_section(&mut module, "name", b"keep");
+
+        let mut component = COMPONENT_HEADER.to_vec();
+        component.push(CORE_MODULE_SECTION);
+        write_leb128(&mut component, module.len());
+        component.extend_from_slice(&module);
+        append_custom_section(&mut component, ".debug_line", b"/home/ci");
+        append_custom_section(&mut component, "producers", b"keep");
+
+        let stripped = strip_custom_sections(&component, &|name| name.starts_with(".debug_")).unwrap();
+        assert!(!stripped.windows(8).any(|w| w == b"/home/ci"));
+        assert_eq!(custom_section(&stripped, "producers"), Some(&b"keep"[..]));
+        assert_eq!(custom_section(&stripped, ".debug_line"), None);
+
+        let nested = &sections(&stripped).unwrap()[0];
+        assert_eq!(nested.id, CORE_MODULE_SECTION);
+        assert_eq!(custom_section(nested.contents, "name"), Some(&b"keep"[..]));
+    }
+
+    fn import(out: &mut Vec<u8>, name: &str, desc: &[u8]) {
+        out.push(0x00);
+        write_leb128(out, name.len());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(desc);
+    }
+
+    #[test]
+    fn test_component_imports() {
+        let mut imports = Vec::new();
+        write_leb128(&mut imports, 3);
+        import(&mut imports, "wasi:io/streams@0.2.3", &[0x05, 0x00]);
+        import(&mut imports, "warpgrid:shim/dns@0.1.0", &[0x05, 0x81, 0x01]);
+        import(&mut imports, "resource", &[0x03, 0x01]);
+
+        let mut component = COMPONENT_HEADER.to_vec();
+        component.push(COMPONENT_IMPORT_SECTION);
+        write_leb128(&mut component, imports.len());
+        component.extend_from_slice(&imports);
+        append_custom_section(&mut component, "producers", b"");
+
+        assert_eq!(
+            component_imports(&component).unwrap(),
+            vec!["wasi:io/streams@0.2.3", "warpgrid:shim/dns@0.1.0", "resource"]
+        );
+        assert_eq!(component_imports(&MODULE_HEADER), None);
+    }
+
+    #[test]
+    fn test_malformed_binaries_are_rejected() {
+        assert!(sections(b"not wasm").is_none());
+        let mut truncated = MODULE_HEADER.to_vec();
+        truncated.extend_from_slice(&[CUSTOM_SECTION, 10, 1]);
+        assert!(strip_custom_sections(&truncated, &|_| true).is_none());
+    }
+}
@@ -0,0 +1,342 @@
+//! Installing missing build tools on demand.
+//!
+//! When a pipeline cannot find cargo-component, TinyGo, jco, or wasm-tools
+//! (env override, SDK build directory, or `$PATH`), it installs the tool
+//! into `~/.warp/toolchains/<tool>/<version>/` and runs it from there:
+//!
+//! - cargo-component, TinyGo, and wasm-tools are downloaded from their
+//!   GitHub releases with `curl` (and unpacked with `tar`);
+//! - jco is installed with `npm`, together with ComponentizeJS.
+//!
+//! The version is the `[toolchain]` pin when there is one, else the
+//! default below, so a pinned project bootstraps exactly what it pins.
+//! Installs are staged in a sibling directory and renamed into place, so
+//! an interrupted download never leaves a half-installed tool behind.
+//!
+//! `WARP_TOOLCHAINS_DIR` moves the install root. `WARP_NO_TOOLCHAIN_INSTALL=1`
+//! turns installation off (air-gapped machines, CI images that must use
+//! their preinstalled tools); a missing tool then fails as before.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::WarpConfig;
+
+use crate::toolchain;
+
+/// A tool the bootstrap can install.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Tool {
+    CargoComponent,
+    TinyGo,
+    Jco,
+    WasmTools,
+}
+
+/// How a tool is fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    /// A release asset: a `.tar.gz` to unpack, or the bare binary.
+    Download { url: String, archive: bool },
+    /// npm packages, installed into the tool directory.
+    Npm { packages: Vec<String> },
+}
+
+impl Tool {
+    /// Name used in paths and messages.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::CargoComponent => "cargo-component",
+            Self::TinyGo => "tinygo",
+            Self::Jco => "jco",
+            Self::WasmTools => "wasm-tools",
+        }
+    }
+
+    /// The `[toolchain]` key pinning this tool.
+    fn pin_key(self) -> &'static str {
+        match self {
+            Self::CargoComponent => "cargo_component",
+            Self::TinyGo => "tinygo",
+            Self::Jco => "jco",
+            Self::WasmTools => "wasm_tools",
+        }
+    }
+
+    /// Version installed when `[toolchain]` does not pin one.
+    fn default_version(self) -> &'static str {
+        match self {
+            Self::CargoComponent => "0.20.0",
+            Self::TinyGo => "0.34.0",
+            Self::Jco => "1.16.1",
+            Self::WasmTools => "1.227.1",
+        }
+    }
+
+    /// Where to fetch `version` for `os`/`arch` (Rust's `std::env::consts`
+    /// names), if the tool publishes a build for that platform.
+    fn source(self, version: &str, os: &str, arch: &str) -> Option<Source> {
+        let download = |url: String, archive| Some(Source::Download { url, archive });
+        match self {
+            Self::CargoComponent => {
+                let target = match os {
+                    "linux" => "unknown-linux-gnu",
+                    "macos" => "apple-darwin",
+                    _ => return None,
+                };
+                download(
+                    format!(
+                        "https://github.com/bytecodealliance/cargo-component/releases/download/\
+                         v{version}/cargo-component-{arch}-{target}"
+                    ),
+                    false,
+                )
+            }
+            Self::TinyGo => {
+                let os = match os {
+                    "linux" => "linux",
+                    "macos" => "darwin",
+                    _ => return None,
+                };
+                let arch = match arch {
+                    "x86_64" => "amd64",
+                    "aarch64" => "arm64",
+                    _ => return None,
+                };
+                download(
+                    format!(
+                        "https://github.com/tinygo-org/tinygo/releases/download/\
+                         v{version}/tinygo{version}.{os}-{arch}.tar.gz"
+                    ),
+                    true,
+                )
+            }
+            Self::WasmTools => {
+                if !matches!(os, "linux" | "macos") {
+                    return None;
+                }
+                download(
+                    format!(
+                        "https://github.com/bytecodealliance/wasm-tools/releases/download/\
+                         v{version}/wasm-tools-{version}-{arch}-{os}.tar.gz"
+                    ),
+                    true,
+                )
+            }
+            Self::Jco => Some(Source::Npm {
+                packages: vec![
+                    format!("@bytecodealliance/jco@{version}"),
+                    "@bytecodealliance/componentize-js".to_string(),
+                ],
+            }),
+        }
+    }
+
+    /// The executable, relative to the install directory.
+    fn binary(self, version: &str, os: &str, arch: &str) -> PathBuf {
+        match self {
+            Self::CargoComponent => PathBuf::from("cargo-component"),
+            Self::TinyGo => PathBuf::from("tinygo/bin/tinygo"),
+            Self::WasmTools => {
+                PathBuf::from(format!("wasm-tools-{version}-{arch}-{os}/wasm-tools"))
+            }
+            Self::Jco => PathBuf::from("node_modules/.bin/jco"),
+        }
+    }
+}
+
+/// `found`, or else `tool` from the toolchain directory, installing it
+/// first if needed. When that fails too, the error keeps `found`'s
+/// install instructions and says why bootstrapping did not help.
+pub(crate) fn or_install(
+    found: Result<PathBuf>,
+    tool: Tool,
+    config: Option<&WarpConfig>,
+) -> Result<PathBuf> {
+    let not_found = match found {
+        Ok(path) => return Ok(path),
+        Err(e) => e,
+    };
+    ensure(tool, config).map_err(|e| {
+        anyhow::anyhow!("{not_found}\n\nAutomatic install of {} failed: {e:#}", tool.name())
+    })
+}
+
+/// Path of `tool` in the toolchain directory, installing it if needed.
+pub(crate) fn ensure(tool: Tool, config: Option<&WarpConfig>) -> Result<PathBuf> {
+    let version = config
+        .and_then(|config| toolchain::pinned(config, tool.pin_key()))
+        .unwrap_or(tool.default_version())
+        .trim_start_matches('v');
+    let root = toolchains_dir()?;
+    let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+    let dir = root.join(tool.name()).join(version);
+    let binary = dir.join(tool.binary(version, os, arch));
+    if binary.is_file() {
+        debug!("Found {} {version} at {}", tool.name(), binary.display());
+        return Ok(binary);
+    }
+
+    if std::env::var_os("WARP_NO_TOOLCHAIN_INSTALL").is_some_and(|v| v != "0" && !v.is_empty()) {
+        bail!("toolchain installs are disabled (WARP_NO_TOOLCHAIN_INSTALL is set)");
+    }
+    // Unit tests exercise the missing-tool paths; they must never download.
+    if cfg!(test) {
+        bail!("toolchain installs are disabled in unit tests");
+    }
+    let source = tool
+        .source(version, os, arch)
+        .with_context(|| format!("no {} build is published for {os}-{arch}", tool.name()))?;
+    info!("{} not found; installing {version} into {}", tool.name(), dir.display());
+    install(&dir, &source, &tool.binary(version, os, arch))?;
+    info!("Installed {} {version}", tool.name());
+    Ok(binary)
+}
+
+/// `$WARP_TOOLCHAINS_DIR`, else `~/.warp/toolchains`.
+fn toolchains_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("WARP_TOOLCHAINS_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = std::env::var_os("HOME").context("HOME is not set; set WARP_TOOLCHAINS_DIR")?;
+    Ok(PathBuf::from(home).join(".warp").join("toolchains"))
+}
+
+/// Fetch `source` into `dir`, which must end up containing `binary`.
+fn install(dir: &Path, source: &Source, binary: &Path) -> Result<()> {
+    let parent = dir.parent().context("toolchain directory has no parent")?;
+    fs::create_dir_all(parent)?;
+    let staging = parent.join(format!(
+        ".{}.partial-{}",
+        dir.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    let fetched = fetch(&staging, source, binary).and_then(|()| {
+        if !staging.join(binary).is_file() {
+            bail!("the download did not contain {}", binary.display());
+        }
+        Ok(())
+    });
+    if let Err(e) = fetched {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    if let Err(e) = fs::rename(&staging, dir) {
+        let _ = fs::remove_dir_all(&staging);
+        // Another pack installed the same version concurrently.
+        if !dir.join(binary).is_file() {
+            return Err(e).with_context(|| format!("Failed to move the install into {}", dir.display()));
+        }
+    }
+    Ok(())
+}
+
+fn fetch(staging: &Path, source: &Source, binary: &Path) -> Result<()> {
+    match source {
+        Source::Download { url, archive } => {
+            let file = if *archive { staging.join("download.tar.gz") } else { staging.join(binary) };
+            run(Command::new("curl").args(["-fsSL", "--retry", "2", "-o"]).arg(&file).arg(url))
+                .with_context(|| format!("Failed to download {url}"))?;
+            if *archive {
+                run(Command::new("tar").arg("-xzf").arg(&file).arg("-C").arg(staging))
+                    .with_context(|| format!("Failed to unpack {url}"))?;
+                fs::remove_file(&file)?;
+            } else {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&file, fs::Permissions::from_mode(0o755))?;
+            }
+        }
+        Source::Npm { packages } => {
+            run(Command::new("npm")
+                .args(["install", "--no-save", "--no-audit", "--no-fund", "--prefix"])
+                .arg(staging)
+                .args(packages))
+            .with_context(|| format!("Failed to npm install {}", packages.join(" ")))?;
+        }
+    }
+    Ok(())
+}
+
+fn run(cmd: &mut Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run {program} (is it installed?)"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} exited with {}: {}",
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_follow_release_naming() {
+        assert_eq!(
+            Tool::TinyGo.source("0.34.0", "macos", "aarch64"),
+            Some(Source::Download {
+                url: "https://github.com/tinygo-org/tinygo/releases/download/v0.34.0/tinygo0.34.0.darwin-arm64.tar.gz".into(),
+                archive: true,
+            })
+        );
+        assert_eq!(
+            Tool::CargoComponent.source("0.20.0", "linux", "x86_64"),
+            Some(Source::Download {
+                url: "https://github.com/bytecodealliance/cargo-component/releases/download/v0.20.0/cargo-component-x86_64-unknown-linux-gnu".into(),
+                archive: false,
+            })
+        );
+        assert_eq!(
+            Tool::WasmTools.binary("1.227.1", "linux", "x86_64"),
+            PathBuf::from("wasm-tools-1.227.1-x86_64-linux/wasm-tools")
+        );
+        assert_eq!(Tool::WasmTools.source("1.227.1", "windows", "x86_64"), None);
+    }
+
+    #[test]
+    fn test_install_unpacks_archive_into_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = dir.path().join("release");
+        fs::create_dir_all(release.join("tinygo/bin")).unwrap();
+        fs::write(release.join("tinygo/bin/tinygo"), "#!/bin/sh\n").unwrap();
+        let tarball = dir.path().join("tinygo.tar.gz");
+        run(Command::new("tar").arg("-czf").arg(&tarball).arg("-C").arg(&release).arg("tinygo")).unwrap();
+
+        let target = dir.path().join("toolchains/tinygo/0.34.0");
+        let binary = Path::new("tinygo/bin/tinygo");
+        let source = Source::Download {
+            url: format!("file://{}", tarball.display()),
+            archive: true,
+        };
+        install(&target, &source, binary).unwrap();
+        assert!(target.join(binary).is_file());
+        assert!(!target.join("download.tar.gz").exists());
+
+        // A download without the expected binary leaves nothing behind.
+        let other = dir.path().join("toolchains/tinygo/0.35.0");
+        let err = install(&other, &source, Path::new("tinygo/bin/missing")).unwrap_err();
+        assert!(err.to_string().contains("did not contain"), "{err}");
+        let leftovers = fs::read_dir(dir.path().join("toolchains/tinygo")).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+
+    #[test]
+    fn test_or_install_prefers_found_tool() {
+        let found = PathBuf::from("/opt/tinygo/bin/tinygo");
+        assert_eq!(or_install(Ok(found.clone()), Tool::TinyGo, None).unwrap(), found);
+    }
+}
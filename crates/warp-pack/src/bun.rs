@@ -11,7 +11,8 @@ use std::process::Command;
 use tracing::{info, debug};
 use warp_core::WarpConfig;
 
-use crate::PackResult;
+use crate::bootstrap::{self, Tool};
+use crate::{PackResult, optimize, toolchain};
 
 /// Resolve the jco binary path.
 ///
@@ -192,7 +193,12 @@ import "{}";
 /// Step 1: Bundle the Bun handler with `bun build`.
 ///
 /// Produces a single-file ES module bundle suitable for jco componentize.
-fn bun_build(project_path: &Path, entry: &str, output: &Path) -> Result<()> {
+fn bun_build(
+    project_path: &Path,
+    entry: &str,
+    output: &Path,
+    env: &[(&str, String)],
+) -> Result<()> {
     let entry_path = {
         let p = Path::new(entry);
         if p.is_absolute() { p.to_path_buf() } else { project_path.join(entry) }
@@ -200,6 +206,7 @@ fn bun_build(project_path: &Path, entry: &str, output: &Path) -> Result<()> {
     info!("Bundling with bun build: {}", entry_path.display());
 
     let result = Command::new("bun")
+        .envs(env.iter().cloned())
         .arg("build")
         .arg(&entry_path)
         .arg("--outfile")
@@ -208,6 +215,9 @@ fn bun_build(project_path: &Path, entry: &str, output: &Path) -> Result<()> {
         .arg("browser")
         .arg("--format")
         .arg("esm")
+        // WIT imports (e.g. `warpgrid:shim/database-proxy@0.1.0`) are
+        // resolved by jco componentize, not bundled.
+        .args(["--external", "wasi:*", "--external", "warpgrid:*"])
         .output()
         .context(
             "Failed to execute 'bun build'. Is Bun installed? \
@@ -243,17 +253,20 @@ fn jco_componentize(
     jco_bin: &Path,
     bundled_js: &Path,
     wit_dir: &Path,
+    world: &str,
     output: &Path,
+    env: &[(&str, String)],
 ) -> Result<()> {
-    info!("Componentizing with jco...");
+    info!("Componentizing with jco (world '{world}')...");
 
     let result = Command::new(jco_bin)
+        .envs(env.iter().cloned())
         .arg("componentize")
         .arg(bundled_js)
         .arg("--wit")
         .arg(wit_dir)
         .arg("--world-name")
-        .arg("handler")
+        .arg(world)
         .arg("--enable")
         .arg("http")
         .arg("--enable")
@@ -292,10 +305,23 @@ fn jco_componentize(
 }
 
 /// Step 3: Validate the Wasm component exports `wasi:http/incoming-handler`.
-fn validate_component(wasm_path: &Path) -> Result<()> {
+pub(crate) fn validate_component(wasm_path: &Path) -> Result<()> {
     info!("Validating Wasm component...");
 
-    let result = Command::new("wasm-tools")
+    let wasm_tools = match optimize::which("wasm-tools") {
+        Some(path) => path,
+        None => match bootstrap::ensure(Tool::WasmTools, None) {
+            Ok(path) => path,
+            Err(e) => {
+                tracing::warn!(
+                    "wasm-tools not found in PATH and could not be installed ({e:#}). \
+                     Skipping component validation."
+                );
+                return Ok(());
+            }
+        },
+    };
+    let result = Command::new(&wasm_tools)
         .arg("component")
         .arg("wit")
         .arg(wasm_path)
@@ -323,10 +349,10 @@ fn validate_component(wasm_path: &Path) -> Result<()> {
             );
         }
         Err(_) => {
-            // wasm-tools not available — skip validation with warning
+            // wasm-tools could not be run — skip validation with warning
             tracing::warn!(
-                "wasm-tools not found in PATH. Skipping component validation. \
-                 Install: cargo install wasm-tools"
+                "Failed to run {}. Skipping component validation.",
+                wasm_tools.display()
             );
             Ok(())
         }
@@ -357,8 +383,14 @@ pub fn pack_bun(project_path: &Path, config: &WarpConfig) -> Result<PackResult>
     }
 
     // Resolve external tool paths
-    let jco_bin = resolve_jco(&project_root)?;
-    let wit_dir = resolve_wit_dir(project_path, &project_root)?;
+    let jco_bin = bootstrap::or_install(resolve_jco(&project_root), Tool::Jco, Some(config))?;
+    toolchain::check_pin(config, "bun", Path::new("bun"))?;
+    toolchain::check_pin(config, "jco", &jco_bin)?;
+    let wit_dir = match build_config.wit {
+        Some(_) => crate::js::wit_dir_for(project_path, config)?,
+        None => resolve_wit_dir(project_path, &project_root)?,
+    };
+    let world = build_config.world.as_deref().unwrap_or("handler");
 
     info!(
         "Packing Bun handler: {} (entry: {}, wit: {})",
@@ -398,14 +430,16 @@ pub fn pack_bun(project_path: &Path, config: &WarpConfig) -> Result<PackResult>
     };
 
     // Step 1: Bundle with bun build (using wrapper entry if polyfills available)
+    let env = toolchain::build_env(project_path, config);
     bun_build(
         project_path,
         &effective_entry,
         &bundled_js,
+        &env,
     )?;
 
     // Step 2: Componentize with jco
-    jco_componentize(&jco_bin, &bundled_js, &wit_dir, &wasm_output)?;
+    jco_componentize(&jco_bin, &bundled_js, &wit_dir, world, &wasm_output, &env)?;
 
     // Step 3: Validate the component
     validate_component(&wasm_output)?;
@@ -424,6 +458,11 @@ pub fn pack_bun(project_path: &Path, config: &WarpConfig) -> Result<PackResult>
         output_path: wasm_output.to_string_lossy().to_string(),
         size_bytes: metadata.len(),
         sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
     })
 }
 
@@ -514,6 +553,8 @@ entry = "src/index.ts"
             health: None,
             shims: None,
             env: None,
+            components: None,
+            toolchain: None,
         };
 
         let dir = tempfile::tempdir().unwrap();
@@ -581,7 +622,7 @@ entry = "src/index.ts"
         fs::write(dir.path().join("src/index.ts"), handler).unwrap();
 
         let output = dir.path().join("bundle.js");
-        let result = bun_build(dir.path(), "src/index.ts", &output);
+        let result = bun_build(dir.path(), "src/index.ts", &output, &[]);
         assert!(result.is_ok(), "bun build failed: {:?}", result.err());
         assert!(output.exists(), "Bundle not produced");
 
@@ -608,7 +649,7 @@ entry = "src/index.ts"
         ).unwrap();
 
         let output = dir.path().join("bundle.js");
-        let result = bun_build(dir.path(), "src/broken.ts", &output);
+        let result = bun_build(dir.path(), "src/broken.ts", &output, &[]);
         // bun build may still succeed with an import it can't resolve
         // (it bundles what it can). But if it fails, the error should
         // include exit code info.
@@ -756,7 +797,7 @@ entry = "src/index.ts"
         }
 
         let output = dir.path().join("output.wasm");
-        let result = jco_componentize(&jco_bin, &invalid_js, &shared_wit, &output);
+        let result = jco_componentize(&jco_bin, &invalid_js, &shared_wit, "handler", &output, &[]);
 
         if result.is_err() {
             let err_msg = result.unwrap_err().to_string();
@@ -867,6 +908,7 @@ entry = "src/index.ts"
             dir.path(),
             &wrapper.to_string_lossy(),
             &output,
+            &[],
         );
         assert!(result.is_ok(), "bun build with polyfill wrapper failed: {:?}", result.err());
 
@@ -0,0 +1,356 @@
+//! Incremental build cache.
+//!
+//! A build is keyed by everything that determines its output:
+//!
+//! - the language pipeline
+//! - `warp.toml`, plus the effective package and build settings (a
+//!   workspace component's may come from the workspace `warp.toml`)
+//! - the versions of the toolchain binaries the pipeline invokes
+//! - a digest of the project tree (build output, dependency install, and
+//!   dot-directories excluded; lockfiles included), and of the WIT
+//!   directory named by `[build].wit`, which may lie outside it
+//!
+//! On a hit the cached artifact is copied to the pipeline's output path
+//! and compilation and optimization are skipped. Signing always runs, so
+//! a signature never outlives the key that made it.
+//!
+//! Entries live in `<project>/.warp/cache/`. Only the most recent
+//! [`MAX_ENTRIES`] are kept. Cache I/O failures never fail a pack; they
+//! fall back to a normal build.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info, warn};
+use warp_core::WarpConfig;
+
+use crate::{OptimizeReport, PackResult, js};
+
+/// Bumped whenever the key derivation or entry format changes.
+const CACHE_VERSION: &str = "v2";
+
+/// Entries kept per project.
+const MAX_ENTRIES: usize = 8;
+
+/// Directories never part of the source digest: build output and
+/// installed dependencies (their lockfiles are hashed instead).
+const EXCLUDED_DIRS: &[&str] = &["target", "node_modules", "dist", "bin", "obj", "__pycache__"];
+
+/// Whether a directory entry named `name` is skipped when digesting (or
+/// watching) the project tree.
+pub(crate) fn is_excluded_dir(name: &str) -> bool {
+    name.starts_with('.') || EXCLUDED_DIRS.contains(&name)
+}
+
+/// Cache directory for a project.
+fn cache_dir(project_path: &Path) -> PathBuf {
+    project_path.join(".warp").join("cache")
+}
+
+/// Metadata stored next to a cached artifact.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Output path relative to the project root.
+    output_path: String,
+    sha256: String,
+    optimization: Option<CachedOptimization>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedOptimization {
+    level: String,
+    size_before: u64,
+    size_after: u64,
+}
+
+/// Toolchain commands whose `--version` output is part of the key.
+fn toolchain_probes(project_path: &Path, lang: &str, config: &WarpConfig) -> Vec<Vec<String>> {
+    let jco = || {
+        js::find_jco(&js::find_sdk_root(project_path))
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "jco".to_string())
+    };
+    let cmd = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+    let mut probes = match lang {
+        "rust" => vec![cmd(&["cargo", "--version"]), cmd(&["cargo", "component", "--version"])],
+        "go" => vec![cmd(&["tinygo", "version"])],
+        "js" | "typescript" => vec![cmd(&["node", "--version"]), vec![jco(), "--version".into()]],
+        "bun" => vec![cmd(&["bun", "--version"]), vec![jco(), "--version".into()]],
+        "python" => vec![cmd(&["componentize-py", "--version"])],
+        "dotnet" => vec![cmd(&["dotnet", "--version"])],
+        _ => vec![],
+    };
+    if config.build.as_ref().is_some_and(|b| b.optimize.is_some()) {
+        probes.push(cmd(&["wasm-opt", "--version"]));
+    }
+    probes
+}
+
+/// `<command>: <version output>`, or `unavailable` if it cannot run.
+fn probe_version(probe: &[String]) -> String {
+    let version = Command::new(&probe[0])
+        .args(&probe[1..])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|| "unavailable".to_string());
+    format!("{}: {version}", probe.join(" "))
+}
+
+/// Digest of every source file under `root`, by relative path and content.
+fn tree_digest(root: &Path) -> Result<String> {
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for (rel, path) in &files {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        hasher.update(rel.as_bytes());
+        hasher.update([0]);
+        hasher.update(Sha256::digest(&bytes));
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // Symlinks are followed for files but not directories (no cycles).
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !is_excluded_dir(&name) {
+                collect_files(root, &path, files)?;
+            }
+        } else if path.is_file() {
+            let rel = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((rel, path));
+        }
+    }
+    Ok(())
+}
+
+/// Compute the cache key for building `project_path` as `lang`.
+pub(crate) fn key(project_path: &Path, lang: &str, config: &WarpConfig) -> Result<String> {
+    // Workspace components may have no warp.toml of their own.
+    let warp_toml = fs::read(project_path.join("warp.toml")).unwrap_or_default();
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!("warp-pack-cache {CACHE_VERSION}\nlang={lang}\n"));
+    hasher.update(b"warp.toml=");
+    hasher.update(Sha256::digest(&warp_toml));
+    hasher.update(b"\n");
+    hasher.update(serde_json::to_vec(&(&config.package, &config.build, &config.toolchain))?);
+    hasher.update(b"\n");
+    for probe in toolchain_probes(project_path, lang, config) {
+        let line = probe_version(&probe);
+        debug!("Toolchain: {line}");
+        hasher.update(line);
+        hasher.update(b"\n");
+    }
+    hasher.update(format!("tree={}\n", tree_digest(project_path)?));
+    // An explicit WIT directory may live outside the tree (e.g. `../wit`).
+    if let Some(wit) = config.build.as_ref().and_then(|b| b.wit.as_deref()) {
+        hasher.update(format!("wit={}\n", tree_digest(&project_path.join(wit))?));
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Restore a cached build, if one exists for `key` and is intact.
+pub(crate) fn restore(project_path: &Path, key: &str) -> Result<Option<PackResult>> {
+    let dir = cache_dir(project_path);
+    let meta_path = dir.join(format!("{key}.json"));
+    let wasm_path = dir.join(format!("{key}.wasm"));
+    if !meta_path.is_file() || !wasm_path.is_file() {
+        return Ok(None);
+    }
+
+    let entry: CacheEntry = serde_json::from_slice(&fs::read(&meta_path)?)?;
+    if crate::sha256_file(&wasm_path)? != entry.sha256 {
+        warn!("Build cache entry {key} is corrupt; rebuilding");
+        let _ = fs::remove_file(&meta_path);
+        let _ = fs::remove_file(&wasm_path);
+        return Ok(None);
+    }
+
+    let output_path = project_path.join(&entry.output_path);
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(&wasm_path, &output_path)?;
+    // Touch the entry so pruning keeps recently used builds.
+    fs::write(&meta_path, fs::read(&meta_path)?)?;
+
+    info!("Build cache hit ({}), skipping compilation", &key[..12]);
+    Ok(Some(PackResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        size_bytes: fs::metadata(&output_path)?.len(),
+        sha256: entry.sha256,
+        optimization: entry.optimization.map(|o| OptimizeReport {
+            level: o.level,
+            size_before: o.size_before,
+            size_after: o.size_after,
+        }),
+        signature_bundle: None,
+        sbom: None,
+        cached: true,
+        manifest: None,
+    }))
+}
+
+/// Store a fresh build under `key` and prune old entries.
+pub(crate) fn store(project_path: &Path, key: &str, result: &PackResult) -> Result<()> {
+    let dir = cache_dir(project_path);
+    fs::create_dir_all(&dir)?;
+
+    let output_path = Path::new(&result.output_path);
+    let relative = output_path
+        .strip_prefix(project_path)
+        .context("Build output is outside the project; not caching")?;
+    let entry = CacheEntry {
+        output_path: relative.to_string_lossy().to_string(),
+        sha256: result.sha256.clone(),
+        optimization: result.optimization.as_ref().map(|o| CachedOptimization {
+            level: o.level.clone(),
+            size_before: o.size_before,
+            size_after: o.size_after,
+        }),
+    };
+
+    // Artifact first: an entry is only visible once its metadata exists.
+    fs::copy(output_path, dir.join(format!("{key}.wasm")))?;
+    fs::write(dir.join(format!("{key}.json")), serde_json::to_vec_pretty(&entry)?)?;
+    debug!("Stored build cache entry {key}");
+
+    prune(&dir)
+}
+
+/// Drop all but the [`MAX_ENTRIES`] most recently used entries.
+fn prune(dir: &Path) -> Result<()> {
+    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| Some((fs::metadata(&p).ok()?.modified().ok()?, p)))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+    for (_, meta) in entries.into_iter().skip(MAX_ENTRIES) {
+        let _ = fs::remove_file(meta.with_extension("wasm"));
+        let _ = fs::remove_file(&meta);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("warp.toml"),
+            "[package]\nname = \"t\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/index.ts"), "export {}").unwrap();
+        dir
+    }
+
+    fn config(dir: &Path) -> WarpConfig {
+        WarpConfig::from_file(&dir.join("warp.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_key_tracks_sources_but_not_build_output() {
+        let dir = project();
+        let base = key(dir.path(), "python", &config(dir.path())).unwrap();
+
+        fs::create_dir_all(dir.path().join("dist")).unwrap();
+        fs::write(dir.path().join("dist/handler.wasm"), b"\0asm").unwrap();
+        fs::create_dir_all(dir.path().join(".warp/cache")).unwrap();
+        fs::write(dir.path().join(".warp/cache/x.json"), "{}").unwrap();
+        assert_eq!(key(dir.path(), "python", &config(dir.path())).unwrap(), base);
+
+        fs::write(dir.path().join("src/index.ts"), "export const x = 1").unwrap();
+        assert_ne!(key(dir.path(), "python", &config(dir.path())).unwrap(), base);
+    }
+
+    #[test]
+    fn test_key_tracks_warp_toml_and_lang() {
+        let dir = project();
+        let base = key(dir.path(), "python", &config(dir.path())).unwrap();
+        assert_ne!(key(dir.path(), "dotnet", &config(dir.path())).unwrap(), base);
+
+        fs::write(
+            dir.path().join("warp.toml"),
+            "[package]\nname = \"t\"\nversion = \"0.2.0\"\n",
+        )
+        .unwrap();
+        assert_ne!(key(dir.path(), "python", &config(dir.path())).unwrap(), base);
+    }
+
+    #[test]
+    fn test_store_then_restore_round_trips() {
+        let dir = project();
+        let output = dir.path().join("dist/handler.wasm");
+        fs::create_dir_all(output.parent().unwrap()).unwrap();
+        fs::write(&output, b"\0asm\x01\0\0\0").unwrap();
+        let built = PackResult {
+            output_path: output.to_string_lossy().to_string(),
+            size_bytes: 8,
+            sha256: crate::sha256_file(&output).unwrap(),
+            optimization: None,
+            signature_bundle: None,
+            sbom: None,
+            cached: false,
+            manifest: None,
+        };
+
+        assert!(restore(dir.path(), "k1").unwrap().is_none());
+        store(dir.path(), "k1", &built).unwrap();
+        fs::remove_file(&output).unwrap();
+
+        let restored = restore(dir.path(), "k1").unwrap().unwrap();
+        assert!(restored.cached);
+        assert_eq!(restored.sha256, built.sha256);
+        assert_eq!(fs::read(&output).unwrap(), b"\0asm\x01\0\0\0");
+    }
+
+    #[test]
+    fn test_corrupt_entry_is_discarded() {
+        let dir = project();
+        let output = dir.path().join("dist/handler.wasm");
+        fs::create_dir_all(output.parent().unwrap()).unwrap();
+        fs::write(&output, b"\0asm").unwrap();
+        let built = PackResult {
+            output_path: output.to_string_lossy().to_string(),
+            size_bytes: 4,
+            sha256: crate::sha256_file(&output).unwrap(),
+            optimization: None,
+            signature_bundle: None,
+            sbom: None,
+            cached: false,
+            manifest: None,
+        };
+        store(dir.path(), "k1", &built).unwrap();
+        fs::write(cache_dir(dir.path()).join("k1.wasm"), b"garbage").unwrap();
+
+        assert!(restore(dir.path(), "k1").unwrap().is_none());
+        assert!(!cache_dir(dir.path()).join("k1.json").exists());
+    }
+}
@@ -0,0 +1,197 @@
+//! Packing a project from its Dockerfile.
+//!
+//! `warp pack --from-dockerfile` lets a team migrate a containerized
+//! service before writing a `warp.toml`. The Dockerfile's build stage
+//! ([`warp_analyzer::analyzers::dockerfile::extract_build`]) decides the
+//! language and entry point, and its steps are replayed in the project
+//! directory (where `COPY . .` would have put the sources):
+//!
+//! - dependency and codegen steps (`npm ci`, `npm run build`,
+//!   `go mod download`, `pip install ...`) run as written;
+//! - native compile steps (`cargo build`, `go build`) are replaced by the
+//!   language's componentization path;
+//! - image setup (`apt-get install`, `useradd`, ...) is skipped.
+//!
+//! An existing `warp.toml` still applies; only a missing `[build]` is
+//! filled in from the Dockerfile.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+use std::process::Command;
+use tracing::{info, warn};
+use warp_analyzer::analyzers;
+use warp_analyzer::analyzers::dockerfile::{self, DockerBuild, StepKind};
+use warp_core::WarpConfig;
+
+use crate::{PackOptions, PackResult, pack_config, workspace};
+
+/// Pack `project_path` as described by `dockerfile` (relative paths are
+/// resolved against the project).
+pub fn pack_from_dockerfile(
+    project_path: &Path,
+    dockerfile: &Path,
+    options: &PackOptions,
+) -> Result<PackResult> {
+    let dockerfile = project_path.join(dockerfile);
+    let build = dockerfile::extract_build(&dockerfile)
+        .with_context(|| format!("Failed to read build from {}", dockerfile.display()))?;
+    info!(
+        "Dockerfile build stage: {} ({}), entry {}",
+        build.base_image,
+        build.lang,
+        build.entry.as_deref().unwrap_or("not named")
+    );
+
+    let config = config_for(project_path, &build)?;
+    prepare(project_path, &build)?;
+
+    // The Dockerfile's language unless warp.toml or --lang says otherwise.
+    let lang = options
+        .lang
+        .clone()
+        .or_else(|| config.build.as_ref().map(|b| b.lang.clone()));
+    pack_config(project_path, &config, &PackOptions { lang, ..options.clone() })
+}
+
+/// The project's `warp.toml`, with a missing `[build]` taken from the
+/// Dockerfile; a scaffolded config when there is none.
+fn config_for(project_path: &Path, build: &DockerBuild) -> Result<WarpConfig> {
+    let entry = build
+        .entry
+        .clone()
+        .unwrap_or_else(|| analyzers::default_entry(&build.lang).to_string());
+    let path = project_path.join("warp.toml");
+    if !path.exists() {
+        let name = project_path
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "app".to_string());
+        return Ok(WarpConfig::scaffold(&name, &build.lang, &entry));
+    }
+
+    let mut config = WarpConfig::from_file(&path)?;
+    if workspace::is_workspace(&config) {
+        bail!(
+            "{} is a multi-component workspace; --from-dockerfile packs a single component",
+            project_path.display()
+        );
+    }
+    if config.build.is_none() {
+        let scaffold = WarpConfig::scaffold(&config.package.name, &build.lang, &entry);
+        config.build = scaffold.build;
+    }
+    Ok(config)
+}
+
+/// Run the build stage's dependency and codegen steps.
+fn prepare(project_path: &Path, build: &DockerBuild) -> Result<()> {
+    for step in &build.steps {
+        match step.kind {
+            StepKind::System => {
+                info!("Skipping image setup step: {}", step.command);
+                continue;
+            }
+            StepKind::Compile => {
+                info!("Replacing compile step with componentization: {}", step.command);
+                continue;
+            }
+            StepKind::Prepare => {}
+        }
+
+        info!("Running Dockerfile step: {}", step.command);
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&step.command)
+            .current_dir(project_path)
+            .output()
+            .with_context(|| format!("Failed to run '{}'", step.command))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let exit_code = output.status.code().unwrap_or(-1);
+            bail!(
+                "Dockerfile step '{}' failed (exit code {exit_code}).\n\n\
+                 --- stderr ---\n{stderr}",
+                step.command
+            );
+        }
+    }
+    if !build.steps.iter().any(|step| step.kind == StepKind::Compile)
+        && matches!(build.lang.as_str(), "rust" | "go" | "dotnet")
+    {
+        warn!("The Dockerfile build stage has no compile step; packing the project as-is");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dockerfile::BuildStep;
+    use std::fs;
+    use warp_core::config::BuildConfig;
+
+    fn build(lang: &str, entry: Option<&str>, steps: &[(&str, StepKind)]) -> DockerBuild {
+        DockerBuild {
+            lang: lang.into(),
+            base_image: "node:20".into(),
+            steps: steps
+                .iter()
+                .map(|(command, kind)| BuildStep { command: command.to_string(), kind: *kind })
+                .collect(),
+            entry: entry.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_config_is_scaffolded_without_warp_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_for(dir.path(), &build("js", Some("dist/server.js"), &[])).unwrap();
+        let build_config = config.build.unwrap();
+        assert_eq!(build_config.lang, "js");
+        assert_eq!(build_config.entry, "dist/server.js");
+
+        let config = config_for(dir.path(), &build("go", None, &[])).unwrap();
+        assert_eq!(config.build.unwrap().entry, "main.go");
+    }
+
+    #[test]
+    fn test_warp_toml_build_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("warp.toml"),
+            "[package]\nname = \"api\"\nversion = \"1.0.0\"\n\n\
+             [build]\nlang = \"typescript\"\nentry = \"src/handler.ts\"\n\n\
+             [shims]\ndns = true\n",
+        )
+        .unwrap();
+        let config = config_for(dir.path(), &build("js", Some("dist/server.js"), &[])).unwrap();
+        let BuildConfig { lang, entry, .. } = config.build.unwrap();
+        assert_eq!((lang.as_str(), entry.as_str()), ("typescript", "src/handler.ts"));
+        assert_eq!(config.shims.unwrap().dns, Some(true));
+
+        let package_only = "[package]\nname = \"api\"\nversion = \"1.0.0\"\n";
+        fs::write(dir.path().join("warp.toml"), package_only).unwrap();
+        let config = config_for(dir.path(), &build("python", Some("app.py"), &[])).unwrap();
+        assert_eq!(config.package.name, "api");
+        assert_eq!(config.build.unwrap().entry, "app.py");
+    }
+
+    #[test]
+    fn test_only_prepare_steps_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let steps = [
+            ("touch system", StepKind::System),
+            ("touch compile", StepKind::Compile),
+            ("touch prepare", StepKind::Prepare),
+        ];
+        prepare(dir.path(), &build("js", None, &steps)).unwrap();
+        assert!(dir.path().join("prepare").exists());
+        assert!(!dir.path().join("system").exists());
+        assert!(!dir.path().join("compile").exists());
+
+        let failing = build("js", None, &[("exit 3", StepKind::Prepare)]);
+        let err = prepare(dir.path(), &failing).unwrap_err();
+        assert!(err.to_string().contains("exit code 3"), "{err}");
+    }
+}
@@ -0,0 +1,289 @@
+//! .NET packaging via componentize-dotnet (NativeAOT-LLVM).
+//!
+//! Pipeline:
+//! 1. Locate the `.csproj` (from `warp.toml` build.entry, or the single
+//!    `*.csproj` in the project root)
+//! 2. Check it references the componentize-dotnet SDK package
+//! 3. Invoke `dotnet publish -c Release -r wasi-wasm` to produce a component
+//! 4. Validate the component exports `wasi:http/incoming-handler`
+//! 5. Copy to `dist/handler.wasm`, compute size + SHA256, return PackResult
+//!
+//! Requires:
+//! - .NET 9 SDK
+//! - `BytecodeAlliance.Componentize.DotNet.Wasm.SDK` package reference
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info, warn};
+use warp_core::WarpConfig;
+
+use crate::PackResult;
+use crate::{bun, toolchain};
+
+/// NuGet package that provides the NativeAOT-LLVM WASI toolchain.
+const COMPONENTIZE_DOTNET_PACKAGE: &str = "BytecodeAlliance.Componentize.DotNet.Wasm.SDK";
+
+/// Runtime identifier targeted by componentize-dotnet.
+const WASI_RID: &str = "wasi-wasm";
+
+/// Locate the `dotnet` CLI.
+///
+/// Search order:
+/// 1. `$WARPGRID_DOTNET_PATH` environment variable
+/// 2. `dotnet` on `$PATH`
+fn find_dotnet() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_DOTNET_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            debug!("Found dotnet at {} (from WARPGRID_DOTNET_PATH)", p.display());
+            return Ok(p);
+        }
+        bail!("WARPGRID_DOTNET_PATH is set to '{path}' but the file does not exist.");
+    }
+
+    if let Ok(output) = Command::new("which").arg("dotnet").output()
+        && output.status.success()
+    {
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !path.is_empty() {
+            debug!("Found dotnet at {} (system PATH)", path);
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    bail!(
+        ".NET SDK not found.\n\
+         \n\
+         Install the .NET 9 SDK from https://dot.net, or set WARPGRID_DOTNET_PATH\n\
+         to point to your dotnet binary."
+    )
+}
+
+/// Find the project file: `build.entry` if it names a `.csproj`, otherwise
+/// the single `*.csproj` in the project root.
+pub(crate) fn find_csproj(project_path: &Path, entry: Option<&str>) -> Result<PathBuf> {
+    if let Some(entry) = entry.filter(|e| e.ends_with(".csproj")) {
+        let path = project_path.join(entry);
+        if !path.is_file() {
+            bail!(
+                "Project file not found: {}\n\
+                 Check [build] entry in warp.toml",
+                path.display()
+            );
+        }
+        return Ok(path);
+    }
+
+    let mut candidates: Vec<PathBuf> = fs::read_dir(project_path)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "csproj"))
+        .collect();
+    candidates.sort();
+
+    match candidates.len() {
+        0 => bail!(
+            "No .csproj found in {}.\n\
+             Set [build] entry in warp.toml to your project file.",
+            project_path.display()
+        ),
+        1 => Ok(candidates.remove(0)),
+        _ => bail!(
+            "Multiple .csproj files found in {}; set [build] entry in warp.toml \
+             to choose one.",
+            project_path.display()
+        ),
+    }
+}
+
+/// Extract the text of a simple `<Tag>value</Tag>` MSBuild property.
+fn msbuild_property(csproj: &str, tag: &str) -> Option<String> {
+    let open = format!("<{tag}>");
+    let start = csproj.find(&open)? + open.len();
+    let end = csproj[start..].find(&format!("</{tag}>"))? + start;
+    let value = csproj[start..end].trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Extract the `World="..."` attribute of the first `<Wit>` item, if any.
+fn wit_world(csproj: &str) -> Option<String> {
+    let item = &csproj[csproj.find("<Wit ")?..];
+    let item = &item[..item.find('>')?];
+    let start = item.find("World=\"")? + "World=\"".len();
+    let end = item[start..].find('"')? + start;
+    Some(item[start..end].to_string())
+}
+
+/// The .NET packaging function invoked by `warp pack --lang dotnet`.
+pub fn pack_dotnet(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
+    // Validate project structure first (before toolchain checks) for better error messages
+    let entry = config.build.as_ref().map(|b| b.entry.as_str());
+    let csproj_path = find_csproj(project_path, entry)?;
+    let csproj = fs::read_to_string(&csproj_path)
+        .with_context(|| format!("Failed to read {}", csproj_path.display()))?;
+
+    if !csproj.contains(COMPONENTIZE_DOTNET_PACKAGE) {
+        bail!(
+            "{} does not reference {COMPONENTIZE_DOTNET_PACKAGE}.\n\
+             \n\
+             Add the componentize-dotnet SDK to your project:\n\
+             \n\
+             \x20 dotnet add package {COMPONENTIZE_DOTNET_PACKAGE} --prerelease",
+            csproj_path.display()
+        );
+    }
+    match wit_world(&csproj) {
+        Some(world) => info!("Targeting WIT world '{world}'"),
+        None => warn!(
+            "No <Wit World=\"...\"> item in {}; componentize-dotnet will use its default world",
+            csproj_path.display()
+        ),
+    }
+
+    let assembly_name = msbuild_property(&csproj, "AssemblyName")
+        .or_else(|| {
+            csproj_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(String::from)
+        })
+        .context("Invalid .csproj file name")?;
+
+    // Now check the toolchain
+    let dotnet = find_dotnet()?;
+
+    info!("Packaging .NET handler: {}", csproj_path.display());
+
+    let dist_dir = project_path.join("dist");
+    let publish_dir = dist_dir.join("dotnet-publish");
+    fs::create_dir_all(&publish_dir)?;
+
+    let mut cmd = Command::new(&dotnet);
+    cmd.envs(toolchain::build_env(project_path, config))
+        .arg("publish")
+        .arg(&csproj_path)
+        .arg("-c")
+        .arg("Release")
+        .arg("-r")
+        .arg(WASI_RID)
+        .arg("-o")
+        .arg(&publish_dir)
+        .current_dir(project_path);
+
+    debug!("Running: {:?}", cmd);
+
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to execute dotnet at {}", dotnet.display()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        bail!(
+            "dotnet publish failed (exit code: {}).\n\n\
+             Stderr:\n{}\n\n\
+             Stdout:\n{}\n\n\
+             Hint: NativeAOT-LLVM does not support reflection-heavy libraries or\n\
+             runtime code generation. Check the trim/AOT warnings above.",
+            output.status.code().unwrap_or(-1),
+            stderr,
+            stdout
+        );
+    }
+
+    let published = publish_dir.join(format!("{assembly_name}.wasm"));
+    if !published.is_file() {
+        bail!(
+            "dotnet publish produced no component at {}",
+            published.display()
+        );
+    }
+
+    bun::validate_component(&published)?;
+
+    let output_path = dist_dir.join("handler.wasm");
+    fs::copy(&published, &output_path)
+        .with_context(|| format!("Failed to copy {} to {}", published.display(), output_path.display()))?;
+
+    let size_bytes = fs::metadata(&output_path)?.len();
+    let sha256 = crate::sha256_file(&output_path)?;
+
+    info!(
+        "Compiled handler.wasm: {} bytes, sha256: {}",
+        size_bytes, sha256
+    );
+
+    Ok(PackResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CSPROJ: &str = r#"<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <TargetFramework>net9.0</TargetFramework>
+    <AssemblyName>Handler</AssemblyName>
+  </PropertyGroup>
+  <ItemGroup>
+    <PackageReference Include="BytecodeAlliance.Componentize.DotNet.Wasm.SDK" Version="0.6.0-preview*" />
+  </ItemGroup>
+  <ItemGroup>
+    <Wit Update="wit/handler.wit" World="handler" />
+  </ItemGroup>
+</Project>
+"#;
+
+    #[test]
+    fn test_msbuild_property_and_world() {
+        assert_eq!(msbuild_property(CSPROJ, "AssemblyName").as_deref(), Some("Handler"));
+        assert_eq!(msbuild_property(CSPROJ, "RootNamespace"), None);
+        assert_eq!(wit_world(CSPROJ).as_deref(), Some("handler"));
+    }
+
+    #[test]
+    fn test_find_csproj_single_in_root() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("App.csproj"), CSPROJ).unwrap();
+
+        let found = find_csproj(dir.path(), Some("Program.cs")).unwrap();
+        assert_eq!(found, dir.path().join("App.csproj"));
+    }
+
+    #[test]
+    fn test_find_csproj_ambiguous() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("A.csproj"), CSPROJ).unwrap();
+        fs::write(dir.path().join("B.csproj"), CSPROJ).unwrap();
+
+        let err = find_csproj(dir.path(), None).unwrap_err().to_string();
+        assert!(err.contains("Multiple .csproj"), "Error: {err}");
+        assert!(find_csproj(dir.path(), Some("B.csproj")).is_ok());
+    }
+
+    #[test]
+    fn test_pack_dotnet_requires_componentize_package() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("App.csproj"),
+            "<Project Sdk=\"Microsoft.NET.Sdk\"></Project>",
+        )
+        .unwrap();
+        let config = WarpConfig::scaffold("app", "dotnet", "App.csproj");
+
+        let err = pack_dotnet(dir.path(), &config).unwrap_err().to_string();
+        assert!(err.contains(COMPONENTIZE_DOTNET_PACKAGE), "Error: {err}");
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Pipeline:
 //! 1. Locate TinyGo binary at `build/tinygo/bin/tinygo` or `$WARPGRID_TINYGO_PATH`
+//!    (bootstrapped into `~/.warp/toolchains` when neither exists)
 //! 2. Locate entry point from `warp.toml` build.entry
 //! 3. Invoke `tinygo build -target=wasip2 -o <output>` to produce a Wasm component
 //! 4. Validate output, compute size + SHA256, return PackResult
@@ -17,7 +18,8 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use tracing::{debug, info};
 
-use crate::PackResult;
+use crate::bootstrap::{self, Tool};
+use crate::{PackResult, toolchain};
 
 /// Locate the TinyGo binary.
 ///
@@ -103,7 +105,8 @@ pub fn pack_go(project_path: &Path, config: &warp_core::WarpConfig) -> Result<Pa
     }
 
     // Locate TinyGo
-    let tinygo = find_tinygo(project_path)?;
+    let tinygo = bootstrap::or_install(find_tinygo(project_path), Tool::TinyGo, Some(config))?;
+    toolchain::check_pin(config, "tinygo", &tinygo)?;
 
     info!("Packaging Go handler: {}", entry_path.display());
 
@@ -121,7 +124,8 @@ pub fn pack_go(project_path: &Path, config: &warp_core::WarpConfig) -> Result<Pa
         .arg("-o")
         .arg(&output_path)
         .arg(&entry_path)
-        .current_dir(project_path);
+        .current_dir(project_path)
+        .envs(toolchain::build_env(project_path, config));
 
     debug!("Running: {:?}", cmd);
 
@@ -168,6 +172,11 @@ pub fn pack_go(project_path: &Path, config: &warp_core::WarpConfig) -> Result<Pa
         output_path: output_path.to_string_lossy().to_string(),
         size_bytes,
         sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
     })
 }
 
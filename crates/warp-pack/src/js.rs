@@ -1,4 +1,4 @@
-//! JavaScript/TypeScript packaging via ComponentizeJS (jco).
+//! JavaScript packaging via ComponentizeJS (jco).
 //!
 //! Pipeline:
 //! 1. Locate jco binary at `build/componentize-js/node_modules/.bin/jco`
@@ -17,10 +17,11 @@ use std::process::Command;
 use tracing::{debug, info, warn};
 use warp_core::WarpConfig;
 
-use crate::PackResult;
+use crate::bootstrap::{self, Tool};
+use crate::{PackResult, toolchain};
 
 /// Locate the jco binary relative to the project root.
-fn find_jco(project_root: &Path) -> Result<PathBuf> {
+pub(crate) fn find_jco(project_root: &Path) -> Result<PathBuf> {
     let jco_path = project_root
         .join("build")
         .join("componentize-js")
@@ -64,7 +65,7 @@ fn find_jco(project_root: &Path) -> Result<PathBuf> {
 ///
 /// The SDK root is detected by the presence of `build/componentize-js/` or `scripts/build-componentize-js.sh`.
 /// If no SDK root is found, falls back to the project path itself.
-fn find_sdk_root(project_path: &Path) -> PathBuf {
+pub(crate) fn find_sdk_root(project_path: &Path) -> PathBuf {
     let mut candidate = project_path.to_path_buf();
     for _ in 0..10 {
         if candidate.join("build").join("componentize-js").exists()
@@ -87,7 +88,7 @@ fn find_sdk_root(project_path: &Path) -> PathBuf {
 /// - `globalThis.warpgrid.dns` — resolve (from WIT imports)
 /// - `globalThis.warpgrid.fs` — readFile (from WIT imports)
 /// - `globalThis.process.env` — environment variable access
-fn generate_prelude(config: &WarpConfig) -> String {
+pub(crate) fn generate_prelude(config: &WarpConfig) -> String {
     let shims = config.shims.as_ref();
     let db_enabled = shims.is_none_or(|s| s.database_proxy.unwrap_or(true));
     let dns_enabled = shims.is_none_or(|s| s.dns.unwrap_or(true));
@@ -217,11 +218,12 @@ pub fn pack_js(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
     }
 
     // Locate WIT directory — prefer project-local, fall back to src/wit/
-    let wit_dir = resolve_wit_dir(project_path)?;
+    let wit_dir = wit_dir_for(project_path, config)?;
 
     // Now check the toolchain
     let sdk_root = find_sdk_root(project_path);
-    let jco_path = find_jco(&sdk_root)?;
+    let jco_path = bootstrap::or_install(find_jco(&sdk_root), Tool::Jco, Some(config))?;
+    toolchain::check_pin(config, "jco", &jco_path)?;
 
     info!("Packaging JS/TS handler: {}", entry_path.display());
 
@@ -245,7 +247,7 @@ pub fn pack_js(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
     fs::write(&combined_path, &combined_source)?;
 
     // Determine world name from WIT files or default
-    let world_name = detect_world_name(&wit_dir).unwrap_or_else(|| "handler".to_string());
+    let world_name = world_for(config, &wit_dir);
 
     info!(
         "Componentizing with world '{}', WIT dir: {}",
@@ -253,21 +255,71 @@ pub fn pack_js(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
         wit_dir.display()
     );
 
-    // Build jco command
     let output_path = dist_dir.join("handler.wasm");
-    let mut cmd = Command::new(&jco_path);
-    cmd.arg("componentize")
-        .arg(&combined_path)
+    let env = toolchain::build_env(project_path, config);
+    let result = componentize(&jco_path, &combined_path, &wit_dir, &world_name, &output_path, &env);
+
+    // Clean up combined handler (ignore errors)
+    let _ = fs::remove_file(&combined_path);
+    result?;
+
+    // Compute size and SHA256
+    let wasm_bytes = fs::read(&output_path)?;
+    let size_bytes = wasm_bytes.len() as u64;
+    let sha256 = hex::encode(Sha256::digest(&wasm_bytes));
+
+    info!(
+        "Compiled handler.wasm: {} bytes, sha256: {}",
+        size_bytes, sha256
+    );
+
+    // Log shim injection summary
+    if usage.uses_database {
+        info!("Shim injected: warpgrid.database (database-proxy)");
+    }
+    if usage.uses_dns {
+        info!("Shim injected: warpgrid.dns (dns)");
+    }
+    if usage.uses_filesystem {
+        info!("Shim injected: warpgrid.fs (filesystem)");
+    }
+
+    Ok(PackResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
+    })
+}
+
+/// Invoke `jco componentize` on a single JS module, producing a Wasm component
+/// that exports the fetch-event HTTP handler.
+pub(crate) fn componentize(
+    jco_path: &Path,
+    source_path: &Path,
+    wit_dir: &Path,
+    world_name: &str,
+    output_path: &Path,
+    env: &[(&str, String)],
+) -> Result<()> {
+    let mut cmd = Command::new(jco_path);
+    cmd.envs(env.iter().cloned())
+        .arg("componentize")
+        .arg(source_path)
         .arg("--wit")
-        .arg(&wit_dir)
+        .arg(wit_dir)
         .arg("--world-name")
-        .arg(&world_name)
+        .arg(world_name)
         .arg("--enable")
         .arg("http")
         .arg("--enable")
         .arg("fetch-event")
         .arg("-o")
-        .arg(&output_path);
+        .arg(output_path);
 
     debug!("Running: {:?}", cmd);
 
@@ -275,9 +327,6 @@ pub fn pack_js(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
         .output()
         .context("Failed to execute jco. Is Node.js installed?")?;
 
-    // Clean up combined handler (ignore errors)
-    let _ = fs::remove_file(&combined_path);
-
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -302,32 +351,38 @@ pub fn pack_js(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
         );
     }
 
-    // Compute size and SHA256
-    let wasm_bytes = fs::read(&output_path)?;
-    let size_bytes = wasm_bytes.len() as u64;
-    let sha256 = hex::encode(Sha256::digest(&wasm_bytes));
-
-    info!(
-        "Compiled handler.wasm: {} bytes, sha256: {}",
-        size_bytes, sha256
-    );
+    Ok(())
+}
 
-    // Log shim injection summary
-    if usage.uses_database {
-        info!("Shim injected: warpgrid.database (database-proxy)");
-    }
-    if usage.uses_dns {
-        info!("Shim injected: warpgrid.dns (dns)");
-    }
-    if usage.uses_filesystem {
-        info!("Shim injected: warpgrid.fs (filesystem)");
+/// The WIT directory to build against: `[build].wit` when set (relative
+/// to the project, or absolute for a workspace's shared WIT), otherwise
+/// [`resolve_wit_dir`].
+pub(crate) fn wit_dir_for(project_path: &Path, config: &WarpConfig) -> Result<PathBuf> {
+    match config.build.as_ref().and_then(|b| b.wit.as_deref()) {
+        Some(wit) => {
+            let wit_dir = project_path.join(wit);
+            if !wit_dir.is_dir() {
+                bail!(
+                    "WIT directory not found: '{}'. Check [build].wit in warp.toml.",
+                    wit_dir.display()
+                );
+            }
+            debug!("Using WIT directory: {}", wit_dir.display());
+            Ok(wit_dir)
+        }
+        None => resolve_wit_dir(project_path),
     }
+}
 
-    Ok(PackResult {
-        output_path: output_path.to_string_lossy().to_string(),
-        size_bytes,
-        sha256,
-    })
+/// The world to componentize against: `[build].world` when set, otherwise
+/// the first world in `wit_dir`, falling back to `handler`.
+pub(crate) fn world_for(config: &WarpConfig, wit_dir: &Path) -> String {
+    config
+        .build
+        .as_ref()
+        .and_then(|b| b.world.clone())
+        .or_else(|| detect_world_name(wit_dir))
+        .unwrap_or_else(|| "handler".to_string())
 }
 
 /// Find the WIT directory for the project.
@@ -335,7 +390,7 @@ pub fn pack_js(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
 /// Checks in order:
 /// 1. `<project>/wit/` — project-local WIT definitions
 /// 2. `<project>/src/wit/` — source-nested WIT
-fn resolve_wit_dir(project_path: &Path) -> Result<PathBuf> {
+pub(crate) fn resolve_wit_dir(project_path: &Path) -> Result<PathBuf> {
     let candidates = [
         project_path.join("wit"),
         project_path.join("src").join("wit"),
@@ -370,7 +425,7 @@ fn resolve_wit_dir(project_path: &Path) -> Result<PathBuf> {
 /// Try to detect the world name from WIT files in the directory.
 ///
 /// Looks for `world <name> {` patterns in .wit files.
-fn detect_world_name(wit_dir: &Path) -> Option<String> {
+pub(crate) fn detect_world_name(wit_dir: &Path) -> Option<String> {
     let entries = fs::read_dir(wit_dir).ok()?;
 
     for entry in entries.flatten() {
@@ -681,7 +736,7 @@ addEventListener("fetch", (event) => {
             r#"addEventListener("fetch", (e) => e.respondWith(new Response("ok")));"#,
         );
 
-        // This will fail (no jco) but should reach JS pipeline
+        // This will fail (no jco) but should reach the TypeScript pipeline
         let result: Result<crate::PackResult> = crate::pack(&project);
         assert!(result.is_err());
         let err_msg = result.unwrap_err().to_string();
@@ -741,6 +796,8 @@ const config = await warpgrid.fs.readFile("/config.json", "utf-8");
             health: None,
             shims: None,
             env: None,
+            components: None,
+            toolchain: None,
         };
 
         let dir = TempDir::new().unwrap();
@@ -1,25 +1,82 @@
 //! warp pack — compile and package Wasm components.
 //!
 //! Phase 1: wraps cargo-component, TinyGo, and ComponentizeJS.
+//! Missing cargo-component, TinyGo, jco, and wasm-tools installs are
+//! bootstrapped into `~/.warp/toolchains` ([`bootstrap`]).
 //! Phase 2: adds Bun compilation via bun build + jco componentize.
+//! TypeScript is bundled with esbuild before ComponentizeJS.
+//! Python is compiled with componentize-py.
+//! .NET is compiled with componentize-dotnet (NativeAOT-LLVM).
+//! Any of them can be followed by a wasm-opt pass (`[build.optimize]`)
+//! and cosign signing (`[build.sign]`). [`sbom`] records the artifact's
+//! dependencies and toolchain (`[build.sbom]`). `[toolchain]` pins tool
+//! versions and makes the artifact reproducible. A component importing a
+//! `warpgrid:shim/*` interface that `[shims]` disables fails the pack.
+//! Every artifact carries its build metadata in a `warpgrid.meta` custom
+//! section ([`warp_core::BuildMetadata`]).
+//! A JSON [`manifest`] next to each artifact records its digest, size,
+//! toolchain, and imports; [`manifest::verify`] re-checks an artifact
+//! against it.
+//! Unchanged projects are served from an incremental build cache.
+//! [`watch::watch`] repacks on every source change.
+//! [`workspace::pack_workspace`] packs every component of a multi-component
+//! workspace (a `warp.toml` with a `[components]` table).
+//! [`dockerfile::pack_from_dockerfile`] packs a project from the build its
+//! Dockerfile describes.
 
 use anyhow::{Result, bail};
 use sha2::{Sha256, Digest};
 use std::path::Path;
-use tracing::info;
+use tracing::{info, warn};
 use warp_core::WarpConfig;
 
+mod bootstrap;
 mod bun;
+mod cache;
+pub mod dockerfile;
+mod dotnet;
+mod go;
 mod js;
+pub mod manifest;
+mod metadata;
+mod optimize;
+mod python;
+mod rust;
+pub mod sbom;
+mod shims;
+mod sign;
+mod toolchain;
+mod typescript;
+pub mod watch;
+pub mod workspace;
 
 /// Supported languages for `warp pack`.
-pub const SUPPORTED_LANGUAGES: &[&str] = &["rust", "go", "js", "typescript", "bun"];
+pub const SUPPORTED_LANGUAGES: &[&str] = &["rust", "go", "js", "typescript", "bun", "python", "dotnet"];
 
 #[derive(Debug)]
 pub struct PackResult {
     pub output_path: String,
     pub size_bytes: u64,
     pub sha256: String,
+    /// Set when the `[build.optimize]` stage ran.
+    pub optimization: Option<OptimizeReport>,
+    /// Path of the sigstore bundle, set when the `[build.sign]` stage ran.
+    pub signature_bundle: Option<String>,
+    /// Path of the SBOM, set when the `[build.sbom]` stage ran.
+    pub sbom: Option<String>,
+    /// Path of the [`manifest::PackManifest`] written next to the artifact.
+    pub manifest: Option<String>,
+    /// The artifact came from the build cache; nothing was compiled.
+    pub cached: bool,
+}
+
+/// Sizes before and after the wasm-opt stage.
+#[derive(Debug)]
+pub struct OptimizeReport {
+    /// wasm-opt level that was applied (e.g. `"s"`, `"3"`).
+    pub level: String,
+    pub size_before: u64,
+    pub size_after: u64,
 }
 
 /// Pack a project at the given path, reading language from `warp.toml`.
@@ -27,15 +84,49 @@ pub fn pack(project_path: &Path) -> Result<PackResult> {
     pack_with_lang(project_path, None)
 }
 
+/// Options for [`pack_with_options`].
+#[derive(Debug, Default, Clone)]
+pub struct PackOptions {
+    /// Language override, taking precedence over `warp.toml`.
+    pub lang: Option<String>,
+    /// Always compile, bypassing the build cache (nothing is stored either).
+    pub no_cache: bool,
+}
+
 /// Pack a project with an optional language override.
 ///
 /// If `lang_override` is `Some`, it takes precedence over `warp.toml`.
 /// If `warp.toml` has no `[build].lang`, the language is auto-detected
 /// from project marker files (e.g., `bunfig.toml` → bun).
 pub fn pack_with_lang(project_path: &Path, lang_override: Option<&str>) -> Result<PackResult> {
+    pack_with_options(
+        project_path,
+        &PackOptions {
+            lang: lang_override.map(String::from),
+            ..Default::default()
+        },
+    )
+}
+
+/// Pack a project with explicit [`PackOptions`].
+pub fn pack_with_options(project_path: &Path, options: &PackOptions) -> Result<PackResult> {
     let config = WarpConfig::from_file(&project_path.join("warp.toml"))?;
+    if workspace::is_workspace(&config) {
+        bail!(
+            "{} is a multi-component workspace; use workspace::pack_workspace",
+            project_path.display()
+        );
+    }
+    pack_config(project_path, &config, options)
+}
 
-    let lang = if let Some(override_lang) = lang_override {
+/// Pack the project at `project_path` with an already-resolved config.
+pub(crate) fn pack_config(
+    project_path: &Path,
+    config: &WarpConfig,
+    options: &PackOptions,
+) -> Result<PackResult> {
+    let lang = if let Some(override_lang) = options.lang.as_deref() {
         override_lang.to_string()
     } else {
         match config.build.as_ref().map(|b| b.lang.as_str()) {
@@ -44,16 +135,70 @@ pub fn pack_with_lang(project_path: &Path, lang_override: Option<&str>) -> Resul
         }
     };
 
-    match lang.as_str() {
-        "rust" => pack_rust(project_path, &config),
-        "go" => pack_go(project_path, &config),
-        "js" | "typescript" => js::pack_js(project_path, &config),
-        "bun" => bun::pack_bun(project_path, &config),
+    let cache_key = if options.no_cache {
+        None
+    } else {
+        cache::key(project_path, &lang, config)
+            .inspect_err(|e| warn!("Build cache disabled: {e:#}"))
+            .ok()
+    };
+    let cached = cache_key.as_deref().and_then(|key| {
+        cache::restore(project_path, key)
+            .inspect_err(|e| warn!("Build cache read failed: {e:#}"))
+            .ok()
+            .flatten()
+    });
+
+    let mut result = match cached {
+        Some(result) => result,
+        None => build(project_path, &lang, config, cache_key.as_deref())?,
+    };
+    shims::validate(config, &result)?;
+    metadata::stamp(project_path, &lang, config, &mut result)?;
+    toolchain::verify_digest(config, &result)?;
+
+    // Before signing, so the signature covers an embedded SBOM.
+    if let Some(opts) = config.build.as_ref().and_then(|b| b.sbom.as_ref()) {
+        sbom::generate(project_path, &lang, config, opts, &mut result)?;
+    }
+    if let Some(opts) = config.build.as_ref().and_then(|b| b.sign.as_ref()) {
+        sign::sign(project_path, opts, &mut result)?;
+    }
+    manifest::write(&lang, config, &mut result)?;
+    Ok(result)
+}
+
+/// Compile and optimize, storing the artifact in the cache under `cache_key`.
+fn build(
+    project_path: &Path,
+    lang: &str,
+    config: &WarpConfig,
+    cache_key: Option<&str>,
+) -> Result<PackResult> {
+    let mut result = match lang {
+        "rust" => rust::pack_rust(project_path, config),
+        "go" => go::pack_go(project_path, config),
+        "js" => js::pack_js(project_path, config),
+        "typescript" => typescript::pack_typescript(project_path, config),
+        "bun" => bun::pack_bun(project_path, config),
+        "python" => python::pack_python(project_path, config),
+        "dotnet" => dotnet::pack_dotnet(project_path, config),
         _ => bail!(
             "Unsupported language: '{lang}'. Supported: {}",
             SUPPORTED_LANGUAGES.join(", ")
         ),
+    }?;
+
+    if let Some(opts) = config.build.as_ref().and_then(|b| b.optimize.as_ref()) {
+        optimize::optimize(project_path, opts, &mut result)?;
+    }
+    toolchain::normalize(config, &mut result)?;
+    if let Some(key) = cache_key
+        && let Err(e) = cache::store(project_path, key, &result)
+    {
+        warn!("Build cache write failed: {e:#}");
     }
+    Ok(result)
 }
 
 /// Auto-detect language from project marker files.
@@ -62,7 +207,9 @@ pub fn pack_with_lang(project_path: &Path, lang_override: Option<&str>) -> Resul
 /// 1. `bunfig.toml` → bun
 /// 2. `Cargo.toml` (non-workspace) → rust
 /// 3. `go.mod` → go
-/// 4. `package.json` → typescript
+/// 4. `pyproject.toml` / `requirements.txt` → python
+/// 5. `*.csproj` → dotnet
+/// 6. `package.json` → typescript
 fn detect_language(project_path: &Path) -> Result<String> {
     if project_path.join("bunfig.toml").exists() {
         info!("Auto-detected language: bun (found bunfig.toml)");
@@ -83,6 +230,23 @@ fn detect_language(project_path: &Path) -> Result<String> {
         return Ok("go".to_string());
     }
 
+    for marker in ["pyproject.toml", "requirements.txt"] {
+        if project_path.join(marker).exists() {
+            info!("Auto-detected language: python (found {marker})");
+            return Ok("python".to_string());
+        }
+    }
+
+    let has_csproj = std::fs::read_dir(project_path).is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|e| e.path().extension().is_some_and(|ext| ext == "csproj"))
+    });
+    if has_csproj {
+        info!("Auto-detected language: dotnet (found .csproj)");
+        return Ok("dotnet".to_string());
+    }
+
     if project_path.join("package.json").exists() {
         info!("Auto-detected language: typescript (found package.json)");
         return Ok("typescript".to_string());
@@ -90,7 +254,8 @@ fn detect_language(project_path: &Path) -> Result<String> {
 
     bail!(
         "Cannot auto-detect language. No marker files found \
-         (bunfig.toml, Cargo.toml, go.mod, package.json). \
+         (bunfig.toml, Cargo.toml, go.mod, pyproject.toml, requirements.txt, *.csproj, \
+         package.json). \
          Either add [build].lang to warp.toml or use --lang."
     )
 }
@@ -102,18 +267,6 @@ pub(crate) fn sha256_file(path: &Path) -> Result<String> {
     Ok(hex::encode(hash))
 }
 
-fn pack_rust(_path: &Path, _config: &WarpConfig) -> Result<PackResult> {
-    // TODO: Invoke cargo-component
-    // cargo component build --release --target wasm32-wasip2
-    bail!("Rust packaging not yet implemented. Requires cargo-component.")
-}
-
-fn pack_go(_path: &Path, _config: &WarpConfig) -> Result<PackResult> {
-    // TODO: Invoke TinyGo or Go 1.24+
-    // tinygo build -target=wasip2 -o output.wasm
-    bail!("Go packaging not yet implemented. Requires TinyGo.")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,6 +344,26 @@ mod tests {
         assert_eq!(result.unwrap(), "typescript");
     }
 
+    #[test]
+    fn auto_detect_python_from_pyproject_or_requirements() {
+        for marker in ["pyproject.toml", "requirements.txt"] {
+            let dir = tempfile::tempdir().unwrap();
+            fs::write(dir.path().join(marker), "").unwrap();
+
+            let result = detect_language(dir.path());
+            assert_eq!(result.unwrap(), "python", "should detect python from {marker}");
+        }
+    }
+
+    #[test]
+    fn auto_detect_dotnet_from_csproj() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Handler.csproj"), "<Project />").unwrap();
+
+        let result = detect_language(dir.path());
+        assert_eq!(result.unwrap(), "dotnet");
+    }
+
     #[test]
     fn auto_detect_rust_from_cargo_toml() {
         let dir = tempfile::tempdir().unwrap();
@@ -276,7 +449,7 @@ mod tests {
     #[test]
     fn unsupported_language_error_lists_all_supported() {
         let dir = tempfile::tempdir().unwrap();
-        write_warp_toml(dir.path(), Some("python"));
+        write_warp_toml(dir.path(), Some("cobol"));
 
         let result = pack(dir.path());
         assert!(result.is_err());
@@ -287,5 +460,7 @@ mod tests {
         assert!(err.contains("go"), "Should list go in error: {err}");
         assert!(err.contains("js"), "Should list js in error: {err}");
         assert!(err.contains("typescript"), "Should list typescript in error: {err}");
+        assert!(err.contains("python"), "Should list python in error: {err}");
+        assert!(err.contains("dotnet"), "Should list dotnet in error: {err}");
     }
 }
@@ -0,0 +1,182 @@
+//! Machine-readable pack manifest.
+//!
+//! Every pack writes `<artifact>.manifest.json` next to the artifact:
+//!
+//! ```json
+//! {
+//!   "package": "api",
+//!   "version": "1.2.0",
+//!   "language": "rust",
+//!   "artifact": "handler.wasm",
+//!   "sha256": "9f86d0...",
+//!   "size_bytes": 1843200,
+//!   "toolchain": { "cargo": "cargo 1.85.0", "warp-pack": "0.1.0" },
+//!   "imports": ["wasi:http/types@0.2.3", "warpgrid:shim/dns@0.1.0"]
+//! }
+//! ```
+//!
+//! It is written last, so it describes the artifact as shipped (with any
+//! embedded SBOM). [`verify`] re-checks an artifact against its manifest:
+//! digest, size, and imports. Toolchain versions are informational.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use warp_core::{WarpConfig, wasm};
+
+use crate::{PackResult, sbom};
+
+/// What a pack produced, serialized next to the artifact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub package: String,
+    pub version: String,
+    pub language: String,
+    /// Artifact file name, relative to the manifest.
+    pub artifact: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    /// Tool name to the version it reported.
+    pub toolchain: BTreeMap<String, String>,
+    /// Interfaces the component imports (empty for a core module).
+    pub imports: Vec<String>,
+    /// Signature bundle file name, when the artifact was signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_bundle: Option<String>,
+    /// SBOM file name, when one was generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sbom: Option<String>,
+}
+
+/// Path of the manifest for an artifact.
+pub fn manifest_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".manifest.json");
+    PathBuf::from(name)
+}
+
+/// Write the manifest for `result`.
+pub(crate) fn write(lang: &str, config: &WarpConfig, result: &mut PackResult) -> Result<()> {
+    let artifact = Path::new(&result.output_path);
+    let binary = fs::read(artifact).with_context(|| format!("Failed to read {}", artifact.display()))?;
+    let manifest = PackManifest {
+        package: config.package.name.clone(),
+        version: config.package.version.clone(),
+        language: lang.to_string(),
+        artifact: file_name(&result.output_path),
+        sha256: result.sha256.clone(),
+        size_bytes: result.size_bytes,
+        toolchain: sbom::toolchain(lang)
+            .into_iter()
+            .map(|tool| (tool.name, tool.version))
+            .collect(),
+        imports: wasm::component_imports(&binary).unwrap_or_default(),
+        signature_bundle: result.signature_bundle.as_deref().map(file_name),
+        sbom: result.sbom.as_deref().map(file_name),
+    };
+
+    let path = manifest_path(artifact);
+    fs::write(&path, serde_json::to_vec_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    info!("Pack manifest: {}", path.display());
+    result.manifest = Some(path.to_string_lossy().into_owned());
+    Ok(())
+}
+
+/// Re-check `artifact` against the manifest next to it, returning the
+/// manifest when every recorded property still holds.
+pub fn verify(artifact: &Path) -> Result<PackManifest> {
+    let path = manifest_path(artifact);
+    let manifest: PackManifest = serde_json::from_slice(
+        &fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?,
+    )
+    .with_context(|| format!("Invalid pack manifest {}", path.display()))?;
+    let binary = fs::read(artifact).with_context(|| format!("Failed to read {}", artifact.display()))?;
+
+    let mut mismatches = Vec::new();
+    if binary.len() as u64 != manifest.size_bytes {
+        mismatches.push(format!(
+            "size is {} bytes, manifest records {}",
+            binary.len(),
+            manifest.size_bytes
+        ));
+    }
+    let sha256 = hex::encode(Sha256::digest(&binary));
+    if sha256 != manifest.sha256 {
+        mismatches.push(format!("sha256 is {sha256}, manifest records {}", manifest.sha256));
+    }
+    let imports = wasm::component_imports(&binary).unwrap_or_default();
+    for import in &imports {
+        if !manifest.imports.contains(import) {
+            mismatches.push(format!("imports {import}, not in the manifest"));
+        }
+    }
+    for import in &manifest.imports {
+        if !imports.contains(import) {
+            mismatches.push(format!("manifest records import {import}, artifact does not import it"));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        bail!(
+            "{} does not match {}:\n  {}",
+            artifact.display(),
+            path.display(),
+            mismatches.join("\n  ")
+        );
+    }
+    Ok(manifest)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_then_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        let binary = b"\0asm\x0d\0\x01\0";
+        fs::write(&artifact, binary).unwrap();
+        let config: WarpConfig =
+            toml::from_str("[package]\nname = \"api\"\nversion = \"1.2.0\"\n").unwrap();
+        let mut result = PackResult {
+            output_path: artifact.to_string_lossy().to_string(),
+            size_bytes: binary.len() as u64,
+            sha256: hex::encode(Sha256::digest(binary)),
+            optimization: None,
+            signature_bundle: None,
+            sbom: Some(dir.path().join("handler.wasm.cdx.json").to_string_lossy().to_string()),
+            manifest: None,
+            cached: false,
+        };
+
+        write("cobol", &config, &mut result).unwrap();
+        let path = manifest_path(&artifact);
+        assert_eq!(result.manifest.as_deref(), Some(path.to_str().unwrap()));
+
+        let manifest = verify(&artifact).unwrap();
+        assert_eq!(manifest.package, "api");
+        assert_eq!(manifest.artifact, "handler.wasm");
+        assert_eq!(manifest.sbom.as_deref(), Some("handler.wasm.cdx.json"));
+        assert!(manifest.imports.is_empty());
+        assert!(manifest.toolchain.contains_key("warp-pack"));
+
+        // Any change to the artifact is caught.
+        fs::write(&artifact, b"\0asm\x0d\0\x01\0\0").unwrap();
+        let err = verify(&artifact).unwrap_err().to_string();
+        assert!(err.contains("size is 9 bytes, manifest records 8"), "{err}");
+        assert!(err.contains("sha256 is"), "{err}");
+    }
+}
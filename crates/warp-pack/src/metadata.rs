@@ -0,0 +1,106 @@
+//! Build metadata stamped into every artifact.
+//!
+//! Appends a [`warp_core::meta::SECTION_NAME`] custom section recording the
+//! package, its version, the git commit, the build time, the language, and
+//! the enabled shims. Runs after the build cache, so a cached artifact is
+//! stamped with the commit it is packed from, and before the `[toolchain]`
+//! digest check and signing, so both cover it. Under `[toolchain]` the
+//! build time is the reproducible `SOURCE_DATE_EPOCH`.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tracing::debug;
+use warp_core::WarpConfig;
+use warp_core::meta::{self, BuildMetadata};
+
+use crate::{PackResult, toolchain};
+
+/// Append the build metadata section to the artifact.
+pub(crate) fn stamp(
+    project_path: &Path,
+    lang: &str,
+    config: &WarpConfig,
+    result: &mut PackResult,
+) -> Result<()> {
+    let metadata = BuildMetadata {
+        package: config.package.name.clone(),
+        version: config.package.version.clone(),
+        git_sha: git_sha(project_path),
+        build_time: toolchain::build_time(project_path, config),
+        language: lang.to_string(),
+        shims: config.shims.as_ref().map(meta::enabled_shims).unwrap_or_default(),
+    };
+
+    let path = Path::new(&result.output_path);
+    let mut binary = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    metadata.embed(&mut binary)?;
+    fs::write(path, &binary)?;
+    result.size_bytes = binary.len() as u64;
+    result.sha256 = hex::encode(Sha256::digest(&binary));
+    debug!(
+        "Embedded build metadata as custom section '{}'",
+        meta::SECTION_NAME
+    );
+    Ok(())
+}
+
+/// `HEAD` of the repository holding the project, with `-dirty` appended
+/// when tracked files have uncommitted changes.
+fn git_sha(project_path: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(project_path)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let sha = git(&["rev-parse", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(if dirty { format!("{sha}-dirty") } else { sha })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_embeds_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        fs::write(&artifact, b"\0asm\x0d\0\x01\0").unwrap();
+        let config: WarpConfig = toml::from_str(
+            "[package]\nname = \"api\"\nversion = \"1.2.0\"\n\n\
+             [shims]\ndns = true\ntimezone = false\n\n\
+             [toolchain]\nsource_date_epoch = 1700000000\n",
+        )
+        .unwrap();
+        let mut result = PackResult {
+            output_path: artifact.to_string_lossy().to_string(),
+            size_bytes: 8,
+            sha256: String::new(),
+            optimization: None,
+            signature_bundle: None,
+            sbom: None,
+            cached: false,
+            manifest: None,
+        };
+
+        stamp(dir.path(), "rust", &config, &mut result).unwrap();
+
+        let binary = fs::read(&artifact).unwrap();
+        let metadata = BuildMetadata::from_wasm(&binary).unwrap().unwrap();
+        assert_eq!(metadata.package, "api");
+        assert_eq!(metadata.version, "1.2.0");
+        assert_eq!(metadata.build_time, 1_700_000_000);
+        assert_eq!(metadata.language, "rust");
+        assert_eq!(metadata.shims, vec!["dns"]);
+        assert_eq!(result.size_bytes, binary.len() as u64);
+        assert_eq!(result.sha256, crate::sha256_file(&artifact).unwrap());
+    }
+}
@@ -0,0 +1,290 @@
+//! Post-componentization optimization via Binaryen's wasm-opt.
+//!
+//! Enabled by a `[build.optimize]` section in `warp.toml`:
+//!
+//! ```toml
+//! [build.optimize]
+//! level = "z"          # "0"–"4", "s", "z" (default "s")
+//! strip_debug = true   # drop names + DWARF (default true)
+//! asyncify = false     # run the asyncify transform (default false)
+//! ```
+//!
+//! wasm-opt only understands core modules. A core module is optimized
+//! directly; a component (what every language pipeline produces) goes
+//! through `jco opt`, which runs wasm-opt on each embedded core module
+//! and re-links the component.
+//!
+//! Requires:
+//! - wasm-opt (Binaryen) for core modules
+//! - jco for components (bundles its own wasm-opt)
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::config::OptimizeConfig;
+
+use crate::{OptimizeReport, PackResult};
+use crate::bootstrap::{self, Tool};
+use crate::js;
+
+/// Levels accepted by wasm-opt's `-O<level>` flag.
+const LEVELS: &[&str] = &["0", "1", "2", "3", "4", "s", "z"];
+
+/// Default level: optimize for size, since startup cost scales with it.
+const DEFAULT_LEVEL: &str = "s";
+
+#[derive(Debug, PartialEq)]
+enum WasmKind {
+    Module,
+    Component,
+}
+
+/// Classify a Wasm binary by its preamble.
+fn wasm_kind(bytes: &[u8]) -> Result<WasmKind> {
+    if bytes.len() < 8 || &bytes[..4] != b"\0asm" {
+        bail!("Not a Wasm binary (missing \\0asm preamble)");
+    }
+    match bytes[4..8] {
+        [0x01, 0x00, 0x00, 0x00] => Ok(WasmKind::Module),
+        // Component encoding version 0x0d, layer 1.
+        [_, _, 0x01, 0x00] => Ok(WasmKind::Component),
+        _ => bail!("Unrecognized Wasm version/layer {:02x?}", &bytes[4..8]),
+    }
+}
+
+/// The optimization level, validated.
+fn level(config: &OptimizeConfig) -> Result<&str> {
+    let level = config.level.as_deref().unwrap_or(DEFAULT_LEVEL);
+    if !LEVELS.contains(&level) {
+        bail!(
+            "Invalid [build.optimize] level '{level}'. Expected one of: {}",
+            LEVELS.join(", ")
+        );
+    }
+    Ok(level)
+}
+
+/// wasm-opt flags for the configured passes, excluding asyncify (which
+/// `jco opt` takes as its own flag).
+fn wasm_opt_flags(config: &OptimizeConfig) -> Result<Vec<String>> {
+    let mut flags = vec![format!("-O{}", level(config)?)];
+    if config.strip_debug.unwrap_or(true) {
+        // Removes DWARF sections and the name section.
+        flags.push("--strip-debug".to_string());
+    }
+    Ok(flags)
+}
+
+/// Locate the wasm-opt binary.
+///
+/// Search order:
+/// 1. `$WARPGRID_WASM_OPT_PATH` environment variable
+/// 2. `wasm-opt` on `$PATH`
+fn find_wasm_opt() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_WASM_OPT_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            debug!("Found wasm-opt at {} (from WARPGRID_WASM_OPT_PATH)", p.display());
+            return Ok(p);
+        }
+        bail!("WARPGRID_WASM_OPT_PATH is set to '{path}' but the file does not exist.");
+    }
+
+    which("wasm-opt").context(
+        "wasm-opt not found. Install Binaryen (https://github.com/WebAssembly/binaryen),\n\
+         set WARPGRID_WASM_OPT_PATH, or remove [build.optimize] from warp.toml.",
+    )
+}
+
+/// Locate jco for optimizing components: the SDK/project install used for
+/// componentization, falling back to a global install on `$PATH`.
+fn find_jco(project_path: &Path) -> Result<PathBuf> {
+    let found = js::find_jco(&js::find_sdk_root(project_path)).or_else(|err| {
+        which("jco").ok_or_else(|| {
+            err.context("jco is required to run wasm-opt over a component's core modules")
+        })
+    });
+    bootstrap::or_install(found, Tool::Jco, None)
+}
+
+pub(crate) fn which(binary: &str) -> Option<PathBuf> {
+    let output = Command::new("which").arg(binary).output().ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// Build the command that optimizes `input` into `output`.
+fn optimize_command(
+    project_path: &Path,
+    config: &OptimizeConfig,
+    kind: WasmKind,
+    input: &Path,
+    output: &Path,
+) -> Result<Command> {
+    let flags = wasm_opt_flags(config)?;
+    let asyncify = config.asyncify.unwrap_or(false);
+
+    let cmd = match kind {
+        WasmKind::Module => {
+            let mut cmd = Command::new(find_wasm_opt()?);
+            cmd.args(&flags);
+            if asyncify {
+                cmd.arg("--asyncify");
+            }
+            cmd.arg(input).arg("-o").arg(output);
+            cmd
+        }
+        WasmKind::Component => {
+            let mut cmd = Command::new(find_jco(project_path)?);
+            cmd.arg("opt").arg(input).arg("-o").arg(output);
+            if asyncify {
+                cmd.arg("--asyncify");
+            }
+            cmd.arg("--").args(&flags);
+            cmd
+        }
+    };
+    Ok(cmd)
+}
+
+/// Optimize the packed artifact in place, updating its size and digest
+/// and recording the before/after sizes.
+pub(crate) fn optimize(
+    project_path: &Path,
+    config: &OptimizeConfig,
+    result: &mut PackResult,
+) -> Result<()> {
+    let artifact = PathBuf::from(&result.output_path);
+    let bytes = fs::read(&artifact)
+        .with_context(|| format!("Failed to read {}", artifact.display()))?;
+    let kind = wasm_kind(&bytes)?;
+    let level = level(config)?.to_string();
+
+    let optimized = artifact.with_extension("opt.wasm");
+    let mut cmd = optimize_command(project_path, config, kind, &artifact, &optimized)?;
+    cmd.current_dir(project_path);
+
+    info!("Optimizing {} (-O{level})", artifact.display());
+    debug!("Running: {:?}", cmd);
+
+    let output = cmd.output().context("Failed to execute wasm optimizer")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        bail!(
+            "wasm-opt failed (exit code: {}).\n\n\
+             Stderr:\n{}\n\n\
+             Stdout:\n{}",
+            output.status.code().unwrap_or(-1),
+            stderr,
+            stdout
+        );
+    }
+    if !optimized.is_file() {
+        bail!("Optimizer produced no output at {}", optimized.display());
+    }
+
+    fs::rename(&optimized, &artifact)
+        .with_context(|| format!("Failed to replace {}", artifact.display()))?;
+
+    let size_before = result.size_bytes;
+    result.size_bytes = fs::metadata(&artifact)?.len();
+    result.sha256 = crate::sha256_file(&artifact)?;
+    result.optimization = Some(OptimizeReport {
+        level,
+        size_before,
+        size_after: result.size_bytes,
+    });
+
+    info!(
+        "Optimized handler.wasm: {} → {} bytes, sha256: {}",
+        size_before, result.size_bytes, result.sha256
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(level: Option<&str>, strip_debug: Option<bool>) -> OptimizeConfig {
+        OptimizeConfig {
+            level: level.map(String::from),
+            strip_debug,
+            asyncify: None,
+        }
+    }
+
+    #[test]
+    fn test_wasm_kind_from_preamble() {
+        assert_eq!(
+            wasm_kind(b"\0asm\x01\0\0\0").unwrap(),
+            WasmKind::Module
+        );
+        assert_eq!(
+            wasm_kind(b"\0asm\x0d\0\x01\0").unwrap(),
+            WasmKind::Component
+        );
+        assert!(wasm_kind(b"not wasm").is_err());
+    }
+
+    #[test]
+    fn test_flags_default_to_size_and_strip() {
+        let flags = wasm_opt_flags(&config(None, None)).unwrap();
+        assert_eq!(flags, ["-Os", "--strip-debug"]);
+
+        let flags = wasm_opt_flags(&config(Some("3"), Some(false))).unwrap();
+        assert_eq!(flags, ["-O3"]);
+    }
+
+    #[test]
+    fn test_invalid_level_rejected() {
+        let err = wasm_opt_flags(&config(Some("fast"), None)).unwrap_err().to_string();
+        assert!(err.contains("Invalid [build.optimize] level"), "Error: {err}");
+    }
+
+    #[test]
+    fn test_component_asyncify_goes_to_jco() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let bin = dir.path().join("node_modules/.bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join("jco"), "").unwrap();
+
+        let mut opts = config(Some("z"), None);
+        opts.asyncify = Some(true);
+        let cmd = optimize_command(
+            dir.path(),
+            &opts,
+            WasmKind::Component,
+            Path::new("in.wasm"),
+            Path::new("out.wasm"),
+        )
+        .unwrap();
+
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(
+            args,
+            ["opt", "in.wasm", "-o", "out.wasm", "--asyncify", "--", "-Oz", "--strip-debug"]
+        );
+    }
+
+    #[test]
+    fn test_optimize_config_parses_from_warp_toml() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("warp.toml");
+        fs::write(
+            &path,
+            "[package]\nname = \"t\"\nversion = \"0.1.0\"\n\n\
+             [build]\nlang = \"typescript\"\nentry = \"src/index.ts\"\n\n\
+             [build.optimize]\nlevel = \"z\"\nasyncify = true\n",
+        )
+        .unwrap();
+        let config = warp_core::WarpConfig::from_file(&path).unwrap();
+        let opts = config.build.unwrap().optimize.unwrap();
+        assert_eq!(opts.level.as_deref(), Some("z"));
+        assert_eq!(opts.asyncify, Some(true));
+        assert_eq!(opts.strip_debug, None);
+    }
+}
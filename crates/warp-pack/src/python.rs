@@ -0,0 +1,273 @@
+//! Python packaging via componentize-py.
+//!
+//! Pipeline:
+//! 1. Locate handler entry point from `warp.toml` build.entry
+//! 2. Locate WIT directory and the componentize-py binary
+//! 3. Derive the Python module name from the entry file (`app.py` → `app`)
+//! 4. Invoke `componentize-py componentize` against the WarpGrid WIT world,
+//!    with the entry directory and any project virtualenv on the module path
+//! 5. Compute size + SHA256, return PackResult
+//!
+//! Requires:
+//! - componentize-py 0.16+ (`pip install componentize-py`)
+//! - Pure-Python dependencies only; C extensions cannot be componentized
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::WarpConfig;
+
+use crate::PackResult;
+use crate::{js, toolchain};
+
+/// Locate the componentize-py binary.
+///
+/// Search order:
+/// 1. `$WARPGRID_COMPONENTIZE_PY_PATH` environment variable
+/// 2. `<project>/.venv/bin/componentize-py` (project virtualenv)
+/// 3. `componentize-py` on `$PATH`
+fn find_componentize_py(project_path: &Path) -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_COMPONENTIZE_PY_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            debug!("Found componentize-py at {} (from WARPGRID_COMPONENTIZE_PY_PATH)", p.display());
+            return Ok(p);
+        }
+        bail!(
+            "WARPGRID_COMPONENTIZE_PY_PATH is set to '{path}' but the file does not exist. \
+             Install componentize-py: pip install componentize-py"
+        );
+    }
+
+    let venv_bin = project_path.join(".venv").join("bin").join("componentize-py");
+    if venv_bin.is_file() {
+        debug!("Found componentize-py at {} (project virtualenv)", venv_bin.display());
+        return Ok(venv_bin);
+    }
+
+    if let Ok(output) = Command::new("which").arg("componentize-py").output()
+        && output.status.success()
+    {
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !path.is_empty() {
+            debug!("Found componentize-py at {} (system PATH)", path);
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    bail!(
+        "componentize-py not found. Install it with one of:\n  \
+         1. pip install componentize-py (in the project's .venv)\n  \
+         2. pipx install componentize-py\n  \
+         3. Set WARPGRID_COMPONENTIZE_PY_PATH to the componentize-py binary path"
+    )
+}
+
+/// Derive the Python module name from the entry file path.
+fn module_name(entry_path: &Path) -> Result<String> {
+    if entry_path.extension().is_none_or(|ext| ext != "py") {
+        bail!(
+            "Python entry point must be a .py file, got: {}",
+            entry_path.display()
+        );
+    }
+    entry_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(String::from)
+        .context("Invalid Python entry point file name")
+}
+
+/// Directories added to componentize-py's module search path.
+///
+/// The entry's own directory comes first, followed by the project root and
+/// the site-packages of a project `.venv` so installed dependencies are
+/// bundled into the component.
+fn python_paths(project_path: &Path, entry_path: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(parent) = entry_path.parent() {
+        paths.push(parent.to_path_buf());
+    }
+    if !paths.iter().any(|p| p == project_path) {
+        paths.push(project_path.to_path_buf());
+    }
+
+    let venv_lib = project_path.join(".venv").join("lib");
+    if let Ok(entries) = fs::read_dir(&venv_lib) {
+        for entry in entries.flatten() {
+            let site_packages = entry.path().join("site-packages");
+            if site_packages.is_dir() {
+                paths.push(site_packages);
+            }
+        }
+    }
+    paths
+}
+
+/// The Python packaging function invoked by `warp pack --lang python`.
+pub fn pack_python(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
+    // Validate project structure first (before toolchain checks) for better error messages
+    let build = config
+        .build
+        .as_ref()
+        .context("Missing [build] section in warp.toml")?;
+    let entry_path = project_path.join(&build.entry);
+    if !entry_path.is_file() {
+        bail!(
+            "Entry point not found: {}\n\
+             Check [build] entry in warp.toml",
+            entry_path.display()
+        );
+    }
+    let module = module_name(&entry_path)?;
+
+    let wit_dir = js::wit_dir_for(project_path, config)?;
+
+    // Now check the toolchain
+    let componentize_py = find_componentize_py(project_path)?;
+    toolchain::check_pin(config, "componentize_py", &componentize_py)?;
+
+    info!("Packaging Python handler: {}", entry_path.display());
+
+    let dist_dir = project_path.join("dist");
+    fs::create_dir_all(&dist_dir)?;
+    let output_path = dist_dir.join("handler.wasm");
+
+    let world_name = js::world_for(config, &wit_dir);
+    info!(
+        "Componentizing module '{}' with world '{}', WIT dir: {}",
+        module,
+        world_name,
+        wit_dir.display()
+    );
+
+    let mut cmd = Command::new(&componentize_py);
+    cmd.envs(toolchain::build_env(project_path, config))
+        .arg("-d")
+        .arg(&wit_dir)
+        .arg("-w")
+        .arg(&world_name)
+        .arg("componentize")
+        .arg(&module);
+    for path in python_paths(project_path, &entry_path) {
+        cmd.arg("-p").arg(path);
+    }
+    cmd.arg("-o").arg(&output_path).current_dir(project_path);
+
+    debug!("Running: {:?}", cmd);
+
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to execute componentize-py at {}", componentize_py.display()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        bail!(
+            "componentize-py compilation failed (exit code: {}).\n\n\
+             Stderr:\n{}\n\n\
+             Stdout:\n{}\n\n\
+             Hint: Ensure your handler module defines a class implementing the\n\
+             exported WIT interface, and that all dependencies are pure Python:\n\
+             \x20 warp convert analyze --lang python",
+            output.status.code().unwrap_or(-1),
+            stderr,
+            stdout
+        );
+    }
+
+    if !output_path.is_file() {
+        bail!(
+            "Componentization produced no output at {}",
+            output_path.display()
+        );
+    }
+
+    let size_bytes = fs::metadata(&output_path)?.len();
+    let sha256 = crate::sha256_file(&output_path)?;
+
+    info!(
+        "Compiled handler.wasm: {} bytes, sha256: {}",
+        size_bytes, sha256
+    );
+
+    Ok(PackResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_py_project(handler: Option<&str>, with_wit: bool) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().to_path_buf();
+
+        let config = WarpConfig::scaffold("test-py-handler", "python", "app.py");
+        fs::write(project.join("warp.toml"), config.to_toml_string().unwrap()).unwrap();
+
+        if let Some(source) = handler {
+            fs::write(project.join("app.py"), source).unwrap();
+        }
+        if with_wit {
+            fs::create_dir_all(project.join("wit")).unwrap();
+            fs::write(
+                project.join("wit/handler.wit"),
+                "package test:handler;\n\nworld handler {\n  export wasi:http/incoming-handler@0.2.3;\n}\n",
+            )
+            .unwrap();
+        }
+
+        (dir, project)
+    }
+
+    const HANDLER: &str = "class IncomingHandler:\n    def handle(self, request, response_out):\n        pass\n";
+
+    #[test]
+    fn test_module_name_from_entry() {
+        assert_eq!(module_name(Path::new("/p/src/app.py")).unwrap(), "app");
+        assert!(module_name(Path::new("/p/src/app.ts")).is_err());
+    }
+
+    #[test]
+    fn test_python_paths_include_venv_site_packages() {
+        let dir = TempDir::new().unwrap();
+        let site = dir.path().join(".venv/lib/python3.12/site-packages");
+        fs::create_dir_all(&site).unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+
+        let paths = python_paths(dir.path(), &dir.path().join("src/app.py"));
+        assert_eq!(paths[0], dir.path().join("src"));
+        assert_eq!(paths[1], dir.path());
+        assert!(paths.contains(&site));
+    }
+
+    #[test]
+    fn test_pack_python_missing_entry() {
+        let (_dir, project) = create_py_project(None, true);
+
+        let config = WarpConfig::from_file(&project.join("warp.toml")).unwrap();
+        let err = pack_python(&project, &config).unwrap_err().to_string();
+        assert!(err.contains("Entry point not found"), "Error: {err}");
+    }
+
+    #[test]
+    fn test_pack_python_missing_wit_dir() {
+        let (_dir, project) = create_py_project(Some(HANDLER), false);
+
+        let config = WarpConfig::from_file(&project.join("warp.toml")).unwrap();
+        let err = pack_python(&project, &config).unwrap_err().to_string();
+        assert!(err.contains("WIT directory not found"), "Error: {err}");
+    }
+}
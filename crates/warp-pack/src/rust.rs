@@ -0,0 +1,204 @@
+//! Rust packaging via cargo-component.
+//!
+//! Pipeline:
+//! 1. Locate cargo-component (`$WARPGRID_CARGO_COMPONENT_PATH`, `$PATH`, or
+//!    bootstrapped into `~/.warp/toolchains`)
+//! 2. Read the crate name and target directory from `cargo metadata`
+//! 3. Invoke `cargo component build --release`
+//! 4. Copy `<target>/wasm32-wasip1/release/<crate>.wasm` to `dist/handler.wasm`
+//! 5. Compute size + SHA256, return PackResult
+//!
+//! Requires:
+//! - cargo (cargo-component adds the `wasm32-wasip1` target through rustup)
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::WarpConfig;
+
+use crate::bootstrap::{self, Tool};
+use crate::{PackResult, optimize, toolchain};
+
+/// Target cargo-component builds components for.
+const TARGET: &str = "wasm32-wasip1";
+
+/// Locate the cargo-component binary.
+///
+/// Search order:
+/// 1. `$WARPGRID_CARGO_COMPONENT_PATH` environment variable
+/// 2. `cargo-component` on `$PATH`
+fn find_cargo_component() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_CARGO_COMPONENT_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            debug!("Found cargo-component at {} (from WARPGRID_CARGO_COMPONENT_PATH)", p.display());
+            return Ok(p);
+        }
+        bail!("WARPGRID_CARGO_COMPONENT_PATH is set to '{path}' but the file does not exist.");
+    }
+
+    if let Some(path) = optimize::which("cargo-component") {
+        debug!("Found cargo-component at {} (system PATH)", path.display());
+        return Ok(path);
+    }
+
+    bail!(
+        "cargo-component not found.\n\
+         \n\
+         To install, run:\n\
+         \n\
+         \x20 cargo install cargo-component\n\
+         \n\
+         Or set WARPGRID_CARGO_COMPONENT_PATH to point to your cargo-component binary."
+    )
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+}
+
+/// The crate at `project_path` and the directory cargo builds into.
+fn crate_metadata(project_path: &Path) -> Result<(String, PathBuf)> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(project_path)
+        .output()
+        .context("Failed to run 'cargo metadata' (is cargo installed?)")?;
+    if !output.status.success() {
+        bail!(
+            "cargo metadata failed:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let metadata: Metadata =
+        serde_json::from_slice(&output.stdout).context("Failed to parse cargo metadata output")?;
+
+    let manifest = project_path.join("Cargo.toml").canonicalize()?;
+    let package = metadata
+        .packages
+        .into_iter()
+        .find(|p| p.manifest_path.canonicalize().ok().as_ref() == Some(&manifest))
+        .with_context(|| format!("{} is not a package (virtual workspace manifest?)", manifest.display()))?;
+    Ok((package.name, metadata.target_directory))
+}
+
+/// Where cargo-component may have written the component for `name`: a
+/// library crate's artifact uses underscores, a binary's keeps dashes.
+fn artifact_candidates(target_dir: &Path, name: &str) -> Vec<PathBuf> {
+    let release = target_dir.join(TARGET).join("release");
+    let mut candidates = vec![release.join(format!("{}.wasm", name.replace('-', "_")))];
+    if name.contains('-') {
+        candidates.push(release.join(format!("{name}.wasm")));
+    }
+    candidates
+}
+
+/// The main Rust packaging function invoked by `warp pack --lang rust`.
+pub fn pack_rust(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
+    if !project_path.join("Cargo.toml").is_file() {
+        bail!(
+            "No Cargo.toml in {}.\n\
+             Rust projects are built with cargo-component from their crate root.",
+            project_path.display()
+        );
+    }
+
+    let cargo_component = bootstrap::or_install(find_cargo_component(), Tool::CargoComponent, Some(config))?;
+    toolchain::check_pin(config, "cargo_component", &cargo_component)?;
+    let (name, target_dir) = crate_metadata(project_path)?;
+
+    info!("Packaging Rust crate '{name}' with cargo-component");
+    let mut cmd = Command::new(&cargo_component);
+    cmd.args(["component", "build", "--release"])
+        .envs(toolchain::build_env(project_path, config))
+        .current_dir(project_path);
+
+    debug!("Running: {:?}", cmd);
+
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to execute cargo-component at {}", cargo_component.display()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "cargo component build failed (exit code: {}).\n\n\
+             Stderr:\n{}\n\n\
+             Hint: check the crate's dependencies for wasm compatibility:\n\
+             \x20 warp convert analyze --lang rust",
+            output.status.code().unwrap_or(-1),
+            stderr
+        );
+    }
+
+    let candidates = artifact_candidates(&target_dir, &name);
+    let Some(built) = candidates.iter().find(|path| path.is_file()) else {
+        bail!(
+            "cargo component build produced no component at {}",
+            candidates[0].display()
+        );
+    };
+
+    let dist_dir = project_path.join("dist");
+    fs::create_dir_all(&dist_dir)?;
+    let output_path = dist_dir.join("handler.wasm");
+    fs::copy(built, &output_path)
+        .with_context(|| format!("Failed to copy {} to {}", built.display(), output_path.display()))?;
+
+    let size_bytes = fs::metadata(&output_path)?.len();
+    let sha256 = crate::sha256_file(&output_path)?;
+
+    info!(
+        "Compiled handler.wasm: {} bytes, sha256: {}",
+        size_bytes, sha256
+    );
+
+    Ok(PackResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_rust_requires_cargo_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let config: WarpConfig =
+            toml::from_str("[package]\nname = \"api\"\nversion = \"0.1.0\"\n").unwrap();
+        let err = pack_rust(dir.path(), &config).unwrap_err();
+        assert!(err.to_string().contains("No Cargo.toml"), "{err}");
+    }
+
+    #[test]
+    fn test_artifact_candidates() {
+        let target = Path::new("/work/target");
+        assert_eq!(
+            artifact_candidates(target, "my-handler"),
+            [
+                PathBuf::from("/work/target/wasm32-wasip1/release/my_handler.wasm"),
+                PathBuf::from("/work/target/wasm32-wasip1/release/my-handler.wasm"),
+            ]
+        );
+        assert_eq!(artifact_candidates(target, "api").len(), 1);
+    }
+}
@@ -0,0 +1,607 @@
+//! Software bill of materials for packed artifacts.
+//!
+//! Enabled by a `[build.sbom]` section in `warp.toml`:
+//!
+//! ```toml
+//! [build.sbom]
+//! format = "cyclonedx"   # or "spdx" (default "cyclonedx")
+//! embed = true           # also store it in the artifact (default false)
+//! ```
+//!
+//! Dependencies come from the project's lockfiles: `Cargo.lock` (registry
+//! and git packages), `go.sum`, and `package-lock.json` (production
+//! packages only; dev dependencies are not bundled). The versions of the
+//! toolchain that built the artifact are recorded as tools.
+//!
+//! The SBOM is written next to the artifact (`handler.wasm.cdx.json` or
+//! `handler.wasm.spdx.json`). With `embed`, it is also appended to the
+//! artifact as a [`SECTION_NAME`] custom section, so provenance travels
+//! with the binary. This stage runs before signing, so the signature
+//! covers the embedded SBOM.
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::WarpConfig;
+use warp_core::config::SbomConfig;
+use warp_core::wasm;
+
+use crate::{PackResult, toolchain};
+
+/// Name of the custom section holding an embedded SBOM.
+pub const SECTION_NAME: &str = "warpgrid.sbom";
+
+const FORMATS: &[&str] = &["cyclonedx", "spdx"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ecosystem {
+    Cargo,
+    Go,
+    Npm,
+}
+
+/// One third-party package from a lockfile.
+#[derive(Debug, Clone, PartialEq)]
+struct Dependency {
+    ecosystem: Ecosystem,
+    name: String,
+    version: String,
+    /// Hex SHA-256 of the package archive, when the lockfile records one.
+    sha256: Option<String>,
+}
+
+impl Dependency {
+    /// Package URL (https://github.com/package-url/purl-spec).
+    fn purl(&self) -> String {
+        match self.ecosystem {
+            Ecosystem::Cargo => format!("pkg:cargo/{}@{}", self.name, self.version),
+            Ecosystem::Go => format!("pkg:golang/{}@{}", self.name, self.version),
+            Ecosystem::Npm => {
+                let name = self.name.replacen('@', "%40", 1);
+                format!("pkg:npm/{name}@{}", self.version)
+            }
+        }
+    }
+}
+
+/// A build tool and the version it reported.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Tool {
+    pub(crate) name: String,
+    pub(crate) version: String,
+}
+
+/// What the SBOM describes: the artifact and everything that went into it.
+struct Bill<'a> {
+    package: &'a str,
+    version: &'a str,
+    sha256: &'a str,
+    timestamp: String,
+    dependencies: Vec<Dependency>,
+    tools: Vec<Tool>,
+}
+
+/// Generate the SBOM for `result`, writing it next to the artifact and
+/// optionally embedding it.
+pub(crate) fn generate(
+    project_path: &Path,
+    lang: &str,
+    warp: &WarpConfig,
+    config: &SbomConfig,
+    result: &mut PackResult,
+) -> Result<()> {
+    let format = format(config)?;
+    let artifact = PathBuf::from(&result.output_path);
+
+    let bill = Bill {
+        package: &warp.package.name,
+        version: &warp.package.version,
+        sha256: &result.sha256,
+        timestamp: rfc3339(toolchain::build_time(project_path, warp)),
+        dependencies: dependencies(project_path)?,
+        tools: toolchain(lang),
+    };
+    let document = match format {
+        "spdx" => spdx(&bill),
+        _ => cyclonedx(&bill),
+    };
+    let bytes = serde_json::to_vec_pretty(&document)?;
+
+    let path = sbom_path(&artifact, format);
+    fs::write(&path, &bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    info!(
+        "SBOM ({format}, {} dependencies): {}",
+        bill.dependencies.len(),
+        path.display()
+    );
+    result.sbom = Some(path.to_string_lossy().into_owned());
+
+    if config.embed.unwrap_or(false) {
+        let mut binary = fs::read(&artifact)?;
+        wasm::append_custom_section(&mut binary, SECTION_NAME, &bytes);
+        fs::write(&artifact, &binary)?;
+        result.size_bytes = binary.len() as u64;
+        result.sha256 = hex::encode(Sha256::digest(&binary));
+        debug!("Embedded SBOM as custom section '{SECTION_NAME}'");
+    }
+    Ok(())
+}
+
+/// The configured format, validated.
+fn format(config: &SbomConfig) -> Result<&str> {
+    let format = config.format.as_deref().unwrap_or("cyclonedx");
+    if !FORMATS.contains(&format) {
+        bail!(
+            "Invalid [build.sbom] format '{format}'. Expected one of: {}",
+            FORMATS.join(", ")
+        );
+    }
+    Ok(format)
+}
+
+/// Path of the SBOM for an artifact.
+fn sbom_path(artifact: &Path, format: &str) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(if format == "spdx" { ".spdx.json" } else { ".cdx.json" });
+    PathBuf::from(name)
+}
+
+// ── Lockfiles ───────────────────────────────────────────────────────
+
+/// Third-party dependencies from every lockfile in the project root.
+fn dependencies(project_path: &Path) -> Result<Vec<Dependency>> {
+    let mut deps = Vec::new();
+    let read = |name: &str| -> Result<Option<String>> {
+        let path = project_path.join(name);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        fs::read_to_string(&path)
+            .map(Some)
+            .with_context(|| format!("Failed to read {}", path.display()))
+    };
+    if let Some(lock) = read("Cargo.lock")? {
+        deps.extend(parse_cargo_lock(&lock).context("Failed to parse Cargo.lock")?);
+    }
+    if let Some(sum) = read("go.sum")? {
+        deps.extend(parse_go_sum(&sum));
+    }
+    if let Some(lock) = read("package-lock.json")? {
+        deps.extend(parse_package_lock(&lock).context("Failed to parse package-lock.json")?);
+    }
+    deps.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    deps.dedup();
+    Ok(deps)
+}
+
+/// Registry and git packages from a `Cargo.lock`. Path dependencies
+/// (workspace members, the project itself) carry no `source` and are
+/// part of the artifact rather than dependencies of it.
+fn parse_cargo_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lock: toml::Value = toml::from_str(content)?;
+    let packages = lock.get("package").and_then(|p| p.as_array());
+    Ok(packages
+        .into_iter()
+        .flatten()
+        .filter(|p| p.get("source").is_some())
+        .filter_map(|p| {
+            Some(Dependency {
+                ecosystem: Ecosystem::Cargo,
+                name: p.get("name")?.as_str()?.to_string(),
+                version: p.get("version")?.as_str()?.to_string(),
+                sha256: p.get("checksum").and_then(|c| c.as_str()).map(String::from),
+            })
+        })
+        .collect())
+}
+
+/// Modules from a `go.sum`. Each module appears twice (source tree and
+/// `go.mod` hash); the `/go.mod`-only entries are skipped.
+fn parse_go_sum(content: &str) -> Vec<Dependency> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (module, version) = (fields.next()?, fields.next()?);
+            (!version.ends_with("/go.mod")).then(|| Dependency {
+                ecosystem: Ecosystem::Go,
+                name: module.to_string(),
+                version: version.to_string(),
+                sha256: None,
+            })
+        })
+        .collect()
+}
+
+/// Production packages from a `package-lock.json` (lockfile v1–v3).
+fn parse_package_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lock: Value = serde_json::from_str(content)?;
+    let npm = |name: &str, version: &str| Dependency {
+        ecosystem: Ecosystem::Npm,
+        name: name.to_string(),
+        version: version.to_string(),
+        sha256: None,
+    };
+    let mut deps = Vec::new();
+
+    if let Some(packages) = lock.get("packages").and_then(Value::as_object) {
+        // v2/v3: flat map keyed by install path.
+        for (path, entry) in packages {
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                continue; // the root project
+            };
+            let flag = |key: &str| entry.get(key).and_then(Value::as_bool).unwrap_or(false);
+            if flag("dev") || flag("link") {
+                continue;
+            }
+            if let Some(version) = entry.get("version").and_then(Value::as_str) {
+                deps.push(npm(name, version));
+            }
+        }
+    } else if let Some(dependencies) = lock.get("dependencies").and_then(Value::as_object) {
+        // v1: nested tree.
+        let mut stack = vec![dependencies];
+        while let Some(level) = stack.pop() {
+            for (name, entry) in level {
+                if entry.get("dev").and_then(Value::as_bool).unwrap_or(false) {
+                    continue;
+                }
+                if let Some(version) = entry.get("version").and_then(Value::as_str) {
+                    deps.push(npm(name, version));
+                }
+                if let Some(nested) = entry.get("dependencies").and_then(Value::as_object) {
+                    stack.push(nested);
+                }
+            }
+        }
+    }
+    Ok(deps)
+}
+
+// ── Toolchain ───────────────────────────────────────────────────────
+
+/// Tools each language pipeline runs.
+fn toolchain_binaries(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "rust" => &["cargo", "cargo-component"],
+        "go" => &["tinygo"],
+        "js" => &["node", "jco"],
+        "typescript" => &["node", "esbuild", "jco"],
+        "bun" => &["bun", "jco"],
+        "python" => &["componentize-py"],
+        "dotnet" => &["dotnet"],
+        _ => &[],
+    }
+}
+
+/// warp-pack itself plus the version of every installed pipeline tool.
+pub(crate) fn toolchain(lang: &str) -> Vec<Tool> {
+    let mut tools = vec![Tool {
+        name: "warp-pack".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }];
+    tools.extend(toolchain_binaries(lang).iter().filter_map(|binary| {
+        let version = tool_version(binary)?;
+        Some(Tool {
+            name: binary.to_string(),
+            version,
+        })
+    }));
+    tools
+}
+
+/// First line of `<binary> --version`, or `None` if it is not installed.
+fn tool_version(binary: &str) -> Option<String> {
+    let output = Command::new(binary).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+// ── Documents ───────────────────────────────────────────────────────
+
+/// CycloneDX 1.5 JSON.
+fn cyclonedx(bill: &Bill) -> Value {
+    let components: Vec<Value> = bill
+        .dependencies
+        .iter()
+        .map(|dep| {
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": dep.purl(),
+                "name": dep.name,
+                "version": dep.version,
+                "purl": dep.purl(),
+            });
+            if let Some(sha256) = &dep.sha256 {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+            }
+            component
+        })
+        .collect();
+    let tools: Vec<Value> = bill
+        .tools
+        .iter()
+        .map(|tool| json!({ "type": "application", "name": tool.name, "version": tool.version }))
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid_from(bill)),
+        "version": 1,
+        "metadata": {
+            "timestamp": bill.timestamp,
+            "tools": { "components": tools },
+            "component": {
+                "type": "application",
+                "bom-ref": bill.package,
+                "name": bill.package,
+                "version": bill.version,
+                "hashes": [{ "alg": "SHA-256", "content": bill.sha256 }],
+            },
+        },
+        "components": components,
+        "dependencies": [{
+            "ref": bill.package,
+            "dependsOn": bill.dependencies.iter().map(Dependency::purl).collect::<Vec<_>>(),
+        }],
+    })
+}
+
+/// SPDX 2.3 JSON.
+fn spdx(bill: &Bill) -> Value {
+    let mut packages = vec![json!({
+        "SPDXID": "SPDXRef-Artifact",
+        "name": bill.package,
+        "versionInfo": bill.version,
+        "downloadLocation": "NOASSERTION",
+        "checksums": [{ "algorithm": "SHA256", "checksumValue": bill.sha256 }],
+    })];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-Artifact",
+    })];
+    for (i, dep) in bill.dependencies.iter().enumerate() {
+        let id = format!("SPDXRef-Package-{i}");
+        let mut package = json!({
+            "SPDXID": id,
+            "name": dep.name,
+            "versionInfo": dep.version,
+            "downloadLocation": "NOASSERTION",
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": dep.purl(),
+            }],
+        });
+        if let Some(sha256) = &dep.sha256 {
+            package["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+        }
+        packages.push(package);
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-Artifact",
+            "relationshipType": "DEPENDS_ON",
+            "relatedSpdxElement": id,
+        }));
+    }
+    let creators: Vec<String> = bill
+        .tools
+        .iter()
+        .map(|tool| format!("Tool: {}-{}", tool.name, tool.version))
+        .collect();
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("{}-{}", bill.package, bill.version),
+        "documentNamespace": format!(
+            "https://warpgrid.dev/spdx/{}-{}-{}",
+            bill.package, bill.version, bill.sha256
+        ),
+        "creationInfo": { "created": bill.timestamp, "creators": creators },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// A UUID derived from the artifact, so the same build gets the same
+/// serial number.
+fn uuid_from(bill: &Bill) -> String {
+    let digest = Sha256::digest(format!("{}@{}:{}", bill.package, bill.version, bill.sha256));
+    let mut bytes: [u8; 16] = digest[..16].try_into().expect("digest is 32 bytes");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4 layout
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// `secs` since the epoch as an RFC 3339 UTC timestamp.
+fn rfc3339(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+/// The SBOM embedded in an artifact, if any.
+pub fn embedded(wasm: &[u8]) -> Option<&[u8]> {
+    wasm::custom_section(wasm, SECTION_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_LOCK: &str = r#"
+version = 3
+
+[[package]]
+name = "handler"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "ddc6f9cc94d67c0e21aaf7eda3a010fd3af78ebf6e096aa6e2e13c79749cce4f"
+"#;
+
+    const GO_SUM: &str = "\
+github.com/jackc/pgx/v5 v5.5.0 h1:abc=
+github.com/jackc/pgx/v5 v5.5.0/go.mod h1:def=
+golang.org/x/text v0.14.0/go.mod h1:ghi=
+";
+
+    const PACKAGE_LOCK_V3: &str = r#"{
+  "lockfileVersion": 3,
+  "packages": {
+    "": { "name": "app", "version": "1.0.0" },
+    "node_modules/hono": { "version": "4.7.2" },
+    "node_modules/@types/node": { "version": "20.1.0", "dev": true },
+    "node_modules/@warpgrid/bun-sdk": { "resolved": "../sdk", "link": true },
+    "node_modules/a/node_modules/@scope/b": { "version": "2.0.0" }
+  }
+}"#;
+
+    fn bill(dependencies: Vec<Dependency>) -> Bill<'static> {
+        Bill {
+            package: "handler",
+            version: "0.1.0",
+            sha256: "ab12",
+            timestamp: rfc3339(0),
+            dependencies,
+            tools: vec![Tool { name: "bun".into(), version: "1.1.0".into() }],
+        }
+    }
+
+    #[test]
+    fn test_cargo_lock_skips_path_packages() {
+        let deps = parse_cargo_lock(CARGO_LOCK).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].purl(), "pkg:cargo/serde@1.0.200");
+        assert!(deps[0].sha256.as_deref().unwrap().starts_with("ddc6f9"));
+    }
+
+    #[test]
+    fn test_go_sum_skips_go_mod_entries() {
+        let deps = parse_go_sum(GO_SUM);
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].purl(), "pkg:golang/github.com/jackc/pgx/v5@v5.5.0");
+    }
+
+    #[test]
+    fn test_package_lock_keeps_production_packages() {
+        let deps = parse_package_lock(PACKAGE_LOCK_V3).unwrap();
+        let purls: Vec<String> = deps.iter().map(Dependency::purl).collect();
+        assert_eq!(purls.len(), 2);
+        assert!(purls.contains(&"pkg:npm/hono@4.7.2".to_string()));
+        assert!(purls.contains(&"pkg:npm/%40scope/b@2.0.0".to_string()));
+
+        let v1 = r#"{"lockfileVersion": 1, "dependencies": {
+            "hono": {"version": "4.0.0", "dependencies": {"tiny": {"version": "1.0.0"}}},
+            "jest": {"version": "29.0.0", "dev": true}
+        }}"#;
+        let deps = parse_package_lock(v1).unwrap();
+        assert_eq!(deps.len(), 2);
+    }
+
+    #[test]
+    fn test_cyclonedx_document() {
+        let deps = parse_cargo_lock(CARGO_LOCK).unwrap();
+        let doc = cyclonedx(&bill(deps));
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["metadata"]["component"]["hashes"][0]["content"], "ab12");
+        assert_eq!(doc["metadata"]["tools"]["components"][0]["name"], "bun");
+        assert_eq!(doc["components"][0]["purl"], "pkg:cargo/serde@1.0.200");
+        assert_eq!(doc["components"][0]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(doc["dependencies"][0]["dependsOn"][0], "pkg:cargo/serde@1.0.200");
+        // Same artifact, same serial number.
+        assert_eq!(doc["serialNumber"], cyclonedx(&bill(vec![]))["serialNumber"]);
+    }
+
+    #[test]
+    fn test_spdx_document() {
+        let doc = spdx(&bill(parse_go_sum(GO_SUM)));
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["creationInfo"]["created"], "1970-01-01T00:00:00Z");
+        assert_eq!(doc["creationInfo"]["creators"][0], "Tool: bun-1.1.0");
+        assert_eq!(doc["packages"][1]["name"], "github.com/jackc/pgx/v5");
+        assert_eq!(doc["relationships"][1]["relationshipType"], "DEPENDS_ON");
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_709_251_199), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn test_invalid_format_is_rejected() {
+        let config = SbomConfig { format: Some("swid".into()), embed: None };
+        assert!(format(&config).is_err());
+    }
+
+    #[test]
+    fn test_generate_writes_and_embeds() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.lock"), CARGO_LOCK).unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        // An empty component: magic, version 0x0d, layer 1.
+        let wasm = b"\0asm\x0d\0\x01\0".to_vec();
+        fs::write(&artifact, &wasm).unwrap();
+
+        let warp = WarpConfig::scaffold("handler", "cobol", "src/lib.rs");
+        let mut result = PackResult {
+            output_path: artifact.to_string_lossy().to_string(),
+            size_bytes: wasm.len() as u64,
+            sha256: hex::encode(Sha256::digest(&wasm)),
+            optimization: None,
+            signature_bundle: None,
+            sbom: None,
+            cached: false,
+            manifest: None,
+        };
+        let config = SbomConfig { format: None, embed: Some(true) };
+        generate(dir.path(), "cobol", &warp, &config, &mut result).unwrap();
+
+        let sbom_file = result.sbom.clone().unwrap();
+        assert!(sbom_file.ends_with("handler.wasm.cdx.json"));
+        let written = fs::read(&sbom_file).unwrap();
+        let packed = fs::read(&artifact).unwrap();
+        assert_eq!(embedded(&packed), Some(written.as_slice()));
+        assert_eq!(result.size_bytes, packed.len() as u64);
+        assert_eq!(result.sha256, crate::sha256_file(&artifact).unwrap());
+    }
+}
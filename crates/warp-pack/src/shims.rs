@@ -0,0 +1,157 @@
+//! Post-pack check of the component's shim imports against `[shims]`.
+//!
+//! The runtime only links the `warpgrid:shim/*` interfaces warp.toml
+//! enables, so a component importing a disabled shim fails to instantiate
+//! at deploy time. Catch that here, naming every mismatch. Defaults follow
+//! the runtime: `timezone`, `dev_urandom`, `dns`, and `signals` are on
+//! unless set to `false`; `database_proxy` and `threading` must be set.
+//! `render` is enabled per node, not in warp.toml, and `feature-flags` is always
+//! linked; neither is checked.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+use tracing::{debug, warn};
+use warp_core::config::ShimsConfig;
+use warp_core::{WarpConfig, wasm};
+
+use crate::PackResult;
+
+/// Package of the host shim interfaces.
+const SHIM_PACKAGE: &str = "warpgrid:shim/";
+
+/// Fail when the artifact imports a shim interface warp.toml disables.
+pub(crate) fn validate(config: &WarpConfig, result: &PackResult) -> Result<()> {
+    let path = Path::new(&result.output_path);
+    let binary = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let Some(imports) = wasm::component_imports(&binary) else {
+        debug!("Artifact is not a component; skipping shim import check");
+        return Ok(());
+    };
+
+    let shims = config.shims.clone().unwrap_or_default();
+    let mismatches = mismatches(&imports, &shims);
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "{} imports shims that warp.toml does not enable:\n  {}\n\
+         Enable them under [shims] or remove the imports.",
+        path.display(),
+        mismatches.join("\n  ")
+    )
+}
+
+/// One line per imported shim interface that `shims` leaves disabled.
+fn mismatches(imports: &[String], shims: &ShimsConfig) -> Vec<String> {
+    let mut out = Vec::new();
+    for import in imports {
+        let Some(interface) = import.strip_prefix(SHIM_PACKAGE) else {
+            continue;
+        };
+        let interface = interface.split('@').next().unwrap_or(interface);
+        let reason = match interface {
+            "filesystem" => (shims.timezone == Some(false) && shims.dev_urandom == Some(false))
+                .then(|| "timezone=false and dev_urandom=false".to_string()),
+            "dns" => disabled("dns", shims.dns, true),
+            "signals" => disabled("signals", shims.signals, true),
+            "database-proxy" => disabled("database_proxy", shims.database_proxy, false),
+            "threading" => shims
+                .threading
+                .is_none()
+                .then(|| "threading is not set".to_string()),
+            "render" | "feature-flags" | "async-handler" | "http-types" => None,
+            other => {
+                warn!("Component imports unknown shim interface '{SHIM_PACKAGE}{other}'");
+                None
+            }
+        };
+        if let Some(reason) = reason {
+            out.push(format!("imports {SHIM_PACKAGE}{interface} but {reason}"));
+        }
+    }
+    out
+}
+
+/// Why the boolean shim `key` is off, if it is.
+fn disabled(key: &str, value: Option<bool>, default: bool) -> Option<String> {
+    match value {
+        Some(true) => None,
+        Some(false) => Some(format!("{key}=false")),
+        None if default => None,
+        None => Some(format!("{key} is not set")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imports(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_defaults_follow_the_runtime() {
+        let imports = imports(&[
+            "wasi:io/streams@0.2.3",
+            "warpgrid:shim/dns@0.1.0",
+            "warpgrid:shim/filesystem@0.1.0",
+            "warpgrid:shim/database-proxy@0.1.0",
+            "warpgrid:shim/threading@0.1.0",
+        ]);
+        assert_eq!(
+            mismatches(&imports, &ShimsConfig::default()),
+            vec![
+                "imports warpgrid:shim/database-proxy but database_proxy is not set",
+                "imports warpgrid:shim/threading but threading is not set",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explicitly_disabled_shims() {
+        let shims = ShimsConfig {
+            timezone: Some(false),
+            dev_urandom: Some(false),
+            dns: Some(false),
+            threading: Some("cooperative".into()),
+            signals: Some(true),
+            database_proxy: Some(false),
+        };
+        let imports = imports(&[
+            "warpgrid:shim/filesystem@0.1.0",
+            "warpgrid:shim/dns",
+            "warpgrid:shim/signals@0.1.0",
+            "warpgrid:shim/database-proxy@0.1.0",
+            "warpgrid:shim/threading@0.1.0",
+        ]);
+        assert_eq!(
+            mismatches(&imports, &shims),
+            vec![
+                "imports warpgrid:shim/filesystem but timezone=false and dev_urandom=false",
+                "imports warpgrid:shim/dns but dns=false",
+                "imports warpgrid:shim/database-proxy but database_proxy=false",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_core_modules_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        fs::write(&artifact, b"\0asm\x01\0\0\0").unwrap();
+        let config: WarpConfig = toml::from_str("[package]\nname = \"api\"\nversion = \"0.1.0\"\n").unwrap();
+        let result = PackResult {
+            output_path: artifact.to_string_lossy().to_string(),
+            size_bytes: 8,
+            sha256: String::new(),
+            optimization: None,
+            signature_bundle: None,
+            sbom: None,
+            cached: false,
+            manifest: None,
+        };
+        validate(&config, &result).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//! Artifact signing via sigstore's cosign.
+//!
+//! Enabled by a `[build.sign]` section in `warp.toml`:
+//!
+//! ```toml
+//! [build.sign]
+//! key = "cosign.key"   # file path or KMS URI; omit for keyless signing
+//! ```
+//!
+//! Signing runs last, after the optional wasm-opt stage, so the signature
+//! covers the bytes that are deployed. The result is a sigstore bundle
+//! written next to the artifact (`handler.wasm.sigstore.json`), which the
+//! runtime checks before loading the module.
+//!
+//! Requires:
+//! - cosign (https://github.com/sigstore/cosign)
+//! - for keyless signing, an OIDC identity (interactive browser flow, or
+//!   `SIGSTORE_ID_TOKEN` in CI)
+
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::config::SignConfig;
+
+use crate::PackResult;
+
+/// Path of the sigstore bundle for an artifact.
+fn bundle_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".sigstore.json");
+    PathBuf::from(name)
+}
+
+/// Locate the cosign binary.
+///
+/// Search order:
+/// 1. `$WARPGRID_COSIGN_PATH` environment variable
+/// 2. `cosign` on `$PATH`
+fn find_cosign() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_COSIGN_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            debug!("Found cosign at {} (from WARPGRID_COSIGN_PATH)", p.display());
+            return Ok(p);
+        }
+        bail!("WARPGRID_COSIGN_PATH is set to '{path}' but the file does not exist.");
+    }
+
+    let output = Command::new("which").arg("cosign").output().ok();
+    if let Some(output) = output {
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !path.is_empty() {
+            return Ok(PathBuf::from(path));
+        }
+    }
+    bail!(
+        "cosign not found. Install it (https://docs.sigstore.dev/cosign/system_config/installation/),\n\
+         set WARPGRID_COSIGN_PATH, or remove [build.sign] from warp.toml."
+    )
+}
+
+/// Build the `cosign sign-blob` command for `artifact`.
+fn sign_command(cosign: &Path, config: &SignConfig, artifact: &Path, bundle: &Path) -> Command {
+    let mut cmd = Command::new(cosign);
+    cmd.arg("sign-blob").arg("--yes");
+    if let Some(key) = &config.key {
+        cmd.arg("--key").arg(key);
+    }
+    cmd.arg("--bundle").arg(bundle).arg(artifact);
+    cmd
+}
+
+/// Sign the packed artifact, writing a sigstore bundle next to it.
+pub(crate) fn sign(project_path: &Path, config: &SignConfig, result: &mut PackResult) -> Result<()> {
+    let artifact = PathBuf::from(&result.output_path);
+    let bundle = bundle_path(&artifact);
+    let mut cmd = sign_command(&find_cosign()?, config, &artifact, &bundle);
+    cmd.current_dir(project_path);
+
+    let mode = if config.key.is_some() { "key" } else { "keyless" };
+    info!("Signing {} ({mode})", artifact.display());
+    debug!("Running: {:?}", cmd);
+
+    let output = cmd.output().context("Failed to execute cosign")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        bail!(
+            "cosign sign-blob failed (exit code: {}).\n\n\
+             Stderr:\n{}\n\n\
+             Stdout:\n{}",
+            output.status.code().unwrap_or(-1),
+            stderr,
+            stdout
+        );
+    }
+    if !bundle.is_file() {
+        bail!("cosign produced no bundle at {}", bundle.display());
+    }
+
+    info!("Signature bundle: {}", bundle.display());
+    result.signature_bundle = Some(bundle.to_string_lossy().into_owned());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_bundle_path_sits_next_to_artifact() {
+        assert_eq!(
+            bundle_path(Path::new("dist/handler.wasm")),
+            PathBuf::from("dist/handler.wasm.sigstore.json")
+        );
+    }
+
+    #[test]
+    fn test_key_based_sign_command() {
+        let config = SignConfig { key: Some("cosign.key".into()) };
+        let cmd = sign_command(
+            Path::new("cosign"),
+            &config,
+            Path::new("handler.wasm"),
+            Path::new("handler.wasm.sigstore.json"),
+        );
+        assert_eq!(
+            args(&cmd),
+            [
+                "sign-blob",
+                "--yes",
+                "--key",
+                "cosign.key",
+                "--bundle",
+                "handler.wasm.sigstore.json",
+                "handler.wasm"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keyless_sign_command_omits_key() {
+        let config = SignConfig { key: None };
+        let cmd = sign_command(
+            Path::new("cosign"),
+            &config,
+            Path::new("handler.wasm"),
+            Path::new("handler.wasm.sigstore.json"),
+        );
+        assert!(!args(&cmd).contains(&"--key".to_string()));
+    }
+}
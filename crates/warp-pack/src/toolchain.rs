@@ -0,0 +1,270 @@
+//! Pinned toolchains and reproducible builds.
+//!
+//! A `[toolchain]` section in `warp.toml` pins the tools a pipeline runs
+//! and makes the artifact byte-identical across machines:
+//!
+//! ```toml
+//! [toolchain]
+//! jco = "1.16.1"
+//! bun = "1.2.2"
+//! tinygo = "0.34.0"
+//! cargo_component = "0.20.0"
+//! wasm_tools = "1.227.1"
+//! reproducible = true           # default true
+//! source_date_epoch = 1700000000  # default: time of the last git commit
+//! sha256 = "9f86d0…"            # fail unless the artifact has this digest
+//! ```
+//!
+//! Each pin is checked against the binary the pipeline is about to run
+//! (`<tool> --version`), so a mismatched install fails before building.
+//! Pins for tools a language does not use are ignored. A pinned tool that
+//! is not installed at all is bootstrapped at that version ([`crate::bootstrap`]).
+//!
+//! Reproducible builds run every tool with a fixed `SOURCE_DATE_EPOCH`,
+//! `TZ=UTC` and `LC_ALL=C`, and drop the custom sections that carry
+//! build-machine paths (DWARF, source maps, external debug info) from the
+//! artifact, including its nested modules.
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+use warp_core::WarpConfig;
+use warp_core::config::ToolchainConfig;
+use warp_core::wasm;
+
+use crate::PackResult;
+
+fn toolchain(config: &WarpConfig) -> Option<&ToolchainConfig> {
+    config.toolchain.as_ref()
+}
+
+/// Whether builds of this project must be reproducible.
+fn reproducible(config: &WarpConfig) -> bool {
+    toolchain(config).is_some_and(|t| t.reproducible.unwrap_or(true))
+}
+
+/// The version pinned for `tool` (a `[toolchain]` key).
+pub(crate) fn pinned<'a>(config: &'a WarpConfig, tool: &str) -> Option<&'a str> {
+    let toolchain = toolchain(config)?;
+    let pin = match tool {
+        "cargo_component" => &toolchain.cargo_component,
+        "tinygo" => &toolchain.tinygo,
+        "jco" => &toolchain.jco,
+        "bun" => &toolchain.bun,
+        "esbuild" => &toolchain.esbuild,
+        "componentize_py" => &toolchain.componentize_py,
+        "wasm_tools" => &toolchain.wasm_tools,
+        _ => return None,
+    };
+    pin.as_deref()
+}
+
+/// Fail unless `binary` reports the version `[toolchain]` pins for `tool`.
+pub(crate) fn check_pin(config: &WarpConfig, tool: &str, binary: &Path) -> Result<()> {
+    let Some(pin) = pinned(config, tool) else {
+        return Ok(());
+    };
+    let output = Command::new(binary)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to run '{} --version'", binary.display()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let found = parse_version(&stdout);
+    let pin = pin.trim_start_matches('v');
+    if found != Some(pin) {
+        bail!(
+            "[toolchain] pins {tool} {pin}, but {} reports {}.\n\
+             Install {tool} {pin} or update the pin in warp.toml.",
+            binary.display(),
+            found.unwrap_or("no version")
+        );
+    }
+    debug!("{tool} {pin} matches [toolchain]");
+    Ok(())
+}
+
+/// The first `x.y[.z…]` version in a `--version` output.
+fn parse_version(output: &str) -> Option<&str> {
+    output
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .map(|token| token.trim_start_matches('v'))
+        .find(|token| {
+            token.contains('.') && token.starts_with(|c: char| c.is_ascii_digit())
+        })
+}
+
+/// Environment every build tool runs with.
+pub(crate) fn build_env(project_path: &Path, config: &WarpConfig) -> Vec<(&'static str, String)> {
+    if !reproducible(config) {
+        return Vec::new();
+    }
+    vec![
+        ("SOURCE_DATE_EPOCH", source_date_epoch(project_path, config).to_string()),
+        ("TZ", "UTC".to_string()),
+        ("LC_ALL", "C".to_string()),
+    ]
+}
+
+/// Build time recorded in artifacts: the reproducible epoch, else
+/// `SOURCE_DATE_EPOCH` from the environment, else now.
+pub(crate) fn build_time(project_path: &Path, config: &WarpConfig) -> u64 {
+    if reproducible(config) {
+        return source_date_epoch(project_path, config);
+    }
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        })
+}
+
+/// `[toolchain].source_date_epoch`, else the last commit time, else 0.
+fn source_date_epoch(project_path: &Path, config: &WarpConfig) -> u64 {
+    if let Some(epoch) = toolchain(config).and_then(|t| t.source_date_epoch) {
+        return epoch;
+    }
+    Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(project_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Custom sections that record the build machine.
+fn machine_specific(name: &str) -> bool {
+    name.starts_with(".debug_")
+        || matches!(name, "sourceMappingURL" | "external_debug_info" | "build_id")
+}
+
+/// Strip machine-specific sections from the artifact of a reproducible build.
+pub(crate) fn normalize(config: &WarpConfig, result: &mut PackResult) -> Result<()> {
+    if !reproducible(config) {
+        return Ok(());
+    }
+    let path = Path::new(&result.output_path);
+    let binary = fs::read(path)?;
+    let normalized = wasm::strip_custom_sections(&binary, &machine_specific)
+        .with_context(|| format!("{} is not a valid wasm binary", path.display()))?;
+    if normalized.len() != binary.len() {
+        fs::write(path, &normalized)?;
+        result.size_bytes = normalized.len() as u64;
+        result.sha256 = hex::encode(Sha256::digest(&normalized));
+        debug!(
+            "Stripped {} bytes of build-machine sections",
+            binary.len() - normalized.len()
+        );
+    }
+    Ok(())
+}
+
+/// Fail when the artifact differs from the digest pinned in `[toolchain]`.
+pub(crate) fn verify_digest(config: &WarpConfig, result: &PackResult) -> Result<()> {
+    let Some(expected) = toolchain(config).and_then(|t| t.sha256.as_deref()) else {
+        return Ok(());
+    };
+    let expected = expected.trim_start_matches("sha256:");
+    if !result.sha256.eq_ignore_ascii_case(expected) {
+        bail!(
+            "Artifact digest {} does not match [toolchain] sha256 {expected}.\n\
+             The sources or toolchain differ from the reference build.",
+            result.sha256
+        );
+    }
+    info!("Artifact digest matches [toolchain] sha256");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toolchain: &str) -> WarpConfig {
+        toml::from_str(&format!(
+            "[package]\nname = \"t\"\nversion = \"0.1.0\"\n\n[toolchain]\n{toolchain}"
+        ))
+        .unwrap()
+    }
+
+    fn fake_tool(dir: &Path, output: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("tool");
+        fs::write(&path, format!("#!/bin/sh\necho '{output}'\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.16.1\n"), Some("1.16.1"));
+        assert_eq!(parse_version("tinygo version 0.34.0 linux/amd64"), Some("0.34.0"));
+        assert_eq!(parse_version("cargo-component 0.20.0 (wasi:0.2.0)"), Some("0.20.0"));
+        assert_eq!(parse_version("componentize-py v0.16.0"), Some("0.16.0"));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_check_pin() {
+        let dir = tempfile::tempdir().unwrap();
+        let jco = fake_tool(dir.path(), "1.16.1");
+        check_pin(&config("jco = \"1.16.1\""), "jco", &jco).unwrap();
+        check_pin(&config("jco = \"v1.16.1\""), "jco", &jco).unwrap();
+        // Tools without a pin are not probed.
+        check_pin(&config(""), "jco", Path::new("/nonexistent/jco")).unwrap();
+
+        let err = check_pin(&config("jco = \"1.17.0\""), "jco", &jco).unwrap_err();
+        assert!(err.to_string().contains("pins jco 1.17.0"), "{err}");
+    }
+
+    #[test]
+    fn test_build_env_is_fixed_when_reproducible() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = build_env(dir.path(), &config("source_date_epoch = 42"));
+        assert!(env.contains(&("SOURCE_DATE_EPOCH", "42".to_string())));
+        assert!(env.contains(&("TZ", "UTC".to_string())));
+        assert_eq!(build_time(dir.path(), &config("source_date_epoch = 42")), 42);
+
+        assert!(build_env(dir.path(), &config("reproducible = false")).is_empty());
+        let mut unpinned = config("");
+        unpinned.toolchain = None;
+        assert!(build_env(dir.path(), &unpinned).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_and_verify_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        let mut binary = b"\0asm\x0d\0\x01\0".to_vec();
+        wasm::append_custom_section(&mut binary, ".debug_str", b"/home/ci/build");
+        fs::write(&artifact, &binary).unwrap();
+        let mut result = PackResult {
+            output_path: artifact.to_string_lossy().to_string(),
+            size_bytes: binary.len() as u64,
+            sha256: hex::encode(Sha256::digest(&binary)),
+            optimization: None,
+            signature_bundle: None,
+            sbom: None,
+            cached: false,
+            manifest: None,
+        };
+
+        normalize(&config(""), &mut result).unwrap();
+        assert_eq!(result.size_bytes, 8);
+        assert_eq!(result.sha256, crate::sha256_file(&artifact).unwrap());
+
+        let pinned = config(&format!("sha256 = \"sha256:{}\"", result.sha256));
+        verify_digest(&pinned, &result).unwrap();
+        let err = verify_digest(&config("sha256 = \"00\""), &result).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+    }
+}
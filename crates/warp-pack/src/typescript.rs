@@ -0,0 +1,346 @@
+//! TypeScript packaging via esbuild + ComponentizeJS (jco).
+//!
+//! ComponentizeJS only accepts a single plain-JS ES module, so TypeScript
+//! handlers (and any npm dependencies they import) are bundled first.
+//!
+//! Pipeline:
+//! 1. Locate handler entry point from `warp.toml` build.entry
+//! 2. Locate WIT directory and toolchain (jco, esbuild)
+//! 3. Bundle the entry with esbuild into a single ES module, leaving
+//!    `warpgrid:*` / `wasi:*` imports external for the component linker
+//! 4. Prepend the Node-compat polyfills and the WarpGrid shim prelude
+//! 5. Invoke `jco componentize` against the WarpGrid WIT world
+//! 6. Compute size + SHA256, return PackResult
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use warp_core::WarpConfig;
+
+use crate::PackResult;
+use crate::bootstrap::{self, Tool};
+use crate::{js, toolchain};
+
+/// Node.js compatibility polyfills injected ahead of the bundled handler.
+///
+/// StarlingMonkey (the engine behind ComponentizeJS) is a web-style runtime,
+/// so common Node globals that npm packages probe for at import time are
+/// missing. These shims only fill gaps; anything already defined is kept.
+const NODE_COMPAT_POLYFILLS: &str = r#"
+// ── Node.js compat polyfills (auto-injected by warp pack --lang typescript) ──
+if (typeof globalThis.global === "undefined") {
+  globalThis.global = globalThis;
+}
+if (typeof globalThis.process === "undefined") {
+  globalThis.process = {};
+}
+if (typeof globalThis.process.env === "undefined") {
+  globalThis.process.env = {};
+}
+globalThis.process.platform ??= "wasi";
+globalThis.process.version ??= "v20.0.0";
+globalThis.process.versions ??= { node: "20.0.0" };
+globalThis.process.argv ??= [];
+globalThis.process.cwd ??= () => "/";
+globalThis.process.nextTick ??= (fn, ...args) => queueMicrotask(() => fn(...args));
+if (typeof globalThis.setImmediate === "undefined") {
+  globalThis.setImmediate = (fn, ...args) => setTimeout(fn, 0, ...args);
+  globalThis.clearImmediate = (id) => clearTimeout(id);
+}
+// ── End Node.js compat polyfills ──
+"#;
+
+/// Resolve the esbuild binary used to bundle TypeScript.
+///
+/// Search order:
+/// 1. `$WARPGRID_ESBUILD_PATH` environment variable
+/// 2. `<project>/node_modules/.bin/esbuild` (project-local install)
+/// 3. `<sdk>/build/componentize-js/node_modules/.bin/esbuild` (SDK toolchain)
+/// 4. `esbuild` on `$PATH` (global install)
+fn resolve_esbuild(project_path: &Path, sdk_root: &Path) -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("WARPGRID_ESBUILD_PATH") {
+        let p = PathBuf::from(&path);
+        if p.is_file() {
+            return Ok(p);
+        }
+        bail!(
+            "WARPGRID_ESBUILD_PATH is set to '{path}' but the file does not exist. \
+             Install esbuild: npm install -g esbuild"
+        );
+    }
+
+    let candidates = [
+        project_path.join("node_modules/.bin/esbuild"),
+        sdk_root.join("build/componentize-js/node_modules/.bin/esbuild"),
+    ];
+    for candidate in &candidates {
+        if candidate.is_file() {
+            debug!("Found esbuild at {}", candidate.display());
+            return Ok(candidate.clone());
+        }
+    }
+
+    if let Ok(output) = Command::new("which").arg("esbuild").output()
+        && output.status.success()
+    {
+        let path_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !path_str.is_empty() {
+            return Ok(PathBuf::from(path_str));
+        }
+    }
+
+    bail!(
+        "esbuild not found (required to bundle TypeScript). Install it with one of:\n  \
+         1. npm install --save-dev esbuild (in the project)\n  \
+         2. npm install -g esbuild\n  \
+         3. Set WARPGRID_ESBUILD_PATH to the esbuild binary path"
+    )
+}
+
+/// Bundle the TypeScript entry into a single ES module with esbuild.
+///
+/// WIT imports (`warpgrid:*`, `wasi:*`) stay external so jco can bind them
+/// to the component's imports.
+fn esbuild_bundle(
+    esbuild_bin: &Path,
+    entry_path: &Path,
+    output: &Path,
+    env: &[(&str, String)],
+) -> Result<()> {
+    info!("Bundling with esbuild: {}", entry_path.display());
+
+    let result = Command::new(esbuild_bin)
+        .envs(env.iter().cloned())
+        .arg(entry_path)
+        .arg("--bundle")
+        .arg("--format=esm")
+        .arg("--platform=neutral")
+        .arg("--target=es2022")
+        .arg("--main-fields=module,main")
+        .arg("--external:warpgrid:*")
+        .arg("--external:wasi:*")
+        .arg(format!("--outfile={}", output.display()))
+        .output()
+        .context("Failed to execute esbuild")?;
+
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let exit_code = result.status.code().unwrap_or(-1);
+        bail!(
+            "esbuild bundling failed (exit code {exit_code}).\n\n\
+             --- stderr ---\n{stderr}"
+        );
+    }
+
+    if !output.is_file() {
+        bail!(
+            "esbuild succeeded but output file not produced at '{}'",
+            output.display()
+        );
+    }
+
+    debug!(
+        "esbuild output: {} ({} bytes)",
+        output.display(),
+        fs::metadata(output)?.len()
+    );
+    Ok(())
+}
+
+/// Build the final module source: polyfills, shim prelude, then the bundle.
+fn assemble_module(config: &WarpConfig, bundle: &str) -> String {
+    let prelude = js::generate_prelude(config);
+    format!("{NODE_COMPAT_POLYFILLS}\n{prelude}{bundle}")
+}
+
+/// The TypeScript packaging function invoked by `warp pack --lang typescript`.
+pub fn pack_typescript(project_path: &Path, config: &WarpConfig) -> Result<PackResult> {
+    // Validate project structure first (before toolchain checks) for better error messages
+    let build = config
+        .build
+        .as_ref()
+        .context("Missing [build] section in warp.toml")?;
+    let entry_path = project_path.join(&build.entry);
+    if !entry_path.is_file() {
+        bail!(
+            "Entry point not found: {}\n\
+             Check [build] entry in warp.toml",
+            entry_path.display()
+        );
+    }
+
+    let wit_dir = js::wit_dir_for(project_path, config)?;
+
+    // Now check the toolchain
+    let sdk_root = js::find_sdk_root(project_path);
+    let jco_path = bootstrap::or_install(js::find_jco(&sdk_root), Tool::Jco, Some(config))?;
+    let esbuild_path = resolve_esbuild(project_path, &sdk_root)?;
+    toolchain::check_pin(config, "jco", &jco_path)?;
+    toolchain::check_pin(config, "esbuild", &esbuild_path)?;
+    let env = toolchain::build_env(project_path, config);
+
+    info!("Packaging TypeScript handler: {}", entry_path.display());
+
+    let dist_dir = project_path.join("dist");
+    fs::create_dir_all(&dist_dir)?;
+
+    // Step 1: bundle the entry and its dependencies
+    let bundle_path = dist_dir.join(".handler-bundle.js");
+    esbuild_bundle(&esbuild_path, &entry_path, &bundle_path, &env)?;
+    let bundle = fs::read_to_string(&bundle_path)
+        .with_context(|| format!("Failed to read {}", bundle_path.display()))?;
+    let _ = fs::remove_file(&bundle_path);
+
+    // Step 2: inject polyfills + shim prelude
+    let module_path = dist_dir.join(".handler-combined.js");
+    fs::write(&module_path, assemble_module(config, &bundle))?;
+
+    // Step 3: componentize against the WarpGrid world
+    let world_name = js::world_for(config, &wit_dir);
+    info!(
+        "Componentizing with world '{}', WIT dir: {}",
+        world_name,
+        wit_dir.display()
+    );
+
+    let output_path = dist_dir.join("handler.wasm");
+    let result = js::componentize(&jco_path, &module_path, &wit_dir, &world_name, &output_path, &env);
+    let _ = fs::remove_file(&module_path);
+    result?;
+
+    let size_bytes = fs::metadata(&output_path)?.len();
+    let sha256 = crate::sha256_file(&output_path)?;
+
+    info!(
+        "Compiled handler.wasm: {} bytes, sha256: {}",
+        size_bytes, sha256
+    );
+
+    Ok(PackResult {
+        output_path: output_path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256,
+        optimization: None,
+        signature_bundle: None,
+        sbom: None,
+        cached: false,
+        manifest: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_ts_project(handler: Option<&str>, with_wit: bool) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().to_path_buf();
+
+        let config = WarpConfig::scaffold("test-ts-handler", "typescript", "src/handler.ts");
+        fs::write(project.join("warp.toml"), config.to_toml_string().unwrap()).unwrap();
+
+        if let Some(source) = handler {
+            fs::create_dir_all(project.join("src")).unwrap();
+            fs::write(project.join("src/handler.ts"), source).unwrap();
+        }
+        if with_wit {
+            fs::create_dir_all(project.join("wit")).unwrap();
+            fs::write(
+                project.join("wit/handler.wit"),
+                "package test:handler;\n\nworld handler {\n  export wasi:http/incoming-handler@0.2.3;\n}\n",
+            )
+            .unwrap();
+        }
+
+        (dir, project)
+    }
+
+    const HANDLER: &str = r#"addEventListener("fetch", (e: FetchEvent) => e.respondWith(new Response("ok")));"#;
+
+    #[test]
+    fn test_assemble_module_orders_polyfills_prelude_bundle() {
+        let config = WarpConfig::scaffold("test", "typescript", "src/handler.ts");
+        let module = assemble_module(&config, "// user bundle\n");
+
+        let polyfills = module.find("Node.js compat polyfills").unwrap();
+        let prelude = module.find("WarpGrid Shim Prelude").unwrap();
+        let bundle = module.find("// user bundle").unwrap();
+        assert!(polyfills < prelude && prelude < bundle);
+    }
+
+    #[test]
+    fn test_node_compat_polyfills_cover_common_globals() {
+        for global in [
+            "globalThis.global",
+            "process.env",
+            "process.nextTick",
+            "process.platform",
+            "setImmediate",
+        ] {
+            assert!(
+                NODE_COMPAT_POLYFILLS.contains(global),
+                "missing polyfill for {global}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_esbuild_project_local() {
+        let dir = TempDir::new().unwrap();
+        let bin_dir = dir.path().join("node_modules/.bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::write(bin_dir.join("esbuild"), "#!/bin/sh\n").unwrap();
+
+        let found = resolve_esbuild(dir.path(), dir.path()).unwrap();
+        assert_eq!(found, bin_dir.join("esbuild"));
+    }
+
+    #[test]
+    fn test_pack_typescript_missing_build_section() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("warp.toml"),
+            "[package]\nname = \"t\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+
+        let config = WarpConfig::from_file(&dir.path().join("warp.toml")).unwrap();
+        let err = pack_typescript(dir.path(), &config).unwrap_err().to_string();
+        assert!(err.contains("Missing [build]"), "Error: {err}");
+    }
+
+    #[test]
+    fn test_pack_typescript_missing_entry() {
+        let (_dir, project) = create_ts_project(None, true);
+
+        let config = WarpConfig::from_file(&project.join("warp.toml")).unwrap();
+        let err = pack_typescript(&project, &config).unwrap_err().to_string();
+        assert!(err.contains("Entry point not found"), "Error: {err}");
+    }
+
+    #[test]
+    fn test_pack_typescript_missing_wit_dir() {
+        let (_dir, project) = create_ts_project(Some(HANDLER), false);
+
+        let config = WarpConfig::from_file(&project.join("warp.toml")).unwrap();
+        let err = pack_typescript(&project, &config).unwrap_err().to_string();
+        assert!(err.contains("WIT directory not found"), "Error: {err}");
+    }
+
+    #[test]
+    fn test_pack_routes_typescript_to_bundler_pipeline() {
+        let (_dir, project) = create_ts_project(Some(HANDLER), true);
+
+        // No jco/esbuild in the temp project — should fail on the toolchain,
+        // not on language dispatch.
+        let err = crate::pack(&project).unwrap_err().to_string();
+        assert!(!err.contains("Unsupported language"), "Error: {err}");
+        assert!(
+            err.contains("jco") || err.contains("ComponentizeJS") || err.contains("esbuild"),
+            "Error: {err}"
+        );
+    }
+}
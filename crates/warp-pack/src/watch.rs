@@ -0,0 +1,172 @@
+//! Watch mode: repack whenever the project's sources change.
+//!
+//! The project tree is watched recursively. Changes under the directories
+//! the build cache ignores (build output, installed dependencies,
+//! dot-directories) are not sources and never trigger a rebuild — in
+//! particular the pipeline's own output cannot retrigger it.
+//!
+//! Editors and `git checkout` produce bursts of events, so a rebuild only
+//! starts once the tree has been quiet for the debounce interval. Each
+//! rebuild goes through the build cache like a normal pack.
+
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::ops::ControlFlow;
+use std::path::{Component, Path};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::{PackOptions, PackResult, cache, pack_with_options};
+
+/// Quiet period before a burst of changes triggers a rebuild.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Pack `project_path`, then repack after every source change.
+///
+/// `on_pack` receives the outcome of each pack (the initial one included);
+/// failures are reported there rather than ending the watch. Returns when
+/// `on_pack` breaks, or with an error if the file watcher fails.
+pub fn watch(
+    project_path: &Path,
+    options: &PackOptions,
+    debounce: Duration,
+    mut on_pack: impl FnMut(Result<PackResult>) -> ControlFlow<()>,
+) -> Result<()> {
+    // Watcher events carry absolute paths; match them against a canonical root.
+    let root = project_path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", project_path.display()))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", root.display()))?;
+    info!("Watching {} for changes", root.display());
+
+    if on_pack(pack_with_options(&root, options)).is_break() {
+        return Ok(());
+    }
+
+    loop {
+        let event = rx.recv().context("File watcher stopped")?;
+        if !is_source_change(&root, &event) {
+            continue;
+        }
+        loop {
+            match rx.recv_timeout(debounce) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("File watcher stopped"),
+            }
+        }
+
+        info!("Change detected, repacking");
+        if on_pack(pack_with_options(&root, options)).is_break() {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether a watcher event touched a source file.
+fn is_source_change(root: &Path, event: &notify::Result<Event>) -> bool {
+    match event {
+        Ok(event) => {
+            !matches!(event.kind, EventKind::Access(_))
+                && event.paths.iter().any(|path| is_source_path(root, path))
+        }
+        Err(e) => {
+            warn!("File watcher error: {e}");
+            false
+        }
+    }
+}
+
+/// Whether `path` lies in the part of the tree the build cache digests.
+fn is_source_path(root: &Path, path: &Path) -> bool {
+    let Ok(rel) = path.strip_prefix(root) else {
+        return false;
+    };
+    let mut names: Vec<_> = rel
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    let Some(last) = names.pop() else {
+        return false;
+    };
+    if names.iter().any(|dir| cache::is_excluded_dir(dir)) {
+        return false;
+    }
+    // The entry itself may be an excluded directory being created.
+    let excluded = path.is_dir() && cache::is_excluded_dir(&last);
+    if excluded {
+        debug!("Ignoring change to {}", rel.display());
+    }
+    !excluded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_build_output_is_not_a_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+
+        assert!(is_source_path(root, &root.join("src/main.rs")));
+        assert!(is_source_path(root, &root.join("warp.toml")));
+        assert!(is_source_path(root, &root.join(".env")));
+        assert!(!is_source_path(root, &root.join("target")));
+        assert!(!is_source_path(root, &root.join("target/wasm/handler.wasm")));
+        assert!(!is_source_path(root, &root.join("dist/handler.wasm")));
+        assert!(!is_source_path(root, &root.join(".warp/cache/abc.wasm")));
+        assert!(!is_source_path(root, &root.join(".git/index")));
+        assert!(!is_source_path(root, Path::new("/elsewhere/main.rs")));
+    }
+
+    #[test]
+    fn test_source_edits_trigger_a_repack() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("warp.toml"),
+            "[package]\nname = \"watched\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        // No toolchain is needed: an unknown language fails fast, and the
+        // failure is still reported to the callback.
+        let options = PackOptions {
+            lang: Some("cobol".into()),
+            no_cache: true,
+        };
+
+        let root = dir.path().to_path_buf();
+        let mut packs = 0;
+        watch(dir.path(), &options, Duration::from_millis(50), |result| {
+            assert!(result.is_err());
+            packs += 1;
+            if packs == 1 {
+                let root = root.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(100));
+                    for i in 0..3 {
+                        fs::write(root.join("src/lib.rs"), format!("// {i}")).unwrap();
+                    }
+                });
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })
+        .unwrap();
+        assert_eq!(packs, 2);
+    }
+}
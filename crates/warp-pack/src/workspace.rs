@@ -0,0 +1,347 @@
+//! Multi-component workspaces.
+//!
+//! A `warp.toml` with a `[components]` table describes an app made of
+//! several components (e.g. a gateway and the services behind it), each in
+//! its own directory:
+//!
+//! ```toml
+//! [package]
+//! name = "shop"
+//! version = "0.1.0"
+//!
+//! [components.gateway]
+//! path = "gateway-svc"
+//! world = "gateway-service"
+//!
+//! [components.users]
+//! path = "user-svc"
+//! world = "user-service"
+//! ```
+//!
+//! A component with its own `warp.toml` is packed from it. One without is
+//! packed from the workspace `warp.toml` (its `[build]`, `[shims]`, `[env]`
+//! and so on) under the component's name. In both cases the component
+//! table's `lang`, `entry`, and `world` take precedence.
+//!
+//! Components without a WIT directory of their own build against the
+//! workspace's shared `wit/`, so worlds and `deps/` are defined once.
+//!
+//! After every component is packed, a combined manifest listing each
+//! artifact is written to [`MANIFEST_PATH`] under the workspace root.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use warp_core::WarpConfig;
+use warp_core::config::{BuildConfig, ComponentConfig, PackageConfig, ToolchainConfig};
+
+use crate::{PackOptions, PackResult, pack_config};
+
+/// Combined manifest location, relative to the workspace root.
+pub const MANIFEST_PATH: &str = "dist/components.json";
+
+/// The combined output manifest of a workspace pack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceManifest {
+    pub package: String,
+    pub version: String,
+    pub components: Vec<ComponentArtifact>,
+}
+
+/// One packed component in a [`WorkspaceManifest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentArtifact {
+    pub name: String,
+    /// Component directory, relative to the workspace root.
+    pub path: String,
+    pub lang: String,
+    pub world: Option<String>,
+    /// Packed artifact, relative to the workspace root.
+    pub output: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug)]
+pub struct WorkspaceResult {
+    /// Path of the combined manifest.
+    pub manifest_path: String,
+    pub manifest: WorkspaceManifest,
+    /// Per-component results, in manifest order.
+    pub components: Vec<(String, PackResult)>,
+}
+
+/// Whether `config` describes a multi-component workspace.
+pub fn is_workspace(config: &WarpConfig) -> bool {
+    config.components.as_ref().is_some_and(|c| !c.is_empty())
+}
+
+/// Pack every component of the workspace at `root`, then write the
+/// combined manifest.
+///
+/// Components are packed in name order; the first failure stops the run.
+/// `options` apply to every component.
+pub fn pack_workspace(root: &Path, options: &PackOptions) -> Result<WorkspaceResult> {
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", root.display()))?;
+    let workspace = WarpConfig::from_file(&root.join("warp.toml"))?;
+    let Some(components) = workspace.components.as_ref().filter(|c| !c.is_empty()) else {
+        bail!("No [components] table in {}", root.join("warp.toml").display());
+    };
+
+    let mut manifest = WorkspaceManifest {
+        package: workspace.package.name.clone(),
+        version: workspace.package.version.clone(),
+        components: Vec::new(),
+    };
+    let mut results = Vec::new();
+    for (name, component) in components {
+        let dir = root.join(&component.path);
+        let config = component_config(&root, &workspace, name, component)?;
+        info!("Packing component '{name}' ({})", component.path);
+        let result = pack_config(&dir, &config, options)
+            .with_context(|| format!("Failed to pack component '{name}'"))?;
+
+        let build = config.build.as_ref();
+        manifest.components.push(ComponentArtifact {
+            name: name.clone(),
+            path: component.path.clone(),
+            lang: options
+                .lang
+                .clone()
+                .or_else(|| build.map(|b| b.lang.clone()).filter(|l| !l.is_empty()))
+                .unwrap_or_default(),
+            world: build.and_then(|b| b.world.clone()),
+            output: relative_to(&root, &result.output_path),
+            sha256: result.sha256.clone(),
+            size_bytes: result.size_bytes,
+        });
+        results.push((name.clone(), result));
+    }
+
+    let manifest_path = root.join(MANIFEST_PATH);
+    if let Some(parent) = manifest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+    info!("Wrote workspace manifest {}", manifest_path.display());
+
+    Ok(WorkspaceResult {
+        manifest_path: manifest_path.to_string_lossy().to_string(),
+        manifest,
+        components: results,
+    })
+}
+
+/// The effective config for packing component `name`.
+fn component_config(
+    root: &Path,
+    workspace: &WarpConfig,
+    name: &str,
+    component: &ComponentConfig,
+) -> Result<WarpConfig> {
+    let dir = root.join(&component.path);
+    if !dir.is_dir() {
+        bail!(
+            "Component '{name}': directory not found: {}",
+            dir.display()
+        );
+    }
+
+    let own_toml = dir.join("warp.toml");
+    // Toolchain pins apply workspace-wide; a digest only fits one artifact.
+    let toolchain = workspace.toolchain.clone().map(|toolchain| ToolchainConfig {
+        sha256: None,
+        ..toolchain
+    });
+    let mut config = if own_toml.is_file() {
+        let mut config = WarpConfig::from_file(&own_toml)?;
+        if config.toolchain.is_none() {
+            config.toolchain = toolchain;
+        }
+        config
+    } else {
+        let mut config = workspace.clone();
+        config.package = PackageConfig {
+            name: name.to_string(),
+            version: workspace.package.version.clone(),
+            description: None,
+        };
+        config.components = None;
+        config.toolchain = toolchain;
+        // The workspace's `[build].wit` is relative to the workspace root.
+        if let Some(build) = config.build.as_mut() {
+            build.wit = build
+                .wit
+                .take()
+                .map(|wit| root.join(wit).to_string_lossy().to_string());
+        }
+        config
+    };
+
+    let build = config.build.get_or_insert_with(|| BuildConfig {
+        lang: String::new(),
+        entry: String::new(),
+        target: None,
+        flags: None,
+        optimize: None,
+        sign: None,
+        sbom: None,
+        wit: None,
+        world: None,
+    });
+    if let Some(lang) = &component.lang {
+        build.lang = lang.clone();
+    }
+    if let Some(entry) = &component.entry {
+        build.entry = entry.clone();
+    }
+    if let Some(world) = &component.world {
+        build.world = Some(world.clone());
+    }
+    let has_own_wit = dir.join("wit").is_dir() || dir.join("src").join("wit").is_dir();
+    let shared_wit = root.join("wit");
+    if build.wit.is_none() && !has_own_wit && shared_wit.is_dir() {
+        build.wit = Some(shared_wit.to_string_lossy().to_string());
+    }
+    Ok(config)
+}
+
+/// `path` relative to `root` when it lies inside it.
+fn relative_to(root: &Path, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache;
+
+    const WORKSPACE: &str = r#"
+[package]
+name = "shop"
+version = "0.3.0"
+
+[components.gateway]
+path = "gateway-svc"
+lang = "cobol"
+world = "gateway-service"
+
+[components.users]
+path = "user-svc"
+lang = "cobol"
+"#;
+
+    fn workspace() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("warp.toml"), WORKSPACE).unwrap();
+        fs::create_dir_all(root.join("wit/deps/shim")).unwrap();
+        fs::write(root.join("wit/worlds.wit"), "world gateway-service {}").unwrap();
+        fs::create_dir_all(root.join("gateway-svc/src")).unwrap();
+        fs::create_dir_all(root.join("user-svc/wit")).unwrap();
+        fs::write(
+            root.join("user-svc/warp.toml"),
+            "[package]\nname = \"user-svc\"\nversion = \"1.0.0\"\n\n[build]\nlang = \"go\"\nentry = \"main.go\"\n",
+        )
+        .unwrap();
+        (dir, root)
+    }
+
+    fn components(root: &Path) -> (WarpConfig, Vec<(String, ComponentConfig)>) {
+        let config = WarpConfig::from_file(&root.join("warp.toml")).unwrap();
+        let components = config.components.clone().unwrap().into_iter().collect();
+        (config, components)
+    }
+
+    #[test]
+    fn test_component_without_warp_toml_uses_table_and_shared_wit() {
+        let (_dir, root) = workspace();
+        let (config, components) = components(&root);
+        let (name, gateway) = &components[0];
+
+        let resolved = component_config(&root, &config, name, gateway).unwrap();
+        assert_eq!(resolved.package.name, "gateway");
+        assert_eq!(resolved.package.version, "0.3.0");
+        assert!(resolved.components.is_none());
+        let build = resolved.build.unwrap();
+        assert_eq!(build.lang, "cobol");
+        assert_eq!(build.world.as_deref(), Some("gateway-service"));
+        assert_eq!(
+            build.wit.as_deref(),
+            Some(root.join("wit").to_string_lossy().as_ref())
+        );
+    }
+
+    #[test]
+    fn test_component_warp_toml_is_overridden_by_table() {
+        let (_dir, root) = workspace();
+        let (config, components) = components(&root);
+        let (name, users) = &components[1];
+
+        let resolved = component_config(&root, &config, name, users).unwrap();
+        assert_eq!(resolved.package.name, "user-svc");
+        let build = resolved.build.unwrap();
+        assert_eq!(build.lang, "cobol");
+        assert_eq!(build.entry, "main.go");
+        // The component has its own wit/, so the shared one is not forced on it.
+        assert!(build.wit.is_none());
+    }
+
+    #[test]
+    fn test_missing_component_directory_is_named() {
+        let (_dir, root) = workspace();
+        fs::remove_dir_all(root.join("gateway-svc")).unwrap();
+
+        let err = pack_workspace(&root, &PackOptions::default()).unwrap_err();
+        assert!(format!("{err:#}").contains("Component 'gateway'"), "{err:#}");
+    }
+
+    #[test]
+    fn test_pack_writes_combined_manifest() {
+        let (_dir, root) = workspace();
+        let (config, components) = components(&root);
+        // No toolchain here: seed each component's build cache so the
+        // pipeline is served from it.
+        for (name, component) in &components {
+            let dir = root.join(&component.path);
+            let resolved = component_config(&root, &config, name, component).unwrap();
+            let output = dir.join("dist").join(format!("{name}.wasm"));
+            fs::create_dir_all(output.parent().unwrap()).unwrap();
+            fs::write(&output, format!("\0asm {name}")).unwrap();
+            let built = PackResult {
+                output_path: output.to_string_lossy().to_string(),
+                size_bytes: fs::metadata(&output).unwrap().len(),
+                sha256: crate::sha256_file(&output).unwrap(),
+                optimization: None,
+                signature_bundle: None,
+                sbom: None,
+                cached: false,
+                manifest: None,
+            };
+            let key = cache::key(&dir, "cobol", &resolved).unwrap();
+            cache::store(&dir, &key, &built).unwrap();
+        }
+
+        let result = pack_workspace(&root, &PackOptions::default()).unwrap();
+        assert_eq!(result.components.len(), 2);
+        assert!(result.components.iter().all(|(_, r)| r.cached));
+
+        let written: WorkspaceManifest =
+            serde_json::from_slice(&fs::read(root.join(MANIFEST_PATH)).unwrap()).unwrap();
+        assert_eq!(written.package, "shop");
+        let gateway = &written.components[0];
+        assert_eq!(gateway.name, "gateway");
+        assert_eq!(gateway.output, "gateway-svc/dist/gateway.wasm");
+        assert_eq!(gateway.world.as_deref(), Some("gateway-service"));
+        assert_eq!(written.components[1].name, "users");
+        assert_eq!(written.components[1].lang, "cobol");
+    }
+}
@@ -0,0 +1,127 @@
+//! Startup CPU benchmark.
+//!
+//! `capacity_cpu_weight` is a hand-set number of weight units a node can
+//! host, so a node with fast cores and one with slow cores look the same
+//! to placement. Agents run [`calibrate`] at startup instead: a short
+//! single-threaded Wasm loop (integer mixing plus linear-memory traffic,
+//! the shape of typical guest code) compiled with the same engine
+//! settings guests get. The measured rate per core, relative to
+//! [`REFERENCE_ITERATIONS_PER_SEC`], times the core count gives the
+//! node's effective weight, reported at Join.
+//!
+//! A reference core is worth [`WEIGHT_PER_CORE`], the same 100 units per
+//! core that standalone mode assumes.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use wasmtime::{Engine, Instance, Module, Store};
+
+/// Loop iterations per second of one reference core.
+pub const REFERENCE_ITERATIONS_PER_SEC: f64 = 400_000_000.0;
+
+/// Weight units one reference core is worth.
+pub const WEIGHT_PER_CORE: u32 = 100;
+
+/// Shortest timed run; shorter runs are dominated by timer noise.
+const MIN_RUN: Duration = Duration::from_millis(20);
+
+/// `run(n)` mixes a 64-bit state `n` times with xorshift and folds it
+/// into a 64 KiB window of linear memory.
+const BENCHMARK_WAT: &str = r#"
+(module
+  (memory 1)
+  (func (export "run") (param $n i64) (result i64)
+    (local $i i64) (local $x i64) (local $addr i32)
+    (local.set $x (i64.const 0x2545F4914F6CDD1D))
+    (block $done
+      (loop $next
+        (br_if $done (i64.ge_u (local.get $i) (local.get $n)))
+        (local.set $x (i64.xor (local.get $x) (i64.shl (local.get $x) (i64.const 13))))
+        (local.set $x (i64.xor (local.get $x) (i64.shr_u (local.get $x) (i64.const 7))))
+        (local.set $x (i64.xor (local.get $x) (i64.shl (local.get $x) (i64.const 17))))
+        (local.set $addr (i32.wrap_i64 (i64.and (local.get $x) (i64.const 0xFFF8))))
+        (i64.store (local.get $addr)
+          (i64.add (i64.load (local.get $addr)) (local.get $x)))
+        (local.set $i (i64.add (local.get $i) (i64.const 1)))
+        (br $next)))
+    (local.get $x)))
+"#;
+
+/// What the benchmark measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuBenchmark {
+    /// Best single-core loop rate.
+    pub iterations_per_sec: f64,
+    /// Cores the node can run guests on.
+    pub cores: u32,
+    /// Effective weight, see [`weight_for`].
+    pub cpu_weight: u32,
+}
+
+/// Run the benchmark for about `budget` and derive the node's weight.
+///
+/// Blocking; call it from `spawn_blocking` on an async runtime.
+pub fn calibrate(budget: Duration) -> Result<CpuBenchmark> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, BENCHMARK_WAT).context("failed to compile the CPU benchmark")?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<i64, i64>(&mut store, "run")?;
+
+    // Grow the run until it is long enough to time, then repeat it for
+    // the rest of the budget and keep the best (least disturbed) rate.
+    let started = Instant::now();
+    let mut iterations: i64 = 1 << 16;
+    let mut best = 0.0_f64;
+    loop {
+        let begin = Instant::now();
+        run.call(&mut store, iterations)?;
+        let elapsed = begin.elapsed();
+        if elapsed < MIN_RUN {
+            iterations = iterations.saturating_mul(2);
+            continue;
+        }
+        best = best.max(iterations as f64 / elapsed.as_secs_f64());
+        if started.elapsed() >= budget {
+            break;
+        }
+    }
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+    Ok(CpuBenchmark {
+        iterations_per_sec: best,
+        cores,
+        cpu_weight: weight_for(best, cores),
+    })
+}
+
+/// [`WEIGHT_PER_CORE`] per reference core: `cores` cores running at
+/// `iterations_per_sec` each. Never below one core's worth of a tenth of
+/// the reference, so a badly disturbed run cannot zero out a node.
+pub fn weight_for(iterations_per_sec: f64, cores: u32) -> u32 {
+    let per_core = WEIGHT_PER_CORE as f64 * iterations_per_sec / REFERENCE_ITERATIONS_PER_SEC;
+    let weight = (per_core * cores.max(1) as f64).round() as u32;
+    weight.max(WEIGHT_PER_CORE / 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_scales_with_speed_and_cores() {
+        assert_eq!(weight_for(REFERENCE_ITERATIONS_PER_SEC, 8), 800);
+        assert_eq!(weight_for(REFERENCE_ITERATIONS_PER_SEC * 1.5, 8), 1200);
+        assert_eq!(weight_for(REFERENCE_ITERATIONS_PER_SEC / 2.0, 4), 200);
+        assert_eq!(weight_for(0.0, 4), WEIGHT_PER_CORE / 10);
+    }
+
+    #[test]
+    fn calibrate_measures_a_rate() {
+        let result = calibrate(Duration::from_millis(50)).unwrap();
+        assert!(result.iterations_per_sec > 0.0);
+        assert!(result.cores >= 1);
+        assert_eq!(result.cpu_weight, weight_for(result.iterations_per_sec, result.cores));
+    }
+}
@@ -0,0 +1,286 @@
+//! Calling component exports by name with JSON arguments.
+//!
+//! Backs `warp exec`: an export is named either `function` for a
+//! top-level function or `interface#function` for one inside an exported
+//! interface (e.g. `warpgrid:shim/lifecycle@0.1.0#on-start`). Arguments
+//! arrive as JSON and are converted using the function's parameter types;
+//! results are converted back the same way.
+//!
+//! | WIT type | JSON |
+//! |---|---|
+//! | `bool`, integers, floats | boolean, number (range-checked) |
+//! | `char`, `string` | string (one character for `char`) |
+//! | `list<T>`, `tuple<..>` | array |
+//! | `record` | object keyed by field name |
+//! | `enum` | case name |
+//! | `flags` | array of set flag names |
+//! | `variant` | `{"case": payload}`, or the case name without a payload |
+//! | `option<T>` | `null` or the value |
+//! | `result<T, E>` | `{"ok": value}` or `{"err": value}` |
+//!
+//! Resources, futures, and streams cannot be passed or returned.
+
+use anyhow::{Context, anyhow, bail};
+use serde_json::{Map, Number, Value};
+use wasmtime::component::types::ComponentFunc;
+use wasmtime::component::{Type, Val};
+
+/// Split an export name into its interface (if any) and function name.
+pub fn parse_export(export: &str) -> anyhow::Result<(Option<&str>, &str)> {
+    let (interface, function) = match export.rsplit_once('#') {
+        Some((interface, function)) if !interface.is_empty() => (Some(interface), function),
+        Some(_) => bail!("export '{export}' has an empty interface name"),
+        None => (None, export),
+    };
+    if function.is_empty() {
+        bail!("export '{export}' has an empty function name");
+    }
+    Ok((interface, function))
+}
+
+/// Convert JSON `args` into the parameters `ty` expects.
+pub fn to_params(ty: &ComponentFunc, args: &[Value]) -> anyhow::Result<Vec<Val>> {
+    let params = ty.params();
+    if params.len() != args.len() {
+        bail!("expected {} arguments, got {}", params.len(), args.len());
+    }
+    params
+        .zip(args)
+        .map(|((name, ty), arg)| to_val(&ty, arg).with_context(|| format!("argument '{name}'")))
+        .collect()
+}
+
+/// Convert call results to JSON: `null` for none, the value for one, an
+/// array for several.
+pub fn from_results(results: &[Val]) -> anyhow::Result<Value> {
+    match results {
+        [] => Ok(Value::Null),
+        [result] => from_val(result),
+        results => results.iter().map(from_val).collect::<anyhow::Result<_>>().map(Value::Array),
+    }
+}
+
+fn to_val(ty: &Type, value: &Value) -> anyhow::Result<Val> {
+    Ok(match ty {
+        Type::Bool => Val::Bool(value.as_bool().ok_or_else(|| mismatch("a boolean", value))?),
+        Type::S8 => Val::S8(signed(value)?),
+        Type::U8 => Val::U8(unsigned(value)?),
+        Type::S16 => Val::S16(signed(value)?),
+        Type::U16 => Val::U16(unsigned(value)?),
+        Type::S32 => Val::S32(signed(value)?),
+        Type::U32 => Val::U32(unsigned(value)?),
+        Type::S64 => Val::S64(signed(value)?),
+        Type::U64 => Val::U64(unsigned(value)?),
+        Type::Float32 => Val::Float32(float(value)? as f32),
+        Type::Float64 => Val::Float64(float(value)?),
+        Type::Char => {
+            let s = string(value)?;
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Val::Char(c),
+                _ => bail!("expected a single character, got {value}"),
+            }
+        }
+        Type::String => Val::String(string(value)?.to_string()),
+        Type::List(list) => {
+            let item = list.ty();
+            Val::List(array(value)?.iter().map(|v| to_val(&item, v)).collect::<anyhow::Result<_>>()?)
+        }
+        Type::Tuple(tuple) => {
+            let items = array(value)?;
+            if items.len() != tuple.types().len() {
+                bail!("expected a {}-tuple, got {value}", tuple.types().len());
+            }
+            Val::Tuple(tuple.types().zip(items).map(|(ty, v)| to_val(&ty, v)).collect::<anyhow::Result<_>>()?)
+        }
+        Type::Record(record) => {
+            let fields = value.as_object().ok_or_else(|| mismatch("an object", value))?;
+            Val::Record(
+                record
+                    .fields()
+                    .map(|field| {
+                        let value = fields.get(field.name).unwrap_or(&Value::Null);
+                        let val = to_val(&field.ty, value).with_context(|| format!("field '{}'", field.name))?;
+                        Ok((field.name.to_string(), val))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        }
+        Type::Enum(cases) => {
+            let name = string(value)?;
+            if !cases.names().any(|case| case == name) {
+                bail!("unknown case '{name}'");
+            }
+            Val::Enum(name.to_string())
+        }
+        Type::Flags(flags) => {
+            let set = array(value)?
+                .iter()
+                .map(|flag| {
+                    let name = string(flag)?;
+                    if !flags.names().any(|known| known == name) {
+                        bail!("unknown flag '{name}'");
+                    }
+                    Ok(name.to_string())
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Val::Flags(set)
+        }
+        Type::Variant(variant) => {
+            let (name, payload) = case(value)?;
+            let case = variant
+                .cases()
+                .find(|case| case.name == name)
+                .ok_or_else(|| anyhow!("unknown case '{name}'"))?;
+            let payload = match (case.ty, payload) {
+                (Some(ty), Some(payload)) => Some(Box::new(to_val(&ty, payload)?)),
+                (None, None) => None,
+                (Some(_), None) => bail!("case '{name}' needs a payload"),
+                (None, Some(_)) => bail!("case '{name}' has no payload"),
+            };
+            Val::Variant(name.to_string(), payload)
+        }
+        Type::Option(option) => match value {
+            Value::Null => Val::Option(None),
+            value => Val::Option(Some(Box::new(to_val(&option.ty(), value)?))),
+        },
+        Type::Result(result) => {
+            let (name, payload) = case(value)?;
+            let ty = match name {
+                "ok" => result.ok(),
+                "err" => result.err(),
+                other => bail!("expected 'ok' or 'err', got '{other}'"),
+            };
+            let payload = match (ty, payload) {
+                (Some(ty), Some(payload)) => Some(Box::new(to_val(&ty, payload)?)),
+                (None, None | Some(Value::Null)) => None,
+                (Some(_), None) => bail!("'{name}' needs a payload"),
+                (None, Some(_)) => bail!("'{name}' has no payload"),
+            };
+            Val::Result(if name == "ok" { Ok(payload) } else { Err(payload) })
+        }
+        Type::Own(_) | Type::Borrow(_) | Type::Future(_) | Type::Stream(_) | Type::ErrorContext => {
+            bail!("resources, futures, and streams cannot be passed as JSON")
+        }
+    })
+}
+
+fn from_val(val: &Val) -> anyhow::Result<Value> {
+    Ok(match val {
+        Val::Bool(b) => Value::Bool(*b),
+        Val::S8(n) => (*n).into(),
+        Val::U8(n) => (*n).into(),
+        Val::S16(n) => (*n).into(),
+        Val::U16(n) => (*n).into(),
+        Val::S32(n) => (*n).into(),
+        Val::U32(n) => (*n).into(),
+        Val::S64(n) => (*n).into(),
+        Val::U64(n) => (*n).into(),
+        Val::Float32(n) => Number::from_f64(f64::from(*n)).map_or(Value::Null, Value::Number),
+        Val::Float64(n) => Number::from_f64(*n).map_or(Value::Null, Value::Number),
+        Val::Char(c) => Value::String(c.to_string()),
+        Val::String(s) => Value::String(s.clone()),
+        Val::List(items) | Val::Tuple(items) => {
+            Value::Array(items.iter().map(from_val).collect::<anyhow::Result<_>>()?)
+        }
+        Val::Record(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, val)| Ok((name.clone(), from_val(val)?)))
+                .collect::<anyhow::Result<Map<_, _>>>()?,
+        ),
+        Val::Enum(name) => Value::String(name.clone()),
+        Val::Flags(names) => Value::Array(names.iter().cloned().map(Value::String).collect()),
+        Val::Variant(name, None) => Value::String(name.clone()),
+        Val::Variant(name, Some(payload)) => single(name, from_val(payload)?),
+        Val::Option(None) => Value::Null,
+        Val::Option(Some(val)) => from_val(val)?,
+        Val::Result(Ok(payload)) => single("ok", payload.as_deref().map_or(Ok(Value::Null), from_val)?),
+        Val::Result(Err(payload)) => single("err", payload.as_deref().map_or(Ok(Value::Null), from_val)?),
+        Val::Resource(_) | Val::Future(_) | Val::Stream(_) | Val::ErrorContext(_) => {
+            bail!("resources, futures, and streams cannot be returned as JSON")
+        }
+    })
+}
+
+fn single(key: &str, value: Value) -> Value {
+    Value::Object(Map::from_iter([(key.to_string(), value)]))
+}
+
+/// A variant-like value: a bare case name, or an object with one key.
+fn case(value: &Value) -> anyhow::Result<(&str, Option<&Value>)> {
+    match value {
+        Value::String(name) => Ok((name, None)),
+        Value::Object(map) if map.len() == 1 => {
+            let (name, payload) = map.iter().next().expect("one entry");
+            Ok((name, Some(payload)))
+        }
+        other => Err(mismatch("a case name or a one-key object", other)),
+    }
+}
+
+fn signed<T: TryFrom<i64>>(value: &Value) -> anyhow::Result<T> {
+    value
+        .as_i64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| mismatch("an integer in range", value))
+}
+
+fn unsigned<T: TryFrom<u64>>(value: &Value) -> anyhow::Result<T> {
+    value
+        .as_u64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| mismatch("a non-negative integer in range", value))
+}
+
+fn float(value: &Value) -> anyhow::Result<f64> {
+    value.as_f64().ok_or_else(|| mismatch("a number", value))
+}
+
+fn string(value: &Value) -> anyhow::Result<&str> {
+    value.as_str().ok_or_else(|| mismatch("a string", value))
+}
+
+fn array(value: &Value) -> anyhow::Result<&Vec<Value>> {
+    value.as_array().ok_or_else(|| mismatch("an array", value))
+}
+
+fn mismatch(expected: &str, value: &Value) -> anyhow::Error {
+    anyhow!("expected {expected}, got {value}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn export_names_split_at_the_last_hash() {
+        assert_eq!(parse_export("run").unwrap(), (None, "run"));
+        assert_eq!(
+            parse_export("warpgrid:shim/lifecycle@0.1.0#on-start").unwrap(),
+            (Some("warpgrid:shim/lifecycle@0.1.0"), "on-start")
+        );
+        assert!(parse_export("#run").is_err());
+        assert!(parse_export("iface#").is_err());
+    }
+
+    #[test]
+    fn results_become_json() {
+        assert_eq!(from_results(&[]).unwrap(), Value::Null);
+        assert_eq!(from_results(&[Val::U32(7)]).unwrap(), json!(7));
+        let record = Val::Record(vec![
+            ("name".into(), Val::String("api".into())),
+            ("tags".into(), Val::Flags(vec!["read".into()])),
+        ]);
+        assert_eq!(
+            from_results(&[record, Val::Option(None)]).unwrap(),
+            json!([{"name": "api", "tags": ["read"]}, null])
+        );
+        assert_eq!(
+            from_results(&[Val::Result(Err(Some(Box::new(Val::Enum("denied".into())))))]).unwrap(),
+            json!({"err": "denied"})
+        );
+        assert_eq!(from_results(&[Val::Variant("none".into(), None)]).unwrap(), json!("none"));
+    }
+}
@@ -3,11 +3,21 @@
 //! Wraps a `wasmtime::component::Instance` with its associated `Store`
 //! and provides a typed interface for interacting with the guest.
 
-use wasmtime::component::{Component, Instance};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde_json::Value;
+use wasmtime::component::{Component, Instance, InstancePre, Val};
 use wasmtime::{Engine, StoreLimitsBuilder, Store};
 
+use warp_core::BuildMetadata;
 use warpgrid_host::engine::{HostState, WarpGridEngine};
 
+use crate::exec;
+use crate::lifecycle::{Hook, HookOutcome, LIFECYCLE_INTERFACE};
+use crate::usage::{ExecutionMeter, UsageSample};
+
 /// A loaded and compiled Wasm component, ready to be instantiated.
 ///
 /// Components are expensive to compile but cheap to instantiate.
@@ -18,26 +28,43 @@ pub struct CompiledModule {
     component: Component,
     /// Human-readable name for logging.
     name: String,
+    /// Imports resolved against the engine's linker ahead of time, so
+    /// instantiation skips the per-instance import lookup.
+    pre: Option<InstancePre<HostState>>,
+    /// Build metadata from the artifact's `warpgrid.meta` section.
+    metadata: Option<Arc<BuildMetadata>>,
 }
 
 impl CompiledModule {
     /// Compile a Wasm component from raw bytes.
     pub fn from_bytes(engine: &Engine, name: &str, bytes: &[u8]) -> anyhow::Result<Self> {
         let component = Component::from_binary(engine, bytes)?;
-        tracing::info!(%name, "compiled wasm component");
+        let metadata = read_metadata(name, bytes);
+        tracing::info!(%name, build = %describe(metadata.as_deref()), "compiled wasm component");
         Ok(Self {
             component,
             name: name.to_string(),
+            pre: None,
+            metadata,
         })
     }
 
     /// Compile a Wasm component from a file path.
     pub fn from_file(engine: &Engine, name: &str, path: &str) -> anyhow::Result<Self> {
-        let component = Component::from_file(engine, path)?;
-        tracing::info!(%name, %path, "compiled wasm component from file");
+        let bytes = std::fs::read(path)?;
+        let component = Component::from_binary(engine, &bytes)?;
+        let metadata = read_metadata(name, &bytes);
+        tracing::info!(
+            %name,
+            %path,
+            build = %describe(metadata.as_deref()),
+            "compiled wasm component from file"
+        );
         Ok(Self {
             component,
             name: name.to_string(),
+            pre: None,
+            metadata,
         })
     }
 
@@ -46,17 +73,71 @@ impl CompiledModule {
         &self.name
     }
 
+    /// Build metadata embedded by `warp pack`, if the artifact carries any.
+    pub fn metadata(&self) -> Option<&BuildMetadata> {
+        self.metadata.as_deref()
+    }
+
     /// Access the underlying component.
     pub fn component(&self) -> &Component {
         &self.component
     }
+
+    /// Whether imports were pre-resolved with [`Self::pre_instantiate`].
+    pub fn is_pre_instantiated(&self) -> bool {
+        self.pre.is_some()
+    }
+
+    /// Resolve the component's imports against the engine's linker once,
+    /// so every later instantiation reuses the result.
+    pub fn pre_instantiate(&mut self, warpgrid_engine: &WarpGridEngine) -> anyhow::Result<()> {
+        self.pre = Some(warpgrid_engine.linker().instantiate_pre(&self.component)?);
+        Ok(())
+    }
+
+    /// The same compiled component under another module name.
+    ///
+    /// Cheap: the component and any pre-instantiated imports are shared.
+    pub fn shared_as(&self, name: &str) -> Self {
+        Self {
+            component: self.component.clone(),
+            name: name.to_string(),
+            pre: self.pre.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// Decode an artifact's build metadata. A corrupt section is logged and
+/// ignored: it never prevents the component from loading.
+fn read_metadata(name: &str, bytes: &[u8]) -> Option<Arc<BuildMetadata>> {
+    match BuildMetadata::from_wasm(bytes) {
+        Ok(metadata) => metadata.map(Arc::new),
+        Err(err) => {
+            tracing::warn!(%name, error = %err, "ignoring unreadable build metadata");
+            None
+        }
+    }
+}
+
+/// `package@version (sha)` for logs, or `unknown` without metadata.
+fn describe(metadata: Option<&BuildMetadata>) -> String {
+    match metadata {
+        Some(meta) => match &meta.git_sha {
+            Some(sha) => format!("{}@{} ({sha})", meta.package, meta.version),
+            None => format!("{}@{}", meta.package, meta.version),
+        },
+        None => "unknown".to_string(),
+    }
 }
 
 /// A running Wasm component instance with its store.
 pub struct WasmInstance {
     store: Store<HostState>,
-    _instance: Instance,
+    instance: Instance,
     module_name: String,
+    /// Execution meter (present when the engine has metering enabled).
+    meter: Option<Arc<ExecutionMeter>>,
 }
 
 impl WasmInstance {
@@ -77,7 +158,7 @@ impl WasmInstance {
             .memory_size(memory_limit)
             .table_elements(10_000)
             .build();
-        host_state.limiter = Some(limits);
+        host_state.limiter = Some(limits.into());
 
         let mut store = Store::new(warpgrid_engine.engine(), host_state);
         store.limiter(|data| {
@@ -86,17 +167,31 @@ impl WasmInstance {
                 .expect("limiter must be set before instantiation")
         });
 
-        let instance = warpgrid_engine
-            .linker()
-            .instantiate_async(&mut store, &module.component)
-            .await?;
+        let meter = warpgrid_engine.epoch_tick().map(|tick| {
+            let meter = Arc::new(ExecutionMeter::new(tick, memory_limit as u64));
+            let callback_meter = Arc::clone(&meter);
+            store.set_epoch_deadline(1);
+            store.epoch_deadline_callback(move |_| callback_meter.on_epoch_tick());
+            meter
+        });
+
+        let instance = match &module.pre {
+            Some(pre) => pre.instantiate_async(&mut store).await?,
+            None => {
+                warpgrid_engine
+                    .linker()
+                    .instantiate_async(&mut store, &module.component)
+                    .await?
+            }
+        };
 
         tracing::info!(name = %module.name, "wasm instance created");
 
         Ok(Self {
             store,
-            _instance: instance,
+            instance,
             module_name: module.name.clone(),
+            meter,
         })
     }
 
@@ -114,6 +209,108 @@ impl WasmInstance {
     pub fn module_name(&self) -> &str {
         &self.module_name
     }
+
+    /// Largest linear memory (bytes) this instance has grown to.
+    pub fn peak_memory_bytes(&self) -> usize {
+        self.store
+            .data()
+            .limiter
+            .as_ref()
+            .map_or(0, |limiter| limiter.peak_memory_bytes())
+    }
+
+    /// Start metering a request, optionally bounded by a wall-clock budget.
+    ///
+    /// No-op when the engine was created without metering.
+    pub fn begin_request(&self, budget: Option<Duration>) {
+        if let Some(meter) = &self.meter {
+            meter.begin(budget);
+        }
+    }
+
+    /// Stop metering the current request and return its usage.
+    ///
+    /// Returns `None` when the engine was created without metering.
+    pub fn finish_request(&self) -> Option<UsageSample> {
+        self.meter.as_ref().map(|meter| meter.finish())
+    }
+
+    /// Call a lifecycle hook if the component exports it, within `budget`.
+    ///
+    /// A hook that times out or traps leaves the store mid-call; the
+    /// caller must not reuse the instance afterwards.
+    pub async fn run_hook(&mut self, hook: Hook, budget: Duration) -> HookOutcome {
+        let Some(index) = self
+            .instance
+            .get_export_index(&mut self.store, None, LIFECYCLE_INTERFACE)
+            .and_then(|interface| {
+                self.instance
+                    .get_export_index(&mut self.store, Some(&interface), hook.export_name())
+            })
+        else {
+            return HookOutcome::NotExported;
+        };
+        let func = match self
+            .instance
+            .get_typed_func::<(), (Result<(), String>,)>(&mut self.store, &index)
+        {
+            Ok(func) => func,
+            Err(e) => return HookOutcome::Failed(format!("unexpected signature: {e}")),
+        };
+
+        self.begin_request(Some(budget));
+        let store = &mut self.store;
+        let call = async {
+            let (result,) = func.call_async(&mut *store, ()).await?;
+            func.post_return_async(&mut *store).await?;
+            anyhow::Ok(result)
+        };
+        let outcome = match tokio::time::timeout(budget, call).await {
+            Ok(Ok(Ok(()))) => HookOutcome::Completed,
+            Ok(Ok(Err(reason))) => HookOutcome::Failed(reason),
+            Ok(Err(trap)) => HookOutcome::Failed(format!("trapped: {trap:#}")),
+            Err(_) => HookOutcome::TimedOut(budget),
+        };
+        match (outcome, self.finish_request()) {
+            (HookOutcome::Failed(_), Some(usage)) if usage.budget_exceeded => {
+                HookOutcome::TimedOut(budget)
+            }
+            (outcome, _) => outcome,
+        }
+    }
+
+    /// Call the export named `export` (`function` or
+    /// `interface#function`) with JSON arguments and return its results
+    /// as JSON; see [`crate::exec`] for the mapping. Meter the call, and
+    /// hold it to a budget, with [`Self::begin_request`] and
+    /// [`Self::finish_request`] around it.
+    pub async fn call_export(&mut self, export: &str, args: &[Value]) -> anyhow::Result<Value> {
+        let (interface, function) = exec::parse_export(export)?;
+        let interface = match interface {
+            Some(name) => Some(
+                self.instance
+                    .get_export_index(&mut self.store, None, name)
+                    .with_context(|| format!("component does not export interface '{name}'"))?,
+            ),
+            None => None,
+        };
+        let func = self
+            .instance
+            .get_export_index(&mut self.store, interface.as_ref(), function)
+            .and_then(|index| self.instance.get_func(&mut self.store, index))
+            .with_context(|| format!("component does not export function '{export}'"))?;
+        let ty = func.ty(&self.store);
+        let params = exec::to_params(&ty, args)?;
+        let mut results = vec![Val::Bool(false); ty.results().len()];
+
+        let call = async {
+            func.call_async(&mut self.store, &params, &mut results).await?;
+            func.post_return_async(&mut self.store).await
+        }
+        .await;
+        call.with_context(|| format!("'{export}' trapped"))?;
+        exec::from_results(&results)
+    }
 }
 
 /// Shared handle to a pre-configured engine + compiled module.
@@ -168,13 +365,98 @@ mod tests {
             .build();
 
         let state = HostState {
+            render: None,
+            flags: None,
             filesystem: None,
             dns: None,
             db_proxy: None,
             signals: warpgrid_host::signals::host::SignalsHost::new(),
             threading_model: None,
-            limiter: Some(limits),
+            limiter: Some(limits.into()),
         };
         assert!(state.limiter.is_some());
     }
+
+    #[test]
+    fn compiled_module_exposes_build_metadata() {
+        let engine = WarpGridEngine::new(ShimConfig::default()).unwrap();
+        // An empty component.
+        let mut bytes = b"\0asm\x0d\0\x01\0".to_vec();
+        let module = CompiledModule::from_bytes(engine.engine(), "bare", &bytes).unwrap();
+        assert!(module.metadata().is_none());
+
+        let metadata = BuildMetadata {
+            package: "api".into(),
+            version: "1.2.0".into(),
+            git_sha: Some("0123abcd".into()),
+            build_time: 1_700_000_000,
+            language: "rust".into(),
+            shims: vec![],
+        };
+        metadata.embed(&mut bytes).unwrap();
+        let module = CompiledModule::from_bytes(engine.engine(), "api", &bytes).unwrap();
+        assert_eq!(module.metadata(), Some(&metadata));
+        assert_eq!(module.shared_as("api-2").metadata(), Some(&metadata));
+        assert_eq!(describe(module.metadata()), "api@1.2.0 (0123abcd)");
+    }
+
+    #[tokio::test]
+    async fn exports_are_called_by_name_with_json() {
+        let bytes = wat::parse_str(
+            r#"(component
+                (core module $m
+                    (memory (export "mem") 1)
+                    (data (i32.const 16) "\01\00\00\00\20\00\00\00\04\00\00\00")
+                    (data (i32.const 32) "nope")
+                    (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add)
+                    (func (export "check") (result i32) i32.const 16))
+                (core instance $i (instantiate $m))
+                (alias core export $i "mem" (core memory $mem))
+                (func $add (param "a" u32) (param "b" u32) (result u32) (canon lift (core func $i "add")))
+                (func $check (result (result (error string))) (canon lift (core func $i "check") (memory $mem)))
+                (instance $probe (export "check" (func $check)))
+                (export "add" (func $add))
+                (export "test:fixture/probe" (instance $probe)))"#,
+        )
+        .unwrap();
+        let runtime = crate::Runtime::new(ShimConfig::default()).unwrap();
+        let module = runtime.load_module("fixture", &bytes).await.unwrap();
+        let mut instance = runtime.instantiate(&module, 64 * 1024 * 1024).await.unwrap();
+
+        let sum = instance.call_export("add", &[serde_json::json!(2), serde_json::json!(40)]).await.unwrap();
+        assert_eq!(sum, serde_json::json!(42));
+        let check = instance.call_export("test:fixture/probe#check", &[]).await.unwrap();
+        assert_eq!(check, serde_json::json!({"err": "nope"}));
+
+        let err = instance.call_export("add", &[serde_json::json!(2)]).await.unwrap_err();
+        assert!(err.to_string().contains("expected 2 arguments, got 1"), "{err}");
+        let err = instance.call_export("add", &[serde_json::json!(-1), serde_json::json!(1)]).await.unwrap_err();
+        assert!(format!("{err:#}").contains("argument 'a'"), "{err:#}");
+        let err = instance.call_export("sub", &[]).await.unwrap_err();
+        assert!(err.to_string().contains("does not export function 'sub'"), "{err}");
+        let err = instance.call_export("test:fixture/missing#check", &[]).await.unwrap_err();
+        assert!(err.to_string().contains("does not export interface"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn metered_calls_are_held_to_their_budget() {
+        let bytes = wat::parse_str(
+            r#"(component
+                (core module $m (func (export "spin") (loop $l (br $l))))
+                (core instance $i (instantiate $m))
+                (func $spin (canon lift (core func $i "spin")))
+                (export "spin" (func $spin)))"#,
+        )
+        .unwrap();
+        let runtime = crate::Runtime::new_metered(ShimConfig::default(), Duration::from_millis(5)).unwrap();
+        let module = runtime.load_module("spinner", &bytes).await.unwrap();
+        let mut instance = runtime.instantiate(&module, 64 * 1024 * 1024).await.unwrap();
+
+        instance.begin_request(Some(Duration::from_millis(50)));
+        let err = instance.call_export("spin", &[]).await.unwrap_err();
+        let sample = instance.finish_request().unwrap();
+        assert!(sample.budget_exceeded, "{err:#}");
+        assert!(sample.cpu_time > Duration::ZERO);
+        assert!(sample.wall_time >= Duration::from_millis(50));
+    }
 }
@@ -9,6 +9,22 @@
 //! - **Instance pooling**: Manages warm pools of pre-instantiated modules
 //! - **Resource limiting**: Enforces memory and table size limits per instance
 //!   via wasmtime's built-in `StoreLimits`
+//! - **Memory overcommit**: Pools can reserve observed peak memory plus
+//!   headroom instead of the full limit, shedding idle instances under
+//!   node memory pressure
+//! - **Lifecycle hooks**: Optional guest `on-start` / `on-shutdown`
+//!   exports, called within a time budget when pooled instances are
+//!   created and before they are recycled
+//! - **Direct calls**: Any exported function can be called by name with
+//!   JSON arguments, for debugging without crafting HTTP requests
+//! - **Usage metering**: Optional epoch-based guest CPU accounting and
+//!   per-request wall-clock execution budgets
+//! - **Signature verification**: Optional cosign check of an artifact's
+//!   sigstore bundle before it is compiled
+//! - **Module sharing**: Deployments loading the same artifact share one
+//!   compiled component (and, optionally, its pre-resolved imports)
+//! - **CPU calibration**: A startup Wasm benchmark that turns measured core
+//!   speed into the node's effective CPU weight
 //!
 //! # Architecture
 //!
@@ -16,14 +32,22 @@
 //! Runtime
 //!   ├── WarpGridEngine (shared wasmtime::Engine + Linker)
 //!   ├── CompiledModule cache (module name → Component)
+//!   ├── SharedModules (artifact digest + shim hash → Component)
 //!   └── InstancePool per deployment
 //!       ├── InstanceFactory (engine + module)
 //!       └── VecDeque<WasmInstance> (idle instances)
 //! ```
 
+pub mod benchmark;
+pub mod exec;
 pub mod instance;
+pub mod lifecycle;
 pub mod limiter;
+pub mod overcommit;
 pub mod pool;
+pub mod shared;
+pub mod signing;
+pub mod usage;
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -33,8 +57,13 @@ use tokio::sync::Mutex;
 use warpgrid_host::engine::WarpGridEngine;
 
 pub use instance::{CompiledModule, InstanceFactory, WasmInstance};
+pub use lifecycle::{Hook, HookOutcome, LifecycleBudgets};
+pub use overcommit::{NodeMemory, OvercommitPolicy, PressureLevel, PressureThreshold};
 pub use pool::{InstancePool, PoolConfig};
-pub use warpgrid_host::config::ShimConfig;
+pub use shared::ModuleKey;
+pub use signing::{SignatureMode, SignaturePolicy, TrustRoot};
+pub use usage::{EpochTicker, UsageSample};
+pub use warpgrid_host::config::{SHIM_PACKAGE_VERSION, SHIM_WORLDS, ShimConfig};
 
 /// The top-level WarpGrid runtime.
 ///
@@ -44,6 +73,14 @@ pub struct Runtime {
     engine: WarpGridEngine,
     /// Compiled module cache: name → compiled component.
     modules: Arc<Mutex<HashMap<String, CompiledModule>>>,
+    /// Epoch ticker driving usage metering (metered runtimes only).
+    _ticker: Option<EpochTicker>,
+    /// Signature check applied before a module is compiled.
+    signature_policy: SignaturePolicy,
+    /// Compiled components shared between module names with equal bytes.
+    shared: Arc<Mutex<shared::SharedModules>>,
+    /// Pre-resolve imports once per shared component.
+    pre_instantiate: bool,
 }
 
 impl Runtime {
@@ -54,9 +91,53 @@ impl Runtime {
         Ok(Self {
             engine,
             modules: Arc::new(Mutex::new(HashMap::new())),
+            _ticker: None,
+            signature_policy: SignaturePolicy::disabled(),
+            shared: Arc::default(),
+            pre_instantiate: false,
         })
     }
 
+    /// Create a runtime with per-request usage metering.
+    ///
+    /// Guest CPU time is sampled every `epoch_tick`; instances report usage
+    /// through [`WasmInstance::begin_request`] / [`WasmInstance::finish_request`].
+    pub fn new_metered(
+        config: ShimConfig,
+        epoch_tick: std::time::Duration,
+    ) -> anyhow::Result<Self> {
+        let engine = WarpGridEngine::new_metered(config, epoch_tick)?;
+        let ticker = EpochTicker::start(engine.engine().clone(), epoch_tick)?;
+        tracing::info!(
+            epoch_tick_ms = epoch_tick.as_millis() as u64,
+            "WarpGrid metered runtime initialized"
+        );
+        Ok(Self {
+            engine,
+            modules: Arc::new(Mutex::new(HashMap::new())),
+            _ticker: Some(ticker),
+            signature_policy: SignaturePolicy::disabled(),
+            shared: Arc::default(),
+            pre_instantiate: false,
+        })
+    }
+
+    /// Verify artifact signatures before loading modules.
+    pub fn with_signature_policy(mut self, policy: SignaturePolicy) -> Self {
+        tracing::info!(mode = %policy.mode(), "artifact signature policy set");
+        self.signature_policy = policy;
+        self
+    }
+
+    /// Pre-resolve each shared component's imports against the linker.
+    ///
+    /// Saves the import lookup on every instantiation at the cost of
+    /// keeping the resolved imports resident per distinct artifact.
+    pub fn with_pre_instantiation(mut self, enabled: bool) -> Self {
+        self.pre_instantiate = enabled;
+        self
+    }
+
     /// Get a reference to the underlying engine.
     pub fn engine(&self) -> &WarpGridEngine {
         &self.engine
@@ -64,25 +145,51 @@ impl Runtime {
 
     /// Load and compile a Wasm module from raw bytes.
     ///
-    /// The compiled module is cached by name for reuse.
+    /// The compiled module is cached by name for reuse. Raw bytes carry no
+    /// signature bundle, so this is refused under an enforcing policy.
     pub async fn load_module(&self, name: &str, bytes: &[u8]) -> anyhow::Result<CompiledModule> {
-        let module = CompiledModule::from_bytes(self.engine.engine(), name, bytes)?;
-        self.modules
-            .lock()
-            .await
-            .insert(name.to_string(), module.clone());
-        Ok(module)
+        self.signature_policy.verify_unsigned(name)?;
+        self.load_shared(name, bytes).await
     }
 
     /// Load and compile a Wasm module from a file path.
     ///
-    /// The compiled module is cached by name for reuse.
+    /// The compiled module is cached by name for reuse. The artifact is
+    /// checked against its sigstore bundle (`<path>.sigstore.json`) first.
     pub async fn load_module_from_file(
         &self,
         name: &str,
         path: &str,
     ) -> anyhow::Result<CompiledModule> {
-        let module = CompiledModule::from_file(self.engine.engine(), name, path)?;
+        self.signature_policy.verify_file(name, std::path::Path::new(path))?;
+        let bytes = std::fs::read(path)?;
+        self.load_shared(name, &bytes).await
+    }
+
+    /// Compile `bytes` under `name`, reusing an existing compile of the
+    /// same artifact for this engine when there is one.
+    async fn load_shared(&self, name: &str, bytes: &[u8]) -> anyhow::Result<CompiledModule> {
+        let key = ModuleKey::new(bytes, self.engine.config());
+        let cached = self.shared.lock().await.acquire(&key, name);
+        let module = match cached {
+            Some(module) => {
+                tracing::info!(%name, digest = %key.digest, "reusing shared wasm component");
+                module
+            }
+            None => {
+                let mut module = CompiledModule::from_bytes(self.engine.engine(), name, bytes)?;
+                if self.pre_instantiate
+                    && let Err(err) = module.pre_instantiate(&self.engine)
+                {
+                    tracing::warn!(
+                        %name,
+                        error = %err,
+                        "pre-instantiation failed; resolving imports per instance"
+                    );
+                }
+                self.shared.lock().await.insert(key, name, module)
+            }
+        };
         self.modules
             .lock()
             .await
@@ -90,6 +197,13 @@ impl Runtime {
         Ok(module)
     }
 
+    /// Forget a module by name, freeing its compiled component once no
+    /// other module name shares it.
+    pub async fn unload_module(&self, name: &str) -> bool {
+        self.shared.lock().await.release(name);
+        self.modules.lock().await.remove(name).is_some()
+    }
+
     /// Get a previously compiled module by name.
     pub async fn get_module(&self, name: &str) -> Option<CompiledModule> {
         self.modules.lock().await.get(name).cloned()
@@ -120,6 +234,11 @@ impl Runtime {
     pub async fn cached_modules(&self) -> Vec<String> {
         self.modules.lock().await.keys().cloned().collect()
     }
+
+    /// Number of distinct compiled components behind the cached modules.
+    pub async fn shared_module_count(&self) -> usize {
+        self.shared.lock().await.len()
+    }
 }
 
 #[cfg(test)]
@@ -132,12 +251,94 @@ mod tests {
         assert!(runtime.is_ok());
     }
 
+    #[tokio::test]
+    async fn metered_runtime_enables_epoch_metering() {
+        let runtime =
+            Runtime::new_metered(ShimConfig::default(), usage::DEFAULT_EPOCH_TICK).unwrap();
+        assert_eq!(runtime.engine().epoch_tick(), Some(usage::DEFAULT_EPOCH_TICK));
+    }
+
+    #[tokio::test]
+    async fn enforcing_runtime_refuses_unsigned_bytes() {
+        let policy = SignaturePolicy::new(
+            SignatureMode::Enforce,
+            Some(TrustRoot::Key("cosign.pub".into())),
+        )
+        .unwrap();
+        let runtime = Runtime::new(ShimConfig::default())
+            .unwrap()
+            .with_signature_policy(policy);
+        let Err(err) = runtime.load_module("app", b"\0asm").await else {
+            panic!("unsigned module loaded under an enforcing policy");
+        };
+        assert!(err.to_string().contains("signature verification failed"), "{err}");
+        assert!(runtime.cached_modules().await.is_empty());
+    }
+
     #[tokio::test]
     async fn module_cache_starts_empty() {
         let runtime = Runtime::new(ShimConfig::default()).unwrap();
         assert!(runtime.cached_modules().await.is_empty());
     }
 
+    /// Import-free component whose bytes differ per `marker`.
+    fn empty_component(marker: &str) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(component (core module (func (export "{marker}"))))"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn same_artifact_is_compiled_once() {
+        let runtime = Runtime::new(ShimConfig::default()).unwrap();
+        let bytes = empty_component("gateway");
+        runtime.load_module("tenant-a", &bytes).await.unwrap();
+        runtime.load_module("tenant-b", &bytes).await.unwrap();
+        assert_eq!(runtime.cached_modules().await.len(), 2);
+        assert_eq!(runtime.shared_module_count().await, 1);
+
+        runtime
+            .load_module("other", &empty_component("other"))
+            .await
+            .unwrap();
+        assert_eq!(runtime.shared_module_count().await, 2);
+    }
+
+    #[tokio::test]
+    async fn shared_module_freed_with_its_last_user() {
+        let runtime = Runtime::new(ShimConfig::default()).unwrap();
+        let bytes = empty_component("gateway");
+        runtime.load_module("tenant-a", &bytes).await.unwrap();
+        runtime.load_module("tenant-b", &bytes).await.unwrap();
+
+        assert!(runtime.unload_module("tenant-a").await);
+        assert_eq!(runtime.shared_module_count().await, 1);
+        assert!(runtime.get_module("tenant-b").await.is_some());
+
+        // Reloading a name with new bytes drops its old reference.
+        runtime
+            .load_module("tenant-b", &empty_component("v2"))
+            .await
+            .unwrap();
+        assert_eq!(runtime.shared_module_count().await, 1);
+        assert!(runtime.unload_module("tenant-b").await);
+        assert_eq!(runtime.shared_module_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn pre_instantiated_modules_still_instantiate() {
+        let runtime = Runtime::new(ShimConfig::default())
+            .unwrap()
+            .with_pre_instantiation(true);
+        let bytes = empty_component("gateway");
+        let a = runtime.load_module("tenant-a", &bytes).await.unwrap();
+        let b = runtime.load_module("tenant-b", &bytes).await.unwrap();
+        assert!(a.is_pre_instantiated() && b.is_pre_instantiated());
+        let instance = runtime.instantiate(&b, 16 << 20).await.unwrap();
+        assert_eq!(instance.module_name(), "tenant-b");
+    }
+
     #[test]
     fn pool_creation_api_works() {
         let rt = tokio::runtime::Builder::new_current_thread()
@@ -0,0 +1,125 @@
+//! Instance lifecycle hooks.
+//!
+//! Guests may export the `warpgrid:shim/lifecycle` interface (see
+//! `warpgrid-host/wit/lifecycle.wit`). The pool calls `on-start` after
+//! creating an instance and before handing it out, and `on-shutdown`
+//! before dropping an idle instance — on scale-down, when shedding under
+//! memory pressure, and when draining on unschedule or daemon shutdown.
+//!
+//! Each call runs under a wall-clock budget from [`LifecycleBudgets`].
+//! The budget is enforced twice: as the instance's execution budget (so
+//! metered engines trap a guest spinning in a loop) and as a timeout on
+//! the call itself (so a guest blocked on host I/O is abandoned too).
+//! Hooks are looked up by name; components without them are unaffected.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Fully qualified name of the exported lifecycle interface.
+pub const LIFECYCLE_INTERFACE: &str = "warpgrid:shim/lifecycle@0.1.0";
+
+/// One of the exported lifecycle functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    OnStart,
+    OnShutdown,
+}
+
+impl Hook {
+    /// The function's name within [`LIFECYCLE_INTERFACE`].
+    pub fn export_name(self) -> &'static str {
+        match self {
+            Self::OnStart => "on-start",
+            Self::OnShutdown => "on-shutdown",
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.export_name())
+    }
+}
+
+/// Wall-clock budgets for the lifecycle hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleBudgets {
+    /// Budget for `on-start`; an instance that overruns it is discarded.
+    pub on_start: Duration,
+    /// Budget for `on-shutdown`; the instance is dropped when it runs out.
+    pub on_shutdown: Duration,
+}
+
+impl Default for LifecycleBudgets {
+    fn default() -> Self {
+        Self {
+            on_start: Duration::from_secs(10),
+            on_shutdown: Duration::from_secs(5),
+        }
+    }
+}
+
+impl LifecycleBudgets {
+    /// The budget for `hook`.
+    pub fn for_hook(&self, hook: Hook) -> Duration {
+        match hook {
+            Hook::OnStart => self.on_start,
+            Hook::OnShutdown => self.on_shutdown,
+        }
+    }
+}
+
+/// How a lifecycle hook call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// The component does not export the hook.
+    NotExported,
+    /// The hook returned `ok`.
+    Completed,
+    /// The hook returned an error, trapped, or has the wrong signature.
+    Failed(String),
+    /// The hook ran past its budget and was abandoned.
+    TimedOut(Duration),
+}
+
+impl HookOutcome {
+    /// Whether the instance may be used after this outcome of `on-start`.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::NotExported | Self::Completed)
+    }
+}
+
+impl fmt::Display for HookOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotExported => f.write_str("not exported"),
+            Self::Completed => f.write_str("completed"),
+            Self::Failed(reason) => write!(f, "failed: {reason}"),
+            Self::TimedOut(budget) => write!(f, "timed out after {budget:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets_per_hook() {
+        let budgets = LifecycleBudgets::default();
+        assert_eq!(budgets.for_hook(Hook::OnStart), Duration::from_secs(10));
+        assert_eq!(budgets.for_hook(Hook::OnShutdown), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn only_clean_outcomes_admit_an_instance() {
+        assert!(HookOutcome::NotExported.is_ok());
+        assert!(HookOutcome::Completed.is_ok());
+        assert!(!HookOutcome::Failed("cache cold".into()).is_ok());
+        assert!(!HookOutcome::TimedOut(Duration::from_secs(1)).is_ok());
+        assert_eq!(
+            HookOutcome::TimedOut(Duration::from_millis(50)).to_string(),
+            "timed out after 50ms"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Memory overcommit for instance pools.
+//!
+//! An instance's memory limit is a ceiling, not its footprint: most
+//! guests settle well below it. Reserving the full limit for every pooled
+//! instance strands node memory, so pools can instead reserve what their
+//! instances have actually grown to plus headroom. The limit itself is
+//! still enforced by the store limiter, so an instance can always grow to
+//! it — overcommit only changes what the node accounts for.
+//!
+//! Because reservations can then undershoot real growth, nodes watch
+//! physical memory ([`NodeMemory`]) and shed idle pooled instances when
+//! available memory drops below a [`PressureThreshold`]. Pressure comes in
+//! two [`PressureLevel`]s: soft pressure only trims pools back to their
+//! warm minimum, hard pressure goes down to each deployment's disruption
+//! budget.
+
+use std::str::FromStr;
+
+/// How much memory a pool reserves per instance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OvercommitPolicy {
+    /// Reserve the full memory limit (no overcommit).
+    #[default]
+    Reserve,
+    /// Reserve the pool's observed peak plus `headroom_percent` of it,
+    /// capped at the memory limit.
+    HighWater { headroom_percent: u32 },
+}
+
+impl OvercommitPolicy {
+    /// Bytes to reserve per instance.
+    ///
+    /// `peak` is the largest linear memory seen across the pool's
+    /// instances; without a sample yet, the full limit is reserved.
+    pub fn reservation(&self, limit: usize, peak: Option<usize>) -> usize {
+        match (self, peak) {
+            (Self::HighWater { headroom_percent }, Some(peak)) => {
+                let headroom = peak.saturating_mul(*headroom_percent as usize) / 100;
+                peak.saturating_add(headroom).min(limit)
+            }
+            _ => limit,
+        }
+    }
+}
+
+impl FromStr for OvercommitPolicy {
+    type Err = String;
+
+    /// `reserve`, or `high-water:<headroom percent>` (e.g. `high-water:25`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "reserve" {
+            return Ok(Self::Reserve);
+        }
+        let percent = s
+            .strip_prefix("high-water:")
+            .ok_or_else(|| format!("unknown overcommit policy '{s}' (reserve|high-water:<pct>)"))?;
+        let headroom_percent = percent
+            .parse()
+            .map_err(|_| format!("invalid headroom percent '{percent}'"))?;
+        Ok(Self::HighWater { headroom_percent })
+    }
+}
+
+/// Physical memory of the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeMemory {
+    pub total_bytes: u64,
+    /// Memory the kernel can hand out without swapping (`MemAvailable`).
+    pub available_bytes: u64,
+}
+
+impl NodeMemory {
+    /// Read the current figures from `/proc/meminfo`.
+    pub fn read() -> anyhow::Result<Self> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo")?;
+        Self::parse_meminfo(&meminfo)
+            .ok_or_else(|| anyhow::anyhow!("MemTotal/MemAvailable missing from /proc/meminfo"))
+    }
+
+    fn parse_meminfo(meminfo: &str) -> Option<Self> {
+        let field = |name: &str| {
+            meminfo.lines().find_map(|line| {
+                let kib = line.strip_prefix(name)?.strip_prefix(':')?;
+                kib.trim().trim_end_matches("kB").trim().parse::<u64>().ok()
+            })
+        };
+        Some(Self {
+            total_bytes: field("MemTotal")? * 1024,
+            available_bytes: field("MemAvailable")? * 1024,
+        })
+    }
+}
+
+/// How hard the node is squeezed for memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PressureLevel {
+    #[default]
+    None,
+    /// Below [`PressureThreshold::min_available_percent`].
+    Soft,
+    /// Below [`PressureThreshold::hard_available_percent`].
+    Hard,
+}
+
+/// When the node counts as under memory pressure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PressureThreshold {
+    /// Shed idle instances once available memory falls below this share
+    /// of total memory.
+    pub min_available_percent: u32,
+    /// Escalate to [`PressureLevel::Hard`] below this share.
+    pub hard_available_percent: u32,
+}
+
+impl Default for PressureThreshold {
+    fn default() -> Self {
+        Self {
+            min_available_percent: 10,
+            hard_available_percent: 5,
+        }
+    }
+}
+
+impl PressureThreshold {
+    /// The pressure level `memory` is at.
+    pub fn level(&self, memory: &NodeMemory) -> PressureLevel {
+        let below = |percent: u32| {
+            memory.available_bytes < memory.total_bytes.saturating_mul(u64::from(percent)) / 100
+        };
+        if below(self.hard_available_percent) {
+            PressureLevel::Hard
+        } else if below(self.min_available_percent) {
+            PressureLevel::Soft
+        } else {
+            PressureLevel::None
+        }
+    }
+
+    /// Bytes that must be freed to get back above the threshold
+    /// (0 when the node is not under pressure).
+    pub fn bytes_to_free(&self, memory: &NodeMemory) -> u64 {
+        let floor = memory.total_bytes.saturating_mul(u64::from(self.min_available_percent)) / 100;
+        floor.saturating_sub(memory.available_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+
+    #[test]
+    fn high_water_reserves_peak_plus_headroom_up_to_limit() {
+        let policy = OvercommitPolicy::HighWater {
+            headroom_percent: 25,
+        };
+        assert_eq!(policy.reservation(64 * MIB, None), 64 * MIB);
+        assert_eq!(policy.reservation(64 * MIB, Some(8 * MIB)), 10 * MIB);
+        assert_eq!(policy.reservation(64 * MIB, Some(60 * MIB)), 64 * MIB);
+        assert_eq!(
+            OvercommitPolicy::Reserve.reservation(64 * MIB, Some(8 * MIB)),
+            64 * MIB
+        );
+    }
+
+    #[test]
+    fn policy_parses_from_flag_syntax() {
+        assert_eq!("reserve".parse(), Ok(OvercommitPolicy::Reserve));
+        assert_eq!(
+            "high-water:30".parse(),
+            Ok(OvercommitPolicy::HighWater {
+                headroom_percent: 30
+            })
+        );
+        assert!("high-water:lots".parse::<OvercommitPolicy>().is_err());
+        assert!("greedy".parse::<OvercommitPolicy>().is_err());
+    }
+
+    #[test]
+    fn pressure_is_measured_against_available_memory() {
+        let memory = NodeMemory::parse_meminfo(
+            "MemTotal:       1000000 kB\nMemFree:          20000 kB\nMemAvailable:      60000 kB\n",
+        )
+        .unwrap();
+        assert_eq!(memory.total_bytes, 1_000_000 * 1024);
+        assert_eq!(memory.available_bytes, 60_000 * 1024);
+
+        let threshold = PressureThreshold::default();
+        assert_eq!(threshold.bytes_to_free(&memory), 40_000 * 1024);
+        assert_eq!(threshold.level(&memory), PressureLevel::Soft);
+        let relaxed = PressureThreshold {
+            min_available_percent: 5,
+            hard_available_percent: 2,
+        };
+        assert_eq!(relaxed.bytes_to_free(&memory), 0);
+        assert_eq!(relaxed.level(&memory), PressureLevel::None);
+        let strict = PressureThreshold {
+            min_available_percent: 20,
+            hard_available_percent: 8,
+        };
+        assert_eq!(strict.level(&memory), PressureLevel::Hard);
+    }
+}
@@ -2,14 +2,28 @@
 //!
 //! Supports min/max instance scaling, round-robin dispatch, and
 //! instance lifecycle management (create, recycle, destroy).
+//!
+//! Pools account for the memory their instances reserve on the node
+//! according to an [`OvercommitPolicy`], and can shed idle instances
+//! when the node is under memory pressure.
+//!
+//! Guests exporting lifecycle hooks get `on-start` before an instance
+//! joins the pool and `on-shutdown` before an idle one is dropped; see
+//! [`crate::lifecycle`].
 
 use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use tokio::sync::Mutex;
-use tracing::{debug, info};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+use warpgrid_host::bundles::SharedBundle;
+use warpgrid_host::flags::host::FlagsHost;
 
 use crate::instance::{InstanceFactory, WasmInstance};
+use crate::lifecycle::{Hook, HookOutcome, LifecycleBudgets};
+use crate::overcommit::OvercommitPolicy;
 
 /// Configuration for an instance pool.
 #[derive(Debug, Clone)]
@@ -20,6 +34,14 @@ pub struct PoolConfig {
     pub max_instances: u32,
     /// Memory limit per instance (bytes).
     pub memory_limit: usize,
+    /// How much of `memory_limit` each instance reserves on the node.
+    pub overcommit: OvercommitPolicy,
+    /// Time budgets for the guest's lifecycle hooks.
+    pub lifecycle: LifecycleBudgets,
+    /// Feature flags the instances read, when the deployment has a handle.
+    pub flags: Option<FlagsHost>,
+    /// Config bundle the instances follow, when the deployment has a handle.
+    pub config: Option<SharedBundle>,
 }
 
 impl Default for PoolConfig {
@@ -28,6 +50,10 @@ impl Default for PoolConfig {
             min_instances: 1,
             max_instances: 10,
             memory_limit: 64 * 1024 * 1024,
+            overcommit: OvercommitPolicy::Reserve,
+            lifecycle: LifecycleBudgets::default(),
+            flags: None,
+            config: None,
         }
     }
 }
@@ -44,6 +70,8 @@ pub struct InstancePool {
     available: Arc<Mutex<VecDeque<WasmInstance>>>,
     /// Total number of instances (available + checked out).
     total_count: Arc<Mutex<u32>>,
+    /// Largest linear memory seen on any instance (0 until sampled).
+    peak_memory: AtomicUsize,
 }
 
 impl InstancePool {
@@ -54,6 +82,7 @@ impl InstancePool {
             config,
             available: Arc::new(Mutex::new(VecDeque::new())),
             total_count: Arc::new(Mutex::new(0)),
+            peak_memory: AtomicUsize::new(0),
         }
     }
 
@@ -63,10 +92,7 @@ impl InstancePool {
         let needed = self.config.min_instances.saturating_sub(current);
 
         for _ in 0..needed {
-            let instance = self
-                .factory
-                .create_instance(self.config.memory_limit)
-                .await?;
+            let instance = self.start_instance().await?;
             self.available.lock().await.push_back(instance);
             *self.total_count.lock().await += 1;
         }
@@ -96,10 +122,13 @@ impl InstancePool {
             *count += 1;
             drop(count); // Release lock before async work.
 
-            let instance = self
-                .factory
-                .create_instance(self.config.memory_limit)
-                .await?;
+            let instance = match self.start_instance().await {
+                Ok(instance) => instance,
+                Err(e) => {
+                    *self.total_count.lock().await -= 1;
+                    return Err(e);
+                }
+            };
             debug!("created new instance for pool");
             Ok(Some(instance))
         } else {
@@ -113,10 +142,20 @@ impl InstancePool {
 
     /// Return an instance to the pool for reuse.
     pub async fn release(&self, instance: WasmInstance) {
+        self.record_peak(&instance);
         self.available.lock().await.push_back(instance);
         debug!("instance returned to pool");
     }
 
+    /// Drop a checked-out instance instead of returning it, e.g. after a
+    /// call trapped and left its store mid-call.
+    pub async fn discard(&self, instance: WasmInstance) {
+        self.record_peak(&instance);
+        drop(instance);
+        *self.total_count.lock().await -= 1;
+        debug!("discarded instance");
+    }
+
     /// Current number of available (idle) instances.
     pub async fn available_count(&self) -> usize {
         self.available.lock().await.len()
@@ -145,19 +184,146 @@ impl InstancePool {
         let target = target.max(self.config.min_instances);
         let mut available = self.available.lock().await;
         let mut count = self.total_count.lock().await;
+        let mut retired = Vec::new();
 
-        while *count > target && !available.is_empty() {
-            available.pop_back(); // Drop the instance.
+        while *count > target {
+            let Some(instance) = available.pop_back() else {
+                break;
+            };
+            self.record_peak(&instance);
+            retired.push(instance);
             *count -= 1;
         }
 
         debug!(target, actual = *count, "scaled down instance pool");
+        drop((available, count));
+        self.retire(retired).await;
+    }
+
+    /// Drop every idle instance, running their `on-shutdown` hooks.
+    ///
+    /// Used when a deployment is unscheduled and on daemon shutdown.
+    /// Instances checked out at the time are not waited for. Returns the
+    /// number of instances drained.
+    pub async fn drain(&self) -> u32 {
+        let retired: Vec<_> = self.available.lock().await.drain(..).collect();
+        let drained = retired.len() as u32;
+        *self.total_count.lock().await -= drained;
+        for instance in &retired {
+            self.record_peak(instance);
+        }
+        self.retire(retired).await;
+        if drained > 0 {
+            info!(drained, "drained instance pool");
+        }
+        drained
+    }
+
+    /// Memory reserved on the node for each instance, per the pool's
+    /// [`OvercommitPolicy`].
+    pub fn memory_reservation(&self) -> usize {
+        let peak = match self.peak_memory.load(Ordering::Relaxed) {
+            0 => None,
+            peak => Some(peak),
+        };
+        self.config
+            .overcommit
+            .reservation(self.config.memory_limit, peak)
+    }
+
+    /// Memory reserved on the node by all instances of this pool.
+    pub async fn reserved_memory_bytes(&self) -> u64 {
+        u64::from(self.total_count().await) * self.memory_reservation() as u64
+    }
+
+    /// Drop idle instances until about `bytes` of reservation is freed,
+    /// leaving at least `keep` instances in the pool.
+    ///
+    /// Used under node memory pressure, so unlike [`Self::scale_down_to`]
+    /// `keep` may be below `min_instances`; the next warm-up or scale-up
+    /// restores them. Returns the instances shed and the reservation freed.
+    pub async fn shed_idle(&self, bytes: u64, keep: u32) -> (u32, u64) {
+        let mut available = self.available.lock().await;
+        let mut count = self.total_count.lock().await;
+        let mut shed = 0;
+        let mut freed = 0;
+        let mut retired = Vec::new();
+
+        while freed < bytes && *count > keep {
+            let Some(instance) = available.pop_back() else {
+                break;
+            };
+            self.record_peak(&instance);
+            retired.push(instance);
+            freed += self.memory_reservation() as u64;
+            *count -= 1;
+            shed += 1;
+        }
+
+        if shed > 0 {
+            info!(shed, freed, remaining = *count, "shed idle instances under memory pressure");
+        }
+        drop((available, count));
+        self.retire(retired).await;
+        (shed, freed)
+    }
+
+    /// Create an instance and run its `on-start` hook.
+    ///
+    /// An instance whose hook fails or overruns its budget is dropped.
+    async fn start_instance(&self) -> anyhow::Result<WasmInstance> {
+        let mut instance = self
+            .factory
+            .create_instance(self.config.memory_limit)
+            .await?;
+        if let Some(flags) = &self.config.flags {
+            instance.store_mut().data_mut().flags = Some(flags.clone());
+        }
+        if let Some(config) = &self.config.config {
+            instance.store_mut().data_mut().attach_config(config.clone());
+        }
+        let budget = self.config.lifecycle.for_hook(Hook::OnStart);
+        match instance.run_hook(Hook::OnStart, budget).await {
+            HookOutcome::NotExported => {}
+            HookOutcome::Completed => debug!(module = instance.module_name(), "on-start completed"),
+            outcome => anyhow::bail!("{} on-start hook {outcome}", instance.module_name()),
+        }
+        Ok(instance)
+    }
+
+    /// Run `on-shutdown` on instances leaving the pool, concurrently,
+    /// then drop them.
+    async fn retire(&self, instances: Vec<WasmInstance>) {
+        if instances.is_empty() {
+            return;
+        }
+        let budget = self.config.lifecycle.for_hook(Hook::OnShutdown);
+        let mut hooks = JoinSet::new();
+        for mut instance in instances {
+            hooks.spawn(async move {
+                let outcome = instance.run_hook(Hook::OnShutdown, budget).await;
+                (instance.module_name().to_string(), outcome)
+            });
+        }
+        while let Some(joined) = hooks.join_next().await {
+            match joined {
+                Ok((_, HookOutcome::NotExported | HookOutcome::Completed)) => {}
+                Ok((module, outcome)) => warn!(%module, %outcome, "on-shutdown hook did not complete"),
+                Err(e) => warn!(error = %e, "on-shutdown hook task failed"),
+            }
+        }
+    }
+
+    fn record_peak(&self, instance: &WasmInstance) {
+        self.peak_memory
+            .fetch_max(instance.peak_memory_bytes(), Ordering::Relaxed);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     // Note: Full pool tests require a real .wasm component to instantiate.
     // These tests verify the configuration and structural aspects.
@@ -176,8 +342,155 @@ mod tests {
             min_instances: 2,
             max_instances: 50,
             memory_limit: 128 * 1024 * 1024,
+            overcommit: OvercommitPolicy::Reserve,
+            lifecycle: LifecycleBudgets::default(),
+            flags: None,
+            config: None,
         };
         assert_eq!(config.min_instances, 2);
         assert_eq!(config.max_instances, 50);
     }
+
+    #[tokio::test]
+    async fn high_water_pool_reserves_observed_memory() {
+        let runtime = crate::Runtime::new(crate::ShimConfig::default()).unwrap();
+        // One core instance with a two-page (128 KiB) memory.
+        let bytes = wat::parse_str(
+            "(component (core module $m (memory 2)) (core instance (instantiate $m)))",
+        )
+        .unwrap();
+        let module = runtime.load_module("app", &bytes).await.unwrap();
+        let pool = runtime.create_pool(
+            module,
+            PoolConfig {
+                min_instances: 2,
+                max_instances: 4,
+                memory_limit: 64 * 1024 * 1024,
+                overcommit: OvercommitPolicy::HighWater {
+                    headroom_percent: 50,
+                },
+                lifecycle: LifecycleBudgets::default(),
+                flags: None,
+                config: None,
+            },
+        );
+        pool.warm_up().await.unwrap();
+        // Nothing observed yet: the full limit is reserved.
+        assert_eq!(pool.reserved_memory_bytes().await, 2 * 64 * 1024 * 1024);
+
+        let instance = pool.acquire().await.unwrap().unwrap();
+        pool.release(instance).await;
+        assert_eq!(pool.memory_reservation(), 192 * 1024);
+        assert_eq!(pool.reserved_memory_bytes().await, 2 * 192 * 1024);
+
+        assert_eq!(pool.shed_idle(1, 0).await, (1, 192 * 1024));
+        assert_eq!(pool.total_count().await, 1);
+        // The last instance is protected by `keep`.
+        assert_eq!(pool.shed_idle(u64::MAX, 1).await, (0, 0));
+        assert_eq!(pool.total_count().await, 1);
+
+        let instance = pool.acquire().await.unwrap().unwrap();
+        pool.discard(instance).await;
+        assert_eq!(pool.total_count().await, 0);
+        assert_eq!(pool.available_count().await, 0);
+    }
+
+    /// A component exporting the lifecycle interface; each hook's core
+    /// function body is given as WAT. A body returning 0 yields `ok`, one
+    /// returning 16 yields `err("cache cold")`.
+    fn lifecycle_component(on_start: &str, on_shutdown: &str) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(component
+                (core module $m
+                    (memory (export "mem") 1)
+                    (data (i32.const 16) "\01\00\00\00\20\00\00\00\0a\00\00\00")
+                    (data (i32.const 32) "cache cold")
+                    (func (export "on-start") (result i32) {on_start})
+                    (func (export "on-shutdown") (result i32) {on_shutdown}))
+                (core instance $i (instantiate $m))
+                (alias core export $i "mem" (core memory $mem))
+                (type $hook (func (result (result (error string)))))
+                (func $start (type $hook) (canon lift (core func $i "on-start") (memory $mem)))
+                (func $shutdown (type $hook) (canon lift (core func $i "on-shutdown") (memory $mem)))
+                (instance $lifecycle
+                    (export "on-start" (func $start))
+                    (export "on-shutdown" (func $shutdown)))
+                (export "warpgrid:shim/lifecycle@0.1.0" (instance $lifecycle)))"#
+        ))
+        .unwrap()
+    }
+
+    async fn lifecycle_pool(runtime: &crate::Runtime, bytes: &[u8], budgets: LifecycleBudgets) -> InstancePool {
+        let module = runtime.load_module("hooks", bytes).await.unwrap();
+        runtime.create_pool(
+            module,
+            PoolConfig {
+                min_instances: 1,
+                max_instances: 2,
+                lifecycle: budgets,
+                ..PoolConfig::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn lifecycle_hooks_run_on_start_and_drain() {
+        let runtime = crate::Runtime::new(crate::ShimConfig::default()).unwrap();
+        let bytes = lifecycle_component("i32.const 0", "i32.const 16");
+        let pool = lifecycle_pool(&runtime, &bytes, LifecycleBudgets::default()).await;
+        pool.warm_up().await.unwrap();
+        assert_eq!(pool.total_count().await, 1);
+
+        let mut instance = pool.acquire().await.unwrap().unwrap();
+        assert_eq!(
+            instance.run_hook(Hook::OnShutdown, Duration::from_secs(1)).await,
+            HookOutcome::Failed("cache cold".into())
+        );
+        // A failing on-shutdown is logged; the instance is dropped anyway.
+        pool.release(instance).await;
+        assert_eq!(pool.drain().await, 1);
+        assert_eq!(pool.total_count().await, 0);
+        assert_eq!(pool.available_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn failed_on_start_keeps_the_instance_out() {
+        let runtime = crate::Runtime::new(crate::ShimConfig::default()).unwrap();
+        let bytes = lifecycle_component("i32.const 16", "i32.const 0");
+        let pool = lifecycle_pool(&runtime, &bytes, LifecycleBudgets::default()).await;
+
+        let err = pool.acquire().await.err().unwrap();
+        assert!(err.to_string().contains("on-start hook failed: cache cold"), "{err}");
+        assert_eq!(pool.total_count().await, 0);
+    }
+
+    // The epoch ticker needs a worker thread while the guest spins.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn on_start_past_its_budget_is_abandoned() {
+        let runtime =
+            crate::Runtime::new_metered(crate::ShimConfig::default(), Duration::from_millis(5))
+                .unwrap();
+        let bytes = lifecycle_component("(loop br 0) i32.const 0", "i32.const 0");
+        let budgets = LifecycleBudgets {
+            on_start: Duration::from_millis(50),
+            ..LifecycleBudgets::default()
+        };
+        let pool = lifecycle_pool(&runtime, &bytes, budgets).await;
+
+        let err = pool.warm_up().await.err().unwrap();
+        assert!(err.to_string().contains("timed out after 50ms"), "{err}");
+        assert_eq!(pool.total_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn components_without_hooks_are_unaffected() {
+        let runtime = crate::Runtime::new(crate::ShimConfig::default()).unwrap();
+        let bytes = wat::parse_str("(component)").unwrap();
+        let module = runtime.load_module("bare", &bytes).await.unwrap();
+        let mut instance = runtime.instantiate(&module, 64 * 1024 * 1024).await.unwrap();
+        assert_eq!(
+            instance.run_hook(Hook::OnStart, Duration::from_secs(1)).await,
+            HookOutcome::NotExported
+        );
+    }
 }
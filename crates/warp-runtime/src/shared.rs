@@ -0,0 +1,156 @@
+//! Compiled-module sharing across deployments.
+//!
+//! Deployments frequently reference the same artifact (a shared gateway,
+//! several tenants of one app). Compiling it once per deployment wastes
+//! compile time and keeps duplicate machine code resident, so the runtime
+//! shares one compiled component between every module name that loads
+//! the same bytes.
+//!
+//! Entries are keyed by [`ModuleKey`]: the artifact's SHA-256 plus a hash
+//! of the shim interfaces linked into the engine. The second half matters
+//! for pre-instantiated modules ([`CompiledModule::pre_instantiate`]),
+//! which are only valid for the linker they were resolved against.
+//!
+//! An entry lives as long as at least one module name refers to it.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use sha2::{Digest, Sha256};
+use warpgrid_host::config::ShimConfig;
+
+use crate::instance::CompiledModule;
+
+/// Identity of a compiled module: artifact digest + linker shape.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleKey {
+    /// Hex SHA-256 of the component bytes.
+    pub digest: String,
+    /// [`shim_config_hash`] of the engine the module was compiled for.
+    pub shim_hash: u64,
+}
+
+impl ModuleKey {
+    pub fn new(bytes: &[u8], shims: &ShimConfig) -> Self {
+        Self {
+            digest: hex::encode(Sha256::digest(bytes)),
+            shim_hash: shim_config_hash(shims),
+        }
+    }
+}
+
+/// Hash of the shim interfaces a [`ShimConfig`] links into the engine.
+///
+/// Only the enabled interfaces shape the linker; per-instance data such
+/// as env vars or the service registry does not affect a compiled module.
+pub fn shim_config_hash(config: &ShimConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (
+        config.filesystem,
+        config.dns,
+        config.signals,
+        config.database_proxy,
+        config.threading,
+        config.render,
+    )
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+struct Entry {
+    module: CompiledModule,
+    names: HashSet<String>,
+}
+
+/// Compiled modules by key, with the module names referring to each.
+#[derive(Default)]
+pub(crate) struct SharedModules {
+    entries: HashMap<ModuleKey, Entry>,
+    keys: HashMap<String, ModuleKey>,
+}
+
+impl SharedModules {
+    /// The compiled module for `key`, recording `name` as a user.
+    pub(crate) fn acquire(&mut self, key: &ModuleKey, name: &str) -> Option<CompiledModule> {
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+        self.bind(key, name);
+        let entry = self.entries.get_mut(key)?;
+        entry.names.insert(name.to_string());
+        Some(entry.module.shared_as(name))
+    }
+
+    /// Register a freshly compiled module under `key`.
+    ///
+    /// If another load of the same key won the race, its module is kept
+    /// and returned instead.
+    pub(crate) fn insert(
+        &mut self,
+        key: ModuleKey,
+        name: &str,
+        module: CompiledModule,
+    ) -> CompiledModule {
+        if let Some(existing) = self.acquire(&key, name) {
+            return existing;
+        }
+        self.bind(&key, name);
+        self.entries.insert(
+            key,
+            Entry {
+                module: module.clone(),
+                names: HashSet::from([name.to_string()]),
+            },
+        );
+        module
+    }
+
+    /// Drop `name`'s reference, freeing its entry once nothing uses it.
+    pub(crate) fn release(&mut self, name: &str) {
+        if let Some(key) = self.keys.remove(name) {
+            self.unref(&key, name);
+        }
+    }
+
+    /// Number of distinct compiled modules.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Point `name` at `key`, releasing whatever it referred to before.
+    fn bind(&mut self, key: &ModuleKey, name: &str) {
+        if let Some(previous) = self.keys.insert(name.to_string(), key.clone())
+            && &previous != key
+        {
+            self.unref(&previous, name);
+        }
+    }
+
+    fn unref(&mut self, key: &ModuleKey, name: &str) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.names.remove(name);
+            if entry.names.is_empty() {
+                self.entries.remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_per_instance_shim_data() {
+        let base = ShimConfig::default();
+        let mut with_env = base.clone();
+        with_env.env.insert("TENANT".into(), "a".into());
+        assert_eq!(ModuleKey::new(b"x", &base), ModuleKey::new(b"x", &with_env));
+
+        let mut no_dns = base.clone();
+        no_dns.dns = false;
+        assert_ne!(ModuleKey::new(b"x", &base), ModuleKey::new(b"x", &no_dns));
+        assert_ne!(ModuleKey::new(b"x", &base), ModuleKey::new(b"y", &base));
+    }
+}
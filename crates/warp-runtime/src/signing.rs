@@ -0,0 +1,288 @@
+//! Signature verification for packed artifacts.
+//!
+//! `warp pack` can sign `handler.wasm` with cosign (`[build.sign]`), which
+//! writes a sigstore bundle next to it (`handler.wasm.sigstore.json`). The
+//! runtime checks that bundle before compiling a module, according to the
+//! node's [`SignaturePolicy`]:
+//!
+//! - **disabled** — no check (default)
+//! - **warn** — load the module, logging a warning if verification fails
+//! - **enforce** — refuse to load anything that does not verify
+//!
+//! Verification shells out to `cosign verify-blob`, against either a
+//! public key or a keyless (Fulcio) certificate identity and issuer.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+use tracing::{debug, warn};
+
+/// What to do when an artifact's signature is missing or invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignatureMode {
+    #[default]
+    Disabled,
+    Warn,
+    Enforce,
+}
+
+impl FromStr for SignatureMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disabled" => Ok(Self::Disabled),
+            "warn" => Ok(Self::Warn),
+            "enforce" => Ok(Self::Enforce),
+            other => Err(format!(
+                "unknown signature mode '{other}' (expected disabled, warn, or enforce)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SignatureMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Disabled => "disabled",
+            Self::Warn => "warn",
+            Self::Enforce => "enforce",
+        })
+    }
+}
+
+/// Who an artifact must be signed by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustRoot {
+    /// A cosign public key (file path or KMS URI).
+    Key(String),
+    /// A keyless signature whose Fulcio certificate names this identity
+    /// (e.g. a CI workflow URL or email) and OIDC issuer.
+    Keyless { identity: String, issuer: String },
+}
+
+/// Per-node signature verification policy applied before `load_module`.
+#[derive(Debug, Clone, Default)]
+pub struct SignaturePolicy {
+    mode: SignatureMode,
+    trust: Option<TrustRoot>,
+    cosign: Option<PathBuf>,
+}
+
+impl SignaturePolicy {
+    /// A policy that performs no verification.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Create a policy. Any mode other than `Disabled` needs a trust root.
+    pub fn new(mode: SignatureMode, trust: Option<TrustRoot>) -> anyhow::Result<Self> {
+        if mode != SignatureMode::Disabled && trust.is_none() {
+            anyhow::bail!(
+                "signature mode '{mode}' needs a public key or a keyless identity and issuer"
+            );
+        }
+        Ok(Self {
+            mode,
+            trust,
+            cosign: std::env::var_os("WARPGRID_COSIGN_PATH").map(PathBuf::from),
+        })
+    }
+
+    /// Use a specific cosign binary instead of `$WARPGRID_COSIGN_PATH` / `$PATH`.
+    pub fn with_cosign(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cosign = Some(path.into());
+        self
+    }
+
+    pub fn mode(&self) -> SignatureMode {
+        self.mode
+    }
+
+    /// Path of the sigstore bundle expected next to an artifact.
+    pub fn bundle_path(artifact: &Path) -> PathBuf {
+        let mut name = artifact.as_os_str().to_owned();
+        name.push(".sigstore.json");
+        PathBuf::from(name)
+    }
+
+    /// Check the artifact at `path` against its sigstore bundle.
+    ///
+    /// Errors only in `Enforce` mode; in `Warn` mode failures are logged.
+    pub fn verify_file(&self, name: &str, path: &Path) -> anyhow::Result<()> {
+        let Some(trust) = self.active_trust() else {
+            return Ok(());
+        };
+        let bundle = Self::bundle_path(path);
+        if !bundle.is_file() {
+            return self.reject(name, &format!("no signature bundle at {}", bundle.display()));
+        }
+        match self.run_cosign(trust, path, &bundle) {
+            Ok(()) => {
+                debug!(module = name, "artifact signature verified");
+                Ok(())
+            }
+            Err(reason) => self.reject(name, &reason),
+        }
+    }
+
+    /// Check a module loaded from raw bytes, which carries no bundle.
+    pub fn verify_unsigned(&self, name: &str) -> anyhow::Result<()> {
+        if self.active_trust().is_none() {
+            return Ok(());
+        }
+        self.reject(name, "loaded from memory without a signature bundle")
+    }
+
+    fn active_trust(&self) -> Option<&TrustRoot> {
+        match self.mode {
+            SignatureMode::Disabled => None,
+            SignatureMode::Warn | SignatureMode::Enforce => self.trust.as_ref(),
+        }
+    }
+
+    fn reject(&self, name: &str, reason: &str) -> anyhow::Result<()> {
+        if self.mode == SignatureMode::Enforce {
+            anyhow::bail!("signature verification failed for module '{name}': {reason}");
+        }
+        warn!(module = name, reason, "signature verification failed; loading anyway (warn mode)");
+        Ok(())
+    }
+
+    fn verify_command(&self, trust: &TrustRoot, artifact: &Path, bundle: &Path) -> Command {
+        let mut cmd = Command::new(self.cosign.as_deref().unwrap_or(Path::new("cosign")));
+        cmd.arg("verify-blob").arg("--bundle").arg(bundle);
+        match trust {
+            TrustRoot::Key(key) => {
+                cmd.arg("--key").arg(key);
+            }
+            TrustRoot::Keyless { identity, issuer } => {
+                cmd.arg("--certificate-identity")
+                    .arg(identity)
+                    .arg("--certificate-oidc-issuer")
+                    .arg(issuer);
+            }
+        }
+        cmd.arg(artifact);
+        cmd
+    }
+
+    fn run_cosign(&self, trust: &TrustRoot, artifact: &Path, bundle: &Path) -> Result<(), String> {
+        let output = self
+            .verify_command(trust, artifact, bundle)
+            .output()
+            .map_err(|e| format!("failed to run cosign: {e}"))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(format!(
+            "cosign verify-blob exited with {}: {}",
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_policy(mode: SignatureMode) -> SignaturePolicy {
+        SignaturePolicy::new(mode, Some(TrustRoot::Key("cosign.pub".into()))).unwrap()
+    }
+
+    /// A fake cosign that exits with `code`.
+    fn fake_cosign(dir: &Path, code: i32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(format!("cosign-{code}"));
+        std::fs::write(&path, format!("#!/bin/sh\necho bad signature >&2\nexit {code}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn signed_artifact(dir: &Path) -> PathBuf {
+        let artifact = dir.join("handler.wasm");
+        std::fs::write(&artifact, b"\0asm").unwrap();
+        std::fs::write(SignaturePolicy::bundle_path(&artifact), b"{}").unwrap();
+        artifact
+    }
+
+    #[test]
+    fn mode_parses_and_displays() {
+        for mode in ["disabled", "warn", "enforce"] {
+            assert_eq!(mode.parse::<SignatureMode>().unwrap().to_string(), mode);
+        }
+        assert!("strict".parse::<SignatureMode>().is_err());
+    }
+
+    #[test]
+    fn enabled_mode_requires_trust_root() {
+        assert!(SignaturePolicy::new(SignatureMode::Enforce, None).is_err());
+        assert!(SignaturePolicy::new(SignatureMode::Disabled, None).is_ok());
+    }
+
+    #[test]
+    fn enforce_rejects_missing_bundle_and_warn_allows_it() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+
+        let err = key_policy(SignatureMode::Enforce)
+            .verify_file("app", &artifact)
+            .unwrap_err();
+        assert!(err.to_string().contains("no signature bundle"), "{err}");
+        assert!(key_policy(SignatureMode::Warn).verify_file("app", &artifact).is_ok());
+        assert!(SignaturePolicy::disabled().verify_file("app", &artifact).is_ok());
+    }
+
+    #[test]
+    fn enforce_follows_cosign_exit_status() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let artifact = signed_artifact(dir.path());
+
+        let ok = key_policy(SignatureMode::Enforce).with_cosign(fake_cosign(dir.path(), 0));
+        assert!(ok.verify_file("app", &artifact).is_ok());
+
+        let bad = key_policy(SignatureMode::Enforce).with_cosign(fake_cosign(dir.path(), 1));
+        let err = bad.verify_file("app", &artifact).unwrap_err().to_string();
+        assert!(err.contains("bad signature"), "{err}");
+    }
+
+    #[test]
+    fn enforce_rejects_in_memory_modules() {
+        assert!(key_policy(SignatureMode::Enforce).verify_unsigned("app").is_err());
+        assert!(key_policy(SignatureMode::Warn).verify_unsigned("app").is_ok());
+    }
+
+    #[test]
+    fn keyless_verify_pins_identity_and_issuer() {
+        let policy = SignaturePolicy::new(
+            SignatureMode::Enforce,
+            Some(TrustRoot::Keyless {
+                identity: "ci@example.com".into(),
+                issuer: "https://accounts.example.com".into(),
+            }),
+        )
+        .unwrap();
+        let cmd = policy.verify_command(
+            policy.trust.as_ref().unwrap(),
+            Path::new("handler.wasm"),
+            Path::new("handler.wasm.sigstore.json"),
+        );
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(
+            args,
+            [
+                "verify-blob",
+                "--bundle",
+                "handler.wasm.sigstore.json",
+                "--certificate-identity",
+                "ci@example.com",
+                "--certificate-oidc-issuer",
+                "https://accounts.example.com",
+                "handler.wasm"
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Per-request execution metering.
+//!
+//! Measures the resources a request consumes on a Wasm instance so the
+//! control plane can emit billing-grade usage events:
+//!
+//! - **Guest CPU time** is sampled with Wasmtime epochs. An [`EpochTicker`]
+//!   advances the engine epoch every tick; each time running guest code
+//!   crosses a tick, the store's deadline callback charges one tick to the
+//!   instance's [`ExecutionMeter`]. Time spent awaiting host I/O is not
+//!   charged, since no guest code is running.
+//! - **Wall time** is measured from [`ExecutionMeter::begin`] to
+//!   [`ExecutionMeter::finish`].
+//! - **Execution budget**: when a wall-clock budget is set, the deadline
+//!   callback traps the guest once it is exhausted.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use wasmtime::{Engine, UpdateDeadline};
+
+/// Default epoch tick for metered runtimes.
+pub const DEFAULT_EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Background thread that advances the engine epoch at a fixed interval.
+///
+/// A thread of its own rather than a tokio task: guests only yield to the
+/// executor when the epoch moves, so a ticker sharing the executor with
+/// busy guests would never get to run. It stops when the ticker is dropped.
+pub struct EpochTicker {
+    stop: Arc<AtomicBool>,
+}
+
+impl EpochTicker {
+    /// Start ticking `engine`'s epoch every `tick`.
+    pub fn start(engine: Engine, tick: Duration) -> std::io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        std::thread::Builder::new()
+            .name("warpgrid-epoch".to_string())
+            .spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    std::thread::sleep(tick);
+                    engine.increment_epoch();
+                }
+            })?;
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Resources consumed by a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsageSample {
+    /// Wall-clock time from `begin` to `finish`.
+    pub wall_time: Duration,
+    /// Guest CPU time (epoch ticks × tick interval).
+    pub cpu_time: Duration,
+    /// Memory limit of the instance.
+    pub memory_limit_bytes: u64,
+    /// Whether the guest was trapped for exceeding its execution budget.
+    pub budget_exceeded: bool,
+}
+
+/// Per-instance meter shared with the store's epoch deadline callback.
+#[derive(Debug)]
+pub struct ExecutionMeter {
+    tick: Duration,
+    memory_limit_bytes: u64,
+    ticks: AtomicU64,
+    budget_exceeded: AtomicBool,
+    /// Request start and optional budget deadline.
+    window: Mutex<Option<(Instant, Option<Instant>)>>,
+}
+
+impl ExecutionMeter {
+    /// Create a meter for an instance with the given tick and memory limit.
+    pub fn new(tick: Duration, memory_limit_bytes: u64) -> Self {
+        Self {
+            tick,
+            memory_limit_bytes,
+            ticks: AtomicU64::new(0),
+            budget_exceeded: AtomicBool::new(false),
+            window: Mutex::new(None),
+        }
+    }
+
+    /// Start metering a request, optionally bounded by a wall-clock budget.
+    pub fn begin(&self, budget: Option<Duration>) {
+        let now = Instant::now();
+        self.ticks.store(0, Ordering::Relaxed);
+        self.budget_exceeded.store(false, Ordering::Relaxed);
+        *self.window.lock().expect("meter lock poisoned") =
+            Some((now, budget.map(|b| now + b)));
+    }
+
+    /// Stop metering and return the request's usage.
+    ///
+    /// Returns a zero sample if no request was in progress.
+    pub fn finish(&self) -> UsageSample {
+        let window = self.window.lock().expect("meter lock poisoned").take();
+        let wall_time = window.map_or(Duration::ZERO, |(start, _)| start.elapsed());
+        let ticks = self.ticks.swap(0, Ordering::Relaxed);
+        UsageSample {
+            wall_time,
+            cpu_time: self.tick.saturating_mul(ticks.min(u32::MAX as u64) as u32),
+            memory_limit_bytes: self.memory_limit_bytes,
+            budget_exceeded: self.budget_exceeded.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Charge one epoch tick; called from the store's deadline callback.
+    ///
+    /// Returns an error (trapping the guest) once the budget is exhausted.
+    pub fn on_epoch_tick(&self) -> wasmtime::Result<UpdateDeadline> {
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+
+        let deadline = self
+            .window
+            .lock()
+            .expect("meter lock poisoned")
+            .and_then(|(_, deadline)| deadline);
+        if let Some(deadline) = deadline
+            && Instant::now() >= deadline
+        {
+            self.budget_exceeded.store(true, Ordering::Relaxed);
+            return Err(wasmtime::Error::msg("execution budget exceeded"));
+        }
+
+        // Yield so long-running guests don't starve other tasks on the executor.
+        Ok(UpdateDeadline::Yield(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn finish_reports_ticks_as_cpu_time() {
+        let meter = ExecutionMeter::new(Duration::from_millis(10), 64 * MIB);
+        meter.begin(None);
+        for _ in 0..3 {
+            assert!(meter.on_epoch_tick().is_ok());
+        }
+
+        let sample = meter.finish();
+        assert_eq!(sample.cpu_time, Duration::from_millis(30));
+        assert_eq!(sample.memory_limit_bytes, 64 * MIB);
+        assert!(!sample.budget_exceeded);
+    }
+
+    #[test]
+    fn begin_resets_previous_request() {
+        let meter = ExecutionMeter::new(Duration::from_millis(10), 64 * MIB);
+        meter.begin(None);
+        meter.on_epoch_tick().unwrap();
+        meter.finish();
+
+        meter.begin(None);
+        assert_eq!(meter.finish().cpu_time, Duration::ZERO);
+    }
+
+    #[test]
+    fn exhausted_budget_traps_guest() {
+        let meter = ExecutionMeter::new(Duration::from_millis(10), 64 * MIB);
+        meter.begin(Some(Duration::ZERO));
+
+        assert!(meter.on_epoch_tick().is_err());
+        assert!(meter.finish().budget_exceeded);
+    }
+
+    #[test]
+    fn finish_without_begin_is_zero() {
+        let meter = ExecutionMeter::new(Duration::from_millis(10), 64 * MIB);
+        let sample = meter.finish();
+        assert_eq!(sample.wall_time, Duration::ZERO);
+        assert_eq!(sample.cpu_time, Duration::ZERO);
+    }
+}
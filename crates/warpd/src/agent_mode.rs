@@ -1,11 +1,24 @@
 //! Agent mode — runs on worker nodes, joins an existing cluster.
 //!
 //! In this mode, the daemon:
-//! 1. Opens a local state store for instance tracking
+//! 1. Opens a local state store for instance tracking, plus a read-only
+//!    replica of control-plane state (deployments, service endpoints, flags)
 //! 2. Initializes the Wasm runtime and local scheduler
-//! 3. Connects to the control plane and joins the cluster
+//! 3. Benchmarks the CPU, then connects to the control plane and joins
+//!    the cluster, reporting the measured CPU weight
 //! 4. Runs a heartbeat loop, processing commands from the control plane
-//! 5. On shutdown, gracefully leaves the cluster
+//!    and applying state deltas to the replica
+//! 5. Evicts idle instances under memory pressure and reports it in
+//!    heartbeats, so the control plane places new work elsewhere
+//! 6. Keeps the local DNS/proxy view and the feature flags in sync with
+//!    the replica, optionally exporting the service records to corporate
+//!    DNS and bridging them with a Consul or etcd catalog
+//! 7. On shutdown, gracefully leaves the cluster
+//!
+//! Secrets replicate sealed with the control planes' `secrets.key` (see
+//! [`warpd::load_secrets_key`]), and agents are never sent it: an agent
+//! neither opens nor mounts secrets, and its replica, like a backup, holds
+//! none in the clear.
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -16,36 +29,128 @@ use tokio::sync::watch;
 use tracing::info;
 
 use warpgrid_cluster::agent::{AgentConfig, NodeAgent};
+use warpgrid_proxy::{
+    DiscoveryBridge, DiscoveryConfig, DnsExportConfig, DnsExporter, DnsResolver, ProxySync, Router,
+};
+
+/// Longest the local proxy view goes without checking the replica; each
+/// applied sync wakes it at once.
+const PROXY_RESYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long the startup CPU benchmark runs.
+const CPU_BENCHMARK_BUDGET: Duration = Duration::from_millis(500);
+
+/// Measure this node's CPU weight; `None` (with a warning) if the
+/// benchmark cannot run, leaving placement on the configured capacity.
+async fn calibrate_cpu_weight() -> Option<u32> {
+    let result = tokio::task::spawn_blocking(|| warp_runtime::benchmark::calibrate(CPU_BENCHMARK_BUDGET)).await;
+    match result {
+        Ok(Ok(bench)) => {
+            info!(
+                cpu_weight = bench.cpu_weight,
+                cores = bench.cores,
+                iterations_per_sec = bench.iterations_per_sec as u64,
+                "calibrated CPU weight"
+            );
+            Some(bench.cpu_weight)
+        }
+        Ok(Err(e)) => {
+            tracing::warn!(error = %e, "CPU benchmark failed; using --capacity-cpu-weight");
+            None
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "CPU benchmark panicked; using --capacity-cpu-weight");
+            None
+        }
+    }
+}
+
+/// Everything agent mode runs with.
+pub struct AgentModeConfig {
+    /// Control plane gRPC address to join.
+    pub control_plane_addr: String,
+    /// Address this node advertises to the cluster.
+    pub address: String,
+    pub port: u16,
+    /// Data directory for the local store and the replica.
+    pub data_dir: PathBuf,
+    pub capacity_memory_bytes: u64,
+    /// CPU weight to report when the benchmark is skipped or fails.
+    pub capacity_cpu_weight: u32,
+    /// Calibrate the CPU weight with a benchmark at startup.
+    pub cpu_benchmark: bool,
+    /// Metrics snapshot interval in seconds.
+    pub metrics_interval: u64,
+    /// Pre-resolve imports of each distinct artifact once.
+    pub pre_instantiate: bool,
+    /// DNS export config file, if any.
+    pub dns_export: Option<PathBuf>,
+    /// Service catalog bridge config file, if any.
+    pub discovery: Option<PathBuf>,
+    pub metrics_sinks: crate::MetricsSinkArgs,
+    pub memory: crate::MemoryArgs,
+    pub signature_policy: warp_runtime::SignaturePolicy,
+}
 
 /// Run the agent node.
-pub async fn run_agent(
-    control_plane_addr: String,
-    address: String,
-    port: u16,
-    data_dir: PathBuf,
-    capacity_memory_bytes: u64,
-    capacity_cpu_weight: u32,
-    metrics_interval: u64,
-) -> anyhow::Result<()> {
+pub async fn run_agent(config: AgentModeConfig) -> anyhow::Result<()> {
+    let AgentModeConfig {
+        control_plane_addr,
+        address,
+        port,
+        data_dir,
+        capacity_memory_bytes,
+        capacity_cpu_weight,
+        cpu_benchmark,
+        metrics_interval,
+        pre_instantiate,
+        dns_export,
+        discovery,
+        metrics_sinks,
+        memory,
+        signature_policy,
+    } = config;
     info!("WarpGrid daemon starting in agent mode");
     std::fs::create_dir_all(&data_dir)?;
 
+    // Validate the DNS export, catalog bridge, and metrics sinks before
+    // joining anything.
+    let dns_exporter = dns_export
+        .map(|path| DnsExporter::new(DnsExportConfig::load(&path)?))
+        .transpose()?;
+    let discovery_bridge = discovery
+        .map(|path| DiscoveryBridge::new(DiscoveryConfig::load(&path)?))
+        .transpose()?;
+    let metrics_sinks = metrics_sinks.load(&data_dir)?;
+
     // ── Local state store ────────────────────────────────────────
     let db_path = data_dir.join("warpgrid-agent.redb");
     let state = warpgrid_state::StateStore::open(&db_path)?;
     info!(path = ?db_path, "local state store opened");
+    warpd::migrate_state(&state)?;
+
+    let replica_path = data_dir.join("warpgrid-replica.redb");
+    let replica = warpgrid_state::ReadReplica::open(&replica_path)?;
+    // Old-format replica records are readable before the control plane's
+    // migrated copies arrive.
+    warpd::migrate_state(replica.store())?;
+    info!(path = ?replica_path, revision = replica.revision()?, "state replica opened");
 
     // ── Wasm runtime ─────────────────────────────────────────────
-    let runtime = Arc::new(warp_runtime::Runtime::new(
-        warp_runtime::ShimConfig::default(),
-    )?);
+    let runtime = Arc::new(
+        warp_runtime::Runtime::new_metered(
+            warp_runtime::ShimConfig::default(),
+            warp_runtime::usage::DEFAULT_EPOCH_TICK,
+        )?
+        .with_pre_instantiation(pre_instantiate)
+        .with_signature_policy(signature_policy),
+    );
     info!("wasm runtime initialized");
 
     // ── Local scheduler (Standalone mode for executing local work) ─
-    let _scheduler = warpgrid_scheduler::Scheduler::new(
-        runtime.clone(),
-        state.clone(),
-        "agent".to_string(),
+    let scheduler = Arc::new(
+        warpgrid_scheduler::Scheduler::new(runtime.clone(), state.clone(), "agent".to_string())
+            .with_overcommit(memory.memory_overcommit),
     );
     info!("local scheduler initialized");
 
@@ -63,13 +168,74 @@ pub async fn run_agent(
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let metrics_shutdown = shutdown_rx.clone();
     let heartbeat_shutdown = shutdown_rx.clone();
+    let mut proxy_shutdown = shutdown_rx.clone();
 
-    // Start metrics collector.
+    // Start metrics collector and its sinks.
+    let sink_handles = metrics_sinks.spawn(&metrics, shutdown_rx.clone());
     let metrics_handle = tokio::spawn(async move {
         metrics.run(metrics_shutdown).await;
     });
 
+    // Evict idle instances under memory pressure; heartbeats report it.
+    let (pressure_handle, pressure) =
+        memory.spawn_pressure_monitor(scheduler.clone(), shutdown_rx.clone());
+
+    // ── Service mesh view (reads the replica, never the control plane) ─
+    let dns = DnsResolver::default();
+    let dns_export_handle = dns_exporter.map(|exporter| {
+        info!("exporting service DNS records");
+        tokio::spawn(exporter.run(dns.clone(), shutdown_rx.clone()))
+    });
+    let discovery_handle = discovery_bridge.map(|bridge| {
+        info!("bridging services with external catalog");
+        tokio::spawn(bridge.run(dns.clone(), shutdown_rx.clone()))
+    });
+    let proxy_replica = replica.clone();
+    let flags = runtime.engine().flags().clone();
+    let bundles = runtime.engine().bundles().clone();
+    // Every sync applied to the replica records its revision.
+    let mut replica_changes = Box::pin(replica.store().watch(warpgrid_state::tables::REPLICA_META));
+    let proxy_handle = tokio::spawn(async move {
+        let sync = ProxySync::new(Router::new(), dns);
+        let mut applied = None;
+        loop {
+            // Only rebuild when the replica has moved.
+            match proxy_replica.revision() {
+                Ok(revision) if applied != Some(revision) => {
+                    // Feature flags ride the same replica deltas.
+                    match proxy_replica.store().list_flags() {
+                        Ok(sets) => flags.replace_all(
+                            sets.into_iter().map(|flags| (flags.deployment_id, flags.set)),
+                        ),
+                        Err(e) => tracing::warn!(error = %e, "flag sync from replica failed"),
+                    }
+                    // So do config bundles: the control plane activates one
+                    // only after every node's replica holds it.
+                    match proxy_replica.store().active_config_bundles() {
+                        Ok(active) => bundles.replace_all(active),
+                        Err(e) => tracing::warn!(error = %e, "config bundle sync from replica failed"),
+                    }
+                    match sync.sync(proxy_replica.store()) {
+                        Ok(_) => applied = Some(revision),
+                        Err(e) => tracing::warn!(error = %e, "proxy sync from replica failed"),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!(error = %e, "failed to read replica revision"),
+            }
+            tokio::select! {
+                _ = warpgrid_state::watch::changed(&mut replica_changes, PROXY_RESYNC_INTERVAL) => {}
+                _ = proxy_shutdown.changed() => break,
+            }
+        }
+    });
+
     // ── Join cluster ─────────────────────────────────────────────
+    let calibrated_cpu_weight = if cpu_benchmark {
+        calibrate_cpu_weight().await
+    } else {
+        None
+    };
     let agent_config = AgentConfig {
         control_plane_addr,
         address: address.clone(),
@@ -77,9 +243,12 @@ pub async fn run_agent(
         labels: HashMap::new(),
         capacity_memory_bytes,
         capacity_cpu_weight,
+        calibrated_cpu_weight,
     };
 
-    let mut agent = NodeAgent::new(agent_config);
+    let mut agent = NodeAgent::new(agent_config)
+        .with_replica(replica)
+        .with_pressure(pressure);
     let node_id = agent.join().await?;
     info!(%node_id, "joined cluster");
 
@@ -99,10 +268,22 @@ pub async fn run_agent(
         .expect("failed to install CTRL+C handler");
     info!("shutdown signal received");
     let _ = shutdown_tx.send(true);
+    scheduler.drain().await;
 
     // Wait for background tasks.
     let _ = heartbeat_handle.await;
     let _ = metrics_handle.await;
+    for handle in sink_handles {
+        let _ = handle.await;
+    }
+    let _ = pressure_handle.await;
+    let _ = proxy_handle.await;
+    if let Some(handle) = dns_export_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = discovery_handle {
+        let _ = handle.await;
+    }
 
     info!("agent stopped");
     Ok(())
@@ -0,0 +1,169 @@
+//! Standalone app serving.
+//!
+//! Keeps the ingress handlers in step with the state store: every
+//! HTTP-triggered deployment with a local artifact is compiled and its
+//! component registered as the handler for its route. A deployment is
+//! reloaded when its spec or one of the secrets it mounts changes, and
+//! unregistered when it is deleted or paused. Each request's usage event
+//! goes to the loader's usage sink.
+//! Feature flags are copied into the engine's flag registry on every sync,
+//! so running instances see flag changes without a reload. Staged config
+//! bundles are activated right away (a standalone node is the only node)
+//! and swapped into the engine's bundle registry the same way.
+//!
+//! Only `file://` (and bare path) sources are loaded here; other schemes
+//! keep their route but answer 503 until something else serves them.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::BoxFuture;
+use tracing::{info, warn};
+use warp_core::SourceUri;
+use warp_runtime::Runtime;
+use warpgrid_state::{DeploymentSpec, MountedSecrets, StateStore, TriggerConfig};
+use warpgrid_trigger::{IngressRouter, ResponseLimits, UsageSink};
+
+/// A deployment the loader has acted on.
+struct Loaded {
+    name: String,
+    /// `updated_at` of the spec and of its newest mounted secret.
+    version: (u64, u64),
+}
+
+/// Outcome of the last [`AppLoader::sync`], reported as the `runtime`
+/// health check: served apps only follow the state store while syncs
+/// succeed.
+#[derive(Default)]
+pub struct SyncHealth(Mutex<Option<String>>);
+
+impl SyncHealth {
+    pub fn record(&self, result: &anyhow::Result<()>) {
+        *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            result.as_ref().err().map(|e| format!("app sync failed: {e:#}"));
+    }
+}
+
+impl warpgrid_api::HealthCheck for SyncHealth {
+    fn name(&self) -> &'static str {
+        "runtime"
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        let error = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        Box::pin(async move { error.map_or(Ok(()), Err) })
+    }
+}
+
+pub struct AppLoader {
+    runtime: Arc<Runtime>,
+    ingress: IngressRouter,
+    limits: ResponseLimits,
+    usage: Arc<dyn UsageSink>,
+    loaded: HashMap<String, Loaded>,
+}
+
+impl AppLoader {
+    pub fn new(
+        runtime: Arc<Runtime>,
+        ingress: IngressRouter,
+        limits: ResponseLimits,
+        usage: Arc<dyn UsageSink>,
+    ) -> Self {
+        Self {
+            runtime,
+            ingress,
+            limits,
+            usage,
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// Load new and changed deployments and drop deleted ones.
+    pub async fn sync(&mut self, state: &StateStore) -> anyhow::Result<()> {
+        let flags = state.list_flags()?;
+        self.runtime
+            .engine()
+            .flags()
+            .replace_all(flags.into_iter().map(|flags| (flags.deployment_id, flags.set)));
+        state.promote_staged_configs(&HashMap::new(), crate::standalone::epoch_secs())?;
+        self.runtime.engine().bundles().replace_all(state.active_config_bundles()?);
+
+        let specs: Vec<DeploymentSpec> = state
+            .list_deployments()?
+            .into_iter()
+            .filter(|spec| matches!(spec.trigger, TriggerConfig::Http { .. }))
+            // Paused deployments are unloaded like deleted ones.
+            .filter(|spec| !spec.paused)
+            .collect();
+
+        let live: Vec<&str> = specs.iter().map(|spec| spec.id.as_str()).collect();
+        let gone: Vec<String> = self
+            .loaded
+            .keys()
+            .filter(|id| !live.contains(&id.as_str()))
+            .cloned()
+            .collect();
+        for id in gone {
+            let loaded = self.loaded.remove(&id).expect("listed above");
+            self.ingress.unregister(&id);
+            warpgrid_metrics::route_usage::route_usage().forget(&id);
+            warpgrid_metrics::guest_logs::guest_logs().forget(&id);
+            self.runtime.unload_module(&loaded.name).await;
+            info!(deployment = %id, "app unloaded");
+        }
+
+        for spec in &specs {
+            let secrets = state.mount_secrets(spec);
+            let version = (spec.updated_at, secrets.as_ref().map_or(0, |secrets| secrets.updated_at));
+            if self
+                .loaded
+                .get(&spec.id)
+                .is_some_and(|loaded| loaded.version == version)
+            {
+                continue;
+            }
+            // Recorded even on failure so a broken spec is reported once
+            // per revision rather than on every sync.
+            self.loaded.insert(
+                spec.id.clone(),
+                Loaded {
+                    name: spec.name.clone(),
+                    version,
+                },
+            );
+            let loaded = match secrets {
+                Ok(secrets) => self.load(spec, secrets).await,
+                Err(e) => Err(e.into()),
+            };
+            match loaded {
+                Ok(true) => info!(deployment = %spec.id, source = %spec.source, "app loaded"),
+                Ok(false) => {}
+                Err(e) => {
+                    self.ingress.unregister(&spec.id);
+                    warn!(deployment = %spec.id, error = %format!("{e:#}"), "app failed to load");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile `spec`'s artifact and register its handler. Returns `false`
+    /// when the source is not a local file.
+    async fn load(&self, spec: &DeploymentSpec, secrets: MountedSecrets) -> anyhow::Result<bool> {
+        let SourceUri::File { path } = SourceUri::parse(&spec.source)? else {
+            return Ok(false);
+        };
+        let module = self.runtime.load_module_from_file(&spec.name, &path).await?;
+        let handler = warpgrid_trigger::component::component_handler(
+            self.runtime.engine(),
+            module.component(),
+            spec,
+            secrets,
+            self.limits,
+            Some(self.usage.clone()),
+        )?;
+        self.ingress.register(&spec.id, handler);
+        Ok(true)
+    }
+}
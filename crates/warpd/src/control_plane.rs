@@ -6,37 +6,113 @@
 //! 2. Bootstraps (or rejoins) a Raft cluster
 //! 3. Serves both Raft RPCs and cluster membership RPCs over gRPC
 //! 4. Serves the REST API over HTTP (separate port)
-//! 5. Runs background tasks (metrics, autoscaler, dead node reaper)
+//!
+//! Listener addresses, TLS, and auth come from the resolved
+//! [`Planes`](crate::planes::Planes).
+//! 5. Runs background tasks (metrics, autoscaler, dead node reaper,
+//!    config bundle barrier)
+//!
+//! `/readyz` reports the control plane ready once a Raft leader is known.
 
 use std::collections::BTreeMap;
-use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use futures_util::future::BoxFuture;
 use openraft::BasicNode;
 use tokio::sync::watch;
-use tracing::info;
+use tracing::{info, warn};
 
 use warpgrid_cluster::MembershipManager;
-use warpgrid_raft::{LogStore, NetworkFactory, NodeIdMap, RaftGrpcServer, StateMachine};
+use warpgrid_raft::{LogStore, NetworkFactory, NodeIdMap, RaftGrpcServer, StateMachine, WarpGridRaft};
+
+use crate::planes::Planes;
+
+/// How often staged config bundles are checked against node replicas.
+const CONFIG_BARRIER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The `raft` health check: ready once a Raft leader is known.
+struct RaftLeader(Arc<WarpGridRaft>);
+
+impl warpgrid_api::HealthCheck for RaftLeader {
+    fn name(&self) -> &'static str {
+        "raft"
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            match self.0.current_leader().await {
+                Some(_) => Ok(()),
+                None => Err("no raft leader known".to_string()),
+            }
+        })
+    }
+}
+
+/// Everything control-plane mode runs with.
+pub struct ControlPlaneConfig {
+    /// Must include the cluster plane.
+    pub planes: Planes,
+    /// Data directory for the Raft log and the local state store.
+    pub data_dir: PathBuf,
+    /// Shared state backend URL; `None` uses a redb file in `data_dir`.
+    pub state_url: Option<String>,
+    /// Stable name of this node in the Raft cluster.
+    pub raft_node_id: String,
+    /// Metrics snapshot interval in seconds.
+    pub metrics_interval: u64,
+    /// Autoscaler check interval in seconds.
+    pub autoscale_interval: u64,
+    /// Verify every state record at startup, quarantining corrupt ones.
+    pub verify_state: bool,
+    /// Require an API token on `/api/v1` (see [`warpd::require_api_tokens`]).
+    pub api_tokens: bool,
+    /// Per-token and per-IP limits on the management API.
+    pub api_rate_limits: warpgrid_api::ratelimit::RateLimits,
+    pub metrics_sinks: crate::MetricsSinkArgs,
+}
 
 /// Run the control plane node.
-pub async fn run_control_plane(
-    api_port: u16,
-    grpc_port: u16,
-    data_dir: PathBuf,
-    raft_node_id: String,
-    metrics_interval: u64,
-    autoscale_interval: u64,
-) -> anyhow::Result<()> {
+pub async fn run_control_plane(config: ControlPlaneConfig) -> anyhow::Result<()> {
+    let ControlPlaneConfig {
+        planes,
+        data_dir,
+        state_url,
+        raft_node_id,
+        metrics_interval,
+        autoscale_interval,
+        verify_state,
+        api_tokens,
+        api_rate_limits,
+        metrics_sinks,
+    } = config;
     info!("WarpGrid daemon starting in control-plane mode");
     std::fs::create_dir_all(&data_dir)?;
+    let metrics_sinks = metrics_sinks.load(&data_dir)?;
 
     // ── State store (application data) ───────────────────────────
-    let app_db_path = data_dir.join("warpgrid.redb");
-    let state = warpgrid_state::StateStore::open(&app_db_path)?;
-    info!(path = ?app_db_path, "application state store opened");
+    let state = match state_url {
+        Some(url) => warpgrid_state::StateStore::connect(&url)?,
+        None => {
+            let app_db_path = data_dir.join("warpgrid.redb");
+            let state = warpgrid_state::StateStore::open(&app_db_path)?;
+            info!(path = ?app_db_path, "application state store opened");
+            state
+        }
+    };
+    let state = state.with_secrets_key(warpd::load_secrets_key(&data_dir)?);
+    warpd::migrate_state(&state)?;
+    info!(backend = state.backend_name(), "application state store ready");
+    if verify_state {
+        let report = state.verify_integrity()?;
+        if !report.quarantined.is_empty() {
+            warn!(
+                quarantined = report.quarantined.len(),
+                "corrupt state records quarantined; see /api/v1/admin/state-integrity"
+            );
+        }
+    }
 
     // ── Raft storage (separate redb for Raft log + state machine) ─
     let raft_db_path = data_dir.join("raft.redb");
@@ -76,7 +152,12 @@ pub async fn run_control_plane(
     info!("raft instance created");
 
     // Bootstrap as single-node cluster if fresh.
-    let grpc_addr = format!("0.0.0.0:{grpc_port}");
+    let grpc_addr_parsed = planes
+        .cluster
+        .as_ref()
+        .expect("control plane serves the cluster plane")
+        .addr();
+    let grpc_addr = grpc_addr_parsed.to_string();
     let mut members = BTreeMap::new();
     members.insert(my_raft_id, BasicNode::new(&grpc_addr));
 
@@ -93,7 +174,6 @@ pub async fn run_control_plane(
     let raft_grpc = RaftGrpcServer::new(Arc::clone(&raft));
     let cluster_grpc = warpgrid_cluster::ClusterServer::new(Arc::clone(&membership));
 
-    let grpc_addr_parsed: SocketAddr = grpc_addr.parse()?;
     info!(%grpc_addr_parsed, "gRPC server starting (raft + cluster)");
 
     let grpc_handle = tokio::spawn(async move {
@@ -112,12 +192,14 @@ pub async fn run_control_plane(
     let metrics_shutdown = shutdown_rx.clone();
     let autoscale_shutdown = shutdown_rx.clone();
     let reaper_shutdown = shutdown_rx.clone();
+    let mut bundle_shutdown = shutdown_rx.clone();
 
-    // Metrics collector.
+    // Metrics collector and its sinks.
     let metrics = warpgrid_metrics::MetricsCollector::new(
         state.clone(),
         Duration::from_secs(metrics_interval),
     );
+    let sink_handles = metrics_sinks.spawn(&metrics, shutdown_rx.clone());
     let metrics_handle = tokio::spawn(async move {
         metrics.run(metrics_shutdown).await;
     });
@@ -153,14 +235,61 @@ pub async fn run_control_plane(
         }
     });
 
-    // ── REST API server ──────────────────────────────────────────
-    let router = warpgrid_api::build_router(state);
-    let api_addr = SocketAddr::from(([0, 0, 0, 0], api_port));
+    // Config bundle barrier: activate staged bundles once every node
+    // running the deployment has replicated them.
+    let bundle_membership = Arc::clone(&membership);
+    let bundle_handle = tokio::spawn(async move {
+        loop {
+            match bundle_membership.promote_config_bundles() {
+                Ok(promoted) if !promoted.is_empty() => {
+                    info!(deployments = ?promoted, "config bundles activated");
+                }
+                Err(e) => warn!(error = %e, "config bundle promotion error"),
+                _ => {}
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(CONFIG_BARRIER_INTERVAL) => {}
+                _ = bundle_shutdown.changed() => break,
+            }
+        }
+    });
 
-    info!(%api_addr, "API server starting");
-    let listener = tokio::net::TcpListener::bind(api_addr).await?;
+    // Outbound webhooks.
+    let webhooks_handle = warpd::webhooks::spawn(state.clone(), shutdown_rx.clone());
 
-    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+    // Usage retention.
+    let usage_prune_handle = warpd::spawn_usage_pruning(state.clone(), shutdown_rx.clone());
+
+    // ── Metrics listener (when separate from the API) ────────────
+    let rollouts: warpgrid_api::RolloutStore = Default::default();
+    let metrics_listener_handle = planes.metrics.clone().map(|plane| {
+        plane.spawn_router(
+            warpgrid_api::build_metrics_router(state.clone()),
+            shutdown_rx.clone(),
+        )
+    });
+
+    // ── REST API server ──────────────────────────────────────────
+    // Agents run guests with the default shims and response limits.
+    let capabilities = warpd::capabilities(
+        &warp_runtime::ShimConfig::default(),
+        &warpgrid_trigger::ResponseLimits::default(),
+        false,
+    );
+    let mut router = match planes.metrics {
+        Some(_) => warpgrid_api::build_management_router(state.clone(), rollouts),
+        None => warpgrid_api::build_router_with_rollouts(state.clone(), rollouts),
+    }
+    .layer(axum::Extension(Arc::new(capabilities)))
+    .layer(axum::Extension(warpd::artifact_store(&data_dir, warp_runtime::SignaturePolicy::disabled())?))
+    .layer(axum::Extension(warpgrid_api::HealthChecks(vec![Arc::new(RaftLeader(Arc::clone(&raft)))])));
+    if api_tokens {
+        router = warpd::require_api_tokens(router, &state, &data_dir)?;
+    }
+    let router = warpd::rate_limit_api(router, api_rate_limits, &state);
+
+    info!(api_addr = %planes.management.addr(), "API server starting");
+    let server = planes.management.serve_router(router, async move {
         tokio::signal::ctrl_c()
             .await
             .expect("failed to install CTRL+C handler");
@@ -173,8 +302,17 @@ pub async fn run_control_plane(
     // Clean up.
     grpc_handle.abort();
     let _ = metrics_handle.await;
+    for handle in sink_handles {
+        let _ = handle.await;
+    }
     let _ = autoscale_handle.await;
     let _ = reaper_handle.await;
+    let _ = bundle_handle.await;
+    let _ = webhooks_handle.await;
+    let _ = usage_prune_handle.await;
+    if let Some(handle) = metrics_listener_handle {
+        let _ = handle.await;
+    }
 
     info!("control plane stopped");
     Ok(())
@@ -0,0 +1,122 @@
+//! Direct export calls for `POST /deployments/{id}/exec`.
+//!
+//! Calls run on a small instance pool per deployment, separate from the
+//! ingress handlers so a debugging session never holds up live traffic.
+//! Pools are built from the component the app loader compiled and are
+//! replaced when the deployment's spec changes. An instance whose call
+//! failed is discarded rather than returned, since a trap can leave its
+//! store mid-call.
+//!
+//! Calls are held to the deployment's execution budget and metered like
+//! requests: each sends a usage event, with instance `exec`, to the usage
+//! sink.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::Mutex;
+use warp_runtime::{InstancePool, PoolConfig, Runtime};
+use warpgrid_api::{ExecError, ExportInvoker};
+use warpgrid_state::{DeploymentSpec, StateStore};
+use warpgrid_trigger::UsageSink;
+
+/// Most instances a deployment's exec pool creates.
+const MAX_EXEC_INSTANCES: u32 = 2;
+
+pub struct ExecPools {
+    runtime: Arc<Runtime>,
+    state: StateStore,
+    usage: Arc<dyn UsageSink>,
+    /// deployment id → (`updated_at` of the spec it was built for, pool).
+    pools: Mutex<HashMap<String, (u64, Arc<InstancePool>)>>,
+}
+
+impl ExecPools {
+    pub fn new(runtime: Arc<Runtime>, state: StateStore, usage: Arc<dyn UsageSink>) -> Self {
+        Self {
+            runtime,
+            state,
+            usage,
+            pools: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The deployment's pool and execution budget.
+    async fn pool(
+        &self,
+        deployment_id: &str,
+    ) -> Result<(Arc<InstancePool>, Option<Duration>), ExecError> {
+        let spec = self
+            .state
+            .get_deployment(deployment_id)
+            .map_err(|e| ExecError::Failed(e.to_string()))?
+            .ok_or(ExecError::NotLoaded)?;
+        let budget = spec
+            .resources
+            .execution_budget_ms
+            .map(Duration::from_millis);
+        let mut pools = self.pools.lock().await;
+        if let Some((version, pool)) = pools.get(deployment_id)
+            && *version == spec.updated_at
+        {
+            return Ok((pool.clone(), budget));
+        }
+        let module = self
+            .runtime
+            .get_module(&spec.name)
+            .await
+            .ok_or(ExecError::NotLoaded)?;
+        let pool = Arc::new(self.runtime.create_pool(module, self.pool_config(&spec)));
+        pools.insert(deployment_id.to_string(), (spec.updated_at, pool.clone()));
+        Ok((pool, budget))
+    }
+
+    fn pool_config(&self, spec: &DeploymentSpec) -> PoolConfig {
+        PoolConfig {
+            min_instances: 0,
+            max_instances: MAX_EXEC_INSTANCES,
+            memory_limit: spec.resources.memory_bytes as usize,
+            flags: self.runtime.engine().flags_host(&spec.id),
+            config: Some(self.runtime.engine().bundles().handle(&spec.id)),
+            ..PoolConfig::default()
+        }
+    }
+}
+
+impl ExportInvoker for ExecPools {
+    fn invoke<'a>(
+        &'a self,
+        deployment_id: &'a str,
+        export: &'a str,
+        args: &'a [Value],
+    ) -> BoxFuture<'a, Result<Value, ExecError>> {
+        Box::pin(async move {
+            let (pool, budget) = self.pool(deployment_id).await?;
+            let mut instance = pool
+                .acquire()
+                .await
+                .map_err(|e| ExecError::Failed(format!("{e:#}")))?
+                .ok_or(ExecError::Busy)?;
+            let started = SystemTime::now();
+            instance.begin_request(budget);
+            let result = instance.call_export(export, args).await;
+            if let Some(sample) = instance.finish_request() {
+                self.usage
+                    .record_sample(deployment_id, "exec", started, &sample);
+            }
+            match result {
+                Ok(result) => {
+                    pool.release(instance).await;
+                    Ok(result)
+                }
+                Err(e) => {
+                    pool.discard(instance).await;
+                    Err(ExecError::Failed(format!("{e:#}")))
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,478 @@
+//! warpd as a library.
+//!
+//! The `warpd` binary parses the command line and runs one of its modes.
+//! Standalone mode is also exposed here so it can run inside another
+//! process — `warpgrid-testkit` boots it in-process for black-box tests.
+
+mod apps;
+mod exec;
+pub mod planes;
+mod portforward;
+pub mod standalone;
+pub mod webhooks;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+/// How often node memory is checked for pressure.
+const MEMORY_PRESSURE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Memory accounting for pooled instances on this node.
+#[derive(clap::Args, Clone, Debug)]
+pub struct MemoryArgs {
+    /// Per-instance memory reservation: `reserve` (the full limit) or
+    /// `high-water:<pct>` (observed peak plus headroom, capped at the limit).
+    #[arg(long, default_value = "reserve")]
+    pub memory_overcommit: warp_runtime::OvercommitPolicy,
+
+    /// Shed idle instances above each deployment's minimum when available
+    /// memory drops below this percentage of physical memory.
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub memory_pressure_percent: u32,
+
+    /// Below this percentage, shed idle instances down to each
+    /// deployment's disruption budget (`min_available`).
+    #[arg(long, default_value = "5", value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub memory_pressure_hard_percent: u32,
+}
+
+impl Default for MemoryArgs {
+    /// The command-line defaults.
+    fn default() -> Self {
+        Self {
+            memory_overcommit: warp_runtime::OvercommitPolicy::Reserve,
+            memory_pressure_percent: 10,
+            memory_pressure_hard_percent: 5,
+        }
+    }
+}
+
+impl MemoryArgs {
+    /// Start evicting idle instances of `scheduler` under memory pressure.
+    ///
+    /// The returned receiver tracks whether the node is under pressure.
+    pub fn spawn_pressure_monitor(
+        &self,
+        scheduler: Arc<warpgrid_scheduler::Scheduler>,
+        shutdown: watch::Receiver<bool>,
+    ) -> (tokio::task::JoinHandle<()>, watch::Receiver<bool>) {
+        let threshold = warp_runtime::PressureThreshold {
+            min_available_percent: self.memory_pressure_percent,
+            hard_available_percent: self.memory_pressure_hard_percent,
+        };
+        let (pressure_tx, pressure_rx) = watch::channel(false);
+        let handle = tokio::spawn(async move {
+            scheduler
+                .run_pressure_monitor(threshold, MEMORY_PRESSURE_INTERVAL, pressure_tx, shutdown)
+                .await;
+        });
+        (handle, pressure_rx)
+    }
+}
+
+/// Limits on what a guest may send back through the HTTP trigger, and how
+/// its body is streamed to slow clients.
+#[derive(clap::Args, Clone, Debug)]
+pub struct ResponseLimitArgs {
+    /// Maximum number of response headers.
+    #[arg(long, default_value = "100")]
+    pub max_response_headers: usize,
+
+    /// Maximum total size of response header names and values, in bytes.
+    #[arg(long, default_value = "65536")]
+    pub max_response_header_bytes: usize,
+
+    /// Maximum response body size, in bytes.
+    #[arg(long, default_value = "33554432")]
+    pub max_response_body_bytes: u64,
+
+    /// Body frames buffered per response before a slow client makes the
+    /// guest wait.
+    #[arg(long, default_value = "16")]
+    pub response_stream_buffer: usize,
+
+    /// Abort a response whose client has read nothing for this many seconds.
+    #[arg(long, default_value = "30")]
+    pub response_stall_timeout: u64,
+}
+
+impl Default for ResponseLimitArgs {
+    /// The command-line defaults.
+    fn default() -> Self {
+        let limits = warpgrid_trigger::ResponseLimits::default();
+        Self {
+            max_response_headers: limits.max_header_count,
+            max_response_header_bytes: limits.max_header_bytes,
+            max_response_body_bytes: limits.max_body_bytes,
+            response_stream_buffer: limits.stream_buffer_frames,
+            response_stall_timeout: limits.stall_timeout.as_secs(),
+        }
+    }
+}
+
+impl ResponseLimitArgs {
+    pub fn limits(&self) -> warpgrid_trigger::ResponseLimits {
+        warpgrid_trigger::ResponseLimits {
+            max_header_count: self.max_response_headers,
+            max_header_bytes: self.max_response_header_bytes,
+            max_body_bytes: self.max_response_body_bytes,
+            stream_buffer_frames: self.response_stream_buffer,
+            stall_timeout: Duration::from_secs(self.response_stall_timeout),
+        }
+    }
+}
+
+/// Per-token and per-client-IP request limits on the management API.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ApiRateLimitArgs {
+    /// Sustained requests per second allowed per API token (0: unlimited).
+    #[arg(long, default_value = "0")]
+    pub api_token_rate: f64,
+
+    /// Requests an API token may make at once before the rate applies.
+    #[arg(long, default_value = "0")]
+    pub api_token_burst: u32,
+
+    /// Sustained requests per second allowed per client IP (0: unlimited).
+    #[arg(long, default_value = "0")]
+    pub api_ip_rate: f64,
+
+    /// Requests a client IP may make at once before the rate applies.
+    #[arg(long, default_value = "0")]
+    pub api_ip_burst: u32,
+}
+
+impl ApiRateLimitArgs {
+    /// A burst left at 0 defaults to one second's worth of requests.
+    pub fn limits(&self) -> warpgrid_api::ratelimit::RateLimits {
+        let limit = |per_second: f64, burst: u32| {
+            (per_second > 0.0).then(|| warpgrid_api::ratelimit::RateLimit {
+                burst: if burst == 0 { per_second.ceil() as u32 } else { burst },
+                per_second,
+            })
+        };
+        warpgrid_api::ratelimit::RateLimits {
+            per_token: limit(self.api_token_rate, self.api_token_burst),
+            per_ip: limit(self.api_ip_rate, self.api_ip_burst),
+        }
+    }
+}
+
+/// The `/api/v1/capabilities` document for a node running guests with
+/// `shims` and `limits`.
+pub fn capabilities(
+    shims: &warp_runtime::ShimConfig,
+    limits: &warpgrid_trigger::ResponseLimits,
+    cpu_metering: bool,
+) -> warpgrid_api::Capabilities {
+    use warp_runtime::{SHIM_PACKAGE_VERSION, SHIM_WORLDS};
+
+    let mut worlds = vec![warpgrid_trigger::component::HTTP_WORLD.to_string()];
+    worlds.extend(SHIM_WORLDS.iter().map(|world| format!("warpgrid:shim/{world}@{SHIM_PACKAGE_VERSION}")));
+    warpgrid_api::Capabilities {
+        worlds,
+        shims: shims
+            .interfaces()
+            .into_iter()
+            .map(|(name, enabled)| warpgrid_api::capabilities::ShimInterface {
+                name: format!("warpgrid:shim/{name}"),
+                version: SHIM_PACKAGE_VERSION.to_string(),
+                enabled,
+            })
+            .collect(),
+        features: warpgrid_api::capabilities::Features {
+            response_streaming: true,
+            websockets: false,
+            wasi_nn: false,
+            cpu_metering,
+            guest_logs: true,
+            config_bundles: true,
+        },
+        limits: warpgrid_api::capabilities::Limits {
+            max_response_body_bytes: Some(limits.max_body_bytes),
+            max_response_headers: Some(limits.max_header_count),
+            max_response_header_bytes: Some(limits.max_header_bytes),
+            max_instance_memory_bytes: None,
+        },
+        ..Default::default()
+    }
+}
+
+/// File in the data directory the bootstrap API token is written to.
+pub const BOOTSTRAP_TOKEN_FILE: &str = "api-token";
+
+/// Require an API token on every `/api/v1` route of `router`.
+///
+/// With no tokens stored yet, nobody could call the API to create one, so
+/// a token named `bootstrap` is created and written to
+/// `<data_dir>/api-token` (mode 0600). Revoke it once real tokens exist.
+pub fn require_api_tokens(
+    router: axum::Router,
+    state: &warpgrid_state::StateStore,
+    data_dir: &Path,
+) -> anyhow::Result<axum::Router> {
+    if state.list_api_tokens()?.is_empty() {
+        let created = warpgrid_api::auth::create_token(state, "bootstrap").map_err(anyhow::Error::msg)?;
+        let path = data_dir.join(BOOTSTRAP_TOKEN_FILE);
+        write_private(&path, &format!("{}\n", created.token))?;
+        tracing::warn!(path = %path.display(), id = %created.info.id, "no API tokens; wrote a bootstrap token");
+    }
+    Ok(warpgrid_api::auth::require_tokens(router, state.clone()))
+}
+
+/// Layer `limits` on `router`, unless every limit is off. Only tokens in
+/// `state` get a bucket of their own.
+pub fn rate_limit_api(
+    router: axum::Router,
+    limits: warpgrid_api::ratelimit::RateLimits,
+    state: &warpgrid_state::StateStore,
+) -> axum::Router {
+    if limits == warpgrid_api::ratelimit::RateLimits::default() {
+        return router;
+    }
+    tracing::info!(per_token = ?limits.per_token, per_ip = ?limits.per_ip, "API rate limits on");
+    warpgrid_api::ratelimit::rate_limit(router, limits, state.clone())
+}
+
+/// Directory in the data directory uploaded artifacts are kept in.
+pub const ARTIFACTS_DIR: &str = "artifacts";
+
+/// The store `POST /api/v1/artifacts` writes to, in
+/// `<data_dir>/artifacts`. Uploads are checked against `policy` as the
+/// runtime will check them on load, so an enforcing node refuses an
+/// artifact it could never run.
+pub fn artifact_store(
+    data_dir: &Path,
+    policy: warp_runtime::SignaturePolicy,
+) -> anyhow::Result<warpgrid_api::ArtifactStore> {
+    let store = warpgrid_api::ArtifactStore::open(&data_dir.join(ARTIFACTS_DIR))?;
+    Ok(store.with_verifier(Arc::new(UploadPolicy(policy))))
+}
+
+struct UploadPolicy(warp_runtime::SignaturePolicy);
+
+impl warpgrid_api::ArtifactVerifier for UploadPolicy {
+    fn verify(&self, path: &Path) -> Result<(), String> {
+        self.0.verify_file("upload", path).map_err(|e| format!("{e:#}"))
+    }
+}
+
+/// File in the data directory holding the key secret values are
+/// encrypted with.
+pub const SECRETS_KEY_FILE: &str = "secrets.key";
+
+/// Read the secrets key from `<data_dir>/secrets.key`, creating it (mode
+/// 0600) on first start. Control planes sharing a Postgres state store
+/// must share this file. Agents do not need it: they replicate secrets
+/// sealed and never open them.
+pub fn load_secrets_key(data_dir: &Path) -> anyhow::Result<warpgrid_state::SecretsKey> {
+    let path = data_dir.join(SECRETS_KEY_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(hex) => Ok(warpgrid_state::SecretsKey::from_hex(&hex)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = warpgrid_state::SecretsKey::generate()?;
+            write_private(&path, &format!("{}\n", key.to_hex()))?;
+            tracing::info!(path = %path.display(), "generated a secrets key");
+            Ok(key)
+        }
+        Err(e) => Err(anyhow::anyhow!("failed to read {}: {e}", path.display())),
+    }
+}
+
+/// Appends the usage event of each request served on `node_id` to the
+/// state store's usage stream.
+pub struct StoreUsageSink {
+    pub state: warpgrid_state::StateStore,
+    pub node_id: String,
+}
+
+impl warpgrid_trigger::UsageSink for StoreUsageSink {
+    fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    fn record(&self, event: warpgrid_state::UsageEvent) {
+        let state = self.state.clone();
+        // One write per request; keep it off the async workers.
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = state.record_usage(&event) {
+                tracing::warn!(event_id = %event.event_id, error = %e, "failed to record usage event");
+            }
+        });
+    }
+}
+
+/// How often usage past its retention is pruned.
+const USAGE_PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Prune usage past [`warpgrid_state::USAGE_RETENTION_SECS`] now and every
+/// [`USAGE_PRUNE_INTERVAL`], until `shutdown` changes.
+pub fn spawn_usage_pruning(
+    state: warpgrid_state::StateStore,
+    mut shutdown: watch::Receiver<bool>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            match state.prune_usage(now) {
+                Ok(0) => {}
+                Ok(pruned) => tracing::info!(pruned, "usage past retention pruned"),
+                Err(e) => tracing::warn!(error = %e, "failed to prune usage"),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(USAGE_PRUNE_INTERVAL) => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+    })
+}
+
+/// Upgrade records written by an older release before anything reads them;
+/// refuses a store written by a newer one.
+pub fn migrate_state(state: &warpgrid_state::StateStore) -> anyhow::Result<()> {
+    let report = state.migrate()?;
+    if report.from != report.to {
+        tracing::info!(
+            from = report.from,
+            to = report.to,
+            records = report.records_migrated,
+            "state schema migrated"
+        );
+    }
+    Ok(())
+}
+
+/// Write `contents` readable by the owner only.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Where metrics snapshots are pushed, besides the state store.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct MetricsSinkArgs {
+    /// Push metrics snapshots to a Prometheus remote-write endpoint,
+    /// configured by this TOML file (format in
+    /// crates/warpgrid-metrics/src/remote_write/mod.rs).
+    #[arg(long)]
+    pub remote_write: Option<PathBuf>,
+
+    /// Send metrics snapshots to a statsd or DogStatsD agent over UDP or a
+    /// Unix socket, configured by this TOML file (format in
+    /// crates/warpgrid-metrics/src/statsd.rs).
+    #[arg(long)]
+    pub statsd: Option<PathBuf>,
+}
+
+impl MetricsSinkArgs {
+    /// Load and validate the configured sinks. Their buffers default into
+    /// `data_dir`.
+    pub fn load(&self, data_dir: &Path) -> anyhow::Result<MetricsSinks> {
+        let remote_write = self
+            .remote_write
+            .as_deref()
+            .map(|path| {
+                let config = warpgrid_metrics::RemoteWriteConfig::load(path)?;
+                warpgrid_metrics::RemoteWriter::new(config, &data_dir.join("remote-write"))
+            })
+            .transpose()?;
+        let statsd = self
+            .statsd
+            .as_deref()
+            .map(|path| warpgrid_metrics::StatsdSink::new(warpgrid_metrics::StatsdConfig::load(path)?))
+            .transpose()?;
+        Ok(MetricsSinks {
+            remote_write,
+            statsd,
+        })
+    }
+}
+
+/// Metrics sinks ready to start.
+pub struct MetricsSinks {
+    remote_write: Option<warpgrid_metrics::RemoteWriter>,
+    statsd: Option<warpgrid_metrics::StatsdSink>,
+}
+
+impl MetricsSinks {
+    /// Subscribe each sink to `collector`'s snapshots and start it.
+    pub fn spawn(
+        self,
+        collector: &warpgrid_metrics::MetricsCollector,
+        shutdown: watch::Receiver<bool>,
+    ) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut handles = Vec::new();
+        if let Some(writer) = self.remote_write {
+            handles.push(tokio::spawn(writer.run(collector.subscribe(), shutdown.clone())));
+        }
+        if let Some(sink) = self.statsd {
+            handles.push(tokio::spawn(sink.run(collector.subscribe(), shutdown)));
+        }
+        handles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn rate_limit_args_default_off_and_burst_to_one_second() {
+        use warpgrid_api::ratelimit::{RateLimit, RateLimits};
+
+        assert_eq!(ApiRateLimitArgs::default().limits(), RateLimits::default());
+        let args = ApiRateLimitArgs { api_token_rate: 2.5, api_ip_rate: 10.0, api_ip_burst: 50, ..Default::default() };
+        assert_eq!(
+            args.limits(),
+            RateLimits {
+                per_token: Some(RateLimit { burst: 3, per_second: 2.5 }),
+                per_ip: Some(RateLimit { burst: 50, per_second: 10.0 }),
+            }
+        );
+    }
+
+    #[test]
+    fn artifact_store_checks_uploads_against_the_policy() {
+        use warp_runtime::{SignatureMode, SignaturePolicy, TrustRoot};
+        use warpgrid_api::ArtifactVerifier;
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("handler.wasm");
+        std::fs::write(&artifact, b"\0asm").unwrap();
+        let enforce = SignaturePolicy::new(SignatureMode::Enforce, Some(TrustRoot::Key("cosign.pub".into()))).unwrap();
+
+        assert!(UploadPolicy(SignaturePolicy::disabled()).verify(&artifact).is_ok());
+        let err = UploadPolicy(enforce).verify(&artifact).unwrap_err();
+        assert!(err.contains("no signature bundle"), "{err}");
+        artifact_store(dir.path(), SignaturePolicy::disabled()).unwrap();
+        assert!(dir.path().join(ARTIFACTS_DIR).is_dir());
+    }
+
+    #[test]
+    fn require_api_tokens_writes_a_bootstrap_token_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = warpgrid_state::StateStore::open_in_memory().unwrap();
+        let _router = require_api_tokens(axum::Router::new(), &state, dir.path()).unwrap();
+
+        let path = dir.path().join(BOOTSTRAP_TOKEN_FILE);
+        let token = std::fs::read_to_string(&path).unwrap();
+        assert!(token.starts_with(warpgrid_api::auth::TOKEN_PREFIX));
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(state.list_api_tokens().unwrap()[0].name, "bootstrap");
+
+        std::fs::remove_file(&path).unwrap();
+        let _router = require_api_tokens(axum::Router::new(), &state, dir.path()).unwrap();
+        assert!(!path.exists());
+        assert_eq!(state.list_api_tokens().unwrap().len(), 1);
+    }
+}
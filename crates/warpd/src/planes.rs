@@ -0,0 +1,637 @@
+//! Traffic planes — which interface, TLS, and auth each kind of traffic
+//! is served with.
+//!
+//! warpd serves up to four planes:
+//!
+//! - **management** — REST API and dashboard (`--port` / `--api-port`)
+//! - **ingress** — app traffic for HTTP triggers (`--ingress-port`)
+//! - **metrics** — Prometheus `/metrics`; shares the management listener
+//!   unless given its own port
+//! - **cluster** — Raft and membership gRPC (`--grpc-port`)
+//!
+//! Without configuration every plane binds all interfaces, in plain text
+//! and unauthenticated. `--planes-config <file>` overrides that per plane:
+//!
+//! ```toml
+//! [management]
+//! bind = "10.0.0.5"
+//! private_only = true
+//! tls = { cert = "/etc/warpgrid/api.crt", key = "/etc/warpgrid/api.key" }
+//! auth = { token_file = "/etc/warpgrid/api.token" }
+//!
+//! [metrics]
+//! bind = "127.0.0.1"
+//! port = 9100
+//!
+//! [ingress]
+//! ports = [443]
+//! tls = { cert = "/etc/warpgrid/apps.crt", key = "/etc/warpgrid/apps.key" }
+//! ```
+//!
+//! The whole file is validated — addresses, port clashes between planes,
+//! `private_only`, certificates, tokens — before anything is bound, and
+//! warpd only constructs listeners from the resolved [`Plane`]s.
+//!
+//! The cluster plane accepts `bind`, `port`, and `private_only`. Agents
+//! and Raft peers dial it over plain gRPC, so TLS and auth on it are
+//! rejected rather than silently ignored.
+
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use axum::Router;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::serve::ListenerExt;
+use serde::Deserialize;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
+use tracing::{debug, error, info, warn};
+
+/// How long a client gets to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A kind of traffic warpd serves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaneKind {
+    Management,
+    Ingress,
+    Metrics,
+    Cluster,
+}
+
+impl fmt::Display for PlaneKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Management => "management",
+            Self::Ingress => "ingress",
+            Self::Metrics => "metrics",
+            Self::Cluster => "cluster",
+        })
+    }
+}
+
+/// The `--planes-config` file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanesConfig {
+    #[serde(default)]
+    management: PlaneConfig,
+    #[serde(default)]
+    ingress: PlaneConfig,
+    #[serde(default)]
+    metrics: PlaneConfig,
+    #[serde(default)]
+    cluster: PlaneConfig,
+}
+
+/// One `[plane]` section.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneConfig {
+    /// Interface address to bind (default: all interfaces).
+    bind: Option<IpAddr>,
+    /// Port, overriding the command-line default.
+    port: Option<u16>,
+    /// Ports (ingress only, which may listen on several).
+    ports: Option<Vec<u16>>,
+    /// Refuse to start unless `bind` is a loopback or private address.
+    #[serde(default)]
+    private_only: bool,
+    tls: Option<TlsFiles>,
+    auth: Option<AuthConfig>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TlsFiles {
+    /// PEM certificate chain.
+    cert: PathBuf,
+    /// PEM private key.
+    key: PathBuf,
+}
+
+/// Bearer token required on every request, inline or read from a file.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthConfig {
+    token: Option<String>,
+    token_file: Option<PathBuf>,
+}
+
+/// Ports each plane uses when the config file does not set one. A `None`
+/// plane is not served in the current mode.
+pub struct PlaneDefaults {
+    pub management_port: u16,
+    pub ingress_ports: Option<Vec<u16>>,
+    pub cluster_port: Option<u16>,
+}
+
+/// Every plane served in this mode, validated and ready to bind.
+#[derive(Debug)]
+pub struct Planes {
+    pub management: Plane,
+    /// `None` when metrics share the management listener.
+    pub metrics: Option<Plane>,
+    pub ingress: Option<Plane>,
+    pub cluster: Option<Plane>,
+}
+
+/// A validated plane: where to listen and how to secure it.
+#[derive(Clone)]
+pub struct Plane {
+    pub kind: PlaneKind,
+    pub addrs: Vec<SocketAddr>,
+    pub tls: Option<Arc<ServerConfig>>,
+    pub auth_token: Option<Arc<str>>,
+}
+
+impl fmt::Debug for Plane {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Plane")
+            .field("kind", &self.kind)
+            .field("addrs", &self.addrs)
+            .field("tls", &self.tls.is_some())
+            .field("auth", &self.auth_token.is_some())
+            .finish()
+    }
+}
+
+impl PlanesConfig {
+    /// Read a planes file, or the all-defaults config when none is given.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read planes config {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("invalid planes config {}", path.display()))
+    }
+
+    /// Validate the config against this mode's planes and defaults.
+    pub fn resolve(self, defaults: PlaneDefaults) -> anyhow::Result<Planes> {
+        let management = resolve_plane(
+            PlaneKind::Management,
+            self.management,
+            vec![defaults.management_port],
+        )?;
+
+        let metrics = if self.metrics.port.is_some() {
+            Some(resolve_plane(PlaneKind::Metrics, self.metrics, vec![])?)
+        } else if self.metrics != PlaneConfig::default() {
+            bail!(
+                "[metrics] needs a port to be served apart from the management plane; \
+                 without one it inherits the management listener"
+            );
+        } else {
+            None
+        };
+
+        let ingress = match defaults.ingress_ports {
+            Some(ports) => Some(resolve_plane(PlaneKind::Ingress, self.ingress, ports)?),
+            None => {
+                not_served(PlaneKind::Ingress, &self.ingress)?;
+                None
+            }
+        };
+
+        if self.cluster.tls.is_some() || self.cluster.auth.is_some() {
+            bail!(
+                "[cluster] does not support tls or auth: agents and raft peers connect over \
+                 plain gRPC. Restrict it with bind/private_only instead."
+            );
+        }
+        let cluster = match defaults.cluster_port {
+            Some(port) => Some(resolve_plane(PlaneKind::Cluster, self.cluster, vec![port])?),
+            None => {
+                not_served(PlaneKind::Cluster, &self.cluster)?;
+                None
+            }
+        };
+
+        let planes = Planes {
+            management,
+            metrics,
+            ingress,
+            cluster,
+        };
+        planes.check_conflicts()?;
+        Ok(planes)
+    }
+}
+
+fn not_served(kind: PlaneKind, config: &PlaneConfig) -> anyhow::Result<()> {
+    if *config != PlaneConfig::default() {
+        bail!("[{kind}] is configured but this mode does not serve the {kind} plane");
+    }
+    Ok(())
+}
+
+fn resolve_plane(
+    kind: PlaneKind,
+    config: PlaneConfig,
+    default_ports: Vec<u16>,
+) -> anyhow::Result<Plane> {
+    let ports = match (config.port, config.ports) {
+        (Some(_), Some(_)) => bail!("[{kind}] sets both port and ports"),
+        (_, Some(_)) if kind != PlaneKind::Ingress => {
+            bail!("[{kind}] listens on a single port; use port, not ports")
+        }
+        (_, Some(ports)) if ports.is_empty() => bail!("[{kind}] ports is empty"),
+        (_, Some(ports)) => ports,
+        (Some(port), None) => vec![port],
+        (None, None) => default_ports,
+    };
+
+    let ip = config.bind.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    if config.private_only && !is_private(ip) {
+        bail!(
+            "[{kind}] is private_only but binds {ip}, which is not a loopback or private address"
+        );
+    }
+
+    let tls = config
+        .tls
+        .map(|files| load_tls(&files))
+        .transpose()
+        .with_context(|| format!("[{kind}] tls"))?;
+    let auth_token = config
+        .auth
+        .map(|auth| auth.token())
+        .transpose()
+        .with_context(|| format!("[{kind}] auth"))?;
+
+    Ok(Plane {
+        kind,
+        addrs: ports.into_iter().map(|port| SocketAddr::new(ip, port)).collect(),
+        tls,
+        auth_token,
+    })
+}
+
+/// Loopback, RFC 1918 / link-local IPv4, or unique-local / link-local IPv6.
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local(),
+    }
+}
+
+fn load_tls(files: &TlsFiles) -> anyhow::Result<Arc<ServerConfig>> {
+    let cert_pem = std::fs::read(&files.cert)
+        .with_context(|| format!("failed to read {}", files.cert.display()))?;
+    let certs = rustls_pemfile::certs(&mut cert_pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid certificate PEM in {}", files.cert.display()))?;
+    if certs.is_empty() {
+        bail!("no certificates in {}", files.cert.display());
+    }
+
+    let key_pem = std::fs::read(&files.key)
+        .with_context(|| format!("failed to read {}", files.key.display()))?;
+    let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+        .with_context(|| format!("invalid key PEM in {}", files.key.display()))?
+        .with_context(|| format!("no private key in {}", files.key.display()))?;
+
+    // Explicit provider: both ring and aws-lc-rs are compiled into warpd.
+    let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("certificate and key do not match")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+impl AuthConfig {
+    fn token(self) -> anyhow::Result<Arc<str>> {
+        let token = match (self.token, self.token_file) {
+            (Some(token), None) => token,
+            (None, Some(path)) => std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?
+                .trim()
+                .to_string(),
+            _ => bail!("set exactly one of token or token_file"),
+        };
+        if token.is_empty() {
+            bail!("token is empty");
+        }
+        Ok(token.into())
+    }
+}
+
+impl Planes {
+    /// Reject two planes that would listen on the same port of overlapping
+    /// addresses, so a misconfiguration fails at startup instead of at bind.
+    fn check_conflicts(&self) -> anyhow::Result<()> {
+        let listeners: Vec<(PlaneKind, SocketAddr)> = [
+            Some(&self.management),
+            self.metrics.as_ref(),
+            self.ingress.as_ref(),
+            self.cluster.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|plane| plane.addrs.iter().map(move |addr| (plane.kind, *addr)))
+        .collect();
+
+        for (i, (kind_a, a)) in listeners.iter().enumerate() {
+            for (kind_b, b) in &listeners[i + 1..] {
+                let overlap = a.ip() == b.ip() || a.ip().is_unspecified() || b.ip().is_unspecified();
+                if a.port() == b.port() && overlap {
+                    if kind_a == kind_b {
+                        bail!("[{kind_a}] lists port {} twice", a.port());
+                    }
+                    bail!("[{kind_a}] ({a}) and [{kind_b}] ({b}) would share a listener");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Plane {
+    /// The plane's address (management, metrics, and cluster have one).
+    pub fn addr(&self) -> SocketAddr {
+        self.addrs[0]
+    }
+
+    /// Serve an axum router on this plane, applying its TLS and auth.
+    pub async fn serve_router(
+        &self,
+        router: Router,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let addr = self.addr();
+        let router = match &self.auth_token {
+            Some(token) => router.layer(middleware::from_fn_with_state(token.clone(), require_bearer)),
+            None => router,
+        };
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind {} plane on {addr}", self.kind))?;
+        info!(
+            plane = %self.kind,
+            %addr,
+            tls = self.tls.is_some(),
+            auth = self.auth_token.is_some(),
+            "listener bound"
+        );
+
+        // Client addresses reach handlers as `ConnectInfo`, for per-IP
+        // rate limits.
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
+        match &self.tls {
+            Some(tls) => {
+                // `tap_io` lends `TlsListener` axum's `ConnectInfo` support.
+                let listener = TlsListener::new(listener, tls.clone()).tap_io(|_| {});
+                axum::serve(listener, service)
+                    .with_graceful_shutdown(shutdown)
+                    .await?
+            }
+            None => axum::serve(listener, service).with_graceful_shutdown(shutdown).await?,
+        }
+        Ok(())
+    }
+
+    /// Serve a router in the background until `shutdown` flips.
+    pub fn spawn_router(
+        self,
+        router: Router,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let stop = async move {
+                let _ = shutdown.changed().await;
+            };
+            if let Err(e) = self.serve_router(router, stop).await {
+                error!(plane = %self.kind, error = %e, "listener failed");
+            }
+        })
+    }
+
+    /// Build the app ingress server for this plane.
+    pub fn ingress_server(
+        &self,
+        router: warpgrid_trigger::IngressRouter,
+    ) -> warpgrid_trigger::IngressServer {
+        info!(
+            plane = %self.kind,
+            addrs = ?self.addrs,
+            tls = self.tls.is_some(),
+            auth = self.auth_token.is_some(),
+            "app ingress starting"
+        );
+        let mut server = warpgrid_trigger::IngressServer::new(self.addrs.clone(), router);
+        if let Some(tls) = &self.tls {
+            server = server.with_tls(tls.clone());
+        }
+        if let Some(token) = &self.auth_token {
+            server = server.with_auth_token(token.clone());
+        }
+        server
+    }
+}
+
+async fn require_bearer(State(token): State<Arc<str>>, req: Request, next: Next) -> Response {
+    if warpgrid_trigger::bearer_authorized(req.headers(), &token) {
+        return next.run(req).await;
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        [(WWW_AUTHENTICATE, "Bearer")],
+        "Unauthorized",
+    )
+        .into_response()
+}
+
+/// TCP listener that completes TLS handshakes before handing connections
+/// to axum. Handshakes run concurrently so a slow client cannot stall
+/// accepts.
+struct TlsListener {
+    tcp: TcpListener,
+    acceptor: TlsAcceptor,
+    handshakes: JoinSet<Option<(TlsStream<TcpStream>, SocketAddr)>>,
+}
+
+impl TlsListener {
+    fn new(tcp: TcpListener, config: Arc<ServerConfig>) -> Self {
+        Self {
+            tcp,
+            acceptor: TlsAcceptor::from(config),
+            handshakes: JoinSet::new(),
+        }
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            tokio::select! {
+                accepted = self.tcp.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let acceptor = self.acceptor.clone();
+                        self.handshakes.spawn(async move {
+                            match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                                Ok(Ok(tls)) => Some((tls, peer)),
+                                Ok(Err(e)) => {
+                                    debug!(%peer, error = %e, "TLS handshake failed");
+                                    None
+                                }
+                                Err(_) => {
+                                    debug!(%peer, "TLS handshake timed out");
+                                    None
+                                }
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "accept failed");
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                    }
+                },
+                Some(done) = self.handshakes.join_next(), if !self.handshakes.is_empty() => {
+                    if let Ok(Some(conn)) = done {
+                        return conn;
+                    }
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        self.tcp.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    fn standalone() -> PlaneDefaults {
+        PlaneDefaults {
+            management_port: 8443,
+            ingress_ports: Some(vec![8080]),
+            cluster_port: None,
+        }
+    }
+
+    fn parse(toml: &str) -> anyhow::Result<Planes> {
+        toml::from_str::<PlanesConfig>(toml)?.resolve(standalone())
+    }
+
+    #[test]
+    fn defaults_bind_all_interfaces_and_share_metrics() {
+        let planes = PlanesConfig::default().resolve(standalone()).unwrap();
+        assert_eq!(planes.management.addr(), "0.0.0.0:8443".parse().unwrap());
+        assert_eq!(planes.ingress.unwrap().addrs, ["0.0.0.0:8080".parse().unwrap()]);
+        assert!(planes.metrics.is_none());
+        assert!(planes.cluster.is_none());
+    }
+
+    #[test]
+    fn planes_bind_their_own_interfaces() {
+        let planes = parse(
+            "[management]\nbind = \"10.0.0.5\"\nprivate_only = true\n\
+             [metrics]\nbind = \"127.0.0.1\"\nport = 9100\n\
+             [ingress]\nports = [80, 8080]\n",
+        )
+        .unwrap();
+        assert_eq!(planes.management.addr(), "10.0.0.5:8443".parse().unwrap());
+        assert_eq!(planes.metrics.unwrap().addr(), "127.0.0.1:9100".parse().unwrap());
+        assert_eq!(planes.ingress.unwrap().addrs.len(), 2);
+    }
+
+    #[test]
+    fn private_only_rejects_public_and_wildcard_binds() {
+        let err = parse("[management]\nprivate_only = true\n").unwrap_err();
+        assert!(err.to_string().contains("not a loopback or private"), "{err}");
+        assert!(parse("[management]\nbind = \"203.0.113.7\"\nprivate_only = true\n").is_err());
+        assert!(parse("[management]\nbind = \"fd00::1\"\nprivate_only = true\n").is_ok());
+    }
+
+    #[test]
+    fn overlapping_listeners_are_rejected() {
+        let err = parse("[ingress]\nport = 8443\n").unwrap_err();
+        assert!(err.to_string().contains("would share a listener"), "{err}");
+        // Same port on distinct interfaces is fine.
+        assert!(
+            parse("[management]\nbind = \"127.0.0.1\"\n[ingress]\nbind = \"10.0.0.5\"\nport = 8443\n")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn misplaced_settings_are_rejected() {
+        assert!(parse("[metrics]\nbind = \"127.0.0.1\"\n").is_err());
+        assert!(parse("[management]\nports = [1, 2]\n").is_err());
+        assert!(parse("[cluster]\nport = 50051\n").is_err());
+        assert!(parse("[management]\nauth = {}\n").is_err());
+        assert!(parse("[management]\nlisten = \"x\"\n").is_err());
+
+        let cluster = toml::from_str::<PlanesConfig>("[cluster]\nauth = { token = \"t\" }\n")
+            .unwrap()
+            .resolve(PlaneDefaults {
+                management_port: 8443,
+                ingress_ports: None,
+                cluster_port: Some(50051),
+            });
+        assert!(cluster.unwrap_err().to_string().contains("does not support tls or auth"));
+    }
+
+    #[test]
+    fn tls_files_are_loaded_at_resolve_time() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (pair, _) = warpgrid_cluster::tls::generate_ca().unwrap();
+        let cert = dir.path().join("api.crt");
+        let key = dir.path().join("api.key");
+        std::fs::write(&cert, pair.cert_pem).unwrap();
+        std::fs::write(&key, pair.key_pem).unwrap();
+
+        let toml = format!(
+            "[management]\ntls = {{ cert = \"{}\", key = \"{}\" }}\n",
+            cert.display(),
+            key.display()
+        );
+        assert!(parse(&toml).unwrap().management.tls.is_some());
+
+        let missing = "[management]\ntls = { cert = \"/nonexistent.crt\", key = \"/nonexistent.key\" }\n";
+        assert!(parse(missing).is_err());
+    }
+
+    #[tokio::test]
+    async fn auth_token_guards_every_route() {
+        let router = Router::new()
+            .route("/api/v1/nodes", axum::routing::get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(Arc::<str>::from("s3cret"), require_bearer));
+
+        let anonymous = Request::builder().uri("/api/v1/nodes").body(axum::body::Body::empty()).unwrap();
+        let resp = router.clone().oneshot(anonymous).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let authed = Request::builder()
+            .uri("/api/v1/nodes")
+            .header("authorization", "Bearer s3cret")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(router.oneshot(authed).await.unwrap().status(), StatusCode::OK);
+    }
+}
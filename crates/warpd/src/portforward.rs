@@ -0,0 +1,32 @@
+//! Port-forward tunnels for `GET /deployments/{id}/port-forward`.
+//!
+//! Tunneled connections are served by the app ingress's router, so a
+//! request reaches the same handler it would through a listener. They
+//! arrive through the management plane and its auth, not the ingress
+//! listener's.
+
+use futures_util::future::BoxFuture;
+use warpgrid_api::{TunnelServer, TunnelStream};
+use warpgrid_trigger::IngressRouter;
+
+pub struct IngressTunnels {
+    router: IngressRouter,
+    ports: Vec<u16>,
+}
+
+impl IngressTunnels {
+    pub fn new(router: IngressRouter, ports: Vec<u16>) -> Self {
+        Self { router, ports }
+    }
+}
+
+impl TunnelServer for IngressTunnels {
+    fn ports(&self) -> Vec<u16> {
+        self.ports.clone()
+    }
+
+    fn serve(&self, deployment_id: String, stream: TunnelStream) -> BoxFuture<'static, ()> {
+        let router = self.router.clone();
+        Box::pin(async move { router.serve_tunnel(&deployment_id, stream).await })
+    }
+}
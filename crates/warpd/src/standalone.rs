@@ -0,0 +1,386 @@
+//! Standalone mode — all subsystems in one process (single node, no Raft).
+//!
+//! [`run`] opens the state store in the data directory, registers this
+//! host as the `standalone` node, and serves the management API and the
+//! app ingress on the given [`Planes`] until its shutdown future resolves.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+use warpgrid_state::InstanceStatus;
+
+use crate::{MemoryArgs, MetricsSinkArgs};
+use crate::{apps, exec, portforward};
+use crate::planes::Planes;
+
+/// Tables the app ingress and loaded apps are synced from.
+const INGRESS_TABLES: [&str; 5] = [
+    warpgrid_state::tables::DEPLOYMENTS,
+    warpgrid_state::tables::FLAGS,
+    warpgrid_state::tables::SECRETS,
+    warpgrid_state::tables::DEPLOYMENT_CONFIGS,
+    warpgrid_state::tables::CONFIG_BUNDLES,
+];
+
+/// Longest the app ingress goes without a sync while none of
+/// [`INGRESS_TABLES`] changes; changes sync it at once.
+const INGRESS_RESYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Everything standalone mode runs with.
+pub struct StandaloneConfig {
+    /// Must include the ingress plane.
+    pub planes: Planes,
+    /// Data directory for persistent state.
+    pub data_dir: PathBuf,
+    /// Metrics snapshot interval in seconds.
+    pub metrics_interval: u64,
+    /// Autoscaler check interval in seconds.
+    pub autoscale_interval: u64,
+    /// Verify every state record at startup, quarantining corrupt ones.
+    pub verify_state: bool,
+    /// Require an API token on `/api/v1` (see [`crate::require_api_tokens`]).
+    pub api_tokens: bool,
+    /// Pre-resolve imports of each distinct artifact once.
+    pub pre_instantiate: bool,
+    pub memory: MemoryArgs,
+    pub metrics_sinks: MetricsSinkArgs,
+    /// Limits on guest responses served through the ingress.
+    pub response_limits: warpgrid_trigger::ResponseLimits,
+    /// Per-token and per-IP limits on the management API.
+    pub api_rate_limits: warpgrid_api::ratelimit::RateLimits,
+    pub signature_policy: warp_runtime::SignaturePolicy,
+}
+
+/// Run the standalone daemon until `shutdown` resolves.
+pub async fn run(
+    config: StandaloneConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let StandaloneConfig {
+        planes,
+        data_dir,
+        metrics_interval,
+        autoscale_interval,
+        verify_state,
+        api_tokens,
+        pre_instantiate,
+        memory,
+        metrics_sinks,
+        response_limits,
+        api_rate_limits,
+        signature_policy,
+    } = config;
+    info!("WarpGrid daemon starting in standalone mode");
+
+    // Ensure data directory exists.
+    std::fs::create_dir_all(&data_dir)?;
+    let metrics_sinks = metrics_sinks.load(&data_dir)?;
+    let db_path = data_dir.join("warpgrid.redb");
+
+    // ── Initialize subsystems ──────────────────────────────────
+
+    // State store.
+    let state = warpgrid_state::StateStore::open(&db_path)?.with_secrets_key(crate::load_secrets_key(&data_dir)?);
+    info!(path = ?db_path, "state store opened");
+    crate::migrate_state(&state)?;
+    if verify_state {
+        let report = state.verify_integrity()?;
+        if !report.quarantined.is_empty() {
+            warn!(
+                quarantined = report.quarantined.len(),
+                "corrupt state records quarantined; see /api/v1/admin/state-integrity"
+            );
+        }
+    }
+
+    // Register this host as a standalone node with detected system capabilities.
+    let (detected_mem, detected_cpus) = detect_system_resources();
+    let standalone_node = warpgrid_state::NodeInfo {
+        id: "standalone".to_string(),
+        address: "127.0.0.1".to_string(),
+        port: planes.management.addr().port(),
+        capacity_memory_bytes: detected_mem,
+        capacity_cpu_weight: detected_cpus * 100, // 100 weight per core
+        used_memory_bytes: 0,
+        used_cpu_weight: 0,
+        labels: HashMap::from([("mode".to_string(), "standalone".to_string())]),
+        last_heartbeat: epoch_secs(),
+        pressure_until: None,
+        replica_revision: None,
+        calibrated_cpu_weight: None,
+        cordoned: false,
+    };
+    state.put_node(&standalone_node)?;
+    info!(
+        memory_bytes = detected_mem,
+        cpu_cores = detected_cpus,
+        "standalone node registered with detected system resources"
+    );
+
+    // Wasm runtime.
+    let runtime = Arc::new(
+        warp_runtime::Runtime::new_metered(
+            warp_runtime::ShimConfig::default(),
+            warp_runtime::usage::DEFAULT_EPOCH_TICK,
+        )?
+        .with_pre_instantiation(pre_instantiate)
+        .with_signature_policy(signature_policy.clone()),
+    );
+    info!("wasm runtime initialized");
+
+    // Scheduler.
+    let scheduler = Arc::new(
+        warpgrid_scheduler::Scheduler::new(
+            runtime.clone(),
+            state.clone(),
+            "standalone".to_string(),
+        )
+        .with_overcommit(memory.memory_overcommit),
+    );
+    info!("scheduler initialized");
+
+    // Health monitor.
+    let _health_monitor = warpgrid_health::HealthMonitor::new(state.clone());
+    info!("health monitor initialized");
+
+    // Metrics collector.
+    let metrics = warpgrid_metrics::MetricsCollector::new(
+        state.clone(),
+        Duration::from_secs(metrics_interval),
+    );
+    info!(interval = metrics_interval, "metrics collector initialized");
+
+    // Autoscaler.
+    let mut autoscaler = warpgrid_autoscale::Autoscaler::new(state.clone());
+    info!(interval = autoscale_interval, "autoscaler initialized");
+
+    // ── Shutdown signal ────────────────────────────────────────
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let metrics_shutdown = shutdown_rx.clone();
+    let autoscale_shutdown = shutdown_rx.clone();
+    let heartbeat_shutdown = shutdown_rx.clone();
+    let mut ingress_sync_shutdown = shutdown_rx.clone();
+    let ingress_shutdown = shutdown_rx.clone();
+
+    // ── Start background tasks ─────────────────────────────────
+
+    // Metrics snapshot loop, and the sinks it feeds.
+    let sink_handles = metrics_sinks.spawn(&metrics, shutdown_rx.clone());
+    let metrics_handle = tokio::spawn(async move {
+        metrics.run(metrics_shutdown).await;
+    });
+
+    // Autoscaler loop.
+    let autoscale_handle = tokio::spawn(async move {
+        autoscaler
+            .run(Duration::from_secs(autoscale_interval), autoscale_shutdown)
+            .await;
+    });
+
+    // Memory pressure loop.
+    let (pressure_handle, _) = memory.spawn_pressure_monitor(scheduler.clone(), shutdown_rx.clone());
+
+    // Unschedule deployments deleted from the state store.
+    let deletion_scheduler = scheduler.clone();
+    let deletion_shutdown = shutdown_rx.clone();
+    let deletion_handle = tokio::spawn(async move {
+        deletion_scheduler.run_deployment_watch(deletion_shutdown).await;
+    });
+
+    // Standalone node heartbeat loop.
+    let heartbeat_state = state.clone();
+    let heartbeat_handle = tokio::spawn(async move {
+        let mut shutdown = heartbeat_shutdown;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(10)) => {
+                    if let Err(e) = update_standalone_node(&heartbeat_state) {
+                        tracing::warn!(error = %e, "standalone heartbeat failed");
+                    }
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+    });
+
+    // Outbound webhooks.
+    let webhooks_handle = crate::webhooks::spawn(state.clone(), shutdown_rx.clone());
+
+    // Usage retention.
+    let usage_prune_handle = crate::spawn_usage_pruning(state.clone(), shutdown_rx.clone());
+
+    // ── Start app ingress ──────────────────────────────────────
+
+    // Deployments' HTTP triggers share the ingress listeners; the route
+    // table and the loaded apps follow the state store.
+    let ingress = warpgrid_trigger::IngressRouter::new();
+    // Watched before the first sync, so no change after it is missed.
+    let mut ingress_changes = Box::pin(state.watch_any(&INGRESS_TABLES));
+    ingress.sync(&state)?;
+    let usage: Arc<dyn warpgrid_trigger::UsageSink> = Arc::new(crate::StoreUsageSink {
+        state: state.clone(),
+        node_id: "standalone".to_string(),
+    });
+    let mut apps = apps::AppLoader::new(runtime.clone(), ingress.clone(), response_limits, usage.clone());
+    apps.sync(&state).await?;
+    let sync_health = Arc::new(apps::SyncHealth::default());
+    let sync_router = ingress.clone();
+    let sync_state = state.clone();
+    let app_health = Arc::clone(&sync_health);
+    let ingress_sync_handle = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = warpgrid_state::watch::changed(&mut ingress_changes, INGRESS_RESYNC_INTERVAL) => {
+                    if let Err(e) = sync_router.sync(&sync_state) {
+                        tracing::warn!(error = %e, "ingress route sync failed");
+                    }
+                    let synced = apps.sync(&sync_state).await;
+                    app_health.record(&synced);
+                    if let Err(e) = synced {
+                        tracing::warn!(error = %e, "app sync failed");
+                    }
+                }
+                _ = ingress_sync_shutdown.changed() => break,
+            }
+        }
+    });
+
+    let ingress_plane = planes.ingress.as_ref().expect("standalone serves the ingress plane");
+    let tunnels: Arc<dyn warpgrid_api::TunnelServer> = Arc::new(portforward::IngressTunnels::new(
+        ingress.clone(),
+        ingress_plane.addrs.iter().map(|addr| addr.port()).collect(),
+    ));
+    let ingress_server = ingress_plane.ingress_server(ingress);
+    let ingress_handle = tokio::spawn(async move {
+        if let Err(e) = ingress_server.serve(ingress_shutdown).await {
+            tracing::error!(error = %e, "app ingress failed");
+        }
+    });
+
+    // ── Start metrics listener (when separate from the API) ────
+
+    let rollouts: warpgrid_api::RolloutStore = Default::default();
+    let metrics_listener_handle = planes.metrics.clone().map(|plane| {
+        plane.spawn_router(
+            warpgrid_api::build_metrics_router(state.clone()),
+            shutdown_rx.clone(),
+        )
+    });
+
+    // ── Start API server ───────────────────────────────────────
+
+    let capabilities = crate::capabilities(
+        runtime.engine().config(),
+        &response_limits,
+        runtime.engine().epoch_tick().is_some(),
+    );
+    let invoker: Arc<dyn warpgrid_api::ExportInvoker> =
+        Arc::new(exec::ExecPools::new(runtime.clone(), state.clone(), usage));
+    let mut router = match planes.metrics {
+        Some(_) => warpgrid_api::build_management_router(state.clone(), rollouts),
+        None => warpgrid_api::build_router_with_rollouts(state.clone(), rollouts),
+    }
+    .layer(axum::Extension(Arc::new(capabilities)))
+    .layer(axum::Extension(invoker))
+    .layer(axum::Extension(tunnels))
+    .layer(axum::Extension(crate::artifact_store(&data_dir, signature_policy)?))
+    .layer(axum::Extension(warpgrid_api::HealthChecks(vec![sync_health])));
+    if api_tokens {
+        router = crate::require_api_tokens(router, &state, &data_dir)?;
+    }
+    let router = crate::rate_limit_api(router, api_rate_limits, &state);
+    info!(addr = %planes.management.addr(), "API server starting");
+
+    let server = planes.management.serve_router(router, async move {
+        shutdown.await;
+        info!("shutdown signal received");
+        let _ = shutdown_tx.send(true);
+    });
+
+    server.await?;
+
+    // Give guests their on-shutdown hooks before the runtime goes away.
+    scheduler.drain().await;
+
+    // Wait for background tasks.
+    let _ = metrics_handle.await;
+    for handle in sink_handles {
+        let _ = handle.await;
+    }
+    let _ = autoscale_handle.await;
+    let _ = pressure_handle.await;
+    let _ = deletion_handle.await;
+    let _ = heartbeat_handle.await;
+    let _ = webhooks_handle.await;
+    let _ = usage_prune_handle.await;
+    let _ = ingress_sync_handle.await;
+    let _ = ingress_handle.await;
+    if let Some(handle) = metrics_listener_handle {
+        let _ = handle.await;
+    }
+
+    info!("WarpGrid daemon stopped");
+    Ok(())
+}
+
+/// Update the standalone node's heartbeat and resource usage from instance data.
+fn update_standalone_node(state: &warpgrid_state::StateStore) -> anyhow::Result<()> {
+    let mut node = state
+        .get_node("standalone")?
+        .ok_or_else(|| anyhow::anyhow!("standalone node not found"))?;
+
+    let deployments = state.list_deployments()?;
+    let mut used_mem: u64 = 0;
+    let mut used_cpu: u32 = 0;
+    for d in &deployments {
+        let instances = state.list_instances_for_deployment(&d.id)?;
+        for inst in &instances {
+            if inst.status == InstanceStatus::Running {
+                used_mem += inst.memory_bytes;
+                used_cpu += d.resources.cpu_weight;
+            }
+        }
+    }
+
+    node.last_heartbeat = epoch_secs();
+    node.used_memory_bytes = used_mem;
+    node.used_cpu_weight = used_cpu;
+    state.put_node(&node)?;
+    Ok(())
+}
+
+pub(crate) fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Detect total physical memory (bytes) and CPU core count from the OS.
+/// Falls back to 8 GiB / 4 cores if detection fails.
+fn detect_system_resources() -> (u64, u32) {
+    let memory = detect_total_memory().unwrap_or(8 * 1024 * 1024 * 1024);
+    let cpus = std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(4);
+    (memory, cpus)
+}
+
+/// Read total physical memory via POSIX sysconf.
+fn detect_total_memory() -> Option<u64> {
+    unsafe {
+        let pages = libc::sysconf(libc::_SC_PHYS_PAGES);
+        let page_size = libc::sysconf(libc::_SC_PAGE_SIZE);
+        if pages > 0 && page_size > 0 {
+            Some(pages as u64 * page_size as u64)
+        } else {
+            None
+        }
+    }
+}
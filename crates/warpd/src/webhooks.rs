@@ -0,0 +1,259 @@
+//! Outbound webhooks: POST cluster events to the URLs registered through
+//! `/api/v1/webhooks`.
+//!
+//! The dispatcher follows the cluster event log, starting from the newest
+//! event when warpd starts, and sends each event every webhook selects as
+//! `{"webhook": id, "event": {...}}` with these headers:
+//!
+//! - `X-Warpgrid-Event`: the event kind, e.g. `rollout_rolled_back`
+//! - `X-Warpgrid-Delivery`: `{webhook}-{sequence}`, the same on every retry
+//! - `X-Warpgrid-Signature`: `sha256=` and the hex HMAC-SHA256 of the body,
+//!   keyed with the webhook's secret
+//!
+//! Network errors, `429`, and `5xx` are retried up to [`MAX_ATTEMPTS`]
+//! times, the delay doubling from [`RETRY_BACKOFF`]; any other response
+//! ends the delivery. Deliveries run independently, so a receiver that
+//! cares about order should sort by the event's `sequence`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::rt::{Read, Write};
+use ring::hmac;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use warpgrid_state::{ClusterEvent, StateStore, Webhook};
+
+/// How often the event log is checked for new events.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Events read from the log per check.
+const BATCH: usize = 100;
+
+/// Attempts per delivery, the first included.
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled on each further one.
+pub const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Time allowed for one attempt, from connecting to the response head.
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start following the event log, until `shutdown` changes.
+pub fn spawn(store: StateStore, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut after = match store.list_events(0, usize::MAX, |_| true) {
+            Ok(events) => events.last().map_or(0, |event| event.sequence),
+            Err(e) => {
+                warn!(error = %e, "webhooks: cannot read the event log; not delivering");
+                return;
+            }
+        };
+        let tls = Arc::new(tls_connector());
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = shutdown.changed() => break,
+            }
+            match dispatch(&store, after, &tls) {
+                Ok(last) => after = last,
+                Err(e) => warn!(error = %e, "webhooks: dispatch failed"),
+            }
+        }
+    })
+}
+
+/// Start a delivery for each new event a webhook selects. Returns the
+/// sequence of the last event handled.
+fn dispatch(store: &StateStore, after: u64, tls: &Arc<tokio_rustls::TlsConnector>) -> anyhow::Result<u64> {
+    let events = store.list_events(after, BATCH, |_| true)?;
+    let Some(last) = events.last().map(|event| event.sequence) else {
+        return Ok(after);
+    };
+    let webhooks = store.list_webhooks()?;
+    for event in &events {
+        for webhook in webhooks.iter().filter(|webhook| webhook.matches(event)) {
+            let (webhook, event, tls) = (webhook.clone(), event.clone(), Arc::clone(tls));
+            tokio::spawn(async move { deliver(&webhook, &event, &tls, RETRY_BACKOFF).await });
+        }
+    }
+    Ok(last)
+}
+
+/// Send `event` to `webhook`, retrying transient failures. Returns whether
+/// it was accepted.
+pub async fn deliver(
+    webhook: &Webhook,
+    event: &ClusterEvent,
+    tls: &tokio_rustls::TlsConnector,
+    backoff: Duration,
+) -> bool {
+    let body = serde_json::json!({"webhook": webhook.id, "event": event}).to_string();
+    let kind = serde_json::to_value(event.kind).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+    let headers = [
+        ("x-warpgrid-event", kind),
+        ("x-warpgrid-delivery", format!("{}-{}", webhook.id, event.sequence)),
+        ("x-warpgrid-signature", signature(&webhook.secret, body.as_bytes())),
+    ];
+    let mut delay = backoff;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = tokio::time::timeout(ATTEMPT_TIMEOUT, post(&webhook.url, &headers, body.clone(), tls))
+            .await
+            .unwrap_or_else(|_| Err("timed out".to_string()));
+        let retry = match result {
+            Ok(status) if (200..300).contains(&status) => {
+                debug!(webhook = %webhook.id, sequence = event.sequence, "webhook delivered");
+                return true;
+            }
+            Ok(status) => {
+                let retry = status == 429 || status >= 500;
+                warn!(webhook = %webhook.id, sequence = event.sequence, status, attempt, "webhook rejected");
+                retry
+            }
+            Err(e) => {
+                warn!(webhook = %webhook.id, sequence = event.sequence, error = %e, attempt, "webhook delivery failed");
+                true
+            }
+        };
+        if !retry || attempt == MAX_ATTEMPTS {
+            break;
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+    false
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `body` keyed with `secret`.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    format!("sha256={}", hex::encode(hmac::sign(&key, body).as_ref()))
+}
+
+/// POST a JSON `body` to `url`, over TLS for `https://`. Returns the
+/// response status.
+async fn post(
+    url: &str,
+    headers: &[(&str, String)],
+    body: String,
+    tls: &tokio_rustls::TlsConnector,
+) -> Result<u16, String> {
+    let uri: http::Uri = url.parse().map_err(|e| format!("{url}: {e}"))?;
+    let host = uri.host().ok_or_else(|| format!("{url}: missing host"))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+    let https = uri.scheme_str() == Some("https");
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+    let authority = uri.authority().map_or(host.clone(), |a| a.to_string());
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+
+    let mut request = http::Request::builder()
+        .method("POST")
+        .uri(path)
+        .header("host", authority)
+        .header("user-agent", "warpd-webhooks")
+        .header("content-type", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    let request = request.body(Full::new(Bytes::from(body))).map_err(|e| e.to_string())?;
+
+    let stream = TcpStream::connect((host.as_str(), port)).await.map_err(|e| e.to_string())?;
+    if https {
+        let name = rustls::pki_types::ServerName::try_from(host.clone()).map_err(|e| format!("{host}: {e}"))?;
+        let stream = tls.connect(name, stream).await.map_err(|e| e.to_string())?;
+        send(hyper_util::rt::TokioIo::new(stream), request).await
+    } else {
+        send(hyper_util::rt::TokioIo::new(stream), request).await
+    }
+}
+
+async fn send<IO>(io: IO, request: http::Request<Full<Bytes>>) -> Result<u16, String>
+where
+    IO: Read + Write + Unpin + Send + 'static,
+{
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await.map_err(|e| e.to_string())?;
+    tokio::spawn(async move {
+        let _ = conn.await;
+    });
+    let response = sender.send_request(request).await.map_err(|e| e.to_string())?;
+    Ok(response.status().as_u16())
+}
+
+/// A TLS connector trusting the bundled web PKI roots.
+fn tls_connector() -> tokio_rustls::TlsConnector {
+    let roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let config = rustls::ClientConfig::builder_with_provider(rustls::crypto::ring::default_provider().into())
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    tokio_rustls::TlsConnector::from(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use warpgrid_state::ClusterEventKind;
+
+    /// Bodies and headers received, and the statuses to answer with.
+    #[derive(Clone, Default)]
+    struct Receiver {
+        received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        statuses: Arc<Mutex<Vec<StatusCode>>>,
+    }
+
+    async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: String) -> StatusCode {
+        receiver.received.lock().unwrap().push((headers, body));
+        receiver.statuses.lock().unwrap().pop().unwrap_or(StatusCode::OK)
+    }
+
+    async fn serve(receiver: Receiver) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = axum::Router::new().route("/hook", axum::routing::post(receive)).with_state(receiver);
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{addr}/hook")
+    }
+
+    fn webhook(url: String) -> Webhook {
+        Webhook { id: "wh1".into(), url, kinds: vec![], deployment: None, secret: "s3cret".into(), created_at: 0 }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_signs_the_body() {
+        let receiver = Receiver::default();
+        // Popped from the end: a 503, then a 200.
+        *receiver.statuses.lock().unwrap() = vec![StatusCode::OK, StatusCode::SERVICE_UNAVAILABLE];
+        let webhook = webhook(serve(receiver.clone()).await);
+        let event = ClusterEvent { sequence: 7, ..ClusterEvent::new(ClusterEventKind::DeploymentCreated, 1, "created") };
+
+        assert!(deliver(&webhook, &event, &tls_connector(), Duration::from_millis(10)).await);
+        let received = receiver.received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(headers["x-warpgrid-event"], "deployment_created");
+        assert_eq!(headers["x-warpgrid-delivery"], "wh1-7");
+        assert_eq!(headers["x-warpgrid-signature"], signature("s3cret", body.as_bytes()).as_str());
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!((body["webhook"].as_str(), body["event"]["sequence"].as_u64()), (Some("wh1"), Some(7)));
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let receiver = Receiver::default();
+        *receiver.statuses.lock().unwrap() = vec![StatusCode::BAD_REQUEST];
+        let webhook = webhook(serve(receiver.clone()).await);
+        let event = ClusterEvent::new(ClusterEventKind::NodeLost, 1, "lost");
+
+        assert!(!deliver(&webhook, &event, &tls_connector(), Duration::from_millis(10)).await);
+        assert_eq!(receiver.received.lock().unwrap().len(), 1);
+    }
+}
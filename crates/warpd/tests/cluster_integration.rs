@@ -28,11 +28,12 @@ fn test_deployment(ns: &str, name: &str) -> DeploymentSpec {
         namespace: ns.to_string(),
         name: name.to_string(),
         source: "file://test.wasm".to_string(),
-        trigger: TriggerConfig::Http { port: Some(8080) },
+        trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
         instances: InstanceConstraints { min: 2, max: 10 },
         resources: ResourceLimits {
             memory_bytes: 64 * 1024 * 1024,
             cpu_weight: 100,
+            execution_budget_ms: None,
         },
         scaling: None,
         health: None,
@@ -40,6 +41,12 @@ fn test_deployment(ns: &str, name: &str) -> DeploymentSpec {
         env: HashMap::new(),
         created_at: 1000,
         updated_at: 1000,
+        priority: None,
+        min_available: None,
+        labels: Default::default(),
+        paused: false,
+        secrets: Vec::new(),
+        config_maps: Vec::new(),
     }
 }
 
@@ -57,6 +64,10 @@ fn make_node(store: &StateStore, id: &str, addr: &str, port: u16) -> NodeInfo {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs(),
+        pressure_until: None,
+        replica_revision: None,
+        calibrated_cpu_weight: None,
+        cordoned: false,
     };
     store.put_node(&node).unwrap();
     node
@@ -90,13 +101,13 @@ fn cluster_formation_three_nodes() {
 
     let labels = HashMap::new();
     let id1 = mgr
-        .join("10.0.0.1", 8443, labels.clone(), 8_000_000_000, 1000)
+        .join("10.0.0.1", 8443, labels.clone(), 8_000_000_000, 1000, None)
         .unwrap();
     let id2 = mgr
-        .join("10.0.0.2", 8443, labels.clone(), 8_000_000_000, 1000)
+        .join("10.0.0.2", 8443, labels.clone(), 8_000_000_000, 1000, None)
         .unwrap();
     let id3 = mgr
-        .join("10.0.0.3", 8443, labels, 8_000_000_000, 1000)
+        .join("10.0.0.3", 8443, labels, 8_000_000_000, 1000, None)
         .unwrap();
 
     // All three nodes should appear.
@@ -118,7 +129,7 @@ fn cluster_node_leave_and_rejoin() {
     let mgr = MembershipManager::new(state);
 
     let id = mgr
-        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
         .unwrap();
 
     assert_eq!(mgr.ready_count().unwrap(), 1);
@@ -128,7 +139,7 @@ fn cluster_node_leave_and_rejoin() {
 
     // Rejoin.
     let _new_id = mgr
-        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
         .unwrap();
 
     assert_eq!(mgr.ready_count().unwrap(), 1);
@@ -212,7 +223,7 @@ fn dead_node_detection_and_reaping() {
         .with_dead_timeout(Duration::from_secs(0));
 
     let id = mgr
-        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
         .unwrap();
 
     // Set heartbeat to a very old timestamp to simulate death.
@@ -318,6 +329,65 @@ fn proxy_sync_removes_undeployed_services() {
     assert!(sync.router().get_backends("prod/api").is_empty());
 }
 
+// ── Agent Read Replica ──────────────────────────────────────────
+
+#[tokio::test]
+async fn agent_replica_follows_heartbeat_state_sync() {
+    use warpgrid_cluster::proto::cluster_service_server::ClusterService;
+    use warpgrid_cluster::{ClusterServer, proto};
+
+    let state = test_store();
+    let membership = Arc::new(MembershipManager::new(state.clone()));
+    let node_id = membership
+        .join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000, None)
+        .unwrap();
+    let server = ClusterServer::new(Arc::clone(&membership));
+    let replica = ReadReplica::open_in_memory().unwrap();
+
+    let heartbeat = |revision: u64| proto::HeartbeatRequest {
+        node_id: node_id.clone(),
+        used_memory_bytes: 0,
+        used_cpu_weight: 0,
+        active_instances: 0,
+        replica_revision: Some(revision),
+        memory_pressure: false,
+    };
+    let apply = |resp: proto::HeartbeatResponse| {
+        for cmd in resp.commands {
+            assert_eq!(cmd.command_type, warpgrid_cluster::STATE_SYNC_COMMAND);
+            let sync: ReplicaSync = serde_json::from_str(&cmd.payload).unwrap();
+            replica.apply(&sync).unwrap();
+        }
+    };
+
+    state.put_deployment(&test_deployment("prod", "api")).unwrap();
+    state
+        .put_instance(&make_instance("i1", "prod/api", "node-1", InstanceStatus::Running))
+        .unwrap();
+
+    // First heartbeat ships a snapshot.
+    let resp = server.heartbeat(tonic::Request::new(heartbeat(0))).await.unwrap();
+    apply(resp.into_inner());
+
+    let sync = ProxySync::new(Router::new(), DnsResolver::default());
+    let stats = sync.sync(replica.store()).unwrap();
+    assert_eq!(stats.services_synced, 1);
+    assert_eq!(stats.backends_total, 1);
+
+    // Later changes arrive as deltas.
+    state.delete_deployment("prod/api").unwrap();
+    let revision = replica.revision().unwrap();
+    let resp = server.heartbeat(tonic::Request::new(heartbeat(revision))).await.unwrap();
+    apply(resp.into_inner());
+    assert_eq!(replica.revision().unwrap(), state.state_revision().unwrap());
+    assert_eq!(sync.sync(replica.store()).unwrap().services_removed, 1);
+
+    // An up-to-date replica receives no command.
+    let revision = replica.revision().unwrap();
+    let resp = server.heartbeat(tonic::Request::new(heartbeat(revision))).await.unwrap();
+    assert!(resp.into_inner().commands.is_empty());
+}
+
 // ── Raft Node Map ───────────────────────────────────────────────
 
 #[test]
@@ -417,10 +487,10 @@ fn e2e_deploy_place_and_sync() {
     // 1. Register 2 nodes via MembershipManager.
     let mgr = MembershipManager::new(state.clone());
     let id1 = mgr
-        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+        .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
         .unwrap();
     let id2 = mgr
-        .join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000)
+        .join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000, None)
         .unwrap();
     assert_eq!(mgr.ready_count().unwrap(), 2);
 
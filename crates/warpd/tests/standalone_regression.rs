@@ -21,11 +21,12 @@ fn test_deployment(ns: &str, name: &str) -> DeploymentSpec {
         namespace: ns.to_string(),
         name: name.to_string(),
         source: "file://test.wasm".to_string(),
-        trigger: TriggerConfig::Http { port: Some(8080) },
+        trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
         instances: InstanceConstraints { min: 1, max: 5 },
         resources: ResourceLimits {
             memory_bytes: 64 * 1024 * 1024,
             cpu_weight: 100,
+            execution_budget_ms: None,
         },
         scaling: None,
         health: None,
@@ -33,6 +34,12 @@ fn test_deployment(ns: &str, name: &str) -> DeploymentSpec {
         env: HashMap::new(),
         created_at: 1000,
         updated_at: 1000,
+        priority: None,
+        min_available: None,
+        labels: Default::default(),
+        paused: false,
+        secrets: Vec::new(),
+        config_maps: Vec::new(),
     }
 }
 
@@ -0,0 +1,350 @@
+//! T7: Bun + Hono + Postgres through the full pipeline.
+//!
+//! Packs `test-apps/t7-bun-hono-postgres` with warp-pack (bun build →
+//! jco componentize), boots `warpd standalone`, deploys the artifact
+//! through the management API and drives it over the app ingress. The
+//! Hono handler queries a mock Postgres server through the database
+//! proxy shim using the `@warpgrid/bun-sdk` pg client.
+//!
+//! Needs Bun and jco; without them the test reports a skip.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use warpgrid_state::*;
+
+// ── Postgres protocol ───────────────────────────────────────────────
+
+/// AuthenticationOk: 'R' + Int32(8) + Int32(0)
+const AUTH_OK: [u8; 9] = [b'R', 0, 0, 0, 8, 0, 0, 0, 0];
+
+/// ReadyForQuery: 'Z' + Int32(5) + 'I' (idle)
+const READY_FOR_QUERY: [u8; 6] = [b'Z', 0, 0, 0, 5, b'I'];
+
+/// Same seed data as T4/T5.
+const SEED_USERS: [(&str, &str, &str); 5] = [
+    ("1", "Alice Johnson", "alice@example.com"),
+    ("2", "Bob Smith", "bob@example.com"),
+    ("3", "Carol Williams", "carol@example.com"),
+    ("4", "Dave Brown", "dave@example.com"),
+    ("5", "Eve Davis", "eve@example.com"),
+];
+
+fn message(tag: u8, payload: &[u8]) -> Vec<u8> {
+    let mut buf = vec![tag];
+    buf.extend_from_slice(&((payload.len() + 4) as i32).to_be_bytes());
+    buf.extend_from_slice(payload);
+    buf
+}
+
+/// RowDescription for (id int4, name text, email text).
+fn row_description_users() -> Vec<u8> {
+    let mut payload = 3_i16.to_be_bytes().to_vec();
+    for (name, type_oid, type_len) in [("id", 23_i32, 4_i16), ("name", 25, -1), ("email", 25, -1)] {
+        payload.extend_from_slice(name.as_bytes());
+        payload.push(0);
+        payload.extend_from_slice(&0_i32.to_be_bytes()); // table OID
+        payload.extend_from_slice(&0_i16.to_be_bytes()); // column attr number
+        payload.extend_from_slice(&type_oid.to_be_bytes());
+        payload.extend_from_slice(&type_len.to_be_bytes());
+        payload.extend_from_slice(&(-1_i32).to_be_bytes()); // type modifier
+        payload.extend_from_slice(&0_i16.to_be_bytes()); // text format
+    }
+    message(b'T', &payload)
+}
+
+fn data_row(fields: &[&str]) -> Vec<u8> {
+    let mut payload = (fields.len() as i16).to_be_bytes().to_vec();
+    for field in fields {
+        payload.extend_from_slice(&(field.len() as i32).to_be_bytes());
+        payload.extend_from_slice(field.as_bytes());
+    }
+    message(b'D', &payload)
+}
+
+fn command_complete(tag: &str) -> Vec<u8> {
+    let mut payload = tag.as_bytes().to_vec();
+    payload.push(0);
+    message(b'C', &payload)
+}
+
+/// Mock Postgres: trusts every startup and answers simple queries on
+/// `test_users` with the seed rows.
+struct MockPostgresServer {
+    addr: SocketAddr,
+}
+
+impl MockPostgresServer {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind to random port");
+        let addr = listener.local_addr().expect("local addr");
+        std::thread::spawn(move || {
+            while let Ok((mut stream, _)) = listener.accept() {
+                std::thread::spawn(move || Self::handle_connection(&mut stream));
+            }
+        });
+        Self { addr }
+    }
+
+    fn handle_connection(stream: &mut TcpStream) {
+        let mut len = [0u8; 4];
+        if stream.read_exact(&mut len).is_err() {
+            return;
+        }
+        let mut startup = vec![0u8; (u32::from_be_bytes(len) as usize).saturating_sub(4)];
+        if stream.read_exact(&mut startup).is_err()
+            || stream.write_all(&AUTH_OK).is_err()
+            || stream.write_all(&READY_FOR_QUERY).is_err()
+        {
+            return;
+        }
+
+        loop {
+            let mut header = [0u8; 5];
+            if stream.read_exact(&mut header).is_err() {
+                return;
+            }
+            let len = i32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+            let mut body = vec![0u8; len.saturating_sub(4)];
+            if stream.read_exact(&mut body).is_err() {
+                return;
+            }
+            match header[0] {
+                b'Q' => {
+                    let sql = String::from_utf8_lossy(&body).to_lowercase();
+                    let mut response = Vec::new();
+                    if sql.contains("select") && sql.contains("test_users") {
+                        response.extend(row_description_users());
+                        for (id, name, email) in SEED_USERS {
+                            response.extend(data_row(&[id, name, email]));
+                        }
+                        response.extend(command_complete("SELECT 5"));
+                    } else {
+                        response.extend(command_complete("SELECT 0"));
+                    }
+                    response.extend_from_slice(&READY_FOR_QUERY);
+                    if stream.write_all(&response).is_err() {
+                        return;
+                    }
+                }
+                b'X' => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+// ── warpd ───────────────────────────────────────────────────────────
+
+/// A `warpd standalone` child process, killed on drop.
+struct Warpd {
+    child: Child,
+    api: SocketAddr,
+    ingress: SocketAddr,
+    _data_dir: tempfile::TempDir,
+}
+
+impl Warpd {
+    fn start() -> Self {
+        let data_dir = tempfile::tempdir().unwrap();
+        let api = free_addr();
+        let ingress = free_addr();
+        let child = Command::new(env!("CARGO_BIN_EXE_warpd"))
+            .args(["standalone", "--port", &api.port().to_string()])
+            .args(["--ingress-port", &ingress.port().to_string()])
+            .arg("--data-dir")
+            .arg(data_dir.path())
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to start warpd");
+        Self {
+            child,
+            api,
+            ingress,
+            _data_dir: data_dir,
+        }
+    }
+}
+
+impl Drop for Warpd {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+/// A plain HTTP/1.1 exchange; returns (status, headers, body).
+fn http(addr: SocketAddr, method: &str, path: &str, body: Option<&str>) -> std::io::Result<(u16, String, String)> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(60)))?;
+    let body = body.unwrap_or_default();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    Ok((status, head.to_ascii_lowercase(), body.to_string()))
+}
+
+/// Poll `f` until it returns `Some` or `timeout` passes.
+fn wait_for<T>(timeout: Duration, mut f: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(value) = f() {
+            return Some(value);
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+    None
+}
+
+// ── Toolchain ───────────────────────────────────────────────────────
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+fn has_command(cmd: &str) -> bool {
+    Command::new(cmd)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn has_jco(root: &Path) -> bool {
+    std::env::var_os("WARPGRID_JCO_PATH").is_some()
+        || root.join("build/componentize-js/node_modules/.bin/jco").is_file()
+        || has_command("jco")
+}
+
+fn deployment(artifact: &str, db: SocketAddr, ingress_port: u16) -> DeploymentSpec {
+    DeploymentSpec {
+        id: "default/t7".to_string(),
+        namespace: "default".to_string(),
+        name: "t7".to_string(),
+        source: format!("file://{artifact}"),
+        trigger: TriggerConfig::Http { port: Some(ingress_port), hosts: vec![], path_prefix: None },
+        instances: InstanceConstraints { min: 1, max: 1 },
+        resources: ResourceLimits {
+            memory_bytes: 256 * 1024 * 1024,
+            cpu_weight: 100,
+            execution_budget_ms: None,
+        },
+        scaling: None,
+        health: None,
+        shims: ShimsEnabled {
+            database_proxy: true,
+            ..ShimsEnabled::default()
+        },
+        env: HashMap::from([
+            ("APP_NAME".to_string(), "t7".to_string()),
+            ("DB_HOST".to_string(), db.ip().to_string()),
+            ("DB_PORT".to_string(), db.port().to_string()),
+        ]),
+        created_at: 1000,
+        updated_at: 1000,
+        priority: None,
+        min_available: None,
+        labels: Default::default(),
+        paused: false,
+        secrets: Vec::new(),
+        config_maps: Vec::new(),
+    }
+}
+
+// ── Test ────────────────────────────────────────────────────────────
+
+#[test]
+#[ignore = "requires Bun and ComponentizeJS (jco) toolchains"]
+fn t7_bun_hono_postgres_serves_through_standalone_warpd() {
+    let root = workspace_root();
+    if !has_command("bun") || !has_jco(&root) {
+        eprintln!("bun or jco not found, skipping");
+        return;
+    }
+    let app = root.join("test-apps/t7-bun-hono-postgres");
+
+    let installed = Command::new("bun")
+        .arg("install")
+        .current_dir(&app)
+        .status()
+        .expect("failed to run bun install");
+    assert!(installed.success(), "bun install failed");
+
+    let packed = warp_pack::pack_with_options(
+        &app,
+        &warp_pack::PackOptions {
+            lang: None,
+            no_cache: true,
+        },
+    )
+    .expect("warp pack failed");
+
+    let db = MockPostgresServer::start();
+    let warpd = Warpd::start();
+    wait_for(Duration::from_secs(60), || {
+        http(warpd.api, "GET", "/api/v1/deployments", None)
+            .ok()
+            .filter(|(status, _, _)| *status == 200)
+    })
+    .expect("warpd API did not come up");
+
+    let spec = deployment(&packed.output_path, db.addr, warpd.ingress.port());
+    let (status, _, body) = http(
+        warpd.api,
+        "POST",
+        "/api/v1/deployments",
+        Some(&serde_json::to_string(&spec).unwrap()),
+    )
+    .unwrap();
+    assert_eq!(status, 201, "create deployment: {body}");
+
+    // Routes and apps are picked up by warpd's sync loop.
+    let (_, headers, body) = wait_for(Duration::from_secs(120), || {
+        http(warpd.ingress, "GET", "/health", None)
+            .ok()
+            .filter(|(status, _, _)| *status == 200)
+    })
+    .expect("app never served /health");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+        serde_json::json!({ "status": "ok" })
+    );
+    assert!(headers.contains("x-app-name: t7"), "{headers}");
+
+    let (status, _, body) = http(warpd.ingress, "GET", "/users", None).unwrap();
+    assert_eq!(status, 200, "GET /users: {body}");
+    let users: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(users.len(), 5);
+    assert_eq!(
+        users[0],
+        serde_json::json!({ "id": 1, "name": "Alice Johnson", "email": "alice@example.com" })
+    );
+
+    let (status, _, _) = http(warpd.ingress, "GET", "/nope", None).unwrap();
+    assert_eq!(status, 404);
+}
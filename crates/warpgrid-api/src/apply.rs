@@ -0,0 +1,304 @@
+//! Declarative apply: converge the cluster on a manifest of deployment
+//! specs, the backend of `warp apply`.
+//!
+//! `POST /api/v1/apply` takes every deployment the manifest lists, each a
+//! full spec as for `POST /deployments` (its HTTP route is the trigger,
+//! its scaling the `instances` bounds and `scaling`). The server diffs each
+//! one against the stored spec and returns the plan: a create, an update
+//! with the dotted paths of the fields it changes, or unchanged. With
+//! `prune`, deployments in the manifest's namespaces that it no longer
+//! lists are deleted. `?dry_run=true` returns the plan without changing
+//! anything.
+//!
+//! The whole manifest is checked before anything is written, so an invalid
+//! entry fails the request with nothing applied. A write that fails later
+//! is reported on its change and the rest still go ahead.
+
+use std::collections::BTreeSet;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+use warpgrid_state::{Clock, DeploymentSpec, SystemClock};
+
+use crate::ApiState;
+use crate::configmaps::validate_refs;
+use crate::handlers::{ApiResponse, error_response, remove_deployment, write_deployment};
+use crate::namespaces::is_valid_name;
+
+/// Spec fields the diff leaves out: identity and timestamps.
+const UNDIFFED_FIELDS: [&str; 5] = ["id", "namespace", "name", "created_at", "updated_at"];
+
+/// `POST /apply` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyRequest {
+    /// Every deployment the manifest lists. A spec without a namespace
+    /// goes in the default one.
+    pub deployments: Vec<DeploymentSpec>,
+    /// Delete deployments in the listed namespaces that are not listed.
+    #[serde(default)]
+    pub prune: bool,
+}
+
+/// Query parameters for `POST /apply`.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyQuery {
+    /// Return the plan without changing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyAction {
+    Create,
+    Update,
+    Delete,
+    Unchanged,
+}
+
+/// One deployment's line of the plan.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyChange {
+    pub id: String,
+    pub action: ApplyAction,
+    /// Dotted paths of the fields an update changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// Why applying this change failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// `POST /apply` response.
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyResponse {
+    pub dry_run: bool,
+    pub changes: Vec<ApplyChange>,
+}
+
+/// POST /api/v1/apply
+pub async fn apply(
+    State(state): State<ApiState>,
+    Query(query): Query<ApplyQuery>,
+    Json(mut req): Json<ApplyRequest>,
+) -> Response {
+    let now = SystemClock.epoch_secs();
+    let mut listed = BTreeSet::new();
+    for spec in &mut req.deployments {
+        spec.fill_defaults();
+        if let Err(e) = check(spec) {
+            return error_response(&format!("{}: {e}", spec.id), StatusCode::BAD_REQUEST).into_response();
+        }
+        if !listed.insert(spec.id.clone()) {
+            return error_response(&format!("the manifest lists {} twice", spec.id), StatusCode::BAD_REQUEST)
+                .into_response();
+        }
+    }
+    let current = match state.store.list_deployments() {
+        Ok(current) => current,
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+
+    let mut changes = Vec::new();
+    let mut writes = Vec::new();
+    for mut spec in req.deployments {
+        let existing = current.iter().find(|existing| existing.id == spec.id);
+        let (action, fields) = match existing {
+            None => (ApplyAction::Create, Vec::new()),
+            Some(existing) => match changed_fields(existing, &spec) {
+                fields if fields.is_empty() => (ApplyAction::Unchanged, fields),
+                fields => (ApplyAction::Update, fields),
+            },
+        };
+        changes.push(ApplyChange { id: spec.id.clone(), action, fields, error: None });
+        if action != ApplyAction::Unchanged {
+            spec.created_at = existing.map_or(now, |existing| existing.created_at);
+            spec.updated_at = now;
+            writes.push((changes.len() - 1, Some(spec)));
+        }
+    }
+    if req.prune {
+        let namespaces: BTreeSet<&str> = listed.iter().filter_map(|id| id.split_once('/')).map(|(ns, _)| ns).collect();
+        for spec in &current {
+            if namespaces.contains(spec.namespace.as_str()) && !listed.contains(&spec.id) {
+                changes.push(ApplyChange {
+                    id: spec.id.clone(),
+                    action: ApplyAction::Delete,
+                    fields: Vec::new(),
+                    error: None,
+                });
+                writes.push((changes.len() - 1, None));
+            }
+        }
+    }
+
+    if !query.dry_run {
+        for (index, spec) in writes {
+            let result = match spec {
+                Some(spec) => write_deployment(&state.store, &spec),
+                None => remove_deployment(&state.store, &changes[index].id).map(|_| ()),
+            };
+            if let Err(e) = result {
+                changes[index].error = Some(e.to_string());
+            }
+        }
+    }
+    ApiResponse::ok(ApplyResponse { dry_run: query.dry_run, changes }).into_response()
+}
+
+/// Reject a spec `POST /deployments` would store but nothing could serve.
+fn check(spec: &DeploymentSpec) -> Result<(), String> {
+    if !is_valid_name(&spec.namespace) || !is_valid_name(&spec.name) {
+        return Err("namespace and name must be 1-128 letters, digits, '.', '_' or '-'".to_string());
+    }
+    if spec.id != spec.table_key() {
+        return Err(format!("id must be {}", spec.table_key()));
+    }
+    if spec.instances.max < spec.instances.min {
+        return Err(format!("instances.max ({}) is below instances.min ({})", spec.instances.max, spec.instances.min));
+    }
+    validate_refs(&spec.config_maps)
+}
+
+/// Dotted paths of the fields that differ between two specs, leaving out
+/// [`UNDIFFED_FIELDS`]. A missing field counts as null.
+fn changed_fields(current: &DeploymentSpec, desired: &DeploymentSpec) -> Vec<String> {
+    let current = serde_json::to_value(current).expect("specs serialize");
+    let desired = serde_json::to_value(desired).expect("specs serialize");
+    let keys: BTreeSet<&String> = current.as_object().into_iter().chain(desired.as_object()).flat_map(|o| o.keys()).collect();
+    let mut fields = Vec::new();
+    for key in keys.into_iter().filter(|key| !UNDIFFED_FIELDS.contains(&key.as_str())) {
+        diff(key, &current[key], &desired[key], &mut fields);
+    }
+    fields
+}
+
+fn diff(path: &str, current: &Value, desired: &Value, fields: &mut Vec<String>) {
+    match (current, desired) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                diff(&format!("{path}.{key}"), &current[key], &desired[key], fields);
+            }
+        }
+        // An empty map and a missing one mean the same thing.
+        (Value::Object(map), Value::Null) | (Value::Null, Value::Object(map)) if map.is_empty() => {}
+        _ if current != desired => fields.push(path.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warpgrid_state::{InstanceConstraints, ResourceLimits, ShimsEnabled, StateStore, TriggerConfig};
+
+    fn spec(namespace: &str, name: &str, max: u32) -> DeploymentSpec {
+        DeploymentSpec {
+            id: String::new(),
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            source: "file://test.wasm".to_string(),
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min: 1, max },
+            resources: ResourceLimits { memory_bytes: 64 * 1024 * 1024, cpu_weight: 100, execution_budget_ms: None },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: Default::default(),
+            created_at: 1000,
+            updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        }
+    }
+
+    async fn apply_manifest(state: &ApiState, deployments: Vec<DeploymentSpec>, prune: bool, dry_run: bool) -> Value {
+        let response =
+            apply(State(state.clone()), Query(ApplyQuery { dry_run }), Json(ApplyRequest { deployments, prune })).await;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(status.is_success(), "{body}");
+        body["data"]["changes"].clone()
+    }
+
+    #[tokio::test]
+    async fn dashboard_serves_apply_for_the_wizard() {
+        use tower::ServiceExt;
+
+        let store = StateStore::open_in_memory().unwrap();
+        let router = crate::build_router(store.clone());
+        let body = serde_json::json!({ "deployments": [spec("prod", "api", 2)] });
+        let req = axum::http::Request::post("/dashboard/apply?dry_run=true")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let response = router.oneshot(req).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["data"]["changes"][0]["action"], "create");
+        assert!(store.get_deployment("prod/api").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn plans_then_converges() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        let mut api = spec("prod", "api", 3);
+        api.fill_defaults();
+        state.store.put_deployment(&api).unwrap();
+        let mut legacy = spec("prod", "legacy", 1);
+        legacy.fill_defaults();
+        state.store.put_deployment(&legacy).unwrap();
+        let mut other = spec("staging", "api", 1);
+        other.fill_defaults();
+        state.store.put_deployment(&other).unwrap();
+
+        let manifest = vec![spec("prod", "api", 5), spec("prod", "worker", 1)];
+        let changes = apply_manifest(&state, manifest.clone(), true, true).await;
+        assert_eq!(
+            changes,
+            serde_json::json!([
+                {"id": "prod/api", "action": "update", "fields": ["instances.max"]},
+                {"id": "prod/worker", "action": "create"},
+                {"id": "prod/legacy", "action": "delete"},
+            ])
+        );
+        assert_eq!(state.store.get_deployment("prod/api").unwrap().unwrap().instances.max, 3);
+
+        apply_manifest(&state, manifest.clone(), true, false).await;
+        let stored = state.store.get_deployment("prod/api").unwrap().unwrap();
+        assert_eq!((stored.instances.max, stored.created_at), (5, 1000));
+        assert!(state.store.get_deployment("prod/worker").unwrap().is_some());
+        assert!(state.store.get_deployment("prod/legacy").unwrap().is_none());
+        assert!(state.store.get_deployment("staging/api").unwrap().is_some());
+
+        let changes = apply_manifest(&state, manifest, true, false).await;
+        let actions: Vec<_> = changes.as_array().unwrap().iter().map(|c| c["action"].clone()).collect();
+        assert_eq!(actions, ["unchanged", "unchanged"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_manifests_whole() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        for manifest in [
+            vec![spec("prod", "api", 1), spec("prod", "api", 2)],
+            vec![spec("prod", "api", 1), spec("prod", "bad name", 1)],
+            vec![spec("prod", "api", 1), spec("prod", "web", 0)],
+        ] {
+            let response =
+                apply(State(state.clone()), Query(ApplyQuery::default()), Json(ApplyRequest { deployments: manifest, prune: false }))
+                    .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(state.store.list_deployments().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+//! Uploading wasm components, so a deployment's artifact no longer has to
+//! be on the node's filesystem already.
+//!
+//! `POST /api/v1/artifacts` takes either:
+//!
+//! - the component itself as the body (`application/wasm` or
+//!   `application/octet-stream`, chunked or not), with `?sha256=` to check
+//!   it against, or
+//! - `multipart/form-data` with an `artifact` part, an optional
+//!   `signature` part holding its sigstore bundle (as `warp pack` writes
+//!   to `handler.wasm.sigstore.json`), and an optional `sha256` part.
+//!
+//! The body is streamed to disk while it is hashed. The upload is refused
+//! when it is not a wasm binary, is over [`MAX_ARTIFACT_BYTES`], or its
+//! digest differs from the one given. Artifacts are stored by digest, the
+//! bundle next to them, so the runtime checks the signature again each
+//! time it loads one. The response's `source` is a `file://` URI to put in
+//! a deployment spec; it names a file on this daemon's disk.
+//!
+//! The API keeps no files of its own: warpd attaches an [`ArtifactStore`]
+//! with [`axum::Extension`], checking uploads against its signature
+//! policy. Without one the endpoint answers 503.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Multipart, Query, Request};
+use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use futures_util::{Stream, StreamExt};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::handlers::{ApiResponse, error_response};
+
+/// Largest component accepted.
+pub const MAX_ARTIFACT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Largest signature bundle accepted.
+const MAX_BUNDLE_BYTES: usize = 1024 * 1024;
+
+/// Every wasm binary starts with `\0asm`.
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Checks an artifact written to `path`, with its bundle (if any) at
+/// `<path>.sigstore.json`, before it is kept.
+pub trait ArtifactVerifier: Send + Sync {
+    fn verify(&self, path: &Path) -> Result<(), String>;
+}
+
+/// Where uploaded artifacts are kept.
+#[derive(Clone)]
+pub struct ArtifactStore {
+    dir: PathBuf,
+    verifier: Option<Arc<dyn ArtifactVerifier>>,
+}
+
+impl ArtifactStore {
+    /// Keep artifacts in `dir`, creating it.
+    pub fn open(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.canonicalize()?, verifier: None })
+    }
+
+    /// Check each upload with `verifier` before keeping it.
+    pub fn with_verifier(mut self, verifier: Arc<dyn ArtifactVerifier>) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Path an artifact with this hex digest is stored at.
+    pub fn path(&self, digest: &str) -> PathBuf {
+        self.dir.join(format!("{digest}.wasm"))
+    }
+}
+
+/// Query parameters for `POST /artifacts`.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct UploadQuery {
+    /// Hex SHA-256 the upload must have.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// `POST /artifacts` response.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ArtifactInfo {
+    /// `sha256:` and the hex digest.
+    pub digest: String,
+    pub size: u64,
+    /// `file://` URI for a deployment spec's `source`.
+    pub source: String,
+    /// Whether a signature bundle was stored with it.
+    pub signed: bool,
+}
+
+/// Why an upload was refused: status and message.
+type UploadError = (StatusCode, String);
+
+/// An upload being written under a temporary name. Whatever is still
+/// under that name when it is dropped — a refused upload, or one whose
+/// client went away mid-stream — is removed.
+struct Staged {
+    path: PathBuf,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl Staged {
+    fn bundle_path(&self) -> PathBuf {
+        bundle_path(&self.path)
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        let _ = std::fs::remove_file(self.bundle_path());
+    }
+}
+
+/// POST /api/v1/artifacts
+pub async fn upload_artifact(
+    store: Option<Extension<ArtifactStore>>,
+    Query(query): Query<UploadQuery>,
+    req: Request,
+) -> Response {
+    let Some(Extension(store)) = store else {
+        return error_response("this server does not store artifacts", StatusCode::SERVICE_UNAVAILABLE)
+            .into_response();
+    };
+    let path = store.dir.join(format!(".upload-{}", random_suffix()));
+    let staged = Staged { path, hasher: Sha256::new(), size: 0 };
+    match receive(&store, staged, query.sha256, req).await {
+        Ok(info) => (StatusCode::CREATED, ApiResponse::ok(info)).into_response(),
+        Err((status, msg)) => error_response(&msg, status).into_response(),
+    }
+}
+
+async fn receive(
+    store: &ArtifactStore,
+    mut staged: Staged,
+    mut expected: Option<String>,
+    req: Request,
+) -> Result<ArtifactInfo, UploadError> {
+    let multipart = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    let result = if multipart {
+        receive_multipart(&mut staged, &mut expected, req).await
+    } else {
+        write_stream(&mut staged, req.into_body().into_data_stream()).await
+    };
+    let result = match result {
+        // Verifying may hash or parse the whole file; keep it off the runtime.
+        Ok(()) => {
+            let store = store.clone();
+            tokio::task::spawn_blocking(move || keep(&store, staged, expected.as_deref()))
+                .await
+                .unwrap_or_else(|e| Err((StatusCode::INTERNAL_SERVER_ERROR, format!("storing the upload: {e}"))))
+        }
+        // Dropping `staged` removes what was written.
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        tracing::warn!(error = %e.1, "artifact upload refused");
+    }
+    result
+}
+
+async fn receive_multipart(
+    staged: &mut Staged,
+    expected: &mut Option<String>,
+    req: Request,
+) -> Result<(), UploadError> {
+    let mut multipart = Multipart::from_request(req, &()).await.map_err(|e| (e.status(), e.body_text()))?;
+    let mut artifact = false;
+    while let Some(field) = multipart.next_field().await.map_err(|e| (e.status(), e.body_text()))? {
+        match field.name() {
+            // A second part would be hashed on top of the first while it
+            // replaced it on disk.
+            Some("artifact") if artifact => return Err(bad_request("more than one artifact part".to_string())),
+            Some("artifact") => {
+                write_stream(staged, field).await?;
+                artifact = true;
+            }
+            Some("signature") => {
+                let bundle = field.bytes().await.map_err(|e| (e.status(), e.body_text()))?;
+                if bundle.len() > MAX_BUNDLE_BYTES {
+                    return Err(bad_request(format!("signature bundle is over {MAX_BUNDLE_BYTES} bytes")));
+                }
+                tokio::fs::write(staged.bundle_path(), &bundle).await.map_err(internal)?;
+            }
+            Some("sha256") => *expected = Some(field.text().await.map_err(|e| (e.status(), e.body_text()))?),
+            other => return Err(bad_request(format!("unexpected part {:?}", other.unwrap_or("")))),
+        }
+    }
+    if !artifact {
+        return Err(bad_request("missing the artifact part".to_string()));
+    }
+    Ok(())
+}
+
+/// Write `body` to the staged file, hashing it as it goes.
+async fn write_stream<S, E>(staged: &mut Staged, body: S) -> Result<(), UploadError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::fmt::Display,
+{
+    let mut body = std::pin::pin!(body);
+    let mut file = tokio::fs::File::create(&staged.path).await.map_err(internal)?;
+    let mut head: Vec<u8> = Vec::with_capacity(WASM_MAGIC.len());
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| bad_request(format!("reading the upload: {e}")))?;
+        staged.size += chunk.len() as u64;
+        if staged.size > MAX_ARTIFACT_BYTES {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("artifact is over {MAX_ARTIFACT_BYTES} bytes")));
+        }
+        if head.len() < WASM_MAGIC.len() {
+            head.extend(chunk.iter().take(WASM_MAGIC.len() - head.len()));
+        }
+        staged.hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(internal)?;
+    }
+    file.flush().await.map_err(internal)?;
+    if head != WASM_MAGIC {
+        return Err(bad_request("not a wasm binary".to_string()));
+    }
+    Ok(())
+}
+
+/// Check the staged upload and move it to its digest's name.
+///
+/// A bundle that came with it, now verified, replaces any kept from an
+/// earlier upload of the same bytes; one without a bundle leaves an
+/// earlier bundle in place, since it still signs those bytes.
+fn keep(store: &ArtifactStore, staged: Staged, expected: Option<&str>) -> Result<ArtifactInfo, UploadError> {
+    let digest = check(store, &staged, expected)?;
+    let path = store.path(&digest);
+    std::fs::rename(&staged.path, &path).map_err(internal)?;
+    if staged.bundle_path().is_file() {
+        std::fs::rename(staged.bundle_path(), bundle_path(&path)).map_err(internal)?;
+    }
+    let signed = bundle_path(&path).is_file();
+    tracing::info!(digest = %digest, size = staged.size, signed, "artifact stored");
+    Ok(ArtifactInfo {
+        digest: format!("sha256:{digest}"),
+        size: staged.size,
+        source: format!("file://{}", path.display()),
+        signed,
+    })
+}
+
+/// The upload's hex digest, once it matches `expected` and passes the
+/// store's verifier.
+fn check(store: &ArtifactStore, staged: &Staged, expected: Option<&str>) -> Result<String, UploadError> {
+    let digest = hex::encode(staged.hasher.clone().finalize());
+    if let Some(expected) = expected {
+        let expected = expected.trim();
+        let expected = expected.strip_prefix("sha256:").unwrap_or(expected);
+        if !expected.eq_ignore_ascii_case(&digest) {
+            return Err(bad_request(format!("sha256 mismatch: expected {expected}, got {digest}")));
+        }
+    }
+    if let Some(verifier) = &store.verifier {
+        verifier.verify(&staged.path).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+    Ok(digest)
+}
+
+/// Where the runtime looks for an artifact's sigstore bundle.
+fn bundle_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".sigstore.json");
+    PathBuf::from(name)
+}
+
+fn random_suffix() -> String {
+    let mut bytes = [0u8; 8];
+    // Only keeps concurrent uploads apart; a failure just risks a clash.
+    let _ = getrandom::getrandom(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn bad_request(msg: String) -> UploadError {
+    (StatusCode::BAD_REQUEST, msg)
+}
+
+fn internal(e: std::io::Error) -> UploadError {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    const COMPONENT: &[u8] = b"\0asm\x0d\0\x01\0";
+
+    struct RejectUnsigned;
+
+    impl ArtifactVerifier for RejectUnsigned {
+        fn verify(&self, path: &Path) -> Result<(), String> {
+            match bundle_path(path).is_file() {
+                true => Ok(()),
+                false => Err("unsigned".to_string()),
+            }
+        }
+    }
+
+    async fn upload(store: &ArtifactStore, sha256: Option<String>, req: Request) -> (StatusCode, serde_json::Value) {
+        let response = upload_artifact(Some(Extension(store.clone())), Query(UploadQuery { sha256 }), req).await;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn raw(body: &'static [u8]) -> Request {
+        Request::post("/api/v1/artifacts").header(CONTENT_TYPE, "application/wasm").body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn raw_uploads_are_stored_by_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        let digest = hex::encode(Sha256::digest(COMPONENT));
+
+        let (status, body) = upload(&store, Some(format!("sha256:{digest}")), raw(COMPONENT)).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        assert_eq!(body["data"]["digest"], format!("sha256:{digest}"));
+        assert_eq!(body["data"]["source"], format!("file://{}", store.path(&digest).display()));
+        assert_eq!(std::fs::read(store.path(&digest)).unwrap(), COMPONENT);
+
+        let (status, _) = upload(&store, Some("00".repeat(32)), raw(COMPONENT)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = upload(&store, None, raw(b"#!/bin/sh")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        // Refused uploads leave nothing behind.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn multipart_uploads_keep_their_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap().with_verifier(Arc::new(RejectUnsigned));
+        let multipart = |parts: &[(&str, &[u8])]| {
+            let mut body = Vec::new();
+            for (name, value) in parts {
+                body.extend(format!("--XX\r\ncontent-disposition: form-data; name=\"{name}\"\r\n\r\n").as_bytes());
+                body.extend(*value);
+                body.extend(b"\r\n");
+            }
+            body.extend(b"--XX--\r\n");
+            Request::post("/api/v1/artifacts")
+                .header(CONTENT_TYPE, "multipart/form-data; boundary=XX")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let (status, body) = upload(&store, None, multipart(&[("artifact", COMPONENT)])).await;
+        assert_eq!((status, body["error"].as_str()), (StatusCode::UNPROCESSABLE_ENTITY, Some("unsigned")));
+
+        let (status, body) = upload(&store, None, multipart(&[("artifact", COMPONENT), ("signature", b"{}")])).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        assert_eq!(body["data"]["signed"], true);
+        let digest = hex::encode(Sha256::digest(COMPONENT));
+        assert_eq!(std::fs::read(bundle_path(&store.path(&digest))).unwrap(), b"{}");
+
+        let parts: &[(&str, &[u8])] = &[("artifact", COMPONENT), ("signature", b"{}"), ("artifact", COMPONENT)];
+        let (status, body) = upload(&store, None, multipart(parts)).await;
+        assert_eq!((status, body["error"].as_str()), (StatusCode::BAD_REQUEST, Some("more than one artifact part")));
+        // Only the stored artifact and its bundle are left.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn unsigned_reuploads_keep_the_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        let digest = hex::encode(Sha256::digest(COMPONENT));
+        std::fs::write(bundle_path(&store.path(&digest)), b"{}").unwrap();
+
+        let (status, body) = upload(&store, None, raw(COMPONENT)).await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        assert_eq!(body["data"]["signed"], true);
+        assert_eq!(std::fs::read(bundle_path(&store.path(&digest))).unwrap(), b"{}");
+    }
+
+    #[tokio::test]
+    async fn abandoned_uploads_leave_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        // The client sends the start of the component, then goes quiet.
+        let head = futures_util::stream::iter([Ok::<_, std::io::Error>(Bytes::from_static(COMPONENT))]);
+        let req = Request::post("/api/v1/artifacts")
+            .header(CONTENT_TYPE, "application/wasm")
+            .body(Body::from_stream(head.chain(futures_util::stream::pending())))
+            .unwrap();
+        let task = tokio::spawn({
+            let store = store.clone();
+            async move { upload(&store, None, req).await }
+        });
+        while std::fs::read_dir(dir.path()).unwrap().count() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        // The connection drops, and the handler with it.
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,287 @@
+//! API tokens: bearer-token authentication for `/api/v1`.
+//!
+//! - `GET /api/v1/tokens` lists tokens (id, name, creation time)
+//! - `POST /api/v1/tokens` creates one and returns it, the only time the
+//!   token itself is shown
+//! - `DELETE /api/v1/tokens/{id}` revokes one
+//!
+//! Tokens are `wgt_` and 64 hex characters. The store keeps only their
+//! SHA-256 digest, keyed by it, so checking a request is one lookup; a
+//! token's id is the first 12 hex characters of its digest.
+//!
+//! [`require_token`] is opt-in: warpd layers it on the management router
+//! with [`require_tokens`] when started with `--api-tokens`. It guards
+//! `/api/v1`, except the OpenAPI document, and every dashboard request
+//! that changes state, which may carry the token in the dashboard's
+//! sign-in cookie instead (see [`warpgrid_dashboard::session`]). Dashboard
+//! pages, `/metrics`, and the health probes are left to the planes config.
+
+use axum::extract::{Path, Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use sha2::{Digest, Sha256};
+use warpgrid_dashboard::session;
+use warpgrid_state::{ApiToken, Clock, StateStore, SystemClock};
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+use crate::openapi::OPENAPI_PATH;
+
+/// Prefix of every API token, so leaked tokens are easy to scan for.
+pub const TOKEN_PREFIX: &str = "wgt_";
+
+/// Path prefix the middleware guards.
+const API_PREFIX: &str = "/api/v1/";
+
+/// Path prefix whose state-changing requests the middleware guards.
+const DASHBOARD_PREFIX: &str = "/dashboard/";
+
+/// A token as listed: everything but its digest.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TokenInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+}
+
+impl From<&ApiToken> for TokenInfo {
+    fn from(token: &ApiToken) -> Self {
+        Self { id: token.id.clone(), name: token.name.clone(), created_at: token.created_at }
+    }
+}
+
+/// `POST /tokens` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateTokenRequest {
+    pub name: String,
+}
+
+/// `POST /tokens` response.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    pub info: TokenInfo,
+    pub token: String,
+}
+
+/// Generate a token named `name` and store its digest.
+pub fn create_token(store: &StateStore, name: &str) -> Result<CreatedToken, String> {
+    let mut secret = [0u8; 32];
+    getrandom::getrandom(&mut secret).map_err(|e| e.to_string())?;
+    let token = format!("{TOKEN_PREFIX}{}", hex::encode(secret));
+    let digest = digest(&token);
+    let record = ApiToken {
+        id: digest[..12].to_string(),
+        name: name.to_string(),
+        digest,
+        created_at: SystemClock.epoch_secs(),
+    };
+    store.put_api_token(&record).map_err(|e| e.to_string())?;
+    Ok(CreatedToken { info: TokenInfo::from(&record), token })
+}
+
+pub(crate) fn digest(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Guard every `/api/v1` route of `router` with [`require_token`].
+pub fn require_tokens(router: Router, store: StateStore) -> Router {
+    router.layer(middleware::from_fn_with_state(store, require_token))
+}
+
+/// Middleware: pass `/api/v1` requests only with the bearer token of a
+/// stored API token. `/api/v1/openapi.json` is open, so clients can be
+/// generated before a token exists. Dashboard requests other than reads,
+/// signing in and signing out need a token too, as a bearer or in the
+/// sign-in cookie.
+pub async fn require_token(State(store): State<StateStore>, req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    let dashboard = path.starts_with(DASHBOARD_PREFIX)
+        && !req.method().is_safe()
+        && path != session::LOGIN_PATH
+        && path != session::LOGOUT_PATH;
+    if !dashboard && (!path.starts_with(API_PREFIX) || path == OPENAPI_PATH) {
+        return next.run(req).await;
+    }
+    let token = match bearer(req.headers()) {
+        None if dashboard => session::cookie_token(req.headers()),
+        token => token,
+    };
+    let Some(token) = token else {
+        return if dashboard { sign_in("sign in to make changes") } else { unauthorized("missing bearer token") };
+    };
+    match store.get_api_token(&digest(token)) {
+        Ok(Some(_)) => next.run(req).await,
+        Ok(None) if dashboard => sign_in("invalid or revoked token"),
+        Ok(None) => unauthorized("invalid or revoked token"),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+pub(crate) fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers.get(AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ")
+}
+
+fn unauthorized(msg: &str) -> Response {
+    let mut response = error_response(msg, StatusCode::UNAUTHORIZED).into_response();
+    response.headers_mut().insert(WWW_AUTHENTICATE, "Bearer".parse().expect("valid header"));
+    response
+}
+
+/// A dashboard request without a valid token: 401, and HTMX is sent to
+/// the sign-in page.
+fn sign_in(msg: &str) -> Response {
+    let mut response = unauthorized(msg);
+    response.headers_mut().insert("HX-Redirect", session::LOGIN_PATH.parse().expect("valid header"));
+    response
+}
+
+/// GET /api/v1/tokens
+pub async fn list_tokens(State(state): State<ApiState>) -> Response {
+    match state.store.list_api_tokens() {
+        Ok(mut tokens) => {
+            tokens.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+            ApiResponse::ok(tokens.iter().map(TokenInfo::from).collect::<Vec<_>>()).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/tokens
+pub async fn post_token(State(state): State<ApiState>, Json(req): Json<CreateTokenRequest>) -> Response {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 128 {
+        return error_response("token name must be 1-128 characters", StatusCode::BAD_REQUEST).into_response();
+    }
+    match create_token(&state.store, name) {
+        Ok(created) => (StatusCode::CREATED, ApiResponse::ok(created)).into_response(),
+        Err(e) => error_response(&e, StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// DELETE /api/v1/tokens/:id
+pub async fn delete_token(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    match state.store.delete_api_token(&id) {
+        Ok(true) => ApiResponse::ok("revoked").into_response(),
+        Ok(false) => error_response("token not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn router(store: &StateStore) -> Router {
+        let rollouts = Default::default();
+        require_tokens(crate::build_router_with_rollouts(store.clone(), rollouts), store.clone())
+    }
+
+    async fn call(router: &Router, method: &str, path: &str, token: Option<&str>) -> (StatusCode, serde_json::Value) {
+        let mut req = Request::builder().method(method).uri(path);
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let body = if method == "POST" { Body::from(r#"{"name":"ci"}"#) } else { Body::empty() };
+        let req = req.header("content-type", "application/json").body(body).unwrap();
+        let resp = router.clone().oneshot(req).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn api_routes_need_a_stored_token() {
+        let store = StateStore::open_in_memory().unwrap();
+        let router = router(&store);
+        let admin = create_token(&store, "admin").unwrap();
+        assert!(admin.token.starts_with(TOKEN_PREFIX));
+        assert_eq!(store.get_api_token(&digest(&admin.token)).unwrap().unwrap().id, admin.info.id);
+
+        let (status, body) = call(&router, "GET", "/api/v1/deployments", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "missing bearer token");
+        let (status, _) = call(&router, "GET", "/api/v1/deployments", Some("wgt_guess")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&router, "GET", "/api/v1/deployments", Some(&admin.token)).await;
+        assert_eq!(status, StatusCode::OK);
+        // Outside /api/v1 is left alone.
+        let resp = router.clone().oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn dashboard_changes_need_a_token_but_pages_do_not() {
+        let store = StateStore::open_in_memory().unwrap();
+        let router = router(&store);
+        let admin = create_token(&store, "admin").unwrap().token;
+        let teardown = |cookie: Option<String>| {
+            let mut req = Request::post("/dashboard/density-demo/teardown");
+            if let Some(cookie) = cookie {
+                req = req.header(axum::http::header::COOKIE, cookie);
+            }
+            req.body(Body::empty()).unwrap()
+        };
+
+        let resp = router.clone().oneshot(teardown(None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers()["HX-Redirect"], session::LOGIN_PATH);
+        let guess = Some(format!("{}=wgt_guess", session::TOKEN_COOKIE));
+        let resp = router.clone().oneshot(teardown(guess)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let signed_in = Some(format!("{}={admin}", session::TOKEN_COOKIE));
+        let resp = router.clone().oneshot(teardown(signed_in)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+
+        // Pages and signing in stay open.
+        let resp = router.clone().oneshot(Request::get("/dashboard").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let login = Request::post(session::LOGIN_PATH)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!("token={admin}")))
+            .unwrap();
+        let resp = router.clone().oneshot(login).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        let cookie = resp.headers()[axum::http::header::SET_COOKIE].to_str().unwrap();
+        assert!(cookie.starts_with(&format!("{}={admin};", session::TOKEN_COOKIE)), "{cookie}");
+        assert!(cookie.contains("HttpOnly") && cookie.contains("SameSite=Strict"), "{cookie}");
+
+        // A revoked or guessed cookie can still be cleared.
+        let logout = Request::post(session::LOGOUT_PATH)
+            .header("cookie", format!("{}=wgt_guess", session::TOKEN_COOKIE))
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.clone().oneshot(logout).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    }
+
+    #[tokio::test]
+    async fn tokens_are_created_listed_and_revoked() {
+        let store = StateStore::open_in_memory().unwrap();
+        let router = router(&store);
+        let admin = create_token(&store, "admin").unwrap().token;
+
+        let (status, created) = call(&router, "POST", "/api/v1/tokens", Some(&admin)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let ci = created["data"]["token"].as_str().unwrap().to_string();
+        let id = created["data"]["id"].as_str().unwrap().to_string();
+        assert_eq!(created["data"]["name"], "ci");
+
+        let (_, listed) = call(&router, "GET", "/api/v1/tokens", Some(&ci)).await;
+        let tokens = listed["data"].as_array().unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens.iter().all(|t| t.get("digest").is_none() && t.get("token").is_none()));
+
+        let (status, _) = call(&router, "DELETE", &format!("/api/v1/tokens/{id}"), Some(&admin)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, "GET", "/api/v1/tokens", Some(&ci)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&router, "DELETE", &format!("/api/v1/tokens/{id}"), Some(&admin)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
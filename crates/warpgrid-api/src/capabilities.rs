@@ -0,0 +1,146 @@
+//! What the cluster can run, for pre-flight checks and feature detection.
+//!
+//! `GET /api/v1/capabilities` returns a [`Capabilities`] document: the WIT
+//! worlds a component can target, the shim interfaces and whether each is
+//! enabled, optional features, and limits. warpd builds the document from
+//! its runtime configuration and attaches it to the router with
+//! [`axum::Extension`]; without one the endpoint reports only what the API
+//! itself knows. `limits.max_instance_memory_bytes` is filled in from the
+//! largest node in the state store on every request.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+
+/// Version of the REST API served under `/api/v1`.
+pub const API_VERSION: &str = "v1";
+
+/// The capability report.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Capabilities {
+    pub api_version: String,
+    /// warpd version.
+    pub version: String,
+    /// Worlds a component can target, e.g. `wasi:http/proxy@0.2.6`.
+    pub worlds: Vec<String>,
+    pub shims: Vec<ShimInterface>,
+    pub features: Features,
+    pub limits: Limits,
+}
+
+/// One `warpgrid:shim` interface.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ShimInterface {
+    /// Fully qualified, e.g. `warpgrid:shim/dns`.
+    pub name: String,
+    pub version: String,
+    /// Disabled interfaces are not linked; components importing them fail
+    /// to instantiate.
+    pub enabled: bool,
+}
+
+/// Optional features.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Features {
+    /// Response bodies reach the client as the guest writes them.
+    pub response_streaming: bool,
+    pub websockets: bool,
+    pub wasi_nn: bool,
+    /// Guest CPU time is metered per request and route.
+    pub cpu_metering: bool,
+    /// Guest stdout/stderr is captured for `/deployments/{id}/logs`.
+    pub guest_logs: bool,
+    /// Deployments can ship config bundles (`/deployments/{id}/config`).
+    pub config_bundles: bool,
+}
+
+/// Limits a deployment has to fit in. `None` when unknown.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Limits {
+    pub max_response_body_bytes: Option<u64>,
+    pub max_response_headers: Option<usize>,
+    pub max_response_header_bytes: Option<usize>,
+    /// Memory of the largest node: no instance can be given more.
+    pub max_instance_memory_bytes: Option<u64>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            worlds: Vec::new(),
+            shims: Vec::new(),
+            features: Features::default(),
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// GET /api/v1/capabilities
+pub async fn get_capabilities(
+    State(state): State<ApiState>,
+    capabilities: Option<Extension<Arc<Capabilities>>>,
+) -> Response {
+    let mut capabilities = capabilities.map(|Extension(c)| (*c).clone()).unwrap_or_default();
+    match state.store.list_nodes() {
+        Ok(nodes) => {
+            capabilities.limits.max_instance_memory_bytes =
+                nodes.iter().map(|node| node.capacity_memory_bytes).max();
+            ApiResponse::ok(capabilities).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use warpgrid_state::{NodeInfo, StateStore};
+
+    fn node(id: &str, memory: u64) -> NodeInfo {
+        NodeInfo {
+            id: id.to_string(),
+            address: "127.0.0.1".to_string(),
+            port: 9000,
+            capacity_memory_bytes: memory,
+            capacity_cpu_weight: 1000,
+            used_memory_bytes: 0,
+            used_cpu_weight: 0,
+            labels: HashMap::new(),
+            last_heartbeat: 0,
+            pressure_until: None,
+            replica_revision: None,
+            calibrated_cpu_weight: None,
+            cordoned: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_the_attached_document_and_node_memory() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_node(&node("a", 4 << 30)).unwrap();
+        store.put_node(&node("b", 16 << 30)).unwrap();
+        let attached = Capabilities {
+            worlds: vec!["wasi:http/proxy@0.2.6".to_string()],
+            features: Features { response_streaming: true, ..Default::default() },
+            ..Default::default()
+        };
+
+        let response = get_capabilities(State(ApiState { store }), Some(Extension(Arc::new(attached)))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["data"]["api_version"], "v1");
+        assert_eq!(body["data"]["worlds"][0], "wasi:http/proxy@0.2.6");
+        assert_eq!(body["data"]["features"]["response_streaming"], true);
+        assert_eq!(body["data"]["limits"]["max_instance_memory_bytes"], 16u64 << 30);
+    }
+}
@@ -0,0 +1,288 @@
+//! Config maps: named configuration that deployments read as files, and
+//! that can change without a redeploy.
+//!
+//! - `GET /api/v1/configmaps?namespace=` lists a namespace's config maps
+//! - `POST /api/v1/configmaps` creates one, refusing to replace one
+//! - `GET /api/v1/configmaps/{id}` returns one
+//! - `PUT /api/v1/configmaps/{id}` creates or replaces one's data
+//! - `DELETE /api/v1/configmaps/{id}` removes it
+//!
+//! Ids are `{namespace}/{name}`, as for secrets. A deployment binds a
+//! config map with an entry in its `config_maps` list, and each key then
+//! appears as a file under the entry's `path`, served from the deployment's
+//! config bundle. Writing or deleting a config map stages a new bundle for
+//! every deployment bound to it; instances switch to it at the config
+//! barrier and receive `SIGHUP`, as for `PUT /deployments/{id}/config`.
+
+use std::collections::BTreeMap;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use warpgrid_state::{Clock, ConfigMap, ConfigMapRef, DEFAULT_NAMESPACE, DeploymentSpec, StateStore, SystemClock};
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+use crate::namespaces::is_valid_name;
+
+/// `POST /configmaps` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateConfigMapRequest {
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub name: String,
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+}
+
+/// `PUT /configmaps/{id}` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PutConfigMapRequest {
+    pub data: BTreeMap<String, String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ConfigMapsQuery {
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+}
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+/// GET /api/v1/configmaps
+pub async fn list_config_maps(State(state): State<ApiState>, Query(query): Query<ConfigMapsQuery>) -> Response {
+    match state.store.list_config_maps(&query.namespace) {
+        Ok(config_maps) => ApiResponse::ok(config_maps).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/configmaps
+pub async fn create_config_map(
+    State(state): State<ApiState>,
+    Json(req): Json<CreateConfigMapRequest>,
+) -> Response {
+    let id = format!("{}/{}", req.namespace, req.name);
+    match state.store.get_config_map(&id) {
+        Ok(Some(_)) => {
+            return error_response(&format!("config map {id} already exists"), StatusCode::CONFLICT).into_response();
+        }
+        Ok(None) => {}
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+    match save(&state.store, &id, req.data) {
+        Ok(config_map) => (StatusCode::CREATED, ApiResponse::ok(config_map)).into_response(),
+        Err((status, message)) => error_response(&message, status).into_response(),
+    }
+}
+
+/// GET /api/v1/configmaps/:id
+pub async fn get_config_map(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    match state.store.get_config_map(&id) {
+        Ok(Some(config_map)) => ApiResponse::ok(config_map).into_response(),
+        Ok(None) => error_response("config map not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// PUT /api/v1/configmaps/:id
+pub async fn put_config_map(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(req): Json<PutConfigMapRequest>,
+) -> Response {
+    match save(&state.store, &id, req.data) {
+        Ok(config_map) => ApiResponse::ok(config_map).into_response(),
+        Err((status, message)) => error_response(&message, status).into_response(),
+    }
+}
+
+/// DELETE /api/v1/configmaps/:id
+pub async fn delete_config_map(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    match state.store.delete_config_map(&id) {
+        Ok(true) => {
+            if let Some((namespace, name)) = id.split_once('/') {
+                rebind(&state.store, namespace, name);
+            }
+            ApiResponse::ok("deleted").into_response()
+        }
+        Ok(false) => error_response("config map not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Validate and store config map `id`, keeping its creation time, then
+/// restage the deployments bound to it.
+fn save(store: &StateStore, id: &str, data: BTreeMap<String, String>) -> Result<ConfigMap, (StatusCode, String)> {
+    let (namespace, name) = parse_id(id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if let Some(key) = data.keys().find(|key| !is_valid_name(key)) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("invalid config map key '{key}': use 1-128 letters, digits, '.', '_' or '-'"),
+        ));
+    }
+    let internal = |e: warpgrid_state::StateError| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let now = SystemClock.epoch_secs();
+    let created_at = store.get_config_map(id).map_err(internal)?.map_or(now, |existing| existing.created_at);
+    let config_map = ConfigMap {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        data,
+        created_at,
+        updated_at: now,
+    };
+    store.put_config_map(&config_map).map_err(internal)?;
+    rebind(store, namespace, name);
+    Ok(config_map)
+}
+
+/// Restage the bundle of every deployment bound to config map
+/// `namespace/name`, logging failures rather than failing the request.
+fn rebind(store: &StateStore, namespace: &str, name: &str) {
+    let specs = match store.config_map_bindings(namespace, name) {
+        Ok(specs) => specs,
+        Err(e) => {
+            tracing::warn!(config_map = %format!("{namespace}/{name}"), error = %e, "failed to find bound deployments");
+            return;
+        }
+    };
+    for spec in specs {
+        bind(store, &spec, &[]);
+    }
+}
+
+/// Stage `spec`'s config maps into its bundle, clearing the directories of
+/// `unbound` references.
+pub(crate) fn bind(store: &StateStore, spec: &DeploymentSpec, unbound: &[ConfigMapRef]) {
+    if spec.config_maps.is_empty() && unbound.is_empty() {
+        return;
+    }
+    if let Err(e) = store.bind_config_maps(spec, unbound, SystemClock.epoch_secs()) {
+        tracing::warn!(deployment = %spec.id, error = %e, "failed to stage config maps");
+    }
+}
+
+/// Check a deployment's config map references: valid names and absolute
+/// paths without `.` or `..`.
+pub(crate) fn validate_refs(refs: &[ConfigMapRef]) -> Result<(), String> {
+    for reference in refs {
+        if !is_valid_name(&reference.config_map) {
+            return Err(format!("invalid config map name '{}'", reference.config_map));
+        }
+        let path = reference.path.trim_end_matches('/');
+        if !path.starts_with('/') || path.split('/').skip(1).any(|part| matches!(part, "" | "." | "..")) {
+            return Err(format!(
+                "config map path must be an absolute directory without '.' or '..', got '{}'",
+                reference.path
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Split `{namespace}/{name}`, checking both parts.
+fn parse_id(id: &str) -> Result<(&str, &str), String> {
+    let Some((namespace, name)) = id.split_once('/') else {
+        return Err(format!("config map id '{id}' must be namespace/name"));
+    };
+    if !is_valid_name(namespace) {
+        return Err(format!("invalid namespace '{namespace}'"));
+    }
+    if !is_valid_name(name) {
+        return Err(format!("invalid config map name '{name}': use 1-128 letters, digits, '.', '_' or '-'"));
+    }
+    Ok((namespace, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    async fn body(response: Response) -> (StatusCode, Value) {
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn put(state: &ApiState, id: &str, data: &[(&str, &str)]) -> impl std::future::Future<Output = Response> {
+        let data = data.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        put_config_map(State(state.clone()), Path(id.to_string()), Json(PutConfigMapRequest { data }))
+    }
+
+    fn staged_files(store: &StateStore, deployment: &str) -> BTreeMap<String, String> {
+        let staged = store.get_deployment_config(deployment).unwrap().unwrap().staged.unwrap();
+        store.get_config_bundle(&staged.digest).unwrap().unwrap().files
+    }
+
+    #[tokio::test]
+    async fn writes_restage_bound_deployments() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        let spec: DeploymentSpec = serde_json::from_value(serde_json::json!({
+            "id": "default/api",
+            "namespace": "default",
+            "name": "api",
+            "source": "file://test.wasm",
+            "trigger": {"type": "http", "port": null},
+            "instances": {"min": 1, "max": 1},
+            "resources": {"memory_bytes": 67108864, "cpu_weight": 100, "execution_budget_ms": null},
+            "scaling": null,
+            "health": null,
+            "shims": {"timezone": false, "dev_urandom": false, "dns": false, "signals": false, "database_proxy": false},
+            "env": {},
+            "config_maps": [{"config_map": "app", "path": "/etc/app"}],
+            "created_at": 1000,
+            "updated_at": 1000,
+        }))
+        .unwrap();
+        state.store.put_deployment(&spec).unwrap();
+
+        let (status, _) = body(put(&state, "default/app", &[("port", "80")]).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(staged_files(&state.store, "default/api")["/etc/app/port"], "80");
+
+        put(&state, "default/app", &[("port", "81")]).await;
+        assert_eq!(staged_files(&state.store, "default/api")["/etc/app/port"], "81");
+
+        let (status, _) = body(delete_config_map(State(state.clone()), Path("default/app".into())).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(staged_files(&state.store, "default/api").is_empty());
+
+        let (status, _) = body(put(&state, "default/app", &[("bad key", "x")]).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn create_refuses_to_replace() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        let create = || {
+            create_config_map(
+                State(state.clone()),
+                Json(CreateConfigMapRequest { namespace: "prod".into(), name: "app".into(), data: BTreeMap::new() }),
+            )
+        };
+        let (status, created) = body(create().await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["data"]["namespace"], "prod");
+        let (status, _) = body(create().await).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (_, listed) = body(
+            list_config_maps(State(state.clone()), Query(ConfigMapsQuery { namespace: "prod".into() })).await,
+        )
+        .await;
+        assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn validates_refs() {
+        let reference = |name: &str, path: &str| ConfigMapRef { config_map: name.into(), path: path.into() };
+        assert!(validate_refs(&[reference("app", "/etc/app"), reference("app", "/etc/app/")]).is_ok());
+        for bad in [reference("a b", "/etc"), reference("app", "etc/app"), reference("app", "/etc/../app")] {
+            assert!(validate_refs(&[bad]).is_err());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Cluster events, so operators can see why something changed.
+//!
+//! - `GET /api/v1/events` returns recorded events, oldest first
+//! - `GET /api/v1/events?watch=true` sends them as `event` server-sent
+//!   events, then keeps sending new ones as they are recorded
+//!
+//! Both take `deployment`, `node`, and `kind` filters, `after` (an event
+//! sequence to resume from), and `limit`. Without `after`, the newest
+//! `limit` events are returned. Events are recorded by the API handlers,
+//! the health monitor, and cluster membership; the store keeps the last
+//! [`CLUSTER_EVENT_RETENTION`].
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use warpgrid_state::{CLUSTER_EVENT_RETENTION, ClusterEvent, ClusterEventKind, StateResult, StateStore};
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+
+/// Events returned when no `limit` is given.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// How often a watch checks for new events.
+const WATCH_POLL: Duration = Duration::from_secs(1);
+
+/// Query parameters for `GET /events`.
+#[derive(Debug, Clone, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct EventsQuery {
+    /// Only events with a greater sequence.
+    pub after: Option<u64>,
+    pub limit: Option<usize>,
+    /// Only events about this deployment (`namespace/name`).
+    pub deployment: Option<String>,
+    /// Only events about this node.
+    pub node: Option<String>,
+    pub kind: Option<ClusterEventKind>,
+    /// Keep the response open and send new events as they are recorded.
+    #[serde(default)]
+    pub watch: bool,
+}
+
+impl EventsQuery {
+    fn matches(&self, event: &ClusterEvent) -> bool {
+        self.deployment.as_ref().is_none_or(|d| event.deployment_id.as_ref() == Some(d))
+            && self.node.as_ref().is_none_or(|n| event.node_id.as_ref() == Some(n))
+            && self.kind.is_none_or(|k| event.kind == k)
+    }
+
+    /// The first batch: events after `after`, or the newest `limit`.
+    fn backlog(&self, store: &StateStore) -> StateResult<Vec<ClusterEvent>> {
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if let Some(after) = self.after {
+            return store.list_events(after, limit, |e| self.matches(e));
+        }
+        let mut events = store.list_events(0, CLUSTER_EVENT_RETENTION as usize, |e| self.matches(e))?;
+        events.drain(..events.len().saturating_sub(limit));
+        Ok(events)
+    }
+}
+
+/// Record `event`, logging rather than failing the request if the store
+/// refuses it.
+pub(crate) fn record(store: &StateStore, event: ClusterEvent) {
+    if let Err(e) = store.record_event(&event) {
+        tracing::warn!(kind = ?event.kind, error = %e, "failed to record cluster event");
+    }
+}
+
+/// GET /api/v1/events
+pub async fn list_events(State(state): State<ApiState>, Query(query): Query<EventsQuery>) -> Response {
+    let backlog = match query.backlog(&state.store) {
+        Ok(events) => events,
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    if !query.watch {
+        return ApiResponse::ok(backlog).into_response();
+    }
+    let after = backlog.last().map_or(query.after.unwrap_or(0), |e| e.sequence);
+    let stream = futures_util::stream::unfold(
+        (state.store, query, after, VecDeque::from(backlog)),
+        |(store, query, mut after, mut pending)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    after = event.sequence;
+                    let sse = Event::default()
+                        .event("event")
+                        .data(serde_json::to_string(&event).expect("events serialize"));
+                    return Some((Ok::<_, Infallible>(sse), (store, query, after, pending)));
+                }
+                tokio::time::sleep(WATCH_POLL).await;
+                match store.list_events(after, usize::MAX, |e| query.matches(e)) {
+                    Ok(events) => pending.extend(events),
+                    Err(e) => tracing::warn!(error = %e, "failed to read cluster events"),
+                }
+            }
+        },
+    );
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: ClusterEventKind, deployment: &str) -> ClusterEvent {
+        ClusterEvent::new(kind, 1000, "test").for_deployment(deployment)
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_events_filters_and_keeps_newest() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        record(&state.store, event(ClusterEventKind::DeploymentCreated, "default/api"));
+        record(&state.store, event(ClusterEventKind::DeploymentCreated, "default/web"));
+        record(&state.store, event(ClusterEventKind::DeploymentScaled, "default/api"));
+        record(&state.store, event(ClusterEventKind::DeploymentDeleted, "default/api"));
+
+        let query = EventsQuery { deployment: Some("default/api".into()), limit: Some(2), ..Default::default() };
+        let body = body_json(list_events(State(state.clone()), Query(query)).await).await;
+        let kinds: Vec<_> = body["data"].as_array().unwrap().iter().map(|e| e["kind"].clone()).collect();
+        assert_eq!(kinds, ["deployment_scaled", "deployment_deleted"]);
+
+        let query = EventsQuery { after: Some(1), kind: Some(ClusterEventKind::DeploymentCreated), ..Default::default() };
+        let body = body_json(list_events(State(state), Query(query)).await).await;
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["data"][0]["deployment_id"], "default/web");
+        assert_eq!(body["data"][0]["sequence"], 2);
+    }
+}
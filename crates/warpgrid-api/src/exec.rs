@@ -0,0 +1,178 @@
+//! Calling a deployment's exports directly, for `warp exec`.
+//!
+//! `POST /api/v1/deployments/{id}/exec` takes an [`ExecRequest`] naming an
+//! export (`function` or `interface#function`) and its arguments as JSON,
+//! calls it on one of the deployment's pooled instances, and returns the
+//! result as an [`ExecResult`].
+//!
+//! The API has no runtime of its own: warpd attaches an [`ExportInvoker`]
+//! to the router with [`axum::Extension`]. Without one the endpoint
+//! answers 503.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use futures_util::future::BoxFuture;
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+
+/// `POST /deployments/{id}/exec` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExecRequest {
+    pub export: String,
+    #[serde(default)]
+    pub args: Vec<serde_json::Value>,
+}
+
+/// `POST /deployments/{id}/exec` response.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExecResult {
+    pub deployment_id: String,
+    pub export: String,
+    /// `null` for no results, the value for one, an array for several.
+    pub result: serde_json::Value,
+    pub duration_ms: u64,
+}
+
+/// Why an export could not be called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    /// The deployment has no component loaded on this node.
+    NotLoaded,
+    /// Every pooled instance is busy.
+    Busy,
+    /// The export is missing, the arguments do not fit, or the guest trapped.
+    Failed(String),
+}
+
+/// Calls exports on a deployment's pooled instances.
+pub trait ExportInvoker: Send + Sync {
+    fn invoke<'a>(
+        &'a self,
+        deployment_id: &'a str,
+        export: &'a str,
+        args: &'a [serde_json::Value],
+    ) -> BoxFuture<'a, Result<serde_json::Value, ExecError>>;
+}
+
+/// POST /api/v1/deployments/:id/exec
+pub async fn exec_export(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    invoker: Option<Extension<Arc<dyn ExportInvoker>>>,
+    Json(req): Json<ExecRequest>,
+) -> Response {
+    match state.store.get_deployment(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+    let Some(Extension(invoker)) = invoker else {
+        return error_response("this server cannot call exports", StatusCode::SERVICE_UNAVAILABLE).into_response();
+    };
+
+    let started = std::time::Instant::now();
+    match invoker.invoke(&id, &req.export, &req.args).await {
+        Ok(result) => ApiResponse::ok(ExecResult {
+            deployment_id: id,
+            export: req.export,
+            result,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+        .into_response(),
+        Err(ExecError::NotLoaded) => {
+            error_response("deployment has no component loaded", StatusCode::SERVICE_UNAVAILABLE).into_response()
+        }
+        Err(ExecError::Busy) => {
+            error_response("all instances are busy; try again", StatusCode::TOO_MANY_REQUESTS).into_response()
+        }
+        Err(ExecError::Failed(msg)) => error_response(&msg, StatusCode::UNPROCESSABLE_ENTITY).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use warpgrid_state::{DeploymentSpec, InstanceConstraints, ResourceLimits, ShimsEnabled, StateStore, TriggerConfig};
+
+    /// Adds its arguments, or fails for any export but `add`.
+    struct Adder;
+
+    impl ExportInvoker for Adder {
+        fn invoke<'a>(
+            &'a self,
+            _deployment_id: &'a str,
+            export: &'a str,
+            args: &'a [Value],
+        ) -> BoxFuture<'a, Result<Value, ExecError>> {
+            Box::pin(async move {
+                match export {
+                    "add" => Ok(json!(args.iter().filter_map(Value::as_u64).sum::<u64>())),
+                    other => Err(ExecError::Failed(format!("component does not export function '{other}'"))),
+                }
+            })
+        }
+    }
+
+    fn store_with_deployment() -> StateStore {
+        let store = StateStore::open_in_memory().unwrap();
+        store
+            .put_deployment(&DeploymentSpec {
+                id: "default/api".to_string(),
+                namespace: "default".to_string(),
+                name: "api".to_string(),
+                source: "file://api.wasm".to_string(),
+                trigger: TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
+                instances: InstanceConstraints { min: 1, max: 1 },
+                resources: ResourceLimits { memory_bytes: 64 << 20, cpu_weight: 100, execution_budget_ms: None },
+                scaling: None,
+                health: None,
+                shims: ShimsEnabled::default(),
+                env: Default::default(),
+                created_at: 1000,
+                updated_at: 1000,
+                priority: None,
+                min_available: None,
+                labels: Default::default(),
+                paused: false,
+                secrets: Vec::new(),
+                config_maps: Vec::new(),
+            })
+            .unwrap();
+        store
+    }
+
+    async fn call(store: StateStore, id: &str, export: &str, invoker: Option<Arc<dyn ExportInvoker>>) -> (StatusCode, Value) {
+        let req = ExecRequest { export: export.to_string(), args: vec![json!(2), json!(40)] };
+        let response =
+            exec_export(State(ApiState { store }), Path(id.to_string()), invoker.map(Extension), Json(req)).await;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn calls_the_attached_invoker() {
+        let (status, body) = call(store_with_deployment(), "default/api", "add", Some(Arc::new(Adder))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["result"], 42);
+        assert_eq!(body["data"]["export"], "add");
+
+        let (status, body) = call(store_with_deployment(), "default/api", "sub", Some(Arc::new(Adder))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"], "component does not export function 'sub'");
+    }
+
+    #[tokio::test]
+    async fn missing_deployment_or_invoker() {
+        let (status, _) = call(store_with_deployment(), "default/web", "add", Some(Arc::new(Adder))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(store_with_deployment(), "default/api", "add", None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -2,159 +2,1033 @@
 //!
 //! Each handler reads/writes via `StateStore` and returns JSON responses.
 
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use std::collections::HashMap;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::IntoResponse;
 use axum::Json;
 
 use warpgrid_state::*;
 
 use crate::ApiState;
+use crate::configmaps;
+use crate::events;
 
 /// Response wrapper for consistent API format.
 #[derive(serde::Serialize)]
-struct ApiResponse<T: serde::Serialize> {
+pub(crate) struct ApiResponse<T: serde::Serialize> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<T>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    /// Cursor of the next page of a paged listing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
 }
 
 impl<T: serde::Serialize> ApiResponse<T> {
-    fn ok(data: T) -> Json<Self> {
+    pub(crate) fn ok(data: T) -> Json<Self> {
         Json(Self {
             success: true,
             data: Some(data),
             error: None,
+            next_cursor: None,
+        })
+    }
+}
+
+impl<T: serde::Serialize> ApiResponse<Vec<T>> {
+    pub(crate) fn page(page: Page<T>) -> Json<Self> {
+        Json(Self {
+            success: true,
+            data: Some(page.items),
+            error: None,
+            next_cursor: page.next.map(|key| hex::encode(key.as_bytes())),
         })
     }
 }
 
-fn error_response(msg: &str, status: StatusCode) -> impl IntoResponse {
+pub(crate) fn error_response(msg: &str, status: StatusCode) -> impl IntoResponse {
     (
         status,
         Json(ApiResponse::<()> {
             success: false,
             data: None,
             error: Some(msg.to_string()),
+            next_cursor: None,
         }),
     )
 }
 
-// ── Deployments ────────────────────────────────────────────────
+// ── Listing ────────────────────────────────────────────────────
 
-/// GET /api/v1/deployments
-pub async fn list_deployments(State(state): State<ApiState>) -> impl IntoResponse {
-    match state.store.list_deployments() {
-        Ok(deployments) => ApiResponse::ok(deployments).into_response(),
-        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+/// Largest page a list request can ask for.
+const LIST_LIMIT_MAX: usize = 1000;
+
+/// Heartbeat age after which a node is `not-ready`, as `warp nodes` shows it.
+const NODE_STALE_SECS: u64 = 30;
+
+/// Query parameters of the deployment, instance, and node listings.
+///
+/// Items come in key order: `namespace/name`, instance ID, node ID. The
+/// cursor is the last key of a page, so paging is stable while entries
+/// are added or removed.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListQuery {
+    /// Page size, up to 1000. Everything matching is returned when omitted.
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    /// Comma-separated `key=value` labels that must all match.
+    pub label_selector: Option<String>,
+    /// Deployments: `active` or `paused`. Instances: `starting`, `running`,
+    /// `unhealthy`, `stopping`, or `stopped`. Nodes: `ready`, `not-ready`,
+    /// `pressure`, or `cordoned`.
+    pub status: Option<String>,
+}
+
+impl ListQuery {
+    fn limit(&self) -> Result<usize, String> {
+        match self.limit {
+            None => Ok(usize::MAX),
+            Some(limit) if (1..=LIST_LIMIT_MAX).contains(&limit) => Ok(limit),
+            Some(_) => Err(format!("limit must be between 1 and {LIST_LIMIT_MAX}")),
+        }
+    }
+
+    /// The store key the cursor encodes.
+    fn after(&self) -> Result<Option<String>, String> {
+        let Some(cursor) = &self.cursor else {
+            return Ok(None);
+        };
+        hex::decode(cursor)
+            .ok()
+            .and_then(|key| String::from_utf8(key).ok())
+            .map(Some)
+            .ok_or_else(|| "invalid cursor".to_string())
+    }
+
+    fn selector(&self) -> Result<HashMap<String, String>, String> {
+        let Some(selector) = &self.label_selector else {
+            return Ok(HashMap::new());
+        };
+        selector
+            .split(',')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.trim().to_string())),
+                _ => Err(format!("label selector '{pair}' is not key=value")),
+            })
+            .collect()
+    }
+}
+
+fn labels_match(labels: &HashMap<String, String>, selector: &HashMap<String, String>) -> bool {
+    selector.iter().all(|(k, v)| labels.get(k) == Some(v))
+}
+
+/// Node statuses, the words `warp nodes` shows.
+const NODE_STATUSES: [&str; 4] = ["ready", "not-ready", "pressure", "cordoned"];
+
+/// Whether `node` has `status`, one of [`NODE_STATUSES`].
+fn node_has_status(node: &NodeInfo, status: &str, now: u64) -> bool {
+    let stale = now.saturating_sub(node.last_heartbeat) > NODE_STALE_SECS;
+    match status {
+        "ready" => !stale,
+        "not-ready" => stale,
+        "pressure" => node.pressure_until.is_some_and(|until| until > now),
+        "cordoned" => node.cordoned,
+        _ => false,
+    }
+}
+
+/// Why a listing failed.
+pub(crate) enum ListError {
+    BadRequest(String),
+    Store(StateError),
+}
+
+impl From<String> for ListError {
+    fn from(e: String) -> Self {
+        Self::BadRequest(e)
+    }
+}
+
+impl From<StateError> for ListError {
+    fn from(e: StateError) -> Self {
+        Self::Store(e)
+    }
+}
+
+pub(crate) fn list_response<T: serde::Serialize>(page: Result<Page<T>, ListError>) -> axum::response::Response {
+    match page {
+        Ok(page) => ApiResponse::page(page).into_response(),
+        Err(ListError::BadRequest(e)) => error_response(&e, StatusCode::BAD_REQUEST).into_response(),
+        Err(ListError::Store(e)) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
 
+// ── Deployments ────────────────────────────────────────────────
+
+/// GET /api/v1/deployments?limit=&cursor=&label_selector=&status=
+pub async fn list_deployments(State(state): State<ApiState>, Query(query): Query<ListQuery>) -> impl IntoResponse {
+    list_response(deployments_page(&state.store, None, &query))
+}
+
+/// Deployments of `namespace`, or of all namespaces, matching `query`.
+pub(crate) fn deployments_page(
+    store: &StateStore,
+    namespace: Option<&str>,
+    query: &ListQuery,
+) -> Result<Page<DeploymentSpec>, ListError> {
+    let paused = match query.status.as_deref() {
+        None => None,
+        Some("active") => Some(false),
+        Some("paused") => Some(true),
+        Some(other) => return Err(format!("unknown deployment status '{other}'; use active or paused").into()),
+    };
+    let selector = query.selector()?;
+    let keep = |d: &DeploymentSpec| labels_match(&d.labels, &selector) && paused.is_none_or(|p| d.paused == p);
+    Ok(store.list_deployments_page(namespace, query.after()?.as_deref(), query.limit()?, keep)?)
+}
+
 /// GET /api/v1/deployments/:id
+///
+/// The deployment's version is returned as its `ETag`, for `If-Match`.
 pub async fn get_deployment(
     State(state): State<ApiState>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     match state.store.get_deployment(&id) {
-        Ok(Some(spec)) => ApiResponse::ok(spec).into_response(),
+        Ok(Some(spec)) => with_etag(&state.store, &id, ApiResponse::ok(spec).into_response()),
         Ok(None) => error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
         Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
 
+/// The precondition a deployment write's `If-Match` or `If-None-Match`
+/// header sets: `If-Match: *` or `If-Match: "<version>"`, where the version
+/// is the deployment's `ETag`, or `If-None-Match: *` to only create.
+pub(crate) fn deployment_precondition(headers: &HeaderMap) -> Result<DeploymentPrecondition, String> {
+    let header = |name| headers.get(name).map(|v: &HeaderValue| v.to_str().unwrap_or_default().trim());
+    match (header(IF_MATCH), header(IF_NONE_MATCH)) {
+        (None, None) => Ok(DeploymentPrecondition::Any),
+        (Some(_), Some(_)) => Err("give If-Match or If-None-Match, not both".to_string()),
+        (Some("*"), None) => Ok(DeploymentPrecondition::Exists),
+        (Some(tag), None) => tag
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .and_then(|version| version.parse().ok())
+            .map(DeploymentPrecondition::Version)
+            .ok_or_else(|| format!("invalid If-Match '{tag}'; use * or a deployment ETag such as \"3\"")),
+        (None, Some("*")) => Ok(DeploymentPrecondition::Absent),
+        (None, Some(tag)) => Err(format!("invalid If-None-Match '{tag}'; only * is supported")),
+    }
+}
+
+fn precondition_failed() -> axum::response::Response {
+    error_response("deployment does not match If-Match or If-None-Match", StatusCode::PRECONDITION_FAILED)
+        .into_response()
+}
+
+/// Set `response`'s `ETag` to deployment `id`'s current version.
+fn with_etag(store: &StateStore, id: &str, mut response: axum::response::Response) -> axum::response::Response {
+    if let Ok(Some(version)) = store.deployment_version(id)
+        && let Ok(etag) = HeaderValue::from_str(&format!("\"{version}\""))
+    {
+        response.headers_mut().insert(ETAG, etag);
+    }
+    response
+}
+
 /// POST /api/v1/deployments
+///
+/// A spec without a namespace goes in the default one. Honours `If-Match`
+/// and `If-None-Match` (see [`deployment_precondition`]).
 pub async fn create_deployment(
     State(state): State<ApiState>,
-    Json(spec): Json<DeploymentSpec>,
+    headers: HeaderMap,
+    Json(mut spec): Json<DeploymentSpec>,
 ) -> impl IntoResponse {
-    match state.store.put_deployment(&spec) {
-        Ok(()) => (StatusCode::CREATED, ApiResponse::ok(spec)).into_response(),
+    spec.fill_defaults();
+    save_deployment(&state.store, spec, &headers)
+}
+
+/// Store a created or replaced `spec` and record which it was, if the
+/// request's preconditions hold.
+pub(crate) fn save_deployment(
+    store: &StateStore,
+    spec: DeploymentSpec,
+    headers: &HeaderMap,
+) -> axum::response::Response {
+    let precondition = match deployment_precondition(headers) {
+        Ok(precondition) => precondition,
+        Err(e) => return error_response(&e, StatusCode::BAD_REQUEST).into_response(),
+    };
+    if let Err(e) = configmaps::validate_refs(&spec.config_maps) {
+        return error_response(&e, StatusCode::BAD_REQUEST).into_response();
+    }
+    let id = spec.id.clone();
+    match write_deployment_if(store, &spec, precondition) {
+        Ok(true) => with_etag(store, &id, (StatusCode::CREATED, ApiResponse::ok(spec)).into_response()),
+        Ok(false) => precondition_failed(),
         Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
 
+/// Store `spec`, restage its config maps, and record whether it was
+/// created or updated.
+pub(crate) fn write_deployment(store: &StateStore, spec: &DeploymentSpec) -> StateResult<()> {
+    write_deployment_if(store, spec, DeploymentPrecondition::Any).map(|_| ())
+}
+
+/// [`write_deployment`] if the stored deployment satisfies `precondition`.
+/// Returns false, writing nothing, when it does not.
+pub(crate) fn write_deployment_if(
+    store: &StateStore,
+    spec: &DeploymentSpec,
+    precondition: DeploymentPrecondition,
+) -> StateResult<bool> {
+    let existing = store.get_deployment(&spec.id)?;
+    if !store.put_deployment_if(spec, precondition)? {
+        return Ok(false);
+    }
+    let (kind, message) = if existing.is_some() {
+        (ClusterEventKind::DeploymentUpdated, "spec replaced")
+    } else {
+        (ClusterEventKind::DeploymentCreated, "created")
+    };
+    let unbound: Vec<_> = existing
+        .map(|old| old.config_maps.into_iter().filter(|r| !spec.config_maps.contains(r)).collect())
+        .unwrap_or_default();
+    configmaps::bind(store, spec, &unbound);
+    events::record(store, ClusterEvent::new(kind, spec.updated_at, message).for_deployment(&spec.id));
+    Ok(true)
+}
+
 /// DELETE /api/v1/deployments/:id
+///
+/// Honours `If-Match` (see [`deployment_precondition`]).
 pub async fn delete_deployment(
     State(state): State<ApiState>,
     Path(id): Path<String>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    match state.store.delete_deployment(&id) {
-        Ok(true) => ApiResponse::ok("deleted").into_response(),
-        Ok(false) => error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+    let precondition = match deployment_precondition(&headers) {
+        Ok(precondition) => precondition,
+        Err(e) => return error_response(&e, StatusCode::BAD_REQUEST).into_response(),
+    };
+    match remove_deployment_if(&state.store, &id, precondition) {
+        Ok(Some(true)) => ApiResponse::ok("deleted").into_response(),
+        Ok(Some(false)) => error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Ok(None) => precondition_failed(),
         Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
 
+/// Delete deployment `id` with its flags and config. Returns true if it
+/// existed.
+pub(crate) fn remove_deployment(store: &StateStore, id: &str) -> StateResult<bool> {
+    Ok(remove_deployment_if(store, id, DeploymentPrecondition::Any)?.unwrap_or_default())
+}
+
+/// [`remove_deployment`] if the stored deployment satisfies
+/// `precondition`. Returns `None`, deleting nothing, when it does not.
+pub(crate) fn remove_deployment_if(
+    store: &StateStore,
+    id: &str,
+    precondition: DeploymentPrecondition,
+) -> StateResult<Option<bool>> {
+    match store.delete_deployment_if(id, precondition)? {
+        Some(true) => {}
+        other => return Ok(other),
+    }
+    if let Err(e) = store.delete_flags(id) {
+        tracing::warn!(deployment = %id, error = %e, "failed to delete feature flags");
+    }
+    if let Err(e) = store.delete_deployment_config(id) {
+        tracing::warn!(deployment = %id, error = %e, "failed to delete config bundle");
+    }
+    let event = ClusterEvent::new(ClusterEventKind::DeploymentDeleted, SystemClock.epoch_secs(), "deleted");
+    events::record(store, event.for_deployment(id));
+    Ok(Some(true))
+}
+
+// ── Revisions ──────────────────────────────────────────────────
+
+/// Query parameters for `POST /deployments/:id/rollback`.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct RollbackQuery {
+    /// Revision to restore; defaults to the one before the latest.
+    #[serde(default)]
+    pub revision: Option<u64>,
+}
+
+/// GET /api/v1/deployments/:id/revisions
+pub async fn list_revisions(State(state): State<ApiState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.store.list_deployment_revisions(&id) {
+        Ok(revisions) => ApiResponse::ok(revisions).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/deployments/:id/rollback?revision=
+///
+/// Stores the spec of an earlier revision as the deployment's spec, which
+/// records it as a new revision: history only grows, so a rollback can
+/// itself be rolled back. Honours `If-Match` (see
+/// [`deployment_precondition`]).
+pub async fn rollback_deployment(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<RollbackQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let precondition = match deployment_precondition(&headers) {
+        Ok(precondition) => precondition,
+        Err(e) => return error_response(&e, StatusCode::BAD_REQUEST).into_response(),
+    };
+    let current = match state.store.get_deployment(&id) {
+        Ok(Some(spec)) => spec,
+        Ok(None) => return error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    let revision = match query.revision {
+        Some(revision) => state.store.get_deployment_revision(&id, revision),
+        None => state.store.list_deployment_revisions(&id).map(|mut revisions| {
+            revisions.pop();
+            revisions.pop()
+        }),
+    };
+    let revision = match (revision, query.revision) {
+        (Ok(Some(revision)), _) => revision,
+        (Ok(None), Some(n)) => {
+            return error_response(&format!("revision {n} not found"), StatusCode::NOT_FOUND).into_response();
+        }
+        (Ok(None), None) => {
+            return error_response("no earlier revision to roll back to", StatusCode::CONFLICT).into_response();
+        }
+        (Err(e), _) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+
+    let mut spec = revision.spec;
+    spec.created_at = current.created_at;
+    spec.updated_at = SystemClock.epoch_secs();
+    match state.store.put_deployment_if(&spec, precondition) {
+        Ok(true) => {}
+        Ok(false) => return precondition_failed(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+    let unbound: Vec<_> = current.config_maps.into_iter().filter(|r| !spec.config_maps.contains(r)).collect();
+    configmaps::bind(&state.store, &spec, &unbound);
+    let message = format!("rolled back to revision {}", revision.revision);
+    let event = ClusterEvent::new(ClusterEventKind::DeploymentRolledBack, spec.updated_at, message);
+    events::record(&state.store, event.for_deployment(&spec.id));
+    with_etag(&state.store, &id, ApiResponse::ok(spec).into_response())
+}
+
 // ── Instances ──────────────────────────────────────────────────
 
-/// GET /api/v1/deployments/:id/instances
+/// GET /api/v1/deployments/:id/instances?limit=&cursor=&status=
 pub async fn list_instances(
     State(state): State<ApiState>,
     Path(id): Path<String>,
+    Query(query): Query<ListQuery>,
 ) -> impl IntoResponse {
-    match state.store.list_instances_for_deployment(&id) {
-        Ok(instances) => ApiResponse::ok(instances).into_response(),
-        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    list_response(instances_page(&state.store, &id, &query))
+}
+
+/// Instances have no labels, so a `label_selector` is refused.
+fn instances_page(store: &StateStore, id: &str, query: &ListQuery) -> Result<Page<InstanceState>, ListError> {
+    if query.label_selector.is_some() {
+        return Err("instances have no labels; label_selector applies to deployments and nodes".to_string().into());
     }
+    let status = match query.status.as_deref() {
+        None => None,
+        Some(status) => Some(
+            serde_json::from_value::<InstanceStatus>(serde_json::Value::from(status))
+                .map_err(|_| format!("unknown instance status '{status}'"))?,
+        ),
+    };
+    let keep = |i: &InstanceState| status.is_none_or(|s| i.status == s);
+    Ok(store.list_instances_page(id, query.after()?.as_deref(), query.limit()?, keep)?)
 }
 
 // ── Scaling ────────────────────────────────────────────────────
 
-/// Scale request body.
-#[derive(serde::Deserialize)]
+/// Scale request body. `target` is the number of instances the scheduler
+/// keeps placed, stored as the deployment's minimum; `min` and `max` set
+/// the autoscaler's bounds directly. Omitted fields are left as they are.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct ScaleRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u32>,
+}
+
+/// Scale response body: the instance bounds now stored.
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ScaleResult {
+    pub deployment: String,
     pub target: u32,
+    pub min: u32,
+    pub max: u32,
+    pub status: String,
 }
 
 /// POST /api/v1/deployments/:id/scale
+///
+/// Honours `If-Match` (see [`deployment_precondition`]).
 pub async fn scale_deployment(
     State(state): State<ApiState>,
     Path(id): Path<String>,
+    headers: HeaderMap,
     Json(req): Json<ScaleRequest>,
 ) -> impl IntoResponse {
-    // Validate deployment exists.
-    match state.store.get_deployment(&id) {
-        Ok(Some(spec)) => {
-            if req.target > spec.instances.max {
-                return error_response(
-                    &format!("target {} exceeds max {}", req.target, spec.instances.max),
-                    StatusCode::BAD_REQUEST,
-                )
-                .into_response();
+    let precondition = match deployment_precondition(&headers) {
+        Ok(precondition) => precondition,
+        Err(e) => return error_response(&e, StatusCode::BAD_REQUEST).into_response(),
+    };
+    let mut spec = match state.store.get_deployment(&id) {
+        Ok(Some(spec)) => spec,
+        Ok(None) => return error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    let before = spec.instances.clone();
+    if let Err(e) = scale_spec(&mut spec, &req) {
+        return error_response(&e, StatusCode::BAD_REQUEST).into_response();
+    }
+    spec.updated_at = SystemClock.epoch_secs();
+    match state.store.put_deployment_if(&spec, precondition) {
+        Ok(true) => {}
+        Ok(false) => return precondition_failed(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+    record_scaled(&state.store, &spec, &before);
+    let response = ApiResponse::ok(ScaleResult {
+        deployment: id.clone(),
+        target: spec.instances.min,
+        min: spec.instances.min,
+        max: spec.instances.max,
+        status: "scaling".to_string(),
+    })
+    .into_response();
+    with_etag(&state.store, &id, response)
+}
+
+fn record_scaled(store: &StateStore, spec: &DeploymentSpec, before: &InstanceConstraints) {
+    let message = format!(
+        "scaled from {}-{} to {}-{} instances",
+        before.min, before.max, spec.instances.min, spec.instances.max
+    );
+    let event = ClusterEvent::new(ClusterEventKind::DeploymentScaled, spec.updated_at, message);
+    events::record(store, event.for_deployment(&spec.id));
+}
+
+/// Apply a scale request to `spec`'s instance bounds, rejecting bounds the
+/// scheduler could not honour.
+fn scale_spec(spec: &mut DeploymentSpec, req: &ScaleRequest) -> Result<(), String> {
+    if req.target.is_some() && req.min.is_some() {
+        return Err("target and min both set the minimum; give one".to_string());
+    }
+    let max = req.max.unwrap_or(spec.instances.max);
+    if max == 0 {
+        return Err("max must be at least 1".to_string());
+    }
+    if let Some(target) = req.target
+        && target > max
+    {
+        return Err(format!("target {target} exceeds max {max}"));
+    }
+    let min = req.target.or(req.min).unwrap_or(spec.instances.min);
+    if min > max {
+        return Err(format!("min {min} exceeds max {max}"));
+    }
+    spec.instances = InstanceConstraints { min, max };
+    Ok(())
+}
+
+// ── Batch operations ───────────────────────────────────────────
+
+/// Batch request body: apply `operation` to every deployment whose labels
+/// include all of `selector`.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchRequest {
+    pub selector: HashMap<String, String>,
+    pub operation: BatchOperation,
+    /// Report what would happen without changing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Operation applied to each selected deployment.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchOperation {
+    Scale { target: u32 },
+    Pause,
+    Resume,
+    Delete,
+    /// Merge these variables into each deployment's environment.
+    SetEnv { env: HashMap<String, String> },
+}
+
+/// Outcome of a batch operation on one deployment.
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchItemResult {
+    pub deployment_id: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Batch response body.
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchResponse {
+    pub dry_run: bool,
+    pub matched: usize,
+    pub results: Vec<BatchItemResult>,
+}
+
+fn apply_batch_operation(
+    store: &StateStore,
+    mut spec: DeploymentSpec,
+    operation: &BatchOperation,
+    dry_run: bool,
+) -> Result<(), String> {
+    let before = spec.instances.clone();
+    let updated = match operation {
+        BatchOperation::Scale { target } => {
+            scale_spec(&mut spec, &ScaleRequest { target: Some(*target), ..Default::default() })?;
+            None
+        }
+        BatchOperation::Delete => {
+            if !dry_run {
+                remove_deployment(store, &spec.id).map_err(|e| e.to_string())?;
             }
-            ApiResponse::ok(serde_json::json!({
-                "deployment": id,
-                "target": req.target,
-                "status": "scaling"
-            }))
-            .into_response()
+            return Ok(());
         }
-        Ok(None) => error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        BatchOperation::Pause => {
+            spec.paused = true;
+            Some("paused")
+        }
+        BatchOperation::Resume => {
+            spec.paused = false;
+            Some("resumed")
+        }
+        BatchOperation::SetEnv { env } => {
+            spec.env.extend(env.clone());
+            Some("environment updated")
+        }
+    };
+    if dry_run {
+        return Ok(());
+    }
+    spec.updated_at = SystemClock.epoch_secs();
+    store.put_deployment(&spec).map_err(|e| e.to_string())?;
+    match updated {
+        Some(message) => {
+            let event = ClusterEvent::new(ClusterEventKind::DeploymentUpdated, spec.updated_at, message);
+            events::record(store, event.for_deployment(&spec.id));
+        }
+        None => record_scaled(store, &spec, &before),
+    }
+    Ok(())
+}
+
+/// POST /api/v1/deployments:batch
+///
+/// Applies one operation to every deployment matching a label selector.
+/// Each deployment is handled independently, so one failure does not stop
+/// the rest; the response carries a result per deployment.
+pub async fn batch_deployments(
+    State(state): State<ApiState>,
+    Json(req): Json<BatchRequest>,
+) -> impl IntoResponse {
+    if req.selector.is_empty() {
+        return error_response("selector must not be empty", StatusCode::BAD_REQUEST).into_response();
+    }
+    let deployments = match state.store.list_deployments() {
+        Ok(deployments) => deployments,
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+
+    let results: Vec<_> = deployments
+        .into_iter()
+        .filter(|d| req.selector.iter().all(|(k, v)| d.labels.get(k) == Some(v)))
+        .map(|spec| {
+            let deployment_id = spec.id.clone();
+            match apply_batch_operation(&state.store, spec, &req.operation, req.dry_run) {
+                Ok(()) => BatchItemResult { deployment_id, ok: true, error: None },
+                Err(e) => BatchItemResult { deployment_id, ok: false, error: Some(e) },
+            }
+        })
+        .collect();
+
+    ApiResponse::ok(BatchResponse {
+        dry_run: req.dry_run,
+        matched: results.len(),
+        results,
+    })
+    .into_response()
+}
+
+// ── Feature flags ──────────────────────────────────────────────
+
+/// The stored flags of `id`, or an empty set when none were defined yet.
+fn current_flags(store: &StateStore, id: &str) -> StateResult<DeploymentFlags> {
+    Ok(store.get_flags(id)?.unwrap_or_else(|| DeploymentFlags {
+        deployment_id: id.to_string(),
+        set: FlagSet::default(),
+        updated_at: 0,
+    }))
+}
+
+/// Validate and store `flags` as the full flag set of deployment `id`.
+fn store_flags(state: &ApiState, id: &str, mut flags: DeploymentFlags) -> axum::response::Response {
+    match state.store.get_deployment(id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => {
+            return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response();
+        }
+    }
+    if let Err(e) = flags.set.validate() {
+        return error_response(&e, StatusCode::BAD_REQUEST).into_response();
+    }
+    flags.updated_at = SystemClock.epoch_secs();
+    match state.store.put_flags(&flags) {
+        Ok(()) => ApiResponse::ok(flags).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// GET /api/v1/deployments/:id/flags
+pub async fn get_flags(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match current_flags(&state.store, &id) {
+        Ok(flags) => ApiResponse::ok(flags).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// PUT /api/v1/deployments/:id/flags
+///
+/// Replaces every flag of the deployment. Running instances see the new
+/// values on their next request.
+pub async fn put_flags(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(set): Json<FlagSet>,
+) -> impl IntoResponse {
+    let flags = DeploymentFlags {
+        deployment_id: id.clone(),
+        set,
+        updated_at: 0,
+    };
+    store_flags(&state, &id, flags)
+}
+
+/// PUT /api/v1/deployments/:id/flags/:name
+pub async fn put_flag(
+    State(state): State<ApiState>,
+    Path((id, name)): Path<(String, String)>,
+    Json(flag): Json<FeatureFlag>,
+) -> impl IntoResponse {
+    match current_flags(&state.store, &id) {
+        Ok(mut flags) => {
+            flags.set.flags.insert(name, flag);
+            store_flags(&state, &id, flags)
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// DELETE /api/v1/deployments/:id/flags/:name
+pub async fn delete_flag(
+    State(state): State<ApiState>,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match current_flags(&state.store, &id) {
+        Ok(mut flags) => {
+            if flags.set.flags.remove(&name).is_none() {
+                return error_response("flag not found", StatusCode::NOT_FOUND).into_response();
+            }
+            store_flags(&state, &id, flags)
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+// ── Config bundles ─────────────────────────────────────────────
+
+/// GET /api/v1/deployments/:id/config
+pub async fn get_config(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.store.get_deployment_config(&id) {
+        Ok(Some(config)) => ApiResponse::ok(config).into_response(),
+        Ok(None) => error_response("no config bundle", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// PUT /api/v1/deployments/:id/config
+///
+/// Stages a new bundle. It becomes active on every instance at once, after
+/// every node running the deployment has received it; instances get a
+/// `SIGHUP` when it does.
+pub async fn put_config(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(bundle): Json<ConfigBundle>,
+) -> impl IntoResponse {
+    let spec = match state.store.get_deployment(&id) {
+        Ok(Some(spec)) => spec,
+        Ok(None) => return error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => {
+            return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response();
+        }
+    };
+    if let Err(e) = bundle.validate() {
+        return error_response(&e, StatusCode::BAD_REQUEST).into_response();
+    }
+    if let Err(e) = state.store.stage_config_bundle(&id, &bundle, SystemClock.epoch_secs()) {
+        return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response();
+    }
+    // Bound config maps keep their files in the new bundle.
+    configmaps::bind(&state.store, &spec, &[]);
+    match state.store.get_deployment_config(&id) {
+        Ok(config) => ApiResponse::ok(config).into_response(),
         Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
 
 // ── Metrics ────────────────────────────────────────────────────
 
+/// Snapshots one metrics request returns at most.
+const METRICS_LIMIT: usize = 60;
+
+/// Query parameters for a deployment's metrics.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct MetricsQuery {
+    /// Only snapshots taken at or after this unix time (seconds), newest
+    /// last. Without it, the oldest snapshots are returned.
+    pub since: Option<u64>,
+}
+
 /// GET /api/v1/deployments/:id/metrics
 pub async fn get_metrics(
     State(state): State<ApiState>,
     Path(id): Path<String>,
+    Query(query): Query<MetricsQuery>,
+) -> impl IntoResponse {
+    let Some(since) = query.since else {
+        return match state.store.list_metrics_for_deployment(&id, METRICS_LIMIT) {
+            Ok(metrics) => ApiResponse::ok(metrics).into_response(),
+            Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        };
+    };
+    match state.store.list_metrics_for_deployment(&id, usize::MAX) {
+        Ok(mut metrics) => {
+            metrics.retain(|m| m.epoch >= since);
+            metrics.sort_by_key(|m| m.epoch);
+            let skip = metrics.len().saturating_sub(METRICS_LIMIT);
+            ApiResponse::ok(metrics.split_off(skip)).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Buckets one range request returns at most.
+const METRICS_RANGE_POINTS: u64 = 1000;
+
+/// Query parameters for a deployment's metrics over a time range.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct MetricsRangeQuery {
+    /// Start of the range, a unix time (seconds), inclusive.
+    pub start: u64,
+    /// End of the range, a unix time (seconds), inclusive. Defaults to now.
+    pub end: Option<u64>,
+    /// Bucket width in seconds. Snapshots in a bucket are merged into one
+    /// point at its start; without it every snapshot is returned.
+    pub step: Option<u64>,
+}
+
+/// GET /api/v1/deployments/:id/metrics/range
+pub async fn get_metrics_range(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<MetricsRangeQuery>,
+) -> impl IntoResponse {
+    let end = query.end.unwrap_or_else(|| SystemClock.epoch_secs());
+    if end < query.start {
+        return error_response("end is before start", StatusCode::BAD_REQUEST).into_response();
+    }
+    let step = query.step.unwrap_or(1);
+    if step == 0 {
+        return error_response("step must be at least 1", StatusCode::BAD_REQUEST).into_response();
+    }
+    if (end - query.start) / step >= METRICS_RANGE_POINTS {
+        let msg = format!("range holds over {METRICS_RANGE_POINTS} points; use a larger step");
+        return error_response(&msg, StatusCode::BAD_REQUEST).into_response();
+    }
+    match state.store.list_metrics_for_deployment(&id, usize::MAX) {
+        Ok(mut metrics) => {
+            metrics.retain(|m| (query.start..=end).contains(&m.epoch));
+            metrics.sort_by_key(|m| m.epoch);
+            if query.step.is_some() {
+                metrics = downsample(metrics, query.start, step);
+            }
+            ApiResponse::ok(metrics).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Merge epoch-sorted snapshots into one per `step`-wide bucket from
+/// `start`: rates, error rate, and p50 averaged; p99, memory, and
+/// instances at their peak. Empty buckets are left out.
+fn downsample(metrics: Vec<MetricsSnapshot>, start: u64, step: u64) -> Vec<MetricsSnapshot> {
+    let mut points: Vec<(MetricsSnapshot, u32)> = Vec::new();
+    for m in metrics {
+        let epoch = start + (m.epoch - start) / step * step;
+        match points.last_mut() {
+            Some((point, count)) if point.epoch == epoch => {
+                point.rps += m.rps;
+                point.latency_p50_ms += m.latency_p50_ms;
+                point.error_rate += m.error_rate;
+                point.latency_p99_ms = point.latency_p99_ms.max(m.latency_p99_ms);
+                point.total_memory_bytes = point.total_memory_bytes.max(m.total_memory_bytes);
+                point.active_instances = point.active_instances.max(m.active_instances);
+                *count += 1;
+            }
+            _ => points.push((MetricsSnapshot { epoch, ..m }, 1)),
+        }
+    }
+    points
+        .into_iter()
+        .map(|(mut point, count)| {
+            let n = f64::from(count);
+            point.rps /= n;
+            point.latency_p50_ms /= n;
+            point.error_rate /= n;
+            point
+        })
+        .collect()
+}
+
+// ── Usage ──────────────────────────────────────────────────────
+
+/// Default and maximum page size for the usage export stream.
+const USAGE_PAGE_DEFAULT: usize = 100;
+const USAGE_PAGE_MAX: usize = 1000;
+
+/// Query parameters for the usage event export.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct UsageEventsQuery {
+    /// Return events with a sequence greater than this cursor.
+    #[serde(default)]
+    pub after: u64,
+    pub limit: Option<usize>,
+}
+
+/// A page of the usage event stream.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UsageEventsPage {
+    pub events: Vec<UsageRecord>,
+    /// Cursor to pass as `after` for the next page.
+    pub next_cursor: u64,
+}
+
+/// GET /api/v1/usage/events?after=&limit=
+///
+/// Stable export endpoint for external billing systems. Consumers persist
+/// `next_cursor` and resume from it; each event is delivered with a unique,
+/// strictly increasing `sequence`. Events are kept for
+/// [`USAGE_RETENTION_SECS`](warpgrid_state::USAGE_RETENTION_SECS).
+pub async fn list_usage_events(
+    State(state): State<ApiState>,
+    Query(query): Query<UsageEventsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(USAGE_PAGE_DEFAULT).clamp(1, USAGE_PAGE_MAX);
+    match state.store.list_usage_events(query.after, limit) {
+        Ok(events) => {
+            let next_cursor = events.last().map_or(query.after, |r| r.sequence);
+            ApiResponse::ok(UsageEventsPage {
+                events,
+                next_cursor,
+            })
+            .into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Query parameters for usage rollups.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct UsageRollupsQuery {
+    /// Only return windows starting at or after this unix timestamp.
+    #[serde(default)]
+    pub since: u64,
+}
+
+/// GET /api/v1/deployments/:id/usage?since=
+pub async fn get_usage_rollups(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<UsageRollupsQuery>,
 ) -> impl IntoResponse {
-    match state.store.list_metrics_for_deployment(&id, 60) {
-        Ok(metrics) => ApiResponse::ok(metrics).into_response(),
+    match state.store.list_usage_rollups(&id, query.since) {
+        Ok(rollups) => ApiResponse::ok(rollups).into_response(),
         Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
 
 // ── Nodes ──────────────────────────────────────────────────────
 
-/// GET /api/v1/nodes
-pub async fn list_nodes(State(state): State<ApiState>) -> impl IntoResponse {
-    match state.store.list_nodes() {
-        Ok(nodes) => ApiResponse::ok(nodes).into_response(),
+/// GET /api/v1/nodes?limit=&cursor=&label_selector=&status=
+pub async fn list_nodes(State(state): State<ApiState>, Query(query): Query<ListQuery>) -> impl IntoResponse {
+    list_response(nodes_page(&state.store, &query))
+}
+
+fn nodes_page(store: &StateStore, query: &ListQuery) -> Result<Page<NodeInfo>, ListError> {
+    if let Some(status) = query.status.as_deref()
+        && !NODE_STATUSES.contains(&status)
+    {
+        return Err(format!("unknown node status '{status}'; use one of {}", NODE_STATUSES.join(", ")).into());
+    }
+    let selector = query.selector()?;
+    let now = SystemClock.epoch_secs();
+    let keep = |n: &NodeInfo| {
+        labels_match(&n.labels, &selector) && query.status.as_deref().is_none_or(|s| node_has_status(n, s, now))
+    };
+    Ok(store.list_nodes_page(query.after()?.as_deref(), query.limit()?, keep)?)
+}
+
+// ── Admin ──────────────────────────────────────────────────────
+
+/// GET /api/v1/admin/state-integrity
+pub async fn get_state_integrity(State(state): State<ApiState>) -> impl IntoResponse {
+    match state.store.integrity_status() {
+        Ok(status) => ApiResponse::ok(status).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/admin/state-integrity/verify
+pub async fn verify_state_integrity(State(state): State<ApiState>) -> impl IntoResponse {
+    match state.store.verify_integrity() {
+        Ok(report) => ApiResponse::ok(report).into_response(),
         Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
     }
 }
@@ -173,7 +1047,21 @@ pub async fn prometheus_metrics(State(state): State<ApiState>) -> impl IntoRespo
         }
     }
 
-    let body = warpgrid_metrics::render_prometheus(&snapshots);
+    let mut body = warpgrid_metrics::render_prometheus(&snapshots);
+    let quarantined = state.store.list_quarantined().map_or(0, |q| q.len());
+    body.push_str(&warpgrid_metrics::render_state_integrity(
+        state.store.corrupt_records_detected(),
+        quarantined,
+    ));
+    body.push_str(&warpgrid_metrics::render_response_streaming(
+        &warpgrid_metrics::streaming::streaming().snapshot(),
+    ));
+    body.push_str(&warpgrid_metrics::render_route_usage(
+        &warpgrid_metrics::route_usage::route_usage().snapshot(),
+    ));
+    body.push_str(&warpgrid_metrics::render_dns_cache(
+        &warpgrid_metrics::dns::dns_cache().snapshot(),
+    ));
     (
         StatusCode::OK,
         [("content-type", "text/plain; version=0.0.4; charset=utf-8")],
@@ -184,7 +1072,6 @@ pub async fn prometheus_metrics(State(state): State<ApiState>) -> impl IntoRespo
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
 
     fn test_state() -> ApiState {
         let store = StateStore::open_in_memory().unwrap();
@@ -197,11 +1084,12 @@ mod tests {
             namespace: ns.to_string(),
             name: name.to_string(),
             source: "file://test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -209,23 +1097,135 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
     #[tokio::test]
     async fn list_deployments_empty() {
         let state = test_state();
-        let resp = list_deployments(State(state)).await;
+        let resp = list_deployments(State(state), Query(ListQuery::default())).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    async fn list_json(resp: impl IntoResponse) -> (StatusCode, serde_json::Value) {
+        let resp = resp.into_response();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn list_deployments_pages_with_filters() {
+        let state = test_state();
+        for name in ["a", "b", "c", "d"] {
+            let mut spec = test_deployment("default", name);
+            spec.labels.insert("team".into(), if name == "c" { "ops" } else { "web" }.into());
+            spec.paused = name == "b";
+            state.store.put_deployment(&spec).unwrap();
+        }
+        let query = |cursor: Option<&str>| ListQuery {
+            limit: Some(1),
+            cursor: cursor.map(str::to_string),
+            label_selector: Some("team=web".into()),
+            status: Some("active".into()),
+        };
+
+        let (_, first) = list_json(list_deployments(State(state.clone()), Query(query(None))).await).await;
+        assert_eq!(first["data"][0]["name"], "a");
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let (_, second) = list_json(list_deployments(State(state.clone()), Query(query(Some(cursor)))).await).await;
+        assert_eq!(second["data"][0]["name"], "d");
+        assert!(second.get("next_cursor").is_none());
+
+        let (_, all) = list_json(list_deployments(State(state.clone()), Query(ListQuery::default())).await).await;
+        assert_eq!(all["data"].as_array().unwrap().len(), 4);
+
+        for bad in [
+            ListQuery { limit: Some(0), ..Default::default() },
+            ListQuery { cursor: Some("zz".into()), ..Default::default() },
+            ListQuery { label_selector: Some("team".into()), ..Default::default() },
+            ListQuery { status: Some("running".into()), ..Default::default() },
+        ] {
+            let (status, _) = list_json(list_deployments(State(state.clone()), Query(bad)).await).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn list_instances_and_nodes_filter_by_status() {
+        let state = test_state();
+        for (id, status) in [("i-1", InstanceStatus::Running), ("i-2", InstanceStatus::Stopped)] {
+            state
+                .store
+                .put_instance(&InstanceState {
+                    id: id.into(),
+                    deployment_id: "default/api".into(),
+                    node_id: "node-1".into(),
+                    status,
+                    health: HealthStatus::Healthy,
+                    restart_count: 0,
+                    memory_bytes: 0,
+                    started_at: 0,
+                    updated_at: 0,
+                })
+                .unwrap();
+        }
+        let query = ListQuery { status: Some("stopped".into()), ..Default::default() };
+        let (_, body) = list_json(list_instances(State(state.clone()), Path("default/api".into()), Query(query)).await).await;
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["data"][0]["id"], "i-2");
+        let query = ListQuery { label_selector: Some("a=b".into()), ..Default::default() };
+        let (status, _) = list_json(list_instances(State(state.clone()), Path("default/api".into()), Query(query)).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let now = SystemClock.epoch_secs();
+        for (id, heartbeat, cordoned) in [("node-1", now, false), ("node-2", now, true), ("node-3", 0, false)] {
+            state
+                .store
+                .put_node(&NodeInfo {
+                    id: id.into(),
+                    address: "10.0.0.1".into(),
+                    port: 8443,
+                    capacity_memory_bytes: 0,
+                    capacity_cpu_weight: 0,
+                    used_memory_bytes: 0,
+                    used_cpu_weight: 0,
+                    labels: HashMap::from([("zone".into(), "a".into())]),
+                    last_heartbeat: heartbeat,
+                    pressure_until: None,
+                    replica_revision: None,
+                    calibrated_cpu_weight: None,
+                    cordoned,
+                })
+                .unwrap();
+        }
+        let ids = |body: &serde_json::Value| {
+            body["data"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        };
+        let query = ListQuery { status: Some("ready".into()), label_selector: Some("zone=a".into()), ..Default::default() };
+        let (_, body) = list_json(list_nodes(State(state.clone()), Query(query)).await).await;
+        assert_eq!(ids(&body), ["node-1", "node-2"]);
+        let query = ListQuery { status: Some("cordoned".into()), ..Default::default() };
+        let (_, body) = list_json(list_nodes(State(state.clone()), Query(query)).await).await;
+        assert_eq!(ids(&body), ["node-2"]);
+        let query = ListQuery { status: Some("not-ready".into()), ..Default::default() };
+        let (_, body) = list_json(list_nodes(State(state), Query(query)).await).await;
+        assert_eq!(ids(&body), ["node-3"]);
+    }
+
     #[tokio::test]
     async fn create_and_get_deployment() {
         let state = test_state();
         let spec = test_deployment("default", "api");
 
-        let resp = create_deployment(State(state.clone()), Json(spec.clone())).await;
+        let resp = create_deployment(State(state.clone()), HeaderMap::new(), Json(spec.clone())).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), StatusCode::CREATED);
 
@@ -248,7 +1248,7 @@ mod tests {
         let spec = test_deployment("default", "api");
         state.store.put_deployment(&spec).unwrap();
 
-        let resp = delete_deployment(State(state), Path("default/api".to_string())).await;
+        let resp = delete_deployment(State(state), Path("default/api".to_string()), HeaderMap::new()).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), StatusCode::OK);
     }
@@ -256,7 +1256,7 @@ mod tests {
     #[tokio::test]
     async fn delete_nonexistent_deployment() {
         let state = test_state();
-        let resp = delete_deployment(State(state), Path("nope".to_string())).await;
+        let resp = delete_deployment(State(state), Path("nope".to_string()), HeaderMap::new()).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
@@ -267,24 +1267,442 @@ mod tests {
         let spec = test_deployment("default", "api");
         state.store.put_deployment(&spec).unwrap();
 
-        let req = ScaleRequest { target: 100 };
+        let req = ScaleRequest { target: Some(100), ..Default::default() };
         let resp = scale_deployment(
             State(state),
             Path("default/api".to_string()),
+            HeaderMap::new(),
             Json(req),
         ).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[tokio::test]
+    async fn scale_updates_instance_bounds() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let scale = |req| scale_deployment(State(state.clone()), Path("default/api".to_string()), HeaderMap::new(), Json(req));
+
+        let resp = scale(ScaleRequest { min: Some(2), max: Some(8), ..Default::default() }).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = scale(ScaleRequest { target: Some(6), ..Default::default() }).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let spec = state.store.get_deployment("default/api").unwrap().unwrap();
+        assert_eq!((spec.instances.min, spec.instances.max), (6, 8));
+
+        for bad in [
+            ScaleRequest { min: Some(9), ..Default::default() },
+            ScaleRequest { max: Some(0), min: Some(0), ..Default::default() },
+            ScaleRequest { target: Some(2), min: Some(2), ..Default::default() },
+        ] {
+            let resp = scale(bad).await.into_response();
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+        let spec = state.store.get_deployment("default/api").unwrap().unwrap();
+        assert_eq!((spec.instances.min, spec.instances.max), (6, 8));
+    }
+
+    #[tokio::test]
+    async fn rollback_restores_an_earlier_revision() {
+        let state = test_state();
+        let rollback = |revision| {
+            let query = Query(RollbackQuery { revision });
+            rollback_deployment(State(state.clone()), Path("default/api".to_string()), query, HeaderMap::new())
+        };
+        assert_eq!(rollback(None).await.into_response().status(), StatusCode::NOT_FOUND);
+        let mut spec = test_deployment("default", "api");
+        state.store.put_deployment(&spec).unwrap();
+        assert_eq!(rollback(None).await.into_response().status(), StatusCode::CONFLICT);
+
+        spec.source = "file://v2.wasm".to_string();
+        spec.updated_at = 2000;
+        state.store.put_deployment(&spec).unwrap();
+        assert_eq!(rollback(Some(9)).await.into_response().status(), StatusCode::NOT_FOUND);
+        assert_eq!(rollback(None).await.into_response().status(), StatusCode::OK);
+        let restored = state.store.get_deployment("default/api").unwrap().unwrap();
+        assert_eq!((restored.source.as_str(), restored.created_at), ("file://test.wasm", 1000));
+
+        // The rollback is a revision of its own, so it can be undone.
+        let (status, revisions) = list_json(list_revisions(State(state.clone()), Path("default/api".to_string())).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(revisions["data"].as_array().unwrap().len(), 3);
+        assert_eq!(rollback(Some(2)).await.into_response().status(), StatusCode::OK);
+        assert_eq!(state.store.get_deployment("default/api").unwrap().unwrap().source, "file://v2.wasm");
+        let events = state.store.list_events(0, 10, |_| true).unwrap();
+        assert_eq!(events.last().unwrap().message, "rolled back to revision 2");
+    }
+
+    #[tokio::test]
+    async fn deployment_writes_honour_if_match() {
+        let state = test_state();
+        let spec = test_deployment("default", "api");
+        let headers = |name, value| HeaderMap::from_iter([(name, HeaderValue::from_static(value))]);
+        let id = || Path("default/api".to_string());
+        let scale = || Json(ScaleRequest { target: Some(2), ..Default::default() });
+
+        let create = create_deployment(State(state.clone()), headers(IF_MATCH, "*"), Json(spec.clone())).await;
+        assert_eq!(create.into_response().status(), StatusCode::PRECONDITION_FAILED);
+        let create = create_deployment(State(state.clone()), headers(IF_NONE_MATCH, "*"), Json(spec.clone())).await;
+        let create = create.into_response();
+        assert_eq!((create.status(), create.headers()[ETAG].to_str().unwrap()), (StatusCode::CREATED, "\"1\""));
+        let again = create_deployment(State(state.clone()), headers(IF_NONE_MATCH, "*"), Json(spec)).await;
+        assert_eq!(again.into_response().status(), StatusCode::PRECONDITION_FAILED);
+
+        let get = get_deployment(State(state.clone()), id()).await.into_response();
+        assert_eq!(get.headers()[ETAG], "\"1\"");
+        let stale = scale_deployment(State(state.clone()), id(), headers(IF_MATCH, "\"2\""), scale()).await;
+        assert_eq!(stale.into_response().status(), StatusCode::PRECONDITION_FAILED);
+        let scaled = scale_deployment(State(state.clone()), id(), headers(IF_MATCH, "\"1\""), scale()).await;
+        let scaled = scaled.into_response();
+        assert_eq!((scaled.status(), scaled.headers()[ETAG].to_str().unwrap()), (StatusCode::OK, "\"2\""));
+
+        let invalid = delete_deployment(State(state.clone()), id(), headers(IF_MATCH, "2")).await;
+        assert_eq!(invalid.into_response().status(), StatusCode::BAD_REQUEST);
+        let stale = delete_deployment(State(state.clone()), id(), headers(IF_MATCH, "\"1\"")).await;
+        assert_eq!(stale.into_response().status(), StatusCode::PRECONDITION_FAILED);
+        let deleted = delete_deployment(State(state.clone()), id(), headers(IF_MATCH, "\"2\"")).await;
+        assert_eq!(deleted.into_response().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn deployment_changes_record_events() {
+        let state = test_state();
+        let spec = test_deployment("default", "api");
+        assert!(create_deployment(State(state.clone()), HeaderMap::new(), Json(spec.clone())).await.into_response().status().is_success());
+        assert!(create_deployment(State(state.clone()), HeaderMap::new(), Json(spec)).await.into_response().status().is_success());
+        let req = ScaleRequest { target: Some(3), ..Default::default() };
+        assert!(scale_deployment(State(state.clone()), Path("default/api".to_string()), HeaderMap::new(), Json(req)).await.into_response().status().is_success());
+        assert!(delete_deployment(State(state.clone()), Path("default/api".to_string()), HeaderMap::new()).await.into_response().status().is_success());
+
+        let events = state.store.list_events(0, 10, |_| true).unwrap();
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ClusterEventKind::DeploymentCreated,
+                ClusterEventKind::DeploymentUpdated,
+                ClusterEventKind::DeploymentScaled,
+                ClusterEventKind::DeploymentDeleted,
+            ]
+        );
+        assert_eq!(events[2].message, "scaled from 1-10 to 3-10 instances");
+        assert!(events.iter().all(|e| e.deployment_id.as_deref() == Some("default/api")));
+    }
+
+    fn labelled(name: &str, team: &str) -> DeploymentSpec {
+        let mut spec = test_deployment("default", name);
+        spec.labels.insert("team".to_string(), team.to_string());
+        spec
+    }
+
+    async fn batch(state: &ApiState, req: BatchRequest) -> (StatusCode, serde_json::Value) {
+        let resp = batch_deployments(State(state.clone()), Json(req)).await.into_response();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn batch_applies_to_selected_deployments_only() {
+        let state = test_state();
+        for spec in [labelled("api", "payments"), labelled("worker", "payments"), labelled("web", "growth")] {
+            state.store.put_deployment(&spec).unwrap();
+        }
+        let selector = HashMap::from([("team".to_string(), "payments".to_string())]);
+
+        let (status, json) = batch(
+            &state,
+            BatchRequest {
+                selector: selector.clone(),
+                operation: BatchOperation::SetEnv { env: HashMap::from([("LOG".to_string(), "debug".to_string())]) },
+                dry_run: false,
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["data"]["matched"], 2);
+        assert_eq!(state.store.get_deployment("default/api").unwrap().unwrap().env["LOG"], "debug");
+        assert!(state.store.get_deployment("default/web").unwrap().unwrap().env.is_empty());
+
+        batch(&state, BatchRequest { selector, operation: BatchOperation::Pause, dry_run: false }).await;
+        assert!(state.store.get_deployment("default/worker").unwrap().unwrap().paused);
+        assert!(!state.store.get_deployment("default/web").unwrap().unwrap().paused);
+    }
+
+    #[tokio::test]
+    async fn batch_dry_run_changes_nothing() {
+        let state = test_state();
+        state.store.put_deployment(&labelled("api", "payments")).unwrap();
+        let selector = HashMap::from([("team".to_string(), "payments".to_string())]);
+
+        let (_, json) = batch(&state, BatchRequest { selector, operation: BatchOperation::Delete, dry_run: true }).await;
+        assert_eq!(json["data"]["dry_run"], true);
+        assert_eq!(json["data"]["results"][0]["ok"], true);
+        assert!(state.store.get_deployment("default/api").unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn batch_reports_per_item_failures() {
+        let state = test_state();
+        let mut small = labelled("api", "payments");
+        small.instances.max = 2;
+        state.store.put_deployment(&small).unwrap();
+        state.store.put_deployment(&labelled("worker", "payments")).unwrap();
+        let selector = HashMap::from([("team".to_string(), "payments".to_string())]);
+
+        let (status, json) =
+            batch(&state, BatchRequest { selector, operation: BatchOperation::Scale { target: 5 }, dry_run: false }).await;
+        assert_eq!(status, StatusCode::OK);
+        let results: Vec<BatchItemResult> = serde_json::from_value(json["data"]["results"].clone()).unwrap();
+        let api = results.iter().find(|r| r.deployment_id == "default/api").unwrap();
+        assert!(!api.ok);
+        assert_eq!(api.error.as_deref(), Some("target 5 exceeds max 2"));
+        assert!(results.iter().find(|r| r.deployment_id == "default/worker").unwrap().ok);
+
+        let (status, _) = batch(
+            &state,
+            BatchRequest { selector: HashMap::new(), operation: BatchOperation::Pause, dry_run: false },
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn flags_are_set_individually_and_validated() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let id = || "default/api".to_string();
+
+        let resp = put_flag(
+            State(state.clone()),
+            Path((id(), "new-checkout".to_string())),
+            Json(FeatureFlag::Percentage { percent: 20 }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = put_flag(
+            State(state.clone()),
+            Path((id(), "broken".to_string())),
+            Json(FeatureFlag::Percentage { percent: 120 }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = get_flags(State(state.clone()), Path(id())).await.into_response();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["flags"]["new-checkout"]["percent"], 20);
+        assert!(json["data"]["flags"].get("broken").is_none());
+
+        let resp = delete_flag(State(state.clone()), Path((id(), "new-checkout".to_string())))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(state.store.get_flags("default/api").unwrap().unwrap().set.flags.is_empty());
+    }
+
+    #[tokio::test]
+    async fn flags_require_an_existing_deployment() {
+        let state = test_state();
+        let resp = put_flags(State(state), Path("nope".to_string()), Json(FlagSet::default()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn config_bundles_are_staged_and_validated() {
+        let state = test_state();
+        let mut bundle = ConfigBundle::default();
+        bundle.files.insert("/etc/app.toml".to_string(), "port = 80".to_string());
+
+        let resp = put_config(State(state.clone()), Path("default/api".to_string()), Json(bundle.clone()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let mut relative = ConfigBundle::default();
+        relative.files.insert("app.toml".to_string(), String::new());
+        let resp = put_config(State(state.clone()), Path("default/api".to_string()), Json(relative))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = put_config(State(state.clone()), Path("default/api".to_string()), Json(bundle.clone()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = get_config(State(state.clone()), Path("default/api".to_string())).await.into_response();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["staged"]["digest"], bundle.digest());
+        assert!(json["data"]["active"].is_null());
+
+        delete_deployment(State(state.clone()), Path("default/api".to_string()), HeaderMap::new()).await;
+        assert!(state.store.get_deployment_config("default/api").unwrap().is_none());
+        assert!(state.store.get_config_bundle(&bundle.digest()).unwrap().is_none());
+    }
+
+    fn usage_event(event_id: &str) -> UsageEvent {
+        UsageEvent {
+            event_id: event_id.to_string(),
+            deployment_id: "default/api".to_string(),
+            instance_id: "inst-0".to_string(),
+            node_id: "node-1".to_string(),
+            started_at_ms: 1_000,
+            wall_time_ms: 25,
+            cpu_time_ms: 10,
+            memory_limit_bytes: 64 * 1024 * 1024,
+            budget_exceeded: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn usage_events_page_through_stream() {
+        let state = test_state();
+        for id in ["a", "b", "c"] {
+            state.store.record_usage(&usage_event(id)).unwrap();
+        }
+
+        let query = UsageEventsQuery {
+            after: 1,
+            limit: Some(1),
+        };
+        let resp = list_usage_events(State(state), Query(query)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["next_cursor"], 2);
+        assert_eq!(json["data"]["events"][0]["event_id"], "b");
+    }
+
+    #[tokio::test]
+    async fn usage_rollups_for_deployment() {
+        let state = test_state();
+        state.store.record_usage(&usage_event("a")).unwrap();
+
+        let resp = get_usage_rollups(
+            State(state),
+            Path("default/api".to_string()),
+            Query(UsageRollupsQuery { since: 0 }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"][0]["requests"], 1);
+    }
+
     #[tokio::test]
     async fn list_nodes_empty() {
         let state = test_state();
-        let resp = list_nodes(State(state)).await;
+        let resp = list_nodes(State(state), Query(ListQuery::default())).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn state_integrity_report_includes_last_scan() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+
+        let resp = verify_state_integrity(State(state.clone())).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = get_state_integrity(State(state)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["corrupt_records_detected"], 0);
+        assert!(json["data"]["last_scan"]["records_checked"].as_u64().unwrap() >= 1);
+        assert_eq!(json["data"]["quarantined"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn metrics_since_returns_the_newest_snapshots() {
+        let state = test_state();
+        for epoch in [1_000, 1_010, 1_020, 1_030] {
+            state
+                .store
+                .put_metrics(&MetricsSnapshot {
+                    deployment_id: "default/api".to_string(),
+                    epoch,
+                    rps: 1.0,
+                    latency_p50_ms: 1.0,
+                    latency_p99_ms: 2.0,
+                    error_rate: 0.0,
+                    total_memory_bytes: 0,
+                    active_instances: 1,
+                })
+                .unwrap();
+        }
+        let epochs = |resp: axum::response::Response| async move {
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            json["data"].as_array().unwrap().iter().map(|m| m["epoch"].as_u64().unwrap()).collect::<Vec<_>>()
+        };
+
+        let query = MetricsQuery { since: Some(1_015) };
+        let resp = get_metrics(State(state.clone()), Path("default/api".to_string()), Query(query)).await;
+        assert_eq!(epochs(resp.into_response()).await, [1_020, 1_030]);
+        let resp = get_metrics(State(state), Path("default/api".to_string()), Query(MetricsQuery::default())).await;
+        assert_eq!(epochs(resp.into_response()).await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn metrics_range_buckets_by_step() {
+        let state = test_state();
+        for (epoch, rps, p99) in [(990, 9.0, 9.0), (1_000, 1.0, 5.0), (1_010, 3.0, 2.0), (1_060, 4.0, 1.0)] {
+            state
+                .store
+                .put_metrics(&MetricsSnapshot {
+                    deployment_id: "default/api".to_string(),
+                    epoch,
+                    rps,
+                    latency_p50_ms: 1.0,
+                    latency_p99_ms: p99,
+                    error_rate: 0.0,
+                    total_memory_bytes: epoch,
+                    active_instances: 1,
+                })
+                .unwrap();
+        }
+        let range = |start, end, step| {
+            let query = MetricsRangeQuery { start, end: Some(end), step };
+            let resp = get_metrics_range(State(state.clone()), Path("default/api".to_string()), Query(query));
+            async { resp.await.into_response() }
+        };
+        let data = |resp: axum::response::Response| async move {
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["data"].clone()
+        };
+
+        let raw = data(range(1_000, 1_060, None).await).await;
+        assert_eq!(raw.as_array().unwrap().len(), 3);
+        let points = data(range(1_000, 1_100, Some(30)).await).await;
+        assert_eq!(
+            points,
+            serde_json::json!([
+                {"deployment_id": "default/api", "epoch": 1_000, "rps": 2.0, "latency_p50_ms": 1.0, "latency_p99_ms": 5.0,
+                 "error_rate": 0.0, "total_memory_bytes": 1_010, "active_instances": 1},
+                {"deployment_id": "default/api", "epoch": 1_060, "rps": 4.0, "latency_p50_ms": 1.0, "latency_p99_ms": 1.0,
+                 "error_rate": 0.0, "total_memory_bytes": 1_060, "active_instances": 1},
+            ])
+        );
+        assert_eq!(range(1_100, 1_000, None).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(range(0, 1_000_000, Some(1)).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(range(0, 1_000, Some(0)).await.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn prometheus_endpoint_returns_text() {
         let state = test_state();
@@ -0,0 +1,127 @@
+//! Daemon health for supervisors and load balancers.
+//!
+//! - `GET /healthz` answers `200` while warpd serves requests at all, with
+//!   the status of each subsystem for information
+//! - `GET /readyz` answers `200` only when every subsystem is ready, and
+//!   `503 Service Unavailable` otherwise
+//!
+//! Both return a [`HealthReport`]. The state store is always checked by
+//! committing an empty write. warpd adds the checks of its mode (the wasm
+//! runtime, or a known Raft leader on a control plane) as [`HealthChecks`]
+//! attached with [`axum::Extension`]. Neither route needs an API token or
+//! counts against rate limits.
+
+use std::sync::Arc;
+
+use axum::Extension;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+
+use crate::ApiState;
+
+/// One subsystem's readiness check.
+pub trait HealthCheck: Send + Sync {
+    /// Subsystem name, e.g. `runtime`.
+    fn name(&self) -> &'static str;
+
+    /// `Err` with the reason when the subsystem is not ready.
+    fn check(&self) -> BoxFuture<'_, Result<(), String>>;
+}
+
+/// The checks warpd adds to the state store's.
+#[derive(Clone, Default)]
+pub struct HealthChecks(pub Vec<Arc<dyn HealthCheck>>);
+
+/// Body of `/healthz` and `/readyz`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct HealthReport {
+    /// `ok` when every check passed, otherwise `unavailable`.
+    pub status: String,
+    pub checks: Vec<CheckResult>,
+}
+
+/// One subsystem's status.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HealthReport {
+    fn ready(&self) -> bool {
+        self.checks.iter().all(|check| check.ok)
+    }
+}
+
+async fn report(state: &ApiState, checks: Option<Extension<HealthChecks>>) -> HealthReport {
+    let mut results = vec![result("state_store", state.store.check_writable().map_err(|e| e.to_string()))];
+    for check in checks.map(|Extension(checks)| checks.0).unwrap_or_default() {
+        results.push(result(check.name(), check.check().await));
+    }
+    let ready = results.iter().all(|check| check.ok);
+    HealthReport { status: if ready { "ok" } else { "unavailable" }.to_string(), checks: results }
+}
+
+fn result(name: &str, outcome: Result<(), String>) -> CheckResult {
+    CheckResult { name: name.to_string(), ok: outcome.is_ok(), error: outcome.err() }
+}
+
+/// GET /healthz
+pub async fn healthz(State(state): State<ApiState>, checks: Option<Extension<HealthChecks>>) -> Response {
+    Json(report(&state, checks).await).into_response()
+}
+
+/// GET /readyz
+pub async fn readyz(State(state): State<ApiState>, checks: Option<Extension<HealthChecks>>) -> Response {
+    let report = report(&state, checks).await;
+    let status = if report.ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::extract::Request;
+    use tower::ServiceExt;
+    use warpgrid_state::StateStore;
+
+    struct NoLeader;
+
+    impl HealthCheck for NoLeader {
+        fn name(&self) -> &'static str {
+            "raft"
+        }
+
+        fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(async { Err("no leader elected".to_string()) })
+        }
+    }
+
+    async fn get(router: &axum::Router, path: &str) -> (StatusCode, HealthReport) {
+        let response = router.clone().oneshot(Request::get(path).body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn readyz_fails_while_a_check_fails() {
+        let router = crate::build_router(StateStore::open_in_memory().unwrap());
+        let (status, report) = get(&router, "/readyz").await;
+        assert_eq!((status, report.status.as_str(), report.checks.len()), (StatusCode::OK, "ok", 1));
+
+        let router = router.layer(Extension(HealthChecks(vec![Arc::new(NoLeader)])));
+        let (status, report) = get(&router, "/readyz").await;
+        assert_eq!((status, report.status.as_str()), (StatusCode::SERVICE_UNAVAILABLE, "unavailable"));
+        assert_eq!(report.checks[1].error.as_deref(), Some("no leader elected"));
+        // Liveness holds regardless.
+        let (status, report) = get(&router, "/healthz").await;
+        assert_eq!((status, report.checks[0].ok, report.checks[1].ok), (StatusCode::OK, true, false));
+    }
+}
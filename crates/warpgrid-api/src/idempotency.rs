@@ -0,0 +1,305 @@
+//! `Idempotency-Key` support for `POST` requests, so a client that lost a
+//! response can retry without creating, scaling, or rolling back twice.
+//!
+//! Keys are scoped to the API token that sends them, so clients never
+//! replay or block one another's requests. The first request with a key
+//! reserves it and runs as usual; its status
+//! and JSON body are then kept for a day
+//! ([`IDEMPOTENCY_KEY_TTL_SECS`](warpgrid_state::IDEMPOTENCY_KEY_TTL_SECS)).
+//! A retry with the same key and the same request (method, path and query,
+//! `Authorization`, and body) gets that response again, marked
+//! `Idempotent-Replayed: true`, without running the handler. A retry while
+//! the first is still running gets `409 Conflict`, and reusing a key for a
+//! different request gets `422 Unprocessable Entity`. `5xx` responses are
+//! not kept, so the request can be retried in full. Neither is a request
+//! the client abandons or whose handler panics; one cut short by a daemon
+//! crash holds its key for
+//! [`IDEMPOTENCY_PENDING_TTL_SECS`](warpgrid_state::IDEMPOTENCY_PENDING_TTL_SECS).
+//!
+//! Uploads to `/api/v1/artifacts` are streamed and already idempotent by
+//! digest, and responses from `/api/v1/tokens` carry a token that is never
+//! stored; both ignore the header.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::body::{Body, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use warpgrid_state::{Clock, IdempotencyRecord, StateResult, StateStore, SystemClock};
+
+use crate::auth;
+use crate::handlers::error_response;
+
+/// The request header carrying the key.
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Set on a response replayed for a retry.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Longest key accepted.
+const MAX_KEY_LEN: usize = 255;
+
+/// Largest request body buffered to fingerprint a request.
+const MAX_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// Largest response kept for replay; a larger one releases the key.
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// How often expired keys are pruned, in seconds.
+const PRUNE_INTERVAL_SECS: u64 = 3600;
+
+/// Paths that ignore `Idempotency-Key`.
+const EXEMPT_PATHS: [&str; 2] = ["/api/v1/artifacts", "/api/v1/tokens"];
+
+/// State for [`idempotent`].
+pub struct Idempotency {
+    store: StateStore,
+    /// When expired keys were last pruned.
+    pruned_at: AtomicU64,
+}
+
+impl Idempotency {
+    pub fn new(store: StateStore) -> Arc<Self> {
+        Arc::new(Self { store, pruned_at: AtomicU64::new(0) })
+    }
+
+    fn prune(&self, now: u64) {
+        let last = self.pruned_at.load(Ordering::Relaxed);
+        if now < last + PRUNE_INTERVAL_SECS
+            || self.pruned_at.compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_err()
+        {
+            return;
+        }
+        match self.store.prune_idempotency_keys(now) {
+            Ok(0) => {}
+            Ok(pruned) => tracing::debug!(pruned, "expired idempotency keys pruned"),
+            Err(e) => tracing::warn!(error = %e, "failed to prune idempotency keys"),
+        }
+    }
+}
+
+/// Middleware: answer a `POST` carrying `Idempotency-Key` once, replaying
+/// the response to retries.
+pub async fn idempotent(State(state): State<Arc<Idempotency>>, req: Request, next: Next) -> Response {
+    if req.method() != Method::POST || EXEMPT_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let Some(key) = req.headers().get(&IDEMPOTENCY_KEY) else {
+        return next.run(req).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if valid_key(key) => key.to_string(),
+        _ => {
+            let msg = format!("Idempotency-Key must be 1-{MAX_KEY_LEN} visible ASCII characters");
+            return error_response(&msg, StatusCode::BAD_REQUEST).into_response();
+        }
+    };
+
+    let key = match scoped_key(&state.store, req.headers(), &key) {
+        Ok(key) => key,
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+
+    let (parts, body) = req.into_parts();
+    let body = match to_bytes(body, MAX_REQUEST_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            let msg = format!("request bodies sent with Idempotency-Key are limited to {MAX_REQUEST_BYTES} bytes");
+            return error_response(&msg, StatusCode::PAYLOAD_TOO_LARGE).into_response();
+        }
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update([0]);
+    hasher.update(parts.uri.path_and_query().map_or("", |p| p.as_str()));
+    hasher.update([0]);
+    hasher.update(parts.headers.get(AUTHORIZATION).map_or(&[][..], HeaderValue::as_bytes));
+    hasher.update([0]);
+    hasher.update(&body);
+    let fingerprint = hex::encode(hasher.finalize());
+
+    let now = SystemClock.epoch_secs();
+    state.prune(now);
+    match state.store.reserve_idempotency_key(&key, &fingerprint, now) {
+        Ok(None) => {}
+        Ok(Some(held)) if held.fingerprint != fingerprint => {
+            let msg = "Idempotency-Key was already used for a different request";
+            return error_response(msg, StatusCode::UNPROCESSABLE_ENTITY).into_response();
+        }
+        Ok(Some(held)) => return replay(&held),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+    // Released unless a response is stored, including when the client
+    // disconnects or the handler panics and this future is dropped.
+    let reservation = Reservation { store: &state.store, key: &key };
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    if let Ok(text) = std::str::from_utf8(&body)
+        && !parts.status.is_server_error()
+        && body.len() <= MAX_RESPONSE_BYTES
+    {
+        let record = IdempotencyRecord {
+            key: key.clone(),
+            fingerprint,
+            status: Some(parts.status.as_u16()),
+            body: text.to_string(),
+            created_at: now,
+        };
+        match state.store.complete_idempotency_key(&record) {
+            Ok(()) => std::mem::forget(reservation),
+            Err(e) => tracing::warn!(%key, error = %e, "failed to store idempotent response"),
+        }
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// A reserved key, released when dropped.
+struct Reservation<'a> {
+    store: &'a StateStore,
+    key: &'a str,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.store.release_idempotency_key(self.key) {
+            tracing::warn!(key = %self.key, error = %e, "failed to release idempotency key");
+        }
+    }
+}
+
+/// `key` as stored, prefixed with the id of the request's API token (empty
+/// without one). Token ids are hex, so the prefix never runs into the key.
+fn scoped_key(store: &StateStore, headers: &HeaderMap, key: &str) -> StateResult<String> {
+    let token = match auth::bearer(headers) {
+        Some(token) => store.get_api_token(&auth::digest(token))?.map(|token| token.id),
+        None => None,
+    };
+    Ok(format!("{}/{key}", token.unwrap_or_default()))
+}
+
+fn valid_key(key: &str) -> bool {
+    (1..=MAX_KEY_LEN).contains(&key.len()) && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// The stored response for a retry, or `409` while the first request runs.
+fn replay(held: &IdempotencyRecord) -> Response {
+    let Some(status) = held.status.and_then(|status| StatusCode::from_u16(status).ok()) else {
+        let msg = "a request with this Idempotency-Key is still in progress";
+        return error_response(msg, StatusCode::CONFLICT).into_response();
+    };
+    let mut response = (status, held.body.clone()).into_response();
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    async fn post(router: &Router, key: &str, body: &str) -> (StatusCode, Option<HeaderValue>, String) {
+        post_as(router, None, key, body).await
+    }
+
+    async fn post_as(
+        router: &Router,
+        token: Option<&str>,
+        key: &str,
+        body: &str,
+    ) -> (StatusCode, Option<HeaderValue>, String) {
+        let mut req = Request::post("/api/v1/secrets")
+            .header(IDEMPOTENCY_KEY, key)
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let req = req.body(Body::from(body.to_string())).unwrap();
+        let response = router.clone().oneshot(req).await.unwrap();
+        let status = response.status();
+        let replayed = response.headers().get(&IDEMPOTENT_REPLAYED).cloned();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, replayed, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn retries_replay_the_first_response() {
+        let store = StateStore::open_in_memory().unwrap();
+        let router = crate::build_router(store.clone());
+        let secret = r#"{"name":"db","value":"hunter2"}"#;
+
+        let (status, replayed, first) = post(&router, "create-db", secret).await;
+        assert_eq!((status, replayed), (StatusCode::CREATED, None));
+        let (status, replayed, again) = post(&router, "create-db", secret).await;
+        assert_eq!((status, replayed.as_ref().map(|v| v.to_str().unwrap())), (StatusCode::CREATED, Some("true")));
+        assert_eq!(again, first);
+        assert_eq!(store.list_secrets("default").unwrap().len(), 1);
+
+        // A different request under the same key is refused; without the
+        // first having run, the create would conflict.
+        let (status, _, _) = post(&router, "create-db", r#"{"name":"db","value":"other"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _, _) = post(&router, "create-db-2", secret).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _, _) = post(&router, "bad key", secret).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn keys_are_scoped_to_the_token() {
+        let store = StateStore::open_in_memory().unwrap();
+        let router = crate::build_router(store.clone());
+        let alice = auth::create_token(&store, "alice").unwrap().token;
+        let bob = auth::create_token(&store, "bob").unwrap().token;
+
+        let (status, _, first) = post_as(&router, Some(&alice), "create", r#"{"name":"a","value":"1"}"#).await;
+        assert_eq!(status, StatusCode::CREATED);
+        // Another client's key of the same name is its own.
+        let (status, replayed, _) = post_as(&router, Some(&bob), "create", r#"{"name":"b","value":"2"}"#).await;
+        assert_eq!((status, replayed), (StatusCode::CREATED, None));
+        let (status, replayed, again) = post_as(&router, Some(&alice), "create", r#"{"name":"a","value":"1"}"#).await;
+        assert_eq!((status, replayed.is_some()), (StatusCode::CREATED, true));
+        assert_eq!(again, first);
+        assert_eq!(store.list_secrets("default").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn abandoned_requests_release_their_key() {
+        let store = StateStore::open_in_memory().unwrap();
+        let router = Router::new()
+            .route("/api/v1/slow", axum::routing::post(std::future::pending::<()>))
+            .layer(axum::middleware::from_fn_with_state(Idempotency::new(store.clone()), idempotent));
+        let req = Request::post("/api/v1/slow").header(IDEMPOTENCY_KEY, "k1").body(Body::empty()).unwrap();
+
+        // The client gives up; the request future is dropped mid-handler.
+        let abandoned = tokio::time::timeout(std::time::Duration::from_millis(50), router.oneshot(req)).await;
+        assert!(abandoned.is_err());
+        assert_eq!(store.reserve_idempotency_key("/k1", "fp", SystemClock.epoch_secs()).unwrap(), None);
+    }
+
+    #[test]
+    fn in_flight_keys_conflict() {
+        let held = IdempotencyRecord {
+            key: "busy".into(),
+            fingerprint: "fp".into(),
+            status: None,
+            body: String::new(),
+            created_at: 0,
+        };
+        assert_eq!(replay(&held).status(), StatusCode::CONFLICT);
+        let done = IdempotencyRecord { status: Some(202), body: "{}".into(), ..held };
+        assert_eq!(replay(&done).status(), StatusCode::ACCEPTED);
+    }
+}
@@ -7,32 +7,124 @@
 //!
 //! | Method | Path | Description |
 //! |---|---|---|
-//! | GET | `/api/v1/deployments` | List all deployments |
+//! | GET | `/api/v1/deployments` | List deployments, paged and filtered (see [`handlers::ListQuery`]) |
 //! | POST | `/api/v1/deployments` | Create a deployment |
+//! | POST | `/api/v1/deployments:batch` | Scale, pause, resume, delete, or set env by label selector |
 //! | GET | `/api/v1/deployments/:id` | Get deployment details |
 //! | DELETE | `/api/v1/deployments/:id` | Delete a deployment |
 //! | POST | `/api/v1/deployments/:id/scale` | Scale a deployment |
-//! | GET | `/api/v1/deployments/:id/instances` | List instances |
-//! | GET | `/api/v1/deployments/:id/metrics` | Get metrics |
+//! | GET | `/api/v1/deployments/:id/revisions` | List a deployment's spec revisions |
+//! | POST | `/api/v1/deployments/:id/rollback` | Roll back to an earlier revision (`?revision=N`, default the previous one) |
+//! | GET | `/api/v1/deployments/:id/instances` | List instances, paged and filtered |
+//! | GET | `/api/v1/deployments/:id/metrics` | Get metrics (`?since=` for snapshots since a unix time) |
+//! | GET | `/api/v1/deployments/:id/metrics/range` | Metrics between `start` and `end`, bucketed by `step` seconds |
+//! | GET | `/api/v1/deployments/:id/usage` | Get usage rollups |
+//! | GET | `/api/v1/deployments/:id/logs` | Captured guest stdout/stderr lines; `?follow=true` streams them (SSE or WebSocket) |
+//! | GET | `/api/v1/deployments/:id/logs/stream` | Guest log lines as they arrive (SSE or WebSocket) |
+//! | POST | `/api/v1/deployments/:id/exec` | Call a component export on a pooled instance |
+//! | GET | `/api/v1/deployments/:id/port-forward` | Upgrade to a tunnel to the deployment's HTTP trigger |
+//! | GET | `/api/v1/deployments/:id/health` | Healthy, degraded, unhealthy, or progressing, with reasons |
+//! | GET | `/api/v1/deployments/:id/flags` | Get feature flags |
+//! | PUT | `/api/v1/deployments/:id/flags` | Replace all feature flags |
+//! | PUT | `/api/v1/deployments/:id/flags/:name` | Set one feature flag |
+//! | DELETE | `/api/v1/deployments/:id/flags/:name` | Remove one feature flag |
+//! | GET | `/api/v1/deployments/:id/config` | Get active and staged config bundle |
+//! | PUT | `/api/v1/deployments/:id/config` | Stage a config bundle |
+//! | GET | `/api/v1/namespaces` | List namespaces with deployment and secret counts |
+//! | GET | `/api/v1/namespaces/:ns/deployments` | List a namespace's deployments, paged and filtered |
+//! | POST | `/api/v1/namespaces/:ns/deployments` | Create a deployment in a namespace |
+//! | GET | `/api/v1/namespaces/:ns/deployments/:name` | Get deployment details (alias of `/deployments/:ns%2F:name`) |
+//! | DELETE | `/api/v1/namespaces/:ns/deployments/:name` | Delete a deployment (alias of `/deployments/:ns%2F:name`) |
+//! | GET | `/api/v1/secrets` | List a namespace's secrets (without values) |
+//! | POST | `/api/v1/secrets` | Create a secret |
+//! | GET | `/api/v1/secrets/:id` | Get a secret's metadata (`?reveal=true` adds the value) |
+//! | PUT | `/api/v1/secrets/:id` | Create or replace a secret |
+//! | DELETE | `/api/v1/secrets/:id` | Delete a secret |
+//! | GET | `/api/v1/configmaps` | List a namespace's config maps |
+//! | POST | `/api/v1/configmaps` | Create a config map |
+//! | GET | `/api/v1/configmaps/:id` | Get a config map |
+//! | PUT | `/api/v1/configmaps/:id` | Create or replace a config map, restaging bound deployments |
+//! | DELETE | `/api/v1/configmaps/:id` | Delete a config map |
+//! | POST | `/api/v1/apply` | Diff a manifest of deployments against the cluster and converge (`?dry_run=true` only plans) |
+//! | POST | `/api/v1/artifacts` | Upload a wasm component, raw or multipart with its signature bundle |
+//! | GET | `/api/v1/webhooks` | List webhooks (without their secrets) |
+//! | POST | `/api/v1/webhooks` | Register a webhook for cluster events |
+//! | GET | `/api/v1/webhooks/:id` | Get a webhook (without its secret) |
+//! | DELETE | `/api/v1/webhooks/:id` | Delete a webhook |
+//! | GET | `/api/v1/tokens` | List API tokens (without the tokens) |
+//! | POST | `/api/v1/tokens` | Create an API token |
+//! | DELETE | `/api/v1/tokens/:id` | Revoke an API token |
+//! | GET | `/api/v1/usage/events` | Export the usage event stream |
+//! | GET | `/api/v1/events` | Cluster events, filtered; `?watch=true` streams them (server-sent events) |
 //! | POST | `/api/v1/deployments/:id/rollout` | Start rollout |
 //! | GET | `/api/v1/rollouts` | List active rollouts |
 //! | GET | `/api/v1/rollouts/:id` | Get rollout status |
 //! | POST | `/api/v1/rollouts/:id/pause` | Pause rollout |
 //! | POST | `/api/v1/rollouts/:id/resume` | Resume rollout |
-//! | GET | `/api/v1/nodes` | List nodes |
+//! | POST | `/api/v1/rollouts/:id/rollback` | Roll back an unfinished rollout |
+//! | GET | `/api/v1/watch` | Live cluster overview (server-sent events) |
+//! | GET | `/api/v1/nodes` | List nodes, paged and filtered |
+//! | GET | `/api/v1/nodes/:id` | Node details and the instances placed on it |
+//! | POST | `/api/v1/nodes/:id/cordon` | Stop placing new instances on a node |
+//! | POST | `/api/v1/nodes/:id/uncordon` | Allow placements on a node again |
+//! | POST | `/api/v1/nodes/:id/drain` | Cordon a node and evict its instances |
+//! | PATCH | `/api/v1/nodes/:id/labels` | Set node labels, removing those given as `null` |
+//! | GET | `/api/v1/capabilities` | Supported worlds, shim interfaces, features, and limits |
+//! | GET | `/api/v1/admin/state-integrity` | State corruption report |
+//! | POST | `/api/v1/admin/state-integrity/verify` | Run a state integrity scan |
+//! | GET | `/api/v1/openapi.json` | OpenAPI 3 document of these routes |
 //! | GET | `/metrics` | Prometheus exposition |
+//! | GET | `/healthz` | Liveness, with each subsystem's status |
+//! | GET | `/readyz` | Readiness; 503 until every subsystem is ready |
+//!
+//! Every `/deployments/:id` route is also served at
+//! `/namespaces/:ns/deployments/:name`, and a bare `:id` without a
+//! namespace means the `default` one; see [`namespaces`].
+//!
+//! `POST` requests may carry an `Idempotency-Key` so retries replay the
+//! first response ([`idempotency`]). Deployment writes honour `If-Match`
+//! against the version `GET /deployments/:id` returns as its `ETag`
+//! ([`handlers::deployment_precondition`]).
+//!
+//! Bearer tokens ([`auth`]) and rate limits ([`ratelimit`]) are opt-in
+//! layers warpd adds on top.
 
+pub mod apply;
+pub mod artifacts;
+pub mod auth;
+pub mod capabilities;
+pub mod events;
+pub mod configmaps;
+pub mod exec;
 pub mod handlers;
+pub mod health;
+pub mod idempotency;
+pub mod logs;
+pub mod namespaces;
+pub mod nodes;
+pub mod openapi;
+pub mod portforward;
+pub mod ratelimit;
 pub mod rollout_handlers;
+pub mod secrets;
+pub mod watch;
+pub mod webhooks;
 
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use axum::Router;
-use axum::routing::{get, post};
+use axum::extract::DefaultBodyLimit;
+use axum::middleware;
+use axum::routing::{delete, get, patch, post, put};
 use tokio::sync::RwLock;
 use warpgrid_state::StateStore;
 
+pub use artifacts::{ArtifactStore, ArtifactVerifier};
+pub use capabilities::Capabilities;
+pub use health::{HealthCheck, HealthChecks};
+pub use exec::{ExecError, ExportInvoker};
+pub use portforward::{TunnelServer, TunnelStream};
 pub use rollout_handlers::{RolloutApiState, RolloutStore};
 
 /// Shared state for API handlers.
@@ -49,6 +141,20 @@ pub fn build_router(store: StateStore) -> Router {
 
 /// Build the API router with an externally provided rollout store.
 pub fn build_router_with_rollouts(store: StateStore, rollouts: RolloutStore) -> Router {
+    build_management_router(store.clone(), rollouts).merge(build_metrics_router(store))
+}
+
+/// Build the Prometheus `/metrics` router on its own, for serving it on a
+/// separate listener from the management API.
+pub fn build_metrics_router(store: StateStore) -> Router {
+    Router::new()
+        .route("/metrics", get(handlers::prometheus_metrics))
+        .with_state(ApiState { store })
+}
+
+/// Build the management router (REST + dashboard + rollouts) without
+/// `/metrics`.
+pub fn build_management_router(store: StateStore, rollouts: RolloutStore) -> Router {
     let api_state = ApiState {
         store: store.clone(),
     };
@@ -63,26 +169,89 @@ pub fn build_router_with_rollouts(store: StateStore, rollouts: RolloutStore) ->
         rollouts,
     };
 
+    let probe_routes = Router::new()
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .with_state(api_state.clone());
+
+    // The deployment wizard and node actions call these through the
+    // dashboard, where the sign-in cookie authorizes them.
+    let dashboard_api_routes = Router::new()
+        .route("/apply", post(apply::apply))
+        .route("/artifacts", post(artifacts::upload_artifact).layer(DefaultBodyLimit::disable()))
+        .route("/nodes/{id}/cordon", post(nodes::cordon_node))
+        .route("/nodes/{id}/uncordon", post(nodes::uncordon_node))
+        .route("/nodes/{id}/drain", post(nodes::drain_node))
+        .with_state(api_state.clone());
+
     let api_routes = Router::new()
         .route("/deployments", get(handlers::list_deployments).post(handlers::create_deployment))
+        .route("/deployments:batch", post(handlers::batch_deployments))
         .route("/deployments/{id}", get(handlers::get_deployment).delete(handlers::delete_deployment))
         .route("/deployments/{id}/scale", post(handlers::scale_deployment))
+        .route("/deployments/{id}/revisions", get(handlers::list_revisions))
+        .route("/deployments/{id}/rollback", post(handlers::rollback_deployment))
         .route("/deployments/{id}/instances", get(handlers::list_instances))
         .route("/deployments/{id}/metrics", get(handlers::get_metrics))
+        .route("/deployments/{id}/metrics/range", get(handlers::get_metrics_range))
+        .route("/deployments/{id}/usage", get(handlers::get_usage_rollups))
+        .route("/deployments/{id}/logs", get(logs::get_logs))
+        .route("/deployments/{id}/logs/stream", get(logs::stream_logs))
+        .route("/deployments/{id}/flags", get(handlers::get_flags).put(handlers::put_flags))
+        .route("/deployments/{id}/flags/{name}", put(handlers::put_flag).delete(handlers::delete_flag))
+        .route("/deployments/{id}/config", get(handlers::get_config).put(handlers::put_config))
+        .route("/deployments/{id}/exec", post(exec::exec_export))
+        .route("/deployments/{id}/port-forward", get(portforward::port_forward))
+        .route("/namespaces", get(namespaces::list_namespaces))
+        .route(
+            "/namespaces/{ns}/deployments",
+            get(namespaces::list_namespace_deployments).post(namespaces::create_namespace_deployment),
+        )
+        .route("/secrets", get(secrets::list_secrets).post(secrets::create_secret))
+        .route("/secrets/{id}", get(secrets::get_secret).put(secrets::put_secret).delete(secrets::delete_secret))
+        .route("/configmaps", get(configmaps::list_config_maps).post(configmaps::create_config_map))
+        .route(
+            "/configmaps/{id}",
+            get(configmaps::get_config_map).put(configmaps::put_config_map).delete(configmaps::delete_config_map),
+        )
+        .route("/apply", post(apply::apply))
+        // Uploads are bounded by `artifacts::MAX_ARTIFACT_BYTES` instead.
+        .route("/artifacts", post(artifacts::upload_artifact).layer(DefaultBodyLimit::disable()))
+        .route("/webhooks", get(webhooks::list_webhooks).post(webhooks::create_webhook))
+        .route("/webhooks/{id}", get(webhooks::get_webhook).delete(webhooks::delete_webhook))
+        .route("/tokens", get(auth::list_tokens).post(auth::post_token))
+        .route("/tokens/{id}", delete(auth::delete_token))
+        .route("/usage/events", get(handlers::list_usage_events))
+        .route("/events", get(events::list_events))
         .route("/nodes", get(handlers::list_nodes))
-        .with_state(api_state.clone());
+        .route("/nodes/{id}", get(nodes::get_node))
+        .route("/nodes/{id}/cordon", post(nodes::cordon_node))
+        .route("/nodes/{id}/uncordon", post(nodes::uncordon_node))
+        .route("/nodes/{id}/drain", post(nodes::drain_node))
+        .route("/nodes/{id}/labels", patch(nodes::patch_node_labels))
+        .route("/capabilities", get(capabilities::get_capabilities))
+        .route("/admin/state-integrity", get(handlers::get_state_integrity))
+        .route("/admin/state-integrity/verify", post(handlers::verify_state_integrity))
+        .route("/openapi.json", get(openapi::get_openapi))
+        .with_state(api_state);
 
     let rollout_routes = Router::new()
         .route("/deployments/{id}/rollout", post(rollout_handlers::start_rollout))
+        .route("/deployments/{id}/health", get(rollout_handlers::get_deployment_health))
         .route("/rollouts", get(rollout_handlers::list_rollouts))
         .route("/rollouts/{id}", get(rollout_handlers::get_rollout))
         .route("/rollouts/{id}/pause", post(rollout_handlers::pause_rollout))
         .route("/rollouts/{id}/resume", post(rollout_handlers::resume_rollout))
+        .route("/rollouts/{id}/rollback", post(rollout_handlers::rollback_rollout))
+        .route("/watch", get(watch::watch))
         .with_state(rollout_state);
 
+    // `nest_service` keeps the v1 routes opaque to the outer router, so the
+    // namespace rewrite runs before they are matched.
     Router::new()
-        .nest("/api/v1", api_routes)
-        .nest("/api/v1", rollout_routes)
-        .nest("/dashboard", warpgrid_dashboard::dashboard_router(dashboard_state))
-        .route("/metrics", get(handlers::prometheus_metrics).with_state(api_state))
+        .nest_service("/api/v1", api_routes.merge(rollout_routes))
+        .layer(middleware::from_fn_with_state(idempotency::Idempotency::new(store), idempotency::idempotent))
+        .layer(middleware::map_request(namespaces::route_namespaced))
+        .merge(probe_routes)
+        .nest("/dashboard", warpgrid_dashboard::dashboard_router(dashboard_state).merge(dashboard_api_routes))
 }
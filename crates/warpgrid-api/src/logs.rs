@@ -0,0 +1,260 @@
+//! Captured guest output for `warp logs` and the dashboard log viewer.
+//!
+//! - `GET /api/v1/deployments/{id}/logs` returns the matching lines as JSON
+//! - `GET /api/v1/deployments/{id}/logs?follow=true` sends them as `log`
+//!   server-sent events, then keeps sending new lines as they arrive;
+//!   `/logs/stream` is the same stream
+//! - either path, requested as a WebSocket upgrade, follows the same way
+//!   with one JSON text message per line
+//!
+//! All take `since_ms` (Unix milliseconds), `after` (sequence number),
+//! `instance`, `stream` (`stdout` or `stderr`), and `limit` (newest lines,
+//! default [`DEFAULT_LIMIT`]). Each line carries its instance and
+//! timestamp. Lines come from this process's
+//! [`warpgrid_metrics::guest_logs`], so a node serves the output of the
+//! guests it runs.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::rejection::WebSocketUpgradeRejection;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::{Stream, StreamExt};
+use warpgrid_metrics::guest_logs::{GuestLogLine, LogQuery, LogStream, guest_logs};
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+
+/// Lines returned when no `limit` is given.
+pub const DEFAULT_LIMIT: usize = 500;
+
+/// How often a stream checks for new lines.
+const STREAM_POLL: Duration = Duration::from_millis(500);
+
+/// Query parameters for both endpoints.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct LogsQuery {
+    pub since_ms: Option<u64>,
+    pub after: Option<u64>,
+    pub instance: Option<String>,
+    pub stream: Option<LogStream>,
+    pub limit: Option<usize>,
+    /// Keep the response open and send new lines as they arrive.
+    #[serde(default)]
+    pub follow: bool,
+}
+
+impl LogsQuery {
+    fn query(&self, after: Option<u64>) -> LogQuery<'_> {
+        LogQuery {
+            after,
+            since_ms: self.since_ms,
+            instance: self.instance.as_deref(),
+            stream: self.stream,
+            limit: Some(self.limit.unwrap_or(DEFAULT_LIMIT)),
+        }
+    }
+}
+
+/// GET /api/v1/deployments/:id/logs
+pub async fn get_logs(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<LogsQuery>,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Response {
+    if let Some(response) = missing_deployment(&state, &id) {
+        return response;
+    }
+    match ws {
+        Ok(ws) => ws.on_upgrade(move |socket| send_lines(socket, follow(id, query))),
+        Err(_) if query.follow => sse(follow(id, query)),
+        Err(_) => ApiResponse::ok(guest_logs().query(&id, &query.query(query.after))).into_response(),
+    }
+}
+
+/// GET /api/v1/deployments/:id/logs/stream
+pub async fn stream_logs(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<LogsQuery>,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Response {
+    if let Some(response) = missing_deployment(&state, &id) {
+        return response;
+    }
+    match ws {
+        Ok(ws) => ws.on_upgrade(move |socket| send_lines(socket, follow(id, query))),
+        Err(_) => sse(follow(id, query)),
+    }
+}
+
+/// The backlog matching `query`, then every new line of deployment `id`.
+fn follow(id: String, query: LogsQuery) -> impl Stream<Item = GuestLogLine> + Send + 'static {
+    let query = Arc::new(query);
+    futures_util::stream::unfold(
+        (query.after, VecDeque::<GuestLogLine>::new(), true),
+        move |(mut after, mut pending, mut first)| {
+            let id = id.clone();
+            let query = query.clone();
+            async move {
+                loop {
+                    if let Some(line) = pending.pop_front() {
+                        after = Some(line.seq);
+                        return Some((line, (after, pending, first)));
+                    }
+                    let mut lines = query.query(after);
+                    if first {
+                        first = false;
+                    } else {
+                        // Only the backlog is limited; new lines all go out.
+                        tokio::time::sleep(STREAM_POLL).await;
+                        lines.limit = None;
+                    }
+                    pending.extend(guest_logs().query(&id, &lines));
+                }
+            }
+        },
+    )
+}
+
+fn sse(lines: impl Stream<Item = GuestLogLine> + Send + 'static) -> Response {
+    let events = lines.map(|line| {
+        Ok::<_, Infallible>(
+            Event::default().event("log").data(serde_json::to_string(&line).expect("log lines serialize")),
+        )
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// Send each line as a JSON text message until either side closes.
+async fn send_lines(mut socket: WebSocket, lines: impl Stream<Item = GuestLogLine>) {
+    let mut lines = std::pin::pin!(lines);
+    loop {
+        tokio::select! {
+            Some(line) = lines.next() => {
+                let text = serde_json::to_string(&line).expect("log lines serialize");
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// The error response when `id` is not a deployment.
+fn missing_deployment(state: &ApiState, id: &str) -> Option<Response> {
+    match state.store.get_deployment(id) {
+        Ok(Some(_)) => None,
+        Ok(None) => Some(error_response("deployment not found", StatusCode::NOT_FOUND).into_response()),
+        Err(e) => Some(error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warpgrid_state::{
+        DeploymentSpec, InstanceConstraints, ResourceLimits, ShimsEnabled, StateStore, TriggerConfig,
+    };
+
+    fn state_with(id: &str) -> ApiState {
+        let store = StateStore::open_in_memory().unwrap();
+        let (namespace, name) = id.split_once('/').unwrap();
+        store
+            .put_deployment(&DeploymentSpec {
+                id: id.to_string(),
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+                source: "file://test.wasm".to_string(),
+                trigger: TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
+                instances: InstanceConstraints { min: 1, max: 1 },
+                resources: ResourceLimits { memory_bytes: 64 << 20, cpu_weight: 100, execution_budget_ms: None },
+                scaling: None,
+                health: None,
+                shims: ShimsEnabled::default(),
+                env: Default::default(),
+                created_at: 1000,
+                updated_at: 1000,
+                priority: None,
+                min_available: None,
+                labels: Default::default(),
+                paused: false,
+                secrets: Vec::new(),
+                config_maps: Vec::new(),
+            })
+            .unwrap();
+        ApiState { store }
+    }
+
+    /// What a plain, non-upgrade request extracts as.
+    async fn not_upgrade() -> WebSocketUpgradeRejection {
+        use axum::extract::FromRequestParts;
+        let (mut parts, _) = axum::http::Request::new(()).into_parts();
+        WebSocketUpgrade::from_request_parts(&mut parts, &()).await.unwrap_err()
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_logs_filters_by_instance() {
+        let id = "logs-test/api";
+        let state = state_with(id);
+        guest_logs().push(id, "req-1", LogStream::Stdout, b"first");
+        guest_logs().push(id, "req-2", LogStream::Stdout, b"second");
+
+        let query = LogsQuery { instance: Some("req-2".into()), ..Default::default() };
+        let response = get_logs(State(state.clone()), Path(id.to_string()), Query(query), Err(not_upgrade().await)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_json(response).await;
+        let lines = body["data"].as_array().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["line"], "second");
+        assert_eq!(lines[0]["stream"], "stdout");
+
+        let response = get_logs(State(state), Path("logs-test/nope".into()), Query(LogsQuery::default()), Err(not_upgrade().await)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        guest_logs().forget(id);
+    }
+
+    #[tokio::test]
+    async fn follow_streams_lines_as_events() {
+        use tower::ServiceExt;
+
+        let id = "logs-follow/api";
+        let state = state_with(id);
+        guest_logs().push(id, "api-1", LogStream::Stderr, b"boom");
+
+        let router = crate::build_router(state.store);
+        let request = axum::http::Request::get("/api/v1/deployments/logs-follow%2Fapi/logs?follow=true")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut body = response.into_body().into_data_stream();
+        let frame = body.next().await.unwrap().unwrap();
+        let frame = std::str::from_utf8(&frame).unwrap();
+        assert!(frame.starts_with("event: log\n"), "{frame}");
+        let data: serde_json::Value =
+            serde_json::from_str(frame.lines().find_map(|l| l.strip_prefix("data: ")).unwrap()).unwrap();
+        assert_eq!(data["instance"], "api-1");
+        assert_eq!(data["line"], "boom");
+        assert!(data["timestamp_ms"].as_u64().unwrap() > 0);
+        guest_logs().forget(id);
+    }
+}
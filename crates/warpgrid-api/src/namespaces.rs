@@ -0,0 +1,235 @@
+//! Namespaces, so several teams can share a cluster without their
+//! deployment names colliding.
+//!
+//! - `GET /api/v1/namespaces` lists every namespace holding a deployment
+//!   or secret
+//! - `GET /api/v1/namespaces/{ns}/deployments` lists one namespace's
+//!   deployments, paged and filtered like `GET /deployments`
+//! - `POST /api/v1/namespaces/{ns}/deployments` creates a deployment in it
+//!
+//! Everything under `/namespaces/{ns}/deployments/{name}` is an alias of
+//! `/deployments/{ns}%2F{name}`: [`route_namespaced`] rewrites the path
+//! before routing, so each deployment route is reachable both ways. The
+//! same rewrite gives bare ids a default-namespace fallback:
+//! `/deployments/api` means `/deployments/default%2Fapi`.
+
+use axum::Json;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::http::uri::PathAndQuery;
+use axum::response::{IntoResponse, Response};
+use warpgrid_state::{DEFAULT_NAMESPACE, DeploymentSpec};
+
+use crate::ApiState;
+use crate::handlers::{ListQuery, deployments_page, error_response, list_response, save_deployment};
+
+/// Whether `part` is a valid namespace or name: 1-128 letters, digits,
+/// `.`, `_`, or `-`.
+pub(crate) fn is_valid_name(part: &str) -> bool {
+    !part.is_empty()
+        && part.len() <= 128
+        && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn invalid_namespace(namespace: &str) -> Response {
+    error_response(
+        &format!("invalid namespace '{namespace}': use 1-128 letters, digits, '.', '_' or '-'"),
+        StatusCode::BAD_REQUEST,
+    )
+    .into_response()
+}
+
+/// GET /api/v1/namespaces
+pub async fn list_namespaces(State(state): State<ApiState>) -> Response {
+    match state.store.list_namespaces() {
+        Ok(namespaces) => crate::handlers::ApiResponse::ok(namespaces).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// GET /api/v1/namespaces/:ns/deployments
+pub async fn list_namespace_deployments(
+    State(state): State<ApiState>,
+    Path(namespace): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> Response {
+    if !is_valid_name(&namespace) {
+        return invalid_namespace(&namespace);
+    }
+    list_response(deployments_page(&state.store, Some(&namespace), &query))
+}
+
+/// POST /api/v1/namespaces/:ns/deployments
+///
+/// The spec takes the path's namespace when it names none, and must agree
+/// with it when it does.
+pub async fn create_namespace_deployment(
+    State(state): State<ApiState>,
+    Path(namespace): Path<String>,
+    headers: HeaderMap,
+    Json(mut spec): Json<DeploymentSpec>,
+) -> Response {
+    if !is_valid_name(&namespace) {
+        return invalid_namespace(&namespace);
+    }
+    if spec.namespace.is_empty() {
+        spec.namespace = namespace;
+    } else if spec.namespace != namespace {
+        return error_response(
+            &format!("spec namespace '{}' does not match path namespace '{namespace}'", spec.namespace),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response();
+    }
+    spec.fill_defaults();
+    save_deployment(&state.store, spec, &headers)
+}
+
+/// Rewrite namespaced and bare deployment paths to the flat
+/// `/api/v1/deployments/{ns}%2F{name}` form before routing. Paths with
+/// invalid segments are left alone and fall through to a 404.
+pub async fn route_namespaced(mut req: Request) -> Request {
+    let Some(path) = namespaced_path(req.uri().path()) else {
+        return req;
+    };
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
+    };
+    let mut parts = req.uri().clone().into_parts();
+    if let Ok(path_and_query) = PathAndQuery::try_from(path_and_query) {
+        parts.path_and_query = Some(path_and_query);
+        if let Ok(uri) = axum::http::Uri::from_parts(parts) {
+            *req.uri_mut() = uri;
+        }
+    }
+    req
+}
+
+fn namespaced_path(path: &str) -> Option<String> {
+    if let Some(rest) = path.strip_prefix("/api/v1/namespaces/") {
+        let mut segments = rest.splitn(4, '/');
+        let namespace = segments.next()?;
+        if segments.next()? != "deployments" {
+            return None;
+        }
+        let name = segments.next()?;
+        if !is_valid_name(namespace) || !is_valid_name(name) {
+            return None;
+        }
+        let tail = segments.next().map(|tail| format!("/{tail}")).unwrap_or_default();
+        return Some(format!("/api/v1/deployments/{namespace}%2F{name}{tail}"));
+    }
+    let rest = path.strip_prefix("/api/v1/deployments/")?;
+    let (id, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if !is_valid_name(id) {
+        return None;
+    }
+    Some(format!("/api/v1/deployments/{DEFAULT_NAMESPACE}%2F{id}{tail}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use warpgrid_state::{InstanceConstraints, ResourceLimits, ShimsEnabled, StateStore, TriggerConfig};
+
+    #[test]
+    fn rewrites_namespaced_and_bare_paths() {
+        let cases = [
+            ("/api/v1/namespaces/team-a/deployments/api", Some("/api/v1/deployments/team-a%2Fapi")),
+            (
+                "/api/v1/namespaces/team-a/deployments/api/flags/beta",
+                Some("/api/v1/deployments/team-a%2Fapi/flags/beta"),
+            ),
+            ("/api/v1/deployments/api/scale", Some("/api/v1/deployments/default%2Fapi/scale")),
+            ("/api/v1/deployments/prod%2Fapi/scale", None),
+            ("/api/v1/namespaces/team-a/deployments", None),
+            ("/api/v1/namespaces/team a/deployments/api", None),
+            ("/api/v1/deployments:batch", None),
+            ("/api/v1/secrets/api", None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(namespaced_path(path).as_deref(), expected, "{path}");
+        }
+    }
+
+    async fn call(router: &axum::Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let req = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+        let body = body.map_or_else(Body::empty, |b| Body::from(b.to_string()));
+        let resp = router.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+        let status = resp.status();
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    fn spec(namespace: &str, name: &str) -> Value {
+        serde_json::to_value(DeploymentSpec {
+            id: String::new(),
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            source: "file://test.wasm".to_string(),
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min: 1, max: 10 },
+            resources: ResourceLimits {
+                memory_bytes: 64 * 1024 * 1024,
+                cpu_weight: 100,
+                execution_budget_ms: None,
+            },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: Default::default(),
+            created_at: 1000,
+            updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn namespaces_keep_same_named_deployments_apart() {
+        let router = crate::build_router(StateStore::open_in_memory().unwrap());
+
+        let (status, created) = call(&router, "POST", "/api/v1/namespaces/team-a/deployments", Some(spec("", "api"))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["data"]["id"], "team-a/api");
+        let (status, _) = call(&router, "POST", "/api/v1/namespaces/team-b/deployments", Some(spec("", "api"))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) =
+            call(&router, "POST", "/api/v1/namespaces/team-b/deployments", Some(spec("team-a", "web"))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, created) = call(&router, "POST", "/api/v1/deployments", Some(spec("", "api"))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["data"]["id"], "default/api");
+
+        let (_, listed) = call(&router, "GET", "/api/v1/namespaces/team-a/deployments", None).await;
+        let ids: Vec<_> = listed["data"].as_array().unwrap().iter().map(|d| d["id"].clone()).collect();
+        assert_eq!(ids, ["team-a/api"]);
+
+        let (_, namespaces) = call(&router, "GET", "/api/v1/namespaces", None).await;
+        let names: Vec<_> = namespaces["data"].as_array().unwrap().iter().map(|n| n["name"].clone()).collect();
+        assert_eq!(names, ["default", "team-a", "team-b"]);
+
+        let (status, got) = call(&router, "GET", "/api/v1/namespaces/team-b/deployments/api", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(got["data"]["id"], "team-b/api");
+        let (status, got) = call(&router, "GET", "/api/v1/deployments/api", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(got["data"]["id"], "default/api");
+
+        let (status, _) = call(&router, "DELETE", "/api/v1/namespaces/team-a/deployments/api", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, "GET", "/api/v1/deployments/team-a%2Fapi", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(&router, "GET", "/api/v1/deployments/team-b%2Fapi", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
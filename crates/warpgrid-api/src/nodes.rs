@@ -0,0 +1,345 @@
+//! Node management for `warp nodes`.
+//!
+//! - `GET /api/v1/nodes/{id}` returns the node and the instances placed on it
+//! - `POST /api/v1/nodes/{id}/cordon` stops new placements on the node
+//! - `POST /api/v1/nodes/{id}/uncordon` allows them again
+//! - `POST /api/v1/nodes/{id}/drain` cordons the node and removes the
+//!   instance records placed on it
+//! - `PATCH /api/v1/nodes/{id}/labels` sets labels, or removes those given
+//!   as `null`
+//!
+//! A drain leaves a deployment's instances in place when removing them
+//! would take it below its `min_available` disruption budget; they are
+//! reported as `blocked`. `?force=true` removes them anyway. The scheduler
+//! places replacements for evicted instances on the remaining nodes at its
+//! next reconcile.
+//!
+//! Labels set here survive the node rejoining; the labels it joins with
+//! are applied over them.
+
+use std::collections::HashMap;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use warpgrid_state::{
+    Clock, ClusterEvent, ClusterEventKind, InstanceState, InstanceStatus, NodeInfo, StateResult, StateStore,
+    SystemClock,
+};
+
+use crate::ApiState;
+use crate::events;
+use crate::handlers::{ApiResponse, error_response};
+
+/// `GET /nodes/{id}` body.
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct NodeDetail {
+    pub node: NodeInfo,
+    pub instances: Vec<InstanceState>,
+}
+
+/// `POST /nodes/{id}/drain` body.
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DrainReport {
+    pub node: NodeInfo,
+    /// Instances removed from the node.
+    pub evicted: Vec<InstanceState>,
+    /// Instances left in place to honour a disruption budget.
+    pub blocked: Vec<InstanceState>,
+}
+
+/// `PATCH /nodes/{id}/labels` body: a label to set, or `null` to remove it.
+pub type LabelsPatch = HashMap<String, Option<String>>;
+
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct DrainQuery {
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// GET /api/v1/nodes/:id
+pub async fn get_node(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    let node = match state.store.get_node(&id) {
+        Ok(Some(node)) => node,
+        Ok(None) => return error_response("node not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    match instances_on(&state.store, &id) {
+        Ok(instances) => ApiResponse::ok(NodeDetail { node, instances }).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/nodes/:id/cordon
+pub async fn cordon_node(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    set_cordoned(&state.store, &id, true)
+}
+
+/// POST /api/v1/nodes/:id/uncordon
+pub async fn uncordon_node(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    set_cordoned(&state.store, &id, false)
+}
+
+/// POST /api/v1/nodes/:id/drain
+pub async fn drain_node(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<DrainQuery>,
+) -> Response {
+    let mut node = match state.store.get_node(&id) {
+        Ok(Some(node)) => node,
+        Ok(None) => return error_response("node not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    node.cordoned = true;
+    match state.store.put_node(&node).and_then(|()| drain(&state.store, &id, query.force)) {
+        Ok((evicted, blocked)) => {
+            let message = format!("drained: {} instances evicted, {} blocked", evicted.len(), blocked.len());
+            record(&state.store, ClusterEventKind::NodeDrained, &id, message);
+            ApiResponse::ok(DrainReport { node, evicted, blocked }).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// PATCH /api/v1/nodes/:id/labels
+pub async fn patch_node_labels(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(patch): Json<LabelsPatch>,
+) -> Response {
+    if let Some(key) = patch.keys().find(|key| key.is_empty() || key.contains([',', '=']) || key.trim() != *key) {
+        return error_response(&format!("invalid label key '{key}'"), StatusCode::BAD_REQUEST).into_response();
+    }
+    let mut node = match state.store.get_node(&id) {
+        Ok(Some(node)) => node,
+        Ok(None) => return error_response("node not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    for (key, value) in patch {
+        match value {
+            Some(value) => node.labels.insert(key, value),
+            None => node.labels.remove(&key),
+        };
+    }
+    match state.store.put_node(&node) {
+        Ok(()) => ApiResponse::ok(node).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Remove the instances on `node_id`, deployment by deployment, keeping
+/// each deployment's `min_available` running unless `force`.
+fn drain(
+    store: &StateStore,
+    node_id: &str,
+    force: bool,
+) -> StateResult<(Vec<InstanceState>, Vec<InstanceState>)> {
+    let (mut evicted, mut blocked) = (Vec::new(), Vec::new());
+    for spec in store.list_deployments()? {
+        let instances = store.list_instances_for_deployment(&spec.id)?;
+        let mut running = instances
+            .iter()
+            .filter(|i| i.status == InstanceStatus::Running)
+            .count() as u32;
+        let budget = spec.min_available.unwrap_or(0);
+        for instance in instances.into_iter().filter(|i| i.node_id == node_id) {
+            let is_running = instance.status == InstanceStatus::Running;
+            if is_running && running <= budget && !force {
+                blocked.push(instance);
+                continue;
+            }
+            store.delete_instance(&instance.table_key())?;
+            if is_running {
+                running -= 1;
+            }
+            evicted.push(instance);
+        }
+    }
+    Ok((evicted, blocked))
+}
+
+fn set_cordoned(store: &StateStore, id: &str, cordoned: bool) -> Response {
+    let mut node = match store.get_node(id) {
+        Ok(Some(node)) => node,
+        Ok(None) => return error_response("node not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    node.cordoned = cordoned;
+    match store.put_node(&node) {
+        Ok(()) => {
+            let (kind, message) = if cordoned {
+                (ClusterEventKind::NodeCordoned, "cordoned")
+            } else {
+                (ClusterEventKind::NodeUncordoned, "uncordoned")
+            };
+            record(store, kind, id, message.to_string());
+            ApiResponse::ok(node).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+fn record(store: &StateStore, kind: ClusterEventKind, node_id: &str, message: String) {
+    events::record(store, ClusterEvent::new(kind, SystemClock.epoch_secs(), message).for_node(node_id));
+}
+
+fn instances_on(store: &StateStore, node_id: &str) -> StateResult<Vec<InstanceState>> {
+    let mut instances = Vec::new();
+    for spec in store.list_deployments()? {
+        instances.extend(
+            store
+                .list_instances_for_deployment(&spec.id)?
+                .into_iter()
+                .filter(|i| i.node_id == node_id),
+        );
+    }
+    Ok(instances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use warpgrid_state::{
+        DeploymentSpec, HealthStatus, InstanceConstraints, ResourceLimits, ShimsEnabled, TriggerConfig,
+    };
+
+    fn node(id: &str) -> NodeInfo {
+        NodeInfo {
+            id: id.to_string(),
+            address: "127.0.0.1".to_string(),
+            port: 9000,
+            capacity_memory_bytes: 1 << 30,
+            capacity_cpu_weight: 1000,
+            used_memory_bytes: 0,
+            used_cpu_weight: 0,
+            labels: HashMap::new(),
+            last_heartbeat: 0,
+            pressure_until: None,
+            replica_revision: None,
+            calibrated_cpu_weight: None,
+            cordoned: false,
+        }
+    }
+
+    fn deployment(name: &str, min_available: Option<u32>) -> DeploymentSpec {
+        DeploymentSpec {
+            id: format!("default/{name}"),
+            namespace: "default".to_string(),
+            name: name.to_string(),
+            source: "file://test.wasm".to_string(),
+            trigger: TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min: 1, max: 4 },
+            resources: ResourceLimits { memory_bytes: 64 << 20, cpu_weight: 100, execution_budget_ms: None },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: Default::default(),
+            created_at: 1000,
+            updated_at: 1000,
+            priority: None,
+            min_available,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        }
+    }
+
+    fn instance(deployment_id: &str, id: &str, node_id: &str) -> InstanceState {
+        InstanceState {
+            id: id.to_string(),
+            deployment_id: deployment_id.to_string(),
+            node_id: node_id.to_string(),
+            status: InstanceStatus::Running,
+            health: HealthStatus::Healthy,
+            restart_count: 0,
+            memory_bytes: 0,
+            started_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn dashboard_routes_run_node_actions() {
+        use tower::ServiceExt;
+
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_node(&node("node-1")).unwrap();
+        let router = crate::build_router(store.clone());
+        let post = |path: &str| axum::http::Request::post(path).body(axum::body::Body::empty()).unwrap();
+
+        let resp = router.clone().oneshot(post("/dashboard/nodes/node-1/cordon")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(store.get_node("node-1").unwrap().unwrap().cordoned);
+        let resp = router.clone().oneshot(post("/dashboard/nodes/node-1/uncordon")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!store.get_node("node-1").unwrap().unwrap().cordoned);
+        let resp = router.oneshot(post("/dashboard/nodes/node-1/drain?force=true")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(store.get_node("node-1").unwrap().unwrap().cordoned);
+    }
+
+    #[tokio::test]
+    async fn drain_cordons_and_keeps_disruption_budgets() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_node(&node("a")).unwrap();
+        store.put_node(&node("b")).unwrap();
+        // `api` must keep 2 running: one of its two instances on `a` stays.
+        store.put_deployment(&deployment("api", Some(2))).unwrap();
+        store.put_deployment(&deployment("web", None)).unwrap();
+        for (deployment, id, node) in
+            [("default/api", "a-0", "a"), ("default/api", "a-1", "a"), ("default/api", "b-0", "b"), ("default/web", "a-0", "a")]
+        {
+            store.put_instance(&instance(deployment, id, node)).unwrap();
+        }
+        let state = ApiState { store: store.clone() };
+
+        let response = drain_node(State(state.clone()), Path("a".into()), Query(DrainQuery::default())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let report: DrainReport = serde_json::from_value(body_json(response).await["data"].clone()).unwrap();
+        assert!(report.node.cordoned);
+        assert_eq!(report.evicted.len(), 2);
+        assert_eq!(report.blocked.len(), 1);
+        assert!(store.get_node("a").unwrap().unwrap().cordoned);
+
+        let detail = body_json(get_node(State(state.clone()), Path("a".into())).await).await;
+        assert_eq!(detail["data"]["instances"].as_array().unwrap().len(), 1);
+
+        let response = drain_node(State(state.clone()), Path("a".into()), Query(DrainQuery { force: true })).await;
+        let report: DrainReport = serde_json::from_value(body_json(response).await["data"].clone()).unwrap();
+        assert_eq!((report.evicted.len(), report.blocked.len()), (1, 0));
+
+        let response = uncordon_node(State(state.clone()), Path("a".into())).await;
+        assert_eq!(body_json(response).await["data"].get("cordoned"), None);
+        let response = cordon_node(State(state), Path("nope".into())).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn label_patches_set_and_remove() {
+        let store = StateStore::open_in_memory().unwrap();
+        let mut a = node("a");
+        a.labels.insert("zone".into(), "eu-1".into());
+        store.put_node(&a).unwrap();
+        let state = ApiState { store: store.clone() };
+        let patch = |id: &str, patch: serde_json::Value| {
+            patch_node_labels(State(state.clone()), Path(id.into()), Json(serde_json::from_value(patch).unwrap()))
+        };
+
+        let response = patch("a", serde_json::json!({"zone": null, "gpu": "a100"})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let labels = store.get_node("a").unwrap().unwrap().labels;
+        assert_eq!(labels, HashMap::from([("gpu".to_string(), "a100".to_string())]));
+
+        assert_eq!(patch("a", serde_json::json!({"a=b": "c"})).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(patch("nope", serde_json::json!({"gpu": "a100"})).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,601 @@
+//! OpenAPI 3 description of the REST API, served at
+//! `GET /api/v1/openapi.json`.
+//!
+//! [`operations`] lists every route in the table in the crate docs with
+//! the types its handler reads and returns; their schemas are derived with
+//! schemars and land under `components/schemas`. JSON responses are
+//! wrapped in the `{success, data, error}` envelope, as the handlers send
+//! them. Server-sent event streams list their event types under
+//! `x-events`.
+//!
+//! The document is built once, on first request, and served without a
+//! token even when `--api-tokens` guards the rest of `/api/v1`.
+
+use std::sync::LazyLock;
+
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use schemars::generate::{SchemaGenerator, SchemaSettings};
+use schemars::{JsonSchema, Schema};
+use serde_json::{Map, Value, json};
+use warpgrid_health::summary::DeploymentHealth;
+use warpgrid_metrics::guest_logs::GuestLogLine;
+use warpgrid_state::{
+    ClusterEvent, ConfigBundle, ConfigMap, DeploymentConfig, DeploymentFlags, DeploymentRevision, DeploymentSpec,
+    FeatureFlag, FlagSet, InstanceState, IntegrityReport, IntegrityStatus, MetricsSnapshot, NamespaceSummary, NodeInfo,
+    UsageRollup,
+};
+
+use crate::apply::{ApplyQuery, ApplyRequest, ApplyResponse};
+use crate::artifacts::{ArtifactInfo, UploadQuery};
+use crate::auth::{CreateTokenRequest, CreatedToken, TokenInfo};
+use crate::capabilities::{API_VERSION, Capabilities};
+use crate::configmaps::{ConfigMapsQuery, CreateConfigMapRequest, PutConfigMapRequest};
+use crate::events::EventsQuery;
+use crate::exec::{ExecRequest, ExecResult};
+use crate::handlers::{
+    BatchRequest, BatchResponse, ListQuery, MetricsQuery, MetricsRangeQuery, RollbackQuery, ScaleRequest, ScaleResult,
+    UsageEventsPage, UsageEventsQuery, UsageRollupsQuery,
+};
+use crate::health::HealthReport;
+use crate::logs::LogsQuery;
+use crate::nodes::{DrainQuery, DrainReport, LabelsPatch, NodeDetail};
+use crate::portforward::{PortForwardQuery, TUNNEL_PROTOCOL};
+use crate::rollout_handlers::{RolloutStatus, StartRolloutRequest};
+use crate::secrets::{CreateSecretRequest, GetSecretQuery, PutSecretRequest, SecretInfo, SecretsQuery};
+use crate::watch::{ChangeEvent, ClusterOverview, WatchQuery};
+use crate::webhooks::{CreateWebhookRequest, CreatedWebhook, WebhookInfo};
+
+/// Path the document is served at.
+pub const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+/// Reference to a type's schema, registering it with the generator.
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// A query-parameter struct's schema, with nothing behind a reference.
+type QueryFn = fn() -> Schema;
+
+fn schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Schema {
+    generator.subschema_for::<T>()
+}
+
+fn query<T: JsonSchema>() -> Schema {
+    SchemaSettings::openapi3()
+        .with(|s| s.inline_subschemas = true)
+        .into_generator()
+        .into_root_schema_for::<T>()
+}
+
+/// What an operation answers with on success.
+pub enum Reply {
+    /// `data` of the JSON envelope, with the status code.
+    Json(u16, SchemaFn),
+    /// An array page of the JSON envelope, with its `next_cursor`.
+    Page(SchemaFn),
+    /// Server-sent events, by event name.
+    Events(&'static [(&'static str, SchemaFn)]),
+    /// `101 Switching Protocols` to a tunnel.
+    Upgrade,
+    /// Plain text.
+    Text,
+    /// JSON without the envelope, with each status code it comes with.
+    Bare(SchemaFn, &'static [u16]),
+}
+
+/// One method on one path.
+pub struct Operation {
+    pub method: &'static str,
+    /// Path in OpenAPI form, e.g. `/api/v1/deployments/{id}`.
+    pub path: &'static str,
+    pub tag: &'static str,
+    pub summary: &'static str,
+    pub query: Option<QueryFn>,
+    pub body: Option<SchemaFn>,
+    /// Takes a wasm component, raw or as `multipart/form-data`, instead of
+    /// JSON.
+    pub upload: bool,
+    pub reply: Reply,
+}
+
+impl Operation {
+    fn new(method: &'static str, path: &'static str, tag: &'static str, summary: &'static str) -> Self {
+        let reply = Reply::Json(200, schema::<String>);
+        Self { method, path, tag, summary, query: None, body: None, upload: false, reply }
+    }
+
+    fn query(mut self, query: QueryFn) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    fn body(mut self, body: SchemaFn) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    fn upload(mut self) -> Self {
+        self.upload = true;
+        self
+    }
+
+    fn ok(self, data: SchemaFn) -> Self {
+        self.reply(Reply::Json(200, data))
+    }
+
+    fn page(self, items: SchemaFn) -> Self {
+        self.query(query::<ListQuery>).reply(Reply::Page(items))
+    }
+
+    fn created(self, data: SchemaFn) -> Self {
+        self.reply(Reply::Json(201, data))
+    }
+
+    fn reply(mut self, reply: Reply) -> Self {
+        self.reply = reply;
+        self
+    }
+}
+
+/// Every operation the API serves, in route-table order.
+pub fn operations() -> Vec<Operation> {
+    use Operation as Op;
+    vec![
+        Op::new("get", "/api/v1/deployments", "deployments", "List deployments").page(schema::<Vec<DeploymentSpec>>),
+        Op::new("post", "/api/v1/deployments", "deployments", "Create a deployment")
+            .body(schema::<DeploymentSpec>)
+            .created(schema::<DeploymentSpec>),
+        Op::new("post", "/api/v1/deployments:batch", "deployments", "Apply an operation by label selector")
+            .body(schema::<BatchRequest>)
+            .ok(schema::<BatchResponse>),
+        Op::new("get", "/api/v1/deployments/{id}", "deployments", "Get deployment details").ok(schema::<DeploymentSpec>),
+        Op::new("delete", "/api/v1/deployments/{id}", "deployments", "Delete a deployment"),
+        Op::new("post", "/api/v1/deployments/{id}/scale", "deployments", "Scale a deployment")
+            .body(schema::<ScaleRequest>)
+            .ok(schema::<ScaleResult>),
+        Op::new("get", "/api/v1/deployments/{id}/revisions", "deployments", "List a deployment's spec revisions")
+            .ok(schema::<Vec<DeploymentRevision>>),
+        Op::new("post", "/api/v1/deployments/{id}/rollback", "deployments", "Roll back to an earlier revision")
+            .query(query::<RollbackQuery>)
+            .ok(schema::<DeploymentSpec>),
+        Op::new("get", "/api/v1/deployments/{id}/instances", "deployments", "List instances")
+            .page(schema::<Vec<InstanceState>>),
+        Op::new("get", "/api/v1/deployments/{id}/metrics", "deployments", "Get metrics snapshots")
+            .query(query::<MetricsQuery>)
+            .ok(schema::<Vec<MetricsSnapshot>>),
+        Op::new("get", "/api/v1/deployments/{id}/metrics/range", "deployments", "Get metrics over a time range")
+            .query(query::<MetricsRangeQuery>)
+            .ok(schema::<Vec<MetricsSnapshot>>),
+        Op::new("get", "/api/v1/deployments/{id}/usage", "usage", "Get usage rollups")
+            .query(query::<UsageRollupsQuery>)
+            .ok(schema::<Vec<UsageRollup>>),
+        Op::new("get", "/api/v1/deployments/{id}/logs", "deployments", "Captured guest stdout/stderr lines")
+            .query(query::<LogsQuery>)
+            .ok(schema::<Vec<GuestLogLine>>),
+        Op::new("get", "/api/v1/deployments/{id}/logs/stream", "deployments", "Stream guest log lines")
+            .query(query::<LogsQuery>)
+            .reply(Reply::Events(&[("log", schema::<GuestLogLine>)])),
+        Op::new("post", "/api/v1/deployments/{id}/exec", "deployments", "Call a component export")
+            .body(schema::<ExecRequest>)
+            .ok(schema::<ExecResult>),
+        Op::new("get", "/api/v1/deployments/{id}/port-forward", "deployments", "Tunnel to the HTTP trigger")
+            .query(query::<PortForwardQuery>)
+            .reply(Reply::Upgrade),
+        Op::new("get", "/api/v1/deployments/{id}/health", "rollouts", "Get deployment health")
+            .ok(schema::<DeploymentHealth>),
+        Op::new("get", "/api/v1/deployments/{id}/flags", "flags", "Get feature flags").ok(schema::<DeploymentFlags>),
+        Op::new("put", "/api/v1/deployments/{id}/flags", "flags", "Replace all feature flags")
+            .body(schema::<FlagSet>)
+            .ok(schema::<DeploymentFlags>),
+        Op::new("put", "/api/v1/deployments/{id}/flags/{name}", "flags", "Set one feature flag")
+            .body(schema::<FeatureFlag>)
+            .ok(schema::<DeploymentFlags>),
+        Op::new("delete", "/api/v1/deployments/{id}/flags/{name}", "flags", "Remove one feature flag")
+            .ok(schema::<DeploymentFlags>),
+        Op::new("get", "/api/v1/deployments/{id}/config", "config", "Get active and staged config bundle")
+            .ok(schema::<DeploymentConfig>),
+        Op::new("put", "/api/v1/deployments/{id}/config", "config", "Stage a config bundle")
+            .body(schema::<ConfigBundle>)
+            .ok(schema::<DeploymentConfig>),
+        Op::new("get", "/api/v1/namespaces", "namespaces", "List namespaces").ok(schema::<Vec<NamespaceSummary>>),
+        Op::new("get", "/api/v1/namespaces/{ns}/deployments", "namespaces", "List a namespace's deployments")
+            .page(schema::<Vec<DeploymentSpec>>),
+        Op::new("post", "/api/v1/namespaces/{ns}/deployments", "namespaces", "Create a deployment in a namespace")
+            .body(schema::<DeploymentSpec>)
+            .created(schema::<DeploymentSpec>),
+        Op::new("get", "/api/v1/namespaces/{ns}/deployments/{name}", "namespaces", "Get deployment details")
+            .ok(schema::<DeploymentSpec>),
+        Op::new("delete", "/api/v1/namespaces/{ns}/deployments/{name}", "namespaces", "Delete a deployment"),
+        Op::new("get", "/api/v1/secrets", "secrets", "List a namespace's secrets")
+            .query(query::<SecretsQuery>)
+            .ok(schema::<Vec<SecretInfo>>),
+        Op::new("post", "/api/v1/secrets", "secrets", "Create a secret")
+            .body(schema::<CreateSecretRequest>)
+            .created(schema::<SecretInfo>),
+        Op::new("get", "/api/v1/secrets/{id}", "secrets", "Get a secret's metadata")
+            .query(query::<GetSecretQuery>)
+            .ok(schema::<SecretInfo>),
+        Op::new("put", "/api/v1/secrets/{id}", "secrets", "Create or replace a secret")
+            .body(schema::<PutSecretRequest>)
+            .ok(schema::<SecretInfo>),
+        Op::new("delete", "/api/v1/secrets/{id}", "secrets", "Delete a secret"),
+        Op::new("get", "/api/v1/configmaps", "configmaps", "List a namespace's config maps")
+            .query(query::<ConfigMapsQuery>)
+            .ok(schema::<Vec<ConfigMap>>),
+        Op::new("post", "/api/v1/configmaps", "configmaps", "Create a config map")
+            .body(schema::<CreateConfigMapRequest>)
+            .created(schema::<ConfigMap>),
+        Op::new("get", "/api/v1/configmaps/{id}", "configmaps", "Get a config map").ok(schema::<ConfigMap>),
+        Op::new("put", "/api/v1/configmaps/{id}", "configmaps", "Create or replace a config map")
+            .body(schema::<PutConfigMapRequest>)
+            .ok(schema::<ConfigMap>),
+        Op::new("delete", "/api/v1/configmaps/{id}", "configmaps", "Delete a config map"),
+        Op::new("post", "/api/v1/apply", "apply", "Diff a manifest against the cluster and converge")
+            .query(query::<ApplyQuery>)
+            .body(schema::<ApplyRequest>)
+            .ok(schema::<ApplyResponse>),
+        Op::new("post", "/api/v1/artifacts", "artifacts", "Upload a wasm component")
+            .query(query::<UploadQuery>)
+            .upload()
+            .created(schema::<ArtifactInfo>),
+        Op::new("get", "/api/v1/webhooks", "webhooks", "List webhooks").ok(schema::<Vec<WebhookInfo>>),
+        Op::new("post", "/api/v1/webhooks", "webhooks", "Register a webhook for cluster events")
+            .body(schema::<CreateWebhookRequest>)
+            .created(schema::<CreatedWebhook>),
+        Op::new("get", "/api/v1/webhooks/{id}", "webhooks", "Get a webhook").ok(schema::<WebhookInfo>),
+        Op::new("delete", "/api/v1/webhooks/{id}", "webhooks", "Delete a webhook"),
+        Op::new("get", "/api/v1/tokens", "tokens", "List API tokens").ok(schema::<Vec<TokenInfo>>),
+        Op::new("post", "/api/v1/tokens", "tokens", "Create an API token")
+            .body(schema::<CreateTokenRequest>)
+            .created(schema::<CreatedToken>),
+        Op::new("delete", "/api/v1/tokens/{id}", "tokens", "Revoke an API token"),
+        Op::new("get", "/api/v1/usage/events", "usage", "Export the usage event stream")
+            .query(query::<UsageEventsQuery>)
+            .ok(schema::<UsageEventsPage>),
+        Op::new("get", "/api/v1/events", "events", "List cluster events")
+            .query(query::<EventsQuery>)
+            .ok(schema::<Vec<ClusterEvent>>),
+        Op::new("post", "/api/v1/deployments/{id}/rollout", "rollouts", "Start a rollout")
+            .body(schema::<StartRolloutRequest>)
+            .created(schema::<RolloutStatus>),
+        Op::new("get", "/api/v1/rollouts", "rollouts", "List active rollouts").ok(schema::<Vec<RolloutStatus>>),
+        Op::new("get", "/api/v1/rollouts/{id}", "rollouts", "Get rollout status").ok(schema::<RolloutStatus>),
+        Op::new("post", "/api/v1/rollouts/{id}/pause", "rollouts", "Pause a rollout").ok(schema::<RolloutStatus>),
+        Op::new("post", "/api/v1/rollouts/{id}/resume", "rollouts", "Resume a rollout").ok(schema::<RolloutStatus>),
+        Op::new("post", "/api/v1/rollouts/{id}/rollback", "rollouts", "Roll back an unfinished rollout")
+            .ok(schema::<RolloutStatus>),
+        Op::new("get", "/api/v1/watch", "cluster", "Live cluster overview")
+            .query(query::<WatchQuery>)
+            .reply(Reply::Events(&[("overview", schema::<ClusterOverview>), ("change", schema::<ChangeEvent>)])),
+        Op::new("get", "/api/v1/nodes", "nodes", "List nodes").page(schema::<Vec<NodeInfo>>),
+        Op::new("get", "/api/v1/nodes/{id}", "nodes", "Node details and its instances").ok(schema::<NodeDetail>),
+        Op::new("post", "/api/v1/nodes/{id}/cordon", "nodes", "Stop placing new instances on a node")
+            .ok(schema::<NodeInfo>),
+        Op::new("post", "/api/v1/nodes/{id}/uncordon", "nodes", "Allow placements on a node again")
+            .ok(schema::<NodeInfo>),
+        Op::new("post", "/api/v1/nodes/{id}/drain", "nodes", "Cordon a node and evict its instances")
+            .query(query::<DrainQuery>)
+            .ok(schema::<DrainReport>),
+        Op::new("patch", "/api/v1/nodes/{id}/labels", "nodes", "Set or remove node labels")
+            .body(schema::<LabelsPatch>)
+            .ok(schema::<NodeInfo>),
+        Op::new("get", "/api/v1/capabilities", "cluster", "Supported worlds, shims, features, and limits")
+            .ok(schema::<Capabilities>),
+        Op::new("get", "/api/v1/admin/state-integrity", "admin", "State corruption report")
+            .ok(schema::<IntegrityStatus>),
+        Op::new("post", "/api/v1/admin/state-integrity/verify", "admin", "Run a state integrity scan")
+            .ok(schema::<IntegrityReport>),
+        Op::new("get", OPENAPI_PATH, "cluster", "This document").reply(Reply::Json(200, schema::<Value>)),
+        Op::new("get", "/metrics", "cluster", "Prometheus exposition").reply(Reply::Text),
+        Op::new("get", "/healthz", "cluster", "Liveness, with each subsystem's status")
+            .reply(Reply::Bare(schema::<HealthReport>, &[200])),
+        Op::new("get", "/readyz", "cluster", "Readiness; 503 until every subsystem is ready")
+            .reply(Reply::Bare(schema::<HealthReport>, &[200, 503])),
+    ]
+}
+
+/// Build the document.
+pub fn document() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let mut paths: Map<String, Value> = Map::new();
+    for op in operations() {
+        let item = paths.entry(op.path).or_insert_with(|| json!({})).as_object_mut().expect("path item");
+        item.insert(op.method.to_string(), operation(&mut generator, &op));
+    }
+    let mut schemas = generator.take_definitions(true);
+    schemas.insert(
+        "Error".to_string(),
+        json!({
+            "type": "object",
+            "required": ["success", "error"],
+            "properties": {
+                "success": { "type": "boolean", "enum": [false] },
+                "error": { "type": "string" }
+            }
+        }),
+    );
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "WarpGrid API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": format!("WarpGrid REST API {API_VERSION}."),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "bearerAuth": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "An API token; required when warpd runs with --api-tokens."
+                }
+            }
+        },
+        // Tokens are opt-in, so no security is a valid choice as well.
+        "security": [{}, { "bearerAuth": [] }],
+    })
+}
+
+fn operation(generator: &mut SchemaGenerator, op: &Operation) -> Value {
+    let mut parameters: Vec<Value> = path_params(op.path)
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+    if let Some(query) = op.query {
+        parameters.extend(query_params(&query()));
+    }
+    let mut responses = Map::new();
+    match &op.reply {
+        Reply::Json(status, data) => {
+            let envelope = envelope(data(generator), false);
+            responses.insert(status.to_string(), json!({ "description": "OK", "content": json_content(envelope) }));
+        }
+        Reply::Page(items) => {
+            let envelope = envelope(items(generator), true);
+            responses.insert("200".to_string(), json!({ "description": "OK", "content": json_content(envelope) }));
+        }
+        Reply::Events(events) => {
+            let events: Map<String, Value> =
+                events.iter().map(|(name, data)| (name.to_string(), data(generator).to_value())).collect();
+            responses.insert(
+                "200".to_string(),
+                json!({
+                    "description": "Server-sent events; each event's data is the JSON of its `x-events` schema",
+                    "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                    "x-events": events,
+                }),
+            );
+        }
+        Reply::Upgrade => {
+            responses.insert(
+                "101".to_string(),
+                json!({ "description": format!("Switched to the `{TUNNEL_PROTOCOL}` protocol") }),
+            );
+        }
+        Reply::Text => {
+            responses.insert(
+                "200".to_string(),
+                json!({ "description": "OK", "content": { "text/plain": { "schema": { "type": "string" } } } }),
+            );
+        }
+        Reply::Bare(data, statuses) => {
+            let data = data(generator).to_value();
+            for status in *statuses {
+                let description = if *status < 300 { "OK" } else { "Unavailable" };
+                responses.insert(
+                    status.to_string(),
+                    json!({ "description": description, "content": json_content(data.clone()) }),
+                );
+            }
+        }
+    }
+    responses.insert(
+        "default".to_string(),
+        json!({ "description": "Error", "content": json_content(json!({ "$ref": "#/components/schemas/Error" })) }),
+    );
+
+    let mut value = json!({
+        "tags": [op.tag],
+        "summary": op.summary,
+        "operationId": operation_id(op),
+        "responses": responses,
+    });
+    if !parameters.is_empty() {
+        value["parameters"] = Value::Array(parameters);
+    }
+    if let Some(body) = op.body {
+        value["requestBody"] = json!({ "required": true, "content": json_content(body(generator).to_value()) });
+    }
+    if op.upload {
+        let binary = json!({ "type": "string", "format": "binary" });
+        value["requestBody"] = json!({
+            "required": true,
+            "content": {
+                "application/wasm": { "schema": binary },
+                "multipart/form-data": { "schema": {
+                    "type": "object",
+                    "required": ["artifact"],
+                    "properties": {
+                        "artifact": binary,
+                        "signature": { "type": "string", "format": "binary", "description": "Sigstore bundle" },
+                        "sha256": { "type": "string" }
+                    }
+                } }
+            }
+        });
+    }
+    value
+}
+
+/// `{success, data}`, and `next_cursor` for a page.
+fn envelope(data: Schema, paged: bool) -> Value {
+    let mut envelope = json!({
+        "type": "object",
+        "required": ["success", "data"],
+        "properties": { "success": { "type": "boolean", "enum": [true] }, "data": data }
+    });
+    if paged {
+        envelope["properties"]["next_cursor"] = json!({
+            "type": "string",
+            "description": "Pass as `cursor` for the next page; absent on the last page."
+        });
+    }
+    envelope
+}
+
+fn json_content(schema: impl Into<Value>) -> Value {
+    json!({ "application/json": { "schema": schema.into() } })
+}
+
+/// `{id}` and `{name}` in `/deployments/{id}/flags/{name}`.
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+}
+
+/// One parameter per property of a query struct's schema.
+fn query_params(schema: &Schema) -> Vec<Value> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let mut property = property.clone();
+            let description = property.as_object_mut().and_then(|p| p.remove("description"));
+            let mut param = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&name.as_str()),
+                "schema": property,
+            });
+            if let Some(description) = description {
+                param["description"] = description;
+            }
+            param
+        })
+        .collect()
+}
+
+/// `get /api/v1/deployments/{id}/flags/{name}` → `getDeploymentsIdFlagsName`.
+fn operation_id(op: &Operation) -> String {
+    let mut id = op.method.to_string();
+    let path = op.path.strip_prefix("/api/v1").unwrap_or(op.path);
+    for word in path.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            id.push(first.to_ascii_uppercase());
+            id.extend(chars);
+        }
+    }
+    id
+}
+
+static DOCUMENT: LazyLock<Value> = LazyLock::new(document);
+
+/// GET /api/v1/openapi.json
+pub async fn get_openapi() -> Response {
+    Json(&*DOCUMENT).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Operations by `(method, path)`.
+    fn index() -> HashMap<(String, String), Operation> {
+        operations().into_iter().map(|op| ((op.method.to_string(), op.path.to_string()), op)).collect()
+    }
+
+    /// `| GET | `/api/v1/deployments/:id` | ...` rows of the crate docs.
+    fn route_table() -> Vec<(String, String)> {
+        include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| {
+                let mut cells = line.strip_prefix("//! |")?.split('|').map(str::trim);
+                let method = cells.next()?;
+                let path = cells.next()?.strip_prefix('`')?.strip_suffix('`')?;
+                let path: Vec<String> = path
+                    .split('/')
+                    .map(|s| s.strip_prefix(':').map_or_else(|| s.to_string(), |name| format!("{{{name}}}")))
+                    .collect();
+                Some((method.to_ascii_lowercase(), path.join("/")))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_documented_route_has_an_operation() {
+        let table = route_table();
+        assert!(table.len() > 40);
+        let index = index();
+        for route in &table {
+            assert!(index.contains_key(route), "{route:?} is missing from the OpenAPI document");
+        }
+        assert_eq!(index.len(), table.len(), "an operation is missing from the route table");
+    }
+
+    #[test]
+    fn document_resolves_every_reference() {
+        let doc = document();
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        assert!(schemas.contains_key("DeploymentSpec"));
+        assert!(schemas.contains_key("RolloutStatus"));
+        assert!(schemas.contains_key("NodeInfo"));
+
+        fn refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+            match value {
+                Value::Object(map) => {
+                    if let Some(Value::String(r)) = map.get("$ref") {
+                        out.push(r);
+                    }
+                    map.values().for_each(|v| refs(v, out));
+                }
+                Value::Array(items) => items.iter().for_each(|v| refs(v, out)),
+                _ => {}
+            }
+        }
+        let mut found = Vec::new();
+        refs(&doc, &mut found);
+        assert!(!found.is_empty());
+        for r in found {
+            let name = r.strip_prefix("#/components/schemas/").unwrap_or_else(|| panic!("bad ref {r}"));
+            assert!(schemas.contains_key(name), "dangling ref {r}");
+        }
+    }
+
+    #[test]
+    fn operations_carry_parameters_and_envelopes() {
+        let doc = document();
+        let get = &doc["paths"]["/api/v1/deployments/{id}/flags/{name}"]["put"];
+        let params: Vec<&str> = get["parameters"].as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+        assert_eq!(params, ["id", "name"]);
+        assert_eq!(get["operationId"], "putDeploymentsIdFlagsName");
+        assert_eq!(
+            get["responses"]["200"]["content"]["application/json"]["schema"]["properties"]["data"]["$ref"],
+            "#/components/schemas/DeploymentFlags"
+        );
+
+        let logs = &doc["paths"]["/api/v1/deployments/{id}/logs"]["get"];
+        let stream = logs["parameters"].as_array().unwrap().iter().find(|p| p["name"] == "stream").unwrap();
+        assert_eq!(stream["in"], "query");
+        assert_eq!(stream["required"], false);
+
+        let drain = &doc["paths"]["/api/v1/nodes/{id}/drain"]["post"];
+        assert_eq!(
+            drain["responses"]["200"]["content"]["application/json"]["schema"]["properties"]["data"]["$ref"],
+            "#/components/schemas/DrainReport"
+        );
+        assert_eq!(doc["paths"]["/api/v1/deployments"]["post"]["responses"]["201"]["description"], "OK");
+
+        let nodes = &doc["paths"]["/api/v1/nodes"]["get"];
+        let params: Vec<&str> = nodes["parameters"].as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+        assert_eq!(params, ["cursor", "label_selector", "limit", "status"]);
+        let envelope = &nodes["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(envelope["properties"]["next_cursor"]["type"], "string");
+    }
+}
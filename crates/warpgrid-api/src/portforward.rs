@@ -0,0 +1,227 @@
+//! Tunnels to a deployment's HTTP trigger, for `warp port-forward`.
+//!
+//! `GET /api/v1/deployments/{id}/port-forward?port=` with
+//! `Connection: Upgrade` and `Upgrade: warp-tunnel` answers
+//! `101 Switching Protocols` and then serves HTTP/1.1 on the raw
+//! connection, sending every request to the deployment as the ingress
+//! would. Host and path routing is skipped, so a deployment that only
+//! answers for `api.example.com` can be reached from `localhost`.
+//!
+//! `port` must be an ingress port the trigger is served on: its pinned
+//! port, or any ingress listener when it has none. Checks happen before
+//! the upgrade, so a bad request gets an ordinary JSON error.
+//!
+//! As with exec, warpd attaches a [`TunnelServer`] to the router with
+//! [`axum::Extension`]. Without one the endpoint answers 503.
+
+use std::sync::Arc;
+
+use axum::Extension;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+use warpgrid_state::TriggerConfig;
+
+use crate::ApiState;
+use crate::handlers::error_response;
+
+/// `Upgrade` protocol a port-forward request asks for.
+pub const TUNNEL_PROTOCOL: &str = "warp-tunnel";
+
+/// The client connection once upgraded.
+pub type TunnelStream = TokioIo<Upgraded>;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PortForwardQuery {
+    pub port: u16,
+}
+
+/// Serves tunneled connections with a deployment's request handler.
+pub trait TunnelServer: Send + Sync {
+    /// Ingress listener ports on this node.
+    fn ports(&self) -> Vec<u16>;
+
+    /// Serve HTTP from `stream` until the client hangs up.
+    fn serve(&self, deployment_id: String, stream: TunnelStream) -> BoxFuture<'static, ()>;
+}
+
+/// GET /api/v1/deployments/:id/port-forward
+pub async fn port_forward(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<PortForwardQuery>,
+    tunnels: Option<Extension<Arc<dyn TunnelServer>>>,
+    mut req: Request,
+) -> Response {
+    let spec = match state.store.get_deployment(&id) {
+        Ok(Some(spec)) => spec,
+        Ok(None) => return error_response("deployment not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    let Some(Extension(tunnels)) = tunnels else {
+        return error_response("this server cannot forward ports", StatusCode::SERVICE_UNAVAILABLE).into_response();
+    };
+    let TriggerConfig::Http { port: pinned, .. } = spec.trigger else {
+        return error_response("deployment has no HTTP trigger", StatusCode::BAD_REQUEST).into_response();
+    };
+    let ports = match pinned {
+        Some(port) => vec![port],
+        None => tunnels.ports(),
+    };
+    if !ports.contains(&query.port) {
+        let msg = format!("deployment is not served on port {}; use one of {ports:?}", query.port);
+        return error_response(&msg, StatusCode::BAD_REQUEST).into_response();
+    }
+    if !wants_tunnel(req.headers()) {
+        let msg = format!("expected `Connection: Upgrade` and `Upgrade: {TUNNEL_PROTOCOL}`");
+        return error_response(&msg, StatusCode::UPGRADE_REQUIRED).into_response();
+    }
+
+    let on_upgrade = hyper::upgrade::on(&mut req);
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => tunnels.serve(id, TokioIo::new(upgraded)).await,
+            Err(e) => tracing::warn!(deployment = %id, error = %e, "port-forward upgrade failed"),
+        }
+    });
+    (
+        StatusCode::SWITCHING_PROTOCOLS,
+        [
+            (header::CONNECTION, HeaderValue::from_static("upgrade")),
+            (header::UPGRADE, HeaderValue::from_static(TUNNEL_PROTOCOL)),
+        ],
+    )
+        .into_response()
+}
+
+fn wants_tunnel(headers: &HeaderMap) -> bool {
+    let has = |name, value: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|token| token.trim().eq_ignore_ascii_case(value))
+    };
+    has(header::CONNECTION, "upgrade") && has(header::UPGRADE, TUNNEL_PROTOCOL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use warpgrid_state::{DeploymentSpec, InstanceConstraints, ResourceLimits, ShimsEnabled, StateStore};
+
+    /// Answers every tunneled request with the deployment id.
+    struct Echo;
+
+    impl TunnelServer for Echo {
+        fn ports(&self) -> Vec<u16> {
+            vec![8080]
+        }
+
+        fn serve(&self, deployment_id: String, mut stream: TunnelStream) -> BoxFuture<'static, ()> {
+            Box::pin(async move {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let response =
+                    format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{deployment_id}", deployment_id.len());
+                let _ = stream.write_all(response.as_bytes()).await;
+            })
+        }
+    }
+
+    fn store_with(id: &str, trigger: TriggerConfig) -> StateStore {
+        let store = StateStore::open_in_memory().unwrap();
+        let (namespace, name) = id.split_once('/').unwrap();
+        store
+            .put_deployment(&DeploymentSpec {
+                id: id.to_string(),
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+                source: "file://api.wasm".to_string(),
+                trigger,
+                instances: InstanceConstraints { min: 1, max: 1 },
+                resources: ResourceLimits { memory_bytes: 64 << 20, cpu_weight: 100, execution_budget_ms: None },
+                scaling: None,
+                health: None,
+                shims: ShimsEnabled::default(),
+                env: Default::default(),
+                created_at: 1000,
+                updated_at: 1000,
+                priority: None,
+                min_available: None,
+                labels: Default::default(),
+                paused: false,
+                secrets: Vec::new(),
+                config_maps: Vec::new(),
+            })
+            .unwrap();
+        store
+    }
+
+    async fn serve(store: StateStore) -> std::net::SocketAddr {
+        let router = axum::Router::new()
+            .route("/deployments/{id}/port-forward", axum::routing::get(port_forward))
+            .with_state(ApiState { store })
+            .layer(Extension(Arc::new(Echo) as Arc<dyn TunnelServer>));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        addr
+    }
+
+    /// Send `head`. If the server upgrades, send `then` through the tunnel
+    /// and return what comes back; otherwise return the response.
+    async fn exchange(addr: std::net::SocketAddr, head: &str, then: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(head.as_bytes()).await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+        let mut out = String::from_utf8_lossy(&buf[..n]).into_owned();
+        if out.starts_with("HTTP/1.1 101") {
+            stream.write_all(then.as_bytes()).await.unwrap();
+            let mut tunneled = Vec::new();
+            stream.read_to_end(&mut tunneled).await.unwrap();
+            out = String::from_utf8_lossy(&tunneled).into_owned();
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn upgrades_and_hands_the_connection_over() {
+        let trigger = TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None };
+        let addr = serve(store_with("default/api", trigger)).await;
+        let out = exchange(
+            addr,
+            "GET /deployments/default%2Fapi/port-forward?port=8080 HTTP/1.1\r\nHost: x\r\n\
+             Connection: Upgrade\r\nUpgrade: warp-tunnel\r\n\r\n",
+            "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        )
+        .await;
+        assert!(out.starts_with("HTTP/1.1 200 OK"), "{out}");
+        assert!(out.ends_with("default/api"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn checks_the_port_and_upgrade_headers() {
+        let trigger = TriggerConfig::Http { port: Some(9000), hosts: vec![], path_prefix: None };
+        let addr = serve(store_with("default/admin", trigger)).await;
+        let upgrade = "Connection: Upgrade\r\nUpgrade: warp-tunnel\r\n";
+        let request = |port: u16, headers: &str| {
+            format!("GET /deployments/default%2Fadmin/port-forward?port={port} HTTP/1.1\r\nHost: x\r\n{headers}\r\n")
+        };
+
+        let out = exchange(addr, &request(8080, upgrade), "").await;
+        assert!(out.starts_with("HTTP/1.1 400"), "{out}");
+        assert!(out.contains("use one of [9000]"), "{out}");
+        let out = exchange(addr, &request(9000, ""), "").await;
+        assert!(out.starts_with("HTTP/1.1 426"), "{out}");
+        let out = exchange(addr, &request(9000, upgrade), "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(out.ends_with("default/admin"), "{out}");
+    }
+}
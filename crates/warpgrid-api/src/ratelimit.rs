@@ -0,0 +1,265 @@
+//! Rate limiting for the management API, so a runaway script or a
+//! dashboard polling too often cannot starve the control plane.
+//!
+//! Each client draws from token buckets: one per API token, for requests
+//! carrying the bearer token of a stored one, and one per client IP. A
+//! token that is not stored gets no bucket of its own, so a client cannot
+//! escape its limit by making tokens up; only its IP's bucket limits it,
+//! and auth refuses it after. A bucket holds up to
+//! `burst` requests and refills at `per_second`; a request needs one from
+//! every bucket it draws from, and is otherwise answered `429 Too Many
+//! Requests` with `Retry-After` set to the whole seconds until it would
+//! pass. The client IP comes from the connection (`ConnectInfo`), so
+//! per-IP limits need a router served with
+//! `into_make_service_with_connect_info`; without it only tokens are
+//! limited. Past 4096 clients the least recently seen one's
+//! bucket is dropped; it has refilled the most, so forgetting it forgives
+//! the least.
+//!
+//! [`rate_limit`] is opt-in: warpd layers it on the management router when
+//! started with `--api-token-rate` or `--api-ip-rate`. `/metrics` is left
+//! to the scraper's interval, and `/healthz` and `/readyz` to the probe's.
+
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+
+use warpgrid_state::StateStore;
+
+use crate::auth::{bearer, digest};
+use crate::handlers::error_response;
+
+/// Buckets kept before the least recently used ones are dropped.
+const MAX_BUCKETS: usize = 4096;
+
+/// One token bucket's size and refill rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Requests allowed at once.
+    pub burst: u32,
+    /// Requests allowed per second, sustained.
+    pub per_second: f64,
+}
+
+/// Limits per API token and per client IP; `None` leaves that one off.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimits {
+    pub per_token: Option<RateLimit>,
+    pub per_ip: Option<RateLimit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Client {
+    /// Keyed by the token's digest, like the token store.
+    Token(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    available: f64,
+    updated: Instant,
+}
+
+/// Buckets by client, and the same clients by when they were last seen.
+#[derive(Debug, Default)]
+struct Buckets {
+    by_client: HashMap<Client, Bucket>,
+    by_use: BTreeSet<(Instant, Client)>,
+}
+
+impl Buckets {
+    /// `client`'s bucket refilled to `now`, created full if it has none.
+    fn refill(&mut self, client: &Client, limit: RateLimit, now: Instant) -> &mut Bucket {
+        match self.by_client.get(client) {
+            Some(bucket) => {
+                self.by_use.remove(&(bucket.updated, client.clone()));
+            }
+            None if self.by_client.len() >= MAX_BUCKETS => {
+                if let Some((_, oldest)) = self.by_use.pop_first() {
+                    self.by_client.remove(&oldest);
+                }
+            }
+            None => {}
+        }
+        self.by_use.insert((now, client.clone()));
+        let bucket = self
+            .by_client
+            .entry(client.clone())
+            .or_insert(Bucket { available: f64::from(limit.burst), updated: now });
+        bucket.available = refilled(bucket, limit, now);
+        bucket.updated = now;
+        bucket
+    }
+}
+
+/// Token buckets for the clients seen most recently.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits, buckets: Mutex::default() }
+    }
+
+    /// Take one request from each of `clients`' buckets, or none when any
+    /// is empty; then, how long until all of them would have one.
+    fn acquire(&self, clients: &[(Client, RateLimit)], now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let mut wait = Duration::ZERO;
+        for (client, limit) in clients {
+            let bucket = buckets.refill(client, *limit, now);
+            if bucket.available < 1.0 {
+                wait = wait.max(Duration::from_secs_f64((1.0 - bucket.available) / limit.per_second));
+            }
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        for (client, _) in clients {
+            if let Some(bucket) = buckets.by_client.get_mut(client) {
+                bucket.available -= 1.0;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn refilled(bucket: &Bucket, limit: RateLimit, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    (bucket.available + elapsed * limit.per_second).min(f64::from(limit.burst))
+}
+
+/// Paths never limited.
+const EXEMPT_PATHS: [&str; 3] = ["/metrics", "/healthz", "/readyz"];
+
+/// Limit every route of `router` but [`EXEMPT_PATHS`] with [`limit_request`],
+/// looking tokens up in `store`.
+pub fn rate_limit(router: Router, limits: RateLimits, store: StateStore) -> Router {
+    let state = (Arc::new(RateLimiter::new(limits)), store);
+    router.layer(middleware::from_fn_with_state(state, limit_request))
+}
+
+/// Middleware: answer `429` to a request whose token or client IP has used
+/// up its bucket.
+pub async fn limit_request(
+    State((limiter, store)): State<(Arc<RateLimiter>, StateStore)>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if EXEMPT_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let mut clients = Vec::with_capacity(2);
+    if let (Some(limit), Some(token)) = (limiter.limits.per_token, bearer(req.headers())) {
+        let token = digest(token);
+        if matches!(store.get_api_token(&token), Ok(Some(_))) {
+            clients.push((Client::Token(token), limit));
+        }
+    }
+    if let (Some(limit), Some(ConnectInfo(addr))) =
+        (limiter.limits.per_ip, req.extensions().get::<ConnectInfo<SocketAddr>>())
+    {
+        clients.push((Client::Ip(addr.ip()), limit));
+    }
+    match limiter.acquire(&clients, Instant::now()) {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            let mut response = error_response("rate limit exceeded", StatusCode::TOO_MANY_REQUESTS).into_response();
+            let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            response.headers_mut().insert(RETRY_AFTER, secs.max(1).into());
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::header::AUTHORIZATION;
+    use tower::ServiceExt;
+    use warpgrid_state::StateStore;
+
+    #[test]
+    fn buckets_refill_at_the_steady_rate() {
+        let limit = RateLimit { burst: 2, per_second: 0.5 };
+        let limiter = RateLimiter::new(RateLimits { per_token: Some(limit), per_ip: None });
+        let client = [(Client::Token("a".into()), limit)];
+        let start = Instant::now();
+
+        assert_eq!(limiter.acquire(&client, start), Ok(()));
+        assert_eq!(limiter.acquire(&client, start), Ok(()));
+        assert_eq!(limiter.acquire(&client, start), Err(Duration::from_secs(2)));
+        assert_eq!(limiter.acquire(&client, start + Duration::from_secs(1)), Err(Duration::from_secs(1)));
+        assert_eq!(limiter.acquire(&client, start + Duration::from_secs(2)), Ok(()));
+        // Another client has its own bucket.
+        assert_eq!(limiter.acquire(&[(Client::Token("b".into()), limit)], start), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn over_the_limit_gets_429_with_retry_after() {
+        let limits = RateLimits {
+            per_token: Some(RateLimit { burst: 1, per_second: 0.25 }),
+            per_ip: Some(RateLimit { burst: 2, per_second: 1.0 }),
+        };
+        let store = StateStore::open_in_memory().unwrap();
+        let token = crate::auth::create_token(&store, "ci").unwrap().token;
+        let router = rate_limit(crate::build_router(store.clone()), limits, store);
+        let call = |token: Option<&str>, ip: [u8; 4]| {
+            let mut req = Request::get("/api/v1/deployments");
+            if let Some(token) = token {
+                req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+            }
+            let mut req = req.body(Body::empty()).unwrap();
+            req.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+            router.clone().oneshot(req)
+        };
+
+        assert_eq!(call(Some(&token), [10, 0, 0, 1]).await.unwrap().status(), StatusCode::OK);
+        let limited = call(Some(&token), [10, 0, 0, 2]).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[RETRY_AFTER], "4");
+
+        // The token's refusal took nothing from 10.0.0.2's bucket.
+        assert_eq!(call(None, [10, 0, 0, 2]).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(call(None, [10, 0, 0, 2]).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(call(None, [10, 0, 0, 2]).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+        let metrics = Request::get("/metrics").body(Body::empty()).unwrap();
+        assert_eq!(router.clone().oneshot(metrics).await.unwrap().status(), StatusCode::OK);
+
+        // Made-up tokens draw only from their IP's bucket.
+        assert_eq!(call(Some("wgt_made_up_1"), [10, 0, 0, 3]).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(call(Some("wgt_made_up_2"), [10, 0, 0, 3]).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(call(Some("wgt_made_up_3"), [10, 0, 0, 3]).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn past_the_cap_the_least_recently_seen_bucket_is_dropped() {
+        let limit = RateLimit { burst: 1, per_second: 0.001 };
+        let limiter = RateLimiter::new(RateLimits { per_token: None, per_ip: Some(limit) });
+        let client = |n: usize| [(Client::Ip(IpAddr::from((n as u128).to_be_bytes())), limit)];
+        let start = Instant::now();
+        for n in 0..MAX_BUCKETS {
+            assert_eq!(limiter.acquire(&client(n), start + Duration::from_millis(n as u64)), Ok(()));
+        }
+        let now = start + Duration::from_secs(10);
+        // Client 0, seen again, is now the most recent; client 1 is dropped.
+        assert!(limiter.acquire(&client(0), now).is_err());
+        assert_eq!(limiter.acquire(&client(MAX_BUCKETS), now), Ok(()));
+        assert_eq!(limiter.buckets.lock().unwrap().by_client.len(), MAX_BUCKETS);
+        assert_eq!(limiter.acquire(&client(1), now), Ok(()));
+        assert!(limiter.acquire(&client(0), now).is_err());
+    }
+}
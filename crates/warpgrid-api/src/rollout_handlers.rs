@@ -1,6 +1,8 @@
 //! REST API handlers for rollout management.
 //!
-//! Provides endpoints to start, list, get, pause, and resume rollouts.
+//! Provides endpoints to start, list, get, pause, resume, and roll back
+//! rollouts, and the deployment health summary, which folds in rollout
+//! progress.
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -12,6 +14,9 @@ use axum::Json;
 use tokio::sync::RwLock;
 
 use warpgrid_rollout::{Rollout, RolloutPhase, RolloutStrategy};
+use warpgrid_state::{Clock, ClusterEvent, ClusterEventKind, SystemClock};
+
+use crate::events;
 
 /// Shared rollout state across handlers.
 pub type RolloutStore = Arc<RwLock<HashMap<String, Rollout>>>;
@@ -55,7 +60,7 @@ fn rollout_error(msg: &str, status: StatusCode) -> impl IntoResponse {
 }
 
 /// Serializable rollout status for API responses.
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct RolloutStatus {
     pub deployment_id: String,
     pub phase: RolloutPhase,
@@ -77,7 +82,7 @@ impl From<&Rollout> for RolloutStatus {
 }
 
 /// Request body to start a rollout.
-#[derive(serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct StartRolloutRequest {
     pub strategy: RolloutStrategy,
     pub new_version: String,
@@ -103,16 +108,12 @@ pub async fn start_rollout(
     // Check for existing active rollout.
     {
         let rollouts = state.rollouts.read().await;
-        if let Some(existing) = rollouts.get(&id) {
-            if existing.phase != RolloutPhase::Completed
-                && !matches!(existing.phase, RolloutPhase::RolledBack { .. })
-            {
-                return rollout_error(
-                    "rollout already in progress",
-                    StatusCode::CONFLICT,
-                )
-                .into_response();
-            }
+        if rollouts.get(&id).is_some_and(|existing| !existing.is_finished()) {
+            return rollout_error(
+                "rollout already in progress",
+                StatusCode::CONFLICT,
+            )
+            .into_response();
         }
     }
 
@@ -126,6 +127,7 @@ pub async fn start_rollout(
         &req.new_version,
     );
     rollout.start();
+    record(&state, ClusterEventKind::RolloutStarted, &id, format!("rolling out {}", req.new_version));
 
     let status = RolloutStatus::from(&rollout);
 
@@ -156,6 +158,34 @@ pub async fn get_rollout(
     }
 }
 
+/// GET /api/v1/deployments/:id/health
+pub async fn get_deployment_health(
+    State(state): State<RolloutApiState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let spec = match state.store.get_deployment(&id) {
+        Ok(Some(spec)) => spec,
+        Ok(None) => {
+            return rollout_error("deployment not found", StatusCode::NOT_FOUND).into_response()
+        }
+        Err(e) => {
+            return rollout_error(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response()
+        }
+    };
+    let instances = state.store.list_instances_for_deployment(&id);
+    let metrics = state.store.list_metrics_for_deployment(&id, usize::MAX);
+    let (instances, metrics) = match (instances, metrics) {
+        (Ok(instances), Ok(metrics)) => (instances, metrics),
+        (Err(e), _) | (_, Err(e)) => {
+            return rollout_error(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response()
+        }
+    };
+    let latest = metrics.iter().max_by_key(|m| m.epoch);
+    let rollouts = state.rollouts.read().await;
+    let health = warpgrid_health::summary::summarize(&spec, &instances, latest, rollouts.get(&id));
+    RolloutResponse::ok(health).into_response()
+}
+
 /// POST /api/v1/rollouts/:id/pause
 pub async fn pause_rollout(
     State(state): State<RolloutApiState>,
@@ -165,6 +195,7 @@ pub async fn pause_rollout(
     match rollouts.get_mut(&id) {
         Some(rollout) => {
             rollout.pause();
+            record(&state, ClusterEventKind::RolloutPaused, &id, "paused by operator".to_string());
             RolloutResponse::ok(RolloutStatus::from(&*rollout)).into_response()
         }
         None => rollout_error("rollout not found", StatusCode::NOT_FOUND).into_response(),
@@ -180,12 +211,36 @@ pub async fn resume_rollout(
     match rollouts.get_mut(&id) {
         Some(rollout) => {
             rollout.resume();
+            record(&state, ClusterEventKind::RolloutResumed, &id, "resumed by operator".to_string());
             RolloutResponse::ok(RolloutStatus::from(&*rollout)).into_response()
         }
         None => rollout_error("rollout not found", StatusCode::NOT_FOUND).into_response(),
     }
 }
 
+/// POST /api/v1/rollouts/:id/rollback
+pub async fn rollback_rollout(
+    State(state): State<RolloutApiState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let mut rollouts = state.rollouts.write().await;
+    match rollouts.get_mut(&id) {
+        Some(rollout) => {
+            if !rollout.rollback("rolled back by operator") {
+                return rollout_error("rollout already finished", StatusCode::CONFLICT).into_response();
+            }
+            record(&state, ClusterEventKind::RolloutRolledBack, &id, "rolled back by operator".to_string());
+            RolloutResponse::ok(RolloutStatus::from(&*rollout)).into_response()
+        }
+        None => rollout_error("rollout not found", StatusCode::NOT_FOUND).into_response(),
+    }
+}
+
+fn record(state: &RolloutApiState, kind: ClusterEventKind, deployment_id: &str, message: String) {
+    let event = ClusterEvent::new(kind, SystemClock.epoch_secs(), message);
+    events::record(&state.store, event.for_deployment(deployment_id));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,11 +261,12 @@ mod tests {
             namespace: ns.to_string(),
             name: name.to_string(),
             source: "oci://registry/app:v1".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 3, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -218,6 +274,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -249,6 +311,55 @@ mod tests {
         assert!(rollouts.contains_key("prod/api"));
     }
 
+    #[tokio::test]
+    async fn deployment_health_reflects_replicas_and_rollout() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("prod", "api")).unwrap();
+
+        let health = |state: RolloutApiState| async move {
+            let resp = get_deployment_health(State(state), Path("prod/api".to_string()))
+                .await
+                .into_response();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            json["data"].clone()
+        };
+        let data = health(state.clone()).await;
+        assert_eq!(data["status"], "unhealthy");
+        assert_eq!(data["reasons"][0]["kind"], "insufficient_replicas");
+
+        for n in 0..3 {
+            state
+                .store
+                .put_instance(&InstanceState {
+                    id: format!("inst-{n}"),
+                    deployment_id: "prod/api".to_string(),
+                    node_id: "node-1".to_string(),
+                    status: InstanceStatus::Running,
+                    health: HealthStatus::Healthy,
+                    restart_count: 0,
+                    memory_bytes: 0,
+                    started_at: 1000,
+                    updated_at: 1000,
+                })
+                .unwrap();
+        }
+        assert_eq!(health(state.clone()).await["status"], "healthy");
+
+        let mut rollout = Rollout::new("prod/api", RolloutStrategy::default(), 3, "v1", "v2");
+        rollout.start();
+        state.rollouts.write().await.insert("prod/api".to_string(), rollout);
+        let data = health(state.clone()).await;
+        assert_eq!(data["status"], "progressing");
+        assert_eq!(data["available"], 3);
+
+        let resp = get_deployment_health(State(state), Path("prod/web".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn start_rollout_missing_deployment() {
         let state = test_state();
@@ -360,6 +471,29 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn rollback_rollout_once() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("prod", "api")).unwrap();
+        let req = StartRolloutRequest {
+            strategy: RolloutStrategy::default(),
+            new_version: "v2".to_string(),
+        };
+        start_rollout(State(state.clone()), Path("prod/api".to_string()), Json(req)).await;
+
+        let resp = rollback_rollout(State(state.clone()), Path("prod/api".to_string())).await;
+        assert_eq!(resp.into_response().status(), StatusCode::OK);
+        assert!(matches!(
+            state.rollouts.read().await["prod/api"].phase,
+            RolloutPhase::RolledBack { .. }
+        ));
+
+        let resp = rollback_rollout(State(state.clone()), Path("prod/api".to_string())).await;
+        assert_eq!(resp.into_response().status(), StatusCode::CONFLICT);
+        let resp = rollback_rollout(State(state), Path("nope".to_string())).await;
+        assert_eq!(resp.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn canary_rollout_starts_observing() {
         let state = test_state();
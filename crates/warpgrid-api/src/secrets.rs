@@ -0,0 +1,261 @@
+//! Cluster secrets for `warp secrets`.
+//!
+//! - `GET /api/v1/secrets?namespace=` lists a namespace's secrets without
+//!   their values, which are not even decrypted
+//! - `POST /api/v1/secrets` creates a secret, refusing to replace one
+//! - `GET /api/v1/secrets/{id}` returns one secret's metadata, and its value
+//!   with `?reveal=true`
+//! - `PUT /api/v1/secrets/{id}` creates or replaces a secret's value
+//! - `DELETE /api/v1/secrets/{id}` removes it
+//!
+//! Ids are `{namespace}/{name}`, as for deployments. Names follow the
+//! feature flag rules: 1-128 letters, digits, `.`, `_`, or `-`. Values are
+//! encrypted in the state store when warpd has a secrets key, and reach
+//! guests through a deployment's `secrets` references.
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use warpgrid_state::{Clock, DEFAULT_NAMESPACE, Secret, SystemClock};
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+use crate::namespaces::is_valid_name;
+
+/// A secret without its value, as listed.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SecretInfo {
+    pub id: String,
+    pub namespace: String,
+    pub name: String,
+    /// Length of the value in bytes.
+    pub size: usize,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<&Secret> for SecretInfo {
+    fn from(secret: &Secret) -> Self {
+        Self {
+            id: secret.table_key(),
+            namespace: secret.namespace.clone(),
+            name: secret.name.clone(),
+            size: warpgrid_state::secrets::value_len(&secret.value),
+            created_at: secret.created_at,
+            updated_at: secret.updated_at,
+        }
+    }
+}
+
+/// `POST /secrets` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateSecretRequest {
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub name: String,
+    pub value: String,
+}
+
+/// `PUT /secrets/{id}` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PutSecretRequest {
+    pub value: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SecretsQuery {
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+}
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+/// Query parameters for `GET /secrets/{id}`.
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetSecretQuery {
+    /// Include the value.
+    #[serde(default)]
+    pub reveal: bool,
+}
+
+/// GET /api/v1/secrets
+pub async fn list_secrets(State(state): State<ApiState>, Query(query): Query<SecretsQuery>) -> Response {
+    match state.store.list_secrets(&query.namespace) {
+        Ok(secrets) => ApiResponse::ok(secrets.iter().map(SecretInfo::from).collect::<Vec<_>>()).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/secrets
+pub async fn create_secret(State(state): State<ApiState>, Json(req): Json<CreateSecretRequest>) -> Response {
+    let id = format!("{}/{}", req.namespace, req.name);
+    if let Err(e) = parse_id(&id) {
+        return error_response(&e, StatusCode::BAD_REQUEST).into_response();
+    }
+    match state.store.get_stored_secret(&id) {
+        Ok(Some(_)) => {
+            return error_response(&format!("secret {id} already exists"), StatusCode::CONFLICT).into_response();
+        }
+        Ok(None) => {}
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+    let now = SystemClock.epoch_secs();
+    let secret = Secret { namespace: req.namespace, name: req.name, value: req.value, created_at: now, updated_at: now };
+    match state.store.put_secret(&secret) {
+        Ok(()) => (StatusCode::CREATED, ApiResponse::ok(SecretInfo::from(&secret))).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// GET /api/v1/secrets/:id
+pub async fn get_secret(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Query(query): Query<GetSecretQuery>,
+) -> Response {
+    if query.reveal {
+        return match state.store.get_secret(&id) {
+            Ok(Some(secret)) => ApiResponse::ok(secret).into_response(),
+            Ok(None) => error_response("secret not found", StatusCode::NOT_FOUND).into_response(),
+            Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        };
+    }
+    match state.store.get_stored_secret(&id) {
+        Ok(Some(secret)) => ApiResponse::ok(SecretInfo::from(&secret)).into_response(),
+        Ok(None) => error_response("secret not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// PUT /api/v1/secrets/:id
+pub async fn put_secret(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(req): Json<PutSecretRequest>,
+) -> Response {
+    let (namespace, name) = match parse_id(&id) {
+        Ok(parts) => parts,
+        Err(e) => return error_response(&e, StatusCode::BAD_REQUEST).into_response(),
+    };
+    let now = SystemClock.epoch_secs();
+    let created_at = match state.store.get_stored_secret(&id) {
+        Ok(existing) => existing.map_or(now, |secret| secret.created_at),
+        Err(e) => return error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    let secret = Secret {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        value: req.value,
+        created_at,
+        updated_at: now,
+    };
+    match state.store.put_secret(&secret) {
+        Ok(()) => ApiResponse::ok(SecretInfo::from(&secret)).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// DELETE /api/v1/secrets/:id
+pub async fn delete_secret(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    match state.store.delete_secret(&id) {
+        Ok(true) => ApiResponse::ok("deleted").into_response(),
+        Ok(false) => error_response("secret not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Split `{namespace}/{name}`, checking both parts.
+fn parse_id(id: &str) -> Result<(&str, &str), String> {
+    let Some((namespace, name)) = id.split_once('/') else {
+        return Err(format!("secret id '{id}' must be namespace/name"));
+    };
+    if !is_valid_name(namespace) {
+        return Err(format!("invalid namespace '{namespace}'"));
+    }
+    if !is_valid_name(name) {
+        return Err(format!("invalid secret name '{name}': use 1-128 letters, digits, '.', '_' or '-'"));
+    }
+    Ok((namespace, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use warpgrid_state::{SecretsKey, StateStore};
+
+    async fn body(response: Response) -> (StatusCode, Value) {
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn put(state: &ApiState, id: &str, value: &str) -> impl std::future::Future<Output = Response> {
+        put_secret(
+            State(state.clone()),
+            Path(id.to_string()),
+            Json(PutSecretRequest { value: value.to_string() }),
+        )
+    }
+
+    fn get(state: &ApiState, id: &str, reveal: bool) -> impl std::future::Future<Output = Response> {
+        get_secret(State(state.clone()), Path(id.to_string()), Query(GetSecretQuery { reveal }))
+    }
+
+    #[tokio::test]
+    async fn secrets_round_trip_without_listing_values() {
+        let store = StateStore::open_in_memory().unwrap().with_secrets_key(SecretsKey::generate().unwrap());
+        let state = ApiState { store };
+        let (status, created) = body(put(&state, "default/db-password", "hunter2").await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(created["data"]["size"], 7);
+        put(&state, "prod/db-password", "s3cret").await;
+
+        let (_, listed) =
+            body(list_secrets(State(state.clone()), Query(SecretsQuery { namespace: "default".into() })).await).await;
+        assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+        assert_eq!(listed["data"][0]["id"], "default/db-password");
+        assert!(listed["data"][0].get("value").is_none());
+
+        let (_, secret) = body(get(&state, "default/db-password", false).await).await;
+        assert_eq!(secret["data"]["size"], 7);
+        assert!(secret["data"].get("value").is_none());
+        let (_, secret) = body(get(&state, "default/db-password", true).await).await;
+        assert_eq!(secret["data"]["value"], "hunter2");
+
+        let (status, _) = body(delete_secret(State(state.clone()), Path("default/db-password".into())).await).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = body(get(&state, "default/db-password", false).await).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn create_refuses_to_replace() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        let create = |name: &str| {
+            create_secret(
+                State(state.clone()),
+                Json(CreateSecretRequest { namespace: "prod".into(), name: name.into(), value: "s3cret".into() }),
+            )
+        };
+        let (status, created) = body(create("api-key").await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["data"]["id"], "prod/api-key");
+        let (status, _) = body(create("api-key").await).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = body(create("api key").await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_ids() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        for id in ["db-password", "default/db password", "default/", "a/b/c"] {
+            let (status, _) = body(put(&state, id, "x").await).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{id}");
+        }
+    }
+}
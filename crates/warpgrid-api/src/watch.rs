@@ -0,0 +1,296 @@
+//! Server-sent event stream of cluster state for live clients (`warp top`).
+//!
+//! `GET /api/v1/watch?interval_ms=` polls the state store and pushes:
+//!
+//! - `overview` — a [`ClusterOverview`], on connect and whenever it changes
+//! - `change` — a [`ChangeEvent`] per write to the replicated tables
+//!   (deployments, instances, services, flags), read from the store's change journal
+//!
+//! Metrics and rollouts are not journaled; they show up through `overview`.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::Stream;
+use warpgrid_state::{
+    DeploymentSpec, InstanceStatus, MetricsSnapshot, NodeInfo, ReplicaSync, StateResult,
+};
+
+use crate::rollout_handlers::{RolloutApiState, RolloutStatus};
+
+/// Poll interval bounds, in milliseconds.
+const INTERVAL_DEFAULT_MS: u64 = 1000;
+const INTERVAL_MIN_MS: u64 = 200;
+const INTERVAL_MAX_MS: u64 = 60_000;
+
+/// Journal entries read per poll.
+const CHANGES_PER_POLL: usize = 500;
+
+/// Query parameters for the watch stream.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct WatchQuery {
+    pub interval_ms: Option<u64>,
+}
+
+/// Everything a live view of the cluster needs, in one document.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ClusterOverview {
+    /// State revision the overview was built at.
+    pub revision: u64,
+    pub deployments: Vec<DeploymentOverview>,
+    pub nodes: Vec<NodeInfo>,
+}
+
+/// One deployment's instance counts, latest metrics, and rollout.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeploymentOverview {
+    pub id: String,
+    pub namespace: String,
+    pub name: String,
+    pub source: String,
+    pub min_instances: u32,
+    pub max_instances: u32,
+    /// Instances in any status.
+    pub instances: u32,
+    /// Instances in the `running` status.
+    pub running: u32,
+    pub metrics: Option<MetricsSnapshot>,
+    pub rollout: Option<RolloutStatus>,
+}
+
+/// A write to a replicated table.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ChangeEvent {
+    pub revision: u64,
+    pub table: String,
+    pub key: String,
+    /// `put` or `delete`.
+    pub op: String,
+}
+
+/// GET /api/v1/watch?interval_ms=
+pub async fn watch(
+    State(state): State<RolloutApiState>,
+    Query(query): Query<WatchQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let interval = Duration::from_millis(
+        query
+            .interval_ms
+            .unwrap_or(INTERVAL_DEFAULT_MS)
+            .clamp(INTERVAL_MIN_MS, INTERVAL_MAX_MS),
+    );
+    let watcher = Watcher::new(state);
+    let stream = futures_util::stream::unfold(
+        (watcher, VecDeque::new(), true),
+        move |(mut watcher, mut pending, mut first)| async move {
+            loop {
+                if let Some((name, data)) = pending.pop_front() {
+                    let event = Event::default().event(name).data(data);
+                    return Some((Ok(event), (watcher, pending, first)));
+                }
+                if !first {
+                    tokio::time::sleep(interval).await;
+                }
+                first = false;
+                match watcher.poll().await {
+                    Ok(events) => pending.extend(events),
+                    Err(e) => tracing::warn!(error = %e, "watch poll failed"),
+                }
+            }
+        },
+    );
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Tracks what a watch client has already been sent.
+struct Watcher {
+    state: RolloutApiState,
+    revision: Option<u64>,
+    last: Option<ClusterOverview>,
+}
+
+impl Watcher {
+    fn new(state: RolloutApiState) -> Self {
+        Self {
+            state,
+            revision: None,
+            last: None,
+        }
+    }
+
+    /// Events (name, JSON data) since the previous poll.
+    async fn poll(&mut self) -> StateResult<Vec<(&'static str, String)>> {
+        let store = &self.state.store;
+        let mut events = Vec::new();
+
+        let revision = store.state_revision()?;
+        if let Some(seen) = self.revision
+            && seen != revision
+        {
+            let changes = match store.replica_sync_since(seen, CHANGES_PER_POLL)? {
+                Some(ReplicaSync::Deltas { changes }) => changes,
+                // The journal answers a client at revision 0 with a snapshot;
+                // from an empty store, everything in it is new.
+                Some(ReplicaSync::Snapshot { entries, .. }) if seen == 0 => entries,
+                // Too far behind to replay; the overview still catches up.
+                _ => Vec::new(),
+            };
+            for change in changes {
+                let event = ChangeEvent {
+                    revision: change.revision,
+                    table: change.table,
+                    key: change.key,
+                    op: if change.value.is_some() { "put" } else { "delete" }.to_string(),
+                };
+                events.push(("change", to_json(&event)));
+            }
+        }
+        self.revision = Some(revision);
+
+        let overview = self.overview(revision).await?;
+        if self.last.as_ref() != Some(&overview) {
+            events.push(("overview", to_json(&overview)));
+            self.last = Some(overview);
+        }
+        Ok(events)
+    }
+
+    async fn overview(&self, revision: u64) -> StateResult<ClusterOverview> {
+        let store = &self.state.store;
+        let rollouts = self.state.rollouts.read().await;
+        let mut deployments = Vec::new();
+        for spec in store.list_deployments()? {
+            let instances = store.list_instances_for_deployment(&spec.id)?;
+            let running = instances
+                .iter()
+                .filter(|i| i.status == InstanceStatus::Running)
+                .count() as u32;
+            let metrics = store
+                .list_metrics_for_deployment(&spec.id, usize::MAX)?
+                .into_iter()
+                .max_by_key(|m| m.epoch);
+            let rollout = rollouts.get(&spec.id).map(RolloutStatus::from);
+            deployments.push(deployment_overview(spec, instances.len() as u32, running, metrics, rollout));
+        }
+        deployments.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut nodes = store.list_nodes()?;
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(ClusterOverview {
+            revision,
+            deployments,
+            nodes,
+        })
+    }
+}
+
+fn deployment_overview(
+    spec: DeploymentSpec,
+    instances: u32,
+    running: u32,
+    metrics: Option<MetricsSnapshot>,
+    rollout: Option<RolloutStatus>,
+) -> DeploymentOverview {
+    DeploymentOverview {
+        id: spec.id,
+        namespace: spec.namespace,
+        name: spec.name,
+        source: spec.source,
+        min_instances: spec.instances.min,
+        max_instances: spec.instances.max,
+        instances,
+        running,
+        metrics,
+        rollout,
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use warpgrid_state::*;
+
+    fn deployment(name: &str) -> DeploymentSpec {
+        DeploymentSpec {
+            id: format!("prod/{name}"),
+            namespace: "prod".to_string(),
+            name: name.to_string(),
+            source: "oci://registry/app:v1".to_string(),
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min: 1, max: 4 },
+            resources: ResourceLimits {
+                memory_bytes: 64 * 1024 * 1024,
+                cpu_weight: 100,
+                execution_budget_ms: None,
+            },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: HashMap::new(),
+            created_at: 1000,
+            updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn poll_sends_overview_then_changes() {
+        let state = RolloutApiState {
+            store: StateStore::open_in_memory().unwrap(),
+            rollouts: Arc::new(RwLock::new(HashMap::new())),
+        };
+        let mut watcher = Watcher::new(state.clone());
+        assert_eq!(watcher.poll().await.unwrap()[0].0, "overview");
+
+        // The first write to an empty store is reported too.
+        state.store.put_deployment(&deployment("api")).unwrap();
+        let events = watcher.poll().await.unwrap();
+        let names: Vec<_> = events.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["change", "overview"]);
+        let events = &events[1..];
+        let overview: ClusterOverview = serde_json::from_str(&events[0].1).unwrap();
+        assert_eq!(overview.deployments[0].id, "prod/api");
+        assert_eq!(overview.deployments[0].max_instances, 4);
+
+        // Nothing changed: nothing to send.
+        assert!(watcher.poll().await.unwrap().is_empty());
+
+        state.store.put_deployment(&deployment("web")).unwrap();
+        state.store.delete_deployment("prod/api").unwrap();
+        state
+            .store
+            .put_metrics(&MetricsSnapshot {
+                deployment_id: "prod/web".to_string(),
+                epoch: 7,
+                rps: 12.5,
+                latency_p50_ms: 3.0,
+                latency_p99_ms: 20.0,
+                error_rate: 0.0,
+                total_memory_bytes: 0,
+                active_instances: 1,
+            })
+            .unwrap();
+        let events = watcher.poll().await.unwrap();
+        let names: Vec<_> = events.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["change", "change", "overview"]);
+        let change: ChangeEvent = serde_json::from_str(&events[1].1).unwrap();
+        assert_eq!((change.key.as_str(), change.op.as_str()), ("prod/api", "delete"));
+        let overview: ClusterOverview = serde_json::from_str(&events[2].1).unwrap();
+        assert_eq!(overview.deployments.len(), 1);
+        assert_eq!(overview.deployments[0].metrics.as_ref().unwrap().rps, 12.5);
+    }
+}
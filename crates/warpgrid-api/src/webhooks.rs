@@ -0,0 +1,184 @@
+//! Outbound webhooks: URLs warpd POSTs cluster events to.
+//!
+//! - `GET /api/v1/webhooks` lists webhooks (without their secrets)
+//! - `POST /api/v1/webhooks` registers one and returns it with its signing
+//!   secret, the only time the secret is shown
+//! - `GET /api/v1/webhooks/{id}` returns one (without its secret)
+//! - `DELETE /api/v1/webhooks/{id}` removes it
+//!
+//! A webhook selects events by `kinds` (every kind when empty) and by
+//! `deployment`. warpd sends each selected event recorded after it starts
+//! as `{"webhook": id, "event": {...}}`, signed with the webhook's secret;
+//! see `warpd::webhooks` for the headers and retries.
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use warpgrid_state::{Clock, ClusterEventKind, SystemClock, Webhook};
+
+use crate::ApiState;
+use crate::handlers::{ApiResponse, error_response};
+
+/// Prefix of generated signing secrets.
+pub const SECRET_PREFIX: &str = "whsec_";
+
+/// A webhook as listed: everything but its secret.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct WebhookInfo {
+    pub id: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<ClusterEventKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+    pub created_at: u64,
+}
+
+impl From<&Webhook> for WebhookInfo {
+    fn from(webhook: &Webhook) -> Self {
+        Self {
+            id: webhook.id.clone(),
+            url: webhook.url.clone(),
+            kinds: webhook.kinds.clone(),
+            deployment: webhook.deployment.clone(),
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+/// `POST /webhooks` body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    #[serde(default)]
+    pub kinds: Vec<ClusterEventKind>,
+    #[serde(default)]
+    pub deployment: Option<String>,
+    /// Signing secret; one is generated when omitted.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// `POST /webhooks` response.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub info: WebhookInfo,
+    pub secret: String,
+}
+
+/// GET /api/v1/webhooks
+pub async fn list_webhooks(State(state): State<ApiState>) -> Response {
+    match state.store.list_webhooks() {
+        Ok(mut webhooks) => {
+            webhooks.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+            ApiResponse::ok(webhooks.iter().map(WebhookInfo::from).collect::<Vec<_>>()).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// POST /api/v1/webhooks
+pub async fn create_webhook(State(state): State<ApiState>, Json(req): Json<CreateWebhookRequest>) -> Response {
+    if let Err(e) = validate_url(&req.url) {
+        return error_response(&e, StatusCode::BAD_REQUEST).into_response();
+    }
+    if req.secret.as_deref().is_some_and(str::is_empty) {
+        return error_response("secret must not be empty", StatusCode::BAD_REQUEST).into_response();
+    }
+    let (id, generated) = match (random_hex(6), random_hex(32)) {
+        (Ok(id), Ok(secret)) => (id, format!("{SECRET_PREFIX}{secret}")),
+        (Err(e), _) | (_, Err(e)) => return error_response(&e, StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    };
+    let webhook = Webhook {
+        id,
+        url: req.url,
+        kinds: req.kinds,
+        deployment: req.deployment,
+        secret: req.secret.unwrap_or(generated),
+        created_at: SystemClock.epoch_secs(),
+    };
+    match state.store.put_webhook(&webhook) {
+        Ok(()) => {
+            let created = CreatedWebhook { info: WebhookInfo::from(&webhook), secret: webhook.secret };
+            (StatusCode::CREATED, ApiResponse::ok(created)).into_response()
+        }
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// GET /api/v1/webhooks/:id
+pub async fn get_webhook(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    match state.store.get_webhook(&id) {
+        Ok(Some(webhook)) => ApiResponse::ok(WebhookInfo::from(&webhook)).into_response(),
+        Ok(None) => error_response("webhook not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// DELETE /api/v1/webhooks/:id
+pub async fn delete_webhook(State(state): State<ApiState>, Path(id): Path<String>) -> Response {
+    match state.store.delete_webhook(&id) {
+        Ok(true) => ApiResponse::ok("deleted").into_response(),
+        Ok(false) => error_response("webhook not found", StatusCode::NOT_FOUND).into_response(),
+        Err(e) => error_response(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+/// Accept `http://` and `https://` URLs with a host.
+fn validate_url(url: &str) -> Result<(), String> {
+    let uri: Uri = url.parse().map_err(|e| format!("invalid webhook URL '{url}': {e}"))?;
+    if !matches!(uri.scheme_str(), Some("http" | "https")) || uri.host().is_none() {
+        return Err(format!("webhook URL '{url}' must be http:// or https:// with a host"));
+    }
+    Ok(())
+}
+
+fn random_hex(len: usize) -> Result<String, String> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warpgrid_state::StateStore;
+
+    async fn body(response: Response) -> (StatusCode, serde_json::Value) {
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn secrets_are_shown_once() {
+        let state = ApiState { store: StateStore::open_in_memory().unwrap() };
+        let create = |url: &str| {
+            let req = CreateWebhookRequest {
+                url: url.to_string(),
+                kinds: vec![ClusterEventKind::RolloutRolledBack],
+                deployment: None,
+                secret: None,
+            };
+            create_webhook(State(state.clone()), Json(req))
+        };
+        let (status, created) = body(create("https://hooks.example.com/x").await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created["data"]["secret"].as_str().unwrap().starts_with(SECRET_PREFIX));
+        let id = created["data"]["id"].as_str().unwrap().to_string();
+
+        let (_, listed) = body(list_webhooks(State(state.clone())).await).await;
+        assert_eq!(listed["data"][0]["kinds"], serde_json::json!(["rollout_rolled_back"]));
+        assert!(listed["data"][0].get("secret").is_none());
+        let (_, fetched) = body(get_webhook(State(state.clone()), Path(id.clone())).await).await;
+        assert!(fetched["data"].get("secret").is_none());
+
+        for bad in ["ftp://hooks.example.com", "/relative", "not a url"] {
+            assert_eq!(create(bad).await.status(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(delete_webhook(State(state.clone()), Path(id.clone())).await.status(), StatusCode::OK);
+        assert_eq!(delete_webhook(State(state), Path(id)).await.status(), StatusCode::NOT_FOUND);
+    }
+}
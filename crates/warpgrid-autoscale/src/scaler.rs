@@ -6,7 +6,7 @@
 //! to the scheduler.
 
 use std::collections::HashMap;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
 use tracing::{debug, info, warn};
 
@@ -55,6 +55,8 @@ pub struct Autoscaler {
     scale_states: HashMap<String, ScaleState>,
     /// Callback to perform scaling.
     scale_fn: Option<ScaleCallback>,
+    /// Time source for cooldowns and the evaluation interval.
+    clock: SharedClock,
 }
 
 impl Autoscaler {
@@ -64,6 +66,7 @@ impl Autoscaler {
             state,
             scale_states: HashMap::new(),
             scale_fn: None,
+            clock: SystemClock::shared(),
         }
     }
 
@@ -73,6 +76,12 @@ impl Autoscaler {
         self
     }
 
+    /// Use `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Evaluate a single deployment and return a scaling decision.
     ///
     /// Compares the latest metrics against the deployment's scaling config.
@@ -86,7 +95,7 @@ impl Autoscaler {
             None => return ScaleDecision::NoChange,
         };
 
-        let now = epoch_secs();
+        let now = self.clock.epoch_secs();
         let scale_state = self
             .scale_states
             .entry(spec.id.clone())
@@ -232,7 +241,7 @@ impl Autoscaler {
 
         loop {
             tokio::select! {
-                _ = tokio::time::sleep(interval) => {
+                _ = self.clock.sleep(interval) => {
                     if let Err(e) = self.evaluate_all().await {
                         tracing::error!(error = %e, "autoscaler evaluation failed");
                     }
@@ -258,17 +267,12 @@ fn parse_duration_secs(s: &str) -> u64 {
     }
 }
 
-fn epoch_secs() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
     fn test_spec_with_scaling(metric: &str, target: f64) -> DeploymentSpec {
         DeploymentSpec {
@@ -276,11 +280,12 @@ mod tests {
             namespace: "default".to_string(),
             name: "api".to_string(),
             source: "file://test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: Some(ScalingConfig {
                 metric: metric.to_string(),
@@ -293,6 +298,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -447,6 +458,59 @@ mod tests {
         assert_eq!(parse_duration_secs("invalid"), 30);
     }
 
+    #[test]
+    fn scale_up_waits_for_cooldown() {
+        let clock = Arc::new(ManualClock::default());
+        let state = StateStore::open_in_memory().unwrap();
+        let mut scaler = Autoscaler::new(state).with_clock(clock.clone());
+
+        let mut spec = test_spec_with_scaling("rps", 100.0);
+        spec.scaling.as_mut().unwrap().scale_up_window = "60s".to_string();
+        let snap = test_snapshot(200.0, 2);
+
+        assert!(matches!(scaler.evaluate(&spec, &snap), ScaleDecision::ScaleTo(_)));
+        assert_eq!(scaler.evaluate(&spec, &snap), ScaleDecision::NoChange);
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(scaler.evaluate(&spec, &snap), ScaleDecision::NoChange);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(matches!(scaler.evaluate(&spec, &snap), ScaleDecision::ScaleTo(_)));
+    }
+
+    #[tokio::test]
+    async fn run_evaluates_on_each_interval() {
+        let clock = Arc::new(ManualClock::default());
+        let state = StateStore::open_in_memory().unwrap();
+        state.put_deployment(&test_spec_with_scaling("rps", 100.0)).unwrap();
+        state.put_metrics(&test_snapshot(200.0, 2)).unwrap();
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let mut scaler = Autoscaler::new(state)
+            .with_clock(clock.clone())
+            .with_scale_fn(Box::new(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Ok(()) })
+            }));
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        let task = tokio::spawn(async move {
+            scaler.run(Duration::from_secs(30), shutdown_rx).await;
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        for expected in 1..=3 {
+            clock.advance(Duration::from_secs(30));
+            tokio::task::yield_now().await;
+            assert_eq!(calls.load(Ordering::SeqCst), expected);
+        }
+
+        shutdown_tx.send(true).unwrap();
+        task.await.unwrap();
+    }
+
     #[tokio::test]
     async fn evaluate_all_reads_from_state() {
         let state = StateStore::open_in_memory().unwrap();
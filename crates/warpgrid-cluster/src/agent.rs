@@ -2,7 +2,11 @@
 //!
 //! The agent runs on each worker node and connects to the control
 //! plane's `ClusterService` to join, send heartbeats, and receive
-//! commands.
+//! commands. When configured with a [`ReadReplica`], it also applies the
+//! state deltas shipped with heartbeat responses so node-local readers
+//! see deployments and service endpoints without calling the control plane.
+//! When given a memory pressure signal, it reports pressure in every
+//! heartbeat so the control plane places new work elsewhere.
 
 use std::collections::HashMap;
 use std::time::Duration;
@@ -10,6 +14,7 @@ use std::time::Duration;
 use tokio::sync::watch;
 use tonic::transport::Channel;
 use tracing::{debug, info, warn};
+use warpgrid_state::{ReadReplica, ReplicaSync};
 
 use crate::proto;
 use crate::proto::cluster_service_client::ClusterServiceClient;
@@ -29,6 +34,8 @@ pub struct AgentConfig {
     pub capacity_memory_bytes: u64,
     /// Total CPU weight capacity.
     pub capacity_cpu_weight: u32,
+    /// CPU weight measured by the startup benchmark, reported at join.
+    pub calibrated_cpu_weight: Option<u32>,
 }
 
 /// The node agent that maintains cluster membership.
@@ -38,6 +45,10 @@ pub struct NodeAgent {
     node_id: Option<String>,
     /// Heartbeat interval (set by control plane).
     heartbeat_interval: Duration,
+    /// Local read replica of control-plane state, if enabled.
+    replica: Option<ReadReplica>,
+    /// Whether the node is under memory pressure, if monitored.
+    pressure: Option<watch::Receiver<bool>>,
 }
 
 impl NodeAgent {
@@ -47,9 +58,24 @@ impl NodeAgent {
             config,
             node_id: None,
             heartbeat_interval: Duration::from_secs(5),
+            replica: None,
+            pressure: None,
         }
     }
 
+    /// Keep `replica` up to date with control-plane state.
+    pub fn with_replica(mut self, replica: ReadReplica) -> Self {
+        self.replica = Some(replica);
+        self
+    }
+
+    /// Report the node's memory pressure (as published by the local
+    /// pressure monitor) in heartbeats.
+    pub fn with_pressure(mut self, pressure: watch::Receiver<bool>) -> Self {
+        self.pressure = Some(pressure);
+        self
+    }
+
     /// Join the cluster.
     ///
     /// Connects to the control plane and registers this node.
@@ -63,6 +89,7 @@ impl NodeAgent {
                 labels: self.config.labels.clone(),
                 capacity_memory_bytes: self.config.capacity_memory_bytes,
                 capacity_cpu_weight: self.config.capacity_cpu_weight,
+                calibrated_cpu_weight: self.config.calibrated_cpu_weight,
             })
             .await?;
 
@@ -125,12 +152,18 @@ impl NodeAgent {
                         used_memory_bytes,
                         used_cpu_weight,
                         active_instances: 0, // Updated by caller.
+                        replica_revision: self.replica_revision(),
+                        memory_pressure: self.under_pressure(),
                     }).await {
                         Ok(resp) => {
                             let inner = resp.into_inner();
                             debug!(%node_id, ack = inner.acknowledged, "heartbeat sent");
 
                             for cmd in &inner.commands {
+                                if cmd.command_type == crate::STATE_SYNC_COMMAND {
+                                    self.apply_state_sync(&cmd.payload);
+                                    continue;
+                                }
                                 info!(
                                     %node_id,
                                     command = %cmd.command_type,
@@ -153,6 +186,39 @@ impl NodeAgent {
         Ok(())
     }
 
+    /// Revision of the local replica reported to the control plane, or
+    /// `None` if this agent keeps no replica.
+    fn replica_revision(&self) -> Option<u64> {
+        let replica = self.replica.as_ref()?;
+        match replica.revision() {
+            Ok(revision) => Some(revision),
+            Err(e) => {
+                // Ask for a fresh snapshot rather than skipping the sync.
+                warn!(error = %e, "failed to read replica revision");
+                Some(0)
+            }
+        }
+    }
+
+    /// Whether the local pressure monitor currently reports memory pressure.
+    fn under_pressure(&self) -> bool {
+        self.pressure.as_ref().is_some_and(|p| *p.borrow())
+    }
+
+    /// Apply a `state_sync` command payload to the local replica.
+    fn apply_state_sync(&self, payload: &str) {
+        let Some(replica) = &self.replica else {
+            return;
+        };
+        let result = serde_json::from_str::<ReplicaSync>(payload)
+            .map_err(anyhow::Error::from)
+            .and_then(|sync| replica.apply(&sync).map_err(anyhow::Error::from));
+        match result {
+            Ok(revision) => debug!(revision, "replica updated"),
+            Err(e) => warn!(error = %e, "failed to apply state sync"),
+        }
+    }
+
     /// Get the assigned node ID (None if not yet joined).
     pub fn node_id(&self) -> Option<&str> {
         self.node_id.as_deref()
@@ -178,6 +244,7 @@ mod tests {
             labels: HashMap::new(),
             capacity_memory_bytes: 8_000_000_000,
             capacity_cpu_weight: 1000,
+            calibrated_cpu_weight: None,
         }
     }
 
@@ -187,6 +254,42 @@ mod tests {
         assert!(agent.node_id().is_none());
     }
 
+    #[test]
+    fn agent_without_replica_reports_no_revision() {
+        let agent = NodeAgent::new(test_config());
+        assert_eq!(agent.replica_revision(), None);
+    }
+
+    #[test]
+    fn agent_applies_state_sync_to_replica() {
+        let agent = NodeAgent::new(test_config())
+            .with_replica(ReadReplica::open_in_memory().unwrap());
+        assert_eq!(agent.replica_revision(), Some(0));
+
+        let sync = ReplicaSync::Snapshot {
+            revision: 42,
+            entries: vec![],
+        };
+        agent.apply_state_sync(&serde_json::to_string(&sync).unwrap());
+        assert_eq!(agent.replica_revision(), Some(42));
+
+        // Malformed payloads are logged and ignored.
+        agent.apply_state_sync("not json");
+        assert_eq!(agent.replica_revision(), Some(42));
+    }
+
+    #[test]
+    fn agent_reports_pressure_from_monitor() {
+        let agent = NodeAgent::new(test_config());
+        assert!(!agent.under_pressure());
+
+        let (tx, rx) = watch::channel(false);
+        let agent = agent.with_pressure(rx);
+        assert!(!agent.under_pressure());
+        tx.send_replace(true);
+        assert!(agent.under_pressure());
+    }
+
     #[test]
     fn agent_config_with_labels() {
         let mut config = test_config();
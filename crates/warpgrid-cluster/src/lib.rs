@@ -11,6 +11,7 @@
 //!   ├── ClusterServer (gRPC)
 //!   │   ├── Join() → assigns node_id, returns membership
 //!   │   ├── Heartbeat() → updates node state, returns commands
+//!   │   │                 (including state deltas for agent replicas)
 //!   │   └── Leave() → drains node, removes from membership
 //!   └── MembershipManager
 //!       ├── Tracks node status (Ready, Draining, Left)
@@ -21,6 +22,7 @@
 //!   └── NodeAgent
 //!       ├── Connects to control plane via gRPC
 //!       ├── Sends periodic heartbeats
+//!       ├── Applies state deltas to its local ReadReplica
 //!       └── Executes commands from control plane
 //! ```
 
@@ -34,6 +36,12 @@ pub mod proto {
     tonic::include_proto!("warpgrid.cluster");
 }
 
+/// Command type carrying a JSON-encoded `warpgrid_state::ReplicaSync`.
+pub const STATE_SYNC_COMMAND: &str = "state_sync";
+
+/// Maximum state deltas shipped in a single heartbeat response.
+pub const MAX_DELTAS_PER_HEARTBEAT: usize = 1000;
+
 pub use agent::NodeAgent;
 pub use membership::MembershipManager;
 pub use server::ClusterServer;
@@ -45,6 +45,8 @@ pub struct MembershipManager {
     dead_timeout: Duration,
     /// Heartbeat interval expected from agents.
     heartbeat_interval: Duration,
+    /// How long placement avoids a node after it reports memory pressure.
+    pressure_cooldown: Duration,
 }
 
 impl MembershipManager {
@@ -54,6 +56,7 @@ impl MembershipManager {
             state,
             dead_timeout: Duration::from_secs(30),
             heartbeat_interval: Duration::from_secs(5),
+            pressure_cooldown: Duration::from_secs(60),
         }
     }
 
@@ -69,6 +72,17 @@ impl MembershipManager {
         self
     }
 
+    /// Set how long placement avoids a node after it reports memory pressure.
+    pub fn with_pressure_cooldown(mut self, cooldown: Duration) -> Self {
+        self.pressure_cooldown = cooldown;
+        self
+    }
+
+    /// The state store backing cluster membership.
+    pub fn state(&self) -> &StateStore {
+        &self.state
+    }
+
     /// Get the heartbeat interval in seconds.
     pub fn heartbeat_interval_secs(&self) -> u32 {
         self.heartbeat_interval.as_secs() as u32
@@ -77,7 +91,8 @@ impl MembershipManager {
     /// Register a new node in the cluster.
     ///
     /// Generates a node ID and persists the node info. Returns the
-    /// assigned node ID.
+    /// assigned node ID. `calibrated_cpu_weight` is the weight measured by
+    /// the node's startup benchmark, if it ran one.
     pub fn join(
         &self,
         address: &str,
@@ -85,9 +100,19 @@ impl MembershipManager {
         labels: HashMap<String, String>,
         capacity_memory_bytes: u64,
         capacity_cpu_weight: u32,
+        calibrated_cpu_weight: Option<u32>,
     ) -> StateResult<String> {
         let node_id = generate_node_id(address, port);
         let now = epoch_secs();
+        // A node rejoining after a restart stays cordoned, and keeps labels
+        // set through the API beneath the ones it joins with.
+        let (cordoned, labels) = match self.state.get_node(&node_id)? {
+            Some(mut existing) => {
+                existing.labels.extend(labels);
+                (existing.cordoned, existing.labels)
+            }
+            None => (false, labels),
+        };
 
         let node = NodeInfo {
             id: node_id.clone(),
@@ -99,28 +124,47 @@ impl MembershipManager {
             used_cpu_weight: 0,
             labels,
             last_heartbeat: now,
+            pressure_until: None,
+            replica_revision: None,
+            calibrated_cpu_weight,
+            cordoned,
         };
 
         self.state.put_node(&node)?;
-        info!(%node_id, %address, port, "node joined cluster");
+        let event = ClusterEvent::new(ClusterEventKind::NodeJoined, now, format!("joined from {address}:{port}"));
+        self.state.record_event(&event.for_node(&node_id))?;
+        info!(%node_id, %address, port, ?calibrated_cpu_weight, "node joined cluster");
         Ok(node_id)
     }
 
     /// Process a heartbeat from a node.
     ///
-    /// Updates resource usage and last-seen timestamp.
+    /// Updates resource usage, the applied replica revision, and the
+    /// last-seen timestamp. A node reporting `memory_pressure` is avoided by
+    /// placement until the pressure cooldown has passed since its last such
+    /// report.
     pub fn heartbeat(
         &self,
         node_id: &str,
         used_memory_bytes: u64,
         used_cpu_weight: u32,
+        memory_pressure: bool,
+        replica_revision: Option<u64>,
     ) -> StateResult<bool> {
         let node = self.state.get_node(node_id)?;
         match node {
             Some(mut n) => {
+                let now = epoch_secs();
                 n.used_memory_bytes = used_memory_bytes;
                 n.used_cpu_weight = used_cpu_weight;
-                n.last_heartbeat = epoch_secs();
+                n.replica_revision = replica_revision;
+                n.last_heartbeat = now;
+                if memory_pressure {
+                    if !n.is_under_pressure(now) {
+                        warn!(%node_id, "node reports memory pressure; avoiding it for placement");
+                    }
+                    n.pressure_until = Some(now + self.pressure_cooldown.as_secs());
+                }
                 self.state.put_node(&n)?;
                 debug!(%node_id, "heartbeat received");
                 Ok(true)
@@ -211,6 +255,9 @@ impl MembershipManager {
         for member in members {
             if member.status == MemberStatus::Dead {
                 self.state.delete_node(&member.node_id)?;
+                let message = format!("no heartbeat for {}s", self.dead_timeout.as_secs());
+                let event = ClusterEvent::new(ClusterEventKind::NodeLost, epoch_secs(), message);
+                self.state.record_event(&event.for_node(&member.node_id))?;
                 warn!(node_id = %member.node_id, "reaped dead node");
                 reaped.push(member.node_id);
             }
@@ -219,6 +266,23 @@ impl MembershipManager {
         Ok(reaped)
     }
 
+    /// Activate staged config bundles once every live node running the
+    /// deployment reports a replica that holds them. Dead nodes and nodes
+    /// without a replica are not waited for.
+    ///
+    /// Returns the deployments whose bundle was activated.
+    pub fn promote_config_bundles(&self) -> StateResult<Vec<String>> {
+        let now = epoch_secs();
+        let applied = self
+            .state
+            .list_nodes()?
+            .into_iter()
+            .filter(|n| now.saturating_sub(n.last_heartbeat) <= self.dead_timeout.as_secs())
+            .filter_map(|n| Some((n.id, n.replica_revision?)))
+            .collect();
+        self.state.promote_staged_configs(&applied, now)
+    }
+
     /// Count of ready (alive) nodes.
     pub fn ready_count(&self) -> StateResult<usize> {
         let members = self.list_members()?;
@@ -255,7 +319,7 @@ mod tests {
     fn join_creates_node() {
         let mgr = MembershipManager::new(test_state());
         let node_id = mgr
-            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
         assert!(node_id.starts_with("node-"));
@@ -263,16 +327,49 @@ mod tests {
         assert_eq!(member.address, "10.0.0.1");
         assert_eq!(member.port, 8443);
         assert_eq!(member.status, MemberStatus::Ready);
+        let events = mgr.state().list_events(0, 10, |_| true).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ClusterEventKind::NodeJoined);
+        assert_eq!(events[0].node_id.as_deref(), Some(node_id.as_str()));
+    }
+
+    #[test]
+    fn join_records_calibrated_cpu_weight() {
+        let mgr = MembershipManager::new(test_state());
+        let node_id = mgr
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, Some(1450))
+            .unwrap();
+
+        let node = mgr.state().get_node(&node_id).unwrap().unwrap();
+        assert_eq!(node.capacity_cpu_weight, 1000);
+        assert_eq!(node.effective_cpu_weight(), 1450);
+    }
+
+    #[test]
+    fn rejoin_keeps_cordon_and_labels() {
+        let mgr = MembershipManager::new(test_state());
+        let label = |k: &str, v: &str| (k.to_string(), v.to_string());
+        let node_id = mgr.join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None).unwrap();
+        let mut node = mgr.state().get_node(&node_id).unwrap().unwrap();
+        node.cordoned = true;
+        node.labels = HashMap::from([label("rack", "r1"), label("zone", "eu-1")]);
+        mgr.state().put_node(&node).unwrap();
+
+        let joined = HashMap::from([label("zone", "eu-2")]);
+        mgr.join("10.0.0.1", 8443, joined, 8_000_000_000, 1000, None).unwrap();
+        let node = mgr.state().get_node(&node_id).unwrap().unwrap();
+        assert!(node.cordoned);
+        assert_eq!(node.labels, HashMap::from([label("rack", "r1"), label("zone", "eu-2")]));
     }
 
     #[test]
     fn heartbeat_updates_usage() {
         let mgr = MembershipManager::new(test_state());
         let node_id = mgr
-            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
-        mgr.heartbeat(&node_id, 1_000_000_000, 200).unwrap();
+        mgr.heartbeat(&node_id, 1_000_000_000, 200, false, None).unwrap();
 
         let member = mgr.get_member(&node_id).unwrap().unwrap();
         assert_eq!(member.used_memory_bytes, 1_000_000_000);
@@ -282,15 +379,66 @@ mod tests {
     #[test]
     fn heartbeat_unknown_node_returns_false() {
         let mgr = MembershipManager::new(test_state());
-        let ack = mgr.heartbeat("unknown", 0, 0).unwrap();
+        let ack = mgr.heartbeat("unknown", 0, 0, false, None).unwrap();
         assert!(!ack);
     }
 
+    #[test]
+    fn memory_pressure_marks_node_for_cooldown() {
+        let mgr = MembershipManager::new(test_state())
+            .with_pressure_cooldown(Duration::from_secs(120));
+        let node_id = mgr
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
+            .unwrap();
+        let now = epoch_secs();
+
+        mgr.heartbeat(&node_id, 0, 0, true, None).unwrap();
+        let node = mgr.state().get_node(&node_id).unwrap().unwrap();
+        assert!(node.is_under_pressure(now));
+        assert!(!node.is_under_pressure(now + 121));
+
+        // A pressure-free heartbeat lets the cooldown run out on its own.
+        mgr.heartbeat(&node_id, 0, 0, false, None).unwrap();
+        let node = mgr.state().get_node(&node_id).unwrap().unwrap();
+        assert!(node.is_under_pressure(now));
+    }
+
+    #[test]
+    fn config_bundle_promoted_once_nodes_report_it() {
+        let mgr = MembershipManager::new(test_state());
+        let node_id = mgr
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
+            .unwrap();
+        mgr.state()
+            .put_instance(&InstanceState {
+                id: "inst-0".to_string(),
+                deployment_id: "default/api".to_string(),
+                node_id: node_id.clone(),
+                status: InstanceStatus::Running,
+                health: HealthStatus::Healthy,
+                restart_count: 0,
+                memory_bytes: 0,
+                started_at: 0,
+                updated_at: 0,
+            })
+            .unwrap();
+        let bundle = ConfigBundle {
+            files: [("/etc/app.toml".to_string(), "port = 80".to_string())].into(),
+        };
+        let staged = mgr.state().stage_config_bundle("default/api", &bundle, 0).unwrap().staged.unwrap();
+
+        mgr.heartbeat(&node_id, 0, 0, false, Some(staged.revision - 1)).unwrap();
+        assert!(mgr.promote_config_bundles().unwrap().is_empty());
+
+        mgr.heartbeat(&node_id, 0, 0, false, Some(staged.revision)).unwrap();
+        assert_eq!(mgr.promote_config_bundles().unwrap(), ["default/api"]);
+    }
+
     #[test]
     fn leave_removes_node() {
         let mgr = MembershipManager::new(test_state());
         let node_id = mgr
-            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
         assert!(mgr.leave(&node_id).unwrap());
@@ -300,9 +448,9 @@ mod tests {
     #[test]
     fn list_members_returns_all() {
         let mgr = MembershipManager::new(test_state());
-        mgr.join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+        mgr.join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
-        mgr.join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000)
+        mgr.join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
         let members = mgr.list_members().unwrap();
@@ -316,7 +464,7 @@ mod tests {
             .with_dead_timeout(Duration::from_secs(0));
 
         let node_id = mgr
-            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
         // With 0s timeout, node should be immediately "dead" on next check
@@ -337,7 +485,7 @@ mod tests {
             .with_dead_timeout(Duration::from_secs(0));
 
         let node_id = mgr
-            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+            .join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
         // Make it dead.
@@ -353,9 +501,9 @@ mod tests {
     #[test]
     fn ready_count() {
         let mgr = MembershipManager::new(test_state());
-        mgr.join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000)
+        mgr.join("10.0.0.1", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
-        mgr.join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000)
+        mgr.join("10.0.0.2", 8443, HashMap::new(), 8_000_000_000, 1000, None)
             .unwrap();
 
         assert_eq!(mgr.ready_count().unwrap(), 2);
@@ -369,7 +517,7 @@ mod tests {
         labels.insert("zone".to_string(), "a".to_string());
 
         let node_id = mgr
-            .join("10.0.0.1", 8443, labels.clone(), 8_000_000_000, 1000)
+            .join("10.0.0.1", 8443, labels.clone(), 8_000_000_000, 1000, None)
             .unwrap();
 
         let member = mgr.get_member(&node_id).unwrap().unwrap();
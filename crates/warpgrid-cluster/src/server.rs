@@ -8,7 +8,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use tonic::{Request, Response, Status};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::membership::MembershipManager;
 use crate::proto;
@@ -31,6 +31,35 @@ impl ClusterServer {
     ) -> proto::cluster_service_server::ClusterServiceServer<Self> {
         proto::cluster_service_server::ClusterServiceServer::new(self)
     }
+
+    /// Build the command that brings an agent's read replica up to date,
+    /// if it is behind.
+    ///
+    /// Failures are logged rather than failing the heartbeat; the agent
+    /// simply retries from the same revision next time.
+    fn state_sync_command(&self, node_id: &str, revision: u64) -> Option<proto::NodeCommand> {
+        let sync = match self
+            .membership
+            .state()
+            .replica_sync_since(revision, crate::MAX_DELTAS_PER_HEARTBEAT)
+        {
+            Ok(sync) => sync?,
+            Err(e) => {
+                warn!(%node_id, error = %e, "failed to build replica sync");
+                return None;
+            }
+        };
+        match serde_json::to_string(&sync) {
+            Ok(payload) => Some(proto::NodeCommand {
+                command_type: crate::STATE_SYNC_COMMAND.to_string(),
+                payload,
+            }),
+            Err(e) => {
+                warn!(%node_id, error = %e, "failed to encode replica sync");
+                None
+            }
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -51,6 +80,7 @@ impl ClusterService for ClusterServer {
                 labels,
                 req.capacity_memory_bytes,
                 req.capacity_cpu_weight,
+                req.calibrated_cpu_weight,
             )
             .map_err(|e| Status::internal(e.to_string()))?;
 
@@ -112,12 +142,24 @@ impl ClusterService for ClusterServer {
 
         let acknowledged = self
             .membership
-            .heartbeat(&req.node_id, req.used_memory_bytes, req.used_cpu_weight)
+            .heartbeat(
+                &req.node_id,
+                req.used_memory_bytes,
+                req.used_cpu_weight,
+                req.memory_pressure,
+                req.replica_revision,
+            )
             .map_err(|e| Status::internal(e.to_string()))?;
 
+        // Other commands are populated by the scheduler.
+        let mut commands = Vec::new();
+        if acknowledged && let Some(revision) = req.replica_revision {
+            commands.extend(self.state_sync_command(&req.node_id, revision));
+        }
+
         Ok(Response::new(proto::HeartbeatResponse {
             acknowledged,
-            commands: vec![], // Commands are populated by the scheduler.
+            commands,
         }))
     }
 
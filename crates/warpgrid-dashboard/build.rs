@@ -0,0 +1,23 @@
+fn main() {
+    // Embed the self-hosted Tailwind/HTMX bundle vendored by
+    // scripts/vendor-dashboard-assets.sh. The pages never fall back to a
+    // CDN, so a build without it is flagged.
+    println!("cargo::rustc-check-cfg=cfg(embedded_assets)");
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let assets_dir = std::path::Path::new(&manifest_dir).join("assets");
+    let mut vendored = true;
+    for file in ["tailwind.css", "htmx.min.js", "htmx-ext-sse.js"] {
+        let path = assets_dir.join(file);
+        println!("cargo:rerun-if-changed={}", path.display());
+        vendored &= path.is_file();
+    }
+    if vendored {
+        println!("cargo:rustc-cfg=embedded_assets");
+    } else {
+        println!(
+            "cargo::warning=dashboard assets are not vendored; run scripts/vendor-dashboard-assets.sh \
+             and commit crates/warpgrid-dashboard/assets/, or the dashboard has no styles or scripts"
+        );
+    }
+}
@@ -7,10 +7,11 @@ use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Redirect};
 
-use warpgrid_rollout::{Rollout, RolloutStrategy};
+use warpgrid_rollout::{CanaryConfig, RollingConfig, Rollout, RolloutStrategy};
 use warpgrid_state::{
-    DeploymentSpec, HealthStatus, InstanceConstraints, InstanceState, InstanceStatus,
-    MetricsSnapshot, ResourceLimits, ShimsEnabled, TriggerConfig,
+    Clock, DeploymentFlags, DeploymentSpec, FeatureFlag, FlagSet, HealthStatus,
+    InstanceConstraints, InstanceState, InstanceStatus, MetricsSnapshot, ResourceLimits,
+    ScalingConfig, ShimsEnabled, SystemClock, TriggerConfig,
 };
 
 use crate::DashboardState;
@@ -62,10 +63,111 @@ pub async fn scale_deployment(
 
 // ── Start Rollout ───────────────────────────────────────────────
 
-#[derive(serde::Deserialize)]
+/// Start-rollout form. The strategy's tuning fields are optional; left
+/// blank, they take the strategy's defaults.
+#[derive(Default, serde::Deserialize)]
+#[serde(default)]
 pub struct RolloutForm {
+    /// Deployment to roll out, for the form on the rollouts page.
+    pub deployment: String,
+    /// `rolling`, `canary`, or `blue_green`.
     pub strategy: String,
     pub new_version: String,
+    pub batch_size: String,
+    pub batch_interval_secs: String,
+    pub max_unavailable: String,
+    pub health_timeout_secs: String,
+    pub canary_percent: String,
+    pub canary_instances: String,
+    pub observation_secs: String,
+    pub error_rate_threshold: String,
+    pub latency_threshold_ms: String,
+}
+
+impl RolloutForm {
+    fn to_strategy(&self) -> Result<RolloutStrategy, String> {
+        let strategy = match self.strategy.as_str() {
+            "" | "rolling" => {
+                let defaults = RollingConfig::default();
+                let cfg = RollingConfig {
+                    batch_size: field("batch size", &self.batch_size, defaults.batch_size)?,
+                    batch_interval_secs: field(
+                        "batch interval",
+                        &self.batch_interval_secs,
+                        defaults.batch_interval_secs,
+                    )?,
+                    max_unavailable: field(
+                        "max unavailable",
+                        &self.max_unavailable,
+                        defaults.max_unavailable,
+                    )?,
+                    health_timeout_secs: field(
+                        "health timeout",
+                        &self.health_timeout_secs,
+                        defaults.health_timeout_secs,
+                    )?,
+                };
+                if cfg.batch_size == 0 {
+                    return Err("batch size must be at least 1".to_string());
+                }
+                RolloutStrategy::Rolling(cfg)
+            }
+            "canary" => {
+                let defaults = CanaryConfig::default();
+                let cfg = CanaryConfig {
+                    traffic_percent: field(
+                        "canary percentage",
+                        self.canary_percent.trim_end_matches('%'),
+                        defaults.traffic_percent,
+                    )?,
+                    canary_instances: field(
+                        "canary instances",
+                        &self.canary_instances,
+                        defaults.canary_instances,
+                    )?,
+                    observation_secs: field(
+                        "observation window",
+                        &self.observation_secs,
+                        defaults.observation_secs,
+                    )?,
+                    error_rate_threshold: field(
+                        "error rate threshold",
+                        self.error_rate_threshold.trim_end_matches('%'),
+                        defaults.error_rate_threshold,
+                    )?,
+                    latency_threshold_ms: field(
+                        "latency threshold",
+                        &self.latency_threshold_ms,
+                        defaults.latency_threshold_ms,
+                    )?,
+                };
+                if !(1..=100).contains(&cfg.traffic_percent) {
+                    return Err("canary percentage must be between 1 and 100".to_string());
+                }
+                if cfg.canary_instances == 0 {
+                    return Err("canary instances must be at least 1".to_string());
+                }
+                if !(0.0..=100.0).contains(&cfg.error_rate_threshold) {
+                    return Err("error rate threshold must be between 0 and 100".to_string());
+                }
+                RolloutStrategy::Canary(cfg)
+            }
+            "blue_green" => RolloutStrategy::BlueGreen,
+            other => return Err(format!("unknown strategy '{other}'")),
+        };
+        Ok(strategy)
+    }
+}
+
+/// Parse an optional form field, falling back to `default` when blank.
+fn field<T: std::str::FromStr>(name: &str, value: &str, default: T) -> Result<T, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(default);
+    }
+    value
+        .parse()
+        .map_err(|_| format!("invalid {name} '{value}'"))
 }
 
 pub async fn start_rollout(
@@ -98,23 +200,34 @@ pub async fn start_rollout(
         .into_response();
     }
 
-    let strategy = match form.strategy.as_str() {
-        "canary" => RolloutStrategy::Canary(Default::default()),
-        "blue_green" => RolloutStrategy::BlueGreen,
-        _ => RolloutStrategy::default(),
+    let strategy = match form.to_strategy() {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            return Html(format!(
+                r#"<div class="text-amber-400 text-sm font-mono">{}</div>"#,
+                e
+            ))
+            .into_response()
+        }
     };
 
-    let mut rollout = Rollout::new(
-        &id,
-        strategy,
-        spec.instances.min,
-        &spec.source,
-        &form.new_version,
-    );
-    rollout.start();
-
     {
         let mut rollouts = state.rollouts.write().await;
+        if rollouts.get(&id).is_some_and(|existing| !existing.is_finished()) {
+            return Html(format!(
+                r#"<div class="text-amber-400 text-sm font-mono">A rollout of {} is already in progress</div>"#,
+                id
+            ))
+            .into_response();
+        }
+        let mut rollout = Rollout::new(
+            &id,
+            strategy,
+            spec.instances.min,
+            &spec.source,
+            &form.new_version,
+        );
+        rollout.start();
         rollouts.insert(id.clone(), rollout);
     }
 
@@ -125,6 +238,25 @@ pub async fn start_rollout(
     .into_response()
 }
 
+/// POST /dashboard/rollouts — the start form on the rollouts page, which
+/// names the deployment in the form rather than the path.
+pub async fn start_rollout_for(
+    State(state): State<DashboardState>,
+    axum::extract::Form(form): axum::extract::Form<RolloutForm>,
+) -> impl IntoResponse {
+    if form.deployment.is_empty() {
+        return Html(
+            r#"<div class="text-amber-400 text-sm font-mono">Deployment is required</div>"#
+                .to_string(),
+        )
+        .into_response();
+    }
+    let id = form.deployment.clone();
+    start_rollout(State(state), Path(id), axum::extract::Form(form))
+        .await
+        .into_response()
+}
+
 // ── Pause / Resume Rollout ──────────────────────────────────────
 
 pub async fn pause_rollout(
@@ -165,6 +297,305 @@ pub async fn resume_rollout(
     }
 }
 
+// ── Roll Back Rollout ───────────────────────────────────────────
+
+pub async fn rollback_rollout(
+    State(state): State<DashboardState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let mut rollouts = state.rollouts.write().await;
+    match rollouts.get_mut(&id).map(|rollout| rollout.rollback("rolled back by operator")) {
+        Some(true) => Html(
+            r#"<div class="text-rose-400 text-sm font-mono">Rollout rolled back</div>"#.to_string(),
+        ),
+        Some(false) => Html(
+            r#"<div class="text-amber-400 text-sm font-mono">Rollout already finished</div>"#
+                .to_string(),
+        ),
+        None => Html(
+            r#"<div class="text-rose-400 text-sm font-mono">Rollout not found</div>"#.to_string(),
+        ),
+    }
+}
+
+// ── Feature Flags ───────────────────────────────────────────────
+
+#[derive(serde::Deserialize)]
+pub struct FlagForm {
+    pub name: String,
+    /// `boolean`, `string`, or `percentage`.
+    pub kind: String,
+    pub value: String,
+}
+
+impl FlagForm {
+    fn to_flag(&self) -> Result<FeatureFlag, String> {
+        let value = self.value.trim();
+        let flag = match self.kind.as_str() {
+            "boolean" => match value {
+                "on" | "true" => FeatureFlag::Boolean { enabled: true },
+                "off" | "false" => FeatureFlag::Boolean { enabled: false },
+                _ => return Err(format!("expected on or off, got '{value}'")),
+            },
+            "string" => FeatureFlag::String {
+                value: value.to_string(),
+            },
+            "percentage" => FeatureFlag::Percentage {
+                percent: value
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| format!("expected a percentage, got '{value}'"))?,
+            },
+            other => return Err(format!("unknown flag type '{other}'")),
+        };
+        flag.validate()?;
+        Ok(flag)
+    }
+}
+
+fn flag_error(msg: &str) -> axum::response::Response {
+    Html(format!(
+        r#"<div class="text-rose-400 text-sm font-mono">{}</div>"#,
+        msg
+    ))
+    .into_response()
+}
+
+/// Set one flag; an empty value removes it.
+pub async fn set_flag(
+    State(state): State<DashboardState>,
+    Path(id): Path<String>,
+    axum::extract::Form(form): axum::extract::Form<FlagForm>,
+) -> impl IntoResponse {
+    match state.store.get_deployment(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return flag_error("Deployment not found"),
+        Err(e) => return flag_error(&format!("Error: {e}")),
+    }
+    let mut flags = match state.store.get_flags(&id) {
+        Ok(flags) => flags.unwrap_or_else(|| DeploymentFlags {
+            deployment_id: id.clone(),
+            set: FlagSet::default(),
+            updated_at: 0,
+        }),
+        Err(e) => return flag_error(&format!("Error: {e}")),
+    };
+
+    let message = if form.value.trim().is_empty() {
+        flags.set.flags.remove(&form.name);
+        format!("Removed flag {}", form.name)
+    } else {
+        match form.to_flag() {
+            Ok(flag) => {
+                flags.set.flags.insert(form.name.clone(), flag);
+                format!("Set flag {}", form.name)
+            }
+            Err(e) => return flag_error(&e),
+        }
+    };
+    if let Err(e) = flags.set.validate() {
+        return flag_error(&e);
+    }
+    flags.updated_at = SystemClock.epoch_secs();
+    if let Err(e) = state.store.put_flags(&flags) {
+        return flag_error(&format!("Error: {e}"));
+    }
+
+    Html(format!(
+        r#"<div class="text-emerald-400 text-sm font-mono">{}</div>"#,
+        message
+    ))
+    .into_response()
+}
+
+// ── Create Deployment ───────────────────────────────────────────
+
+/// The deployment wizard's fields. Numbers are strings so a blank one
+/// takes its default; checkboxes send `true` when ticked.
+#[derive(Default, serde::Deserialize)]
+#[serde(default)]
+pub struct DeploymentForm {
+    pub namespace: String,
+    pub name: String,
+    pub source: String,
+    pub shim_timezone: bool,
+    pub shim_dev_urandom: bool,
+    pub shim_dns: bool,
+    pub shim_signals: bool,
+    pub shim_database_proxy: bool,
+    pub memory_mb: String,
+    pub cpu_weight: String,
+    pub execution_budget_ms: String,
+    pub instances_min: String,
+    pub instances_max: String,
+    pub autoscale: bool,
+    pub scaling_metric: String,
+    pub scaling_target: String,
+    pub scale_up_window: String,
+    pub scale_down_window: String,
+    /// `http`, `cron`, or `queue`.
+    pub trigger: String,
+    pub port: String,
+    /// Comma-separated host names.
+    pub hosts: String,
+    pub path_prefix: String,
+    pub schedule: String,
+    pub topic: String,
+    /// `KEY=value` lines.
+    pub env: String,
+}
+
+impl DeploymentForm {
+    /// The spec the wizard would apply, stamped `now`.
+    pub fn to_spec(&self, now: u64) -> Result<DeploymentSpec, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("name is required".to_string());
+        }
+        let source = self.source.trim();
+        if source.is_empty() {
+            return Err("an artifact source is required".to_string());
+        }
+
+        let min = field("minimum instances", &self.instances_min, 1)?;
+        let max = field("maximum instances", &self.instances_max, min.max(1))?;
+        if max == 0 || min > max {
+            return Err(format!("instances must satisfy min <= max and max >= 1, got {min}..{max}"));
+        }
+
+        let trigger = match self.trigger.as_str() {
+            "" | "http" => TriggerConfig::Http {
+                port: match self.port.trim() {
+                    "" => None,
+                    port => Some(field("port", port, 0)?),
+                },
+                hosts: self
+                    .hosts
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|host| !host.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                path_prefix: Some(self.path_prefix.trim())
+                    .filter(|prefix| !prefix.is_empty())
+                    .map(str::to_string),
+            },
+            "cron" if !self.schedule.trim().is_empty() => TriggerConfig::Cron {
+                schedule: self.schedule.trim().to_string(),
+            },
+            "queue" if !self.topic.trim().is_empty() => TriggerConfig::Queue {
+                topic: self.topic.trim().to_string(),
+            },
+            "cron" => return Err("a cron trigger needs a schedule".to_string()),
+            "queue" => return Err("a queue trigger needs a topic".to_string()),
+            other => return Err(format!("unknown trigger '{other}'")),
+        };
+
+        let scaling = if self.autoscale {
+            Some(ScalingConfig {
+                metric: match self.scaling_metric.as_str() {
+                    "" => "rps".to_string(),
+                    metric @ ("rps" | "latency_p99" | "cpu" | "memory") => metric.to_string(),
+                    other => return Err(format!("unknown scaling metric '{other}'")),
+                },
+                target_value: field("scaling target", &self.scaling_target, 100.0)?,
+                scale_up_window: text_or(&self.scale_up_window, "30s"),
+                scale_down_window: text_or(&self.scale_down_window, "5m"),
+            })
+        } else {
+            None
+        };
+
+        let mut env = std::collections::HashMap::new();
+        for line in self.env.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("environment line '{line}' is not KEY=value"));
+            };
+            env.insert(key.trim().to_string(), value.to_string());
+        }
+
+        let mut spec = DeploymentSpec {
+            id: String::new(),
+            namespace: self.namespace.trim().to_string(),
+            name: name.to_string(),
+            source: source.to_string(),
+            trigger,
+            instances: InstanceConstraints { min, max },
+            resources: ResourceLimits {
+                memory_bytes: field::<u64>("memory", &self.memory_mb, 64)?.saturating_mul(1024 * 1024),
+                cpu_weight: field("CPU weight", &self.cpu_weight, 100)?,
+                execution_budget_ms: match self.execution_budget_ms.trim() {
+                    "" => None,
+                    budget => Some(field("execution budget", budget, 0)?),
+                },
+            },
+            scaling,
+            health: None,
+            shims: ShimsEnabled {
+                timezone: self.shim_timezone,
+                dev_urandom: self.shim_dev_urandom,
+                dns: self.shim_dns,
+                signals: self.shim_signals,
+                database_proxy: self.shim_database_proxy,
+            },
+            env,
+            created_at: now,
+            updated_at: now,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        };
+        spec.fill_defaults();
+        Ok(spec)
+    }
+}
+
+fn text_or(value: &str, default: &str) -> String {
+    match value.trim() {
+        "" => default.to_string(),
+        value => value.to_string(),
+    }
+}
+
+#[derive(askama::Template)]
+#[template(path = "_partials/spec_preview.html")]
+struct SpecPreviewTemplate {
+    id: String,
+    json: String,
+}
+
+/// POST /dashboard/deployments/new/preview — the wizard's spec as JSON,
+/// for the review step to show and submit to `/api/v1/apply`.
+pub async fn preview_deployment(
+    axum::extract::Form(form): axum::extract::Form<DeploymentForm>,
+) -> impl IntoResponse {
+    let spec = match form.to_spec(SystemClock.epoch_secs()) {
+        Ok(spec) => spec,
+        Err(e) => {
+            return Html(format!(
+                r#"<div class="text-amber-400 text-sm font-mono">{}</div>"#,
+                e
+            ))
+            .into_response()
+        }
+    };
+    let json = serde_json::to_string_pretty(&spec).unwrap_or_default();
+    match askama::Template::render(&SpecPreviewTemplate { id: spec.id, json }) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => Html(format!(
+            r#"<div class="text-rose-400 text-sm font-mono">Template error: {}</div>"#,
+            e
+        ))
+        .into_response(),
+    }
+}
+
 // ── Delete Deployment ───────────────────────────────────────────
 
 pub async fn delete_deployment(
@@ -174,6 +605,8 @@ pub async fn delete_deployment(
     match state.store.delete_deployment(&id) {
         Ok(true) => {
             let _ = state.store.delete_instances_for_deployment(&id);
+            let _ = state.store.delete_flags(&id);
+            let _ = state.store.delete_deployment_config(&id);
             Redirect::to("/dashboard/deployments").into_response()
         }
         Ok(false) => (
@@ -231,7 +664,7 @@ pub async fn deploy_demo(
         namespace: "demo".to_string(),
         name: "wastebin-density".to_string(),
         source: "file://demos/wastebin/wastebin-demo.wasm".to_string(),
-        trigger: TriggerConfig::Http { port: Some(8080) },
+        trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
         instances: InstanceConstraints {
             min: instance_count as u32,
             max: (instance_count as u32) * 2,
@@ -239,6 +672,7 @@ pub async fn deploy_demo(
         resources: ResourceLimits {
             memory_bytes: 16 * 1024 * 1024,
             cpu_weight: 50,
+            execution_budget_ms: None,
         },
         scaling: None,
         health: None,
@@ -252,6 +686,12 @@ pub async fn deploy_demo(
         env,
         created_at: now,
         updated_at: now,
+        priority: None,
+        min_available: None,
+        labels: Default::default(),
+        paused: false,
+        secrets: Vec::new(),
+        config_maps: Vec::new(),
     };
 
     if let Err(e) = state.store.put_deployment(&spec) {
@@ -326,11 +766,12 @@ mod tests {
             namespace: ns.to_string(),
             name: name.to_string(),
             source: "file://test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -338,6 +779,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -377,6 +824,56 @@ mod tests {
         assert_eq!(resp.status(), 200); // Returns HTML warning, not 400
     }
 
+    #[tokio::test]
+    async fn set_and_remove_flag_action() {
+        let state = test_state();
+        state
+            .store
+            .put_deployment(&test_deployment("default", "api"))
+            .unwrap();
+        let form = |kind: &str, value: &str| FlagForm {
+            name: "new-checkout".to_string(),
+            kind: kind.to_string(),
+            value: value.to_string(),
+        };
+
+        let resp = set_flag(
+            State(state.clone()),
+            Path("default/api".to_string()),
+            axum::extract::Form(form("percentage", "25%")),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), 200);
+        let flags = state.store.get_flags("default/api").unwrap().unwrap();
+        assert_eq!(
+            flags.set.flags["new-checkout"],
+            FeatureFlag::Percentage { percent: 25 }
+        );
+
+        // Out-of-range values are rejected and leave the flag unchanged.
+        set_flag(
+            State(state.clone()),
+            Path("default/api".to_string()),
+            axum::extract::Form(form("percentage", "250")),
+        )
+        .await;
+        let flags = state.store.get_flags("default/api").unwrap().unwrap();
+        assert_eq!(
+            flags.set.flags["new-checkout"],
+            FeatureFlag::Percentage { percent: 25 }
+        );
+
+        set_flag(
+            State(state.clone()),
+            Path("default/api".to_string()),
+            axum::extract::Form(form("boolean", "")),
+        )
+        .await;
+        let flags = state.store.get_flags("default/api").unwrap().unwrap();
+        assert!(flags.set.flags.is_empty());
+    }
+
     #[tokio::test]
     async fn delete_existing_deployment() {
         let state = test_state();
@@ -413,6 +910,7 @@ mod tests {
             axum::extract::Form(RolloutForm {
                 strategy: "rolling".to_string(),
                 new_version: "v2".to_string(),
+                ..Default::default()
             }),
         )
         .await;
@@ -439,6 +937,7 @@ mod tests {
             axum::extract::Form(RolloutForm {
                 strategy: "rolling".to_string(),
                 new_version: "v2".to_string(),
+                ..Default::default()
             }),
         )
         .await;
@@ -454,6 +953,98 @@ mod tests {
         assert_eq!(resp.status(), 200);
     }
 
+    #[tokio::test]
+    async fn start_canary_from_rollouts_page_and_roll_back() {
+        let state = test_state();
+        state
+            .store
+            .put_deployment(&test_deployment("default", "api"))
+            .unwrap();
+        let form = |percent: &str| RolloutForm {
+            deployment: "default/api".to_string(),
+            strategy: "canary".to_string(),
+            new_version: "v2".to_string(),
+            canary_percent: percent.to_string(),
+            latency_threshold_ms: "250".to_string(),
+            ..Default::default()
+        };
+
+        start_rollout_for(State(state.clone()), axum::extract::Form(form("150"))).await;
+        assert!(state.rollouts.read().await.is_empty());
+
+        start_rollout_for(State(state.clone()), axum::extract::Form(form("25%"))).await;
+        match &state.rollouts.read().await["default/api"].strategy {
+            RolloutStrategy::Canary(cfg) => {
+                assert_eq!((cfg.traffic_percent, cfg.latency_threshold_ms), (25, 250));
+                assert_eq!(cfg.observation_secs, CanaryConfig::default().observation_secs);
+            }
+            other => panic!("expected a canary, got {other:?}"),
+        }
+
+        // A second start while the first runs leaves it in place.
+        start_rollout_for(
+            State(state.clone()),
+            axum::extract::Form(RolloutForm {
+                strategy: "blue_green".to_string(),
+                ..form("")
+            }),
+        )
+        .await;
+        assert!(matches!(
+            state.rollouts.read().await["default/api"].strategy,
+            RolloutStrategy::Canary(_)
+        ));
+
+        rollback_rollout(State(state.clone()), Path("default/api".to_string())).await;
+        assert!(matches!(
+            state.rollouts.read().await["default/api"].phase,
+            warpgrid_rollout::RolloutPhase::RolledBack { .. }
+        ));
+    }
+
+    #[test]
+    fn deployment_form_builds_spec() {
+        let form = DeploymentForm {
+            name: "api".to_string(),
+            source: "file:///artifacts/api.wasm".to_string(),
+            shim_dns: true,
+            memory_mb: "128".to_string(),
+            instances_min: "2".to_string(),
+            instances_max: "6".to_string(),
+            autoscale: true,
+            hosts: "api.internal, api.example.com".to_string(),
+            env: "LOG_LEVEL=info\n# comment\nDSN=postgres://db?a=b".to_string(),
+            ..Default::default()
+        };
+        let spec = form.to_spec(1000).unwrap();
+        assert_eq!(spec.id, "default/api");
+        assert_eq!((spec.instances.min, spec.instances.max), (2, 6));
+        assert_eq!(spec.resources.memory_bytes, 128 * 1024 * 1024);
+        assert!(spec.shims.dns && !spec.shims.signals);
+        assert_eq!(spec.scaling.unwrap().scale_down_window, "5m");
+        assert_eq!(spec.env["DSN"], "postgres://db?a=b");
+        match spec.trigger {
+            TriggerConfig::Http { hosts, port, .. } => {
+                assert_eq!(hosts, ["api.internal", "api.example.com"]);
+                assert_eq!(port, None);
+            }
+            other => panic!("expected an HTTP trigger, got {other:?}"),
+        }
+
+        let bad = |form: DeploymentForm| form.to_spec(1000).unwrap_err();
+        assert!(bad(DeploymentForm { instances_min: "3".into(), instances_max: "2".into(), ..form_for("api") }).contains("min <= max"));
+        assert!(bad(DeploymentForm { trigger: "cron".into(), ..form_for("api") }).contains("schedule"));
+        assert!(bad(DeploymentForm { env: "NOT_A_PAIR".into(), ..form_for("api") }).contains("KEY=value"));
+    }
+
+    fn form_for(name: &str) -> DeploymentForm {
+        DeploymentForm {
+            name: name.to_string(),
+            source: "file:///artifacts/api.wasm".to_string(),
+            ..Default::default()
+        }
+    }
+
     #[tokio::test]
     async fn deploy_demo_creates_deployment() {
         let state = test_state();
@@ -0,0 +1,60 @@
+//! Static assets served under `/dashboard/static/`.
+//!
+//! `scripts/vendor-dashboard-assets.sh` writes the compiled stylesheet and
+//! the HTMX scripts into `assets/`, where they are committed, and the build
+//! embeds them, so the dashboard needs no outbound network access. Pages
+//! never load them from a CDN: a build without them (see [`EMBEDDED`])
+//! warns, and its pages have no styles or scripts.
+//! Fonts are never fetched: Outfit and IBM Plex Mono are used when installed
+//! and the system UI and monospace fonts otherwise.
+
+use axum::extract::Path;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+/// Whether Tailwind and HTMX were vendored into this build.
+pub const EMBEDDED: bool = cfg!(embedded_assets);
+
+/// `(file name, content type, body)` for every servable asset.
+const ASSETS: &[(&str, &str, &[u8])] = &[
+    #[cfg(embedded_assets)]
+    ("tailwind.css", "text/css; charset=utf-8", include_bytes!("../assets/tailwind.css")),
+    #[cfg(embedded_assets)]
+    ("htmx.min.js", "text/javascript; charset=utf-8", include_bytes!("../assets/htmx.min.js")),
+    #[cfg(embedded_assets)]
+    ("htmx-ext-sse.js", "text/javascript; charset=utf-8", include_bytes!("../assets/htmx-ext-sse.js")),
+];
+
+/// Serve an embedded asset by file name.
+pub async fn asset(Path(file): Path<String>) -> Response {
+    match ASSETS.iter().find(|(name, _, _)| *name == file) {
+        Some((_, content_type, body)) => (
+            [
+                (header::CONTENT_TYPE, *content_type),
+                (header::CACHE_CONTROL, "public, max-age=3600"),
+            ],
+            *body,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_known_assets_only() {
+        let resp = asset(Path("tailwind.css".to_string())).await;
+        let expected = if EMBEDDED { StatusCode::OK } else { StatusCode::NOT_FOUND };
+        assert_eq!(resp.status(), expected);
+
+        // Only the Tailwind Play CDN read the theme; it is not served.
+        let resp = asset(Path("tailwind.config.js".to_string())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = asset(Path("../Cargo.toml".to_string())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
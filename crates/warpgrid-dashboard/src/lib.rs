@@ -1,8 +1,9 @@
 //! warpgrid-dashboard — server-rendered web UI for WarpGrid.
 //!
 //! Provides axum route handlers that render Askama HTML templates for the
-//! WarpGrid dashboard. Uses Tailwind CSS (CDN) for styling and HTMX (CDN)
-//! for live updates via polling.
+//! WarpGrid dashboard. Uses Tailwind CSS for styling and HTMX for live
+//! updates, pushed over server-sent events ([`live`]) or polled. Both are
+//! embedded in the binary and nothing is loaded from a CDN; see [`assets`].
 //!
 //! # Routes
 //!
@@ -10,19 +11,33 @@
 //! |---|---|
 //! | `/dashboard/` | Cluster overview |
 //! | `/dashboard/deployments` | Deployment list |
+//! | `/dashboard/deployments/new` | Deployment creation wizard |
+//! | `/dashboard/artifacts`, `/dashboard/apply` | Wizard upload and apply, mounted by warpgrid-api |
 //! | `/dashboard/deployments/:id` | Deployment detail |
-//! | `/dashboard/nodes` | Node topology |
-//! | `/dashboard/nodes/:id` | Node detail |
-//! | `/dashboard/rollouts` | Rollout tracker |
+//! | `/dashboard/nodes` | Node list |
+//! | `/dashboard/nodes/:id` | Node detail with cordon, drain, and evacuate actions |
+//! | `/dashboard/nodes/:id/{cordon,uncordon,drain}` | Node actions, mounted by warpgrid-api |
+//! | `/dashboard/topology` | Cluster topology graph |
+//! | `/dashboard/rollouts` | Rollout tracker; `POST` starts a rollout |
+//! | `/dashboard/rollouts/:id/rollback` | Roll back an active rollout |
+//! | `/dashboard/events` | Cluster event timeline, filterable by deployment, node, and kind |
+//! | `/dashboard/login` | Sign in with an API token; `POST /dashboard/logout` signs out |
 //! | `/dashboard/_overview_stats` | HTMX partial: overview stats |
 //! | `/dashboard/_deployments_table` | HTMX partial: deployment rows |
 //! | `/dashboard/_deployment_instances/:id` | HTMX partial: instance table |
 //! | `/dashboard/_rollout_cards` | HTMX partial: rollout cards |
 //! | `/dashboard/_node_cards` | HTMX partial: node cards |
+//! | `/dashboard/_node_detail/:id` | HTMX partial: node status, gauges, and instances |
+//! | `/dashboard/_events` | Server-sent events: overview, deployment, and rollout partials as they change |
+//! | `/dashboard/static/:file` | Embedded stylesheet and scripts |
 
 pub mod actions;
+pub mod assets;
+pub mod live;
 pub mod pages;
 pub mod partials;
+pub mod session;
+pub mod topology;
 pub mod views;
 
 use std::collections::HashMap;
@@ -46,15 +61,24 @@ pub struct DashboardState {
 
 /// Build the dashboard router.
 pub fn dashboard_router(state: DashboardState) -> Router {
+    if !assets::EMBEDDED {
+        tracing::warn!("dashboard assets were not vendored into this build; pages load without styles or scripts");
+    }
     Router::new()
         // Page routes
         .route("/", get(pages::overview))
         .route("/deployments", get(pages::deployments))
+        .route("/deployments/new", get(pages::new_deployment))
+        .route("/deployments/new/preview", post(actions::preview_deployment))
         .route("/deployments/{id}", get(pages::deployment_detail))
         .route("/nodes", get(pages::nodes))
         .route("/nodes/{id}", get(pages::node_detail))
-        .route("/rollouts", get(pages::rollouts))
+        .route("/topology", get(pages::topology))
+        .route("/rollouts", get(pages::rollouts).post(actions::start_rollout_for))
+        .route("/events", get(pages::events))
         .route("/density-demo", get(pages::density_demo))
+        .route("/login", get(session::login_form).post(session::login))
+        .route("/logout", post(session::logout))
         // HTMX partial routes
         .route("/_overview_stats", get(partials::overview_stats))
         .route("/_deployments_table", get(partials::deployments_table))
@@ -64,7 +88,10 @@ pub fn dashboard_router(state: DashboardState) -> Router {
         )
         .route("/_rollout_cards", get(partials::rollout_cards))
         .route("/_node_cards", get(partials::node_cards))
+        .route("/_node_detail/{id}", get(partials::node_detail))
         .route("/_density_stats", get(partials::density_stats))
+        .route("/_events", get(live::events))
+        .route("/static/{file}", get(assets::asset))
         // Action routes
         .route("/density-demo/deploy", post(actions::deploy_demo))
         .route("/density-demo/teardown", post(actions::teardown_demo))
@@ -76,11 +103,13 @@ pub fn dashboard_router(state: DashboardState) -> Router {
             "/deployments/{id}/rollout",
             post(actions::start_rollout),
         )
+        .route("/deployments/{id}/flags", post(actions::set_flag))
         .route(
             "/deployments/{id}",
             delete(actions::delete_deployment),
         )
         .route("/rollouts/{id}/pause", post(actions::pause_rollout))
         .route("/rollouts/{id}/resume", post(actions::resume_rollout))
+        .route("/rollouts/{id}/rollback", post(actions::rollback_rollout))
         .with_state(state)
 }
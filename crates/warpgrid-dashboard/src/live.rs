@@ -0,0 +1,144 @@
+//! Live page updates over server-sent events.
+//!
+//! `GET /dashboard/_events?views=overview_stats,deployments_table` streams
+//! the named partials as HTML, one event per partial named after it, for
+//! the HTMX SSE extension to swap in (`sse-swap="overview_stats"`). A
+//! partial is sent on connect and then only when its HTML changes.
+//!
+//! Partials are re-rendered when the state store changes a table they
+//! show, and every [`REFRESH`] regardless, since metrics and rollout
+//! progress are not journaled. An idle cluster costs one render per
+//! client per [`REFRESH`], and nothing in between.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::Stream;
+use warpgrid_state::tables::{DEPLOYMENTS, INSTANCES, NODES};
+use warpgrid_state::watch;
+
+use crate::DashboardState;
+use crate::partials;
+
+/// Longest a client waits for a re-render while nothing it watches changes.
+const REFRESH: Duration = Duration::from_secs(5);
+
+/// Tables the partials are rendered from.
+const WATCHED: [&str; 3] = [DEPLOYMENTS, INSTANCES, NODES];
+
+/// Partials a page can subscribe to.
+const VIEWS: [&str; 3] = ["overview_stats", "deployments_table", "rollout_cards"];
+
+/// Query parameters for `/_events`.
+#[derive(Debug, serde::Deserialize)]
+pub struct EventsQuery {
+    /// Comma-separated partial names; all of [`VIEWS`] when absent.
+    pub views: Option<String>,
+}
+
+/// GET /dashboard/_events?views=
+pub async fn events(
+    State(state): State<DashboardState>,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let views: Vec<&'static str> = match &query.views {
+        Some(names) => VIEWS.into_iter().filter(|view| names.split(',').any(|name| name == *view)).collect(),
+        None => VIEWS.to_vec(),
+    };
+    // Subscribed before the first render, so no change after it is missed.
+    let changes = Box::pin(state.store.watch_any(&WATCHED));
+    let feed = Feed { state, views, sent: HashMap::new() };
+    let stream = futures_util::stream::unfold(
+        (feed, changes, VecDeque::new(), true),
+        |(mut feed, mut changes, mut pending, mut first)| async move {
+            loop {
+                if let Some((view, html)) = pending.pop_front() {
+                    let event = Event::default().event(view).data(html);
+                    return Some((Ok(event), (feed, changes, pending, first)));
+                }
+                if !first {
+                    watch::changed(&mut changes, REFRESH).await;
+                }
+                first = false;
+                pending.extend(feed.poll().await);
+            }
+        },
+    );
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// What one client has been sent.
+struct Feed {
+    state: DashboardState,
+    views: Vec<&'static str>,
+    sent: HashMap<&'static str, String>,
+}
+
+impl Feed {
+    /// Partials (name, HTML) that changed since they were last sent.
+    async fn poll(&mut self) -> Vec<(&'static str, String)> {
+        let mut changed = Vec::new();
+        for &view in &self.views {
+            let html = render(&self.state, view).await;
+            if self.sent.get(view) != Some(&html) {
+                self.sent.insert(view, html.clone());
+                changed.push((view, html));
+            }
+        }
+        changed
+    }
+}
+
+async fn render(state: &DashboardState, view: &str) -> String {
+    let state = State(state.clone());
+    match view {
+        "overview_stats" => partials::overview_stats(state).await.0,
+        "deployments_table" => partials::deployments_table(state).await.0,
+        "rollout_cards" => partials::rollout_cards(state).await.0,
+        _ => unreachable!("views are filtered against VIEWS"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::tests::{test_deployment, test_state};
+
+    #[tokio::test]
+    async fn partials_are_sent_once_until_they_change() {
+        let state = test_state();
+        let mut feed = Feed {
+            state: state.clone(),
+            views: vec!["overview_stats", "deployments_table"],
+            sent: HashMap::new(),
+        };
+        let views =
+            |changed: Vec<(&'static str, String)>| changed.into_iter().map(|(view, _)| view).collect::<Vec<_>>();
+
+        assert_eq!(views(feed.poll().await), ["overview_stats", "deployments_table"]);
+        assert!(feed.poll().await.is_empty());
+
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        assert_eq!(views(feed.poll().await), ["overview_stats", "deployments_table"]);
+        assert!(feed.poll().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_change_is_pushed_without_waiting_for_the_refresh() {
+        use axum::response::IntoResponse;
+        use futures_util::StreamExt;
+
+        let state = test_state();
+        let query = EventsQuery { views: Some("deployments_table".to_string()) };
+        let response = events(State(state.clone()), Query(query)).await.into_response();
+        let mut events = response.into_body().into_data_stream();
+        assert!(events.next().await.is_some());
+
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let pushed = tokio::time::timeout(REFRESH / 2, events.next()).await;
+        assert!(matches!(pushed, Ok(Some(Ok(_)))));
+    }
+}
@@ -4,12 +4,14 @@
 //! an Askama template. HTMX partials are in `partials.rs`.
 
 use askama::Template;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::response::Html;
 
 use warpgrid_rollout::RolloutPhase;
+use warpgrid_state::{CLUSTER_EVENT_RETENTION, ClusterEvent, ClusterEventKind};
 
 use crate::DashboardState;
+use crate::topology::{DeploymentEntry, TopologyView};
 use crate::views::*;
 
 fn render<T: Template>(tmpl: T) -> Html<String> {
@@ -135,6 +137,40 @@ pub async fn deployments(State(state): State<DashboardState>) -> Html<String> {
     })
 }
 
+// ── New Deployment ──────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "deployment_new.html")]
+struct NewDeploymentTemplate {
+    active_page: &'static str,
+    cluster_mode: String,
+    /// Sources of existing deployments, offered as artifacts to reuse.
+    sources: Vec<String>,
+}
+
+pub async fn new_deployment(State(state): State<DashboardState>) -> Html<String> {
+    let nodes = state.store.list_nodes().unwrap_or_default();
+    let sources: std::collections::BTreeSet<String> = state
+        .store
+        .list_deployments()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|spec| spec.source)
+        .collect();
+
+    let cluster_mode = if nodes.is_empty() {
+        "Standalone".to_string()
+    } else {
+        format!("Cluster ({})", nodes.len())
+    };
+
+    render(NewDeploymentTemplate {
+        active_page: "deployments",
+        cluster_mode,
+        sources: sources.into_iter().collect(),
+    })
+}
+
 // ── Deployment Detail ───────────────────────────────────────────
 
 #[derive(Template)]
@@ -146,6 +182,8 @@ struct DeploymentDetailTemplate {
     instances: Vec<InstanceView>,
     metrics: Vec<MetricsRow>,
     rollout: Option<RolloutView>,
+    health: Option<HealthBadge>,
+    flags: Vec<FlagView>,
 }
 
 pub async fn deployment_detail(
@@ -166,10 +204,19 @@ pub async fn deployment_detail(
 
     let instance_views: Vec<InstanceView> = instances.iter().map(InstanceView::from_state).collect();
     let metrics = build_metrics_rows(&snapshots);
+    let flags = match state.store.get_flags(&id) {
+        Ok(Some(flags)) => FlagView::from_flags(&flags),
+        _ => Vec::new(),
+    };
 
-    let rollout = {
+    let (rollout, health) = {
         let rollouts = state.rollouts.read().await;
-        rollouts.get(&id).map(RolloutView::from_rollout)
+        let rollout = rollouts.get(&id);
+        let health = spec.as_ref().map(|s| {
+            let health = warpgrid_health::summary::summarize(s, &instances, snapshots.first(), rollout);
+            HealthBadge::from_health(&health)
+        });
+        (rollout.map(RolloutView::from_rollout), health)
     };
 
     let deployment_view = match spec {
@@ -185,11 +232,12 @@ pub async fn deployment_detail(
                     namespace: "unknown".to_string(),
                     name: id.clone(),
                     source: "unknown".to_string(),
-                    trigger: warpgrid_state::TriggerConfig::Http { port: None },
+                    trigger: warpgrid_state::TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
                     instances: warpgrid_state::InstanceConstraints { min: 0, max: 0 },
                     resources: warpgrid_state::ResourceLimits {
                         memory_bytes: 0,
                         cpu_weight: 0,
+                        execution_budget_ms: None,
                     },
                     scaling: None,
                     health: None,
@@ -197,6 +245,12 @@ pub async fn deployment_detail(
                     env: std::collections::HashMap::new(),
                     created_at: 0,
                     updated_at: 0,
+                    priority: None,
+                    min_available: None,
+                    labels: Default::default(),
+                    paused: false,
+                    secrets: Vec::new(),
+                    config_maps: Vec::new(),
                 },
                 &instances,
                 None,
@@ -217,6 +271,8 @@ pub async fn deployment_detail(
         instances: instance_views,
         metrics,
         rollout,
+        health,
+        flags,
     })
 }
 
@@ -283,7 +339,26 @@ pub async fn node_detail(
     Path(id): Path<String>,
 ) -> Html<String> {
     let node_infos = state.store.list_nodes().unwrap_or_default();
-    let node_info = state.store.get_node(&id).unwrap_or(None);
+    let (node, instances) = node_with_instances(&state, &id);
+
+    let cluster_mode = if node_infos.is_empty() {
+        "Standalone".to_string()
+    } else {
+        format!("Cluster ({})", node_infos.len())
+    };
+
+    render(NodeDetailTemplate {
+        active_page: "nodes",
+        cluster_mode,
+        node,
+        instances,
+    })
+}
+
+/// The node `id` and the instances placed on it; an unknown node renders
+/// as an empty one so its instances stay visible.
+pub(crate) fn node_with_instances(state: &DashboardState, id: &str) -> (NodeView, Vec<InstanceView>) {
+    let node_info = state.store.get_node(id).unwrap_or(None);
 
     // Get all instances on this node
     let instances_on_node: Vec<InstanceView> = state
@@ -305,7 +380,7 @@ pub async fn node_detail(
         Some(ref n) => NodeView::from_node(n, instances_on_node.len()),
         None => NodeView::from_node(
             &warpgrid_state::NodeInfo {
-                id: id.clone(),
+                id: id.to_string(),
                 address: "unknown".to_string(),
                 port: 0,
                 capacity_memory_bytes: 0,
@@ -314,22 +389,151 @@ pub async fn node_detail(
                 used_cpu_weight: 0,
                 labels: std::collections::HashMap::new(),
                 last_heartbeat: 0,
+                pressure_until: None,
+                replica_revision: None,
+                calibrated_cpu_weight: None,
+                cordoned: false,
             },
             instances_on_node.len(),
         ),
     };
+    (node_view, instances_on_node)
+}
 
-    let cluster_mode = if node_infos.is_empty() {
+// ── Topology ────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "topology.html")]
+struct TopologyTemplate {
+    active_page: &'static str,
+    cluster_mode: String,
+    graph: TopologyView,
+}
+
+pub async fn topology(State(state): State<DashboardState>) -> Html<String> {
+    let specs = state.store.list_deployments().unwrap_or_default();
+    let nodes = state.store.list_nodes().unwrap_or_default();
+
+    let instances: Vec<_> = specs
+        .iter()
+        .map(|spec| state.store.list_instances_for_deployment(&spec.id).unwrap_or_default())
+        .collect();
+    let health: Vec<_> = {
+        let rollouts = state.rollouts.read().await;
+        specs
+            .iter()
+            .zip(&instances)
+            .map(|(spec, instances)| {
+                let metrics = state.store.list_metrics_for_deployment(&spec.id, 1).unwrap_or_default();
+                warpgrid_health::summary::summarize(spec, instances, metrics.first(), rollouts.get(&spec.id))
+            })
+            .collect()
+    };
+    let entries: Vec<DeploymentEntry<'_>> = specs
+        .iter()
+        .zip(&instances)
+        .zip(&health)
+        .map(|((spec, instances), health)| DeploymentEntry { spec, instances, health })
+        .collect();
+
+    let cluster_mode = if nodes.is_empty() {
         "Standalone".to_string()
     } else {
-        format!("Cluster ({})", node_infos.len())
+        format!("Cluster ({})", nodes.len())
     };
 
-    render(NodeDetailTemplate {
-        active_page: "nodes",
+    render(TopologyTemplate {
+        active_page: "topology",
+        cluster_mode,
+        graph: TopologyView::build(&entries, &nodes),
+    })
+}
+
+// ── Events ──────────────────────────────────────────────────────
+
+/// Events shown per page.
+const EVENTS_PER_PAGE: usize = 100;
+
+/// Query parameters for `/dashboard/events`; blank filters match everything.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct EventsFilter {
+    pub deployment: String,
+    pub node: String,
+    /// A [`ClusterEventKind`] name, e.g. `node_lost`.
+    pub kind: String,
+    /// Only events older than this sequence: the next page.
+    pub before: Option<u64>,
+}
+
+impl EventsFilter {
+    fn matches(&self, event: &ClusterEvent) -> bool {
+        (self.deployment.is_empty() || event.deployment_id.as_deref() == Some(self.deployment.as_str()))
+            && (self.node.is_empty() || event.node_id.as_deref() == Some(self.node.as_str()))
+            && (self.kind.is_empty() || event.kind.as_str() == self.kind)
+            && self.before.is_none_or(|before| event.sequence < before)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "events.html")]
+struct EventsTemplate {
+    active_page: &'static str,
+    cluster_mode: String,
+    days: Vec<EventDay>,
+    event_count: usize,
+    filter: EventsFilter,
+    deployment_ids: Vec<String>,
+    node_ids: Vec<String>,
+    kinds: Vec<&'static str>,
+    /// Link to the next, older page.
+    older_href: Option<String>,
+}
+
+pub async fn events(
+    State(state): State<DashboardState>,
+    Query(filter): Query<EventsFilter>,
+) -> Html<String> {
+    let nodes = state.store.list_nodes().unwrap_or_default();
+    let mut events = state
+        .store
+        .list_events(0, CLUSTER_EVENT_RETENTION as usize, |e| filter.matches(e))
+        .unwrap_or_default();
+    let more = events.len() > EVENTS_PER_PAGE;
+    events.drain(..events.len().saturating_sub(EVENTS_PER_PAGE));
+    events.reverse();
+
+    let older_href = match events.last() {
+        Some(oldest) if more => Some(format!(
+            "/dashboard/events?deployment={}&node={}&kind={}&before={}",
+            filter.deployment, filter.node, filter.kind, oldest.sequence
+        )),
+        _ => None,
+    };
+    let deployment_ids = state
+        .store
+        .list_deployments()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|spec| spec.id)
+        .collect();
+
+    let cluster_mode = if nodes.is_empty() {
+        "Standalone".to_string()
+    } else {
+        format!("Cluster ({})", nodes.len())
+    };
+
+    render(EventsTemplate {
+        active_page: "events",
         cluster_mode,
-        node: node_view,
-        instances: instances_on_node,
+        days: group_events_by_day(&events),
+        event_count: events.len(),
+        filter,
+        deployment_ids,
+        node_ids: nodes.into_iter().map(|node| node.id).collect(),
+        kinds: ClusterEventKind::ALL.iter().map(|kind| kind.as_str()).collect(),
+        older_href,
     })
 }
 
@@ -342,10 +546,19 @@ struct RolloutsTemplate {
     cluster_mode: String,
     active_rollouts: Vec<RolloutView>,
     completed_rollouts: Vec<RolloutView>,
+    /// Deployments the start form can roll out.
+    deployment_ids: Vec<String>,
 }
 
 pub async fn rollouts(State(state): State<DashboardState>) -> Html<String> {
     let nodes = state.store.list_nodes().unwrap_or_default();
+    let deployment_ids: Vec<String> = state
+        .store
+        .list_deployments()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|spec| spec.id)
+        .collect();
 
     let (active, completed) = {
         let rollouts = state.rollouts.read().await;
@@ -366,11 +579,12 @@ pub async fn rollouts(State(state): State<DashboardState>) -> Html<String> {
         cluster_mode,
         active_rollouts: active,
         completed_rollouts: completed,
+        deployment_ids,
     })
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use axum::response::IntoResponse;
     use std::collections::HashMap;
@@ -378,7 +592,7 @@ mod tests {
     use tokio::sync::RwLock;
     use warpgrid_state::*;
 
-    fn test_state() -> DashboardState {
+    pub(crate) fn test_state() -> DashboardState {
         let store = StateStore::open_in_memory().unwrap();
         DashboardState {
             store,
@@ -386,17 +600,18 @@ mod tests {
         }
     }
 
-    fn test_deployment(ns: &str, name: &str) -> DeploymentSpec {
+    pub(crate) fn test_deployment(ns: &str, name: &str) -> DeploymentSpec {
         DeploymentSpec {
             id: format!("{ns}/{name}"),
             namespace: ns.to_string(),
             name: name.to_string(),
             source: "file://test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -404,6 +619,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -418,6 +639,11 @@ mod tests {
         let resp = overview(State(state)).await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), 200);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        // Styles and scripts are self-hosted, never loaded from a CDN.
+        assert!(html.contains(r#"src="/dashboard/static/htmx.min.js""#));
+        assert!(!html.contains("cdn.tailwindcss.com") && !html.contains("unpkg.com"));
     }
 
     #[tokio::test]
@@ -460,6 +686,10 @@ mod tests {
         .await;
         let resp = resp.into_response();
         assert_eq!(resp.status(), 200);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"data-chart="p99""#));
+        assert!(html.contains("/metrics/range?start="));
     }
 
     #[tokio::test]
@@ -485,6 +715,10 @@ mod tests {
                 used_cpu_weight: 300,
                 labels: HashMap::new(),
                 last_heartbeat: 1000,
+                pressure_until: None,
+                replica_revision: None,
+                calibrated_cpu_weight: None,
+                cordoned: false,
             })
             .unwrap();
 
@@ -501,6 +735,61 @@ mod tests {
         assert_eq!(resp.status(), 200);
     }
 
+    #[tokio::test]
+    async fn new_deployment_wizard_offers_known_sources() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let html = new_deployment(State(state)).await.0;
+        assert!(html.contains(r#"<option value="file://test.wasm">"#));
+        assert!(html.contains(r#"hx-post="/dashboard/deployments/new/preview""#));
+        assert!(html.contains("/dashboard/apply?dry_run=true"));
+        assert!(!html.contains("/api/v1/"));
+    }
+
+    #[tokio::test]
+    async fn topology_page_renders_graph() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let html = topology(State(state)).await.0;
+        assert!(html.contains(r#"data-deployment="default/api""#));
+        assert!(html.contains("<svg"));
+    }
+
+    #[tokio::test]
+    async fn events_page_filters_and_links() {
+        let state = test_state();
+        state
+            .store
+            .record_event(&ClusterEvent::new(ClusterEventKind::DeploymentScaled, 1000, "scaled to 3").for_deployment("default/api"))
+            .unwrap();
+        state
+            .store
+            .record_event(&ClusterEvent::new(ClusterEventKind::NodeLost, 1001, "heartbeat missed").for_node("node-1"))
+            .unwrap();
+
+        let filter = EventsFilter { deployment: "default/api".to_string(), ..Default::default() };
+        let html = events(State(state.clone()), Query(filter)).await.0;
+        assert!(html.contains("scaled to 3"));
+        assert!(html.contains(r#"href="/dashboard/deployments/default/api""#));
+        assert!(!html.contains("heartbeat missed"));
+
+        let filter = EventsFilter { kind: "node_lost".to_string(), ..Default::default() };
+        let html = events(State(state), Query(filter)).await.0;
+        assert!(html.contains(r#"href="/dashboard/nodes/node-1""#));
+        assert!(!html.contains("scaled to 3"));
+    }
+
+    #[tokio::test]
+    async fn rollouts_page_offers_strategy_forms() {
+        let state = test_state();
+        state.store.put_deployment(&test_deployment("default", "api")).unwrap();
+        let html = rollouts(State(state)).await.0;
+        assert!(html.contains(r#"<option value="default/api">"#));
+        assert!(html.contains(r#"name="batch_size""#));
+        assert!(html.contains(r#"name="canary_percent""#));
+        assert!(html.contains("No active rollouts."));
+    }
+
     #[tokio::test]
     async fn node_detail_renders() {
         let state = test_state();
@@ -516,6 +805,10 @@ mod tests {
                 used_cpu_weight: 0,
                 labels: HashMap::new(),
                 last_heartbeat: 1000,
+                pressure_until: None,
+                replica_revision: None,
+                calibrated_cpu_weight: None,
+                cordoned: false,
             })
             .unwrap();
 
@@ -546,11 +839,12 @@ mod tests {
             namespace: "demo".to_string(),
             name: "wastebin-density".to_string(),
             source: "file://demos/wastebin/wastebin-demo.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 10, max: 20 },
             resources: ResourceLimits {
                 memory_bytes: 16 * 1024 * 1024,
                 cpu_weight: 50,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -558,6 +852,12 @@ mod tests {
             env: HashMap::new(),
             created_at: now,
             updated_at: now,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         };
         state.store.put_deployment(&spec).unwrap();
 
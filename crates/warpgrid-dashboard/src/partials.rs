@@ -170,6 +170,23 @@ pub async fn node_cards(State(state): State<DashboardState>) -> Html<String> {
     render(NodeCardsPartial { nodes: node_views })
 }
 
+// ── Node Detail ─────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "_partials/node_detail.html")]
+struct NodeDetailPartial {
+    node: NodeView,
+    instances: Vec<InstanceView>,
+}
+
+pub async fn node_detail(
+    State(state): State<DashboardState>,
+    Path(id): Path<String>,
+) -> Html<String> {
+    let (node, instances) = crate::pages::node_with_instances(&state, &id);
+    render(NodeDetailPartial { node, instances })
+}
+
 // ── Density Stats ───────────────────────────────────────────────
 
 #[derive(Template)]
@@ -244,6 +261,51 @@ mod tests {
         assert_eq!(resp.status(), 200);
     }
 
+    #[tokio::test]
+    async fn node_detail_partial_lists_instances_and_actions() {
+        let state = test_state();
+        state
+            .store
+            .put_node(&NodeInfo {
+                id: "node-1".to_string(),
+                address: "10.0.0.1".to_string(),
+                port: 8443,
+                capacity_memory_bytes: 1024 * 1024 * 1024,
+                capacity_cpu_weight: 1000,
+                used_memory_bytes: 0,
+                used_cpu_weight: 0,
+                labels: HashMap::new(),
+                last_heartbeat: 1000,
+                pressure_until: None,
+                replica_revision: None,
+                calibrated_cpu_weight: None,
+                cordoned: true,
+            })
+            .unwrap();
+        state.store.put_deployment(&crate::pages::tests::test_deployment("default", "api")).unwrap();
+        state
+            .store
+            .put_instance(&InstanceState {
+                id: "inst-0".to_string(),
+                deployment_id: "default/api".to_string(),
+                node_id: "node-1".to_string(),
+                status: InstanceStatus::Running,
+                health: HealthStatus::Healthy,
+                restart_count: 0,
+                memory_bytes: 64 * 1024 * 1024,
+                started_at: 1000,
+                updated_at: 1000,
+            })
+            .unwrap();
+
+        let html = node_detail(State(state), Path("node-1".to_string())).await.0;
+        assert!(html.contains("inst-0"));
+        assert!(html.contains("Cordoned"));
+        assert!(html.contains(r#"data-node-action="uncordon""#));
+        assert!(!html.contains(r#"data-node-action="cordon""#));
+        assert!(html.contains(r#"data-node-action="evacuate""#));
+    }
+
     #[tokio::test]
     async fn density_stats_partial_renders() {
         let state = test_state();
@@ -266,11 +328,12 @@ mod tests {
             namespace: "demo".to_string(),
             name: "wastebin-density".to_string(),
             source: "file://demos/wastebin/wastebin-demo.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 5, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 16 * 1024 * 1024,
                 cpu_weight: 50,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -278,6 +341,12 @@ mod tests {
             env: HashMap::new(),
             created_at: now,
             updated_at: now,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         };
         state.store.put_deployment(&spec).unwrap();
 
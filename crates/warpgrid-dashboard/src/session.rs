@@ -0,0 +1,100 @@
+//! Dashboard sign-in for nodes that require API tokens.
+//!
+//! `POST /dashboard/login` keeps an API token in the [`TOKEN_COOKIE`]
+//! cookie, scoped to `/dashboard`, `HttpOnly`, and `SameSite=Strict`, so
+//! scripts cannot read it and other sites cannot post with it. warpd's
+//! token middleware checks it on every dashboard request that changes
+//! state; pages stay readable without it. `POST /dashboard/logout` clears
+//! it, whether or not the token is still valid.
+
+use askama::Template;
+use axum::extract::{Form, State};
+use axum::http::HeaderMap;
+use axum::http::header::{COOKIE, SET_COOKIE};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+
+use crate::DashboardState;
+
+/// Cookie the signed-in API token is kept in.
+pub const TOKEN_COOKIE: &str = "warpgrid_token";
+
+/// Sign-in page, open even when tokens are required.
+pub const LOGIN_PATH: &str = "/dashboard/login";
+
+/// Sign-out, open so a stale or revoked cookie can still be cleared.
+pub const LOGOUT_PATH: &str = "/dashboard/logout";
+
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    active_page: &'static str,
+    cluster_mode: String,
+    error: Option<&'static str>,
+}
+
+fn login_page(state: &DashboardState, error: Option<&'static str>) -> Html<String> {
+    let nodes = state.store.list_nodes().unwrap_or_default().len();
+    let cluster_mode = if nodes == 0 { "Standalone".to_string() } else { format!("Cluster ({nodes})") };
+    let page = LoginTemplate { active_page: "login", cluster_mode, error };
+    Html(page.render().unwrap_or_else(|e| format!("<pre>Template error: {e}</pre>")))
+}
+
+/// GET /dashboard/login
+pub async fn login_form(State(state): State<DashboardState>) -> Html<String> {
+    login_page(&state, None)
+}
+
+#[derive(serde::Deserialize)]
+pub struct LoginForm {
+    pub token: String,
+}
+
+/// POST /dashboard/login
+///
+/// The token is only checked when it is used, by the middleware that
+/// guards the route it is used on.
+pub async fn login(State(state): State<DashboardState>, Form(form): Form<LoginForm>) -> Response {
+    let token = form.token.trim();
+    // API tokens are `wgt_` and hex; anything else could not be one and
+    // must not reach the header.
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+        return login_page(&state, Some("That is not an API token.")).into_response();
+    }
+    let cookie = format!("{TOKEN_COOKIE}={token}; Path=/dashboard; HttpOnly; SameSite=Strict");
+    ([(SET_COOKIE, cookie)], Redirect::to("/dashboard")).into_response()
+}
+
+/// POST /dashboard/logout
+pub async fn logout() -> Response {
+    let cookie = format!("{TOKEN_COOKIE}=; Path=/dashboard; HttpOnly; SameSite=Strict; Max-Age=0");
+    ([(SET_COOKIE, cookie)], Redirect::to(LOGIN_PATH)).into_response()
+}
+
+/// The token in a request's [`TOKEN_COOKIE`], if any.
+pub fn cookie_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, token)| token)
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn cookie_token_finds_the_token_among_other_cookies() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(cookie_token(&headers), None);
+        headers.insert(COOKIE, HeaderValue::from_static("theme=dark; warpgrid_token=wgt_ab12"));
+        assert_eq!(cookie_token(&headers), Some("wgt_ab12"));
+        headers.insert(COOKIE, HeaderValue::from_static("warpgrid_token="));
+        assert_eq!(cookie_token(&headers), None);
+    }
+}
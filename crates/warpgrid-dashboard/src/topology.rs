@@ -0,0 +1,332 @@
+//! Cluster topology graph for `/dashboard/topology`.
+//!
+//! Lays out deployments in a column on the left and nodes on the right,
+//! each node holding a dot per instance placed on it. Placement edges run
+//! from a deployment to every node that hosts its instances, thicker for
+//! more instances. Service-mesh edges run between deployments: the control
+//! plane keeps no call graph, so a deployment is taken to call another
+//! when one of its environment values names one of the other's exact HTTP
+//! host names (`API_URL=https://api.internal/v1` names `api.internal`).
+//!
+//! The layout is computed here in SVG user units so the template only
+//! places shapes; it stays readable for a few dozen deployments and nodes.
+
+use std::collections::{BTreeMap, HashMap};
+
+use warpgrid_health::{DeploymentHealth, DeploymentStatus};
+use warpgrid_state::{DeploymentSpec, HealthStatus, InstanceState, InstanceStatus, NodeInfo, TriggerConfig};
+
+use crate::views::NodeView;
+
+const MARGIN: u32 = 24;
+/// Room left of the deployment column for mesh arcs.
+const MESH_GUTTER: u32 = 96;
+const DEPLOYMENT_WIDTH: u32 = 200;
+const DEPLOYMENT_HEIGHT: u32 = 44;
+const DEPLOYMENT_GAP: u32 = 20;
+const NODE_X: u32 = MARGIN + MESH_GUTTER + DEPLOYMENT_WIDTH + 200;
+const NODE_WIDTH: u32 = 280;
+const NODE_HEADER: u32 = 40;
+const NODE_GAP: u32 = 24;
+const DOT: u32 = 14;
+const DOTS_PER_ROW: u32 = (NODE_WIDTH - 16) / DOT;
+
+/// The graph, positioned.
+pub struct TopologyView {
+    pub width: u32,
+    pub height: u32,
+    pub deployments: Vec<TopologyDeployment>,
+    pub nodes: Vec<TopologyNode>,
+    pub placements: Vec<TopologyEdge>,
+    pub mesh: Vec<TopologyEdge>,
+}
+
+pub struct TopologyDeployment {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Text color class of the health status, used for the outline.
+    pub color: &'static str,
+    pub status: &'static str,
+    pub instance_count: usize,
+}
+
+pub struct TopologyNode {
+    pub id: String,
+    pub address: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub color: &'static str,
+    pub status: &'static str,
+    /// Whether the node is registered; instances can name nodes that are not.
+    pub registered: bool,
+    pub instances: Vec<TopologyInstance>,
+}
+
+pub struct TopologyInstance {
+    pub id: String,
+    pub deployment_id: String,
+    pub cx: u32,
+    pub cy: u32,
+    pub color: &'static str,
+    pub title: String,
+}
+
+pub struct TopologyEdge {
+    pub from: String,
+    pub to: String,
+    /// SVG path data.
+    pub path: String,
+    pub stroke_width: u32,
+    pub title: String,
+}
+
+/// One deployment with what the graph shows of it.
+pub struct DeploymentEntry<'a> {
+    pub spec: &'a DeploymentSpec,
+    pub instances: &'a [InstanceState],
+    pub health: &'a DeploymentHealth,
+}
+
+impl TopologyView {
+    pub fn build(deployments: &[DeploymentEntry<'_>], nodes: &[NodeInfo]) -> Self {
+        let mut deployment_views = Vec::new();
+        let mut deployment_y = HashMap::new();
+        let mut y = MARGIN;
+        for entry in deployments {
+            let (color, status) = health_color(entry.health.status);
+            let live = entry.instances.iter().filter(|i| i.status != InstanceStatus::Stopped).count();
+            deployment_y.insert(entry.spec.id.as_str(), y);
+            deployment_views.push(TopologyDeployment {
+                id: entry.spec.id.clone(),
+                name: entry.spec.name.clone(),
+                namespace: entry.spec.namespace.clone(),
+                x: MARGIN + MESH_GUTTER,
+                y,
+                width: DEPLOYMENT_WIDTH,
+                height: DEPLOYMENT_HEIGHT,
+                color,
+                status,
+                instance_count: live,
+            });
+            y += DEPLOYMENT_HEIGHT + DEPLOYMENT_GAP;
+        }
+        let deployments_bottom = y;
+
+        // Registered nodes first, then any node an instance names that is not.
+        let mut placed: BTreeMap<&str, Vec<&InstanceState>> = BTreeMap::new();
+        for entry in deployments {
+            for instance in entry.instances.iter().filter(|i| i.status != InstanceStatus::Stopped) {
+                placed.entry(instance.node_id.as_str()).or_default().push(instance);
+            }
+        }
+        let mut node_ids: Vec<(&str, Option<&NodeInfo>)> = nodes.iter().map(|n| (n.id.as_str(), Some(n))).collect();
+        node_ids.extend(placed.keys().filter(|id| !nodes.iter().any(|n| n.id == **id)).map(|id| (*id, None)));
+
+        let mut node_views = Vec::new();
+        let mut node_y = HashMap::new();
+        let mut y = MARGIN;
+        for (id, info) in node_ids {
+            let instances = placed.get(id).map(Vec::as_slice).unwrap_or_default();
+            let rows = (instances.len() as u32).div_ceil(DOTS_PER_ROW).max(1);
+            let height = NODE_HEADER + rows * DOT + 12;
+            let (color, status, address) = match info {
+                Some(node) => {
+                    let view = NodeView::from_node(node, instances.len());
+                    (view.status_color, view.status, format!("{}:{}", node.address, node.port))
+                }
+                None => ("text-slate-500", "Unregistered", String::new()),
+            };
+            let dots = instances
+                .iter()
+                .enumerate()
+                .map(|(i, instance)| {
+                    let (col, row) = (i as u32 % DOTS_PER_ROW, i as u32 / DOTS_PER_ROW);
+                    TopologyInstance {
+                        id: instance.id.clone(),
+                        deployment_id: instance.deployment_id.clone(),
+                        cx: NODE_X + 8 + col * DOT + DOT / 2,
+                        cy: y + NODE_HEADER + row * DOT + DOT / 2,
+                        color: instance_color(instance.status, instance.health),
+                        title: format!(
+                            "{} ({}) — {:?}, {:?}",
+                            instance.id, instance.deployment_id, instance.status, instance.health
+                        ),
+                    }
+                })
+                .collect();
+            node_y.insert(id.to_string(), y);
+            node_views.push(TopologyNode {
+                id: id.to_string(),
+                address,
+                x: NODE_X,
+                y,
+                width: NODE_WIDTH,
+                height,
+                color,
+                status,
+                registered: info.is_some(),
+                instances: dots,
+            });
+            y += height + NODE_GAP;
+        }
+        let nodes_bottom = y;
+
+        let mut placements = Vec::new();
+        for entry in deployments {
+            let mut per_node: BTreeMap<&str, u32> = BTreeMap::new();
+            for instance in entry.instances.iter().filter(|i| i.status != InstanceStatus::Stopped) {
+                *per_node.entry(instance.node_id.as_str()).or_default() += 1;
+            }
+            let from_y = deployment_y[entry.spec.id.as_str()] + DEPLOYMENT_HEIGHT / 2;
+            let from_x = MARGIN + MESH_GUTTER + DEPLOYMENT_WIDTH;
+            for (node, count) in per_node {
+                let to_y = node_y[node] + NODE_HEADER / 2;
+                let mid = (from_x + NODE_X) / 2;
+                placements.push(TopologyEdge {
+                    from: entry.spec.id.clone(),
+                    to: node.to_string(),
+                    path: format!("M {from_x} {from_y} C {mid} {from_y}, {mid} {to_y}, {NODE_X} {to_y}"),
+                    stroke_width: 1 + count.min(6),
+                    title: format!("{} → {}: {count} instance(s)", entry.spec.id, node),
+                });
+            }
+        }
+
+        let mut mesh = Vec::new();
+        for (from, to) in mesh_edges(deployments.iter().map(|entry| entry.spec)) {
+            let (y1, y2) = (deployment_y[from] + DEPLOYMENT_HEIGHT / 2, deployment_y[to] + DEPLOYMENT_HEIGHT / 2);
+            let x = MARGIN + MESH_GUTTER;
+            let bulge = x.saturating_sub((24 + y1.abs_diff(y2) / 4).min(MESH_GUTTER));
+            mesh.push(TopologyEdge {
+                from: from.to_string(),
+                to: to.to_string(),
+                path: format!("M {x} {y1} C {bulge} {y1}, {bulge} {y2}, {x} {y2}"),
+                stroke_width: 1,
+                title: format!("{from} calls {to}"),
+            });
+        }
+
+        Self {
+            width: NODE_X + NODE_WIDTH + MARGIN,
+            height: deployments_bottom.max(nodes_bottom).max(MARGIN + DEPLOYMENT_HEIGHT) + MARGIN - DEPLOYMENT_GAP,
+            deployments: deployment_views,
+            nodes: node_views,
+            placements,
+            mesh,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deployments.is_empty() && self.nodes.is_empty()
+    }
+}
+
+/// `(caller, callee)` pairs: a caller's environment value names one of the
+/// callee's exact HTTP host names.
+fn mesh_edges<'a>(specs: impl Iterator<Item = &'a DeploymentSpec> + Clone) -> Vec<(&'a str, &'a str)> {
+    let mut edges = Vec::new();
+    for caller in specs.clone() {
+        for callee in specs.clone().filter(|callee| callee.id != caller.id) {
+            let TriggerConfig::Http { hosts, .. } = &callee.trigger else {
+                continue;
+            };
+            let calls = hosts
+                .iter()
+                .filter(|host| !host.starts_with("*."))
+                .any(|host| caller.env.values().any(|value| names_host(value, host)));
+            if calls {
+                edges.push((caller.id.as_str(), callee.id.as_str()));
+            }
+        }
+    }
+    edges
+}
+
+/// Whether `value` contains `host` as a whole host name, not as part of a
+/// longer one (`api.internal` is not in `myapi.internal.example`).
+fn names_host(value: &str, host: &str) -> bool {
+    let is_host_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+    value.match_indices(host).any(|(at, _)| {
+        let before = value[..at].chars().next_back();
+        let after = value[at + host.len()..].chars().next();
+        !before.is_some_and(is_host_char) && !after.is_some_and(is_host_char)
+    })
+}
+
+fn health_color(status: DeploymentStatus) -> (&'static str, &'static str) {
+    let color = match status {
+        DeploymentStatus::Healthy => "text-emerald-400",
+        DeploymentStatus::Progressing => "text-sky-400",
+        DeploymentStatus::Degraded => "text-amber-400",
+        DeploymentStatus::Unhealthy => "text-rose-400",
+    };
+    (color, status.label())
+}
+
+fn instance_color(status: InstanceStatus, health: HealthStatus) -> &'static str {
+    match (status, health) {
+        (InstanceStatus::Running, HealthStatus::Healthy) => "text-emerald-400",
+        (InstanceStatus::Running, HealthStatus::Unhealthy) => "text-rose-400",
+        (InstanceStatus::Running, HealthStatus::Unknown) => "text-amber-400",
+        (InstanceStatus::Starting, _) => "text-sky-400",
+        (InstanceStatus::Unhealthy, _) => "text-rose-400",
+        (InstanceStatus::Stopping, _) => "text-amber-400",
+        (InstanceStatus::Stopped, _) => "text-slate-500",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::tests::test_deployment;
+
+    fn instance(id: &str, deployment: &str, node: &str) -> InstanceState {
+        InstanceState {
+            id: id.to_string(),
+            deployment_id: deployment.to_string(),
+            node_id: node.to_string(),
+            status: InstanceStatus::Running,
+            health: HealthStatus::Healthy,
+            restart_count: 0,
+            memory_bytes: 0,
+            started_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn places_instances_and_finds_mesh_edges() {
+        let mut web = test_deployment("default", "web");
+        web.env.insert("API_URL".to_string(), "https://api.internal/v1".to_string());
+        web.env.insert("OTHER".to_string(), "myapi.internal.example".to_string());
+        let mut api = test_deployment("default", "api");
+        api.trigger = TriggerConfig::Http { port: None, hosts: vec!["api.internal".to_string()], path_prefix: None };
+        let web_instances = [instance("w1", &web.id, "node-1"), instance("w2", &web.id, "node-1")];
+        let api_instances = [instance("a1", &api.id, "node-2")];
+        let web_health = warpgrid_health::summary::summarize(&web, &web_instances, None, None);
+        let api_health = warpgrid_health::summary::summarize(&api, &api_instances, None, None);
+
+        let view = TopologyView::build(
+            &[
+                DeploymentEntry { spec: &web, instances: &web_instances, health: &web_health },
+                DeploymentEntry { spec: &api, instances: &api_instances, health: &api_health },
+            ],
+            &[],
+        );
+
+        let nodes: Vec<_> = view.nodes.iter().map(|n| (n.id.as_str(), n.instances.len(), n.registered)).collect();
+        assert_eq!(nodes, [("node-1", 2, false), ("node-2", 1, false)]);
+        let placements: Vec<_> = view.placements.iter().map(|e| (e.to.as_str(), e.stroke_width)).collect();
+        assert_eq!(placements, [("node-1", 3), ("node-2", 2)]);
+        let mesh: Vec<_> = view.mesh.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect();
+        assert_eq!(mesh, [(web.id.as_str(), api.id.as_str())]);
+        assert!(!names_host("myapi.internal.example", "api.internal"));
+    }
+}
@@ -5,10 +5,11 @@
 
 use std::collections::HashMap;
 
+use warpgrid_health::{DeploymentHealth, DeploymentStatus};
 use warpgrid_rollout::{Rollout, RolloutPhase, RolloutStrategy};
 use warpgrid_state::{
-    DeploymentSpec, HealthStatus, InstanceState, InstanceStatus, MetricsSnapshot, NodeInfo,
-    TriggerConfig,
+    ClusterEvent, ClusterEventKind, DeploymentFlags, DeploymentSpec, FeatureFlag, HealthStatus,
+    InstanceState, InstanceStatus, MetricsSnapshot, NodeInfo, TriggerConfig,
 };
 
 // ── Cluster Summary ─────────────────────────────────────────────
@@ -269,6 +270,38 @@ impl InstanceView {
     }
 }
 
+// ── Flag View ───────────────────────────────────────────────────
+
+pub struct FlagView {
+    pub name: String,
+    pub kind: &'static str,
+    pub value_display: String,
+}
+
+impl FlagView {
+    pub fn from_flags(flags: &DeploymentFlags) -> Vec<Self> {
+        flags
+            .set
+            .flags
+            .iter()
+            .map(|(name, flag)| {
+                let (kind, value_display) = match flag {
+                    FeatureFlag::Boolean { enabled } => {
+                        ("boolean", if *enabled { "on" } else { "off" }.to_string())
+                    }
+                    FeatureFlag::String { value } => ("string", value.clone()),
+                    FeatureFlag::Percentage { percent } => ("percentage", format!("{percent}%")),
+                };
+                Self {
+                    name: name.clone(),
+                    kind,
+                    value_display,
+                }
+            })
+            .collect()
+    }
+}
+
 // ── Node View ───────────────────────────────────────────────────
 
 pub struct NodeView {
@@ -283,6 +316,8 @@ pub struct NodeView {
     pub cpu_bar: ResourceBar,
     pub labels: Vec<(String, String)>,
     pub instance_count: usize,
+    /// Closed to new placements.
+    pub cordoned: bool,
 }
 
 impl NodeView {
@@ -325,9 +360,41 @@ impl NodeView {
             heartbeat_display: format_relative_time(node.last_heartbeat),
             heartbeat_color,
             memory_bar: ResourceBar::memory(node.used_memory_bytes, node.capacity_memory_bytes),
-            cpu_bar: ResourceBar::cpu(node.used_cpu_weight, node.capacity_cpu_weight),
+            cpu_bar: ResourceBar::cpu(node.used_cpu_weight, node.effective_cpu_weight()),
             labels,
             instance_count,
+            cordoned: node.cordoned,
+        }
+    }
+}
+
+// ── Health Badge ────────────────────────────────────────────────
+
+/// The deployment health summary, as the detail page header badge.
+pub struct HealthBadge {
+    pub label: &'static str,
+    pub class: &'static str,
+    /// Contributing reasons, shown as the badge tooltip.
+    pub reasons: String,
+}
+
+impl HealthBadge {
+    pub fn from_health(health: &DeploymentHealth) -> Self {
+        let class = match health.status {
+            DeploymentStatus::Healthy => "text-emerald-400 bg-emerald-500/10 border-emerald-500/20",
+            DeploymentStatus::Progressing => "text-sky-400 bg-sky-500/10 border-sky-500/20",
+            DeploymentStatus::Degraded => "text-amber-400 bg-amber-500/10 border-amber-500/20",
+            DeploymentStatus::Unhealthy => "text-rose-400 bg-rose-500/10 border-rose-500/20",
+        };
+        Self {
+            label: health.status.label(),
+            class,
+            reasons: health
+                .reasons
+                .iter()
+                .map(|r| r.message.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
         }
     }
 }
@@ -350,6 +417,69 @@ pub struct RolloutView {
     pub is_active: bool,
     pub can_pause: bool,
     pub can_resume: bool,
+    pub can_rollback: bool,
+    /// One segment per batch of a rolling update, or per stage of a canary
+    /// or blue-green rollout.
+    pub steps: Vec<RolloutStep>,
+}
+
+/// One segment of a rollout's progress strip.
+#[derive(Clone)]
+pub struct RolloutStep {
+    pub label: String,
+    pub color: &'static str,
+}
+
+impl RolloutStep {
+    const DONE: &'static str = "bg-grid-accent";
+    const CURRENT: &'static str = "bg-sky-400 animate-pulse";
+    const PENDING: &'static str = "bg-grid-800";
+    const FAILED: &'static str = "bg-rose-500";
+}
+
+/// Progress segments for `r`. Where a rollout stands is known while a
+/// rolling update is between batches, a canary is observed or promoted, or
+/// a blue-green set waits on its health gate; a paused or resumed rollout
+/// shows no step under way, and a rolled-back one shows every step failed.
+fn rollout_steps(r: &Rollout) -> Vec<RolloutStep> {
+    let labels: Vec<String> = match &r.strategy {
+        RolloutStrategy::Rolling(cfg) => {
+            let total = match r.phase {
+                RolloutPhase::RollingBatch { total, .. } => total,
+                _ => r.target_instances.div_ceil(cfg.batch_size.max(1)).max(1),
+            };
+            (1..=total).map(|batch| format!("Batch {batch}")).collect()
+        }
+        RolloutStrategy::Canary(cfg) => vec![
+            format!("Canary {}%", cfg.traffic_percent),
+            "Observe".to_string(),
+            "Promote".to_string(),
+        ],
+        RolloutStrategy::BlueGreen => vec!["Health gate".to_string(), "Switch".to_string()],
+    };
+    // Index of the step under way; the length once all are done.
+    let current = match (&r.phase, &r.strategy) {
+        (RolloutPhase::RollingBatch { current, .. }, _) => Some(*current as usize - 1),
+        (RolloutPhase::CanaryObserving, _) => Some(1),
+        (RolloutPhase::CanaryPromoting, _) => Some(2),
+        (RolloutPhase::HealthGate, RolloutStrategy::BlueGreen) => Some(0),
+        (RolloutPhase::Completed, _) => Some(labels.len()),
+        _ => None,
+    };
+    let failed = matches!(r.phase, RolloutPhase::RolledBack { .. });
+    labels
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let color = match current {
+                _ if failed => RolloutStep::FAILED,
+                Some(current) if i < current => RolloutStep::DONE,
+                Some(current) if i == current => RolloutStep::CURRENT,
+                _ => RolloutStep::PENDING,
+            };
+            RolloutStep { label, color }
+        })
+        .collect()
 }
 
 impl RolloutView {
@@ -431,6 +561,8 @@ impl RolloutView {
             is_active,
             can_pause: is_active && r.phase != RolloutPhase::Paused,
             can_resume: r.phase == RolloutPhase::Paused,
+            can_rollback: is_active,
+            steps: rollout_steps(r),
         }
     }
 }
@@ -521,6 +653,68 @@ pub struct AlertView {
     pub message: String,
 }
 
+// ── Event View ──────────────────────────────────────────────────
+
+pub struct EventView {
+    pub sequence: u64,
+    pub kind: &'static str,
+    pub kind_label: String,
+    pub kind_color: &'static str,
+    pub message: String,
+    pub time_display: String,
+    pub timestamp_display: String,
+    pub deployment_id: Option<String>,
+    pub node_id: Option<String>,
+    pub instance_id: Option<String>,
+}
+
+impl EventView {
+    pub fn from_event(event: &ClusterEvent) -> Self {
+        use ClusterEventKind::*;
+        let kind_color = match event.kind {
+            DeploymentDeleted | DeploymentRolledBack | InstanceUnhealthy | RolloutRolledBack | NodeLost => {
+                "bg-rose-500/20 text-rose-400"
+            }
+            RolloutPaused | NodeCordoned | NodeDrained => "bg-amber-500/20 text-amber-400",
+            DeploymentCreated | RolloutResumed | NodeJoined | NodeUncordoned => "bg-emerald-500/20 text-emerald-400",
+            DeploymentUpdated | DeploymentScaled | RolloutStarted => "bg-sky-500/20 text-sky-400",
+        };
+        Self {
+            sequence: event.sequence,
+            kind: event.kind.as_str(),
+            kind_label: event.kind.as_str().replace('_', " "),
+            kind_color,
+            message: event.message.clone(),
+            time_display: format_relative_time(event.timestamp),
+            timestamp_display: format_timestamp(event.timestamp),
+            deployment_id: event.deployment_id.clone(),
+            node_id: event.node_id.clone(),
+            instance_id: event.instance_id.clone(),
+        }
+    }
+}
+
+/// Events of one UTC day, newest first.
+pub struct EventDay {
+    pub date: String,
+    pub events: Vec<EventView>,
+}
+
+/// Group `events`, newest first, by the UTC day they happened on.
+pub fn group_events_by_day(events: &[ClusterEvent]) -> Vec<EventDay> {
+    let mut days: Vec<EventDay> = Vec::new();
+    for event in events {
+        let date = chrono::DateTime::from_timestamp(event.timestamp as i64, 0)
+            .map(|dt| dt.format("%A, %B %-d %Y").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        match days.last_mut() {
+            Some(day) if day.date == date => day.events.push(EventView::from_event(event)),
+            _ => days.push(EventDay { date, events: vec![EventView::from_event(event)] }),
+        }
+    }
+    days
+}
+
 // ── Format Helpers ──────────────────────────────────────────────
 
 pub fn format_bytes(bytes: u64) -> String {
@@ -600,7 +794,7 @@ fn instance_status_color(status: InstanceStatus, health: HealthStatus) -> &'stat
 
 fn format_trigger(trigger: &TriggerConfig) -> (String, &'static str) {
     match trigger {
-        TriggerConfig::Http { port } => (
+        TriggerConfig::Http { port, .. } => (
             format!("HTTP :{}", port.unwrap_or(8080)),
             "HTTP",
         ),
@@ -639,7 +833,7 @@ pub fn build_cluster_summary(
 
     let total_mem: u64 = nodes.iter().map(|n| n.capacity_memory_bytes).sum();
     let used_mem: u64 = nodes.iter().map(|n| n.used_memory_bytes).sum();
-    let total_cpu: u32 = nodes.iter().map(|n| n.capacity_cpu_weight).sum();
+    let total_cpu: u32 = nodes.iter().map(|n| n.effective_cpu_weight()).sum();
     let used_cpu: u32 = nodes.iter().map(|n| n.used_cpu_weight).sum();
 
     let now = std::time::SystemTime::now()
@@ -758,6 +952,33 @@ mod tests {
         assert!(result.contains("1970"));
     }
 
+    #[test]
+    fn health_badge_joins_reasons() {
+        use warpgrid_health::summary::{HealthReason, ReasonKind};
+        let badge = HealthBadge::from_health(&DeploymentHealth {
+            deployment_id: "default/api".to_string(),
+            status: DeploymentStatus::Degraded,
+            reasons: vec![
+                HealthReason {
+                    kind: ReasonKind::FailedProbes,
+                    status: DeploymentStatus::Degraded,
+                    message: "1 of 2 instance(s) failing health probes".to_string(),
+                },
+                HealthReason {
+                    kind: ReasonKind::RolloutInProgress,
+                    status: DeploymentStatus::Progressing,
+                    message: "rolling out v2".to_string(),
+                },
+            ],
+            available: 1,
+            desired: 2,
+            error_rate: None,
+        });
+        assert_eq!(badge.label, "Degraded");
+        assert!(badge.class.contains("amber"));
+        assert_eq!(badge.reasons, "1 of 2 instance(s) failing health probes; rolling out v2");
+    }
+
     #[test]
     fn resource_bar_percent_calculation() {
         let bar = ResourceBar::memory(512 * 1024 * 1024, 1024 * 1024 * 1024);
@@ -804,11 +1025,12 @@ mod tests {
             namespace: "default".to_string(),
             name: "api".to_string(),
             source: "file://test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: warpgrid_state::InstanceConstraints { min: 1, max: 10 },
             resources: warpgrid_state::ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -816,6 +1038,12 @@ mod tests {
             env: std::collections::HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         };
         let instances = vec![InstanceState {
             id: "inst-0".to_string(),
@@ -845,11 +1073,12 @@ mod tests {
                 namespace: "default".to_string(),
                 name: "a".to_string(),
                 source: "test".to_string(),
-                trigger: TriggerConfig::Http { port: None },
+                trigger: TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
                 instances: warpgrid_state::InstanceConstraints { min: 1, max: 5 },
                 resources: warpgrid_state::ResourceLimits {
                     memory_bytes: 64 * 1024 * 1024,
                     cpu_weight: 100,
+                    execution_budget_ms: None,
                 },
                 scaling: None,
                 health: None,
@@ -857,6 +1086,12 @@ mod tests {
                 env: std::collections::HashMap::new(),
                 created_at: 1000,
                 updated_at: 1000,
+                priority: None,
+                min_available: None,
+                labels: Default::default(),
+                paused: false,
+                secrets: Vec::new(),
+                config_maps: Vec::new(),
             },
         ];
         let instances = vec![
@@ -915,6 +1150,32 @@ mod tests {
         assert!((rows[0].rps_bar_width - 50.0).abs() < 0.1);
         assert!((rows[1].rps_bar_width - 100.0).abs() < 0.1);
     }
+
+    #[test]
+    fn rollout_steps_track_batches() {
+        let strategy = RolloutStrategy::Rolling(warpgrid_rollout::RollingConfig {
+            batch_size: 2,
+            ..Default::default()
+        });
+        let mut rollout = Rollout::new("default/api", strategy, 5, "v1", "v2");
+        let colors = |r: &Rollout| RolloutView::from_rollout(r).steps.iter().map(|s| s.color).collect::<Vec<_>>();
+        assert_eq!(colors(&rollout), [RolloutStep::PENDING; 3]);
+
+        rollout.start();
+        let healthy = warpgrid_rollout::HealthMetrics {
+            healthy_count: 5,
+            total_count: 5,
+            error_rate: 0.0,
+            p99_latency_ms: 10,
+        };
+        rollout.advance(&healthy);
+        assert_eq!(colors(&rollout), [RolloutStep::DONE, RolloutStep::CURRENT, RolloutStep::PENDING]);
+        assert!(RolloutView::from_rollout(&rollout).can_rollback);
+
+        rollout.rollback("rolled back by operator");
+        assert_eq!(colors(&rollout), [RolloutStep::FAILED; 3]);
+        assert!(!RolloutView::from_rollout(&rollout).can_rollback);
+    }
 }
 
 // ── Density Demo ────────────────────────────────────────────────
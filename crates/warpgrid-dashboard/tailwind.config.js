@@ -0,0 +1,53 @@
+// Tailwind theme for the dashboard, shared by both styling modes:
+//
+// - CDN mode: served at /dashboard/static/tailwind.config.js and picked up by
+//   the Tailwind Play CDN in the browser.
+// - Embedded mode: read by the Tailwind CLI in scripts/vendor-dashboard-assets.sh
+//   to compile assets/tailwind.css from the classes used in templates/ and src/.
+const config = {
+  content: ['./templates/**/*.html', './src/**/*.rs'],
+  darkMode: 'class',
+  theme: {
+    extend: {
+      fontFamily: {
+        display: ['Outfit', 'system-ui', 'sans-serif'],
+        mono: ['IBM Plex Mono', 'ui-monospace', 'SFMono-Regular', 'monospace'],
+        body: ['Outfit', 'system-ui', 'sans-serif']
+      },
+      colors: {
+        grid: {
+          950: '#040608',
+          900: '#0a0f14',
+          850: '#0f161d',
+          800: '#141e28',
+          700: '#1e2d3d',
+          600: '#2a3f52',
+          500: '#3a5570',
+          accent: '#00e5a0',
+          glow: 'rgba(0, 229, 160, 0.15)',
+          warn: '#ffbe0b',
+          danger: '#ff5c6c',
+          info: '#38bdf8',
+        }
+      },
+      animation: {
+        'fade-in': 'fadeIn 0.4s ease-out forwards',
+        'slide-up': 'slideUp 0.4s ease-out forwards',
+        'pulse-glow': 'pulseGlow 2s ease-in-out infinite',
+        'scan': 'scan 4s linear infinite',
+      },
+      keyframes: {
+        fadeIn: { '0%': { opacity: '0' }, '100%': { opacity: '1' } },
+        slideUp: { '0%': { opacity: '0', transform: 'translateY(8px)' }, '100%': { opacity: '1', transform: 'translateY(0)' } },
+        pulseGlow: { '0%, 100%': { boxShadow: '0 0 4px rgba(0, 229, 160, 0.2)' }, '50%': { boxShadow: '0 0 12px rgba(0, 229, 160, 0.4)' } },
+        scan: { '0%': { backgroundPosition: '0 0' }, '100%': { backgroundPosition: '0 100%' } },
+      }
+    }
+  }
+};
+
+if (typeof module !== 'undefined') {
+  module.exports = config;
+} else {
+  tailwind.config = config;
+}
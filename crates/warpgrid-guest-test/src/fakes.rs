@@ -0,0 +1,380 @@
+//! Scriptable fake shims.
+//!
+//! [`FakeShims`] implements every `warpgrid:shim` interface from canned
+//! data instead of the network and the host filesystem, and records each
+//! call as a [`ShimCall`]. Anything not scripted fails the way the real
+//! shim would (unknown host, connection refused, not a virtual path), so
+//! a guest's error handling can be tested too.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+use warpgrid_host::bindings::async_handler_bindings::warpgrid::shim::http_types;
+use warpgrid_host::bindings::warpgrid::shim::{
+    database_proxy, dns, feature_flags, filesystem, render, signals, threading,
+};
+
+pub use signals::SignalType;
+pub use threading::ThreadingModel;
+
+use crate::script::DbScript;
+
+/// One call the guest made into a shim, with its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShimCall {
+    ResolveAddress { hostname: String },
+    DbConnect { host: String, port: u16, database: String, user: String },
+    DbSend { handle: u64, data: Vec<u8> },
+    DbRecv { handle: u64, max_bytes: u32 },
+    DbClose { handle: u64 },
+    OpenVirtual { path: String },
+    ReadVirtual { handle: u64, len: u32 },
+    StatVirtual { path: String },
+    CloseVirtual { handle: u64 },
+    OnSignal { signal: SignalType },
+    PollSignal,
+    DeclareThreadingModel { model: ThreadingModel },
+    RenderPdf { html: String },
+    GetBool { name: String, key: String },
+    GetString { name: String },
+}
+
+/// Canned shim behaviour for one guest instance, and the record of what
+/// the guest did with it.
+#[derive(Debug, Default)]
+pub struct FakeShims {
+    hosts: HashMap<String, Vec<String>>,
+    files: HashMap<String, Vec<u8>>,
+    /// Scripts per `host:port`, one per expected connection, in order.
+    databases: HashMap<(String, u16), VecDeque<DbScript>>,
+    bool_flags: HashMap<String, bool>,
+    string_flags: HashMap<String, String>,
+    pdf: Option<Result<Vec<u8>, String>>,
+    signals: VecDeque<SignalType>,
+
+    next_handle: u64,
+    open_files: HashMap<u64, (Vec<u8>, usize)>,
+    connections: HashMap<u64, ((String, u16), DbScript)>,
+    closed: Vec<((String, u16), DbScript)>,
+    threading_model: Option<ThreadingModel>,
+    calls: Vec<ShimCall>,
+    violations: Vec<String>,
+}
+
+impl FakeShims {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `hostname` resolves to `addresses`.
+    pub fn dns<I, S>(mut self, hostname: &str, addresses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.hosts
+            .insert(hostname.to_string(), addresses.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// A virtual file at `path`.
+    pub fn file(mut self, path: &str, contents: impl AsRef<[u8]>) -> Self {
+        self.files.insert(path.to_string(), contents.as_ref().to_vec());
+        self
+    }
+
+    /// The next connection to `host:port` follows `script`. Call again for
+    /// each further connection the guest is expected to open.
+    pub fn database(mut self, host: &str, port: u16, script: DbScript) -> Self {
+        self.databases
+            .entry((host.to_string(), port))
+            .or_default()
+            .push_back(script);
+        self
+    }
+
+    /// A boolean feature flag, the same for every key.
+    pub fn bool_flag(mut self, name: &str, value: bool) -> Self {
+        self.bool_flags.insert(name.to_string(), value);
+        self
+    }
+
+    /// A string feature flag.
+    pub fn string_flag(mut self, name: &str, value: &str) -> Self {
+        self.string_flags.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// What `render-pdf` returns (default: an error).
+    pub fn render_pdf(mut self, result: Result<Vec<u8>, String>) -> Self {
+        self.pdf = Some(result);
+        self
+    }
+
+    /// A signal for the guest's `poll-signal` to pick up.
+    pub fn signal(mut self, signal: SignalType) -> Self {
+        self.signals.push_back(signal);
+        self
+    }
+
+    /// Every shim call so far, in order.
+    pub fn calls(&self) -> &[ShimCall] {
+        &self.calls
+    }
+
+    /// The threading model the guest declared, if any.
+    pub fn threading_model(&self) -> Option<ThreadingModel> {
+        self.threading_model
+    }
+
+    /// Script deviations, and scripted connections and steps the guest
+    /// never got to. Empty when the guest did exactly what was scripted.
+    pub fn unmet_expectations(&self) -> Vec<String> {
+        let mut unmet = self.violations.clone();
+        let used = self
+            .connections
+            .values()
+            .map(|(endpoint, script)| (endpoint, script))
+            .chain(self.closed.iter().map(|(endpoint, script)| (endpoint, script)));
+        for ((host, port), script) in used {
+            unmet.extend(script.remaining().into_iter().map(|step| format!("{host}:{port}: {step}")));
+        }
+        for ((host, port), scripts) in &self.databases {
+            if !scripts.is_empty() {
+                unmet.push(format!("{host}:{port}: {} scripted connection(s) never opened", scripts.len()));
+            }
+        }
+        unmet.sort();
+        unmet
+    }
+
+    fn handle(&mut self) -> u64 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+
+    /// Record a script deviation and hand it to the guest as an error.
+    fn violation(&mut self, message: String) -> String {
+        self.violations.push(message.clone());
+        message
+    }
+}
+
+impl dns::Host for FakeShims {
+    fn resolve_address(&mut self, hostname: String) -> Result<Vec<dns::IpAddressRecord>, String> {
+        self.calls.push(ShimCall::ResolveAddress { hostname: hostname.clone() });
+        let addresses = self
+            .hosts
+            .get(&hostname)
+            .ok_or_else(|| format!("unknown host: {hostname}"))?;
+        Ok(addresses
+            .iter()
+            .map(|address| dns::IpAddressRecord {
+                is_ipv6: address.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()),
+                address: address.clone(),
+            })
+            .collect())
+    }
+}
+
+impl database_proxy::Host for FakeShims {
+    fn connect(&mut self, config: database_proxy::ConnectConfig) -> Result<u64, String> {
+        self.calls.push(ShimCall::DbConnect {
+            host: config.host.clone(),
+            port: config.port,
+            database: config.database.clone(),
+            user: config.user.clone(),
+        });
+        let endpoint = (config.host, config.port);
+        let Some(script) = self.databases.get_mut(&endpoint).and_then(VecDeque::pop_front) else {
+            return Err(format!("connection refused: {}:{}", endpoint.0, endpoint.1));
+        };
+        let handle = self.handle();
+        self.connections.insert(handle, (endpoint, script));
+        Ok(handle)
+    }
+
+    fn send(&mut self, handle: u64, data: Vec<u8>) -> Result<u32, String> {
+        self.calls.push(ShimCall::DbSend { handle, data: data.clone() });
+        let Some((_, script)) = self.connections.get_mut(&handle) else {
+            return Err(format!("invalid connection handle: {handle}"));
+        };
+        match script.send(&data) {
+            Ok(()) => Ok(data.len() as u32),
+            Err(e) => Err(self.violation(format!("connection {handle}: {e}"))),
+        }
+    }
+
+    fn recv(&mut self, handle: u64, max_bytes: u32) -> Result<Vec<u8>, String> {
+        self.calls.push(ShimCall::DbRecv { handle, max_bytes });
+        let Some((_, script)) = self.connections.get_mut(&handle) else {
+            return Err(format!("invalid connection handle: {handle}"));
+        };
+        match script.recv(max_bytes as usize) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) => Err(self.violation(format!("connection {handle}: {e}"))),
+        }
+    }
+
+    fn close(&mut self, handle: u64) -> Result<(), String> {
+        self.calls.push(ShimCall::DbClose { handle });
+        let connection = self
+            .connections
+            .remove(&handle)
+            .ok_or_else(|| format!("invalid connection handle: {handle}"))?;
+        self.closed.push(connection);
+        Ok(())
+    }
+}
+
+impl filesystem::Host for FakeShims {
+    fn open_virtual(&mut self, path: String) -> Result<u64, String> {
+        self.calls.push(ShimCall::OpenVirtual { path: path.clone() });
+        let contents = self
+            .files
+            .get(&path)
+            .cloned()
+            .ok_or_else(|| format!("not a virtual path: {path}"))?;
+        let handle = self.handle();
+        self.open_files.insert(handle, (contents, 0));
+        Ok(handle)
+    }
+
+    fn read_virtual(&mut self, handle: u64, len: u32) -> Result<Vec<u8>, String> {
+        self.calls.push(ShimCall::ReadVirtual { handle, len });
+        let (contents, offset) = self
+            .open_files
+            .get_mut(&handle)
+            .ok_or_else(|| format!("invalid file handle: {handle}"))?;
+        let end = contents.len().min(*offset + len as usize);
+        let out = contents[*offset..end].to_vec();
+        *offset = end;
+        Ok(out)
+    }
+
+    fn stat_virtual(&mut self, path: String) -> Result<filesystem::FileStat, String> {
+        self.calls.push(ShimCall::StatVirtual { path: path.clone() });
+        if let Some(contents) = self.files.get(&path) {
+            return Ok(filesystem::FileStat { size: contents.len() as u64, is_file: true, is_directory: false });
+        }
+        // A path with scripted files below it is a directory.
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        if self.files.keys().any(|file| file.starts_with(&prefix)) {
+            return Ok(filesystem::FileStat { size: 0, is_file: false, is_directory: true });
+        }
+        Err(format!("not a virtual path: {path}"))
+    }
+
+    fn close_virtual(&mut self, handle: u64) -> Result<(), String> {
+        self.calls.push(ShimCall::CloseVirtual { handle });
+        self.open_files
+            .remove(&handle)
+            .map(|_| ())
+            .ok_or_else(|| format!("invalid file handle: {handle}"))
+    }
+}
+
+impl signals::Host for FakeShims {
+    fn on_signal(&mut self, signal: SignalType) -> Result<(), String> {
+        self.calls.push(ShimCall::OnSignal { signal });
+        Ok(())
+    }
+
+    fn poll_signal(&mut self) -> Option<SignalType> {
+        self.calls.push(ShimCall::PollSignal);
+        self.signals.pop_front()
+    }
+}
+
+impl threading::Host for FakeShims {
+    fn declare_threading_model(&mut self, model: ThreadingModel) -> Result<(), String> {
+        self.calls.push(ShimCall::DeclareThreadingModel { model });
+        if self.threading_model.is_some() {
+            return Err("threading model already declared".to_string());
+        }
+        self.threading_model = Some(model);
+        Ok(())
+    }
+}
+
+impl render::Host for FakeShims {
+    fn render_pdf(&mut self, html: String, _options: render::RenderOptions) -> Result<Vec<u8>, String> {
+        self.calls.push(ShimCall::RenderPdf { html });
+        self.pdf
+            .clone()
+            .unwrap_or_else(|| Err("render shim not scripted".to_string()))
+    }
+}
+
+impl feature_flags::Host for FakeShims {
+    fn get_bool(&mut self, name: String, key: String) -> Option<bool> {
+        let value = self.bool_flags.get(&name).copied();
+        self.calls.push(ShimCall::GetBool { name, key });
+        value
+    }
+
+    fn get_string(&mut self, name: String) -> Option<String> {
+        let value = self.string_flags.get(&name).cloned();
+        self.calls.push(ShimCall::GetString { name });
+        value
+    }
+}
+
+impl http_types::Host for FakeShims {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database_proxy::Host as _;
+    use dns::Host as _;
+    use filesystem::Host as _;
+
+    fn connect_config(host: &str, port: u16) -> database_proxy::ConnectConfig {
+        database_proxy::ConnectConfig {
+            host: host.to_string(),
+            port,
+            database: "app".to_string(),
+            user: "app".to_string(),
+            password: None,
+        }
+    }
+
+    #[test]
+    fn scripted_database_conversation() {
+        let mut fakes = FakeShims::new()
+            .dns("db.internal", ["10.0.0.7"])
+            .database("10.0.0.7", 6379, DbScript::new().expect(b"PING\r\n").reply(b"+PONG\r\n"));
+
+        let records = fakes.resolve_address("db.internal".into()).unwrap();
+        assert_eq!(records[0].address, "10.0.0.7");
+        assert!(!records[0].is_ipv6);
+        let handle = fakes.connect(connect_config("10.0.0.7", 6379)).unwrap();
+        assert_eq!(fakes.send(handle, b"PING\r\n".to_vec()).unwrap(), 6);
+        assert_eq!(fakes.recv(handle, 512).unwrap(), b"+PONG\r\n");
+        fakes.close(handle).unwrap();
+
+        assert!(fakes.unmet_expectations().is_empty(), "{:?}", fakes.unmet_expectations());
+        assert_eq!(fakes.calls().len(), 5);
+        assert!(matches!(&fakes.calls()[1], ShimCall::DbConnect { port: 6379, .. }));
+    }
+
+    #[test]
+    fn unscripted_calls_fail_like_the_real_shims() {
+        let mut fakes = FakeShims::new()
+            .file("/etc/app/config.toml", "debug = true\n")
+            .database("10.0.0.7", 5432, DbScript::new().expect(b"startup"));
+
+        assert!(fakes.resolve_address("nowhere".into()).unwrap_err().contains("unknown host"));
+        assert!(fakes.connect(connect_config("10.0.0.8", 5432)).unwrap_err().contains("refused"));
+        assert!(fakes.open_virtual("/etc/passwd".into()).is_err());
+        assert!(fakes.stat_virtual("/etc/app".into()).unwrap().is_directory);
+
+        let unmet = fakes.unmet_expectations();
+        assert_eq!(unmet, ["10.0.0.7:5432: 1 scripted connection(s) never opened"]);
+
+        let handle = fakes.connect(connect_config("10.0.0.7", 5432)).unwrap();
+        assert!(fakes.send(handle, b"garbage".to_vec()).is_err());
+        let unmet = fakes.unmet_expectations();
+        assert_eq!(unmet.len(), 2, "the deviation and the unmet step: {unmet:?}");
+    }
+}
@@ -0,0 +1,146 @@
+//! Compiling and running a guest component against [`FakeShims`].
+
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use wasmtime::component::{
+    Component, ComponentNamedList, HasSelf, Instance, Lift, Linker, Lower, ResourceTable,
+};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView};
+
+use warpgrid_host::bindings::async_handler_bindings::warpgrid::shim::http_types;
+use warpgrid_host::bindings::warpgrid::shim::{
+    database_proxy, dns, feature_flags, filesystem, render, signals, threading,
+};
+
+use crate::fakes::{FakeShims, ShimCall};
+
+/// Store data of a guest instance.
+struct GuestState {
+    shims: FakeShims,
+    wasi: WasiCtx,
+    table: ResourceTable,
+}
+
+impl WasiView for GuestState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+/// A compiled guest component, ready to be instantiated against fakes.
+///
+/// Every `warpgrid:shim` interface is linked to [`FakeShims`], and WASI
+/// preview 2 to a sandboxed context that only inherits stderr, so a
+/// guest's panics and `eprintln!`s show up in the test output.
+pub struct GuestHarness {
+    engine: Engine,
+    linker: Linker<GuestState>,
+    component: Component,
+}
+
+impl GuestHarness {
+    /// Compile a component from its bytes.
+    pub fn new(wasm: &[u8]) -> anyhow::Result<Self> {
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        let engine = Engine::new(&config)?;
+
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::p2::add_to_linker_sync(&mut linker)?;
+        let get: fn(&mut GuestState) -> &mut FakeShims = |state| &mut state.shims;
+        filesystem::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        dns::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        signals::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        database_proxy::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        threading::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        render::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        feature_flags::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+        http_types::add_to_linker::<GuestState, HasSelf<FakeShims>>(&mut linker, get)?;
+
+        let component = Component::new(&engine, wasm).context("failed to compile guest component")?;
+        Ok(Self { engine, linker, component })
+    }
+
+    /// Compile a component from a `.wasm` file.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let wasm = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::new(&wasm)
+    }
+
+    /// A fresh instance whose shim calls are answered by `shims`.
+    pub fn instantiate(&self, shims: FakeShims) -> anyhow::Result<GuestInstance> {
+        let state = GuestState {
+            shims,
+            wasi: WasiCtx::builder().inherit_stderr().build(),
+            table: ResourceTable::new(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.component)
+            .context("failed to instantiate guest component")?;
+        Ok(GuestInstance { store, instance })
+    }
+}
+
+/// One instantiated guest and the fakes it talks to.
+pub struct GuestInstance {
+    store: Store<GuestState>,
+    instance: Instance,
+}
+
+impl GuestInstance {
+    /// Call the export `func`, either top-level (`interface: None`) or in
+    /// an exported interface such as `"my:app/api@0.1.0"`.
+    pub fn call<P, R>(&mut self, interface: Option<&str>, func: &str, params: P) -> anyhow::Result<R>
+    where
+        P: ComponentNamedList + Lower + Send + Sync,
+        R: ComponentNamedList + Lift + Send + Sync,
+    {
+        let parent = match interface {
+            Some(name) => Some(
+                self.instance
+                    .get_export_index(&mut self.store, None, name)
+                    .with_context(|| format!("guest does not export interface {name}"))?,
+            ),
+            None => None,
+        };
+        let index = self
+            .instance
+            .get_export_index(&mut self.store, parent.as_ref(), func)
+            .with_context(|| format!("guest does not export {func}"))?;
+        let typed = self
+            .instance
+            .get_typed_func::<P, R>(&mut self.store, index)
+            .with_context(|| format!("export {func} has a different signature"))?;
+        let result = typed.call(&mut self.store, params)?;
+        typed.post_return(&mut self.store)?;
+        Ok(result)
+    }
+
+    /// Every shim call the guest has made, in order.
+    pub fn calls(&self) -> &[ShimCall] {
+        self.store.data().shims.calls()
+    }
+
+    /// The fakes, e.g. to check the declared threading model.
+    pub fn shims(&self) -> &FakeShims {
+        &self.store.data().shims
+    }
+
+    /// Fail if the guest deviated from a database script or left any of
+    /// it unplayed.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let unmet = self.shims().unmet_expectations();
+        if !unmet.is_empty() {
+            bail!("guest did not follow its scripts:\n  {}", unmet.join("\n  "));
+        }
+        Ok(())
+    }
+}
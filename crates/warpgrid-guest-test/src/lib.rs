@@ -0,0 +1,36 @@
+//! warpgrid-guest-test — unit tests for guest components without a cluster.
+//!
+//! [`GuestHarness`] compiles a guest component and links every
+//! `warpgrid:shim` import to [`FakeShims`]: canned DNS answers, virtual
+//! files, feature flags, and [`DbScript`]s — the exact bytes a Postgres or
+//! Redis connection is expected to exchange. The guest runs natively in
+//! the test process, and the test asserts on the shim calls it made.
+//!
+//! ```no_run
+//! # fn example() -> anyhow::Result<()> {
+//! use warpgrid_guest_test::{DbScript, FakeShims, GuestHarness, ShimCall};
+//!
+//! let harness = GuestHarness::from_file("target/wasm32-wasip2/release/app.wasm")?;
+//! let shims = FakeShims::new()
+//!     .dns("cache.internal", ["10.0.0.9"])
+//!     .database("10.0.0.9", 6379, DbScript::new().expect(b"*1\r\n$4\r\nPING\r\n").reply(b"+PONG\r\n"));
+//!
+//! let mut guest = harness.instantiate(shims)?;
+//! let (pong,): (Result<String, String>,) = guest.call(Some("my:app/checks@0.1.0"), "ping-cache", ())?;
+//! assert_eq!(pong.unwrap(), "PONG");
+//! assert!(matches!(guest.calls()[0], ShimCall::ResolveAddress { .. }));
+//! guest.verify()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each instance gets its own fakes; scripts are consumed as the guest
+//! plays them, so instantiate again for every scenario.
+
+pub mod fakes;
+pub mod harness;
+pub mod script;
+
+pub use fakes::{FakeShims, ShimCall, SignalType, ThreadingModel};
+pub use harness::{GuestHarness, GuestInstance};
+pub use script::DbScript;
@@ -0,0 +1,146 @@
+//! Scripted database conversations.
+//!
+//! A [`DbScript`] is the byte exchange one proxied connection is expected
+//! to have: what the guest sends, in order, and what the "server" answers.
+//! The guest may split an expected message over several `send` calls and
+//! read a reply with several `recv` calls; only the byte order matters.
+
+use std::collections::VecDeque;
+
+/// One step of a [`DbScript`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// The guest must send these bytes next.
+    Expect(Vec<u8>),
+    /// The guest's next reads return these bytes.
+    Reply(Vec<u8>),
+}
+
+/// The expected byte exchange of one database connection.
+///
+/// ```
+/// use warpgrid_guest_test::DbScript;
+///
+/// // Redis: PING → +PONG
+/// let script = DbScript::new()
+///     .expect(b"*1\r\n$4\r\nPING\r\n")
+///     .reply(b"+PONG\r\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbScript {
+    steps: VecDeque<Step>,
+}
+
+impl DbScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The guest sends exactly `bytes` next.
+    pub fn expect(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.steps.push_back(Step::Expect(bytes.as_ref().to_vec()));
+        self
+    }
+
+    /// The guest's next reads return `bytes`.
+    pub fn reply(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.steps.push_back(Step::Reply(bytes.as_ref().to_vec()));
+        self
+    }
+
+    /// Match bytes the guest sent against the script.
+    pub(crate) fn send(&mut self, mut data: &[u8]) -> Result<(), String> {
+        while !data.is_empty() {
+            let Some(Step::Expect(expected)) = self.steps.front_mut() else {
+                return Err(match self.steps.front() {
+                    Some(Step::Reply(_)) => format!(
+                        "sent {} before reading the scripted reply",
+                        describe(data)
+                    ),
+                    _ => format!("sent {} after the script ended", describe(data)),
+                });
+            };
+            let n = expected.len().min(data.len());
+            if expected[..n] != data[..n] {
+                return Err(format!("sent {}, expected {}", describe(data), describe(expected)));
+            }
+            expected.drain(..n);
+            if expected.is_empty() {
+                self.steps.pop_front();
+            }
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    /// The next scripted reply bytes, up to `max`. Empty (end of stream)
+    /// once the script is done.
+    pub(crate) fn recv(&mut self, max: usize) -> Result<Vec<u8>, String> {
+        match self.steps.front_mut() {
+            Some(Step::Reply(reply)) => {
+                let out: Vec<u8> = reply.drain(..max.min(reply.len())).collect();
+                if reply.is_empty() {
+                    self.steps.pop_front();
+                }
+                Ok(out)
+            }
+            Some(Step::Expect(expected)) => Err(format!(
+                "read before sending the scripted {}",
+                describe(expected)
+            )),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Steps the guest never got to, for [`crate::GuestInstance::verify`].
+    pub(crate) fn remaining(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .map(|step| match step {
+                Step::Expect(bytes) => format!("expected send of {}", describe(bytes)),
+                Step::Reply(bytes) => format!("unread reply {}", describe(bytes)),
+            })
+            .collect()
+    }
+}
+
+/// Bytes as an escaped string, truncated for error messages.
+fn describe(bytes: &[u8]) -> String {
+    const MAX: usize = 64;
+    let shown: String = bytes[..bytes.len().min(MAX)].escape_ascii().to_string();
+    if bytes.len() > MAX {
+        format!("\"{shown}\"… ({} bytes)", bytes.len())
+    } else {
+        format!("\"{shown}\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_sends_and_reads_follow_the_script() {
+        let mut script = DbScript::new().expect(b"PING\r\n").reply(b"+PONG\r\n");
+        script.send(b"PI").unwrap();
+        script.send(b"NG\r\n").unwrap();
+        assert_eq!(script.recv(3).unwrap(), b"+PO");
+        assert_eq!(script.recv(64).unwrap(), b"NG\r\n");
+        assert_eq!(script.recv(64).unwrap(), b"", "end of stream after the script");
+        assert!(script.remaining().is_empty());
+    }
+
+    #[test]
+    fn deviations_are_errors() {
+        let mut script = DbScript::new().expect(b"PING\r\n").reply(b"+PONG\r\n");
+        let err = script.send(b"QUIT\r\n").unwrap_err();
+        assert!(err.contains("expected \"PING\\r\\n\""), "{err}");
+        assert!(script.recv(8).unwrap_err().contains("read before sending"));
+
+        let mut script = DbScript::new().reply(b"hello");
+        assert!(script.send(b"x").unwrap_err().contains("before reading"));
+        assert_eq!(script.remaining(), ["unread reply \"hello\""]);
+        script.recv(5).unwrap();
+        assert!(script.send(b"x").unwrap_err().contains("after the script ended"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Runs a minimal filesystem-shim guest against fake virtual files.
+//!
+//! The guest is written in the component text format so the test needs
+//! no wasm toolchain: `read-len(path)` opens `path` through
+//! `warpgrid:shim/filesystem`, reads up to 4 KiB, closes the handle, and
+//! returns the byte count (`u32::MAX` on any shim error).
+
+use warpgrid_guest_test::{FakeShims, GuestHarness, ShimCall};
+
+const GUEST: &str = r#"
+(component
+  (import "warpgrid:shim/filesystem@0.1.0" (instance $fs
+    (export "open-virtual" (func (param "path" string) (result (result u64 (error string)))))
+    (export "read-virtual" (func (param "handle" u64) (param "len" u32) (result (result (list u8) (error string)))))
+    (export "close-virtual" (func (param "handle" u64) (result (result (error string)))))
+  ))
+
+  (core module $mem
+    (memory (export "memory") 1)
+    (global $bump (mut i32) (i32.const 1024))
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ret i32)
+      (local.set $ret
+        (i32.and
+          (i32.add (global.get $bump) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.xor (i32.sub (local.get 2) (i32.const 1)) (i32.const -1))))
+      (global.set $bump (i32.add (local.get $ret) (local.get 3)))
+      (local.get $ret)))
+  (core instance $m (instantiate $mem))
+
+  (core func $open (canon lower (func $fs "open-virtual")
+    (memory $m "memory") (realloc (func $m "realloc"))))
+  (core func $read (canon lower (func $fs "read-virtual")
+    (memory $m "memory") (realloc (func $m "realloc"))))
+  (core func $close (canon lower (func $fs "close-virtual")
+    (memory $m "memory") (realloc (func $m "realloc"))))
+
+  (core module $main
+    (import "m" "memory" (memory 1))
+    (import "fs" "open" (func $open (param i32 i32 i32)))
+    (import "fs" "read" (func $read (param i64 i32 i32)))
+    (import "fs" "close" (func $close (param i64 i32)))
+    (func (export "read-len") (param $ptr i32) (param $len i32) (result i32)
+      (local $handle i64)
+      (call $open (local.get $ptr) (local.get $len) (i32.const 0))
+      (if (result i32) (i32.load8_u (i32.const 0))
+        (then (i32.const -1))
+        (else
+          (local.set $handle (i64.load (i32.const 8)))
+          (call $read (local.get $handle) (i32.const 4096) (i32.const 0))
+          (if (result i32) (i32.load8_u (i32.const 0))
+            (then (i32.const -1))
+            (else
+              (i32.load (i32.const 8))
+              (call $close (local.get $handle) (i32.const 16))))))))
+  (core instance $i (instantiate $main
+    (with "m" (instance $m))
+    (with "fs" (instance
+      (export "open" (func $open))
+      (export "read" (func $read))
+      (export "close" (func $close))))))
+
+  (func (export "read-len") (param "path" string) (result u32)
+    (canon lift (core func $i "read-len")
+      (memory $m "memory") (realloc (func $m "realloc"))))
+)
+"#;
+
+fn harness() -> GuestHarness {
+    GuestHarness::new(GUEST.as_bytes()).expect("guest compiles")
+}
+
+#[test]
+fn reads_a_fake_virtual_file() {
+    let shims = FakeShims::new().file("/etc/resolv.conf", "nameserver 10.0.0.53\n");
+    let mut guest = harness().instantiate(shims).unwrap();
+
+    let (len,): (u32,) = guest.call(None, "read-len", ("/etc/resolv.conf",)).unwrap();
+    assert_eq!(len, 21);
+    assert_eq!(
+        guest.calls(),
+        [
+            ShimCall::OpenVirtual { path: "/etc/resolv.conf".into() },
+            ShimCall::ReadVirtual { handle: 1, len: 4096 },
+            ShimCall::CloseVirtual { handle: 1 },
+        ]
+    );
+    guest.verify().unwrap();
+}
+
+#[test]
+fn unscripted_paths_are_not_virtual() {
+    let mut guest = harness().instantiate(FakeShims::new()).unwrap();
+
+    let (len,): (u32,) = guest.call(None, "read-len", ("/etc/hosts",)).unwrap();
+    assert_eq!(len, u32::MAX, "no fake /etc/hosts was scripted");
+    assert_eq!(guest.calls(), [ShimCall::OpenVirtual { path: "/etc/hosts".into() }]);
+}
+
+#[test]
+fn missing_exports_are_reported() {
+    let mut guest = harness().instantiate(FakeShims::new()).unwrap();
+    let err = guest.call::<(), (u32,)>(Some("my:app/api@0.1.0"), "read-len", ()).unwrap_err();
+    assert!(err.to_string().contains("does not export interface"), "{err}");
+}
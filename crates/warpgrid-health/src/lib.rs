@@ -23,9 +23,16 @@
 //!
 //! Exponential backoff (1s → 60s) prevents hammering unhealthy instances.
 //! A single successful probe resets the backoff and restores `Healthy`.
+//!
+//! # Deployment Health
+//!
+//! [`summary::summarize`] folds probes, metrics, replica counts, and the
+//! rollout into one deployment-level status with its reasons.
 
 pub mod checker;
 pub mod monitor;
+pub mod summary;
 
 pub use checker::{HealthTracker, ProbeResult};
 pub use monitor::HealthMonitor;
+pub use summary::{DeploymentHealth, DeploymentStatus};
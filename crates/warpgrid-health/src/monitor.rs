@@ -41,6 +41,8 @@ pub struct HealthMonitor {
     monitors: Arc<RwLock<HashMap<String, MonitorSlot>>>,
     /// Optional callback when health status changes.
     on_status_change: Option<HealthCallback>,
+    /// Time source for probe intervals and status timestamps.
+    clock: SharedClock,
 }
 
 impl HealthMonitor {
@@ -50,6 +52,7 @@ impl HealthMonitor {
             state,
             monitors: Arc::new(RwLock::new(HashMap::new())),
             on_status_change: None,
+            clock: SystemClock::shared(),
         }
     }
 
@@ -59,6 +62,12 @@ impl HealthMonitor {
         self
     }
 
+    /// Use `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Start monitoring a deployment's health.
     ///
     /// The deployment must have a `health` config in its spec.
@@ -76,6 +85,7 @@ impl HealthMonitor {
         let address = address.to_string();
         let state = self.state.clone();
         let callback = self.on_status_change.clone();
+        let clock = self.clock.clone();
 
         let handle = tokio::spawn(async move {
             run_health_loop(
@@ -84,6 +94,7 @@ impl HealthMonitor {
                 &address,
                 state,
                 callback,
+                clock,
                 shutdown_rx,
             )
             .await;
@@ -146,6 +157,7 @@ async fn run_health_loop(
     address: &str,
     state: StateStore,
     callback: Option<HealthCallback>,
+    clock: SharedClock,
     mut shutdown: watch::Receiver<bool>,
 ) {
     let timeout = parse_timeout(&config.timeout);
@@ -157,14 +169,14 @@ async fn run_health_loop(
         let interval = tracker.next_interval();
 
         tokio::select! {
-            _ = tokio::time::sleep(interval) => {
+            _ = clock.sleep(interval) => {
                 let result = http_probe(address, &config.endpoint, timeout).await;
                 let prev_status = tracker.status();
                 let new_status = tracker.record(result);
 
                 // Update instance states in the store if status changed.
                 if new_status != prev_status {
-                    if let Err(e) = update_deployment_health(&state, deployment_id, new_status) {
+                    if let Err(e) = update_deployment_health(&state, deployment_id, new_status, clock.epoch_secs()) {
                         error!(%deployment_id, error = %e, "failed to update health status in store");
                     }
 
@@ -181,17 +193,26 @@ async fn run_health_loop(
     }
 }
 
-/// Update all instance health statuses for a deployment.
+/// Update all instance health statuses for a deployment, recording an
+/// event for each instance that turns unhealthy.
 fn update_deployment_health(
     state: &StateStore,
     deployment_id: &str,
     status: HealthStatus,
+    now: u64,
 ) -> Result<(), warpgrid_state::StateError> {
     let instances = state.list_instances_for_deployment(deployment_id)?;
     for mut inst in instances {
         inst.health = status;
-        inst.updated_at = epoch_secs();
+        inst.updated_at = now;
         if status == HealthStatus::Unhealthy {
+            if inst.status != InstanceStatus::Unhealthy {
+                let event = ClusterEvent::new(ClusterEventKind::InstanceUnhealthy, now, "health checks failing")
+                    .for_deployment(deployment_id)
+                    .for_instance(&inst.id)
+                    .for_node(&inst.node_id);
+                state.record_event(&event)?;
+            }
             inst.status = InstanceStatus::Unhealthy;
         } else if inst.status == InstanceStatus::Unhealthy && status == HealthStatus::Healthy {
             inst.status = InstanceStatus::Running;
@@ -218,13 +239,6 @@ fn parse_timeout(s: &str) -> Duration {
     }
 }
 
-fn epoch_secs() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -294,16 +308,23 @@ mod tests {
         state.put_instance(&test_instance("deploy-1", 0)).unwrap();
         state.put_instance(&test_instance("deploy-1", 1)).unwrap();
 
-        update_deployment_health(&state, "deploy-1", HealthStatus::Unhealthy).unwrap();
+        update_deployment_health(&state, "deploy-1", HealthStatus::Unhealthy, 2000).unwrap();
 
         let instances = state.list_instances_for_deployment("deploy-1").unwrap();
         for inst in &instances {
             assert_eq!(inst.health, HealthStatus::Unhealthy);
             assert_eq!(inst.status, InstanceStatus::Unhealthy);
+            assert_eq!(inst.updated_at, 2000);
         }
+        // Only the transition is recorded.
+        update_deployment_health(&state, "deploy-1", HealthStatus::Unhealthy, 2500).unwrap();
+        let events = state.list_events(0, 10, |_| true).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.kind == ClusterEventKind::InstanceUnhealthy));
+        assert_eq!(events[0].deployment_id.as_deref(), Some("deploy-1"));
 
         // Recovery.
-        update_deployment_health(&state, "deploy-1", HealthStatus::Healthy).unwrap();
+        update_deployment_health(&state, "deploy-1", HealthStatus::Healthy, 3000).unwrap();
         let instances = state.list_instances_for_deployment("deploy-1").unwrap();
         for inst in &instances {
             assert_eq!(inst.health, HealthStatus::Healthy);
@@ -311,6 +332,46 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn failing_probes_back_off_on_the_injected_clock() {
+        let clock = Arc::new(ManualClock::new(5000));
+        let state = StateStore::open_in_memory().unwrap();
+        state.put_instance(&test_instance("deploy-1", 0)).unwrap();
+        let monitor = HealthMonitor::new(state.clone()).with_clock(clock.clone());
+
+        // Nothing listens on port 1, so every probe fails immediately.
+        monitor
+            .start_monitor("deploy-1", &test_health_config(), "127.0.0.1:1")
+            .await;
+        let instance = || state.list_instances_for_deployment("deploy-1").unwrap()[0].clone();
+        // Real time for the probe itself; the check interval is on the clock.
+        let settle = || tokio::time::sleep(Duration::from_millis(50));
+        // Let the loop arm its first sleep before the clock moves.
+        settle().await;
+
+        // t=1s: first failure, interval backs off to 2s.
+        clock.advance(Duration::from_secs(1));
+        settle().await;
+        assert_eq!(instance().health, HealthStatus::Healthy);
+
+        // t=2s: still backing off, no probe.
+        clock.advance(Duration::from_secs(1));
+        settle().await;
+        assert_eq!(instance().health, HealthStatus::Healthy);
+
+        // t=3s: second failure reaches the threshold.
+        clock.advance(Duration::from_secs(1));
+        for _ in 0..100 {
+            if instance().health == HealthStatus::Unhealthy {
+                break;
+            }
+            settle().await;
+        }
+        assert_eq!(instance().health, HealthStatus::Unhealthy);
+        assert_eq!(instance().updated_at, 5003);
+        monitor.stop_all().await;
+    }
+
     #[test]
     fn parse_timeout_values() {
         assert_eq!(parse_timeout("2s"), Duration::from_secs(2));
@@ -0,0 +1,273 @@
+//! Deployment health summary.
+//!
+//! Folds probe results, the latest metrics snapshot, replica counts, and
+//! the rollout into one status with the reasons behind it. Served at
+//! `GET /api/v1/deployments/:id/health` and shown as the dashboard badge.
+//!
+//! | Status | When |
+//! |---|---|
+//! | `unhealthy` | No instance is available, or at least half of requests fail |
+//! | `degraded` | Failed probes, an error-rate breach, too few replicas, or a stalled rollout |
+//! | `progressing` | A rollout is under way and nothing above applies |
+//! | `healthy` | None of the above |
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warpgrid_rollout::{Rollout, RolloutPhase};
+use warpgrid_state::{DeploymentSpec, HealthStatus, InstanceState, InstanceStatus, MetricsSnapshot};
+
+/// Error rate (0.0–1.0) at which a deployment is degraded.
+pub const ERROR_RATE_DEGRADED: f64 = 0.05;
+
+/// Error rate (0.0–1.0) at which a deployment is unhealthy.
+pub const ERROR_RATE_UNHEALTHY: f64 = 0.5;
+
+/// Overall status of a deployment, worst first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentStatus {
+    Unhealthy,
+    Degraded,
+    Progressing,
+    Healthy,
+}
+
+impl DeploymentStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeploymentStatus::Unhealthy => "Unhealthy",
+            DeploymentStatus::Degraded => "Degraded",
+            DeploymentStatus::Progressing => "Progressing",
+            DeploymentStatus::Healthy => "Healthy",
+        }
+    }
+}
+
+/// What contributed to a status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonKind {
+    FailedProbes,
+    ErrorRate,
+    InsufficientReplicas,
+    RolloutStalled,
+    RolloutInProgress,
+}
+
+/// One contributing reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct HealthReason {
+    pub kind: ReasonKind,
+    /// The status this reason alone implies.
+    pub status: DeploymentStatus,
+    pub message: String,
+}
+
+/// A deployment's computed health.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DeploymentHealth {
+    pub deployment_id: String,
+    pub status: DeploymentStatus,
+    /// Worst first; empty when healthy.
+    pub reasons: Vec<HealthReason>,
+    /// Running instances that pass their probes.
+    pub available: u32,
+    pub desired: u32,
+    /// Error rate of the latest metrics snapshot.
+    pub error_rate: Option<f64>,
+}
+
+/// Compute the health of `spec` from its instances, latest metrics
+/// snapshot, and rollout.
+pub fn summarize(
+    spec: &DeploymentSpec,
+    instances: &[InstanceState],
+    metrics: Option<&MetricsSnapshot>,
+    rollout: Option<&Rollout>,
+) -> DeploymentHealth {
+    let mut reasons = Vec::new();
+    let mut reason = |kind, status, message: String| reasons.push(HealthReason { kind, status, message });
+
+    let live: Vec<&InstanceState> = instances
+        .iter()
+        .filter(|i| !matches!(i.status, InstanceStatus::Stopping | InstanceStatus::Stopped))
+        .collect();
+    let failing = live
+        .iter()
+        .filter(|i| i.status == InstanceStatus::Unhealthy || i.health == HealthStatus::Unhealthy)
+        .count() as u32;
+    let available = live
+        .iter()
+        .filter(|i| i.status == InstanceStatus::Running && i.health != HealthStatus::Unhealthy)
+        .count() as u32;
+    let desired = spec.instances.min;
+
+    if failing > 0 {
+        reason(
+            ReasonKind::FailedProbes,
+            DeploymentStatus::Degraded,
+            format!("{failing} of {} instance(s) failing health probes", live.len()),
+        );
+    }
+    if available < desired {
+        let status = if available == 0 { DeploymentStatus::Unhealthy } else { DeploymentStatus::Degraded };
+        reason(
+            ReasonKind::InsufficientReplicas,
+            status,
+            format!("{available} of {desired} desired instance(s) available"),
+        );
+    }
+    let error_rate = metrics.map(|m| m.error_rate);
+    if let Some(rate) = error_rate
+        && rate >= ERROR_RATE_DEGRADED
+    {
+        let status =
+            if rate >= ERROR_RATE_UNHEALTHY { DeploymentStatus::Unhealthy } else { DeploymentStatus::Degraded };
+        reason(
+            ReasonKind::ErrorRate,
+            status,
+            format!("error rate {:.1}% exceeds {:.0}%", rate * 100.0, ERROR_RATE_DEGRADED * 100.0),
+        );
+    }
+    if let Some(rollout) = rollout.filter(|r| is_active(&r.phase)) {
+        if rollout.is_stalled() {
+            reason(
+                ReasonKind::RolloutStalled,
+                DeploymentStatus::Degraded,
+                format!("rollout to {} stalled in {:?}", rollout.new_version, rollout.phase),
+            );
+        } else {
+            reason(
+                ReasonKind::RolloutInProgress,
+                DeploymentStatus::Progressing,
+                format!("rolling out {} ({:?})", rollout.new_version, rollout.phase),
+            );
+        }
+    }
+
+    reasons.sort_by_key(|r| r.status);
+    DeploymentHealth {
+        deployment_id: spec.id.clone(),
+        status: reasons.first().map_or(DeploymentStatus::Healthy, |r| r.status),
+        reasons,
+        available,
+        desired,
+        error_rate,
+    }
+}
+
+/// Rollouts that still change the deployment; paused ones count, since
+/// the deployment runs a mix of versions until they finish.
+fn is_active(phase: &RolloutPhase) -> bool {
+    !matches!(phase, RolloutPhase::Pending | RolloutPhase::Completed | RolloutPhase::RolledBack { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use warpgrid_rollout::RolloutStrategy;
+    use warpgrid_state::*;
+
+    fn spec(min: u32) -> DeploymentSpec {
+        DeploymentSpec {
+            id: "default/api".to_string(),
+            namespace: "default".to_string(),
+            name: "api".to_string(),
+            source: "file://api.wasm".to_string(),
+            trigger: TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min, max: 4 },
+            resources: ResourceLimits { memory_bytes: 0, cpu_weight: 100, execution_budget_ms: None },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: HashMap::new(),
+            created_at: 0,
+            updated_at: 0,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        }
+    }
+
+    fn instance(n: u32, status: InstanceStatus, health: HealthStatus) -> InstanceState {
+        InstanceState {
+            id: format!("inst-{n}"),
+            deployment_id: "default/api".to_string(),
+            node_id: "node-1".to_string(),
+            status,
+            health,
+            restart_count: 0,
+            memory_bytes: 0,
+            started_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn metrics(error_rate: f64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            deployment_id: "default/api".to_string(),
+            epoch: 0,
+            rps: 10.0,
+            latency_p50_ms: 1.0,
+            latency_p99_ms: 5.0,
+            error_rate,
+            total_memory_bytes: 0,
+            active_instances: 2,
+        }
+    }
+
+    #[test]
+    fn healthy_and_degraded() {
+        let running = [
+            instance(0, InstanceStatus::Running, HealthStatus::Healthy),
+            instance(1, InstanceStatus::Running, HealthStatus::Unknown),
+        ];
+        let health = summarize(&spec(2), &running, Some(&metrics(0.01)), None);
+        assert_eq!(health.status, DeploymentStatus::Healthy);
+        assert!(health.reasons.is_empty());
+
+        let probe_failing = [
+            instance(0, InstanceStatus::Running, HealthStatus::Healthy),
+            instance(1, InstanceStatus::Running, HealthStatus::Unhealthy),
+        ];
+        let health = summarize(&spec(2), &probe_failing, Some(&metrics(0.08)), None);
+        assert_eq!(health.status, DeploymentStatus::Degraded);
+        let kinds: Vec<_> = health.reasons.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, [ReasonKind::FailedProbes, ReasonKind::InsufficientReplicas, ReasonKind::ErrorRate]);
+        assert_eq!(health.available, 1);
+    }
+
+    #[test]
+    fn unhealthy_outranks_everything() {
+        let health = summarize(&spec(1), &[], Some(&metrics(0.01)), None);
+        assert_eq!(health.status, DeploymentStatus::Unhealthy);
+        assert_eq!(health.reasons[0].kind, ReasonKind::InsufficientReplicas);
+
+        let running = [instance(0, InstanceStatus::Running, HealthStatus::Healthy)];
+        let health = summarize(&spec(1), &running, Some(&metrics(0.6)), None);
+        assert_eq!(health.status, DeploymentStatus::Unhealthy);
+        assert_eq!(health.reasons[0].kind, ReasonKind::ErrorRate);
+    }
+
+    #[test]
+    fn active_rollouts_are_progressing() {
+        let running = [instance(0, InstanceStatus::Running, HealthStatus::Healthy)];
+        let mut rollout = Rollout::new("default/api", RolloutStrategy::default(), 1, "v1", "v2");
+        let health = summarize(&spec(1), &running, None, Some(&rollout));
+        assert_eq!(health.status, DeploymentStatus::Healthy, "not started yet");
+
+        rollout.start();
+        let health = summarize(&spec(1), &running, None, Some(&rollout));
+        assert_eq!(health.status, DeploymentStatus::Progressing);
+        assert_eq!(health.reasons[0].kind, ReasonKind::RolloutInProgress);
+
+        // Problems during a rollout still show.
+        let health = summarize(&spec(2), &running, None, Some(&rollout));
+        assert_eq!(health.status, DeploymentStatus::Degraded);
+        assert_eq!(health.reasons.len(), 2);
+    }
+}
@@ -16,7 +16,8 @@ wasmtime::component::bindgen!({
 /// type allows the host to instantiate components that export
 /// `handle-request` and invoke them.
 ///
-/// Import-side types (filesystem, dns, signals, database-proxy, threading)
+/// Import-side types (filesystem, dns, signals, database-proxy, threading, render,
+/// feature-flags)
 /// are shared with the `warpgrid-shims` bindings via the `with` parameter,
 /// so `HostState` only needs one set of Host trait implementations.
 pub mod async_handler_bindings {
@@ -29,6 +30,8 @@ pub mod async_handler_bindings {
             "warpgrid:shim/signals": super::warpgrid::shim::signals,
             "warpgrid:shim/database-proxy": super::warpgrid::shim::database_proxy,
             "warpgrid:shim/threading": super::warpgrid::shim::threading,
+            "warpgrid:shim/render": super::warpgrid::shim::render,
+            "warpgrid:shim/feature-flags": super::warpgrid::shim::feature_flags,
         },
         exports: { default: async },
     });
@@ -0,0 +1,157 @@
+//! Config bundle delivery.
+//!
+//! Serves each deployment's active [`ConfigBundle`] to its guests through
+//! the filesystem shim and tells them about swaps with `SIGHUP`.
+//!
+//! # Architecture
+//!
+//! [`BundleRegistry`] is node-wide and shared by every clone of the engine.
+//! It holds one [`SharedBundle`] per deployment. Every instance of the
+//! deployment reads through that handle, so [`BundleRegistry::update`]
+//! switches all of them at once: the next `open` on any instance sees the
+//! new files, and each instance's signals shim queues a `Hangup` the next
+//! time the guest polls. The control plane only activates a bundle once
+//! every node running the deployment holds it, so instances never run
+//! with a mix of old and new files.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+pub use warp_core::bundle::ConfigBundle;
+
+/// The bundle a deployment's instances currently read.
+#[derive(Debug, Clone, Default)]
+pub struct ActiveBundle {
+    /// Bumped on every swap; instances compare it to spot one.
+    pub generation: u64,
+    /// Digest of `bundle`, `None` while the deployment has no bundle.
+    pub digest: Option<String>,
+    pub bundle: Arc<ConfigBundle>,
+}
+
+/// A deployment's active bundle, shared by all of its instances.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBundle(Arc<RwLock<ActiveBundle>>);
+
+impl SharedBundle {
+    /// The bundle as of now.
+    pub fn snapshot(&self) -> ActiveBundle {
+        self.0.read().expect("bundle lock poisoned").clone()
+    }
+
+    /// The current generation, without cloning the bundle.
+    pub fn generation(&self) -> u64 {
+        self.0.read().expect("bundle lock poisoned").generation
+    }
+
+    /// Swap in `bundle`. Returns `false` when `digest` is already active.
+    fn replace(&self, digest: Option<String>, bundle: ConfigBundle) -> bool {
+        let mut current = self.0.write().expect("bundle lock poisoned");
+        if current.digest == digest {
+            return false;
+        }
+        current.generation += 1;
+        current.digest = digest;
+        current.bundle = Arc::new(bundle);
+        true
+    }
+}
+
+/// Node-wide map of deployment id → [`SharedBundle`].
+#[derive(Debug, Clone, Default)]
+pub struct BundleRegistry {
+    deployments: Arc<RwLock<HashMap<String, SharedBundle>>>,
+}
+
+impl BundleRegistry {
+    /// The handle for `deployment_id`, created empty on first use.
+    pub fn handle(&self, deployment_id: &str) -> SharedBundle {
+        if let Some(bundle) = self.deployments.read().expect("bundle lock poisoned").get(deployment_id) {
+            return bundle.clone();
+        }
+        self.deployments
+            .write()
+            .expect("bundle lock poisoned")
+            .entry(deployment_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Activate `bundle` for `deployment_id`. Returns `true` when it changed.
+    pub fn update(&self, deployment_id: &str, bundle: ConfigBundle) -> bool {
+        let digest = bundle.digest();
+        let changed = self.handle(deployment_id).replace(Some(digest.clone()), bundle);
+        if changed {
+            tracing::info!(deployment = %deployment_id, %digest, "config bundle activated");
+        }
+        changed
+    }
+
+    /// Drop `deployment_id`'s bundle; instances see no bundle files.
+    pub fn clear(&self, deployment_id: &str) -> bool {
+        let changed = self.handle(deployment_id).replace(None, ConfigBundle::default());
+        if changed {
+            tracing::info!(deployment = %deployment_id, "config bundle cleared");
+        }
+        changed
+    }
+
+    /// Make `bundles` the active bundles of this node: listed deployments
+    /// get their bundle, every other known deployment is cleared. Used by
+    /// the daemon to follow the state store.
+    pub fn replace_all(&self, bundles: impl IntoIterator<Item = (String, ConfigBundle)>) {
+        let bundles: HashMap<String, ConfigBundle> = bundles.into_iter().collect();
+        for deployment_id in self.deployments() {
+            if !bundles.contains_key(&deployment_id) {
+                self.clear(&deployment_id);
+            }
+        }
+        for (deployment_id, bundle) in bundles {
+            self.update(&deployment_id, bundle);
+        }
+    }
+
+    /// Deployments with a handle on this node.
+    pub fn deployments(&self) -> Vec<String> {
+        self.deployments.read().expect("bundle lock poisoned").keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(content: &str) -> ConfigBundle {
+        let mut bundle = ConfigBundle::default();
+        bundle.files.insert("/etc/app.toml".to_string(), content.to_string());
+        bundle
+    }
+
+    #[test]
+    fn swaps_bump_the_generation_once() {
+        let registry = BundleRegistry::default();
+        let handle = registry.handle("default/api");
+        assert_eq!(handle.generation(), 0);
+        assert!(handle.snapshot().digest.is_none());
+
+        assert!(registry.update("default/api", bundle("port = 80")));
+        assert!(!registry.update("default/api", bundle("port = 80")), "same digest");
+        assert_eq!(handle.generation(), 1);
+        assert_eq!(handle.snapshot().bundle.file("/etc/app.toml"), Some("port = 80"));
+
+        registry.update("default/api", bundle("port = 81"));
+        assert_eq!(handle.generation(), 2);
+    }
+
+    #[test]
+    fn replace_all_clears_unlisted_deployments() {
+        let registry = BundleRegistry::default();
+        let api = registry.handle("default/api");
+        registry.update("default/api", bundle("a"));
+
+        registry.replace_all([("default/web".to_string(), bundle("b"))]);
+        assert!(api.snapshot().bundle.files.is_empty());
+        assert_eq!(api.generation(), 2);
+        assert_eq!(registry.handle("default/web").snapshot().bundle.file("/etc/app.toml"), Some("b"));
+    }
+}
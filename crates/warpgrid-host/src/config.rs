@@ -2,7 +2,7 @@
 //!
 //! Parses WarpGrid deployment specifications into shim configuration:
 //! virtual filesystem entries, DNS overrides, database pool settings,
-//! signal handlers, and threading model.
+//! signal handlers, threading model, document rendering, and feature flags.
 //!
 //! Supports two parsing paths:
 //! - `ShimConfig::from_warp_config()` — from a typed `warp-core::ShimsConfig`
@@ -14,6 +14,7 @@ use std::time::Duration;
 
 use crate::db_proxy::PoolConfig;
 use crate::dns::cache::DnsCacheConfig;
+use crate::render::RenderQuotas;
 
 /// Known shim domain names for forward-compatibility validation.
 const KNOWN_SHIM_KEYS: &[&str] = &[
@@ -22,6 +23,8 @@ const KNOWN_SHIM_KEYS: &[&str] = &[
     "signals",
     "database_proxy",
     "threading",
+    "render",
+    "flags",
 ];
 
 /// Domain-specific configuration for the DNS shim.
@@ -31,6 +34,9 @@ pub struct DnsConfig {
     pub ttl_seconds: u64,
     /// Maximum number of cached DNS entries (default: 1024).
     pub cache_size: usize,
+    /// Seconds an expired entry may still be answered while the upstream
+    /// resolver is unreachable (default: 0, disabled).
+    pub max_stale_seconds: u64,
 }
 
 impl Default for DnsConfig {
@@ -38,6 +44,7 @@ impl Default for DnsConfig {
         Self {
             ttl_seconds: 30,
             cache_size: 1024,
+            max_stale_seconds: 0,
         }
     }
 }
@@ -48,6 +55,7 @@ impl DnsConfig {
         DnsCacheConfig {
             ttl: Duration::from_secs(self.ttl_seconds),
             max_entries: self.cache_size,
+            max_stale: Duration::from_secs(self.max_stale_seconds),
         }
     }
 }
@@ -112,6 +120,61 @@ impl DatabaseProxyConfig {
     }
 }
 
+/// Domain-specific configuration for the document rendering shim.
+///
+/// Rendering depends on an external renderer binary, so it is only
+/// available on nodes where `command` is configured.
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    /// Renderer executable (reads HTML on stdin, writes PDF to stdout).
+    pub command: Option<String>,
+    /// Fixed arguments passed to the renderer.
+    pub args: Vec<String>,
+    /// Maximum HTML input size in bytes (default: 4 MiB).
+    pub max_input_bytes: usize,
+    /// Maximum PDF output size in bytes (default: 32 MiB).
+    pub max_output_bytes: usize,
+    /// Per-job timeout in seconds (default: 30).
+    pub timeout_seconds: u64,
+    /// Maximum render jobs per instance (default: unlimited).
+    pub max_jobs_per_instance: Option<u32>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        let quotas = RenderQuotas::default();
+        Self {
+            command: None,
+            args: Vec::new(),
+            max_input_bytes: quotas.max_input_bytes,
+            max_output_bytes: quotas.max_output_bytes,
+            timeout_seconds: quotas.timeout.as_secs(),
+            max_jobs_per_instance: None,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Convert to the per-job `RenderQuotas` enforced by the renderer.
+    pub fn to_quotas(&self) -> RenderQuotas {
+        RenderQuotas {
+            max_input_bytes: self.max_input_bytes,
+            max_output_bytes: self.max_output_bytes,
+            timeout: Duration::from_secs(self.timeout_seconds),
+        }
+    }
+}
+
+/// Version of the `warpgrid:shim` WIT package in `wit/`.
+pub const SHIM_PACKAGE_VERSION: &str = "0.1.0";
+
+/// Worlds of the `warpgrid:shim` package a component can target.
+pub const SHIM_WORLDS: [&str; 3] = [
+    "warpgrid-shims",
+    "warpgrid-async-handler",
+    "warpgrid-lifecycle-handler",
+];
+
 /// Host-side shim configuration for a single Wasm instance.
 ///
 /// Built from a `warp-core::ShimsConfig` (the user-facing TOML config)
@@ -128,12 +191,18 @@ pub struct ShimConfig {
     pub database_proxy: bool,
     /// Enable threading model declaration shim.
     pub threading: bool,
+    /// Enable document rendering shim (off by default; needs a node-local renderer).
+    pub render: bool,
+    /// Enable feature flag shim.
+    pub flags: bool,
     /// Domain-specific filesystem configuration.
     pub filesystem_config: FilesystemConfig,
     /// Domain-specific DNS configuration.
     pub dns_config: DnsConfig,
     /// Domain-specific database proxy configuration.
     pub database_proxy_config: DatabaseProxyConfig,
+    /// Domain-specific rendering configuration.
+    pub render_config: RenderConfig,
     /// DNS cache configuration (derived from dns_config).
     pub dns_cache_config: DnsCacheConfig,
     /// Service registry entries for DNS resolution.
@@ -156,10 +225,13 @@ impl Default for ShimConfig {
             signals: true,
             database_proxy: true,
             threading: true,
+            render: false,
+            flags: true,
             filesystem_config: FilesystemConfig::default(),
             dns_cache_config: dns_config.to_cache_config(),
             dns_config,
             database_proxy_config: db_config.clone(),
+            render_config: RenderConfig::default(),
             service_registry: HashMap::new(),
             etc_hosts_content: String::new(),
             pool_config: db_config.to_pool_config(),
@@ -174,6 +246,20 @@ impl ShimConfig {
         Self::default()
     }
 
+    /// Each shim's WIT interface name (`warpgrid:shim/<name>`) and whether
+    /// it is enabled.
+    pub fn interfaces(&self) -> [(&'static str, bool); 7] {
+        [
+            ("filesystem", self.filesystem),
+            ("dns", self.dns),
+            ("signals", self.signals),
+            ("database-proxy", self.database_proxy),
+            ("threading", self.threading),
+            ("render", self.render),
+            ("feature-flags", self.flags),
+        ]
+    }
+
     /// Parse a `ShimConfig` from a raw `toml::Value` representing the `[shims]` table.
     ///
     /// If `value` is `None` (missing `[shims]` section), returns the default config
@@ -244,6 +330,9 @@ impl ShimConfig {
                     if let Some(size) = t.get("cache_size").and_then(|v| v.as_integer()) {
                         config.dns_config.cache_size = size as usize;
                     }
+                    if let Some(stale) = t.get("max_stale_seconds").and_then(|v| v.as_integer()) {
+                        config.dns_config.max_stale_seconds = stale as u64;
+                    }
                     config.dns_cache_config = config.dns_config.to_cache_config();
                 }
                 _ => anyhow::bail!("shims.dns must be a boolean or table"),
@@ -257,6 +346,13 @@ impl ShimConfig {
                 .ok_or_else(|| anyhow::anyhow!("shims.signals must be a boolean"))?;
         }
 
+        // Parse flags — bool only
+        if let Some(val) = table.get("flags") {
+            config.flags = val
+                .as_bool()
+                .ok_or_else(|| anyhow::anyhow!("shims.flags must be a boolean"))?;
+        }
+
         // Parse database_proxy — accepts bool or table with sub-config
         if let Some(val) = table.get("database_proxy") {
             match val {
@@ -305,6 +401,52 @@ impl ShimConfig {
                 .ok_or_else(|| anyhow::anyhow!("shims.threading must be a boolean"))?;
         }
 
+        // Parse render — accepts bool or table with sub-config
+        if let Some(val) = table.get("render") {
+            match val {
+                toml::Value::Boolean(b) => {
+                    config.render = *b;
+                }
+                toml::Value::Table(t) => {
+                    config.render = t.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true);
+                    if let Some(cmd) = t.get("command").and_then(|v| v.as_str()) {
+                        config.render_config.command = Some(cmd.to_string());
+                    }
+                    if let Some(args) = t.get("args").and_then(|v| v.as_array()) {
+                        config.render_config.args = args
+                            .iter()
+                            .filter_map(|a| a.as_str().map(String::from))
+                            .collect();
+                    }
+                    if let Some(max) = t.get("max_input_bytes").and_then(|v| v.as_integer()) {
+                        config.render_config.max_input_bytes = usize::try_from(max).map_err(|_| {
+                            anyhow::anyhow!("shims.render.max_input_bytes must be non-negative, got {max}")
+                        })?;
+                    }
+                    if let Some(max) = t.get("max_output_bytes").and_then(|v| v.as_integer()) {
+                        config.render_config.max_output_bytes = usize::try_from(max).map_err(|_| {
+                            anyhow::anyhow!("shims.render.max_output_bytes must be non-negative, got {max}")
+                        })?;
+                    }
+                    if let Some(timeout) = t.get("timeout_seconds").and_then(|v| v.as_integer()) {
+                        config.render_config.timeout_seconds = u64::try_from(timeout).map_err(|_| {
+                            anyhow::anyhow!("shims.render.timeout_seconds must be non-negative, got {timeout}")
+                        })?;
+                    }
+                    if let Some(max) = t.get("max_jobs_per_instance").and_then(|v| v.as_integer()) {
+                        config.render_config.max_jobs_per_instance =
+                            Some(u32::try_from(max).map_err(|_| {
+                                anyhow::anyhow!(
+                                    "shims.render.max_jobs_per_instance must be between 0 and {}, got {max}",
+                                    u32::MAX
+                                )
+                            })?);
+                    }
+                }
+                _ => anyhow::bail!("shims.render must be a boolean or table"),
+            }
+        }
+
         Ok(config)
     }
 
@@ -370,6 +512,7 @@ mod tests {
         let config = ShimConfig::default();
         assert_eq!(config.dns_config.ttl_seconds, 30);
         assert_eq!(config.dns_config.cache_size, 1024);
+        assert_eq!(config.dns_config.max_stale_seconds, 0);
     }
 
     #[test]
@@ -447,6 +590,7 @@ mod tests {
             enabled = true
             ttl_seconds = 60
             cache_size = 2048
+            max_stale_seconds = 120
         "#;
         let value: toml::Value = toml::from_str(toml_str).unwrap();
         let config = ShimConfig::from_toml(Some(&value)).unwrap();
@@ -454,6 +598,7 @@ mod tests {
         assert!(config.dns);
         assert_eq!(config.dns_config.ttl_seconds, 60);
         assert_eq!(config.dns_config.cache_size, 2048);
+        assert_eq!(config.dns_cache_config.max_stale, std::time::Duration::from_secs(120));
     }
 
     #[test]
@@ -542,6 +687,64 @@ mod tests {
         assert_eq!(config.database_proxy_config.pool_size, 5);
     }
 
+    // ---- from_toml: render shim ----
+
+    #[test]
+    fn default_render_is_disabled() {
+        let config = ShimConfig::default();
+        assert!(!config.render);
+        assert!(config.render_config.command.is_none());
+    }
+
+    #[test]
+    fn from_toml_render_table_with_quotas() {
+        let toml_str = r#"
+            [render]
+            command = "/usr/local/bin/html2pdf"
+            args = ["--quiet"]
+            max_input_bytes = 1048576
+            timeout_seconds = 10
+            max_jobs_per_instance = 5
+        "#;
+        let value: toml::Value = toml::from_str(toml_str).unwrap();
+        let config = ShimConfig::from_toml(Some(&value)).unwrap();
+
+        assert!(config.render);
+        assert_eq!(
+            config.render_config.command.as_deref(),
+            Some("/usr/local/bin/html2pdf")
+        );
+        assert_eq!(config.render_config.args, vec!["--quiet".to_string()]);
+        assert_eq!(config.render_config.max_jobs_per_instance, Some(5));
+
+        let quotas = config.render_config.to_quotas();
+        assert_eq!(quotas.max_input_bytes, 1_048_576);
+        assert_eq!(quotas.max_output_bytes, 32 * 1024 * 1024);
+        assert_eq!(quotas.timeout, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn from_toml_render_rejects_negative_quotas() {
+        for field in ["max_input_bytes", "max_output_bytes", "timeout_seconds", "max_jobs_per_instance"] {
+            let value: toml::Value = toml::from_str(&format!("[render]\n{field} = -1")).unwrap();
+            let err = ShimConfig::from_toml(Some(&value)).unwrap_err().to_string();
+            assert!(err.contains(field), "{field}: {err}");
+        }
+    }
+
+    #[test]
+    fn from_toml_wrong_type_for_render_errors() {
+        let value: toml::Value = toml::from_str("render = 1").unwrap();
+        let result = ShimConfig::from_toml(Some(&value));
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("render must be a boolean or table")
+        );
+    }
+
     // ---- from_toml: unknown shim names warn but don't error ----
 
     #[test]
@@ -580,6 +783,17 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("signals must be a boolean"));
     }
 
+    #[test]
+    fn from_toml_flags_toggle() {
+        assert!(ShimConfig::default().flags);
+        let value: toml::Value = toml::from_str("flags = false").unwrap();
+        assert!(!ShimConfig::from_toml(Some(&value)).unwrap().flags);
+
+        let value: toml::Value = toml::from_str("flags = 1").unwrap();
+        let err = ShimConfig::from_toml(Some(&value)).unwrap_err();
+        assert!(err.to_string().contains("flags must be a boolean"));
+    }
+
     #[test]
     fn from_toml_wrong_type_for_threading_errors() {
         let toml_str = r#"
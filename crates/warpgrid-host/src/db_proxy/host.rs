@@ -36,6 +36,8 @@ pub struct DbProxyHost {
     pool_manager: Arc<ConnectionPoolManager>,
     /// Tokio runtime handle for running async operations from sync context.
     runtime_handle: tokio::runtime::Handle,
+    /// Password used when the guest connects without one.
+    default_password: Option<String>,
 }
 
 impl DbProxyHost {
@@ -47,8 +49,16 @@ impl DbProxyHost {
         Self {
             pool_manager,
             runtime_handle,
+            default_password: None,
         }
     }
+
+    /// Send `password` for connections whose config has none, so the
+    /// guest never has to hold the credential.
+    pub fn with_default_password(mut self, password: Option<String>) -> Self {
+        self.default_password = password;
+        self
+    }
 }
 
 impl Host for DbProxyHost {
@@ -62,7 +72,7 @@ impl Host for DbProxyHost {
         );
 
         let key = PoolKey::new(&config.host, config.port, &config.database, &config.user);
-        let password = config.password.as_deref();
+        let password = config.password.as_deref().or(self.default_password.as_deref());
         let mgr = Arc::clone(&self.pool_manager);
 
         let handle = self.runtime_handle.clone();
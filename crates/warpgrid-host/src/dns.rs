@@ -7,7 +7,11 @@
 //!
 //! Resolution stops at the first chain link that returns results.
 //! Results are cached with configurable TTL and returned in round-robin
-//! order for load balancing across service replicas.
+//! order for load balancing across service replicas. Optionally, expired
+//! results are served stale for a configurable window while the upstream
+//! resolver is unreachable, and refreshed in the background, so a brief
+//! outage does not fail lookups. A name the upstream no longer knows is
+//! evicted rather than served stale.
 //! All resolution steps are logged at `tracing::debug` level.
 
 pub mod cache;
@@ -15,7 +19,7 @@ pub mod host;
 
 use std::collections::HashMap;
 use std::net::IpAddr;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use cache::{DnsCache, DnsCacheConfig};
 
@@ -75,6 +79,33 @@ impl EtcHosts {
     }
 }
 
+/// Prefix of the error for a lookup that failed because the upstream
+/// resolver could not be reached, rather than because the name is unknown
+/// (`HostNotFound`).
+pub const UNAVAILABLE: &str = "Unavailable";
+
+/// Whether a [`DnsResolver::resolve`] error means the upstream resolver was
+/// unreachable, so the name may still exist.
+pub fn is_unavailable(err: &str) -> bool {
+    err.starts_with(UNAVAILABLE)
+}
+
+/// Whether a failed system lookup says nothing about the name itself: the
+/// resolver timed out, could not be reached, or asked to try again
+/// (`EAI_AGAIN`).
+fn is_transient(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    matches!(
+        err.kind(),
+        TimedOut
+            | ConnectionRefused
+            | ConnectionReset
+            | NetworkUnreachable
+            | HostUnreachable
+            | Interrupted
+    ) || err.to_string().contains("Temporary failure")
+}
+
 /// DNS resolver with a three-tier resolution chain.
 ///
 /// Constructed immutably with an injected service registry and `/etc/hosts`
@@ -110,7 +141,8 @@ impl DnsResolver {
     /// Resolve a hostname through the three-tier chain.
     ///
     /// Returns `Ok(addresses)` on success, or `Err` with a `HostNotFound`
-    /// message if no chain link resolves the hostname.
+    /// message if no chain link resolves the hostname, or an
+    /// [`UNAVAILABLE`] one if system DNS could not be reached.
     ///
     /// This is an async method because the final fallback uses
     /// `tokio::net::lookup_host`.
@@ -176,7 +208,11 @@ impl DnsResolver {
                     error = %e,
                     "system DNS lookup failed"
                 );
-                Err(format!("HostNotFound: {hostname}"))
+                if is_transient(&e) {
+                    Err(format!("{UNAVAILABLE}: {hostname}: {e}"))
+                } else {
+                    Err(format!("HostNotFound: {hostname}"))
+                }
             }
         }
     }
@@ -185,8 +221,13 @@ impl DnsResolver {
 /// DNS resolver with TTL caching and round-robin address selection.
 ///
 /// Wraps a [`DnsResolver`] and a [`DnsCache`], caching successful resolution
-/// results with a configurable TTL. Consecutive lookups for hostnames with
-/// multiple addresses are returned in round-robin order using per-hostname
+/// results with a configurable TTL. An expired entry is re-resolved on the
+/// next lookup. If the upstream is unreachable then, the entry is still
+/// returned for up to `max_stale`: the next lookup starts a background
+/// re-resolution and every lookup gets the stale answer until it lands. A
+/// refresh that cannot reach the upstream keeps the stale entry and is
+/// retried on the next lookup. An answer that the name is gone evicts the
+/// entry. Consecutive lookups for hostnames with multiple addresses are returned in round-robin order using per-hostname
 /// atomic counters (no mutex contention on the hot path once the cache
 /// lock is released).
 ///
@@ -197,9 +238,9 @@ impl DnsResolver {
 /// resolution runs *outside* the lock.
 pub struct CachedDnsResolver {
     /// The underlying resolver implementing the three-tier chain.
-    resolver: DnsResolver,
-    /// TTL-bounded, LRU-evicting DNS cache.
-    cache: Mutex<DnsCache>,
+    resolver: Arc<DnsResolver>,
+    /// TTL-bounded, LRU-evicting DNS cache, shared with background refreshes.
+    cache: Arc<Mutex<DnsCache>>,
 }
 
 impl CachedDnsResolver {
@@ -210,26 +251,86 @@ impl CachedDnsResolver {
     /// - `cache_config` — TTL and capacity configuration for the cache
     pub fn new(resolver: DnsResolver, cache_config: DnsCacheConfig) -> Self {
         Self {
-            resolver,
-            cache: Mutex::new(DnsCache::new(cache_config)),
+            resolver: Arc::new(resolver),
+            cache: Arc::new(Mutex::new(DnsCache::new(cache_config))),
+        }
+    }
+
+    /// Re-resolve `hostname` in the background and replace its stale entry.
+    ///
+    /// Must be called from within a tokio runtime.
+    fn spawn_refresh(&self, hostname: &str) {
+        let resolver = Arc::clone(&self.resolver);
+        let cache = Arc::clone(&self.cache);
+        let hostname = hostname.to_string();
+        tokio::spawn(async move {
+            match resolver.resolve(&hostname).await {
+                Ok(addrs) => cache.lock().unwrap().insert(&hostname, addrs),
+                Err(e) if is_unavailable(&e) => {
+                    warpgrid_metrics::dns::dns_cache().refresh_failed();
+                    tracing::warn!(
+                        hostname = %hostname,
+                        error = %e,
+                        "dns refresh failed, serving stale answer"
+                    );
+                    cache.lock().unwrap().refresh_failed(&hostname);
+                }
+                Err(e) => {
+                    tracing::debug!(hostname = %hostname, error = %e, "dns name gone, evicting stale answer");
+                    cache.lock().unwrap().remove(&hostname);
+                }
+            }
+        });
+    }
+
+    /// Handle a lookup of `hostname` that missed the cache and failed with
+    /// `err`. If the upstream was unreachable, `stale` may answer from an
+    /// expired entry still within `max_stale`; a name the upstream does not
+    /// know is evicted.
+    fn lookup_failed<T>(
+        &self,
+        hostname: &str,
+        err: String,
+        stale: impl FnOnce(&mut DnsCache) -> Option<T>,
+    ) -> Result<T, String> {
+        let mut cache = self.cache.lock().unwrap();
+        if is_unavailable(&err) {
+            if let Some(answer) = stale(&mut cache) {
+                tracing::warn!(hostname = %hostname, error = %err, "dns upstream unreachable, serving stale answer");
+                return Ok(answer);
+            }
+        } else {
+            cache.remove(hostname);
         }
+        Err(err)
     }
 
     /// Resolve a hostname, returning all addresses.
     ///
-    /// Checks the cache first. On a miss (or TTL expiry), delegates to the
-    /// underlying resolver and caches the result.
+    /// Checks the cache first, serving stale entries while an unreachable
+    /// upstream is retried. On a miss, delegates to the underlying resolver
+    /// and caches the result.
     pub async fn resolve(&self, hostname: &str) -> Result<Vec<IpAddr>, String> {
         // Fast path: check cache
-        {
+        let stale = {
             let mut cache = self.cache.lock().unwrap();
             if let Some(addrs) = cache.get(hostname) {
                 return Ok(addrs.to_vec());
             }
+            cache.get_stale(hostname)
+        };
+        if let Some((addrs, refresh)) = stale {
+            if refresh {
+                self.spawn_refresh(hostname);
+            }
+            return Ok(addrs);
         }
 
         // Cache miss — resolve through the chain
-        let addrs = self.resolver.resolve(hostname).await?;
+        let addrs = match self.resolver.resolve(hostname).await {
+            Ok(addrs) => addrs,
+            Err(e) => return self.lookup_failed(hostname, e, |cache| cache.serve_stale(hostname)),
+        };
 
         // Populate cache
         {
@@ -246,15 +347,28 @@ impl CachedDnsResolver {
     /// the first address. Subsequent calls cycle through all cached addresses.
     pub async fn resolve_round_robin(&self, hostname: &str) -> Result<IpAddr, String> {
         // Fast path: check cache for round-robin hit
-        {
+        let stale = {
             let mut cache = self.cache.lock().unwrap();
             if let Some(addr) = cache.get_round_robin(hostname) {
                 return Ok(addr);
             }
+            cache.get_stale_round_robin(hostname)
+        };
+        if let Some((addr, refresh)) = stale {
+            if refresh {
+                self.spawn_refresh(hostname);
+            }
+            return Ok(addr);
         }
 
         // Cache miss — resolve through the chain
-        let addrs = self.resolver.resolve(hostname).await?;
+        let addrs = match self.resolver.resolve(hostname).await {
+            Ok(addrs) => addrs,
+            Err(e) => {
+                return self
+                    .lookup_failed(hostname, e, |cache| cache.serve_stale_round_robin(hostname));
+            }
+        };
 
         if addrs.is_empty() {
             return Err(format!("HostNotFound: {hostname}"));
@@ -288,6 +402,11 @@ impl CachedDnsResolver {
         let cache = self.cache.lock().unwrap();
         cache.stats()
     }
+
+    /// Get the number of lookups answered with a stale entry.
+    pub fn stale_hits(&self) -> u64 {
+        self.cache.lock().unwrap().stale_hits()
+    }
 }
 
 #[cfg(test)]
@@ -603,6 +722,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_millis(50),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         };
         let cached = make_cached_resolver(registry, "", config);
 
@@ -620,6 +740,74 @@ mod tests {
         assert_eq!(misses, 2);
     }
 
+    #[tokio::test]
+    async fn cached_resolve_serves_stale_only_while_unreachable() {
+        use std::time::Duration;
+
+        let mut registry = HashMap::new();
+        let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        registry.insert("svc.warp.local".to_string(), vec![addr]);
+        let config = DnsCacheConfig {
+            ttl: Duration::from_millis(50),
+            max_entries: 1024,
+            max_stale: Duration::from_secs(30),
+        };
+        let cached = make_cached_resolver(registry, "", config);
+
+        cached.resolve("svc.warp.local").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(80)).await;
+
+        // Expired, with the upstream answering: re-resolved, not stale.
+        assert_eq!(cached.resolve("svc.warp.local").await.unwrap(), vec![addr]);
+        assert_eq!(cached.stale_hits(), 0);
+        tokio::time::sleep(Duration::from_millis(80)).await;
+
+        // Expired, with the upstream unreachable: answered stale, and the
+        // next lookup is stale too while a refresh runs.
+        let unreachable = format!("{UNAVAILABLE}: svc.warp.local: timed out");
+        let stale = cached.lookup_failed("svc.warp.local", unreachable, |cache| {
+            cache.serve_stale("svc.warp.local")
+        });
+        assert_eq!(stale.unwrap(), vec![addr]);
+        assert_eq!(cached.resolve("svc.warp.local").await.unwrap(), vec![addr]);
+        assert_eq!(cached.stale_hits(), 2);
+
+        // Once the refresh lands, lookups are fresh hits again.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(cached.resolve("svc.warp.local").await.unwrap(), vec![addr]);
+        let (hits, misses, _) = cached.cache_stats();
+        assert_eq!((hits, misses), (1, 2));
+        assert_eq!(cached.stale_hits(), 2);
+    }
+
+    #[tokio::test]
+    async fn cached_resolve_evicts_names_the_upstream_no_longer_knows() {
+        use std::time::Duration;
+
+        let mut registry = HashMap::new();
+        registry.insert(
+            "svc.warp.local".to_string(),
+            vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))],
+        );
+        let config = DnsCacheConfig {
+            ttl: Duration::from_millis(50),
+            max_entries: 1024,
+            max_stale: Duration::from_secs(30),
+        };
+        let cached = make_cached_resolver(registry, "", config);
+
+        cached.resolve("svc.warp.local").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(80)).await;
+
+        let gone = "HostNotFound: svc.warp.local".to_string();
+        let result = cached.lookup_failed("svc.warp.local", gone, |cache| {
+            cache.serve_stale("svc.warp.local")
+        });
+        assert!(result.unwrap_err().contains("HostNotFound"));
+        assert_eq!(cached.stale_hits(), 0);
+        assert!(cached.cache.lock().unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn cached_round_robin_cycles_addresses() {
         let mut registry = HashMap::new();
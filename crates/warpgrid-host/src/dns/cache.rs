@@ -5,8 +5,14 @@
 //! a hostname, [`DnsCache::get_round_robin`] returns them in rotating order
 //! using a per-entry atomic counter (no mutex contention on the hot path).
 //!
-//! Cache statistics (hits, misses, evictions) are emitted as `tracing::info`
-//! metrics.
+//! When the upstream resolver is unreachable, an entry past its TTL can
+//! still be served for up to `max_stale` ([`DnsCache::serve_stale`], then
+//! [`DnsCache::get_stale`] while it is refreshed in the background), so a
+//! brief outage does not fail lookups. Stale answers are off by default.
+//!
+//! Cache statistics (hits, stale hits, misses, evictions) are emitted as
+//! `tracing::info` metrics and counted process-wide in
+//! [`warpgrid_metrics::dns`].
 
 use std::collections::HashMap;
 use std::net::IpAddr;
@@ -20,6 +26,9 @@ pub struct DnsCacheConfig {
     pub ttl: Duration,
     /// Maximum number of entries in the cache (default: 1024).
     pub max_entries: usize,
+    /// How long past its TTL an entry may still be served while the
+    /// upstream is unreachable (default: zero, which disables stale answers).
+    pub max_stale: Duration,
 }
 
 impl Default for DnsCacheConfig {
@@ -27,6 +36,7 @@ impl Default for DnsCacheConfig {
         Self {
             ttl: Duration::from_secs(30),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         }
     }
 }
@@ -41,6 +51,11 @@ struct CacheEntry {
     round_robin_counter: AtomicUsize,
     /// Last access time for LRU tracking (stored as nanos since cache creation).
     last_accessed_nanos: AtomicU64,
+    /// Set once the upstream was unreachable after the TTL ran out, so
+    /// the entry is served stale until it is refreshed.
+    unreachable: bool,
+    /// Set once a stale read has asked for a refresh, so only one runs.
+    refreshing: bool,
 }
 
 impl CacheEntry {
@@ -52,6 +67,8 @@ impl CacheEntry {
             inserted_at: now,
             round_robin_counter: AtomicUsize::new(0),
             last_accessed_nanos: AtomicU64::new(nanos),
+            unreachable: false,
+            refreshing: false,
         }
     }
 
@@ -96,6 +113,7 @@ pub struct DnsCache {
 /// Accumulated cache statistics.
 struct CacheStats {
     hits: u64,
+    stale: u64,
     misses: u64,
     evictions: u64,
 }
@@ -109,6 +127,7 @@ impl DnsCache {
             epoch: Instant::now(),
             stats: CacheStats {
                 hits: 0,
+                stale: 0,
                 misses: 0,
                 evictions: 0,
             },
@@ -118,16 +137,25 @@ impl DnsCache {
     /// Look up a hostname in the cache, returning all addresses if present and not expired.
     ///
     /// Returns `None` on cache miss or TTL expiration. Expired entries are
-    /// removed eagerly.
+    /// removed eagerly, except those still within `max_stale`, which are
+    /// kept in case the upstream is unreachable. One already being served
+    /// stale is left for [`DnsCache::get_stale`] and not counted here.
     pub fn get(&mut self, hostname: &str) -> Option<&[IpAddr]> {
         let key = hostname.to_lowercase();
 
         // Check if entry exists and is not expired
         if let Some(entry) = self.entries.get(&key) {
             if entry.is_expired(self.config.ttl) {
-                // Expired — remove and count as miss
-                self.entries.remove(&key);
+                if self.is_servable_stale(entry) {
+                    if entry.unreachable {
+                        return None;
+                    }
+                } else {
+                    self.entries.remove(&key);
+                }
+                // Expired — count as miss
                 self.stats.misses += 1;
+                warpgrid_metrics::dns::dns_cache().miss();
                 tracing::info!(
                     hostname = %hostname,
                     cache_hits = self.stats.hits,
@@ -139,6 +167,7 @@ impl DnsCache {
             }
         } else {
             self.stats.misses += 1;
+            warpgrid_metrics::dns::dns_cache().miss();
             tracing::info!(
                 hostname = %hostname,
                 cache_hits = self.stats.hits,
@@ -153,6 +182,7 @@ impl DnsCache {
         let entry = self.entries.get(&key).unwrap();
         entry.touch(self.epoch);
         self.stats.hits += 1;
+        warpgrid_metrics::dns::dns_cache().hit();
         tracing::info!(
             hostname = %hostname,
             cache_hits = self.stats.hits,
@@ -165,15 +195,23 @@ impl DnsCache {
 
     /// Get the next address for a hostname in round-robin order.
     ///
-    /// Returns `None` on cache miss or TTL expiration.
+    /// Returns `None` on cache miss or TTL expiration. Like
+    /// [`DnsCache::get`], entries within `max_stale` are left in place.
     pub fn get_round_robin(&mut self, hostname: &str) -> Option<IpAddr> {
         let key = hostname.to_lowercase();
 
         // Check expiration first
         if let Some(entry) = self.entries.get(&key) {
             if entry.is_expired(self.config.ttl) {
-                self.entries.remove(&key);
+                if self.is_servable_stale(entry) {
+                    if entry.unreachable {
+                        return None;
+                    }
+                } else {
+                    self.entries.remove(&key);
+                }
                 self.stats.misses += 1;
+                warpgrid_metrics::dns::dns_cache().miss();
                 tracing::info!(
                     hostname = %hostname,
                     cache_hits = self.stats.hits,
@@ -185,6 +223,7 @@ impl DnsCache {
             }
         } else {
             self.stats.misses += 1;
+            warpgrid_metrics::dns::dns_cache().miss();
             tracing::info!(
                 hostname = %hostname,
                 cache_hits = self.stats.hits,
@@ -198,6 +237,7 @@ impl DnsCache {
         let entry = self.entries.get(&key).unwrap();
         entry.touch(self.epoch);
         self.stats.hits += 1;
+        warpgrid_metrics::dns::dns_cache().hit();
         let addr = entry.next_round_robin();
         tracing::info!(
             hostname = %hostname,
@@ -210,6 +250,87 @@ impl DnsCache {
         addr
     }
 
+    /// Whether an expired `entry` may still be served stale.
+    fn is_servable_stale(&self, entry: &CacheEntry) -> bool {
+        !self.config.max_stale.is_zero()
+            && !entry.is_expired(self.config.ttl + self.config.max_stale)
+    }
+
+    /// Find an entry past its TTL but within `max_stale`, counting a stale
+    /// hit. Unless `unreachable` marks it as served stale from now on, only
+    /// an entry already marked is found. The flag is `true` for the first
+    /// stale read since it was marked (or since its last refresh failed):
+    /// that caller should refresh the entry, everyone else keeps getting
+    /// the stale answer.
+    fn stale_entry(&mut self, hostname: &str, unreachable: bool) -> Option<(&CacheEntry, bool)> {
+        let key = hostname.to_lowercase();
+        let entry = self.entries.get(&key)?;
+        if !entry.is_expired(self.config.ttl)
+            || !self.is_servable_stale(entry)
+            || !(unreachable || entry.unreachable)
+        {
+            return None;
+        }
+        let entry = self.entries.get_mut(&key)?;
+        // A caller marking the entry just failed to reach the upstream, so
+        // the next read refreshes instead.
+        let marked = std::mem::replace(&mut entry.unreachable, true);
+        let refresh = marked && !std::mem::replace(&mut entry.refreshing, true);
+        entry.touch(self.epoch);
+        self.stats.stale += 1;
+        warpgrid_metrics::dns::dns_cache().stale();
+        tracing::info!(
+            hostname = %hostname,
+            cache_stale = self.stats.stale,
+            refresh,
+            "dns cache stale hit"
+        );
+        Some((entry, refresh))
+    }
+
+    /// Look up an entry being served stale because the upstream was
+    /// unreachable (see [`DnsCache::serve_stale`]).
+    ///
+    /// Returns its addresses and whether the caller should refresh it; see
+    /// [`DnsCache::stale_entry`]. Call after [`DnsCache::get`] missed.
+    pub fn get_stale(&mut self, hostname: &str) -> Option<(Vec<IpAddr>, bool)> {
+        self.stale_entry(hostname, false)
+            .map(|(entry, refresh)| (entry.addresses.clone(), refresh))
+    }
+
+    /// Round-robin variant of [`DnsCache::get_stale`].
+    pub fn get_stale_round_robin(&mut self, hostname: &str) -> Option<(IpAddr, bool)> {
+        let (entry, refresh) = self.stale_entry(hostname, false)?;
+        entry.next_round_robin().map(|addr| (addr, refresh))
+    }
+
+    /// Answer from an expired entry within `max_stale` after the upstream
+    /// could not be reached, and keep serving it through
+    /// [`DnsCache::get_stale`] until it is refreshed.
+    pub fn serve_stale(&mut self, hostname: &str) -> Option<Vec<IpAddr>> {
+        self.stale_entry(hostname, true)
+            .map(|(entry, _)| entry.addresses.clone())
+    }
+
+    /// Round-robin variant of [`DnsCache::serve_stale`].
+    pub fn serve_stale_round_robin(&mut self, hostname: &str) -> Option<IpAddr> {
+        let (entry, _) = self.stale_entry(hostname, true)?;
+        entry.next_round_robin()
+    }
+
+    /// Record that refreshing a stale entry failed, so the next stale read
+    /// tries again. The entry keeps being served until `max_stale` runs out.
+    pub fn refresh_failed(&mut self, hostname: &str) {
+        if let Some(entry) = self.entries.get_mut(&hostname.to_lowercase()) {
+            entry.refreshing = false;
+        }
+    }
+
+    /// Drop a hostname's entry, e.g. once the upstream no longer knows it.
+    pub fn remove(&mut self, hostname: &str) -> bool {
+        self.entries.remove(&hostname.to_lowercase()).is_some()
+    }
+
     /// Insert or update a cache entry.
     ///
     /// If the cache is at capacity, the least-recently-used entry is evicted.
@@ -261,6 +382,11 @@ impl DnsCache {
         (self.stats.hits, self.stats.misses, self.stats.evictions)
     }
 
+    /// Get the number of lookups answered with a stale entry.
+    pub fn stale_hits(&self) -> u64 {
+        self.stats.stale
+    }
+
     /// Get the number of entries currently in the cache.
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -292,6 +418,7 @@ mod tests {
         let config = DnsCacheConfig::default();
         assert_eq!(config.ttl, Duration::from_secs(30));
         assert_eq!(config.max_entries, 1024);
+        assert!(config.max_stale.is_zero());
     }
 
     // ── Insert and Get ───────────────────────────────────────────────
@@ -362,6 +489,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_millis(50),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -382,6 +510,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_secs(30),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -397,6 +526,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_millis(50),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -457,6 +587,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_millis(50),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -510,6 +641,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_secs(30),
             max_entries: 3,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
 
@@ -542,6 +674,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_secs(30),
             max_entries: 2,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -563,6 +696,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_secs(30),
             max_entries: 2,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr1 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -606,6 +740,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_millis(50),
             max_entries: 1024,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -625,6 +760,82 @@ mod tests {
         assert_eq!(misses, 1);
     }
 
+    // ── Stale answers ────────────────────────────────────────────────
+
+    #[test]
+    fn stale_entry_served_within_max_stale() {
+        let config = DnsCacheConfig {
+            ttl: Duration::from_millis(50),
+            max_entries: 1024,
+            max_stale: Duration::from_secs(30),
+        };
+        let mut cache = DnsCache::new(config);
+        let addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        cache.insert("svc", vec![addr]);
+
+        thread::sleep(Duration::from_millis(80));
+
+        // Past TTL: a miss to re-resolve, but the entry is kept.
+        assert!(cache.get("svc").is_none());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.stats().1, 1);
+
+        // Not served stale while the upstream answers.
+        assert!(cache.get_stale("svc").is_none());
+
+        // Served once the upstream is unreachable; only the first read
+        // after that asks for a refresh.
+        assert_eq!(cache.serve_stale("svc"), Some(vec![addr]));
+        assert!(cache.get("svc").is_none());
+        assert_eq!(cache.get_stale("svc"), Some((vec![addr], true)));
+        assert_eq!(cache.get_stale_round_robin("svc"), Some((addr, false)));
+        assert_eq!(cache.stale_hits(), 3);
+        assert_eq!(cache.stats().1, 1);
+
+        // A failed refresh lets the next stale read retry.
+        cache.refresh_failed("svc");
+        assert_eq!(cache.get_stale("svc"), Some((vec![addr], true)));
+
+        // A successful refresh makes the entry fresh again.
+        cache.insert("svc", vec![addr]);
+        assert!(cache.get_stale("svc").is_none());
+        assert_eq!(cache.get("svc"), Some(&[addr][..]));
+    }
+
+    #[test]
+    fn stale_entry_dropped_after_max_stale() {
+        let config = DnsCacheConfig {
+            ttl: Duration::from_millis(20),
+            max_entries: 1024,
+            max_stale: Duration::from_millis(30),
+        };
+        let mut cache = DnsCache::new(config);
+        cache.insert("svc", vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+
+        thread::sleep(Duration::from_millis(80));
+
+        assert!(cache.serve_stale("svc").is_none());
+        assert!(cache.get("svc").is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().1, 1);
+    }
+
+    #[test]
+    fn stale_answers_are_off_by_default() {
+        let config = DnsCacheConfig {
+            ttl: Duration::from_millis(20),
+            ..DnsCacheConfig::default()
+        };
+        let mut cache = DnsCache::new(config);
+        cache.insert("svc", vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+
+        thread::sleep(Duration::from_millis(40));
+
+        assert!(cache.get("svc").is_none());
+        assert!(cache.serve_stale("svc").is_none());
+        assert!(cache.is_empty());
+    }
+
     #[test]
     fn initial_stats_are_zero() {
         let cache = DnsCache::new(DnsCacheConfig::default());
@@ -641,6 +852,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_secs(30),
             max_entries: 5,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
 
@@ -676,6 +888,7 @@ mod tests {
         let config = DnsCacheConfig {
             ttl: Duration::from_secs(30),
             max_entries: 1,
+            max_stale: Duration::ZERO,
         };
         let mut cache = DnsCache::new(config);
         let addr1 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
@@ -1,7 +1,8 @@
 //! WarpGridEngine — top-level orchestrator.
 //!
 //! Wires together all shim components (filesystem, DNS, signals, database proxy,
-//! threading) and registers them with the Wasmtime linker at instantiation time.
+//! threading, render, flags) and registers them with the Wasmtime linker at
+//! instantiation time.
 //!
 //! # Architecture
 //!
@@ -9,13 +10,15 @@
 //! and async execution. A `Linker<HostState>` is set up with host functions
 //! registered conditionally based on `ShimConfig`.
 //!
-//! `HostState` holds the per-instance shim state. It implements all five WIT
+//! `HostState` holds the per-instance shim state. It implements all seven WIT
 //! Host traits by delegating to the individual shim implementations.
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use wasmtime::component::{Component, HasSelf, Instance, Linker};
-use wasmtime::{Config, Engine, Store, StoreLimitsBuilder};
+use wasmtime::{Config, Engine, ResourceLimiter, Store, StoreLimits, StoreLimitsBuilder};
 
 use crate::bindings::async_handler_bindings::warpgrid::shim::http_types;
 use crate::bindings::warpgrid::shim;
@@ -26,7 +29,12 @@ use crate::dns::CachedDnsResolver;
 use crate::dns::host::DnsHost;
 use crate::dns::DnsResolver;
 use crate::filesystem::host::FilesystemHost;
+use crate::bundles::{BundleRegistry, SharedBundle};
+use crate::flags::FlagRegistry;
+use crate::flags::host::FlagsHost;
 use crate::filesystem::VirtualFileMap;
+use crate::render::host::RenderHost;
+use crate::render::CommandRenderer;
 use crate::signals::host::SignalsHost;
 
 /// Per-instance host state.
@@ -38,13 +46,114 @@ pub struct HostState {
     pub filesystem: Option<FilesystemHost>,
     pub dns: Option<DnsHost>,
     pub db_proxy: Option<DbProxyHost>,
+    /// Document rendering (only on nodes with a configured renderer).
+    pub render: Option<RenderHost>,
+    /// Feature flags of the deployment this instance serves.
+    pub flags: Option<FlagsHost>,
     /// Signal handling: interest registration, bounded queue, and filtering.
     pub signals: SignalsHost,
     /// Declared threading model (set by guest).
     pub threading_model: Option<shim::threading::ThreadingModel>,
     /// Optional resource limiter for memory/table enforcement.
-    /// Uses `wasmtime::StoreLimits` for compatibility with `Store::limiter()`.
-    pub limiter: Option<wasmtime::StoreLimits>,
+    /// Wraps `wasmtime::StoreLimits` for compatibility with `Store::limiter()`.
+    pub limiter: Option<TrackedLimits>,
+}
+
+impl HostState {
+    /// Follow `config`: its files are served by the filesystem shim and a
+    /// swap to a new bundle is delivered to the guest as `Hangup`.
+    pub fn attach_config(&mut self, config: SharedBundle) {
+        self.filesystem = self.filesystem.take().map(|fs| fs.with_config(config.clone()));
+        self.signals = std::mem::take(&mut self.signals).with_config(config);
+    }
+
+    /// Mount a deployment's secrets: `files` are served by the filesystem
+    /// shim and `database_password` is sent by the database proxy shim for
+    /// connections that give none.
+    pub fn attach_secrets(&mut self, files: Arc<HashMap<String, Vec<u8>>>, database_password: Option<String>) {
+        self.filesystem = self.filesystem.take().map(|fs| fs.with_files(files));
+        self.db_proxy = self.db_proxy.take().map(|db| db.with_default_password(database_password));
+    }
+}
+
+/// `StoreLimits` that also records how much linear memory the instance
+/// has actually grown to.
+///
+/// The configured memory limit is a ceiling; most instances stay far
+/// below it. Pools use the observed peak to size reservations.
+pub struct TrackedLimits {
+    limits: StoreLimits,
+    memory_bytes: usize,
+    peak_memory_bytes: usize,
+}
+
+impl TrackedLimits {
+    /// Linear memory currently allocated across the instance's memories.
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes
+    }
+
+    /// Largest value [`Self::memory_bytes`] has reached.
+    pub fn peak_memory_bytes(&self) -> usize {
+        self.peak_memory_bytes
+    }
+}
+
+impl From<StoreLimits> for TrackedLimits {
+    fn from(limits: StoreLimits) -> Self {
+        Self {
+            limits,
+            memory_bytes: 0,
+            peak_memory_bytes: 0,
+        }
+    }
+}
+
+impl ResourceLimiter for TrackedLimits {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let allowed = self.limits.memory_growing(current, desired, maximum)?;
+        if allowed {
+            // A new memory is reported as growth from zero, so the sum
+            // covers every memory in the store.
+            self.memory_bytes += desired.saturating_sub(current);
+            self.peak_memory_bytes = self.peak_memory_bytes.max(self.memory_bytes);
+        }
+        Ok(allowed)
+    }
+
+    fn memory_grow_failed(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+        self.limits.memory_grow_failed(error)
+    }
+
+    fn table_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        self.limits.table_growing(current, desired, maximum)
+    }
+
+    fn table_grow_failed(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+        self.limits.table_grow_failed(error)
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.limits.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.limits.memories()
+    }
 }
 
 // ── Host trait implementations ─────────────────────────────────────
@@ -160,6 +269,33 @@ impl shim::threading::Host for HostState {
     }
 }
 
+impl shim::render::Host for HostState {
+    fn render_pdf(
+        &mut self,
+        html: String,
+        options: shim::render::RenderOptions,
+    ) -> Result<Vec<u8>, String> {
+        self.render
+            .as_mut()
+            .ok_or_else(|| "render shim not enabled".to_string())
+            .and_then(|r| shim::render::Host::render_pdf(r, html, options))
+    }
+}
+
+impl shim::feature_flags::Host for HostState {
+    fn get_bool(&mut self, name: String, key: String) -> Option<bool> {
+        self.flags
+            .as_mut()
+            .and_then(|f| shim::feature_flags::Host::get_bool(f, name, key))
+    }
+
+    fn get_string(&mut self, name: String) -> Option<String> {
+        self.flags
+            .as_mut()
+            .and_then(|f| shim::feature_flags::Host::get_string(f, name))
+    }
+}
+
 /// The `http-types` interface defines only types (no functions), but
 /// the bindgen! macro still generates a Host trait for interface-level
 /// dispatch. This empty implementation satisfies the trait bound.
@@ -177,6 +313,12 @@ pub struct WarpGridEngine {
     engine: Engine,
     linker: Arc<Linker<HostState>>,
     config: ShimConfig,
+    /// Epoch tick interval when execution metering is enabled.
+    epoch_tick: Option<Duration>,
+    /// Feature flags of the deployments on this node.
+    flags: FlagRegistry,
+    /// Active config bundles of the deployments on this node.
+    bundles: BundleRegistry,
 }
 
 impl WarpGridEngine {
@@ -186,10 +328,25 @@ impl WarpGridEngine {
     /// If a guest component imports a disabled interface, instantiation will
     /// fail at link time (expected behavior).
     pub fn new(config: ShimConfig) -> anyhow::Result<Self> {
+        Self::build(config, None)
+    }
+
+    /// Create a `WarpGridEngine` with epoch-based execution metering.
+    ///
+    /// Enables Wasmtime epoch interruption. The caller is responsible for
+    /// advancing the epoch every `epoch_tick` (see `warp-runtime`'s
+    /// `EpochTicker`), and every `Store` created from this engine must
+    /// install an epoch deadline before running guest code.
+    pub fn new_metered(config: ShimConfig, epoch_tick: Duration) -> anyhow::Result<Self> {
+        Self::build(config, Some(epoch_tick))
+    }
+
+    fn build(config: ShimConfig, epoch_tick: Option<Duration>) -> anyhow::Result<Self> {
         let mut wasm_config = Config::new();
         wasm_config.async_support(true);
         wasm_config.wasm_component_model(true);
         wasm_config.wasm_component_model_async(true);
+        wasm_config.epoch_interruption(epoch_tick.is_some());
 
         let engine = Engine::new(&wasm_config)?;
         let mut linker = Linker::new(&engine);
@@ -204,10 +361,14 @@ impl WarpGridEngine {
             signals = config.signals,
             database_proxy = config.database_proxy,
             threading = config.threading,
+            render = config.render,
+            flags = config.flags,
             dns_cache_ttl_seconds = config.dns_config.ttl_seconds,
             dns_cache_max_entries = config.dns_config.cache_size,
+            dns_cache_max_stale_seconds = config.dns_config.max_stale_seconds,
             db_pool_size = config.database_proxy_config.pool_size,
             fs_timezone = %config.filesystem_config.timezone_name,
+            epoch_tick_ms = epoch_tick.map(|t| t.as_millis() as u64),
             "WarpGrid engine initialized"
         );
 
@@ -215,6 +376,9 @@ impl WarpGridEngine {
             engine,
             linker: Arc::new(linker),
             config,
+            epoch_tick,
+            flags: FlagRegistry::default(),
+            bundles: BundleRegistry::default(),
         })
     }
 
@@ -222,36 +386,48 @@ impl WarpGridEngine {
     fn register_shim_interfaces(
         config: &ShimConfig,
         linker: &mut Linker<HostState>,
+    ) -> anyhow::Result<()> {
+        Self::register_shims_with(config, linker, |state: &mut HostState| state)
+    }
+
+    /// Register the enabled shim interfaces with a linker whose store data
+    /// embeds a [`HostState`].
+    ///
+    /// Embedders serving other worlds (e.g. `wasi:http/proxy`) keep their
+    /// own store type and reach the shims through `get`.
+    pub fn add_shims_to_linker<T: Send + 'static>(
+        &self,
+        linker: &mut Linker<T>,
+        get: fn(&mut T) -> &mut HostState,
+    ) -> anyhow::Result<()> {
+        Self::register_shims_with(&self.config, linker, get)
+    }
+
+    fn register_shims_with<T: Send + 'static>(
+        config: &ShimConfig,
+        linker: &mut Linker<T>,
+        get: fn(&mut T) -> &mut HostState,
     ) -> anyhow::Result<()> {
         if config.filesystem {
-            shim::filesystem::add_to_linker::<HostState, HasSelf<HostState>>(
-                linker,
-                |state: &mut HostState| state,
-            )?;
+            shim::filesystem::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
         }
         if config.dns {
-            shim::dns::add_to_linker::<HostState, HasSelf<HostState>>(
-                linker,
-                |state: &mut HostState| state,
-            )?;
+            shim::dns::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
         }
         if config.signals {
-            shim::signals::add_to_linker::<HostState, HasSelf<HostState>>(
-                linker,
-                |state: &mut HostState| state,
-            )?;
+            shim::signals::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
         }
         if config.database_proxy {
-            shim::database_proxy::add_to_linker::<HostState, HasSelf<HostState>>(
-                linker,
-                |state: &mut HostState| state,
-            )?;
+            shim::database_proxy::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
         }
         if config.threading {
-            shim::threading::add_to_linker::<HostState, HasSelf<HostState>>(
-                linker,
-                |state: &mut HostState| state,
-            )?;
+            shim::threading::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
+        }
+        if config.render {
+            shim::render::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
+        }
+        if config.flags {
+            shim::feature_flags::add_to_linker::<T, HasSelf<HostState>>(linker, get)?;
         }
         Ok(())
     }
@@ -261,6 +437,29 @@ impl WarpGridEngine {
         &self.config
     }
 
+    /// Epoch tick interval, if execution metering is enabled.
+    pub fn epoch_tick(&self) -> Option<Duration> {
+        self.epoch_tick
+    }
+
+    /// The node's feature flag registry, shared by every clone.
+    pub fn flags(&self) -> &FlagRegistry {
+        &self.flags
+    }
+
+    /// A flags host reading `deployment_id`'s flags, when the shim is enabled.
+    pub fn flags_host(&self, deployment_id: &str) -> Option<FlagsHost> {
+        self.config
+            .flags
+            .then(|| FlagsHost::new(self.flags.handle(deployment_id)))
+    }
+
+    /// The node's config bundle registry, shared by every clone. Embedders
+    /// attach a deployment's handle with [`HostState::attach_config`].
+    pub fn bundles(&self) -> &BundleRegistry {
+        &self.bundles
+    }
+
     /// Get a reference to the underlying `wasmtime::Engine`.
     pub fn engine(&self) -> &Engine {
         &self.engine
@@ -319,7 +518,7 @@ impl WarpGridEngine {
             .memory_size(64 * 1024 * 1024)
             .table_elements(10_000)
             .build();
-        host_state.limiter = Some(limits);
+        host_state.limiter = Some(limits.into());
 
         let mut store = Store::new(&self.engine, host_state);
         store.limiter(|data| {
@@ -327,6 +526,10 @@ impl WarpGridEngine {
                 .as_mut()
                 .expect("limiter must be set before instantiation")
         });
+        if self.epoch_tick.is_some() {
+            // Unmetered use of a metered engine: yield on every tick.
+            store.epoch_deadline_async_yield_and_update(1);
+        }
 
         let instance = self.linker.instantiate_async(&mut store, &component).await?;
 
@@ -405,10 +608,37 @@ impl WarpGridEngine {
             None
         };
 
+        let render = if config.render {
+            match &config.render_config.command {
+                Some(command) => {
+                    let renderer = Arc::new(CommandRenderer::new(
+                        command.clone(),
+                        config.render_config.args.clone(),
+                        config.render_config.to_quotas(),
+                    ));
+                    Some(RenderHost::new(
+                        renderer,
+                        config.render_config.max_jobs_per_instance,
+                    ))
+                }
+                None => {
+                    tracing::warn!(
+                        "render enabled but no renderer command configured on this node"
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         HostState {
             filesystem,
             dns,
             db_proxy,
+            render,
+            // Embedders attach the deployment's flags; see `flags_host`.
+            flags: config.flags.then(FlagsHost::default),
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -426,6 +656,16 @@ mod tests {
         assert!(engine.is_ok());
     }
 
+    #[test]
+    fn engine_metering_is_opt_in() {
+        let engine = WarpGridEngine::new(ShimConfig::default()).unwrap();
+        assert_eq!(engine.epoch_tick(), None);
+
+        let tick = Duration::from_millis(5);
+        let engine = WarpGridEngine::new_metered(ShimConfig::default(), tick).unwrap();
+        assert_eq!(engine.epoch_tick(), Some(tick));
+    }
+
     #[test]
     fn engine_creates_with_all_shims_disabled() {
         let config = ShimConfig {
@@ -533,6 +773,8 @@ mod tests {
             filesystem: None,
             dns: None,
             db_proxy: None,
+            render: None,
+            flags: None,
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -544,6 +786,69 @@ mod tests {
 
         let result = shim::dns::Host::resolve_address(&mut state, "example.com".to_string());
         assert!(result.is_err());
+
+        let result = shim::render::Host::render_pdf(
+            &mut state,
+            "<p></p>".to_string(),
+            shim::render::RenderOptions {
+                page_size: None,
+                landscape: false,
+            },
+        );
+        assert!(result.unwrap_err().contains("not enabled"));
+    }
+
+    #[test]
+    fn host_state_render_requires_configured_command() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let _guard = rt.enter();
+
+        let mut config = ShimConfig {
+            render: true,
+            dns: false,
+            ..ShimConfig::default()
+        };
+        let engine = WarpGridEngine::new(config.clone()).unwrap();
+        assert!(engine.build_host_state(None).render.is_none());
+
+        config.render_config.command = Some("/usr/local/bin/html2pdf".to_string());
+        let engine = WarpGridEngine::new(config).unwrap();
+        assert!(engine.build_host_state(None).render.is_some());
+    }
+
+    #[test]
+    fn flags_host_follows_the_engine_registry() {
+        use crate::flags::{FeatureFlag, FlagSet};
+
+        let engine = WarpGridEngine::new(ShimConfig {
+            dns: false,
+            ..ShimConfig::default()
+        })
+        .unwrap();
+        let mut state = HostState {
+            flags: engine.flags_host("default/api"),
+            ..engine.build_host_state(None)
+        };
+        assert_eq!(shim::feature_flags::Host::get_string(&mut state, "banner".into()), None);
+
+        let mut set = FlagSet::default();
+        set.flags.insert("banner".into(), FeatureFlag::String { value: "hi".into() });
+        // Clones share the registry, as the runtime and ingress do.
+        engine.clone().flags().update("default/api", set);
+        assert_eq!(
+            shim::feature_flags::Host::get_string(&mut state, "banner".into()).as_deref(),
+            Some("hi")
+        );
+
+        let disabled = WarpGridEngine::new(ShimConfig {
+            flags: false,
+            ..ShimConfig::default()
+        })
+        .unwrap();
+        assert!(disabled.flags_host("default/api").is_none());
     }
 
     #[test]
@@ -552,6 +857,8 @@ mod tests {
             filesystem: None,
             dns: None,
             db_proxy: None,
+            render: None,
+            flags: None,
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -605,6 +912,8 @@ mod tests {
             filesystem: None,
             dns: None,
             db_proxy: None,
+            render: None,
+            flags: None,
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -628,6 +937,8 @@ mod tests {
             filesystem: None,
             dns: None,
             db_proxy: None,
+            render: None,
+            flags: None,
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -651,6 +962,8 @@ mod tests {
             filesystem: None,
             dns: None,
             db_proxy: None,
+            render: None,
+            flags: None,
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -689,6 +1002,8 @@ mod tests {
             filesystem: None,
             dns: None,
             db_proxy: None,
+            render: None,
+            flags: None,
             signals: SignalsHost::new(),
             threading_model: None,
             limiter: None,
@@ -857,4 +1172,29 @@ mod tests {
         let data = shim::database_proxy::Host::recv(&mut state, handle, 1024).unwrap();
         assert!(!data.is_empty());
     }
+
+    #[test]
+    fn attached_config_reaches_filesystem_and_signals() {
+        use crate::bundles::ConfigBundle;
+
+        let engine = WarpGridEngine::new(ShimConfig {
+            dns: false,
+            ..ShimConfig::default()
+        })
+        .unwrap();
+        let mut state = engine.build_host_state(None);
+        state.attach_config(engine.bundles().handle("default/api"));
+        shim::signals::Host::on_signal(&mut state, shim::signals::SignalType::Hangup).unwrap();
+
+        let mut bundle = ConfigBundle::default();
+        bundle.files.insert("/etc/app.toml".into(), "port = 80".into());
+        engine.clone().bundles().update("default/api", bundle);
+
+        let stat = shim::filesystem::Host::stat_virtual(&mut state, "/etc/app.toml".into()).unwrap();
+        assert_eq!(stat.size, 9);
+        assert!(matches!(
+            shim::signals::Host::poll_signal(&mut state),
+            Some(shim::signals::SignalType::Hangup)
+        ));
+    }
 }
@@ -263,7 +263,7 @@ fn read_provider(provider: &ContentProvider, sub_path: Option<&str>) -> VirtualC
 ///
 /// This prevents bypass attempts like `/etc/../etc/hosts` mapping back to `/etc/hosts`.
 /// Does NOT resolve symlinks (we don't have a real filesystem).
-fn canonicalize_path(path: &str) -> String {
+pub(crate) fn canonicalize_path(path: &str) -> String {
     let mut components: Vec<&str> = Vec::new();
 
     for component in path.split('/') {
@@ -18,7 +18,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::bindings::warpgrid::shim::filesystem::{FileStat, Host};
-use super::{VirtualContent, VirtualFileMap};
+use crate::bundles::SharedBundle;
+use super::{canonicalize_path, VirtualContent, VirtualFileMap};
 
 /// Distinguishes special virtual files from regular buffered content.
 #[derive(Debug)]
@@ -53,6 +54,10 @@ struct OpenVirtualFile {
 pub struct FilesystemHost {
     /// Immutable virtual file map (shared across instances).
     file_map: Arc<VirtualFileMap>,
+    /// The deployment's config bundle, served ahead of `file_map`.
+    config: Option<SharedBundle>,
+    /// Files mounted from the deployment's secrets, served ahead of both.
+    files: Arc<HashMap<String, Vec<u8>>>,
     /// Open file handles → file state.
     open_files: HashMap<u64, OpenVirtualFile>,
     /// Next handle to allocate (monotonically increasing, starts at 1).
@@ -64,11 +69,47 @@ impl FilesystemHost {
     pub fn new(file_map: Arc<VirtualFileMap>) -> Self {
         Self {
             file_map,
+            config: None,
+            files: Arc::default(),
             open_files: HashMap::new(),
             next_handle: 1,
         }
     }
 
+    /// Serve the files of `config`'s active bundle as regular files.
+    ///
+    /// Bundle files shadow the virtual file map, and a swap is visible to
+    /// the next `open_virtual`; handles opened earlier keep the old content.
+    pub fn with_config(mut self, config: SharedBundle) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Serve `files` (guest path → content) as regular files, ahead of the
+    /// config bundle and the file map.
+    pub fn with_files(mut self, files: Arc<HashMap<String, Vec<u8>>>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Resolve `path` against the mounted files, the config bundle, then
+    /// the file map.
+    fn lookup(&self, path: &str) -> VirtualContent {
+        if !self.files.is_empty() {
+            let path = canonicalize_path(path);
+            if let Some(content) = self.files.get(&path) {
+                return VirtualContent::Found(content.clone());
+            }
+        }
+        if let Some(config) = &self.config {
+            let active = config.snapshot();
+            if let Some(content) = active.bundle.file(&canonicalize_path(path)) {
+                return VirtualContent::Found(content.as_bytes().to_vec());
+            }
+        }
+        self.file_map.lookup(path)
+    }
+
     /// Allocate the next file handle.
     fn allocate_handle(&mut self) -> u64 {
         let handle = self.next_handle;
@@ -81,7 +122,7 @@ impl Host for FilesystemHost {
     fn open_virtual(&mut self, path: String) -> Result<u64, String> {
         tracing::debug!(path = %path, "filesystem intercept: open_virtual");
 
-        let content = self.file_map.lookup(&path);
+        let content = self.lookup(&path);
 
         match content {
             VirtualContent::Found(data) => {
@@ -190,7 +231,7 @@ impl Host for FilesystemHost {
     fn stat_virtual(&mut self, path: String) -> Result<FileStat, String> {
         tracing::debug!(path = %path, "filesystem intercept: stat_virtual");
 
-        let content = self.file_map.lookup(&path);
+        let content = self.lookup(&path);
 
         match content {
             VirtualContent::Found(data) => {
@@ -705,4 +746,46 @@ mod tests {
         );
         host.close_virtual(handle).unwrap();
     }
+
+    // ── Config bundles ───────────────────────────────────────────────
+
+    #[test]
+    fn bundle_files_shadow_the_map_and_follow_swaps() {
+        use crate::bundles::{BundleRegistry, ConfigBundle};
+
+        let registry = BundleRegistry::default();
+        let map = VirtualFileMap::builder().with_etc_hosts("127.0.0.1 localhost").build();
+        let mut host = host_with_map(map).with_config(registry.handle("default/api"));
+        assert!(host.open_virtual("/etc/app.toml".into()).is_err());
+
+        let mut bundle = ConfigBundle::default();
+        bundle.files.insert("/etc/app.toml".into(), "port = 80".into());
+        bundle.files.insert("/etc/hosts".into(), "10.0.0.1 db".into());
+        registry.update("default/api", bundle);
+
+        let old = host.open_virtual("/etc/./app.toml".into()).unwrap();
+        assert_eq!(host.stat_virtual("/etc/app.toml".into()).unwrap().size, 9);
+        let hosts = host.open_virtual("/etc/hosts".into()).unwrap();
+        assert_eq!(host.read_virtual(hosts, 64).unwrap(), b"10.0.0.1 db");
+
+        let mut next = ConfigBundle::default();
+        next.files.insert("/etc/app.toml".into(), "port = 81".into());
+        registry.update("default/api", next);
+        let new = host.open_virtual("/etc/app.toml".into()).unwrap();
+        assert_eq!(host.read_virtual(new, 64).unwrap(), b"port = 81");
+        assert_eq!(host.read_virtual(old, 64).unwrap(), b"port = 80", "open handles keep their content");
+        let hosts = host.open_virtual("/etc/hosts".into()).unwrap();
+        assert_eq!(host.read_virtual(hosts, 64).unwrap(), b"127.0.0.1 localhost");
+    }
+
+    #[test]
+    fn mounted_files_shadow_the_map() {
+        let map = VirtualFileMap::builder().with_etc_hosts("127.0.0.1 localhost").build();
+        let files = HashMap::from([("/etc/hosts".to_string(), b"10.0.0.1 db".to_vec())]);
+        let mut host = host_with_map(map).with_files(Arc::new(files));
+
+        let hosts = host.open_virtual("/etc/./hosts".into()).unwrap();
+        assert_eq!(host.read_virtual(hosts, 64).unwrap(), b"10.0.0.1 db");
+        assert!(host.open_virtual("/run/secrets/missing".into()).is_err());
+    }
 }
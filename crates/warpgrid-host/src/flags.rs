@@ -0,0 +1,147 @@
+//! Feature flag shim.
+//!
+//! Serves each deployment's [`FlagSet`] to its guests through the
+//! `warpgrid:shim/feature-flags` interface.
+//!
+//! # Architecture
+//!
+//! [`FlagRegistry`] is node-wide and shared by every clone of the engine.
+//! It holds one [`SharedFlags`] per deployment. Every instance of the
+//! deployment reads through that handle. Replacing a deployment's set with
+//! [`FlagRegistry::update`] changes the answer of the next guest call on
+//! every instance; nothing is restarted.
+//!
+//! The [`host`] submodule provides the WIT `Host` trait implementation.
+
+pub mod host;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+pub use warp_core::flags::{FeatureFlag, FlagSet};
+
+/// A deployment's current flags, shared by all of its instances.
+#[derive(Debug, Clone, Default)]
+pub struct SharedFlags(Arc<RwLock<Arc<FlagSet>>>);
+
+impl SharedFlags {
+    /// The flags as of now. Guests evaluate one call against one snapshot.
+    pub fn snapshot(&self) -> Arc<FlagSet> {
+        self.0.read().expect("flags lock poisoned").clone()
+    }
+
+    /// Swap in `set`. Returns `false` when it equals the current set.
+    fn replace(&self, set: FlagSet) -> bool {
+        let mut current = self.0.write().expect("flags lock poisoned");
+        if **current == set {
+            return false;
+        }
+        *current = Arc::new(set);
+        true
+    }
+}
+
+/// Node-wide map of deployment id → [`SharedFlags`].
+#[derive(Debug, Clone, Default)]
+pub struct FlagRegistry {
+    deployments: Arc<RwLock<HashMap<String, SharedFlags>>>,
+}
+
+impl FlagRegistry {
+    /// The handle for `deployment_id`, created empty on first use.
+    pub fn handle(&self, deployment_id: &str) -> SharedFlags {
+        if let Some(flags) = self.deployments.read().expect("flags lock poisoned").get(deployment_id) {
+            return flags.clone();
+        }
+        self.deployments
+            .write()
+            .expect("flags lock poisoned")
+            .entry(deployment_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Replace `deployment_id`'s flags. Returns `true` when they changed.
+    pub fn update(&self, deployment_id: &str, set: FlagSet) -> bool {
+        let changed = self.handle(deployment_id).replace(set);
+        if changed {
+            tracing::info!(deployment = %deployment_id, "feature flags updated");
+        }
+        changed
+    }
+
+    /// Make `sets` the flags of this node: listed deployments get their
+    /// set, every other known deployment is cleared. Used by the daemon to
+    /// follow the state store.
+    pub fn replace_all(&self, sets: impl IntoIterator<Item = (String, FlagSet)>) {
+        let sets: HashMap<String, FlagSet> = sets.into_iter().collect();
+        for deployment_id in self.deployments() {
+            if !sets.contains_key(&deployment_id) {
+                self.update(&deployment_id, FlagSet::default());
+            }
+        }
+        for (deployment_id, set) in sets {
+            self.update(&deployment_id, set);
+        }
+    }
+
+    /// Forget `deployment_id`. Instances still holding its handle keep
+    /// their last flags.
+    pub fn remove(&self, deployment_id: &str) {
+        self.deployments.write().expect("flags lock poisoned").remove(deployment_id);
+    }
+
+    /// Deployments with a handle on this node.
+    pub fn deployments(&self) -> Vec<String> {
+        self.deployments.read().expect("flags lock poisoned").keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beta(enabled: bool) -> FlagSet {
+        let mut set = FlagSet::default();
+        set.flags.insert("beta".to_string(), FeatureFlag::Boolean { enabled });
+        set
+    }
+
+    #[test]
+    fn updates_reach_existing_handles() {
+        let registry = FlagRegistry::default();
+        let handle = registry.handle("default/api");
+        assert_eq!(handle.snapshot().boolean("beta", "k"), None);
+
+        assert!(registry.update("default/api", beta(true)));
+        assert_eq!(handle.snapshot().boolean("beta", "k"), Some(true));
+        assert!(!registry.update("default/api", beta(true)), "unchanged set");
+
+        registry.update("default/api", beta(false));
+        assert_eq!(registry.handle("default/api").snapshot().boolean("beta", "k"), Some(false));
+    }
+
+    #[test]
+    fn deployments_are_isolated() {
+        let registry = FlagRegistry::default();
+        registry.update("default/api", beta(true));
+        assert_eq!(registry.handle("default/web").snapshot().boolean("beta", "k"), None);
+
+        let mut deployments = registry.deployments();
+        deployments.sort();
+        assert_eq!(deployments, ["default/api", "default/web"]);
+        registry.remove("default/web");
+        assert_eq!(registry.deployments(), ["default/api"]);
+    }
+
+    #[test]
+    fn replace_all_clears_unlisted_deployments() {
+        let registry = FlagRegistry::default();
+        let api = registry.handle("default/api");
+        registry.update("default/api", beta(true));
+
+        registry.replace_all([("default/web".to_string(), beta(true))]);
+        assert_eq!(api.snapshot().boolean("beta", "k"), None);
+        assert_eq!(registry.handle("default/web").snapshot().boolean("beta", "k"), Some(true));
+    }
+}
@@ -0,0 +1,55 @@
+//! Feature flag host functions.
+//!
+//! Implements the `warpgrid:shim/feature-flags` [`Host`] trait over a deployment's
+//! [`SharedFlags`].
+
+use super::SharedFlags;
+use crate::bindings::warpgrid::shim::feature_flags::Host;
+
+/// Host-side implementation of the `warpgrid:shim/feature-flags` interface.
+///
+/// Each `FlagsHost` belongs to a single Wasm instance and reads the flags
+/// of the deployment it serves. The default reads an empty set.
+#[derive(Debug, Clone, Default)]
+pub struct FlagsHost {
+    flags: SharedFlags,
+}
+
+impl FlagsHost {
+    /// Create a `FlagsHost` reading `flags`.
+    pub fn new(flags: SharedFlags) -> Self {
+        Self { flags }
+    }
+}
+
+impl Host for FlagsHost {
+    fn get_bool(&mut self, name: String, key: String) -> Option<bool> {
+        self.flags.snapshot().boolean(&name, &key)
+    }
+
+    fn get_string(&mut self, name: String) -> Option<String> {
+        self.flags.snapshot().string(&name).map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::{FeatureFlag, FlagRegistry, FlagSet};
+
+    #[test]
+    fn guest_calls_read_the_latest_flags() {
+        let registry = FlagRegistry::default();
+        let mut host = FlagsHost::new(registry.handle("default/api"));
+        assert_eq!(host.get_string("banner".into()), None);
+
+        let mut set = FlagSet::default();
+        set.flags.insert("banner".into(), FeatureFlag::String { value: "sale".into() });
+        set.flags.insert("checkout-v2".into(), FeatureFlag::Percentage { percent: 100 });
+        registry.update("default/api", set);
+
+        assert_eq!(host.get_string("banner".into()).as_deref(), Some("sale"));
+        assert_eq!(host.get_bool("checkout-v2".into(), "user-1".into()), Some(true));
+        assert_eq!(host.get_bool("banner".into(), "user-1".into()), None);
+    }
+}
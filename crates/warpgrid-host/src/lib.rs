@@ -6,15 +6,21 @@
 //! - **signals**: Lifecycle signal delivery (SIGTERM, SIGHUP, SIGINT)
 //! - **db_proxy**: Wire-protocol-level database connection pooling (Postgres, MySQL, Redis)
 //! - **threading**: Threading model declaration and compatibility checks
+//! - **render**: HTML→PDF rendering offloaded to a node-local renderer
+//! - **flags**: Deployment-scoped feature flags, updated live
+//! - **bundles**: Deployment config bundles, swapped atomically with SIGHUP
 //! - **config**: ShimConfig parsing from deployment specs
 //! - **engine**: Top-level WarpGridEngine that wires everything together
 
 pub mod bindings;
+pub mod bundles;
 pub mod config;
 pub mod db_proxy;
 pub mod dns;
 pub mod engine;
 pub mod filesystem;
+pub mod flags;
+pub mod render;
 pub mod signals;
 pub mod threading;
 pub mod tzdata;
@@ -0,0 +1,315 @@
+//! Document rendering shim.
+//!
+//! Offloads HTML→PDF rendering to an external renderer process configured
+//! on the node (e.g. `wkhtmltopdf`, a headless-chromium wrapper script).
+//! Guests call `render-pdf` through the `warpgrid:shim/render` interface
+//! instead of bundling a browser engine, which is not portable to Wasm.
+//!
+//! # Renderer protocol
+//!
+//! The renderer is spawned once per job:
+//! - the HTML document is written to its **stdin**
+//! - the PDF is read from its **stdout**
+//! - a non-zero exit status is a render failure (stderr is surfaced)
+//! - job options are passed via environment variables
+//!   (`WARPGRID_RENDER_PAGE_SIZE`, `WARPGRID_RENDER_LANDSCAPE`)
+//!
+//! # Quotas
+//!
+//! Every job is bounded by [`RenderQuotas`]: maximum HTML input size,
+//! maximum PDF output size, and a wall-clock timeout after which the
+//! renderer process is killed.
+
+pub mod host;
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Polling interval while waiting for the renderer process to exit.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Per-job resource quotas enforced by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderQuotas {
+    /// Maximum size of the HTML document accepted from the guest.
+    pub max_input_bytes: usize,
+    /// Maximum size of the rendered PDF returned to the guest.
+    pub max_output_bytes: usize,
+    /// Wall-clock budget for a single render job.
+    pub timeout: Duration,
+}
+
+impl Default for RenderQuotas {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: 4 * 1024 * 1024,
+            max_output_bytes: 32 * 1024 * 1024,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Options for a single render job (mirrors the WIT `render-options` record).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderJob {
+    /// Page size name passed through to the renderer.
+    pub page_size: Option<String>,
+    /// Landscape orientation.
+    pub landscape: bool,
+}
+
+/// External renderer invoked as a child process per job.
+#[derive(Debug, Clone)]
+pub struct CommandRenderer {
+    /// Renderer executable.
+    program: String,
+    /// Fixed arguments passed to the renderer on every job.
+    args: Vec<String>,
+    /// Quotas applied to every job.
+    quotas: RenderQuotas,
+}
+
+impl CommandRenderer {
+    /// Create a renderer that spawns `program` with `args` for each job.
+    pub fn new(program: impl Into<String>, args: Vec<String>, quotas: RenderQuotas) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            quotas,
+        }
+    }
+
+    /// The quotas applied to each job.
+    pub fn quotas(&self) -> &RenderQuotas {
+        &self.quotas
+    }
+
+    /// Render an HTML document to PDF bytes, enforcing the configured quotas.
+    ///
+    /// Blocks the calling thread until the renderer exits or the timeout
+    /// elapses. On timeout the renderer process is killed.
+    pub fn render_pdf(&self, html: &[u8], job: &RenderJob) -> Result<Vec<u8>, String> {
+        if html.len() > self.quotas.max_input_bytes {
+            return Err(format!(
+                "QuotaExceeded: html input is {} bytes (limit {})",
+                html.len(),
+                self.quotas.max_input_bytes
+            ));
+        }
+
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args)
+            .env(
+                "WARPGRID_RENDER_LANDSCAPE",
+                if job.landscape { "1" } else { "0" },
+            )
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(page_size) = &job.page_size {
+            cmd.env("WARPGRID_RENDER_PAGE_SIZE", page_size);
+        }
+
+        let mut child = cmd.spawn().map_err(|e| {
+            format!(
+                "RendererUnavailable: failed to spawn '{}': {e}",
+                self.program
+            )
+        })?;
+
+        // Feed stdin and drain stdout/stderr on helper threads so a renderer
+        // that interleaves reads and writes can't deadlock on a full pipe.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = html.to_vec();
+        let writer = std::thread::spawn(move || {
+            // A renderer that exits early closes its stdin; that surfaces
+            // through the exit status, so the write error itself is ignored.
+            let _ = stdin.write_all(&input);
+        });
+
+        let max_output = self.quotas.max_output_bytes;
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            // Keep one byte past the limit to detect overflow without
+            // buffering an unbounded document, and discard the rest so an
+            // oversized renderer can still finish writing and exit.
+            (&mut stdout)
+                .take(max_output as u64 + 1)
+                .read_to_end(&mut buf)?;
+            std::io::copy(&mut stdout, &mut std::io::sink())?;
+            Ok::<_, std::io::Error>(buf)
+        });
+
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let err_reader = std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        });
+
+        let deadline = Instant::now() + self.quotas.timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    tracing::warn!(
+                        program = %self.program,
+                        timeout_ms = self.quotas.timeout.as_millis() as u64,
+                        "render job timed out; renderer killed"
+                    );
+                    return Err(format!(
+                        "Timeout: render exceeded {}ms",
+                        self.quotas.timeout.as_millis()
+                    ));
+                }
+                Ok(None) => std::thread::sleep(WAIT_POLL_INTERVAL),
+                Err(e) => return Err(format!("RenderFailed: {e}")),
+            }
+        };
+
+        let _ = writer.join();
+        let output = reader
+            .join()
+            .map_err(|_| "RenderFailed: output reader panicked".to_string())?
+            .map_err(|e| format!("RenderFailed: reading renderer output: {e}"))?;
+        let stderr = err_reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(format!(
+                "RenderFailed: renderer exited with {}: {}",
+                status
+                    .code()
+                    .map_or("signal".to_string(), |c| c.to_string()),
+                stderr.trim()
+            ));
+        }
+
+        if output.len() > max_output {
+            return Err(format!(
+                "QuotaExceeded: rendered output exceeds {max_output} bytes"
+            ));
+        }
+
+        tracing::debug!(
+            input_bytes = html.len(),
+            output_bytes = output.len(),
+            "render job completed"
+        );
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str, quotas: RenderQuotas) -> CommandRenderer {
+        CommandRenderer::new("sh", vec!["-c".to_string(), script.to_string()], quotas)
+    }
+
+    #[test]
+    fn default_quotas_are_bounded() {
+        let quotas = RenderQuotas::default();
+        assert_eq!(quotas.max_input_bytes, 4 * 1024 * 1024);
+        assert_eq!(quotas.max_output_bytes, 32 * 1024 * 1024);
+        assert_eq!(quotas.timeout, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn renders_via_stdin_stdout() {
+        let renderer = sh("cat", RenderQuotas::default());
+        let out = renderer
+            .render_pdf(b"<h1>report</h1>", &RenderJob::default())
+            .unwrap();
+        assert_eq!(out, b"<h1>report</h1>");
+    }
+
+    #[test]
+    fn passes_options_via_env() {
+        let renderer = sh(
+            "printf '%s/%s' \"$WARPGRID_RENDER_PAGE_SIZE\" \"$WARPGRID_RENDER_LANDSCAPE\"",
+            RenderQuotas::default(),
+        );
+        let job = RenderJob {
+            page_size: Some("A4".to_string()),
+            landscape: true,
+        };
+        let out = renderer.render_pdf(b"", &job).unwrap();
+        assert_eq!(out, b"A4/1");
+    }
+
+    #[test]
+    fn rejects_oversized_input() {
+        let quotas = RenderQuotas {
+            max_input_bytes: 4,
+            ..RenderQuotas::default()
+        };
+        let err = sh("cat", quotas)
+            .render_pdf(b"<html></html>", &RenderJob::default())
+            .unwrap_err();
+        assert!(err.starts_with("QuotaExceeded"), "got: {err}");
+    }
+
+    #[test]
+    fn rejects_oversized_output() {
+        let quotas = RenderQuotas {
+            max_output_bytes: 8,
+            ..RenderQuotas::default()
+        };
+        let err = sh("cat", quotas)
+            .render_pdf(b"0123456789abcdef", &RenderJob::default())
+            .unwrap_err();
+        assert!(err.contains("exceeds 8 bytes"), "got: {err}");
+    }
+
+    #[test]
+    fn oversized_output_beyond_pipe_buffer_is_quota_exceeded() {
+        let quotas = RenderQuotas {
+            max_output_bytes: 8,
+            timeout: Duration::from_secs(10),
+            ..RenderQuotas::default()
+        };
+        let err = sh("head -c 1048576 /dev/zero", quotas)
+            .render_pdf(b"", &RenderJob::default())
+            .unwrap_err();
+        assert!(err.starts_with("QuotaExceeded"), "got: {err}");
+    }
+
+    #[test]
+    fn kills_renderer_on_timeout() {
+        let quotas = RenderQuotas {
+            timeout: Duration::from_millis(100),
+            ..RenderQuotas::default()
+        };
+        let start = Instant::now();
+        let err = sh("sleep 5", quotas)
+            .render_pdf(b"", &RenderJob::default())
+            .unwrap_err();
+        assert!(err.starts_with("Timeout"), "got: {err}");
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn surfaces_renderer_failure() {
+        let err = sh("echo 'bad markup' >&2; exit 3", RenderQuotas::default())
+            .render_pdf(b"", &RenderJob::default())
+            .unwrap_err();
+        assert!(err.contains("exited with 3"), "got: {err}");
+        assert!(err.contains("bad markup"), "got: {err}");
+    }
+
+    #[test]
+    fn missing_renderer_is_unavailable() {
+        let renderer = CommandRenderer::new(
+            "/nonexistent/warpgrid-renderer",
+            vec![],
+            RenderQuotas::default(),
+        );
+        let err = renderer.render_pdf(b"", &RenderJob::default()).unwrap_err();
+        assert!(err.starts_with("RendererUnavailable"), "got: {err}");
+    }
+}
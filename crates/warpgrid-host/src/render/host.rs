@@ -0,0 +1,154 @@
+//! Document rendering host functions.
+//!
+//! Implements the `warpgrid:shim/render` [`Host`] trait, delegating render
+//! jobs to the node's [`CommandRenderer`].
+//!
+//! # Render flow
+//!
+//! ```text
+//! Guest calls render_pdf(html, options)
+//!   → RenderHost checks the per-instance job quota
+//!     → Exhausted → Err("QuotaExceeded: ...")
+//!   → CommandRenderer checks input size, spawns renderer, enforces timeout
+//!     → Success → Ok(pdf bytes)
+//!     → Failure → Err("RenderFailed: ..." | "Timeout: ..." | "QuotaExceeded: ...")
+//! ```
+
+use std::sync::Arc;
+
+use tokio::runtime::RuntimeFlavor;
+
+use super::{CommandRenderer, RenderJob};
+use crate::bindings::warpgrid::shim::render::{Host, RenderOptions};
+
+/// Host-side implementation of the `warpgrid:shim/render` interface.
+///
+/// Each `RenderHost` belongs to a single Wasm instance and shares the
+/// node-wide [`CommandRenderer`] with other instances.
+pub struct RenderHost {
+    renderer: Arc<CommandRenderer>,
+    /// Maximum render jobs this instance may run (`None` = unlimited).
+    max_jobs: Option<u32>,
+    /// Jobs started so far by this instance.
+    jobs_started: u32,
+}
+
+impl RenderHost {
+    /// Create a new `RenderHost` backed by the given renderer.
+    pub fn new(renderer: Arc<CommandRenderer>, max_jobs: Option<u32>) -> Self {
+        Self {
+            renderer,
+            max_jobs,
+            jobs_started: 0,
+        }
+    }
+
+    /// Number of render jobs started by this instance.
+    pub fn jobs_started(&self) -> u32 {
+        self.jobs_started
+    }
+}
+
+impl Host for RenderHost {
+    fn render_pdf(&mut self, html: String, options: RenderOptions) -> Result<Vec<u8>, String> {
+        if let Some(max) = self.max_jobs
+            && self.jobs_started >= max
+        {
+            return Err(format!(
+                "QuotaExceeded: instance has used all {max} render jobs"
+            ));
+        }
+        self.jobs_started += 1;
+
+        tracing::debug!(
+            html_bytes = html.len(),
+            page_size = ?options.page_size,
+            landscape = options.landscape,
+            "render intercept: render_pdf"
+        );
+
+        let job = RenderJob {
+            page_size: options.page_size,
+            landscape: options.landscape,
+        };
+        let renderer = Arc::clone(&self.renderer);
+        let render = move || renderer.render_pdf(html.as_bytes(), &job);
+        // The renderer blocks on a child process. On a multi-threaded
+        // runtime, move the worker's tasks elsewhere while it runs, as the
+        // DNS and database hosts do. `block_in_place` panics on a
+        // current-thread runtime, so there it runs on the blocking pool.
+        let result = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(render)
+            }
+            Ok(handle) => {
+                let (tx, rx) = std::sync::mpsc::channel();
+                handle.spawn_blocking(move || {
+                    let _ = tx.send(render());
+                });
+                rx.recv()
+                    .unwrap_or_else(|_| Err("RenderFailed: render task panicked".to_string()))
+            }
+            Err(_) => render(),
+        };
+
+        if let Err(e) = &result {
+            tracing::debug!(error = %e, "render_pdf failed");
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RenderQuotas;
+
+    fn cat_host(max_jobs: Option<u32>) -> RenderHost {
+        let renderer = CommandRenderer::new(
+            "sh",
+            vec!["-c".to_string(), "cat".to_string()],
+            RenderQuotas::default(),
+        );
+        RenderHost::new(Arc::new(renderer), max_jobs)
+    }
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            page_size: None,
+            landscape: false,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn host_render_pdf_returns_renderer_output() {
+        let mut host = cat_host(None);
+        let out = host.render_pdf("<p>hi</p>".to_string(), options()).unwrap();
+        assert_eq!(out, b"<p>hi</p>");
+        assert_eq!(host.jobs_started(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn host_enforces_per_instance_job_quota() {
+        let mut host = cat_host(Some(1));
+        assert!(host.render_pdf("a".to_string(), options()).is_ok());
+
+        let err = host.render_pdf("b".to_string(), options()).unwrap_err();
+        assert!(err.starts_with("QuotaExceeded"), "got: {err}");
+        assert_eq!(host.jobs_started(), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn host_render_pdf_works_on_current_thread_runtime() {
+        let mut host = cat_host(None);
+        let out = host.render_pdf("<p>hi</p>".to_string(), options()).unwrap();
+        assert_eq!(out, b"<p>hi</p>");
+    }
+
+    #[test]
+    fn host_render_pdf_works_outside_a_runtime() {
+        let mut host = cat_host(None);
+        let out = host.render_pdf("<p>hi</p>".to_string(), options()).unwrap();
+        assert_eq!(out, b"<p>hi</p>");
+    }
+}
@@ -16,8 +16,14 @@
 //!     → Queue non-empty → Some(signal_type)
 //!     → Queue empty     → None
 //! ```
+//!
+//! When the instance follows a config bundle (see [`with_config`]), a swap
+//! to a new bundle is delivered as [`SignalType::Hangup`] on the next poll.
+//!
+//! [`with_config`]: SignalsHost::with_config
 
 use crate::bindings::warpgrid::shim::signals::{Host, SignalType};
+use crate::bundles::SharedBundle;
 use super::SignalQueue;
 
 /// Host-side implementation of the `warpgrid:shim/signals` interface.
@@ -32,6 +38,8 @@ use super::SignalQueue;
 /// [`deliver_signal`]: SignalsHost::deliver_signal
 pub struct SignalsHost {
     queue: SignalQueue,
+    /// The deployment's config bundle and the generation last signalled.
+    config: Option<(SharedBundle, u64)>,
 }
 
 impl SignalsHost {
@@ -39,12 +47,22 @@ impl SignalsHost {
     pub fn new() -> Self {
         Self {
             queue: SignalQueue::new(),
+            config: None,
         }
     }
 
     /// Create a new `SignalsHost` wrapping the given signal queue.
     pub fn with_queue(queue: SignalQueue) -> Self {
-        Self { queue }
+        Self { queue, config: None }
+    }
+
+    /// Deliver `Hangup` whenever `config` swaps to a new bundle.
+    ///
+    /// The bundle active now is taken as already loaded by the guest.
+    pub fn with_config(mut self, config: SharedBundle) -> Self {
+        let generation = config.generation();
+        self.config = Some((config, generation));
+        self
     }
 
     /// Host-side API: deliver a signal to this module instance.
@@ -73,6 +91,13 @@ impl Host for SignalsHost {
 
     fn poll_signal(&mut self) -> Option<SignalType> {
         tracing::debug!("signals intercept: poll_signal");
+        if let Some((config, seen)) = &mut self.config {
+            let generation = config.generation();
+            if generation != *seen {
+                *seen = generation;
+                self.queue.deliver(SignalType::Hangup);
+            }
+        }
         self.queue.poll()
     }
 }
@@ -251,4 +276,35 @@ mod tests {
         assert!(matches!(host.poll_signal(), Some(SignalType::Terminate)));
         assert!(host.poll_signal().is_none());
     }
+
+    // ── Config bundle swaps ────────────────────────────────────────
+
+    #[test]
+    fn bundle_swap_delivers_one_hangup() {
+        use crate::bundles::{BundleRegistry, ConfigBundle};
+
+        let registry = BundleRegistry::default();
+        let mut bundle = ConfigBundle::default();
+        bundle.files.insert("/etc/app.toml".into(), "port = 80".into());
+        registry.update("default/api", bundle.clone());
+
+        let mut host = SignalsHost::new().with_config(registry.handle("default/api"));
+        host.on_signal(SignalType::Hangup).unwrap();
+        assert!(host.poll_signal().is_none(), "the starting bundle is not a swap");
+
+        bundle.files.insert("/etc/app.toml".into(), "port = 81".into());
+        registry.update("default/api", bundle);
+        assert!(matches!(host.poll_signal(), Some(SignalType::Hangup)));
+        assert!(host.poll_signal().is_none());
+    }
+
+    #[test]
+    fn bundle_swap_respects_interest() {
+        use crate::bundles::{BundleRegistry, ConfigBundle};
+
+        let registry = BundleRegistry::default();
+        let mut host = SignalsHost::new().with_config(registry.handle("default/api"));
+        registry.update("default/api", ConfigBundle::default());
+        assert!(host.poll_signal().is_none());
+    }
 }
@@ -118,6 +118,8 @@ fn minimal_host_state() -> HostState {
         .build();
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: None,
         db_proxy: None,
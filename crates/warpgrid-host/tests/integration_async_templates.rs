@@ -161,6 +161,8 @@ async fn rust_template_handles_health_request() {
 
     let linker = engine.async_handler_linker().unwrap();
     let host_state = HostState {
+        render: None,
+        flags: None,
         filesystem: None,
         dns: None,
         db_proxy: None,
@@ -210,6 +212,8 @@ async fn rust_template_handles_echo_request() {
 
     let linker = engine.async_handler_linker().unwrap();
     let host_state = HostState {
+        render: None,
+        flags: None,
         filesystem: None,
         dns: None,
         db_proxy: None,
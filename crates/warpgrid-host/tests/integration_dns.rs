@@ -103,6 +103,8 @@ fn test_host_state(
     let runtime_handle = tokio::runtime::Handle::current();
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: None,
         dns: Some(DnsHost::new(cached, runtime_handle)),
         db_proxy: None,
@@ -324,6 +326,7 @@ async fn test_wasm_ttl_caching_within_and_after_expiry() {
     let cache_config = DnsCacheConfig {
         ttl: Duration::from_millis(50),
         max_entries: 1024,
+        max_stale: Duration::ZERO,
     };
 
     // Build a shared CachedDnsResolver that persists across calls
@@ -336,6 +339,8 @@ async fn test_wasm_ttl_caching_within_and_after_expiry() {
                         cached: &Arc<CachedDnsResolver>| {
         let runtime_handle = tokio::runtime::Handle::current();
         let host_state = HostState {
+            render: None,
+            flags: None,
             filesystem: None,
             dns: Some(DnsHost::new(Arc::clone(cached), runtime_handle)),
             db_proxy: None,
@@ -99,6 +99,8 @@ fn test_host_state(etc_hosts: &str) -> HostState {
         .build();
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: None,
         db_proxy: None,
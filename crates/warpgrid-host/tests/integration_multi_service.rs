@@ -277,6 +277,7 @@ fn gateway_host_state(runtime_handle: &tokio::runtime::Handle) -> HostState {
     let cache_config = DnsCacheConfig {
         ttl: Duration::from_secs(30),
         max_entries: 128,
+        max_stale: Duration::ZERO,
     };
     let cached = Arc::new(CachedDnsResolver::new(resolver, cache_config));
 
@@ -288,6 +289,8 @@ fn gateway_host_state(runtime_handle: &tokio::runtime::Handle) -> HostState {
         .build();
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: Some(DnsHost::new(cached, runtime_handle.clone())),
         db_proxy: None,
@@ -316,6 +319,8 @@ fn postgres_host_state(
         .build();
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: None,
         db_proxy: Some(DbProxyHost::new(pool_manager, runtime_handle.clone())),
@@ -344,6 +349,8 @@ fn redis_host_state(
         .build();
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: None,
         db_proxy: Some(DbProxyHost::new(pool_manager, runtime_handle.clone())),
@@ -725,6 +732,7 @@ async fn service_error_includes_source_service_header() {
     let cache_config = DnsCacheConfig {
         ttl: Duration::from_secs(30),
         max_entries: 128,
+        max_stale: Duration::ZERO,
     };
     let cached = Arc::new(CachedDnsResolver::new(resolver, cache_config));
     let file_map = VirtualFileMapBuilder::new().with_dev_null().build();
@@ -733,6 +741,8 @@ async fn service_error_includes_source_service_header() {
     // We need a custom path that maps to a non-existent service.
     // Since the gateway maps paths to hostnames, we use a path not in the route table.
     let state = HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: Some(DnsHost::new(cached, runtime_handle.clone())),
         db_proxy: None,
@@ -788,11 +798,14 @@ async fn dns_failure_populates_error_source() {
     let cache_config = DnsCacheConfig {
         ttl: Duration::from_secs(30),
         max_entries: 128,
+        max_stale: Duration::ZERO,
     };
     let cached = Arc::new(CachedDnsResolver::new(resolver, cache_config));
     let file_map = VirtualFileMapBuilder::new().with_dev_null().build();
 
     let state = HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: Some(DnsHost::new(cached, runtime_handle.clone())),
         db_proxy: None,
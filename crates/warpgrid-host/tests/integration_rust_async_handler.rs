@@ -126,6 +126,8 @@ fn host_state_with_dns() -> HostState {
     let dns = DnsHost::new(cached, runtime_handle);
 
     HostState {
+        render: None,
+        flags: None,
         filesystem: Some(FilesystemHost::new(Arc::new(file_map))),
         dns: Some(dns),
         db_proxy: None,
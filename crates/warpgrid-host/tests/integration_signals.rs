@@ -103,6 +103,8 @@ fn build_signal_guest_component() -> &'static [u8] {
 /// Create a minimal HostState with only the signals shim active.
 fn minimal_host_state() -> HostState {
     HostState {
+        render: None,
+        flags: None,
         filesystem: None,
         dns: None,
         db_proxy: None,
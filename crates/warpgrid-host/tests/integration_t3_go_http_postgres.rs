@@ -299,6 +299,8 @@ fn test_pool_config() -> PoolConfig {
 fn test_host_state(pool_manager: Arc<ConnectionPoolManager>) -> HostState {
     let runtime_handle = tokio::runtime::Handle::current();
     HostState {
+        render: None,
+        flags: None,
         filesystem: None,
         dns: None,
         db_proxy: Some(DbProxyHost::new(pool_manager, runtime_handle)),
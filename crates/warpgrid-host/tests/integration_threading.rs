@@ -104,6 +104,8 @@ fn build_threading_guest_component() -> &'static [u8] {
 /// Create a minimal HostState with only defaults (threading starts as None).
 fn minimal_host_state() -> HostState {
     HostState {
+        render: None,
+        flags: None,
         filesystem: None,
         dns: None,
         db_proxy: None,
@@ -8,7 +8,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast};
 use tracing::{debug, info};
 
 use warpgrid_state::{InstanceStatus, MetricsSnapshot, StateStore};
@@ -46,8 +46,14 @@ impl DeploymentMetrics {
     }
 }
 
+/// Snapshot batches a sink may fall behind by before it starts losing them.
+const SINK_CAPACITY: usize = 16;
+
 /// Collects metrics across all deployments and periodically snapshots
 /// them to the state store.
+///
+/// Sinks (e.g. [`crate::remote_write::RemoteWriter`]) receive every
+/// persisted batch through [`MetricsCollector::subscribe`].
 pub struct MetricsCollector {
     /// Per-deployment metrics: deployment_id → metrics.
     metrics: Arc<RwLock<HashMap<String, Arc<DeploymentMetrics>>>>,
@@ -55,6 +61,8 @@ pub struct MetricsCollector {
     state: StateStore,
     /// Snapshot interval.
     interval: Duration,
+    /// Fan-out of persisted snapshot batches to sinks.
+    sinks: broadcast::Sender<Arc<Vec<MetricsSnapshot>>>,
 }
 
 impl MetricsCollector {
@@ -64,9 +72,15 @@ impl MetricsCollector {
             metrics: Arc::new(RwLock::new(HashMap::new())),
             state,
             interval,
+            sinks: broadcast::channel(SINK_CAPACITY).0,
         }
     }
 
+    /// Receive every snapshot batch persisted from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Vec<MetricsSnapshot>>> {
+        self.sinks.subscribe()
+    }
+
     /// Register a deployment for metrics collection.
     pub async fn register(&self, deployment_id: &str) {
         let mut metrics = self.metrics.write().await;
@@ -198,6 +212,8 @@ impl MetricsCollector {
             deployments = snapshots.len(),
             epoch, "metrics snapshot persisted"
         );
+        // No subscribers is fine: sinks are optional.
+        let _ = self.sinks.send(Arc::new(snapshots.clone()));
         Ok(snapshots)
     }
 
@@ -292,11 +308,12 @@ mod tests {
             namespace: "default".to_string(),
             name: id.to_string(),
             source: "file://test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: None },
+            trigger: TriggerConfig::Http { port: None, hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 3 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -304,6 +321,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 0,
             updated_at: 0,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -397,6 +420,19 @@ mod tests {
         assert_eq!(collector.current_request_count("deploy-1").await, 0);
     }
 
+    #[tokio::test]
+    async fn snapshot_is_sent_to_subscribers() {
+        let collector = MetricsCollector::new(test_state(), Duration::from_secs(60));
+        collector.register("deploy-1").await;
+        let mut sink = collector.subscribe();
+
+        collector.record_request("deploy-1", 5000, false).await;
+        let snapshots = collector.snapshot().await.unwrap();
+
+        let received = sink.try_recv().unwrap();
+        assert_eq!(*received, snapshots);
+    }
+
     #[test]
     fn percentiles_empty() {
         let (p50, p99) = compute_percentiles(&[]);
@@ -0,0 +1,73 @@
+//! Process-wide counters for the DNS shim cache.
+//!
+//! Every instance's DNS resolver records here whether a lookup was answered
+//! fresh from the cache, answered with a stale entry while it is refreshed
+//! in the background, or missed the cache. Failed background refreshes are
+//! counted too: a rising count means guests are running on stale answers
+//! because the service registry or system DNS is unavailable. `/metrics`
+//! renders them with [`render_dns_cache`].
+//!
+//! [`render_dns_cache`]: crate::render_dns_cache
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// DNS cache counters; see [`dns_cache`].
+#[derive(Debug, Default)]
+pub struct DnsCacheCounters {
+    hits: AtomicU64,
+    stale: AtomicU64,
+    misses: AtomicU64,
+    refresh_failures: AtomicU64,
+}
+
+/// Point-in-time copy of [`DnsCacheCounters`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DnsCacheSnapshot {
+    /// Lookups answered by an entry within its TTL.
+    pub hits: u64,
+    /// Lookups answered by an expired entry within the max-staleness window.
+    pub stale: u64,
+    /// Lookups that had to go through the resolution chain.
+    pub misses: u64,
+    /// Background refreshes of stale entries that failed.
+    pub refresh_failures: u64,
+}
+
+static COUNTERS: DnsCacheCounters = DnsCacheCounters {
+    hits: AtomicU64::new(0),
+    stale: AtomicU64::new(0),
+    misses: AtomicU64::new(0),
+    refresh_failures: AtomicU64::new(0),
+};
+
+/// The counters shared by every DNS resolver in this process.
+pub fn dns_cache() -> &'static DnsCacheCounters {
+    &COUNTERS
+}
+
+impl DnsCacheCounters {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stale(&self) {
+        self.stale.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn refresh_failed(&self) {
+        self.refresh_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> DnsCacheSnapshot {
+        DnsCacheSnapshot {
+            hits: self.hits.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            refresh_failures: self.refresh_failures.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Process-wide capture of guest stdout and stderr.
+//!
+//! The HTTP trigger hands each instance a writer that splits its output
+//! into lines and appends them here, tagged with the deployment, the
+//! instance, and the stream. Each deployment keeps its last
+//! [`MAX_LINES_PER_DEPLOYMENT`] lines; older ones are dropped. The API
+//! serves them at `/api/v1/deployments/{id}/logs` for `warp logs`.
+//!
+//! Every line gets a sequence number that increases across the process,
+//! so followers can ask for everything after the last line they saw.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lines kept per deployment.
+pub const MAX_LINES_PER_DEPLOYMENT: usize = 2000;
+
+/// Longer lines are cut at this many bytes.
+pub const MAX_LINE_BYTES: usize = 8 * 1024;
+
+/// Which output stream a line was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// One captured line.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct GuestLogLine {
+    /// Position in this process's capture, increasing across deployments.
+    pub seq: u64,
+    /// Unix time in milliseconds when the line was completed.
+    pub timestamp_ms: u64,
+    pub deployment_id: String,
+    pub instance: String,
+    pub stream: LogStream,
+    /// The line without its trailing newline.
+    pub line: String,
+}
+
+/// Which lines to return from [`GuestLogs::query`].
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery<'a> {
+    /// Only lines with a larger sequence number.
+    pub after: Option<u64>,
+    /// Only lines at or after this Unix time in milliseconds.
+    pub since_ms: Option<u64>,
+    pub instance: Option<&'a str>,
+    pub stream: Option<LogStream>,
+    /// At most this many lines, the newest ones.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_seq: u64,
+    deployments: HashMap<String, VecDeque<GuestLogLine>>,
+}
+
+/// Captured lines per deployment; see [`guest_logs`].
+#[derive(Debug, Default)]
+pub struct GuestLogs {
+    inner: Mutex<Inner>,
+}
+
+static LOGS: LazyLock<GuestLogs> = LazyLock::new(GuestLogs::default);
+
+/// The capture shared by every trigger in this process.
+pub fn guest_logs() -> &'static GuestLogs {
+    &LOGS
+}
+
+impl GuestLogs {
+    /// Append one line (without its newline) written by `instance`.
+    pub fn push(&self, deployment_id: &str, instance: &str, stream: LogStream, line: &[u8]) {
+        let line = &line[..line.len().min(MAX_LINE_BYTES)];
+        let line = String::from_utf8_lossy(line).trim_end_matches('\r').to_string();
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+
+        let mut inner = self.inner.lock().expect("guest log lock poisoned");
+        inner.next_seq += 1;
+        let seq = inner.next_seq;
+        let lines = inner.deployments.entry(deployment_id.to_string()).or_default();
+        if lines.len() == MAX_LINES_PER_DEPLOYMENT {
+            lines.pop_front();
+        }
+        lines.push_back(GuestLogLine {
+            seq,
+            timestamp_ms,
+            deployment_id: deployment_id.to_string(),
+            instance: instance.to_string(),
+            stream,
+            line,
+        });
+    }
+
+    /// Lines of `deployment_id` matching `query`, oldest first.
+    pub fn query(&self, deployment_id: &str, query: &LogQuery<'_>) -> Vec<GuestLogLine> {
+        let inner = self.inner.lock().expect("guest log lock poisoned");
+        let Some(lines) = inner.deployments.get(deployment_id) else {
+            return Vec::new();
+        };
+        let mut out: Vec<GuestLogLine> = lines
+            .iter()
+            .filter(|line| query.after.is_none_or(|after| line.seq > after))
+            .filter(|line| query.since_ms.is_none_or(|since| line.timestamp_ms >= since))
+            .filter(|line| query.instance.is_none_or(|instance| line.instance == instance))
+            .filter(|line| query.stream.is_none_or(|stream| line.stream == stream))
+            .cloned()
+            .collect();
+        if let Some(limit) = query.limit
+            && out.len() > limit
+        {
+            out.drain(..out.len() - limit);
+        }
+        out
+    }
+
+    /// Drop a deployment's lines, e.g. when it is deleted.
+    pub fn forget(&self, deployment_id: &str) {
+        self.inner
+            .lock()
+            .expect("guest log lock poisoned")
+            .deployments
+            .remove(deployment_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_filters_and_limits() {
+        let logs = GuestLogs::default();
+        logs.push("default/api", "req-1", LogStream::Stdout, b"starting\r");
+        logs.push("default/api", "req-1", LogStream::Stderr, b"warning: slow query");
+        logs.push("default/api", "req-2", LogStream::Stdout, b"done");
+        logs.push("default/web", "req-3", LogStream::Stdout, b"other deployment");
+
+        let all = logs.query("default/api", &LogQuery::default());
+        assert_eq!(all.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(), ["starting", "warning: slow query", "done"]);
+        assert!(all.windows(2).all(|w| w[0].seq < w[1].seq));
+
+        let after = logs.query("default/api", &LogQuery { after: Some(all[0].seq), ..Default::default() });
+        assert_eq!(after.len(), 2);
+        let req1 = logs.query("default/api", &LogQuery { instance: Some("req-1"), ..Default::default() });
+        assert_eq!(req1.len(), 2);
+        let stderr = logs.query("default/api", &LogQuery { stream: Some(LogStream::Stderr), ..Default::default() });
+        assert_eq!(stderr[0].line, "warning: slow query");
+        let last = logs.query("default/api", &LogQuery { limit: Some(1), ..Default::default() });
+        assert_eq!(last[0].line, "done");
+
+        logs.forget("default/api");
+        assert!(logs.query("default/api", &LogQuery::default()).is_empty());
+        assert_eq!(logs.query("default/web", &LogQuery::default()).len(), 1);
+    }
+
+    #[test]
+    fn old_lines_are_dropped() {
+        let logs = GuestLogs::default();
+        for n in 0..MAX_LINES_PER_DEPLOYMENT + 10 {
+            logs.push("default/api", "req-1", LogStream::Stdout, format!("line {n}").as_bytes());
+        }
+        let lines = logs.query("default/api", &LogQuery::default());
+        assert_eq!(lines.len(), MAX_LINES_PER_DEPLOYMENT);
+        assert_eq!(lines[0].line, "line 10");
+    }
+}
@@ -1,8 +1,10 @@
 //! warpgrid-metrics — observability for WarpGrid deployments.
 //!
 //! Tracks per-deployment request metrics (RPS, latency, error rate),
-//! persists periodic snapshots to the state store, and provides
-//! Prometheus-compatible text exposition.
+//! persists periodic snapshots to the state store, provides
+//! Prometheus-compatible text exposition, and pushes snapshots to a
+//! Prometheus remote-write endpoint or a statsd agent. Guest stdout and
+//! stderr are captured per deployment in [`guest_logs`].
 //!
 //! # Architecture
 //!
@@ -10,14 +12,35 @@
 //! MetricsCollector
 //!   ├── record_request() ← called per HTTP request
 //!   ├── snapshot() → persists MetricsSnapshot to StateStore
-//!   └── run() → periodic snapshot loop
+//!   ├── run() → periodic snapshot loop
+//!   └── subscribe() → snapshot batches for push sinks
+//!
+//! RemoteWriter (Prometheus remote write)
+//!   └── run() → WAL-buffered, batched pushes of subscribed snapshots
+//!
+//! StatsdSink (statsd / DogStatsD over UDP or UDS)
+//!   └── run() → gauges of subscribed snapshots, tagged per deployment
 //!
 //! Prometheus exposition
-//!   └── render_prometheus() → text/plain for /metrics endpoint
+//!   ├── render_prometheus() → text/plain for /metrics endpoint
+//!   ├── render_state_integrity() → state-store corruption counters
+//!   ├── render_response_streaming() → response streaming counters
+//!   ├── render_route_usage() → guest CPU and memory per route
+//!   └── render_dns_cache() → DNS shim cache counters
 //! ```
 
 pub mod collector;
+pub mod dns;
+pub mod guest_logs;
 pub mod prometheus;
+pub mod remote_write;
+pub mod route_usage;
+pub mod statsd;
+pub mod streaming;
 
 pub use collector::MetricsCollector;
-pub use prometheus::render_prometheus;
+pub use prometheus::{
+    render_dns_cache, render_prometheus, render_response_streaming, render_route_usage, render_state_integrity,
+};
+pub use remote_write::{RemoteWriteConfig, RemoteWriter};
+pub use statsd::{StatsdConfig, StatsdSink};
@@ -5,6 +5,23 @@
 
 use warpgrid_state::MetricsSnapshot;
 
+use crate::dns::DnsCacheSnapshot;
+use crate::route_usage::RouteUsageSnapshot;
+use crate::streaming::StreamingSnapshot;
+
+/// Each gauge of a snapshot as `(metric name, value)`, named as in the
+/// text exposition. Shared by the push sinks.
+pub(crate) fn gauges(s: &MetricsSnapshot) -> [(&'static str, f64); 6] {
+    [
+        ("warpgrid_requests_per_second", s.rps),
+        ("warpgrid_latency_p50_ms", s.latency_p50_ms),
+        ("warpgrid_latency_p99_ms", s.latency_p99_ms),
+        ("warpgrid_error_rate", s.error_rate),
+        ("warpgrid_memory_bytes", s.total_memory_bytes as f64),
+        ("warpgrid_active_instances", s.active_instances as f64),
+    ]
+}
+
 /// Render a list of metrics snapshots into Prometheus text format.
 ///
 /// Produces GAUGE and COUNTER metrics with `deployment` labels.
@@ -69,6 +86,123 @@ pub fn render_prometheus(snapshots: &[MetricsSnapshot]) -> String {
     out
 }
 
+/// Render state-store integrity metrics.
+///
+/// `corrupt_detected` counts corrupt records seen by this process;
+/// `quarantined` is the current size of the quarantine table.
+pub fn render_state_integrity(corrupt_detected: u64, quarantined: usize) -> String {
+    let mut out = String::new();
+
+    out.push_str("# HELP warpgrid_state_corrupt_records_total Corrupt state records detected.\n");
+    out.push_str("# TYPE warpgrid_state_corrupt_records_total counter\n");
+    out.push_str(&format!("warpgrid_state_corrupt_records_total {corrupt_detected}\n"));
+
+    out.push_str("# HELP warpgrid_state_quarantined_records State records held in quarantine.\n");
+    out.push_str("# TYPE warpgrid_state_quarantined_records gauge\n");
+    out.push_str(&format!("warpgrid_state_quarantined_records {quarantined}\n"));
+
+    out
+}
+
+/// Render response streaming counters (see [`crate::streaming`]).
+pub fn render_response_streaming(s: &StreamingSnapshot) -> String {
+    let mut out = String::new();
+    let metrics: [(&str, &str, &str, u64); 5] = [
+        ("warpgrid_response_streams_total", "counter", "Response bodies streamed to clients.", s.streams),
+        ("warpgrid_response_streams_active", "gauge", "Response bodies streaming now.", s.active),
+        ("warpgrid_response_stream_bytes_total", "counter", "Response body bytes streamed to clients.", s.bytes),
+        (
+            "warpgrid_response_stream_backpressure_total",
+            "counter",
+            "Times a guest waited on a slow client.",
+            s.backpressure_waits,
+        ),
+        (
+            "warpgrid_response_stream_stall_timeouts_total",
+            "counter",
+            "Response streams abandoned because the client stopped reading.",
+            s.stall_timeouts,
+        ),
+    ];
+    for (name, kind, help, value) in metrics {
+        out.push_str(&format!("# HELP {name} {help}\n"));
+        out.push_str(&format!("# TYPE {name} {kind}\n"));
+        out.push_str(&format!("{name} {value}\n"));
+    }
+    out
+}
+
+/// A per-route metric as `(name, type, help, value of a route)`.
+type RouteMetric = (&'static str, &'static str, &'static str, fn(&RouteUsageSnapshot) -> String);
+
+/// Render per-route guest usage (see [`crate::route_usage`]).
+pub fn render_route_usage(routes: &[RouteUsageSnapshot]) -> String {
+    let mut out = String::new();
+    let metrics: [RouteMetric; 4] = [
+        ("warpgrid_route_requests_total", "counter", "Requests served per route.", |r| {
+            r.requests.to_string()
+        }),
+        ("warpgrid_route_cpu_seconds_total", "counter", "Guest CPU time charged to a route.", |r| {
+            format!("{:.3}", r.cpu.as_secs_f64())
+        }),
+        (
+            "warpgrid_route_memory_growth_bytes_total",
+            "counter",
+            "Guest linear memory grown by a route's requests.",
+            |r| r.memory_growth_bytes.to_string(),
+        ),
+        (
+            "warpgrid_route_memory_growth_max_bytes",
+            "gauge",
+            "Most guest linear memory grown by one request of a route.",
+            |r| r.max_memory_growth_bytes.to_string(),
+        ),
+    ];
+    for (name, kind, help, value) in metrics {
+        out.push_str(&format!("# HELP {name} {help}\n"));
+        out.push_str(&format!("# TYPE {name} {kind}\n"));
+        for r in routes {
+            out.push_str(&format!(
+                "{name}{{deployment=\"{}\",route=\"{}\"}} {}\n",
+                r.deployment_id,
+                escape_label(&r.route),
+                value(r)
+            ));
+        }
+    }
+    out
+}
+
+/// Escape a label value; routes come from request paths.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Render DNS shim cache counters (see [`crate::dns`]).
+pub fn render_dns_cache(s: &DnsCacheSnapshot) -> String {
+    let mut out = String::new();
+    let metrics: [(&str, &str, u64); 4] = [
+        ("warpgrid_dns_cache_hits_total", "DNS lookups answered from the cache within TTL.", s.hits),
+        (
+            "warpgrid_dns_cache_stale_total",
+            "DNS lookups answered with a stale entry while it was refreshed.",
+            s.stale,
+        ),
+        ("warpgrid_dns_cache_misses_total", "DNS lookups that went through the resolution chain.", s.misses),
+        (
+            "warpgrid_dns_cache_refresh_failures_total",
+            "Background refreshes of stale DNS entries that failed.",
+            s.refresh_failures,
+        ),
+    ];
+    for (name, help, value) in metrics {
+        out.push_str(&format!("# HELP {name} {help}\n"));
+        out.push_str(&format!("# TYPE {name} counter\n"));
+        out.push_str(&format!("{name} {value}\n"));
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,4 +269,56 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn render_state_integrity_counters() {
+        let output = render_state_integrity(3, 2);
+        assert!(output.contains("# TYPE warpgrid_state_corrupt_records_total counter"));
+        assert!(output.contains("warpgrid_state_corrupt_records_total 3\n"));
+        assert!(output.contains("warpgrid_state_quarantined_records 2\n"));
+    }
+
+    #[test]
+    fn render_response_streaming_counters() {
+        let output = render_response_streaming(&StreamingSnapshot {
+            streams: 5,
+            active: 1,
+            bytes: 4096,
+            backpressure_waits: 7,
+            stall_timeouts: 2,
+        });
+        assert!(output.contains("# TYPE warpgrid_response_streams_active gauge"));
+        assert!(output.contains("warpgrid_response_stream_backpressure_total 7\n"));
+        assert!(output.contains("warpgrid_response_stream_stall_timeouts_total 2\n"));
+    }
+
+    #[test]
+    fn render_route_usage_series() {
+        let output = render_route_usage(&[RouteUsageSnapshot {
+            deployment_id: "default/api".to_string(),
+            route: "GET /search\"q".to_string(),
+            requests: 3,
+            cpu: std::time::Duration::from_millis(1250),
+            memory_growth_bytes: 196_608,
+            max_memory_growth_bytes: 131_072,
+        }]);
+        assert!(output.contains("# TYPE warpgrid_route_cpu_seconds_total counter"));
+        assert!(output.contains(
+            "warpgrid_route_cpu_seconds_total{deployment=\"default/api\",route=\"GET /search\\\"q\"} 1.250\n"
+        ));
+        assert!(output.contains("warpgrid_route_memory_growth_max_bytes{deployment=\"default/api\""));
+    }
+
+    #[test]
+    fn render_dns_cache_counters() {
+        let output = render_dns_cache(&DnsCacheSnapshot {
+            hits: 40,
+            stale: 3,
+            misses: 5,
+            refresh_failures: 2,
+        });
+        assert!(output.contains("# TYPE warpgrid_dns_cache_stale_total counter"));
+        assert!(output.contains("warpgrid_dns_cache_hits_total 40\n"));
+        assert!(output.contains("warpgrid_dns_cache_refresh_failures_total 2\n"));
+    }
 }
@@ -0,0 +1,143 @@
+//! HTTP/1.1 POST to the remote-write endpoint, over plain TCP or TLS
+//! (verified against the bundled web PKI roots).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::rt::{Read, Write};
+use tokio::net::TcpStream;
+
+use super::RemoteWriteError;
+
+pub(crate) struct Client {
+    uri: http::Uri,
+    host: String,
+    port: u16,
+    tls: Option<tokio_rustls::TlsConnector>,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+}
+
+impl Client {
+    pub(crate) fn new(
+        url: &str,
+        headers: Vec<(String, String)>,
+        timeout: Duration,
+    ) -> Result<Self, RemoteWriteError> {
+        let uri: http::Uri = url
+            .parse()
+            .map_err(|e| RemoteWriteError::Config(format!("{url}: {e}")))?;
+        let tls = match uri.scheme_str() {
+            Some("http") => None,
+            Some("https") => Some(tls_connector()?),
+            _ => {
+                return Err(RemoteWriteError::Config(format!(
+                    "{url}: expected an http:// or https:// URL"
+                )));
+            }
+        };
+        let host = uri
+            .host()
+            .ok_or_else(|| RemoteWriteError::Config(format!("{url}: missing host")))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = uri.port_u16().unwrap_or(if tls.is_some() { 443 } else { 80 });
+        Ok(Self {
+            uri,
+            host,
+            port,
+            tls,
+            headers,
+            timeout,
+        })
+    }
+
+    /// POST `body`. Non-2xx responses come back as
+    /// [`RemoteWriteError::Rejected`].
+    pub(crate) async fn post(&self, body: Vec<u8>) -> Result<(), RemoteWriteError> {
+        let send = async {
+            let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+            match &self.tls {
+                None => self.send(hyper_util::rt::TokioIo::new(stream), body).await,
+                Some(connector) => {
+                    let name = rustls::pki_types::ServerName::try_from(self.host.clone())
+                        .map_err(|e| RemoteWriteError::Config(format!("{}: {e}", self.host)))?;
+                    let stream = connector.connect(name, stream).await?;
+                    self.send(hyper_util::rt::TokioIo::new(stream), body).await
+                }
+            }
+        };
+        tokio::time::timeout(self.timeout, send)
+            .await
+            .map_err(|_| RemoteWriteError::Http(format!("{}: timed out", self.uri)))?
+    }
+
+    async fn send<IO>(&self, io: IO, body: Vec<u8>) -> Result<(), RemoteWriteError>
+    where
+        IO: Read + Write + Unpin + Send + 'static,
+    {
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(io)
+            .await
+            .map_err(|e| RemoteWriteError::Http(e.to_string()))?;
+        tokio::spawn(async move {
+            let _ = conn.await;
+        });
+
+        let path = self
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let authority = self.uri.authority().map(|a| a.as_str()).unwrap_or(&self.host);
+        let mut builder = http::Request::builder()
+            .method("POST")
+            .uri(path)
+            .header("host", authority)
+            .header("user-agent", "warpgrid-metrics/0.1")
+            .header("content-type", "application/x-protobuf")
+            .header("content-encoding", "snappy")
+            .header("x-prometheus-remote-write-version", "0.1.0");
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let request = builder
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| RemoteWriteError::Http(e.to_string()))?;
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| RemoteWriteError::Http(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map(|body| String::from_utf8_lossy(&body.to_bytes()).trim().to_string())
+            .unwrap_or_default();
+        Err(RemoteWriteError::Rejected {
+            status: status.as_u16(),
+            body,
+        })
+    }
+}
+
+fn tls_connector() -> Result<tokio_rustls::TlsConnector, RemoteWriteError> {
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(
+        rustls::crypto::ring::default_provider().into(),
+    )
+    .with_safe_default_protocol_versions()
+    .map_err(|e| RemoteWriteError::Config(e.to_string()))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(config)))
+}
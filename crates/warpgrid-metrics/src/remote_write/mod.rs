@@ -0,0 +1,504 @@
+//! Pushing metrics snapshots to a Prometheus remote-write endpoint
+//! (Prometheus, VictoriaMetrics, Mimir, ...).
+//!
+//! [`RemoteWriter`] subscribes to the collector's snapshots, turns each
+//! into the same gauges the `/metrics` endpoint exposes, and POSTs them in
+//! batches. Configured from a TOML file, e.g.:
+//!
+//! ```toml
+//! url = "https://vm.example.com/api/v1/write"
+//! bearer_token = "..."             # or [basic_auth] username / password
+//! batch_size = 500                 # samples per request
+//! flush_interval_secs = 15
+//! max_retries = 5                  # per flush; backoff doubles from retry_backoff_ms
+//! retry_backoff_ms = 500
+//! timeout_secs = 10
+//! max_pending = 100000             # oldest samples are dropped beyond this
+//! wal_dir = "/var/lib/warpgrid/remote-write"   # default: <data-dir>/remote-write
+//!
+//! [external_labels]
+//! cluster = "prod-eu"
+//!
+//! [headers]
+//! X-Scope-OrgID = "warpgrid"
+//! ```
+//!
+//! Samples are written to a WAL before they are sent and removed only once
+//! the endpoint accepts them, so an outage or a daemon restart delays
+//! samples instead of losing them. Network errors, `429`, and `5xx` are
+//! retried; any other rejection drops the batch, since resending the same
+//! bytes cannot succeed.
+
+mod client;
+mod proto;
+mod wal;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, info, warn};
+use warpgrid_state::MetricsSnapshot;
+
+use client::Client;
+use wal::Wal;
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_flush_interval_secs() -> u64 {
+    15
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+fn default_max_pending() -> usize {
+    100_000
+}
+
+/// Errors configuring or talking to the remote-write endpoint.
+#[derive(Debug, thiserror::Error)]
+pub enum RemoteWriteError {
+    #[error("invalid remote-write config: {0}")]
+    Config(String),
+    #[error("remote-write I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("remote write failed: {0}")]
+    Http(String),
+    #[error("remote write rejected with {status}: {body}")]
+    Rejected { status: u16, body: String },
+}
+
+impl RemoteWriteError {
+    /// Whether sending the same batch again may succeed.
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Rejected { status, .. } => *status == 429 || *status >= 500,
+            Self::Config(_) => false,
+            Self::Io(_) | Self::Http(_) => true,
+        }
+    }
+}
+
+/// Where and how to push samples.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteWriteConfig {
+    /// Endpoint URL, `http://` or `https://`.
+    pub url: String,
+    /// Sent as `Authorization: Bearer <token>`.
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    #[serde(default)]
+    pub basic_auth: Option<BasicAuth>,
+    /// Extra request headers, e.g. a tenant id.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Labels added to every series.
+    #[serde(default)]
+    pub external_labels: BTreeMap<String, String>,
+    /// Maximum samples per request.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Seconds between flushes of a partial batch.
+    #[serde(default = "default_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+    /// Retries of a failed request within one flush.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each further one.
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Per-request timeout, in seconds.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Samples kept while the endpoint is unreachable.
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,
+    /// WAL directory (the daemon defaults it into its data directory).
+    #[serde(default)]
+    pub wal_dir: Option<PathBuf>,
+}
+
+/// HTTP basic authentication.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BasicAuth {
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+impl RemoteWriteConfig {
+    /// Read a config file.
+    pub fn load(path: &Path) -> Result<Self, RemoteWriteError> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| RemoteWriteError::Config(format!("{}: {e}", path.display())))
+    }
+
+    /// Request headers, including authentication.
+    fn request_headers(&self) -> Result<Vec<(String, String)>, RemoteWriteError> {
+        let mut headers: Vec<(String, String)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        match (&self.bearer_token, &self.basic_auth) {
+            (Some(_), Some(_)) => {
+                return Err(RemoteWriteError::Config(
+                    "set bearer_token or basic_auth, not both".to_string(),
+                ));
+            }
+            (Some(token), None) => {
+                headers.push(("authorization".to_string(), format!("Bearer {token}")));
+            }
+            (None, Some(auth)) => {
+                let credentials = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", auth.username, auth.password));
+                headers.push(("authorization".to_string(), format!("Basic {credentials}")));
+            }
+            (None, None) => {}
+        }
+        Ok(headers)
+    }
+}
+
+/// One gauge value awaiting delivery, as stored in the WAL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Sample {
+    pub metric: String,
+    pub deployment: String,
+    pub value: f64,
+    pub timestamp_ms: i64,
+}
+
+/// The gauges of `snapshots`, timestamped with each snapshot's epoch.
+fn samples(snapshots: &[MetricsSnapshot]) -> Vec<Sample> {
+    snapshots
+        .iter()
+        .flat_map(|snapshot| {
+            crate::prometheus::gauges(snapshot)
+                .into_iter()
+                .map(|(metric, value)| Sample {
+                    metric: metric.to_string(),
+                    deployment: snapshot.deployment_id.clone(),
+                    value,
+                    timestamp_ms: snapshot.epoch as i64 * 1000,
+                })
+        })
+        .collect()
+}
+
+/// Pushes snapshots to a remote-write endpoint, buffering them in a WAL.
+pub struct RemoteWriter {
+    config: RemoteWriteConfig,
+    client: Client,
+    wal: Wal,
+    pending: VecDeque<Sample>,
+}
+
+impl RemoteWriter {
+    /// Validate `config` and reopen its WAL. `default_wal_dir` is used
+    /// when the config does not name one.
+    pub fn new(config: RemoteWriteConfig, default_wal_dir: &Path) -> Result<Self, RemoteWriteError> {
+        if config.batch_size == 0 {
+            return Err(RemoteWriteError::Config("batch_size must be at least 1".to_string()));
+        }
+        let client = Client::new(
+            &config.url,
+            config.request_headers()?,
+            Duration::from_secs(config.timeout_secs),
+        )?;
+        let wal_dir = config.wal_dir.clone().unwrap_or_else(|| default_wal_dir.to_path_buf());
+        let (wal, pending) = Wal::open(&wal_dir)?;
+        if !pending.is_empty() {
+            info!(samples = pending.len(), wal = %wal_dir.display(), "restored pending remote-write samples");
+        }
+        let mut writer = Self {
+            config,
+            client,
+            wal,
+            pending,
+        };
+        writer.enforce_max_pending()?;
+        Ok(writer)
+    }
+
+    /// Samples not yet accepted by the endpoint.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Buffer the gauges of `snapshots` (durably) for the next flush.
+    pub fn enqueue(&mut self, snapshots: &[MetricsSnapshot]) -> Result<(), RemoteWriteError> {
+        let samples = samples(snapshots);
+        if samples.is_empty() {
+            return Ok(());
+        }
+        self.wal.append(&samples)?;
+        self.pending.extend(samples);
+        self.enforce_max_pending()
+    }
+
+    /// Send every pending sample, batch by batch, retrying transient
+    /// failures. Stops at the first batch that still fails; its samples
+    /// stay pending for the next flush.
+    pub async fn flush(&mut self) -> Result<(), RemoteWriteError> {
+        while !self.pending.is_empty() {
+            let count = self.pending.len().min(self.config.batch_size);
+            let batch: Vec<Sample> = self.pending.iter().take(count).cloned().collect();
+            let body = proto::encode(&proto::write_request(&batch, &self.config.external_labels));
+
+            match self.send_with_retries(body).await {
+                Ok(()) => debug!(samples = count, "remote write accepted"),
+                Err(e) if e.is_retryable() => return Err(e),
+                Err(e) => warn!(samples = count, error = %e, "dropping remote-write batch"),
+            }
+            self.pending.drain(..count);
+            self.wal.rewrite(&self.pending)?;
+        }
+        Ok(())
+    }
+
+    async fn send_with_retries(&self, body: Vec<u8>) -> Result<(), RemoteWriteError> {
+        let mut backoff = Duration::from_millis(self.config.retry_backoff_ms);
+        let mut attempt = 0;
+        loop {
+            match self.client.post(body.clone()).await {
+                Err(e) if e.is_retryable() && attempt < self.config.max_retries => {
+                    attempt += 1;
+                    debug!(attempt, error = %e, "retrying remote write");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Drop the oldest samples beyond `max_pending`.
+    fn enforce_max_pending(&mut self) -> Result<(), RemoteWriteError> {
+        let excess = self.pending.len().saturating_sub(self.config.max_pending);
+        if excess > 0 {
+            warn!(dropped = excess, max_pending = self.config.max_pending, "remote-write buffer full; dropping oldest samples");
+            self.pending.drain(..excess);
+            self.wal.rewrite(&self.pending)?;
+        }
+        Ok(())
+    }
+
+    /// Push `snapshots` as they arrive until `shutdown` fires; a final
+    /// flush is attempted on the way out and anything left stays in the WAL.
+    pub async fn run(
+        mut self,
+        mut snapshots: broadcast::Receiver<Arc<Vec<MetricsSnapshot>>>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        info!(url = %self.config.url, "remote-write exporter started");
+        let mut ticker = tokio::time::interval(Duration::from_secs(self.config.flush_interval_secs.max(1)));
+        loop {
+            tokio::select! {
+                received = snapshots.recv() => match received {
+                    Ok(batch) => {
+                        if let Err(e) = self.enqueue(&batch) {
+                            warn!(error = %e, "failed to buffer remote-write samples");
+                        }
+                        if self.pending.len() >= self.config.batch_size
+                            && let Err(e) = self.flush().await
+                        {
+                            warn!(error = %e, pending = self.pending.len(), "remote write failed");
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(missed, "remote-write exporter fell behind; snapshots skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = ticker.tick() => {
+                    if let Err(e) = self.flush().await {
+                        warn!(error = %e, pending = self.pending.len(), "remote write failed");
+                    }
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        // One bounded attempt to deliver what is buffered.
+        while let Ok(batch) = snapshots.try_recv() {
+            if let Err(e) = self.enqueue(&batch) {
+                warn!(error = %e, "failed to buffer remote-write samples");
+            }
+        }
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        match tokio::time::timeout(timeout, self.flush()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!(error = %e, pending = self.pending.len(), "final remote write failed; samples kept in the WAL"),
+            Err(_) => warn!(pending = self.pending.len(), "final remote write timed out; samples kept in the WAL"),
+        }
+        info!("remote-write exporter stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn snapshot(deployment: &str, epoch: u64, rps: f64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            deployment_id: deployment.to_string(),
+            epoch,
+            rps,
+            latency_p50_ms: 1.5,
+            latency_p99_ms: 9.0,
+            error_rate: 0.0,
+            total_memory_bytes: 64,
+            active_instances: 2,
+        }
+    }
+
+    fn config(url: &str, dir: &Path) -> RemoteWriteConfig {
+        let mut config: RemoteWriteConfig = toml::from_str(&format!(
+            "url = \"{url}\"\nbatch_size = 6\nretry_backoff_ms = 1\nmax_retries = 1\n\
+             [external_labels]\ncluster = \"eu\"\n"
+        ))
+        .unwrap();
+        config.wal_dir = Some(dir.to_path_buf());
+        config
+    }
+
+    /// Accept connections, answering each request with the next status
+    /// and forwarding the decoded body.
+    async fn fake_endpoint(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<proto::WriteRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v1/write", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let body = loop {
+                    let mut chunk = [0u8; 4096];
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    let Some(end) = text.find("\r\n\r\n") else { continue };
+                    let length: usize = text[..end]
+                        .lines()
+                        .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length: ").map(|v| v.parse().unwrap()))
+                        .unwrap();
+                    if buf.len() >= end + 4 + length {
+                        break buf[end + 4..end + 4 + length].to_vec();
+                    }
+                };
+                let raw = snap::raw::Decoder::new().decompress_vec(&body).unwrap();
+                tx.send(proto::WriteRequest::decode(raw.as_slice()).unwrap()).unwrap();
+                let response = format!("HTTP/1.1 {status} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[test]
+    fn test_series_are_labelled_and_sorted() {
+        let samples = samples(&[snapshot("api", 10, 4.0), snapshot("api", 20, 5.0)]);
+        let labels = BTreeMap::from([("cluster".to_string(), "eu".to_string())]);
+        let request = proto::write_request(&samples, &labels);
+
+        assert_eq!(request.timeseries.len(), 6);
+        let rps = request
+            .timeseries
+            .iter()
+            .find(|series| series.labels.iter().any(|l| l.value == "warpgrid_requests_per_second"))
+            .unwrap();
+        let names: Vec<&str> = rps.labels.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["__name__", "cluster", "deployment"]);
+        let points: Vec<(i64, f64)> = rps.samples.iter().map(|s| (s.timestamp, s.value)).collect();
+        assert_eq!(points, [(10_000, 4.0), (20_000, 5.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_flush_batches_and_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        // First batch: one 503, then accepted. Second batch: accepted.
+        let (url, mut received) = fake_endpoint(vec![503, 200, 200]).await;
+        let mut writer = RemoteWriter::new(config(&url, dir.path()), dir.path()).unwrap();
+
+        writer.enqueue(&[snapshot("api", 10, 4.0), snapshot("web", 10, 1.0)]).unwrap();
+        assert_eq!(writer.pending(), 12);
+        writer.flush().await.unwrap();
+        assert_eq!(writer.pending(), 0);
+
+        let mut series = 0;
+        for _ in 0..3 {
+            let request = received.recv().await.unwrap();
+            series += request.timeseries.len();
+            let cluster = &request.timeseries[0].labels[1];
+            assert_eq!((cluster.name.as_str(), cluster.value.as_str()), ("cluster", "eu"));
+        }
+        // The retried batch is resent whole.
+        assert_eq!(series, 18);
+    }
+
+    #[tokio::test]
+    async fn test_pending_samples_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, _received) = fake_endpoint(vec![503, 503]).await;
+        let mut writer = RemoteWriter::new(config(&url, dir.path()), dir.path()).unwrap();
+        writer.enqueue(&[snapshot("api", 10, 4.0)]).unwrap();
+        assert!(writer.flush().await.is_err());
+        drop(writer);
+
+        // A torn trailing line from a crash is skipped.
+        let wal = dir.path().join("pending.jsonl");
+        let mut text = std::fs::read_to_string(&wal).unwrap();
+        text.push_str("{\"metric\":");
+        std::fs::write(&wal, text).unwrap();
+
+        let (url, mut received) = fake_endpoint(vec![400]).await;
+        let mut writer = RemoteWriter::new(config(&url, dir.path()), dir.path()).unwrap();
+        assert_eq!(writer.pending(), 6);
+        // A rejected batch is dropped rather than retried forever.
+        writer.flush().await.unwrap();
+        assert_eq!(received.recv().await.unwrap().timeseries.len(), 6);
+        assert_eq!(writer.pending(), 0);
+        assert_eq!(std::fs::read_to_string(&wal).unwrap(), "");
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bad = config("ftp://example.com/write", dir.path());
+        assert!(matches!(RemoteWriter::new(bad.clone(), dir.path()), Err(RemoteWriteError::Config(_))));
+
+        bad.url = "http://example.com/write".to_string();
+        bad.bearer_token = Some("t".to_string());
+        bad.basic_auth = Some(BasicAuth { username: "u".to_string(), password: "p".to_string() });
+        assert!(matches!(RemoteWriter::new(bad, dir.path()), Err(RemoteWriteError::Config(_))));
+
+        let mut full = config("http://example.com/write", dir.path());
+        full.max_pending = 6;
+        let mut writer = RemoteWriter::new(full, dir.path()).unwrap();
+        writer.enqueue(&[snapshot("api", 10, 1.0), snapshot("api", 20, 2.0)]).unwrap();
+        assert_eq!(writer.pending(), 6);
+        assert!(writer.pending.iter().all(|s| s.timestamp_ms == 20_000));
+    }
+}
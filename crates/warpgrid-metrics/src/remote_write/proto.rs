@@ -0,0 +1,93 @@
+//! The remote-write 1.0 wire format: a snappy-compressed (block format,
+//! not framed) protobuf `prometheus.WriteRequest`.
+//!
+//! Only the fields WarpGrid sends are declared; receivers ignore the
+//! metadata field we leave out.
+
+use std::collections::BTreeMap;
+
+use prost::Message;
+
+use super::Sample;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub timeseries: Vec<TimeSeries>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct TimeSeries {
+    /// Sorted by name.
+    #[prost(message, repeated, tag = "1")]
+    pub labels: Vec<Label>,
+    /// Oldest first.
+    #[prost(message, repeated, tag = "2")]
+    pub samples: Vec<ProtoSample>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Label {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ProtoSample {
+    #[prost(double, tag = "1")]
+    pub value: f64,
+    /// Milliseconds since the epoch.
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+}
+
+/// Group `samples` into one series per metric and deployment, labelled
+/// `__name__`, `deployment`, and `external_labels`.
+pub(crate) fn write_request(
+    samples: &[Sample],
+    external_labels: &BTreeMap<String, String>,
+) -> WriteRequest {
+    let mut series: BTreeMap<(&str, &str), Vec<ProtoSample>> = BTreeMap::new();
+    for sample in samples {
+        series
+            .entry((sample.metric.as_str(), sample.deployment.as_str()))
+            .or_default()
+            .push(ProtoSample {
+                value: sample.value,
+                timestamp: sample.timestamp_ms,
+            });
+    }
+
+    let timeseries = series
+        .into_iter()
+        .map(|((metric, deployment), mut samples)| {
+            let mut labels: BTreeMap<&str, &str> = external_labels
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            labels.insert("__name__", metric);
+            labels.insert("deployment", deployment);
+            samples.sort_by_key(|sample| sample.timestamp);
+            TimeSeries {
+                labels: labels
+                    .into_iter()
+                    .map(|(name, value)| Label {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+                samples,
+            }
+        })
+        .collect();
+    WriteRequest { timeseries }
+}
+
+/// The HTTP request body for `request`.
+pub(crate) fn encode(request: &WriteRequest) -> Vec<u8> {
+    snap::raw::Encoder::new()
+        .compress_vec(&request.encode_to_vec())
+        .expect("snappy compression of an in-memory buffer cannot fail")
+}
@@ -0,0 +1,79 @@
+//! On-disk buffer of samples not yet accepted by the endpoint.
+//!
+//! `pending.jsonl` holds one sample per line, oldest first. New samples
+//! are appended; once a batch is accepted (or dropped) the file is
+//! rewritten from the in-memory queue via a temp file and rename, so a
+//! crash leaves either the old or the new contents. A torn last line from
+//! a crash mid-append is skipped on load.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use super::{RemoteWriteError, Sample};
+
+const FILE_NAME: &str = "pending.jsonl";
+
+pub(crate) struct Wal {
+    path: PathBuf,
+}
+
+impl Wal {
+    /// Open the WAL in `dir`, returning it with the samples it holds.
+    pub(crate) fn open(dir: &Path) -> Result<(Self, VecDeque<Sample>), RemoteWriteError> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let mut samples = VecDeque::new();
+        if path.exists() {
+            let text = fs::read_to_string(&path)?;
+            for (index, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(line) {
+                    Ok(sample) => samples.push_back(sample),
+                    Err(e) => warn!(
+                        path = %path.display(),
+                        line = index + 1,
+                        error = %e,
+                        "skipping unreadable remote-write WAL entry"
+                    ),
+                }
+            }
+        }
+        Ok((Self { path }, samples))
+    }
+
+    /// Append `samples` and sync them to disk.
+    pub(crate) fn append(&self, samples: &[Sample]) -> Result<(), RemoteWriteError> {
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut out = BufWriter::new(file);
+        write_lines(&mut out, samples.iter())?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_data()?;
+        Ok(())
+    }
+
+    /// Replace the WAL contents with `samples`.
+    pub(crate) fn rewrite(&self, samples: &VecDeque<Sample>) -> Result<(), RemoteWriteError> {
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        write_lines(&mut out, samples.iter())?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_data()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+fn write_lines<'a>(
+    out: &mut impl Write,
+    samples: impl Iterator<Item = &'a Sample>,
+) -> Result<(), RemoteWriteError> {
+    for sample in samples {
+        serde_json::to_writer(&mut *out, sample).map_err(std::io::Error::from)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Process-wide guest resource usage per route.
+//!
+//! The HTTP trigger charges each request's guest CPU (epoch ticks times
+//! the tick interval) and linear-memory growth to the request's route, so
+//! expensive endpoints show up without an external profiler. `/metrics`
+//! renders the totals with [`render_route_usage`].
+//!
+//! Routes are `METHOD /path` with id-like segments (numbers, UUIDs, long
+//! hex strings) collapsed to `:id`; see [`route_key`]. Each deployment
+//! keeps at most [`MAX_ROUTES_PER_DEPLOYMENT`] routes; requests to further
+//! routes are counted under [`OVERFLOW_ROUTE`].
+//!
+//! [`render_route_usage`]: crate::render_route_usage
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Distinct routes tracked per deployment.
+pub const MAX_ROUTES_PER_DEPLOYMENT: usize = 128;
+
+/// Route that requests beyond [`MAX_ROUTES_PER_DEPLOYMENT`] are counted under.
+pub const OVERFLOW_ROUTE: &str = "other";
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    requests: u64,
+    cpu: Duration,
+    memory_growth_bytes: u64,
+    max_memory_growth_bytes: u64,
+}
+
+/// Per-route usage; see [`route_usage`].
+#[derive(Debug, Default)]
+pub struct RouteUsage {
+    /// deployment id → route → totals.
+    routes: Mutex<HashMap<String, HashMap<String, Totals>>>,
+}
+
+/// Point-in-time totals of one route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteUsageSnapshot {
+    pub deployment_id: String,
+    pub route: String,
+    /// Requests that finished on this route.
+    pub requests: u64,
+    /// Guest CPU time charged to them (zero on unmetered engines).
+    pub cpu: Duration,
+    /// Linear memory they grew, summed.
+    pub memory_growth_bytes: u64,
+    /// Most linear memory one of them grew.
+    pub max_memory_growth_bytes: u64,
+}
+
+static USAGE: LazyLock<RouteUsage> = LazyLock::new(RouteUsage::default);
+
+/// The usage shared by every trigger in this process.
+pub fn route_usage() -> &'static RouteUsage {
+    &USAGE
+}
+
+impl RouteUsage {
+    /// Charge one finished request to `route` of `deployment_id`.
+    pub fn record(&self, deployment_id: &str, route: &str, cpu: Duration, memory_growth_bytes: u64) {
+        let mut routes = self.routes.lock().expect("route usage lock poisoned");
+        let deployment = routes.entry(deployment_id.to_string()).or_default();
+        let route = if deployment.contains_key(route) || deployment.len() < MAX_ROUTES_PER_DEPLOYMENT {
+            route
+        } else {
+            OVERFLOW_ROUTE
+        };
+        let totals = deployment.entry(route.to_string()).or_default();
+        totals.requests += 1;
+        totals.cpu += cpu;
+        totals.memory_growth_bytes += memory_growth_bytes;
+        totals.max_memory_growth_bytes = totals.max_memory_growth_bytes.max(memory_growth_bytes);
+    }
+
+    /// Drop a deployment's routes, e.g. when it is deleted.
+    pub fn forget(&self, deployment_id: &str) {
+        self.routes.lock().expect("route usage lock poisoned").remove(deployment_id);
+    }
+
+    /// Every route's totals, sorted by deployment and route.
+    pub fn snapshot(&self) -> Vec<RouteUsageSnapshot> {
+        let routes = self.routes.lock().expect("route usage lock poisoned");
+        let mut out: Vec<RouteUsageSnapshot> = routes
+            .iter()
+            .flat_map(|(deployment_id, routes)| {
+                routes.iter().map(move |(route, totals)| RouteUsageSnapshot {
+                    deployment_id: deployment_id.clone(),
+                    route: route.clone(),
+                    requests: totals.requests,
+                    cpu: totals.cpu,
+                    memory_growth_bytes: totals.memory_growth_bytes,
+                    max_memory_growth_bytes: totals.max_memory_growth_bytes,
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| (&a.deployment_id, &a.route).cmp(&(&b.deployment_id, &b.route)));
+        out
+    }
+}
+
+/// `METHOD /path` with id-like segments replaced by `:id`, so
+/// `/users/42` and `/users/43` share a route.
+pub fn route_key(method: &str, path: &str) -> String {
+    let segments: Vec<&str> = path
+        .split('/')
+        .map(|segment| if is_id_like(segment) { ":id" } else { segment })
+        .collect();
+    format!("{method} {}", segments.join("/"))
+}
+
+/// Numbers, and UUID- or hash-like segments of 16+ hex digits and dashes.
+fn is_id_like(segment: &str) -> bool {
+    if segment.is_empty() {
+        return false;
+    }
+    if segment.bytes().all(|b| b.is_ascii_digit()) {
+        return true;
+    }
+    segment.len() >= 16
+        && segment.bytes().any(|b| b.is_ascii_digit())
+        && segment.bytes().all(|b| b.is_ascii_hexdigit() || b == b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_keys_collapse_ids() {
+        assert_eq!(route_key("GET", "/users/42/orders"), "GET /users/:id/orders");
+        assert_eq!(
+            route_key("DELETE", "/items/6f1c2a9e-3b4d-4e5f-8a7b-9c0d1e2f3a4b"),
+            "DELETE /items/:id"
+        );
+        assert_eq!(route_key("GET", "/"), "GET /");
+        assert_eq!(route_key("GET", "/api/v2/health"), "GET /api/v2/health");
+        assert_eq!(route_key("GET", "/blob/deadbeefcafe"), "GET /blob/deadbeefcafe", "short hex is a name");
+    }
+
+    #[test]
+    fn record_sums_and_caps_routes() {
+        let usage = RouteUsage::default();
+        usage.record("default/api", "GET /a", Duration::from_millis(20), 1024);
+        usage.record("default/api", "GET /a", Duration::from_millis(10), 4096);
+        for n in 0..MAX_ROUTES_PER_DEPLOYMENT + 5 {
+            usage.record("default/api", &format!("GET /r{n}"), Duration::ZERO, 0);
+        }
+
+        let snapshot = usage.snapshot();
+        assert_eq!(snapshot.len(), MAX_ROUTES_PER_DEPLOYMENT + 1, "capped routes plus the overflow route");
+        let a = snapshot.iter().find(|s| s.route == "GET /a").unwrap();
+        assert_eq!(a.requests, 2);
+        assert_eq!(a.cpu, Duration::from_millis(30));
+        assert_eq!((a.memory_growth_bytes, a.max_memory_growth_bytes), (5120, 4096));
+        let other = snapshot.iter().find(|s| s.route == OVERFLOW_ROUTE).unwrap();
+        assert_eq!(other.requests, 6);
+
+        usage.forget("default/api");
+        assert!(usage.snapshot().is_empty());
+    }
+}
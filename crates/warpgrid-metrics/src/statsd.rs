@@ -0,0 +1,361 @@
+//! Emitting metrics snapshots to a statsd or DogStatsD agent.
+//!
+//! Teams running a Datadog agent on every node can skip the Prometheus
+//! hop: [`StatsdSink`] subscribes to the collector's snapshots and sends
+//! each gauge as it is taken. Configured from a TOML file, e.g.:
+//!
+//! ```toml
+//! address = "unix:///var/run/datadog/dsd.socket"   # or "127.0.0.1:8125" (UDP)
+//! flavor = "dogstatsd"             # or "statsd"
+//! prefix = "warpgrid"
+//! tags = ["env:prod", "team:platform"]
+//! max_packet_bytes = 1432          # default: 1432 for UDP, 8192 for UDS
+//! ```
+//!
+//! With DogStatsD, each gauge is tagged `deployment:<id>` plus `tags`
+//! (`warpgrid.requests_per_second:4.5|g|#deployment:api,env:prod`).
+//! Plain statsd has no tags, so the deployment goes into the name
+//! (`warpgrid.api.requests_per_second:4.5|g`) and `tags` is ignored.
+//! Lines are packed into datagrams up to `max_packet_bytes`. Sends are
+//! fire-and-forget, as statsd is: a missing agent costs a warning, not a
+//! retry.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::net::{UdpSocket, UnixDatagram};
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, info, warn};
+use warpgrid_state::MetricsSnapshot;
+
+/// Largest payload that fits an Ethernet MTU without fragmentation.
+const UDP_MAX_PACKET_BYTES: usize = 1432;
+
+/// The Datadog agent's default buffer for Unix datagrams.
+const UDS_MAX_PACKET_BYTES: usize = 8192;
+
+fn default_prefix() -> String {
+    "warpgrid".to_string()
+}
+
+/// Errors configuring or reaching the statsd agent.
+#[derive(Debug, thiserror::Error)]
+pub enum StatsdError {
+    #[error("invalid statsd config: {0}")]
+    Config(String),
+    #[error("statsd I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Line format understood by the agent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flavor {
+    /// DogStatsD: `name:value|g|#tag,tag`.
+    #[default]
+    Dogstatsd,
+    /// Plain statsd: `name:value|g`, deployment in the name.
+    Statsd,
+}
+
+/// Where and how to emit gauges.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsdConfig {
+    /// `host:port` (UDP) or `unix:///path/to/socket` (Unix datagram).
+    pub address: String,
+    #[serde(default)]
+    pub flavor: Flavor,
+    /// Prepended to every metric name, dot-separated.
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    /// `key:value` tags added to every gauge (DogStatsD only).
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Datagram size limit; defaults by transport.
+    #[serde(default)]
+    pub max_packet_bytes: Option<usize>,
+}
+
+impl StatsdConfig {
+    /// Read a config file.
+    pub fn load(path: &Path) -> Result<Self, StatsdError> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|e| StatsdError::Config(format!("{}: {e}", path.display())))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Udp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl Target {
+    fn parse(address: &str) -> Result<Self, StatsdError> {
+        if let Some(path) = address.strip_prefix("unix://").or_else(|| address.strip_prefix("unix:")) {
+            if path.is_empty() {
+                return Err(StatsdError::Config(format!("{address}: missing socket path")));
+            }
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        let addr = address
+            .to_socket_addrs()
+            .map_err(|e| StatsdError::Config(format!("{address}: {e}")))?
+            .next()
+            .ok_or_else(|| StatsdError::Config(format!("{address}: no address found")))?;
+        Ok(Self::Udp(addr))
+    }
+}
+
+enum Socket {
+    Udp(UdpSocket),
+    Unix(UnixDatagram, PathBuf),
+}
+
+impl Socket {
+    async fn open(target: &Target) -> Result<Self, StatsdError> {
+        Ok(match target {
+            Target::Udp(addr) => {
+                let bind: SocketAddr = if addr.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0u16; 8], 0).into()
+                };
+                let socket = UdpSocket::bind(bind).await?;
+                socket.connect(addr).await?;
+                Self::Udp(socket)
+            }
+            Target::Unix(path) => Self::Unix(UnixDatagram::unbound()?, path.clone()),
+        })
+    }
+
+    async fn send(&self, packet: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Udp(socket) => socket.send(packet).await,
+            Self::Unix(socket, path) => socket.send_to(packet, path).await,
+        }
+    }
+}
+
+/// Sends snapshot gauges to a statsd or DogStatsD agent.
+pub struct StatsdSink {
+    config: StatsdConfig,
+    target: Target,
+    max_packet_bytes: usize,
+}
+
+impl StatsdSink {
+    /// Validate `config` and resolve its address.
+    pub fn new(config: StatsdConfig) -> Result<Self, StatsdError> {
+        let target = Target::parse(&config.address)?;
+        if let Some(tag) = config.tags.iter().find(|tag| tag.is_empty() || tag.contains([',', '|', '\n'])) {
+            return Err(StatsdError::Config(format!("invalid tag {tag:?}")));
+        }
+        let max_packet_bytes = config.max_packet_bytes.unwrap_or(match target {
+            Target::Udp(_) => UDP_MAX_PACKET_BYTES,
+            Target::Unix(_) => UDS_MAX_PACKET_BYTES,
+        });
+        Ok(Self {
+            config,
+            target,
+            max_packet_bytes,
+        })
+    }
+
+    /// One line per gauge of `snapshots`.
+    fn lines(&self, snapshots: &[MetricsSnapshot]) -> Vec<String> {
+        let prefix = if self.config.prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}.", self.config.prefix)
+        };
+        let mut lines = Vec::new();
+        for snapshot in snapshots {
+            for (metric, value) in crate::prometheus::gauges(snapshot) {
+                let metric = metric.strip_prefix("warpgrid_").unwrap_or(metric);
+                lines.push(match self.config.flavor {
+                    Flavor::Dogstatsd => {
+                        let mut tags = vec![format!("deployment:{}", sanitize(&snapshot.deployment_id))];
+                        tags.extend(self.config.tags.iter().cloned());
+                        format!("{prefix}{metric}:{value}|g|#{}", tags.join(","))
+                    }
+                    Flavor::Statsd => format!(
+                        "{prefix}{}.{metric}:{value}|g",
+                        sanitize(&snapshot.deployment_id).replace(':', "_")
+                    ),
+                });
+            }
+        }
+        lines
+    }
+
+    /// Newline-joined `lines`, packed into datagrams of at most
+    /// `max_packet_bytes` (a longer line goes alone).
+    fn packets(&self, lines: &[String]) -> Vec<String> {
+        let mut packets = Vec::new();
+        let mut packet = String::new();
+        for line in lines {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > self.max_packet_bytes {
+                packets.push(std::mem::take(&mut packet));
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(line);
+        }
+        if !packet.is_empty() {
+            packets.push(packet);
+        }
+        packets
+    }
+
+    /// Send the gauges of `snapshots` as they are taken until `shutdown`
+    /// fires.
+    pub async fn run(
+        self,
+        mut snapshots: broadcast::Receiver<Arc<Vec<MetricsSnapshot>>>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        let socket = match Socket::open(&self.target).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!(address = %self.config.address, error = %e, "statsd sink disabled");
+                return;
+            }
+        };
+        info!(address = %self.config.address, flavor = ?self.config.flavor, "statsd sink started");
+
+        loop {
+            let batch = tokio::select! {
+                received = snapshots.recv() => match received {
+                    Ok(batch) => batch,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(missed, "statsd sink fell behind; snapshots skipped");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = shutdown.changed() => break,
+            };
+            let packets = self.packets(&self.lines(&batch));
+            let mut failed = 0;
+            for packet in &packets {
+                if let Err(e) = socket.send(packet.as_bytes()).await {
+                    failed += 1;
+                    debug!(error = %e, "statsd send failed");
+                }
+            }
+            if failed > 0 {
+                warn!(address = %self.config.address, failed, packets = packets.len(), "statsd packets not delivered");
+            }
+        }
+        info!("statsd sink stopped");
+    }
+}
+
+/// Replace characters the line protocol reserves.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if matches!(c, ',' | '|' | '#' | '\n' | ' ') { '_' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(deployment: &str) -> MetricsSnapshot {
+        MetricsSnapshot {
+            deployment_id: deployment.to_string(),
+            epoch: 10,
+            rps: 4.5,
+            latency_p50_ms: 1.0,
+            latency_p99_ms: 9.0,
+            error_rate: 0.0,
+            total_memory_bytes: 64,
+            active_instances: 2,
+        }
+    }
+
+    fn sink(toml: &str) -> StatsdSink {
+        StatsdSink::new(toml::from_str(toml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_line_formats() {
+        let dog = sink("address = \"127.0.0.1:8125\"\ntags = [\"env:prod\"]\n");
+        let lines = dog.lines(&[snapshot("api")]);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "warpgrid.requests_per_second:4.5|g|#deployment:api,env:prod");
+        assert_eq!(lines[5], "warpgrid.active_instances:2|g|#deployment:api,env:prod");
+
+        let plain = sink("address = \"127.0.0.1:8125\"\nflavor = \"statsd\"\nprefix = \"\"\ntags = [\"env:prod\"]\n");
+        assert_eq!(plain.lines(&[snapshot("a:b")])[4], "a_b.memory_bytes:64|g");
+    }
+
+    #[test]
+    fn test_packets_respect_the_size_limit() {
+        let sink = sink("address = \"127.0.0.1:8125\"\nmax_packet_bytes = 20\n");
+        let lines: Vec<String> = ["aaaaaaaa", "bbbbbbbb", "cccccccc", "dddddddddddddddddddddddd"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            sink.packets(&lines),
+            ["aaaaaaaa\nbbbbbbbb", "cccccccc", "dddddddddddddddddddddddd"]
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = |toml: &str| StatsdSink::new(toml::from_str(toml).unwrap());
+        assert!(matches!(config("address = \"unix://\""), Err(StatsdError::Config(_))));
+        assert!(matches!(config("address = \"no-port\""), Err(StatsdError::Config(_))));
+        assert!(matches!(
+            config("address = \"127.0.0.1:8125\"\ntags = [\"a,b\"]"),
+            Err(StatsdError::Config(_))
+        ));
+        let uds = config("address = \"unix:///run/dsd.socket\"").unwrap();
+        assert_eq!(uds.target, Target::Unix(PathBuf::from("/run/dsd.socket")));
+        assert_eq!(uds.max_packet_bytes, UDS_MAX_PACKET_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_are_sent_over_udp_and_uds() {
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("dsd.socket");
+        let uds = UnixDatagram::bind(&socket_path).unwrap();
+
+        for address in [udp.local_addr().unwrap().to_string(), format!("unix://{}", socket_path.display())] {
+            let sink = sink(&format!("address = \"{address}\"\n"));
+            let (tx, rx) = broadcast::channel(4);
+            let (shutdown_tx, shutdown_rx) = watch::channel(false);
+            let task = tokio::spawn(sink.run(rx, shutdown_rx));
+
+            // Resend until the sink has subscribed and opened its socket.
+            let mut buf = [0u8; 8192];
+            let received = loop {
+                tx.send(Arc::new(vec![snapshot("api")])).unwrap();
+                let recv = async {
+                    if address.starts_with("unix://") {
+                        uds.recv(&mut buf).await.unwrap()
+                    } else {
+                        udp.recv(&mut buf).await.unwrap()
+                    }
+                };
+                if let Ok(n) = tokio::time::timeout(std::time::Duration::from_millis(200), recv).await {
+                    break String::from_utf8_lossy(&buf[..n]).to_string();
+                }
+            };
+            assert_eq!(received.lines().count(), 6, "{received}");
+            assert!(received.starts_with("warpgrid.requests_per_second:4.5|g|#deployment:api"));
+
+            shutdown_tx.send(true).unwrap();
+            task.await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Process-wide counters for streamed HTTP response bodies.
+//!
+//! The HTTP trigger streams guest response bodies to clients through a
+//! bounded buffer. It records here how often a slow client made the guest
+//! wait, and how often a client stalled long enough for the stream to be
+//! abandoned. `/metrics` renders them with [`render_response_streaming`].
+//!
+//! [`render_response_streaming`]: crate::render_response_streaming
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Streaming counters; see [`streaming`].
+#[derive(Debug, Default)]
+pub struct StreamingCounters {
+    streams: AtomicU64,
+    active: AtomicU64,
+    bytes: AtomicU64,
+    backpressure_waits: AtomicU64,
+    stall_timeouts: AtomicU64,
+}
+
+/// Point-in-time copy of [`StreamingCounters`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamingSnapshot {
+    /// Response bodies streamed since start.
+    pub streams: u64,
+    /// Response bodies streaming right now.
+    pub active: u64,
+    /// Body bytes handed to clients.
+    pub bytes: u64,
+    /// Times the buffer was full and the guest had to wait for the client.
+    pub backpressure_waits: u64,
+    /// Streams abandoned because the client stopped reading.
+    pub stall_timeouts: u64,
+}
+
+static COUNTERS: StreamingCounters = StreamingCounters {
+    streams: AtomicU64::new(0),
+    active: AtomicU64::new(0),
+    bytes: AtomicU64::new(0),
+    backpressure_waits: AtomicU64::new(0),
+    stall_timeouts: AtomicU64::new(0),
+};
+
+/// The counters shared by every trigger in this process.
+pub fn streaming() -> &'static StreamingCounters {
+    &COUNTERS
+}
+
+impl StreamingCounters {
+    pub fn stream_started(&self) {
+        self.streams.fetch_add(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stream_finished(&self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn backpressure_wait(&self) {
+        self.backpressure_waits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stall_timeout(&self) {
+        self.stall_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StreamingSnapshot {
+        StreamingSnapshot {
+            streams: self.streams.load(Ordering::Relaxed),
+            active: self.active.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            backpressure_waits: self.backpressure_waits.load(Ordering::Relaxed),
+            stall_timeouts: self.stall_timeouts.load(Ordering::Relaxed),
+        }
+    }
+}
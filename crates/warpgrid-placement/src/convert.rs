@@ -9,9 +9,6 @@ use warpgrid_state::{DeploymentSpec, NodeInfo};
 
 use crate::scorer::{NodeResources, PlacementRequirements};
 
-/// Default priority for deployments that don't specify one.
-const DEFAULT_PRIORITY: u32 = 10;
-
 /// Convert a [`NodeInfo`] to [`NodeResources`] for placement.
 ///
 /// `is_draining` is passed externally because drain state is managed
@@ -21,7 +18,7 @@ pub fn node_info_to_resources(node: &NodeInfo, is_draining: bool) -> NodeResourc
         node_id: node.id.clone(),
         labels: node.labels.clone(),
         capacity_memory_bytes: node.capacity_memory_bytes,
-        capacity_cpu_weight: node.capacity_cpu_weight,
+        capacity_cpu_weight: node.effective_cpu_weight(),
         used_memory_bytes: node.used_memory_bytes,
         used_cpu_weight: node.used_cpu_weight,
         active_instances: 0,
@@ -39,7 +36,7 @@ pub fn node_info_to_resources_with_instances(
         node_id: node.id.clone(),
         labels: node.labels.clone(),
         capacity_memory_bytes: node.capacity_memory_bytes,
-        capacity_cpu_weight: node.capacity_cpu_weight,
+        capacity_cpu_weight: node.effective_cpu_weight(),
         used_memory_bytes: node.used_memory_bytes,
         used_cpu_weight: node.used_cpu_weight,
         active_instances,
@@ -58,7 +55,7 @@ pub fn deployment_to_requirements(
         instance_count,
         required_labels: HashMap::new(),
         preferred_labels: HashMap::new(),
-        priority: DEFAULT_PRIORITY,
+        priority: spec.effective_priority(),
     }
 }
 
@@ -83,6 +80,10 @@ mod tests {
                 m
             },
             last_heartbeat: 1700000000,
+            pressure_until: None,
+            replica_revision: None,
+            calibrated_cpu_weight: None,
+            cordoned: false,
         }
     }
 
@@ -92,11 +93,12 @@ mod tests {
             namespace: "prod".to_string(),
             name: "api".to_string(),
             source: "oci://registry/api:v1".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 3, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 128 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -104,6 +106,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -121,6 +129,14 @@ mod tests {
         assert!(!res.is_draining);
     }
 
+    #[test]
+    fn calibrated_weight_replaces_configured_capacity() {
+        let mut node = sample_node();
+        node.calibrated_cpu_weight = Some(1600);
+        assert_eq!(node_info_to_resources(&node, false).capacity_cpu_weight, 1600);
+        assert_eq!(node_info_to_resources_with_instances(&node, 2, false).capacity_cpu_weight, 1600);
+    }
+
     #[test]
     fn preserves_labels() {
         let node = sample_node();
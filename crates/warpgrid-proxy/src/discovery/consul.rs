@@ -0,0 +1,135 @@
+//! Consul catalog, through the local agent's HTTP API.
+//!
+//! Services are registered with the agent (`/v1/agent/service/register`)
+//! tagged `warpgrid` and with `warpgrid-*` meta, which is how the bridge
+//! tells its own registrations apart from everything else on the agent.
+//! Imports read the catalog and keep only passing instances.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::http::HttpClient;
+use super::{DiscoveryError, ExternalService, Registration, MANAGED_TAG};
+
+/// Connection to a Consul agent.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConsulConfig {
+    /// Agent address, e.g. `http://127.0.0.1:8500`.
+    #[serde(default = "default_address")]
+    pub address: String,
+    /// ACL token, sent as `X-Consul-Token`.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+fn default_address() -> String {
+    "http://127.0.0.1:8500".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AgentService {
+    #[serde(rename = "ID")]
+    id: String,
+    /// `Service` when read back from the agent, `Name` when registering.
+    #[serde(rename(serialize = "Name", deserialize = "Service"))]
+    name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    address: String,
+    #[serde(default)]
+    port: u16,
+    #[serde(default)]
+    meta: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HealthEntry {
+    node: HealthNode,
+    service: AgentService,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HealthNode {
+    #[serde(default)]
+    address: String,
+}
+
+pub(crate) struct Consul {
+    client: HttpClient,
+}
+
+impl Consul {
+    pub(crate) fn new(config: &ConsulConfig, client: HttpClient) -> Self {
+        let client = match &config.token {
+            Some(token) => client.with_header("x-consul-token", token),
+            None => client,
+        };
+        Self { client }
+    }
+
+    /// Ids of the services the bridge registered.
+    pub(crate) async fn registered(&self) -> Result<Vec<String>, DiscoveryError> {
+        let services: HashMap<String, AgentService> =
+            self.client.get("/v1/agent/services").await?;
+        Ok(services
+            .into_values()
+            .filter(|service| service.meta.contains_key("warpgrid-namespace"))
+            .map(|service| service.id)
+            .collect())
+    }
+
+    pub(crate) async fn register(&self, registration: &Registration) -> Result<(), DiscoveryError> {
+        let service = AgentService {
+            id: registration.id(),
+            name: registration.name.clone(),
+            tags: vec![MANAGED_TAG.to_string(), format!("namespace={}", registration.namespace)],
+            address: registration.address.to_string(),
+            port: registration.port,
+            meta: HashMap::from([(
+                "warpgrid-namespace".to_string(),
+                registration.namespace.clone(),
+            )]),
+        };
+        self.client.put("/v1/agent/service/register", &service).await
+    }
+
+    pub(crate) async fn deregister(&self, id: &str) -> Result<(), DiscoveryError> {
+        self.client
+            .put(&format!("/v1/agent/service/deregister/{id}"), &())
+            .await
+    }
+
+    /// Passing instances of every catalog service not registered by a
+    /// WarpGrid bridge.
+    pub(crate) async fn external(&self) -> Result<Vec<ExternalService>, DiscoveryError> {
+        let catalog: HashMap<String, Vec<String>> = self.client.get("/v1/catalog/services").await?;
+        let mut services = Vec::new();
+        for (name, tags) in catalog {
+            if tags.iter().any(|tag| tag == MANAGED_TAG) {
+                continue;
+            }
+            let entries: Vec<HealthEntry> = self
+                .client
+                .get(&format!("/v1/health/service/{name}?passing=true"))
+                .await?;
+            let addresses = entries
+                .into_iter()
+                .map(|entry| {
+                    let host = if entry.service.address.is_empty() {
+                        entry.node.address
+                    } else {
+                        entry.service.address
+                    };
+                    super::join_host_port(&host, entry.service.port)
+                })
+                .collect();
+            services.push(ExternalService { name, addresses });
+        }
+        Ok(services)
+    }
+}
@@ -0,0 +1,193 @@
+//! etcd catalog, through the v3 JSON gateway (`/v3/kv/*`).
+//!
+//! Each backend is one key, `<prefix>/<namespace>/<service>/<ip>:<port>`,
+//! whose value is the JSON [`Registration`]. Imports read every key under
+//! `import_prefix` as `<import_prefix>/<service>/<anything>`, with either
+//! a `host:port` string or an `{"address", "port"}` object as the value.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::http::HttpClient;
+use super::{DiscoveryError, ExternalService, Registration};
+
+/// Connection to an etcd cluster.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EtcdConfig {
+    /// Gateway address, e.g. `http://127.0.0.1:2379`.
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+    /// Key prefix WarpGrid services are written under.
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    /// Key prefix external services are imported from.
+    #[serde(default = "default_import_prefix")]
+    pub import_prefix: String,
+}
+
+fn default_endpoint() -> String {
+    "http://127.0.0.1:2379".to_string()
+}
+
+fn default_prefix() -> String {
+    "/warpgrid/services".to_string()
+}
+
+fn default_import_prefix() -> String {
+    "/services".to_string()
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RangeResponse {
+    #[serde(default)]
+    kvs: Vec<KeyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyValue {
+    key: String,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Endpoint {
+    HostPort(String),
+    Object { address: String, port: u16 },
+}
+
+pub(crate) struct Etcd {
+    client: HttpClient,
+    prefix: String,
+    import_prefix: String,
+}
+
+impl Etcd {
+    pub(crate) fn new(config: &EtcdConfig, client: HttpClient) -> Self {
+        Self {
+            client,
+            prefix: config.prefix.trim_end_matches('/').to_string(),
+            import_prefix: config.import_prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub(crate) fn key(&self, registration: &Registration) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.prefix,
+            registration.namespace,
+            registration.name,
+            super::join_host_port(&registration.address.to_string(), registration.port)
+        )
+    }
+
+    /// Keys and values under `prefix/`, decoded.
+    async fn range(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, DiscoveryError> {
+        let start = format!("{prefix}/");
+        // Every key with the prefix sorts before the prefix with its last
+        // byte incremented ('/' + 1 = '0').
+        let end = format!("{prefix}0");
+        let response: RangeResponse = self
+            .client
+            .post(
+                "/v3/kv/range",
+                &json!({ "key": STANDARD.encode(start), "range_end": STANDARD.encode(end) }),
+            )
+            .await?;
+        response
+            .kvs
+            .into_iter()
+            .map(|kv| {
+                let key = String::from_utf8_lossy(&decode(&kv.key)?).into_owned();
+                Ok((key, decode(&kv.value)?))
+            })
+            .collect()
+    }
+
+    /// Keys of the services the bridge registered.
+    pub(crate) async fn registered(&self) -> Result<Vec<String>, DiscoveryError> {
+        Ok(self
+            .range(&self.prefix)
+            .await?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    pub(crate) async fn register(&self, registration: &Registration) -> Result<(), DiscoveryError> {
+        let value = serde_json::to_vec(registration).map_err(|e| DiscoveryError::Http(e.to_string()))?;
+        let _: serde_json::Value = self
+            .client
+            .post(
+                "/v3/kv/put",
+                &json!({ "key": STANDARD.encode(self.key(registration)), "value": STANDARD.encode(value) }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn deregister(&self, key: &str) -> Result<(), DiscoveryError> {
+        let _: serde_json::Value = self
+            .client
+            .post("/v3/kv/deleterange", &json!({ "key": STANDARD.encode(key) }))
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn external(&self) -> Result<Vec<ExternalService>, DiscoveryError> {
+        let mut services: Vec<ExternalService> = Vec::new();
+        for (key, value) in self.range(&self.import_prefix).await? {
+            let Some(name) = key
+                .strip_prefix(&self.import_prefix)
+                .and_then(|rest| rest.trim_start_matches('/').split('/').next())
+                .filter(|name| !name.is_empty())
+            else {
+                continue;
+            };
+            let address = match serde_json::from_slice::<Endpoint>(&value) {
+                Ok(Endpoint::HostPort(address)) => address,
+                Ok(Endpoint::Object { address, port }) => super::join_host_port(&address, port),
+                Err(_) => String::from_utf8_lossy(&value).trim().to_string(),
+            };
+            match services.iter_mut().find(|service| service.name == name) {
+                Some(service) => service.addresses.push(address),
+                None => services.push(ExternalService {
+                    name: name.to_string(),
+                    addresses: vec![address],
+                }),
+            }
+        }
+        Ok(services)
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, DiscoveryError> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| DiscoveryError::Http(format!("invalid base64 from etcd: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn etcd() -> Etcd {
+        let config: EtcdConfig = toml::from_str("").unwrap();
+        let client = HttpClient::new("http://127.0.0.1:2379", std::time::Duration::from_secs(1)).unwrap();
+        Etcd::new(&config, client)
+    }
+
+    #[test]
+    fn keys_are_namespaced_under_the_prefix() {
+        let registration = Registration {
+            namespace: "prod".into(),
+            name: "api".into(),
+            address: "fd00::1".parse().unwrap(),
+            port: 8080,
+        };
+        assert_eq!(etcd().key(&registration), "/warpgrid/services/prod/api/[fd00::1]:8080");
+    }
+}
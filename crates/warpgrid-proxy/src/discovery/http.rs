@@ -0,0 +1,120 @@
+//! Minimal HTTP/1.1 JSON client for the catalog APIs.
+//!
+//! One connection per request, plain `http://` only — catalogs are
+//! reached over the node-local agent (Consul) or the cluster network
+//! (etcd's JSON gateway).
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::DiscoveryError;
+
+/// Client for one `http://host:port` endpoint.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
+    authority: String,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+}
+
+impl HttpClient {
+    /// Parse `http://host:port` (a trailing `/` is allowed).
+    pub(crate) fn new(url: &str, timeout: Duration) -> Result<Self, DiscoveryError> {
+        let authority = url
+            .strip_prefix("http://")
+            .ok_or_else(|| DiscoveryError::Config(format!("{url}: only http:// is supported")))?
+            .trim_end_matches('/');
+        if authority.is_empty() || authority.contains('/') {
+            return Err(DiscoveryError::Config(format!("{url}: expected http://host:port")));
+        }
+        Ok(Self {
+            authority: authority.to_string(),
+            headers: Vec::new(),
+            timeout,
+        })
+    }
+
+    /// Send `name: value` with every request.
+    pub(crate) fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, DiscoveryError> {
+        let body = self.send("GET", path, Vec::new()).await?;
+        parse(path, &body)
+    }
+
+    pub(crate) async fn put<B: Serialize>(&self, path: &str, body: &B) -> Result<(), DiscoveryError> {
+        self.send("PUT", path, encode(body)?).await.map(drop)
+    }
+
+    pub(crate) async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, DiscoveryError> {
+        let response = self.send("POST", path, encode(body)?).await?;
+        parse(path, &response)
+    }
+
+    async fn send(&self, method: &str, path: &str, body: Vec<u8>) -> Result<Bytes, DiscoveryError> {
+        let request = async {
+            let stream = tokio::net::TcpStream::connect(&self.authority).await?;
+            let io = hyper_util::rt::TokioIo::new(stream);
+            let (mut sender, conn) = hyper::client::conn::http1::handshake(io)
+                .await
+                .map_err(|e| DiscoveryError::Http(e.to_string()))?;
+            tokio::spawn(async move {
+                let _ = conn.await;
+            });
+
+            let mut builder = http::Request::builder()
+                .method(method)
+                .uri(path)
+                .header("host", &self.authority)
+                .header("user-agent", "warpgrid-proxy/0.1")
+                .header("content-type", "application/json");
+            for (name, value) in &self.headers {
+                builder = builder.header(name, value);
+            }
+            let request = builder
+                .body(Full::new(Bytes::from(body)))
+                .map_err(|e| DiscoveryError::Http(e.to_string()))?;
+
+            let response = sender
+                .send_request(request)
+                .await
+                .map_err(|e| DiscoveryError::Http(e.to_string()))?;
+            let status = response.status();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| DiscoveryError::Http(e.to_string()))?
+                .to_bytes();
+            if !status.is_success() {
+                return Err(DiscoveryError::Http(format!(
+                    "{method} {path}: {status}: {}",
+                    String::from_utf8_lossy(&body).trim()
+                )));
+            }
+            Ok(body)
+        };
+        tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| DiscoveryError::Http(format!("{method} {path}: timed out")))?
+    }
+}
+
+fn encode<B: Serialize>(body: &B) -> Result<Vec<u8>, DiscoveryError> {
+    serde_json::to_vec(body).map_err(|e| DiscoveryError::Http(e.to_string()))
+}
+
+fn parse<T: DeserializeOwned>(path: &str, body: &[u8]) -> Result<T, DiscoveryError> {
+    serde_json::from_slice(body).map_err(|e| DiscoveryError::Http(format!("{path}: {e}")))
+}
@@ -0,0 +1,530 @@
+//! Bridging service discovery with an external Consul or etcd catalog.
+//!
+//! During a migration some services live in WarpGrid and some do not.
+//! [`DiscoveryBridge`] registers every WarpGrid service backend in the
+//! external catalog and, optionally, imports the catalog's services back
+//! into the mesh DNS under a dedicated namespace, so both sides resolve
+//! each other. Configured from a TOML file, e.g.:
+//!
+//! ```toml
+//! interval_secs = 30
+//!
+//! [consul]                        # or [etcd]
+//! address = "http://127.0.0.1:8500"
+//! token = "..."
+//!
+//! # Import the catalog's services as `<service>.external.svc.<suffix>`.
+//! [import]
+//! namespace = "external"
+//! services = ["billing", "ledger"]  # default: all of them
+//! ```
+//!
+//! Registration is reconciled, not incremental: every pass registers what
+//! is missing and removes what the bridge registered earlier but no longer
+//! exists, so a restart or a lost update heals on the next pass. Like the
+//! zone export ([`crate::dns::zone`]), only backends with an IP address
+//! are registered.
+
+mod consul;
+mod etcd;
+mod http;
+
+pub use consul::ConsulConfig;
+pub use etcd::EtcdConfig;
+
+use std::collections::{BTreeSet, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::dns::DnsResolver;
+use consul::Consul;
+use etcd::Etcd;
+use http::HttpClient;
+
+/// Consul tag marking services registered by a bridge, so they are never
+/// imported back.
+const MANAGED_TAG: &str = "warpgrid";
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+fn default_import_namespace() -> String {
+    "external".to_string()
+}
+
+fn default_import_ttl() -> u32 {
+    30
+}
+
+/// Errors configuring or talking to the external catalog.
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    #[error("invalid discovery config: {0}")]
+    Config(String),
+    #[error("discovery I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("catalog request failed: {0}")]
+    Http(String),
+}
+
+/// Which catalog to bridge with, and what to import from it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryConfig {
+    #[serde(default)]
+    pub consul: Option<ConsulConfig>,
+    #[serde(default)]
+    pub etcd: Option<EtcdConfig>,
+    /// Import external services into the mesh DNS.
+    #[serde(default)]
+    pub import: Option<ImportConfig>,
+    /// Seconds between reconcile / import passes.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Per-request timeout, in seconds.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl DiscoveryConfig {
+    /// Read a config file.
+    pub fn load(path: &Path) -> Result<Self, DiscoveryError> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| DiscoveryError::Config(format!("{}: {e}", path.display())))
+    }
+}
+
+/// Importing external services.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportConfig {
+    /// Namespace imported services are resolvable under. WarpGrid
+    /// services in this namespace are not registered externally.
+    #[serde(default = "default_import_namespace")]
+    pub namespace: String,
+    /// Only import these services (default: all).
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// TTL of the imported DNS records, in seconds.
+    #[serde(default = "default_import_ttl")]
+    pub ttl: u32,
+}
+
+/// One WarpGrid service backend as registered externally.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Registration {
+    pub namespace: String,
+    pub name: String,
+    pub address: IpAddr,
+    pub port: u16,
+}
+
+impl Registration {
+    /// Stable catalog id, e.g. `warpgrid-prod-api-10.0.0.1-8080`.
+    pub fn id(&self) -> String {
+        format!("warpgrid-{}-{}-{}-{}", self.namespace, self.name, self.address, self.port)
+    }
+}
+
+/// A service found in the external catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalService {
+    pub name: String,
+    /// `host:port` of each healthy instance.
+    pub addresses: Vec<String>,
+}
+
+enum Catalog {
+    Consul(Consul),
+    Etcd(Etcd),
+}
+
+impl Catalog {
+    /// Catalog keys of what the bridge registered earlier.
+    async fn registered(&self) -> Result<Vec<String>, DiscoveryError> {
+        match self {
+            Catalog::Consul(consul) => consul.registered().await,
+            Catalog::Etcd(etcd) => etcd.registered().await,
+        }
+    }
+
+    /// Catalog key `registration` is stored under.
+    fn key(&self, registration: &Registration) -> String {
+        match self {
+            Catalog::Consul(_) => registration.id(),
+            Catalog::Etcd(etcd) => etcd.key(registration),
+        }
+    }
+
+    async fn register(&self, registration: &Registration) -> Result<(), DiscoveryError> {
+        match self {
+            Catalog::Consul(consul) => consul.register(registration).await,
+            Catalog::Etcd(etcd) => etcd.register(registration).await,
+        }
+    }
+
+    async fn deregister(&self, key: &str) -> Result<(), DiscoveryError> {
+        match self {
+            Catalog::Consul(consul) => consul.deregister(key).await,
+            Catalog::Etcd(etcd) => etcd.deregister(key).await,
+        }
+    }
+
+    async fn external(&self) -> Result<Vec<ExternalService>, DiscoveryError> {
+        match self {
+            Catalog::Consul(consul) => consul.external().await,
+            Catalog::Etcd(etcd) => etcd.external().await,
+        }
+    }
+}
+
+/// What a reconcile pass changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReconcileStats {
+    pub registered: usize,
+    pub deregistered: usize,
+}
+
+/// Keeps an external catalog in step with a [`DnsResolver`].
+pub struct DiscoveryBridge {
+    config: DiscoveryConfig,
+    catalog: Catalog,
+    /// Names imported on the previous pass, to remove ones that vanish.
+    imported: HashSet<String>,
+}
+
+impl DiscoveryBridge {
+    /// Validate `config`.
+    pub fn new(config: DiscoveryConfig) -> Result<Self, DiscoveryError> {
+        let timeout = Duration::from_secs(config.timeout_secs.max(1));
+        let catalog = match (&config.consul, &config.etcd) {
+            (Some(consul), None) => {
+                Catalog::Consul(Consul::new(consul, HttpClient::new(&consul.address, timeout)?))
+            }
+            (None, Some(etcd)) => {
+                Catalog::Etcd(Etcd::new(etcd, HttpClient::new(&etcd.endpoint, timeout)?))
+            }
+            _ => {
+                return Err(DiscoveryError::Config(
+                    "configure exactly one of [consul] or [etcd]".to_string(),
+                ));
+            }
+        };
+        if config
+            .import
+            .as_ref()
+            .is_some_and(|import| import.namespace.is_empty())
+        {
+            return Err(DiscoveryError::Config("import namespace must not be empty".to_string()));
+        }
+        Ok(Self {
+            config,
+            catalog,
+            imported: HashSet::new(),
+        })
+    }
+
+    /// The WarpGrid backends to register, from the resolver's records.
+    pub fn registrations(&self, resolver: &DnsResolver) -> Vec<Registration> {
+        let internal = format!(".svc.{}", resolver.domain_suffix());
+        let import_namespace = self.config.import.as_ref().map(|import| import.namespace.as_str());
+        let registrations: BTreeSet<Registration> = resolver
+            .list_records()
+            .iter()
+            .filter_map(|record| {
+                let (name, namespace) = record.fqdn.strip_suffix(&internal)?.split_once('.')?;
+                (Some(namespace) != import_namespace).then_some((name, namespace, record))
+            })
+            .flat_map(|(name, namespace, record)| {
+                record.addresses.iter().filter_map(move |address| {
+                    let addr = address.parse::<SocketAddr>().ok()?;
+                    Some(Registration {
+                        namespace: namespace.to_string(),
+                        name: name.to_string(),
+                        address: addr.ip(),
+                        port: addr.port(),
+                    })
+                })
+            })
+            .collect();
+        registrations.into_iter().collect()
+    }
+
+    /// Register missing backends and remove stale ones.
+    pub async fn reconcile(&self, resolver: &DnsResolver) -> Result<ReconcileStats, DiscoveryError> {
+        let desired = self.registrations(resolver);
+        let desired_keys: HashSet<String> =
+            desired.iter().map(|registration| self.catalog.key(registration)).collect();
+        let existing: HashSet<String> = self.catalog.registered().await?.into_iter().collect();
+
+        let mut stats = ReconcileStats::default();
+        for registration in &desired {
+            if !existing.contains(&self.catalog.key(registration)) {
+                self.catalog.register(registration).await?;
+                stats.registered += 1;
+            }
+        }
+        for key in existing.difference(&desired_keys) {
+            self.catalog.deregister(key).await?;
+            stats.deregistered += 1;
+        }
+        if stats != ReconcileStats::default() {
+            info!(
+                registered = stats.registered,
+                deregistered = stats.deregistered,
+                "reconciled external service catalog"
+            );
+        }
+        Ok(stats)
+    }
+
+    /// Import the catalog's services into the resolver. Returns how many
+    /// services are imported.
+    pub async fn import(&mut self, resolver: &DnsResolver) -> Result<usize, DiscoveryError> {
+        let Some(import) = &self.config.import else {
+            return Ok(0);
+        };
+        let services: Vec<ExternalService> = self
+            .catalog
+            .external()
+            .await?
+            .into_iter()
+            .filter(|service| import.services.is_empty() || import.services.contains(&service.name))
+            .collect();
+
+        let mut seen = HashSet::new();
+        for service in services {
+            resolver.upsert(&service.name, &import.namespace, service.addresses, import.ttl);
+            seen.insert(service.name);
+        }
+        for gone in self.imported.difference(&seen) {
+            resolver.remove(gone, &import.namespace);
+        }
+        debug!(services = seen.len(), "imported external services");
+        self.imported = seen;
+        Ok(self.imported.len())
+    }
+
+    /// Reconcile on every record change and every `interval_secs`, and
+    /// import on every interval, until `shutdown` fires.
+    pub async fn run(mut self, resolver: DnsResolver, mut shutdown: watch::Receiver<bool>) {
+        let mut changes = resolver.subscribe();
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs.max(1)));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.import(&resolver).await {
+                        warn!(error = %e, "importing external services failed");
+                    }
+                }
+                changed = changes.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+                _ = shutdown.changed() => break,
+            }
+            changes.mark_unchanged();
+            if let Err(e) = self.reconcile(&resolver).await {
+                warn!(error = %e, "registering services in external catalog failed");
+            }
+        }
+    }
+}
+
+/// `host:port`, bracketing IPv6 hosts.
+fn join_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    type Requests = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    /// Serve one request per connection, answering from `routes` (method,
+    /// path, body) and everything else with `{}`.
+    async fn fake_catalog(routes: Vec<(&'static str, &'static str, String)>) -> (String, Requests) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Requests::default();
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let (head, body_len) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&buf[..end]).to_string();
+                        let len = head
+                            .lines()
+                            .find_map(|l| {
+                                let l = l.to_ascii_lowercase();
+                                l.strip_prefix("content-length: ").map(|v| v.parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        buf.drain(..end + 4);
+                        break (head, len);
+                    }
+                };
+                while buf.len() < body_len {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let mut parts = head.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = parts.next().unwrap().to_string();
+                let response = routes
+                    .iter()
+                    .find(|(m, p, _)| method == *m && path == *p)
+                    .map_or("{}", |(_, _, body)| body.as_str());
+                seen.lock().unwrap().push((method, path, String::from_utf8_lossy(&buf).to_string()));
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{response}",
+                    response.len()
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn bridge(toml: &str) -> DiscoveryBridge {
+        DiscoveryBridge::new(toml::from_str(toml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn config_requires_one_catalog() {
+        let err = DiscoveryBridge::new(toml::from_str("").unwrap()).err().unwrap();
+        assert!(err.to_string().contains("exactly one"), "{err}");
+        let err = DiscoveryBridge::new(
+            toml::from_str("[consul]\naddress = \"https://consul:8501\"").unwrap(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("http://"), "{err}");
+    }
+
+    #[test]
+    fn registrations_skip_non_ip_and_imported_backends() {
+        let bridge = bridge("[consul]\n[import]\nnamespace = \"external\"");
+        let dns = DnsResolver::default();
+        dns.upsert("api", "prod", vec!["10.0.0.1:8080".into(), "node-1:0".into()], 60);
+        dns.upsert("billing", "external", vec!["10.9.0.1:80".into()], 60);
+        assert_eq!(
+            bridge.registrations(&dns),
+            vec![Registration {
+                namespace: "prod".into(),
+                name: "api".into(),
+                address: "10.0.0.1".parse().unwrap(),
+                port: 8080,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn consul_reconcile_registers_and_removes() {
+        let (url, requests) = fake_catalog(vec![(
+            "GET",
+            "/v1/agent/services",
+            r#"{
+                "warpgrid-prod-old-10.0.0.9-80": {"ID": "warpgrid-prod-old-10.0.0.9-80", "Service": "old", "Meta": {"warpgrid-namespace": "prod"}},
+                "billing-1": {"ID": "billing-1", "Service": "billing", "Meta": {}}
+            }"#
+            .to_string(),
+        )])
+        .await;
+        let bridge = bridge(&format!("[consul]\naddress = {url:?}\ntoken = \"secret\""));
+        let dns = DnsResolver::default();
+        dns.upsert("api", "prod", vec!["10.0.0.1:8080".into()], 60);
+
+        let stats = bridge.reconcile(&dns).await.unwrap();
+        assert_eq!(stats, ReconcileStats { registered: 1, deregistered: 1 });
+
+        let requests = requests.lock().unwrap();
+        let (_, _, body) = requests
+            .iter()
+            .find(|(method, path, _)| method == "PUT" && path == "/v1/agent/service/register")
+            .unwrap();
+        let service: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(service["ID"], "warpgrid-prod-api-10.0.0.1-8080");
+        assert_eq!(service["Name"], "api");
+        assert_eq!(service["Port"], 8080);
+        assert!(requests.iter().any(|(method, path, _)| method == "PUT"
+            && path == "/v1/agent/service/deregister/warpgrid-prod-old-10.0.0.9-80"));
+    }
+
+    #[tokio::test]
+    async fn consul_import_skips_warpgrid_services() {
+        let (url, _) = fake_catalog(vec![
+            (
+                "GET",
+                "/v1/catalog/services",
+                r#"{"billing": ["v2"], "api": ["warpgrid"]}"#.to_string(),
+            ),
+            (
+                "GET",
+                "/v1/health/service/billing?passing=true",
+                r#"[{"Node": {"Address": "10.9.0.5"}, "Service": {"ID": "b1", "Service": "billing", "Address": "", "Port": 9000}}]"#
+                    .to_string(),
+            ),
+        ])
+        .await;
+        let mut bridge = bridge(&format!("[consul]\naddress = {url:?}\n[import]"));
+        let dns = DnsResolver::default();
+        dns.upsert("legacy", "external", vec!["10.9.0.9:80".into()], 30);
+        bridge.imported.insert("legacy".into());
+
+        assert_eq!(bridge.import(&dns).await.unwrap(), 1);
+        let record = dns.resolve_service("billing", "external").unwrap();
+        assert_eq!(record.addresses, vec!["10.9.0.5:9000"]);
+        assert!(dns.resolve_service("api", "external").is_none());
+        assert!(dns.resolve_service("legacy", "external").is_none());
+    }
+
+    #[tokio::test]
+    async fn etcd_import_groups_keys_by_service() {
+        use base64::Engine;
+        let encode = |s: &str| base64::engine::general_purpose::STANDARD.encode(s);
+        let body = format!(
+            r#"{{"kvs": [
+                {{"key": "{}", "value": "{}"}},
+                {{"key": "{}", "value": "{}"}}
+            ]}}"#,
+            encode("/services/ledger/a"),
+            encode("10.8.0.1:7000"),
+            encode("/services/ledger/b"),
+            encode(r#"{"address": "10.8.0.2", "port": 7000}"#),
+        );
+        let (url, requests) = fake_catalog(vec![("POST", "/v3/kv/range", body)]).await;
+        let mut bridge = bridge(&format!("[etcd]\nendpoint = {url:?}\n[import]"));
+        let dns = DnsResolver::default();
+
+        assert_eq!(bridge.import(&dns).await.unwrap(), 1);
+        let record = dns.resolve_service("ledger", "external").unwrap();
+        assert_eq!(record.addresses, vec!["10.8.0.1:7000", "10.8.0.2:7000"]);
+
+        let requests = requests.lock().unwrap();
+        let range: serde_json::Value = serde_json::from_str(&requests[0].2).unwrap();
+        assert_eq!(range["key"], encode("/services/"));
+        assert_eq!(range["range_end"], encode("/services0"));
+    }
+}
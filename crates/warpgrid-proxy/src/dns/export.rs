@@ -0,0 +1,223 @@
+//! Publishing service records to corporate DNS.
+//!
+//! Configured from a TOML file, e.g.:
+//!
+//! ```toml
+//! origin = "warp.local"        # zone corporate resolvers delegate to us
+//! ttl = 60
+//! nameserver = "ns1.warp.local"
+//! hostmaster = "dns-admin@corp.example"
+//!
+//! # Write a zone file for the corporate primary to load (or serve).
+//! zone_file = "/var/lib/warpgrid/warp.local.zone"
+//!
+//! # And/or push RFC 2136 dynamic updates to the primary.
+//! [update]
+//! server = "10.0.0.53:53"
+//! tsig = { key_name = "warpgrid-update", secret = "c2VjcmV0..." }
+//! ```
+//!
+//! [`DnsExporter::run`] republishes whenever the resolver's records
+//! change, retrying failed updates every `retry_secs`. The first update
+//! after startup replaces every current name; names that vanished while
+//! the exporter was down are only removed from the zone file.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use super::DnsResolver;
+use super::update::{DynamicUpdater, UpdateConfig};
+use super::zone::{self, ZoneConfig, ZoneRecord};
+
+fn default_retry_secs() -> u64 {
+    30
+}
+
+/// Errors configuring or publishing a DNS export.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("invalid DNS export config: {0}")]
+    Config(String),
+    #[error("DNS export I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("DNS update failed: {0}")]
+    Update(String),
+}
+
+/// Where the service records are exported to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DnsExportConfig {
+    #[serde(flatten)]
+    pub zone: ZoneConfig,
+    /// Write the zone to this file.
+    #[serde(default)]
+    pub zone_file: Option<PathBuf>,
+    /// Send RFC 2136 dynamic updates.
+    #[serde(default)]
+    pub update: Option<UpdateConfig>,
+    /// Seconds between retries after a failed publish.
+    #[serde(default = "default_retry_secs")]
+    pub retry_secs: u64,
+}
+
+impl DnsExportConfig {
+    /// Read a config file.
+    pub fn load(path: &Path) -> Result<Self, ExportError> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| ExportError::Config(format!("{}: {e}", path.display())))
+    }
+}
+
+/// Keeps external DNS in step with a [`DnsResolver`].
+pub struct DnsExporter {
+    config: DnsExportConfig,
+    updater: Option<DynamicUpdater>,
+    /// Records last published everywhere, `None` before the first publish.
+    published: Option<Vec<ZoneRecord>>,
+    last_serial: u32,
+}
+
+impl DnsExporter {
+    /// Validate `config`.
+    pub fn new(config: DnsExportConfig) -> Result<Self, ExportError> {
+        if config.zone.origin.trim_matches('.').is_empty() {
+            return Err(ExportError::Config("origin must not be empty".to_string()));
+        }
+        if config.zone_file.is_none() && config.update.is_none() {
+            return Err(ExportError::Config(
+                "set zone_file, [update], or both".to_string(),
+            ));
+        }
+        let updater = config
+            .update
+            .as_ref()
+            .map(|update| DynamicUpdater::new(update, &config.zone))
+            .transpose()?;
+        Ok(Self {
+            config,
+            updater,
+            published: None,
+            last_serial: 0,
+        })
+    }
+
+    /// Publish the resolver's current records, if they changed since the
+    /// last successful publish.
+    pub async fn publish(&mut self, resolver: &DnsResolver) -> Result<(), ExportError> {
+        let records = zone::zone_records(&resolver.list_records(), resolver.domain_suffix());
+        if self.published.as_ref() == Some(&records) {
+            return Ok(());
+        }
+
+        if let Some(path) = &self.config.zone_file {
+            // Secondaries only transfer the zone when the serial grows.
+            let serial = (epoch_secs() as u32).max(self.last_serial + 1);
+            write_atomic(path, &zone::render(&self.config.zone, &records, serial))?;
+            self.last_serial = serial;
+            debug!(path = %path.display(), serial, "wrote DNS zone file");
+        }
+        if let Some(updater) = &mut self.updater {
+            let names = updater.apply(self.published.as_deref(), &records).await?;
+            debug!(names, "pushed DNS updates");
+        }
+
+        info!(records = records.len(), origin = %self.config.zone.origin(), "exported service DNS records");
+        self.published = Some(records);
+        Ok(())
+    }
+
+    /// Publish on every record change until `shutdown` fires.
+    pub async fn run(mut self, resolver: DnsResolver, mut shutdown: watch::Receiver<bool>) {
+        let mut changes = resolver.subscribe();
+        let retry = Duration::from_secs(self.config.retry_secs.max(1));
+        loop {
+            changes.mark_unchanged();
+            let failed = match self.publish(&resolver).await {
+                Ok(()) => false,
+                Err(e) => {
+                    warn!(error = %e, "DNS export failed; retrying");
+                    true
+                }
+            };
+            tokio::select! {
+                changed = changes.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+                _ = tokio::time::sleep(retry), if failed => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+}
+
+/// Replace `path` so readers never see a half-written zone.
+fn write_atomic(path: &Path, contents: &str) -> Result<(), ExportError> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> DnsExportConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn config_requires_a_destination() {
+        let err = DnsExporter::new(config("origin = \"warp.local\"")).err().unwrap();
+        assert!(err.to_string().contains("zone_file"), "{err}");
+
+        let err = DnsExporter::new(config(
+            "origin = \"warp.local\"\n[update]\nserver = \"dns.corp\"",
+        ))
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("not an ip"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn zone_file_follows_the_resolver() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warp.local.zone");
+        let mut exporter = DnsExporter::new(config(&format!(
+            "origin = \"warp.local\"\nzone_file = {:?}",
+            path.to_str().unwrap()
+        )))
+        .unwrap();
+        let dns = DnsResolver::default();
+
+        dns.upsert("api", "prod", vec!["10.0.0.1:8080".to_string()], 60);
+        exporter.publish(&dns).await.unwrap();
+        let zone = std::fs::read_to_string(&path).unwrap();
+        assert!(zone.contains("api.prod 60 IN A 10.0.0.1"), "{zone}");
+        let serial = exporter.last_serial;
+
+        // Unchanged records are not rewritten.
+        exporter.publish(&dns).await.unwrap();
+        assert_eq!(exporter.last_serial, serial);
+
+        dns.remove("api", "prod");
+        exporter.publish(&dns).await.unwrap();
+        assert!(exporter.last_serial > serial);
+        let zone = std::fs::read_to_string(&path).unwrap();
+        assert!(!zone.contains("api.prod"), "{zone}");
+    }
+}
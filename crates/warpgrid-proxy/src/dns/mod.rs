@@ -2,14 +2,23 @@
 //!
 //! Maps service names to their backend addresses within the mesh.
 //! Supports namespace-scoped names: `{service}.{namespace}.svc.warpgrid`
+//!
+//! The records can also be published to corporate DNS ([`export`]): as an
+//! RFC 1035 zone file ([`zone`]) and/or through RFC 2136 dynamic updates
+//! ([`update`]), kept current as services change.
+
+pub mod export;
+pub mod update;
+pub mod zone;
 
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+use tokio::sync::watch;
 use tracing::debug;
 
 /// A DNS record for internal service resolution.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct DnsRecord {
     /// Fully-qualified internal name.
     pub fqdn: String,
@@ -20,9 +29,14 @@ pub struct DnsRecord {
 }
 
 /// Internal DNS resolver for the service mesh.
+///
+/// Clones share the same records.
+#[derive(Clone)]
 pub struct DnsResolver {
     records: Arc<RwLock<HashMap<String, DnsRecord>>>,
     domain_suffix: String,
+    /// Bumped whenever a record is added, changed, or removed.
+    version: watch::Sender<u64>,
 }
 
 impl DnsResolver {
@@ -31,9 +45,25 @@ impl DnsResolver {
         Self {
             records: Arc::new(RwLock::new(HashMap::new())),
             domain_suffix: domain_suffix.to_string(),
+            version: watch::Sender::new(0),
         }
     }
 
+    /// The domain suffix names are built with.
+    pub fn domain_suffix(&self) -> &str {
+        &self.domain_suffix
+    }
+
+    /// Watch for record changes. The value is a counter bumped on every
+    /// change; upserting an identical record is not a change.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.version.subscribe()
+    }
+
+    fn changed(&self) {
+        self.version.send_modify(|version| *version += 1);
+    }
+
     /// Build a FQDN from service name and namespace.
     pub fn fqdn(&self, service: &str, namespace: &str) -> String {
         format!("{}.{}.svc.{}", service, namespace, self.domain_suffix)
@@ -49,7 +79,10 @@ impl DnsResolver {
         };
         let mut records = self.records.write().expect("dns lock");
         debug!(fqdn = %fqdn, "upserted DNS record");
-        records.insert(fqdn, record);
+        if records.insert(fqdn, record.clone()).as_ref() != Some(&record) {
+            drop(records);
+            self.changed();
+        }
     }
 
     /// Resolve a FQDN to addresses.
@@ -68,7 +101,10 @@ impl DnsResolver {
     pub fn remove(&self, service: &str, namespace: &str) {
         let fqdn = self.fqdn(service, namespace);
         let mut records = self.records.write().expect("dns lock");
-        records.remove(&fqdn);
+        if records.remove(&fqdn).is_some() {
+            drop(records);
+            self.changed();
+        }
     }
 
     /// List all registered FQDNs.
@@ -137,4 +173,19 @@ mod tests {
         assert_eq!(record.addresses.len(), 2);
         assert_eq!(record.ttl, 30);
     }
+
+    #[test]
+    fn only_real_changes_bump_the_version() {
+        let dns = DnsResolver::new("warpgrid");
+        let changes = dns.subscribe();
+
+        dns.upsert("api", "prod", vec!["10.0.0.1".to_string()], 60);
+        assert_eq!(*changes.borrow(), 1);
+        dns.upsert("api", "prod", vec!["10.0.0.1".to_string()], 60);
+        assert_eq!(*changes.borrow(), 1);
+
+        dns.remove("api", "prod");
+        dns.remove("api", "prod");
+        assert_eq!(*changes.borrow(), 2);
+    }
 }
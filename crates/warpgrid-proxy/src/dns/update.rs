@@ -0,0 +1,444 @@
+//! RFC 2136 dynamic updates, optionally signed with TSIG (RFC 8945).
+//!
+//! Each changed name has its `A` and `AAAA` RRsets replaced in a single
+//! UPDATE message; names that disappeared have theirs deleted. Other
+//! record types at the same names are left alone. Messages go over UDP,
+//! or TCP when they exceed 512 bytes.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tracing::debug;
+
+use super::export::ExportError;
+use super::zone::{ZoneConfig, ZoneRecord};
+
+const OPCODE_UPDATE: u16 = 5 << 11;
+const TYPE_A: u16 = 1;
+const TYPE_SOA: u16 = 6;
+const TYPE_AAAA: u16 = 28;
+const TYPE_TSIG: u16 = 250;
+const CLASS_IN: u16 = 1;
+const CLASS_NONE_ANY: u16 = 255;
+/// Largest message sent over UDP without EDNS.
+const MAX_UDP_MESSAGE: usize = 512;
+/// Allowed clock skew for TSIG, in seconds.
+const TSIG_FUDGE: u16 = 300;
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+fn default_algorithm() -> String {
+    "hmac-sha256".to_string()
+}
+
+/// Where and how to send dynamic updates.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateConfig {
+    /// Primary server accepting updates for the zone (`ip` or `ip:port`).
+    pub server: String,
+    /// Sign updates with this TSIG key.
+    #[serde(default)]
+    pub tsig: Option<TsigConfig>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// A TSIG key, as configured on the DNS server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TsigConfig {
+    /// Key name, e.g. `warpgrid-update`.
+    pub key_name: String,
+    /// Base64 shared secret.
+    pub secret: String,
+    /// Only `hmac-sha256` is supported.
+    #[serde(default = "default_algorithm")]
+    pub algorithm: String,
+}
+
+struct TsigKey {
+    name: String,
+    secret: Vec<u8>,
+}
+
+/// Sends the difference between two record sets as UPDATE messages.
+pub struct DynamicUpdater {
+    server: SocketAddr,
+    origin: String,
+    key: Option<TsigKey>,
+    timeout: Duration,
+    next_id: u16,
+}
+
+impl DynamicUpdater {
+    /// Validate `config` for updates to the zone at `zone`.
+    pub fn new(config: &UpdateConfig, zone: &ZoneConfig) -> Result<Self, ExportError> {
+        let server = config
+            .server
+            .parse::<SocketAddr>()
+            .or_else(|_| config.server.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+            .map_err(|_| {
+                ExportError::Config(format!(
+                    "update server '{}' is not an ip or ip:port",
+                    config.server
+                ))
+            })?;
+        let key = config.tsig.as_ref().map(TsigKey::new).transpose()?;
+        Ok(Self {
+            server,
+            origin: zone.origin(),
+            key,
+            timeout: Duration::from_secs(config.timeout_secs),
+            next_id: (epoch_secs() & 0xffff) as u16,
+        })
+    }
+
+    /// Bring the server from `previous` to `current`. Without a previous
+    /// state, every current name is replaced.
+    pub async fn apply(
+        &mut self,
+        previous: Option<&[ZoneRecord]>,
+        current: &[ZoneRecord],
+    ) -> Result<usize, ExportError> {
+        let changes = changes(previous, current);
+        if changes.is_empty() {
+            return Ok(0);
+        }
+        self.next_id = self.next_id.wrapping_add(1);
+        let mut message = build_update(self.next_id, &self.origin, &changes)?;
+        if let Some(key) = &self.key {
+            key.sign(&mut message, epoch_secs())?;
+        }
+        let response = self.exchange(&message).await?;
+        check_response(self.next_id, &response)?;
+        debug!(server = %self.server, names = changes.len(), "applied DNS update");
+        Ok(changes.len())
+    }
+
+    async fn exchange(&self, message: &[u8]) -> Result<Vec<u8>, ExportError> {
+        let exchange = async {
+            if message.len() <= MAX_UDP_MESSAGE {
+                let bind: SocketAddr = if self.server.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0u16; 8], 0).into()
+                };
+                let socket = UdpSocket::bind(bind).await?;
+                socket.connect(self.server).await?;
+                socket.send(message).await?;
+                let mut buf = vec![0; 4096];
+                let len = socket.recv(&mut buf).await?;
+                buf.truncate(len);
+                Ok(buf)
+            } else {
+                let mut stream = TcpStream::connect(self.server).await?;
+                stream.write_u16(message.len() as u16).await?;
+                stream.write_all(message).await?;
+                let len = stream.read_u16().await?;
+                let mut buf = vec![0; len as usize];
+                stream.read_exact(&mut buf).await?;
+                Ok(buf)
+            }
+        };
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| ExportError::Update(format!("{} did not answer", self.server)))?
+    }
+}
+
+impl TsigKey {
+    fn new(config: &TsigConfig) -> Result<Self, ExportError> {
+        if !config.algorithm.eq_ignore_ascii_case("hmac-sha256") {
+            return Err(ExportError::Config(format!(
+                "unsupported TSIG algorithm '{}' (expected hmac-sha256)",
+                config.algorithm
+            )));
+        }
+        let secret = base64::engine::general_purpose::STANDARD
+            .decode(config.secret.trim())
+            .map_err(|e| ExportError::Config(format!("TSIG secret is not base64: {e}")))?;
+        Ok(Self {
+            name: config.key_name.to_ascii_lowercase(),
+            secret,
+        })
+    }
+
+    /// Append a TSIG record to `message` (RFC 8945 §4).
+    fn sign(&self, message: &mut Vec<u8>, time_signed: u64) -> Result<(), ExportError> {
+        let key_name = encode_name(&self.name)?;
+        let algorithm = encode_name("hmac-sha256")?;
+        let time = &time_signed.to_be_bytes()[2..];
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .map_err(|e| ExportError::Config(format!("TSIG secret: {e}")))?;
+        mac.update(message);
+        mac.update(&key_name);
+        mac.update(&CLASS_NONE_ANY.to_be_bytes());
+        mac.update(&0u32.to_be_bytes()); // TTL
+        mac.update(&algorithm);
+        mac.update(time);
+        mac.update(&TSIG_FUDGE.to_be_bytes());
+        mac.update(&0u16.to_be_bytes()); // error
+        mac.update(&0u16.to_be_bytes()); // other len
+        let mac = mac.finalize().into_bytes();
+
+        let mut rdata = algorithm;
+        rdata.extend_from_slice(time);
+        rdata.extend_from_slice(&TSIG_FUDGE.to_be_bytes());
+        rdata.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(&mac);
+        rdata.extend_from_slice(&message[0..2]); // original id
+        rdata.extend_from_slice(&0u16.to_be_bytes()); // error
+        rdata.extend_from_slice(&0u16.to_be_bytes()); // other len
+
+        push_rr(message, &key_name, TYPE_TSIG, CLASS_NONE_ANY, 0, &rdata);
+        let additional = u16::from_be_bytes([message[10], message[11]]) + 1;
+        message[10..12].copy_from_slice(&additional.to_be_bytes());
+        Ok(())
+    }
+}
+
+/// Names whose address records differ, with their new records (empty
+/// when the name is gone).
+fn changes<'a>(
+    previous: Option<&'a [ZoneRecord]>,
+    current: &'a [ZoneRecord],
+) -> BTreeMap<&'a str, Vec<&'a ZoneRecord>> {
+    let by_name = |records: &'a [ZoneRecord]| {
+        let mut names: BTreeMap<&'a str, Vec<&'a ZoneRecord>> = BTreeMap::new();
+        for record in records {
+            names.entry(record.name.as_str()).or_default().push(record);
+        }
+        names
+    };
+    let now = by_name(current);
+    let Some(previous) = previous else {
+        return now;
+    };
+    let before = by_name(previous);
+
+    let removed: Vec<&str> = before
+        .keys()
+        .filter(|name| !now.contains_key(*name))
+        .copied()
+        .collect();
+    let mut changes: BTreeMap<&str, Vec<&ZoneRecord>> = now
+        .into_iter()
+        .filter(|(name, records)| before.get(name) != Some(records))
+        .collect();
+    for name in removed {
+        changes.insert(name, Vec::new());
+    }
+    changes
+}
+
+/// An UPDATE message replacing the address RRsets of every changed name.
+fn build_update(
+    id: u16,
+    origin: &str,
+    changes: &BTreeMap<&str, Vec<&ZoneRecord>>,
+) -> Result<Vec<u8>, ExportError> {
+    let zone = encode_name(origin)?;
+    let mut updates = 0u16;
+    let mut body = Vec::new();
+    for (name, records) in changes {
+        let owner = encode_name(&format!("{name}.{origin}"))?;
+        // Delete both address RRsets, then add the current records.
+        for rtype in [TYPE_A, TYPE_AAAA] {
+            push_rr(&mut body, &owner, rtype, CLASS_NONE_ANY, 0, &[]);
+            updates += 1;
+        }
+        for record in records {
+            let (rtype, rdata) = match record.addr {
+                IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+                IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+            };
+            push_rr(&mut body, &owner, rtype, CLASS_IN, record.ttl, &rdata);
+            updates += 1;
+        }
+    }
+
+    let mut message = Vec::with_capacity(12 + zone.len() + 4 + body.len());
+    message.extend_from_slice(&id.to_be_bytes());
+    message.extend_from_slice(&OPCODE_UPDATE.to_be_bytes());
+    message.extend_from_slice(&1u16.to_be_bytes()); // zone count
+    message.extend_from_slice(&0u16.to_be_bytes()); // prerequisites
+    message.extend_from_slice(&updates.to_be_bytes());
+    message.extend_from_slice(&0u16.to_be_bytes()); // additional
+    message.extend_from_slice(&zone);
+    message.extend_from_slice(&TYPE_SOA.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    message.extend_from_slice(&body);
+    Ok(message)
+}
+
+fn push_rr(out: &mut Vec<u8>, name: &[u8], rtype: u16, class: u16, ttl: u32, rdata: &[u8]) {
+    out.extend_from_slice(name);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+}
+
+/// Uncompressed wire form of a domain name.
+fn encode_name(name: &str) -> Result<Vec<u8>, ExportError> {
+    let mut out = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > 63 {
+            return Err(ExportError::Config(format!("DNS label '{label}' is longer than 63 bytes")));
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    if out.len() > 255 {
+        return Err(ExportError::Config(format!("DNS name '{name}' is longer than 255 bytes")));
+    }
+    Ok(out)
+}
+
+/// Fail unless `response` answers `id` with NOERROR.
+fn check_response(id: u16, response: &[u8]) -> Result<(), ExportError> {
+    if response.len() < 12 || u16::from_be_bytes([response[0], response[1]]) != id {
+        return Err(ExportError::Update("malformed or mismatched response".to_string()));
+    }
+    if response[2] & 0x80 == 0 {
+        return Err(ExportError::Update("response is not a reply".to_string()));
+    }
+    match response[3] & 0x0f {
+        0 => Ok(()),
+        rcode => Err(ExportError::Update(format!("server answered {}", rcode_name(rcode)))),
+    }
+}
+
+fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        1 => "FORMERR".into(),
+        2 => "SERVFAIL".into(),
+        4 => "NOTIMP".into(),
+        5 => "REFUSED".into(),
+        8 => "NXRRSET".into(),
+        9 => "NOTAUTH".into(),
+        10 => "NOTZONE".into(),
+        other => format!("RCODE {other}"),
+    }
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone() -> ZoneConfig {
+        toml::from_str("origin = \"warp.local\"").unwrap()
+    }
+
+    fn record(name: &str, addr: &str) -> ZoneRecord {
+        ZoneRecord {
+            name: name.into(),
+            addr: addr.parse().unwrap(),
+            ttl: 60,
+        }
+    }
+
+    fn counts(message: &[u8]) -> [u16; 4] {
+        let at = |i: usize| u16::from_be_bytes([message[i], message[i + 1]]);
+        [at(4), at(6), at(8), at(10)]
+    }
+
+    #[test]
+    fn only_changed_and_removed_names_are_sent() {
+        let before = [record("api.prod", "10.0.0.1"), record("old.prod", "10.0.0.9")];
+        let after = [record("api.prod", "10.0.0.1"), record("web.prod", "10.0.0.2")];
+
+        let changes = changes(Some(&before), &after);
+        let names: Vec<_> = changes.iter().map(|(name, records)| (*name, records.len())).collect();
+        assert_eq!(names, vec![("old.prod", 0), ("web.prod", 1)]);
+
+        // Without history, everything current is replaced.
+        assert_eq!(super::changes(None, &after).len(), 2);
+    }
+
+    #[test]
+    fn update_message_layout() {
+        let after = [record("web.prod", "10.0.0.2"), record("web.prod", "fd00::2")];
+        let message = build_update(7, "warp.local.", &changes(None, &after)).unwrap();
+        assert_eq!(&message[0..4], &[0, 7, 0x28, 0]);
+        // One zone, two RRset deletions plus two additions.
+        assert_eq!(counts(&message), [1, 0, 4, 0]);
+        assert!(message.windows(21).any(|w| w == b"\x03web\x04prod\x04warp\x05local\x00"));
+    }
+
+    #[test]
+    fn tsig_is_appended_to_the_additional_section() {
+        let key = TsigKey::new(&TsigConfig {
+            key_name: "WarpGrid-Update".into(),
+            secret: "c2VjcmV0".into(),
+            algorithm: default_algorithm(),
+        })
+        .unwrap();
+        let unsigned = build_update(7, "warp.local.", &changes(None, &[record("a", "10.0.0.1")])).unwrap();
+        let mut signed = unsigned.clone();
+        key.sign(&mut signed, 1_700_000_000).unwrap();
+        assert_eq!(counts(&signed)[3], 1);
+        assert!(signed.starts_with(&unsigned[..10]));
+        assert!(signed.windows(16).any(|w| w == b"\x0fwarpgrid-update"));
+
+        // Signing is deterministic for a given time.
+        let mut again = unsigned;
+        key.sign(&mut again, 1_700_000_000).unwrap();
+        assert_eq!(signed, again);
+
+        let bad = TsigConfig {
+            key_name: "k".into(),
+            secret: "c2VjcmV0".into(),
+            algorithm: "hmac-md5".into(),
+        };
+        assert!(TsigKey::new(&bad).is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_reports_server_errors() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            for rcode in [0u8, 5] {
+                let (len, peer) = server.recv_from(&mut buf).await.unwrap();
+                let mut reply = buf[..len.min(12)].to_vec();
+                reply[2] |= 0x80;
+                reply[3] = rcode;
+                server.send_to(&reply, peer).await.unwrap();
+            }
+        });
+
+        let config = UpdateConfig {
+            server: addr.to_string(),
+            tsig: None,
+            timeout_secs: 5,
+        };
+        let mut updater = DynamicUpdater::new(&config, &zone()).unwrap();
+        let records = [record("api.prod", "10.0.0.1")];
+        assert_eq!(updater.apply(None, &records).await.unwrap(), 1);
+        // Nothing changed, nothing sent.
+        assert_eq!(updater.apply(Some(&records), &records).await.unwrap(), 0);
+
+        let err = updater.apply(Some(&records), &[]).await.unwrap_err();
+        assert!(err.to_string().contains("REFUSED"), "{err}");
+    }
+}
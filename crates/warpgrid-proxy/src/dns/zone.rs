@@ -0,0 +1,194 @@
+//! RFC 1035 zone files for the service records.
+//!
+//! A service `api` in namespace `prod` (internally
+//! `api.prod.svc.<suffix>`) is exported as `api.prod.<origin>`, with one
+//! `A`/`AAAA` record per backend address. Addresses that are not IPs
+//! (e.g. node ids standing in for unresolved backends) are left out.
+
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+
+use serde::Deserialize;
+
+use super::DnsRecord;
+
+/// SOA refresh / retry / expire timers, in seconds.
+const SOA_REFRESH: u32 = 3600;
+const SOA_RETRY: u32 = 600;
+const SOA_EXPIRE: u32 = 86_400;
+
+fn default_ttl() -> u32 {
+    60
+}
+
+/// The zone the records are exported into.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneConfig {
+    /// Zone apex, e.g. `warp.local`.
+    pub origin: String,
+    /// Primary nameserver for the SOA and NS records (default `ns.<origin>`).
+    #[serde(default)]
+    pub nameserver: Option<String>,
+    /// Responsible mailbox for the SOA record (default `hostmaster.<origin>`).
+    #[serde(default)]
+    pub hostmaster: Option<String>,
+    /// Default TTL and SOA negative-caching TTL, in seconds.
+    #[serde(default = "default_ttl")]
+    pub ttl: u32,
+}
+
+impl ZoneConfig {
+    /// The origin as an absolute name (`warp.local.`).
+    pub fn origin(&self) -> String {
+        absolute(&self.origin)
+    }
+
+    /// `name` relative to the origin, as an absolute name.
+    pub fn qualify(&self, name: &str) -> String {
+        format!("{name}.{}", self.origin())
+    }
+
+    fn nameserver(&self) -> String {
+        self.nameserver
+            .as_deref()
+            .map(absolute)
+            .unwrap_or_else(|| self.qualify("ns"))
+    }
+
+    fn hostmaster(&self) -> String {
+        self.hostmaster
+            .as_deref()
+            .map(|mailbox| absolute(&mailbox.replacen('@', ".", 1)))
+            .unwrap_or_else(|| self.qualify("hostmaster"))
+    }
+}
+
+fn absolute(name: &str) -> String {
+    let name = name.trim();
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+/// One exported address record.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ZoneRecord {
+    /// Owner name relative to the origin, e.g. `api.prod`.
+    pub name: String,
+    pub addr: IpAddr,
+    pub ttl: u32,
+}
+
+/// The exportable records among `records`, sorted and deduplicated.
+pub fn zone_records(records: &[DnsRecord], domain_suffix: &str) -> Vec<ZoneRecord> {
+    let internal = format!(".svc.{domain_suffix}");
+    let mut out: Vec<ZoneRecord> = records
+        .iter()
+        .filter_map(|record| {
+            let name = record.fqdn.strip_suffix(&internal)?;
+            Some((name, record))
+        })
+        .flat_map(|(name, record)| {
+            record.addresses.iter().filter_map(move |address| {
+                Some(ZoneRecord {
+                    name: name.to_string(),
+                    addr: parse_addr(address)?,
+                    ttl: record.ttl,
+                })
+            })
+        })
+        .collect();
+    out.sort();
+    out.dedup_by(|a, b| a.name == b.name && a.addr == b.addr);
+    out
+}
+
+/// An IP from `ip`, `ip:port`, or `[ipv6]:port`.
+fn parse_addr(address: &str) -> Option<IpAddr> {
+    address
+        .parse::<SocketAddr>()
+        .map(|addr| addr.ip())
+        .or_else(|_| address.parse::<IpAddr>())
+        .ok()
+}
+
+/// Render a complete zone file.
+pub fn render(config: &ZoneConfig, records: &[ZoneRecord], serial: u32) -> String {
+    let mut zone = String::new();
+    let _ = writeln!(zone, "; Generated by WarpGrid. Do not edit; changes are overwritten.");
+    let _ = writeln!(zone, "$ORIGIN {}", config.origin());
+    let _ = writeln!(zone, "$TTL {}", config.ttl);
+    let _ = writeln!(
+        zone,
+        "@ IN SOA {} {} ( {serial} {SOA_REFRESH} {SOA_RETRY} {SOA_EXPIRE} {} )",
+        config.nameserver(),
+        config.hostmaster(),
+        config.ttl
+    );
+    let _ = writeln!(zone, "@ IN NS {}", config.nameserver());
+    for record in records {
+        let kind = if record.addr.is_ipv4() { "A" } else { "AAAA" };
+        let _ = writeln!(zone, "{} {} IN {kind} {}", record.name, record.ttl, record.addr);
+    }
+    zone
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ZoneConfig {
+        ZoneConfig {
+            origin: "warp.local".into(),
+            nameserver: None,
+            hostmaster: Some("dns-admin@corp.example".into()),
+            ttl: 30,
+        }
+    }
+
+    fn record(fqdn: &str, addresses: &[&str]) -> DnsRecord {
+        DnsRecord {
+            fqdn: fqdn.into(),
+            addresses: addresses.iter().map(|a| a.to_string()).collect(),
+            ttl: 60,
+        }
+    }
+
+    #[test]
+    fn records_are_mapped_into_the_zone() {
+        let records = zone_records(
+            &[
+                record("web.prod.svc.warpgrid", &["10.0.0.2:8080", "node-1:0", "[fd00::1]:80"]),
+                record("api.prod.svc.warpgrid", &["10.0.0.1", "10.0.0.1:9000"]),
+                record("elsewhere.example", &["10.0.0.9"]),
+            ],
+            "warpgrid",
+        );
+        let names: Vec<_> = records.iter().map(|r| (r.name.as_str(), r.addr.to_string())).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("api.prod", "10.0.0.1".to_string()),
+                ("web.prod", "10.0.0.2".to_string()),
+                ("web.prod", "fd00::1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_zone_file() {
+        let records = zone_records(&[record("api.prod.svc.warpgrid", &["10.0.0.1:80"])], "warpgrid");
+        let zone = render(&config(), &records, 1_700_000_000);
+        assert!(zone.contains("$ORIGIN warp.local.\n"), "{zone}");
+        assert!(
+            zone.contains(
+                "@ IN SOA ns.warp.local. dns-admin.corp.example. ( 1700000000 3600 600 86400 30 )"
+            ),
+            "{zone}"
+        );
+        assert!(zone.contains("@ IN NS ns.warp.local.\n"), "{zone}");
+        assert!(zone.contains("api.prod 60 IN A 10.0.0.1\n"), "{zone}");
+    }
+}
@@ -6,15 +6,20 @@
 //! # Components
 //!
 //! - **`router`** — Request routing with round-robin backend selection
-//! - **`dns`** — Internal DNS resolver for service discovery
+//! - **`dns`** — Internal DNS resolver for service discovery, with zone
+//!   file and RFC 2136 export to corporate DNS
 //! - **`tls`** — TLS termination with SNI-based certificate resolution
 //! - **`sync`** — State store → proxy synchronization
+//! - **`discovery`** — Consul/etcd catalog bridge for mixed environments
 
+pub mod discovery;
 pub mod dns;
 pub mod router;
 pub mod sync;
 pub mod tls;
 
+pub use discovery::{DiscoveryBridge, DiscoveryConfig};
+pub use dns::export::{DnsExportConfig, DnsExporter};
 pub use dns::{DnsRecord, DnsResolver};
 pub use router::{Backend, Router};
 pub use sync::{ProxySync, SyncStats};
@@ -3,14 +3,18 @@
 //! The controller progresses through rollout phases, checking health
 //! gates between batches. It can pause, resume, or rollback.
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use tracing::{debug, info, warn};
+use warpgrid_state::{SharedClock, SystemClock};
 
 use crate::strategy::{CanaryConfig, RolloutStrategy};
 
+/// Slack past a step's own wait before an active rollout counts as stalled.
+const STALL_GRACE: Duration = Duration::from_secs(120);
+
 /// Current phase of a rollout.
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub enum RolloutPhase {
     /// Rollout not started.
     Pending,
@@ -53,6 +57,10 @@ pub struct Rollout {
     pub old_version: String,
     pub new_version: String,
     pub started_at: Option<Instant>,
+    /// When the last batch action was issued.
+    last_step_at: Option<Instant>,
+    /// Time source for batch intervals and canary observation.
+    clock: SharedClock,
 }
 
 impl Rollout {
@@ -72,12 +80,20 @@ impl Rollout {
             old_version: old_version.to_string(),
             new_version: new_version.to_string(),
             started_at: None,
+            last_step_at: None,
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Use `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Start the rollout.
     pub fn start(&mut self) {
-        self.started_at = Some(Instant::now());
+        self.started_at = Some(self.clock.now());
         match &self.strategy {
             RolloutStrategy::Rolling(cfg) => {
                 let total_batches = batch_count(self.target_instances, cfg.batch_size);
@@ -109,11 +125,68 @@ impl Rollout {
         }
     }
 
+    /// How long until the next step is due: the batch interval after the
+    /// previous batch, or the observation window after a canary starts.
+    pub fn next_step_in(&self) -> Duration {
+        let (since, wait) = match (&self.phase, &self.strategy) {
+            (RolloutPhase::RollingBatch { current, .. }, RolloutStrategy::Rolling(cfg))
+                if *current > 1 =>
+            {
+                (self.last_step_at, cfg.batch_interval_secs)
+            }
+            (RolloutPhase::CanaryObserving, RolloutStrategy::Canary(cfg)) => {
+                (self.started_at, cfg.observation_secs)
+            }
+            _ => return Duration::ZERO,
+        };
+        let Some(since) = since else {
+            return Duration::ZERO;
+        };
+        let elapsed = self.clock.now().saturating_duration_since(since);
+        Duration::from_secs(wait).saturating_sub(elapsed)
+    }
+
+    /// Whether an active rollout has gone without a step for longer than
+    /// its strategy allows: the batch interval plus the health timeout for
+    /// rolling updates, the observation window for canaries, plus
+    /// [`STALL_GRACE`]. Paused and finished rollouts never stall.
+    pub fn is_stalled(&self) -> bool {
+        let allowance = match (&self.phase, &self.strategy) {
+            (
+                RolloutPhase::Pending | RolloutPhase::Paused | RolloutPhase::Completed | RolloutPhase::RolledBack { .. },
+                _,
+            ) => return false,
+            (_, RolloutStrategy::Rolling(cfg)) => cfg.batch_interval_secs + cfg.health_timeout_secs,
+            (_, RolloutStrategy::Canary(cfg)) => cfg.observation_secs,
+            (_, RolloutStrategy::BlueGreen) => 0,
+        };
+        let Some(since) = self.last_step_at.or(self.started_at) else {
+            return false;
+        };
+        self.clock.now().saturating_duration_since(since) > Duration::from_secs(allowance) + STALL_GRACE
+    }
+
+    /// Sleep until the next step is due (see [`Rollout::next_step_in`]).
+    pub async fn wait_for_next_step(&self) {
+        let wait = self.next_step_in();
+        if !wait.is_zero() {
+            self.clock.sleep(wait).await;
+        }
+    }
+
     /// Advance the rollout by one step, given current health metrics.
     ///
     /// Returns the instances to update in this step, or None if the
     /// rollout is complete/paused/rolled-back.
     pub fn advance(&mut self, health: &HealthMetrics) -> Option<BatchAction> {
+        let action = self.step(health);
+        if action.is_some() {
+            self.last_step_at = Some(self.clock.now());
+        }
+        action
+    }
+
+    fn step(&mut self, health: &HealthMetrics) -> Option<BatchAction> {
         match &self.phase {
             RolloutPhase::Pending => None,
             RolloutPhase::Paused => None,
@@ -233,6 +306,24 @@ impl Rollout {
         }
     }
 
+    /// Abandon an unfinished rollout at the operator's request. Returns
+    /// whether it was rolled back; finished rollouts are left as they are.
+    pub fn rollback(&mut self, reason: &str) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        warn!(deployment = %self.deployment_id, reason, "rolling back rollout");
+        self.phase = RolloutPhase::RolledBack {
+            reason: reason.to_string(),
+        };
+        true
+    }
+
+    /// Whether the rollout has completed or been rolled back.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.phase, RolloutPhase::Completed | RolloutPhase::RolledBack { .. })
+    }
+
     /// Check if the health gate passes.
     fn check_health_gate(&self, health: &HealthMetrics) -> bool {
         if health.total_count == 0 {
@@ -291,6 +382,8 @@ fn batch_count(total_instances: u32, batch_size: u32) -> u32 {
 mod tests {
     use super::*;
     use crate::strategy::{CanaryConfig, RollingConfig};
+    use std::sync::Arc;
+    use warpgrid_state::ManualClock;
 
     fn healthy_metrics() -> HealthMetrics {
         HealthMetrics {
@@ -480,6 +573,119 @@ mod tests {
         assert_eq!(rollout.phase, RolloutPhase::HealthGate);
     }
 
+    #[test]
+    fn operator_rollback_only_applies_to_unfinished_rollouts() {
+        let mut rollout = Rollout::new("deploy/a", RolloutStrategy::BlueGreen, 3, "v1", "v2");
+        rollout.start();
+        assert!(rollout.rollback("bad build"));
+        assert_eq!(
+            rollout.phase,
+            RolloutPhase::RolledBack {
+                reason: "bad build".to_string()
+            }
+        );
+        assert!(rollout.advance(&healthy_metrics()).is_none());
+        assert!(!rollout.rollback("again"));
+
+        let mut done = Rollout::new("deploy/b", RolloutStrategy::BlueGreen, 3, "v1", "v2");
+        done.start();
+        done.advance(&healthy_metrics());
+        assert!(!done.rollback("too late"));
+        assert_eq!(done.phase, RolloutPhase::Completed);
+    }
+
+    #[test]
+    fn batches_are_spaced_by_the_batch_interval() {
+        let clock = Arc::new(ManualClock::default());
+        let mut rollout = Rollout::new(
+            "deploy/a",
+            RolloutStrategy::Rolling(RollingConfig {
+                batch_size: 1,
+                batch_interval_secs: 10,
+                ..Default::default()
+            }),
+            2,
+            "v1",
+            "v2",
+        )
+        .with_clock(clock.clone());
+
+        rollout.start();
+        assert_eq!(rollout.next_step_in(), Duration::ZERO);
+        rollout.advance(&healthy_metrics()).unwrap();
+        assert_eq!(rollout.next_step_in(), Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(rollout.next_step_in(), Duration::from_secs(6));
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(rollout.next_step_in(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn canary_waits_out_the_observation_window() {
+        let clock = Arc::new(ManualClock::default());
+        let mut rollout = Rollout::new(
+            "deploy/a",
+            RolloutStrategy::Canary(CanaryConfig {
+                observation_secs: 300,
+                ..Default::default()
+            }),
+            5,
+            "v1",
+            "v2",
+        )
+        .with_clock(clock.clone());
+        rollout.start();
+
+        let waiter = {
+            let rollout = rollout.clone();
+            tokio::spawn(async move { rollout.wait_for_next_step().await })
+        };
+        clock.advance(Duration::from_secs(299));
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        waiter.await.unwrap();
+        assert_eq!(
+            rollout.advance(&healthy_metrics()),
+            Some(BatchAction::PromoteCanary)
+        );
+    }
+
+    #[test]
+    fn idle_rollouts_stall_after_their_allowance() {
+        let clock = Arc::new(ManualClock::default());
+        let mut rollout = Rollout::new(
+            "deploy/a",
+            RolloutStrategy::Rolling(RollingConfig {
+                batch_size: 1,
+                batch_interval_secs: 10,
+                health_timeout_secs: 30,
+                ..Default::default()
+            }),
+            3,
+            "v1",
+            "v2",
+        )
+        .with_clock(clock.clone());
+        assert!(!rollout.is_stalled(), "not started");
+
+        rollout.start();
+        rollout.advance(&healthy_metrics()).unwrap();
+        clock.advance(Duration::from_secs(40) + STALL_GRACE);
+        assert!(!rollout.is_stalled());
+        clock.advance(Duration::from_secs(1));
+        assert!(rollout.is_stalled());
+
+        rollout.advance(&healthy_metrics()).unwrap();
+        assert!(!rollout.is_stalled(), "a step resets the timer");
+
+        rollout.pause();
+        clock.advance(Duration::from_secs(3600));
+        assert!(!rollout.is_stalled(), "paused rollouts do not stall");
+    }
+
     #[test]
     fn batch_count_calculation() {
         assert_eq!(batch_count(4, 2), 2);
@@ -1,7 +1,7 @@
 //! Rollout strategies — rolling update, canary, blue-green.
 
 /// How to roll out a new version of a deployment.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub enum RolloutStrategy {
     /// Replace instances in batches. Default.
     Rolling(RollingConfig),
@@ -17,8 +17,9 @@ impl Default for RolloutStrategy {
     }
 }
 
-/// Configuration for rolling updates.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Configuration for rolling updates. Omitted fields take their defaults.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
 pub struct RollingConfig {
     /// Number of instances to update per batch.
     pub batch_size: u32,
@@ -41,8 +42,9 @@ impl Default for RollingConfig {
     }
 }
 
-/// Configuration for canary deployments.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Configuration for canary deployments. Omitted fields take their defaults.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
 pub struct CanaryConfig {
     /// Percentage of traffic to route to the canary (0-100).
     pub traffic_percent: u32,
@@ -97,4 +99,17 @@ mod tests {
             _ => panic!("expected Canary"),
         }
     }
+
+    #[test]
+    fn partial_configs_take_defaults() {
+        let strategy: RolloutStrategy =
+            serde_json::from_str(r#"{"Rolling": {"batch_size": 3}}"#).unwrap();
+        match strategy {
+            RolloutStrategy::Rolling(cfg) => {
+                assert_eq!(cfg.batch_size, 3);
+                assert_eq!(cfg.batch_interval_secs, 10);
+            }
+            _ => panic!("expected Rolling"),
+        }
+    }
 }
@@ -5,18 +5,27 @@
 //! - Manages the lifecycle of instances (start, stop, restart)
 //! - Persists instance state to the state store
 //! - Provides load-balanced access to instances for request routing
+//! - Evicts idle instances when the node runs low on physical memory,
+//!   least important deployments first and within their disruption budgets
+//! - Unschedules deployments deleted from the state store by anyone
 
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use futures_util::StreamExt;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-use warp_runtime::{InstancePool, PoolConfig, Runtime};
+use warp_runtime::{
+    InstancePool, LifecycleBudgets, NodeMemory, OvercommitPolicy, PoolConfig, PressureLevel,
+    PressureThreshold, Runtime,
+};
 use warpgrid_placement::convert::{deployment_to_requirements, node_info_to_resources};
 use warpgrid_placement::placer::{PlacementPlan, compute_placement};
 use warpgrid_placement::scorer::ScoringWeights;
+use warpgrid_state::tables::DEPLOYMENTS;
 use warpgrid_state::*;
 
 use crate::error::{SchedulerError, SchedulerResult};
@@ -41,6 +50,16 @@ struct DeploymentSlot {
     balancer: RoundRobinBalancer,
 }
 
+/// Idle instances evicted from one deployment under memory pressure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eviction {
+    pub deployment_id: String,
+    pub name: String,
+    pub priority: u32,
+    pub instances: u32,
+    pub freed_bytes: u64,
+}
+
 /// The scheduler manages deployment → instance pool mappings.
 ///
 /// It reads `DeploymentSpec` from the state store, creates `InstancePool`s
@@ -59,6 +78,10 @@ pub struct Scheduler {
     node_id: String,
     /// Placement mode (standalone or distributed).
     mode: PlacementMode,
+    /// Per-instance memory reservation policy for new pools.
+    overcommit: OvercommitPolicy,
+    /// Time budgets for guest lifecycle hooks in new pools.
+    lifecycle: LifecycleBudgets,
 }
 
 impl Scheduler {
@@ -70,6 +93,8 @@ impl Scheduler {
             slots: Arc::new(RwLock::new(HashMap::new())),
             node_id,
             mode: PlacementMode::Standalone,
+            overcommit: OvercommitPolicy::Reserve,
+            lifecycle: LifecycleBudgets::default(),
         }
     }
 
@@ -85,9 +110,25 @@ impl Scheduler {
             slots: Arc::new(RwLock::new(HashMap::new())),
             node_id,
             mode: PlacementMode::Distributed,
+            overcommit: OvercommitPolicy::Reserve,
+            lifecycle: LifecycleBudgets::default(),
         }
     }
 
+    /// Reserve memory for pooled instances per `policy` instead of the
+    /// full memory limit. Applies to deployments scheduled afterwards.
+    pub fn with_overcommit(mut self, policy: OvercommitPolicy) -> Self {
+        self.overcommit = policy;
+        self
+    }
+
+    /// Give guest `on-start` / `on-shutdown` hooks these budgets.
+    /// Applies to deployments scheduled afterwards.
+    pub fn with_lifecycle_budgets(mut self, budgets: LifecycleBudgets) -> Self {
+        self.lifecycle = budgets;
+        self
+    }
+
     /// Returns the current placement mode.
     pub fn placement_mode(&self) -> PlacementMode {
         self.mode
@@ -133,6 +174,7 @@ impl Scheduler {
 
         // Record instance states in the store.
         let now = epoch_secs();
+        let reservation = pool.memory_reservation() as u64;
         for i in 0..pool.total_count().await {
             let instance_state = InstanceState {
                 id: format!("inst-{i}"),
@@ -141,7 +183,7 @@ impl Scheduler {
                 status: InstanceStatus::Running,
                 health: HealthStatus::Unknown,
                 restart_count: 0,
-                memory_bytes: spec.resources.memory_bytes,
+                memory_bytes: reservation,
                 started_at: now,
                 updated_at: now,
             };
@@ -174,10 +216,11 @@ impl Scheduler {
             slots.remove(deployment_id)
         };
 
-        if slot.is_none() {
+        let Some(slot) = slot else {
             warn!(%deployment_id, "deployment not scheduled, nothing to unschedule");
             return Ok(());
-        }
+        };
+        slot.pool.drain().await;
 
         // Clean up instance states from the store.
         let deleted = self.state.delete_instances_for_deployment(deployment_id)?;
@@ -186,6 +229,18 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Drain every deployment's idle instances, running their
+    /// `on-shutdown` hooks. Called once on daemon shutdown; deployments
+    /// stay scheduled so their state records survive a restart.
+    pub async fn drain(&self) {
+        let slots = self.slots.read().await;
+        let mut drained = 0;
+        for slot in slots.values() {
+            drained += slot.pool.drain().await;
+        }
+        info!(deployments = slots.len(), instances = drained, "scheduler drained");
+    }
+
     /// Scale a deployment to a target number of instances.
     ///
     /// If target > current, new instances are created.
@@ -229,7 +284,7 @@ impl Scheduler {
         }
 
         // Update instance states in store.
-        self.sync_instance_states(deployment_id, &slot.spec, &slot.pool)
+        self.sync_instance_states(deployment_id, &slot.pool)
             .await?;
 
         Ok(())
@@ -266,6 +321,180 @@ impl Scheduler {
         slots.keys().cloned().collect()
     }
 
+    /// Memory reserved on this node by all scheduled instances.
+    pub async fn reserved_memory_bytes(&self) -> u64 {
+        let slots = self.slots.read().await;
+        let mut total = 0;
+        for slot in slots.values() {
+            total += slot.pool.reserved_memory_bytes().await;
+        }
+        total
+    }
+
+    /// Evict idle instances until about `bytes` of reservation is freed.
+    ///
+    /// Deployments are visited least important first (highest
+    /// [`DeploymentSpec::priority`]), then by reserved memory. Soft pressure
+    /// only trims pools back to `instances.min`; hard pressure goes down to
+    /// the deployment's disruption budget (`min_available`). Busy instances
+    /// are never evicted.
+    pub async fn relieve_memory_pressure(
+        &self,
+        bytes: u64,
+        level: PressureLevel,
+    ) -> SchedulerResult<Vec<Eviction>> {
+        if level == PressureLevel::None {
+            return Ok(Vec::new());
+        }
+        let slots = self.slots.read().await;
+        let mut candidates = Vec::with_capacity(slots.len());
+        for (id, slot) in slots.iter() {
+            let reserved = slot.pool.reserved_memory_bytes().await;
+            candidates.push((eviction_rank(&slot.spec, reserved), id, slot));
+        }
+        candidates.sort_by_key(|(rank, _, _)| *rank);
+
+        let mut evictions = Vec::new();
+        let mut freed = 0;
+        for (_, deployment_id, slot) in candidates {
+            if freed >= bytes {
+                break;
+            }
+            let keep = eviction_floor(&slot.spec, level);
+            let (instances, shed) = slot.pool.shed_idle(bytes - freed, keep).await;
+            if instances == 0 {
+                continue;
+            }
+            freed += shed;
+            self.sync_instance_states(deployment_id, &slot.pool)
+                .await?;
+            evictions.push(Eviction {
+                deployment_id: deployment_id.clone(),
+                name: slot.spec.name.clone(),
+                priority: slot.spec.effective_priority(),
+                instances,
+                freed_bytes: shed,
+            });
+        }
+
+        if freed < bytes {
+            warn!(
+                wanted = bytes,
+                freed,
+                ?level,
+                "memory pressure persists; no evictable idle instances left"
+            );
+        }
+        Ok(evictions)
+    }
+
+    /// Run the memory pressure loop, evicting idle instances whenever
+    /// available node memory falls below `threshold`.
+    ///
+    /// Whether the node is under pressure is published on `pressure`, so
+    /// the cluster agent can tell the control plane to place elsewhere.
+    pub async fn run_pressure_monitor(
+        &self,
+        threshold: PressureThreshold,
+        interval: Duration,
+        pressure: tokio::sync::watch::Sender<bool>,
+        mut shutdown: tokio::sync::watch::Receiver<bool>,
+    ) {
+        info!(
+            min_available_percent = threshold.min_available_percent,
+            hard_available_percent = threshold.hard_available_percent,
+            "memory pressure monitor started"
+        );
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {
+                    let memory = match NodeMemory::read() {
+                        Ok(memory) => memory,
+                        Err(e) => {
+                            warn!(error = %e, "cannot read node memory; pressure monitor stopped");
+                            break;
+                        }
+                    };
+                    let level = threshold.level(&memory);
+                    let under_pressure = level != PressureLevel::None;
+                    if pressure.send_replace(under_pressure) != under_pressure {
+                        if under_pressure {
+                            warn!(?level, available = memory.available_bytes, "node entered memory pressure");
+                        } else {
+                            info!(available = memory.available_bytes, "node memory pressure cleared");
+                        }
+                    }
+                    if !under_pressure {
+                        continue;
+                    }
+                    warn!(
+                        ?level,
+                        available = memory.available_bytes,
+                        total = memory.total_bytes,
+                        "node under memory pressure"
+                    );
+                    let wanted = threshold.bytes_to_free(&memory);
+                    match self.relieve_memory_pressure(wanted, level).await {
+                        Ok(evictions) => {
+                            for e in evictions {
+                                warn!(
+                                    deployment_id = %e.deployment_id,
+                                    name = %e.name,
+                                    priority = e.priority,
+                                    instances = e.instances,
+                                    freed_bytes = e.freed_bytes,
+                                    "evicted idle instances under memory pressure"
+                                );
+                            }
+                        }
+                        Err(e) => error!(error = %e, "failed to evict instances under memory pressure"),
+                    }
+                }
+                _ = shutdown.changed() => {
+                    info!("memory pressure monitor shutting down");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Unschedule deployments as they are deleted from the state store,
+    /// whether through this node's API or not, until `shutdown`.
+    pub async fn run_deployment_watch(&self, mut shutdown: tokio::sync::watch::Receiver<bool>) {
+        let mut changes = Box::pin(self.state.watch(DEPLOYMENTS));
+        loop {
+            tokio::select! {
+                change = changes.next() => match change {
+                    Some(Ok(WatchEvent::Delete { key, .. })) => {
+                        if self.is_scheduled(&key).await {
+                            self.unschedule_deleted(&key).await;
+                        }
+                    }
+                    Some(Ok(WatchEvent::Put { .. })) => {}
+                    Some(Err(e)) => {
+                        // Deletes may have been missed; check every slot.
+                        warn!(error = %e, "deployment watch fell behind");
+                        for deployment_id in self.scheduled_deployments().await {
+                            if matches!(self.state.get_deployment(&deployment_id), Ok(None)) {
+                                self.unschedule_deleted(&deployment_id).await;
+                            }
+                        }
+                    }
+                    None => break,
+                },
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+
+    async fn unschedule_deleted(&self, deployment_id: &str) {
+        match self.unschedule(deployment_id).await {
+            Ok(()) => info!(%deployment_id, "unscheduled deleted deployment"),
+            Err(e) => error!(%deployment_id, error = %e, "failed to unschedule deleted deployment"),
+        }
+    }
+
     /// Check if a deployment is currently scheduled.
     pub async fn is_scheduled(&self, deployment_id: &str) -> bool {
         let slots = self.slots.read().await;
@@ -304,9 +533,12 @@ impl Scheduler {
             ));
         }
 
+        // Cordoned nodes and nodes reporting memory pressure are skipped
+        // like draining ones.
+        let now = epoch_secs();
         let node_resources: Vec<_> = nodes
             .iter()
-            .map(|n| node_info_to_resources(n, false))
+            .map(|n| node_info_to_resources(n, !n.is_schedulable(now)))
             .collect();
 
         let requirements = deployment_to_requirements(&spec, spec.instances.min);
@@ -332,6 +564,10 @@ impl Scheduler {
             min_instances: spec.instances.min,
             max_instances: spec.instances.max,
             memory_limit: spec.resources.memory_bytes as usize,
+            overcommit: self.overcommit,
+            lifecycle: self.lifecycle,
+            flags: self.runtime.engine().flags_host(&spec.id),
+            config: Some(self.runtime.engine().bundles().handle(&spec.id)),
         }
     }
 
@@ -339,7 +575,6 @@ impl Scheduler {
     async fn sync_instance_states(
         &self,
         deployment_id: &str,
-        spec: &DeploymentSpec,
         pool: &InstancePool,
     ) -> SchedulerResult<()> {
         // Remove existing instance records for this deployment.
@@ -348,6 +583,7 @@ impl Scheduler {
         // Write new records for current instance count.
         let now = epoch_secs();
         let total = pool.total_count().await;
+        let reservation = pool.memory_reservation() as u64;
         for i in 0..total {
             let instance_state = InstanceState {
                 id: format!("inst-{i}"),
@@ -356,7 +592,7 @@ impl Scheduler {
                 status: InstanceStatus::Running,
                 health: HealthStatus::Unknown,
                 restart_count: 0,
-                memory_bytes: spec.resources.memory_bytes,
+                memory_bytes: reservation,
                 started_at: now,
                 updated_at: now,
             };
@@ -367,6 +603,21 @@ impl Scheduler {
     }
 }
 
+/// Sort key for eviction: least important deployments first, then the
+/// ones reserving the most memory.
+fn eviction_rank(spec: &DeploymentSpec, reserved: u64) -> (Reverse<u32>, Reverse<u64>) {
+    (Reverse(spec.effective_priority()), Reverse(reserved))
+}
+
+/// Instances of `spec` an eviction at `level` must leave running.
+fn eviction_floor(spec: &DeploymentSpec, level: PressureLevel) -> u32 {
+    let budget = spec.min_available.unwrap_or(0);
+    match level {
+        PressureLevel::Hard => budget,
+        _ => spec.instances.min.max(budget),
+    }
+}
+
 /// Current Unix epoch in seconds.
 fn epoch_secs() -> u64 {
     SystemTime::now()
@@ -390,11 +641,12 @@ mod tests {
             namespace: namespace.to_string(),
             name: name.to_string(),
             source: "file://./test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: None,
@@ -402,6 +654,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -494,6 +752,87 @@ mod tests {
         assert_eq!(config.min_instances, 2);
         assert_eq!(config.max_instances, 20);
         assert_eq!(config.memory_limit, 128 * 1024 * 1024);
+        assert_eq!(config.overcommit, OvercommitPolicy::Reserve);
+    }
+
+    #[tokio::test]
+    async fn overcommit_policy_flows_into_pool_config() {
+        let runtime = Arc::new(Runtime::new(ShimConfig::default()).unwrap());
+        let policy = OvercommitPolicy::HighWater {
+            headroom_percent: 20,
+        };
+        let scheduler = Scheduler::new(runtime, test_state(), "node-1".to_string())
+            .with_overcommit(policy);
+        let config = scheduler.build_pool_config(&test_deployment("default", "api"));
+        assert_eq!(config.overcommit, policy);
+
+        // Nothing scheduled: nothing reserved, nothing to shed.
+        assert_eq!(scheduler.reserved_memory_bytes().await, 0);
+        let evictions = scheduler
+            .relieve_memory_pressure(1 << 20, PressureLevel::Hard)
+            .await
+            .unwrap();
+        assert!(evictions.is_empty());
+    }
+
+    #[test]
+    fn lifecycle_budgets_flow_into_pool_config() {
+        let runtime = Arc::new(Runtime::new(ShimConfig::default()).unwrap());
+        let budgets = LifecycleBudgets {
+            on_start: Duration::from_secs(2),
+            on_shutdown: Duration::from_millis(500),
+        };
+        let scheduler = Scheduler::new(runtime, test_state(), "node-1".to_string())
+            .with_lifecycle_budgets(budgets);
+        let config = scheduler.build_pool_config(&test_deployment("default", "api"));
+        assert_eq!(config.lifecycle, budgets);
+    }
+
+    #[test]
+    fn eviction_prefers_low_priority_and_respects_budgets() {
+        let mut batch = test_deployment("default", "batch");
+        batch.priority = Some(50);
+        batch.instances.min = 2;
+        let mut api = test_deployment("default", "api");
+        api.instances.min = 3;
+        api.min_available = Some(1);
+
+        // Lowest priority first, whatever it reserves; ties go to the
+        // bigger reservation.
+        let big_api = eviction_rank(&api, 1 << 30);
+        assert!(eviction_rank(&batch, 1) < big_api);
+        assert!(big_api < eviction_rank(&api, 1));
+
+        // Soft pressure keeps the warm minimum, hard pressure only the budget.
+        assert_eq!(eviction_floor(&api, PressureLevel::Soft), 3);
+        assert_eq!(eviction_floor(&api, PressureLevel::Hard), 1);
+        assert_eq!(eviction_floor(&batch, PressureLevel::Hard), 0);
+        api.min_available = Some(5);
+        assert_eq!(eviction_floor(&api, PressureLevel::Soft), 5);
+    }
+
+    #[test]
+    fn distributed_placement_avoids_pressured_and_cordoned_nodes() {
+        let runtime = Arc::new(Runtime::new(ShimConfig::default()).unwrap());
+        let state = test_state();
+        let spec = test_deployment("default", "api");
+        state.put_deployment(&spec).unwrap();
+        let mut pressured = test_node("node-1", 8 << 30, 0);
+        pressured.pressure_until = Some(epoch_secs() + 60);
+        state.put_node(&pressured).unwrap();
+        let mut recovered = test_node("node-2", 8 << 30, 0);
+        recovered.pressure_until = Some(1000);
+        state.put_node(&recovered).unwrap();
+        // Emptier than node-2, so it would win if cordoning were ignored.
+        let mut cordoned = test_node("node-3", 16 << 30, 0);
+        cordoned.cordoned = true;
+        state.put_node(&cordoned).unwrap();
+
+        let scheduler = Scheduler::new_distributed(runtime, state, "cp".to_string());
+        let plan = scheduler.compute_distributed_placement(&spec.id).unwrap();
+        assert!(!plan.assignments.contains_key("node-1"));
+        assert!(!plan.assignments.contains_key("node-3"));
+        assert_eq!(plan.assignments.get("node-2"), Some(&1));
     }
 
     #[tokio::test]
@@ -548,6 +887,10 @@ mod tests {
             used_cpu_weight: 0,
             labels: HashMap::new(),
             last_heartbeat: 1700000000,
+            pressure_until: None,
+            replica_revision: None,
+            calibrated_cpu_weight: None,
+            cordoned: false,
         }
     }
 
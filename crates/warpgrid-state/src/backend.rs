@@ -0,0 +1,73 @@
+//! Storage backends for the state store.
+//!
+//! `StateStore` owns all typed domain logic (keys, JSON encoding, rollups);
+//! a backend only provides an ordered key/value surface over named tables.
+//! This keeps new backends small and guarantees every backend stores the
+//! same bytes under the same keys.
+//!
+//! - [`embedded::RedbBackend`] — node-local redb file (default)
+//! - [`postgres::PostgresBackend`] — external managed Postgres
+//!   (requires the `postgres` feature)
+
+pub mod embedded;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+use crate::error::StateResult;
+
+/// A `(key, value)` pair returned by table scans.
+pub type KvEntry = (String, Vec<u8>);
+
+/// Ordered key/value storage over named tables.
+///
+/// Keys are compared bytewise, so composite keys such as
+/// `{deployment_id}:{epoch}` scan in the same order on every backend.
+pub trait StateBackend: Send + Sync {
+    /// Human-readable backend name for logging.
+    fn name(&self) -> &'static str;
+
+    /// Read a single value.
+    fn get(&self, table: &str, key: &str) -> StateResult<Option<Vec<u8>>>;
+
+    /// Insert or overwrite a value.
+    fn put(&self, table: &str, key: &str, value: &[u8]) -> StateResult<()>;
+
+    /// Remove a value. Returns true if it existed.
+    fn remove(&self, table: &str, key: &str) -> StateResult<bool>;
+
+    /// All entries whose key starts with `prefix`, in key order.
+    fn scan_prefix(&self, table: &str, prefix: &str) -> StateResult<Vec<KvEntry>>;
+
+    /// Up to `limit` entries with a key `>= start`, in key order.
+    fn scan_from(&self, table: &str, start: &str, limit: usize) -> StateResult<Vec<KvEntry>>;
+
+    /// The greatest key in the table, if any.
+    fn last_key(&self, table: &str) -> StateResult<Option<String>>;
+
+    /// Run `f` atomically: its writes commit together, or not at all if it
+    /// returns an error. A backend may run `f` again after a conflict with
+    /// a concurrent transaction, so it must not carry state between runs.
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut dyn BackendTxn) -> StateResult<()>,
+    ) -> StateResult<()>;
+}
+
+/// Operations available inside [`StateBackend::transaction`].
+pub trait BackendTxn {
+    /// Read a single value, observing earlier writes in this transaction.
+    fn get(&mut self, table: &str, key: &str) -> StateResult<Option<Vec<u8>>>;
+
+    /// Insert or overwrite a value.
+    fn put(&mut self, table: &str, key: &str, value: &[u8]) -> StateResult<()>;
+
+    /// Remove a value. Returns true if it existed.
+    fn remove(&mut self, table: &str, key: &str) -> StateResult<bool>;
+
+    /// All entries whose key starts with `prefix`, in key order, observing
+    /// earlier writes in this transaction.
+    fn scan_prefix(&mut self, table: &str, prefix: &str) -> StateResult<Vec<KvEntry>>;
+
+    /// The greatest key in the table, if any.
+    fn last_key(&mut self, table: &str) -> StateResult<Option<String>>;
+}
@@ -0,0 +1,182 @@
+//! Embedded redb backend — the default, node-local state store.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition, WriteTransaction};
+
+use super::{BackendTxn, KvEntry, StateBackend};
+use crate::error::{StateError, StateResult};
+use crate::tables::ALL_TABLES;
+
+/// Convert any `Display` error into a `StateError` variant via a closure factory.
+macro_rules! map_err {
+    ($variant:ident) => {
+        |e| StateError::$variant(e.to_string())
+    };
+}
+
+fn table(name: &str) -> TableDefinition<'_, &'static str, &'static [u8]> {
+    TableDefinition::new(name)
+}
+
+/// State backend stored in a redb database file (or in memory for tests).
+pub struct RedbBackend {
+    db: Arc<Database>,
+}
+
+impl RedbBackend {
+    /// Open (or create) a redb database at the given path.
+    pub fn open(path: &Path) -> StateResult<Self> {
+        let db = Database::create(path).map_err(map_err!(Open))?;
+        let backend = Self { db: Arc::new(db) };
+        backend.ensure_tables()?;
+        Ok(backend)
+    }
+
+    /// Create an ephemeral in-memory database.
+    pub fn open_in_memory() -> StateResult<Self> {
+        let db = Database::builder()
+            .create_with_backend(redb::backends::InMemoryBackend::new())
+            .map_err(map_err!(Open))?;
+        let backend = Self { db: Arc::new(db) };
+        backend.ensure_tables()?;
+        Ok(backend)
+    }
+
+    /// Create all tables if they don't exist yet, so read transactions
+    /// never hit a missing table.
+    fn ensure_tables(&self) -> StateResult<()> {
+        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
+        for name in ALL_TABLES {
+            // Opening a table in a write transaction creates it if absent.
+            txn.open_table(table(name)).map_err(map_err!(Table))?;
+        }
+        txn.commit().map_err(map_err!(Transaction))?;
+        Ok(())
+    }
+
+    fn scan(
+        &self,
+        name: &str,
+        start: &str,
+        mut keep: impl FnMut(&str, usize) -> Option<bool>,
+    ) -> StateResult<Vec<KvEntry>> {
+        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
+        let tbl = txn.open_table(table(name)).map_err(map_err!(Table))?;
+        let mut results = Vec::new();
+        for entry in tbl.range(start..).map_err(map_err!(Read))? {
+            let (key, value) = entry.map_err(map_err!(Read))?;
+            // `None` stops the scan; `Some(false)` skips the entry.
+            match keep(key.value(), results.len()) {
+                None => break,
+                Some(false) => continue,
+                Some(true) => results.push((key.value().to_string(), value.value().to_vec())),
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl StateBackend for RedbBackend {
+    fn name(&self) -> &'static str {
+        "redb"
+    }
+
+    fn get(&self, name: &str, key: &str) -> StateResult<Option<Vec<u8>>> {
+        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
+        let tbl = txn.open_table(table(name)).map_err(map_err!(Table))?;
+        Ok(tbl
+            .get(key)
+            .map_err(map_err!(Read))?
+            .map(|guard| guard.value().to_vec()))
+    }
+
+    fn put(&self, name: &str, key: &str, value: &[u8]) -> StateResult<()> {
+        self.transaction(&mut |txn| txn.put(name, key, value))
+    }
+
+    fn remove(&self, name: &str, key: &str) -> StateResult<bool> {
+        let mut existed = false;
+        self.transaction(&mut |txn| {
+            existed = txn.remove(name, key)?;
+            Ok(())
+        })?;
+        Ok(existed)
+    }
+
+    fn scan_prefix(&self, name: &str, prefix: &str) -> StateResult<Vec<KvEntry>> {
+        self.scan(name, prefix, |key, _| key.starts_with(prefix).then_some(true))
+    }
+
+    fn scan_from(&self, name: &str, start: &str, limit: usize) -> StateResult<Vec<KvEntry>> {
+        self.scan(name, start, |_, len| (len < limit).then_some(true))
+    }
+
+    fn last_key(&self, name: &str) -> StateResult<Option<String>> {
+        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
+        let tbl = txn.open_table(table(name)).map_err(map_err!(Table))?;
+        Ok(tbl
+            .last()
+            .map_err(map_err!(Read))?
+            .map(|(key, _)| key.value().to_string()))
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut dyn BackendTxn) -> StateResult<()>,
+    ) -> StateResult<()> {
+        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
+        let mut wrapper = RedbTxn { txn };
+        // Dropping an uncommitted write transaction aborts it.
+        f(&mut wrapper)?;
+        wrapper.txn.commit().map_err(map_err!(Transaction))?;
+        Ok(())
+    }
+}
+
+struct RedbTxn {
+    txn: WriteTransaction,
+}
+
+impl BackendTxn for RedbTxn {
+    fn get(&mut self, name: &str, key: &str) -> StateResult<Option<Vec<u8>>> {
+        let tbl = self.txn.open_table(table(name)).map_err(map_err!(Table))?;
+        Ok(tbl
+            .get(key)
+            .map_err(map_err!(Read))?
+            .map(|guard| guard.value().to_vec()))
+    }
+
+    fn put(&mut self, name: &str, key: &str, value: &[u8]) -> StateResult<()> {
+        let mut tbl = self.txn.open_table(table(name)).map_err(map_err!(Table))?;
+        tbl.insert(key, value).map_err(map_err!(Write))?;
+        Ok(())
+    }
+
+    fn remove(&mut self, name: &str, key: &str) -> StateResult<bool> {
+        let mut tbl = self.txn.open_table(table(name)).map_err(map_err!(Table))?;
+        Ok(tbl.remove(key).map_err(map_err!(Write))?.is_some())
+    }
+
+    fn scan_prefix(&mut self, name: &str, prefix: &str) -> StateResult<Vec<KvEntry>> {
+        let tbl = self.txn.open_table(table(name)).map_err(map_err!(Table))?;
+        let mut results = Vec::new();
+        for entry in tbl.range(prefix..).map_err(map_err!(Read))? {
+            let (key, value) = entry.map_err(map_err!(Read))?;
+            if !key.value().starts_with(prefix) {
+                break;
+            }
+            results.push((key.value().to_string(), value.value().to_vec()));
+        }
+        Ok(results)
+    }
+
+    fn last_key(&mut self, name: &str) -> StateResult<Option<String>> {
+        let tbl = self.txn.open_table(table(name)).map_err(map_err!(Table))?;
+        Ok(tbl
+            .last()
+            .map_err(map_err!(Read))?
+            .map(|(key, _)| key.value().to_string()))
+    }
+}
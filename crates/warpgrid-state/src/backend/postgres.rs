@@ -0,0 +1,466 @@
+//! External Postgres backend for control planes that keep cluster state in
+//! a managed database instead of node-local redb.
+//!
+//! All tables share a single relation keyed by `(tbl, key)`:
+//!
+//! ```sql
+//! CREATE TABLE warpgrid_state (
+//!     tbl   TEXT  NOT NULL,
+//!     key   TEXT  COLLATE "C" NOT NULL,
+//!     value BYTEA NOT NULL,
+//!     PRIMARY KEY (tbl, key)
+//! );
+//! ```
+//!
+//! Keys use the `"C"` collation so scans, and the primary key index
+//! serving them, match redb's bytewise ordering. Connections use TLS (rustls) when the URL asks for it, e.g.
+//! with `sslmode=require` or `sslmode=verify-full`.
+//!
+//! `StateStore` is synchronous, so queries run on a small private tokio
+//! runtime owned by the backend and the caller blocks on the result, moving
+//! off a multi-threaded caller's worker while it waits. Transactions run at
+//! `SERIALIZABLE` isolation, so concurrent writers on any control-plane node
+//! (e.g. allocating usage sequences) cannot interleave; one that conflicts
+//! is rolled back and retried.
+
+use std::future::Future;
+
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::{Postgres, Row, Transaction};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use super::{BackendTxn, KvEntry, StateBackend};
+use crate::error::{StateError, StateResult};
+
+/// Convert any `Display` error into a `StateError` variant via a closure factory.
+macro_rules! map_err {
+    ($variant:ident) => {
+        |e| StateError::$variant(e.to_string())
+    };
+}
+
+/// Maximum pooled connections to Postgres.
+const MAX_CONNECTIONS: u32 = 8;
+
+/// Attempts at a transaction that keeps conflicting with concurrent ones.
+const MAX_TXN_ATTEMPTS: u32 = 5;
+
+/// SQLSTATEs of a serialization failure and a deadlock, which a retry of
+/// the whole transaction resolves.
+const CONFLICT_CODES: [&str; 2] = ["40001", "40P01"];
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS warpgrid_state (
+    tbl   TEXT  NOT NULL,
+    key   TEXT  COLLATE \"C\" NOT NULL,
+    value BYTEA NOT NULL,
+    PRIMARY KEY (tbl, key)
+)";
+
+/// Moves a table created with the database's default collation to `"C"`.
+const COLLATE_KEYS: &str = "DO $$ BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'warpgrid_state'
+          AND column_name = 'key' AND collation_name IS DISTINCT FROM 'C'
+    ) THEN
+        ALTER TABLE warpgrid_state ALTER COLUMN key TYPE TEXT COLLATE \"C\";
+    END IF;
+END $$";
+
+/// State backend stored in an external Postgres database.
+pub struct PostgresBackend {
+    pool: PgPool,
+    /// Private runtime driving sqlx; `None` only while dropping.
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl PostgresBackend {
+    /// Connect to Postgres and create the state table if needed.
+    pub fn connect(url: &str) -> StateResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("warpgrid-state-pg")
+            .enable_all()
+            .build()
+            .map_err(map_err!(Open))?;
+
+        let url = url.to_string();
+        let pool = block_on(&runtime, async move {
+            let pool = PgPoolOptions::new()
+                .max_connections(MAX_CONNECTIONS)
+                .connect(&url)
+                .await
+                .map_err(map_err!(Open))?;
+            for statement in [SCHEMA, COLLATE_KEYS] {
+                sqlx::query(statement)
+                    .execute(&pool)
+                    .await
+                    .map_err(map_err!(Table))?;
+            }
+            Ok(pool)
+        })?;
+
+        Ok(Self {
+            pool,
+            runtime: Some(runtime),
+        })
+    }
+
+    fn run<T, F>(&self, fut: F) -> StateResult<T>
+    where
+        T: Send + 'static,
+        F: Future<Output = StateResult<T>> + Send + 'static,
+    {
+        block_on(self.runtime.as_ref().expect("runtime present"), fut)
+    }
+
+    /// Run `f` once in a `SERIALIZABLE` transaction, returning its result
+    /// and whether it failed on a conflict with a concurrent transaction.
+    fn attempt(&self, f: &mut dyn FnMut(&mut dyn BackendTxn) -> StateResult<()>) -> (StateResult<()>, bool) {
+        let pool = self.pool.clone();
+        let txn = self.run(async move {
+            let mut txn = pool.begin().await.map_err(map_err!(Transaction))?;
+            sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+                .execute(&mut *txn)
+                .await
+                .map_err(map_err!(Transaction))?;
+            Ok(txn)
+        });
+        let txn = match txn {
+            Ok(txn) => txn,
+            Err(e) => return (Err(e), false),
+        };
+
+        let mut wrapper = PgTxn {
+            backend: self,
+            txn: Some(txn),
+            conflict: false,
+        };
+        let result = f(&mut wrapper);
+        let txn = wrapper.txn.take().expect("transaction present");
+
+        match result {
+            Ok(()) => match self.run(async move { Ok(txn.commit().await) }) {
+                Ok(Ok(())) => (Ok(()), false),
+                Ok(Err(e)) => (Err(StateError::Transaction(e.to_string())), is_conflict(&e)),
+                Err(e) => (Err(e), false),
+            },
+            Err(e) => {
+                let rolled_back = self.run(async move { txn.rollback().await.map_err(map_err!(Transaction)) });
+                (rolled_back.and(Err(e)), wrapper.conflict)
+            }
+        }
+    }
+}
+
+impl Drop for PostgresBackend {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics inside an async context.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Run `fut` on `runtime` and block the current thread until it completes.
+///
+/// Uses a std channel rather than `Runtime::block_on` so it is safe to call
+/// from within another tokio runtime. On a multi-threaded one, the wait
+/// moves off the worker with `block_in_place`; a current-thread runtime has
+/// no other worker to hand its tasks to, so it blocks.
+fn block_on<T, F>(runtime: &tokio::runtime::Runtime, fut: F) -> StateResult<T>
+where
+    T: Send + 'static,
+    F: Future<Output = StateResult<T>> + Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel();
+    runtime.spawn(async move {
+        let _ = tx.send(fut.await);
+    });
+    let wait = move || {
+        rx.recv()
+            .map_err(|_| StateError::Transaction("postgres task aborted".to_string()))?
+    };
+    match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(wait),
+        _ => wait(),
+    }
+}
+
+/// Whether `err` is a conflict with a concurrent transaction.
+fn is_conflict(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|err| err.code())
+        .is_some_and(|code| CONFLICT_CODES.contains(&code.as_ref()))
+}
+
+fn entries(rows: Vec<sqlx::postgres::PgRow>) -> StateResult<Vec<KvEntry>> {
+    rows.into_iter()
+        .map(|row| {
+            let key: String = row.try_get("key").map_err(map_err!(Read))?;
+            let value: Vec<u8> = row.try_get("value").map_err(map_err!(Read))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+impl StateBackend for PostgresBackend {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn get(&self, table: &str, key: &str) -> StateResult<Option<Vec<u8>>> {
+        let (pool, table, key) = (self.pool.clone(), table.to_string(), key.to_string());
+        self.run(async move {
+            sqlx::query_scalar("SELECT value FROM warpgrid_state WHERE tbl = $1 AND key = $2")
+                .bind(table)
+                .bind(key)
+                .fetch_optional(&pool)
+                .await
+                .map_err(map_err!(Read))
+        })
+    }
+
+    fn put(&self, table: &str, key: &str, value: &[u8]) -> StateResult<()> {
+        let (pool, table, key, value) = (
+            self.pool.clone(),
+            table.to_string(),
+            key.to_string(),
+            value.to_vec(),
+        );
+        self.run(async move {
+            sqlx::query(
+                "INSERT INTO warpgrid_state (tbl, key, value) VALUES ($1, $2, $3)
+                 ON CONFLICT (tbl, key) DO UPDATE SET value = EXCLUDED.value",
+            )
+            .bind(table)
+            .bind(key)
+            .bind(value)
+            .execute(&pool)
+            .await
+            .map_err(map_err!(Write))?;
+            Ok(())
+        })
+    }
+
+    fn remove(&self, table: &str, key: &str) -> StateResult<bool> {
+        let (pool, table, key) = (self.pool.clone(), table.to_string(), key.to_string());
+        self.run(async move {
+            let result = sqlx::query("DELETE FROM warpgrid_state WHERE tbl = $1 AND key = $2")
+                .bind(table)
+                .bind(key)
+                .execute(&pool)
+                .await
+                .map_err(map_err!(Write))?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn scan_prefix(&self, table: &str, prefix: &str) -> StateResult<Vec<KvEntry>> {
+        let (pool, table, prefix) = (self.pool.clone(), table.to_string(), prefix.to_string());
+        self.run(async move {
+            let rows = sqlx::query(
+                "SELECT key, value FROM warpgrid_state
+                 WHERE tbl = $1 AND starts_with(key, $2)
+                 ORDER BY key",
+            )
+            .bind(table)
+            .bind(prefix)
+            .fetch_all(&pool)
+            .await
+            .map_err(map_err!(Read))?;
+            entries(rows)
+        })
+    }
+
+    fn scan_from(&self, table: &str, start: &str, limit: usize) -> StateResult<Vec<KvEntry>> {
+        let (pool, table, start) = (self.pool.clone(), table.to_string(), start.to_string());
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        self.run(async move {
+            let rows = sqlx::query(
+                "SELECT key, value FROM warpgrid_state
+                 WHERE tbl = $1 AND key >= $2
+                 ORDER BY key
+                 LIMIT $3",
+            )
+            .bind(table)
+            .bind(start)
+            .bind(limit)
+            .fetch_all(&pool)
+            .await
+            .map_err(map_err!(Read))?;
+            entries(rows)
+        })
+    }
+
+    fn last_key(&self, table: &str) -> StateResult<Option<String>> {
+        let (pool, table) = (self.pool.clone(), table.to_string());
+        self.run(async move {
+            sqlx::query_scalar(
+                "SELECT key FROM warpgrid_state WHERE tbl = $1
+                 ORDER BY key DESC LIMIT 1",
+            )
+            .bind(table)
+            .fetch_optional(&pool)
+            .await
+            .map_err(map_err!(Read))
+        })
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut dyn BackendTxn) -> StateResult<()>,
+    ) -> StateResult<()> {
+        let mut attempt = 1;
+        loop {
+            let (result, conflict) = self.attempt(f);
+            if !conflict || attempt == MAX_TXN_ATTEMPTS {
+                return result;
+            }
+            tracing::debug!(attempt, "state transaction conflicted; retrying");
+            attempt += 1;
+        }
+    }
+}
+
+/// An open Postgres transaction, moved into each query future and back.
+struct PgTxn<'a> {
+    backend: &'a PostgresBackend,
+    txn: Option<Transaction<'static, Postgres>>,
+    /// A query failed on a conflict with a concurrent transaction.
+    conflict: bool,
+}
+
+impl PgTxn<'_> {
+    /// Run a query on the transaction, reporting its error as `kind`.
+    fn exec<T, F, Fut>(&mut self, kind: fn(String) -> StateError, f: F) -> StateResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Transaction<'static, Postgres>) -> Fut,
+        Fut: Future<Output = (Transaction<'static, Postgres>, Result<T, sqlx::Error>)> + Send + 'static,
+    {
+        let txn = self.txn.take().expect("transaction present");
+        let fut = f(txn);
+        let (txn, result) = self.backend.run(async move { Ok(fut.await) })?;
+        self.txn = Some(txn);
+        result.map_err(|e| {
+            self.conflict |= is_conflict(&e);
+            kind(e.to_string())
+        })
+    }
+}
+
+impl BackendTxn for PgTxn<'_> {
+    fn get(&mut self, table: &str, key: &str) -> StateResult<Option<Vec<u8>>> {
+        let (table, key) = (table.to_string(), key.to_string());
+        self.exec(StateError::Read, |mut txn| async move {
+            let result =
+                sqlx::query_scalar("SELECT value FROM warpgrid_state WHERE tbl = $1 AND key = $2")
+                    .bind(table)
+                    .bind(key)
+                    .fetch_optional(&mut *txn)
+                    .await;
+            (txn, result)
+        })
+    }
+
+    fn put(&mut self, table: &str, key: &str, value: &[u8]) -> StateResult<()> {
+        let (table, key, value) = (table.to_string(), key.to_string(), value.to_vec());
+        self.exec(StateError::Write, |mut txn| async move {
+            let result = sqlx::query(
+                "INSERT INTO warpgrid_state (tbl, key, value) VALUES ($1, $2, $3)
+                 ON CONFLICT (tbl, key) DO UPDATE SET value = EXCLUDED.value",
+            )
+            .bind(table)
+            .bind(key)
+            .bind(value)
+            .execute(&mut *txn)
+            .await
+            .map(|_| ());
+            (txn, result)
+        })
+    }
+
+    fn remove(&mut self, table: &str, key: &str) -> StateResult<bool> {
+        let (table, key) = (table.to_string(), key.to_string());
+        self.exec(StateError::Write, |mut txn| async move {
+            let result = sqlx::query("DELETE FROM warpgrid_state WHERE tbl = $1 AND key = $2")
+                .bind(table)
+                .bind(key)
+                .execute(&mut *txn)
+                .await
+                .map(|r| r.rows_affected() > 0);
+            (txn, result)
+        })
+    }
+
+    fn scan_prefix(&mut self, table: &str, prefix: &str) -> StateResult<Vec<KvEntry>> {
+        let (table, prefix) = (table.to_string(), prefix.to_string());
+        let rows = self.exec(StateError::Read, |mut txn| async move {
+            let result = sqlx::query(
+                "SELECT key, value FROM warpgrid_state
+                 WHERE tbl = $1 AND starts_with(key, $2)
+                 ORDER BY key",
+            )
+            .bind(table)
+            .bind(prefix)
+            .fetch_all(&mut *txn)
+            .await;
+            (txn, result)
+        })?;
+        entries(rows)
+    }
+
+    fn last_key(&mut self, table: &str) -> StateResult<Option<String>> {
+        let table = table.to_string();
+        self.exec(StateError::Read, |mut txn| async move {
+            let result = sqlx::query_scalar(
+                "SELECT key FROM warpgrid_state WHERE tbl = $1
+                 ORDER BY key DESC LIMIT 1",
+            )
+            .bind(table)
+            .fetch_optional(&mut *txn)
+            .await;
+            (txn, result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Requires a reachable Postgres; set `WARPGRID_TEST_POSTGRES_URL` and run
+    /// with `cargo test -p warpgrid-state --features postgres -- --ignored`.
+    #[test]
+    #[ignore]
+    fn postgres_backend_round_trip() {
+        let Ok(url) = std::env::var("WARPGRID_TEST_POSTGRES_URL") else {
+            eprintln!("WARPGRID_TEST_POSTGRES_URL not set; skipping");
+            return;
+        };
+        let backend = PostgresBackend::connect(&url).unwrap();
+        let table = "test_round_trip";
+
+        backend.put(table, "a:1", b"one").unwrap();
+        backend.put(table, "a:2", b"two").unwrap();
+        backend.put(table, "b:1", b"other").unwrap();
+
+        assert_eq!(backend.get(table, "a:1").unwrap(), Some(b"one".to_vec()));
+        assert_eq!(backend.scan_prefix(table, "a:").unwrap().len(), 2);
+        assert_eq!(backend.scan_from(table, "a:2", 10).unwrap().len(), 2);
+
+        backend
+            .transaction(&mut |txn| {
+                assert_eq!(txn.last_key(table)?, Some("b:1".to_string()));
+                txn.remove(table, "b:1")?;
+                Ok(())
+            })
+            .unwrap();
+        assert!(backend.get(table, "b:1").unwrap().is_none());
+
+        for key in ["a:1", "a:2"] {
+            backend.remove(table, key).unwrap();
+        }
+    }
+}
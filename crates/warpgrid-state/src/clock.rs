@@ -0,0 +1,154 @@
+//! Time source for the background controllers.
+//!
+//! The autoscaler, health monitor, and rollout controller read the time and
+//! sleep through a [`Clock`] instead of calling the system clock directly.
+//! Production code uses [`SystemClock`]; tests inject a [`ManualClock`] and
+//! advance it explicitly, so cooldowns, probe backoff, and batch intervals
+//! elapse instantly and deterministically.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::watch;
+
+/// Future returned by [`Clock::sleep`].
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A clock shared between a controller and its background tasks.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// A source of time that controllers can sleep on.
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Monotonic time, for measuring intervals.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time in seconds since the Unix epoch, for timestamps
+    /// written to the state store.
+    fn epoch_secs(&self) -> u64;
+
+    /// Complete once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// The real clock, backed by the OS and the tokio timer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// A [`SharedClock`] reading the real time.
+    pub fn shared() -> SharedClock {
+        Arc::new(Self)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn epoch_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock that only moves when [`advance`](ManualClock::advance) is called.
+///
+/// Sleeps complete as soon as the clock has been advanced past their
+/// deadline; tasks woken this way still need a chance to run, e.g. via
+/// `tokio::task::yield_now`.
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    start_epoch: u64,
+    elapsed: watch::Sender<Duration>,
+}
+
+impl ManualClock {
+    /// A clock frozen at `start_epoch` seconds since the Unix epoch.
+    pub fn new(start_epoch: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            start_epoch,
+            elapsed: watch::Sender::new(Duration::ZERO),
+        }
+    }
+
+    /// Move the clock forward, waking every sleep whose deadline has passed.
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    /// Time advanced since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(1_000_000)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn epoch_secs(&self) -> u64 {
+        self.start_epoch + self.elapsed().as_secs()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut elapsed = self.elapsed.subscribe();
+        let deadline = *elapsed.borrow() + duration;
+        Box::pin(async move {
+            // A dropped clock never advances again.
+            if elapsed.wait_for(|now| *now >= deadline).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn manual_clock_wakes_sleeps_when_advanced() {
+        let clock = Arc::new(ManualClock::new(100));
+        let start = clock.now();
+
+        let sleeper = {
+            let clock = clock.clone();
+            tokio::spawn(async move { clock.sleep(Duration::from_secs(30)).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(29));
+        tokio::task::yield_now().await;
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        sleeper.await.unwrap();
+        assert_eq!(clock.now() - start, Duration::from_secs(30));
+        assert_eq!(clock.epoch_secs(), 130);
+    }
+
+    #[tokio::test]
+    async fn zero_sleep_completes_immediately() {
+        let clock = ManualClock::default();
+        clock.sleep(Duration::ZERO).await;
+    }
+}
@@ -31,4 +31,13 @@ pub enum StateError {
 
     #[error("not found: {0}")]
     NotFound(String),
+
+    #[error("secret encryption error: {0}")]
+    Encryption(String),
+
+    #[error("watcher lagged: {0} changes missed")]
+    WatchLagged(u64),
+
+    #[error("schema error: {0}")]
+    Schema(String),
 }
@@ -0,0 +1,217 @@
+//! Record integrity — CRC-sealed values and corruption quarantine.
+//!
+//! Every value the store writes is sealed as
+//! `[SEAL_MARKER][crc32 of payload, LE][JSON payload]`. Reads verify the
+//! checksum and decode the payload; a record failing either check is moved
+//! to the [`QUARANTINE`] table and treated as absent, so a single bad
+//! record cannot fail a list call or a node's startup.
+//!
+//! A record that verifies but does not match the type being read — a
+//! record written by a newer release, say — is not damaged. It stays in
+//! place and the read fails with [`StateError::Deserialize`].
+//!
+//! Values written before sealing was introduced are plain JSON. They are
+//! still accepted, since no JSON document starts with [`SEAL_MARKER`].
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::warn;
+
+use crate::backend::BackendTxn;
+use crate::error::{StateError, StateResult};
+use crate::tables::QUARANTINE;
+use crate::types::{IntegrityReport, QuarantinedRecord};
+
+/// First byte of a sealed value. `0xC1` never occurs in UTF-8, so it
+/// cannot be confused with the start of a legacy JSON value.
+const SEAL_MARKER: u8 = 0xC1;
+
+/// Marker byte plus the 4-byte checksum.
+const HEADER_LEN: usize = 5;
+
+/// Serialize a value to JSON and seal it.
+pub(crate) fn encode<T: Serialize + ?Sized>(value: &T) -> StateResult<Vec<u8>> {
+    let json = serde_json::to_vec(value).map_err(|e| StateError::Serialize(e.to_string()))?;
+    Ok(seal(&json))
+}
+
+/// Prefix a JSON payload with the seal marker and its CRC32.
+pub(crate) fn seal(json: &[u8]) -> Vec<u8> {
+    let mut sealed = Vec::with_capacity(HEADER_LEN + json.len());
+    sealed.push(SEAL_MARKER);
+    sealed.extend_from_slice(&crc32fast::hash(json).to_le_bytes());
+    sealed.extend_from_slice(json);
+    sealed
+}
+
+/// Verify a stored value and return its JSON payload.
+///
+/// Returns the corruption reason if the checksum does not match.
+pub(crate) fn unseal(bytes: &[u8]) -> Result<&[u8], String> {
+    if bytes.first() != Some(&SEAL_MARKER) {
+        return Ok(bytes);
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(format!("truncated header ({} bytes)", bytes.len()));
+    }
+    let expected = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+    let payload = &bytes[HEADER_LEN..];
+    let actual = crc32fast::hash(payload);
+    if actual != expected {
+        return Err(format!(
+            "checksum mismatch (stored {expected:08x}, computed {actual:08x})"
+        ));
+    }
+    Ok(payload)
+}
+
+/// Why a stored value could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DecodeError {
+    /// Checksum mismatch or invalid JSON: the record is damaged and
+    /// should be quarantined.
+    Corrupt(String),
+    /// Valid JSON that does not match the type being read.
+    Mismatch(String),
+}
+
+impl DecodeError {
+    /// The reason to quarantine `table/key`, or the read error if the
+    /// record is intact but of the wrong shape.
+    pub(crate) fn corruption_reason(self, table: &str, key: &str) -> StateResult<String> {
+        match self {
+            Self::Corrupt(reason) => Ok(reason),
+            Self::Mismatch(reason) => Err(StateError::Deserialize(format!("{table}/{key}: {reason}"))),
+        }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Corrupt(reason) | Self::Mismatch(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Verify and deserialize a stored value.
+pub(crate) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let payload = unseal(bytes).map_err(DecodeError::Corrupt)?;
+    serde_json::from_slice(payload).map_err(|e| {
+        // A type error can surface before a syntax error later in the
+        // payload, so only well-formed JSON counts as a mismatch.
+        match serde_json::from_slice::<serde::de::IgnoredAny>(payload) {
+            Ok(_) => DecodeError::Mismatch(format!("unexpected shape: {e}")),
+            Err(_) => DecodeError::Corrupt(format!("invalid JSON: {e}")),
+        }
+    })
+}
+
+/// Whether a stored value predates sealing.
+pub(crate) fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.first() == Some(&SEAL_MARKER)
+}
+
+/// Move a corrupt record into [`QUARANTINE`] inside an open transaction.
+///
+/// The record is only moved if it still holds `raw`, so a concurrent
+/// rewrite of the key is never discarded. Returns the quarantined record.
+pub(crate) fn quarantine(
+    txn: &mut dyn BackendTxn,
+    table: &str,
+    key: &str,
+    raw: &[u8],
+    reason: &str,
+) -> StateResult<Option<QuarantinedRecord>> {
+    if txn.get(table, key)?.as_deref() != Some(raw) {
+        return Ok(None);
+    }
+    let record = QuarantinedRecord {
+        table: table.to_string(),
+        key: key.to_string(),
+        reason: reason.to_string(),
+        raw: raw.to_vec(),
+        detected_at: now_secs(),
+    };
+    txn.put(QUARANTINE, &format!("{table}/{key}"), &encode(&record)?)?;
+    txn.remove(table, key)?;
+    warn!(table, key, reason, "corrupt state record quarantined");
+    Ok(Some(record))
+}
+
+/// Process-wide integrity counters, shared by every clone of a store.
+#[derive(Default)]
+pub(crate) struct IntegrityStats {
+    corrupt_detected: AtomicU64,
+    last_scan: Mutex<Option<IntegrityReport>>,
+}
+
+impl IntegrityStats {
+    pub(crate) fn record_corrupt(&self) {
+        self.corrupt_detected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn corrupt_detected(&self) -> u64 {
+        self.corrupt_detected.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_last_scan(&self, report: &IntegrityReport) {
+        *self.last_scan.lock().unwrap() = Some(report.clone());
+    }
+
+    pub(crate) fn last_scan(&self) -> Option<IntegrityReport> {
+        self.last_scan.lock().unwrap().clone()
+    }
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_value_round_trips() {
+        let bytes = encode(&serde_json::json!({"a": 1})).unwrap();
+        assert!(is_sealed(&bytes));
+        let value: serde_json::Value = decode(&bytes).unwrap();
+        assert_eq!(value["a"], 1);
+    }
+
+    #[test]
+    fn legacy_json_is_accepted() {
+        let value: u64 = decode(b"42").unwrap();
+        assert_eq!(value, 42);
+        assert!(!is_sealed(b"42"));
+    }
+
+    #[test]
+    fn flipped_payload_byte_fails_checksum() {
+        let mut bytes = encode(&"hello").unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0x01;
+        let err = decode::<String>(&bytes).unwrap_err();
+        assert!(matches!(&err, DecodeError::Corrupt(reason) if reason.contains("checksum mismatch")), "{err}");
+    }
+
+    #[test]
+    fn wrong_type_is_a_mismatch_not_corruption() {
+        let bytes = encode(&serde_json::json!({"a": 1})).unwrap();
+        assert!(matches!(decode::<u64>(&bytes), Err(DecodeError::Mismatch(_))));
+        assert!(matches!(decode::<u64>(b"{\"a\""), Err(DecodeError::Corrupt(_))));
+    }
+
+    #[test]
+    fn truncated_header_is_rejected() {
+        assert!(unseal(&[SEAL_MARKER, 0, 0]).unwrap_err().contains("truncated"));
+    }
+}
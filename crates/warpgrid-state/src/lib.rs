@@ -1,22 +1,54 @@
-//! warpgrid-state — embedded state store for WarpGrid.
+//! warpgrid-state — state store for WarpGrid.
 //!
-//! Backed by [redb](https://docs.rs/redb), provides persistent and in-memory
-//! state management for deployments, instances, nodes, services, and metrics.
+//! Backed by [redb](https://docs.rs/redb) by default, provides persistent and
+//! in-memory state management for deployments, instances, nodes, services, metrics,
+//! and usage.
 //!
 //! # Architecture
 //!
-//! All domain types are JSON-serialized into redb's `&[u8]` value columns.
+//! All domain types are JSON-serialized into `&[u8]` value columns, sealed
+//! with a CRC32 so corrupt records are detected on read and quarantined
+//! instead of failing the caller.
 //! Composite keys (`{namespace}/{name}`, `{deployment_id}:{index}`) enable
 //! efficient prefix scans for related records.
 //!
-//! The `StateStore` is `Clone` + `Send` + `Sync` (backed by `Arc<Database>`)
-//! and can be shared across async tasks.
+//! Storage is pluggable through the [`backend::StateBackend`] trait: the
+//! embedded redb backend suits standalone mode, while the `postgres` feature
+//! adds a backend for control planes that keep cluster state in managed
+//! Postgres.
+//!
+//! The `StateStore` is `Clone` + `Send` + `Sync` (backed by
+//! `Arc<dyn StateBackend>`) and can be shared across async tasks.
+//!
+//! Stored records carry a schema version; [`StateStore::migrate`] upgrades
+//! records written by older releases ([`migrate`]).
+//!
+//! [`StateStore::watch`] streams committed writes under a key prefix, so
+//! consumers can react to changes instead of polling ([`watch`]).
+//!
+//! Secret values are encrypted at rest once the store is given a
+//! [`SecretsKey`] ([`secrets`]).
+//!
+//! [`clock`] holds the time source the controllers built on the store
+//! share, so their timing can be driven by hand in tests.
 
+pub mod backend;
+pub mod clock;
 pub mod error;
+mod integrity;
+pub mod migrate;
+pub mod replica;
+pub mod secrets;
 pub mod store;
 pub mod tables;
 pub mod types;
+pub mod watch;
 
+pub use clock::{Clock, ManualClock, SharedClock, SystemClock};
 pub use error::{StateError, StateResult};
-pub use store::StateStore;
+pub use migrate::MigrationReport;
+pub use replica::ReadReplica;
+pub use secrets::SecretsKey;
+pub use store::{Page, StateStore};
 pub use types::*;
+pub use watch::WatchEvent;
@@ -0,0 +1,111 @@
+//! Schema versioning — upgrading stored records between releases.
+//!
+//! The store records which schema its records follow in [`STATE_META`].
+//! At startup warpd calls [`StateStore::migrate`], which runs every
+//! [`Migration`] newer than the stored version, oldest first, and records
+//! each as it completes, so an interrupted upgrade resumes where it
+//! stopped. A store without a record is at version 1, the format written
+//! before versions were recorded. A store recorded at a newer version than
+//! this build knows is refused rather than read with the wrong types.
+//!
+//! A migration rewrites the JSON of every record in its tables. Add one
+//! when a change to a stored type would fail to read old records or give
+//! them a different meaning: a renamed field, a new required field, or a
+//! new default that differs from what old records implied. Fields added
+//! with `#[serde(default)]` need none. Rewritten records in replicated
+//! tables are journaled, so agent replicas receive them as deltas.
+//!
+//! [`STATE_META`]: crate::tables::STATE_META
+//! [`StateStore::migrate`]: crate::StateStore::migrate
+
+use serde_json::{Map, Value};
+
+/// Schema version this build reads and writes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Migrations to [`SCHEMA_VERSION`], ordered by version.
+pub const MIGRATIONS: &[Migration] = &[];
+
+/// One step of a schema upgrade.
+pub struct Migration {
+    /// Version the store is at once this migration has run.
+    pub version: u32,
+    /// What changed, for the startup log.
+    pub description: &'static str,
+    /// Tables whose records it rewrites.
+    pub tables: &'static [&'static str],
+    /// Upgrade one record in place. Returns whether it changed, so
+    /// untouched records are not rewritten.
+    pub upgrade: fn(&mut Value) -> bool,
+}
+
+/// Outcome of [`StateStore::migrate`](crate::StateStore::migrate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Schema version found in the store.
+    pub from: u32,
+    /// Schema version the store is at now.
+    pub to: u32,
+    /// Records rewritten.
+    pub records_migrated: u64,
+}
+
+/// Rename field `from` to `to` in the object at `path` (field names,
+/// outermost first). A record that already has `to` keeps it.
+pub fn rename_field(record: &mut Value, path: &[&str], from: &str, to: &str) -> bool {
+    let Some(object) = object_at(record, path) else {
+        return false;
+    };
+    if object.contains_key(to) {
+        return false;
+    }
+    match object.remove(from) {
+        Some(value) => {
+            object.insert(to.to_string(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Set `field` of the object at `path` to `value` where it is absent.
+pub fn default_field(record: &mut Value, path: &[&str], field: &str, value: Value) -> bool {
+    match object_at(record, path) {
+        Some(object) if !object.contains_key(field) => {
+            object.insert(field.to_string(), value);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn object_at<'a>(record: &'a mut Value, path: &[&str]) -> Option<&'a mut Map<String, Value>> {
+    path.iter()
+        .try_fold(record, |value, field| value.get_mut(*field))?
+        .as_object_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrations_lead_to_schema_version() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as u32 + 2, "{}", migration.description);
+        }
+        assert_eq!(MIGRATIONS.last().map_or(1, |m| m.version), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn field_helpers_edit_nested_objects() {
+        let mut record = json!({ "trigger": { "type": "http", "listen_port": 8080 } });
+        assert!(rename_field(&mut record, &["trigger"], "listen_port", "port"));
+        assert!(!rename_field(&mut record, &["trigger"], "listen_port", "port"));
+        assert!(default_field(&mut record, &["trigger"], "hosts", json!([])));
+        assert!(!default_field(&mut record, &["trigger"], "port", json!(80)));
+        assert!(!default_field(&mut record, &["missing"], "port", json!(80)));
+        assert_eq!(record, json!({ "trigger": { "type": "http", "port": 8080, "hosts": [] } }));
+    }
+}
@@ -0,0 +1,253 @@
+//! ReadReplica — node-local, read-only copy of control-plane state.
+//!
+//! Agents keep deployment specs, instances, and service endpoints in a
+//! replica so local readers (DNS, proxy sync) never round-trip to the
+//! control plane. The replica only changes through [`ReadReplica::apply`],
+//! fed by [`ReplicaSync`] payloads produced with
+//! [`StateStore::replica_sync_since`] on the control plane.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use tracing::debug;
+
+use crate::backend::{BackendTxn, StateBackend};
+use crate::backend::embedded::RedbBackend;
+use crate::error::{StateError, StateResult};
+use crate::integrity::encode;
+use crate::store::StateStore;
+use crate::tables::{REPLICA_META, REPLICATED_TABLES};
+use crate::types::{ReplicaSync, StateChange};
+
+/// Key in [`REPLICA_META`] holding the last applied revision.
+const REVISION_KEY: &str = "revision";
+
+/// Read-only replica of the control plane's replicated tables.
+#[derive(Clone)]
+pub struct ReadReplica {
+    store: StateStore,
+}
+
+impl ReadReplica {
+    /// Open (or create) a persistent replica at the given path.
+    pub fn open(path: &Path) -> StateResult<Self> {
+        Ok(Self::from_backend(Arc::new(RedbBackend::open(path)?)))
+    }
+
+    /// Create an ephemeral in-memory replica (for testing).
+    pub fn open_in_memory() -> StateResult<Self> {
+        Ok(Self::from_backend(Arc::new(RedbBackend::open_in_memory()?)))
+    }
+
+    fn from_backend(backend: Arc<dyn StateBackend>) -> Self {
+        Self {
+            store: StateStore::with_backend(backend),
+        }
+    }
+
+    /// The replica's state, for readers such as `ProxySync`.
+    ///
+    /// Writes must go through [`ReadReplica::apply`]; anything written
+    /// directly is overwritten by the next snapshot.
+    pub fn store(&self) -> &StateStore {
+        &self.store
+    }
+
+    /// Last control-plane revision applied (0 for a new replica).
+    pub fn revision(&self) -> StateResult<u64> {
+        Ok(self.store.get_json(REPLICA_META, REVISION_KEY)?.unwrap_or(0))
+    }
+
+    /// Apply a sync payload atomically. Returns the new revision.
+    ///
+    /// Deltas at or below the current revision are skipped, so re-delivered
+    /// payloads are harmless.
+    pub fn apply(&self, sync: &ReplicaSync) -> StateResult<u64> {
+        let backend = self.store.backend();
+        let current = self.revision()?;
+
+        let revision = match sync {
+            ReplicaSync::Snapshot { revision, entries } => {
+                let mut stale = Vec::new();
+                for table in REPLICATED_TABLES {
+                    for (key, _) in backend.scan_prefix(table, "")? {
+                        stale.push((*table, key));
+                    }
+                }
+                backend.transaction(&mut |txn| {
+                    for (table, key) in &stale {
+                        txn.remove(table, key)?;
+                    }
+                    for entry in entries {
+                        write_change(txn, entry)?;
+                    }
+                    set_revision(txn, *revision)
+                })?;
+                debug!(revision, entries = entries.len(), "replica snapshot applied");
+                *revision
+            }
+            ReplicaSync::Deltas { changes } => {
+                let pending: Vec<&StateChange> =
+                    changes.iter().filter(|c| c.revision > current).collect();
+                let Some(last) = pending.last().map(|c| c.revision) else {
+                    return Ok(current);
+                };
+                backend.transaction(&mut |txn| {
+                    for change in &pending {
+                        write_change(txn, change)?;
+                    }
+                    set_revision(txn, last)
+                })?;
+                debug!(revision = last, applied = pending.len(), "replica deltas applied");
+                last
+            }
+        };
+        Ok(revision)
+    }
+}
+
+fn write_change(txn: &mut dyn BackendTxn, change: &StateChange) -> StateResult<()> {
+    if !REPLICATED_TABLES.contains(&change.table.as_str()) {
+        return Err(StateError::Write(format!(
+            "table '{}' is not replicated",
+            change.table
+        )));
+    }
+    match &change.value {
+        Some(value) => txn.put(&change.table, &change.key, &encode(value)?),
+        None => txn.remove(&change.table, &change.key).map(|_| ()),
+    }
+}
+
+fn set_revision(txn: &mut dyn BackendTxn, revision: u64) -> StateResult<()> {
+    txn.put(REPLICA_META, REVISION_KEY, &encode(&revision)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+    use std::collections::HashMap;
+
+    fn test_deployment(name: &str) -> DeploymentSpec {
+        DeploymentSpec {
+            id: format!("default-{name}"),
+            namespace: "default".to_string(),
+            name: name.to_string(),
+            source: "file://./test.wasm".to_string(),
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min: 1, max: 1 },
+            resources: ResourceLimits {
+                memory_bytes: 64 * 1024 * 1024,
+                cpu_weight: 100,
+                execution_budget_ms: None,
+            },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: HashMap::new(),
+            created_at: 1000,
+            updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        }
+    }
+
+    fn sync(primary: &StateStore, replica: &ReadReplica) -> Option<ReplicaSync> {
+        let payload = primary
+            .replica_sync_since(replica.revision().unwrap(), 1000)
+            .unwrap();
+        if let Some(payload) = &payload {
+            replica.apply(payload).unwrap();
+        }
+        payload
+    }
+
+    #[test]
+    fn new_replica_gets_snapshot_then_deltas() {
+        let primary = StateStore::open_in_memory().unwrap();
+        let replica = ReadReplica::open_in_memory().unwrap();
+        primary.put_deployment(&test_deployment("api")).unwrap();
+
+        let first = sync(&primary, &replica).unwrap();
+        assert!(matches!(first, ReplicaSync::Snapshot { .. }));
+        assert!(replica.store().get_deployment("default/api").unwrap().is_some());
+
+        primary.put_deployment(&test_deployment("web")).unwrap();
+        primary.delete_deployment("default/api").unwrap();
+
+        let second = sync(&primary, &replica).unwrap();
+        assert!(matches!(second, ReplicaSync::Deltas { ref changes } if changes.len() == 2));
+        assert!(replica.store().get_deployment("default/api").unwrap().is_none());
+        assert!(replica.store().get_deployment("default/web").unwrap().is_some());
+        assert_eq!(replica.revision().unwrap(), primary.state_revision().unwrap());
+
+        assert!(sync(&primary, &replica).is_none(), "replica is current");
+    }
+
+    #[test]
+    fn redelivered_deltas_are_skipped() {
+        let primary = StateStore::open_in_memory().unwrap();
+        let replica = ReadReplica::open_in_memory().unwrap();
+        primary.put_deployment(&test_deployment("api")).unwrap();
+        sync(&primary, &replica);
+
+        primary.delete_deployment("default/api").unwrap();
+        let deltas = primary.replica_sync_since(1, 1000).unwrap().unwrap();
+        replica.apply(&deltas).unwrap();
+
+        // A stale re-delivery must not resurrect the deleted deployment.
+        let stale = ReplicaSync::Deltas {
+            changes: vec![StateChange {
+                revision: 1,
+                table: crate::tables::DEPLOYMENTS.to_string(),
+                key: "default/api".to_string(),
+                value: Some(serde_json::to_value(test_deployment("api")).unwrap()),
+            }],
+        };
+        assert_eq!(replica.apply(&stale).unwrap(), 2);
+        assert!(replica.store().list_deployments().unwrap().is_empty());
+    }
+
+    #[test]
+    fn snapshot_replaces_stale_entries() {
+        let replica = ReadReplica::open_in_memory().unwrap();
+        replica
+            .apply(&ReplicaSync::Deltas {
+                changes: vec![StateChange {
+                    revision: 1,
+                    table: crate::tables::DEPLOYMENTS.to_string(),
+                    key: "default/old".to_string(),
+                    value: Some(serde_json::to_value(test_deployment("old")).unwrap()),
+                }],
+            })
+            .unwrap();
+
+        replica
+            .apply(&ReplicaSync::Snapshot {
+                revision: 7,
+                entries: vec![],
+            })
+            .unwrap();
+        assert!(replica.store().list_deployments().unwrap().is_empty());
+        assert_eq!(replica.revision().unwrap(), 7);
+    }
+
+    #[test]
+    fn non_replicated_table_is_rejected() {
+        let replica = ReadReplica::open_in_memory().unwrap();
+        let result = replica.apply(&ReplicaSync::Deltas {
+            changes: vec![StateChange {
+                revision: 1,
+                table: crate::tables::NODES.to_string(),
+                key: "node-1".to_string(),
+                value: None,
+            }],
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Encryption of secret values at rest.
+//!
+//! A store given a [`SecretsKey`] ([`crate::StateStore::with_secrets_key`])
+//! seals each secret's value with AES-256-GCM before writing it and opens
+//! it again on read, so the value never reaches the backend, the replicas,
+//! or a backup in the clear. The secret's table key is bound in as
+//! associated data: a sealed value copied onto another secret fails to open.
+//!
+//! Sealed values are stored as [`SEALED_PREFIX`] followed by the hex of the
+//! 12-byte nonce and the ciphertext. Values written before a key was
+//! configured carry no prefix and read back as they are.
+
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::error::{StateError, StateResult};
+
+/// Marks a stored secret value as sealed.
+pub const SEALED_PREFIX: &str = "enc:v1:";
+
+/// Key length in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// The AES-256-GCM key secret values are sealed with.
+pub struct SecretsKey {
+    bytes: [u8; KEY_LEN],
+    key: LessSafeKey,
+}
+
+impl std::fmt::Debug for SecretsKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretsKey(..)")
+    }
+}
+
+impl SecretsKey {
+    /// A new random key.
+    pub fn generate() -> StateResult<Self> {
+        let mut bytes = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| StateError::Encryption("no randomness for a new key".into()))?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    /// Parse a key written by [`Self::to_hex`].
+    pub fn from_hex(hex: &str) -> StateResult<Self> {
+        let bytes = hex::decode(hex.trim()).map_err(|e| StateError::Encryption(format!("invalid key: {e}")))?;
+        let bytes: [u8; KEY_LEN] = bytes
+            .try_into()
+            .map_err(|_| StateError::Encryption(format!("invalid key: expected {KEY_LEN} bytes")))?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &bytes).expect("key has the AES-256 length"));
+        Self { bytes, key }
+    }
+
+    /// The key as hex, for writing to a key file.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.bytes)
+    }
+
+    /// Seal `value`, stored under `table_key`.
+    pub fn seal(&self, table_key: &str, value: &str) -> StateResult<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| StateError::Encryption("no randomness for a nonce".into()))?;
+        let mut sealed = value.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(table_key.as_bytes()), &mut sealed)
+            .map_err(|_| StateError::Encryption(format!("failed to seal secret {table_key}")))?;
+        Ok(format!("{SEALED_PREFIX}{}{}", hex::encode(nonce), hex::encode(sealed)))
+    }
+
+    /// Open a value sealed under `table_key`.
+    pub fn open(&self, table_key: &str, stored: &str) -> StateResult<String> {
+        let failed = || StateError::Encryption(format!("failed to open secret {table_key}"));
+        let hex = stored.strip_prefix(SEALED_PREFIX).ok_or_else(failed)?;
+        let bytes = hex::decode(hex).map_err(|_| failed())?;
+        if bytes.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| failed())?;
+        let mut sealed = sealed.to_vec();
+        let value = self
+            .key
+            .open_in_place(nonce, Aad::from(table_key.as_bytes()), &mut sealed)
+            .map_err(|_| failed())?;
+        String::from_utf8(value.to_vec()).map_err(|_| failed())
+    }
+}
+
+/// Whether a stored value was sealed by a [`SecretsKey`].
+pub fn is_sealed(stored: &str) -> bool {
+    stored.starts_with(SEALED_PREFIX)
+}
+
+/// Length in bytes of a stored value once opened, worked out without the
+/// key.
+pub fn value_len(stored: &str) -> usize {
+    match stored.strip_prefix(SEALED_PREFIX) {
+        Some(hex) => (hex.len() / 2).saturating_sub(NONCE_LEN + AES_256_GCM.tag_len()),
+        None => stored.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_round_trips_and_binds_the_table_key() {
+        let key = SecretsKey::generate().unwrap();
+        let sealed = key.seal("default/db-password", "hunter2").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("hunter2"));
+        assert_ne!(sealed, key.seal("default/db-password", "hunter2").unwrap());
+        assert_eq!(key.open("default/db-password", &sealed).unwrap(), "hunter2");
+        assert!(key.open("prod/db-password", &sealed).is_err());
+
+        let reloaded = SecretsKey::from_hex(&key.to_hex()).unwrap();
+        assert_eq!(reloaded.open("default/db-password", &sealed).unwrap(), "hunter2");
+        assert!(SecretsKey::generate().unwrap().open("default/db-password", &sealed).is_err());
+        assert!(SecretsKey::from_hex("abcd").is_err());
+        assert_eq!((value_len(&sealed), value_len("hunter2")), (7, 7));
+    }
+}
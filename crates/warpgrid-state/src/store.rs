@@ -1,19 +1,30 @@
-//! StateStore — redb-backed state persistence for WarpGrid.
+//! StateStore — typed state persistence for WarpGrid.
 //!
 //! Provides typed CRUD operations over deployments, instances, nodes,
-//! services, and metrics. All values are JSON-serialized into redb's
-//! `&[u8]` value columns. The store supports both on-disk and in-memory
-//! backends (the latter for testing).
+//! services, metrics, and usage. All values are JSON-serialized and
+//! checksum-sealed into the `&[u8]` value columns
+//! of a [`StateBackend`]. The embedded redb backend
+//! (on-disk or in-memory) is the default; operators can point a control
+//! plane at Postgres instead with [`StateStore::connect`].
 
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::sync::Arc;
 
-use redb::{Database, ReadableDatabase, ReadableTable};
-use tracing::debug;
+use futures_util::Stream;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::{debug, info, warn};
 
+use crate::backend::{BackendTxn, KvEntry, StateBackend};
+use crate::backend::embedded::RedbBackend;
 use crate::error::{StateError, StateResult};
+use crate::integrity::{self, IntegrityStats, decode, encode, seal};
+use crate::migrate::{self, Migration, MigrationReport};
+use crate::secrets::{SecretsKey, is_sealed};
 use crate::tables::*;
 use crate::types::*;
+use crate::watch::{self, WatchEvent, WatchedBackend};
 
 /// Convert any `Display` error into a `StateError` variant via a closure factory.
 macro_rules! map_err {
@@ -22,135 +33,469 @@ macro_rules! map_err {
     };
 }
 
-/// Thread-safe state store backed by redb.
+/// Key in [`STATE_META`] holding the schema version; see [`migrate`].
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Prefix of the keys in [`STATE_META`] holding the version a deleted
+/// deployment had reached, so one re-created under its name continues
+/// past it and an `If-Match` for the old one cannot match the new.
+const DELETED_VERSION_PREFIX: &str = "deleted_deployment_version/";
+
+/// Key in [`REPLICA_META`] written by [`StateStore::check_writable`].
+const WRITE_PROBE_KEY: &str = "write_probe";
+
+/// Page size for [`StateStore::verify_integrity`] table scans.
+const VERIFY_BATCH: usize = 1000;
+
+/// Entries read per backend scan while filling a [`Page`].
+const PAGE_SCAN_BATCH: usize = 256;
+
+/// One page of a key-ordered listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Key of the last item, to pass as `after` for the next page; `None`
+    /// when no matching entry follows.
+    pub next: Option<String>,
+}
+
+/// Thread-safe state store over a pluggable backend.
 #[derive(Clone)]
 pub struct StateStore {
-    db: Arc<Database>,
+    backend: Arc<dyn StateBackend>,
+    /// The same backend, for subscribing to its writes.
+    watched: Arc<WatchedBackend>,
+    integrity: Arc<IntegrityStats>,
+    secrets_key: Option<Arc<SecretsKey>>,
 }
 
 impl StateStore {
-    /// Open (or create) a persistent state store at the given path.
+    /// Open (or create) a persistent redb state store at the given path.
     pub fn open(path: &Path) -> StateResult<Self> {
-        let db = Database::create(path).map_err(map_err!(Open))?;
-        let store = Self { db: Arc::new(db) };
-        store.ensure_tables()?;
+        let store = Self::with_backend(Arc::new(RedbBackend::open(path)?));
         debug!(?path, "state store opened");
         Ok(store)
     }
 
     /// Create an ephemeral in-memory state store (for testing).
     pub fn open_in_memory() -> StateResult<Self> {
-        let backend = redb::backends::InMemoryBackend::new();
-        let db = Database::builder()
-            .create_with_backend(backend)
-            .map_err(map_err!(Open))?;
-        let store = Self { db: Arc::new(db) };
-        store.ensure_tables()?;
+        let store = Self::with_backend(Arc::new(RedbBackend::open_in_memory()?));
         debug!("in-memory state store opened");
         Ok(store)
     }
 
-    /// Create all tables if they don't exist yet.
-    fn ensure_tables(&self) -> StateResult<()> {
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        // Opening a table in a write transaction creates it if absent.
-        txn.open_table(DEPLOYMENTS).map_err(map_err!(Table))?;
-        txn.open_table(INSTANCES).map_err(map_err!(Table))?;
-        txn.open_table(NODES).map_err(map_err!(Table))?;
-        txn.open_table(SERVICES).map_err(map_err!(Table))?;
-        txn.open_table(METRICS).map_err(map_err!(Table))?;
-        txn.commit().map_err(map_err!(Transaction))?;
+    /// Connect to an external state store by URL.
+    ///
+    /// Supports `postgres://` and `postgresql://` URLs when built with the
+    /// `postgres` feature.
+    pub fn connect(url: &str) -> StateResult<Self> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            return Self::connect_postgres(url);
+        }
+        Err(StateError::Open(format!(
+            "unsupported state store URL scheme: {}",
+            url.split("://").next().unwrap_or(url)
+        )))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn connect_postgres(url: &str) -> StateResult<Self> {
+        let backend = crate::backend::postgres::PostgresBackend::connect(url)?;
+        debug!("postgres state store connected");
+        Ok(Self::with_backend(Arc::new(backend)))
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    fn connect_postgres(_url: &str) -> StateResult<Self> {
+        Err(StateError::Open(
+            "postgres state store requires the `postgres` feature".to_string(),
+        ))
+    }
+
+    /// Wrap an existing backend.
+    pub fn with_backend(backend: Arc<dyn StateBackend>) -> Self {
+        let watched = Arc::new(WatchedBackend::new(backend));
+        Self {
+            backend: watched.clone(),
+            watched,
+            integrity: Arc::new(IntegrityStats::default()),
+            secrets_key: None,
+        }
+    }
+
+    /// Encrypt secret values at rest with `key` (see [`crate::secrets`]).
+    pub fn with_secrets_key(mut self, key: SecretsKey) -> Self {
+        self.secrets_key = Some(Arc::new(key));
+        self
+    }
+
+    /// Name of the active backend (e.g. `"redb"`, `"postgres"`).
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Check the backend accepts writes, with a transaction that writes
+    /// and removes a probe key, committing no change.
+    pub fn check_writable(&self) -> StateResult<()> {
+        self.backend.transaction(&mut |txn| {
+            txn.put(REPLICA_META, WRITE_PROBE_KEY, b"")?;
+            txn.remove(REPLICA_META, WRITE_PROBE_KEY).map(|_| ())
+        })
+    }
+
+    /// Stream the writes committed from now on to `prefix`, a table or
+    /// `{table}/{key prefix}` such as `"instances/default/api:"`, with old
+    /// and new values.
+    ///
+    /// A watcher that falls behind gets [`StateError::WatchLagged`] and
+    /// keeps receiving later changes; see [`crate::watch`].
+    pub fn watch(&self, prefix: &str) -> impl Stream<Item = StateResult<WatchEvent>> + Send + use<> {
+        watch::stream(&self.watched, prefix.to_string())
+    }
+
+    /// [`StateStore::watch`] several prefixes as one stream, for readers
+    /// that rebuild from more than one table.
+    pub fn watch_any(&self, prefixes: &[&str]) -> impl Stream<Item = StateResult<WatchEvent>> + Send + use<> {
+        futures_util::stream::select_all(prefixes.iter().map(|prefix| Box::pin(self.watch(prefix))))
+    }
+
+    pub(crate) fn backend(&self) -> &dyn StateBackend {
+        self.backend.as_ref()
+    }
+
+    fn put_json<T: Serialize>(&self, table: &str, key: &str, value: &T) -> StateResult<()> {
+        self.backend.put(table, key, &encode(value)?)
+    }
+
+    /// Read and verify a value. A corrupt record is quarantined and read
+    /// as absent; one of the wrong shape is left in place and fails the read.
+    pub(crate) fn get_json<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        key: &str,
+    ) -> StateResult<Option<T>> {
+        let Some(bytes) = self.backend.get(table, key)? else {
+            return Ok(None);
+        };
+        match decode(&bytes) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => {
+                let reason = err.corruption_reason(table, key)?;
+                self.quarantine(table, key, &bytes, &reason)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Verify scanned entries, quarantining and dropping corrupt ones. An
+    /// entry of the wrong shape fails the scan.
+    fn decode_entries<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        entries: Vec<KvEntry>,
+    ) -> StateResult<Vec<(String, T)>> {
+        let mut decoded = Vec::with_capacity(entries.len());
+        for (key, bytes) in entries {
+            match decode(&bytes) {
+                Ok(value) => decoded.push((key, value)),
+                Err(err) => {
+                    let reason = err.corruption_reason(table, &key)?;
+                    self.quarantine(table, &key, &bytes, &reason)?;
+                }
+            }
+        }
+        Ok(decoded)
+    }
+
+    /// Move a corrupt record found outside a transaction into quarantine.
+    fn quarantine(
+        &self,
+        table: &str,
+        key: &str,
+        raw: &[u8],
+        reason: &str,
+    ) -> StateResult<Option<QuarantinedRecord>> {
+        self.integrity.record_corrupt();
+        let mut moved = None;
+        self.backend.transaction(&mut |txn| {
+            moved = integrity::quarantine(txn, table, key, raw, reason)?;
+            Ok(())
+        })?;
+        Ok(moved)
+    }
+
+    /// Write (or with `None`, delete) a replicated entry and journal the
+    /// change in the same transaction. `value` is unsealed JSON. Returns
+    /// true if the key existed.
+    fn write_replicated(&self, table: &str, key: &str, value: Option<&[u8]>) -> StateResult<bool> {
+        let mut existed = false;
+        self.backend.transaction(&mut |txn| {
+            existed = match value {
+                Some(json) => {
+                    let existed = txn.get(table, key)?.is_some();
+                    txn.put(table, key, &seal(json))?;
+                    existed
+                }
+                None => txn.remove(table, key)?,
+            };
+            journal_change(txn, table, key, value)
+        })?;
+        Ok(existed)
+    }
+
+    fn put_replicated<T: Serialize>(&self, table: &str, key: &str, value: &T) -> StateResult<()> {
+        let value = serde_json::to_vec(value).map_err(map_err!(Serialize))?;
+        self.write_replicated(table, key, Some(&value))?;
         Ok(())
     }
 
+    fn scan_json<T: DeserializeOwned>(&self, table: &str, prefix: &str) -> StateResult<Vec<T>> {
+        let entries = self.backend.scan_prefix(table, prefix)?;
+        Ok(self
+            .decode_entries(table, entries)?
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect())
+    }
+
+    /// Up to `limit` entries under `prefix` with a key after `after` that
+    /// `keep` accepts, in key order. Keys are the cursor, so a page never
+    /// repeats or skips an entry that exists across both requests.
+    fn scan_page<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        prefix: &str,
+        after: Option<&str>,
+        limit: usize,
+        keep: &dyn Fn(&T) -> bool,
+    ) -> StateResult<Page<T>> {
+        // `after` + "\0" is the first key that sorts after `after`.
+        let mut start = match after {
+            Some(after) if after >= prefix => format!("{after}\0"),
+            _ => prefix.to_string(),
+        };
+        let mut page = Page { items: Vec::new(), next: None };
+        let mut last = None;
+        loop {
+            let entries = self.backend.scan_from(table, &start, PAGE_SCAN_BATCH)?;
+            let Some((final_key, _)) = entries.last() else {
+                return Ok(page);
+            };
+            start = format!("{final_key}\0");
+            let scanned = entries.len();
+            let entries: Vec<KvEntry> = entries.into_iter().take_while(|(key, _)| key.starts_with(prefix)).collect();
+            let done = entries.len() < scanned || scanned < PAGE_SCAN_BATCH;
+            for (key, value) in self.decode_entries::<T>(table, entries)? {
+                if !keep(&value) {
+                    continue;
+                }
+                if page.items.len() == limit {
+                    page.next = last;
+                    return Ok(page);
+                }
+                page.items.push(value);
+                last = Some(key);
+            }
+            if done {
+                return Ok(page);
+            }
+        }
+    }
+
     // ── Deployments ────────────────────────────────────────────────
 
-    /// Insert or update a deployment spec.
+    /// Insert or update a deployment spec, recording it in the
+    /// deployment's revision history.
     pub fn put_deployment(&self, spec: &DeploymentSpec) -> StateResult<()> {
+        self.put_deployment_if(spec, DeploymentPrecondition::Any)?;
+        Ok(())
+    }
+
+    /// Store `spec` if its deployment's current version satisfies
+    /// `precondition`. Returns false, storing nothing, when it does not.
+    ///
+    /// The check, the spec, and its revision commit in one transaction, so
+    /// concurrent writers — on other control planes too, when they share a
+    /// backend — cannot both pass the same precondition.
+    pub fn put_deployment_if(&self, spec: &DeploymentSpec, precondition: DeploymentPrecondition) -> StateResult<bool> {
         let key = spec.table_key();
-        let value = serde_json::to_vec(spec).map_err(map_err!(Serialize))?;
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        {
-            let mut table = txn.open_table(DEPLOYMENTS).map_err(map_err!(Table))?;
-            table
-                .insert(key.as_str(), value.as_slice())
-                .map_err(map_err!(Write))?;
+        let json = serde_json::to_vec(spec).map_err(map_err!(Serialize))?;
+        let mut stored = false;
+        self.backend.transaction(&mut |txn| {
+            stored = false;
+            let revisions = self.revisions_in(txn, &key)?;
+            let exists = txn.get(DEPLOYMENTS, &key)?.is_some();
+            if !precondition.holds(version(exists, &revisions)) {
+                return Ok(());
+            }
+            let deleted_version = if revisions.is_empty() { self.deleted_version_in(txn, &key)? } else { 0 };
+            txn.put(DEPLOYMENTS, &key, &seal(&json))?;
+            journal_change(txn, DEPLOYMENTS, &key, Some(&json))?;
+            record_revision(txn, &key, spec, &revisions, deleted_version + 1)?;
+            if deleted_version > 0 {
+                txn.remove(STATE_META, &deleted_version_key(&key))?;
+            }
+            stored = true;
+            Ok(())
+        })?;
+        if stored {
+            debug!(%key, "deployment stored");
         }
-        txn.commit().map_err(map_err!(Transaction))?;
-        debug!(%key, "deployment stored");
-        Ok(())
+        Ok(stored)
     }
 
-    /// Get a deployment by namespace/name key.
-    pub fn get_deployment(&self, key: &str) -> StateResult<Option<DeploymentSpec>> {
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(DEPLOYMENTS).map_err(map_err!(Table))?;
-        match table.get(key).map_err(map_err!(Read))? {
-            Some(guard) => {
-                let spec: DeploymentSpec =
-                    serde_json::from_slice(guard.value()).map_err(map_err!(Deserialize))?;
-                Ok(Some(spec))
+    /// A deployment's version: its latest revision, 0 for one stored before
+    /// revisions were recorded, or `None` when it does not exist.
+    pub fn deployment_version(&self, key: &str) -> StateResult<Option<u64>> {
+        let exists = self.backend.get(DEPLOYMENTS, key)?.is_some();
+        Ok(version(exists, &self.list_deployment_revisions(key)?))
+    }
+
+    /// Deployment `key`'s revisions inside a transaction, oldest first,
+    /// quarantining corrupt ones.
+    fn revisions_in(&self, txn: &mut dyn BackendTxn, key: &str) -> StateResult<Vec<DeploymentRevision>> {
+        let mut revisions = Vec::new();
+        for (revision_key, bytes) in txn.scan_prefix(DEPLOYMENT_REVISIONS, &format!("{key}:"))? {
+            match decode(&bytes) {
+                Ok(revision) => revisions.push(revision),
+                Err(err) => {
+                    let reason = err.corruption_reason(DEPLOYMENT_REVISIONS, &revision_key)?;
+                    self.integrity.record_corrupt();
+                    integrity::quarantine(txn, DEPLOYMENT_REVISIONS, &revision_key, &bytes, &reason)?;
+                }
+            }
+        }
+        Ok(revisions)
+    }
+
+    /// The version deployment `key` had when it was last deleted, or 0.
+    fn deleted_version_in(&self, txn: &mut dyn BackendTxn, key: &str) -> StateResult<u64> {
+        let meta_key = deleted_version_key(key);
+        let Some(bytes) = txn.get(STATE_META, &meta_key)? else {
+            return Ok(0);
+        };
+        match decode(&bytes) {
+            Ok(version) => Ok(version),
+            Err(err) => {
+                let reason = err.corruption_reason(STATE_META, &meta_key)?;
+                self.integrity.record_corrupt();
+                integrity::quarantine(txn, STATE_META, &meta_key, &bytes, &reason)?;
+                Ok(0)
             }
-            None => Ok(None),
         }
     }
 
+    /// A deployment's retained revisions, oldest first.
+    pub fn list_deployment_revisions(&self, key: &str) -> StateResult<Vec<DeploymentRevision>> {
+        self.scan_json(DEPLOYMENT_REVISIONS, &format!("{key}:"))
+    }
+
+    /// One revision of a deployment, if it is still retained.
+    pub fn get_deployment_revision(&self, key: &str, revision: u64) -> StateResult<Option<DeploymentRevision>> {
+        self.get_json(DEPLOYMENT_REVISIONS, &revision_key(key, revision))
+    }
+
+    /// Get a deployment by namespace/name key.
+    pub fn get_deployment(&self, key: &str) -> StateResult<Option<DeploymentSpec>> {
+        self.get_json(DEPLOYMENTS, key)
+    }
+
     /// List all deployments.
     pub fn list_deployments(&self) -> StateResult<Vec<DeploymentSpec>> {
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(DEPLOYMENTS).map_err(map_err!(Table))?;
-        let mut results = Vec::new();
-        for entry in table.iter().map_err(map_err!(Read))? {
-            let (_, value) = entry.map_err(map_err!(Read))?;
-            let spec: DeploymentSpec =
-                serde_json::from_slice(value.value()).map_err(map_err!(Deserialize))?;
-            results.push(spec);
+        self.scan_json(DEPLOYMENTS, "")
+    }
+
+    /// List the deployments of one namespace.
+    pub fn list_deployments_in_namespace(&self, namespace: &str) -> StateResult<Vec<DeploymentSpec>> {
+        self.scan_json(DEPLOYMENTS, &format!("{namespace}/"))
+    }
+
+    /// A page of deployments `keep` accepts, in `namespace` or in all
+    /// namespaces, ordered by `namespace/name`.
+    pub fn list_deployments_page(
+        &self,
+        namespace: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+        keep: impl Fn(&DeploymentSpec) -> bool,
+    ) -> StateResult<Page<DeploymentSpec>> {
+        let prefix = namespace.map(|ns| format!("{ns}/")).unwrap_or_default();
+        self.scan_page(DEPLOYMENTS, &prefix, after, limit, &keep)
+    }
+
+    /// Every namespace holding a deployment or secret, by name, read from
+    /// the `namespace/name` keys.
+    pub fn list_namespaces(&self) -> StateResult<Vec<NamespaceSummary>> {
+        let mut namespaces: BTreeMap<String, NamespaceSummary> = BTreeMap::new();
+        for (table, is_deployment) in [(DEPLOYMENTS, true), (SECRETS, false)] {
+            for (key, _) in self.backend.scan_prefix(table, "")? {
+                let Some((namespace, _)) = key.split_once('/') else {
+                    continue;
+                };
+                let summary = namespaces.entry(namespace.to_string()).or_insert_with(|| NamespaceSummary {
+                    name: namespace.to_string(),
+                    deployments: 0,
+                    secrets: 0,
+                });
+                if is_deployment {
+                    summary.deployments += 1;
+                } else {
+                    summary.secrets += 1;
+                }
+            }
         }
-        Ok(results)
+        Ok(namespaces.into_values().collect())
     }
 
-    /// Delete a deployment by key. Returns true if it existed.
+    /// Delete a deployment and its revision history by key. Returns true
+    /// if it existed.
     pub fn delete_deployment(&self, key: &str) -> StateResult<bool> {
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        let existed;
-        {
-            let mut table = txn.open_table(DEPLOYMENTS).map_err(map_err!(Table))?;
-            existed = table.remove(key).map_err(map_err!(Write))?.is_some();
+        Ok(self.delete_deployment_if(key, DeploymentPrecondition::Any)?.unwrap_or(false))
+    }
+
+    /// Delete a deployment if its current version satisfies
+    /// `precondition`. Returns `None`, deleting nothing, when it does not,
+    /// or whether it existed. Like [`StateStore::put_deployment_if`], the
+    /// check and the delete are one transaction.
+    pub fn delete_deployment_if(&self, key: &str, precondition: DeploymentPrecondition) -> StateResult<Option<bool>> {
+        let mut deleted = None;
+        self.backend.transaction(&mut |txn| {
+            deleted = None;
+            let revisions = txn.scan_prefix(DEPLOYMENT_REVISIONS, &format!("{key}:"))?;
+            let exists = txn.get(DEPLOYMENTS, key)?.is_some();
+            let version = exists
+                .then(|| revisions.last().map_or(Ok(0), |(revision_key, _)| revision_number(revision_key)))
+                .transpose()?;
+            if !precondition.holds(version) {
+                return Ok(());
+            }
+            let existed = txn.remove(DEPLOYMENTS, key)?;
+            journal_change(txn, DEPLOYMENTS, key, None)?;
+            for (revision_key, _) in &revisions {
+                txn.remove(DEPLOYMENT_REVISIONS, revision_key)?;
+            }
+            if let Some(version) = version.filter(|&version| version > 0) {
+                txn.put(STATE_META, &deleted_version_key(key), &encode(&version)?)?;
+            }
+            deleted = Some(existed);
+            Ok(())
+        })?;
+        if let Some(existed) = deleted {
+            debug!(%key, existed, "deployment deleted");
         }
-        txn.commit().map_err(map_err!(Transaction))?;
-        debug!(%key, existed, "deployment deleted");
-        Ok(existed)
+        Ok(deleted)
     }
 
     // ── Instances ──────────────────────────────────────────────────
 
     /// Insert or update an instance state.
     pub fn put_instance(&self, state: &InstanceState) -> StateResult<()> {
-        let key = state.table_key();
-        let value = serde_json::to_vec(state).map_err(map_err!(Serialize))?;
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        {
-            let mut table = txn.open_table(INSTANCES).map_err(map_err!(Table))?;
-            table
-                .insert(key.as_str(), value.as_slice())
-                .map_err(map_err!(Write))?;
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(())
+        self.put_replicated(INSTANCES, &state.table_key(), state)
     }
 
     /// Get an instance by its composite key.
     pub fn get_instance(&self, key: &str) -> StateResult<Option<InstanceState>> {
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(INSTANCES).map_err(map_err!(Table))?;
-        match table.get(key).map_err(map_err!(Read))? {
-            Some(guard) => {
-                let state: InstanceState =
-                    serde_json::from_slice(guard.value()).map_err(map_err!(Deserialize))?;
-                Ok(Some(state))
-            }
-            None => Ok(None),
-        }
+        self.get_json(INSTANCES, key)
     }
 
     /// List all instances for a given deployment ID.
@@ -158,165 +503,512 @@ impl StateStore {
         &self,
         deployment_id: &str,
     ) -> StateResult<Vec<InstanceState>> {
-        let prefix = format!("{deployment_id}:");
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(INSTANCES).map_err(map_err!(Table))?;
-        let mut results = Vec::new();
-        for entry in table.iter().map_err(map_err!(Read))? {
-            let (key, value) = entry.map_err(map_err!(Read))?;
-            if key.value().starts_with(&prefix) {
-                let state: InstanceState =
-                    serde_json::from_slice(value.value()).map_err(map_err!(Deserialize))?;
-                results.push(state);
-            }
-        }
-        Ok(results)
+        self.scan_json(INSTANCES, &format!("{deployment_id}:"))
+    }
+
+    /// A page of a deployment's instances `keep` accepts, ordered by key
+    /// (`{deployment_id}:{instance_id}`).
+    pub fn list_instances_page(
+        &self,
+        deployment_id: &str,
+        after: Option<&str>,
+        limit: usize,
+        keep: impl Fn(&InstanceState) -> bool,
+    ) -> StateResult<Page<InstanceState>> {
+        self.scan_page(INSTANCES, &format!("{deployment_id}:"), after, limit, &keep)
     }
 
     /// Delete an instance by key. Returns true if it existed.
     pub fn delete_instance(&self, key: &str) -> StateResult<bool> {
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        let existed;
-        {
-            let mut table = txn.open_table(INSTANCES).map_err(map_err!(Table))?;
-            existed = table.remove(key).map_err(map_err!(Write))?.is_some();
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(existed)
+        self.write_replicated(INSTANCES, key, None)
     }
 
     /// Delete all instances for a deployment. Returns number deleted.
     pub fn delete_instances_for_deployment(&self, deployment_id: &str) -> StateResult<u32> {
         let prefix = format!("{deployment_id}:");
-        // Collect keys in a read transaction first.
-        let keys: Vec<String> = {
-            let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-            let table = txn.open_table(INSTANCES).map_err(map_err!(Table))?;
-            table
-                .iter()
-                .map_err(map_err!(Read))?
-                .filter_map(|entry| {
-                    let (key, _) = entry.ok()?;
-                    let k = key.value().to_string();
-                    k.starts_with(&prefix).then_some(k)
-                })
-                .collect()
-        };
-        // Delete in a write transaction.
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        let count = keys.len() as u32;
-        {
-            let mut table = txn.open_table(INSTANCES).map_err(map_err!(Table))?;
+        // Collect keys with a scan first, then delete them atomically.
+        let keys: Vec<String> = self
+            .backend
+            .scan_prefix(INSTANCES, &prefix)?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        self.backend.transaction(&mut |txn| {
             for key in &keys {
-                table.remove(key.as_str()).map_err(map_err!(Write))?;
+                txn.remove(INSTANCES, key)?;
+                journal_change(txn, INSTANCES, key, None)?;
             }
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(count)
+            Ok(())
+        })?;
+        Ok(keys.len() as u32)
     }
 
     // ── Nodes ──────────────────────────────────────────────────────
 
     /// Insert or update a node info.
     pub fn put_node(&self, node: &NodeInfo) -> StateResult<()> {
-        let value = serde_json::to_vec(node).map_err(map_err!(Serialize))?;
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        {
-            let mut table = txn.open_table(NODES).map_err(map_err!(Table))?;
-            table
-                .insert(node.id.as_str(), value.as_slice())
-                .map_err(map_err!(Write))?;
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(())
+        self.put_json(NODES, &node.id, node)
     }
 
     /// Get a node by ID.
     pub fn get_node(&self, node_id: &str) -> StateResult<Option<NodeInfo>> {
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(NODES).map_err(map_err!(Table))?;
-        match table.get(node_id).map_err(map_err!(Read))? {
-            Some(guard) => {
-                let node: NodeInfo =
-                    serde_json::from_slice(guard.value()).map_err(map_err!(Deserialize))?;
-                Ok(Some(node))
-            }
-            None => Ok(None),
-        }
+        self.get_json(NODES, node_id)
     }
 
     /// List all nodes.
     pub fn list_nodes(&self) -> StateResult<Vec<NodeInfo>> {
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(NODES).map_err(map_err!(Table))?;
-        let mut results = Vec::new();
-        for entry in table.iter().map_err(map_err!(Read))? {
-            let (_, value) = entry.map_err(map_err!(Read))?;
-            let node: NodeInfo =
-                serde_json::from_slice(value.value()).map_err(map_err!(Deserialize))?;
-            results.push(node);
-        }
-        Ok(results)
+        self.scan_json(NODES, "")
+    }
+
+    /// A page of nodes `keep` accepts, ordered by ID.
+    pub fn list_nodes_page(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+        keep: impl Fn(&NodeInfo) -> bool,
+    ) -> StateResult<Page<NodeInfo>> {
+        self.scan_page(NODES, "", after, limit, &keep)
     }
 
     /// Delete a node by ID. Returns true if it existed.
     pub fn delete_node(&self, node_id: &str) -> StateResult<bool> {
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        let existed;
-        {
-            let mut table = txn.open_table(NODES).map_err(map_err!(Table))?;
-            existed = table.remove(node_id).map_err(map_err!(Write))?.is_some();
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(existed)
+        self.backend.remove(NODES, node_id)
     }
 
     // ── Services ───────────────────────────────────────────────────
 
     /// Insert or update a service endpoint entry.
     pub fn put_service(&self, svc: &ServiceEndpoints) -> StateResult<()> {
-        let key = svc.table_key();
-        let value = serde_json::to_vec(svc).map_err(map_err!(Serialize))?;
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        {
-            let mut table = txn.open_table(SERVICES).map_err(map_err!(Table))?;
-            table
-                .insert(key.as_str(), value.as_slice())
-                .map_err(map_err!(Write))?;
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(())
+        self.put_replicated(SERVICES, &svc.table_key(), svc)
     }
 
     /// Get a service by namespace/name key.
     pub fn get_service(&self, key: &str) -> StateResult<Option<ServiceEndpoints>> {
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(SERVICES).map_err(map_err!(Table))?;
-        match table.get(key).map_err(map_err!(Read))? {
-            Some(guard) => {
-                let svc: ServiceEndpoints =
-                    serde_json::from_slice(guard.value()).map_err(map_err!(Deserialize))?;
-                Ok(Some(svc))
+        self.get_json(SERVICES, key)
+    }
+
+    // ── Secrets ────────────────────────────────────────────────────
+
+    /// Insert or update a secret, sealing its value when the store has a
+    /// secrets key.
+    pub fn put_secret(&self, secret: &Secret) -> StateResult<()> {
+        let key = secret.table_key();
+        match &self.secrets_key {
+            Some(secrets_key) => {
+                let sealed = Secret { value: secrets_key.seal(&key, &secret.value)?, ..secret.clone() };
+                self.put_replicated(SECRETS, &key, &sealed)
+            }
+            None => self.put_replicated(SECRETS, &key, secret),
+        }
+    }
+
+    /// Get a secret by namespace/name key, with its value opened.
+    pub fn get_secret(&self, key: &str) -> StateResult<Option<Secret>> {
+        self.get_json(SECRETS, key)?.map(|secret| self.open_secret(secret)).transpose()
+    }
+
+    /// Get a secret by namespace/name key as stored, its value still
+    /// sealed when the store has a secrets key; for reading its metadata.
+    pub fn get_stored_secret(&self, key: &str) -> StateResult<Option<Secret>> {
+        self.get_json(SECRETS, key)
+    }
+
+    /// List the secrets in a namespace as stored, their values still sealed
+    /// when the store has a secrets key. Listing never needs the values, so
+    /// none is opened; [`crate::secrets::value_len`] sizes them.
+    pub fn list_secrets(&self, namespace: &str) -> StateResult<Vec<Secret>> {
+        self.scan_json(SECRETS, &format!("{namespace}/"))
+    }
+
+    fn open_secret(&self, mut secret: Secret) -> StateResult<Secret> {
+        if !is_sealed(&secret.value) {
+            return Ok(secret);
+        }
+        let key = secret.table_key();
+        let Some(secrets_key) = &self.secrets_key else {
+            return Err(StateError::Encryption(format!("secret {key} is encrypted but no secrets key is configured")));
+        };
+        secret.value = secrets_key.open(&key, &secret.value)?;
+        Ok(secret)
+    }
+
+    /// Resolve `spec`'s secret references to their values. Fails when a
+    /// referenced secret does not exist.
+    pub fn mount_secrets(&self, spec: &DeploymentSpec) -> StateResult<MountedSecrets> {
+        let mut mounted = MountedSecrets::default();
+        for reference in &spec.secrets {
+            let key = format!("{}/{}", spec.namespace, reference.secret);
+            let secret = self.get_secret(&key)?.ok_or_else(|| StateError::NotFound(format!("secret {key}")))?;
+            mounted.updated_at = mounted.updated_at.max(secret.updated_at);
+            if let Some(env) = &reference.env {
+                mounted.env.push((env.clone(), secret.value.clone()));
+            }
+            if let Some(path) = &reference.path {
+                mounted.files.insert(path.clone(), secret.value.clone().into_bytes());
+            }
+            if reference.database_password {
+                mounted.database_password = Some(secret.value);
+            }
+        }
+        Ok(mounted)
+    }
+
+    /// Delete a secret by namespace/name key. Returns true if it existed.
+    pub fn delete_secret(&self, key: &str) -> StateResult<bool> {
+        self.write_replicated(SECRETS, key, None)
+    }
+
+    // ── API tokens ─────────────────────────────────────────────────
+
+    /// Insert or replace an API token.
+    pub fn put_api_token(&self, token: &ApiToken) -> StateResult<()> {
+        self.put_replicated(API_TOKENS, &token.digest, token)
+    }
+
+    /// Look up an API token by the digest of the presented token.
+    pub fn get_api_token(&self, digest: &str) -> StateResult<Option<ApiToken>> {
+        self.get_json(API_TOKENS, digest)
+    }
+
+    /// List all API tokens.
+    pub fn list_api_tokens(&self) -> StateResult<Vec<ApiToken>> {
+        self.scan_json(API_TOKENS, "")
+    }
+
+    /// Delete an API token by its id. Returns true if it existed.
+    pub fn delete_api_token(&self, id: &str) -> StateResult<bool> {
+        match self.list_api_tokens()?.into_iter().find(|token| token.id == id) {
+            Some(token) => self.write_replicated(API_TOKENS, &token.digest, None),
+            None => Ok(false),
+        }
+    }
+
+    // ── Feature flags ──────────────────────────────────────────────
+
+    /// Replace a deployment's feature flags.
+    pub fn put_flags(&self, flags: &DeploymentFlags) -> StateResult<()> {
+        self.put_replicated(FLAGS, &flags.deployment_id, flags)
+    }
+
+    /// Get a deployment's feature flags.
+    pub fn get_flags(&self, deployment_id: &str) -> StateResult<Option<DeploymentFlags>> {
+        self.get_json(FLAGS, deployment_id)
+    }
+
+    /// List the feature flags of every deployment that has any.
+    pub fn list_flags(&self) -> StateResult<Vec<DeploymentFlags>> {
+        self.scan_json(FLAGS, "")
+    }
+
+    /// Delete a deployment's feature flags. Returns true if they existed.
+    pub fn delete_flags(&self, deployment_id: &str) -> StateResult<bool> {
+        self.write_replicated(FLAGS, deployment_id, None)
+    }
+
+    // ── Config bundles ─────────────────────────────────────────────
+
+    /// Store a bundle under its digest. Returns the digest.
+    pub fn put_config_bundle(&self, bundle: &ConfigBundle) -> StateResult<String> {
+        let digest = bundle.digest();
+        self.put_replicated(CONFIG_BUNDLES, &digest, bundle)?;
+        Ok(digest)
+    }
+
+    /// Get a bundle by digest.
+    pub fn get_config_bundle(&self, digest: &str) -> StateResult<Option<ConfigBundle>> {
+        self.get_json(CONFIG_BUNDLES, digest)
+    }
+
+    /// Get a deployment's active and staged bundle.
+    pub fn get_deployment_config(&self, deployment_id: &str) -> StateResult<Option<DeploymentConfig>> {
+        self.get_json(DEPLOYMENT_CONFIGS, deployment_id)
+    }
+
+    /// List the config of every deployment that has a bundle.
+    pub fn list_deployment_configs(&self) -> StateResult<Vec<DeploymentConfig>> {
+        self.scan_json(DEPLOYMENT_CONFIGS, "")
+    }
+
+    /// Every deployment's active bundle, for nodes to serve.
+    pub fn active_config_bundles(&self) -> StateResult<Vec<(String, ConfigBundle)>> {
+        let mut bundles = Vec::new();
+        for config in self.list_deployment_configs()? {
+            let Some(digest) = config.active else {
+                continue;
+            };
+            match self.get_config_bundle(&digest)? {
+                Some(bundle) => bundles.push((config.deployment_id, bundle)),
+                None => warn!(deployment = %config.deployment_id, %digest, "active config bundle missing"),
+            }
+        }
+        Ok(bundles)
+    }
+
+    /// Stage `bundle` for `deployment_id`: store it, then record it as
+    /// staged at the revision that stored it. Staging the active bundle
+    /// again clears any staged one instead.
+    pub fn stage_config_bundle(
+        &self,
+        deployment_id: &str,
+        bundle: &ConfigBundle,
+        now: u64,
+    ) -> StateResult<DeploymentConfig> {
+        let digest = self.put_config_bundle(bundle)?;
+        let revision = self.state_revision()?;
+        let mut config = self.get_deployment_config(deployment_id)?.unwrap_or_else(|| DeploymentConfig {
+            deployment_id: deployment_id.to_string(),
+            active: None,
+            staged: None,
+            updated_at: now,
+        });
+        let replaced = config.staged.take().map(|staged| staged.digest);
+        if config.active.as_deref() != Some(digest.as_str()) {
+            config.staged = Some(StagedConfig {
+                digest,
+                revision,
+                staged_at: now,
+            });
+        }
+        config.updated_at = now;
+        self.put_replicated(DEPLOYMENT_CONFIGS, deployment_id, &config)?;
+        if let Some(replaced) = replaced {
+            self.prune_config_bundle(&replaced)?;
+        }
+        Ok(config)
+    }
+
+    /// Activate staged bundles whose barrier has been reached.
+    ///
+    /// `applied` maps node ids to the replica revision they last reported.
+    /// A staged bundle is activated once every listed node running an
+    /// instance of the deployment has applied the revision that stored it;
+    /// nodes not listed are not waited for. Returns the deployments whose
+    /// bundle was activated.
+    pub fn promote_staged_configs(&self, applied: &HashMap<String, u64>, now: u64) -> StateResult<Vec<String>> {
+        let mut promoted = Vec::new();
+        for mut config in self.list_deployment_configs()? {
+            let Some(staged) = config.staged.clone() else {
+                continue;
+            };
+            let waiting = self
+                .list_instances_for_deployment(&config.deployment_id)?
+                .into_iter()
+                .filter(|instance| applied.get(&instance.node_id).is_some_and(|r| *r < staged.revision))
+                .count();
+            if waiting > 0 {
+                debug!(deployment = %config.deployment_id, waiting, "config bundle waiting for nodes");
+                continue;
             }
-            None => Ok(None),
+            let previous = config.active.replace(staged.digest.clone());
+            config.staged = None;
+            config.updated_at = now;
+            self.put_replicated(DEPLOYMENT_CONFIGS, &config.deployment_id, &config)?;
+            info!(deployment = %config.deployment_id, digest = %staged.digest, "config bundle activated");
+            if let Some(previous) = previous {
+                self.prune_config_bundle(&previous)?;
+            }
+            promoted.push(config.deployment_id);
+        }
+        Ok(promoted)
+    }
+
+    /// Delete a deployment's config and the bundles nothing else uses.
+    /// Returns true if it existed.
+    pub fn delete_deployment_config(&self, deployment_id: &str) -> StateResult<bool> {
+        let Some(config) = self.get_deployment_config(deployment_id)? else {
+            return Ok(false);
+        };
+        self.write_replicated(DEPLOYMENT_CONFIGS, deployment_id, None)?;
+        for digest in config.active.into_iter().chain(config.staged.map(|s| s.digest)) {
+            self.prune_config_bundle(&digest)?;
         }
+        Ok(true)
+    }
+
+    /// Delete the bundle `digest` unless a deployment still references it.
+    fn prune_config_bundle(&self, digest: &str) -> StateResult<()> {
+        let referenced = self.list_deployment_configs()?.iter().any(|config| {
+            config.active.as_deref() == Some(digest)
+                || config.staged.as_ref().is_some_and(|staged| staged.digest == digest)
+        });
+        if !referenced {
+            self.write_replicated(CONFIG_BUNDLES, digest, None)?;
+        }
+        Ok(())
+    }
+
+    // ── Config maps ────────────────────────────────────────────────
+
+    /// Insert or update a config map.
+    pub fn put_config_map(&self, config_map: &ConfigMap) -> StateResult<()> {
+        self.put_json(CONFIG_MAPS, &config_map.table_key(), config_map)
+    }
+
+    /// Get a config map by namespace/name key.
+    pub fn get_config_map(&self, key: &str) -> StateResult<Option<ConfigMap>> {
+        self.get_json(CONFIG_MAPS, key)
+    }
+
+    /// List the config maps in a namespace.
+    pub fn list_config_maps(&self, namespace: &str) -> StateResult<Vec<ConfigMap>> {
+        self.scan_json(CONFIG_MAPS, &format!("{namespace}/"))
+    }
+
+    /// Delete a config map by namespace/name key. Returns true if it existed.
+    pub fn delete_config_map(&self, key: &str) -> StateResult<bool> {
+        self.backend.remove(CONFIG_MAPS, key)
+    }
+
+    /// The deployments in `namespace` that bind config map `name`.
+    pub fn config_map_bindings(&self, namespace: &str, name: &str) -> StateResult<Vec<DeploymentSpec>> {
+        Ok(self
+            .list_deployments_in_namespace(namespace)?
+            .into_iter()
+            .filter(|spec| spec.config_maps.iter().any(|r| r.config_map == name))
+            .collect())
+    }
+
+    /// Stage a config bundle for `spec` that holds each bound config map's
+    /// keys under its path, on top of the deployment's latest bundle.
+    /// Those directories, and the directories of `unbound` references, are
+    /// cleared first, so a removed key or config map disappears; a missing
+    /// config map leaves its directory empty. Returns `None` when the
+    /// bundle already matches.
+    pub fn bind_config_maps(
+        &self,
+        spec: &DeploymentSpec,
+        unbound: &[ConfigMapRef],
+        now: u64,
+    ) -> StateResult<Option<DeploymentConfig>> {
+        let config = self.get_deployment_config(&spec.id)?;
+        let latest = config.and_then(|config| config.staged.map(|staged| staged.digest).or(config.active));
+        let current = match &latest {
+            Some(digest) => self.get_config_bundle(digest)?.unwrap_or_default(),
+            None => ConfigBundle::default(),
+        };
+        let mut bundle = current.clone();
+        for reference in unbound.iter().chain(&spec.config_maps) {
+            let dir = reference.dir();
+            bundle.files.retain(|path, _| !path.starts_with(&dir));
+        }
+        for reference in &spec.config_maps {
+            let key = format!("{}/{}", spec.namespace, reference.config_map);
+            let Some(config_map) = self.get_config_map(&key)? else {
+                continue;
+            };
+            let dir = reference.dir();
+            bundle.files.extend(config_map.data.into_iter().map(|(key, value)| (format!("{dir}{key}"), value)));
+        }
+        if bundle == current {
+            return Ok(None);
+        }
+        self.stage_config_bundle(&spec.id, &bundle, now).map(Some)
+    }
+
+    // ── Webhooks ───────────────────────────────────────────────────
+
+    /// Insert or update a webhook, sealing its signing secret when the
+    /// store has a secrets key.
+    pub fn put_webhook(&self, webhook: &Webhook) -> StateResult<()> {
+        match &self.secrets_key {
+            Some(secrets_key) => {
+                let secret = secrets_key.seal(&webhook_aad(&webhook.id), &webhook.secret)?;
+                self.put_json(WEBHOOKS, &webhook.id, &Webhook { secret, ..webhook.clone() })
+            }
+            None => self.put_json(WEBHOOKS, &webhook.id, webhook),
+        }
+    }
+
+    /// Get a webhook by id, with its secret opened.
+    pub fn get_webhook(&self, id: &str) -> StateResult<Option<Webhook>> {
+        self.get_json(WEBHOOKS, id)?.map(|webhook| self.open_webhook(webhook)).transpose()
+    }
+
+    /// List every webhook, with their secrets opened.
+    pub fn list_webhooks(&self) -> StateResult<Vec<Webhook>> {
+        self.scan_json(WEBHOOKS, "")?.into_iter().map(|webhook| self.open_webhook(webhook)).collect()
+    }
+
+    fn open_webhook(&self, mut webhook: Webhook) -> StateResult<Webhook> {
+        if !is_sealed(&webhook.secret) {
+            return Ok(webhook);
+        }
+        let Some(secrets_key) = &self.secrets_key else {
+            return Err(StateError::Encryption(format!(
+                "webhook {} is encrypted but no secrets key is configured",
+                webhook.id
+            )));
+        };
+        webhook.secret = secrets_key.open(&webhook_aad(&webhook.id), &webhook.secret)?;
+        Ok(webhook)
+    }
+
+    /// Delete a webhook by id. Returns true if it existed.
+    pub fn delete_webhook(&self, id: &str) -> StateResult<bool> {
+        self.backend.remove(WEBHOOKS, id)
+    }
+
+    // ── Idempotency keys ───────────────────────────────────────────
+
+    /// Claim idempotency `key` for a request with `fingerprint`, storing a
+    /// record with no response yet. Returns the record already held
+    /// instead, unless it has expired (see [`IdempotencyRecord::expires_at`]).
+    pub fn reserve_idempotency_key(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        now: u64,
+    ) -> StateResult<Option<IdempotencyRecord>> {
+        let mut held = None;
+        self.backend.transaction(&mut |txn| {
+            if let Some(bytes) = txn.get(IDEMPOTENCY_KEYS, key)? {
+                // A record that no longer decodes is replaced like an expired one.
+                if let Ok(record) = decode::<IdempotencyRecord>(&bytes)
+                    && now < record.expires_at()
+                {
+                    held = Some(record);
+                    return Ok(());
+                }
+            }
+            let record = IdempotencyRecord {
+                key: key.to_string(),
+                fingerprint: fingerprint.to_string(),
+                status: None,
+                body: String::new(),
+                created_at: now,
+            };
+            txn.put(IDEMPOTENCY_KEYS, key, &encode(&record)?)
+        })?;
+        Ok(held)
+    }
+
+    /// Store the response to a reserved key's request.
+    pub fn complete_idempotency_key(&self, record: &IdempotencyRecord) -> StateResult<()> {
+        self.put_json(IDEMPOTENCY_KEYS, &record.key, record)
+    }
+
+    /// Drop a key, so the request can be retried in full. Returns true if
+    /// it existed.
+    pub fn release_idempotency_key(&self, key: &str) -> StateResult<bool> {
+        self.backend.remove(IDEMPOTENCY_KEYS, key)
+    }
+
+    /// Drop expired keys. Returns how many.
+    pub fn prune_idempotency_keys(&self, now: u64) -> StateResult<usize> {
+        let mut pruned = 0;
+        for record in self.scan_json::<IdempotencyRecord>(IDEMPOTENCY_KEYS, "")? {
+            if now >= record.expires_at() && self.backend.remove(IDEMPOTENCY_KEYS, &record.key)? {
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
     }
 
     // ── Metrics ────────────────────────────────────────────────────
 
     /// Insert a metrics snapshot.
     pub fn put_metrics(&self, snapshot: &MetricsSnapshot) -> StateResult<()> {
-        let key = snapshot.table_key();
-        let value = serde_json::to_vec(snapshot).map_err(map_err!(Serialize))?;
-        let txn = self.db.begin_write().map_err(map_err!(Transaction))?;
-        {
-            let mut table = txn.open_table(METRICS).map_err(map_err!(Table))?;
-            table
-                .insert(key.as_str(), value.as_slice())
-                .map_err(map_err!(Write))?;
-        }
-        txn.commit().map_err(map_err!(Transaction))?;
-        Ok(())
+        self.put_json(METRICS, &snapshot.table_key(), snapshot)
     }
 
     /// Get recent metrics snapshots for a deployment (by key prefix scan).
@@ -325,23 +1017,469 @@ impl StateStore {
         deployment_id: &str,
         limit: usize,
     ) -> StateResult<Vec<MetricsSnapshot>> {
-        let prefix = format!("{deployment_id}:");
-        let txn = self.db.begin_read().map_err(map_err!(Transaction))?;
-        let table = txn.open_table(METRICS).map_err(map_err!(Table))?;
-        let mut results = Vec::new();
-        for entry in table.iter().map_err(map_err!(Read))? {
-            let (key, value) = entry.map_err(map_err!(Read))?;
-            if key.value().starts_with(&prefix) {
-                let snapshot: MetricsSnapshot =
-                    serde_json::from_slice(value.value()).map_err(map_err!(Deserialize))?;
-                results.push(snapshot);
-                if results.len() >= limit {
-                    break;
+        let mut snapshots: Vec<MetricsSnapshot> =
+            self.scan_json(METRICS, &format!("{deployment_id}:"))?;
+        snapshots.truncate(limit);
+        Ok(snapshots)
+    }
+
+    // ── Usage ──────────────────────────────────────────────────────
+
+    /// Append a usage event to the durable stream and fold it into its
+    /// deployment's rollup window.
+    ///
+    /// The append, the idempotency index, and the rollup update commit in a
+    /// single transaction, so each event is counted exactly once even if the
+    /// producer retries. Returns the assigned sequence, or `None` if an event
+    /// with the same `event_id` was already recorded.
+    pub fn record_usage(&self, event: &UsageEvent) -> StateResult<Option<u64>> {
+        let mut assigned = None;
+        self.backend.transaction(&mut |txn| {
+            assigned = None;
+            if txn.get(USAGE_EVENT_IDS, &event.event_id)?.is_some() {
+                debug!(event_id = %event.event_id, "duplicate usage event ignored");
+                return Ok(());
+            }
+
+            let sequence = match txn.last_key(USAGE_EVENTS)? {
+                Some(key) => key.parse::<u64>().map_err(map_err!(Deserialize))? + 1,
+                None => 1,
+            };
+            let record = UsageRecord {
+                sequence,
+                event: event.clone(),
+            };
+            txn.put(USAGE_EVENTS, &sequence_key(sequence), &encode(&record)?)?;
+            txn.put(USAGE_EVENT_IDS, &event.event_id, &encode(&sequence)?)?;
+
+            let mut rollup = UsageRollup::empty(&event.deployment_id, event.window_start());
+            let key = rollup.table_key();
+            if let Some(bytes) = txn.get(USAGE_ROLLUPS, &key)? {
+                match decode(&bytes) {
+                    Ok(existing) => rollup = existing,
+                    // Restart the window rather than refuse new usage.
+                    Err(err) => {
+                        let reason = err.corruption_reason(USAGE_ROLLUPS, &key)?;
+                        self.integrity.record_corrupt();
+                        integrity::quarantine(txn, USAGE_ROLLUPS, &key, &bytes, &reason)?;
+                    }
                 }
             }
+            rollup.add(event);
+            txn.put(USAGE_ROLLUPS, &key, &encode(&rollup)?)?;
+
+            assigned = Some(sequence);
+            Ok(())
+        })?;
+        if let Some(sequence) = assigned {
+            debug!(sequence, deployment = %event.deployment_id, "usage event recorded");
         }
+        Ok(assigned)
+    }
+
+    /// Read usage records with a sequence greater than `after`, oldest first.
+    pub fn list_usage_events(&self, after: u64, limit: usize) -> StateResult<Vec<UsageRecord>> {
+        let start = sequence_key(after.saturating_add(1));
+        let entries = self.backend.scan_from(USAGE_EVENTS, &start, limit)?;
+        Ok(self
+            .decode_entries(USAGE_EVENTS, entries)?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+
+    /// List usage rollups for a deployment with windows starting at or after `since`.
+    pub fn list_usage_rollups(
+        &self,
+        deployment_id: &str,
+        since: u64,
+    ) -> StateResult<Vec<UsageRollup>> {
+        let mut results: Vec<UsageRollup> =
+            self.scan_json(USAGE_ROLLUPS, &format!("{deployment_id}:"))?;
+        results.retain(|r| r.window_start >= since);
+        results.sort_by_key(|r| r.window_start);
         Ok(results)
     }
+
+    /// Drop usage older than [`USAGE_RETENTION_SECS`]: events that started
+    /// before `now` less the retention, with their ids, and rollups whose
+    /// window ended before it. The newest event is always kept, so
+    /// sequences keep increasing. Returns how many events and rollups were
+    /// dropped.
+    pub fn prune_usage(&self, now: u64) -> StateResult<usize> {
+        let cutoff = now.saturating_sub(USAGE_RETENTION_SECS);
+        let newest = self.backend.last_key(USAGE_EVENTS)?;
+        let mut pruned = 0;
+        let mut start = sequence_key(1);
+        // Sequences follow arrival, not start times, so the scan stops at
+        // the first event still inside retention: an event recorded late
+        // (a producer retrying) is pruned with those recorded before it.
+        loop {
+            let entries = self.backend.scan_from(USAGE_EVENTS, &start, USAGE_PRUNE_BATCH)?;
+            let scanned = entries.len();
+            let expired: Vec<UsageRecord> = self
+                .decode_entries(USAGE_EVENTS, entries)?
+                .into_iter()
+                .map(|(_, record)| record)
+                .take_while(|record: &UsageRecord| {
+                    record.event.started_at_ms / 1000 < cutoff
+                        && newest.as_deref() != Some(sequence_key(record.sequence).as_str())
+                })
+                .collect();
+            let Some(last) = expired.last() else { break };
+            start = sequence_key(last.sequence + 1);
+            self.backend.transaction(&mut |txn| {
+                for record in &expired {
+                    txn.remove(USAGE_EVENTS, &sequence_key(record.sequence))?;
+                    txn.remove(USAGE_EVENT_IDS, &record.event.event_id)?;
+                }
+                Ok(())
+            })?;
+            pruned += expired.len();
+            if expired.len() < scanned || scanned < USAGE_PRUNE_BATCH {
+                break;
+            }
+        }
+        for rollup in self.scan_json::<UsageRollup>(USAGE_ROLLUPS, "")? {
+            if rollup.window_start + USAGE_ROLLUP_WINDOW_SECS <= cutoff
+                && self.backend.remove(USAGE_ROLLUPS, &rollup.table_key())?
+            {
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    // ── Cluster events ─────────────────────────────────────────────
+
+    /// Append an event, assigning its sequence, and drop the oldest one
+    /// past [`CLUSTER_EVENT_RETENTION`]. Returns the sequence.
+    pub fn record_event(&self, event: &ClusterEvent) -> StateResult<u64> {
+        let mut sequence = 0;
+        self.backend.transaction(&mut |txn| {
+            sequence = match txn.last_key(CLUSTER_EVENTS)? {
+                Some(key) => key.parse::<u64>().map_err(map_err!(Deserialize))? + 1,
+                None => 1,
+            };
+            let record = ClusterEvent { sequence, ..event.clone() };
+            txn.put(CLUSTER_EVENTS, &sequence_key(sequence), &encode(&record)?)?;
+            if sequence > CLUSTER_EVENT_RETENTION {
+                txn.remove(CLUSTER_EVENTS, &sequence_key(sequence - CLUSTER_EVENT_RETENTION))?;
+            }
+            Ok(())
+        })?;
+        debug!(sequence, kind = ?event.kind, "cluster event recorded");
+        Ok(sequence)
+    }
+
+    /// Up to `limit` events with a sequence greater than `after` that
+    /// `keep` accepts, oldest first.
+    pub fn list_events(
+        &self,
+        after: u64,
+        limit: usize,
+        keep: impl Fn(&ClusterEvent) -> bool,
+    ) -> StateResult<Vec<ClusterEvent>> {
+        let after = (after > 0).then(|| sequence_key(after));
+        Ok(self.scan_page(CLUSTER_EVENTS, "", after.as_deref(), limit, &keep)?.items)
+    }
+
+    // ── Replication ────────────────────────────────────────────────
+
+    /// Latest revision of the replicated tables (0 if nothing was written).
+    pub fn state_revision(&self) -> StateResult<u64> {
+        self.backend
+            .last_key(STATE_CHANGES)?
+            .map_or(Ok(0), |key| key.parse::<u64>().map_err(map_err!(Deserialize)))
+    }
+
+    /// Build the payload that brings a replica at `revision` up to date.
+    ///
+    /// Returns up to `limit` deltas when the journal still covers the
+    /// replica's position, a full snapshot when it does not (or the replica
+    /// is new), and `None` when the replica is already current.
+    pub fn replica_sync_since(
+        &self,
+        revision: u64,
+        limit: usize,
+    ) -> StateResult<Option<ReplicaSync>> {
+        let current = self.state_revision()?;
+        if revision == current {
+            return Ok(None);
+        }
+
+        let oldest = self
+            .backend
+            .scan_from(STATE_CHANGES, "", 1)?
+            .first()
+            .map(|(key, _)| key.parse::<u64>().map_err(map_err!(Deserialize)))
+            .transpose()?;
+        let covered = oldest.is_some_and(|oldest| revision + 1 >= oldest);
+        if revision == 0 || revision > current || !covered {
+            return self.replica_snapshot(current).map(Some);
+        }
+
+        let start = sequence_key(revision + 1);
+        let entries = self.backend.scan_from(STATE_CHANGES, &start, limit)?;
+        let changes: Vec<StateChange> = self
+            .decode_entries(STATE_CHANGES, entries)?
+            .into_iter()
+            .map(|(_, change)| change)
+            .collect();
+        // A quarantined journal entry leaves a gap the replica cannot
+        // bridge with deltas.
+        let contiguous = changes
+            .iter()
+            .zip(revision + 1..)
+            .all(|(change, expected)| change.revision == expected);
+        if !contiguous {
+            return self.replica_snapshot(current).map(Some);
+        }
+        Ok(Some(ReplicaSync::Deltas { changes }))
+    }
+
+    /// Snapshot every replicated table, labelled with `revision`.
+    ///
+    /// The revision is read before scanning, so writes racing the snapshot
+    /// are re-sent as deltas and applied idempotently.
+    fn replica_snapshot(&self, revision: u64) -> StateResult<ReplicaSync> {
+        let mut entries = Vec::new();
+        for table in REPLICATED_TABLES {
+            let scanned = self.backend.scan_prefix(table, "")?;
+            for (key, value) in self.decode_entries(table, scanned)? {
+                entries.push(StateChange {
+                    revision,
+                    table: table.to_string(),
+                    key,
+                    value: Some(value),
+                });
+            }
+        }
+        debug!(revision, entries = entries.len(), "replica snapshot built");
+        Ok(ReplicaSync::Snapshot { revision, entries })
+    }
+
+    // ── Schema ─────────────────────────────────────────────────────
+
+    /// Schema version the stored records follow (1 if never recorded).
+    pub fn schema_version(&self) -> StateResult<u32> {
+        Ok(self.get_json(STATE_META, SCHEMA_VERSION_KEY)?.unwrap_or(1))
+    }
+
+    /// Upgrade stored records to [`migrate::SCHEMA_VERSION`]; see [`migrate`].
+    ///
+    /// Fails, changing nothing, when the store was written by a newer
+    /// release.
+    pub fn migrate(&self) -> StateResult<MigrationReport> {
+        self.run_migrations(migrate::MIGRATIONS, migrate::SCHEMA_VERSION)
+    }
+
+    fn run_migrations(&self, migrations: &[Migration], target: u32) -> StateResult<MigrationReport> {
+        let from = self.schema_version()?;
+        if from > target {
+            return Err(StateError::Schema(format!(
+                "state schema version {from} is newer than this release supports ({target})"
+            )));
+        }
+        let mut report = MigrationReport { from, to: from, records_migrated: 0 };
+        for migration in migrations.iter().filter(|m| m.version > from && m.version <= target) {
+            let mut migrated = 0;
+            for table in migration.tables {
+                migrated += self.migrate_table(table, migration.upgrade)?;
+            }
+            self.put_json(STATE_META, SCHEMA_VERSION_KEY, &migration.version)?;
+            info!(version = migration.version, migrated, "state migration applied: {}", migration.description);
+            report.to = migration.version;
+            report.records_migrated += migrated;
+        }
+        if report.to < target {
+            self.put_json(STATE_META, SCHEMA_VERSION_KEY, &target)?;
+            report.to = target;
+        }
+        Ok(report)
+    }
+
+    /// Rewrite the records of `table` that `upgrade` changes. Records that
+    /// fail verification are left for [`StateStore::verify_integrity`].
+    fn migrate_table(&self, table: &str, upgrade: fn(&mut serde_json::Value) -> bool) -> StateResult<u64> {
+        let replicated = REPLICATED_TABLES.contains(&table);
+        let mut migrated = 0;
+        let mut start = String::new();
+        loop {
+            let batch = self.backend.scan_from(table, &start, VERIFY_BATCH)?;
+            let exhausted = batch.len() < VERIFY_BATCH;
+            for (key, bytes) in batch {
+                start = format!("{key}\0");
+                let Ok(mut value) = decode::<serde_json::Value>(&bytes) else {
+                    continue;
+                };
+                if !upgrade(&mut value) {
+                    continue;
+                }
+                if replicated {
+                    let json = serde_json::to_vec(&value).map_err(map_err!(Serialize))?;
+                    self.write_replicated(table, &key, Some(&json))?;
+                } else {
+                    self.put_json(table, &key, &value)?;
+                }
+                migrated += 1;
+            }
+            if exhausted {
+                return Ok(migrated);
+            }
+        }
+    }
+
+    // ── Integrity ──────────────────────────────────────────────────
+
+    /// Verify every record in every table, quarantining corrupt ones.
+    ///
+    /// Used by `warpd --verify-state` at startup; the report is also kept
+    /// for [`StateStore::integrity_status`].
+    pub fn verify_integrity(&self) -> StateResult<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        for table in ALL_TABLES.iter().copied().filter(|t| *t != QUARANTINE) {
+            report.tables_scanned += 1;
+            let mut start = String::new();
+            loop {
+                let batch = self.backend.scan_from(table, &start, VERIFY_BATCH)?;
+                let exhausted = batch.len() < VERIFY_BATCH;
+                for (key, bytes) in batch {
+                    report.records_checked += 1;
+                    if !integrity::is_sealed(&bytes) {
+                        report.unsealed_records += 1;
+                    }
+                    if let Err(err) = decode::<serde_json::Value>(&bytes) {
+                        report
+                            .quarantined
+                            .extend(self.quarantine(table, &key, &bytes, &err.to_string())?);
+                    }
+                    // Smallest key after this one.
+                    start = format!("{key}\0");
+                }
+                if exhausted {
+                    break;
+                }
+            }
+        }
+        report.completed_at = integrity::now_secs();
+        self.integrity.set_last_scan(&report);
+        info!(
+            backend = self.backend_name(),
+            records = report.records_checked,
+            unsealed = report.unsealed_records,
+            quarantined = report.quarantined.len(),
+            "state integrity scan complete"
+        );
+        Ok(report)
+    }
+
+    /// Corrupt records detected by this process (on read or by a scan).
+    pub fn corrupt_records_detected(&self) -> u64 {
+        self.integrity.corrupt_detected()
+    }
+
+    /// Records currently held in quarantine.
+    pub fn list_quarantined(&self) -> StateResult<Vec<QuarantinedRecord>> {
+        self.scan_json(QUARANTINE, "")
+    }
+
+    /// Counters, last scan, and quarantine contents for the admin report.
+    pub fn integrity_status(&self) -> StateResult<IntegrityStatus> {
+        Ok(IntegrityStatus {
+            corrupt_records_detected: self.corrupt_records_detected(),
+            last_scan: self.integrity.last_scan(),
+            quarantined: self.list_quarantined()?,
+        })
+    }
+}
+
+/// Zero-padded sequence key so lexical order matches numeric order.
+fn sequence_key(sequence: u64) -> String {
+    format!("{sequence:020}")
+}
+
+/// Associated data a webhook's secret is sealed with. Secret keys never
+/// contain `:`, so a sealed secret value cannot be swapped in for it.
+fn webhook_aad(id: &str) -> String {
+    format!("{WEBHOOKS}:{id}")
+}
+
+/// Usage events read per transaction when pruning.
+const USAGE_PRUNE_BATCH: usize = 1000;
+
+/// Key of revision `revision` of deployment `key`.
+fn revision_key(key: &str, revision: u64) -> String {
+    format!("{key}:{}", sequence_key(revision))
+}
+
+/// The revision number in a [`revision_key`].
+fn revision_number(revision_key: &str) -> StateResult<u64> {
+    let (_, revision) = revision_key.rsplit_once(':').unwrap_or(("", revision_key));
+    revision.parse::<u64>().map_err(map_err!(Deserialize))
+}
+
+fn deleted_version_key(key: &str) -> String {
+    format!("{DELETED_VERSION_PREFIX}{key}")
+}
+
+/// A deployment's version from whether it exists and its revisions.
+fn version(exists: bool, revisions: &[DeploymentRevision]) -> Option<u64> {
+    exists.then(|| revisions.last().map_or(0, |latest| latest.revision))
+}
+
+/// Append `spec` to deployment `key`'s history unless it matches the
+/// latest revision apart from `updated_at`, dropping the oldest
+/// revisions past [`DEPLOYMENT_REVISION_RETENTION`]. A deployment
+/// without revisions starts at `first`.
+fn record_revision(
+    txn: &mut dyn BackendTxn,
+    key: &str,
+    spec: &DeploymentSpec,
+    revisions: &[DeploymentRevision],
+    first: u64,
+) -> StateResult<()> {
+    let latest = revisions.last();
+    if latest.is_some_and(|latest| DeploymentSpec { updated_at: spec.updated_at, ..latest.spec.clone() } == *spec) {
+        return Ok(());
+    }
+    let revision = latest.map_or(first, |latest| latest.revision + 1);
+    let record = DeploymentRevision { deployment_id: key.to_string(), revision, spec: spec.clone() };
+    txn.put(DEPLOYMENT_REVISIONS, &revision_key(key, revision), &encode(&record)?)?;
+    let excess = (revisions.len() as u64 + 1).saturating_sub(DEPLOYMENT_REVISION_RETENTION);
+    for old in revisions.iter().take(excess as usize) {
+        txn.remove(DEPLOYMENT_REVISIONS, &revision_key(key, old.revision))?;
+    }
+    debug!(%key, revision, "deployment revision recorded");
+    Ok(())
+}
+
+/// Append a change to the replication journal inside an open transaction,
+/// trimming it to the last [`STATE_CHANGE_RETENTION`] entries.
+fn journal_change(
+    txn: &mut dyn BackendTxn,
+    table: &str,
+    key: &str,
+    value: Option<&[u8]>,
+) -> StateResult<()> {
+    let revision = match txn.last_key(STATE_CHANGES)? {
+        Some(last) => last.parse::<u64>().map_err(map_err!(Deserialize))? + 1,
+        None => 1,
+    };
+    let change = StateChange {
+        revision,
+        table: table.to_string(),
+        key: key.to_string(),
+        value: value
+            .map(serde_json::from_slice)
+            .transpose()
+            .map_err(map_err!(Deserialize))?,
+    };
+    txn.put(STATE_CHANGES, &sequence_key(revision), &encode(&change)?)?;
+    if revision > STATE_CHANGE_RETENTION {
+        txn.remove(STATE_CHANGES, &sequence_key(revision - STATE_CHANGE_RETENTION))?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -355,11 +1493,12 @@ mod tests {
             namespace: namespace.to_string(),
             name: name.to_string(),
             source: "file://./test.wasm".to_string(),
-            trigger: TriggerConfig::Http { port: Some(8080) },
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
             instances: InstanceConstraints { min: 1, max: 10 },
             resources: ResourceLimits {
                 memory_bytes: 64 * 1024 * 1024,
                 cpu_weight: 100,
+                execution_budget_ms: None,
             },
             scaling: None,
             health: Some(HealthConfig {
@@ -372,6 +1511,12 @@ mod tests {
             env: HashMap::new(),
             created_at: 1000,
             updated_at: 1000,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
         }
     }
 
@@ -400,7 +1545,189 @@ mod tests {
             used_cpu_weight: 0,
             labels: HashMap::new(),
             last_heartbeat: 1000,
+            pressure_until: None,
+            replica_revision: None,
+            calibrated_cpu_weight: None,
+            cordoned: false,
+        }
+    }
+
+    // ── Paged listings ─────────────────────────────────────────────
+
+    #[test]
+    fn deployment_pages_follow_key_order_and_filter() {
+        let store = StateStore::open_in_memory().unwrap();
+        for name in ["e", "a", "d", "b", "c"] {
+            let mut spec = test_deployment("default", name);
+            spec.paused = name == "b";
+            store.put_deployment(&spec).unwrap();
+        }
+        let names = |page: &Page<DeploymentSpec>| page.items.iter().map(|d| d.name.clone()).collect::<Vec<_>>();
+
+        let first = store.list_deployments_page(None, None, 2, |d| !d.paused).unwrap();
+        assert_eq!(names(&first), ["a", "c"]);
+        assert_eq!(first.next.as_deref(), Some("default/c"));
+        // An entry added before the cursor is not returned twice.
+        store.put_deployment(&test_deployment("default", "aa")).unwrap();
+        let second = store.list_deployments_page(None, first.next.as_deref(), 2, |d| !d.paused).unwrap();
+        assert_eq!(names(&second), ["d", "e"]);
+        assert_eq!(second.next, None);
+
+        let all = store.list_deployments_page(None, None, usize::MAX, |_| true).unwrap();
+        assert_eq!(all.items.len(), 6);
+        assert_eq!(all.next, None);
+    }
+
+    #[test]
+    fn namespaces_scope_deployment_keys() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_deployment(&test_deployment("prod", "api")).unwrap();
+        store.put_deployment(&test_deployment("staging", "api")).unwrap();
+        store.put_deployment(&test_deployment("prod", "web")).unwrap();
+        store
+            .put_secret(&Secret {
+                namespace: "ops".into(),
+                name: "token".into(),
+                value: "x".into(),
+                created_at: 0,
+                updated_at: 0,
+            })
+            .unwrap();
+
+        let prod = store.list_deployments_in_namespace("prod").unwrap();
+        assert_eq!(prod.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), ["api", "web"]);
+        let page = store.list_deployments_page(Some("staging"), None, 10, |_| true).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].namespace, "staging");
+
+        let summary = |name: &str, deployments, secrets| NamespaceSummary { name: name.into(), deployments, secrets };
+        assert_eq!(
+            store.list_namespaces().unwrap(),
+            [summary("ops", 0, 1), summary("prod", 2, 0), summary("staging", 1, 0)]
+        );
+
+        let mut bare = test_deployment("", "api");
+        bare.id = String::new();
+        bare.fill_defaults();
+        assert_eq!((bare.namespace.as_str(), bare.id.as_str()), (DEFAULT_NAMESPACE, "default/api"));
+    }
+
+    #[test]
+    fn spec_changes_are_recorded_as_revisions() {
+        let store = StateStore::open_in_memory().unwrap();
+        let mut spec = test_deployment("default", "api");
+        store.put_deployment(&spec).unwrap();
+        // Touching only `updated_at` is not a new revision.
+        spec.updated_at = 2000;
+        store.put_deployment(&spec).unwrap();
+        for max in 2..=(DEPLOYMENT_REVISION_RETENTION as u32 + 2) {
+            spec.instances.max = max;
+            store.put_deployment(&spec).unwrap();
+        }
+        store.put_deployment(&test_deployment("default", "api-v2")).unwrap();
+
+        let revisions = store.list_deployment_revisions("default/api").unwrap();
+        assert_eq!(revisions.len() as u64, DEPLOYMENT_REVISION_RETENTION);
+        assert_eq!(revisions.first().unwrap().revision, 3);
+        assert_eq!(revisions.last().unwrap().revision, DEPLOYMENT_REVISION_RETENTION + 2);
+        assert!(store.get_deployment_revision("default/api", 2).unwrap().is_none());
+        assert_eq!(store.get_deployment_revision("default/api", 3).unwrap().unwrap().spec.instances.max, 3);
+
+        store.delete_deployment("default/api").unwrap();
+        assert!(store.list_deployment_revisions("default/api").unwrap().is_empty());
+        assert_eq!(store.list_deployment_revisions("default/api-v2").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn write_probe_leaves_nothing_behind() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.check_writable().unwrap();
+        assert!(store.backend.get(REPLICA_META, WRITE_PROBE_KEY).unwrap().is_none());
+    }
+
+    #[test]
+    fn conditional_deployment_writes_check_the_version() {
+        let store = StateStore::open_in_memory().unwrap();
+        let mut spec = test_deployment("default", "api");
+        assert_eq!(store.deployment_version("default/api").unwrap(), None);
+        assert!(!store.put_deployment_if(&spec, DeploymentPrecondition::Exists).unwrap());
+        assert!(store.put_deployment_if(&spec, DeploymentPrecondition::Absent).unwrap());
+        assert!(!store.put_deployment_if(&spec, DeploymentPrecondition::Absent).unwrap());
+        assert_eq!(store.deployment_version("default/api").unwrap(), Some(1));
+
+        spec.instances.max = 5;
+        assert!(!store.put_deployment_if(&spec, DeploymentPrecondition::Version(2)).unwrap());
+        assert!(store.put_deployment_if(&spec, DeploymentPrecondition::Version(1)).unwrap());
+        assert_eq!(store.deployment_version("default/api").unwrap(), Some(2));
+
+        assert_eq!(store.delete_deployment_if("default/api", DeploymentPrecondition::Version(1)).unwrap(), None);
+        assert_eq!(store.delete_deployment_if("default/api", DeploymentPrecondition::Version(2)).unwrap(), Some(true));
+        assert_eq!(store.delete_deployment_if("default/api", DeploymentPrecondition::Any).unwrap(), Some(false));
+
+        // Re-created, it continues past the deleted one's versions, so an
+        // ETag from before the delete no longer matches.
+        assert!(store.put_deployment_if(&spec, DeploymentPrecondition::Absent).unwrap());
+        assert_eq!(store.deployment_version("default/api").unwrap(), Some(3));
+        assert!(!store.put_deployment_if(&spec, DeploymentPrecondition::Version(1)).unwrap());
+        assert!(store.backend.get(STATE_META, &deleted_version_key("default/api")).unwrap().is_none());
+    }
+
+    #[test]
+    fn idempotency_keys_are_held_until_they_expire() {
+        let store = StateStore::open_in_memory().unwrap();
+        assert_eq!(store.reserve_idempotency_key("k1", "fp", 100).unwrap(), None);
+        let held = store.reserve_idempotency_key("k1", "other", 101).unwrap().unwrap();
+        assert_eq!((held.fingerprint.as_str(), held.status), ("fp", None));
+
+        let done = IdempotencyRecord { status: Some(201), body: "{}".into(), ..held };
+        store.complete_idempotency_key(&done).unwrap();
+        assert_eq!(store.reserve_idempotency_key("k1", "fp", 102).unwrap(), Some(done));
+
+        // Released keys and expired ones can be reserved again.
+        assert_eq!(store.reserve_idempotency_key("k2", "fp", 100).unwrap(), None);
+        assert!(store.release_idempotency_key("k2").unwrap());
+        assert_eq!(store.reserve_idempotency_key("k2", "fp", 100).unwrap(), None);
+        let expired = 100 + IDEMPOTENCY_KEY_TTL_SECS;
+        assert_eq!(store.reserve_idempotency_key("k1", "fp2", expired).unwrap(), None);
+        assert_eq!(store.prune_idempotency_keys(expired).unwrap(), 1);
+        assert!(!store.release_idempotency_key("k2").unwrap());
+    }
+
+    #[test]
+    fn abandoned_idempotency_reservations_expire_early() {
+        let store = StateStore::open_in_memory().unwrap();
+        assert_eq!(store.reserve_idempotency_key("k1", "fp", 100).unwrap(), None);
+        let pending = 100 + IDEMPOTENCY_PENDING_TTL_SECS;
+        assert!(store.reserve_idempotency_key("k1", "fp", pending - 1).unwrap().is_some());
+        assert_eq!(store.prune_idempotency_keys(pending).unwrap(), 1);
+        assert_eq!(store.reserve_idempotency_key("k1", "fp", pending).unwrap(), None);
+        assert_eq!(store.reserve_idempotency_key("k1", "fp", 2 * pending).unwrap(), None);
+    }
+
+    #[test]
+    fn instance_pages_stay_within_their_deployment() {
+        let store = StateStore::open_in_memory().unwrap();
+        for index in 0..300 {
+            store.put_instance(&test_instance("dep-1", index)).unwrap();
+        }
+        store.put_instance(&test_instance("dep-10", 0)).unwrap();
+        store.put_node(&test_node("node-b")).unwrap();
+        store.put_node(&test_node("node-a")).unwrap();
+
+        let mut after = None;
+        let mut seen = 0;
+        loop {
+            let page = store.list_instances_page("dep-1", after.as_deref(), 120, |_| true).unwrap();
+            assert!(page.items.iter().all(|i| i.deployment_id == "dep-1"));
+            seen += page.items.len();
+            match page.next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
         }
+        assert_eq!(seen, 300);
+        let nodes = store.list_nodes_page(Some("node-a"), 10, |_| true).unwrap();
+        assert_eq!(nodes.items.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), ["node-b"]);
     }
 
     // ── Deployment CRUD ────────────────────────────────────────────
@@ -559,6 +1886,257 @@ mod tests {
         assert_eq!(retrieved, Some(svc));
     }
 
+    // ── Secret CRUD ────────────────────────────────────────────────
+
+    #[test]
+    fn secrets_are_listed_per_namespace() {
+        let store = StateStore::open_in_memory().unwrap();
+        let secret = |namespace: &str, name: &str| Secret {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            value: "hunter2".to_string(),
+            created_at: 1000,
+            updated_at: 1000,
+        };
+        store.put_secret(&secret("default", "db-password")).unwrap();
+        store.put_secret(&secret("default", "api-key")).unwrap();
+        store.put_secret(&secret("prod", "db-password")).unwrap();
+
+        assert_eq!(
+            store.get_secret("default/db-password").unwrap(),
+            Some(secret("default", "db-password"))
+        );
+        let names: Vec<String> = store.list_secrets("default").unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["api-key", "db-password"]);
+
+        assert!(store.delete_secret("default/db-password").unwrap());
+        assert!(!store.delete_secret("default/db-password").unwrap());
+        assert_eq!(store.list_secrets("default").unwrap().len(), 1);
+        assert_eq!(store.list_secrets("prod").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn secrets_are_sealed_at_rest_and_mounted_for_deployments() {
+        let backend = Arc::new(RedbBackend::open_in_memory().unwrap());
+        let plain = StateStore::with_backend(backend.clone());
+        let store = StateStore::with_backend(backend).with_secrets_key(SecretsKey::generate().unwrap());
+        let secret = |name: &str, value: &str, updated_at| Secret {
+            namespace: "default".to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            created_at: 1000,
+            updated_at,
+        };
+        plain.put_secret(&secret("legacy", "old", 1000)).unwrap();
+        store.put_secret(&secret("db-password", "hunter2", 2000)).unwrap();
+
+        let raw: Secret = store.get_json(SECRETS, "default/db-password").unwrap().unwrap();
+        assert!(is_sealed(&raw.value));
+        assert_eq!(store.get_secret("default/db-password").unwrap().unwrap().value, "hunter2");
+        assert_eq!(store.get_secret("default/legacy").unwrap().unwrap().value, "old");
+        assert!(matches!(plain.get_secret("default/db-password"), Err(StateError::Encryption(_))));
+        // Listing leaves values sealed, so it works without the key.
+        let listed = plain.list_secrets("default").unwrap();
+        assert_eq!(listed.iter().map(|s| crate::secrets::value_len(&s.value)).collect::<Vec<_>>(), [7, 3]);
+        assert!(is_sealed(&listed[0].value));
+
+        let mut spec = test_deployment("default", "api");
+        spec.secrets = vec![
+            SecretRef { secret: "db-password".into(), env: Some("DB_PASSWORD".into()), path: None, database_password: true },
+            SecretRef { secret: "legacy".into(), env: None, path: Some("/etc/app/token".into()), database_password: false },
+        ];
+        let mounted = store.mount_secrets(&spec).unwrap();
+        assert_eq!(mounted.env, [("DB_PASSWORD".to_string(), "hunter2".to_string())]);
+        assert_eq!(mounted.files["/etc/app/token"], b"old");
+        assert_eq!(mounted.database_password.as_deref(), Some("hunter2"));
+        assert_eq!(mounted.updated_at, 2000);
+
+        spec.secrets.push(SecretRef { secret: "missing".into(), env: None, path: None, database_password: false });
+        assert!(matches!(store.mount_secrets(&spec), Err(StateError::NotFound(_))));
+    }
+
+    #[test]
+    fn webhook_secrets_are_sealed_and_events_filtered() {
+        let store = StateStore::open_in_memory().unwrap().with_secrets_key(SecretsKey::generate().unwrap());
+        let webhook = Webhook {
+            id: "wh1".to_string(),
+            url: "https://hooks.example.com/warpgrid".to_string(),
+            kinds: vec![ClusterEventKind::DeploymentCreated, ClusterEventKind::RolloutRolledBack],
+            deployment: Some("prod/api".to_string()),
+            secret: "s3cret".to_string(),
+            created_at: 1000,
+        };
+        store.put_webhook(&webhook).unwrap();
+        let raw: Webhook = store.get_json(WEBHOOKS, "wh1").unwrap().unwrap();
+        assert!(is_sealed(&raw.secret));
+        assert_eq!(store.list_webhooks().unwrap(), std::slice::from_ref(&webhook));
+
+        let event = |kind, deployment: &str| ClusterEvent::new(kind, 0, "").for_deployment(deployment);
+        assert!(webhook.matches(&event(ClusterEventKind::DeploymentCreated, "prod/api")));
+        assert!(!webhook.matches(&event(ClusterEventKind::DeploymentCreated, "prod/web")));
+        assert!(!webhook.matches(&event(ClusterEventKind::DeploymentDeleted, "prod/api")));
+        assert!(Webhook { kinds: vec![], deployment: None, ..webhook }.matches(&ClusterEvent::new(
+            ClusterEventKind::NodeLost,
+            0,
+            ""
+        )));
+
+        assert!(store.delete_webhook("wh1").unwrap());
+        assert!(store.get_webhook("wh1").unwrap().is_none());
+    }
+
+    // ── API token CRUD ─────────────────────────────────────────────
+
+    #[test]
+    fn api_tokens_are_found_by_digest_and_revoked_by_id() {
+        let store = StateStore::open_in_memory().unwrap();
+        let token = |id: &str, digest: &str| ApiToken {
+            id: id.to_string(),
+            name: "ci".to_string(),
+            digest: digest.to_string(),
+            created_at: 1000,
+        };
+        store.put_api_token(&token("a1", "a1f0")).unwrap();
+        store.put_api_token(&token("b2", "b2e9")).unwrap();
+
+        assert_eq!(store.get_api_token("a1f0").unwrap(), Some(token("a1", "a1f0")));
+        assert_eq!(store.get_api_token("a1").unwrap(), None);
+        assert_eq!(store.list_api_tokens().unwrap().len(), 2);
+
+        assert!(store.delete_api_token("a1").unwrap());
+        assert!(!store.delete_api_token("a1").unwrap());
+        assert_eq!(store.get_api_token("a1f0").unwrap(), None);
+        assert_eq!(store.list_api_tokens().unwrap(), [token("b2", "b2e9")]);
+    }
+
+    // ── Feature flag CRUD ──────────────────────────────────────────
+
+    #[test]
+    fn flags_are_journaled_for_replicas() {
+        let store = StateStore::open_in_memory().unwrap();
+        let mut set = FlagSet::default();
+        set.flags.insert("beta".to_string(), FeatureFlag::Percentage { percent: 10 });
+        let flags = DeploymentFlags {
+            deployment_id: "default/api".to_string(),
+            set,
+            updated_at: 1000,
+        };
+
+        store.put_flags(&flags).unwrap();
+        assert_eq!(store.get_flags("default/api").unwrap(), Some(flags.clone()));
+        assert_eq!(store.list_flags().unwrap(), vec![flags]);
+
+        let Some(ReplicaSync::Snapshot { entries, .. }) = store.replica_sync_since(0, 10).unwrap()
+        else {
+            panic!("expected a snapshot");
+        };
+        assert_eq!(entries[0].table, FLAGS);
+
+        assert!(store.delete_flags("default/api").unwrap());
+        assert!(store.get_flags("default/api").unwrap().is_none());
+        let Some(ReplicaSync::Deltas { changes }) = store.replica_sync_since(1, 10).unwrap() else {
+            panic!("expected deltas");
+        };
+        assert_eq!((changes[0].table.as_str(), changes[0].value.is_none()), (FLAGS, true));
+    }
+
+    // ── Config bundles ─────────────────────────────────────────────
+
+    fn bundle(content: &str) -> ConfigBundle {
+        ConfigBundle {
+            files: [("/etc/app.toml".to_string(), content.to_string())].into(),
+        }
+    }
+
+    #[test]
+    fn staged_bundle_waits_for_every_node() {
+        let store = StateStore::open_in_memory().unwrap();
+        let mut on_node_2 = test_instance("default/api", 1);
+        on_node_2.node_id = "node-2".to_string();
+        store.put_instance(&test_instance("default/api", 0)).unwrap();
+        store.put_instance(&on_node_2).unwrap();
+
+        let config = store.stage_config_bundle("default/api", &bundle("v1"), 1000).unwrap();
+        let staged = config.staged.unwrap();
+        assert_eq!(store.get_config_bundle(&staged.digest).unwrap(), Some(bundle("v1")));
+
+        // node-2 has not applied the revision that stored the bundle yet.
+        let mut applied = HashMap::from([
+            ("node-1".to_string(), staged.revision),
+            ("node-2".to_string(), staged.revision - 1),
+        ]);
+        assert!(store.promote_staged_configs(&applied, 1001).unwrap().is_empty());
+        assert!(store.active_config_bundles().unwrap().is_empty());
+
+        applied.insert("node-2".to_string(), staged.revision);
+        assert_eq!(store.promote_staged_configs(&applied, 1002).unwrap(), ["default/api"]);
+        let config = store.get_deployment_config("default/api").unwrap().unwrap();
+        assert_eq!(config.active.as_deref(), Some(staged.digest.as_str()));
+        assert!(config.staged.is_none());
+        assert_eq!(store.active_config_bundles().unwrap(), [("default/api".to_string(), bundle("v1"))]);
+    }
+
+    #[test]
+    fn replaced_bundles_are_pruned() {
+        let store = StateStore::open_in_memory().unwrap();
+        let v1 = store.stage_config_bundle("default/api", &bundle("v1"), 1000).unwrap();
+        let v1 = v1.staged.unwrap().digest;
+        store.promote_staged_configs(&HashMap::new(), 1001).unwrap();
+
+        // Staging v2 keeps v1 active until v2 is promoted.
+        let v2 = store.stage_config_bundle("default/api", &bundle("v2"), 1002).unwrap();
+        let v2 = v2.staged.unwrap().digest;
+        assert!(store.get_config_bundle(&v1).unwrap().is_some());
+        store.promote_staged_configs(&HashMap::new(), 1003).unwrap();
+        assert!(store.get_config_bundle(&v1).unwrap().is_none());
+
+        // Re-staging the active bundle is a no-op.
+        let config = store.stage_config_bundle("default/api", &bundle("v2"), 1004).unwrap();
+        assert!(config.staged.is_none());
+
+        assert!(store.delete_deployment_config("default/api").unwrap());
+        assert!(store.get_config_bundle(&v2).unwrap().is_none());
+    }
+
+    // ── Config maps ────────────────────────────────────────────────
+
+    #[test]
+    fn bound_config_maps_are_staged_beside_other_files() {
+        let store = StateStore::open_in_memory().unwrap();
+        let config_map = |data: &[(&str, &str)]| ConfigMap {
+            namespace: "default".to_string(),
+            name: "app".to_string(),
+            data: data.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            created_at: 1000,
+            updated_at: 1000,
+        };
+        let mut spec = test_deployment("default", "api");
+        spec.id = "default/api".to_string();
+        spec.config_maps = vec![ConfigMapRef { config_map: "app".into(), path: "/etc/app/".into() }];
+        store.put_deployment(&spec).unwrap();
+        store.stage_config_bundle("default/api", &bundle("v1"), 1000).unwrap();
+        store.promote_staged_configs(&HashMap::new(), 1000).unwrap();
+
+        store.put_config_map(&config_map(&[("port", "80"), ("mode", "fast")])).unwrap();
+        assert_eq!(store.config_map_bindings("default", "app").unwrap().len(), 1);
+        let staged = store.bind_config_maps(&spec, &[], 1001).unwrap().unwrap().staged.unwrap();
+        let files = store.get_config_bundle(&staged.digest).unwrap().unwrap().files;
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["/etc/app.toml", "/etc/app/mode", "/etc/app/port"]);
+        assert!(store.bind_config_maps(&spec, &[], 1002).unwrap().is_none());
+
+        store.put_config_map(&config_map(&[("port", "81")])).unwrap();
+        let staged = store.bind_config_maps(&spec, &[], 1003).unwrap().unwrap().staged.unwrap();
+        let files = store.get_config_bundle(&staged.digest).unwrap().unwrap().files;
+        assert_eq!(files.get("/etc/app/port").map(String::as_str), Some("81"));
+        assert!(!files.contains_key("/etc/app/mode"));
+
+        // Unbinding returns to the active bundle, so nothing stays staged.
+        let unbound = std::mem::take(&mut spec.config_maps);
+        let config = store.bind_config_maps(&spec, &unbound, 1004).unwrap().unwrap();
+        assert!(config.staged.is_none());
+        assert_eq!(config.active, Some(bundle("v1").digest()));
+    }
+
     // ── Metrics CRUD ───────────────────────────────────────────────
 
     #[test]
@@ -586,6 +2164,245 @@ mod tests {
         assert_eq!(limited.len(), 2);
     }
 
+    // ── Usage ──────────────────────────────────────────────────────
+
+    fn test_usage_event(event_id: &str, started_at_ms: u64) -> UsageEvent {
+        UsageEvent {
+            event_id: event_id.to_string(),
+            deployment_id: "deploy-1".to_string(),
+            instance_id: "inst-0".to_string(),
+            node_id: "node-1".to_string(),
+            started_at_ms,
+            wall_time_ms: 40,
+            cpu_time_ms: 10,
+            memory_limit_bytes: 64 * 1024 * 1024,
+            budget_exceeded: false,
+        }
+    }
+
+    #[test]
+    fn usage_events_get_increasing_sequences() {
+        let store = StateStore::open_in_memory().unwrap();
+
+        assert_eq!(store.record_usage(&test_usage_event("a", 1_000)).unwrap(), Some(1));
+        assert_eq!(store.record_usage(&test_usage_event("b", 2_000)).unwrap(), Some(2));
+
+        let all = store.list_usage_events(0, 10).unwrap();
+        assert_eq!(all.iter().map(|r| r.sequence).collect::<Vec<_>>(), vec![1, 2]);
+
+        let after_first = store.list_usage_events(1, 10).unwrap();
+        assert_eq!(after_first.len(), 1);
+        assert_eq!(after_first[0].event.event_id, "b");
+    }
+
+    #[test]
+    fn usage_duplicate_event_is_counted_once() {
+        let store = StateStore::open_in_memory().unwrap();
+        let event = test_usage_event("req-1", 1_000);
+
+        assert!(store.record_usage(&event).unwrap().is_some());
+        assert!(store.record_usage(&event).unwrap().is_none());
+
+        assert_eq!(store.list_usage_events(0, 10).unwrap().len(), 1);
+        let rollups = store.list_usage_rollups("deploy-1", 0).unwrap();
+        assert_eq!(rollups.len(), 1);
+        assert_eq!(rollups[0].requests, 1);
+        assert_eq!(rollups[0].wall_time_ms, 40);
+        assert_eq!(rollups[0].memory_mib_ms, 64 * 40);
+    }
+
+    #[test]
+    fn usage_past_retention_is_pruned_but_the_newest_event_kept() {
+        let store = StateStore::open_in_memory().unwrap();
+        let day_ms = 24 * 3600 * 1000;
+        store.record_usage(&test_usage_event("old-1", day_ms)).unwrap();
+        store.record_usage(&test_usage_event("old-2", day_ms + 1)).unwrap();
+        store.record_usage(&test_usage_event("new", 40 * day_ms)).unwrap();
+        store.record_usage(&test_usage_event("newest-but-old", day_ms + 2)).unwrap();
+
+        let now = 40 * 24 * 3600;
+        // Two events and the old window's rollup.
+        assert_eq!(store.prune_usage(now).unwrap(), 3);
+        let left: Vec<u64> = store.list_usage_events(0, 10).unwrap().iter().map(|r| r.sequence).collect();
+        assert_eq!(left, [3, 4]);
+        assert_eq!(store.list_usage_rollups("deploy-1", 0).unwrap().len(), 1);
+        // A pruned id is free again; sequences carry on.
+        assert_eq!(store.record_usage(&test_usage_event("old-1", 40 * day_ms)).unwrap(), Some(5));
+        assert_eq!(store.prune_usage(now).unwrap(), 0);
+    }
+
+    #[test]
+    fn usage_rollups_bucket_by_window() {
+        let store = StateStore::open_in_memory().unwrap();
+        let window_ms = USAGE_ROLLUP_WINDOW_SECS * 1000;
+
+        store.record_usage(&test_usage_event("a", 10)).unwrap();
+        store.record_usage(&test_usage_event("b", 20)).unwrap();
+        let mut over = test_usage_event("c", window_ms + 5);
+        over.budget_exceeded = true;
+        store.record_usage(&over).unwrap();
+
+        let rollups = store.list_usage_rollups("deploy-1", 0).unwrap();
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(rollups[0].requests, 2);
+        assert_eq!(rollups[0].cpu_time_ms, 20);
+        assert_eq!(rollups[1].window_start, USAGE_ROLLUP_WINDOW_SECS);
+        assert_eq!(rollups[1].budget_exceeded, 1);
+
+        let recent = store
+            .list_usage_rollups("deploy-1", USAGE_ROLLUP_WINDOW_SECS)
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+    }
+
+    #[test]
+    fn usage_list_respects_limit() {
+        let store = StateStore::open_in_memory().unwrap();
+        for i in 0..5 {
+            store
+                .record_usage(&test_usage_event(&format!("e{i}"), 1_000))
+                .unwrap();
+        }
+
+        let page = store.list_usage_events(0, 2).unwrap();
+        assert_eq!(page.len(), 2);
+        let next = store.list_usage_events(page[1].sequence, 10).unwrap();
+        assert_eq!(next.len(), 3);
+    }
+
+    // ── Cluster events ─────────────────────────────────────────────
+
+    #[test]
+    fn events_get_sequences_and_filter() {
+        let store = StateStore::open_in_memory().unwrap();
+        let created = ClusterEvent::new(ClusterEventKind::DeploymentCreated, 100, "created").for_deployment("default/api");
+        let joined = ClusterEvent::new(ClusterEventKind::NodeJoined, 101, "joined").for_node("node-a");
+        assert_eq!(store.record_event(&created).unwrap(), 1);
+        assert_eq!(store.record_event(&joined).unwrap(), 2);
+        assert_eq!(store.record_event(&created).unwrap(), 3);
+
+        let all = store.list_events(0, 10, |_| true).unwrap();
+        assert_eq!(all.iter().map(|e| e.sequence).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(all[1].node_id.as_deref(), Some("node-a"));
+
+        let later = store.list_events(1, 10, |e| e.deployment_id.is_some()).unwrap();
+        assert_eq!(later.iter().map(|e| e.sequence).collect::<Vec<_>>(), [3]);
+        assert_eq!(store.list_events(0, 1, |_| true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn migrations_upgrade_old_records_once() {
+        fn rename_source(record: &mut serde_json::Value) -> bool {
+            migrate::rename_field(record, &[], "source_uri", "source")
+        }
+        let migrations = [Migration {
+            version: 2,
+            description: "rename source_uri to source",
+            tables: &[DEPLOYMENTS],
+            upgrade: rename_source,
+        }];
+
+        let store = StateStore::open_in_memory().unwrap();
+        let mut old = serde_json::to_value(test_deployment("default", "api")).unwrap();
+        migrate::rename_field(&mut old, &[], "source", "source_uri");
+        store.backend().put(DEPLOYMENTS, "default/api", &serde_json::to_vec(&old).unwrap()).unwrap();
+
+        let report = store.run_migrations(&migrations, 2).unwrap();
+        assert_eq!(report, MigrationReport { from: 1, to: 2, records_migrated: 1 });
+        assert_eq!(store.get_deployment("default/api").unwrap().unwrap().source, "file://./test.wasm");
+        assert_eq!(store.schema_version().unwrap(), 2);
+        assert_eq!(store.state_revision().unwrap(), 1, "replicated rewrite is journaled");
+
+        let report = store.run_migrations(&migrations, 2).unwrap();
+        assert_eq!(report, MigrationReport { from: 2, to: 2, records_migrated: 0 });
+    }
+
+    #[test]
+    fn migrate_refuses_newer_schema() {
+        let store = StateStore::open_in_memory().unwrap();
+        assert_eq!(store.migrate().unwrap(), MigrationReport { from: 1, to: 1, records_migrated: 0 });
+
+        store.put_json(STATE_META, SCHEMA_VERSION_KEY, &(migrate::SCHEMA_VERSION + 1)).unwrap();
+        assert!(matches!(store.migrate(), Err(StateError::Schema(_))));
+    }
+
+    #[tokio::test]
+    async fn watch_streams_committed_writes_under_prefix() {
+        use futures_util::StreamExt;
+
+        let store = StateStore::open_in_memory().unwrap();
+        let mut deployments = Box::pin(store.watch("deployments/default/"));
+        let mut nodes = Box::pin(store.watch("nodes/"));
+
+        let mut spec = test_deployment("default", "api");
+        store.put_deployment(&spec).unwrap();
+        store.put_deployment(&test_deployment("prod", "api")).unwrap();
+        spec.source = "file://./v2.wasm".to_string();
+        store.put_deployment(&spec).unwrap();
+        store.delete_deployment("default/api").unwrap();
+        store.put_node(&test_node("node-1")).unwrap();
+
+        let Some(Ok(WatchEvent::Put { key, old: None, new, .. })) = deployments.next().await else {
+            panic!("expected the insert");
+        };
+        assert_eq!(key, "default/api");
+        assert_eq!(new["source"], "file://./test.wasm");
+        let Some(Ok(WatchEvent::Put { old: Some(old), new, .. })) = deployments.next().await else {
+            panic!("expected the update");
+        };
+        assert_eq!((&old["source"], &new["source"]), (&"file://./test.wasm".into(), &"file://./v2.wasm".into()));
+        let Some(Ok(WatchEvent::Delete { key, old, .. })) = deployments.next().await else {
+            panic!("expected the delete");
+        };
+        assert_eq!((key.as_str(), &old["source"]), ("default/api", &"file://./v2.wasm".into()));
+
+        let event = nodes.next().await.unwrap().unwrap();
+        assert_eq!((event.table(), event.key()), (NODES, "node-1"));
+    }
+
+    #[tokio::test]
+    async fn watch_reports_lag() {
+        use futures_util::StreamExt;
+
+        let store = StateStore::open_in_memory().unwrap();
+        let mut watch = Box::pin(store.watch("nodes/"));
+        for i in 0..crate::watch::WATCH_CAPACITY + 1 {
+            store.put_node(&test_node(&format!("node-{i}"))).unwrap();
+        }
+        assert!(matches!(watch.next().await, Some(Err(StateError::WatchLagged(1)))));
+        assert_eq!(watch.next().await.unwrap().unwrap().key(), "node-1");
+    }
+
+    #[tokio::test]
+    async fn watchers_only_see_their_tables() {
+        use futures_util::StreamExt;
+
+        let store = StateStore::open_in_memory().unwrap();
+        let mut watch = Box::pin(store.watch_any(&["deployments", "nodes/node-"]));
+        // Writes to other tables do not count towards the watcher's lag.
+        for i in 0..crate::watch::WATCH_CAPACITY + 1 {
+            store.backend.put(USAGE_EVENTS, &format!("{i}"), b"{}").unwrap();
+        }
+        store.put_node(&test_node("other")).unwrap();
+        store.put_node(&test_node("node-1")).unwrap();
+        store.put_deployment(&test_deployment("default", "api")).unwrap();
+
+        let mut seen = Vec::new();
+        for _ in 0..2 {
+            let event = watch.next().await.unwrap().unwrap();
+            seen.push(format!("{}/{}", event.table(), event.key()));
+        }
+        seen.sort();
+        assert_eq!(seen, ["deployments/default/api", "nodes/node-1"]);
+    }
+
+    #[test]
+    fn event_kind_names_match_serde() {
+        for kind in ClusterEventKind::ALL {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+    }
+
     // ── Persistence (on-disk) ──────────────────────────────────────
 
     #[test]
@@ -607,6 +2424,43 @@ mod tests {
 
     // ── Edge cases ─────────────────────────────────────────────────
 
+    #[test]
+    fn replicated_writes_advance_state_revision() {
+        let store = StateStore::open_in_memory().unwrap();
+        assert_eq!(store.state_revision().unwrap(), 0);
+
+        store.put_deployment(&test_deployment("default", "api")).unwrap();
+        store.put_instance(&test_instance("default-api", 0)).unwrap();
+        store.put_instance(&test_instance("default-api", 1)).unwrap();
+        assert_eq!(store.state_revision().unwrap(), 3);
+
+        assert_eq!(store.delete_instances_for_deployment("default-api").unwrap(), 2);
+        assert_eq!(store.state_revision().unwrap(), 5);
+
+        // Nodes are not replicated.
+        store.put_node(&test_node("node-1")).unwrap();
+        assert_eq!(store.state_revision().unwrap(), 5);
+
+        let Some(ReplicaSync::Deltas { changes }) = store.replica_sync_since(3, 100).unwrap()
+        else {
+            panic!("expected deltas");
+        };
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|c| c.table == INSTANCES && c.value.is_none()));
+    }
+
+    #[test]
+    fn connect_rejects_unknown_scheme() {
+        let err = StateStore::connect("mysql://localhost/warpgrid").err().unwrap();
+        assert!(matches!(err, StateError::Open(_)));
+    }
+
+    #[test]
+    fn in_memory_store_uses_redb_backend() {
+        let store = StateStore::open_in_memory().unwrap();
+        assert_eq!(store.backend_name(), "redb");
+    }
+
     #[test]
     fn empty_store_operations() {
         let store = StateStore::open_in_memory().unwrap();
@@ -644,4 +2498,126 @@ mod tests {
         let all = store.list_deployments().unwrap();
         assert_eq!(all.len(), 3);
     }
+
+    // ── Integrity ──────────────────────────────────────────────────
+
+    /// Flip a payload byte of a stored record, leaving its checksum stale.
+    fn corrupt(store: &StateStore, table: &str, key: &str) {
+        let mut bytes = store.backend().get(table, key).unwrap().unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0x01;
+        store.backend().put(table, key, &bytes).unwrap();
+    }
+
+    #[test]
+    fn corrupt_record_is_quarantined_on_read() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_deployment(&test_deployment("default", "api")).unwrap();
+        store.put_deployment(&test_deployment("default", "web")).unwrap();
+        corrupt(&store, DEPLOYMENTS, "default/api");
+
+        assert!(store.get_deployment("default/api").unwrap().is_none());
+        let remaining = store.list_deployments().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "web");
+
+        let quarantined = store.list_quarantined().unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].table, DEPLOYMENTS);
+        assert_eq!(quarantined[0].key, "default/api");
+        assert!(quarantined[0].reason.contains("checksum mismatch"));
+        assert_eq!(store.corrupt_records_detected(), 1);
+    }
+
+    #[test]
+    fn corrupt_list_entry_does_not_fail_listing() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_node(&test_node("node-1")).unwrap();
+        store.backend().put(NODES, "node-2", b"{not json").unwrap();
+
+        let nodes = store.list_nodes().unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert!(store.get_node("node-2").unwrap().is_none());
+        assert!(store.list_quarantined().unwrap()[0].reason.contains("invalid JSON"));
+    }
+
+    #[test]
+    fn mismatched_record_fails_read_and_stays_in_place() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_node(&test_node("node-1")).unwrap();
+        // Valid, sealed JSON that is not a node, e.g. from a newer release.
+        store
+            .backend()
+            .put(NODES, "node-2", &encode(&serde_json::json!({"id": 7})).unwrap())
+            .unwrap();
+
+        assert!(matches!(store.get_node("node-2"), Err(StateError::Deserialize(_))));
+        assert!(matches!(store.list_nodes(), Err(StateError::Deserialize(_))));
+        assert!(store.backend().get(NODES, "node-2").unwrap().is_some());
+        assert!(store.list_quarantined().unwrap().is_empty());
+        assert_eq!(store.corrupt_records_detected(), 0);
+    }
+
+    #[test]
+    fn verify_integrity_reports_corrupt_and_unsealed_records() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_deployment(&test_deployment("default", "api")).unwrap();
+        store.put_node(&test_node("node-1")).unwrap();
+        corrupt(&store, DEPLOYMENTS, "default/api");
+        // Written before values were sealed.
+        let legacy = serde_json::to_vec(&test_node("node-2")).unwrap();
+        store.backend().put(NODES, "node-2", &legacy).unwrap();
+
+        let report = store.verify_integrity().unwrap();
+        assert_eq!(report.tables_scanned as usize, ALL_TABLES.len() - 1);
+        // Deployment, its revision, its journal entry, and two nodes.
+        assert_eq!(report.records_checked, 5);
+        assert_eq!(report.unsealed_records, 1);
+        assert_eq!(report.quarantined.len(), 1);
+        assert_eq!(report.quarantined[0].key, "default/api");
+
+        assert_eq!(store.list_nodes().unwrap().len(), 2);
+        let status = store.integrity_status().unwrap();
+        assert_eq!(status.last_scan, Some(report));
+        assert_eq!(status.quarantined.len(), 1);
+
+        let rescan = store.verify_integrity().unwrap();
+        assert!(rescan.quarantined.is_empty());
+    }
+
+    #[test]
+    fn corrupt_rollup_restarts_window() {
+        let store = StateStore::open_in_memory().unwrap();
+        let mut event = UsageEvent {
+            event_id: "evt-1".to_string(),
+            deployment_id: "default/api".to_string(),
+            instance_id: "inst-0".to_string(),
+            node_id: "node-1".to_string(),
+            started_at_ms: 3_600_000,
+            wall_time_ms: 10,
+            cpu_time_ms: 5,
+            memory_limit_bytes: 64 * 1024 * 1024,
+            budget_exceeded: false,
+        };
+        store.record_usage(&event).unwrap();
+        corrupt(&store, USAGE_ROLLUPS, "default/api:3600");
+
+        event.event_id = "evt-2".to_string();
+        assert_eq!(store.record_usage(&event).unwrap(), Some(2));
+        let rollups = store.list_usage_rollups("default/api", 0).unwrap();
+        assert_eq!(rollups[0].requests, 1);
+        assert_eq!(store.list_quarantined().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn quarantined_journal_entry_forces_snapshot() {
+        let store = StateStore::open_in_memory().unwrap();
+        store.put_deployment(&test_deployment("default", "a")).unwrap();
+        store.put_deployment(&test_deployment("default", "b")).unwrap();
+        store.put_deployment(&test_deployment("default", "c")).unwrap();
+        corrupt(&store, STATE_CHANGES, &sequence_key(2));
+
+        let sync = store.replica_sync_since(1, 100).unwrap().unwrap();
+        assert!(matches!(sync, ReplicaSync::Snapshot { revision: 3, .. }));
+    }
 }
@@ -1,21 +1,111 @@
-//! redb table definitions for the WarpGrid state store.
+//! Table names for the WarpGrid state store.
 //!
 //! Each table uses `&str` keys and `&[u8]` values (JSON-serialized domain types).
 //! Composite keys follow the pattern `{namespace}/{name}` or `{parent_id}:{child_id}`.
-
-use redb::TableDefinition;
+//! Backends map each name to their own storage (a redb table, or a `tbl`
+//! column value in Postgres).
 
 /// Deployment specs keyed by `{namespace}/{name}`.
-pub const DEPLOYMENTS: TableDefinition<&str, &[u8]> = TableDefinition::new("deployments");
+pub const DEPLOYMENTS: &str = "deployments";
+
+/// Deployment spec history keyed by `{deployment_id}:{revision}`, the
+/// revision zero-padded.
+pub const DEPLOYMENT_REVISIONS: &str = "deployment_revisions";
 
 /// Instance state keyed by `{deployment_id}:{instance_index}`.
-pub const INSTANCES: TableDefinition<&str, &[u8]> = TableDefinition::new("instances");
+pub const INSTANCES: &str = "instances";
 
 /// Node info keyed by `{node_id}`.
-pub const NODES: TableDefinition<&str, &[u8]> = TableDefinition::new("nodes");
+pub const NODES: &str = "nodes";
 
 /// Service endpoints keyed by `{namespace}/{service}`.
-pub const SERVICES: TableDefinition<&str, &[u8]> = TableDefinition::new("services");
+pub const SERVICES: &str = "services";
+
+/// Secrets keyed by `{namespace}/{name}`.
+pub const SECRETS: &str = "secrets";
+
+/// Management API tokens keyed by the hex SHA-256 digest of the token.
+pub const API_TOKENS: &str = "api_tokens";
+
+/// Deployment feature flags keyed by `{deployment_id}`.
+pub const FLAGS: &str = "flags";
+
+/// Config bundles keyed by their content digest.
+pub const CONFIG_BUNDLES: &str = "config_bundles";
+
+/// Active and staged config bundle of each deployment, keyed by `{deployment_id}`.
+pub const DEPLOYMENT_CONFIGS: &str = "deployment_configs";
+
+/// Config maps keyed by `{namespace}/{name}`.
+pub const CONFIG_MAPS: &str = "config_maps";
+
+/// Outbound webhooks keyed by `{id}`.
+pub const WEBHOOKS: &str = "webhooks";
+
+/// Responses to requests sent with an `Idempotency-Key`, keyed by the key.
+pub const IDEMPOTENCY_KEYS: &str = "idempotency_keys";
 
 /// Metrics snapshots keyed by `{deployment_id}:{epoch}`.
-pub const METRICS: TableDefinition<&str, &[u8]> = TableDefinition::new("metrics");
+pub const METRICS: &str = "metrics";
+
+/// Durable usage stream keyed by zero-padded `{sequence}`.
+pub const USAGE_EVENTS: &str = "usage_events";
+
+/// Idempotency index: `{event_id}` → assigned sequence.
+pub const USAGE_EVENT_IDS: &str = "usage_event_ids";
+
+/// Usage rollups keyed by `{deployment_id}:{window_start}`.
+pub const USAGE_ROLLUPS: &str = "usage_rollups";
+
+/// Cluster events keyed by zero-padded `{sequence}`.
+pub const CLUSTER_EVENTS: &str = "cluster_events";
+
+/// Change journal for replicated tables keyed by zero-padded `{revision}`.
+pub const STATE_CHANGES: &str = "state_changes";
+
+/// Read-replica bookkeeping (e.g. the applied revision) keyed by name.
+pub const REPLICA_META: &str = "replica_meta";
+
+/// Store bookkeeping (e.g. the schema version) keyed by name.
+pub const STATE_META: &str = "state_meta";
+
+/// Corrupt records moved aside on read, keyed by `{table}/{key}`.
+pub const QUARANTINE: &str = "quarantine";
+
+/// Tables shipped to agent read replicas.
+pub const REPLICATED_TABLES: &[&str] = &[
+    DEPLOYMENTS,
+    INSTANCES,
+    SERVICES,
+    SECRETS,
+    FLAGS,
+    CONFIG_BUNDLES,
+    DEPLOYMENT_CONFIGS,
+    API_TOKENS,
+];
+
+/// Every table, so backends can create them up front.
+pub const ALL_TABLES: &[&str] = &[
+    DEPLOYMENTS,
+    DEPLOYMENT_REVISIONS,
+    INSTANCES,
+    NODES,
+    SERVICES,
+    SECRETS,
+    API_TOKENS,
+    FLAGS,
+    CONFIG_BUNDLES,
+    DEPLOYMENT_CONFIGS,
+    CONFIG_MAPS,
+    WEBHOOKS,
+    IDEMPOTENCY_KEYS,
+    METRICS,
+    USAGE_EVENTS,
+    USAGE_EVENT_IDS,
+    USAGE_ROLLUPS,
+    CLUSTER_EVENTS,
+    STATE_CHANGES,
+    REPLICA_META,
+    STATE_META,
+    QUARANTINE,
+];
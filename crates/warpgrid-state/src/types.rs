@@ -1,11 +1,12 @@
 //! Domain types for the WarpGrid state store.
 //!
 //! These types represent the persisted state of deployments, instances,
-//! nodes, services, and metrics snapshots. All types are serializable
+//! nodes, services, feature flags, metrics snapshots, and usage records. All types are serializable
 //! to/from JSON for storage in redb tables.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Unique identifier for a deployment (namespace-scoped).
 pub type DeploymentId = String;
@@ -19,7 +20,7 @@ pub type NodeId = String;
 // ── Deployment ─────────────────────────────────────────────────────
 
 /// Specification for a deployed Wasm workload.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct DeploymentSpec {
     pub id: DeploymentId,
     pub namespace: String,
@@ -40,6 +41,27 @@ pub struct DeploymentSpec {
     pub shims: ShimsEnabled,
     /// Environment variables injected into the Wasm module.
     pub env: HashMap<String, String>,
+    /// Placement priority: 0 is the most important. Under node memory
+    /// pressure, higher values are evicted first (default 10).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    /// Disruption budget: instances a node-pressure eviction must leave
+    /// running on a node (default 0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_available: Option<u32>,
+    /// Labels for selecting deployments in bulk operations.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// A paused deployment keeps its spec but is not served; its routes
+    /// answer 503 until it is resumed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// Secrets handed to the deployment's instances.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretRef>,
+    /// Config maps whose keys the deployment's instances read as files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_maps: Vec<ConfigMapRef>,
     /// Unix timestamp (seconds) when this spec was created.
     pub created_at: u64,
     /// Unix timestamp (seconds) when this spec was last updated.
@@ -47,32 +69,48 @@ pub struct DeploymentSpec {
 }
 
 /// Trigger configuration for a deployment.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum TriggerConfig {
-    Http { port: Option<u16> },
+    /// Served by the node's HTTP ingress. `port` pins the deployment to
+    /// one ingress listener (default: all of them); `hosts` and
+    /// `path_prefix` select which requests reach it.
+    Http {
+        port: Option<u16>,
+        /// Host names, exact (`api.example.com`) or wildcard
+        /// (`*.example.com`). Empty matches any host.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        hosts: Vec<String>,
+        /// Only requests under this path are routed here (default `/`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path_prefix: Option<String>,
+    },
     Cron { schedule: String },
     Queue { topic: String },
 }
 
 /// Min/max instance count for a deployment.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct InstanceConstraints {
     pub min: u32,
     pub max: u32,
 }
 
 /// Resource limits per Wasm instance.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ResourceLimits {
     /// Memory limit in bytes.
     pub memory_bytes: u64,
     /// CPU weight (relative, higher = more CPU time).
     pub cpu_weight: u32,
+    /// Wall-clock execution budget per request in milliseconds
+    /// (`None` = unbounded). Requests over budget are trapped.
+    #[serde(default)]
+    pub execution_budget_ms: Option<u64>,
 }
 
 /// Autoscaling parameters.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ScalingConfig {
     /// Metric to scale on: "rps", "latency_p99", "cpu", "memory".
     pub metric: String,
@@ -85,7 +123,7 @@ pub struct ScalingConfig {
 }
 
 /// Health check parameters.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct HealthConfig {
     /// HTTP path to probe (e.g., "/healthz").
     pub endpoint: String,
@@ -98,7 +136,7 @@ pub struct HealthConfig {
 }
 
 /// Which host shims are enabled for a deployment.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
 pub struct ShimsEnabled {
     pub timezone: bool,
     pub dev_urandom: bool,
@@ -107,10 +145,84 @@ pub struct ShimsEnabled {
     pub database_proxy: bool,
 }
 
+/// A secret from the deployment's namespace and where its instances
+/// receive it. One secret may be delivered several ways at once.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct SecretRef {
+    /// Name of the secret.
+    pub secret: String,
+    /// Set this environment variable to the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Serve the value as a read-only file at this path through the
+    /// filesystem shim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Give the value to the database proxy shim as the password for
+    /// connections that do not send one.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub database_password: bool,
+}
+
+/// A deployment's [`SecretRef`]s resolved to their values, for starting
+/// its instances.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MountedSecrets {
+    /// Environment variables, added to the spec's `env`.
+    pub env: Vec<(String, String)>,
+    /// Guest path → file content.
+    pub files: HashMap<String, Vec<u8>>,
+    pub database_password: Option<String>,
+    /// Latest `updated_at` of the referenced secrets, so a changed value
+    /// can be told apart from the one already mounted.
+    pub updated_at: u64,
+}
+
+/// Number of revisions kept per deployment; older ones are dropped as new
+/// ones are recorded.
+pub const DEPLOYMENT_REVISION_RETENTION: u64 = 20;
+
+/// A deployment spec as it was stored, numbered per deployment from 1, so
+/// an earlier one can be rolled back to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct DeploymentRevision {
+    pub deployment_id: DeploymentId,
+    pub revision: u64,
+    pub spec: DeploymentSpec,
+}
+
+/// What a conditional deployment write expects of the stored deployment,
+/// from `If-Match` and `If-None-Match`. A deployment's version is its
+/// latest revision (see [`crate::StateStore::deployment_version`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeploymentPrecondition {
+    /// No condition.
+    #[default]
+    Any,
+    /// It exists, at any version (`If-Match: *`).
+    Exists,
+    /// It does not exist yet (`If-None-Match: *`).
+    Absent,
+    /// It is at this version (`If-Match: "<version>"`).
+    Version(u64),
+}
+
+impl DeploymentPrecondition {
+    /// Whether a deployment at `version` (`None`: missing) satisfies this.
+    pub fn holds(self, version: Option<u64>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exists => version.is_some(),
+            Self::Absent => version.is_none(),
+            Self::Version(expected) => version == Some(expected),
+        }
+    }
+}
+
 // ── Instance ──────────────────────────────────────────────────────
 
 /// Runtime state of a single Wasm instance.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct InstanceState {
     pub id: InstanceId,
     pub deployment_id: DeploymentId,
@@ -127,7 +239,7 @@ pub struct InstanceState {
 }
 
 /// Lifecycle status of a Wasm instance.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum InstanceStatus {
     Starting,
@@ -138,7 +250,7 @@ pub enum InstanceStatus {
 }
 
 /// Health status as determined by health probes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HealthStatus {
     Healthy,
@@ -149,7 +261,7 @@ pub enum HealthStatus {
 // ── Node ──────────────────────────────────────────────────────────
 
 /// Information about a node in the cluster.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct NodeInfo {
     pub id: NodeId,
     pub address: String,
@@ -166,12 +278,43 @@ pub struct NodeInfo {
     pub labels: HashMap<String, String>,
     /// Unix timestamp of last heartbeat.
     pub last_heartbeat: u64,
+    /// Unix timestamp until which placement avoids this node because it
+    /// reported memory pressure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure_until: Option<u64>,
+    /// Last control-plane state revision the node's read replica applied,
+    /// as of its last heartbeat. `None` when the node keeps no replica.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replica_revision: Option<u64>,
+    /// CPU weight measured by the node's startup benchmark. Placement uses
+    /// it instead of `capacity_cpu_weight` when set; see
+    /// [`NodeInfo::effective_cpu_weight`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibrated_cpu_weight: Option<u32>,
+    /// A cordoned node keeps its instances but gets no new placements;
+    /// set by `POST /api/v1/nodes/:id/cordon` before maintenance.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cordoned: bool,
+}
+
+// ── Namespaces ────────────────────────────────────────────────────
+
+/// Namespace of deployments and secrets that don't name one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// A namespace and what it holds. Namespaces are implicit: one exists
+/// while a deployment or secret is keyed under it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct NamespaceSummary {
+    pub name: String,
+    pub deployments: u32,
+    pub secrets: u32,
 }
 
 // ── Service ───────────────────────────────────────────────────────
 
 /// Service endpoint entry for internal routing.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ServiceEndpoints {
     pub namespace: String,
     pub service: String,
@@ -181,10 +324,134 @@ pub struct ServiceEndpoints {
     pub updated_at: u64,
 }
 
+// ── Secrets ───────────────────────────────────────────────────────
+
+/// A named secret value, scoped to a namespace.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Secret {
+    pub namespace: String,
+    pub name: String,
+    pub value: String,
+    /// Unix timestamp of creation.
+    pub created_at: u64,
+    /// Unix timestamp of last update.
+    pub updated_at: u64,
+}
+
+impl Secret {
+    /// Key in the secrets table: `{namespace}/{name}`.
+    pub fn table_key(&self) -> String {
+        format!("{}/{}", self.namespace, self.name)
+    }
+}
+
+// ── API tokens ────────────────────────────────────────────────────
+
+/// A bearer token for the management API. Only its digest is stored; the
+/// token itself is shown once, when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ApiToken {
+    /// Short public id, for listing and revoking.
+    pub id: String,
+    /// What the token is for (e.g. "ci").
+    pub name: String,
+    /// Hex SHA-256 digest of the token.
+    pub digest: String,
+    /// Unix timestamp of creation.
+    pub created_at: u64,
+}
+
+// ── Feature flags ─────────────────────────────────────────────────
+
+pub use warp_core::flags::{FeatureFlag, FlagSet};
+
+/// A deployment's feature flags, read by its guests through the flags shim.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct DeploymentFlags {
+    pub deployment_id: DeploymentId,
+    #[serde(flatten)]
+    pub set: FlagSet,
+    /// Unix timestamp of last update.
+    pub updated_at: u64,
+}
+
+// ── Config bundles ────────────────────────────────────────────────
+
+pub use warp_core::bundle::ConfigBundle;
+
+/// A config bundle on its way to every node running a deployment.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct StagedConfig {
+    /// Digest of the staged bundle.
+    pub digest: String,
+    /// State revision that stored the bundle. A node whose replica has
+    /// applied this revision holds the bundle.
+    pub revision: u64,
+    /// Unix timestamp when the bundle was staged.
+    pub staged_at: u64,
+}
+
+/// Which config bundle a deployment's instances read.
+///
+/// A new bundle is first `staged`. Once every node running the deployment
+/// holds it, the control plane makes it `active` in a single write, and
+/// every instance switches at that barrier.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct DeploymentConfig {
+    pub deployment_id: DeploymentId,
+    /// Digest of the bundle instances read now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staged: Option<StagedConfig>,
+    /// Unix timestamp of last update.
+    pub updated_at: u64,
+}
+
+// ── Config maps ───────────────────────────────────────────────────
+
+/// Named configuration data, scoped to a namespace. Deployments bind it
+/// with a [`ConfigMapRef`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ConfigMap {
+    pub namespace: String,
+    pub name: String,
+    /// Key → value. Each key becomes a file named after it.
+    pub data: BTreeMap<String, String>,
+    /// Unix timestamp of creation.
+    pub created_at: u64,
+    /// Unix timestamp of last update.
+    pub updated_at: u64,
+}
+
+impl ConfigMap {
+    /// Key in the config maps table: `{namespace}/{name}`.
+    pub fn table_key(&self) -> String {
+        format!("{}/{}", self.namespace, self.name)
+    }
+}
+
+/// A config map from the deployment's namespace, served as one file per
+/// key under `path` in the deployment's config bundle.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ConfigMapRef {
+    /// Name of the config map.
+    pub config_map: String,
+    /// Absolute directory the keys appear in.
+    pub path: String,
+}
+
+impl ConfigMapRef {
+    /// The directory's prefix, ending in `/`.
+    pub fn dir(&self) -> String {
+        format!("{}/", self.path.trim_end_matches('/'))
+    }
+}
+
 // ── Metrics ───────────────────────────────────────────────────────
 
 /// Point-in-time metrics snapshot for a deployment.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct MetricsSnapshot {
     pub deployment_id: DeploymentId,
     /// Epoch (unix timestamp, bucketed to interval).
@@ -203,11 +470,410 @@ pub struct MetricsSnapshot {
     pub active_instances: u32,
 }
 
+// ── Usage ─────────────────────────────────────────────────────────
+
+/// Width of a usage rollup window in seconds.
+pub const USAGE_ROLLUP_WINDOW_SECS: u64 = 3600;
+
+/// How long usage events, their ids, and rollups are kept, in seconds.
+/// Consumers of the event stream must read it more often than this, and an
+/// event retried after it would be counted again.
+pub const USAGE_RETENTION_SECS: u64 = 30 * 24 * 3600;
+
+/// Resource usage of a single request, as reported by the runtime.
+///
+/// `event_id` is assigned by the producer and acts as the idempotency key:
+/// re-submitting an event with the same ID is a no-op, so producers can
+/// safely retry after a crash.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct UsageEvent {
+    pub event_id: String,
+    pub deployment_id: DeploymentId,
+    pub instance_id: InstanceId,
+    pub node_id: NodeId,
+    /// Unix timestamp (milliseconds) when the request started.
+    pub started_at_ms: u64,
+    /// Wall-clock time spent handling the request.
+    pub wall_time_ms: u64,
+    /// Guest CPU time, measured in epoch ticks while executing Wasm.
+    pub cpu_time_ms: u64,
+    /// Memory limit of the instance that served the request.
+    pub memory_limit_bytes: u64,
+    /// Whether the request was trapped for exceeding its execution budget.
+    pub budget_exceeded: bool,
+}
+
+/// A usage event as stored in the durable usage stream.
+///
+/// `sequence` is assigned by the store, strictly increasing and never
+/// reused, so external consumers can page through the stream with it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct UsageRecord {
+    pub sequence: u64,
+    #[serde(flatten)]
+    pub event: UsageEvent,
+}
+
+/// Aggregated usage for a deployment over one rollup window.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct UsageRollup {
+    pub deployment_id: DeploymentId,
+    /// Unix timestamp (seconds) of the window start.
+    pub window_start: u64,
+    pub requests: u64,
+    pub wall_time_ms: u64,
+    pub cpu_time_ms: u64,
+    /// Memory limit × wall time, in MiB-milliseconds.
+    pub memory_mib_ms: u64,
+    /// Requests trapped for exceeding the execution budget.
+    pub budget_exceeded: u64,
+}
+
+// ── Cluster events ────────────────────────────────────────────────
+
+/// Number of cluster events retained; older ones are dropped as new ones
+/// are recorded.
+pub const CLUSTER_EVENT_RETENTION: u64 = 10_000;
+
+/// What happened in a [`ClusterEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterEventKind {
+    DeploymentCreated,
+    DeploymentUpdated,
+    DeploymentDeleted,
+    DeploymentScaled,
+    DeploymentRolledBack,
+    /// Health checks failed and the instance was marked unhealthy.
+    InstanceUnhealthy,
+    RolloutStarted,
+    RolloutPaused,
+    RolloutResumed,
+    RolloutRolledBack,
+    NodeJoined,
+    /// Missed heartbeats past the dead timeout and was removed.
+    NodeLost,
+    NodeCordoned,
+    NodeUncordoned,
+    NodeDrained,
+}
+
+/// Something that changed in the cluster, and why, for operators.
+///
+/// `sequence` is assigned by the store, strictly increasing, so watchers
+/// can resume after the last event they saw.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ClusterEvent {
+    pub sequence: u64,
+    /// Unix timestamp (seconds).
+    pub timestamp: u64,
+    pub kind: ClusterEventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment_id: Option<DeploymentId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<InstanceId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<NodeId>,
+    /// What happened, e.g. "scaled from 2 to 5 instances".
+    pub message: String,
+}
+
+// ── Webhooks ──────────────────────────────────────────────────────
+
+/// An endpoint that is sent the cluster events it selects.
+///
+/// Each delivery is signed with `secret`: the `X-Warpgrid-Signature` header
+/// carries `sha256=` and the hex HMAC-SHA256 of the body.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Webhook {
+    pub id: String,
+    /// `http://` or `https://` URL the events are POSTed to.
+    pub url: String,
+    /// Only events of these kinds; every kind when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<ClusterEventKind>,
+    /// Only events about this deployment (`namespace/name`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<DeploymentId>,
+    /// HMAC key the deliveries are signed with.
+    pub secret: String,
+    /// Unix timestamp of creation.
+    pub created_at: u64,
+}
+
+// ── Idempotency ───────────────────────────────────────────────────
+
+/// How long a response is kept for replay to retries sent with the same
+/// `Idempotency-Key`, in seconds.
+pub const IDEMPOTENCY_KEY_TTL_SECS: u64 = 24 * 3600;
+
+/// How long a key stays reserved for a request that never completed, e.g.
+/// because its daemon died mid-request, in seconds.
+pub const IDEMPOTENCY_PENDING_TTL_SECS: u64 = 15 * 60;
+
+/// The response to a request sent with an `Idempotency-Key`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct IdempotencyRecord {
+    pub key: String,
+    /// Digest of the request the key was first sent with.
+    pub fingerprint: String,
+    /// Response status; `None` while the request is still being handled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub body: String,
+    /// Unix timestamp of the first request.
+    pub created_at: u64,
+}
+
+impl IdempotencyRecord {
+    /// When the key can be reused: a day after a response was stored, or
+    /// [`IDEMPOTENCY_PENDING_TTL_SECS`] after a reservation that has none.
+    pub fn expires_at(&self) -> u64 {
+        let ttl = match self.status {
+            Some(_) => IDEMPOTENCY_KEY_TTL_SECS,
+            None => IDEMPOTENCY_PENDING_TTL_SECS,
+        };
+        self.created_at + ttl
+    }
+}
+
+/// Number of state changes retained for read-replica catch-up. Replicas
+/// further behind than this receive a full snapshot instead.
+pub const STATE_CHANGE_RETENTION: u64 = 10_000;
+
+/// A single write to a replicated table (deployments, instances, services, flags).
+///
+/// `revision` is assigned by the store, strictly increasing across all
+/// replicated tables. `value` is the stored JSON document, or `None` for
+/// a delete.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct StateChange {
+    pub revision: u64,
+    pub table: String,
+    pub key: String,
+    pub value: Option<serde_json::Value>,
+}
+
+/// Payload that brings a read replica up to date.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplicaSync {
+    /// Full contents of the replicated tables as of `revision`.
+    /// Replaces everything the replica holds.
+    Snapshot {
+        revision: u64,
+        entries: Vec<StateChange>,
+    },
+    /// Changes after the replica's revision, oldest first.
+    Deltas { changes: Vec<StateChange> },
+}
+
+// ── Integrity ─────────────────────────────────────────────────────
+
+/// A record that failed its checksum or could not be decoded, moved out
+/// of its table so reads can continue without it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct QuarantinedRecord {
+    /// Table the record was removed from.
+    pub table: String,
+    pub key: String,
+    /// Why the record was rejected (checksum mismatch, truncated, bad JSON).
+    pub reason: String,
+    /// The stored bytes, kept for manual recovery.
+    pub raw: Vec<u8>,
+    /// Unix timestamp (seconds) when the corruption was detected.
+    pub detected_at: u64,
+}
+
+/// Outcome of a full integrity scan of the state store.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct IntegrityReport {
+    pub tables_scanned: u32,
+    pub records_checked: u64,
+    /// Records written before checksums were introduced. They are still
+    /// readable, but corruption in them is only caught if the JSON is invalid.
+    pub unsealed_records: u64,
+    /// Records quarantined by this scan.
+    pub quarantined: Vec<QuarantinedRecord>,
+    /// Unix timestamp (seconds) when the scan finished.
+    pub completed_at: u64,
+}
+
+/// Admin view of state-store integrity.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct IntegrityStatus {
+    /// Corrupt records detected since this process started.
+    pub corrupt_records_detected: u64,
+    /// Most recent full scan, if one has run in this process.
+    pub last_scan: Option<IntegrityReport>,
+    /// Every record currently held in quarantine.
+    pub quarantined: Vec<QuarantinedRecord>,
+}
+
+impl UsageEvent {
+    /// Start of the rollup window this event falls into.
+    pub fn window_start(&self) -> u64 {
+        let secs = self.started_at_ms / 1000;
+        secs - secs % USAGE_ROLLUP_WINDOW_SECS
+    }
+}
+
+impl ClusterEventKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [ClusterEventKind; 15] = [
+        Self::DeploymentCreated,
+        Self::DeploymentUpdated,
+        Self::DeploymentDeleted,
+        Self::DeploymentScaled,
+        Self::DeploymentRolledBack,
+        Self::InstanceUnhealthy,
+        Self::RolloutStarted,
+        Self::RolloutPaused,
+        Self::RolloutResumed,
+        Self::RolloutRolledBack,
+        Self::NodeJoined,
+        Self::NodeLost,
+        Self::NodeCordoned,
+        Self::NodeUncordoned,
+        Self::NodeDrained,
+    ];
+
+    /// The serialized name, e.g. `deployment_scaled`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DeploymentCreated => "deployment_created",
+            Self::DeploymentUpdated => "deployment_updated",
+            Self::DeploymentDeleted => "deployment_deleted",
+            Self::DeploymentScaled => "deployment_scaled",
+            Self::DeploymentRolledBack => "deployment_rolled_back",
+            Self::InstanceUnhealthy => "instance_unhealthy",
+            Self::RolloutStarted => "rollout_started",
+            Self::RolloutPaused => "rollout_paused",
+            Self::RolloutResumed => "rollout_resumed",
+            Self::RolloutRolledBack => "rollout_rolled_back",
+            Self::NodeJoined => "node_joined",
+            Self::NodeLost => "node_lost",
+            Self::NodeCordoned => "node_cordoned",
+            Self::NodeUncordoned => "node_uncordoned",
+            Self::NodeDrained => "node_drained",
+        }
+    }
+}
+
+impl ClusterEvent {
+    /// An event about nothing in particular yet; see the `for_*` methods.
+    pub fn new(kind: ClusterEventKind, timestamp: u64, message: impl Into<String>) -> Self {
+        Self {
+            sequence: 0,
+            timestamp,
+            kind,
+            deployment_id: None,
+            instance_id: None,
+            node_id: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn for_deployment(mut self, deployment_id: &str) -> Self {
+        self.deployment_id = Some(deployment_id.to_string());
+        self
+    }
+
+    pub fn for_instance(mut self, instance_id: &str) -> Self {
+        self.instance_id = Some(instance_id.to_string());
+        self
+    }
+
+    pub fn for_node(mut self, node_id: &str) -> Self {
+        self.node_id = Some(node_id.to_string());
+        self
+    }
+}
+
+impl Webhook {
+    /// Whether this webhook selects `event`.
+    pub fn matches(&self, event: &ClusterEvent) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && self.deployment.as_ref().is_none_or(|d| event.deployment_id.as_ref() == Some(d))
+    }
+}
+
+impl UsageRollup {
+    /// Create an empty rollup for a deployment window.
+    pub fn empty(deployment_id: &str, window_start: u64) -> Self {
+        Self {
+            deployment_id: deployment_id.to_string(),
+            window_start,
+            requests: 0,
+            wall_time_ms: 0,
+            cpu_time_ms: 0,
+            memory_mib_ms: 0,
+            budget_exceeded: 0,
+        }
+    }
+
+    /// Fold a single usage event into this rollup.
+    pub fn add(&mut self, event: &UsageEvent) {
+        self.requests += 1;
+        self.wall_time_ms += event.wall_time_ms;
+        self.cpu_time_ms += event.cpu_time_ms;
+        self.memory_mib_ms += (event.memory_limit_bytes / (1024 * 1024)) * event.wall_time_ms;
+        if event.budget_exceeded {
+            self.budget_exceeded += 1;
+        }
+    }
+
+    /// Build the composite key for the usage rollups table.
+    pub fn table_key(&self) -> String {
+        format!("{}:{}", self.deployment_id, self.window_start)
+    }
+}
+
+/// Priority of deployments that don't specify one.
+pub const DEFAULT_PRIORITY: u32 = 10;
+
 impl DeploymentSpec {
     /// Build the composite key for the deployments table.
     pub fn table_key(&self) -> String {
         format!("{}/{}", self.namespace, self.name)
     }
+
+    /// Put a spec without a namespace in [`DEFAULT_NAMESPACE`], and give
+    /// one without an id its table key.
+    pub fn fill_defaults(&mut self) {
+        if self.namespace.is_empty() {
+            self.namespace = DEFAULT_NAMESPACE.to_string();
+        }
+        if self.id.is_empty() {
+            self.id = self.table_key();
+        }
+    }
+
+    /// Placement priority, defaulting to [`DEFAULT_PRIORITY`].
+    pub fn effective_priority(&self) -> u32 {
+        self.priority.unwrap_or(DEFAULT_PRIORITY)
+    }
+}
+
+impl NodeInfo {
+    /// Whether placement should avoid this node at `now` (unix seconds)
+    /// because of recently reported memory pressure.
+    pub fn is_under_pressure(&self, now: u64) -> bool {
+        self.pressure_until.is_some_and(|until| until > now)
+    }
+
+    /// Whether placement may put new instances here at `now`: the node is
+    /// not cordoned and not under memory pressure.
+    pub fn is_schedulable(&self, now: u64) -> bool {
+        !self.cordoned && !self.is_under_pressure(now)
+    }
+
+    /// The CPU weight placement packs against: the benchmarked weight when
+    /// the node measured one, else the configured capacity.
+    pub fn effective_cpu_weight(&self) -> u32 {
+        self.calibrated_cpu_weight.unwrap_or(self.capacity_cpu_weight)
+    }
 }
 
 impl InstanceState {
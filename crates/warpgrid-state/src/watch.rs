@@ -0,0 +1,276 @@
+//! Change notifications for [`StateStore::watch`](crate::StateStore::watch).
+//!
+//! [`WatchedBackend`] wraps the store's backend and broadcasts a
+//! [`WatchEvent`] for every put and delete once it is committed: direct
+//! writes right away, transactional ones after the transaction commits, in
+//! the order they were made.
+//!
+//! Each table has its own channel, so a busy table such as metrics or
+//! usage cannot make a deployments watcher lag, and old values are only
+//! read for tables someone is watching. Direct writes stay direct: their
+//! old value is read just before the write rather than in a transaction,
+//! which on Postgres would take the store's transaction lock, so a
+//! concurrent write in between can make it stale.
+//!
+//! Watchers select changes by `{table}` or `{table}/{key prefix}`, e.g.
+//! `"deployments"` or `"instances/default/api:"`. A watcher that falls
+//! more than [`WATCH_CAPACITY`] changes to its table behind gets
+//! [`StateError::WatchLagged`] and should re-read what it tracks.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures_util::{FutureExt, Stream, StreamExt};
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::backend::{BackendTxn, KvEntry, StateBackend};
+use crate::error::{StateError, StateResult};
+use crate::integrity::decode;
+
+/// Changes buffered per watcher before it lags.
+pub const WATCH_CAPACITY: usize = 1024;
+
+/// A committed write to the store.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchEvent {
+    /// `key` was inserted (`old` is `None`) or overwritten.
+    Put {
+        table: String,
+        key: String,
+        old: Option<Value>,
+        new: Value,
+    },
+    /// `key` was removed.
+    Delete { table: String, key: String, old: Value },
+}
+
+impl WatchEvent {
+    pub fn table(&self) -> &str {
+        match self {
+            Self::Put { table, .. } | Self::Delete { table, .. } => table,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Put { key, .. } | Self::Delete { key, .. } => key,
+        }
+    }
+
+    /// Whether the event is in `prefix`'s table, under its key prefix.
+    pub fn matches(&self, prefix: &str) -> bool {
+        let (table, key) = split_prefix(prefix);
+        self.table() == table && self.key().starts_with(key)
+    }
+}
+
+/// A watch prefix's table and key prefix.
+fn split_prefix(prefix: &str) -> (&str, &str) {
+    prefix.split_once('/').unwrap_or((prefix, ""))
+}
+
+/// Changes under `prefix`, as returned by `StateStore::watch`.
+pub(crate) fn stream(
+    backend: &WatchedBackend,
+    prefix: String,
+) -> impl Stream<Item = StateResult<WatchEvent>> + Send + use<> {
+    let receiver = backend.subscribe(split_prefix(&prefix).0);
+    futures_util::stream::unfold(receiver, move |mut receiver| {
+        let prefix = prefix.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.matches(&prefix) => return Some((Ok(event), receiver)),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        return Some((Err(StateError::WatchLagged(missed)), receiver));
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    })
+}
+
+/// Wait for the next change from `changes`, a [`StateStore::watch`] stream,
+/// or for `timeout` to pass, whichever comes first; then drop the changes
+/// already waiting, so a burst of writes wakes a reader that re-reads
+/// everything only once. Returns false on timeout.
+///
+/// A lag counts as a change. Readers keep a timeout for what is not
+/// journaled, or as a backstop.
+///
+/// [`StateStore::watch`]: crate::StateStore::watch
+pub async fn changed<S>(changes: &mut S, timeout: Duration) -> bool
+where
+    S: Stream<Item = StateResult<WatchEvent>> + Unpin,
+{
+    let changed = tokio::select! {
+        Some(_) = changes.next() => true,
+        _ = tokio::time::sleep(timeout) => false,
+    };
+    while let Some(Some(_)) = changes.next().now_or_never() {}
+    changed
+}
+
+/// A backend that broadcasts its committed writes.
+pub(crate) struct WatchedBackend {
+    inner: Arc<dyn StateBackend>,
+    /// Channels by table, created when the table is first watched.
+    channels: RwLock<HashMap<String, broadcast::Sender<WatchEvent>>>,
+}
+
+impl WatchedBackend {
+    pub(crate) fn new(inner: Arc<dyn StateBackend>) -> Self {
+        Self { inner, channels: RwLock::default() }
+    }
+
+    fn subscribe(&self, table: &str) -> broadcast::Receiver<WatchEvent> {
+        let mut channels = self.channels.write().expect("watch channels lock poisoned");
+        channels.entry(table.to_string()).or_insert_with(|| broadcast::channel(WATCH_CAPACITY).0).subscribe()
+    }
+
+    /// `table`'s channel, if anyone is watching it.
+    fn sender(&self, table: &str) -> Option<broadcast::Sender<WatchEvent>> {
+        let channels = self.channels.read().expect("watch channels lock poisoned");
+        channels.get(table).filter(|sender| sender.receiver_count() > 0).cloned()
+    }
+
+    fn watched(&self) -> bool {
+        let channels = self.channels.read().expect("watch channels lock poisoned");
+        channels.values().any(|sender| sender.receiver_count() > 0)
+    }
+
+    fn publish(&self, events: Vec<WatchEvent>) {
+        for event in events {
+            if let Some(sender) = self.sender(event.table()) {
+                // Its last receiver may have gone since; nobody is watching.
+                let _ = sender.send(event);
+            }
+        }
+    }
+}
+
+/// A stored value as JSON; one that does not verify reads as `null`.
+fn json(bytes: &[u8]) -> Value {
+    decode(bytes).unwrap_or(Value::Null)
+}
+
+fn put_event(table: &str, key: &str, old: Option<Vec<u8>>, new: &[u8]) -> WatchEvent {
+    WatchEvent::Put {
+        table: table.to_string(),
+        key: key.to_string(),
+        old: old.as_deref().map(json),
+        new: json(new),
+    }
+}
+
+fn delete_event(table: &str, key: &str, old: &[u8]) -> WatchEvent {
+    WatchEvent::Delete { table: table.to_string(), key: key.to_string(), old: json(old) }
+}
+
+impl StateBackend for WatchedBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn get(&self, table: &str, key: &str) -> StateResult<Option<Vec<u8>>> {
+        self.inner.get(table, key)
+    }
+
+    fn put(&self, table: &str, key: &str, value: &[u8]) -> StateResult<()> {
+        let Some(sender) = self.sender(table) else {
+            return self.inner.put(table, key, value);
+        };
+        let old = self.inner.get(table, key)?;
+        self.inner.put(table, key, value)?;
+        let _ = sender.send(put_event(table, key, old, value));
+        Ok(())
+    }
+
+    fn remove(&self, table: &str, key: &str) -> StateResult<bool> {
+        let Some(sender) = self.sender(table) else {
+            return self.inner.remove(table, key);
+        };
+        let Some(old) = self.inner.get(table, key)? else {
+            return Ok(false);
+        };
+        let existed = self.inner.remove(table, key)?;
+        if existed {
+            let _ = sender.send(delete_event(table, key, &old));
+        }
+        Ok(existed)
+    }
+
+    fn scan_prefix(&self, table: &str, prefix: &str) -> StateResult<Vec<KvEntry>> {
+        self.inner.scan_prefix(table, prefix)
+    }
+
+    fn scan_from(&self, table: &str, start: &str, limit: usize) -> StateResult<Vec<KvEntry>> {
+        self.inner.scan_from(table, start, limit)
+    }
+
+    fn last_key(&self, table: &str) -> StateResult<Option<String>> {
+        self.inner.last_key(table)
+    }
+
+    fn transaction(&self, f: &mut dyn FnMut(&mut dyn BackendTxn) -> StateResult<()>) -> StateResult<()> {
+        if !self.watched() {
+            return self.inner.transaction(f);
+        }
+        let mut events = Vec::new();
+        self.inner.transaction(&mut |txn| {
+            // A backend may retry the closure; only the committed attempt counts.
+            events.clear();
+            f(&mut WatchedTxn { backend: self, inner: txn, events: &mut events })
+        })?;
+        self.publish(events);
+        Ok(())
+    }
+}
+
+/// A transaction that records its writes to watched tables as
+/// [`WatchEvent`]s.
+struct WatchedTxn<'a> {
+    backend: &'a WatchedBackend,
+    inner: &'a mut dyn BackendTxn,
+    events: &'a mut Vec<WatchEvent>,
+}
+
+impl BackendTxn for WatchedTxn<'_> {
+    fn get(&mut self, table: &str, key: &str) -> StateResult<Option<Vec<u8>>> {
+        self.inner.get(table, key)
+    }
+
+    fn put(&mut self, table: &str, key: &str, value: &[u8]) -> StateResult<()> {
+        if self.backend.sender(table).is_none() {
+            return self.inner.put(table, key, value);
+        }
+        let old = self.inner.get(table, key)?;
+        self.inner.put(table, key, value)?;
+        self.events.push(put_event(table, key, old, value));
+        Ok(())
+    }
+
+    fn remove(&mut self, table: &str, key: &str) -> StateResult<bool> {
+        if self.backend.sender(table).is_none() {
+            return self.inner.remove(table, key);
+        }
+        let Some(old) = self.inner.get(table, key)? else {
+            return Ok(false);
+        };
+        self.inner.remove(table, key)?;
+        self.events.push(delete_event(table, key, &old));
+        Ok(true)
+    }
+
+    fn scan_prefix(&mut self, table: &str, prefix: &str) -> StateResult<Vec<KvEntry>> {
+        self.inner.scan_prefix(table, prefix)
+    }
+
+    fn last_key(&mut self, table: &str) -> StateResult<Option<String>> {
+        self.inner.last_key(table)
+    }
+}
@@ -0,0 +1,305 @@
+//! Typed client for the management API (`/api/v1`).
+//!
+//! Every call opens a fresh HTTP/1.1 connection, so a client is cheap to
+//! clone and never holds state between requests. Responses are unwrapped
+//! from the API's `{ success, data, error }` envelope.
+
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+use http::{Method, Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use warpgrid_api::handlers::{ScaleRequest, UsageEventsPage};
+use warpgrid_api::rollout_handlers::{RolloutStatus, StartRolloutRequest};
+use warpgrid_rollout::RolloutStrategy;
+use warpgrid_state::{DeploymentSpec, InstanceState, MetricsSnapshot, NodeInfo, UsageRollup};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("request to {path} failed: {source}")]
+    Transport {
+        path: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("{path} returned {status}: {message}")]
+    Api {
+        path: String,
+        status: StatusCode,
+        message: String,
+    },
+
+    #[error("{path} returned an unexpected body: {source}")]
+    Decode {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl ClientError {
+    /// The HTTP status of an API error response.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// The API's response envelope.
+#[derive(serde::Deserialize)]
+struct Envelope<T> {
+    data: Option<T>,
+    error: Option<String>,
+}
+
+/// Body of a successful scale request.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Scaled {
+    pub deployment: String,
+    pub target: u32,
+    pub status: String,
+}
+
+/// Client for a daemon's management API.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    addr: SocketAddr,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr, token: None }
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // ── Deployments ────────────────────────────────────────────
+
+    pub async fn list_deployments(&self) -> Result<Vec<DeploymentSpec>> {
+        self.call(Method::GET, "/api/v1/deployments", None::<&()>).await
+    }
+
+    pub async fn create_deployment(&self, spec: &DeploymentSpec) -> Result<DeploymentSpec> {
+        self.call(Method::POST, "/api/v1/deployments", Some(spec)).await
+    }
+
+    /// `None` when the deployment does not exist.
+    pub async fn get_deployment(&self, id: &str) -> Result<Option<DeploymentSpec>> {
+        let path = format!("/api/v1/deployments/{}", encode(id));
+        match self.call(Method::GET, &path, None::<&()>).await {
+            Err(e) if e.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    pub async fn delete_deployment(&self, id: &str) -> Result<()> {
+        let path = format!("/api/v1/deployments/{}", encode(id));
+        self.call::<String>(Method::DELETE, &path, None::<&()>).await?;
+        Ok(())
+    }
+
+    pub async fn scale(&self, id: &str, target: u32) -> Result<Scaled> {
+        let path = format!("/api/v1/deployments/{}/scale", encode(id));
+        self.call(Method::POST, &path, Some(&ScaleRequest { target: Some(target), ..Default::default() })).await
+    }
+
+    pub async fn list_instances(&self, id: &str) -> Result<Vec<InstanceState>> {
+        let path = format!("/api/v1/deployments/{}/instances", encode(id));
+        self.call(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn metrics(&self, id: &str) -> Result<Vec<MetricsSnapshot>> {
+        let path = format!("/api/v1/deployments/{}/metrics", encode(id));
+        self.call(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn usage(&self, id: &str, since: u64) -> Result<Vec<UsageRollup>> {
+        let path = format!("/api/v1/deployments/{}/usage?since={since}", encode(id));
+        self.call(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn usage_events(&self, after: u64) -> Result<UsageEventsPage> {
+        let path = format!("/api/v1/usage/events?after={after}");
+        self.call(Method::GET, &path, None::<&()>).await
+    }
+
+    // ── Nodes ──────────────────────────────────────────────────
+
+    pub async fn list_nodes(&self) -> Result<Vec<NodeInfo>> {
+        self.call(Method::GET, "/api/v1/nodes", None::<&()>).await
+    }
+
+    // ── Rollouts ───────────────────────────────────────────────
+
+    pub async fn start_rollout(
+        &self,
+        id: &str,
+        strategy: RolloutStrategy,
+        new_version: &str,
+    ) -> Result<RolloutStatus> {
+        let path = format!("/api/v1/deployments/{}/rollout", encode(id));
+        let body = StartRolloutRequest {
+            strategy,
+            new_version: new_version.to_string(),
+        };
+        self.call(Method::POST, &path, Some(&body)).await
+    }
+
+    pub async fn list_rollouts(&self) -> Result<Vec<RolloutStatus>> {
+        self.call(Method::GET, "/api/v1/rollouts", None::<&()>).await
+    }
+
+    pub async fn get_rollout(&self, id: &str) -> Result<RolloutStatus> {
+        let path = format!("/api/v1/rollouts/{}", encode(id));
+        self.call(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn pause_rollout(&self, id: &str) -> Result<RolloutStatus> {
+        let path = format!("/api/v1/rollouts/{}/pause", encode(id));
+        self.call(Method::POST, &path, None::<&()>).await
+    }
+
+    pub async fn resume_rollout(&self, id: &str) -> Result<RolloutStatus> {
+        let path = format!("/api/v1/rollouts/{}/resume", encode(id));
+        self.call(Method::POST, &path, None::<&()>).await
+    }
+
+    // ── Raw ────────────────────────────────────────────────────
+
+    /// Prometheus exposition from `/metrics`.
+    pub async fn prometheus(&self) -> Result<String> {
+        let (status, body) = self.raw(Method::GET, "/metrics", None).await?;
+        if !status.is_success() {
+            return Err(ClientError::Api {
+                path: "/metrics".to_string(),
+                status,
+                message: String::from_utf8_lossy(&body).into_owned(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Send a request and return the status and body as-is.
+    pub async fn raw(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<(StatusCode, Bytes)> {
+        send(self.addr, method, "localhost", path, body, self.token.as_deref())
+            .await
+            .map_err(|source| ClientError::Transport {
+                path: path.to_string(),
+                source,
+            })
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&impl Serialize>,
+    ) -> Result<T> {
+        let decode = |source| ClientError::Decode {
+            path: path.to_string(),
+            source,
+        };
+        let body = body.map(serde_json::to_vec).transpose().map_err(decode)?;
+        let (status, bytes) = self.raw(method, path, body).await?;
+        let envelope: Envelope<T> = match serde_json::from_slice(&bytes) {
+            Ok(envelope) => envelope,
+            Err(_) if !status.is_success() => {
+                return Err(ClientError::Api {
+                    path: path.to_string(),
+                    status,
+                    message: String::from_utf8_lossy(&bytes).into_owned(),
+                });
+            }
+            Err(e) => return Err(decode(e)),
+        };
+        match envelope.data {
+            Some(data) if status.is_success() => Ok(data),
+            _ => Err(ClientError::Api {
+                path: path.to_string(),
+                status,
+                message: envelope.error.unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+/// Percent-encode a path segment (deployment ids contain `/`).
+fn encode(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// One HTTP/1.1 exchange with `addr`, sending `host` as the Host header.
+pub(crate) async fn send(
+    addr: SocketAddr,
+    method: Method,
+    host: &str,
+    path: &str,
+    body: Option<Vec<u8>>,
+    token: Option<&str>,
+) -> anyhow::Result<(StatusCode, Bytes)> {
+    let stream = tokio::net::TcpStream::connect(addr).await?;
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        let _ = conn.await;
+    });
+
+    let mut req = Request::builder()
+        .method(method)
+        .uri(path)
+        .header("host", host);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    if let Some(token) = token {
+        req = req.header("authorization", format!("Bearer {token}"));
+    }
+    let req = req.body(Full::new(Bytes::from(body.unwrap_or_default())))?;
+
+    let response = sender.send_request(req).await?;
+    let status = response.status();
+    let body = response.into_body().collect().await?.to_bytes();
+    Ok((status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_escapes_slashes() {
+        assert_eq!(encode("default/web-1"), "default%2Fweb-1");
+        assert_eq!(encode("a b"), "a%20b");
+    }
+}
@@ -0,0 +1,151 @@
+//! `warpd standalone`, running inside the test process.
+
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use bytes::Bytes;
+use http::{Method, StatusCode};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use warpd::planes::{Plane, PlaneKind, Planes};
+use warpd::standalone::StandaloneConfig;
+
+use crate::client::{self, ApiClient};
+
+/// How long the daemon gets to open its listeners.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A standalone daemon with its own data directory and loopback ports.
+pub struct TestDaemon {
+    api: SocketAddr,
+    ingress: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<anyhow::Result<()>>>,
+    data_dir: tempfile::TempDir,
+}
+
+impl TestDaemon {
+    /// Start the daemon and wait until the API and ingress are listening.
+    pub async fn start() -> anyhow::Result<Self> {
+        let data_dir = tempfile::tempdir()?;
+        let api = free_port()?;
+        let ingress = free_port()?;
+        let plane = |kind, addr| Plane {
+            kind,
+            addrs: vec![addr],
+            tls: None,
+            auth_token: None,
+        };
+        let config = StandaloneConfig {
+            planes: Planes {
+                management: plane(PlaneKind::Management, api),
+                metrics: None,
+                ingress: Some(plane(PlaneKind::Ingress, ingress)),
+                cluster: None,
+            },
+            data_dir: data_dir.path().to_path_buf(),
+            metrics_interval: 60,
+            autoscale_interval: 30,
+            verify_state: false,
+            api_tokens: false,
+            pre_instantiate: false,
+            memory: warpd::MemoryArgs::default(),
+            metrics_sinks: warpd::MetricsSinkArgs::default(),
+            response_limits: warpd::ResponseLimitArgs::default().limits(),
+            api_rate_limits: Default::default(),
+            signature_policy: warp_runtime::SignaturePolicy::disabled(),
+        };
+
+        let (shutdown, stop) = oneshot::channel::<()>();
+        let task = tokio::spawn(warpd::standalone::run(config, async {
+            let _ = stop.await;
+        }));
+
+        let mut daemon = Self {
+            api,
+            ingress,
+            shutdown: Some(shutdown),
+            task: Some(task),
+            data_dir,
+        };
+        daemon.wait_ready().await?;
+        Ok(daemon)
+    }
+
+    /// Poll until both listeners accept and the API answers, failing early
+    /// if the daemon exits.
+    async fn wait_ready(&mut self) -> anyhow::Result<()> {
+        let client = self.client();
+        let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+        loop {
+            if self.task.as_ref().is_some_and(|task| task.is_finished()) {
+                let task = self.task.take().expect("checked above");
+                task.await?.context("warpd exited during startup")?;
+                bail!("warpd exited during startup");
+            }
+            let ingress_up = tokio::net::TcpStream::connect(self.ingress).await.is_ok();
+            if ingress_up && client.list_nodes().await.is_ok() {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                bail!("warpd did not become ready within {READY_TIMEOUT:?}");
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Management API address.
+    pub fn api_addr(&self) -> SocketAddr {
+        self.api
+    }
+
+    /// App ingress address.
+    pub fn ingress_addr(&self) -> SocketAddr {
+        self.ingress
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        self.data_dir.path()
+    }
+
+    /// A client for the management API.
+    pub fn client(&self) -> ApiClient {
+        ApiClient::new(self.api)
+    }
+
+    /// Send a request through the app ingress, routed by `host` and `path`.
+    pub async fn ingress(
+        &self,
+        method: Method,
+        host: &str,
+        path: &str,
+    ) -> anyhow::Result<(StatusCode, Bytes)> {
+        client::send(self.ingress, method, host, path, None, None).await
+    }
+
+    /// Shut the daemon down gracefully and return how it exited.
+    pub async fn stop(mut self) -> anyhow::Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        match self.task.take() {
+            Some(task) => task.await?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TestDaemon {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// A loopback address with a port that was free a moment ago.
+fn free_port() -> anyhow::Result<SocketAddr> {
+    Ok(TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?.local_addr()?)
+}
@@ -0,0 +1,41 @@
+//! Ready-made specs for tests.
+
+use std::collections::HashMap;
+
+use warpgrid_state::*;
+
+/// An HTTP deployment `namespace/name` with 1–5 instances of 64 MiB.
+///
+/// The source points at a file that does not exist, so the daemon keeps
+/// the route but never loads an app for it.
+pub fn deployment(namespace: &str, name: &str) -> DeploymentSpec {
+    DeploymentSpec {
+        id: format!("{namespace}/{name}"),
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        source: format!("file:///nonexistent/{name}.wasm"),
+        trigger: TriggerConfig::Http {
+            port: None,
+            hosts: vec![],
+            path_prefix: None,
+        },
+        instances: InstanceConstraints { min: 1, max: 5 },
+        resources: ResourceLimits {
+            memory_bytes: 64 * 1024 * 1024,
+            cpu_weight: 100,
+            execution_budget_ms: None,
+        },
+        scaling: None,
+        health: None,
+        shims: ShimsEnabled::default(),
+        env: HashMap::new(),
+        created_at: 1000,
+        updated_at: 1000,
+        priority: None,
+        min_available: None,
+        labels: Default::default(),
+        paused: false,
+        secrets: Vec::new(),
+        config_maps: Vec::new(),
+    }
+}
@@ -0,0 +1,106 @@
+//! Black-box tests against an in-process standalone daemon.
+
+use std::time::Duration;
+
+use http::{Method, StatusCode};
+use warpgrid_rollout::{RollingConfig, RolloutPhase, RolloutStrategy};
+use warpgrid_testkit::{TestDaemon, eventually, fixtures};
+
+#[tokio::test]
+async fn registers_the_standalone_node() {
+    let daemon = TestDaemon::start().await.unwrap();
+    let nodes = daemon.client().list_nodes().await.unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].id, "standalone");
+    assert_eq!(nodes[0].port, daemon.api_addr().port());
+    daemon.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn deployment_lifecycle() {
+    let daemon = TestDaemon::start().await.unwrap();
+    let api = daemon.client();
+
+    let spec = fixtures::deployment("default", "web");
+    assert_eq!(api.create_deployment(&spec).await.unwrap(), spec);
+    assert_eq!(api.get_deployment("default/web").await.unwrap(), Some(spec));
+    assert_eq!(api.list_deployments().await.unwrap().len(), 1);
+    assert!(api.list_instances("default/web").await.unwrap().is_empty());
+
+    api.delete_deployment("default/web").await.unwrap();
+    assert_eq!(api.get_deployment("default/web").await.unwrap(), None);
+    let err = api.delete_deployment("default/web").await.unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+    daemon.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn scale_is_bounded_by_max_instances() {
+    let daemon = TestDaemon::start().await.unwrap();
+    let api = daemon.client();
+    api.create_deployment(&fixtures::deployment("default", "web"))
+        .await
+        .unwrap();
+
+    let scaled = api.scale("default/web", 3).await.unwrap();
+    assert_eq!(scaled.target, 3);
+    let err = api.scale("default/web", 6).await.unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+    daemon.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn rollout_can_be_paused_and_resumed() {
+    let daemon = TestDaemon::start().await.unwrap();
+    let api = daemon.client();
+    api.create_deployment(&fixtures::deployment("default", "web"))
+        .await
+        .unwrap();
+
+    let strategy = RolloutStrategy::Rolling(RollingConfig::default());
+    let started = api
+        .start_rollout("default/web", strategy.clone(), "file:///v2.wasm")
+        .await
+        .unwrap();
+    assert_eq!(started.new_version, "file:///v2.wasm");
+    let err = api
+        .start_rollout("default/web", strategy, "file:///v3.wasm")
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+
+    let paused = api.pause_rollout("default/web").await.unwrap();
+    assert_eq!(paused.phase, RolloutPhase::Paused);
+    let resumed = api.resume_rollout("default/web").await.unwrap();
+    assert_eq!(resumed.phase, RolloutPhase::HealthGate);
+    assert_eq!(api.list_rollouts().await.unwrap().len(), 1);
+    daemon.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn ingress_follows_deployments() {
+    let daemon = TestDaemon::start().await.unwrap();
+    let (status, _) = daemon.ingress(Method::GET, "web.local", "/").await.unwrap();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The artifact does not exist, so the route is served without an app.
+    daemon
+        .client()
+        .create_deployment(&fixtures::deployment("default", "web"))
+        .await
+        .unwrap();
+    let routed = eventually(Duration::from_secs(10), || async {
+        let (status, _) = daemon.ingress(Method::GET, "web.local", "/").await.ok()?;
+        (status == StatusCode::SERVICE_UNAVAILABLE).then_some(())
+    })
+    .await;
+    assert!(routed.is_some(), "route was never synced");
+    daemon.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn prometheus_endpoint_is_served() {
+    let daemon = TestDaemon::start().await.unwrap();
+    daemon.client().prometheus().await.unwrap();
+    daemon.stop().await.unwrap();
+}
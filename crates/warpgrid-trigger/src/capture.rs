@@ -0,0 +1,124 @@
+//! Guest stdout/stderr capture.
+//!
+//! [`LogCapture`] is a WASI output stream that splits what the guest
+//! writes into lines and appends them to
+//! [`warpgrid_metrics::guest_logs`]. A trailing partial line is kept
+//! until the next newline, or until the instance's store is dropped.
+//! Stderr is also copied to the daemon's own stderr, as before capture.
+
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use tokio::io::AsyncWrite;
+use wasmtime_wasi::cli::{IsTerminal, StdoutStream};
+use warpgrid_metrics::guest_logs::{LogStream, MAX_LINE_BYTES, guest_logs};
+
+/// Output stream of one instance; see the module docs.
+#[derive(Clone)]
+pub struct LogCapture {
+    sink: Arc<LineSink>,
+}
+
+struct LineSink {
+    deployment_id: String,
+    instance: String,
+    stream: LogStream,
+    partial: Mutex<Vec<u8>>,
+}
+
+impl LogCapture {
+    pub fn new(deployment_id: &str, instance: &str, stream: LogStream) -> Self {
+        Self {
+            sink: Arc::new(LineSink {
+                deployment_id: deployment_id.to_string(),
+                instance: instance.to_string(),
+                stream,
+                partial: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+}
+
+impl LineSink {
+    fn write(&self, bytes: &[u8]) {
+        if self.stream == LogStream::Stderr {
+            let _ = std::io::stderr().write_all(bytes);
+        }
+        let mut partial = self.partial.lock().expect("log capture lock poisoned");
+        partial.extend_from_slice(bytes);
+        while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+            self.push(&partial[..end]);
+            partial.drain(..=end);
+        }
+        // A line that never ends is cut rather than buffered forever.
+        if partial.len() >= MAX_LINE_BYTES {
+            self.push(&partial);
+            partial.clear();
+        }
+    }
+
+    fn push(&self, line: &[u8]) {
+        guest_logs().push(&self.deployment_id, &self.instance, self.stream, line);
+    }
+}
+
+impl Drop for LineSink {
+    fn drop(&mut self) {
+        let partial = self.partial.get_mut().expect("log capture lock poisoned");
+        if !partial.is_empty() {
+            let line = std::mem::take(partial);
+            self.push(&line);
+        }
+    }
+}
+
+impl IsTerminal for LogCapture {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl StdoutStream for LogCapture {
+    fn async_stream(&self) -> Box<dyn AsyncWrite + Send + Sync> {
+        Box::new(Writer(self.sink.clone()))
+    }
+}
+
+struct Writer(Arc<LineSink>);
+
+impl AsyncWrite for Writer {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        self.0.write(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warpgrid_metrics::guest_logs::LogQuery;
+
+    #[test]
+    fn splits_lines_and_flushes_the_tail_on_drop() {
+        let deployment = "test/capture-lines";
+        let capture = LogCapture::new(deployment, "req-1", LogStream::Stdout);
+        capture.sink.write(b"hello\nwor");
+        capture.sink.write(b"ld\nno newline");
+        let lines = |q| guest_logs().query(deployment, &q).into_iter().map(|l| l.line).collect::<Vec<_>>();
+        assert_eq!(lines(LogQuery::default()), ["hello", "world"]);
+
+        drop(capture);
+        assert_eq!(lines(LogQuery::default()), ["hello", "world", "no newline"]);
+        guest_logs().forget(deployment);
+    }
+}
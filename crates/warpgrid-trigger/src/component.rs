@@ -0,0 +1,458 @@
+//! Serving requests from a component's `wasi:http/incoming-handler`.
+//!
+//! Each request gets a fresh store: WASI (environment from the deployment
+//! spec), `wasi:http`, and the WarpGrid shims enabled on the engine, with
+//! the deployment's feature flags and mounted secrets. The component's imports are resolved
+//! once, when the handler is built.
+//!
+//! Responses are held to the handler's [`ResponseLimits`] and their bodies
+//! streamed to the client as the guest writes them.
+//!
+//! The database proxy shim connects over plain TCP to whatever host and
+//! port the guest asks for, with the engine's proxy timeouts.
+//!
+//! A deployment's `execution_budget_ms` bounds each request's wall time,
+//! from arrival until the guest finishes: a guest still running then is
+//! stopped, and the request fails if no response was set yet. On metered
+//! engines a busy guest yields every epoch tick, so it is stopped on time
+//! even if it never waits on the host.
+//!
+//! Once the guest finishes a request, the epoch ticks it ran for and the
+//! linear memory it grew after instantiation are written to the access log
+//! (target `warpgrid::access`) and charged to the request's route in
+//! [`warpgrid_metrics::route_usage`], and a [`warpgrid_state::UsageEvent`]
+//! goes to the handler's [`UsageSink`], if it has one. Ticks are only
+//! counted on metered engines, which warpd and `warp dev` both run.
+//!
+//! Guest stdout and stderr are captured line by line into
+//! [`warpgrid_metrics::guest_logs`] ([`crate::capture`]). Every request
+//! runs in its own instance, named `req-<n>` in the captured lines.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, anyhow, bail};
+use hyper::body::Incoming;
+use hyper::{Request, Response};
+use tracing::{debug, info, warn};
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Store, StoreLimitsBuilder, UpdateDeadline};
+use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView};
+use wasmtime_wasi_http::bindings::ProxyPre;
+use wasmtime_wasi_http::bindings::http::types::Scheme;
+use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
+use warpgrid_host::db_proxy::tcp::{AsyncTcpConnectionFactory, TcpConnectionFactory};
+use warpgrid_host::engine::{HostState, WarpGridEngine};
+use warpgrid_host::bundles::SharedBundle;
+use warpgrid_host::flags::host::FlagsHost;
+use warpgrid_metrics::guest_logs::LogStream;
+use warpgrid_metrics::route_usage::{route_key, route_usage};
+use warp_runtime::UsageSample;
+use warpgrid_state::{DeploymentSpec, MountedSecrets};
+
+use crate::capture::LogCapture;
+use crate::convert::{ResponseLimits, limit_violation_response};
+use crate::handler::{RequestHandler, ResponseBody};
+use crate::stream::stream_body;
+use crate::usage::UsageSink;
+
+/// The world a deployment's component must target to be served here.
+pub const HTTP_WORLD: &str = "wasi:http/proxy@0.2.6";
+
+/// Per-request store data.
+struct RequestState {
+    host: HostState,
+    wasi: WasiCtx,
+    http: WasiHttpCtx,
+    table: ResourceTable,
+    /// Epoch ticks the guest has run for.
+    epoch_ticks: u64,
+}
+
+impl RequestState {
+    /// Linear memory currently allocated in the store.
+    fn memory_bytes(&self) -> usize {
+        self.host.limiter.as_ref().map_or(0, |limiter| limiter.memory_bytes())
+    }
+
+    /// Largest linear memory the store has reached.
+    fn peak_memory_bytes(&self) -> usize {
+        self.host.limiter.as_ref().map_or(0, |limiter| limiter.peak_memory_bytes())
+    }
+}
+
+/// What one request cost the guest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RequestUsage {
+    epoch_ticks: u64,
+    /// Peak linear memory above what instantiation allocated.
+    memory_growth_bytes: u64,
+    /// Whether the guest was stopped for running past its budget.
+    budget_exceeded: bool,
+}
+
+impl WasiView for RequestState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+impl WasiHttpView for RequestState {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+/// A deployment's component, ready to serve requests.
+struct ComponentHandler {
+    engine: WarpGridEngine,
+    deployment_id: String,
+    pre: ProxyPre<RequestState>,
+    env: Vec<(String, String)>,
+    memory_limit: usize,
+    /// Wall-clock budget of each request.
+    budget: Option<Duration>,
+    limits: ResponseLimits,
+    usage: Option<Arc<dyn UsageSink>>,
+    db_connect: Arc<TcpConnectionFactory>,
+    db_connect_async: Arc<AsyncTcpConnectionFactory>,
+    flags: Option<FlagsHost>,
+    config: SharedBundle,
+    secret_files: Arc<HashMap<String, Vec<u8>>>,
+    database_password: Option<String>,
+    /// Instances started so far, for naming the next one.
+    instances: AtomicU64,
+}
+
+impl ComponentHandler {
+    fn new(
+        engine: &WarpGridEngine,
+        component: &Component,
+        spec: &DeploymentSpec,
+        secrets: MountedSecrets,
+        limits: ResponseLimits,
+        usage: Option<Arc<dyn UsageSink>>,
+    ) -> anyhow::Result<Self> {
+        let mut linker = Linker::new(engine.engine());
+        wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+        wasmtime_wasi_http::add_only_http_to_linker_async(&mut linker)?;
+        engine.add_shims_to_linker(&mut linker, |state: &mut RequestState| &mut state.host)?;
+
+        let pre = linker
+            .instantiate_pre(component)
+            .context("failed to resolve component imports")?;
+        let pre = ProxyPre::new(pre)
+            .context("component does not export wasi:http/incoming-handler")?;
+
+        let db = &engine.config().database_proxy_config;
+        let connect_timeout = Duration::from_secs(db.connect_timeout_seconds);
+        let recv_timeout = Duration::from_secs(db.recv_timeout_seconds);
+
+        let mut env: HashMap<_, _> = spec.env.clone();
+        env.extend(secrets.env);
+        let mut env: Vec<_> = env.into_iter().collect();
+        env.sort();
+
+        Ok(Self {
+            engine: engine.clone(),
+            deployment_id: spec.id.clone(),
+            pre,
+            env,
+            memory_limit: spec.resources.memory_bytes as usize,
+            budget: spec.resources.execution_budget_ms.map(Duration::from_millis),
+            limits,
+            usage,
+            db_connect: Arc::new(TcpConnectionFactory::plain(recv_timeout, connect_timeout)),
+            db_connect_async: Arc::new(AsyncTcpConnectionFactory::new(connect_timeout)),
+            flags: engine.flags_host(&spec.id),
+            config: engine.bundles().handle(&spec.id),
+            secret_files: Arc::new(secrets.files),
+            database_password: secrets.database_password,
+            instances: AtomicU64::new(0),
+        })
+    }
+
+    fn new_store(&self, instance: &str) -> Store<RequestState> {
+        let mut host = self.engine.build_host_state_with_async(
+            Some(self.db_connect.clone()),
+            Some(self.db_connect_async.clone()),
+        );
+        host.flags = self.flags.clone();
+        host.attach_config(self.config.clone());
+        host.attach_secrets(self.secret_files.clone(), self.database_password.clone());
+        host.limiter = Some(
+            StoreLimitsBuilder::new()
+                .memory_size(self.memory_limit)
+                .table_elements(10_000)
+                .build()
+                .into(),
+        );
+        let wasi = WasiCtx::builder()
+            .envs(&self.env)
+            .stdout(LogCapture::new(&self.deployment_id, instance, LogStream::Stdout))
+            .stderr(LogCapture::new(&self.deployment_id, instance, LogStream::Stderr))
+            .build();
+
+        let mut store = Store::new(
+            self.engine.engine(),
+            RequestState {
+                host,
+                wasi,
+                http: WasiHttpCtx::new(),
+                table: ResourceTable::new(),
+                epoch_ticks: 0,
+            },
+        );
+        store.limiter(|state| {
+            state
+                .host
+                .limiter
+                .as_mut()
+                .expect("limiter must be set before instantiation")
+        });
+        if self.engine.epoch_tick().is_some() {
+            // Metered engines interrupt on every tick; count it and yield
+            // back to the executor instead of trapping.
+            store.epoch_deadline_callback(|mut store| {
+                store.data_mut().epoch_ticks += 1;
+                Ok(UpdateDeadline::Yield(1))
+            });
+            store.set_epoch_deadline(1);
+        }
+        store
+    }
+
+    async fn handle(&self, req: Request<Incoming>) -> anyhow::Result<Response<ResponseBody>> {
+        let started = Instant::now();
+        let started_at = SystemTime::now();
+        let method = req.method().to_string();
+        let path = req.uri().path().to_string();
+        let instance = format!("req-{}", self.instances.fetch_add(1, Ordering::Relaxed) + 1);
+        let mut store = self.new_store(&instance);
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let req = store.data_mut().new_incoming_request(Scheme::Http, req)?;
+        let out = store.data_mut().new_response_outparam(sender)?;
+        let pre = self.pre.clone();
+        let budget = self.budget;
+
+        // The guest may keep running after it has set the response (e.g.
+        // to stream the body), so it gets a task of its own.
+        let task = tokio::spawn(async move {
+            let mut baseline = 0;
+            let run = async {
+                let proxy = pre.instantiate_async(&mut store).await?;
+                baseline = store.data().memory_bytes();
+                proxy
+                    .wasi_http_incoming_handler()
+                    .call_handle(&mut store, req, out)
+                    .await
+            };
+            let (result, budget_exceeded) = match budget {
+                // Dropping the call stops the guest; its store is not reused.
+                Some(budget) => match tokio::time::timeout_at((started + budget).into(), run).await {
+                    Ok(result) => (result, false),
+                    Err(_) => (Err(anyhow!("execution budget of {} ms exceeded", budget.as_millis())), true),
+                },
+                None => (run.await, false),
+            };
+            let state = store.data();
+            let usage = RequestUsage {
+                epoch_ticks: state.epoch_ticks,
+                memory_growth_bytes: state.peak_memory_bytes().saturating_sub(baseline) as u64,
+                budget_exceeded,
+            };
+            (result, usage)
+        });
+        let request = ServedRequest { method, path, instance, started, started_at };
+
+        let response = match receiver.await {
+            Ok(Ok(response)) => response,
+            Ok(Err(code)) => {
+                self.account(task, request, 500);
+                bail!("guest returned an error response: {code:?}");
+            }
+            Err(_) => {
+                let (e, usage) = match task.await {
+                    Ok((Ok(()), usage)) => (anyhow!("guest never set a response"), usage),
+                    Ok((Err(e), usage)) => (e, usage),
+                    Err(e) => (e.into(), RequestUsage::default()),
+                };
+                self.access_logger().log(&request, 500, usage);
+                return Err(e.context("guest never set a response"));
+            }
+        };
+
+        let (parts, body) = response.into_parts();
+        if let Err(violation) = self.limits.check_headers(&parts.headers) {
+            warn!(status = %parts.status, %violation, "guest response rejected");
+            let response = limit_violation_response(&violation);
+            self.account(task, request, response.status().as_u16());
+            return Ok(response);
+        }
+        debug!(status = %parts.status, "component responded");
+        self.account(task, request, parts.status.as_u16());
+        Ok(Response::from_parts(parts, stream_body(body, self.limits)))
+    }
+
+    /// Log and charge the request once the guest task finishes, which may
+    /// be after the response has been sent.
+    fn account(
+        &self,
+        task: tokio::task::JoinHandle<(anyhow::Result<()>, RequestUsage)>,
+        request: ServedRequest,
+        status: u16,
+    ) {
+        let logger = self.access_logger();
+        tokio::spawn(async move {
+            let usage = match task.await {
+                Ok((result, usage)) => {
+                    if let Err(e) = result {
+                        debug!(error = %format!("{e:#}"), "guest failed after responding");
+                    }
+                    usage
+                }
+                Err(_) => RequestUsage::default(),
+            };
+            logger.log(&request, status, usage);
+        });
+    }
+
+    fn access_logger(&self) -> AccessLogger {
+        AccessLogger {
+            deployment_id: self.deployment_id.clone(),
+            epoch_tick: self.engine.epoch_tick(),
+            memory_limit: self.memory_limit as u64,
+            usage: self.usage.clone(),
+        }
+    }
+}
+
+/// A request as the access log and usage stream see it.
+struct ServedRequest {
+    method: String,
+    path: String,
+    /// Instance that served it, e.g. `req-3`.
+    instance: String,
+    started: Instant,
+    started_at: SystemTime,
+}
+
+/// Writes a finished request to the access log, route usage, and usage
+/// stream.
+struct AccessLogger {
+    deployment_id: String,
+    epoch_tick: Option<Duration>,
+    memory_limit: u64,
+    usage: Option<Arc<dyn UsageSink>>,
+}
+
+impl AccessLogger {
+    fn log(&self, request: &ServedRequest, status: u16, usage: RequestUsage) {
+        let duration = request.started.elapsed();
+        let cpu = self
+            .epoch_tick
+            .map_or(Duration::ZERO, |tick| tick.saturating_mul(usage.epoch_ticks.min(u32::MAX as u64) as u32));
+        let route = route_key(&request.method, &request.path);
+        route_usage().record(&self.deployment_id, &route, cpu, usage.memory_growth_bytes);
+        if let Some(sink) = &self.usage {
+            let sample = UsageSample {
+                wall_time: duration,
+                cpu_time: cpu,
+                memory_limit_bytes: self.memory_limit,
+                budget_exceeded: usage.budget_exceeded,
+            };
+            sink.record_sample(&self.deployment_id, &request.instance, request.started_at, &sample);
+        }
+        info!(
+            target: "warpgrid::access",
+            deployment = %self.deployment_id,
+            method = %request.method,
+            path = %request.path,
+            status,
+            duration_ms = duration.as_millis() as u64,
+            epoch_ticks = usage.epoch_ticks,
+            cpu_ms = cpu.as_millis() as u64,
+            memory_growth_bytes = usage.memory_growth_bytes,
+            budget_exceeded = usage.budget_exceeded,
+            "request"
+        );
+    }
+}
+
+/// Build the request handler serving `spec` from `component`, with
+/// `secrets` mounted into every instance, holding responses to `limits`,
+/// and sending each request's usage event to `usage`.
+///
+/// Fails when the component does not export `wasi:http/incoming-handler`
+/// or imports something the engine does not provide.
+pub fn component_handler(
+    engine: &WarpGridEngine,
+    component: &Component,
+    spec: &DeploymentSpec,
+    secrets: MountedSecrets,
+    limits: ResponseLimits,
+    usage: Option<Arc<dyn UsageSink>>,
+) -> anyhow::Result<RequestHandler> {
+    let handler = Arc::new(ComponentHandler::new(engine, component, spec, secrets, limits, usage)?);
+    Ok(Arc::new(move |req: Request<Incoming>| {
+        let handler = handler.clone();
+        Box::pin(async move { handler.handle(req).await })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use warpgrid_host::config::ShimConfig;
+    use warpgrid_state::*;
+
+    fn spec() -> DeploymentSpec {
+        DeploymentSpec {
+            id: "default/app".to_string(),
+            namespace: "default".to_string(),
+            name: "app".to_string(),
+            source: "file://app.wasm".to_string(),
+            trigger: TriggerConfig::Http { port: Some(8080), hosts: vec![], path_prefix: None },
+            instances: InstanceConstraints { min: 1, max: 1 },
+            resources: ResourceLimits {
+                memory_bytes: 64 * 1024 * 1024,
+                cpu_weight: 100,
+                execution_budget_ms: None,
+            },
+            scaling: None,
+            health: None,
+            shims: ShimsEnabled::default(),
+            env: HashMap::from([("APP_NAME".to_string(), "t".to_string())]),
+            created_at: 0,
+            updated_at: 0,
+            priority: None,
+            min_available: None,
+            labels: Default::default(),
+            paused: false,
+            secrets: Vec::new(),
+            config_maps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_component_without_incoming_handler_is_rejected() {
+        let engine = WarpGridEngine::new(ShimConfig::default()).unwrap();
+        let bytes = wat::parse_str("(component)").unwrap();
+        let component = Component::from_binary(engine.engine(), &bytes).unwrap();
+
+        let Err(err) = component_handler(&engine, &component, &spec(), MountedSecrets::default(), ResponseLimits::default(), None) else {
+            panic!("a component without a handler export must be rejected");
+        };
+        assert!(format!("{err:#}").contains("wasi:http/incoming-handler"), "{err:#}");
+    }
+}
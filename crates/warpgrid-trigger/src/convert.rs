@@ -2,8 +2,128 @@
 //!
 //! Converts between the external HTTP types (hyper/http) and the
 //! wasmtime-wasi-http internal types used by the component model.
+//!
+//! [`ResponseLimits`] bounds what a guest may send back: header count,
+//! header bytes, and body bytes. A response whose headers (or declared
+//! `content-length`) are over a limit is replaced by a 502 naming the
+//! limit. Bodies are streamed (see [`crate::stream`]), so an undeclared
+//! body that crosses the limit mid-stream can only be cut off.
+
+use std::fmt;
+use std::time::Duration;
+
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Response, StatusCode, Uri};
+
+use crate::handler::{ResponseBody, full_body};
+
+/// Limits on a guest's outgoing response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseLimits {
+    /// Maximum number of header fields.
+    pub max_header_count: usize,
+    /// Maximum total size of header names and values, in bytes.
+    pub max_header_bytes: usize,
+    /// Maximum body size, in bytes.
+    pub max_body_bytes: u64,
+    /// Body frames buffered between the guest and a slow client before the
+    /// guest has to wait.
+    pub stream_buffer_frames: usize,
+    /// How long the guest may wait on a client that reads nothing before
+    /// the response is abandoned.
+    pub stall_timeout: Duration,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        Self {
+            max_header_count: 100,
+            max_header_bytes: 64 * 1024,
+            max_body_bytes: 32 * 1024 * 1024,
+            stream_buffer_frames: 16,
+            stall_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A response limit the guest went over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitViolation {
+    HeaderCount { count: usize, limit: usize },
+    HeaderBytes { bytes: usize, limit: usize },
+    /// `bytes` is the declared `content-length`, or what had been read when
+    /// the limit was crossed.
+    BodyBytes { bytes: u64, limit: u64 },
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HeaderCount { count, limit } => {
+                write!(f, "response has {count} headers, limit is {limit}")
+            }
+            Self::HeaderBytes { bytes, limit } => {
+                write!(f, "response headers are {bytes} bytes, limit is {limit}")
+            }
+            Self::BodyBytes { bytes, limit } => {
+                write!(f, "response body exceeds {limit} bytes ({bytes} bytes or more)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitViolation {}
+
+impl ResponseLimits {
+    /// Check header count and size, and a declared `content-length`.
+    pub fn check_headers(&self, headers: &HeaderMap) -> Result<(), LimitViolation> {
+        let count = headers.len();
+        if count > self.max_header_count {
+            return Err(LimitViolation::HeaderCount {
+                count,
+                limit: self.max_header_count,
+            });
+        }
+        let bytes = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        if bytes > self.max_header_bytes {
+            return Err(LimitViolation::HeaderBytes {
+                bytes,
+                limit: self.max_header_bytes,
+            });
+        }
+        let declared = headers
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        match declared {
+            Some(length) => self.check_body(length),
+            None => Ok(()),
+        }
+    }
+
+    /// Check a body size (declared, or read so far).
+    pub fn check_body(&self, bytes: u64) -> Result<(), LimitViolation> {
+        if bytes > self.max_body_bytes {
+            return Err(LimitViolation::BodyBytes {
+                bytes,
+                limit: self.max_body_bytes,
+            });
+        }
+        Ok(())
+    }
+}
 
-use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+/// The 502 sent in place of a response that broke a limit.
+pub fn limit_violation_response(violation: &LimitViolation) -> Response<ResponseBody> {
+    Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .header("content-type", "text/plain")
+        .header("x-warpgrid-error", "response-limit")
+        .body(full_body(format!("Bad Gateway: {violation}\n")))
+        .expect("static response parts are valid")
+}
 
 /// Convert an http::Method to a string representation used by wasi-http.
 pub fn method_to_string(method: &Method) -> String {
@@ -87,6 +207,46 @@ mod tests {
         assert_eq!(restored.get("x-custom").unwrap(), "hello");
     }
 
+    #[test]
+    fn response_limits_check_headers() {
+        let limits = ResponseLimits {
+            max_header_count: 2,
+            max_header_bytes: 32,
+            max_body_bytes: 10,
+            ..ResponseLimits::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("x-a", "1".parse().unwrap());
+        assert_eq!(limits.check_headers(&headers), Ok(()));
+
+        headers.insert("x-b", "x".repeat(40).parse().unwrap());
+        assert_eq!(
+            limits.check_headers(&headers),
+            Err(LimitViolation::HeaderBytes { bytes: 47, limit: 32 })
+        );
+
+        headers.insert("x-c", "1".parse().unwrap());
+        assert!(matches!(
+            limits.check_headers(&headers),
+            Err(LimitViolation::HeaderCount { count: 3, limit: 2 })
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-length", "11".parse().unwrap());
+        assert_eq!(
+            limits.check_headers(&headers),
+            Err(LimitViolation::BodyBytes { bytes: 11, limit: 10 })
+        );
+    }
+
+    #[test]
+    fn limit_violation_is_a_502_with_diagnostics() {
+        let response =
+            limit_violation_response(&LimitViolation::HeaderCount { count: 150, limit: 100 });
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response.headers()["x-warpgrid-error"], "response-limit");
+    }
+
     #[test]
     fn uri_path_and_query_full() {
         let uri: Uri = "http://localhost:8080/api/v1?foo=bar".parse().unwrap();
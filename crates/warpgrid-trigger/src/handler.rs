@@ -8,14 +8,18 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use bytes::Bytes;
-use http_body_util::Full;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
 use hyper::body::Incoming;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Request, Response};
 use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
-use tracing::{error, info};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tracing::{debug, error, info};
 
 /// Callback type for handling HTTP requests.
 ///
@@ -25,9 +29,17 @@ pub type RequestHandler =
     Arc<dyn Fn(Request<Incoming>) -> BoxFuture + Send + Sync>;
 
 type BoxFuture = std::pin::Pin<
-    Box<dyn std::future::Future<Output = anyhow::Result<Response<Full<Bytes>>>> + Send>,
+    Box<dyn std::future::Future<Output = anyhow::Result<Response<ResponseBody>>> + Send>,
 >;
 
+/// Body of a handler's response: buffered, or streamed from a guest.
+pub type ResponseBody = BoxBody<Bytes, std::io::Error>;
+
+/// A buffered response body.
+pub fn full_body(bytes: impl Into<Bytes>) -> ResponseBody {
+    Full::new(bytes.into()).map_err(|never| match never {}).boxed()
+}
+
 /// HTTP trigger server.
 ///
 /// Binds to a TCP port and forwards incoming HTTP requests to a
@@ -36,12 +48,23 @@ type BoxFuture = std::pin::Pin<
 pub struct HttpTrigger {
     bind_addr: SocketAddr,
     handler: RequestHandler,
+    tls: Option<TlsAcceptor>,
 }
 
 impl HttpTrigger {
     /// Create a new HTTP trigger bound to the given address.
     pub fn new(bind_addr: SocketAddr, handler: RequestHandler) -> Self {
-        Self { bind_addr, handler }
+        Self {
+            bind_addr,
+            handler,
+            tls: None,
+        }
+    }
+
+    /// Terminate TLS on every accepted connection.
+    pub fn with_tls(mut self, config: Arc<ServerConfig>) -> Self {
+        self.tls = Some(TlsAcceptor::from(config));
+        self
     }
 
     /// Start the HTTP server.
@@ -53,37 +76,22 @@ impl HttpTrigger {
             .await
             .context("failed to bind HTTP trigger")?;
 
-        info!(addr = %self.bind_addr, "HTTP trigger listening");
+        info!(addr = %self.bind_addr, tls = self.tls.is_some(), "HTTP trigger listening");
 
         loop {
             tokio::select! {
                 accept_result = listener.accept() => {
                     let (stream, peer_addr) = accept_result.context("accept failed")?;
                     let handler = self.handler.clone();
+                    let tls = self.tls.clone();
 
                     tokio::spawn(async move {
-                        let io = TokioIo::new(stream);
-                        let svc = service_fn(move |req: Request<Incoming>| {
-                            let handler = handler.clone();
-                            async move {
-                                match handler(req).await {
-                                    Ok(resp) => Ok::<_, hyper::Error>(resp),
-                                    Err(e) => {
-                                        error!(%peer_addr, error = %e, "request handler failed");
-                                        Ok(Response::builder()
-                                            .status(500)
-                                            .body(Full::new(Bytes::from("Internal Server Error")))
-                                            .unwrap())
-                                    }
-                                }
-                            }
-                        });
-
-                        if let Err(e) = http1::Builder::new()
-                            .serve_connection(io, svc)
-                            .await
-                        {
-                            error!(%peer_addr, error = %e, "connection error");
+                        match tls {
+                            Some(acceptor) => match acceptor.accept(stream).await {
+                                Ok(stream) => serve_connection(stream, peer_addr, handler).await,
+                                Err(e) => debug!(%peer_addr, error = %e, "TLS handshake failed"),
+                            },
+                            None => serve_connection(stream, peer_addr, handler).await,
                         }
                     });
                 }
@@ -98,6 +106,37 @@ impl HttpTrigger {
     }
 }
 
+/// Serve HTTP/1.1 on one accepted connection. `peer_addr` only labels
+/// log lines.
+pub(crate) async fn serve_connection<S, P>(stream: S, peer_addr: P, handler: RequestHandler)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    P: std::fmt::Display + Clone + Send + Sync + 'static,
+{
+    let io = TokioIo::new(stream);
+    let peer = peer_addr.clone();
+    let svc = service_fn(move |req: Request<Incoming>| {
+        let handler = handler.clone();
+        let peer_addr = peer.clone();
+        async move {
+            match handler(req).await {
+                Ok(resp) => Ok::<_, hyper::Error>(resp),
+                Err(e) => {
+                    error!(%peer_addr, error = %e, "request handler failed");
+                    Ok(Response::builder()
+                        .status(500)
+                        .body(full_body("Internal Server Error"))
+                        .unwrap())
+                }
+            }
+        }
+    });
+
+    if let Err(e) = http1::Builder::new().serve_connection(io, svc).await {
+        error!(%peer_addr, error = %e, "connection error");
+    }
+}
+
 /// Create a simple echo handler for testing.
 ///
 /// Returns the request path and method as the response body.
@@ -112,7 +151,7 @@ pub fn echo_handler() -> RequestHandler {
             Ok(Response::builder()
                 .status(200)
                 .header("content-type", "text/plain")
-                .body(Full::new(Bytes::from(body)))
+                .body(full_body(body))
                 .unwrap())
         })
     })
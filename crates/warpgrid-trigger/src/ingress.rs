@@ -0,0 +1,510 @@
+//! HTTP ingress — many deployments behind shared listeners.
+//!
+//! Every deployment with an HTTP trigger gets an [`IngressRoute`] built
+//! from its spec. Requests arriving on any ingress listener are matched
+//! against the route table and dispatched to the handler registered for
+//! the winning deployment.
+//!
+//! # Matching
+//!
+//! A route matches when its port (if pinned) equals the listener port,
+//! its host list (if any) contains the request host, and the request path
+//! sits under its path prefix. Among matching routes the most specific
+//! wins: exact host over wildcard host over any host, then the longest
+//! path prefix.
+//!
+//! | Outcome | Status |
+//! |---|---|
+//! | No route matches | 404 |
+//! | Route matches, no handler registered | 503 |
+//! | Bearer token required and missing/wrong | 401 |
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+use http::HeaderMap;
+use http::header::{AUTHORIZATION, HOST, WWW_AUTHENTICATE};
+use hyper::body::Incoming;
+use hyper::{Request, Response};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::ServerConfig;
+use tracing::{debug, info};
+use warpgrid_state::{DeploymentSpec, StateError, StateStore, TriggerConfig};
+
+use crate::handler::{HttpTrigger, RequestHandler, ResponseBody, full_body, serve_connection};
+
+/// Routing rule for one deployment's HTTP trigger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngressRoute {
+    pub deployment_id: String,
+    /// Only serve on this ingress listener port (`None` = every listener).
+    pub port: Option<u16>,
+    /// Lowercased host names; `*.example.com` matches any subdomain.
+    /// Empty matches any host.
+    pub hosts: Vec<String>,
+    /// Normalized path prefix (`/`, `/api`, ...).
+    pub path_prefix: String,
+}
+
+impl IngressRoute {
+    /// Build the route for a deployment, or `None` if it has no HTTP trigger.
+    pub fn from_spec(spec: &DeploymentSpec) -> Option<Self> {
+        let TriggerConfig::Http {
+            port,
+            hosts,
+            path_prefix,
+        } = &spec.trigger
+        else {
+            return None;
+        };
+        Some(Self {
+            deployment_id: spec.id.clone(),
+            port: *port,
+            hosts: hosts.iter().map(|h| h.to_ascii_lowercase()).collect(),
+            path_prefix: normalize_prefix(path_prefix.as_deref().unwrap_or("/")),
+        })
+    }
+
+    /// Specificity of a match as `(host rank, prefix length)`, or `None`
+    /// if the route does not match.
+    fn matches(&self, port: u16, host: &str, path: &str) -> Option<(u8, usize)> {
+        if self.port.is_some_and(|p| p != port) || !path_under(path, &self.path_prefix) {
+            return None;
+        }
+        let host_rank = if self.hosts.is_empty() {
+            0
+        } else if self.hosts.iter().any(|h| h == host) {
+            2
+        } else if self.hosts.iter().any(|h| {
+            h.strip_prefix('*')
+                .is_some_and(|suffix| suffix.starts_with('.') && host.ends_with(suffix))
+        }) {
+            1
+        } else {
+            return None;
+        };
+        Some((host_rank, self.path_prefix.len()))
+    }
+}
+
+/// Ensure a leading `/` and drop trailing slashes (except for the root).
+fn normalize_prefix(prefix: &str) -> String {
+    let trimmed = prefix.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else if trimmed.starts_with('/') {
+        trimmed.to_string()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+/// Whether `path` is `prefix` or lies beneath it on a segment boundary.
+fn path_under(path: &str, prefix: &str) -> bool {
+    prefix == "/"
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Request host without port, lowercased. Falls back to the URI authority
+/// for HTTP/2 requests that carry no `Host` header.
+fn request_host<B>(req: &Request<B>) -> String {
+    let raw = req
+        .headers()
+        .get(HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| req.uri().host())
+        .unwrap_or_default();
+    let host = if raw.starts_with('[') {
+        // IPv6 literal: keep the brackets, drop the port.
+        raw.split_inclusive(']').next().unwrap_or(raw)
+    } else {
+        raw.split(':').next().unwrap_or(raw)
+    };
+    host.to_ascii_lowercase()
+}
+
+#[derive(Default)]
+struct IngressTable {
+    routes: Vec<IngressRoute>,
+    handlers: HashMap<String, RequestHandler>,
+}
+
+/// Shared route table and per-deployment handlers for the ingress.
+///
+/// Cheap to clone; all clones share the same table. Routes come from the
+/// state store via [`IngressRouter::sync`]; handlers are registered by
+/// whatever serves the deployment's instances.
+#[derive(Clone, Default)]
+pub struct IngressRouter {
+    table: Arc<RwLock<IngressTable>>,
+}
+
+impl IngressRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the route table.
+    pub fn set_routes(&self, routes: Vec<IngressRoute>) {
+        self.table.write().unwrap().routes = routes;
+    }
+
+    /// Rebuild the route table from every HTTP-triggered deployment.
+    /// Returns the number of routes.
+    pub fn sync(&self, store: &StateStore) -> Result<usize, StateError> {
+        let routes: Vec<IngressRoute> = store
+            .list_deployments()?
+            .iter()
+            .filter_map(IngressRoute::from_spec)
+            .collect();
+        let count = routes.len();
+        self.set_routes(routes);
+        debug!(routes = count, "ingress routes synced");
+        Ok(count)
+    }
+
+    /// Current routes.
+    pub fn routes(&self) -> Vec<IngressRoute> {
+        self.table.read().unwrap().routes.clone()
+    }
+
+    /// Register the handler serving a deployment's requests.
+    pub fn register(&self, deployment_id: &str, handler: RequestHandler) {
+        self.table
+            .write()
+            .unwrap()
+            .handlers
+            .insert(deployment_id.to_string(), handler);
+    }
+
+    /// Remove a deployment's handler; its route then answers 503.
+    pub fn unregister(&self, deployment_id: &str) {
+        self.table.write().unwrap().handlers.remove(deployment_id);
+    }
+
+    /// Deployment that should serve a request, if any.
+    pub fn resolve(&self, port: u16, host: &str, path: &str) -> Option<String> {
+        let table = self.table.read().unwrap();
+        let mut best: Option<(&IngressRoute, (u8, usize))> = None;
+        for route in &table.routes {
+            if let Some(score) = route.matches(port, host, path)
+                && best.is_none_or(|(_, b)| score > b)
+            {
+                best = Some((route, score));
+            }
+        }
+        best.map(|(route, _)| route.deployment_id.clone())
+    }
+
+    /// Serve HTTP/1.1 on `stream` with one deployment's handler, whatever
+    /// the host and path of each request. `warp port-forward` tunnels
+    /// arrive this way, through the management API rather than a listener.
+    pub async fn serve_tunnel<S>(&self, deployment_id: &str, stream: S)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let router = self.clone();
+        let id: Arc<str> = deployment_id.into();
+        let label = format!("tunnel to {deployment_id}");
+        let handler: RequestHandler = Arc::new(move |req: Request<Incoming>| {
+            let handler = router.table.read().unwrap().handlers.get(&*id).cloned();
+            match handler {
+                Some(handler) => handler(req),
+                None => Box::pin(async { Ok(text_response(503, "Deployment has no running instances")) }),
+            }
+        });
+        serve_connection(stream, label, handler).await;
+    }
+
+    /// Request handler for the ingress listener on `port`.
+    pub fn handler(&self, port: u16) -> RequestHandler {
+        let router = self.clone();
+        Arc::new(move |req: Request<Incoming>| {
+            let router = router.clone();
+            Box::pin(async move {
+                let host = request_host(&req);
+                let path = req.uri().path().to_string();
+                let Some(deployment_id) = router.resolve(port, &host, &path) else {
+                    debug!(%host, %path, port, "no ingress route");
+                    return Ok(text_response(404, "No deployment serves this host and path"));
+                };
+                let handler = router
+                    .table
+                    .read()
+                    .unwrap()
+                    .handlers
+                    .get(&deployment_id)
+                    .cloned();
+                match handler {
+                    Some(handler) => handler(req).await,
+                    None => {
+                        debug!(%deployment_id, "ingress route has no handler");
+                        Ok(text_response(503, "Deployment has no running instances"))
+                    }
+                }
+            })
+        })
+    }
+}
+
+fn text_response(status: u16, body: &'static str) -> Response<ResponseBody> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "text/plain")
+        .body(full_body(Bytes::from_static(body.as_bytes())))
+        .unwrap()
+}
+
+/// Whether `headers` carry `Authorization: Bearer <token>`.
+///
+/// The comparison takes the same time wherever the first mismatch is.
+pub fn bearer_authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(presented) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Wrap a handler so requests without the bearer token get a 401.
+fn require_bearer(token: Arc<str>, inner: RequestHandler) -> RequestHandler {
+    Arc::new(move |req: Request<Incoming>| {
+        if bearer_authorized(req.headers(), &token) {
+            return inner(req);
+        }
+        Box::pin(async {
+            Ok(Response::builder()
+                .status(401)
+                .header(WWW_AUTHENTICATE, "Bearer")
+                .header("content-type", "text/plain")
+                .body(full_body(Bytes::from_static(b"Unauthorized")))
+                .unwrap())
+        })
+    })
+}
+
+/// App traffic entry point: one [`HttpTrigger`] per listener address, all
+/// dispatching through a shared [`IngressRouter`].
+pub struct IngressServer {
+    addrs: Vec<SocketAddr>,
+    router: IngressRouter,
+    tls: Option<Arc<ServerConfig>>,
+    auth_token: Option<Arc<str>>,
+}
+
+impl IngressServer {
+    pub fn new(addrs: Vec<SocketAddr>, router: IngressRouter) -> Self {
+        Self {
+            addrs,
+            router,
+            tls: None,
+            auth_token: None,
+        }
+    }
+
+    /// Terminate TLS on every listener.
+    pub fn with_tls(mut self, config: Arc<ServerConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// Require `Authorization: Bearer <token>` on every request.
+    pub fn with_auth_token(mut self, token: impl Into<Arc<str>>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    /// Serve every listener until shutdown. Fails if any listener fails.
+    pub async fn serve(self, shutdown: tokio::sync::watch::Receiver<bool>) -> anyhow::Result<()> {
+        info!(listeners = self.addrs.len(), "HTTP ingress starting");
+        let mut tasks = tokio::task::JoinSet::new();
+        for addr in self.addrs {
+            let mut handler = self.router.handler(addr.port());
+            if let Some(token) = &self.auth_token {
+                handler = require_bearer(token.clone(), handler);
+            }
+            let mut trigger = HttpTrigger::new(addr, handler);
+            if let Some(tls) = &self.tls {
+                trigger = trigger.with_tls(tls.clone());
+            }
+            tasks.spawn(trigger.serve(shutdown.clone()));
+        }
+        while let Some(result) = tasks.join_next().await {
+            result??;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn route(id: &str, port: Option<u16>, hosts: &[&str], prefix: &str) -> IngressRoute {
+        IngressRoute {
+            deployment_id: id.to_string(),
+            port,
+            hosts: hosts.iter().map(|h| h.to_string()).collect(),
+            path_prefix: normalize_prefix(prefix),
+        }
+    }
+
+    fn router(routes: Vec<IngressRoute>) -> IngressRouter {
+        let router = IngressRouter::new();
+        router.set_routes(routes);
+        router
+    }
+
+    #[test]
+    fn most_specific_host_wins() {
+        let router = router(vec![
+            route("catch-all", None, &[], "/"),
+            route("wildcard", None, &["*.example.com"], "/"),
+            route("exact", None, &["api.example.com"], "/"),
+        ]);
+        assert_eq!(router.resolve(8080, "api.example.com", "/").as_deref(), Some("exact"));
+        assert_eq!(router.resolve(8080, "web.example.com", "/").as_deref(), Some("wildcard"));
+        assert_eq!(router.resolve(8080, "example.com", "/").as_deref(), Some("catch-all"));
+        assert_eq!(router.resolve(8080, "other.org", "/x").as_deref(), Some("catch-all"));
+    }
+
+    #[test]
+    fn longest_path_prefix_wins_on_segment_boundaries() {
+        let router = router(vec![
+            route("root", None, &["shop.io"], "/"),
+            route("api", None, &["shop.io"], "/api/"),
+        ]);
+        assert_eq!(router.resolve(80, "shop.io", "/api").as_deref(), Some("api"));
+        assert_eq!(router.resolve(80, "shop.io", "/api/v1/items").as_deref(), Some("api"));
+        assert_eq!(router.resolve(80, "shop.io", "/apiary").as_deref(), Some("root"));
+    }
+
+    #[test]
+    fn pinned_port_only_matches_its_listener() {
+        let router = router(vec![route("admin", Some(9000), &[], "/")]);
+        assert_eq!(router.resolve(9000, "x", "/").as_deref(), Some("admin"));
+        assert!(router.resolve(8080, "x", "/").is_none());
+    }
+
+    #[test]
+    fn route_from_spec_normalizes_fields() {
+        let mut spec: DeploymentSpec = serde_json::from_value(serde_json::json!({
+            "id": "default/api", "namespace": "default", "name": "api",
+            "source": "file://api.wasm",
+            "trigger": {"type": "http", "port": null, "hosts": ["API.Example.com"], "path_prefix": "v1/"},
+            "instances": {"min": 1, "max": 1},
+            "resources": {"memory_bytes": 1, "cpu_weight": 1},
+            "scaling": null, "health": null, "shims": {
+                "timezone": false, "dev_urandom": false, "dns": false,
+                "signals": false, "database_proxy": false
+            },
+            "env": {}, "created_at": 0, "updated_at": 0
+        }))
+        .unwrap();
+        let route = IngressRoute::from_spec(&spec).unwrap();
+        assert_eq!(route.hosts, ["api.example.com"]);
+        assert_eq!(route.path_prefix, "/v1");
+
+        spec.trigger = TriggerConfig::Cron {
+            schedule: "* * * * *".to_string(),
+        };
+        assert!(IngressRoute::from_spec(&spec).is_none());
+    }
+
+    #[test]
+    fn request_host_strips_port() {
+        let req = Request::builder()
+            .header(HOST, "API.example.com:8080")
+            .body(())
+            .unwrap();
+        assert_eq!(request_host(&req), "api.example.com");
+
+        let req = Request::builder().header(HOST, "[::1]:8080").body(()).unwrap();
+        assert_eq!(request_host(&req), "[::1]");
+    }
+
+    #[test]
+    fn bearer_token_must_match_exactly() {
+        let mut headers = HeaderMap::new();
+        assert!(!bearer_authorized(&headers, "s3cret"));
+        headers.insert(AUTHORIZATION, "Bearer s3cre".parse().unwrap());
+        assert!(!bearer_authorized(&headers, "s3cret"));
+        headers.insert(AUTHORIZATION, "Basic s3cret".parse().unwrap());
+        assert!(!bearer_authorized(&headers, "s3cret"));
+        headers.insert(AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(bearer_authorized(&headers, "s3cret"));
+    }
+
+    async fn get(addr: SocketAddr, host: &str, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn ingress_dispatches_by_host() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let router = router(vec![
+            route("echo", None, &["echo.local"], "/"),
+            route("idle", None, &["idle.local"], "/"),
+        ]);
+        router.register("echo", crate::handler::echo_handler());
+
+        let (tx, rx) = tokio::sync::watch::channel(false);
+        let server = tokio::spawn(IngressServer::new(vec![addr], router).serve(rx));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let echo = get(addr, "echo.local", "/hello").await;
+        assert!(echo.starts_with("HTTP/1.1 200"), "{echo}");
+        assert!(echo.ends_with("GET /hello"), "{echo}");
+        assert!(get(addr, "idle.local", "/").await.starts_with("HTTP/1.1 503"));
+        assert!(get(addr, "nobody.local", "/").await.starts_with("HTTP/1.1 404"));
+
+        tx.send(true).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn tunnel_skips_host_and_path_routing() {
+        let router = router(vec![route("echo", None, &["echo.local"], "/api")]);
+        router.register("echo", crate::handler::echo_handler());
+
+        let (mut client, server) = tokio::io::duplex(4096);
+        let tunnel = router.clone();
+        tokio::spawn(async move { tunnel.serve_tunnel("echo", server).await });
+        client
+            .write_all(b"GET /elsewhere HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("GET /elsewhere"), "{response}");
+
+        let (mut client, server) = tokio::io::duplex(4096);
+        tokio::spawn(async move { router.serve_tunnel("idle", server).await });
+        client.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+    }
+}
@@ -21,9 +21,23 @@
 //! ```
 //!
 //! The handler uses `wasmtime-wasi-http` for type conversions and
-//! the proxy world binding.
+//! the proxy world binding. Response bodies are streamed to the client
+//! with backpressure ([`stream`]).
+//!
+//! The [`ingress`] module puts many deployments behind shared listeners,
+//! dispatching by host and path prefix. [`component::component_handler`]
+//! builds the handler that runs a deployment's component, reporting each
+//! request's [`usage`] event.
 
+pub mod capture;
+pub mod component;
 pub mod handler;
 pub mod convert;
+pub mod ingress;
+pub mod stream;
+pub mod usage;
 
-pub use handler::HttpTrigger;
+pub use convert::{LimitViolation, ResponseLimits};
+pub use handler::{HttpTrigger, ResponseBody};
+pub use ingress::{IngressRoute, IngressRouter, IngressServer, bearer_authorized};
+pub use usage::UsageSink;
@@ -0,0 +1,219 @@
+//! Streaming guest response bodies to clients with backpressure.
+//!
+//! A pump task moves body frames from the guest's outgoing body into a
+//! bounded channel that hyper drains as the client reads:
+//!
+//! ```text
+//! guest output-stream ──▶ outgoing body ──▶ pump ──▶ channel (N frames) ──▶ hyper ──▶ client
+//! ```
+//!
+//! When the channel is full the pump stops polling the guest's body, so
+//! the guest's writes block instead of piling up in the host. A client
+//! that reads nothing for [`ResponseLimits::stall_timeout`] gets the
+//! stream aborted, as does a body that crosses
+//! [`ResponseLimits::max_body_bytes`]. Counters go to
+//! [`warpgrid_metrics::streaming`].
+
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame};
+use tokio::sync::mpsc;
+use tracing::warn;
+use warpgrid_metrics::streaming::streaming;
+
+use crate::convert::ResponseLimits;
+use crate::handler::ResponseBody;
+
+/// Stream `body` to the client under `limits`.
+pub fn stream_body<B>(body: B, limits: ResponseLimits) -> ResponseBody
+where
+    B: Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: Debug,
+{
+    let (tx, rx) = mpsc::channel(limits.stream_buffer_frames.max(1));
+    let abort = Arc::new(Mutex::new(None));
+    tokio::spawn(pump(body, tx, abort.clone(), limits));
+    ChannelBody { rx, abort }.boxed()
+}
+
+/// Move frames from `body` into `tx` until the body ends, the client goes
+/// away, or a limit is hit. Why the stream was cut short goes in `abort`.
+async fn pump<B>(
+    mut body: B,
+    tx: mpsc::Sender<Frame<Bytes>>,
+    abort: Arc<Mutex<Option<String>>>,
+    limits: ResponseLimits,
+) where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Debug,
+{
+    let counters = streaming();
+    counters.stream_started();
+    let mut sent = 0u64;
+    let reason = loop {
+        let frame = match body.frame().await {
+            None => break None,
+            Some(Ok(frame)) => frame,
+            Some(Err(code)) => break Some(format!("guest response body failed: {code:?}")),
+        };
+        let len = frame.data_ref().map_or(0, |data| data.len() as u64);
+        sent += len;
+        if let Err(violation) = limits.check_body(sent) {
+            break Some(violation.to_string());
+        }
+
+        let permit = match tx.try_reserve() {
+            Ok(permit) => permit,
+            // The client went away; nobody is left to tell.
+            Err(mpsc::error::TrySendError::Closed(())) => break None,
+            Err(mpsc::error::TrySendError::Full(())) => {
+                counters.backpressure_wait();
+                match tokio::time::timeout(limits.stall_timeout, tx.reserve()).await {
+                    Ok(Ok(permit)) => permit,
+                    Ok(Err(_)) => break None,
+                    Err(_) => {
+                        counters.stall_timeout();
+                        break Some(format!(
+                            "client read nothing for {:?}",
+                            limits.stall_timeout
+                        ));
+                    }
+                }
+            }
+        };
+        permit.send(frame);
+        counters.add_bytes(len);
+    };
+    if let Some(reason) = reason {
+        warn!(%reason, bytes = sent, "response stream aborted");
+        *abort.lock().unwrap() = Some(reason);
+    }
+    counters.stream_finished();
+}
+
+/// The client side of the pump: frames in order, then the abort reason as
+/// an error if the stream was cut short, so hyper resets the connection
+/// rather than ending a truncated body cleanly.
+struct ChannelBody {
+    rx: mpsc::Receiver<Frame<Bytes>>,
+    abort: Arc<Mutex<Option<String>>>,
+}
+
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(frame)) => Poll::Ready(Some(Ok(frame))),
+            Poll::Ready(None) => {
+                Poll::Ready(self.abort.lock().unwrap().take().map(|reason| Err(std::io::Error::other(reason))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    fn limits(frames: usize, stall: Duration) -> ResponseLimits {
+        ResponseLimits {
+            stream_buffer_frames: frames,
+            stall_timeout: stall,
+            ..ResponseLimits::default()
+        }
+    }
+
+    /// A guest body fed from a channel, so the test sees when it is polled.
+    struct GuestBody(mpsc::Receiver<Frame<Bytes>>);
+
+    impl Body for GuestBody {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+            self.0.poll_recv(cx).map(|frame| frame.map(Ok))
+        }
+    }
+
+    fn guest_body() -> (mpsc::Sender<Frame<Bytes>>, GuestBody) {
+        let (tx, rx) = mpsc::channel(1);
+        (tx, GuestBody(rx))
+    }
+
+    fn chunk(n: usize) -> Frame<Bytes> {
+        Frame::data(Bytes::from(vec![b'x'; n]))
+    }
+
+    #[tokio::test]
+    async fn slow_client_backpressures_the_guest() {
+        let (guest, body) = guest_body();
+        let mut client = stream_body(body, limits(2, Duration::from_secs(30)));
+
+        // Two frames fill the channel; the third is taken by the pump and
+        // held; the fourth fills the guest's own one-slot buffer; the
+        // fifth has to wait for the client.
+        for _ in 0..4 {
+            guest.send(chunk(10)).await.unwrap();
+        }
+        let blocked = tokio::time::timeout(Duration::from_millis(100), guest.send(chunk(10))).await;
+        assert!(blocked.is_err(), "guest should be held back by the slow client");
+
+        let frame = client.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap().len(), 10);
+        guest.send(chunk(10)).await.unwrap();
+        drop(guest);
+
+        let mut rest = 0;
+        while let Some(frame) = client.frame().await {
+            rest += frame.unwrap().into_data().unwrap().len();
+        }
+        assert_eq!(rest, 40);
+    }
+
+    #[tokio::test]
+    async fn stalled_client_aborts_the_stream() {
+        let (guest, body) = guest_body();
+        let mut client = stream_body(body, limits(1, Duration::from_millis(50)));
+        guest.send(chunk(1)).await.unwrap();
+        guest.send(chunk(1)).await.unwrap();
+        drop(guest);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(client.frame().await.unwrap().is_ok());
+        let err = client.frame().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("client read nothing"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn body_over_the_limit_is_cut_off() {
+        let (guest, body) = guest_body();
+        let limits = ResponseLimits {
+            max_body_bytes: 15,
+            ..limits(4, Duration::from_secs(30))
+        };
+        let mut client = stream_body(body, limits);
+        guest.send(chunk(10)).await.unwrap();
+        guest.send(chunk(10)).await.unwrap();
+        drop(guest);
+
+        assert!(client.frame().await.unwrap().is_ok());
+        let err = client.frame().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("exceeds 15 bytes"), "{err}");
+    }
+}
@@ -0,0 +1,97 @@
+//! Usage events for served requests.
+//!
+//! Every request a component handler serves ends as one [`UsageEvent`]
+//! handed to the handler's [`UsageSink`]: when it started, its wall time,
+//! the guest CPU time counted in epoch ticks, the instance's memory limit,
+//! and whether it ran out of its execution budget. warpd's sink appends
+//! the events to the state store's usage stream, which the usage export
+//! API pages through.
+//!
+//! Event ids are the node, the time this process started, and a counter,
+//! so they never repeat across nodes or restarts.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use warp_runtime::UsageSample;
+use warpgrid_state::UsageEvent;
+
+/// Receives the usage event of every request a handler serves.
+pub trait UsageSink: Send + Sync {
+    /// Node the requests are served on.
+    fn node_id(&self) -> &str;
+
+    /// Record one request, once the guest has finished with it.
+    fn record(&self, event: UsageEvent);
+
+    /// Record a request to `deployment_id`, served by `instance_id`, that
+    /// started at `started` and used `sample`.
+    fn record_sample(&self, deployment_id: &str, instance_id: &str, started: SystemTime, sample: &UsageSample) {
+        self.record(UsageEvent {
+            event_id: event_id(self.node_id()),
+            deployment_id: deployment_id.to_string(),
+            instance_id: instance_id.to_string(),
+            node_id: self.node_id().to_string(),
+            started_at_ms: unix_millis(started),
+            wall_time_ms: sample.wall_time.as_millis() as u64,
+            cpu_time_ms: sample.cpu_time.as_millis() as u64,
+            memory_limit_bytes: sample.memory_limit_bytes,
+            budget_exceeded: sample.budget_exceeded,
+        });
+    }
+}
+
+/// A fresh event id for a request served on `node_id`.
+fn event_id(node_id: &str) -> String {
+    static BOOT: OnceLock<u128> = OnceLock::new();
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let boot = BOOT.get_or_init(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos());
+    format!("{node_id}-{boot:x}-{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+fn unix_millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<UsageEvent>>);
+
+    impl UsageSink for Collect {
+        fn node_id(&self) -> &str {
+            "node-1"
+        }
+
+        fn record(&self, event: UsageEvent) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn samples_become_events_with_distinct_ids() {
+        let sink = Collect::default();
+        let started = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let sample = UsageSample {
+            wall_time: Duration::from_millis(42),
+            cpu_time: Duration::from_millis(30),
+            memory_limit_bytes: 64 << 20,
+            budget_exceeded: true,
+        };
+        sink.record_sample("default/app", "req-1", started, &sample);
+        sink.record_sample("default/app", "req-2", started, &sample);
+
+        let events = sink.0.into_inner().unwrap();
+        assert_eq!(events[0].started_at_ms, 1_700_000_000_123);
+        assert_eq!((events[0].wall_time_ms, events[0].cpu_time_ms), (42, 30));
+        assert_eq!((events[0].node_id.as_str(), events[0].instance_id.as_str()), ("node-1", "req-1"));
+        assert!(events[0].budget_exceeded);
+        assert!(events[0].event_id.starts_with("node-1-"));
+        assert_ne!(events[0].event_id, events[1].event_id);
+    }
+}
@@ -11,6 +11,8 @@
  * - File watcher uses `fs.watch` (recursive) with debouncing
  * - Native mode: dynamic `import()` with cache-busting query param
  * - Wasm mode: subprocess management (jco serve) with restart on recompile
+ * - Wasm mode: `POST /__warp/reload` swaps in an artifact packed elsewhere
+ *   (sent by `warp pack --watch --notify <url>`)
  * - Compilation errors are displayed but don't crash the server
  */
 
@@ -47,6 +49,9 @@ export interface DevServerHandle {
 
 export const DEFAULT_WATCH_EXTENSIONS = [".ts", ".tsx", ".js", ".jsx"];
 
+/** Wasm-mode endpoint that hot-swaps the served component. */
+export const RELOAD_PATH = "/__warp/reload";
+
 const IGNORED_SEGMENTS = ["node_modules", ".git", "dist", "target"];
 
 // ── Utility: shouldWatch ──────────────────────────────────────────────────
@@ -320,6 +325,10 @@ class WasmDevServer implements DevServerHandle {
     this.proxyServer = Bun.serve({
       port: this.config.port,
       fetch: async (req: Request) => {
+        if (req.method === "POST" && new URL(req.url).pathname === RELOAD_PATH) {
+          return this.handleReload(req);
+        }
+
         if (!this.wasmReady || this.jcoPort === 0) {
           return new Response(
             JSON.stringify({
@@ -355,6 +364,38 @@ class WasmDevServer implements DevServerHandle {
     this.startWatcher();
   }
 
+  /** Serve the artifact named in a `{ "artifact": "<path>" }` body. */
+  private async handleReload(req: Request): Promise<Response> {
+    const json = (status: number, body: unknown) =>
+      new Response(JSON.stringify(body), {
+        status,
+        headers: { "content-type": "application/json" },
+      });
+
+    let artifact: unknown;
+    try {
+      ({ artifact } = (await req.json()) as { artifact?: unknown });
+    } catch {
+      return json(400, { error: "Invalid reload request", message: "body must be JSON" });
+    }
+    if (typeof artifact !== "string" || extname(artifact) !== ".wasm") {
+      return json(400, { error: "Invalid reload request", message: "artifact must be a .wasm path" });
+    }
+    try {
+      await access(artifact);
+    } catch {
+      return json(404, { error: "Artifact not found", message: artifact });
+    }
+
+    process.stderr.write(`[warp dev] Reloading ${artifact}\n`);
+    await this.startJcoServe(resolve(artifact));
+    if (!this.wasmReady) {
+      return json(500, { error: "Reload failed", message: this.lastError });
+    }
+    this.lastError = null;
+    return json(200, { reloaded: artifact });
+  }
+
   private async compile(): Promise<void> {
     this.compileAttempted = true;
     const projectPath = this.config.projectPath;
@@ -0,0 +1,116 @@
+#!/usr/bin/env bash
+#
+# vendor-dashboard-assets.sh — Self-host the dashboard's Tailwind CSS and HTMX.
+#
+# Downloads pinned HTMX releases and compiles the Tailwind stylesheet for the
+# classes used by the dashboard templates, writing them to
+# crates/warpgrid-dashboard/assets/. The next cargo build embeds them, so the
+# dashboard works in air-gapped clusters; it never loads them from a CDN, and
+# builds without them warn. Commit the generated files.
+#
+# Re-run after adding Tailwind classes to the dashboard templates.
+#
+# Prerequisites:
+#   - curl
+#
+# Usage:
+#   scripts/vendor-dashboard-assets.sh            # Vendor the assets
+#   scripts/vendor-dashboard-assets.sh --help     # Show this help
+#
+# Environment variables:
+#   TAILWIND_VERSION    Tailwind CLI release (default: 3.4.17)
+#   HTMX_VERSION        htmx.org release (default: 2.0.8)
+#   HTMX_SSE_VERSION    htmx-ext-sse release (default: 2.2.3)
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(cd "${SCRIPT_DIR}/.." && pwd)"
+CACHE_DIR="${PROJECT_ROOT}/build/cache"
+DASHBOARD_DIR="${PROJECT_ROOT}/crates/warpgrid-dashboard"
+ASSETS_DIR="${DASHBOARD_DIR}/assets"
+
+TAILWIND_VERSION="${TAILWIND_VERSION:-3.4.17}"
+HTMX_VERSION="${HTMX_VERSION:-2.0.8}"
+HTMX_SSE_VERSION="${HTMX_SSE_VERSION:-2.2.3}"
+
+# ─── Helpers ─────────────────────────────────────────────────────────────────
+
+log() { echo "==> $*" >&2; }
+err() { echo "ERROR: $*" >&2; exit 1; }
+
+usage() {
+    echo "Usage: scripts/vendor-dashboard-assets.sh [--help]"
+    echo
+    echo "Vendor Tailwind CSS and HTMX into ${ASSETS_DIR#"${PROJECT_ROOT}/"}."
+    echo
+    echo "Environment:"
+    echo "  TAILWIND_VERSION    Tailwind CLI release (default: ${TAILWIND_VERSION})"
+    echo "  HTMX_VERSION        htmx.org release (default: ${HTMX_VERSION})"
+    echo "  HTMX_SSE_VERSION    htmx-ext-sse release (default: ${HTMX_SSE_VERSION})"
+}
+
+download() {
+    local url="$1" dest="$2"
+    log "Downloading ${url}"
+    curl -fsSL --retry 3 -o "${dest}.tmp" "${url}" || err "Failed to download ${url}"
+    mv "${dest}.tmp" "${dest}"
+}
+
+# ─── Tailwind CLI ────────────────────────────────────────────────────────────
+
+tailwind_platform() {
+    local os arch
+    case "$(uname -s)" in
+        Linux) os="linux" ;;
+        Darwin) os="macos" ;;
+        *) err "Unsupported OS: $(uname -s)" ;;
+    esac
+    case "$(uname -m)" in
+        x86_64|amd64) arch="x64" ;;
+        arm64|aarch64) arch="arm64" ;;
+        *) err "Unsupported architecture: $(uname -m)" ;;
+    esac
+    echo "${os}-${arch}"
+}
+
+ensure_tailwind() {
+    TAILWIND_BIN="${CACHE_DIR}/tailwindcss-${TAILWIND_VERSION}"
+    if [[ -x "${TAILWIND_BIN}" ]]; then
+        return
+    fi
+    mkdir -p "${CACHE_DIR}"
+    download \
+        "https://github.com/tailwindlabs/tailwindcss/releases/download/v${TAILWIND_VERSION}/tailwindcss-$(tailwind_platform)" \
+        "${TAILWIND_BIN}"
+    chmod +x "${TAILWIND_BIN}"
+}
+
+# ─── Main ────────────────────────────────────────────────────────────────────
+
+main() {
+    case "${1:-}" in
+        --help|-h) usage; exit 0 ;;
+        "") ;;
+        *) usage; err "Unknown argument: $1" ;;
+    esac
+
+    command -v curl >/dev/null || err "curl is required"
+    mkdir -p "${ASSETS_DIR}"
+
+    download "https://unpkg.com/htmx.org@${HTMX_VERSION}/dist/htmx.min.js" "${ASSETS_DIR}/htmx.min.js"
+    download "https://unpkg.com/htmx-ext-sse@${HTMX_SSE_VERSION}/sse.js" "${ASSETS_DIR}/htmx-ext-sse.js"
+
+    ensure_tailwind
+    log "Compiling tailwind.css"
+    # The config's content globs are relative to the dashboard crate.
+    (cd "${DASHBOARD_DIR}" && "${TAILWIND_BIN}" \
+        --config tailwind.config.js \
+        --input assets/tailwind.src.css \
+        --output assets/tailwind.css \
+        --minify)
+
+    log "Vendored dashboard assets into ${ASSETS_DIR}"
+}
+
+main "$@"
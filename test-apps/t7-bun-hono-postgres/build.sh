@@ -0,0 +1,40 @@
+#!/usr/bin/env bash
+#
+# build.sh — Pack the T7 Bun + Hono handler with `warp pack`.
+#
+# Unlike T5, which drives jco directly, T7 goes through the real pipeline:
+#   1. bun install      → hono + @warpgrid/bun-sdk
+#   2. warp pack        → bun build (with @warpgrid/bun-polyfills injected)
+#                         → jco componentize → wasm-tools validation
+#
+# Prerequisites:
+#   - bun (https://bun.sh)
+#   - jco installed via scripts/build-componentize-js.sh
+#   - wasm-tools (for component validation)
+#
+# Output:
+#   target/wasm/t7-bun-hono-postgres.wasm
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(cd "${SCRIPT_DIR}/../.." && pwd)"
+
+log() { echo "==> $*" >&2; }
+err() { echo "ERROR: $*" >&2; exit 1; }
+
+command -v bun &>/dev/null || err "bun not found. Install from https://bun.sh"
+
+if [ ! -x "${PROJECT_ROOT}/build/componentize-js/node_modules/.bin/jco" ] && ! command -v jco &>/dev/null; then
+    log "jco not found. Building ComponentizeJS toolchain..."
+    "${PROJECT_ROOT}/scripts/build-componentize-js.sh" --npm
+fi
+
+log "Installing dependencies..."
+(cd "${SCRIPT_DIR}" && bun install)
+
+log "Packing with warp pack..."
+cargo run --quiet --manifest-path "${PROJECT_ROOT}/Cargo.toml" -p warp-cli -- \
+    pack --path "${SCRIPT_DIR}"
+
+log "Build complete: ${SCRIPT_DIR}/target/wasm/t7-bun-hono-postgres.wasm"
@@ -0,0 +1,101 @@
+/**
+ * T7 Integration Test: Bun + Hono + Postgres through the full pipeline.
+ *
+ * A Hono app using the `@warpgrid/bun-sdk` pg client, packed with
+ * `warp pack` (bun build → jco componentize) and served by standalone
+ * warpd. The pg client talks Postgres wire protocol over the
+ * warpgrid:shim/database-proxy import — no raw sockets in the guest.
+ *
+ * Routes:
+ *   GET  /health     — health check (no database)
+ *   GET  /users      — list all users from test_users
+ *
+ * Environment (from the deployment spec):
+ *   APP_NAME          — included in X-App-Name response header
+ *   DB_HOST           — Postgres host (default: db.test.warp.local)
+ *   DB_PORT           — Postgres port (default: 5432)
+ *   DB_NAME           — database name (default: testdb)
+ *   DB_USER           — database user (default: testuser)
+ */
+
+import { Hono } from "hono";
+import { Client } from "@warpgrid/bun-sdk/pg";
+import type { DatabaseProxyShim } from "@warpgrid/bun-sdk/pg";
+
+// WarpGrid shim imports (warpgrid:shim/database-proxy)
+import {
+  connect as dbConnect,
+  send as dbSend,
+  recv as dbRecv,
+  close as dbClose,
+} from "warpgrid:shim/database-proxy@0.1.0";
+
+// ── Environment ─────────────────────────────────────────────────────
+
+function env(key: string): string | undefined {
+  const g = globalThis as Record<string, any>;
+  return g.Bun?.env?.[key] ?? g.process?.env?.[key];
+}
+
+// ── Database ────────────────────────────────────────────────────────
+
+/**
+ * The SDK's shim interface over the componentized imports. Handles are
+ * u64 in WIT (bigint in JS); the SDK keeps them as numbers.
+ */
+const shim: DatabaseProxyShim = {
+  connect: (config) => Number(dbConnect(config)),
+  send: (handle, data) => dbSend(BigInt(handle), data),
+  recv: (handle, maxBytes) => dbRecv(BigInt(handle), maxBytes),
+  close: (handle) => dbClose(BigInt(handle)),
+};
+
+async function withClient<T>(fn: (client: Client) => Promise<T>): Promise<T> {
+  const client = new Client({
+    host: env("DB_HOST") ?? "db.test.warp.local",
+    port: parseInt(env("DB_PORT") ?? "5432", 10),
+    database: env("DB_NAME") ?? "testdb",
+    user: env("DB_USER") ?? "testuser",
+    mode: "wasm",
+    shim,
+  });
+  await client.connect();
+  try {
+    return await fn(client);
+  } finally {
+    await client.end();
+  }
+}
+
+// ── App ─────────────────────────────────────────────────────────────
+
+const app = new Hono();
+
+app.use("*", async (c, next) => {
+  await next();
+  const appName = env("APP_NAME");
+  if (appName) c.header("X-App-Name", appName);
+});
+
+app.get("/health", (c) => c.json({ status: "ok" }));
+
+app.get("/users", async (c) => {
+  const result = await withClient((client) =>
+    client.query("SELECT id, name, email FROM test_users ORDER BY id"),
+  );
+  const users = result.rows.map((row) => ({
+    id: Number(row.id),
+    name: row.name,
+    email: row.email,
+  }));
+  return c.json(users);
+});
+
+app.notFound((c) => c.json({ error: "Not Found" }, 404));
+
+app.onError((err, c) => c.json({ error: err.message }, 500));
+
+// jco componentize serves wasi:http/incoming-handler through the fetch event.
+addEventListener("fetch", (event: any) => {
+  event.respondWith(app.fetch(event.request));
+});
@@ -0,0 +1,22 @@
+#!/usr/bin/env bash
+#
+# test.sh — End-to-end test for the T7 Bun + Hono + Postgres handler.
+#
+# Runs the warpd integration test, which:
+#   1. Packs this app with warp-pack (bun build → jco componentize)
+#   2. Starts a mock Postgres server
+#   3. Boots `warpd standalone` and deploys the artifact through the API
+#   4. Asserts GET /health and GET /users over the app ingress
+#
+# Prerequisites: bun, jco (scripts/build-componentize-js.sh), wasm-tools.
+# Without them the test reports a skip.
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(cd "${SCRIPT_DIR}/../.." && pwd)"
+
+(cd "${SCRIPT_DIR}" && bun install)
+
+cargo test --manifest-path "${PROJECT_ROOT}/Cargo.toml" -p warpd \
+    --test t7_bun_hono_postgres -- --ignored --nocapture